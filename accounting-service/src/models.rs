@@ -75,6 +75,12 @@ pub struct AccountsReceivable {
     pub age_61_90: Decimal,
     pub age_91_plus: Decimal,
     pub total_due: Decimal,
+    /// Part of `total_due` under a payment plan and not yet due
+    #[serde(default)]
+    pub planned_balance: Decimal,
+    /// Part of `total_due` under a payment plan from missed installments
+    #[serde(default)]
+    pub overdue_balance: Decimal,
     pub last_payment_date: Option<DateTime<Utc>>,
     pub last_charge_date: Option<DateTime<Utc>>,
 }
@@ -1,5 +1,6 @@
 use crate::models::AccountsReceivable;
-use crate::error::AccountingResult;
+use crate::error::{AccountingError, AccountingResult};
+use rust_decimal::Decimal;
 use uuid::Uuid;
 
 /// Accounts Receivable service
@@ -24,6 +25,8 @@ impl AccountsReceivableService {
             age_61_90: rust_decimal::Decimal::ZERO,
             age_91_plus: rust_decimal::Decimal::ZERO,
             total_due: rust_decimal::Decimal::ZERO,
+            planned_balance: rust_decimal::Decimal::ZERO,
+            overdue_balance: rust_decimal::Decimal::ZERO,
             last_payment_date: None,
             last_charge_date: None,
         })
@@ -42,6 +45,34 @@ impl AccountsReceivableService {
     }
 }
 
+impl AccountsReceivable {
+    /// Split the balance a payment plan covers into its planned and overdue
+    /// parts, replacing any earlier split
+    pub fn apply_payment_plan(&mut self, planned: Decimal, overdue: Decimal) -> AccountingResult<()> {
+        if planned < Decimal::ZERO || overdue < Decimal::ZERO {
+            return Err(AccountingError::Validation(
+                "Payment plan balances cannot be negative".to_string(),
+            ));
+        }
+        if planned + overdue > self.total_due {
+            return Err(AccountingError::Reconciliation(format!(
+                "Payment plan balance {} exceeds the {} due from patient {}",
+                planned + overdue,
+                self.total_due,
+                self.patient_id
+            )));
+        }
+        self.planned_balance = planned;
+        self.overdue_balance = overdue;
+        Ok(())
+    }
+
+    /// Balance owed outside any payment plan
+    pub fn unplanned_balance(&self) -> Decimal {
+        self.total_due - self.planned_balance - self.overdue_balance
+    }
+}
+
 impl Default for AccountsReceivableService {
     fn default() -> Self {
         Self::new()
@@ -19,9 +19,39 @@ pub enum AuditError {
     
     #[error("Merkle tree integrity check failed")]
     IntegrityCheckError,
+
+    #[error("Audit chain integrity violated: {0}")]
+    IntegrityViolation(String),
+
+    #[error("Audit storage persistence failed: {0}")]
+    PersistenceError(String),
+
+    #[error("Legal hold error: {0}")]
+    LegalHoldError(String),
+
+    #[error("Invalid retention policy: {0}")]
+    RetentionPolicyError(String),
+
+    #[error("Invalid checkpoint configuration: {0}")]
+    CheckpointConfigError(String),
+
+    #[error("Inclusion proof unavailable: {0}")]
+    ProofUnavailable(String),
     
     #[error("Audit export failed")]
     ExportError,
+
+    #[error("Invalid export configuration: {0}")]
+    ExportConfigError(String),
+
+    #[error("Audit export delivery failed: {0}")]
+    DeliveryError(String),
+
+    #[error("Pseudonymization key error: {0}")]
+    KeyError(String),
+
+    #[error("Re-identification denied: {0}")]
+    ReidentificationDenied(String),
     
     #[error("Internal error: {0}")]
     InternalError(#[from] anyhow::Error),
@@ -1,2 +0,0 @@
-// Audit export functionality stub
-pub struct AuditExporter {}
\ No newline at end of file
@@ -0,0 +1,168 @@
+// ArcSight Common Event Format
+//
+// CEF:Version|Device Vendor|Device Product|Device Version|Signature ID|Name|Severity|Extension
+//
+// The signature id is the entry's event type and the name its action. Which
+// entry fields become which extension keys is configured with `CefMapping`s.
+use std::collections::HashMap;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+
+use super::entry_field;
+
+/// Puts an entry field into a CEF extension key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CefMapping {
+    /// Entry field, as accepted by the export field lookup (`subject`, `data.patient_id`, ...)
+    pub field: String,
+    /// Extension key, e.g. `suser` or `cs1`
+    pub key: String,
+    /// Label for custom keys; emitted as `<key>Label`, e.g. `cs1Label=patient`
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CefConfig {
+    pub device_vendor: String,
+    pub device_product: String,
+    pub device_version: String,
+    pub mappings: Vec<CefMapping>,
+    /// Severity (0-10) by event type
+    pub severities: HashMap<String, u8>,
+    pub default_severity: u8,
+}
+
+impl Default for CefConfig {
+    fn default() -> Self {
+        Self {
+            device_vendor: "RustCare".to_string(),
+            device_product: "RustCare Engine".to_string(),
+            device_version: env!("CARGO_PKG_VERSION").to_string(),
+            mappings: vec![],
+            severities: HashMap::new(),
+            default_severity: 3,
+        }
+        .map("id", "externalId")
+        .map("timestamp", "rt")
+        .map("event_type", "cat")
+        .map("subject", "suser")
+        .map("action", "act")
+        .map("data.source_ip", "src")
+        .map("data.outcome", "outcome")
+        .map_labeled("data.patient_id", "cs1", "patient")
+        .map_labeled("data.resource", "cs2", "resource")
+    }
+}
+
+impl CefConfig {
+    /// Map `field` to `key`, replacing an earlier mapping to the same key
+    pub fn map(self, field: &str, key: &str) -> Self {
+        self.with_mapping(field, key, None)
+    }
+
+    pub fn map_labeled(self, field: &str, key: &str, label: &str) -> Self {
+        self.with_mapping(field, key, Some(label.to_string()))
+    }
+
+    pub fn severity(mut self, event_type: &str, severity: u8) -> Self {
+        self.severities.insert(event_type.to_string(), severity);
+        self
+    }
+
+    fn with_mapping(mut self, field: &str, key: &str, label: Option<String>) -> Self {
+        self.mappings.retain(|mapping| mapping.key != key);
+        self.mappings.push(CefMapping {
+            field: field.to_string(),
+            key: key.to_string(),
+            label,
+        });
+        self
+    }
+}
+
+pub struct CefFormatter {
+    config: CefConfig,
+}
+
+impl CefFormatter {
+    pub fn new(config: CefConfig) -> Result<Self> {
+        if let Some(mapping) = config
+            .mappings
+            .iter()
+            .find(|mapping| mapping.key.is_empty() || !mapping.key.chars().all(|c| c.is_ascii_alphanumeric()))
+        {
+            return Err(AuditError::ExportConfigError(format!(
+                "CEF extension key '{}' must be alphanumeric",
+                mapping.key
+            )));
+        }
+        if let Some(severity) = config
+            .severities
+            .values()
+            .chain(Some(&config.default_severity))
+            .find(|severity| **severity > 10)
+        {
+            return Err(AuditError::ExportConfigError(format!(
+                "CEF severity {} is outside 0-10",
+                severity
+            )));
+        }
+        Ok(Self { config })
+    }
+
+    /// Severity from 0 (lowest) to 10
+    pub fn severity(&self, entry: &AuditEntry) -> u8 {
+        self.config
+            .severities
+            .get(&entry.event_type)
+            .copied()
+            .unwrap_or(self.config.default_severity)
+    }
+
+    pub fn format(&self, entry: &AuditEntry) -> String {
+        let header = [
+            self.config.device_vendor.as_str(),
+            &self.config.device_product,
+            &self.config.device_version,
+            &entry.event_type,
+            &entry.action,
+        ]
+        .map(escape_header)
+        .join("|");
+
+        let mut extension = Vec::new();
+        for mapping in &self.config.mappings {
+            let Some(value) = entry_field(entry, &mapping.field) else {
+                continue;
+            };
+            // CEF receive times are milliseconds since the epoch
+            let value = if mapping.key == "rt" && mapping.field == "timestamp" {
+                entry.timestamp.timestamp_millis().to_string()
+            } else {
+                value
+            };
+            extension.push(format!("{}={}", mapping.key, escape_extension(&value)));
+            if let Some(label) = &mapping.label {
+                extension.push(format!("{}Label={}", mapping.key, escape_extension(label)));
+            }
+        }
+
+        format!("CEF:0|{}|{}|{}", header, self.severity(entry), extension.join(" "))
+    }
+}
+
+fn escape_header(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+fn escape_extension(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,51 @@
+// Audit export
+//
+// Formatters turn audit entries into the wire formats external systems
+// ingest, and exporters deliver them:
+// - `cef`: ArcSight Common Event Format
+// - `syslog`: RFC 5424 messages carrying CEF
+// - `siem`: batching, retrying delivery to a syslog endpoint with a local spool
+// - `pseudonymize`: de-identified datasets for analytics
+
+pub mod cef;
+pub mod pseudonymize;
+pub mod siem;
+pub mod spool;
+pub mod syslog;
+pub mod transport;
+
+pub use cef::{CefConfig, CefFormatter, CefMapping};
+pub use pseudonymize::{
+    DatasetManifest, FieldAction, FieldRule, Generalization, PseudonymKey, PseudonymizationPolicy,
+    PseudonymizedDataset, PseudonymizedEntry, Pseudonymizer, ReidentificationGrant, Reidentifier,
+    TimestampPrecision, WrappedPseudonymKey,
+};
+pub use siem::{ExportStats, SiemConfig, SiemExporter};
+pub use spool::Spool;
+pub use syslog::{Facility, SyslogConfig, SyslogFormatter};
+pub use transport::{SyslogEndpoint, SyslogTransport};
+
+use crate::entry::AuditEntry;
+use chrono::SecondsFormat;
+use serde_json::Value;
+
+/// Value of a field of `entry` by name: `id`, `timestamp`, `event_type`,
+/// `subject`, `action`, or `data.<path>` with `.`-separated keys into `data`
+pub(crate) fn entry_field(entry: &AuditEntry, field: &str) -> Option<String> {
+    match field {
+        "id" => Some(entry.id.to_string()),
+        "timestamp" => Some(entry.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)),
+        "event_type" => Some(entry.event_type.clone()),
+        "subject" => Some(entry.subject.clone()),
+        "action" => Some(entry.action.clone()),
+        _ => {
+            let path = field.strip_prefix("data.")?;
+            let value = path.split('.').try_fold(&entry.data, |value, key| value.get(key))?;
+            match value {
+                Value::Null => None,
+                Value::String(text) => Some(text.clone()),
+                other => Some(other.to_string()),
+            }
+        }
+    }
+}
@@ -0,0 +1,643 @@
+// Pseudonymized audit datasets for analytics
+//
+// Subjects and other identifiers are replaced with keyed HMAC-SHA256
+// pseudonyms. The same identifier always maps to the same pseudonym under
+// one key, so behaviour can still be followed across entries, but without
+// the key a pseudonym cannot be reversed or even confirmed by guessing.
+//
+// The key is a data key wrapped by the KMS. Exports only ever see the
+// unwrapped key in memory; the dataset records the key id and a fingerprint
+// so analysts can tell which datasets are linkable, never the key itself.
+// Going back from a pseudonym to a person needs the key and a
+// `ReidentificationGrant`, see `Reidentifier`.
+//
+// `data` fields are allowlisted: anything the policy does not name is
+// dropped, so a new field in an audit entry never leaks into a dataset
+// before the policy has been reviewed for it.
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+use chrono::{DateTime, Datelike, Duration, DurationRound, TimeZone, Utc};
+use crypto::kms::KeyManagementService;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+
+/// Domain entry subjects are pseudonymized in
+pub const SUBJECT_DOMAIN: &str = "subject";
+
+/// Domain entry ids are pseudonymized in
+const ENTRY_DOMAIN: &str = "entry";
+
+const KEY_SPEC: &str = "AES_256";
+const KEY_LEN: usize = 32;
+
+/// Pseudonyms keep 128 bits of the HMAC
+const PSEUDONYM_BYTES: usize = 16;
+
+/// Pseudonymization key, encrypted under a KMS key encryption key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedPseudonymKey {
+    pub key_id: String,
+    pub kek_id: String,
+    pub ciphertext: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Unwrapped pseudonymization key; never serialized or logged
+pub struct PseudonymKey {
+    key_id: String,
+    key: hmac::Key,
+    fingerprint: String,
+}
+
+impl std::fmt::Debug for PseudonymKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PseudonymKey")
+            .field("key_id", &self.key_id)
+            .field("fingerprint", &self.fingerprint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PseudonymKey {
+    /// Create a key under `kek_id`; store the wrapped key, not the returned one
+    pub async fn generate(kms: &dyn KeyManagementService, kek_id: &str) -> Result<(Self, WrappedPseudonymKey)> {
+        let key_id = Uuid::new_v4().to_string();
+        let (plaintext, ciphertext) = kms
+            .generate_data_key(kek_id, KEY_SPEC, Some(&key_context(&key_id)))
+            .await
+            .map_err(|e| AuditError::KeyError(format!("Cannot generate pseudonymization key: {}", e)))?;
+        let wrapped = WrappedPseudonymKey {
+            key_id: key_id.clone(),
+            kek_id: kek_id.to_string(),
+            ciphertext,
+            created_at: Utc::now(),
+        };
+        Ok((Self::from_bytes(key_id, plaintext)?, wrapped))
+    }
+
+    /// Unwrap a stored key through the KMS
+    pub async fn unwrap(kms: &dyn KeyManagementService, wrapped: &WrappedPseudonymKey) -> Result<Self> {
+        let plaintext = kms
+            .decrypt_data_key(&wrapped.ciphertext, Some(&key_context(&wrapped.key_id)))
+            .await
+            .map_err(|e| {
+                AuditError::KeyError(format!("Cannot unwrap pseudonymization key {}: {}", wrapped.key_id, e))
+            })?;
+        Self::from_bytes(wrapped.key_id.clone(), plaintext)
+    }
+
+    fn from_bytes(key_id: String, bytes: Zeroizing<Vec<u8>>) -> Result<Self> {
+        if bytes.len() < KEY_LEN {
+            return Err(AuditError::KeyError(format!(
+                "Pseudonymization key {} is {} bytes, expected at least {}",
+                key_id,
+                bytes.len(),
+                KEY_LEN
+            )));
+        }
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &bytes);
+        let fingerprint = hex(&hmac::sign(&key, b"rustcare-pseudonym-key-fingerprint").as_ref()[..8]);
+        Ok(Self {
+            key_id,
+            key,
+            fingerprint,
+        })
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Identifies the key without revealing it; equal fingerprints mean linkable datasets
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Pseudonym of `value` within `domain`
+    ///
+    /// Domains keep identifiers of different kinds apart: the same string as a
+    /// subject and as a device id gets different pseudonyms. Use one domain
+    /// for every field naming the same kind of thing, e.g. patient ids, so
+    /// that those stay joinable.
+    pub fn pseudonym(&self, domain: &str, value: &str) -> String {
+        let mut context = hmac::Context::with_key(&self.key);
+        context.update(domain.as_bytes());
+        context.update(&[0]);
+        context.update(value.as_bytes());
+        format!("ps_{}", hex(&context.sign().as_ref()[..PSEUDONYM_BYTES]))
+    }
+}
+
+fn key_context(key_id: &str) -> HashMap<String, String> {
+    HashMap::from([
+        ("purpose".to_string(), "audit-pseudonymization".to_string()),
+        ("key_id".to_string(), key_id.to_string()),
+    ])
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Precision timestamps are coarsened to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampPrecision {
+    #[default]
+    Exact,
+    Minute,
+    Hour,
+    Day,
+    /// Monday of the week
+    Week,
+    Month,
+}
+
+impl TimestampPrecision {
+    pub fn coarsen(self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let truncated = match self {
+            TimestampPrecision::Exact => return timestamp,
+            TimestampPrecision::Minute => timestamp.duration_trunc(Duration::minutes(1)),
+            TimestampPrecision::Hour => timestamp.duration_trunc(Duration::hours(1)),
+            TimestampPrecision::Day => timestamp.duration_trunc(Duration::days(1)),
+            TimestampPrecision::Week => timestamp
+                .duration_trunc(Duration::days(1))
+                .map(|day| day - Duration::days(day.weekday().num_days_from_monday() as i64)),
+            TimestampPrecision::Month => {
+                return Utc
+                    .with_ymd_and_hms(timestamp.year(), timestamp.month(), 1, 0, 0, 0)
+                    .single()
+                    .unwrap_or(timestamp)
+            }
+        };
+        truncated.unwrap_or(timestamp)
+    }
+}
+
+/// How a value is made less specific
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Generalization {
+    /// Keep the first characters, e.g. the 3-digit prefix of a ZIP code
+    Prefix { length: usize },
+    /// Replace a number with the band it falls in, e.g. `30-39` for ages
+    NumericBand { width: u64 },
+    /// Keep only the year of a date or timestamp
+    Year,
+    /// Zero the host bits of an IP address
+    IpNetwork { v4_prefix: u8, v6_prefix: u8 },
+    /// Replace values with categories; values in no category become `other`
+    Categories { categories: BTreeMap<String, Vec<String>> },
+}
+
+impl Generalization {
+    fn validate(&self, field: &str) -> Result<()> {
+        let invalid = match self {
+            Generalization::Prefix { length } => *length == 0,
+            Generalization::NumericBand { width } => *width == 0,
+            Generalization::IpNetwork { v4_prefix, v6_prefix } => *v4_prefix > 32 || *v6_prefix > 128,
+            Generalization::Year | Generalization::Categories { .. } => false,
+        };
+        if invalid {
+            return Err(AuditError::ExportConfigError(format!(
+                "Invalid generalization {:?} for field '{}'",
+                self, field
+            )));
+        }
+        Ok(())
+    }
+
+    /// Generalized value; `None` when the value does not have the expected shape
+    fn apply(&self, value: &Value) -> Option<Value> {
+        match self {
+            Generalization::Prefix { length } => {
+                let text = value_text(value)?;
+                Some(Value::String(text.chars().take(*length).collect()))
+            }
+            Generalization::NumericBand { width } => {
+                let number = match value {
+                    Value::Number(number) => number.as_f64()?,
+                    Value::String(text) => text.trim().parse().ok()?,
+                    _ => return None,
+                };
+                if !number.is_finite() || number < 0.0 {
+                    return None;
+                }
+                let low = (number as u64 / width) * width;
+                Some(Value::String(format!("{}-{}", low, low + width - 1)))
+            }
+            Generalization::Year => {
+                let text = value_text(value)?;
+                let year = text.get(..4).filter(|year| year.chars().all(|c| c.is_ascii_digit()))?;
+                Some(Value::String(year.to_string()))
+            }
+            Generalization::IpNetwork { v4_prefix, v6_prefix } => {
+                let address: IpAddr = value_text(value)?.trim().parse().ok()?;
+                let network = match address {
+                    IpAddr::V4(v4) => {
+                        let mask = u32::MAX.checked_shl(32 - *v4_prefix as u32).unwrap_or(0);
+                        IpAddr::from((u32::from(v4) & mask).to_be_bytes())
+                    }
+                    IpAddr::V6(v6) => {
+                        let mask = u128::MAX.checked_shl(128 - *v6_prefix as u32).unwrap_or(0);
+                        IpAddr::from((u128::from(v6) & mask).to_be_bytes())
+                    }
+                };
+                let prefix = if address.is_ipv4() { v4_prefix } else { v6_prefix };
+                Some(Value::String(format!("{}/{}", network, prefix)))
+            }
+            Generalization::Categories { categories } => {
+                let text = value_text(value)?;
+                let category = categories
+                    .iter()
+                    .find(|(_, members)| members.iter().any(|member| member == &text))
+                    .map(|(category, _)| category.clone())
+                    .unwrap_or_else(|| "other".to_string());
+                Some(Value::String(category))
+            }
+        }
+    }
+}
+
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// What happens to an allowlisted `data` field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FieldAction {
+    /// Copy unchanged; only for values reviewed as not identifying
+    Keep,
+    /// Replace with its pseudonym in `domain`
+    Pseudonymize { domain: String },
+    Generalize(Generalization),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldRule {
+    /// Path into `data`, `.`-separated, e.g. `patient_id` or `location.zip`
+    pub path: String,
+    #[serde(flatten)]
+    pub action: FieldAction,
+}
+
+/// Which parts of an entry reach a dataset, and in what form
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PseudonymizationPolicy {
+    /// Recorded in the dataset manifest so reviewers know what was applied
+    pub name: String,
+    pub timestamp_precision: TimestampPrecision,
+    /// `data` fields to include; all others are dropped
+    pub fields: Vec<FieldRule>,
+}
+
+impl PseudonymizationPolicy {
+    /// Policy that keeps no `data` fields and exact timestamps
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            timestamp_precision: TimestampPrecision::Exact,
+            fields: vec![],
+        }
+    }
+
+    pub fn timestamps(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+
+    pub fn keep(self, path: &str) -> Self {
+        self.rule(path, FieldAction::Keep)
+    }
+
+    pub fn pseudonymize(self, path: &str, domain: &str) -> Self {
+        self.rule(
+            path,
+            FieldAction::Pseudonymize {
+                domain: domain.to_string(),
+            },
+        )
+    }
+
+    pub fn generalize(self, path: &str, generalization: Generalization) -> Self {
+        self.rule(path, FieldAction::Generalize(generalization))
+    }
+
+    fn rule(mut self, path: &str, action: FieldAction) -> Self {
+        self.fields.retain(|rule| rule.path != path);
+        self.fields.push(FieldRule {
+            path: path.to_string(),
+            action,
+        });
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for rule in &self.fields {
+            if rule.path.is_empty() || rule.path.split('.').any(str::is_empty) {
+                return Err(AuditError::ExportConfigError(format!(
+                    "Invalid data field path '{}' in pseudonymization policy '{}'",
+                    rule.path, self.name
+                )));
+            }
+            match &rule.action {
+                FieldAction::Keep => {}
+                FieldAction::Pseudonymize { domain } if domain.is_empty() => {
+                    return Err(AuditError::ExportConfigError(format!(
+                        "Field '{}' needs a pseudonymization domain",
+                        rule.path
+                    )));
+                }
+                FieldAction::Pseudonymize { .. } => {}
+                FieldAction::Generalize(generalization) => generalization.validate(&rule.path)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Audit entry with identities replaced and PII removed or generalized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PseudonymizedEntry {
+    /// Pseudonym of the source entry id, stable across exports with one key
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub event_type: String,
+    pub subject: String,
+    pub action: String,
+    pub data: Value,
+}
+
+/// Describes a dataset for the people receiving and reviewing it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatasetManifest {
+    pub dataset_id: Uuid,
+    pub generated_at: DateTime<Utc>,
+    pub key_id: String,
+    pub key_fingerprint: String,
+    pub policy: PseudonymizationPolicy,
+    pub entry_count: usize,
+    /// `data` paths that were present in entries but dropped, with how often
+    pub dropped_fields: BTreeMap<String, usize>,
+    /// Allowlisted fields whose values had an unexpected shape and were dropped
+    pub ungeneralizable_values: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PseudonymizedDataset {
+    pub manifest: DatasetManifest,
+    pub entries: Vec<PseudonymizedEntry>,
+}
+
+impl PseudonymizedDataset {
+    /// Manifest on the first line, then one entry per line
+    pub fn to_jsonl(&self) -> Result<String> {
+        let mut lines = vec![serde_json::to_string(&self.manifest)];
+        lines.extend(self.entries.iter().map(serde_json::to_string));
+        let lines = lines
+            .into_iter()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| AuditError::InternalError(e.into()))?;
+        Ok(lines.join("\n") + "\n")
+    }
+}
+
+pub struct Pseudonymizer {
+    key: PseudonymKey,
+    policy: PseudonymizationPolicy,
+}
+
+impl Pseudonymizer {
+    pub fn new(key: PseudonymKey, policy: PseudonymizationPolicy) -> Result<Self> {
+        policy.validate()?;
+        Ok(Self { key, policy })
+    }
+
+    pub fn policy(&self) -> &PseudonymizationPolicy {
+        &self.policy
+    }
+
+    pub fn pseudonymize(&self, entry: &AuditEntry) -> PseudonymizedEntry {
+        self.pseudonymize_counting(entry, &mut BTreeMap::new())
+    }
+
+    pub fn export<'a>(&self, entries: impl IntoIterator<Item = &'a AuditEntry>) -> PseudonymizedDataset {
+        let mut dropped_fields = BTreeMap::new();
+        let mut ungeneralizable_values = BTreeMap::new();
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|entry| {
+                collect_dropped(&entry.data, "", &self.policy.fields, &mut dropped_fields);
+                self.pseudonymize_counting(entry, &mut ungeneralizable_values)
+            })
+            .collect();
+
+        PseudonymizedDataset {
+            manifest: DatasetManifest {
+                dataset_id: Uuid::new_v4(),
+                generated_at: Utc::now(),
+                key_id: self.key.key_id().to_string(),
+                key_fingerprint: self.key.fingerprint().to_string(),
+                policy: self.policy.clone(),
+                entry_count: entries.len(),
+                dropped_fields,
+                ungeneralizable_values,
+            },
+            entries,
+        }
+    }
+
+    fn pseudonymize_counting(
+        &self,
+        entry: &AuditEntry,
+        ungeneralizable: &mut BTreeMap<String, usize>,
+    ) -> PseudonymizedEntry {
+        let mut data = Value::Object(Map::new());
+        for rule in &self.policy.fields {
+            let Some(value) = rule.path.split('.').try_fold(&entry.data, |value, key| value.get(key)) else {
+                continue;
+            };
+            if value.is_null() {
+                continue;
+            }
+            let transformed = match &rule.action {
+                FieldAction::Keep => Some(value.clone()),
+                FieldAction::Pseudonymize { domain } => pseudonymize_value(&self.key, domain, value),
+                FieldAction::Generalize(generalization) => generalization.apply(value),
+            };
+            match transformed {
+                Some(transformed) => insert_path(&mut data, &rule.path, transformed),
+                None => *ungeneralizable.entry(rule.path.clone()).or_default() += 1,
+            }
+        }
+
+        PseudonymizedEntry {
+            id: self.key.pseudonym(ENTRY_DOMAIN, &entry.id.to_string()),
+            timestamp: self.policy.timestamp_precision.coarsen(entry.timestamp),
+            event_type: entry.event_type.clone(),
+            subject: self.key.pseudonym(SUBJECT_DOMAIN, &entry.subject),
+            action: entry.action.clone(),
+            data,
+        }
+    }
+}
+
+/// Pseudonymize a scalar, or each element of an array of scalars
+fn pseudonymize_value(key: &PseudonymKey, domain: &str, value: &Value) -> Option<Value> {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| pseudonymize_value(key, domain, item))
+            .collect::<Option<Vec<_>>>()
+            .map(Value::Array),
+        other => value_text(other).map(|text| Value::String(key.pseudonym(domain, &text))),
+    }
+}
+
+fn insert_path(target: &mut Value, path: &str, value: Value) {
+    let mut current = target;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        let Value::Object(map) = current else {
+            return;
+        };
+        if keys.peek().is_none() {
+            map.insert(key.to_string(), value);
+            return;
+        }
+        current = map.entry(key.to_string()).or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+/// Count leaf paths of `data` that no rule covers
+fn collect_dropped(data: &Value, prefix: &str, rules: &[FieldRule], dropped: &mut BTreeMap<String, usize>) {
+    let Value::Object(map) = data else {
+        return;
+    };
+    for (key, value) in map {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        if rules.iter().any(|rule| rule.path == path) {
+            continue;
+        }
+        let is_ancestor = rules.iter().any(|rule| {
+            rule.path
+                .strip_prefix(path.as_str())
+                .is_some_and(|rest| rest.starts_with('.'))
+        });
+        if is_ancestor && value.is_object() {
+            collect_dropped(value, &path, rules, dropped);
+        } else {
+            *dropped.entry(path).or_default() += 1;
+        }
+    }
+}
+
+/// Approval to re-identify pseudonyms from one key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReidentificationGrant {
+    pub grant_id: Uuid,
+    pub key_id: String,
+    /// Who asked, e.g. an investigator
+    pub requested_by: String,
+    /// Who approved, e.g. the privacy officer; must differ from `requested_by`
+    pub approved_by: String,
+    pub reason: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Matches pseudonyms back to identifiers under a grant
+///
+/// HMAC pseudonyms cannot be decrypted. Re-identification recomputes the
+/// pseudonyms of candidate identifiers supplied by the requester, e.g. the
+/// staff list of a department, and reports which ones match. Every use
+/// produces an audit entry that the caller must log.
+pub struct Reidentifier {
+    key: PseudonymKey,
+    grant: ReidentificationGrant,
+}
+
+impl Reidentifier {
+    pub fn new(key: PseudonymKey, grant: ReidentificationGrant) -> Result<Self> {
+        if grant.key_id != key.key_id() {
+            return Err(AuditError::ReidentificationDenied(format!(
+                "Grant {} is for key {}, not {}",
+                grant.grant_id,
+                grant.key_id,
+                key.key_id()
+            )));
+        }
+        if grant.approved_by.trim().is_empty() || grant.approved_by == grant.requested_by {
+            return Err(AuditError::ReidentificationDenied(format!(
+                "Grant {} must be approved by someone other than the requester",
+                grant.grant_id
+            )));
+        }
+        if grant.reason.trim().is_empty() {
+            return Err(AuditError::ReidentificationDenied(format!(
+                "Grant {} has no reason",
+                grant.grant_id
+            )));
+        }
+        Ok(Self { key, grant })
+    }
+
+    /// Identifiers among `candidates` whose pseudonym in `domain` is one of `pseudonyms`
+    ///
+    /// Returns the matches by pseudonym and the audit entry recording the lookup.
+    pub fn reidentify(
+        &self,
+        domain: &str,
+        pseudonyms: &[String],
+        candidates: &[String],
+    ) -> Result<(HashMap<String, String>, AuditEntry)> {
+        let now = Utc::now();
+        if now >= self.grant.expires_at {
+            return Err(AuditError::ReidentificationDenied(format!(
+                "Grant {} expired at {}",
+                self.grant.grant_id, self.grant.expires_at
+            )));
+        }
+
+        let matches: HashMap<String, String> = candidates
+            .iter()
+            .map(|candidate| (self.key.pseudonym(domain, candidate), candidate.clone()))
+            .filter(|(pseudonym, _)| pseudonyms.contains(pseudonym))
+            .collect();
+
+        let audit = AuditEntry {
+            id: Uuid::new_v4(),
+            timestamp: now,
+            event_type: "data_access".to_string(),
+            subject: self.grant.requested_by.clone(),
+            action: "audit_pseudonyms_reidentified".to_string(),
+            data: serde_json::json!({
+                "grant_id": self.grant.grant_id,
+                "key_id": self.grant.key_id,
+                "approved_by": self.grant.approved_by,
+                "reason": self.grant.reason,
+                "domain": domain,
+                "pseudonyms_requested": pseudonyms.len(),
+                "candidates_checked": candidates.len(),
+                "pseudonyms_matched": matches.keys().collect::<Vec<_>>(),
+            }),
+        };
+        Ok((matches, audit))
+    }
+}
@@ -0,0 +1,264 @@
+// Near-real-time export of audit entries to a SIEM over syslog
+//
+// `SiemExporter::export` queues an entry and returns; a background worker
+// sends queued entries in batches of `batch_size`, or whatever has queued
+// after `flush_interval`. The queue is bounded, so when the worker falls
+// behind `export` waits for room instead of growing memory.
+//
+// No entry is dropped. A batch that still fails after `max_retries` is
+// written to the spool and replayed, oldest first, before any newer batch is
+// sent. If even the spool cannot be written the worker keeps retrying that
+// batch and stops taking new ones, which pushes back on callers.
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+
+use super::cef::{CefConfig, CefFormatter};
+use super::spool::Spool;
+use super::syslog::{SyslogConfig, SyslogFormatter};
+use super::transport::{Connection, SyslogEndpoint};
+
+#[derive(Debug, Clone)]
+pub struct SiemConfig {
+    pub endpoint: SyslogEndpoint,
+    pub syslog: SyslogConfig,
+    pub cef: CefConfig,
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+    /// Entries that may wait for the worker before `export` blocks
+    pub queue_capacity: usize,
+    /// Attempts to send a batch before it is spooled
+    pub max_retries: u32,
+    /// Wait before the first retry; doubled for each further one
+    pub retry_backoff: Duration,
+    pub connect_timeout: Duration,
+    pub spool_dir: PathBuf,
+}
+
+impl SiemConfig {
+    pub fn new(endpoint: SyslogEndpoint, spool_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            endpoint,
+            syslog: SyslogConfig::default(),
+            cef: CefConfig::default(),
+            batch_size: 100,
+            flush_interval: Duration::from_secs(1),
+            queue_capacity: 10_000,
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            connect_timeout: Duration::from_secs(5),
+            spool_dir: spool_dir.into(),
+        }
+    }
+}
+
+/// Delivery counters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExportStats {
+    pub delivered: u64,
+    pub spooled: u64,
+    /// Spooled entries delivered later
+    pub replayed: u64,
+    pub failed_attempts: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    delivered: AtomicU64,
+    spooled: AtomicU64,
+    replayed: AtomicU64,
+    failed_attempts: AtomicU64,
+}
+
+enum Command {
+    Export(Box<AuditEntry>),
+    Flush(oneshot::Sender<Result<()>>),
+}
+
+pub struct SiemExporter {
+    sender: mpsc::Sender<Command>,
+    worker: JoinHandle<()>,
+    counters: Arc<Counters>,
+}
+
+impl SiemExporter {
+    /// Validate `config`, open the spool and start the delivery worker
+    pub async fn start(config: SiemConfig) -> Result<Self> {
+        if config.batch_size == 0 || config.queue_capacity == 0 {
+            return Err(AuditError::ExportConfigError(
+                "SIEM batch size and queue capacity must be at least 1".to_string(),
+            ));
+        }
+        let formatter = SyslogFormatter::new(config.syslog.clone(), CefFormatter::new(config.cef.clone())?)?;
+        let spool = Spool::open(&config.spool_dir).await?;
+        let counters = Arc::new(Counters::default());
+        let (sender, receiver) = mpsc::channel(config.queue_capacity);
+
+        let worker = Worker {
+            config,
+            formatter,
+            spool,
+            counters: counters.clone(),
+            connection: None,
+        };
+        let worker = tokio::spawn(worker.run(receiver));
+        Ok(Self {
+            sender,
+            worker,
+            counters,
+        })
+    }
+
+    /// Queue `entry` for delivery, waiting while the queue is full
+    pub async fn export(&self, entry: AuditEntry) -> Result<()> {
+        self.sender
+            .send(Command::Export(Box::new(entry)))
+            .await
+            .map_err(|_| AuditError::DeliveryError("SIEM exporter has stopped".to_string()))
+    }
+
+    /// Deliver or spool everything queued so far
+    pub async fn flush(&self) -> Result<()> {
+        let (ack, done) = oneshot::channel();
+        self.sender
+            .send(Command::Flush(ack))
+            .await
+            .map_err(|_| AuditError::DeliveryError("SIEM exporter has stopped".to_string()))?;
+        done.await
+            .map_err(|_| AuditError::DeliveryError("SIEM exporter has stopped".to_string()))?
+    }
+
+    /// Flush and stop the worker
+    pub async fn shutdown(self) -> Result<()> {
+        let flushed = self.flush().await;
+        drop(self.sender);
+        self.worker
+            .await
+            .map_err(|e| AuditError::DeliveryError(format!("SIEM exporter worker failed: {}", e)))?;
+        flushed
+    }
+
+    pub fn stats(&self) -> ExportStats {
+        ExportStats {
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            spooled: self.counters.spooled.load(Ordering::Relaxed),
+            replayed: self.counters.replayed.load(Ordering::Relaxed),
+            failed_attempts: self.counters.failed_attempts.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct Worker {
+    config: SiemConfig,
+    formatter: SyslogFormatter,
+    spool: Spool,
+    counters: Arc<Counters>,
+    connection: Option<Connection>,
+}
+
+impl Worker {
+    async fn run(mut self, mut receiver: mpsc::Receiver<Command>) {
+        let mut batch = Vec::with_capacity(self.config.batch_size);
+        let mut ticker = tokio::time::interval(self.config.flush_interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                command = receiver.recv() => match command {
+                    Some(Command::Export(entry)) => {
+                        batch.push(*entry);
+                        if batch.len() >= self.config.batch_size {
+                            self.dispatch(std::mem::take(&mut batch)).await;
+                        }
+                    }
+                    Some(Command::Flush(ack)) => {
+                        self.dispatch(std::mem::take(&mut batch)).await;
+                        let _ = ack.send(self.replay().await);
+                    }
+                    None => {
+                        self.dispatch(std::mem::take(&mut batch)).await;
+                        break;
+                    }
+                },
+                _ = ticker.tick() => {
+                    self.dispatch(std::mem::take(&mut batch)).await;
+                }
+            }
+        }
+    }
+
+    /// Deliver `batch` after anything already spooled, spooling it on failure
+    async fn dispatch(&mut self, batch: Vec<AuditEntry>) {
+        let backlog = self.replay().await.is_err();
+        if batch.is_empty() {
+            return;
+        }
+        if !backlog && self.deliver(&batch).await.is_ok() {
+            self.counters.delivered.fetch_add(batch.len() as u64, Ordering::Relaxed);
+            return;
+        }
+
+        let mut delay = self.config.retry_backoff;
+        while let Err(error) = self.spool.write(&batch).await {
+            tracing::error!(error = %error, entries = batch.len(), "Cannot spool undelivered audit entries; retrying");
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(Duration::from_secs(60));
+        }
+        self.counters.spooled.fetch_add(batch.len() as u64, Ordering::Relaxed);
+        tracing::warn!(entries = batch.len(), "SIEM unreachable; spooled audit entries for replay");
+    }
+
+    /// Send spooled batches oldest first; stops at the first that fails
+    async fn replay(&mut self) -> Result<()> {
+        for file in self.spool.pending().await? {
+            let entries = self.spool.read(&file).await?;
+            self.deliver(&entries).await?;
+            self.spool.remove(&file).await?;
+            self.counters.replayed.fetch_add(entries.len() as u64, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    async fn deliver(&mut self, entries: &[AuditEntry]) -> Result<()> {
+        let messages: Vec<String> = entries.iter().map(|entry| self.formatter.format(entry)).collect();
+        let mut delay = self.config.retry_backoff;
+        let mut attempt = 1;
+        loop {
+            match self.send(&messages).await {
+                Ok(()) => return Ok(()),
+                Err(error) => {
+                    self.connection = None;
+                    self.counters.failed_attempts.fetch_add(1, Ordering::Relaxed);
+                    if attempt >= self.config.max_retries {
+                        return Err(error);
+                    }
+                    tracing::warn!(error = %error, attempt, "Audit export to SIEM failed; retrying");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn send(&mut self, messages: &[String]) -> Result<()> {
+        let connection = match self.connection.as_mut() {
+            Some(connection) => connection,
+            None => self
+                .connection
+                .insert(Connection::open(&self.config.endpoint, self.config.connect_timeout).await?),
+        };
+        connection
+            .send(messages)
+            .await
+            .map_err(|e| AuditError::DeliveryError(format!("Sending to SIEM failed: {}", e)))
+    }
+}
@@ -0,0 +1,89 @@
+// Local spool for entries the SIEM could not take
+//
+// Each undeliverable batch becomes one file of JSON lines, written to a
+// temporary name, synced and then renamed, so a crash leaves either the
+// whole batch or nothing. Files are named by the time they were spooled and
+// replayed oldest first; a file is deleted only after it was delivered.
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+
+const SPOOL_EXTENSION: &str = "jsonl";
+
+pub struct Spool {
+    dir: PathBuf,
+    sequence: AtomicU64,
+}
+
+impl Spool {
+    pub async fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).await.map_err(|e| spool_error(&dir, e))?;
+        Ok(Self {
+            dir,
+            sequence: AtomicU64::new(0),
+        })
+    }
+
+    pub async fn write(&self, entries: &[AuditEntry]) -> Result<()> {
+        let mut lines = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut lines, entry)
+                .map_err(|e| AuditError::DeliveryError(format!("Cannot serialize audit entry {}: {}", entry.id, e)))?;
+            lines.push(b'\n');
+        }
+
+        let name = format!(
+            "{:020}-{:06}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            self.sequence.fetch_add(1, Ordering::Relaxed) % 1_000_000
+        );
+        let partial = self.dir.join(format!("{}.tmp", name));
+        let complete = self.dir.join(format!("{}.{}", name, SPOOL_EXTENSION));
+        let mut file = fs::File::create(&partial).await.map_err(|e| spool_error(&partial, e))?;
+        file.write_all(&lines).await.map_err(|e| spool_error(&partial, e))?;
+        file.sync_all().await.map_err(|e| spool_error(&partial, e))?;
+        fs::rename(&partial, &complete).await.map_err(|e| spool_error(&complete, e))
+    }
+
+    /// Spooled batch files, oldest first
+    pub async fn pending(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut dir = fs::read_dir(&self.dir).await.map_err(|e| spool_error(&self.dir, e))?;
+        while let Some(item) = dir.next_entry().await.map_err(|e| spool_error(&self.dir, e))? {
+            let path = item.path();
+            if path.extension().is_some_and(|extension| extension == SPOOL_EXTENSION) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    pub async fn read(&self, file: &Path) -> Result<Vec<AuditEntry>> {
+        let content = fs::read_to_string(file).await.map_err(|e| spool_error(file, e))?;
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|e| {
+                    AuditError::DeliveryError(format!("Corrupt spool file {}: {}", file.display(), e))
+                })
+            })
+            .collect()
+    }
+
+    /// Remove a file once its entries were delivered
+    pub async fn remove(&self, file: &Path) -> Result<()> {
+        fs::remove_file(file).await.map_err(|e| spool_error(file, e))
+    }
+}
+
+fn spool_error(path: &Path, error: std::io::Error) -> AuditError {
+    AuditError::DeliveryError(format!("Spool I/O on {} failed: {}", path.display(), error))
+}
@@ -0,0 +1,115 @@
+// RFC 5424 syslog messages
+//
+// <PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID [STRUCTURED-DATA] MSG
+//
+// The message is the entry in CEF, which is how SIEMs such as ArcSight and
+// QRadar expect to receive it. The entry id and event type are repeated as
+// structured data so collectors can route without parsing CEF.
+use chrono::SecondsFormat;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+
+use super::cef::CefFormatter;
+
+/// Syslog facility
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facility {
+    Auth = 4,
+    AuthPriv = 10,
+    LogAudit = 13,
+    LogAlert = 14,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyslogConfig {
+    pub facility: Facility,
+    pub hostname: String,
+    pub app_name: String,
+    pub msg_id: String,
+    /// SD-ID of the structured data element, `name@<private enterprise number>`
+    pub sd_id: String,
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        Self {
+            facility: Facility::LogAudit,
+            hostname: std::env::var("HOSTNAME").unwrap_or_else(|_| "-".to_string()),
+            app_name: "rustcare".to_string(),
+            msg_id: "audit".to_string(),
+            // 32473 is the enterprise number reserved for documentation (RFC 5612)
+            sd_id: "rustcare@32473".to_string(),
+        }
+    }
+}
+
+pub struct SyslogFormatter {
+    config: SyslogConfig,
+    cef: CefFormatter,
+}
+
+impl SyslogFormatter {
+    pub fn new(config: SyslogConfig, cef: CefFormatter) -> Result<Self> {
+        for (name, value, max) in [
+            ("hostname", &config.hostname, 255),
+            ("app name", &config.app_name, 48),
+            ("message id", &config.msg_id, 32),
+            ("structured data id", &config.sd_id, 32),
+        ] {
+            let printable = value.bytes().all(|byte| (33..=126).contains(&byte));
+            if value.is_empty() || value.len() > max || !printable {
+                return Err(AuditError::ExportConfigError(format!(
+                    "Syslog {} '{}' must be 1-{} printable ASCII characters without spaces",
+                    name, value, max
+                )));
+            }
+        }
+        if config.sd_id.contains(['=', ']', '"']) {
+            return Err(AuditError::ExportConfigError(format!(
+                "Syslog structured data id '{}' may not contain '=', ']' or '\"'",
+                config.sd_id
+            )));
+        }
+        Ok(Self { config, cef })
+    }
+
+    pub fn format(&self, entry: &AuditEntry) -> String {
+        let priority = self.config.facility as u8 * 8 + syslog_severity(self.cef.severity(entry));
+        format!(
+            "<{}>1 {} {} {} - {} [{} id=\"{}\" event_type=\"{}\"] {}",
+            priority,
+            entry.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+            self.config.hostname,
+            self.config.app_name,
+            self.config.msg_id,
+            self.config.sd_id,
+            entry.id,
+            escape_param(&entry.event_type),
+            self.cef.format(entry),
+        )
+    }
+}
+
+/// Syslog severity for a CEF severity; lower is more severe
+fn syslog_severity(cef_severity: u8) -> u8 {
+    match cef_severity {
+        9.. => 2,    // critical
+        7..=8 => 3,  // error
+        5..=6 => 4,  // warning
+        3..=4 => 5,  // notice
+        _ => 6,      // informational
+    }
+}
+
+fn escape_param(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]")
+}
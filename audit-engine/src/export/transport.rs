@@ -0,0 +1,158 @@
+// Syslog transports
+//
+// UDP sends one message per datagram and cannot tell whether it arrived, so
+// compliance feeds should use TCP or TLS. Over TCP and TLS each message is
+// framed with its length in octets (RFC 6587, RFC 5425). Syslog has no
+// acknowledgements, so even over TCP a write into a connection the collector
+// has just dropped can appear to succeed.
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::error::{AuditError, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+    Tls {
+        /// PEM bundle of CAs to trust instead of the public web roots
+        ca_file: Option<PathBuf>,
+        /// Name to verify the certificate against when it differs from the host
+        server_name: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyslogEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub transport: SyslogTransport,
+}
+
+impl SyslogEndpoint {
+    pub fn tls(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            transport: SyslogTransport::Tls {
+                ca_file: None,
+                server_name: None,
+            },
+        }
+    }
+
+    pub fn tcp(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            transport: SyslogTransport::Tcp,
+        }
+    }
+
+    pub fn udp(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            transport: SyslogTransport::Udp,
+        }
+    }
+
+    fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// Open connection to a syslog endpoint
+pub(crate) enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Connection {
+    pub(crate) async fn open(endpoint: &SyslogEndpoint, timeout: Duration) -> Result<Self> {
+        let failed = |e: &dyn std::fmt::Display| {
+            AuditError::DeliveryError(format!("Cannot connect to {}: {}", endpoint.address(), e))
+        };
+        let connect = async {
+            match &endpoint.transport {
+                SyslogTransport::Udp => {
+                    let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(|e| failed(&e))?;
+                    socket.connect(endpoint.address()).await.map_err(|e| failed(&e))?;
+                    Ok(Connection::Udp(socket))
+                }
+                SyslogTransport::Tcp => {
+                    let stream = TcpStream::connect(endpoint.address()).await.map_err(|e| failed(&e))?;
+                    Ok(Connection::Tcp(stream))
+                }
+                SyslogTransport::Tls { ca_file, server_name } => {
+                    let connector = TlsConnector::from(Arc::new(tls_config(ca_file.as_ref())?));
+                    let name = server_name.clone().unwrap_or_else(|| endpoint.host.clone());
+                    let name = ServerName::try_from(name).map_err(|e| failed(&e))?;
+                    let stream = TcpStream::connect(endpoint.address()).await.map_err(|e| failed(&e))?;
+                    let stream = connector.connect(name, stream).await.map_err(|e| failed(&e))?;
+                    Ok(Connection::Tls(Box::new(stream)))
+                }
+            }
+        };
+        tokio::time::timeout(timeout, connect)
+            .await
+            .map_err(|_| failed(&"connection timed out"))?
+    }
+
+    /// Send `messages` in order; an error means some may not have been delivered
+    pub(crate) async fn send(&mut self, messages: &[String]) -> std::io::Result<()> {
+        match self {
+            Connection::Udp(socket) => {
+                for message in messages {
+                    socket.send(message.as_bytes()).await?;
+                }
+                Ok(())
+            }
+            Connection::Tcp(stream) => write_framed(stream, messages).await,
+            Connection::Tls(stream) => write_framed(stream.as_mut(), messages).await,
+        }
+    }
+}
+
+async fn write_framed<W: AsyncWriteExt + Unpin>(writer: &mut W, messages: &[String]) -> std::io::Result<()> {
+    let mut frames = Vec::new();
+    for message in messages {
+        frames.extend_from_slice(format!("{} ", message.len()).as_bytes());
+        frames.extend_from_slice(message.as_bytes());
+    }
+    writer.write_all(&frames).await?;
+    writer.flush().await
+}
+
+fn tls_config(ca_file: Option<&PathBuf>) -> Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    match ca_file {
+        Some(path) => {
+            let pem = std::fs::read(path).map_err(|e| {
+                AuditError::ExportConfigError(format!("Cannot read CA file {}: {}", path.display(), e))
+            })?;
+            for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+                let cert = cert.map_err(|e| {
+                    AuditError::ExportConfigError(format!("Invalid certificate in {}: {}", path.display(), e))
+                })?;
+                roots.add(cert).map_err(|e| {
+                    AuditError::ExportConfigError(format!("Invalid certificate in {}: {}", path.display(), e))
+                })?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| AuditError::ExportConfigError(format!("TLS configuration failed: {}", e)))
+        .map(|builder| builder.with_root_certificates(roots).with_no_client_auth())
+}
@@ -1,2 +1,101 @@
-// Merkle tree for audit integrity stub
-pub struct MerkleTree {}
\ No newline at end of file
+// Hashing for audit integrity
+//
+// Stored entries form a hash chain: each link is the hash of the previous
+// link and the entry's leaf hash, so changing, removing or reordering an
+// entry breaks every later link. Sets of leaves, such as the entries removed
+// by a retention purge, are summarized by a Merkle root.
+//
+// New leaves collect in an `Accumulator` until the storage commits them as a
+// checkpoint: one Merkle root over the batch, so that the per-write cost is a
+// hash and committing is paid once per batch. A Merkle proof shows an entry
+// belongs to the checkpoint whose root covers it.
+use std::time::Instant;
+
+use rs_merkle::algorithms::Sha256 as MerkleSha256;
+use rs_merkle::MerkleProof;
+use sha2::{Digest, Sha256};
+
+use crate::entry::AuditEntry;
+
+pub type Hash = [u8; 32];
+
+/// Link before the first entry
+pub const GENESIS: Hash = [0; 32];
+
+pub fn leaf_hash(entry: &AuditEntry) -> Hash {
+    let bytes = serde_json::to_vec(entry).expect("audit entries always serialize");
+    Sha256::digest(bytes).into()
+}
+
+pub fn chain_hash(previous: &Hash, leaf: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(previous);
+    hasher.update(leaf);
+    hasher.finalize().into()
+}
+
+pub struct MerkleTree {
+    tree: rs_merkle::MerkleTree<MerkleSha256>,
+}
+
+impl MerkleTree {
+    pub fn from_leaves(leaves: &[Hash]) -> Self {
+        Self {
+            tree: rs_merkle::MerkleTree::from_leaves(leaves),
+        }
+    }
+
+    /// Root of the tree; `None` when it has no leaves
+    pub fn root(&self) -> Option<Hash> {
+        self.tree.root()
+    }
+
+    /// Hashes proving the leaf at `index` is part of this tree
+    pub fn proof(&self, index: usize) -> Vec<Hash> {
+        self.tree.proof(&[index]).proof_hashes().to_vec()
+    }
+}
+
+/// Whether `proof` places `leaf` at `index` in a tree of `leaf_count` leaves with `root`
+pub fn verify_inclusion(root: &Hash, index: usize, leaf: &Hash, leaf_count: usize, proof: &[Hash]) -> bool {
+    index < leaf_count
+        && MerkleProof::<MerkleSha256>::new(proof.to_vec()).verify(*root, &[index], &[*leaf], leaf_count)
+}
+
+/// Leaves appended since the last checkpoint
+#[derive(Debug, Default)]
+pub struct Accumulator {
+    leaves: Vec<Hash>,
+    /// When the first pending leaf arrived
+    opened_at: Option<Instant>,
+}
+
+impl Accumulator {
+    pub fn push(&mut self, leaf: Hash) {
+        self.opened_at.get_or_insert_with(Instant::now);
+        self.leaves.push(leaf);
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// How long the oldest pending leaf has waited
+    pub fn age(&self) -> std::time::Duration {
+        self.opened_at.map(|opened| opened.elapsed()).unwrap_or_default()
+    }
+
+    pub fn root(&self) -> Option<Hash> {
+        MerkleTree::from_leaves(&self.leaves).root()
+    }
+
+    /// Empty the accumulator, returning what it held
+    pub fn take(&mut self) -> Vec<Hash> {
+        self.opened_at = None;
+        std::mem::take(&mut self.leaves)
+    }
+}
@@ -1,2 +0,0 @@
-// Audit storage implementation stub
-pub struct AuditStorage {}
\ No newline at end of file
@@ -0,0 +1,330 @@
+// Merkle checkpoints
+//
+// Appending an entry costs two hashes and a buffered write. Every
+// `max_entries` entries, or once the oldest unchecked entry is
+// `max_interval` old, the pending entries are flushed to disk and committed
+// as a checkpoint: a Merkle root over their leaf hashes, the chain link they
+// end at, and the hash of the previous checkpoint, signed when the storage
+// has a signing key. Checkpoints cover consecutive sequence ranges, so each
+// entry belongs to exactly one, and an inclusion proof against that
+// checkpoint's root shows the entry was logged as it reads now.
+//
+// After a crash, at most `max_entries - 1` entries or `max_interval` worth of
+// entries are not covered by a checkpoint. If the process died, they are on
+// disk, still hash-chained, and the next checkpoint after reopening covers
+// them. If the machine lost power, they may not have reached the disk at all.
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::task::JoinHandle;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+use crate::merkle::{self, Accumulator, Hash, MerkleTree};
+
+use super::{
+    append_line, decode_hash, io_error, AuditStorage, SequenceRange, State, StoredEntry, CHECKPOINTS_FILE,
+    ENTRIES_FILE,
+};
+
+/// When pending entries are committed as a checkpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointConfig {
+    /// Entries per checkpoint
+    pub max_entries: usize,
+    /// Longest an entry waits for its checkpoint
+    pub max_interval: Duration,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 1000,
+            max_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl CheckpointConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_entries == 0 {
+            return Err(AuditError::CheckpointConfigError(
+                "max_entries must be at least 1".to_string(),
+            ));
+        }
+        if self.max_interval.is_zero() {
+            return Err(AuditError::CheckpointConfigError(
+                "max_interval must be longer than zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_due(&self, pending: &Accumulator) -> bool {
+        pending.len() >= self.max_entries || (!pending.is_empty() && pending.age() >= self.max_interval)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub index: u64,
+    pub sequences: SequenceRange,
+    /// Entries the root covers; fewer than the range holds if some had been
+    /// purged before the checkpoint was committed
+    pub entry_count: usize,
+    /// Hex Merkle root over the leaf hashes of the covered entries, in sequence order
+    pub root: String,
+    /// Hex chain link after the last covered entry
+    pub chain_hash: String,
+    pub committed_at: DateTime<Utc>,
+    /// `record_hash` of the previous checkpoint; `None` for the first
+    pub previous_hash: Option<String>,
+    /// Hex SHA-256 of this record with `record_hash` and `signature` left empty
+    pub record_hash: String,
+    /// Hex Ed25519 public key of the signer; `None` for an unsigned checkpoint
+    pub signer: Option<String>,
+    /// Hex Ed25519 signature over `record_hash`
+    pub signature: Option<String>,
+}
+
+impl Checkpoint {
+    pub fn compute_hash(&self) -> String {
+        let unsealed = Checkpoint {
+            record_hash: String::new(),
+            signature: None,
+            ..self.clone()
+        };
+        let bytes = serde_json::to_vec(&unsealed).expect("checkpoints always serialize");
+        hex::encode(Sha256::digest(bytes))
+    }
+
+    /// Check the signature against the recorded signer
+    ///
+    /// Whoever relies on it should also compare `signer` with the key they
+    /// expect. Unsigned checkpoints pass.
+    pub fn verify_signature(&self) -> Result<()> {
+        let (Some(signer), Some(signature)) = (&self.signer, &self.signature) else {
+            if self.signer.is_some() {
+                return Err(AuditError::IntegrityViolation(format!(
+                    "Checkpoint {} names a signer but carries no signature",
+                    self.index
+                )));
+            }
+            return Ok(());
+        };
+        let decode = |value: &str| {
+            hex::decode(value).map_err(|_| {
+                AuditError::IntegrityViolation(format!("Checkpoint {} has a malformed signature", self.index))
+            })
+        };
+        crypto::verify_ed25519(&decode(signer)?, self.record_hash.as_bytes(), &decode(signature)?).map_err(|e| {
+            AuditError::IntegrityViolation(format!("Checkpoint {} signature is invalid: {}", self.index, e))
+        })
+    }
+
+    fn covers(&self, sequence: u64) -> bool {
+        self.sequences.first <= sequence && sequence <= self.sequences.last
+    }
+}
+
+/// Proof that an entry is included in a checkpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub sequence: u64,
+    pub checkpoint: Checkpoint,
+    /// Position of the entry among the checkpoint's leaves
+    pub leaf_index: usize,
+    /// Hex sibling hashes from the leaf up to the root
+    pub proof: Vec<String>,
+}
+
+impl InclusionProof {
+    /// Whether `entry` is the leaf this proof places under the checkpoint root
+    pub fn verify(&self, entry: &AuditEntry) -> bool {
+        let Ok(root) = decode_hash(&self.checkpoint.root) else {
+            return false;
+        };
+        let Ok(proof) = self.proof.iter().map(|hash| decode_hash(hash)).collect::<Result<Vec<Hash>>>() else {
+            return false;
+        };
+        merkle::verify_inclusion(
+            &root,
+            self.leaf_index,
+            &merkle::leaf_hash(entry),
+            self.checkpoint.entry_count,
+            &proof,
+        )
+    }
+}
+
+impl AuditStorage {
+    /// Commit checkpoints per `config` instead of the default
+    pub fn with_checkpoints(mut self, config: CheckpointConfig) -> Result<Self> {
+        config.validate()?;
+        self.checkpointing = config;
+        Ok(self)
+    }
+
+    /// Sign every checkpoint committed from now on
+    pub fn with_checkpoint_signer(mut self, key: crypto::Ed25519KeyPair) -> Self {
+        self.signer = Some(key);
+        self
+    }
+
+    pub async fn checkpoints(&self) -> Vec<Checkpoint> {
+        self.state().lock().await.checkpoints.clone()
+    }
+
+    /// Commit the pending entries now; `None` when there are none
+    ///
+    /// Call before shutting down so no entries are left uncovered.
+    pub async fn checkpoint(&self) -> Result<Option<Checkpoint>> {
+        let mut state = self.state().lock().await;
+        self.commit_checkpoint(&mut state).await
+    }
+
+    pub(crate) async fn commit_checkpoint(&self, state: &mut State) -> Result<Option<Checkpoint>> {
+        if state.pending.is_empty() {
+            return Ok(None);
+        }
+
+        // The entries must be durable before a checkpoint vouches for them
+        let entries_path = self.path(ENTRIES_FILE);
+        let entries_file = fs::OpenOptions::new()
+            .append(true)
+            .open(&entries_path)
+            .await
+            .map_err(|e| io_error(&entries_path, e))?;
+        entries_file.sync_data().await.map_err(|e| io_error(&entries_path, e))?;
+
+        let previous = state.checkpoints.last();
+        let mut checkpoint = Checkpoint {
+            index: previous.map_or(0, |previous| previous.index + 1),
+            sequences: SequenceRange {
+                first: previous.map_or(0, |previous| previous.sequences.last + 1),
+                last: state.next_sequence - 1,
+            },
+            entry_count: state.pending.len(),
+            root: state.pending.root().map(hex::encode).unwrap_or_default(),
+            chain_hash: hex::encode(state.head),
+            committed_at: Utc::now(),
+            previous_hash: previous.map(|previous| previous.record_hash.clone()),
+            record_hash: String::new(),
+            signer: self.signer.as_ref().map(|key| hex::encode(key.public_key())),
+            signature: None,
+        };
+        checkpoint.record_hash = checkpoint.compute_hash();
+        checkpoint.signature = self
+            .signer
+            .as_ref()
+            .map(|key| hex::encode(key.sign(checkpoint.record_hash.as_bytes())));
+
+        append_line(&self.path(CHECKPOINTS_FILE), &checkpoint).await?;
+        state.pending.take();
+        state.checkpoints.push(checkpoint.clone());
+        tracing::debug!(
+            index = checkpoint.index,
+            entries = checkpoint.entry_count,
+            "Committed audit checkpoint"
+        );
+        Ok(Some(checkpoint))
+    }
+
+    /// Proof that the entry with `sequence` is included in its checkpoint
+    ///
+    /// Unavailable until the entry's checkpoint is committed, and once any
+    /// entry the checkpoint covers has been purged.
+    pub async fn inclusion_proof(&self, sequence: u64) -> Result<InclusionProof> {
+        let state = self.state().lock().await;
+        let checkpoint = state
+            .checkpoints
+            .iter()
+            .find(|checkpoint| checkpoint.covers(sequence))
+            .ok_or_else(|| {
+                AuditError::ProofUnavailable(format!("Entry {} is not covered by a checkpoint yet", sequence))
+            })?;
+
+        let covered = covered_entries(&state.entries, checkpoint);
+        if covered.len() != checkpoint.entry_count {
+            return Err(AuditError::ProofUnavailable(format!(
+                "Checkpoint {} no longer holds all of its entries",
+                checkpoint.index
+            )));
+        }
+        let leaf_index = covered
+            .iter()
+            .position(|stored| stored.sequence == sequence)
+            .ok_or_else(|| AuditError::ProofUnavailable(format!("Entry {} is not stored", sequence)))?;
+        let leaves: Vec<Hash> = covered.iter().map(|stored| merkle::leaf_hash(&stored.entry)).collect();
+
+        Ok(InclusionProof {
+            sequence,
+            checkpoint: checkpoint.clone(),
+            leaf_index,
+            proof: MerkleTree::from_leaves(&leaves).proof(leaf_index).iter().map(hex::encode).collect(),
+        })
+    }
+
+    /// Check the checkpoint chain, signatures, and the roots of checkpoints
+    /// whose entries are all still stored
+    pub(crate) fn verify_checkpoints(&self, state: &State) -> Result<()> {
+        let mut previous_hash = None;
+        for checkpoint in &state.checkpoints {
+            if checkpoint.previous_hash != previous_hash || checkpoint.compute_hash() != checkpoint.record_hash {
+                return Err(AuditError::IntegrityViolation(format!(
+                    "Checkpoint {} does not match the checkpoint chain",
+                    checkpoint.index
+                )));
+            }
+            checkpoint.verify_signature()?;
+            previous_hash = Some(checkpoint.record_hash.clone());
+
+            let covered = covered_entries(&state.entries, checkpoint);
+            if covered.len() == checkpoint.entry_count {
+                let leaves: Vec<Hash> = covered.iter().map(|stored| merkle::leaf_hash(&stored.entry)).collect();
+                let root = MerkleTree::from_leaves(&leaves).root().map(hex::encode).unwrap_or_default();
+                if root != checkpoint.root {
+                    return Err(AuditError::IntegrityViolation(format!(
+                        "Entries {}..={} do not match the root of checkpoint {}",
+                        checkpoint.sequences.first, checkpoint.sequences.last, checkpoint.index
+                    )));
+                }
+            }
+            if let Some(last) = covered.last().filter(|last| last.sequence == checkpoint.sequences.last) {
+                if last.chain_hash != checkpoint.chain_hash {
+                    return Err(AuditError::IntegrityViolation(format!(
+                        "Entry {} does not end checkpoint {}",
+                        last.sequence, checkpoint.index
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Commit a checkpoint every `max_interval` until the task is aborted,
+    /// so entries logged during a quiet period are not left uncovered
+    pub fn spawn_checkpointer(self: &Arc<Self>) -> JoinHandle<()> {
+        let storage = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(storage.checkpointing.max_interval);
+            loop {
+                ticker.tick().await;
+                if let Err(error) = storage.checkpoint().await {
+                    tracing::error!(error = %error, "Audit checkpoint failed");
+                }
+            }
+        })
+    }
+}
+
+/// Stored entries within the checkpoint's range, in sequence order
+fn covered_entries<'a>(entries: &'a [StoredEntry], checkpoint: &Checkpoint) -> &'a [StoredEntry] {
+    let start = entries.partition_point(|stored| stored.sequence < checkpoint.sequences.first);
+    let end = entries.partition_point(|stored| stored.sequence <= checkpoint.sequences.last);
+    &entries[start..end]
+}
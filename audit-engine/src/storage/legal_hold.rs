@@ -0,0 +1,130 @@
+// Legal holds
+//
+// A hold keeps entries from being purged however old they are, for as long
+// as it is active. Holds are never deleted: releasing one records who
+// released it and when, so the history of holds is itself auditable.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+
+use super::{write_atomic, AuditStorage, HOLDS_FILE};
+
+/// Entries a hold applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HoldScope {
+    /// Every entry about a subject, including ones logged after the hold was placed
+    Subject { subject: String },
+    /// Entries attached to a legal matter with `AuditStorage::attach_to_matter`
+    Matter { matter: String, entry_ids: Vec<Uuid> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegalHold {
+    pub id: Uuid,
+    pub scope: HoldScope,
+    pub reason: String,
+    pub placed_by: String,
+    pub placed_at: DateTime<Utc>,
+    pub released_by: Option<String>,
+    pub released_at: Option<DateTime<Utc>>,
+}
+
+impl LegalHold {
+    pub fn is_active(&self) -> bool {
+        self.released_at.is_none()
+    }
+
+    pub fn covers(&self, entry: &AuditEntry) -> bool {
+        self.is_active()
+            && match &self.scope {
+                HoldScope::Subject { subject } => entry.subject == *subject,
+                HoldScope::Matter { entry_ids, .. } => entry_ids.contains(&entry.id),
+            }
+    }
+}
+
+impl AuditStorage {
+    pub async fn place_hold(&self, scope: HoldScope, reason: &str, placed_by: &str) -> Result<LegalHold> {
+        if reason.trim().is_empty() || placed_by.trim().is_empty() {
+            return Err(AuditError::LegalHoldError(
+                "A legal hold needs a reason and who placed it".to_string(),
+            ));
+        }
+        let hold = LegalHold {
+            id: Uuid::new_v4(),
+            scope,
+            reason: reason.to_string(),
+            placed_by: placed_by.to_string(),
+            placed_at: Utc::now(),
+            released_by: None,
+            released_at: None,
+        };
+        self.update_holds(|holds| {
+            holds.push(hold.clone());
+            Ok(())
+        })
+        .await?;
+        tracing::info!(hold_id = %hold.id, placed_by, "Legal hold placed on audit entries");
+        Ok(hold)
+    }
+
+    pub async fn release_hold(&self, hold_id: Uuid, released_by: &str) -> Result<LegalHold> {
+        let released = self
+            .update_holds(|holds| {
+                let hold = find_active(holds, hold_id)?;
+                hold.released_by = Some(released_by.to_string());
+                hold.released_at = Some(Utc::now());
+                Ok(hold.clone())
+            })
+            .await?;
+        tracing::info!(hold_id = %hold_id, released_by, "Legal hold released");
+        Ok(released)
+    }
+
+    /// Add entries to an active matter hold
+    pub async fn attach_to_matter(&self, hold_id: Uuid, entry_ids: &[Uuid]) -> Result<LegalHold> {
+        self.update_holds(|holds| {
+            let hold = find_active(holds, hold_id)?;
+            let HoldScope::Matter { entry_ids: held, .. } = &mut hold.scope else {
+                return Err(AuditError::LegalHoldError(format!(
+                    "Legal hold {} is not for a matter",
+                    hold_id
+                )));
+            };
+            for id in entry_ids {
+                if !held.contains(id) {
+                    held.push(*id);
+                }
+            }
+            Ok(hold.clone())
+        })
+        .await
+    }
+
+    /// All holds, released ones included
+    pub async fn legal_holds(&self) -> Vec<LegalHold> {
+        self.state().lock().await.holds.clone()
+    }
+
+    /// Apply `change` to a copy of the holds and keep it only once it is on disk
+    async fn update_holds<T>(&self, change: impl FnOnce(&mut Vec<LegalHold>) -> Result<T>) -> Result<T> {
+        let mut state = self.state().lock().await;
+        let mut holds = state.holds.clone();
+        let result = change(&mut holds)?;
+        let content = serde_json::to_vec_pretty(&holds).map_err(|e| AuditError::InternalError(e.into()))?;
+        write_atomic(&self.path(HOLDS_FILE), &content).await?;
+        state.holds = holds;
+        Ok(result)
+    }
+}
+
+fn find_active(holds: &mut [LegalHold], hold_id: Uuid) -> Result<&mut LegalHold> {
+    holds
+        .iter_mut()
+        .find(|hold| hold.id == hold_id && hold.is_active())
+        .ok_or_else(|| AuditError::LegalHoldError(format!("No active legal hold {}", hold_id)))
+}
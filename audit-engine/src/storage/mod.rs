@@ -0,0 +1,296 @@
+// Durable audit storage
+//
+// Entries are appended to a hash-chained JSON lines log in a directory:
+// - `entries.jsonl`: stored entries with their sequence number and chain link
+// - `legal_holds.json`: placed and released legal holds
+// - `purges.jsonl`: summaries of retention purges, chained among themselves
+// - `checkpoints.jsonl`: Merkle roots over batches of entries, chained and
+//   optionally signed (see `checkpoint`)
+//
+// Purging removes entries from the middle of the chain. Each purge records
+// the links of the removed entries that the chain continues from, so the
+// remaining entries still verify, and a Merkle root over what it removed.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+use crate::merkle::{self, Accumulator, Hash, GENESIS};
+
+pub mod checkpoint;
+pub mod legal_hold;
+pub mod retention;
+
+pub use checkpoint::{Checkpoint, CheckpointConfig, InclusionProof};
+pub use legal_hold::{HoldScope, LegalHold};
+pub use retention::{ChainAnchor, PurgeRecord, RetentionPolicy, RetentionSweeper, SequenceRange};
+
+const ENTRIES_FILE: &str = "entries.jsonl";
+const HOLDS_FILE: &str = "legal_holds.json";
+const PURGES_FILE: &str = "purges.jsonl";
+const CHECKPOINTS_FILE: &str = "checkpoints.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEntry {
+    pub sequence: u64,
+    pub entry: AuditEntry,
+    /// Hex link of the chain after this entry
+    pub chain_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerificationReport {
+    pub entries_verified: usize,
+    pub purges_verified: usize,
+    pub checkpoints_verified: usize,
+    /// Sequence the next entry will get
+    pub next_sequence: u64,
+}
+
+pub struct AuditStorage {
+    dir: PathBuf,
+    state: Mutex<State>,
+    checkpointing: CheckpointConfig,
+    signer: Option<crypto::Ed25519KeyPair>,
+}
+
+pub(crate) struct State {
+    pub(crate) entries: Vec<StoredEntry>,
+    pub(crate) holds: Vec<LegalHold>,
+    pub(crate) purges: Vec<PurgeRecord>,
+    pub(crate) checkpoints: Vec<Checkpoint>,
+    /// Leaves of the entries appended since the last checkpoint
+    pub(crate) pending: Accumulator,
+    /// Link of the last entry ever appended, purged or not
+    pub(crate) head: Hash,
+    pub(crate) next_sequence: u64,
+}
+
+impl AuditStorage {
+    /// Open the store in `dir`, creating it if needed
+    pub async fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).await.map_err(|e| io_error(&dir, e))?;
+
+        let entries: Vec<StoredEntry> = read_lines(&dir.join(ENTRIES_FILE)).await?;
+        let purges: Vec<PurgeRecord> = read_lines(&dir.join(PURGES_FILE)).await?;
+        let checkpoints: Vec<Checkpoint> = read_lines(&dir.join(CHECKPOINTS_FILE)).await?;
+        let holds_path = dir.join(HOLDS_FILE);
+        let holds = match fs::read(&holds_path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| corrupt(&holds_path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(io_error(&holds_path, e)),
+        };
+
+        // The head is the latest link, whether its entry is still stored or
+        // was purged and is only known through an anchor
+        let mut head = (GENESIS, 0);
+        let links = entries
+            .iter()
+            .map(|stored| (stored.sequence, stored.chain_hash.as_str()))
+            .chain(purges.iter().flat_map(|purge| {
+                purge
+                    .anchors
+                    .iter()
+                    .map(|anchor| (anchor.sequence, anchor.chain_hash.as_str()))
+            }));
+        for (sequence, link) in links {
+            if sequence + 1 > head.1 {
+                head = (decode_hash(link)?, sequence + 1);
+            }
+        }
+
+        // Entries after the last checkpoint go into the next one
+        let mut pending = Accumulator::default();
+        let checkpointed = checkpoints.last().map(|checkpoint| checkpoint.sequences.last);
+        for stored in &entries {
+            if checkpointed.is_none_or(|last| stored.sequence > last) {
+                pending.push(merkle::leaf_hash(&stored.entry));
+            }
+        }
+
+        Ok(Self {
+            dir,
+            state: Mutex::new(State {
+                entries,
+                holds,
+                purges,
+                checkpoints,
+                pending,
+                head: head.0,
+                next_sequence: head.1,
+            }),
+            checkpointing: CheckpointConfig::default(),
+            signer: None,
+        })
+    }
+
+    /// Append an entry to the chain
+    ///
+    /// The entry is written but not flushed to disk; that happens when the
+    /// checkpoint covering it is committed, which this call does once the
+    /// configured number of entries or time has been reached.
+    pub async fn append(&self, entry: AuditEntry) -> Result<StoredEntry> {
+        let mut state = self.state.lock().await;
+        let leaf = merkle::leaf_hash(&entry);
+        let link = merkle::chain_hash(&state.head, &leaf);
+        let stored = StoredEntry {
+            sequence: state.next_sequence,
+            entry,
+            chain_hash: hex::encode(link),
+        };
+        write_line(&self.dir.join(ENTRIES_FILE), &stored, false).await?;
+        state.head = link;
+        state.next_sequence += 1;
+        state.entries.push(stored.clone());
+        state.pending.push(leaf);
+        if self.checkpointing.is_due(&state.pending) {
+            self.commit_checkpoint(&mut state).await?;
+        }
+        Ok(stored)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Option<StoredEntry> {
+        let state = self.state.lock().await;
+        state.entries.iter().find(|stored| stored.entry.id == id).cloned()
+    }
+
+    /// Stored entries in sequence order
+    pub async fn entries(&self) -> Vec<StoredEntry> {
+        self.state.lock().await.entries.clone()
+    }
+
+    pub async fn purge_records(&self) -> Vec<PurgeRecord> {
+        self.state.lock().await.purges.clone()
+    }
+
+    /// Check the entry chain, bridging purged stretches with their anchors,
+    /// and the chain of purge records
+    pub async fn verify(&self) -> Result<VerificationReport> {
+        let state = self.state.lock().await;
+
+        let mut previous_hash = None;
+        let mut anchors = HashMap::new();
+        for purge in &state.purges {
+            if purge.previous_hash != previous_hash || purge.compute_hash() != purge.record_hash {
+                return Err(AuditError::IntegrityViolation(format!(
+                    "Purge record {} does not match the purge chain",
+                    purge.id
+                )));
+            }
+            previous_hash = Some(purge.record_hash.clone());
+            for anchor in &purge.anchors {
+                anchors.insert(anchor.sequence, decode_hash(&anchor.chain_hash)?);
+            }
+        }
+
+        let mut previous: Option<(u64, Hash)> = None;
+        for stored in &state.entries {
+            let link = match (stored.sequence, previous) {
+                (0, _) => GENESIS,
+                (sequence, Some((last, link))) if last + 1 == sequence => link,
+                (sequence, _) => *anchors.get(&(sequence - 1)).ok_or_else(|| {
+                    AuditError::IntegrityViolation(format!(
+                        "Entry {} follows a gap that no purge accounts for",
+                        sequence
+                    ))
+                })?,
+            };
+            let expected = merkle::chain_hash(&link, &merkle::leaf_hash(&stored.entry));
+            if hex::encode(expected) != stored.chain_hash {
+                return Err(AuditError::IntegrityViolation(format!(
+                    "Entry {} ({}) does not match the audit chain",
+                    stored.sequence, stored.entry.id
+                )));
+            }
+            if anchors.get(&stored.sequence).is_some_and(|anchor| *anchor != expected) {
+                return Err(AuditError::IntegrityViolation(format!(
+                    "Entry {} contradicts the anchor recorded for it",
+                    stored.sequence
+                )));
+            }
+            previous = Some((stored.sequence, expected));
+        }
+
+        self.verify_checkpoints(&state)?;
+
+        Ok(VerificationReport {
+            entries_verified: state.entries.len(),
+            purges_verified: state.purges.len(),
+            checkpoints_verified: state.checkpoints.len(),
+            next_sequence: state.next_sequence,
+        })
+    }
+
+    pub(crate) fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    pub(crate) fn state(&self) -> &Mutex<State> {
+        &self.state
+    }
+}
+
+pub(crate) fn decode_hash(value: &str) -> Result<Hash> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| Hash::try_from(bytes).ok())
+        .ok_or_else(|| AuditError::IntegrityViolation(format!("Malformed chain hash '{}'", value)))
+}
+
+async fn read_lines<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let content = match fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(io_error(path, e)),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|e| corrupt(path, e)))
+        .collect()
+}
+
+pub(crate) async fn append_line<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write_line(path, value, true).await
+}
+
+/// Append `value` as a line, flushing it to disk only if `sync` is set
+async fn write_line<T: Serialize>(path: &Path, value: &T, sync: bool) -> Result<()> {
+    let mut line = serde_json::to_vec(value).map_err(|e| AuditError::InternalError(e.into()))?;
+    line.push(b'\n');
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|e| io_error(path, e))?;
+    file.write_all(&line).await.map_err(|e| io_error(path, e))?;
+    if sync {
+        file.sync_data().await.map_err(|e| io_error(path, e))?;
+    }
+    Ok(())
+}
+
+/// Replace `path` with `content` so readers see either the old or the new file
+pub(crate) async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let partial = path.with_extension("tmp");
+    let mut file = fs::File::create(&partial).await.map_err(|e| io_error(&partial, e))?;
+    file.write_all(content).await.map_err(|e| io_error(&partial, e))?;
+    file.sync_all().await.map_err(|e| io_error(&partial, e))?;
+    fs::rename(&partial, path).await.map_err(|e| io_error(path, e))
+}
+
+pub(crate) fn io_error(path: &Path, error: std::io::Error) -> AuditError {
+    AuditError::PersistenceError(format!("I/O on {} failed: {}", path.display(), error))
+}
+
+fn corrupt(path: &Path, error: serde_json::Error) -> AuditError {
+    AuditError::PersistenceError(format!("Corrupt audit store file {}: {}", path.display(), error))
+}
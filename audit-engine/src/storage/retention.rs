@@ -0,0 +1,292 @@
+// Retention enforcement
+//
+// Entries older than their retention period are purged unless an active
+// legal hold covers them. Every purge leaves a `PurgeRecord` that says what
+// policy applied, how many entries of which type went, which holds kept
+// others back, and a Merkle root over the removed entries, so auditors can
+// confirm disposal followed policy without the records themselves.
+//
+// The purge record is written before the entries file is rewritten. A crash
+// in between leaves the expired entries in place; the next sweep purges them
+// again and records that too, and the chain verifies either way.
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Months, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::entry::AuditEntry;
+use crate::error::{AuditError, Result};
+use crate::merkle::{self, MerkleTree};
+
+use super::{append_line, write_atomic, AuditStorage, StoredEntry, ENTRIES_FILE, PURGES_FILE};
+
+/// HIPAA documentation must be kept six years; we keep audit records seven
+const DEFAULT_RETENTION_MONTHS: u32 = 7 * 12;
+
+const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long entries are kept, in months from their timestamp
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub default_months: u32,
+    /// Overrides by event type
+    pub event_type_months: BTreeMap<String, u32>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            default_months: DEFAULT_RETENTION_MONTHS,
+            event_type_months: BTreeMap::new(),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn event_type(mut self, event_type: &str, months: u32) -> Self {
+        self.event_type_months.insert(event_type.to_string(), months);
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some((scope, _)) = std::iter::once(("default", self.default_months))
+            .chain(self.event_type_months.iter().map(|(event_type, months)| (event_type.as_str(), *months)))
+            .find(|(_, months)| *months == 0)
+        {
+            return Err(AuditError::RetentionPolicyError(format!(
+                "Period for {} must be at least one month",
+                scope
+            )));
+        }
+        Ok(())
+    }
+
+    /// When `entry` may be purged
+    pub fn expires_at(&self, entry: &AuditEntry) -> DateTime<Utc> {
+        let months = self
+            .event_type_months
+            .get(&entry.event_type)
+            .copied()
+            .unwrap_or(self.default_months);
+        entry
+            .timestamp
+            .checked_add_months(Months::new(months))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+/// Chain link of a purged entry that a retained entry, or the head, continues from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainAnchor {
+    pub sequence: u64,
+    pub chain_hash: String,
+}
+
+/// Inclusive range of sequence numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequenceRange {
+    pub first: u64,
+    pub last: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurgeRecord {
+    pub id: Uuid,
+    pub swept_at: DateTime<Utc>,
+    pub policy: RetentionPolicy,
+    pub purged_count: usize,
+    pub purged_by_event_type: BTreeMap<String, usize>,
+    pub purged_sequences: Vec<SequenceRange>,
+    /// Hex Merkle root over the leaf hashes of the purged entries, in sequence order
+    pub purged_root: String,
+    /// Expired entries kept because of a legal hold
+    pub held_count: usize,
+    pub holds_applied: Vec<Uuid>,
+    pub anchors: Vec<ChainAnchor>,
+    /// `record_hash` of the previous purge; `None` for the first
+    pub previous_hash: Option<String>,
+    /// Hex SHA-256 of this record with `record_hash` left empty
+    pub record_hash: String,
+}
+
+impl PurgeRecord {
+    pub fn compute_hash(&self) -> String {
+        let unsealed = PurgeRecord {
+            record_hash: String::new(),
+            ..self.clone()
+        };
+        let bytes = serde_json::to_vec(&unsealed).expect("purge records always serialize");
+        hex::encode(Sha256::digest(bytes))
+    }
+}
+
+impl AuditStorage {
+    /// Purge entries expired under `policy` as of `now` that no active hold covers
+    ///
+    /// Returns `None` when nothing was purged.
+    pub async fn purge_expired(&self, policy: &RetentionPolicy, now: DateTime<Utc>) -> Result<Option<PurgeRecord>> {
+        policy.validate()?;
+        let mut state = self.state().lock().await;
+
+        let mut retained = Vec::with_capacity(state.entries.len());
+        let mut purged: Vec<&StoredEntry> = vec![];
+        let mut anchors = vec![];
+        let mut held_count = 0;
+        let mut holds_applied = BTreeSet::new();
+        for (index, stored) in state.entries.iter().enumerate() {
+            if policy.expires_at(&stored.entry) > now {
+                retained.push(stored.clone());
+                continue;
+            }
+            let holds: Vec<Uuid> = state
+                .holds
+                .iter()
+                .filter(|hold| hold.covers(&stored.entry))
+                .map(|hold| hold.id)
+                .collect();
+            if !holds.is_empty() {
+                held_count += 1;
+                holds_applied.extend(holds);
+                retained.push(stored.clone());
+                continue;
+            }
+
+            // The next stored entry links from this one, so keep its link
+            let followed_by_retained = match state.entries.get(index + 1) {
+                Some(next) => {
+                    policy.expires_at(&next.entry) > now
+                        || state.holds.iter().any(|hold| hold.covers(&next.entry))
+                }
+                None => true,
+            };
+            if followed_by_retained {
+                anchors.push(ChainAnchor {
+                    sequence: stored.sequence,
+                    chain_hash: stored.chain_hash.clone(),
+                });
+            }
+            purged.push(stored);
+        }
+        if purged.is_empty() {
+            return Ok(None);
+        }
+
+        let mut purged_by_event_type = BTreeMap::new();
+        let mut purged_sequences: Vec<SequenceRange> = vec![];
+        let mut leaves = Vec::with_capacity(purged.len());
+        for stored in &purged {
+            *purged_by_event_type.entry(stored.entry.event_type.clone()).or_default() += 1;
+            match purged_sequences.last_mut() {
+                Some(range) if range.last + 1 == stored.sequence => range.last = stored.sequence,
+                _ => purged_sequences.push(SequenceRange {
+                    first: stored.sequence,
+                    last: stored.sequence,
+                }),
+            }
+            leaves.push(merkle::leaf_hash(&stored.entry));
+        }
+        let purged_root = MerkleTree::from_leaves(&leaves).root().map(hex::encode).unwrap_or_default();
+
+        let mut record = PurgeRecord {
+            id: Uuid::new_v4(),
+            swept_at: now,
+            policy: policy.clone(),
+            purged_count: purged.len(),
+            purged_by_event_type,
+            purged_sequences,
+            purged_root,
+            held_count,
+            holds_applied: holds_applied.into_iter().collect(),
+            anchors,
+            previous_hash: state.purges.last().map(|previous| previous.record_hash.clone()),
+            record_hash: String::new(),
+        };
+        record.record_hash = record.compute_hash();
+
+        append_line(&self.path(PURGES_FILE), &record).await?;
+        let mut content = Vec::new();
+        for stored in &retained {
+            serde_json::to_writer(&mut content, stored).map_err(|e| AuditError::InternalError(e.into()))?;
+            content.push(b'\n');
+        }
+        write_atomic(&self.path(ENTRIES_FILE), &content).await?;
+
+        state.entries = retained;
+        state.purges.push(record.clone());
+        Ok(Some(record))
+    }
+}
+
+/// Periodically purges expired entries and logs each purge to the audit trail
+pub struct RetentionSweeper {
+    storage: Arc<AuditStorage>,
+    policy: RetentionPolicy,
+    interval: Duration,
+}
+
+impl RetentionSweeper {
+    pub fn new(storage: Arc<AuditStorage>, policy: RetentionPolicy) -> Result<Self> {
+        policy.validate()?;
+        Ok(Self {
+            storage,
+            policy,
+            interval: DEFAULT_SWEEP_INTERVAL,
+        })
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub async fn sweep(&self) -> Result<Option<PurgeRecord>> {
+        self.sweep_at(Utc::now()).await
+    }
+
+    /// Sweep as if the time were `now`
+    pub async fn sweep_at(&self, now: DateTime<Utc>) -> Result<Option<PurgeRecord>> {
+        let Some(record) = self.storage.purge_expired(&self.policy, now).await? else {
+            return Ok(None);
+        };
+        self.storage
+            .append(AuditEntry {
+                id: Uuid::new_v4(),
+                timestamp: now,
+                event_type: "system".to_string(),
+                subject: "retention-sweeper".to_string(),
+                action: "audit_entries_purged".to_string(),
+                data: serde_json::json!({
+                    "purge_id": record.id,
+                    "record_hash": record.record_hash,
+                    "purged_count": record.purged_count,
+                    "held_count": record.held_count,
+                }),
+            })
+            .await?;
+        tracing::info!(
+            purge_id = %record.id,
+            purged = record.purged_count,
+            held = record.held_count,
+            "Purged audit entries past retention"
+        );
+        Ok(Some(record))
+    }
+
+    /// Sweep every interval until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                ticker.tick().await;
+                if let Err(error) = self.sweep().await {
+                    tracing::error!(error = %error, "Audit retention sweep failed");
+                }
+            }
+        })
+    }
+}
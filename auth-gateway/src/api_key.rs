@@ -0,0 +1,415 @@
+//! API key authentication
+//!
+//! Keys are presented as `rck_<key id>_<secret>`. Only a SHA-256 digest of
+//! the secret is kept, and the digest of a presented secret is compared in
+//! constant time. A valid key resolves to an [`ApiKeyPrincipal`] carrying the
+//! scopes it was issued with and nothing more.
+
+use crate::error::{GatewayError, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Prefix identifying RustCare API keys in headers and secret scanners
+pub const API_KEY_PREFIX: &str = "rck";
+
+/// Bytes of randomness in a key's secret part
+const SECRET_BYTES: usize = 32;
+
+/// Request budget of a single key over a fixed window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRateLimit {
+    pub max_requests: u32,
+    pub window: Duration,
+}
+
+impl KeyRateLimit {
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self {
+            max_requests,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Provider-wide limits applied to every issued key
+#[derive(Debug, Clone)]
+pub struct ApiKeyConfig {
+    /// Most scopes a single key may carry
+    pub max_scopes: usize,
+    /// Rate limit for keys issued without their own
+    pub default_rate_limit: KeyRateLimit,
+}
+
+impl Default for ApiKeyConfig {
+    fn default() -> Self {
+        Self {
+            max_scopes: 32,
+            default_rate_limit: KeyRateLimit::per_minute(600),
+        }
+    }
+}
+
+/// Parameters for issuing a new key
+#[derive(Debug, Clone)]
+pub struct IssueApiKey {
+    pub owner_id: Uuid,
+    pub organization_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    /// Client addresses the key may be used from; empty allows any
+    pub allowed_ips: Vec<IpAddr>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub rate_limit: Option<KeyRateLimit>,
+}
+
+impl IssueApiKey {
+    pub fn new(
+        owner_id: Uuid,
+        organization_id: Uuid,
+        name: impl Into<String>,
+        scopes: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            owner_id,
+            organization_id,
+            name: name.into(),
+            scopes: scopes.into_iter().map(Into::into).collect(),
+            allowed_ips: Vec::new(),
+            expires_at: None,
+            rate_limit: None,
+        }
+    }
+
+    pub fn allow_ip(mut self, ip: IpAddr) -> Self {
+        self.allowed_ips.push(ip);
+        self
+    }
+
+    pub fn expires_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn rate_limit(mut self, rate_limit: KeyRateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+}
+
+/// A freshly issued key; `key` is the only copy of the secret
+#[derive(Clone)]
+pub struct IssuedApiKey {
+    pub key_id: String,
+    pub key: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl fmt::Debug for IssuedApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IssuedApiKey")
+            .field("key_id", &self.key_id)
+            .field("key", &"[REDACTED]")
+            .field("scopes", &self.scopes)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// Identity a valid key authenticates as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyPrincipal {
+    pub key_id: String,
+    pub owner_id: Uuid,
+    pub organization_id: Uuid,
+    pub scopes: Vec<String>,
+}
+
+impl ApiKeyPrincipal {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+#[derive(Debug)]
+struct StoredKey {
+    principal: ApiKeyPrincipal,
+    secret_hash: [u8; 32],
+    allowed_ips: Vec<IpAddr>,
+    expires_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+    rate_limit: KeyRateLimit,
+    window_started: Instant,
+    window_count: u32,
+}
+
+impl StoredKey {
+    /// Count one use against the current window, or report when it reopens
+    fn consume(&mut self, now: Instant) -> std::result::Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.window_started);
+        if elapsed >= self.rate_limit.window {
+            self.window_started = now;
+            self.window_count = 0;
+        }
+        if self.window_count >= self.rate_limit.max_requests {
+            return Err(self.rate_limit.window.saturating_sub(elapsed));
+        }
+        self.window_count = self.window_count.saturating_add(1);
+        Ok(())
+    }
+}
+
+/// Issues, revokes and validates API keys
+#[derive(Debug, Default)]
+pub struct ApiKeyProvider {
+    config: ApiKeyConfig,
+    keys: RwLock<HashMap<String, StoredKey>>,
+}
+
+impl ApiKeyProvider {
+    pub fn new(config: ApiKeyConfig) -> Self {
+        Self {
+            config,
+            keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Issue a key with the requested scopes
+    ///
+    /// Scopes are deduplicated; an empty, blank or oversized scope set is rejected.
+    pub async fn issue(&self, request: IssueApiKey) -> Result<IssuedApiKey> {
+        let scopes = self.normalize_scopes(request.scopes)?;
+
+        let key_id = Uuid::new_v4().simple().to_string();
+        let mut secret = [0u8; SECRET_BYTES];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let secret = URL_SAFE_NO_PAD.encode(secret);
+
+        let stored = StoredKey {
+            principal: ApiKeyPrincipal {
+                key_id: key_id.clone(),
+                owner_id: request.owner_id,
+                organization_id: request.organization_id,
+                scopes: scopes.clone(),
+            },
+            secret_hash: Sha256::digest(secret.as_bytes()).into(),
+            allowed_ips: request.allowed_ips,
+            expires_at: request.expires_at,
+            revoked_at: None,
+            rate_limit: request.rate_limit.unwrap_or(self.config.default_rate_limit),
+            window_started: Instant::now(),
+            window_count: 0,
+        };
+        self.keys.write().await.insert(key_id.clone(), stored);
+
+        tracing::info!(
+            key_id = %key_id,
+            name = %request.name,
+            organization_id = %request.organization_id,
+            scopes = scopes.len(),
+            "Issued API key"
+        );
+
+        Ok(IssuedApiKey {
+            key: format!("{API_KEY_PREFIX}_{key_id}_{secret}"),
+            key_id,
+            scopes,
+            expires_at: request.expires_at,
+        })
+    }
+
+    /// Revoke a key; later presentations fail with [`GatewayError::ApiKeyRevoked`]
+    pub async fn revoke(&self, key_id: &str) -> Result<()> {
+        let mut keys = self.keys.write().await;
+        let stored = keys
+            .get_mut(key_id)
+            .ok_or_else(|| GatewayError::KeyNotFound(key_id.to_string()))?;
+        stored.revoked_at.get_or_insert_with(Utc::now);
+        tracing::info!(key_id = %key_id, "Revoked API key");
+        Ok(())
+    }
+
+    /// Authenticate a presented key from `client_ip`
+    ///
+    /// Revocation and expiry are only reported once the secret has matched, so
+    /// a guessed key id reveals nothing about the key's state.
+    pub async fn validate(&self, presented: &str, client_ip: Option<IpAddr>) -> Result<ApiKeyPrincipal> {
+        let result = self.check(presented, client_ip).await;
+        if let Err(ref e) = result {
+            tracing::warn!(reason = e.code(), "API key authentication failed");
+            telemetry::MetricsCollector::global()
+                .counter("api_key_auth_failures_total")
+                .with_label("reason", e.code())
+                .increment();
+        }
+        result
+    }
+
+    async fn check(&self, presented: &str, client_ip: Option<IpAddr>) -> Result<ApiKeyPrincipal> {
+        let (key_id, secret) = parse_key(presented).ok_or(GatewayError::InvalidApiKey)?;
+
+        let mut keys = self.keys.write().await;
+        let stored = keys.get_mut(key_id).ok_or(GatewayError::InvalidApiKey)?;
+
+        let presented_hash: [u8; 32] = Sha256::digest(secret.as_bytes()).into();
+        if !bool::from(stored.secret_hash.ct_eq(&presented_hash)) {
+            return Err(GatewayError::InvalidApiKey);
+        }
+        if stored.revoked_at.is_some() {
+            return Err(GatewayError::ApiKeyRevoked(key_id.to_string()));
+        }
+        if stored.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(GatewayError::ApiKeyExpired(key_id.to_string()));
+        }
+        if !stored.allowed_ips.is_empty() {
+            match client_ip {
+                Some(ip) if stored.allowed_ips.contains(&ip) => {}
+                other => {
+                    return Err(GatewayError::IpNotAllowed {
+                        key_id: key_id.to_string(),
+                        ip: other.map_or_else(|| "unknown".to_string(), |ip| ip.to_string()),
+                    })
+                }
+            }
+        }
+        if let Err(retry_after) = stored.consume(Instant::now()) {
+            return Err(GatewayError::RateLimited {
+                key_id: key_id.to_string(),
+                retry_after_secs: retry_after.as_secs().max(1),
+            });
+        }
+
+        Ok(stored.principal.clone())
+    }
+
+    fn normalize_scopes(&self, scopes: Vec<String>) -> Result<Vec<String>> {
+        let mut normalized: Vec<String> = Vec::with_capacity(scopes.len());
+        for scope in scopes {
+            let scope = scope.trim();
+            if scope.is_empty() || scope.chars().any(char::is_whitespace) {
+                return Err(GatewayError::InvalidScopes(format!("malformed scope {scope:?}")));
+            }
+            if !normalized.iter().any(|s| s == scope) {
+                normalized.push(scope.to_string());
+            }
+        }
+        if normalized.is_empty() {
+            return Err(GatewayError::InvalidScopes("at least one scope is required".to_string()));
+        }
+        if normalized.len() > self.config.max_scopes {
+            return Err(GatewayError::InvalidScopes(format!(
+                "{} scopes requested, at most {} allowed",
+                normalized.len(),
+                self.config.max_scopes
+            )));
+        }
+        Ok(normalized)
+    }
+}
+
+/// Split `rck_<key id>_<secret>` into its key id and secret
+fn parse_key(presented: &str) -> Option<(&str, &str)> {
+    let rest = presented.strip_prefix(API_KEY_PREFIX)?.strip_prefix('_')?;
+    let (key_id, secret) = rest.split_once('_')?;
+    (!key_id.is_empty() && !secret.is_empty()).then_some((key_id, secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(scopes: &[&str]) -> IssueApiKey {
+        IssueApiKey::new(Uuid::new_v4(), Uuid::new_v4(), "ci", scopes.iter().copied())
+    }
+
+    #[tokio::test]
+    async fn test_issued_key_resolves_to_its_scopes() {
+        let provider = ApiKeyProvider::default();
+        let issued = provider.issue(request(&["patients:read", "patients:read", "notes:read"])).await.unwrap();
+
+        let principal = provider.validate(&issued.key, None).await.unwrap();
+        assert_eq!(principal.key_id, issued.key_id);
+        assert_eq!(principal.scopes, ["patients:read", "notes:read"]);
+        assert!(principal.has_scope("notes:read"));
+        assert!(!principal.has_scope("patients:write"));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_or_malformed_keys_are_invalid() {
+        let provider = ApiKeyProvider::default();
+        let issued = provider.issue(request(&["patients:read"])).await.unwrap();
+        let tampered = format!("{}x", issued.key);
+
+        for presented in [tampered.as_str(), "rck_", "Bearer abc", ""] {
+            assert_eq!(provider.validate(presented, None).await, Err(GatewayError::InvalidApiKey));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revoked_and_expired_keys_fail_distinctly() {
+        let provider = ApiKeyProvider::default();
+        let revoked = provider.issue(request(&["patients:read"])).await.unwrap();
+        provider.revoke(&revoked.key_id).await.unwrap();
+        let expired = provider
+            .issue(request(&["patients:read"]).expires_at(Utc::now() - chrono::Duration::seconds(1)))
+            .await
+            .unwrap();
+
+        let revoked_err = provider.validate(&revoked.key, None).await.unwrap_err();
+        let expired_err = provider.validate(&expired.key, None).await.unwrap_err();
+        assert_eq!(revoked_err.code(), "api_key_revoked");
+        assert_eq!(expired_err.code(), "api_key_expired");
+        assert_eq!(provider.revoke("missing").await, Err(GatewayError::KeyNotFound("missing".into())));
+    }
+
+    #[tokio::test]
+    async fn test_ip_allow_list_is_enforced() {
+        let provider = ApiKeyProvider::default();
+        let allowed: IpAddr = "10.0.0.7".parse().unwrap();
+        let issued = provider.issue(request(&["patients:read"]).allow_ip(allowed)).await.unwrap();
+
+        assert!(provider.validate(&issued.key, Some(allowed)).await.is_ok());
+        let denied = provider.validate(&issued.key, Some("10.0.0.8".parse().unwrap())).await;
+        assert!(matches!(denied, Err(GatewayError::IpNotAllowed { .. })));
+        let unknown = provider.validate(&issued.key, None).await;
+        assert!(matches!(unknown, Err(GatewayError::IpNotAllowed { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_usage_is_rate_limited_per_key() {
+        let provider = ApiKeyProvider::default();
+        let limited = provider
+            .issue(request(&["patients:read"]).rate_limit(KeyRateLimit::per_minute(2)))
+            .await
+            .unwrap();
+        let other = provider.issue(request(&["patients:read"])).await.unwrap();
+
+        assert!(provider.validate(&limited.key, None).await.is_ok());
+        assert!(provider.validate(&limited.key, None).await.is_ok());
+        let third = provider.validate(&limited.key, None).await;
+        assert!(matches!(third, Err(GatewayError::RateLimited { retry_after_secs, .. }) if retry_after_secs > 0));
+        assert!(provider.validate(&other.key, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_scope_sets_are_bounded() {
+        let provider = ApiKeyProvider::new(ApiKeyConfig {
+            max_scopes: 2,
+            ..ApiKeyConfig::default()
+        });
+
+        for scopes in [&[][..], &["a", "b", "c"][..], &["has space"][..]] {
+            let result = provider.issue(request(scopes)).await;
+            assert!(matches!(result, Err(GatewayError::InvalidScopes(_))), "{scopes:?}");
+        }
+    }
+}
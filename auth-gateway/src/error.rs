@@ -0,0 +1,81 @@
+use crate::tenant::TenantId;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GatewayError {
+    #[error("Invalid API key")]
+    InvalidApiKey,
+
+    #[error("API key {0} has been revoked")]
+    ApiKeyRevoked(String),
+
+    #[error("API key {0} has expired")]
+    ApiKeyExpired(String),
+
+    #[error("API key {key_id} is not allowed from {ip}")]
+    IpNotAllowed { key_id: String, ip: String },
+
+    #[error("Rate limit exceeded for API key {key_id}; retry after {retry_after_secs}s")]
+    RateLimited { key_id: String, retry_after_secs: u64 },
+
+    #[error("API key not found: {0}")]
+    KeyNotFound(String),
+
+    #[error("Invalid scopes: {0}")]
+    InvalidScopes(String),
+
+    #[error("No tenant could be resolved for the request")]
+    TenantUnresolved,
+
+    #[error("Invalid tenant: {0}")]
+    InvalidTenant(String),
+
+    #[error("Tenant {caller} may not access resources of tenant {target}")]
+    TenantMismatch { caller: TenantId, target: TenantId },
+}
+
+impl GatewayError {
+    /// Stable, machine-readable name of the failure for logs and metrics
+    pub fn code(&self) -> &'static str {
+        match self {
+            GatewayError::InvalidApiKey => "api_key_invalid",
+            GatewayError::ApiKeyRevoked(_) => "api_key_revoked",
+            GatewayError::ApiKeyExpired(_) => "api_key_expired",
+            GatewayError::IpNotAllowed { .. } => "api_key_ip_not_allowed",
+            GatewayError::RateLimited { .. } => "api_key_rate_limited",
+            GatewayError::KeyNotFound(_) => "api_key_not_found",
+            GatewayError::InvalidScopes(_) => "api_key_invalid_scopes",
+            GatewayError::TenantUnresolved => "tenant_unresolved",
+            GatewayError::InvalidTenant(_) => "tenant_invalid",
+            GatewayError::TenantMismatch { .. } => "tenant_mismatch",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            GatewayError::InvalidApiKey | GatewayError::ApiKeyRevoked(_) | GatewayError::ApiKeyExpired(_) => {
+                StatusCode::UNAUTHORIZED
+            }
+            GatewayError::IpNotAllowed { .. } | GatewayError::TenantMismatch { .. } => StatusCode::FORBIDDEN,
+            GatewayError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            GatewayError::KeyNotFound(_) => StatusCode::NOT_FOUND,
+            GatewayError::InvalidScopes(_) | GatewayError::TenantUnresolved | GatewayError::InvalidTenant(_) => {
+                StatusCode::BAD_REQUEST
+            }
+        }
+    }
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({
+            "error_type": self.code(),
+            "message": self.to_string(),
+        });
+        (self.status_code(), Json(body)).into_response()
+    }
+}
+
+pub type Result<T> = std::result::Result<T, GatewayError>;
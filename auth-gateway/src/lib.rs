@@ -4,12 +4,19 @@
 // pub mod extractors;
 // pub mod policies;
 // pub mod rate_limiting;
-// pub mod error;
+pub mod api_key;
+pub mod error;
+pub mod tenant;
 
 // pub use gateway::*;
 // pub use middleware::*;
 // pub use extractors::*;
-// pub use error::*;
+pub use api_key::{
+    ApiKeyConfig, ApiKeyPrincipal, ApiKeyProvider, IssueApiKey, IssuedApiKey, KeyRateLimit,
+    API_KEY_PREFIX,
+};
+pub use error::*;
+pub use tenant::{TenantId, TenantResolver, TenantSource, TENANT_HEADER};
 
 /// Authentication and Authorization Gateway for RustCare Engine
 /// 
@@ -0,0 +1,224 @@
+//! Tenant resolution
+//!
+//! A request's tenant comes from the caller's token claim when there is one.
+//! The `X-Tenant-ID` header or the request's subdomain may name a tenant too,
+//! but they never override the claim: naming a different tenant than the one
+//! the caller belongs to is a [`GatewayError::TenantMismatch`].
+
+use crate::error::{GatewayError, Result};
+use axum::extract::FromRequestParts;
+use axum::http::{header::HOST, request::Parts, HeaderMap};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Header naming the tenant a request targets
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+/// Tenant (organization) a request is bound to
+///
+/// Handlers can require it as an extractor once tenant middleware has run;
+/// the request is rejected if no tenant was resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TenantId(Uuid);
+
+impl TenantId {
+    pub fn new(id: Uuid) -> Self {
+        Self(id)
+    }
+
+    pub fn as_uuid(&self) -> Uuid {
+        self.0
+    }
+}
+
+impl From<Uuid> for TenantId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl fmt::Display for TenantId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for TenantId {
+    type Err = GatewayError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Uuid::parse_str(s.trim())
+            .map(Self)
+            .map_err(|_| GatewayError::InvalidTenant(s.to_string()))
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for TenantId
+where
+    S: Send + Sync,
+{
+    type Rejection = GatewayError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        parts
+            .extensions
+            .get::<TenantId>()
+            .copied()
+            .ok_or(GatewayError::TenantUnresolved)
+    }
+}
+
+/// Where a request's tenant was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenantSource {
+    Claim,
+    Header,
+    Subdomain,
+}
+
+/// Resolves the tenant of a request from its caller and headers
+#[derive(Debug, Clone, Default)]
+pub struct TenantResolver {
+    base_domain: Option<String>,
+    subdomains: HashMap<String, TenantId>,
+}
+
+impl TenantResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve `<tenant>.<base_domain>` hosts to tenants
+    ///
+    /// The subdomain is either a slug registered with [`Self::with_subdomain`]
+    /// or the tenant id itself.
+    pub fn with_base_domain(mut self, base_domain: impl Into<String>) -> Self {
+        self.base_domain = Some(base_domain.into().trim_start_matches('.').to_ascii_lowercase());
+        self
+    }
+
+    pub fn with_subdomain(mut self, slug: impl Into<String>, tenant: TenantId) -> Self {
+        self.subdomains.insert(slug.into().to_ascii_lowercase(), tenant);
+        self
+    }
+
+    /// Tenant named by the request itself, from the header or else the subdomain
+    pub fn requested(&self, headers: &HeaderMap) -> Result<Option<(TenantId, TenantSource)>> {
+        if let Some(value) = headers.get(TENANT_HEADER) {
+            let value = value
+                .to_str()
+                .map_err(|_| GatewayError::InvalidTenant("non-ASCII tenant header".to_string()))?;
+            return value.parse().map(|tenant| Some((tenant, TenantSource::Header)));
+        }
+        Ok(self.tenant_from_host(headers)?.map(|tenant| (tenant, TenantSource::Subdomain)))
+    }
+
+    /// Bind a request to a tenant
+    ///
+    /// `claimed` is the tenant of the authenticated caller, if any. Returns
+    /// `None` for anonymous requests that name no tenant.
+    pub fn resolve(
+        &self,
+        claimed: Option<TenantId>,
+        headers: &HeaderMap,
+    ) -> Result<Option<(TenantId, TenantSource)>> {
+        let requested = self.requested(headers)?;
+        match (claimed, requested) {
+            (Some(caller), Some((target, _))) if caller != target => {
+                Err(GatewayError::TenantMismatch { caller, target })
+            }
+            (Some(caller), _) => Ok(Some((caller, TenantSource::Claim))),
+            (None, requested) => Ok(requested),
+        }
+    }
+
+    fn tenant_from_host(&self, headers: &HeaderMap) -> Result<Option<TenantId>> {
+        let Some(base_domain) = self.base_domain.as_deref() else {
+            return Ok(None);
+        };
+        let Some(host) = headers.get(HOST).and_then(|h| h.to_str().ok()) else {
+            return Ok(None);
+        };
+        let host = host.split(':').next().unwrap_or(host).to_ascii_lowercase();
+        let Some(label) = host
+            .strip_suffix(base_domain)
+            .and_then(|prefix| prefix.strip_suffix('.'))
+            .filter(|label| !label.is_empty() && !label.contains('.'))
+        else {
+            return Ok(None);
+        };
+
+        match self.subdomains.get(label) {
+            Some(tenant) => Ok(Some(*tenant)),
+            None => label.parse().map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (axum::http::HeaderName::from_static(name), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_claim_wins_and_conflicting_requests_are_mismatches() {
+        let resolver = TenantResolver::new();
+        let (caller, other) = (TenantId::new(Uuid::new_v4()), TenantId::new(Uuid::new_v4()));
+
+        let same = headers(&[(TENANT_HEADER, &caller.to_string())]);
+        assert_eq!(resolver.resolve(Some(caller), &same).unwrap(), Some((caller, TenantSource::Claim)));
+        assert_eq!(resolver.resolve(Some(caller), &HeaderMap::new()).unwrap(), Some((caller, TenantSource::Claim)));
+
+        let conflicting = headers(&[(TENANT_HEADER, &other.to_string())]);
+        assert_eq!(
+            resolver.resolve(Some(caller), &conflicting),
+            Err(GatewayError::TenantMismatch { caller, target: other })
+        );
+    }
+
+    #[test]
+    fn test_anonymous_requests_use_the_header_or_subdomain() {
+        let clinic = TenantId::new(Uuid::new_v4());
+        let resolver = TenantResolver::new()
+            .with_base_domain("rustcare.dev")
+            .with_subdomain("northside", clinic);
+
+        let by_slug = headers(&[("host", "northside.rustcare.dev:8443")]);
+        assert_eq!(resolver.resolve(None, &by_slug).unwrap(), Some((clinic, TenantSource::Subdomain)));
+        let by_id = headers(&[("host", &format!("{clinic}.rustcare.dev"))]);
+        assert_eq!(resolver.resolve(None, &by_id).unwrap(), Some((clinic, TenantSource::Subdomain)));
+        let by_header = headers(&[(TENANT_HEADER, &clinic.to_string()), ("host", "api.example.org")]);
+        assert_eq!(resolver.resolve(None, &by_header).unwrap(), Some((clinic, TenantSource::Header)));
+
+        assert_eq!(resolver.resolve(None, &headers(&[("host", "rustcare.dev")])).unwrap(), None);
+        assert!(matches!(
+            resolver.resolve(None, &headers(&[("host", "unknown.rustcare.dev")])),
+            Err(GatewayError::InvalidTenant(_))
+        ));
+        assert!(matches!(
+            resolver.resolve(None, &headers(&[(TENANT_HEADER, "clinic-7")])),
+            Err(GatewayError::InvalidTenant(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_extractor_requires_a_resolved_tenant() {
+        let tenant = TenantId::new(Uuid::new_v4());
+        let (mut parts, ()) = axum::http::Request::new(()).into_parts();
+        assert_eq!(TenantId::from_request_parts(&mut parts, &()).await, Err(GatewayError::TenantUnresolved));
+
+        parts.extensions.insert(tenant);
+        assert_eq!(TenantId::from_request_parts(&mut parts, &()).await, Ok(tenant));
+    }
+}
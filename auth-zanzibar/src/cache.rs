@@ -0,0 +1,390 @@
+//! In-memory tuple cache
+//!
+//! Checks and expansions read tuples by (relation, object): "does this tuple
+//! exist" and "who holds this relation on this object". The cache keeps the
+//! tuples for recently read (relation, object) pairs, so both questions are
+//! answered from memory after the first read of a pair.
+//!
+//! Entries are dropped as soon as a write or delete touches their pair, on
+//! this node through the engine, on other nodes through the tuple change
+//! feed (`AuthorizationEngine::subscribe_cache_invalidation`). The cache
+//! holds at most `capacity` pairs and evicts the least recently used.
+
+use crate::{
+    error::ZanzibarError,
+    models::*,
+    repository::TupleRepository,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Default number of (relation, object) pairs kept
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// How the tuple cache serves reads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    /// Every read goes to the repository
+    #[default]
+    Off,
+    /// Cached tuples are served until invalidated. Writes made elsewhere are
+    /// seen once the change feed delivers them; evaluations carrying a
+    /// consistency token bypass the cache and read the repository.
+    ReadThrough,
+    /// Like `ReadThrough`, but an evaluation carrying a consistency token is
+    /// served entries known to reflect the token's revision: entries read at
+    /// or after it, or any entry once the change feed has been applied up to
+    /// it. Older entries are read again and replaced.
+    ConsistencyGated,
+}
+
+/// Counters for the tuple cache since it was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheMetrics {
+    /// Reads answered from memory
+    pub hits: u64,
+    /// Reads that went to the repository
+    pub misses: u64,
+    /// Entries dropped because a write or delete touched them
+    pub invalidations: u64,
+    /// Entries dropped to stay within capacity
+    pub evictions: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+struct Entry {
+    tuples: Arc<Vec<Tuple>>,
+    /// Repository revision observed before the tuples were read
+    revision: u64,
+    /// Position in the recency order
+    tick: u64,
+}
+
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<String, Entry>,
+    /// Least recently used first
+    order: BTreeMap<u64, String>,
+    next_tick: u64,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &str) {
+        let tick = self.next_tick;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.order.remove(&entry.tick);
+            entry.tick = tick;
+            self.order.insert(tick, key.to_string());
+            self.next_tick += 1;
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.order.remove(&entry.tick);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Bounded LRU cache of tuples by (relation, object)
+pub struct TupleCache {
+    mode: CacheMode,
+    capacity: usize,
+    lru: Mutex<Lru>,
+    /// Bumped by every invalidation; a read that raced one is not stored
+    generation: AtomicU64,
+    /// Highest revision whose changes have been applied from the change feed
+    applied_revision: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl TupleCache {
+    pub fn new(mode: CacheMode, capacity: usize) -> Self {
+        Self {
+            mode,
+            capacity: capacity.max(1),
+            lru: Mutex::new(Lru::default()),
+            generation: AtomicU64::new(0),
+            applied_revision: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn mode(&self) -> CacheMode {
+        self.mode
+    }
+
+    fn key(relation: &Relation, object: &Object) -> String {
+        format!("{}_{}", relation, object)
+    }
+
+    /// Drop the entry for a (relation, object) pair
+    pub fn invalidate(&self, relation: &Relation, object: &Object) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        let removed = self.lock().remove(&Self::key(relation, object));
+        if removed {
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drop the entry a tuple belongs to
+    pub fn invalidate_tuple(&self, tuple: &Tuple) {
+        self.invalidate(&tuple.relation, &tuple.object);
+    }
+
+    /// Apply a change from the tuple change feed
+    pub fn apply_change(&self, change: &TupleChange) {
+        self.invalidate_tuple(&change.tuple);
+        self.applied_revision.fetch_max(change.revision, Ordering::SeqCst);
+    }
+
+    pub fn clear(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        let mut lru = self.lock();
+        self.invalidations.fetch_add(lru.entries.len() as u64, Ordering::Relaxed);
+        *lru = Lru::default();
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.lock().entries.len(),
+            capacity: self.capacity,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        // The map stays consistent even if a holder panicked
+        self.lru.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Cached tuples for the pair, if present and fresh enough for `min_revision`
+    fn get(&self, key: &str, min_revision: Option<u64>) -> Option<Arc<Vec<Tuple>>> {
+        let mut lru = self.lock();
+        let entry = lru.entries.get(key)?;
+        let fresh = min_revision.is_none_or(|min_revision| {
+            entry.revision >= min_revision || self.applied_revision.load(Ordering::SeqCst) >= min_revision
+        });
+        if !fresh {
+            return None;
+        }
+        let tuples = entry.tuples.clone();
+        lru.touch(key);
+        Some(tuples)
+    }
+
+    /// Store tuples read at `revision`, unless an invalidation happened
+    /// since `generation` was taken
+    fn insert(&self, key: String, tuples: Arc<Vec<Tuple>>, revision: u64, generation: u64) {
+        let mut lru = self.lock();
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        lru.remove(&key);
+        while lru.entries.len() >= self.capacity {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&oldest);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        let tick = lru.next_tick;
+        lru.next_tick += 1;
+        lru.order.insert(tick, key.clone());
+        lru.entries.insert(key, Entry { tuples, revision, tick });
+    }
+}
+
+/// Repository wrapper that answers (relation, object) reads from a `TupleCache`
+///
+/// Writes and deletes made through it invalidate the pairs they touch. Other
+/// reads pass straight through.
+pub struct CachedTupleRepository {
+    inner: Arc<dyn TupleRepository>,
+    cache: Arc<TupleCache>,
+    /// Revision the reads must reflect; older entries are read again
+    min_revision: Option<u64>,
+}
+
+impl CachedTupleRepository {
+    pub fn new(inner: Arc<dyn TupleRepository>, cache: Arc<TupleCache>) -> Self {
+        Self {
+            inner,
+            cache,
+            min_revision: None,
+        }
+    }
+
+    /// Reads must reflect every change up to `revision`
+    pub fn with_min_revision(mut self, revision: u64) -> Self {
+        self.min_revision = Some(revision);
+        self
+    }
+
+    async fn tuples_for(&self, relation: &Relation, object: &Object) -> Result<Arc<Vec<Tuple>>, ZanzibarError> {
+        let key = TupleCache::key(relation, object);
+        if let Some(tuples) = self.cache.get(&key, self.min_revision) {
+            self.cache.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(tuples);
+        }
+        self.cache.misses.fetch_add(1, Ordering::Relaxed);
+
+        let generation = self.cache.generation.load(Ordering::SeqCst);
+        let revision = self.inner.current_revision().await?;
+        let tuples = Arc::new(
+            self.inner
+                .read_tuples(None, Some(relation.clone()), Some(object.clone()))
+                .await?,
+        );
+        self.cache.insert(key, tuples.clone(), revision, generation);
+        Ok(tuples)
+    }
+}
+
+#[async_trait]
+impl TupleRepository for CachedTupleRepository {
+    async fn write_tuple(&self, tuple: Tuple) -> Result<u64, ZanzibarError> {
+        let (relation, object) = (tuple.relation.clone(), tuple.object.clone());
+        let result = self.inner.write_tuple(tuple).await;
+        self.cache.invalidate(&relation, &object);
+        result
+    }
+
+    async fn delete_tuple(&self, tuple: Tuple) -> Result<u64, ZanzibarError> {
+        let (relation, object) = (tuple.relation.clone(), tuple.object.clone());
+        let result = self.inner.delete_tuple(tuple).await;
+        self.cache.invalidate(&relation, &object);
+        result
+    }
+
+    async fn batch_write(&self, request: WriteRequest) -> Result<u64, ZanzibarError> {
+        let touched: Vec<(Relation, Object)> = request
+            .writes
+            .iter()
+            .chain(&request.deletes)
+            .map(|tuple| (tuple.relation.clone(), tuple.object.clone()))
+            .collect();
+        let result = self.inner.batch_write(request).await;
+        for (relation, object) in &touched {
+            self.cache.invalidate(relation, object);
+        }
+        result
+    }
+
+    async fn read_tuples(
+        &self,
+        subject: Option<Subject>,
+        relation: Option<Relation>,
+        object: Option<Object>,
+    ) -> Result<Vec<Tuple>, ZanzibarError> {
+        match (subject, relation, object) {
+            (None, Some(relation), Some(object)) => {
+                Ok(self.tuples_for(&relation, &object).await?.as_ref().clone())
+            }
+            (subject, relation, object) => self.inner.read_tuples(subject, relation, object).await,
+        }
+    }
+
+    async fn read_tuples_for_subjects(&self, subjects: &[Subject]) -> Result<Vec<Tuple>, ZanzibarError> {
+        self.inner.read_tuples_for_subjects(subjects).await
+    }
+
+    async fn tuple_exists(&self, tuple: &Tuple) -> Result<bool, ZanzibarError> {
+        let tuples = self.tuples_for(&tuple.relation, &tuple.object).await?;
+        Ok(tuples.iter().any(|cached| cached.subject == tuple.subject))
+    }
+
+    async fn current_revision(&self) -> Result<u64, ZanzibarError> {
+        self.inner.current_revision().await
+    }
+
+    async fn read_changes(
+        &self,
+        after_revision: u64,
+        limit: usize,
+    ) -> Result<Vec<TupleChange>, ZanzibarError> {
+        self.inner.read_changes(after_revision, limit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::InMemoryTupleRepository;
+
+    fn viewer(user: &str, doc: &str) -> Tuple {
+        Tuple::new(Subject::user(user), Relation::new("viewer"), Object::new("document", doc))
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_invalidates_and_evicts() {
+        let inner = Arc::new(InMemoryTupleRepository::new());
+        let cache = Arc::new(TupleCache::new(CacheMode::ReadThrough, 2));
+        let repo = CachedTupleRepository::new(inner.clone(), cache.clone());
+
+        repo.write_tuple(viewer("alice", "doc1")).await.unwrap();
+        assert!(repo.tuple_exists(&viewer("alice", "doc1")).await.unwrap());
+        assert!(!repo.tuple_exists(&viewer("bob", "doc1")).await.unwrap());
+        assert_eq!((cache.metrics().hits, cache.metrics().misses), (1, 1));
+
+        // A write through the cache is seen at once
+        repo.write_tuple(viewer("bob", "doc1")).await.unwrap();
+        assert!(repo.tuple_exists(&viewer("bob", "doc1")).await.unwrap());
+        assert_eq!(cache.metrics().invalidations, 1);
+
+        // A write behind its back is not, until the change is applied
+        inner.delete_tuple(viewer("bob", "doc1")).await.unwrap();
+        assert!(repo.tuple_exists(&viewer("bob", "doc1")).await.unwrap());
+        let changes = inner.read_changes(0, 10).await.unwrap();
+        cache.apply_change(changes.last().unwrap());
+        assert!(!repo.tuple_exists(&viewer("bob", "doc1")).await.unwrap());
+
+        // doc1 was used last, so doc2 is evicted when doc3 arrives
+        repo.tuple_exists(&viewer("alice", "doc2")).await.unwrap();
+        repo.tuple_exists(&viewer("alice", "doc1")).await.unwrap();
+        repo.tuple_exists(&viewer("alice", "doc3")).await.unwrap();
+        let metrics = cache.metrics();
+        assert_eq!((metrics.entries, metrics.evictions), (2, 1));
+        let misses = metrics.misses;
+        repo.tuple_exists(&viewer("alice", "doc1")).await.unwrap();
+        assert_eq!(cache.metrics().misses, misses);
+    }
+
+    #[tokio::test]
+    async fn test_gated_mode_rereads_entries_older_than_token() {
+        let inner = Arc::new(InMemoryTupleRepository::new());
+        let cache = Arc::new(TupleCache::new(CacheMode::ConsistencyGated, 16));
+        let repo = CachedTupleRepository::new(inner.clone(), cache.clone());
+
+        assert!(!repo.tuple_exists(&viewer("alice", "doc1")).await.unwrap());
+
+        // Another node grants access; its token is newer than the entry
+        inner.write_tuple(viewer("alice", "doc1")).await.unwrap();
+        let token = inner.current_revision().await.unwrap();
+
+        // Without a token the stale entry is served; with one it is re-read
+        assert!(!repo.tuple_exists(&viewer("alice", "doc1")).await.unwrap());
+        let gated = CachedTupleRepository::new(inner.clone(), cache.clone()).with_min_revision(token);
+        assert!(gated.tuple_exists(&viewer("alice", "doc1")).await.unwrap());
+        assert!(gated.tuple_exists(&viewer("alice", "doc1")).await.unwrap());
+        assert_eq!(cache.metrics().hits, 2);
+    }
+}
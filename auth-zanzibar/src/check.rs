@@ -4,7 +4,7 @@ use crate::{
     repository::TupleRepository,
     schema::Schema,
 };
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tracing::debug;
 
@@ -45,8 +45,48 @@ impl PermissionChecker {
         object: Object,
         _context: Option<serde_json::Value>,
     ) -> Result<bool, ZanzibarError> {
-        let mut visited = HashSet::new();
-        self.check_recursive(subject, relation, object, &mut visited, 0).await
+        let mut state = EvalState::default();
+        self.check_recursive(subject, relation, object, &mut state, 0).await
+    }
+    
+    /// Check and record how the decision was reached
+    pub async fn check_with_trace(
+        &self,
+        subject: Subject,
+        relation: Relation,
+        object: Object,
+    ) -> Result<CheckTrace, ZanzibarError> {
+        let mut state = EvalState {
+            tracing: true,
+            ..EvalState::default()
+        };
+        let allowed = self.check_recursive(subject, relation, object, &mut state, 0).await?;
+        
+        Ok(CheckTrace {
+            allowed,
+            path: if allowed { state.path } else { Vec::new() },
+            explored: state.explored,
+        })
+    }
+    
+    /// Evaluate several checks in one pass
+    ///
+    /// Repository reads and resolved subproblems are shared across the batch,
+    /// so checks against the same objects or usersets hit storage once.
+    /// Results are returned in input order.
+    pub async fn batch_check(
+        &self,
+        checks: Vec<(Subject, Relation, Object)>,
+    ) -> Result<Vec<bool>, ZanzibarError> {
+        let mut state = EvalState::default();
+        let mut results = Vec::with_capacity(checks.len());
+        
+        for (subject, relation, object) in checks {
+            state.visited.clear();
+            results.push(self.check_recursive(subject, relation, object, &mut state, 0).await?);
+        }
+        
+        Ok(results)
     }
     
     async fn check_recursive(
@@ -54,7 +94,7 @@ impl PermissionChecker {
         subject: Subject,
         relation: Relation,
         object: Object,
-        visited: &mut HashSet<String>,
+        state: &mut EvalState,
         depth: u32,
     ) -> Result<bool, ZanzibarError> {
         Box::pin(async move {
@@ -64,17 +104,22 @@ impl PermissionChecker {
         }
         
         let check_key = format!("{}_{}_{}", subject, relation, object);
-        if visited.contains(&check_key) {
+        if let Some(&known) = state.results.get(&check_key) {
+            return Ok(known);
+        }
+        if state.visited.contains(&check_key) {
             return Ok(false);
         }
-        visited.insert(check_key);
+        state.visited.insert(check_key.clone());
         
         debug!("Checking: {} {} {}", subject, relation, object);
         
         // 1. Direct check: does the tuple exist?
         let direct_tuple = Tuple::new(subject.clone(), relation.clone(), object.clone());
-        if self.repository.tuple_exists(&direct_tuple).await? {
+        if state.tuple_exists(self.repository.as_ref(), &direct_tuple).await? {
             debug!("Direct permission found");
+            state.record(TraceStep::DirectTuple { tuple: direct_tuple });
+            state.results.insert(check_key, true);
             return Ok(true);
         }
         
@@ -87,30 +132,38 @@ impl PermissionChecker {
                 if rel_def.inherits_from.as_ref() == Some(&relation.name) {
                     // Check if subject has the higher-level relation
                     debug!("Checking if subject has higher permission: {}", rel_def.name);
+                    let step = state.enter(TraceStep::Inherited {
+                        object_type: object.object_type.clone(),
+                        from: rel_def.name.clone(),
+                        to: relation.name.clone(),
+                    });
                     if self.check_recursive(
                         subject.clone(),
                         Relation::new(&rel_def.name),
                         object.clone(),
-                        visited,
+                        state,
                         depth + 1,
                     ).await? {
+                        state.results.insert(check_key, true);
                         return Ok(true);
                     }
+                    state.leave(step);
                 }
             }
         }
         
         // 3. Check for userset references
         // Find all tuples where someone has this relation to the object
-        let related_tuples = self.repository.read_tuples(
-            None,
-            Some(relation.clone()),
-            Some(object.clone()),
-        ).await?;
+        let related_tuples = state.usersets(self.repository.as_ref(), &relation, &object).await?;
         
         for tuple in related_tuples {
             // Check if the subject is a member of the userset
             if let Some(ref userset_relation) = tuple.subject.relation {
+                // Plain subjects carry an empty relation; only "#relation" subjects are usersets
+                if userset_relation.is_empty() {
+                    continue;
+                }
+                
                 // The tuple references a userset like "document:doc1#editors"
                 // Check if our subject has that relation to that object
                 let userset_object = Object {
@@ -118,24 +171,106 @@ impl PermissionChecker {
                     object_type: tuple.subject.object_type.clone(),
                     object_id: tuple.subject.object_id.clone(),
                 };
+                let userset_relation = Relation::new(userset_relation);
                 
+                let step = state.enter(TraceStep::Userset { tuple });
                 if self.check_recursive(
                     subject.clone(),
-                    Relation::new(userset_relation),
+                    userset_relation,
                     userset_object,
-                    visited,
+                    state,
                     depth + 1,
                 ).await? {
+                    state.results.insert(check_key, true);
                     return Ok(true);
                 }
+                state.leave(step);
             }
         }
         
+        // A denial is only final for a top-level check; deeper ones may have
+        // been cut short by cycle detection or the depth limit
+        if depth == 0 {
+            state.results.insert(check_key, false);
+        }
+        
         Ok(false)
         }).await
     }
 }
 
+/// Evaluation state for a single check or a batch of checks
+#[derive(Default)]
+struct EvalState {
+    /// Check keys on the current traversal stack (cycle detection)
+    visited: HashSet<String>,
+    /// Resolved subproblems, shared across a batch
+    results: HashMap<String, bool>,
+    /// Memoized `tuple_exists` lookups
+    exists: HashMap<String, bool>,
+    /// Memoized userset reads keyed by relation and object
+    usersets: HashMap<String, Vec<Tuple>>,
+    /// Whether to record trace steps
+    tracing: bool,
+    /// Steps leading to the current position in the traversal
+    path: Vec<TraceStep>,
+    /// Every step followed
+    explored: Vec<TraceStep>,
+}
+
+impl EvalState {
+    async fn tuple_exists(
+        &mut self,
+        repository: &dyn TupleRepository,
+        tuple: &Tuple,
+    ) -> Result<bool, ZanzibarError> {
+        let key = tuple.to_string();
+        if let Some(&exists) = self.exists.get(&key) {
+            return Ok(exists);
+        }
+        let exists = repository.tuple_exists(tuple).await?;
+        self.exists.insert(key, exists);
+        Ok(exists)
+    }
+    
+    async fn usersets(
+        &mut self,
+        repository: &dyn TupleRepository,
+        relation: &Relation,
+        object: &Object,
+    ) -> Result<Vec<Tuple>, ZanzibarError> {
+        let key = format!("{}_{}", relation, object);
+        if let Some(tuples) = self.usersets.get(&key) {
+            return Ok(tuples.clone());
+        }
+        let tuples = repository
+            .read_tuples(None, Some(relation.clone()), Some(object.clone()))
+            .await?;
+        self.usersets.insert(key, tuples.clone());
+        Ok(tuples)
+    }
+    
+    /// Record a terminal step on the current path
+    fn record(&mut self, step: TraceStep) {
+        if self.tracing {
+            self.explored.push(step.clone());
+            self.path.push(step);
+        }
+    }
+    
+    /// Push a step before descending; returns the path length to restore
+    fn enter(&mut self, step: TraceStep) -> usize {
+        let mark = self.path.len();
+        self.record(step);
+        mark
+    }
+    
+    /// Unwind the path after a branch failed
+    fn leave(&mut self, mark: usize) {
+        self.path.truncate(mark);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
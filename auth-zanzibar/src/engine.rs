@@ -1,16 +1,34 @@
 use crate::{
     models::*,
-    repository::TupleRepository,
-    schema::Schema,
+    repository::{watch_tuples, TupleRepository, TupleWatch},
+    schema::{Schema, SchemaViolation},
+    cache::{CacheMetrics, CacheMode, CachedTupleRepository, TupleCache},
     check::PermissionChecker,
     expand::SubjectExpander,
+    lookup::ObjectLookup,
     error::ZanzibarError,
 };
 use dashmap::DashMap;
 use std::sync::Arc;
-use tracing::{debug, info};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+/// Default upper bound on how long an `AtLeastAsFresh` evaluation waits for a
+/// lagging repository to reach the requested revision
+const DEFAULT_CONSISTENCY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Poll interval while waiting for the repository to catch up
+const REVISION_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Cached check result, stamped with the revision it was evaluated at
+#[derive(Debug, Clone, Copy)]
+struct CachedCheck {
+    allowed: bool,
+    revision: u64,
+}
+
 /// Core Zanzibar authorization engine
 pub struct AuthorizationEngine {
     /// Storage for relationship tuples
@@ -25,8 +43,17 @@ pub struct AuthorizationEngine {
     /// Subject expander for listing subjects
     expander: Arc<SubjectExpander>,
     
+    /// Object lookup for listing a subject's objects
+    lookup: Arc<ObjectLookup>,
+    
     /// Cache for permission checks (optional)
-    cache: Option<Arc<DashMap<String, bool>>>,
+    cache: Option<Arc<DashMap<String, CachedCheck>>>,
+    
+    /// Cache of tuples by (relation, object) (optional)
+    tuple_cache: Option<Arc<TupleCache>>,
+    
+    /// How long `AtLeastAsFresh` evaluations wait for the repository
+    consistency_timeout: Duration,
     
     /// Enable debug mode for detailed traces
     debug_mode: bool,
@@ -37,14 +64,18 @@ impl AuthorizationEngine {
     pub async fn new(repository: Arc<dyn TupleRepository>) -> Result<Self, ZanzibarError> {
         let schema = Arc::new(Schema::default());
         let checker = Arc::new(PermissionChecker::new(repository.clone(), schema.clone()));
-        let expander = Arc::new(SubjectExpander::new(repository.clone()));
+        let expander = Arc::new(SubjectExpander::new(repository.clone(), schema.clone()));
+        let lookup = Arc::new(ObjectLookup::new(repository.clone(), schema.clone()));
         
         Ok(Self {
             repository,
             schema,
             checker,
             expander,
+            lookup,
             cache: None,
+            tuple_cache: None,
+            consistency_timeout: DEFAULT_CONSISTENCY_TIMEOUT,
             debug_mode: false,
         })
     }
@@ -56,6 +87,14 @@ impl AuthorizationEngine {
             self.repository.clone(),
             self.schema.clone(),
         ));
+        self.expander = Arc::new(SubjectExpander::new(
+            self.repository.clone(),
+            self.schema.clone(),
+        ));
+        self.lookup = Arc::new(ObjectLookup::new(
+            self.repository.clone(),
+            self.schema.clone(),
+        ));
         self
     }
     
@@ -65,6 +104,27 @@ impl AuthorizationEngine {
         self
     }
     
+    /// Cache the tuples checks and expansions read, keeping at most
+    /// `capacity` (relation, object) pairs
+    ///
+    /// Writes through this engine invalidate the pairs they touch at once;
+    /// call `subscribe_cache_invalidation` so writes from other nodes do too.
+    /// `CacheMode::Off` removes the cache.
+    pub fn with_tuple_cache(mut self, mode: CacheMode, capacity: usize) -> Self {
+        self.tuple_cache = match mode {
+            CacheMode::Off => None,
+            mode => Some(Arc::new(TupleCache::new(mode, capacity))),
+        };
+        self
+    }
+    
+    /// Set how long `AtLeastAsFresh` evaluations wait for a lagging
+    /// repository before failing with `ConsistencyTimeout`
+    pub fn with_consistency_timeout(mut self, timeout: Duration) -> Self {
+        self.consistency_timeout = timeout;
+        self
+    }
+    
     /// Enable debug mode for detailed permission traces
     pub fn with_debug(mut self, enabled: bool) -> Self {
         self.debug_mode = enabled;
@@ -93,50 +153,263 @@ impl AuthorizationEngine {
         object: Object,
         context: Option<serde_json::Value>,
     ) -> Result<bool, ZanzibarError> {
+        self.evaluate_check(subject, relation, object, context, &Consistency::MinimizeLatency)
+            .await
+            .map(|(allowed, _)| allowed)
+    }
+    
+    /// Check permission under an explicit consistency requirement
+    ///
+    /// Pass the token returned by a write as `Consistency::AtLeastAsFresh` to
+    /// guarantee the check observes that write, or `FullyConsistent` to
+    /// bypass the cache altogether. See [`Consistency`] for the trade-offs.
+    pub async fn check_with_consistency(
+        &self,
+        subject: Subject,
+        relation: Relation,
+        object: Object,
+        consistency: Consistency,
+    ) -> Result<bool, ZanzibarError> {
+        self.evaluate_check(subject, relation, object, None, &consistency)
+            .await
+            .map(|(allowed, _)| allowed)
+    }
+    
+    /// Check permission under an explicit consistency requirement, also
+    /// returning a token for the revision the decision was evaluated at
+    ///
+    /// Passing that token to a later check guarantees it sees at least the
+    /// same relationships.
+    pub async fn check_with_revision(
+        &self,
+        subject: Subject,
+        relation: Relation,
+        object: Object,
+        consistency: Consistency,
+    ) -> Result<(bool, ConsistencyToken), ZanzibarError> {
+        let (allowed, revision) = self
+            .evaluate_check(subject, relation, object, None, &consistency)
+            .await?;
+        Ok((allowed, ConsistencyToken::from_revision(revision)))
+    }
+    
+    /// Decision and the revision it was evaluated at
+    async fn evaluate_check(
+        &self,
+        subject: Subject,
+        relation: Relation,
+        object: Object,
+        context: Option<serde_json::Value>,
+        consistency: &Consistency,
+    ) -> Result<(bool, u64), ZanzibarError> {
         let cache_key = format!("{}_{}_{}", subject, relation, object);
+        let min_revision = Self::min_cached_revision(consistency)?;
         
-        // Check cache first if enabled
-        if let Some(ref cache) = self.cache {
-            if let Some(result) = cache.get(&cache_key) {
-                debug!("Cache hit for permission check: {}", cache_key);
-                return Ok(*result);
+        // Check cache first if enabled and allowed by the consistency mode
+        if let (Some(cache), Some(min_revision)) = (&self.cache, min_revision) {
+            if let Some(cached) = cache.get(&cache_key) {
+                if cached.revision >= min_revision {
+                    debug!("Cache hit for permission check: {}", cache_key);
+                    return Ok((cached.allowed, cached.revision));
+                }
+                debug!(
+                    "Cached check at revision {} older than required {}",
+                    cached.revision, min_revision
+                );
             }
         }
         
+        let revision = match consistency {
+            Consistency::AtLeastAsFresh(token) => self.wait_for_revision(token.revision()?).await?,
+            _ => self.repository.current_revision().await?,
+        };
+        
         // Perform the check
-        let result = self.checker.check(subject, relation, object, context).await?;
+        let result = self
+            .checker_for(consistency)?
+            .check(subject, relation, object, context)
+            .await?;
         
         // Update cache if enabled
         if let Some(ref cache) = self.cache {
-            cache.insert(cache_key, result);
+            cache.insert(cache_key, CachedCheck { allowed: result, revision });
         }
         
-        Ok(result)
+        Ok((result, revision))
+    }
+    
+    /// Checker reading through the tuple cache as far as `consistency` allows
+    fn checker_for(&self, consistency: &Consistency) -> Result<Arc<PermissionChecker>, ZanzibarError> {
+        Ok(match self.cached_reader(consistency)? {
+            Some(reader) => Arc::new(PermissionChecker::new(reader, self.schema.clone())),
+            None => self.checker.clone(),
+        })
+    }
+    
+    /// Expander reading through the tuple cache as far as `consistency` allows
+    fn expander_for(&self, consistency: &Consistency) -> Result<Arc<SubjectExpander>, ZanzibarError> {
+        Ok(match self.cached_reader(consistency)? {
+            Some(reader) => Arc::new(SubjectExpander::new(reader, self.schema.clone())),
+            None => self.expander.clone(),
+        })
+    }
+    
+    /// Tuple cache view for an evaluation; `None` when it must read the
+    /// repository directly
+    fn cached_reader(
+        &self,
+        consistency: &Consistency,
+    ) -> Result<Option<Arc<dyn TupleRepository>>, ZanzibarError> {
+        let Some(ref tuple_cache) = self.tuple_cache else {
+            return Ok(None);
+        };
+        let reader = CachedTupleRepository::new(self.repository.clone(), tuple_cache.clone());
+        Ok(match consistency {
+            Consistency::FullyConsistent => None,
+            Consistency::AtLeastAsFresh(token) => match tuple_cache.mode() {
+                CacheMode::ConsistencyGated => Some(Arc::new(reader.with_min_revision(token.revision()?))),
+                // Read-through entries are only fresh up to the change feed
+                _ => None,
+            },
+            Consistency::MinimizeLatency => Some(Arc::new(reader)),
+        })
+    }
+    
+    /// Minimum revision a cached answer must have been computed at to satisfy
+    /// the consistency mode; `None` means the cache must not be used
+    fn min_cached_revision(consistency: &Consistency) -> Result<Option<u64>, ZanzibarError> {
+        Ok(match consistency {
+            Consistency::MinimizeLatency => Some(0),
+            Consistency::AtLeastAsFresh(token) => Some(token.revision()?),
+            Consistency::FullyConsistent => None,
+        })
+    }
+    
+    /// Wait until the repository has applied at least `min_revision`
+    ///
+    /// Returns the revision observed. Fails with `ConsistencyTimeout` if a
+    /// lagging repository does not catch up within the configured timeout.
+    async fn wait_for_revision(&self, min_revision: u64) -> Result<u64, ZanzibarError> {
+        let deadline = tokio::time::Instant::now() + self.consistency_timeout;
+        
+        loop {
+            let revision = self.repository.current_revision().await?;
+            if revision >= min_revision {
+                return Ok(revision);
+            }
+            
+            if tokio::time::Instant::now() >= deadline {
+                warn!(
+                    "Repository at revision {} did not reach {} within {:?}",
+                    revision, min_revision, self.consistency_timeout
+                );
+                return Err(ZanzibarError::ConsistencyTimeout(min_revision));
+            }
+            
+            tokio::time::sleep(REVISION_POLL_INTERVAL).await;
+        }
+    }
+    
+    /// Check permission and return the derivation behind the decision
+    ///
+    /// Always evaluated against the repository (the cache holds no traces).
+    /// The returned trace renders redacted through Display/Debug, so it is
+    /// safe to log as-is.
+    pub async fn check_with_trace(
+        &self,
+        subject: Subject,
+        relation: Relation,
+        object: Object,
+    ) -> Result<CheckTrace, ZanzibarError> {
+        self.checker.check_with_trace(subject, relation, object).await
     }
     
     /// Batch check multiple permissions at once
-    pub async fn batch_check(
+    ///
+    /// Accepts `CheckRequest`s or plain `(Subject, Relation, Object)` triples.
+    /// Cache misses are evaluated in a single pass that shares repository
+    /// reads and resolved subproblems; results come back in input order.
+    pub async fn batch_check<R>(
         &self,
-        requests: Vec<CheckRequest>,
-    ) -> Result<Vec<CheckResponse>, ZanzibarError> {
-        let mut responses = Vec::with_capacity(requests.len());
+        requests: Vec<R>,
+    ) -> Result<Vec<CheckResponse>, ZanzibarError>
+    where
+        R: Into<CheckRequest>,
+    {
+        let requests: Vec<CheckRequest> = requests.into_iter().map(Into::into).collect();
+        let mut results: Vec<Option<bool>> = Vec::with_capacity(requests.len());
+        let mut misses = Vec::new();
+        let mut required_revision = 0;
+        let mut fully_consistent = false;
         
-        for request in requests {
-            let allowed = self.check_with_context(
-                request.subject,
-                request.relation,
-                request.object,
-                request.context,
-            ).await?;
+        for (index, request) in requests.iter().enumerate() {
+            let cache_key = format!("{}_{}_{}", request.subject, request.relation, request.object);
+            let min_revision = Self::min_cached_revision(&request.consistency)?;
+            if let Consistency::AtLeastAsFresh(token) = &request.consistency {
+                required_revision = required_revision.max(token.revision()?);
+            }
+            fully_consistent |= matches!(request.consistency, Consistency::FullyConsistent);
             
-            responses.push(CheckResponse {
-                allowed,
-                debug_trace: if self.debug_mode {
-                    Some(vec![format!("Check completed: {}", allowed)])
-                } else {
-                    None
-                },
-            });
+            let cached = match (&self.cache, min_revision) {
+                (Some(cache), Some(min_revision)) => cache
+                    .get(&cache_key)
+                    .filter(|cached| cached.revision >= min_revision)
+                    .map(|cached| cached.allowed),
+                _ => None,
+            };
+            
+            if cached.is_none() {
+                misses.push((index, cache_key));
+            }
+            results.push(cached);
+        }
+        
+        if !misses.is_empty() {
+            let revision = if required_revision > 0 {
+                self.wait_for_revision(required_revision).await?
+            } else {
+                self.repository.current_revision().await?
+            };
+            
+            let checks = misses
+                .iter()
+                .filter_map(|(index, _)| requests.get(*index))
+                .map(|r| (r.subject.clone(), r.relation.clone(), r.object.clone()))
+                .collect();
+            // The shared pass honours the strictest request in the batch
+            let consistency = if fully_consistent {
+                Consistency::FullyConsistent
+            } else if required_revision > 0 {
+                Consistency::AtLeastAsFresh(ConsistencyToken::from_revision(required_revision))
+            } else {
+                Consistency::MinimizeLatency
+            };
+            let evaluated = self.checker_for(&consistency)?.batch_check(checks).await?;
+            
+            for ((index, cache_key), allowed) in misses.into_iter().zip(evaluated) {
+                if let Some(slot) = results.get_mut(index) {
+                    *slot = Some(allowed);
+                }
+                if let Some(ref cache) = self.cache {
+                    cache.insert(cache_key, CachedCheck { allowed, revision });
+                }
+            }
+        }
+        
+        let mut responses = Vec::with_capacity(requests.len());
+        for (request, allowed) in requests.into_iter().zip(results) {
+            let allowed = allowed.unwrap_or(false);
+            let debug_trace = if self.debug_mode {
+                let trace = self
+                    .checker
+                    .check_with_trace(request.subject, request.relation, request.object)
+                    .await?;
+                Some(trace.redacted_lines())
+            } else {
+                None
+            };
+            
+            responses.push(CheckResponse { allowed, debug_trace });
         }
         
         Ok(responses)
@@ -147,53 +420,164 @@ impl AuthorizationEngine {
     // =============================================================================
     
     /// Write a relationship tuple
-    pub async fn write_tuple(&self, tuple: Tuple) -> Result<(), ZanzibarError> {
+    ///
+    /// Returns a consistency token covering this write; pass it to
+    /// `check_with_consistency` to guarantee later checks observe it.
+    pub async fn write_tuple(&self, tuple: Tuple) -> Result<ConsistencyToken, ZanzibarError> {
         info!("Writing tuple: {}", tuple);
         
         // Validate tuple against schema
         self.schema.validate_tuple(&tuple)?;
         
         // Write to repository
-        self.repository.write_tuple(tuple).await?;
+        let written = tuple.clone();
+        let revision = self.repository.write_tuple(tuple).await?;
         
         // Invalidate cache if enabled
         if let Some(ref cache) = self.cache {
             cache.clear();
         }
+        if let Some(ref tuple_cache) = self.tuple_cache {
+            tuple_cache.invalidate_tuple(&written);
+        }
         
-        Ok(())
+        Ok(ConsistencyToken::from_revision(revision))
     }
     
     /// Delete a relationship tuple
-    pub async fn delete_tuple(&self, tuple: Tuple) -> Result<(), ZanzibarError> {
+    ///
+    /// Returns a consistency token covering this delete.
+    pub async fn delete_tuple(&self, tuple: Tuple) -> Result<ConsistencyToken, ZanzibarError> {
         info!("Deleting tuple: {}", tuple);
         
-        self.repository.delete_tuple(tuple).await?;
+        let deleted = tuple.clone();
+        let revision = self.repository.delete_tuple(tuple).await?;
         
         // Invalidate cache
         if let Some(ref cache) = self.cache {
             cache.clear();
         }
+        if let Some(ref tuple_cache) = self.tuple_cache {
+            tuple_cache.invalidate_tuple(&deleted);
+        }
         
-        Ok(())
+        Ok(ConsistencyToken::from_revision(revision))
     }
     
     /// Batch write operation (atomic)
-    pub async fn batch_write(&self, request: WriteRequest) -> Result<(), ZanzibarError> {
+    ///
+    /// Returns a consistency token covering every write and delete in the batch.
+    pub async fn batch_write(&self, request: WriteRequest) -> Result<ConsistencyToken, ZanzibarError> {
         // Validate all tuples
         for tuple in &request.writes {
             self.schema.validate_tuple(tuple)?;
         }
         
         // Perform batch write
-        self.repository.batch_write(request).await?;
+        let touched: Vec<Tuple> = request.writes.iter().chain(&request.deletes).cloned().collect();
+        let revision = self.repository.batch_write(request).await?;
         
         // Invalidate cache
         if let Some(ref cache) = self.cache {
             cache.clear();
         }
+        if let Some(ref tuple_cache) = self.tuple_cache {
+            for tuple in &touched {
+                tuple_cache.invalidate_tuple(tuple);
+            }
+        }
         
-        Ok(())
+        Ok(ConsistencyToken::from_revision(revision))
+    }
+    
+    /// Write many tuples in one transaction, validating all of them first
+    ///
+    /// Every tuple is checked against the schema (known object type and
+    /// relation, allowed subject type) before anything is written. If any
+    /// fails, nothing is written and the response names each violation;
+    /// otherwise the batch is written atomically in a single repository call.
+    pub async fn write_tuples(&self, tuples: Vec<Tuple>) -> Result<WriteTuplesResponse, ZanzibarError> {
+        let checked: Vec<(Tuple, Option<SchemaViolation>)> = tuples
+            .into_iter()
+            .map(|tuple| {
+                let violation = self.schema.check_tuple(&tuple).err();
+                (tuple, violation)
+            })
+            .collect();
+        
+        if checked.iter().any(|(_, violation)| violation.is_some()) {
+            let results: Vec<(Tuple, TupleWriteStatus)> = checked
+                .into_iter()
+                .map(|(tuple, violation)| {
+                    let status = match violation {
+                        Some(violation) => TupleWriteStatus::Invalid { violation },
+                        None => TupleWriteStatus::Skipped,
+                    };
+                    (tuple, status)
+                })
+                .collect();
+            warn!(
+                "Rejected bulk write: {} of {} tuples violate the schema",
+                results.iter().filter(|(_, status)| matches!(status, TupleWriteStatus::Invalid { .. })).count(),
+                results.len()
+            );
+            return Ok(WriteTuplesResponse { results, consistency_token: None });
+        }
+        
+        let writes: Vec<Tuple> = checked.into_iter().map(|(tuple, _)| tuple).collect();
+        info!("Writing {} tuples", writes.len());
+        let consistency_token = self
+            .batch_write(WriteRequest { writes: writes.clone(), deletes: Vec::new() })
+            .await?;
+        
+        Ok(WriteTuplesResponse {
+            results: writes.into_iter().map(|tuple| (tuple, TupleWriteStatus::Written)).collect(),
+            consistency_token: Some(consistency_token),
+        })
+    }
+    
+    /// Watch tuple writes and deletes with revision greater than `after_revision`
+    ///
+    /// Persist `TupleWatch::last_revision()` to resume after a disconnect
+    /// without missing or duplicating changes.
+    pub fn watch_tuples(&self, filter: WatchFilter, after_revision: u64) -> TupleWatch {
+        watch_tuples(self.repository.clone(), filter, after_revision)
+    }
+    
+    /// Invalidate this engine's caches as other nodes write to the shared
+    /// repository
+    ///
+    /// Follows the tuple change feed from the current revision: each change
+    /// drops the tuple cache entry it touches and clears the check cache.
+    /// The task restarts the feed where it left off after a repository
+    /// error and runs until aborted.
+    pub async fn subscribe_cache_invalidation(&self) -> Result<JoinHandle<()>, ZanzibarError> {
+        let mut after_revision = self.repository.current_revision().await?;
+        let repository = self.repository.clone();
+        let cache = self.cache.clone();
+        let tuple_cache = self.tuple_cache.clone();
+        
+        Ok(tokio::spawn(async move {
+            loop {
+                let mut watch = watch_tuples(repository.clone(), WatchFilter::all(), after_revision);
+                while let Some(item) = watch.next().await {
+                    let Ok(change) = item else { break };
+                    if let Some(ref tuple_cache) = tuple_cache {
+                        tuple_cache.apply_change(&change);
+                    }
+                    if let Some(ref cache) = cache {
+                        cache.clear();
+                    }
+                }
+                after_revision = watch.last_revision();
+                tokio::time::sleep(crate::repository::watch::DEFAULT_POLL_INTERVAL).await;
+            }
+        }))
+    }
+    
+    /// Tuple cache hit, miss and eviction counts; `None` without a tuple cache
+    pub fn cache_metrics(&self) -> Option<CacheMetrics> {
+        self.tuple_cache.as_ref().map(|tuple_cache| tuple_cache.metrics())
     }
     
     /// Read tuples matching a filter
@@ -217,27 +601,131 @@ impl AuthorizationEngine {
         object: Object,
         max_depth: Option<u32>,
     ) -> Result<SubjectTree, ZanzibarError> {
-        self.expander.expand(relation, object, max_depth).await
+        self.expander_for(&Consistency::MinimizeLatency)?
+            .expand(relation, object, max_depth)
+            .await
+    }
+    
+    /// Expand under an explicit consistency requirement
+    ///
+    /// Expansion never reads the check cache; `FullyConsistent` also skips
+    /// the tuple cache, and `AtLeastAsFresh` waits for the repository to
+    /// reach the token's revision first.
+    pub async fn expand_with_consistency(
+        &self,
+        relation: Relation,
+        object: Object,
+        max_depth: Option<u32>,
+        consistency: Consistency,
+    ) -> Result<SubjectTree, ZanzibarError> {
+        if let Consistency::AtLeastAsFresh(token) = &consistency {
+            self.wait_for_revision(token.revision()?).await?;
+        }
+        
+        self.expander_for(&consistency)?
+            .expand(relation, object, max_depth)
+            .await
+    }
+    
+    /// Ids of every user with a relation to an object, through groups and
+    /// inherited relations, for access reviews
+    pub async fn list_subjects(
+        &self,
+        relation: Relation,
+        object: Object,
+    ) -> Result<Vec<String>, ZanzibarError> {
+        self.expander_for(&Consistency::MinimizeLatency)?
+            .list_subjects(relation, object)
+            .await
     }
     
     /// List all objects a subject has a specific relation to
+    ///
+    /// Follows group membership, usersets and inherited relations, so the
+    /// result is every object of `object_type` for which `check` allows.
     pub async fn list_objects(
         &self,
         subject: Subject,
         relation: Relation,
         object_type: String,
     ) -> Result<Vec<Object>, ZanzibarError> {
-        let tuples = self.repository.read_tuples(
-            Some(subject),
-            Some(relation),
-            None,
-        ).await?;
+        let revision = self.repository.current_revision().await?;
+        self.lookup_objects(&subject, &relation, &object_type, revision).await
+    }
+    
+    /// One page of the ids of objects a subject has a relation to
+    ///
+    /// The lookup runs from the subject side, so its cost depends on the
+    /// subject's grants and memberships rather than on how many objects
+    /// exist (see [`ObjectLookup`]). Every page repeats the lookup; pass the
+    /// returned consistency token with the following pages so they are taken
+    /// from a state at least as new. Objects found are recorded in the check
+    /// cache, so a `check` on a listed object is answered without
+    /// re-evaluating it.
+    pub async fn list_objects_page(
+        &self,
+        request: ListObjectsRequest,
+    ) -> Result<ListObjectsResponse, ZanzibarError> {
+        if request.page_size == Some(0) {
+            return Err(ZanzibarError::ValidationError("page_size must be at least 1".to_string()));
+        }
         
-        Ok(tuples
-            .into_iter()
-            .filter(|t| t.object.object_type == object_type)
-            .map(|t| t.object)
-            .collect())
+        let revision = match &request.consistency {
+            Consistency::AtLeastAsFresh(token) => self.wait_for_revision(token.revision()?).await?,
+            _ => self.repository.current_revision().await?,
+        };
+        
+        let objects = self
+            .lookup_objects(&request.subject, &request.relation, &request.object_type, revision)
+            .await?;
+        
+        // Ids are sorted, so a page starts after the last id of the previous one
+        let start = match &request.page_token {
+            Some(last_id) => objects.partition_point(|object| object.object_id <= *last_id),
+            None => 0,
+        };
+        let remaining = &objects[start..];
+        let page_size = request.page_size.unwrap_or(remaining.len());
+        let object_ids: Vec<String> = remaining
+            .iter()
+            .take(page_size)
+            .map(|object| object.object_id.clone())
+            .collect();
+        let next_page_token = if remaining.len() > object_ids.len() {
+            object_ids.last().cloned()
+        } else {
+            None
+        };
+        
+        Ok(ListObjectsResponse {
+            object_ids,
+            next_page_token,
+            consistency_token: ConsistencyToken::from_revision(revision),
+        })
+    }
+    
+    async fn lookup_objects(
+        &self,
+        subject: &Subject,
+        relation: &Relation,
+        object_type: &str,
+        revision: u64,
+    ) -> Result<Vec<Object>, ZanzibarError> {
+        let objects = self
+            .lookup
+            .lookup(subject.clone(), relation.clone(), object_type)
+            .await?;
+        
+        if let Some(ref cache) = self.cache {
+            for object in &objects {
+                cache.insert(
+                    format!("{}_{}_{}", subject, relation, object),
+                    CachedCheck { allowed: true, revision },
+                );
+            }
+        }
+        
+        Ok(objects)
     }
     
     // =============================================================================
@@ -418,4 +906,299 @@ mod tests {
         let allowed = engine.check(alice, editor, doc).await.unwrap();
         assert!(allowed);
     }
+    
+    #[test]
+    fn test_consistency_token_roundtrip() {
+        let token = ConsistencyToken::from_revision(42);
+        assert_eq!(token.revision().unwrap(), 42);
+        
+        let bogus = ConsistencyToken {
+            token: "not-a-zookie".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        assert!(matches!(bogus.revision(), Err(ZanzibarError::InvalidConsistencyToken)));
+    }
+    
+    #[tokio::test]
+    async fn test_at_least_as_fresh_skips_stale_cache() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let engine = AuthorizationEngine::new(repo.clone()).await.unwrap().with_cache();
+        
+        let alice = Subject::user("alice");
+        let doc = Object::new("document", "doc1");
+        let viewer = Relation::new("viewer");
+        let grant = Tuple::new(alice.clone(), viewer.clone(), doc.clone());
+        
+        engine.write_tuple(grant.clone()).await.unwrap();
+        assert!(engine.check(alice.clone(), viewer.clone(), doc.clone()).await.unwrap());
+        
+        // Revoke through the repository directly, as another replica would,
+        // so this engine's cache still holds the stale grant
+        repo.delete_tuple(grant).await.unwrap();
+        let revoked_at = ConsistencyToken::from_revision(repo.current_revision().await.unwrap());
+        
+        assert!(engine.check(alice.clone(), viewer.clone(), doc.clone()).await.unwrap());
+        assert!(!engine
+            .check_with_consistency(alice.clone(), viewer.clone(), doc.clone(), Consistency::AtLeastAsFresh(revoked_at))
+            .await
+            .unwrap());
+        assert!(!engine
+            .check_with_consistency(alice, viewer, doc, Consistency::FullyConsistent)
+            .await
+            .unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_check_with_revision_reports_the_evaluated_revision() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let engine = AuthorizationEngine::new(repo.clone()).await.unwrap();
+        
+        let alice = Subject::user("alice");
+        let doc = Object::new("document", "doc1");
+        let viewer = Relation::new("viewer");
+        let written = engine.write_tuple(Tuple::new(alice.clone(), viewer.clone(), doc.clone())).await.unwrap();
+        
+        let (allowed, token) = engine
+            .check_with_revision(alice, viewer, doc, Consistency::AtLeastAsFresh(ConsistencyToken::from_revision(0)))
+            .await
+            .unwrap();
+        assert!(allowed);
+        assert_eq!(token.revision().unwrap(), written.revision().unwrap());
+        assert_eq!(token.revision().unwrap(), repo.current_revision().await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_future_token_times_out() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let engine = AuthorizationEngine::new(repo)
+            .await
+            .unwrap()
+            .with_consistency_timeout(Duration::from_millis(20));
+        
+        let result = engine
+            .check_with_consistency(
+                Subject::user("alice"),
+                Relation::new("viewer"),
+                Object::new("document", "doc1"),
+                Consistency::AtLeastAsFresh(ConsistencyToken::from_revision(100)),
+            )
+            .await;
+        assert!(matches!(result, Err(ZanzibarError::ConsistencyTimeout(100))));
+    }
+    
+    #[tokio::test]
+    async fn test_batch_check_preserves_order() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let engine = AuthorizationEngine::new(repo).await.unwrap().with_cache();
+        
+        let alice = Subject::user("alice");
+        let viewer = Relation::new("viewer");
+        engine.write_tuple(Tuple::new(alice.clone(), Relation::new("editor"), Object::new("document", "doc2"))).await.unwrap();
+        engine.write_tuple(Tuple::new(alice.clone(), viewer.clone(), Object::new("document", "doc3"))).await.unwrap();
+        
+        // Warm the cache for one entry so the batch mixes hits and misses
+        engine.check(alice.clone(), viewer.clone(), Object::new("document", "doc3")).await.unwrap();
+        
+        let responses = engine
+            .batch_check(vec![
+                (alice.clone(), viewer.clone(), Object::new("document", "doc1")),
+                (alice.clone(), viewer.clone(), Object::new("document", "doc2")),
+                (alice.clone(), viewer.clone(), Object::new("document", "doc3")),
+                (alice, viewer, Object::new("document", "doc1")),
+            ])
+            .await
+            .unwrap();
+        
+        let allowed: Vec<bool> = responses.iter().map(|r| r.allowed).collect();
+        assert_eq!(allowed, vec![false, true, true, false]);
+    }
+    
+    #[tokio::test]
+    async fn test_check_with_trace_records_path() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let engine = AuthorizationEngine::new(repo).await.unwrap();
+        
+        let alice = Subject::user("alice");
+        let doc = Object::new("document", "doc1");
+        let admins = Subject::userset("group", "admins", "member");
+        
+        engine.write_tuple(Tuple::new(admins, Relation::new("editor"), doc.clone())).await.unwrap();
+        engine.write_tuple(Tuple::new(
+            alice.clone(),
+            Relation::new("member"),
+            Object { namespace: "object".to_string(), object_type: "group".to_string(), object_id: "admins".to_string() },
+        )).await.unwrap();
+        
+        let trace = engine.check_with_trace(alice.clone(), Relation::new("viewer"), doc.clone()).await.unwrap();
+        assert!(trace.allowed);
+        assert_eq!(trace.path.len(), 3);
+        assert!(matches!(trace.path.first(), Some(TraceStep::Inherited { from, .. }) if from == "editor"));
+        assert!(matches!(trace.path.get(1), Some(TraceStep::Userset { .. })));
+        assert!(matches!(trace.path.get(2), Some(TraceStep::DirectTuple { .. })));
+        
+        // Redacted output keeps types and relations but drops identifiers
+        let rendered = format!("{} {:?}", trace, trace);
+        assert!(rendered.contains("group:***#member editor document:***"));
+        assert!(!rendered.contains("alice"));
+        assert!(!rendered.contains("admins"));
+        
+        let denied = engine.check_with_trace(Subject::user("bob"), Relation::new("viewer"), doc).await.unwrap();
+        assert!(!denied.allowed);
+        assert!(denied.path.is_empty());
+        assert!(!denied.explored.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_list_objects_pages_and_warms_cache() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let engine = AuthorizationEngine::new(repo.clone()).await.unwrap().with_cache();
+        
+        let alice = Subject::user("alice");
+        let viewer = Relation::new("viewer");
+        engine.write_tuple(Tuple::new(alice.clone(), Relation::new("member"), Object::new("role", "nurse"))).await.unwrap();
+        for id in ["doc1", "doc2", "doc3"] {
+            engine.write_tuple(Tuple::new(Subject::userset("role", "nurse", "member"), viewer.clone(), Object::new("document", id))).await.unwrap();
+        }
+        engine.write_tuple(Tuple::new(alice.clone(), Relation::new("editor"), Object::new("document", "doc4"))).await.unwrap();
+        
+        let request = |page_token, consistency| ListObjectsRequest {
+            subject: alice.clone(),
+            relation: viewer.clone(),
+            object_type: "document".to_string(),
+            page_size: Some(3),
+            page_token,
+            consistency,
+        };
+        let first = engine.list_objects_page(request(None, Consistency::MinimizeLatency)).await.unwrap();
+        assert_eq!(first.object_ids, vec!["doc1", "doc2", "doc3"]);
+        
+        let second = engine
+            .list_objects_page(request(
+                first.next_page_token.clone(),
+                Consistency::AtLeastAsFresh(first.consistency_token.clone()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(second.object_ids, vec!["doc4"]);
+        assert!(second.next_page_token.is_none());
+        
+        // Listed objects were cached as allowed: a change that bypasses the
+        // engine is not seen until the cache is refreshed
+        repo.delete_tuple(Tuple::new(alice.clone(), Relation::new("editor"), Object::new("document", "doc4"))).await.unwrap();
+        assert!(engine.check(alice.clone(), viewer.clone(), Object::new("document", "doc4")).await.unwrap());
+        assert!(!engine
+            .check_with_consistency(alice, viewer, Object::new("document", "doc4"), Consistency::FullyConsistent)
+            .await
+            .unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_tuple_cache_invalidated_across_engines() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let node_a = AuthorizationEngine::new(repo.clone()).await.unwrap()
+            .with_tuple_cache(CacheMode::ReadThrough, 64);
+        let node_b = AuthorizationEngine::new(repo.clone()).await.unwrap()
+            .with_cache()
+            .with_tuple_cache(CacheMode::ReadThrough, 64);
+        let subscription = node_b.subscribe_cache_invalidation().await.unwrap();
+        
+        let alice = Subject::user("alice");
+        let viewer = Relation::new("viewer");
+        let doc = Object::new("document", "doc1");
+        assert!(!node_b.check(alice.clone(), viewer.clone(), doc.clone()).await.unwrap());
+        
+        // Node A's own cache is invalidated by its write immediately
+        assert!(!node_a.check(alice.clone(), viewer.clone(), doc.clone()).await.unwrap());
+        node_a.write_tuple(Tuple::new(alice.clone(), viewer.clone(), doc.clone())).await.unwrap();
+        assert!(node_a.check(alice.clone(), viewer.clone(), doc.clone()).await.unwrap());
+        let metrics = node_a.cache_metrics().unwrap();
+        assert!(metrics.invalidations >= 1);
+        assert!(metrics.misses >= 2);
+        
+        // Node B sees it once the change feed delivers it
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while !node_b.check(alice.clone(), viewer.clone(), doc.clone()).await.unwrap() {
+            assert!(tokio::time::Instant::now() < deadline, "node B never saw the write");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        subscription.abort();
+        
+        assert!(AuthorizationEngine::new(repo).await.unwrap().cache_metrics().is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_tuple_cache_honours_consistency_tokens() {
+        let alice = Subject::user("alice");
+        let viewer = Relation::new("viewer");
+        let doc = Object::new("document", "doc1");
+        
+        for mode in [CacheMode::ReadThrough, CacheMode::ConsistencyGated] {
+            let repo = Arc::new(InMemoryTupleRepository::new());
+            let engine = AuthorizationEngine::new(repo.clone()).await.unwrap()
+                .with_tuple_cache(mode, 64);
+            assert!(!engine.check(alice.clone(), viewer.clone(), doc.clone()).await.unwrap());
+            
+            // Granted on another node; no change feed, so the entry stays stale
+            let granted_at = repo.write_tuple(Tuple::new(alice.clone(), viewer.clone(), doc.clone())).await.unwrap();
+            assert!(!engine.check(alice.clone(), viewer.clone(), doc.clone()).await.unwrap());
+            
+            let fresh = Consistency::AtLeastAsFresh(ConsistencyToken::from_revision(granted_at));
+            assert!(
+                engine.check_with_consistency(alice.clone(), viewer.clone(), doc.clone(), fresh).await.unwrap(),
+                "{:?} served a tuple older than the token",
+                mode
+            );
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_write_tuples_is_validated_and_all_or_nothing() {
+        let mut schema = Schema::healthcare_schema();
+        for relation in &mut schema.namespaces.get_mut("document").unwrap().relations {
+            relation.allowed_subjects = vec!["user".to_string(), "role#member".to_string()];
+        }
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let engine = AuthorizationEngine::new(repo.clone()).await.unwrap().with_schema(schema);
+        
+        let doc = Object::new("document", "doc1");
+        let valid = Tuple::new(Subject::user("alice"), Relation::new("viewer"), doc.clone());
+        let nurses = Tuple::new(Subject::userset("role", "nurse", "member"), Relation::new("editor"), doc.clone());
+        let bad_relation = Tuple::new(Subject::user("bob"), Relation::new("approver"), doc.clone());
+        let bad_subject = Tuple::new(Subject::userset("organization", "org1", "admin"), Relation::new("viewer"), doc.clone());
+        
+        let rejected = engine
+            .write_tuples(vec![valid.clone(), bad_relation, nurses.clone(), bad_subject])
+            .await
+            .unwrap();
+        assert!(!rejected.is_written());
+        let statuses: Vec<&TupleWriteStatus> = rejected.results.iter().map(|(_, status)| status).collect();
+        assert_eq!(statuses[0], &TupleWriteStatus::Skipped);
+        assert_eq!(statuses[1], &TupleWriteStatus::Invalid {
+            violation: SchemaViolation::UnknownRelation {
+                object_type: "document".to_string(),
+                relation: "approver".to_string(),
+            },
+        });
+        assert!(matches!(
+            statuses[3],
+            TupleWriteStatus::Invalid { violation: SchemaViolation::SubjectNotAllowed { subject_type, .. } }
+                if subject_type == "organization#admin"
+        ));
+        assert_eq!(rejected.violations().count(), 2);
+        assert!(repo.read_tuples(None, None, None).await.unwrap().is_empty());
+        
+        let written = engine.write_tuples(vec![valid, nurses]).await.unwrap();
+        assert!(written.is_written());
+        assert!(written.results.iter().all(|(_, status)| *status == TupleWriteStatus::Written));
+        assert_eq!(repo.read_tuples(None, None, None).await.unwrap().len(), 2);
+        assert!(engine
+            .check_with_consistency(
+                Subject::user("alice"),
+                Relation::new("viewer"),
+                doc,
+                Consistency::AtLeastAsFresh(written.consistency_token.unwrap()),
+            )
+            .await
+            .unwrap());
+    }
 }
@@ -35,6 +35,9 @@ pub enum ZanzibarError {
     #[error("Consistency token invalid")]
     InvalidConsistencyToken,
     
+    #[error("Timed out waiting for repository to reach revision {0}")]
+    ConsistencyTimeout(u64),
+    
     #[error("Repository error: {0}")]
     RepositoryError(String),
     
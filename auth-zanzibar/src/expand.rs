@@ -2,155 +2,162 @@ use crate::{
     error::ZanzibarError,
     models::*,
     repository::TupleRepository,
+    schema::Schema,
 };
 use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::debug;
 
+/// Depth used when the caller does not give one
+pub const DEFAULT_EXPAND_DEPTH: u32 = 10;
+
+/// Upper bound on any requested depth, so a deeply nested group hierarchy
+/// cannot turn one request into an unbounded traversal
+pub const MAX_EXPAND_DEPTH: u32 = 32;
+
 /// Subject expander finds all subjects with a given relation to an object
+///
+/// A relation on an object is made up of:
+/// - subjects holding it directly through a tuple
+/// - members of usersets holding it (e.g. "role:nurse#member is viewer")
+/// - holders of relations that inherit it in the schema (editors are viewers)
+///
+/// Each userset is expanded in turn until only concrete subjects remain.
 pub struct SubjectExpander {
     repository: Arc<dyn TupleRepository>,
+    schema: Arc<Schema>,
 }
 
 impl SubjectExpander {
-    pub fn new(repository: Arc<dyn TupleRepository>) -> Self {
-        Self { repository }
+    pub fn new(repository: Arc<dyn TupleRepository>, schema: Arc<Schema>) -> Self {
+        Self { repository, schema }
     }
-    
+
     /// Expand all subjects that have the specified relation to an object
+    ///
+    /// Usersets deeper than `max_depth` (default `DEFAULT_EXPAND_DEPTH`, at
+    /// most `MAX_EXPAND_DEPTH`) are returned as `Truncated` nodes.
     pub async fn expand(
         &self,
         relation: Relation,
         object: Object,
         max_depth: Option<u32>,
     ) -> Result<SubjectTree, ZanzibarError> {
-        let max_depth = max_depth.unwrap_or(5);
-        let mut visited = HashSet::new();
-        
-        let root = SubjectTree {
-            subject: Subject {
-                namespace: "root".to_string(),
-                object_type: "root".to_string(),
-                object_id: "root".to_string(),
-                relation: None,
-            },
-            children: Vec::new(),
-        };
-        
-        self.expand_recursive(relation, object, &mut visited, 0, max_depth).await
+        let max_depth = max_depth.unwrap_or(DEFAULT_EXPAND_DEPTH).min(MAX_EXPAND_DEPTH);
+        let mut path = HashSet::new();
+        self.expand_recursive(relation, object, &mut path, 0, max_depth).await
     }
-    
+
     async fn expand_recursive(
         &self,
         relation: Relation,
         object: Object,
-        visited: &mut HashSet<String>,
+        path: &mut HashSet<String>,
         depth: u32,
         max_depth: u32,
     ) -> Result<SubjectTree, ZanzibarError> {
         Box::pin(async move {
-        if depth >= max_depth {
-            return Ok(SubjectTree {
-                subject: Subject {
-                    namespace: "max_depth".to_string(),
-                    object_type: "max_depth".to_string(),
-                    object_id: "reached".to_string(),
-                    relation: None,
-                },
-                children: Vec::new(),
-            });
-        }
-        
+        let node = |kind, children| SubjectTree {
+            subject: Subject {
+                namespace: object.namespace.clone(),
+                object_type: object.object_type.clone(),
+                object_id: object.object_id.clone(),
+                relation: Some(relation.name.clone()),
+            },
+            kind,
+            children,
+        };
+
+        // Only usersets on the current branch count as a cycle; the same
+        // group reached along two different branches is expanded under both
         let key = format!("{}_{}", relation, object);
-        if visited.contains(&key) {
-            return Ok(SubjectTree {
-                subject: Subject {
-                    namespace: "cycle".to_string(),
-                    object_type: "cycle".to_string(),
-                    object_id: "detected".to_string(),
-                    relation: None,
-                },
-                children: Vec::new(),
-            });
+        if path.contains(&key) {
+            return Ok(node(SubjectTreeKind::Cycle, Vec::new()));
+        }
+        if depth >= max_depth {
+            return Ok(node(SubjectTreeKind::Truncated, Vec::new()));
         }
-        visited.insert(key);
-        
+        path.insert(key.clone());
+
         debug!("Expanding: {} on {}", relation, object);
-        
-        // Find all tuples with this relation to this object
+
+        let mut children = Vec::new();
+
+        // 1. Tuples granting this relation on this object
         let tuples = self.repository.read_tuples(
             None,
             Some(relation.clone()),
             Some(object.clone()),
         ).await?;
-        
-        let mut children = Vec::new();
-        
+
         for tuple in tuples {
-            // If the subject is a userset, expand it recursively
-            if let Some(ref userset_relation) = tuple.subject.relation {
-                let userset_object = Object {
-                    namespace: tuple.subject.namespace.clone(),
-                    object_type: tuple.subject.object_type.clone(),
-                    object_id: tuple.subject.object_id.clone(),
-                };
-                
-                let child_tree = self.expand_recursive(
-                    Relation::new(userset_relation),
-                    userset_object,
-                    visited,
-                    depth + 1,
-                    max_depth,
-                ).await?;
-                
-                children.push(child_tree);
-            } else {
-                // Direct subject
-                children.push(SubjectTree {
-                    subject: tuple.subject.clone(),
+            match tuple.subject.relation.as_deref() {
+                // "object#relation" subjects are usersets; expand their members
+                Some(userset_relation) if !userset_relation.is_empty() => {
+                    let userset_object = Object {
+                        namespace: tuple.subject.namespace.clone(),
+                        object_type: tuple.subject.object_type.clone(),
+                        object_id: tuple.subject.object_id.clone(),
+                    };
+                    children.push(self.expand_recursive(
+                        Relation::new(userset_relation),
+                        userset_object,
+                        path,
+                        depth + 1,
+                        max_depth,
+                    ).await?);
+                }
+                // Plain subjects carry no relation, or an empty one
+                _ => children.push(SubjectTree {
+                    subject: tuple.subject,
+                    kind: SubjectTreeKind::Subject,
                     children: Vec::new(),
-                });
+                }),
             }
         }
-        
-        Ok(SubjectTree {
-            subject: Subject {
-                namespace: object.namespace,
-                object_type: object.object_type,
-                object_id: object.object_id,
-                relation: Some(relation.name),
-            },
-            children,
-        })
+
+        // 2. Relations that inherit this one, as in `check`
+        if let Some(namespace) = self.schema.namespaces.get(&object.object_type) {
+            for rel_def in &namespace.relations {
+                if rel_def.inherits_from.as_ref() == Some(&relation.name) {
+                    children.push(self.expand_recursive(
+                        Relation::new(&rel_def.name),
+                        object.clone(),
+                        path,
+                        depth + 1,
+                        max_depth,
+                    ).await?);
+                }
+            }
+        }
+
+        path.remove(&key);
+        Ok(node(SubjectTreeKind::Userset, children))
         }).await
     }
-    
-    /// List all subjects (flattened) with the given relation to an object
+
+    /// Ids of the users with the given relation to an object, sorted
+    ///
+    /// Fails with `MaxRecursionDepthExceeded` rather than return a partial
+    /// list when the expansion had to be cut short.
     pub async fn list_subjects(
         &self,
         relation: Relation,
         object: Object,
-    ) -> Result<Vec<Subject>, ZanzibarError> {
-        let tree = self.expand(relation, object, Some(10)).await?;
-        Ok(self.flatten_tree(&tree))
-    }
-    
-    fn flatten_tree(&self, tree: &SubjectTree) -> Vec<Subject> {
-        let mut subjects = Vec::new();
-        
-        // Only include actual user/userset subjects, not the root object or markers
-        if tree.subject.namespace != "root" 
-            && tree.subject.namespace != "cycle" 
-            && tree.subject.namespace != "max_depth" 
-            && tree.subject.object_type == "user" { // Only include user subjects
-            subjects.push(tree.subject.clone());
-        }
-        
-        for child in &tree.children {
-            subjects.extend(self.flatten_tree(child));
+    ) -> Result<Vec<String>, ZanzibarError> {
+        let tree = self.expand(relation, object, Some(MAX_EXPAND_DEPTH)).await?;
+        if tree.is_truncated() {
+            return Err(ZanzibarError::MaxRecursionDepthExceeded);
         }
-        
-        subjects
+
+        let mut user_ids: Vec<String> = tree
+            .subjects()
+            .into_iter()
+            .filter(|subject| subject.object_type == "user")
+            .map(|subject| subject.object_id)
+            .collect();
+        user_ids.sort();
+        Ok(user_ids)
     }
 }
 
@@ -158,32 +165,80 @@ impl SubjectExpander {
 mod tests {
     use super::*;
     use crate::repository::InMemoryTupleRepository;
-    
+
     #[tokio::test]
     async fn test_expand_subjects() {
         let repo = Arc::new(InMemoryTupleRepository::new());
-        let expander = SubjectExpander::new(repo.clone());
-        
+        let expander = SubjectExpander::new(repo.clone(), Arc::new(Schema::healthcare_schema()));
+
         let doc = Object::new("document", "doc1");
         let viewer = Relation::new("viewer");
-        
+
         // Add some subjects with viewer relation
         repo.write_tuple(Tuple::new(
             Subject::user("alice"),
             viewer.clone(),
             doc.clone(),
         )).await.unwrap();
-        
+
         repo.write_tuple(Tuple::new(
             Subject::user("bob"),
             viewer.clone(),
             doc.clone(),
         )).await.unwrap();
-        
+
         // Expand
         let subjects = expander.list_subjects(viewer, doc).await.unwrap();
         assert_eq!(subjects.len(), 2);
     }
+
+    #[tokio::test]
+    async fn test_expand_nested_groups_and_inherited_relations() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let expander = SubjectExpander::new(repo.clone(), Arc::new(Schema::healthcare_schema()));
+
+        let doc = Object::new("document", "doc1");
+        let member = Relation::new("member");
+        let tuples = vec![
+            // Editors are viewers through the schema
+            Tuple::new(Subject::user("alice"), Relation::new("editor"), doc.clone()),
+            // Nurses view the document; night-shift staff are nurses
+            Tuple::new(Subject::userset("role", "nurse", "member"), Relation::new("viewer"), doc.clone()),
+            Tuple::new(Subject::user("bob"), member.clone(), Object::new("role", "nurse")),
+            Tuple::new(Subject::userset("role", "night_shift", "member"), member.clone(), Object::new("role", "nurse")),
+            Tuple::new(Subject::user("carol"), member.clone(), Object::new("role", "night_shift")),
+            // Cycle back to nurses, and bob reached a second way
+            Tuple::new(Subject::userset("role", "nurse", "member"), member.clone(), Object::new("role", "night_shift")),
+            Tuple::new(Subject::user("bob"), member.clone(), Object::new("role", "night_shift")),
+        ];
+        for tuple in tuples {
+            repo.write_tuple(tuple).await.unwrap();
+        }
+
+        let users = expander.list_subjects(Relation::new("viewer"), doc.clone()).await.unwrap();
+        assert_eq!(users, vec!["alice", "bob", "carol"]);
+
+        let tree = expander.expand(Relation::new("viewer"), doc.clone(), None).await.unwrap();
+        assert_eq!(tree.kind, SubjectTreeKind::Userset);
+        assert!(!tree.is_truncated());
+
+        // Alice is found under the inherited editor relation
+        let editors = tree.children.iter()
+            .find(|child| child.subject.relation.as_deref() == Some("editor"))
+            .unwrap();
+        assert_eq!(editors.children[0].subject, Subject::user("alice"));
+
+        // The night shift's reference back to nurses is cut as a cycle
+        fn has_cycle(tree: &SubjectTree) -> bool {
+            tree.kind == SubjectTreeKind::Cycle || tree.children.iter().any(has_cycle)
+        }
+        assert!(has_cycle(&tree));
+
+        // Too shallow to reach carol: the tree says so and the list refuses
+        let shallow = expander.expand(Relation::new("viewer"), doc, Some(2)).await.unwrap();
+        assert!(shallow.is_truncated());
+        assert!(!shallow.subjects().contains(&Subject::user("carol")));
+    }
 }
 
-pub struct ExpandEngine {}
\ No newline at end of file
+pub struct ExpandEngine {}
@@ -6,6 +6,10 @@
 //! - Efficient authorization checks with graph traversal
 //! - Schema validation and consistency checking
 //! - Support for complex permission hierarchies
+//! - Snapshot-consistent checks via consistency tokens ("zookies")
+//! - Reverse lookups (`list_objects`) walking the graph from the subject side
+//! - Tuple change feed (`watch_tuples`) for cache invalidation and audit
+//! - Optional in-memory tuple cache, invalidated on write and from the change feed
 //! 
 //! # Core Concepts
 //! 
@@ -50,6 +54,8 @@ pub mod repository;
 pub mod schema;
 pub mod check;
 pub mod expand;
+pub mod lookup;
+pub mod cache;
 pub mod error;
 pub mod rls_integration;
 
@@ -57,4 +63,5 @@ pub use models::*;
 pub use engine::*;
 pub use schema::*;
 pub use error::*;
+pub use cache::{CacheMetrics, CacheMode};
 pub use rls_integration::{RlsContext, RlsMiddleware};
\ No newline at end of file
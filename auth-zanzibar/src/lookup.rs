@@ -0,0 +1,162 @@
+use crate::{
+    error::ZanzibarError,
+    models::*,
+    repository::TupleRepository,
+    schema::Schema,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Levels of userset nesting followed before giving up, in line with the
+/// recursion bound of `check`
+pub const MAX_LOOKUP_DEPTH: u32 = 10;
+
+/// Object lookup answers "which objects does this subject have a relation
+/// to" by walking the relation graph from the subject side.
+///
+/// Starting from the subject, each level reads every tuple naming one of the
+/// current subjects, which yields (relation, object) pairs the subject holds.
+/// Schema inheritance is applied to each pair (an editor is also a viewer),
+/// and every new pair becomes a userset subject ("role:nurse#member") for the
+/// next level, which is how group membership and userset rewrites are
+/// followed.
+///
+/// Complexity: one batched repository read per level of nesting, at most
+/// `MAX_LOOKUP_DEPTH`, touching only tuples whose subject is the subject or a
+/// userset it belongs to. The cost grows with the subject's own grants and
+/// memberships, not with the number of objects or tuples in the system. The
+/// repository is expected to index tuples by subject for this; see
+/// `TupleRepository::read_tuples_for_subjects`.
+pub struct ObjectLookup {
+    repository: Arc<dyn TupleRepository>,
+    schema: Arc<Schema>,
+}
+
+impl ObjectLookup {
+    pub fn new(repository: Arc<dyn TupleRepository>, schema: Arc<Schema>) -> Self {
+        Self { repository, schema }
+    }
+
+    /// Objects of `object_type` the subject has `relation` to, sorted by id
+    pub async fn lookup(
+        &self,
+        subject: Subject,
+        relation: Relation,
+        object_type: &str,
+    ) -> Result<Vec<Object>, ZanzibarError> {
+        // (relation, object) pairs the subject holds
+        let mut reached: HashSet<(String, Object)> = HashSet::new();
+        let mut frontier = vec![subject];
+        let mut depth = 0;
+
+        while !frontier.is_empty() {
+            if depth > MAX_LOOKUP_DEPTH {
+                debug!("Object lookup stopped at depth {}", MAX_LOOKUP_DEPTH);
+                break;
+            }
+
+            let tuples = self.repository.read_tuples_for_subjects(&frontier).await?;
+            let mut next = Vec::new();
+            for tuple in tuples {
+                for granted in self.implied_relations(&tuple.relation.name, &tuple.object.object_type) {
+                    let pair = (granted, tuple.object.clone());
+                    if reached.contains(&pair) {
+                        continue;
+                    }
+                    next.push(Subject {
+                        namespace: pair.1.namespace.clone(),
+                        object_type: pair.1.object_type.clone(),
+                        object_id: pair.1.object_id.clone(),
+                        relation: Some(pair.0.clone()),
+                    });
+                    reached.insert(pair);
+                }
+            }
+
+            frontier = next;
+            depth += 1;
+        }
+
+        let mut objects: Vec<Object> = reached
+            .into_iter()
+            .filter(|(granted, object)| *granted == relation.name && object.object_type == object_type)
+            .map(|(_, object)| object)
+            .collect();
+        objects.sort_by(|a, b| a.object_id.cmp(&b.object_id).then_with(|| a.namespace.cmp(&b.namespace)));
+        Ok(objects)
+    }
+
+    /// `relation` and every relation it inherits on `object_type`
+    fn implied_relations(&self, relation: &str, object_type: &str) -> Vec<String> {
+        let mut implied = vec![relation.to_string()];
+        let Some(namespace) = self.schema.namespaces.get(object_type) else {
+            return implied;
+        };
+
+        let mut current = relation.to_string();
+        while let Some(parent) = namespace
+            .relations
+            .iter()
+            .find(|rel_def| rel_def.name == current)
+            .and_then(|rel_def| rel_def.inherits_from.clone())
+        {
+            // A schema with an inheritance loop still terminates
+            if implied.contains(&parent) {
+                break;
+            }
+            implied.push(parent.clone());
+            current = parent;
+        }
+        implied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::PermissionChecker;
+    use crate::repository::InMemoryTupleRepository;
+
+    #[tokio::test]
+    async fn test_lookup_follows_groups_and_inheritance() {
+        let repo = Arc::new(InMemoryTupleRepository::new());
+        let schema = Arc::new(Schema::healthcare_schema());
+        let lookup = ObjectLookup::new(repo.clone(), schema.clone());
+        let checker = PermissionChecker::new(repo.clone(), schema);
+
+        let alice = Subject::user("alice");
+        let member = Relation::new("member");
+        let tuples = vec![
+            // Direct, and through inheritance (editors are viewers)
+            Tuple::new(alice.clone(), Relation::new("viewer"), Object::new("document", "doc1")),
+            Tuple::new(alice.clone(), Relation::new("editor"), Object::new("document", "doc3")),
+            // Through nested roles: alice is a night-shift member, night
+            // shift members are nurses, nurses view doc2
+            Tuple::new(alice.clone(), member.clone(), Object::new("role", "night_shift")),
+            Tuple::new(Subject::userset("role", "night_shift", "member"), member.clone(), Object::new("role", "nurse")),
+            Tuple::new(Subject::userset("role", "nurse", "member"), Relation::new("viewer"), Object::new("document", "doc2")),
+            // A membership cycle must not loop
+            Tuple::new(Subject::userset("role", "nurse", "member"), member.clone(), Object::new("role", "night_shift")),
+            // Not alice's
+            Tuple::new(Subject::user("bob"), Relation::new("viewer"), Object::new("document", "doc4")),
+            Tuple::new(alice.clone(), Relation::new("viewer"), Object::new("patient", "p1")),
+        ];
+        for tuple in tuples {
+            repo.write_tuple(tuple).await.unwrap();
+        }
+
+        let objects = lookup.lookup(alice.clone(), Relation::new("viewer"), "document").await.unwrap();
+        let ids: Vec<&str> = objects.iter().map(|object| object.object_id.as_str()).collect();
+        assert_eq!(ids, vec!["doc1", "doc2", "doc3"]);
+
+        // Every listed object passes `check`, and the one left out fails it
+        for object in objects {
+            assert!(checker.check(alice.clone(), Relation::new("viewer"), object, None).await.unwrap());
+        }
+        assert!(!checker
+            .check(alice, Relation::new("viewer"), Object::new("document", "doc4"), None)
+            .await
+            .unwrap());
+    }
+}
@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use std::fmt;
 
+use crate::error::ZanzibarError;
+use crate::schema::SchemaViolation;
+
 /// Represents a subject in the authorization system
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Subject {
@@ -136,6 +139,8 @@ pub struct CheckRequest {
     pub relation: Relation,
     pub object: Object,
     pub context: Option<serde_json::Value>,
+    #[serde(default)]
+    pub consistency: Consistency,
 }
 
 /// Authorization check response
@@ -145,21 +150,199 @@ pub struct CheckResponse {
     pub debug_trace: Option<Vec<String>>,
 }
 
+impl From<(Subject, Relation, Object)> for CheckRequest {
+    fn from((subject, relation, object): (Subject, Relation, Object)) -> Self {
+        Self {
+            subject,
+            relation,
+            object,
+            context: None,
+            consistency: Consistency::default(),
+        }
+    }
+}
+
+/// One step in the derivation of a check decision
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceStep {
+    /// A stored tuple grants the relation directly
+    DirectTuple { tuple: Tuple },
+    /// The schema rewrite `from` implies `to` on this object type
+    Inherited {
+        object_type: String,
+        from: String,
+        to: String,
+    },
+    /// Access flows through a userset tuple (e.g. `group:admins#member viewer document:1`)
+    Userset { tuple: Tuple },
+}
+
+impl TraceStep {
+    /// Log-safe rendering: object and subject ids are redacted, only types
+    /// and relations are kept
+    pub fn redacted(&self) -> String {
+        match self {
+            TraceStep::DirectTuple { tuple } => {
+                format!("direct {}", Self::redact_tuple(tuple))
+            }
+            TraceStep::Inherited { object_type, from, to } => {
+                format!("inherit {}: {} => {}", object_type, from, to)
+            }
+            TraceStep::Userset { tuple } => {
+                format!("userset {}", Self::redact_tuple(tuple))
+            }
+        }
+    }
+
+    fn redact_tuple(tuple: &Tuple) -> String {
+        let subject_relation = match tuple.subject.relation.as_deref() {
+            Some(relation) if !relation.is_empty() => format!("#{}", relation),
+            _ => String::new(),
+        };
+        format!(
+            "{}:***{} {} {}:***",
+            tuple.subject.object_type, subject_relation, tuple.relation, tuple.object.object_type
+        )
+    }
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.redacted())
+    }
+}
+
+// Debug is redacted too so traces can't leak identifiers through `{:?}`
+impl fmt::Debug for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.redacted())
+    }
+}
+
+/// Check decision together with how it was reached
+///
+/// `path` is the chain of steps from the checked object to the granting tuple
+/// (empty when denied); `explored` lists every rewrite and userset the checker
+/// followed, which explains a denial. Display and Debug output is redacted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CheckTrace {
+    pub allowed: bool,
+    pub path: Vec<TraceStep>,
+    pub explored: Vec<TraceStep>,
+}
+
+impl CheckTrace {
+    /// Log-safe lines describing the decision
+    pub fn redacted_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "decision: {}",
+            if self.allowed { "allow" } else { "deny" }
+        )];
+        lines.extend(self.path.iter().map(|step| format!("path: {}", step.redacted())));
+        lines.extend(self.explored.iter().map(|step| format!("explored: {}", step.redacted())));
+        lines
+    }
+}
+
+impl fmt::Display for CheckTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.redacted_lines().join("; "))
+    }
+}
+
+impl fmt::Debug for CheckTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
 /// Expand request to get all subjects with a relation to an object
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExpandRequest {
     pub relation: Relation,
     pub object: Object,
     pub max_depth: Option<u32>,
+    #[serde(default)]
+    pub consistency: Consistency,
+}
+
+/// Request for the objects of one type a subject has a relation to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListObjectsRequest {
+    pub subject: Subject,
+    pub relation: Relation,
+    pub object_type: String,
+    /// Most object ids per page; all of them when `None`
+    pub page_size: Option<usize>,
+    /// `next_page_token` of the previous page
+    pub page_token: Option<String>,
+    #[serde(default)]
+    pub consistency: Consistency,
+}
+
+/// One page of object ids, sorted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListObjectsResponse {
+    pub object_ids: Vec<String>,
+    /// Pass back as `page_token` for the next page; `None` on the last page
+    pub next_page_token: Option<String>,
+    /// Revision the lookup reflects; use it as `AtLeastAsFresh` for the
+    /// following pages so they do not reflect an older state
+    pub consistency_token: ConsistencyToken,
 }
 
 /// Subject tree node for expand responses
+///
+/// A `Userset` node stands for a relation on an object, carried in `subject`
+/// as `object#relation`; its children are the subjects and usersets that make
+/// it up. Every other kind of node is a leaf.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubjectTree {
     pub subject: Subject,
+    #[serde(default)]
+    pub kind: SubjectTreeKind,
     pub children: Vec<SubjectTree>,
 }
 
+/// What a `SubjectTree` node represents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubjectTreeKind {
+    /// A concrete subject, such as a user
+    #[default]
+    Subject,
+    /// A relation on an object, expanded into its children
+    Userset,
+    /// A userset already being expanded further up the tree
+    Cycle,
+    /// A userset left unexpanded because the depth limit was reached
+    Truncated,
+}
+
+impl SubjectTree {
+    /// Concrete subjects in the tree, each once, in first-seen order
+    pub fn subjects(&self) -> Vec<Subject> {
+        let mut subjects = Vec::new();
+        self.collect_subjects(&mut subjects, &mut std::collections::HashSet::new());
+        subjects
+    }
+
+    /// Whether any part of the tree was cut off by the depth limit
+    pub fn is_truncated(&self) -> bool {
+        self.kind == SubjectTreeKind::Truncated || self.children.iter().any(SubjectTree::is_truncated)
+    }
+
+    fn collect_subjects<'a>(&'a self, subjects: &mut Vec<Subject>, seen: &mut std::collections::HashSet<&'a Subject>) {
+        if self.kind == SubjectTreeKind::Subject && seen.insert(&self.subject) {
+            subjects.push(self.subject.clone());
+        }
+        for child in &self.children {
+            child.collect_subjects(subjects, seen);
+        }
+    }
+}
+
 /// Batch write request for multiple tuples
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WriteRequest {
@@ -167,9 +350,199 @@ pub struct WriteRequest {
     pub deletes: Vec<Tuple>,
 }
 
-/// Consistency token for read-after-write consistency
+/// Outcome for one tuple of a bulk write
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TupleWriteStatus {
+    Written,
+    /// The tuple breaks the schema
+    Invalid { violation: SchemaViolation },
+    /// The tuple is valid but was not written because others in the batch
+    /// were invalid
+    Skipped,
+}
+
+/// Result of a bulk tuple write, one status per tuple in input order
+///
+/// The batch is all or nothing: either every tuple is `Written` and a
+/// consistency token is returned, or none is written.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteTuplesResponse {
+    pub results: Vec<(Tuple, TupleWriteStatus)>,
+    /// Covers the whole batch; `None` when nothing was written
+    pub consistency_token: Option<ConsistencyToken>,
+}
+
+impl WriteTuplesResponse {
+    pub fn is_written(&self) -> bool {
+        self.consistency_token.is_some()
+    }
+
+    /// The invalid tuples and what is wrong with each
+    pub fn violations(&self) -> impl Iterator<Item = (&Tuple, &SchemaViolation)> {
+        self.results.iter().filter_map(|(tuple, status)| match status {
+            TupleWriteStatus::Invalid { violation } => Some((tuple, violation)),
+            _ => None,
+        })
+    }
+}
+
+/// Kind of mutation recorded in the tuple change log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TupleOperation {
+    Write,
+    Delete,
+}
+
+impl TupleOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TupleOperation::Write => "write",
+            TupleOperation::Delete => "delete",
+        }
+    }
+}
+
+impl fmt::Display for TupleOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for TupleOperation {
+    type Err = ZanzibarError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "write" => Ok(TupleOperation::Write),
+            "delete" => Ok(TupleOperation::Delete),
+            other => Err(ZanzibarError::StorageError(format!("Unknown tuple operation: {}", other))),
+        }
+    }
+}
+
+/// A single entry in the tuple change feed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TupleChange {
+    pub tuple: Tuple,
+    pub operation: TupleOperation,
+    /// Revision the change was applied at; strictly increasing across the feed
+    pub revision: u64,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl TupleChange {
+    /// Consistency token for this change, usable with `Consistency::AtLeastAsFresh`
+    pub fn consistency_token(&self) -> ConsistencyToken {
+        ConsistencyToken::from_revision(self.revision)
+    }
+}
+
+/// Filter for the tuple change feed; `None` fields match anything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchFilter {
+    pub subject: Option<Subject>,
+    pub relation: Option<Relation>,
+    pub object_type: Option<String>,
+    pub object: Option<Object>,
+    pub operation: Option<TupleOperation>,
+}
+
+impl WatchFilter {
+    /// Match every change
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn with_subject(mut self, subject: Subject) -> Self {
+        self.subject = Some(subject);
+        self
+    }
+
+    pub fn with_relation(mut self, relation: Relation) -> Self {
+        self.relation = Some(relation);
+        self
+    }
+
+    pub fn with_object_type(mut self, object_type: &str) -> Self {
+        self.object_type = Some(object_type.to_string());
+        self
+    }
+
+    pub fn with_object(mut self, object: Object) -> Self {
+        self.object = Some(object);
+        self
+    }
+
+    pub fn with_operation(mut self, operation: TupleOperation) -> Self {
+        self.operation = Some(operation);
+        self
+    }
+
+    pub fn matches(&self, change: &TupleChange) -> bool {
+        let tuple = &change.tuple;
+        self.subject.as_ref().is_none_or(|s| *s == tuple.subject)
+            && self.relation.as_ref().is_none_or(|r| *r == tuple.relation)
+            && self.object_type.as_ref().is_none_or(|t| *t == tuple.object.object_type)
+            && self.object.as_ref().is_none_or(|o| *o == tuple.object)
+            && self.operation.is_none_or(|op| op == change.operation)
+    }
+}
+
+/// Consistency token for read-after-write consistency
+///
+/// Zanzibar calls these "zookies": an opaque token encoding the repository
+/// revision a write was applied at. Passing it back with a later check
+/// guarantees the evaluation sees at least that write.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ConsistencyToken {
     pub token: String,
     pub created_at: DateTime<Utc>,
+}
+
+impl ConsistencyToken {
+    const PREFIX: &'static str = "zk1_";
+
+    /// Create a token for the given repository revision
+    pub fn from_revision(revision: u64) -> Self {
+        Self {
+            token: format!("{}{:016x}", Self::PREFIX, revision),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Decode the repository revision encoded in this token
+    pub fn revision(&self) -> Result<u64, ZanzibarError> {
+        self.token
+            .strip_prefix(Self::PREFIX)
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+            .ok_or(ZanzibarError::InvalidConsistencyToken)
+    }
+}
+
+impl fmt::Display for ConsistencyToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.token)
+    }
+}
+
+/// Consistency requirement for a check or expand evaluation
+///
+/// The modes trade freshness against latency:
+/// - `MinimizeLatency` may answer from the engine cache. Cheapest, but a
+///   just-revoked grant can still pass until the cache entry is replaced.
+/// - `AtLeastAsFresh` guarantees the evaluation reflects every write up to the
+///   token's revision. Cached results older than the token are ignored and the
+///   engine waits (bounded) for a lagging repository to catch up.
+/// - `FullyConsistent` skips the cache entirely and evaluates against the
+///   repository's latest state. Most expensive; use for security-critical
+///   decisions such as revocation checks.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", content = "token", rename_all = "snake_case")]
+pub enum Consistency {
+    #[default]
+    MinimizeLatency,
+    AtLeastAsFresh(ConsistencyToken),
+    FullyConsistent,
 }
\ No newline at end of file
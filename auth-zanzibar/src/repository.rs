@@ -1,19 +1,26 @@
 use crate::{error::ZanzibarError, models::*};
 use async_trait::async_trait;
+use chrono::Utc;
 use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use tokio::sync::Mutex;
 
 /// Repository interface for storing relationship tuples
 #[async_trait]
 pub trait TupleRepository: Send + Sync {
     /// Write a single tuple
-    async fn write_tuple(&self, tuple: Tuple) -> Result<(), ZanzibarError>;
+    /// Returns the revision the write produced; rewriting an existing tuple
+    /// changes nothing and returns the revision current at the write
+    async fn write_tuple(&self, tuple: Tuple) -> Result<u64, ZanzibarError>;
     
     /// Delete a single tuple
-    async fn delete_tuple(&self, tuple: Tuple) -> Result<(), ZanzibarError>;
+    /// Returns the revision the delete produced, as for `write_tuple`
+    async fn delete_tuple(&self, tuple: Tuple) -> Result<u64, ZanzibarError>;
     
     /// Batch write operation (atomic)
-    async fn batch_write(&self, request: WriteRequest) -> Result<(), ZanzibarError>;
+    /// Returns the revision of the batch's last change, as for `write_tuple`
+    async fn batch_write(&self, request: WriteRequest) -> Result<u64, ZanzibarError>;
     
     /// Read tuples matching the given filter
     /// None values act as wildcards
@@ -24,25 +31,87 @@ pub trait TupleRepository: Send + Sync {
         object: Option<Object>,
     ) -> Result<Vec<Tuple>, ZanzibarError>;
     
+    /// Read tuples whose subject is any of `subjects`, matching each subject
+    /// exactly, usersets included
+    ///
+    /// This is the reverse-lookup primitive behind `list_objects`, called once
+    /// per level of group nesting. Implementations should answer it from an
+    /// index on the subject columns in a single round trip.
+    async fn read_tuples_for_subjects(&self, subjects: &[Subject]) -> Result<Vec<Tuple>, ZanzibarError> {
+        let mut tuples = Vec::new();
+        for subject in subjects {
+            let matching = self.read_tuples(Some(subject.clone()), None, None).await?;
+            tuples.extend(matching.into_iter().filter(|tuple| tuple.subject == *subject));
+        }
+        Ok(tuples)
+    }
+    
     /// Check if a specific tuple exists
     async fn tuple_exists(&self, tuple: &Tuple) -> Result<bool, ZanzibarError>;
+    
+    /// Latest revision visible to this repository
+    /// Every write or delete advances the revision; reads from a lagging
+    /// replica may report an older value than the primary
+    async fn current_revision(&self) -> Result<u64, ZanzibarError>;
+    
+    /// Read logged tuple changes with revision greater than `after_revision`,
+    /// oldest first, at most `limit` entries
+    /// This is the cursor primitive behind `watch_tuples`
+    async fn read_changes(
+        &self,
+        after_revision: u64,
+        limit: usize,
+    ) -> Result<Vec<TupleChange>, ZanzibarError>;
 }
 
 /// In-memory tuple repository for testing and development
 pub struct InMemoryTupleRepository {
     tuples: Arc<DashMap<String, Tuple>>,
+    revision: AtomicU64,
+    /// Change log; the lock also serializes mutations so revisions are
+    /// appended in order
+    changes: Mutex<Vec<TupleChange>>,
 }
 
 impl InMemoryTupleRepository {
     pub fn new() -> Self {
         Self {
             tuples: Arc::new(DashMap::new()),
+            revision: AtomicU64::new(0),
+            changes: Mutex::new(Vec::new()),
         }
     }
     
     fn tuple_key(tuple: &Tuple) -> String {
         format!("{}_{}_{}", tuple.subject, tuple.relation, tuple.object)
     }
+    
+    fn record_change(&self, changes: &mut Vec<TupleChange>, tuple: Tuple, operation: TupleOperation) {
+        let revision = self.revision.fetch_add(1, Ordering::SeqCst) + 1;
+        changes.push(TupleChange {
+            tuple,
+            operation,
+            revision,
+            changed_at: Utc::now(),
+        });
+    }
+    
+    /// Insert `tuple`, logging it unless it already existed; the caller
+    /// holds the change log lock
+    fn insert_locked(&self, changes: &mut Vec<TupleChange>, tuple: Tuple) {
+        // Rewriting an existing tuple is a no-op and is not logged
+        if self.tuples.insert(Self::tuple_key(&tuple), tuple.clone()).is_none() {
+            self.record_change(changes, tuple, TupleOperation::Write);
+        }
+    }
+    
+    /// Remove `tuple`, logging it if it existed; the caller holds the change
+    /// log lock
+    fn remove_locked(&self, changes: &mut Vec<TupleChange>, tuple: &Tuple) {
+        if let Some((_, deleted)) = self.tuples.remove(&Self::tuple_key(tuple)) {
+            self.record_change(changes, deleted, TupleOperation::Delete);
+        }
+    }
 }
 
 impl Default for InMemoryTupleRepository {
@@ -53,30 +122,28 @@ impl Default for InMemoryTupleRepository {
 
 #[async_trait]
 impl TupleRepository for InMemoryTupleRepository {
-    async fn write_tuple(&self, tuple: Tuple) -> Result<(), ZanzibarError> {
-        let key = Self::tuple_key(&tuple);
-        self.tuples.insert(key, tuple);
-        Ok(())
+    async fn write_tuple(&self, tuple: Tuple) -> Result<u64, ZanzibarError> {
+        let mut changes = self.changes.lock().await;
+        self.insert_locked(&mut changes, tuple);
+        // Only mutations under the lock advance the revision
+        Ok(self.revision.load(Ordering::SeqCst))
     }
     
-    async fn delete_tuple(&self, tuple: Tuple) -> Result<(), ZanzibarError> {
-        let key = Self::tuple_key(&tuple);
-        self.tuples.remove(&key);
-        Ok(())
+    async fn delete_tuple(&self, tuple: Tuple) -> Result<u64, ZanzibarError> {
+        let mut changes = self.changes.lock().await;
+        self.remove_locked(&mut changes, &tuple);
+        Ok(self.revision.load(Ordering::SeqCst))
     }
     
-    async fn batch_write(&self, request: WriteRequest) -> Result<(), ZanzibarError> {
-        // Write all tuples
+    async fn batch_write(&self, request: WriteRequest) -> Result<u64, ZanzibarError> {
+        let mut changes = self.changes.lock().await;
         for tuple in request.writes {
-            self.write_tuple(tuple).await?;
+            self.insert_locked(&mut changes, tuple);
         }
-        
-        // Delete tuples
-        for tuple in request.deletes {
-            self.delete_tuple(tuple).await?;
+        for tuple in &request.deletes {
+            self.remove_locked(&mut changes, tuple);
         }
-        
-        Ok(())
+        Ok(self.revision.load(Ordering::SeqCst))
     }
     
     async fn read_tuples(
@@ -120,6 +187,22 @@ impl TupleRepository for InMemoryTupleRepository {
         let key = Self::tuple_key(tuple);
         Ok(self.tuples.contains_key(&key))
     }
+    
+    async fn current_revision(&self) -> Result<u64, ZanzibarError> {
+        Ok(self.revision.load(Ordering::SeqCst))
+    }
+    
+    async fn read_changes(
+        &self,
+        after_revision: u64,
+        limit: usize,
+    ) -> Result<Vec<TupleChange>, ZanzibarError> {
+        let changes = self.changes.lock().await;
+        
+        // Revisions are appended in increasing order
+        let start = changes.partition_point(|change| change.revision <= after_revision);
+        Ok(changes.iter().skip(start).take(limit).cloned().collect())
+    }
 }
 
 #[cfg(test)]
@@ -137,7 +220,9 @@ mod tests {
         );
         
         // Write tuple
-        repo.write_tuple(tuple.clone()).await.unwrap();
+        assert_eq!(repo.write_tuple(tuple.clone()).await.unwrap(), 1);
+        // Rewriting it changes nothing
+        assert_eq!(repo.write_tuple(tuple.clone()).await.unwrap(), 1);
         
         // Check it exists
         assert!(repo.tuple_exists(&tuple).await.unwrap());
@@ -151,11 +236,18 @@ mod tests {
         assert_eq!(tuples.len(), 1);
         
         // Delete tuple
-        repo.delete_tuple(tuple.clone()).await.unwrap();
+        assert_eq!(repo.delete_tuple(tuple.clone()).await.unwrap(), 2);
         assert!(!repo.tuple_exists(&tuple).await.unwrap());
+        
+        // Both the write and the delete advanced the revision
+        assert_eq!(repo.current_revision().await.unwrap(), 2);
     }
 }
 
 // PostgreSQL repository implementation
 pub mod postgres;
-pub use postgres::PostgresTupleRepository;
\ No newline at end of file
+pub use postgres::PostgresTupleRepository;
+
+// Tuple change feed
+pub mod watch;
+pub use watch::{watch_tuples, TupleWatch};
\ No newline at end of file
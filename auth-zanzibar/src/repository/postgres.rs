@@ -2,9 +2,12 @@
 //! 
 //! This implementation stores authorization tuples in PostgreSQL with:
 //! - Multi-tenant isolation via organization_id
-//! - Optimized indexes for check/expand operations
+//! - Optimized indexes for check/expand operations and subject-side lookups
 //! - Time-based expiration support
 //! - Batch operations for performance
+//! - Monotonic revisions (`zanzibar_revision_seq`) backing consistency tokens
+//! - A change log (`zanzibar_tuple_changes`) for watchers, written in the same
+//!   transaction as the tuple mutation
 
 use crate::{
     error::ZanzibarError,
@@ -13,7 +16,7 @@ use crate::{
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::{PgPool, Row};
+use sqlx::{PgConnection, PgPool, Row};
 use tracing::{debug, info};
 use uuid::Uuid;
 
@@ -36,17 +39,25 @@ impl PostgresTupleRepository {
         
         Ok(Self::new(pool))
     }
-}
 
-#[async_trait]
-impl TupleRepository for PostgresTupleRepository {
-    async fn write_tuple(&self, tuple: Tuple) -> Result<(), ZanzibarError> {
-        debug!("Writing tuple to PostgreSQL: {}", tuple);
+    /// Serialize tuple mutations for the rest of the transaction so change
+    /// log revisions commit in order and pollers never skip a late commit
+    async fn lock_changes(conn: &mut PgConnection) -> Result<(), ZanzibarError> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('zanzibar_tuple_changes'))")
+            .execute(conn)
+            .await
+            .map_err(|e| ZanzibarError::StorageError(format!("Failed to lock change log: {}", e)))?;
 
-        // Use a default organization ID if none is set (for tests and single-tenant setups)
-        let org_id = Some(Uuid::nil());
+        Ok(())
+    }
 
-        sqlx::query(
+    /// Insert a tuple; returns its revision, or `None` if it already existed
+    async fn insert_tuple(
+        conn: &mut PgConnection,
+        org_id: Option<Uuid>,
+        tuple: &Tuple,
+    ) -> Result<Option<i64>, ZanzibarError> {
+        sqlx::query_scalar::<_, i64>(
             r#"
             INSERT INTO zanzibar_tuples (
                 organization_id,
@@ -62,6 +73,7 @@ impl TupleRepository for PostgresTupleRepository {
                 relation_name,
                 object_namespace, object_type, object_id
             ) DO NOTHING
+            RETURNING revision
             "#,
         )
         .bind(org_id) // organization_id - use default for tests/single-tenant
@@ -75,18 +87,17 @@ impl TupleRepository for PostgresTupleRepository {
         .bind(&tuple.object.object_id)
         .bind(tuple.created_at)
         .bind(None::<DateTime<Utc>>) // expires_at - future: support temporal tuples
-        .execute(&self.pool)
+        .fetch_optional(conn)
         .await
-        .map_err(|e| ZanzibarError::StorageError(format!("Failed to write tuple: {}", e)))?;
-
-        info!("Tuple written successfully");
-        Ok(())
+        .map_err(|e| ZanzibarError::StorageError(format!("Failed to write tuple: {}", e)))
     }
 
-    async fn delete_tuple(&self, tuple: Tuple) -> Result<(), ZanzibarError> {
-        debug!("Deleting tuple from PostgreSQL: {}", tuple);
-
-        sqlx::query(
+    /// Delete a tuple; returns the deleted row's creation time, if any
+    async fn remove_tuple(
+        conn: &mut PgConnection,
+        tuple: &Tuple,
+    ) -> Result<Option<DateTime<Utc>>, ZanzibarError> {
+        sqlx::query_scalar::<_, DateTime<Utc>>(
             r#"
             DELETE FROM zanzibar_tuples
             WHERE subject_namespace = $1
@@ -97,6 +108,7 @@ impl TupleRepository for PostgresTupleRepository {
               AND object_namespace = $6
               AND object_type = $7
               AND object_id = $8
+            RETURNING created_at
             "#,
         )
         .bind(&tuple.subject.namespace)
@@ -107,17 +119,76 @@ impl TupleRepository for PostgresTupleRepository {
         .bind(&tuple.object.namespace)
         .bind(&tuple.object.object_type)
         .bind(&tuple.object.object_id)
-        .execute(&self.pool)
+        .fetch_all(conn)
         .await
-        .map_err(|e| ZanzibarError::StorageError(format!("Failed to delete tuple: {}", e)))?;
+        .map(|rows| rows.into_iter().next())
+        .map_err(|e| ZanzibarError::StorageError(format!("Failed to delete tuple: {}", e)))
+    }
 
-        info!("Tuple deleted successfully");
-        Ok(())
+    /// Append to the change log, returning the change's revision. Writes
+    /// reuse the tuple row's revision; deletes (revision `None`) draw a
+    /// fresh one from the sequence.
+    async fn record_change(
+        conn: &mut PgConnection,
+        org_id: Option<Uuid>,
+        tuple: &Tuple,
+        operation: TupleOperation,
+        revision: Option<i64>,
+    ) -> Result<i64, ZanzibarError> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO zanzibar_tuple_changes (
+                revision, operation, organization_id,
+                subject_namespace, subject_type, subject_id, subject_relation,
+                relation_name,
+                object_namespace, object_type, object_id,
+                tuple_created_at
+            ) VALUES (
+                COALESCE($1, nextval('zanzibar_revision_seq')), $2, $3,
+                $4, $5, $6, $7, $8, $9, $10, $11, $12
+            )
+            RETURNING revision
+            "#,
+        )
+        .bind(revision)
+        .bind(operation.as_str())
+        .bind(org_id)
+        .bind(&tuple.subject.namespace)
+        .bind(&tuple.subject.object_type)
+        .bind(&tuple.subject.object_id)
+        .bind(&tuple.subject.relation)
+        .bind(&tuple.relation.name)
+        .bind(&tuple.object.namespace)
+        .bind(&tuple.object.object_type)
+        .bind(&tuple.object.object_id)
+        .bind(tuple.created_at)
+        .fetch_one(conn)
+        .await
+        .map_err(|e| ZanzibarError::StorageError(format!("Failed to record tuple change: {}", e)))
     }
 
-    async fn batch_write(&self, request: WriteRequest) -> Result<(), ZanzibarError> {
-        debug!("Batch write: {} writes, {} deletes", request.writes.len(), request.deletes.len());
+    /// Latest revision drawn from the sequence, 0 before the first
+    async fn revision_on(conn: &mut PgConnection) -> Result<u64, ZanzibarError> {
+        let (last_value, is_called) = sqlx::query_as::<_, (i64, bool)>(
+            "SELECT last_value, is_called FROM zanzibar_revision_seq",
+        )
+        .fetch_one(conn)
+        .await
+        .map_err(|e| ZanzibarError::StorageError(format!("Failed to read revision: {}", e)))?;
+
+        // A fresh sequence reports last_value = 1 before nextval has ever run
+        if !is_called {
+            return Ok(0);
+        }
 
+        u64::try_from(last_value)
+            .map_err(|_| ZanzibarError::StorageError(format!("Negative revision: {}", last_value)))
+    }
+
+    /// Apply writes and deletes in one transaction, logging each effective
+    /// change. Returns the revision of the last change, or the revision
+    /// current under the change log lock if nothing changed.
+    async fn apply(&self, writes: Vec<Tuple>, deletes: Vec<Tuple>) -> Result<u64, ZanzibarError> {
         // Use a default organization ID if none is set (for tests and single-tenant setups)
         let org_id = Some(Uuid::nil());
 
@@ -127,75 +198,67 @@ impl TupleRepository for PostgresTupleRepository {
             .await
             .map_err(|e| ZanzibarError::StorageError(format!("Failed to start transaction: {}", e)))?;
 
-        // Write all tuples
-        for tuple in request.writes {
-            sqlx::query(
-                r#"
-                INSERT INTO zanzibar_tuples (
-                    organization_id,
-                    subject_namespace, subject_type, subject_id, subject_relation,
-                    relation_name,
-                    object_namespace, object_type, object_id,
-                    created_at
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-                ON CONFLICT (
-                    organization_id, 
-                    subject_namespace, subject_type, subject_id, subject_relation,
-                    relation_name,
-                    object_namespace, object_type, object_id
-                ) DO NOTHING
-                "#,
-            )
-            .bind(org_id)
-            .bind(&tuple.subject.namespace)
-            .bind(&tuple.subject.object_type)
-            .bind(&tuple.subject.object_id)
-            .bind(&tuple.subject.relation)
-            .bind(&tuple.relation.name)
-            .bind(&tuple.object.namespace)
-            .bind(&tuple.object.object_type)
-            .bind(&tuple.object.object_id)
-            .bind(tuple.created_at)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| ZanzibarError::StorageError(format!("Failed to write tuple: {}", e)))?;
+        Self::lock_changes(&mut tx).await?;
+
+        let mut last_revision = None;
+        for tuple in &writes {
+            // Re-writing an existing tuple is a no-op and is not logged
+            if let Some(revision) = Self::insert_tuple(&mut tx, org_id, tuple).await? {
+                let revision = Self::record_change(&mut tx, org_id, tuple, TupleOperation::Write, Some(revision)).await?;
+                last_revision = last_revision.max(Some(revision));
+            }
         }
 
-        // Delete tuples
-        for tuple in request.deletes {
-            sqlx::query(
-                r#"
-                DELETE FROM zanzibar_tuples
-                WHERE subject_namespace = $1
-                  AND subject_type = $2
-                  AND subject_id = $3
-                  AND subject_relation IS NOT DISTINCT FROM $4
-                  AND relation_name = $5
-                  AND object_namespace = $6
-                  AND object_type = $7
-                  AND object_id = $8
-                "#,
-            )
-            .bind(&tuple.subject.namespace)
-            .bind(&tuple.subject.object_type)
-            .bind(&tuple.subject.object_id)
-            .bind(&tuple.subject.relation)
-            .bind(&tuple.relation.name)
-            .bind(&tuple.object.namespace)
-            .bind(&tuple.object.object_type)
-            .bind(&tuple.object.object_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| ZanzibarError::StorageError(format!("Failed to delete tuple: {}", e)))?;
+        for tuple in deletes {
+            if let Some(created_at) = Self::remove_tuple(&mut tx, &tuple).await? {
+                let deleted = Tuple { created_at, ..tuple };
+                let revision = Self::record_change(&mut tx, org_id, &deleted, TupleOperation::Delete, None).await?;
+                last_revision = last_revision.max(Some(revision));
+            }
         }
 
+        let revision = match last_revision {
+            Some(revision) => u64::try_from(revision)
+                .map_err(|_| ZanzibarError::StorageError(format!("Negative revision: {}", revision)))?,
+            None => Self::revision_on(&mut tx).await?,
+        };
+
         // Commit transaction
         tx.commit()
             .await
             .map_err(|e| ZanzibarError::StorageError(format!("Failed to commit transaction: {}", e)))?;
 
+        Ok(revision)
+    }
+}
+
+#[async_trait]
+impl TupleRepository for PostgresTupleRepository {
+    async fn write_tuple(&self, tuple: Tuple) -> Result<u64, ZanzibarError> {
+        debug!("Writing tuple to PostgreSQL: {}", tuple);
+
+        let revision = self.apply(vec![tuple], Vec::new()).await?;
+
+        info!("Tuple written successfully");
+        Ok(revision)
+    }
+
+    async fn delete_tuple(&self, tuple: Tuple) -> Result<u64, ZanzibarError> {
+        debug!("Deleting tuple from PostgreSQL: {}", tuple);
+
+        let revision = self.apply(Vec::new(), vec![tuple]).await?;
+
+        info!("Tuple deleted successfully");
+        Ok(revision)
+    }
+
+    async fn batch_write(&self, request: WriteRequest) -> Result<u64, ZanzibarError> {
+        debug!("Batch write: {} writes, {} deletes", request.writes.len(), request.deletes.len());
+
+        let revision = self.apply(request.writes, request.deletes).await?;
+
         info!("Batch write completed successfully");
-        Ok(())
+        Ok(revision)
     }
 
     async fn read_tuples(
@@ -289,6 +352,60 @@ impl TupleRepository for PostgresTupleRepository {
         Ok(tuples)
     }
 
+    async fn read_tuples_for_subjects(&self, subjects: &[Subject]) -> Result<Vec<Tuple>, ZanzibarError> {
+        if subjects.is_empty() {
+            return Ok(Vec::new());
+        }
+        debug!("Reading tuples for {} subjects", subjects.len());
+
+        // One round trip per call: the subjects are joined in as a set and
+        // matched through idx_zanzibar_subject_lookup
+        let rows = sqlx::query(
+            r#"
+            SELECT t.subject_namespace, t.subject_type, t.subject_id, t.subject_relation,
+                   t.relation_name,
+                   t.object_namespace, t.object_type, t.object_id,
+                   t.created_at
+            FROM zanzibar_tuples t
+            JOIN UNNEST($1::text[], $2::text[], $3::text[], $4::text[])
+                AS s(namespace, object_type, object_id, relation)
+              ON t.subject_namespace = s.namespace
+             AND t.subject_type = s.object_type
+             AND t.subject_id = s.object_id
+             AND t.subject_relation IS NOT DISTINCT FROM s.relation
+            WHERE (t.expires_at IS NULL OR t.expires_at > NOW())
+            "#,
+        )
+        .bind(subjects.iter().map(|s| s.namespace.clone()).collect::<Vec<_>>())
+        .bind(subjects.iter().map(|s| s.object_type.clone()).collect::<Vec<_>>())
+        .bind(subjects.iter().map(|s| s.object_id.clone()).collect::<Vec<_>>())
+        .bind(subjects.iter().map(|s| s.relation.clone()).collect::<Vec<_>>())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ZanzibarError::StorageError(format!("Failed to read tuples by subject: {}", e)))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Tuple {
+                subject: Subject {
+                    namespace: row.get("subject_namespace"),
+                    object_type: row.get("subject_type"),
+                    object_id: row.get("subject_id"),
+                    relation: row.get("subject_relation"),
+                },
+                relation: Relation {
+                    name: row.get("relation_name"),
+                },
+                object: Object {
+                    namespace: row.get("object_namespace"),
+                    object_type: row.get("object_type"),
+                    object_id: row.get("object_id"),
+                },
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
     async fn tuple_exists(&self, tuple: &Tuple) -> Result<bool, ZanzibarError> {
         let result = sqlx::query_scalar::<_, bool>(
             r#"
@@ -320,6 +437,74 @@ impl TupleRepository for PostgresTupleRepository {
 
         Ok(result)
     }
+
+    async fn current_revision(&self) -> Result<u64, ZanzibarError> {
+        let mut conn = self.pool
+            .acquire()
+            .await
+            .map_err(|e| ZanzibarError::StorageError(format!("Failed to read revision: {}", e)))?;
+        Self::revision_on(&mut conn).await
+    }
+
+    async fn read_changes(
+        &self,
+        after_revision: u64,
+        limit: usize,
+    ) -> Result<Vec<TupleChange>, ZanzibarError> {
+        let after = i64::try_from(after_revision)
+            .map_err(|_| ZanzibarError::InvalidConsistencyToken)?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT revision, operation,
+                   subject_namespace, subject_type, subject_id, subject_relation,
+                   relation_name,
+                   object_namespace, object_type, object_id,
+                   tuple_created_at, changed_at
+            FROM zanzibar_tuple_changes
+            WHERE revision > $1
+            ORDER BY revision
+            LIMIT $2
+            "#,
+        )
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ZanzibarError::StorageError(format!("Failed to read tuple changes: {}", e)))?;
+
+        rows.iter()
+            .map(|row| {
+                let revision: i64 = row.get("revision");
+                let operation: String = row.get("operation");
+                Ok(TupleChange {
+                    tuple: Tuple {
+                        subject: Subject {
+                            namespace: row.get("subject_namespace"),
+                            object_type: row.get("subject_type"),
+                            object_id: row.get("subject_id"),
+                            relation: row.get("subject_relation"),
+                        },
+                        relation: Relation {
+                            name: row.get("relation_name"),
+                        },
+                        object: Object {
+                            namespace: row.get("object_namespace"),
+                            object_type: row.get("object_type"),
+                            object_id: row.get("object_id"),
+                        },
+                        created_at: row.get("tuple_created_at"),
+                    },
+                    operation: operation.parse()?,
+                    revision: u64::try_from(revision).map_err(|_| {
+                        ZanzibarError::StorageError(format!("Negative revision: {}", revision))
+                    })?,
+                    changed_at: row.get("changed_at"),
+                })
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
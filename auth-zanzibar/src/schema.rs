@@ -1,6 +1,7 @@
 use crate::{error::ZanzibarError, models::*};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use thiserror::Error;
 
 /// Permission schema definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,21 +35,25 @@ impl Schema {
                     name: "owner".to_string(),
                     inherits_from: None,
                     description: "Full access to patient record".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "provider".to_string(),
                     inherits_from: Some("viewer".to_string()),
                     description: "Healthcare provider with treatment access".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "viewer".to_string(),
                     inherits_from: None,
                     description: "Read-only access to patient record".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "read_phi".to_string(),
                     inherits_from: None,
                     description: "Permission to read PHI fields".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -61,16 +66,19 @@ impl Schema {
                     name: "owner".to_string(),
                     inherits_from: None,
                     description: "Full control over document".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "editor".to_string(),
                     inherits_from: Some("viewer".to_string()),
                     description: "Can edit document".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "viewer".to_string(),
                     inherits_from: None,
                     description: "Can view document".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -83,11 +91,13 @@ impl Schema {
                     name: "admin".to_string(),
                     inherits_from: Some("member".to_string()),
                     description: "Organization administrator".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "member".to_string(),
                     inherits_from: None,
                     description: "Organization member".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -100,11 +110,13 @@ impl Schema {
                     name: "member".to_string(),
                     inherits_from: None,
                     description: "Member of this role".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "can_elevate".to_string(),
                     inherits_from: None,
                     description: "Can request elevated/break-glass access".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -117,21 +129,25 @@ impl Schema {
                     name: "owner".to_string(),
                     inherits_from: Some("editor".to_string()),
                     description: "Full ownership of patient record".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "editor".to_string(),
                     inherits_from: Some("viewer".to_string()),
                     description: "Can edit patient record".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "viewer".to_string(),
                     inherits_from: None,
                     description: "Can view patient record".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "viewers".to_string(), // Userset relation
                     inherits_from: None,
                     description: "Set of viewers for this patient".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -144,11 +160,13 @@ impl Schema {
                     name: "owner".to_string(),
                     inherits_from: Some("viewer".to_string()),
                     description: "Owner of lab report".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "viewer".to_string(),
                     inherits_from: None,
                     description: "Can view lab report".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -161,11 +179,13 @@ impl Schema {
                     name: "owner".to_string(),
                     inherits_from: Some("viewer".to_string()),
                     description: "Owner of billing record".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "viewer".to_string(),
                     inherits_from: None,
                     description: "Can view billing record".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -178,11 +198,13 @@ impl Schema {
                     name: "admin".to_string(),
                     inherits_from: Some("member".to_string()),
                     description: "Ward administrator".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "member".to_string(),
                     inherits_from: None,
                     description: "Member of this ward".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -195,11 +217,13 @@ impl Schema {
                     name: "principal_investigator".to_string(),
                     inherits_from: Some("member".to_string()),
                     description: "Principal investigator of study".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
                 RelationDefinition {
                     name: "member".to_string(),
                     inherits_from: None,
                     description: "Researcher in this study".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -212,6 +236,7 @@ impl Schema {
                     name: "delegate".to_string(),
                     inherits_from: None,
                     description: "Temporary delegation of access".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -224,6 +249,27 @@ impl Schema {
                     name: "member".to_string(),
                     inherits_from: None,
                     description: "Member of this group".to_string(),
+                    allowed_subjects: Vec::new(),
+                },
+            ],
+        });
+        
+        // Secret paths (secrets service policies; object ids are a secret
+        // key or a prefix pattern such as "kv/prod/*")
+        namespaces.insert("secret_path".to_string(), NamespaceDefinition {
+            name: "secret_path".to_string(),
+            relations: vec![
+                RelationDefinition {
+                    name: "writer".to_string(),
+                    inherits_from: Some("reader".to_string()),
+                    description: "Create, update, rotate and delete secrets under this path".to_string(),
+                    allowed_subjects: Vec::new(),
+                },
+                RelationDefinition {
+                    name: "reader".to_string(),
+                    inherits_from: None,
+                    description: "Read and list secrets under this path".to_string(),
+                    allowed_subjects: Vec::new(),
                 },
             ],
         });
@@ -233,19 +279,37 @@ impl Schema {
     
     /// Validate that a tuple conforms to the schema
     pub fn validate_tuple(&self, tuple: &Tuple) -> Result<(), ZanzibarError> {
+        self.check_tuple(tuple)
+            .map_err(|violation| ZanzibarError::InvalidTuple(violation.to_string()))
+    }
+    
+    /// The schema rule a tuple breaks, if any
+    pub fn check_tuple(&self, tuple: &Tuple) -> Result<(), SchemaViolation> {
         let namespace = self.namespaces.get(&tuple.object.object_type)
-            .ok_or_else(|| ZanzibarError::InvalidTuple(
-                format!("Unknown object type: {}", tuple.object.object_type)
-            ))?;
+            .ok_or_else(|| SchemaViolation::UnknownObjectType {
+                object_type: tuple.object.object_type.clone(),
+            })?;
         
-        let relation_exists = namespace.relations.iter()
-            .any(|r| r.name == tuple.relation.name);
+        let relation = namespace.relations.iter()
+            .find(|r| r.name == tuple.relation.name)
+            .ok_or_else(|| SchemaViolation::UnknownRelation {
+                object_type: tuple.object.object_type.clone(),
+                relation: tuple.relation.name.clone(),
+            })?;
         
-        if !relation_exists {
-            return Err(ZanzibarError::InvalidTuple(
-                format!("Unknown relation '{}' for object type '{}'", 
-                    tuple.relation.name, tuple.object.object_type)
-            ));
+        let subject_type = match tuple.subject.relation.as_deref() {
+            Some(userset_relation) if !userset_relation.is_empty() => {
+                format!("{}#{}", tuple.subject.object_type, userset_relation)
+            }
+            _ => tuple.subject.object_type.clone(),
+        };
+        if !relation.allowed_subjects.is_empty() && !relation.allowed_subjects.contains(&subject_type) {
+            return Err(SchemaViolation::SubjectNotAllowed {
+                object_type: tuple.object.object_type.clone(),
+                relation: tuple.relation.name.clone(),
+                subject_type,
+                allowed: relation.allowed_subjects.clone(),
+            });
         }
         
         Ok(())
@@ -301,6 +365,29 @@ pub struct RelationDefinition {
     pub name: String,
     pub inherits_from: Option<String>,
     pub description: String,
+    /// Subject types that may hold this relation: an object type such as
+    /// "user", or a userset such as "role#member". Empty allows any subject.
+    #[serde(default)]
+    pub allowed_subjects: Vec<String>,
+}
+
+/// Why a tuple does not conform to the schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Error)]
+#[serde(tag = "violation", rename_all = "snake_case")]
+pub enum SchemaViolation {
+    #[error("Unknown object type: {object_type}")]
+    UnknownObjectType { object_type: String },
+    
+    #[error("Unknown relation '{relation}' for object type '{object_type}'")]
+    UnknownRelation { object_type: String, relation: String },
+    
+    #[error("Subject type '{subject_type}' may not hold '{relation}' on '{object_type}' (allowed: {})", allowed.join(", "))]
+    SubjectNotAllowed {
+        object_type: String,
+        relation: String,
+        subject_type: String,
+        allowed: Vec<String>,
+    },
 }
 
 /// Permission definition with inheritance
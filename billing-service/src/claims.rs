@@ -1,5 +1,7 @@
+use crate::coding::{self, is_icd10_cm, normalize_code, CodeReference, ModifierIndicator, ProcedureCodeSystem, NCCI_MODIFIERS};
 use crate::models::{Claim, ClaimType};
 use crate::error::BillingResult;
+use serde::{Deserialize, Serialize};
 
 /// Claims generator for different claim formats
 pub struct ClaimsGenerator;
@@ -27,3 +29,231 @@ impl ClaimsGenerator {
     }
 }
 
+
+/// How an issue affects a claim
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    /// Likely to be questioned, but the claim can go out
+    Warn,
+    /// The payer will reject the claim; fix it before submission
+    Reject,
+}
+
+/// Which check found an issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    InvalidFormat,
+    UnknownCode,
+    MissingDiagnosis,
+    MedicalNecessity,
+    NcciEdit,
+    /// A reference table is not loaded, so a check was skipped
+    ReferenceUnavailable,
+}
+
+/// A problem with a claim's codes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaimIssue {
+    pub severity: IssueSeverity,
+    pub kind: IssueKind,
+    /// Index into `Claim::charges` of the line the issue is on
+    pub line: Option<usize>,
+    /// The code at fault
+    pub code: Option<String>,
+    pub message: String,
+}
+
+/// Outcome of [`validate`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaimValidation {
+    /// Release of the reference tables the claim was checked against
+    pub reference_version: String,
+    pub issues: Vec<ClaimIssue>,
+}
+
+impl ClaimValidation {
+    pub fn is_rejected(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity == IssueSeverity::Reject)
+    }
+
+    pub fn rejections(&self) -> impl Iterator<Item = &ClaimIssue> {
+        self.issues.iter().filter(|issue| issue.severity == IssueSeverity::Reject)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ClaimIssue> {
+        self.issues.iter().filter(|issue| issue.severity == IssueSeverity::Warn)
+    }
+}
+
+/// Check a claim's procedure and diagnosis codes against the installed
+/// reference (see [`coding::install_reference`])
+///
+/// Every issue is returned, not just the first: code format and existence,
+/// a diagnosis on each line, medical necessity where the reference has
+/// coverage data for the procedure, and NCCI procedure-to-procedure edits
+/// between lines with the same date of service.
+pub fn validate(claim: &Claim) -> ClaimValidation {
+    validate_with(claim, &coding::reference())
+}
+
+/// [`validate`] against a specific reference
+pub fn validate_with(claim: &Claim, reference: &CodeReference) -> ClaimValidation {
+    let mut issues = Vec::new();
+    let mut issue = |severity, kind, line, code: Option<&str>, message: String| {
+        issues.push(ClaimIssue {
+            severity,
+            kind,
+            line,
+            code: code.map(str::to_string),
+            message,
+        });
+    };
+
+    if !reference.has_procedures() {
+        issue(
+            IssueSeverity::Warn,
+            IssueKind::ReferenceUnavailable,
+            None,
+            None,
+            "No procedure code reference is loaded; CPT/HCPCS codes were checked for format only".to_string(),
+        );
+    }
+    if !reference.has_diagnoses() {
+        issue(
+            IssueSeverity::Warn,
+            IssueKind::ReferenceUnavailable,
+            None,
+            None,
+            "No diagnosis code reference is loaded; ICD-10 codes were checked for format only".to_string(),
+        );
+    }
+
+    for (line, charge) in claim.charges.iter().enumerate() {
+        let code = normalize_code(&charge.service_code);
+        match ProcedureCodeSystem::classify(&code) {
+            None => issue(
+                IssueSeverity::Reject,
+                IssueKind::InvalidFormat,
+                Some(line),
+                Some(&code),
+                format!("'{}' is not a CPT, HCPCS or ICD-10-PCS code", charge.service_code),
+            ),
+            Some(ProcedureCodeSystem::Icd10Pcs) if !matches!(claim.claim_type, ClaimType::Institutional) => issue(
+                IssueSeverity::Reject,
+                IssueKind::InvalidFormat,
+                Some(line),
+                Some(&code),
+                format!("ICD-10-PCS code {code} is only billable on institutional claims"),
+            ),
+            Some(system @ (ProcedureCodeSystem::Cpt | ProcedureCodeSystem::Hcpcs))
+                if reference.has_procedures() && reference.procedure(&code).is_none() =>
+            {
+                issue(
+                    IssueSeverity::Reject,
+                    IssueKind::UnknownCode,
+                    Some(line),
+                    Some(&code),
+                    format!("{} code {code} is not in the {} code set", system.as_str(), reference.version),
+                )
+            }
+            Some(_) => {}
+        }
+
+        for modifier in &charge.modifiers {
+            let valid = modifier.len() == 2 && modifier.bytes().all(|byte| byte.is_ascii_alphanumeric());
+            if !valid {
+                issue(
+                    IssueSeverity::Reject,
+                    IssueKind::InvalidFormat,
+                    Some(line),
+                    Some(modifier),
+                    format!("'{modifier}' on {code} is not a two-character modifier"),
+                );
+            }
+        }
+
+        if charge.diagnosis_codes.is_empty() {
+            issue(
+                IssueSeverity::Reject,
+                IssueKind::MissingDiagnosis,
+                Some(line),
+                Some(&code),
+                format!("{code} has no diagnosis code"),
+            );
+        }
+        for diagnosis in &charge.diagnosis_codes {
+            let normalized = normalize_code(diagnosis);
+            if !is_icd10_cm(&normalized) {
+                issue(
+                    IssueSeverity::Reject,
+                    IssueKind::InvalidFormat,
+                    Some(line),
+                    Some(diagnosis),
+                    format!("'{diagnosis}' is not an ICD-10-CM code"),
+                );
+            } else if reference.has_diagnoses() && reference.diagnosis(&normalized).is_none() {
+                issue(
+                    IssueSeverity::Reject,
+                    IssueKind::UnknownCode,
+                    Some(line),
+                    Some(diagnosis),
+                    format!("ICD-10-CM code {diagnosis} is not in the {} code set", reference.version),
+                );
+            }
+        }
+
+        if !charge.diagnosis_codes.is_empty() && reference.supports(&code, &charge.diagnosis_codes) == Some(false) {
+            issue(
+                IssueSeverity::Reject,
+                IssueKind::MedicalNecessity,
+                Some(line),
+                Some(&code),
+                format!(
+                    "None of the diagnoses {} support medical necessity for {code}",
+                    charge.diagnosis_codes.join(", ")
+                ),
+            );
+        }
+    }
+
+    for (first, column_one) in claim.charges.iter().enumerate() {
+        for (second, column_two) in claim.charges.iter().enumerate() {
+            let date = column_one.date_of_service();
+            if first == second || date != column_two.date_of_service() {
+                continue;
+            }
+            let Some(edit) = reference.ptp_edit(&column_one.service_code, &column_two.service_code, date) else {
+                continue;
+            };
+            let bypassed = column_two
+                .modifiers
+                .iter()
+                .any(|modifier| NCCI_MODIFIERS.contains(&modifier.to_ascii_uppercase().as_str()));
+            let message = match edit.modifier_indicator {
+                ModifierIndicator::NotAllowed => format!(
+                    "NCCI edit: {} cannot be billed with {} on the same date of service",
+                    edit.column_two, edit.column_one
+                ),
+                ModifierIndicator::Allowed if !bypassed => format!(
+                    "NCCI edit: {} cannot be billed with {} on the same date of service without a modifier such as 59 or XU",
+                    edit.column_two, edit.column_one
+                ),
+                ModifierIndicator::Allowed | ModifierIndicator::NotApplicable => continue,
+            };
+            issue(
+                IssueSeverity::Reject,
+                IssueKind::NcciEdit,
+                Some(second),
+                Some(&edit.column_two),
+                message,
+            );
+        }
+    }
+
+    ClaimValidation {
+        reference_version: reference.version.clone(),
+        issues,
+    }
+}
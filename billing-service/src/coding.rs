@@ -0,0 +1,347 @@
+//! Procedure and diagnosis code reference data
+//!
+//! CMS and the AMA revise code sets and NCCI edits every quarter, so the
+//! tables are read from files at runtime instead of being compiled in. A
+//! reference directory holds any of the following CSV files, each starting
+//! with a header line; lines starting with `#` are ignored:
+//!
+//! | File                    | Columns                                                                   |
+//! |-------------------------|---------------------------------------------------------------------------|
+//! | `procedure_codes.csv`   | `code,description` (CPT and HCPCS Level II)                               |
+//! | `diagnosis_codes.csv`   | `code,description` (ICD-10-CM, with or without the dot)                   |
+//! | `medical_necessity.csv` | `procedure_code,diagnosis_code`; a trailing `*` covers a whole category   |
+//! | `ncci_ptp.csv`          | `column_1,column_2,effective_date,deletion_date,modifier_indicator`       |
+//!
+//! A missing file leaves that table unloaded, and checks relying on it are
+//! skipped. Dates are `YYYYMMDD` or `YYYY-MM-DD`; a deletion date of `*` or
+//! empty means the edit is still in force.
+
+use crate::error::{BillingError, BillingResult};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+const PROCEDURE_CODES_FILE: &str = "procedure_codes.csv";
+const DIAGNOSIS_CODES_FILE: &str = "diagnosis_codes.csv";
+const MEDICAL_NECESSITY_FILE: &str = "medical_necessity.csv";
+const NCCI_PTP_FILE: &str = "ncci_ptp.csv";
+
+/// Code system a procedure code belongs to, judged by its format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcedureCodeSystem {
+    /// Five digits, or four digits followed by F, T or U
+    Cpt,
+    /// HCPCS Level II: a letter A-V followed by four digits
+    Hcpcs,
+    /// Seven characters; inpatient procedures on institutional claims
+    Icd10Pcs,
+}
+
+impl ProcedureCodeSystem {
+    pub fn classify(code: &str) -> Option<Self> {
+        let bytes = code.as_bytes();
+        match bytes {
+            [a, b, c, d, last] if [a, b, c, d].iter().all(|byte| byte.is_ascii_digit()) => {
+                (last.is_ascii_digit() || matches!(last, b'F' | b'T' | b'U')).then_some(ProcedureCodeSystem::Cpt)
+            }
+            [b'A'..=b'V', rest @ ..] if rest.len() == 4 && rest.iter().all(u8::is_ascii_digit) => {
+                Some(ProcedureCodeSystem::Hcpcs)
+            }
+            // ICD-10-PCS never uses the letters I and O
+            _ if bytes.len() == 7
+                && bytes
+                    .iter()
+                    .all(|byte| byte.is_ascii_digit() || (byte.is_ascii_uppercase() && !matches!(byte, b'I' | b'O'))) =>
+            {
+                Some(ProcedureCodeSystem::Icd10Pcs)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcedureCodeSystem::Cpt => "CPT",
+            ProcedureCodeSystem::Hcpcs => "HCPCS",
+            ProcedureCodeSystem::Icd10Pcs => "ICD-10-PCS",
+        }
+    }
+}
+
+/// Uppercase a code and drop the dot ICD-10 codes are often written with
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_ascii_uppercase().replace('.', "")
+}
+
+/// Whether a normalized code is shaped like an ICD-10-CM diagnosis:
+/// a letter, a digit, then one to five letters or digits
+pub fn is_icd10_cm(code: &str) -> bool {
+    match code.as_bytes() {
+        [first, second, rest @ ..] => {
+            first.is_ascii_uppercase()
+                && second.is_ascii_digit()
+                && (1..=5).contains(&rest.len())
+                && rest.iter().all(|byte| byte.is_ascii_digit() || byte.is_ascii_uppercase())
+        }
+        _ => false,
+    }
+}
+
+/// Whether a column-two code may be billed alongside column one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModifierIndicator {
+    /// `0`: never billable together
+    NotAllowed,
+    /// `1`: billable together with an NCCI-associated modifier
+    Allowed,
+    /// `9`: the edit was deleted retroactively and does not apply
+    NotApplicable,
+}
+
+impl ModifierIndicator {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(ModifierIndicator::NotAllowed),
+            "1" => Some(ModifierIndicator::Allowed),
+            "9" => Some(ModifierIndicator::NotApplicable),
+            _ => None,
+        }
+    }
+}
+
+/// Modifiers that bypass an NCCI procedure-to-procedure edit with indicator `1`
+pub const NCCI_MODIFIERS: &[&str] = &[
+    "E1", "E2", "E3", "E4", "FA", "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "LC", "LD", "LM",
+    "LT", "RC", "RI", "RT", "TA", "T1", "T2", "T3", "T4", "T5", "T6", "T7", "T8", "T9", "24", "25", "27",
+    "57", "58", "59", "78", "79", "91", "XE", "XP", "XS", "XU",
+];
+
+/// NCCI procedure-to-procedure edit: column two is not payable with column one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PtpEdit {
+    pub column_one: String,
+    pub column_two: String,
+    pub effective: NaiveDate,
+    /// `None` while the edit is in force
+    pub deleted: Option<NaiveDate>,
+    pub modifier_indicator: ModifierIndicator,
+}
+
+impl PtpEdit {
+    pub fn applies_on(&self, date: NaiveDate) -> bool {
+        self.effective <= date && self.deleted.is_none_or(|deleted| date < deleted)
+    }
+}
+
+/// One quarter's code tables
+#[derive(Debug, Clone, Default)]
+pub struct CodeReference {
+    /// Release the tables come from, e.g. `2025Q2`
+    pub version: String,
+    procedures: HashMap<String, String>,
+    diagnoses: HashMap<String, String>,
+    /// Procedure code to the diagnosis codes or `*` categories supporting it
+    necessity: HashMap<String, Vec<String>>,
+    ptp: HashMap<(String, String), Vec<PtpEdit>>,
+}
+
+impl CodeReference {
+    /// Empty reference; codes are only checked for format
+    pub fn new(version: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Load the tables in `dir`, named after the directory (e.g. `reference/2025Q2`)
+    pub fn load_dir(dir: impl AsRef<Path>) -> BillingResult<Self> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(BillingError::ReferenceData(format!("{} is not a directory", dir.display())));
+        }
+        let version = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut reference = Self::new(&version);
+
+        for row in read_table(dir, PROCEDURE_CODES_FILE, 2)? {
+            reference = reference.with_procedure(&row.fields[0], &row.fields[1]);
+        }
+        for row in read_table(dir, DIAGNOSIS_CODES_FILE, 2)? {
+            reference = reference.with_diagnosis(&row.fields[0], &row.fields[1]);
+        }
+        for row in read_table(dir, MEDICAL_NECESSITY_FILE, 2)? {
+            reference = reference.with_medical_necessity(&row.fields[0], &row.fields[1]);
+        }
+        for row in read_table(dir, NCCI_PTP_FILE, 5)? {
+            let [column_one, column_two, effective, deleted, indicator] = &row.fields[..] else {
+                continue;
+            };
+            let invalid = |message: String| {
+                BillingError::ReferenceData(format!("{NCCI_PTP_FILE} line {}: {message}", row.line))
+            };
+            let edit = PtpEdit {
+                column_one: normalize_code(column_one),
+                column_two: normalize_code(column_two),
+                effective: parse_date(effective).ok_or_else(|| invalid(format!("invalid effective date '{effective}'")))?,
+                deleted: match deleted.as_str() {
+                    "" | "*" => None,
+                    value => Some(parse_date(value).ok_or_else(|| invalid(format!("invalid deletion date '{value}'")))?),
+                },
+                modifier_indicator: ModifierIndicator::parse(indicator)
+                    .ok_or_else(|| invalid(format!("modifier indicator must be 0, 1 or 9, found '{indicator}'")))?,
+            };
+            reference = reference.with_ptp_edit(edit);
+        }
+
+        tracing::info!(
+            version = %reference.version,
+            procedures = reference.procedures.len(),
+            diagnoses = reference.diagnoses.len(),
+            necessity = reference.necessity.len(),
+            ptp_edits = reference.ptp.len(),
+            "Loaded billing code reference"
+        );
+        Ok(reference)
+    }
+
+    pub fn with_procedure(mut self, code: &str, description: &str) -> Self {
+        self.procedures.insert(normalize_code(code), description.to_string());
+        self
+    }
+
+    pub fn with_diagnosis(mut self, code: &str, description: &str) -> Self {
+        self.diagnoses.insert(normalize_code(code), description.to_string());
+        self
+    }
+
+    /// Record that `diagnosis` (or, ending in `*`, any code in its category)
+    /// supports billing `procedure`
+    pub fn with_medical_necessity(mut self, procedure: &str, diagnosis: &str) -> Self {
+        self.necessity
+            .entry(normalize_code(procedure))
+            .or_default()
+            .push(normalize_code(diagnosis));
+        self
+    }
+
+    pub fn with_ptp_edit(mut self, edit: PtpEdit) -> Self {
+        self.ptp
+            .entry((edit.column_one.clone(), edit.column_two.clone()))
+            .or_default()
+            .push(edit);
+        self
+    }
+
+    pub fn has_procedures(&self) -> bool {
+        !self.procedures.is_empty()
+    }
+
+    pub fn has_diagnoses(&self) -> bool {
+        !self.diagnoses.is_empty()
+    }
+
+    pub fn procedure(&self, code: &str) -> Option<&str> {
+        self.procedures.get(&normalize_code(code)).map(String::as_str)
+    }
+
+    pub fn diagnosis(&self, code: &str) -> Option<&str> {
+        self.diagnoses.get(&normalize_code(code)).map(String::as_str)
+    }
+
+    /// Whether any of `diagnoses` supports `procedure`; `None` when there is
+    /// no medical necessity data for the procedure
+    pub fn supports(&self, procedure: &str, diagnoses: &[String]) -> Option<bool> {
+        let covered = self.necessity.get(&normalize_code(procedure))?;
+        Some(diagnoses.iter().map(|code| normalize_code(code)).any(|diagnosis| {
+            covered.iter().any(|pattern| match pattern.strip_suffix('*') {
+                Some(category) => diagnosis.starts_with(category),
+                None => *pattern == diagnosis,
+            })
+        }))
+    }
+
+    /// The edit in force on `date` that bars `column_two` alongside `column_one`
+    pub fn ptp_edit(&self, column_one: &str, column_two: &str, date: NaiveDate) -> Option<&PtpEdit> {
+        self.ptp
+            .get(&(normalize_code(column_one), normalize_code(column_two)))?
+            .iter()
+            .find(|edit| edit.applies_on(date))
+    }
+}
+
+fn reference_slot() -> &'static RwLock<Arc<CodeReference>> {
+    static REFERENCE: OnceLock<RwLock<Arc<CodeReference>>> = OnceLock::new();
+    REFERENCE.get_or_init(|| RwLock::new(Arc::new(CodeReference::new("none"))))
+}
+
+/// Reference used by [`crate::claims::validate`]
+pub fn reference() -> Arc<CodeReference> {
+    match reference_slot().read() {
+        Ok(reference) => Arc::clone(&reference),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    }
+}
+
+/// Replace the reference used by [`crate::claims::validate`], e.g. when a new quarter is published
+pub fn install_reference(reference: CodeReference) {
+    tracing::info!(version = %reference.version, "Installing billing code reference");
+    let reference = Arc::new(reference);
+    match reference_slot().write() {
+        Ok(mut slot) => *slot = reference,
+        Err(poisoned) => *poisoned.into_inner() = reference,
+    }
+}
+
+struct Row {
+    line: usize,
+    fields: Vec<String>,
+}
+
+/// Data rows of `file`, split into exactly `columns` fields with the last one
+/// taking the rest of the line; empty when the file does not exist
+fn read_table(dir: &Path, file: &str, columns: usize) -> BillingResult<Vec<Row>> {
+    let path = dir.join(file);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| BillingError::ReferenceData(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    let mut rows = Vec::new();
+    let lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .skip(1);
+    for (index, line) in lines {
+        let fields: Vec<String> = line
+            .splitn(columns, ',')
+            .map(|field| field.trim().trim_matches('"').to_string())
+            .collect();
+        if fields.len() < columns || fields[0].is_empty() {
+            return Err(BillingError::ReferenceData(format!(
+                "{file} line {}: expected {columns} columns",
+                index + 1
+            )));
+        }
+        rows.push(Row {
+            line: index + 1,
+            fields,
+        });
+    }
+    Ok(rows)
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+        .ok()
+}
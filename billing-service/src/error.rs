@@ -17,6 +17,12 @@ pub enum BillingError {
     #[error("Payment processing error: {0}")]
     Payment(String),
 
+    #[error("Reference data error: {0}")]
+    ReferenceData(String),
+
+    #[error("Receivables reconciliation error: {0}")]
+    Reconciliation(String),
+
     #[error("Insurance verification error: {0}")]
     InsuranceVerification(String),
 
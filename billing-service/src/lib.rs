@@ -4,20 +4,27 @@
 //! - Charge capture from clinical encounters
 //! - Claims generation (UB-04, HCFA-1500, 837P/I)
 //! - Payment processing and reconciliation
+//! - Patient payment plans and statements
 //! - Denial management and appeals
 //! - Revenue reporting and analytics
 
 pub mod service;
 pub mod models;
 pub mod claims;
+pub mod coding;
 pub mod payment;
+pub mod payment_plan;
+pub mod statement;
 pub mod reporting;
 pub mod error;
 
 pub use service::*;
 pub use models::*;
 pub use claims::*;
+pub use coding::*;
 pub use payment::*;
+pub use payment_plan::*;
+pub use statement::*;
 pub use reporting::*;
 pub use error::*;
 
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::Decimal;
+use insurance_service::{CobPayer, PayerRank};
 
 /// Billing charge from clinical encounter
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +12,15 @@ pub struct Charge {
     pub patient_id: Uuid,
     pub provider_id: Uuid,
     pub service_code: String, // CPT, HCPCS, ICD-10-PCS
+    /// Procedure modifiers, e.g. `25`, `59`, `RT`
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    /// ICD-10-CM codes this line is billed for, primary first
+    #[serde(default)]
+    pub diagnosis_codes: Vec<String>,
+    /// Date the service was rendered; defaults to the day the charge was captured
+    #[serde(default)]
+    pub service_date: Option<NaiveDate>,
     pub description: String,
     pub quantity: Decimal,
     pub unit_price: Decimal,
@@ -20,6 +30,12 @@ pub struct Charge {
     pub created_at: DateTime<Utc>,
 }
 
+impl Charge {
+    pub fn date_of_service(&self) -> NaiveDate {
+        self.service_date.unwrap_or_else(|| self.created_at.date_naive())
+    }
+}
+
 /// Charge status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -56,6 +72,14 @@ pub struct Claim {
     pub claim_number: String,
     pub patient_id: Uuid,
     pub insurance_id: Uuid,
+    /// Patient coverage billed, when the payer came from coordination of benefits
+    #[serde(default)]
+    pub coverage_id: Option<Uuid>,
+    #[serde(default)]
+    pub payer_rank: PayerRank,
+    /// Adjudication by the payers billed before this one
+    #[serde(default)]
+    pub prior_payers: Vec<PriorPayerAdjudication>,
     pub provider_id: Uuid,
     pub charges: Vec<Charge>,
     pub claim_type: ClaimType,
@@ -88,6 +112,56 @@ pub enum ClaimStatus {
     Appealed,
 }
 
+/// What a payer billed earlier paid and adjusted, reported on the next payer's claim
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorPayerAdjudication {
+    pub coverage_id: Uuid,
+    pub payer_id: String,
+    pub payer_name: String,
+    pub rank: PayerRank,
+    pub claim_id: Uuid,
+    pub paid_amount: Decimal,
+    pub adjustments: Vec<InsuranceAdjustment>,
+    pub adjudicated_date: Option<DateTime<Utc>>,
+}
+
+impl PriorPayerAdjudication {
+    /// Payments and adjustments `payer` remitted against `claim`
+    pub fn from_remittance(
+        payer: &CobPayer,
+        claim: &Claim,
+        payments: &[Payment],
+        adjustments: &[InsuranceAdjustment],
+    ) -> Self {
+        let remitted: Vec<&Payment> = payments.iter().filter(|payment| payment.claim_id == Some(claim.id)).collect();
+        Self {
+            coverage_id: payer.coverage_id,
+            payer_id: payer.payer_id.clone(),
+            payer_name: payer.payer_name.clone(),
+            rank: payer.rank,
+            claim_id: claim.id,
+            paid_amount: remitted.iter().map(|payment| payment.amount).sum(),
+            adjustments: adjustments
+                .iter()
+                .filter(|adjustment| adjustment.claim_id == Some(claim.id))
+                .cloned()
+                .collect(),
+            adjudicated_date: claim
+                .remittance_date
+                .or_else(|| remitted.iter().map(|payment| payment.received_date).max()),
+        }
+    }
+
+    /// Amount the payer assigned to the patient (`PR` adjustments)
+    pub fn patient_responsibility(&self) -> Decimal {
+        self.adjustments
+            .iter()
+            .filter(|adjustment| !adjustment.reduces_balance())
+            .map(|adjustment| adjustment.amount)
+            .sum()
+    }
+}
+
 /// Payment record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Payment {
@@ -129,6 +203,29 @@ pub enum PaymentType {
     Refund,
 }
 
+/// Adjustment a payer's remittance makes to billed charges
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsuranceAdjustment {
+    pub id: Uuid,
+    pub claim_id: Option<Uuid>,
+    pub charge_id: Option<Uuid>,
+    pub patient_id: Uuid,
+    /// CARC group code: `CO`, `PR`, `OA` or `PI`
+    pub group_code: String,
+    /// Claim adjustment reason code, e.g. `45` for charges over the fee schedule
+    pub reason_code: String,
+    pub amount: Decimal,
+    pub posted_date: DateTime<Utc>,
+}
+
+impl InsuranceAdjustment {
+    /// Patient responsibility (`PR`) moves the amount to the patient rather
+    /// than taking it off the bill
+    pub fn reduces_balance(&self) -> bool {
+        !self.group_code.eq_ignore_ascii_case("PR")
+    }
+}
+
 /// Denial reason
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DenialReason {
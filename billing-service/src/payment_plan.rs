@@ -0,0 +1,383 @@
+//! Patient payment plans
+//!
+//! A plan spreads a balance over installments. Payments are applied to the
+//! oldest unpaid installment first, so paying more than one installment pays
+//! the following ones ahead of schedule and paying the remaining balance pays
+//! the plan off. Renegotiating supersedes the unpaid installments with a new
+//! schedule for what is left; superseded installments, the payments made
+//! against them and the terms they were scheduled under stay on the plan.
+
+use crate::error::{BillingError, BillingResult};
+use crate::models::{Payment, PaymentType};
+use accounting_service::AccountsReceivable;
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How often installments fall due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallmentFrequency {
+    Weekly,
+    Biweekly,
+    Monthly,
+}
+
+impl InstallmentFrequency {
+    /// Due date of the installment `periods` after the one due on `first`
+    fn due_date(&self, first: NaiveDate, periods: u32) -> Option<NaiveDate> {
+        match self {
+            InstallmentFrequency::Weekly => first.checked_add_days(Days::new(7 * u64::from(periods))),
+            InstallmentFrequency::Biweekly => first.checked_add_days(Days::new(14 * u64::from(periods))),
+            // Counted from the first due date so the 31st stays the 31st after February
+            InstallmentFrequency::Monthly => first.checked_add_months(Months::new(periods)),
+        }
+    }
+}
+
+/// Installment amount and schedule a plan was agreed on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanTerms {
+    pub installment_amount: Decimal,
+    pub frequency: InstallmentFrequency,
+    pub first_due_date: NaiveDate,
+}
+
+/// Installment status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallmentStatus {
+    Scheduled,
+    PartiallyPaid,
+    Paid,
+    /// Not paid in full within the grace period after its due date
+    Missed,
+    /// Replaced by a renegotiated schedule before it was paid in full
+    Superseded,
+}
+
+/// One scheduled payment of a plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Installment {
+    /// Position across every schedule the plan has had, starting at 1
+    pub sequence: u32,
+    /// Schedule the installment belongs to; 0 for the original terms
+    pub revision: u32,
+    pub due_date: NaiveDate,
+    pub amount: Decimal,
+    pub paid: Decimal,
+    pub status: InstallmentStatus,
+    /// When the installment was first flagged as missed; kept once it is paid
+    pub missed_on: Option<NaiveDate>,
+}
+
+impl Installment {
+    pub fn outstanding(&self) -> Decimal {
+        self.amount - self.paid
+    }
+
+    fn is_open(&self) -> bool {
+        !matches!(self.status, InstallmentStatus::Paid | InstallmentStatus::Superseded)
+    }
+}
+
+/// Share of a payment applied to one installment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Allocation {
+    pub sequence: u32,
+    pub amount: Decimal,
+}
+
+/// Payment received against a plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanPayment {
+    pub payment_id: Uuid,
+    pub received_date: NaiveDate,
+    pub amount: Decimal,
+    pub allocations: Vec<Allocation>,
+}
+
+/// Renegotiation of a plan's terms
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanRevision {
+    pub revision: u32,
+    pub revised_on: NaiveDate,
+    pub reason: String,
+    pub previous_terms: PlanTerms,
+    /// Balance the new schedule was built for
+    pub rescheduled_balance: Decimal,
+}
+
+/// Plan status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStatus {
+    Active,
+    /// At least one installment of the current schedule was missed
+    Delinquent,
+    PaidOff,
+}
+
+/// Installment plan for a patient balance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentPlan {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub total_amount: Decimal,
+    /// Current terms; earlier ones are kept in `revisions`
+    pub terms: PlanTerms,
+    /// Days after the due date before an unpaid installment counts as missed
+    pub grace_days: u32,
+    pub installments: Vec<Installment>,
+    pub payments: Vec<PlanPayment>,
+    pub revisions: Vec<PlanRevision>,
+    pub remaining_balance: Decimal,
+    pub status: PlanStatus,
+    pub paid_off_on: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PaymentPlan {
+    pub const DEFAULT_GRACE_DAYS: u32 = 10;
+    /// Longest schedule a plan may have; thirty years of monthly installments
+    pub const MAX_INSTALLMENTS: u32 = 360;
+
+    /// Plan for `total_amount`, the last installment covering what remains
+    pub fn new(patient_id: Uuid, total_amount: Decimal, terms: PlanTerms) -> BillingResult<Self> {
+        if total_amount <= Decimal::ZERO {
+            return Err(BillingError::Validation(
+                "Payment plan total must be positive".to_string(),
+            ));
+        }
+        let mut plan = Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            total_amount,
+            terms: terms.clone(),
+            grace_days: Self::DEFAULT_GRACE_DAYS,
+            installments: Vec::new(),
+            payments: Vec::new(),
+            revisions: Vec::new(),
+            remaining_balance: total_amount,
+            status: PlanStatus::Active,
+            paid_off_on: None,
+            created_at: Utc::now(),
+        };
+        plan.schedule(&terms, 0)?;
+        Ok(plan)
+    }
+
+    pub fn with_grace_days(mut self, grace_days: u32) -> Self {
+        self.grace_days = grace_days;
+        self
+    }
+
+    /// Installments of the current schedule
+    pub fn current_schedule(&self) -> impl Iterator<Item = &Installment> {
+        let revision = self.revisions.len() as u32;
+        self.installments.iter().filter(move |installment| installment.revision == revision)
+    }
+
+    /// Apply a received payment to the oldest unpaid installments
+    pub fn record_payment(&mut self, payment: &Payment) -> BillingResult<()> {
+        if payment.patient_id != self.patient_id {
+            return Err(BillingError::Payment(format!(
+                "Payment {} is not from the patient on plan {}",
+                payment.id, self.id
+            )));
+        }
+        if matches!(payment.payment_type, PaymentType::Refund) || payment.amount <= Decimal::ZERO {
+            return Err(BillingError::Payment(format!(
+                "Payment {} must be a positive amount to apply to a plan",
+                payment.id
+            )));
+        }
+        if self.payments.iter().any(|recorded| recorded.payment_id == payment.id) {
+            return Err(BillingError::Payment(format!(
+                "Payment {} was already applied to plan {}",
+                payment.id, self.id
+            )));
+        }
+        if payment.amount > self.remaining_balance {
+            return Err(BillingError::Payment(format!(
+                "Payment of {} exceeds the {} remaining on plan {}",
+                payment.amount, self.remaining_balance, self.id
+            )));
+        }
+
+        let mut left = payment.amount;
+        let mut allocations = Vec::new();
+        for installment in self.installments.iter_mut().filter(|installment| installment.is_open()) {
+            if left.is_zero() {
+                break;
+            }
+            let applied = left.min(installment.outstanding());
+            installment.paid += applied;
+            installment.status = if installment.outstanding().is_zero() {
+                InstallmentStatus::Paid
+            } else if installment.status == InstallmentStatus::Missed {
+                InstallmentStatus::Missed
+            } else {
+                InstallmentStatus::PartiallyPaid
+            };
+            left -= applied;
+            allocations.push(Allocation {
+                sequence: installment.sequence,
+                amount: applied,
+            });
+        }
+
+        let received_date = payment.received_date.date_naive();
+        self.payments.push(PlanPayment {
+            payment_id: payment.id,
+            received_date,
+            amount: payment.amount,
+            allocations,
+        });
+        self.recompute();
+        if self.status == PlanStatus::PaidOff {
+            self.paid_off_on = Some(received_date);
+        }
+        Ok(())
+    }
+
+    /// Flag installments still unpaid after their grace period as missed,
+    /// returning the sequence numbers flagged by this call
+    pub fn refresh(&mut self, as_of: NaiveDate) -> Vec<u32> {
+        let grace = Days::new(u64::from(self.grace_days));
+        let mut missed = Vec::new();
+        for installment in self.installments.iter_mut().filter(|installment| installment.is_open()) {
+            let lapsed = installment.due_date.checked_add_days(grace).is_some_and(|last| last < as_of);
+            if lapsed && installment.status != InstallmentStatus::Missed {
+                installment.status = InstallmentStatus::Missed;
+                installment.missed_on.get_or_insert(as_of);
+                missed.push(installment.sequence);
+            }
+        }
+        self.recompute();
+        missed
+    }
+
+    /// Unpaid amount of installments due before `as_of`
+    pub fn past_due(&self, as_of: NaiveDate) -> Decimal {
+        self.open_installments()
+            .filter(|installment| installment.due_date < as_of)
+            .map(Installment::outstanding)
+            .sum()
+    }
+
+    /// Remaining balance that is not yet due on `as_of`
+    pub fn scheduled(&self, as_of: NaiveDate) -> Decimal {
+        self.remaining_balance - self.past_due(as_of)
+    }
+
+    /// First installment due on or after `as_of` that is not paid in full
+    pub fn next_installment(&self, as_of: NaiveDate) -> Option<&Installment> {
+        self.open_installments().find(|installment| installment.due_date >= as_of)
+    }
+
+    /// What the patient should pay now: everything past due plus the next installment
+    pub fn amount_due(&self, as_of: NaiveDate) -> Decimal {
+        self.past_due(as_of) + self.next_installment(as_of).map_or(Decimal::ZERO, Installment::outstanding)
+    }
+
+    /// Replace the unpaid part of the schedule with new terms for the remaining balance
+    pub fn renegotiate(&mut self, terms: PlanTerms, reason: &str, revised_on: NaiveDate) -> BillingResult<()> {
+        if self.status == PlanStatus::PaidOff {
+            return Err(BillingError::Validation(format!(
+                "Payment plan {} is paid off and cannot be renegotiated",
+                self.id
+            )));
+        }
+        let revision = self.revisions.len() as u32 + 1;
+        let previous_terms = std::mem::replace(&mut self.terms, terms.clone());
+        let previous_installments = self.installments.len();
+        if let Err(error) = self.schedule(&terms, revision) {
+            self.installments.truncate(previous_installments);
+            self.terms = previous_terms;
+            return Err(error);
+        }
+        for installment in self.installments[..previous_installments]
+            .iter_mut()
+            .filter(|installment| installment.is_open())
+        {
+            installment.status = InstallmentStatus::Superseded;
+        }
+        self.revisions.push(PlanRevision {
+            revision,
+            revised_on,
+            reason: reason.to_string(),
+            previous_terms,
+            rescheduled_balance: self.remaining_balance,
+        });
+        self.recompute();
+        Ok(())
+    }
+
+    /// Split the patient's receivable into what the plan has scheduled and
+    /// what is overdue under it
+    pub fn reconcile(&self, receivable: &mut AccountsReceivable, as_of: NaiveDate) -> BillingResult<()> {
+        if receivable.patient_id != self.patient_id {
+            return Err(BillingError::Reconciliation(format!(
+                "Receivable {} does not belong to the patient on plan {}",
+                receivable.id, self.id
+            )));
+        }
+        receivable
+            .apply_payment_plan(self.scheduled(as_of), self.past_due(as_of))
+            .map_err(|error| BillingError::Reconciliation(error.to_string()))
+    }
+
+    fn open_installments(&self) -> impl Iterator<Item = &Installment> {
+        self.installments.iter().filter(|installment| installment.is_open())
+    }
+
+    /// Append installments covering the remaining balance under `terms`
+    fn schedule(&mut self, terms: &PlanTerms, revision: u32) -> BillingResult<()> {
+        if terms.installment_amount <= Decimal::ZERO {
+            return Err(BillingError::Validation(
+                "Installment amount must be positive".to_string(),
+            ));
+        }
+        if (self.remaining_balance / terms.installment_amount).ceil() > Decimal::from(Self::MAX_INSTALLMENTS) {
+            return Err(BillingError::Validation(format!(
+                "An installment of {} would need more than {} installments",
+                terms.installment_amount,
+                Self::MAX_INSTALLMENTS
+            )));
+        }
+        let mut left = self.remaining_balance;
+        let mut period = 0;
+        while left > Decimal::ZERO {
+            let due_date = terms.frequency.due_date(terms.first_due_date, period).ok_or_else(|| {
+                BillingError::Validation("Payment plan schedule runs past the supported date range".to_string())
+            })?;
+            let amount = left.min(terms.installment_amount);
+            self.installments.push(Installment {
+                sequence: self.installments.len() as u32 + 1,
+                revision,
+                due_date,
+                amount,
+                paid: Decimal::ZERO,
+                status: InstallmentStatus::Scheduled,
+                missed_on: None,
+            });
+            left -= amount;
+            period += 1;
+        }
+        Ok(())
+    }
+
+    fn recompute(&mut self) {
+        let paid: Decimal = self.payments.iter().map(|payment| payment.amount).sum();
+        self.remaining_balance = self.total_amount - paid;
+        self.status = if self.remaining_balance.is_zero() {
+            PlanStatus::PaidOff
+        } else if self.current_schedule().any(|installment| installment.status == InstallmentStatus::Missed) {
+            PlanStatus::Delinquent
+        } else {
+            PlanStatus::Active
+        };
+    }
+}
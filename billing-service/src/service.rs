@@ -1,5 +1,6 @@
-use crate::error::BillingResult;
+use crate::error::{BillingError, BillingResult};
 use crate::models::*;
+use insurance_service::{CobDetermination, PayerRank};
 use uuid::Uuid;
 
 /// Billing service
@@ -29,6 +30,9 @@ impl BillingService {
             claim_number: format!("CLM-{}", Uuid::new_v4()),
             patient_id: charges[0].patient_id.clone(),
             insurance_id: Uuid::new_v4(), // TODO: Get from charge
+            coverage_id: None,
+            payer_rank: PayerRank::Primary,
+            prior_payers: vec![],
             provider_id: charges[0].provider_id.clone(),
             charges,
             claim_type,
@@ -40,6 +44,46 @@ impl BillingService {
         })
     }
 
+    /// Generate the claim to the payer at `rank` in the coordination-of-benefits order;
+    /// `prior_payers` holds the adjudication of every payer billed before it
+    pub async fn generate_coordinated_claim(
+        &self,
+        charges: Vec<Charge>,
+        claim_type: ClaimType,
+        cob: &CobDetermination,
+        rank: PayerRank,
+        prior_payers: Vec<PriorPayerAdjudication>,
+    ) -> BillingResult<Claim> {
+        if cob.needs_review() {
+            return Err(BillingError::ClaimsGeneration(format!(
+                "Coordination of benefits for patient {} needs manual review before claims are generated",
+                cob.patient_id
+            )));
+        }
+        let payer = cob.payer(rank).ok_or_else(|| {
+            BillingError::ClaimsGeneration(format!("Patient {} has no {:?} payer", cob.patient_id, rank))
+        })?;
+        let earlier = cob.payers_before(payer.coverage_id).unwrap_or_default();
+        let matches_order = earlier.len() == prior_payers.len()
+            && earlier
+                .iter()
+                .zip(&prior_payers)
+                .all(|(payer, prior)| payer.coverage_id == prior.coverage_id);
+        if !matches_order {
+            return Err(BillingError::ClaimsGeneration(format!(
+                "A {:?} claim needs the adjudication of each earlier payer, in order",
+                rank
+            )));
+        }
+
+        let mut claim = self.generate_claim(charges, claim_type).await?;
+        claim.insurance_id = payer.plan_id;
+        claim.coverage_id = Some(payer.coverage_id);
+        claim.payer_rank = rank;
+        claim.prior_payers = prior_payers;
+        Ok(claim)
+    }
+
     /// Process payment
     pub async fn process_payment(&self, payment: Payment) -> BillingResult<Payment> {
         // TODO: Implement payment processing
@@ -0,0 +1,247 @@
+//! Patient statements
+//!
+//! A statement lists the charges, insurance payments and adjustments, and
+//! patient payments posted in a period, carries forward the balance from
+//! before it, and reports the payment plan's standing. Serialized with serde
+//! it is the document handed to print vendors and the patient portal.
+
+use crate::error::{BillingError, BillingResult};
+use crate::models::{Charge, InsuranceAdjustment, Payment, PaymentMethod, PaymentType};
+use crate::payment_plan::{InstallmentFrequency, InstallmentStatus, PaymentPlan, PlanStatus};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Days a patient has to pay a balance that is not on a plan
+pub const STATEMENT_DUE_DAYS: u64 = 30;
+
+/// Kind of activity on a statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementLineKind {
+    Charge,
+    InsurancePayment,
+    InsuranceAdjustment,
+    PatientPayment,
+    Refund,
+}
+
+/// One posted amount; charges and refunds are positive, credits negative
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatementLine {
+    pub date: NaiveDate,
+    pub kind: StatementLineKind,
+    pub description: String,
+    pub reference_id: Uuid,
+    pub amount: Decimal,
+}
+
+/// Payment plan standing as of the statement date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanSummary {
+    pub plan_id: Uuid,
+    pub status: PlanStatus,
+    pub installment_amount: Decimal,
+    pub frequency: InstallmentFrequency,
+    pub remaining_balance: Decimal,
+    pub past_due: Decimal,
+    pub next_due_date: Option<NaiveDate>,
+    pub next_installment: Option<Decimal>,
+    pub missed_installments: usize,
+    pub revisions: usize,
+}
+
+impl PlanSummary {
+    pub fn of(plan: &PaymentPlan, as_of: NaiveDate) -> Self {
+        let next = plan.next_installment(as_of);
+        Self {
+            plan_id: plan.id,
+            status: plan.status,
+            installment_amount: plan.terms.installment_amount,
+            frequency: plan.terms.frequency,
+            remaining_balance: plan.remaining_balance,
+            past_due: plan.past_due(as_of),
+            next_due_date: next.map(|installment| installment.due_date),
+            next_installment: next.map(|installment| installment.outstanding()),
+            missed_installments: plan
+                .current_schedule()
+                .filter(|installment| installment.status == InstallmentStatus::Missed)
+                .count(),
+            revisions: plan.revisions.len(),
+        }
+    }
+}
+
+/// Statement of a patient's account for a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatientStatement {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub statement_date: NaiveDate,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    /// Balance from activity before `period_start`
+    pub previous_balance: Decimal,
+    pub lines: Vec<StatementLine>,
+    pub total_charges: Decimal,
+    pub insurance_payments: Decimal,
+    pub insurance_adjustments: Decimal,
+    /// Patient payments net of refunds
+    pub patient_payments: Decimal,
+    pub balance_due: Decimal,
+    /// Part of `balance_due` to pay by `due_date`; less than the balance while a plan is in force
+    pub amount_due_now: Decimal,
+    pub due_date: NaiveDate,
+    pub payment_plan: Option<PlanSummary>,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl PatientStatement {
+    /// Statement of `patient_id`'s activity between `period_start` and
+    /// `period_end`; activity for other patients or after the period is ignored
+    pub fn generate(
+        patient_id: Uuid,
+        period_start: NaiveDate,
+        period_end: NaiveDate,
+        charges: &[Charge],
+        payments: &[Payment],
+        adjustments: &[InsuranceAdjustment],
+        plan: Option<&PaymentPlan>,
+    ) -> BillingResult<Self> {
+        if period_end < period_start {
+            return Err(BillingError::Validation(format!(
+                "Statement period ends on {period_end}, before it starts on {period_start}"
+            )));
+        }
+        if plan.is_some_and(|plan| plan.patient_id != patient_id) {
+            return Err(BillingError::Validation(
+                "Payment plan belongs to a different patient".to_string(),
+            ));
+        }
+
+        let mut activity: Vec<StatementLine> = charges
+            .iter()
+            .filter(|charge| charge.patient_id == patient_id)
+            .map(|charge| StatementLine {
+                date: charge.date_of_service(),
+                kind: StatementLineKind::Charge,
+                description: format!("{} {}", charge.service_code, charge.description),
+                reference_id: charge.id,
+                amount: charge.total_amount,
+            })
+            .chain(
+                payments
+                    .iter()
+                    .filter(|payment| payment.patient_id == patient_id)
+                    .map(payment_line),
+            )
+            .chain(
+                adjustments
+                    .iter()
+                    .filter(|adjustment| adjustment.patient_id == patient_id && adjustment.reduces_balance())
+                    .map(|adjustment| StatementLine {
+                        date: adjustment.posted_date.date_naive(),
+                        kind: StatementLineKind::InsuranceAdjustment,
+                        description: format!("Insurance adjustment {}-{}", adjustment.group_code, adjustment.reason_code),
+                        reference_id: adjustment.id,
+                        amount: -adjustment.amount,
+                    }),
+            )
+            .filter(|line| line.date <= period_end)
+            .collect();
+        activity.sort_by_key(|line| line.date);
+
+        let (earlier, lines): (Vec<_>, Vec<_>) = activity.into_iter().partition(|line| line.date < period_start);
+        let previous_balance: Decimal = earlier.iter().map(|line| line.amount).sum();
+        let lines_of = |kinds: &'static [StatementLineKind]| {
+            lines.iter().filter(move |line| kinds.contains(&line.kind)).map(|line| line.amount)
+        };
+        let total_charges: Decimal = lines_of(&[StatementLineKind::Charge]).sum();
+        let insurance_payments: Decimal = lines_of(&[StatementLineKind::InsurancePayment]).map(|amount| -amount).sum();
+        let insurance_adjustments: Decimal =
+            lines_of(&[StatementLineKind::InsuranceAdjustment]).map(|amount| -amount).sum();
+        let patient_payments: Decimal = lines_of(&[StatementLineKind::PatientPayment, StatementLineKind::Refund])
+            .map(|amount| -amount)
+            .sum();
+        let balance_due =
+            previous_balance + total_charges - insurance_payments - insurance_adjustments - patient_payments;
+
+        let statement_date = period_end;
+        let (amount_due_now, due_date) = match plan.filter(|plan| plan.status != PlanStatus::PaidOff) {
+            Some(plan) => {
+                // Balance the plan does not cover is due in full alongside the installment
+                let outside_plan = (balance_due - plan.remaining_balance).max(Decimal::ZERO);
+                let due = (plan.amount_due(statement_date) + outside_plan).min(balance_due);
+                let due_date = plan
+                    .next_installment(statement_date)
+                    .map(|installment| installment.due_date)
+                    .unwrap_or(statement_date);
+                (due, due_date)
+            }
+            None => (
+                balance_due,
+                statement_date + Days::new(STATEMENT_DUE_DAYS),
+            ),
+        };
+
+        Ok(Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            statement_date,
+            period_start,
+            period_end,
+            previous_balance,
+            lines,
+            total_charges,
+            insurance_payments,
+            insurance_adjustments,
+            patient_payments,
+            balance_due,
+            amount_due_now: amount_due_now.max(Decimal::ZERO),
+            due_date,
+            payment_plan: plan.map(|plan| PlanSummary::of(plan, statement_date)),
+            generated_at: Utc::now(),
+        })
+    }
+
+    /// Statement as a JSON document
+    pub fn to_json(&self) -> BillingResult<String> {
+        serde_json::to_string_pretty(self).map_err(|error| BillingError::Unknown(error.to_string()))
+    }
+}
+
+fn payment_line(payment: &Payment) -> StatementLine {
+    let (kind, description, amount) = match (&payment.payment_method, &payment.payment_type) {
+        (_, PaymentType::Refund) => (StatementLineKind::Refund, "Refund".to_string(), payment.amount),
+        (PaymentMethod::Insurance, _) => (
+            StatementLineKind::InsurancePayment,
+            "Insurance payment".to_string(),
+            -payment.amount,
+        ),
+        (method, _) => (
+            StatementLineKind::PatientPayment,
+            format!("Payment - {}", method_name(method)),
+            -payment.amount,
+        ),
+    };
+    StatementLine {
+        date: payment.received_date.date_naive(),
+        kind,
+        description,
+        reference_id: payment.id,
+        amount,
+    }
+}
+
+fn method_name(method: &PaymentMethod) -> &'static str {
+    match method {
+        PaymentMethod::Cash => "cash",
+        PaymentMethod::CreditCard => "credit card",
+        PaymentMethod::DebitCard => "debit card",
+        PaymentMethod::Check => "check",
+        PaymentMethod::Ach => "bank transfer",
+        PaymentMethod::Wire => "wire",
+        PaymentMethod::Insurance => "insurance",
+    }
+}
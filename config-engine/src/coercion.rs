@@ -0,0 +1,87 @@
+// Coercion of string values into typed ones
+//
+// Environment variables, and many remote stores, only hold strings. A
+// coercion declared for a path converts such a string into the JSON shape
+// the target type deserializes from, before deserialization. Values that
+// already have the right shape are left alone.
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coercion {
+    /// `true`/`false`, `1`/`0`, `yes`/`no`, `on`/`off`, any case
+    Bool,
+    Integer,
+    Float,
+    /// `30s`, `250ms`, `1h 30m`, or a number of seconds, into `std::time::Duration`
+    Duration,
+    /// Comma-separated items, trimmed, into an array of strings
+    List,
+    /// A string holding JSON
+    Json,
+}
+
+impl Coercion {
+    pub fn expected(self) -> &'static str {
+        match self {
+            Coercion::Bool => "a boolean",
+            Coercion::Integer => "an integer",
+            Coercion::Float => "a number",
+            Coercion::Duration => "a duration such as 30s or 5m",
+            Coercion::List => "a comma-separated list",
+            Coercion::Json => "a JSON value",
+        }
+    }
+
+    /// Coerced `value`; `None` when it cannot be coerced
+    pub fn apply(self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (Coercion::Bool, Value::Bool(_)) => Some(value.clone()),
+            (Coercion::Bool, Value::Number(number)) => match number.as_u64() {
+                Some(0) => Some(Value::Bool(false)),
+                Some(1) => Some(Value::Bool(true)),
+                _ => None,
+            },
+            (Coercion::Bool, Value::String(text)) => match text.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" | "on" => Some(Value::Bool(true)),
+                "false" | "0" | "no" | "off" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            (Coercion::Integer, Value::Number(number)) if number.is_i64() || number.is_u64() => Some(value.clone()),
+            (Coercion::Integer, Value::String(text)) => {
+                let text = text.trim();
+                text.parse::<i64>()
+                    .map(Value::from)
+                    .or_else(|_| text.parse::<u64>().map(Value::from))
+                    .ok()
+            }
+            (Coercion::Float, Value::Number(_)) => Some(value.clone()),
+            (Coercion::Float, Value::String(text)) => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number),
+            (Coercion::Duration, Value::Object(map)) if map.contains_key("secs") => Some(value.clone()),
+            (Coercion::Duration, Value::Number(number)) => number.as_u64().map(|secs| json!({"secs": secs, "nanos": 0})),
+            (Coercion::Duration, Value::String(text)) => {
+                let text = text.trim();
+                let duration = match text.parse::<u64>() {
+                    Ok(secs) => std::time::Duration::from_secs(secs),
+                    Err(_) => humantime::parse_duration(text).ok()?,
+                };
+                Some(json!({"secs": duration.as_secs(), "nanos": duration.subsec_nanos()}))
+            }
+            (Coercion::List, Value::Array(_)) => Some(value.clone()),
+            (Coercion::List, Value::String(text)) => Some(Value::Array(
+                text.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| Value::String(item.to_string()))
+                    .collect(),
+            )),
+            (Coercion::Json, Value::String(text)) => serde_json::from_str(text).ok(),
+            (Coercion::Json, _) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,249 @@
+// Configuration drift detection and reconciliation
+//
+// Drift is a source whose contents changed behind the engine's back, such as
+// an etcd key edited by hand, so that the running configuration no longer
+// matches what the sources hold. Detection loads every source again and
+// compares each with what it returned at the last load.
+//
+// A changed key only counts if it matters: when a higher-priority source
+// overrides the key both before and after the change (an env variable over a
+// file value, say), the change has no effect and is not reported.
+//
+// The loaded configuration is authoritative. `reconcile` writes it back to
+// drifted sources that can be written; read-only sources such as files have
+// to be fixed at their origin, or accepted with `ConfigEngine::reload`.
+//
+// Reports and events mask sensitive values like any other dump of the
+// configuration.
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::engine::{ConfigEngine, ConfigEvent};
+use crate::tree::{flatten, remove_path, set_path};
+
+/// A key whose value in one source differs from when it was loaded
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DriftEntry {
+    pub key: String,
+    pub source: String,
+    /// Value the source had at the last load; `None` if it lacked the key
+    pub loaded: Option<Value>,
+    /// Value the source has now; `None` if the key was removed
+    pub current: Option<Value>,
+}
+
+/// A source that could not be read or written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceFailure {
+    pub source: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DriftReport {
+    pub checked_at: DateTime<Utc>,
+    /// Configuration version the sources were compared with
+    pub version: u64,
+    pub entries: Vec<DriftEntry>,
+    /// Changed keys ignored because a higher-priority source overrides them
+    pub overridden: usize,
+    /// Sources skipped because they could not be loaded
+    pub unavailable: Vec<SourceFailure>,
+}
+
+impl DriftReport {
+    pub fn has_drift(&self) -> bool {
+        !self.entries.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReconcileOutcome {
+    /// Entries written back to their source
+    pub restored: Vec<DriftEntry>,
+    /// Entries in read-only sources, left as they are
+    pub unresolved: Vec<DriftEntry>,
+    pub failed: Vec<SourceFailure>,
+}
+
+impl ConfigEngine {
+    /// Compare every source with what it held at the last load
+    pub async fn detect_drift(&self) -> DriftReport {
+        self.masked_report(self.compare_sources().await.report)
+    }
+
+    /// Write the loaded values back to drifted sources that accept writes
+    pub async fn reconcile(&self) -> ReconcileOutcome {
+        let comparison = self.compare_sources().await;
+        let mut by_source: BTreeMap<usize, Vec<DriftEntry>> = BTreeMap::new();
+        for (index, entry) in comparison.entry_sources.into_iter().zip(comparison.report.entries) {
+            by_source.entry(index).or_default().push(entry);
+        }
+
+        let mut outcome = ReconcileOutcome {
+            restored: vec![],
+            unresolved: vec![],
+            failed: vec![],
+        };
+        for (index, entries) in by_source {
+            let source = &self.sources()[index];
+            let Some(mut restored) = comparison.current[index].clone() else {
+                continue;
+            };
+            if !source.provider().is_writable() {
+                outcome.unresolved.extend(entries.into_iter().map(|entry| self.masked_entry(entry)));
+                continue;
+            }
+            // Start from what the source holds now so unrelated keys keep
+            // any legitimate changes
+            for entry in &entries {
+                match &entry.loaded {
+                    Some(value) => set_path(&mut restored, &entry.key, value.clone()),
+                    None => remove_path(&mut restored, &entry.key),
+                }
+            }
+            match source.provider().store(&restored).await {
+                Ok(()) => {
+                    tracing::info!(source = source.name(), keys = entries.len(), "Reconciled configuration drift");
+                    outcome.restored.extend(entries.into_iter().map(|entry| self.masked_entry(entry)));
+                }
+                Err(error) => {
+                    tracing::error!(source = source.name(), error = %error, "Cannot reconcile configuration drift");
+                    outcome.failed.push(SourceFailure {
+                        source: source.name().to_string(),
+                        error: error.to_string(),
+                    });
+                    outcome.unresolved.extend(entries.into_iter().map(|entry| self.masked_entry(entry)));
+                }
+            }
+        }
+
+        self.emit(ConfigEvent::Reconciled(outcome.clone()));
+        outcome
+    }
+
+    /// Check for drift every `interval`, alerting when the drift found changes
+    pub fn spawn_drift_monitor(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut last_seen = vec![];
+            loop {
+                ticker.tick().await;
+                // Compared unmasked, so a secret changing again still alerts
+                let report = self.compare_sources().await.report;
+                for failure in &report.unavailable {
+                    tracing::warn!(source = %failure.source, error = %failure.error, "Configuration source unavailable for drift check");
+                }
+                if report.entries == last_seen {
+                    continue;
+                }
+                if report.has_drift() {
+                    tracing::warn!(
+                        keys = ?report.entries.iter().map(|entry| entry.key.as_str()).collect::<Vec<_>>(),
+                        version = report.version,
+                        "Configuration drift detected"
+                    );
+                    self.emit(ConfigEvent::DriftDetected(self.masked_report(report.clone())));
+                }
+                last_seen = report.entries;
+            }
+        })
+    }
+
+    fn masked_report(&self, mut report: DriftReport) -> DriftReport {
+        report.entries = report.entries.into_iter().map(|entry| self.masked_entry(entry)).collect();
+        report
+    }
+
+    fn masked_entry(&self, mut entry: DriftEntry) -> DriftEntry {
+        let redaction = self.redaction();
+        entry.loaded = entry.loaded.map(|value| redaction.redact(&value, &entry.key));
+        entry.current = entry.current.map(|value| redaction.redact(&value, &entry.key));
+        entry
+    }
+
+    async fn compare_sources(&self) -> Comparison {
+        let snapshot = self.snapshot().await;
+        let mut current = Vec::with_capacity(self.sources().len());
+        let mut unavailable = vec![];
+        for source in self.sources() {
+            match self.load_source(source).await {
+                Ok(tree) => current.push(Some(tree)),
+                Err(error) => {
+                    unavailable.push(SourceFailure {
+                        source: source.name().to_string(),
+                        error: error.to_string(),
+                    });
+                    current.push(None);
+                }
+            }
+        }
+
+        let loaded_leaves: Vec<_> = (0..self.sources().len())
+            .map(|index| snapshot.layers.get(index).map(flatten).unwrap_or_default())
+            .collect();
+        let current_leaves: Vec<_> = current.iter().map(|tree| tree.as_ref().map(flatten)).collect();
+
+        let mut entries = vec![];
+        let mut entry_sources = vec![];
+        let mut overridden = 0;
+        for (index, source) in self.sources().iter().enumerate() {
+            let Some(now) = &current_leaves[index] else {
+                continue;
+            };
+            let before = &loaded_leaves[index];
+            let keys: BTreeSet<&String> = before.keys().chain(now.keys()).collect();
+            for key in keys {
+                let (loaded, present) = (before.get(key), now.get(key));
+                if loaded == present {
+                    continue;
+                }
+                // An unavailable later source is assumed unchanged
+                let shadowed = (index + 1..self.sources().len()).any(|later| {
+                    loaded_leaves[later].contains_key(key)
+                        && current_leaves[later]
+                            .as_ref()
+                            .is_none_or(|leaves| leaves.contains_key(key))
+                });
+                if shadowed {
+                    overridden += 1;
+                    continue;
+                }
+                entry_sources.push(index);
+                entries.push(DriftEntry {
+                    key: key.clone(),
+                    source: source.name().to_string(),
+                    loaded: loaded.cloned(),
+                    current: present.cloned(),
+                });
+            }
+        }
+
+        Comparison {
+            report: DriftReport {
+                checked_at: Utc::now(),
+                version: snapshot.version,
+                entries,
+                overridden,
+                unavailable,
+            },
+            entry_sources,
+            current,
+        }
+    }
+}
+
+struct Comparison {
+    report: DriftReport,
+    /// Index of the source of each report entry
+    entry_sources: Vec<usize>,
+    /// Each source's tree now; `None` when it could not be loaded
+    current: Vec<Option<Value>>,
+}
@@ -1,14 +1,134 @@
-// Configuration encryption for sensitive values stub
-pub struct ConfigEncryption {
-    // Implementation will go here
+// Inline encrypted configuration values
+//
+// A string of the form `enc:<ciphertext>`, or in YAML a scalar tagged
+// `!enc`, stays encrypted everywhere in the engine - snapshots, dumps,
+// exports and anything written back to a source - and is decrypted only
+// when read through `get`, `get_field` or `get_or_default`.
+//
+// Ciphertexts are AES-256-GCM in the crypto crate's versioned format,
+// `v<key version>:<nonce>:<ciphertext>`. The key manager keeps earlier keys
+// for reading values written under them; new values always use the current
+// key.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crypto::Aes256GcmEncryptor;
+use serde_json::Value;
+
+use crate::error::{ConfigError, Result};
+
+/// Marks a string value as ciphertext
+pub const ENCRYPTED_PREFIX: &str = "enc:";
+
+/// Keys for inline encrypted values, by version
+pub struct EncryptionKeyManager {
+    keys: BTreeMap<u32, Aes256GcmEncryptor>,
+    current: u32,
+}
+
+impl fmt::Debug for EncryptionKeyManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKeyManager")
+            .field("versions", &self.keys.keys().collect::<Vec<_>>())
+            .field("current", &self.current)
+            .finish()
+    }
 }
 
-impl ConfigEncryption {
-    pub fn encrypt(&self, value: &str) -> crate::error::Result<String> {
-        Ok(value.to_string()) // Stub implementation
+impl EncryptionKeyManager {
+    /// Manager encrypting with `key`, known as `version`
+    pub fn new(version: u32, key: [u8; 32]) -> Result<Self> {
+        let mut keys = BTreeMap::new();
+        keys.insert(version, encryptor(version, key)?);
+        Ok(Self { keys, current: version })
+    }
+
+    /// Keep an earlier key for decrypting the values written under it
+    pub fn with_previous_key(mut self, version: u32, key: [u8; 32]) -> Result<Self> {
+        if version == self.current {
+            return Err(ConfigError::ValidationError(format!(
+                "key version {} is already the current key",
+                version
+            )));
+        }
+        self.keys.insert(version, encryptor(version, key)?);
+        Ok(self)
+    }
+
+    pub fn current_version(&self) -> u32 {
+        self.current
+    }
+
+    /// `enc:`-marked ciphertext of `plaintext` under the current key
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let ciphertext = self.keys[&self.current]
+            .encrypt_string(plaintext)
+            .map_err(|_| ConfigError::EncryptionError)?;
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, ciphertext))
     }
-    
-    pub fn decrypt(&self, encrypted_value: &str) -> crate::error::Result<String> {
-        Ok(encrypted_value.to_string()) // Stub implementation
+
+    /// Plaintext of an `enc:`-marked value, under whichever key it names
+    pub fn decrypt(&self, marked: &str) -> Result<String> {
+        let ciphertext = marked.strip_prefix(ENCRYPTED_PREFIX).unwrap_or(marked);
+        let version = ciphertext
+            .split(':')
+            .next()
+            .and_then(|version| version.strip_prefix('v'))
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or(ConfigError::DecryptionError)?;
+        self.keys
+            .get(&version)
+            .ok_or(ConfigError::DecryptionError)?
+            .decrypt_string(ciphertext)
+            .map_err(|_| ConfigError::DecryptionError)
     }
-}
\ No newline at end of file
+}
+
+fn encryptor(version: u32, key: [u8; 32]) -> Result<Aes256GcmEncryptor> {
+    Aes256GcmEncryptor::new(key)
+        .map(|encryptor| encryptor.with_version(version))
+        .map_err(|_| ConfigError::EncryptionError)
+}
+
+pub fn is_encrypted(value: &Value) -> bool {
+    value.as_str().is_some_and(|text| text.starts_with(ENCRYPTED_PREFIX))
+}
+
+/// Decrypt every marked string in `tree`, the value at `base`; failures
+/// name the value's JSON pointer and nothing of its content
+pub(crate) fn decrypt_tree(tree: &mut Value, base: &str, keys: Option<&EncryptionKeyManager>) -> Result<()> {
+    let pointer = if base.is_empty() {
+        String::new()
+    } else {
+        base.split('.').map(|key| format!("/{}", escape_pointer(key))).collect()
+    };
+    decrypt_at(tree, pointer, keys)
+}
+
+fn decrypt_at(value: &mut Value, pointer: String, keys: Option<&EncryptionKeyManager>) -> Result<()> {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                decrypt_at(child, format!("{}/{}", pointer, escape_pointer(key)), keys)?;
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                decrypt_at(item, format!("{}/{}", pointer, index), keys)?;
+            }
+        }
+        Value::String(text) if text.starts_with(ENCRYPTED_PREFIX) => {
+            let plaintext = keys
+                .ok_or(ConfigError::DecryptionError)
+                .and_then(|keys| keys.decrypt(text))
+                .map_err(|_| ConfigError::DecryptionFailed { pointer })?;
+            *text = plaintext;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
@@ -1,10 +1,608 @@
-// Configuration engine implementation stub
+// Configuration engine
+//
+// Sources are merged from lowest to highest priority, sources of equal
+// priority in the order they were added. The merge is deep: a higher source
+// overrides a lower one key by key, so `database.url` from env and
+// `database.pool.size` from a file sit side by side under `database`. The engine keeps the tree each
+// source returned alongside the merged result, so it can later tell which
+// source a value came from and whether a source has changed since.
+//
+// With an environment selected, each source's overlay for it is merged over
+// the source's own tree, and the two are kept as the source's one layer.
+//
+// Values registered as sensitive are masked in dumps, in `Debug` output of
+// snapshots and in error messages; `export_unredacted` is the one way out.
+//
+// `apply_patch` changes the configuration in place: the patch goes to the
+// highest-priority source that accepts writes, and only if doing so gives
+// every patched key its patched value. Each applied patch makes a new
+// version, recorded on the audit log.
+//
+// Values marked `enc:` stay encrypted in snapshots and are decrypted only
+// by the typed getters. A patch replacing an encrypted value is encrypted
+// with the current key before it is written, so plaintext never reaches a
+// source.
+//
+// With a schema attached, every configuration is validated before it is
+// served; one that fails is rejected and the last valid one stays current.
+//
+// Declared defaults sit beneath every source. Declared coercions are applied
+// when values are read, so typed access works on string-only sources.
+//
+// A source that signals a change is read again on its own and replaces only
+// its layer; see the `watchers` module.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use tokio::sync::{broadcast, RwLock};
+
+use crate::coercion::Coercion;
+use crate::drift::{DriftReport, ReconcileOutcome};
+use crate::encryption::{decrypt_tree, is_encrypted, EncryptionKeyManager};
+use crate::error::{ConfigError, Result};
+use crate::overlay::{environment_from_env, ArrayMerge};
+use crate::providers::ConfigSource;
+use crate::sensitive::{Redaction, RedactionConfig, REDACTED};
+use crate::tree::{deep_merge, flatten, get_path, get_path_mut, merge_patch, merge_with, set_path};
+use crate::validation::JsonSchemaValidator;
+
+const EVENT_CAPACITY: usize = 64;
+
+/// How long after a source's first change notification further ones are
+/// gathered into the same reload
+pub const DEFAULT_RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Configuration as loaded from all sources at one point in time
+///
+/// `Debug` output masks sensitive values; the public fields do not.
+#[derive(Clone)]
+pub struct ConfigSnapshot {
+    pub version: u64,
+    pub loaded_at: DateTime<Utc>,
+    /// Tree returned by each source, in merge order (lowest priority first)
+    pub layers: Vec<Value>,
+    pub effective: Value,
+    redaction: Arc<Redaction>,
+}
+
+impl std::fmt::Debug for ConfigSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let layers: Vec<Value> = self
+            .layers
+            .iter()
+            .map(|layer| self.redaction.redact(layer, ""))
+            .collect();
+        f.debug_struct("ConfigSnapshot")
+            .field("version", &self.version)
+            .field("loaded_at", &self.loaded_at)
+            .field("layers", &layers)
+            .field("effective", &self.redaction.redact(&self.effective, ""))
+            .finish()
+    }
+}
+
+impl ConfigSnapshot {
+    fn new(version: u64, defaults: &Value, layers: Vec<Value>, redaction: Arc<Redaction>) -> Self {
+        let mut effective = defaults.clone();
+        for layer in &layers {
+            deep_merge(&mut effective, layer);
+        }
+        Self {
+            version,
+            loaded_at: Utc::now(),
+            layers,
+            effective,
+            redaction,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConfigEvent {
+    Reloaded { version: u64 },
+    /// A patch was written to `source`, changing `keys`
+    Patched { version: u64, source: String, keys: Vec<String> },
+    DriftDetected(DriftReport),
+    Reconciled(ReconcileOutcome),
+    /// Configuration was exported with sensitive values in the clear
+    UnredactedExport { version: u64, reason: String },
+}
+
 pub struct ConfigEngine {
-    // Implementation will go here
+    sources: Vec<ConfigSource>,
+    defaults: Value,
+    coercions: BTreeMap<String, Coercion>,
+    environment: Option<String>,
+    array_merge: ArrayMerge,
+    redaction: Arc<Redaction>,
+    schema: Option<Value>,
+    validator: Option<JsonSchemaValidator>,
+    encryption: Option<Arc<EncryptionKeyManager>>,
+    reload_debounce: Duration,
+    snapshot: RwLock<ConfigSnapshot>,
+    events: broadcast::Sender<ConfigEvent>,
+}
+
+impl Default for ConfigEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ConfigEngine {
+    /// Engine without sources; add them, then `build`
     pub fn new() -> Self {
-        Self {}
+        Self {
+            sources: vec![],
+            defaults: Value::Object(Map::new()),
+            coercions: BTreeMap::new(),
+            environment: None,
+            array_merge: ArrayMerge::default(),
+            redaction: Arc::default(),
+            schema: None,
+            validator: None,
+            encryption: None,
+            reload_debounce: DEFAULT_RELOAD_DEBOUNCE,
+            snapshot: RwLock::new(ConfigSnapshot::new(0, &Value::Object(Map::new()), vec![], Arc::default())),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        }
     }
-}
\ No newline at end of file
+
+    /// Add a source that overrides those of lower priority and those of
+    /// equal priority added before it
+    pub fn add_source(mut self, source: ConfigSource) -> Self {
+        // Kept in merge order; after every source of the same priority
+        let position = self
+            .sources
+            .partition_point(|existing| existing.priority() <= source.priority());
+        self.sources.insert(position, source);
+        self
+    }
+
+    /// Value for `path` when no source sets it
+    pub fn with_default(mut self, path: &str, value: impl Into<Value>) -> Self {
+        set_path(&mut self.defaults, path, value.into());
+        self
+    }
+
+    /// Coerce the value at `path` when it is read
+    pub fn with_coercion(mut self, path: &str, coercion: Coercion) -> Self {
+        self.coercions.insert(path.to_string(), coercion);
+        self
+    }
+
+    /// Apply each source's overlay for `environment`; a source without one
+    /// fails to load rather than falling back to its base
+    pub fn with_environment(mut self, environment: &str) -> Self {
+        self.environment = Some(environment.to_string());
+        self
+    }
+
+    /// `with_environment` for the environment named by `RUSTCARE_ENV`;
+    /// base files only when it is not set
+    pub fn with_environment_from_env(mut self) -> Self {
+        self.environment = environment_from_env();
+        self
+    }
+
+    /// How arrays in an environment overlay combine with the base's
+    pub fn with_array_merge(mut self, array_merge: ArrayMerge) -> Self {
+        self.array_merge = array_merge;
+        self
+    }
+
+    /// Mask the value at `path`, and everything below it; `*` matches any one
+    /// key, as in `databases.*.url`
+    ///
+    /// Keys naming credentials, such as `password` or `api_key`, are masked
+    /// without being registered.
+    pub fn with_sensitive(mut self, path: &str) -> Self {
+        Arc::make_mut(&mut self.redaction).add_path(path);
+        self
+    }
+
+    /// PII patterns applied to the strings that are not masked outright
+    pub fn with_value_redaction(mut self, config: RedactionConfig) -> Self {
+        Arc::make_mut(&mut self.redaction).set_value_patterns(config);
+        self
+    }
+
+    /// Keys for decrypting `enc:` values on read and encrypting patches
+    pub fn with_encryption(mut self, keys: EncryptionKeyManager) -> Self {
+        self.encryption = Some(Arc::new(keys));
+        self
+    }
+
+    /// JSON Schema (draft 2020-12) every configuration must satisfy; checked
+    /// after coercions, so typed values from string-only sources pass
+    pub fn with_schema(mut self, schema: Value) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Window in which a source's change notifications make one reload;
+    /// zero reloads on every notification
+    pub fn with_reload_debounce(mut self, debounce: Duration) -> Self {
+        self.reload_debounce = debounce;
+        self
+    }
+
+    /// Load every source
+    pub async fn build(mut self) -> Result<Self> {
+        if let Some(schema) = &self.schema {
+            self.validator = Some(JsonSchemaValidator::new(schema)?);
+        }
+        self.reload().await?;
+        Ok(self)
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self) -> Result<T> {
+        let mut tree = self.snapshot.read().await.effective.clone();
+        decrypt_tree(&mut tree, "", self.encryption.as_deref())?;
+        self.coerce(&mut tree, "")?;
+        self.deserialize_at(tree, "")
+    }
+
+    /// Value at a `.`-separated path as `T`; errors name the path when it is
+    /// missing or cannot be read as `T`
+    pub async fn get_field<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        match self.field(path).await? {
+            Some(value) => self.deserialize_at(value, path),
+            None => Err(ConfigError::MissingField(path.to_string())),
+        }
+    }
+
+    /// Like `get_field`, but `default` when the path is missing or null;
+    /// a value that is present but malformed is still an error
+    pub async fn get_or_default<T: DeserializeOwned>(&self, path: &str, default: T) -> Result<T> {
+        match self.field(path).await? {
+            Some(value) => self.deserialize_at(value, path),
+            None => Ok(default),
+        }
+    }
+
+    /// Effective value at a `.`-separated path
+    pub async fn get_value(&self, path: &str) -> Option<Value> {
+        get_path(&self.snapshot.read().await.effective, path).cloned()
+    }
+
+    /// Effective configuration with sensitive values masked, for logs and debugging
+    pub async fn dump_effective(&self) -> Value {
+        self.redaction.redact(&self.snapshot.read().await.effective, "")
+    }
+
+    /// Effective configuration with sensitive values in the clear, for backups
+    /// and migrations; the export is logged and announced to subscribers
+    pub async fn export_unredacted(&self, reason: &str) -> Value {
+        let snapshot = self.snapshot.read().await;
+        let (version, effective) = (snapshot.version, snapshot.effective.clone());
+        drop(snapshot);
+
+        tracing::warn!(version, reason, "Configuration exported with sensitive values unmasked");
+        self.emit(ConfigEvent::UnredactedExport {
+            version,
+            reason: reason.to_string(),
+        });
+        effective
+    }
+
+    /// Whether the value at `path` is masked
+    pub fn is_sensitive(&self, path: &str) -> bool {
+        self.redaction.is_sensitive(path)
+    }
+
+    pub async fn snapshot(&self) -> ConfigSnapshot {
+        self.snapshot.read().await.clone()
+    }
+
+    pub async fn version(&self) -> u64 {
+        self.snapshot.read().await.version
+    }
+
+    /// Version of the configuration being served, which passed the schema;
+    /// `None` without a schema. Rejected configurations never take a version.
+    pub async fn last_validated_version(&self) -> Option<u64> {
+        self.validator.as_ref()?;
+        let version = self.version().await;
+        (version > 0).then_some(version)
+    }
+
+    /// Sources in merge order, lowest priority first
+    pub fn sources(&self) -> &[ConfigSource] {
+        &self.sources
+    }
+
+    /// The source the effective value at `key` comes from: the highest-priority
+    /// source setting it, or, for an object, the highest-priority source
+    /// contributing to it. `None` when the key is unset or only has a default.
+    pub async fn source_of(&self, key: &str) -> Option<&ConfigSource> {
+        let snapshot = self.snapshot.read().await;
+        get_path(&snapshot.effective, key)?;
+        let index = snapshot
+            .layers
+            .iter()
+            .rposition(|layer| get_path(layer, key).is_some())?;
+        self.sources.get(index)
+    }
+
+    pub fn environment(&self) -> Option<&str> {
+        self.environment.as_deref()
+    }
+
+    pub(crate) fn reload_debounce(&self) -> Duration {
+        self.reload_debounce
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ConfigEvent> {
+        self.events.subscribe()
+    }
+
+    /// Load every source again and make the result current; on failure,
+    /// including failing the schema, the previous configuration stays in place
+    pub async fn reload(&self) -> Result<u64> {
+        let mut layers = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            layers.push(self.load_source(source).await?);
+        }
+        let mut snapshot = self.snapshot.write().await;
+        let loaded = ConfigSnapshot::new(
+            snapshot.version + 1,
+            &self.defaults,
+            layers,
+            Arc::clone(&self.redaction),
+        );
+        self.check_schema(&loaded.effective)?;
+        *snapshot = loaded;
+        let version = snapshot.version;
+        drop(snapshot);
+
+        tracing::info!(version, sources = self.sources.len(), "Configuration loaded");
+        self.emit(ConfigEvent::Reloaded { version });
+        Ok(version)
+    }
+
+    /// Make `layer` the tree of the source at `index`, keeping every other
+    /// layer as it is. Unless `force`d, a layer equal to the current one
+    /// changes nothing and returns `None`; on failure, including failing the
+    /// schema, the previous configuration stays in place.
+    pub(crate) async fn replace_layer(&self, index: usize, layer: Value, force: bool) -> Result<Option<u64>> {
+        let mut snapshot = self.snapshot.write().await;
+        if snapshot.layers.len() != self.sources.len() {
+            return Err(ConfigError::ValidationError(
+                "configuration must be built before a source is reloaded".to_string(),
+            ));
+        }
+        if !force && snapshot.layers[index] == layer {
+            return Ok(None);
+        }
+        let mut layers = snapshot.layers.clone();
+        layers[index] = layer;
+        let loaded = ConfigSnapshot::new(
+            snapshot.version + 1,
+            &self.defaults,
+            layers,
+            Arc::clone(&self.redaction),
+        );
+        self.check_schema(&loaded.effective)?;
+        *snapshot = loaded;
+        let version = snapshot.version;
+        drop(snapshot);
+
+        tracing::info!(version, source = self.sources[index].name(), "Configuration source reloaded");
+        self.emit(ConfigEvent::Reloaded { version });
+        Ok(Some(version))
+    }
+
+    /// Apply an RFC 7386 JSON Merge Patch to the effective configuration
+    ///
+    /// Objects in `patch` merge key by key, `null` removes a key and anything
+    /// else replaces the value. The change is written to the highest-priority
+    /// writable source, starting from what that source holds now. Nothing is
+    /// written when another source would keep a patched key from taking its
+    /// patched value, e.g. an env variable overriding it or a read-only file
+    /// still holding a removed key.
+    pub async fn apply_patch(&self, patch: Value) -> Result<()> {
+        if !patch.is_object() {
+            return Err(ConfigError::ValidationError(
+                "a configuration patch must be a JSON object".to_string(),
+            ));
+        }
+        let index = self
+            .sources
+            .iter()
+            .rposition(|source| source.provider().is_writable())
+            .ok_or(ConfigError::NoWritableSource)?;
+        let source = &self.sources[index];
+
+        // Held throughout so patches and reloads apply one at a time
+        let mut snapshot = self.snapshot.write().await;
+        let mut layers = snapshot.layers.clone();
+        if layers.len() != self.sources.len() {
+            return Err(ConfigError::ValidationError(
+                "configuration must be built before it is patched".to_string(),
+            ));
+        }
+        let changes = flatten(&patch);
+        let mut stored = source.provider().load().await?;
+        merge_patch(&mut stored, &patch);
+        for (path, value) in &changes {
+            let replaces_secret = get_path(&snapshot.effective, path).is_some_and(is_encrypted);
+            if replaces_secret && !value.is_null() && !is_encrypted(value) {
+                set_path(&mut stored, path, Value::String(self.encrypt(value)?));
+            }
+        }
+        layers[index] = self.apply_overlay(source, stored.clone()).await?;
+        let patched = ConfigSnapshot::new(
+            snapshot.version + 1,
+            &self.defaults,
+            layers,
+            Arc::clone(&self.redaction),
+        );
+
+        // Compared with what is stored, which holds ciphertext where the
+        // patch held plaintext
+        let shadowed: Vec<String> = changes
+            .iter()
+            .filter(|(path, value)| {
+                let now = get_path(&patched.effective, path).filter(|now| !now.is_null());
+                if value.is_null() {
+                    now.is_some()
+                } else {
+                    now != get_path(&stored, path)
+                }
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        if !shadowed.is_empty() {
+            return Err(ConfigError::PatchShadowed {
+                target: source.name().to_string(),
+                keys: shadowed,
+            });
+        }
+
+        self.check_schema(&patched.effective)?;
+
+        source.provider().store(&stored).await?;
+        let previous = snapshot.version;
+        *snapshot = patched;
+        let version = snapshot.version;
+        drop(snapshot);
+
+        let keys: Vec<String> = changes.into_keys().collect();
+        tracing::info!(
+            target: "audit",
+            version,
+            previous_version = previous,
+            source = source.name(),
+            keys = ?keys,
+            "Configuration patched"
+        );
+        self.emit(ConfigEvent::Patched {
+            version,
+            source: source.name().to_string(),
+            keys,
+        });
+        Ok(())
+    }
+
+    /// A source's tree, with its overlay for the selected environment applied
+    pub(crate) async fn load_source(&self, source: &ConfigSource) -> Result<Value> {
+        let tree = source.provider().load().await?;
+        self.apply_overlay(source, tree).await
+    }
+
+    /// Merge the source's overlay for the selected environment over `tree`
+    async fn apply_overlay(&self, source: &ConfigSource, mut tree: Value) -> Result<Value> {
+        if let Some(environment) = &self.environment {
+            if let Some(overlay) = source.provider().load_overlay(environment).await? {
+                merge_with(&mut tree, &overlay, self.array_merge);
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Coerced value at `path`, `None` when missing or null
+    async fn field(&self, path: &str) -> Result<Option<Value>> {
+        let snapshot = self.snapshot.read().await;
+        let Some(value) = get_path(&snapshot.effective, path).filter(|value| !value.is_null()) else {
+            return Ok(None);
+        };
+        let mut value = value.clone();
+        drop(snapshot);
+        decrypt_tree(&mut value, path, self.encryption.as_deref())?;
+        self.coerce(&mut value, path)?;
+        Ok(Some(value))
+    }
+
+    /// `value`, encrypted with the current key; strings as they are, other
+    /// values as JSON
+    fn encrypt(&self, value: &Value) -> Result<String> {
+        let keys = self.encryption.as_deref().ok_or(ConfigError::EncryptionError)?;
+        match value {
+            Value::String(text) => keys.encrypt(text),
+            other => keys.encrypt(&other.to_string()),
+        }
+    }
+
+    /// Validate `effective` against the schema, if there is one
+    fn check_schema(&self, effective: &Value) -> Result<()> {
+        let Some(validator) = &self.validator else {
+            return Ok(());
+        };
+        let mut config = effective.clone();
+        self.coerce(&mut config, "")?;
+        let failures = validator.failures(&config, &self.redaction);
+        if failures.is_empty() {
+            return Ok(());
+        }
+        tracing::error!(
+            failures = ?failures.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            "Configuration rejected by its schema"
+        );
+        Err(ConfigError::SchemaViolation(failures))
+    }
+
+    /// Apply the coercions declared at or below `base` to `tree`, the value at `base`
+    fn coerce(&self, tree: &mut Value, base: &str) -> Result<()> {
+        for (path, coercion) in &self.coercions {
+            let relative = if base.is_empty() {
+                Some(path.as_str())
+            } else if path == base {
+                Some("")
+            } else {
+                path.strip_prefix(base).and_then(|rest| rest.strip_prefix('.'))
+            };
+            let Some(target) = relative.and_then(|relative| get_path_mut(tree, relative)) else {
+                continue;
+            };
+            if target.is_null() {
+                continue;
+            }
+            *target = coercion.apply(target).ok_or_else(|| ConfigError::InvalidField {
+                path: path.clone(),
+                reason: format!(
+                    "expected {}, found {}",
+                    coercion.expected(),
+                    self.redaction.redact(target, path)
+                ),
+            })?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn redaction(&self) -> &Redaction {
+        &self.redaction
+    }
+
+    /// Deserialize `value`, found at `base`, naming the failing path on error;
+    /// errors at sensitive paths leave out what the value was
+    fn deserialize_at<T: DeserializeOwned>(&self, value: Value, base: &str) -> Result<T> {
+        serde_path_to_error::deserialize(value).map_err(|error| {
+            let inner = error.path().to_string();
+            let path = match (base, inner.as_str()) {
+                (base, ".") => base.to_string(),
+                ("", inner) => inner.to_string(),
+                (base, inner) if inner.starts_with('[') => format!("{}{}", base, inner),
+                (base, inner) => format!("{}.{}", base, inner),
+            };
+            let reason = if !path.is_empty() && self.redaction.is_sensitive(&path) {
+                format!("{} cannot be read as {}", REDACTED, std::any::type_name::<T>())
+            } else {
+                format!("{} (reading {})", error.inner(), std::any::type_name::<T>())
+            };
+            ConfigError::InvalidField {
+                path: if path.is_empty() { "(root)".to_string() } else { path },
+                reason,
+            }
+        })
+    }
+
+    pub(crate) fn emit(&self, event: ConfigEvent) {
+        // Nobody listening is fine
+        let _ = self.events.send(event);
+    }
+}
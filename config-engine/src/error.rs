@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::validation::SchemaFailure;
+
 #[derive(Error, Debug)]
 pub enum ConfigError {
     #[error("Configuration source not found")]
@@ -16,18 +18,57 @@ pub enum ConfigError {
     
     #[error("Configuration decryption failed")]
     DecryptionError,
+
+    #[error("Configuration value at {pointer} cannot be decrypted")]
+    DecryptionFailed { pointer: String },
     
     #[error("Configuration watcher error")]
     WatcherError,
     
     #[error("Configuration template error")]
     TemplateError,
+
+    #[error("Configuration template is invalid: {0}")]
+    InvalidTemplate(String),
+
+    #[error("Configuration template has unresolved variables: {}", .0.join(", "))]
+    UnresolvedTemplate(Vec<String>),
     
     #[error("Remote configuration store connection failed")]
     RemoteStoreError,
+
+    #[error("Loading configuration from {name} failed: {reason}")]
+    SourceLoadError { name: String, reason: String },
+
+    #[error("Writing configuration to {name} failed: {reason}")]
+    SourceStoreError { name: String, reason: String },
+
+    #[error("Configuration source {0} is read-only")]
+    ReadOnlySource(String),
+
+    #[error("No configuration source accepts writes")]
+    NoWritableSource,
+
+    #[error("Patching {target} would not change {}: another source overrides them", keys.join(", "))]
+    PatchShadowed { target: String, keys: Vec<String> },
+
+    #[error("No {environment} overlay for configuration: {path} not found")]
+    MissingOverlay { environment: String, path: String },
+
+    #[error("Configuration value '{0}' is missing")]
+    MissingField(String),
+
+    #[error("Configuration value '{path}' is invalid: {reason}")]
+    InvalidField { path: String, reason: String },
     
     #[error("Configuration schema mismatch")]
     SchemaMismatch,
+
+    #[error(
+        "Configuration does not match its schema: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    SchemaViolation(Vec<SchemaFailure>),
     
     #[error("Internal error: {0}")]
     InternalError(#[from] anyhow::Error),
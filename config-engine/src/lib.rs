@@ -14,7 +14,7 @@
 //! 
 //! - **Local Files**: YAML, TOML, JSON configuration files
 //! - **Environment Variables**: System and container environment
-//! - **Remote Stores**: etcd, Consul, HashiCorp Vault
+//! - **Remote Stores**: etcd, Redis, Consul, HashiCorp Vault
 //! - **Databases**: PostgreSQL, MongoDB for large configurations
 //! - **Cloud Services**: AWS Parameter Store, Azure Key Vault, GCP Secret Manager
 //! 
@@ -53,6 +53,10 @@
 //! ```
 
 pub mod engine;
+pub mod coercion;
+pub mod drift;
+pub mod overlay;
+pub mod sensitive;
 pub mod providers;
 pub mod watchers;
 pub mod validation;
@@ -60,8 +64,18 @@ pub mod encryption;
 pub mod templates;
 pub mod error;
 
+mod tree;
+
 pub use engine::*;
+pub use coercion::Coercion;
+pub use drift::*;
+pub use encryption::{EncryptionKeyManager, ENCRYPTED_PREFIX};
+pub use overlay::ArrayMerge;
+pub use sensitive::{Sensitive, REDACTED};
 pub use providers::*;
+pub use watchers::{ConfigWatcher, WatchConfig};
+pub use templates::{render, TemplateContext};
+pub use validation::{ConfigValidator, JsonSchemaValidator, SchemaFailure};
 pub use error::*;
 
 // Re-export all public types and traits for easy access
@@ -0,0 +1,60 @@
+// Environment overlays
+//
+// A deployment keeps one base file, `config.base.yaml` or `config.yaml`, and
+// one overlay per environment beside it, `config.prod.yaml`. With an
+// environment selected, each file source loads its base and then its
+// overlay on top, and the two count as one layer of the engine.
+use std::path::{Path, PathBuf};
+
+use crate::error::{ConfigError, Result};
+
+/// Variable naming the active environment for `ConfigEngine::with_environment_from_env`
+pub const ENVIRONMENT_VAR: &str = "RUSTCARE_ENV";
+
+/// How an array in an overlay combines with the same array in its base
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The overlay's array replaces the base's
+    #[default]
+    Replace,
+    /// The overlay's items follow the base's
+    Append,
+}
+
+/// Environment named by `RUSTCARE_ENV`, if set and not blank
+pub fn environment_from_env() -> Option<String> {
+    std::env::var(ENVIRONMENT_VAR)
+        .ok()
+        .map(|environment| environment.trim().to_string())
+        .filter(|environment| !environment.is_empty())
+}
+
+/// Overlay file for `environment` beside `base`: `config.base.yaml` and
+/// `config.yaml` both become `config.prod.yaml`
+pub fn overlay_path(base: &Path, environment: &str) -> Result<PathBuf> {
+    let valid = !environment.is_empty()
+        && environment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(ConfigError::ValidationError(format!(
+            "environment name '{}' may only contain letters, digits, '-' and '_'",
+            environment
+        )));
+    }
+
+    let file_name = base
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| ConfigError::ValidationError(format!("{} has no file name", base.display())))?;
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (file_name, None),
+    };
+    let stem = stem.strip_suffix(".base").unwrap_or(stem);
+    let overlay = match extension {
+        Some(extension) => format!("{}.{}.{}", stem, environment, extension),
+        None => format!("{}.{}", stem, environment),
+    };
+    Ok(base.with_file_name(overlay))
+}
@@ -0,0 +1,307 @@
+// AWS Systems Manager Parameter Store and Secrets Manager
+//
+// Clients use the default AWS credential chain and are created on first
+// load. Neither service pushes changes, so both providers ask to be polled.
+//
+// Throttled requests are retried with capped exponential backoff, page by
+// page, so a busy account slows a load down instead of failing it.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, Region, SdkConfig};
+use aws_sdk_ssm::config::http::HttpResponse;
+use aws_sdk_ssm::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_ssm::types::ParameterType;
+use serde_json::{Map, Value};
+use tokio::sync::OnceCell;
+
+use crate::error::{ConfigError, Result};
+use crate::tree::set_path;
+
+use super::ConfigProvider;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Error codes AWS uses when a caller exceeds its request rate
+const THROTTLING_CODES: &[&str] = &[
+    "ThrottlingException",
+    "Throttling",
+    "TooManyRequestsException",
+    "RequestLimitExceeded",
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleBackoff {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for ThrottleBackoff {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ThrottleBackoff {
+    /// Delay before retry number `attempt` (from 1): exponential, capped, with
+    /// the upper half jittered so throttled instances do not retry in step
+    fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        let half = ceiling / 2;
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.subsec_nanos() as u64)
+            .unwrap_or_default();
+        half + Duration::from_nanos(nanos % (half.as_nanos() as u64).max(1))
+    }
+
+    async fn run<T, E, F, Fut>(&self, describe: &str, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = std::result::Result<T, SdkError<E, HttpResponse>>>,
+        E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Ok(output) => return Ok(output),
+                Err(error) if is_throttled(&error) && attempt < self.max_attempts => {
+                    let delay = self.delay(attempt);
+                    tracing::warn!(source = describe, attempt, ?delay, "AWS throttled configuration load; backing off");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(error) => {
+                    return Err(ConfigError::SourceLoadError {
+                        name: describe.to_string(),
+                        reason: aws_sdk_ssm::error::DisplayErrorContext(&error).to_string(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+fn is_throttled<E: ProvideErrorMetadata>(error: &SdkError<E, HttpResponse>) -> bool {
+    error.code().is_some_and(|code| THROTTLING_CODES.contains(&code))
+        || error
+            .raw_response()
+            .is_some_and(|response| response.status().as_u16() == 429)
+}
+
+async fn load_sdk_config(region: Option<&str>) -> SdkConfig {
+    let mut loader = aws_config::defaults(BehaviorVersion::latest());
+    if let Some(region) = region {
+        loader = loader.region(Region::new(region.to_string()));
+    }
+    loader.load().await
+}
+
+/// Parameters under a path, e.g. `/rustcare/prod/database/url` as `database.url`
+///
+/// SecureString parameters are decrypted. Values are kept as strings, and
+/// StringList parameters become arrays of strings.
+pub struct AwsSsmProvider {
+    path_prefix: String,
+    region: Option<String>,
+    poll_interval: Duration,
+    backoff: ThrottleBackoff,
+    client: OnceCell<aws_sdk_ssm::Client>,
+}
+
+impl AwsSsmProvider {
+    pub fn new(path_prefix: &str) -> Self {
+        let path_prefix = format!("/{}", path_prefix.trim_matches('/'));
+        Self {
+            path_prefix,
+            region: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            backoff: ThrottleBackoff::default(),
+            client: OnceCell::new(),
+        }
+    }
+
+    /// Region to use instead of the one from the environment or profile
+    pub fn with_region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_string());
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: ThrottleBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    async fn client(&self) -> &aws_sdk_ssm::Client {
+        self.client
+            .get_or_init(|| async { aws_sdk_ssm::Client::new(&load_sdk_config(self.region.as_deref()).await) })
+            .await
+    }
+}
+
+#[async_trait]
+impl ConfigProvider for AwsSsmProvider {
+    async fn load(&self) -> Result<Value> {
+        let client = self.client().await;
+        let describe = self.describe();
+        let mut tree = Value::Object(Map::new());
+        let mut next_token: Option<String> = None;
+        loop {
+            let page = self
+                .backoff
+                .run(&describe, || {
+                    client
+                        .get_parameters_by_path()
+                        .path(&self.path_prefix)
+                        .recursive(true)
+                        .with_decryption(true)
+                        .set_next_token(next_token.clone())
+                        .send()
+                })
+                .await?;
+
+            for parameter in page.parameters() {
+                let (Some(name), Some(value)) = (parameter.name(), parameter.value()) else {
+                    continue;
+                };
+                let Some(relative) = name.strip_prefix(&self.path_prefix) else {
+                    continue;
+                };
+                let path = relative.trim_matches('/').replace('/', ".");
+                if path.is_empty() {
+                    continue;
+                }
+                let value = match parameter.r#type() {
+                    Some(ParameterType::StringList) => Value::Array(
+                        value
+                            .split(',')
+                            .map(|item| Value::String(item.to_string()))
+                            .collect(),
+                    ),
+                    _ => Value::String(value.to_string()),
+                };
+                set_path(&mut tree, &path, value);
+            }
+
+            match page.next_token() {
+                Some(token) => next_token = Some(token.to_string()),
+                None => return Ok(tree),
+            }
+        }
+    }
+
+    fn poll_interval(&self) -> Option<Duration> {
+        Some(self.poll_interval)
+    }
+
+    fn describe(&self) -> String {
+        format!("AWS Parameter Store {}", self.path_prefix)
+    }
+}
+
+/// A Secrets Manager secret holding a JSON object
+pub struct AwsSecretsProvider {
+    secret_id: String,
+    /// Path to place the secret's keys under; the root when `None`
+    mount: Option<String>,
+    region: Option<String>,
+    poll_interval: Duration,
+    backoff: ThrottleBackoff,
+    client: OnceCell<aws_sdk_secretsmanager::Client>,
+}
+
+impl AwsSecretsProvider {
+    pub fn new(secret_id: &str) -> Self {
+        Self {
+            secret_id: secret_id.to_string(),
+            mount: None,
+            region: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            backoff: ThrottleBackoff::default(),
+            client: OnceCell::new(),
+        }
+    }
+
+    /// Place the secret's keys under `path` instead of at the root
+    pub fn mounted_at(mut self, path: &str) -> Self {
+        self.mount = Some(path.to_string());
+        self
+    }
+
+    pub fn with_region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_string());
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: ThrottleBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    async fn client(&self) -> &aws_sdk_secretsmanager::Client {
+        self.client
+            .get_or_init(|| async {
+                aws_sdk_secretsmanager::Client::new(&load_sdk_config(self.region.as_deref()).await)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl ConfigProvider for AwsSecretsProvider {
+    async fn load(&self) -> Result<Value> {
+        let client = self.client().await;
+        let describe = self.describe();
+        let output = self
+            .backoff
+            .run(&describe, || client.get_secret_value().secret_id(&self.secret_id).send())
+            .await?;
+
+        let invalid = |reason: &str| ConfigError::SourceLoadError {
+            name: describe.clone(),
+            reason: reason.to_string(),
+        };
+        let raw = output
+            .secret_string()
+            .ok_or_else(|| invalid("secret has no string value"))?;
+        let secret: Value = serde_json::from_str(raw).map_err(|_| invalid("secret is not JSON"))?;
+        if !secret.is_object() {
+            return Err(invalid("secret is not a JSON object"));
+        }
+
+        Ok(match &self.mount {
+            Some(path) => {
+                let mut tree = Value::Object(Map::new());
+                set_path(&mut tree, path, secret);
+                tree
+            }
+            None => secret,
+        })
+    }
+
+    fn poll_interval(&self) -> Option<Duration> {
+        Some(self.poll_interval)
+    }
+
+    fn describe(&self) -> String {
+        format!("AWS Secrets Manager secret {}", self.secret_id)
+    }
+}
@@ -0,0 +1,55 @@
+// Environment variables
+//
+// `RUSTCARE_DATABASE__POOL_SIZE=10` becomes `database.pool_size = "10"`: the
+// prefix is stripped, `__` separates levels and keys are lowercased. Values
+// stay strings. `RUSTCARE_ENV` selects the environment and is not itself
+// configuration.
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+use crate::overlay::ENVIRONMENT_VAR;
+use crate::tree::set_path;
+
+use super::ConfigProvider;
+
+pub const DEFAULT_PREFIX: &str = "RUSTCARE_";
+
+const LEVEL_SEPARATOR: &str = "__";
+
+pub struct EnvProvider {
+    prefix: String,
+}
+
+impl EnvProvider {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ConfigProvider for EnvProvider {
+    async fn load(&self) -> Result<Value> {
+        let mut tree = Value::Object(Map::new());
+        for (name, value) in std::env::vars() {
+            if name == ENVIRONMENT_VAR {
+                continue;
+            }
+            let Some(key) = name.strip_prefix(&self.prefix) else {
+                continue;
+            };
+            let path = key.to_lowercase().replace(LEVEL_SEPARATOR, ".");
+            if path.is_empty() || path.split('.').any(str::is_empty) {
+                continue;
+            }
+            set_path(&mut tree, &path, Value::String(value));
+        }
+        Ok(tree)
+    }
+
+    fn describe(&self) -> String {
+        format!("environment variables {}*", self.prefix)
+    }
+}
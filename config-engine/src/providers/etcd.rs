@@ -0,0 +1,178 @@
+// etcd, through its v3 JSON gateway
+//
+// Every key under the prefix is one value: `/rustcare/config/database/url`
+// is `database.url`. Values are parsed as JSON when they parse and kept as
+// strings otherwise.
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::error::{ConfigError, Result};
+use crate::tree::{flatten, parse_scalar, set_path};
+
+use super::ConfigProvider;
+
+pub const DEFAULT_PREFIX: &str = "/rustcare/config/";
+
+pub struct EtcdProvider {
+    endpoint: String,
+    prefix: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct RangeResponse {
+    #[serde(default)]
+    kvs: Vec<KeyValue>,
+}
+
+#[derive(Deserialize)]
+struct KeyValue {
+    key: String,
+    #[serde(default)]
+    value: String,
+}
+
+impl EtcdProvider {
+    pub fn new(endpoint: &str, prefix: &str) -> Self {
+        let endpoint = if endpoint.contains("://") {
+            endpoint.trim_end_matches('/').to_string()
+        } else {
+            format!("http://{}", endpoint.trim_end_matches('/'))
+        };
+        let prefix = if prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{}/", prefix)
+        };
+        Self {
+            endpoint,
+            prefix,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn call(&self, method: &str, body: Value) -> Result<Value> {
+        let response = self
+            .client
+            .post(format!("{}/v3/kv/{}", self.endpoint, method))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| self.error(e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(self.error(format!("{} {}", status, text)));
+        }
+        response.json().await.map_err(|e| self.error(e))
+    }
+
+    /// Stored values by key, relative to the prefix
+    async fn range(&self) -> Result<Vec<(String, String)>> {
+        let response = self
+            .call(
+                "range",
+                json!({
+                    "key": BASE64.encode(&self.prefix),
+                    "range_end": BASE64.encode(prefix_end(self.prefix.as_bytes())),
+                }),
+            )
+            .await?;
+        let response: RangeResponse = serde_json::from_value(response).map_err(|e| self.error(e))?;
+
+        let mut pairs = Vec::with_capacity(response.kvs.len());
+        for kv in response.kvs {
+            let key = self.decode(&kv.key)?;
+            let value = self.decode(&kv.value)?;
+            if let Some(relative) = key.strip_prefix(&self.prefix) {
+                pairs.push((relative.to_string(), value));
+            }
+        }
+        Ok(pairs)
+    }
+
+    fn decode(&self, encoded: &str) -> Result<String> {
+        let bytes = BASE64.decode(encoded).map_err(|e| self.error(e))?;
+        String::from_utf8(bytes).map_err(|e| self.error(e))
+    }
+
+    fn error(&self, reason: impl std::fmt::Display) -> ConfigError {
+        ConfigError::SourceLoadError {
+            name: self.describe(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ConfigProvider for EtcdProvider {
+    async fn load(&self) -> Result<Value> {
+        let mut tree = Value::Object(Map::new());
+        for (key, raw) in self.range().await? {
+            let path = key.trim_matches('/').replace('/', ".");
+            if !path.is_empty() {
+                set_path(&mut tree, &path, parse_scalar(&raw));
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Put changed leaves and delete keys no longer present
+    async fn store(&self, value: &Value) -> Result<()> {
+        let current: std::collections::HashMap<String, String> = self.range().await?.into_iter().collect();
+        let wanted: std::collections::HashMap<String, String> = flatten(value)
+            .into_iter()
+            .map(|(path, leaf)| {
+                let raw = match leaf {
+                    Value::String(text) => text,
+                    other => other.to_string(),
+                };
+                (path.replace('.', "/"), raw)
+            })
+            .collect();
+
+        for (key, raw) in &wanted {
+            if current.get(key) != Some(raw) {
+                self.call(
+                    "put",
+                    json!({
+                        "key": BASE64.encode(format!("{}{}", self.prefix, key)),
+                        "value": BASE64.encode(raw),
+                    }),
+                )
+                .await?;
+            }
+        }
+        for key in current.keys().filter(|key| !wanted.contains_key(*key)) {
+            self.call(
+                "deleterange",
+                json!({ "key": BASE64.encode(format!("{}{}", self.prefix, key)) }),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    fn is_writable(&self) -> bool {
+        true
+    }
+
+    fn describe(&self) -> String {
+        format!("etcd {}{}", self.endpoint, self.prefix)
+    }
+}
+
+/// First key after every key starting with `prefix`
+fn prefix_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return end;
+        }
+    }
+    // Every byte was 0xff: range to the end of the keyspace
+    vec![0]
+}
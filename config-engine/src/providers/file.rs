@@ -0,0 +1,265 @@
+// Local configuration files
+//
+// Files are rendered as templates before they are parsed, expanding
+// `${VAR}` placeholders from the environment.
+//
+// Files are read-only unless made `writable`. A write replaces the whole
+// file in its own format, so comments and formatting in it are lost; a file
+// with placeholders is not written, as that would bake in their values.
+//
+// Changes are pushed by file system notifications on the file's directory,
+// for the file and any overlay of it beside it. Editors and `store` replace
+// a file by renaming over it, which a watch on the file itself would lose.
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use notify::{RecursiveMode, Watcher};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::encryption::ENCRYPTED_PREFIX;
+use crate::error::{ConfigError, Result};
+use crate::overlay::overlay_path;
+use crate::templates::{render, TemplateContext};
+
+use super::{ConfigProvider, SourceNotification};
+
+/// Notifications buffered for the engine; a full buffer already holds a
+/// pending change, so further ones are dropped
+const NOTIFICATION_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl FileFormat {
+    /// Format by extension; anything unrecognised is read as YAML, which also covers JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => FileFormat::Toml,
+            Some("json") => FileFormat::Json,
+            _ => FileFormat::Yaml,
+        }
+    }
+}
+
+pub struct FileProvider {
+    path: PathBuf,
+    format: FileFormat,
+    writable: bool,
+    templates: Option<TemplateContext>,
+}
+
+impl FileProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let format = FileFormat::from_path(&path);
+        Self {
+            path,
+            format,
+            writable: false,
+            templates: Some(TemplateContext::from_env()),
+        }
+    }
+
+    pub fn with_format(mut self, format: FileFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Accept writes, such as `ConfigEngine::apply_patch` and drift
+    /// reconciliation
+    pub fn writable(mut self) -> Self {
+        self.writable = true;
+        self
+    }
+
+    /// Expand placeholders from `context` instead of the environment
+    pub fn with_template_context(mut self, context: TemplateContext) -> Self {
+        self.templates = Some(context);
+        self
+    }
+
+    /// Parse the file as it is, without expanding placeholders
+    pub fn without_templates(mut self) -> Self {
+        self.templates = None;
+        self
+    }
+
+    async fn read(&self, path: &Path) -> Result<Value> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| load_error(path, e))?;
+        let content = match &self.templates {
+            Some(context) => render(&content, context).map_err(|e| load_error(path, e))?,
+            None => content,
+        };
+        let value = match self.format {
+            FileFormat::Yaml => {
+                let yaml = serde_yaml::from_str::<serde_yaml::Value>(&content).map_err(|e| load_error(path, e))?;
+                serde_json::to_value(untag(yaml)).map_err(|e| load_error(path, e))?
+            }
+            FileFormat::Toml => toml::from_str::<Value>(&content).map_err(|e| load_error(path, e))?,
+            FileFormat::Json => serde_json::from_str::<Value>(&content).map_err(|e| load_error(path, e))?,
+        };
+        // An empty YAML document is null; treat it as an empty file
+        Ok(if value.is_null() {
+            Value::Object(Default::default())
+        } else {
+            value
+        })
+    }
+
+    fn serialize(&self, value: &Value) -> Result<String> {
+        match self.format {
+            FileFormat::Yaml => serde_yaml::to_string(value).map_err(|e| store_error(&self.path, e)),
+            FileFormat::Json => serde_json::to_string_pretty(value).map_err(|e| store_error(&self.path, e)),
+            FileFormat::Toml => {
+                let content = toml::to_string_pretty(value).map_err(|e| store_error(&self.path, e))?;
+                // TOML has no null and no way to mix tables into arrays of
+                // values; refuse anything that would not read back as written
+                let reread = toml::from_str::<Value>(&content).map_err(|e| store_error(&self.path, e))?;
+                if &reread != value {
+                    return Err(store_error(&self.path, "value cannot be represented in TOML"));
+                }
+                Ok(content)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ConfigProvider for FileProvider {
+    async fn load(&self) -> Result<Value> {
+        self.read(&self.path).await
+    }
+
+    async fn store(&self, value: &Value) -> Result<()> {
+        if !self.writable {
+            return Err(ConfigError::ReadOnlySource(self.describe()));
+        }
+        if self.templates.is_some() {
+            let current = tokio::fs::read_to_string(&self.path).await.unwrap_or_default();
+            if current.contains("${") {
+                return Err(store_error(
+                    &self.path,
+                    "file has template placeholders, which a write would replace with their values",
+                ));
+            }
+        }
+        let content = self.serialize(value)?;
+        // Written alongside and renamed over, so a reader never sees half a file
+        let file_name = self.path.file_name().and_then(|name| name.to_str()).unwrap_or("config");
+        let temporary = self.path.with_file_name(format!(".{}.tmp", file_name));
+        tokio::fs::write(&temporary, content)
+            .await
+            .map_err(|e| store_error(&self.path, e))?;
+        tokio::fs::rename(&temporary, &self.path)
+            .await
+            .map_err(|e| store_error(&self.path, e))
+    }
+
+    fn is_writable(&self) -> bool {
+        self.writable
+    }
+
+    async fn load_overlay(&self, environment: &str) -> Result<Option<Value>> {
+        let path = overlay_path(&self.path, environment)?;
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Err(ConfigError::MissingOverlay {
+                environment: environment.to_string(),
+                path: path.display().to_string(),
+            });
+        }
+        self.read(&path).await.map(Some)
+    }
+
+    fn subscribe(&self) -> Option<mpsc::Receiver<SourceNotification>> {
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        // `config.yaml`, `config.base.yaml` and `config.prod.yaml` all start `config.`
+        let stem = self
+            .path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split('.').next())
+            .map(|stem| format!("{}.", stem))
+            .unwrap_or_default();
+
+        let (sender, receiver) = mpsc::channel(NOTIFICATION_CAPACITY);
+        let notifier = sender.clone();
+        let handler = move |event: notify::Result<notify::Event>| {
+            // An error may have hidden a change
+            let relevant = event.map_or(true, |event| {
+                !event.kind.is_access()
+                    && event.paths.iter().any(|path| {
+                        path.file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(|name| name.starts_with(&stem))
+                    })
+            });
+            if relevant {
+                let _ = notifier.try_send(SourceNotification::Changed);
+            }
+        };
+        let watching = notify::recommended_watcher(handler)
+            .and_then(|mut watcher| watcher.watch(&directory, RecursiveMode::NonRecursive).map(|_| watcher));
+        let watcher = match watching {
+            Ok(watcher) => watcher,
+            Err(error) => {
+                tracing::error!(source = %self.describe(), error = %error, "Cannot watch configuration file");
+                return None;
+            }
+        };
+        // Watching stops when the watcher is dropped
+        tokio::spawn(async move {
+            sender.closed().await;
+            drop(watcher);
+        });
+        Some(receiver)
+    }
+
+    fn describe(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+}
+
+/// Turn `!enc` scalars into `enc:`-marked strings; other tags are dropped,
+/// keeping their values
+fn untag(value: serde_yaml::Value) -> serde_yaml::Value {
+    match value {
+        serde_yaml::Value::Tagged(tagged) => {
+            let tagged = *tagged;
+            match tagged.value {
+                serde_yaml::Value::String(ciphertext) if tagged.tag == "enc" => {
+                    serde_yaml::Value::String(format!("{}{}", ENCRYPTED_PREFIX, ciphertext))
+                }
+                value => untag(value),
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            serde_yaml::Value::Mapping(mapping.into_iter().map(|(key, value)| (key, untag(value))).collect())
+        }
+        serde_yaml::Value::Sequence(items) => serde_yaml::Value::Sequence(items.into_iter().map(untag).collect()),
+        other => other,
+    }
+}
+
+fn load_error(path: &Path, reason: impl std::fmt::Display) -> ConfigError {
+    ConfigError::SourceLoadError {
+        name: format!("file {}", path.display()),
+        reason: reason.to_string(),
+    }
+}
+
+fn store_error(path: &Path, reason: impl std::fmt::Display) -> ConfigError {
+    ConfigError::SourceStoreError {
+        name: format!("file {}", path.display()),
+        reason: reason.to_string(),
+    }
+}
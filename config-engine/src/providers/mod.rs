@@ -0,0 +1,193 @@
+// Configuration providers (file, env, etcd, redis, ...)
+//
+// A provider loads its whole contents as one tree. Writable providers can
+// also replace their contents, which is how the engine pushes values back
+// to a store such as etcd. Providers that can notify of changes, such as
+// Redis and files, push them through a subscription; those that cannot ask
+// to be polled instead.
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::error::{ConfigError, Result};
+
+#[cfg(feature = "aws")]
+pub mod aws;
+pub mod env;
+pub mod etcd;
+pub mod file;
+pub mod redis;
+
+#[cfg(feature = "aws")]
+pub use aws::{AwsSecretsProvider, AwsSsmProvider, ThrottleBackoff};
+pub use env::EnvProvider;
+pub use etcd::EtcdProvider;
+pub use file::{FileFormat, FileProvider};
+pub use self::redis::RedisProvider;
+
+/// A change a provider pushes to the engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceNotification {
+    /// Something in the source changed
+    Changed,
+    /// The subscription was lost and is back; changes made in between
+    /// were not notified
+    Reconnected,
+}
+
+#[async_trait]
+pub trait ConfigProvider: Send + Sync {
+    async fn load(&self) -> Result<Value>;
+
+    /// Replace the provider's contents with `value`
+    async fn store(&self, _value: &Value) -> Result<()> {
+        Err(ConfigError::ReadOnlySource(self.describe()))
+    }
+
+    fn is_writable(&self) -> bool {
+        false
+    }
+
+    /// Overlay for `environment` to merge over `load`'s result; `None` when
+    /// the provider has no notion of environments
+    async fn load_overlay(&self, _environment: &str) -> Result<Option<Value>> {
+        Ok(None)
+    }
+
+    /// How often to load again to pick up changes; `None` to never poll
+    fn poll_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Changes pushed by the store, for providers that can notify; `None`
+    /// when the provider cannot. The provider keeps the subscription up,
+    /// re-establishing it as needed, until the receiver is dropped.
+    fn subscribe(&self) -> Option<mpsc::Receiver<SourceNotification>> {
+        None
+    }
+
+    /// Where the provider reads from, for errors and reports
+    fn describe(&self) -> String;
+}
+
+/// A named provider added to a `ConfigEngine`
+///
+/// Sources with a higher `priority` override those with a lower one; among
+/// sources of equal priority, the one added later wins. Priority defaults
+/// to 0, so without it the order sources are added in decides.
+#[derive(Clone)]
+pub struct ConfigSource {
+    name: String,
+    priority: i32,
+    provider: Arc<dyn ConfigProvider>,
+}
+
+impl std::fmt::Debug for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigSource")
+            .field("name", &self.name)
+            .field("priority", &self.priority)
+            .field("provider", &self.provider.describe())
+            .finish()
+    }
+}
+
+impl ConfigSource {
+    /// YAML, TOML or JSON file, by extension; with an environment selected,
+    /// its overlay file is applied on top
+    pub fn file(path: &str) -> Self {
+        Self::custom(&format!("file:{}", path), FileProvider::new(path))
+    }
+
+    /// `file` that accepts writes, rewriting the file in its format
+    pub fn writable_file(path: &str) -> Self {
+        Self::custom(&format!("file:{}", path), FileProvider::new(path).writable())
+    }
+
+    /// `RUSTCARE_`-prefixed environment variables, `__` separating levels
+    pub fn env() -> Self {
+        Self::custom("env", EnvProvider::new(env::DEFAULT_PREFIX))
+    }
+
+    pub fn env_with_prefix(prefix: &str) -> Self {
+        Self::custom(&format!("env:{}", prefix), EnvProvider::new(prefix))
+    }
+
+    /// Keys under `/rustcare/config/` in etcd
+    pub fn etcd(endpoint: &str) -> Self {
+        Self::etcd_with_prefix(endpoint, etcd::DEFAULT_PREFIX)
+    }
+
+    pub fn etcd_with_prefix(endpoint: &str, prefix: &str) -> Self {
+        Self::custom(&format!("etcd:{}{}", endpoint, prefix), EtcdProvider::new(endpoint, prefix))
+    }
+
+    /// Keys under `rustcare:config:` in Redis, `:` separating levels;
+    /// changes arrive through keyspace notifications
+    pub fn redis(url: &str) -> Self {
+        Self::redis_with_prefix(url, redis::DEFAULT_PREFIX)
+    }
+
+    pub fn redis_with_prefix(url: &str, prefix: &str) -> Self {
+        Self::custom(
+            &format!("redis:{} {}", redis::redact_url(url), prefix),
+            RedisProvider::new(url, prefix),
+        )
+    }
+
+    /// Parameters under `path_prefix` in AWS Parameter Store
+    #[cfg(feature = "aws")]
+    pub fn aws_ssm(path_prefix: &str) -> Self {
+        Self::custom(&format!("aws-ssm:{}", path_prefix), AwsSsmProvider::new(path_prefix))
+    }
+
+    #[cfg(feature = "aws")]
+    pub fn aws_ssm_with_region(path_prefix: &str, region: &str) -> Self {
+        Self::custom(
+            &format!("aws-ssm:{}", path_prefix),
+            AwsSsmProvider::new(path_prefix).with_region(region),
+        )
+    }
+
+    /// A JSON secret in AWS Secrets Manager, merged at the root
+    #[cfg(feature = "aws")]
+    pub fn aws_secrets(secret_id: &str) -> Self {
+        Self::custom(&format!("aws-secrets:{}", secret_id), AwsSecretsProvider::new(secret_id))
+    }
+
+    #[cfg(feature = "aws")]
+    pub fn aws_secrets_with_region(secret_id: &str, region: &str) -> Self {
+        Self::custom(
+            &format!("aws-secrets:{}", secret_id),
+            AwsSecretsProvider::new(secret_id).with_region(region),
+        )
+    }
+
+    pub fn custom(name: &str, provider: impl ConfigProvider + 'static) -> Self {
+        Self {
+            name: name.to_string(),
+            priority: 0,
+            provider: Arc::new(provider),
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn provider(&self) -> &dyn ConfigProvider {
+        self.provider.as_ref()
+    }
+}
@@ -0,0 +1,259 @@
+// Redis, with keyspace notifications for changes
+//
+// Every string key under the prefix is one value: `rustcare:config:database:url`
+// is `database.url`. Values are parsed as JSON when they parse and kept as
+// strings otherwise, unless the provider is told to keep them all raw.
+//
+// Changes are pushed rather than polled. Subscribing turns on keyspace
+// notifications (`CONFIG SET notify-keyspace-events KEA`) and listens for
+// events on keys under the prefix. A dropped subscription is re-established
+// with backoff; since changes made meanwhile were missed, the engine is then
+// told to read the whole prefix again.
+use std::time::Duration;
+
+use ::redis::aio::ConnectionManager;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::{Map, Value};
+use tokio::sync::{mpsc, OnceCell};
+
+use crate::error::{ConfigError, Result};
+use crate::tree::{parse_scalar, set_path};
+
+use super::{ConfigProvider, SourceNotification};
+
+pub const DEFAULT_PREFIX: &str = "rustcare:config:";
+
+/// Keys fetched per `MGET`
+const BATCH_SIZE: usize = 100;
+
+/// Notifications buffered for the engine; a full buffer already holds a
+/// pending change, so further ones are dropped
+const NOTIFICATION_CAPACITY: usize = 16;
+
+const RECONNECT_MIN: Duration = Duration::from_millis(500);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+pub struct RedisProvider {
+    url: String,
+    prefix: String,
+    raw_values: bool,
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl RedisProvider {
+    pub fn new(url: &str, prefix: &str) -> Self {
+        let prefix = if prefix.ends_with(':') {
+            prefix.to_string()
+        } else {
+            format!("{}:", prefix)
+        };
+        Self {
+            url: url.to_string(),
+            prefix,
+            raw_values: false,
+            connection: OnceCell::new(),
+        }
+    }
+
+    /// Keep every value as a string rather than parsing it as JSON
+    pub fn with_raw_values(mut self) -> Self {
+        self.raw_values = true;
+        self
+    }
+
+    fn client(&self) -> Result<::redis::Client> {
+        ::redis::Client::open(self.url.as_str()).map_err(|e| self.error(e))
+    }
+
+    /// Shared connection, reconnecting by itself after failures
+    async fn connection(&self) -> Result<ConnectionManager> {
+        self.connection
+            .get_or_try_init(|| async { ConnectionManager::new(self.client()?).await.map_err(|e| self.error(e)) })
+            .await
+            .cloned()
+    }
+
+    /// Keys under the prefix
+    async fn keys(&self, connection: &mut ConnectionManager) -> Result<Vec<String>> {
+        let pattern = format!("{}*", escape_pattern(&self.prefix));
+        let mut keys = vec![];
+        let mut cursor: u64 = 0;
+        loop {
+            let (next, batch): (u64, Vec<String>) = ::redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(BATCH_SIZE)
+                .query_async(connection)
+                .await
+                .map_err(|e| self.error(e))?;
+            keys.extend(batch);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        // SCAN may return a key more than once
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    fn error(&self, reason: impl std::fmt::Display) -> ConfigError {
+        ConfigError::SourceLoadError {
+            name: self.describe(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ConfigProvider for RedisProvider {
+    async fn load(&self) -> Result<Value> {
+        let mut connection = self.connection().await?;
+        let keys = self.keys(&mut connection).await?;
+
+        let mut tree = Value::Object(Map::new());
+        for batch in keys.chunks(BATCH_SIZE) {
+            // Keys of other types, or deleted since the scan, come back nil
+            let values: Vec<Option<String>> = ::redis::cmd("MGET")
+                .arg(batch)
+                .query_async(&mut connection)
+                .await
+                .map_err(|e| self.error(e))?;
+            for (key, raw) in batch.iter().zip(values) {
+                let (Some(relative), Some(raw)) = (key.strip_prefix(&self.prefix), raw) else {
+                    continue;
+                };
+                let path = relative.trim_matches(':').replace(':', ".");
+                if path.is_empty() {
+                    continue;
+                }
+                let value = if self.raw_values {
+                    Value::String(raw)
+                } else {
+                    parse_scalar(&raw)
+                };
+                set_path(&mut tree, &path, value);
+            }
+        }
+        Ok(tree)
+    }
+
+    fn subscribe(&self) -> Option<mpsc::Receiver<SourceNotification>> {
+        let client = match self.client() {
+            Ok(client) => client,
+            Err(error) => {
+                tracing::error!(source = %self.describe(), error = %error, "Cannot watch Redis configuration");
+                return None;
+            }
+        };
+        let (sender, receiver) = mpsc::channel(NOTIFICATION_CAPACITY);
+        tokio::spawn(listen(client, self.prefix.clone(), self.describe(), sender));
+        Some(receiver)
+    }
+
+    fn describe(&self) -> String {
+        format!("redis {} {}", redact_url(&self.url), self.prefix)
+    }
+}
+
+/// Forward keyspace events under `prefix` until the receiver is dropped,
+/// resubscribing whenever the subscription is lost
+async fn listen(
+    client: ::redis::Client,
+    prefix: String,
+    describe: String,
+    sender: mpsc::Sender<SourceNotification>,
+) {
+    let channel = format!(
+        "__keyspace@{}__:{}*",
+        client.get_connection_info().redis.db,
+        escape_pattern(&prefix)
+    );
+    let mut backoff = RECONNECT_MIN;
+    let mut missed = false;
+    loop {
+        match subscribe_keyspace(&client, &channel).await {
+            Ok(mut pubsub) => {
+                backoff = RECONNECT_MIN;
+                if missed {
+                    tracing::info!(source = %describe, "Redis configuration subscription restored");
+                    if sender.send(SourceNotification::Reconnected).await.is_err() {
+                        return;
+                    }
+                }
+                let mut messages = pubsub.on_message();
+                loop {
+                    tokio::select! {
+                        message = messages.next() => {
+                            if message.is_none() {
+                                break;
+                            }
+                            if let Err(mpsc::error::TrySendError::Closed(_)) =
+                                sender.try_send(SourceNotification::Changed)
+                            {
+                                return;
+                            }
+                        }
+                        _ = sender.closed() => return,
+                    }
+                }
+                tracing::warn!(source = %describe, "Redis configuration subscription dropped; resubscribing");
+            }
+            Err(error) => {
+                tracing::warn!(source = %describe, error = %error, "Cannot subscribe to Redis configuration changes");
+            }
+        }
+        missed = true;
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = sender.closed() => return,
+        }
+        backoff = (backoff * 2).min(RECONNECT_MAX);
+    }
+}
+
+async fn subscribe_keyspace(client: &::redis::Client, channel: &str) -> ::redis::RedisResult<::redis::aio::PubSub> {
+    let mut connection = client.get_multiplexed_tokio_connection().await?;
+    // Managed Redis often refuses CONFIG; notifications may already be on
+    let enabled: ::redis::RedisResult<()> = ::redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("notify-keyspace-events")
+        .arg("KEA")
+        .query_async(&mut connection)
+        .await;
+    if let Err(error) = enabled {
+        tracing::warn!(error = %error, "Cannot enable Redis keyspace notifications; assuming they are configured");
+    }
+
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.psubscribe(channel).await?;
+    Ok(pubsub)
+}
+
+/// Escape glob characters, for use in `SCAN MATCH` and `PSUBSCRIBE`
+fn escape_pattern(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// URL with any password replaced, for errors and logs
+pub(crate) fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("***"));
+            parsed.to_string()
+        }
+        Ok(_) => url.to_string(),
+        Err(_) => "(invalid url)".to_string(),
+    }
+}
@@ -0,0 +1,146 @@
+// Masking of sensitive configuration values
+//
+// A value is sensitive when its path matches a path registered with
+// `ConfigEngine::with_sensitive`, or when its key names a credential
+// (`password`, `api_key`, ...). Masking works on the merged tree, so a secret
+// is hidden the same way whether it came from a file, the environment or a
+// remote store. Sensitive values become `***`; other strings still pass
+// through the logger's PII patterns, so an email address or SSN left in a
+// description field is caught too.
+//
+// Fields typed as `Sensitive<T>` stay masked wherever the deserialized
+// configuration is printed or serialized.
+use std::collections::BTreeSet;
+use std::fmt;
+
+use logger_redacted::PiiRedactor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+pub use logger_redacted::RedactionConfig;
+
+/// What masked values are replaced with
+pub const REDACTED: &str = "***";
+
+/// Key name fragments that mark a value as sensitive wherever it appears
+const SENSITIVE_KEY_FRAGMENTS: &[&str] = &[
+    "password",
+    "passphrase",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "private_key",
+    "credential",
+];
+
+/// A configuration value that prints and serializes as `***`
+///
+/// Deserializes exactly like `T`; use `expose` where the real value is needed.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Sensitive<T>(T);
+
+impl<T> Sensitive<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Sensitive<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Serialize for Sensitive<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Sensitive<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+/// Which paths are sensitive, and the PII patterns applied to everything else
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Redaction {
+    /// `.`-separated paths; `*` matches any one key
+    paths: BTreeSet<String>,
+    values: RedactionConfig,
+}
+
+impl Redaction {
+    pub(crate) fn add_path(&mut self, path: &str) {
+        self.paths.insert(path.to_string());
+    }
+
+    pub(crate) fn set_value_patterns(&mut self, values: RedactionConfig) {
+        self.values = values;
+    }
+
+    /// Whether `path`, or an object containing it, is sensitive
+    pub(crate) fn is_sensitive(&self, path: &str) -> bool {
+        let keys: Vec<&str> = path.split('.').collect();
+        let registered = self.paths.iter().any(|pattern| {
+            let pattern: Vec<&str> = pattern.split('.').collect();
+            pattern.len() <= keys.len()
+                && pattern
+                    .iter()
+                    .zip(&keys)
+                    .all(|(expected, key)| *expected == "*" || expected == key)
+        });
+        registered || keys.iter().any(|key| is_sensitive_key(key))
+    }
+
+    /// Copy of `tree`, the value at `base`, with sensitive values masked
+    pub(crate) fn redact(&self, tree: &Value, base: &str) -> Value {
+        let mut tree = tree.clone();
+        let redactor = PiiRedactor::new(self.values.clone());
+        self.redact_in_place(&mut tree, base, &redactor);
+        tree
+    }
+
+    fn redact_in_place(&self, value: &mut Value, path: &str, redactor: &PiiRedactor) {
+        if !path.is_empty() && self.is_sensitive(path) {
+            if !value.is_null() {
+                *value = Value::String(REDACTED.to_string());
+            }
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map.iter_mut() {
+                    let child_path = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    self.redact_in_place(child, &child_path, redactor);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.redact_in_place(item, path, redactor);
+                }
+            }
+            Value::String(text) => *text = redactor.redact(text),
+            _ => {}
+        }
+    }
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase().replace('-', "_");
+    SENSITIVE_KEY_FRAGMENTS.iter().any(|fragment| key.contains(fragment))
+}
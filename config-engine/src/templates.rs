@@ -1,10 +1,129 @@
-// Configuration templates for dynamic generation stub
-pub struct ConfigTemplate {
-    // Implementation will go here
+// Placeholder expansion in configuration text
+//
+// File sources are rendered before they are parsed, so the expanded text is
+// what is read as YAML, TOML or JSON. Placeholders follow the shell:
+//
+// - `${VAR}` is the variable's value; unset is an error
+// - `${VAR:-default}` is `default` when the variable is unset or empty
+// - `${VAR:?message}` fails with `message` when it is unset or empty
+//
+// `$${` is a literal `${`. Every unset variable is reported at once rather
+// than the render stopping at the first.
+use std::collections::HashMap;
+
+use crate::error::{ConfigError, Result};
+
+/// Where placeholders find their values
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    variables: HashMap<String, String>,
+    /// Fall back to the process environment
+    environment: bool,
 }
 
-impl ConfigTemplate {
-    pub fn render(&self, context: &serde_json::Value) -> crate::error::Result<String> {
-        Ok("{}".to_string()) // Stub implementation
+impl TemplateContext {
+    /// Context with no variables
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Context reading the process environment when rendering
+    pub fn from_env() -> Self {
+        Self {
+            variables: HashMap::new(),
+            environment: true,
+        }
+    }
+
+    /// Set `name`, taking precedence over the environment
+    pub fn with_variable(mut self, name: &str, value: &str) -> Self {
+        self.variables.insert(name.to_string(), value.to_string());
+        self
     }
-}
\ No newline at end of file
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        self.variables
+            .get(name)
+            .cloned()
+            .or_else(|| if self.environment { std::env::var(name).ok() } else { None })
+    }
+}
+
+/// Expand the placeholders in `input`
+pub fn render(input: &str, ctx: &TemplateContext) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut unresolved = vec![];
+    let mut rest = input;
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start..];
+        if let Some(literal) = after.strip_prefix("$${") {
+            output.push_str("${");
+            rest = literal;
+            continue;
+        }
+        let Some(body) = after.strip_prefix("${") else {
+            output.push('$');
+            rest = &after[1..];
+            continue;
+        };
+        let end = body.find('}').ok_or_else(|| {
+            ConfigError::InvalidTemplate(format!("unterminated placeholder at byte {}", input.len() - after.len()))
+        })?;
+        let placeholder = &body[..end];
+        rest = &body[end + 1..];
+
+        match expand(placeholder, ctx)? {
+            Ok(value) => output.push_str(&value),
+            Err(missing) => unresolved.push(missing),
+        }
+    }
+    output.push_str(rest);
+
+    if unresolved.is_empty() {
+        Ok(output)
+    } else {
+        Err(ConfigError::UnresolvedTemplate(unresolved))
+    }
+}
+
+/// The value of one placeholder, or a description of why it has none
+fn expand(placeholder: &str, ctx: &TemplateContext) -> Result<std::result::Result<String, String>> {
+    let (name, operator) = match placeholder.find(':') {
+        Some(colon) => (&placeholder[..colon], Some(&placeholder[colon + 1..])),
+        None => (placeholder, None),
+    };
+    if !is_variable_name(name) {
+        return Err(ConfigError::InvalidTemplate(format!("invalid variable name in ${{{}}}", placeholder)));
+    }
+
+    let value = ctx.lookup(name);
+    Ok(match operator {
+        None => value.ok_or_else(|| name.to_string()),
+        Some(operator) => {
+            let set = value.filter(|value| !value.is_empty());
+            if let Some(default) = operator.strip_prefix('-') {
+                Ok(set.unwrap_or_else(|| default.to_string()))
+            } else if let Some(message) = operator.strip_prefix('?') {
+                set.ok_or_else(|| {
+                    if message.is_empty() {
+                        format!("{} is required", name)
+                    } else {
+                        format!("{}: {}", name, message)
+                    }
+                })
+            } else {
+                return Err(ConfigError::InvalidTemplate(format!(
+                    "unsupported operator in ${{{}}}; expected :- or :?",
+                    placeholder
+                )));
+            }
+        }
+    })
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
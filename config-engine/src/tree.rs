@@ -0,0 +1,138 @@
+// Helpers for configuration trees
+//
+// Configuration is held as a JSON tree. Keys are addressed with `.`-separated
+// paths such as `database.pool.size`; arrays are treated as single values.
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use crate::overlay::ArrayMerge;
+
+/// Merge `overlay` into `base`; objects merge key by key, anything else replaces
+pub(crate) fn deep_merge(base: &mut Value, overlay: &Value) {
+    merge_with(base, overlay, ArrayMerge::Replace)
+}
+
+/// Like `deep_merge`, with `arrays` deciding how an array in `overlay`
+/// combines with an array already in `base`
+pub(crate) fn merge_with(base: &mut Value, overlay: &Value, arrays: ArrayMerge) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(key) {
+                    Some(existing) => merge_with(existing, value, arrays),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay)) if arrays == ArrayMerge::Append => {
+            base.extend(overlay.iter().cloned());
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+/// Apply an RFC 7386 JSON Merge Patch: objects merge key by key, `null`
+/// removes a key, anything else replaces
+pub(crate) fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(map) = target else {
+        unreachable!("replaced with an object above");
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            map.remove(key);
+        } else {
+            merge_patch(map.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Leaf values by path; empty objects have no leaves
+pub(crate) fn flatten(value: &Value) -> BTreeMap<String, Value> {
+    let mut leaves = BTreeMap::new();
+    collect_leaves(value, String::new(), &mut leaves);
+    leaves
+}
+
+fn collect_leaves(value: &Value, path: String, leaves: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                collect_leaves(child, child_path, leaves);
+            }
+        }
+        leaf if !path.is_empty() => {
+            leaves.insert(path, leaf.clone());
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn get_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+/// Mutable value at `path`; the empty path is `value` itself
+pub(crate) fn get_path_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |value, key| value.get_mut(key))
+}
+
+/// Set the value at `path`, creating objects along the way
+pub(crate) fn set_path(target: &mut Value, path: &str, value: Value) {
+    let mut current = target;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let Value::Object(map) = current else {
+            unreachable!("replaced with an object above");
+        };
+        if keys.peek().is_none() {
+            map.insert(key.to_string(), value);
+            return;
+        }
+        current = map.entry(key.to_string()).or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+/// Remove the value at `path`, and any objects that become empty
+pub(crate) fn remove_path(target: &mut Value, path: &str) {
+    let Some((first, rest)) = path.split_once('.') else {
+        if let Value::Object(map) = target {
+            map.remove(path);
+        }
+        return;
+    };
+    let Some(child) = target.get_mut(first) else {
+        return;
+    };
+    remove_path(child, rest);
+    if child.as_object().is_some_and(Map::is_empty) {
+        if let Value::Object(map) = target {
+            map.remove(first);
+        }
+    }
+}
+
+/// Parse a scalar from a key-value store: JSON when it parses, else a string
+pub(crate) fn parse_scalar(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
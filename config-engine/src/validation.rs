@@ -1,4 +1,100 @@
-// Configuration validation and schema enforcement stub
+// Configuration validation and schema enforcement
+//
+// A JSON Schema (draft 2020-12) attached with `ConfigEngine::with_schema` is
+// checked against every merged configuration, after coercions, before it
+// becomes current. A configuration that fails is never served: `build`
+// fails, and a reload or patch is rejected with the last valid configuration
+// left in place.
+//
+// Failure messages from the schema quote the offending value, so at
+// sensitive paths only the failing keyword is reported.
+use std::fmt;
+
+use jsonschema::{Draft, JSONSchema};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{ConfigError, Result};
+use crate::sensitive::{Redaction, REDACTED};
+
 pub trait ConfigValidator {
     fn validate(&self, config: &serde_json::Value) -> crate::error::Result<()>;
-}
\ No newline at end of file
+}
+
+/// One way a configuration fails its schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaFailure {
+    /// JSON pointer to the failing value; empty for the root
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() { "(root)" } else { &self.pointer };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// A compiled JSON Schema, draft 2020-12
+pub struct JsonSchemaValidator {
+    schema: JSONSchema,
+}
+
+impl fmt::Debug for JsonSchemaValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonSchemaValidator").finish_non_exhaustive()
+    }
+}
+
+impl JsonSchemaValidator {
+    pub fn new(schema: &Value) -> Result<Self> {
+        let schema = JSONSchema::options()
+            .with_draft(Draft::Draft202012)
+            .compile(schema)
+            .map_err(|error| ConfigError::ValidationError(format!("invalid configuration schema: {}", error)))?;
+        Ok(Self { schema })
+    }
+
+    /// Every failure of `config`, with messages at sensitive paths masked
+    pub(crate) fn failures(&self, config: &Value, redaction: &Redaction) -> Vec<SchemaFailure> {
+        let Err(errors) = self.schema.validate(config) else {
+            return vec![];
+        };
+        errors
+            .map(|error| {
+                let pointer = error.instance_path.to_string();
+                let path = pointer_to_path(&pointer);
+                let message = if !path.is_empty() && redaction.is_sensitive(&path) {
+                    let schema_path = error.schema_path.to_string();
+                    let keyword = schema_path.rsplit('/').next().unwrap_or_default();
+                    format!("{} fails `{}`", REDACTED, keyword)
+                } else {
+                    error.to_string()
+                };
+                SchemaFailure { pointer, message }
+            })
+            .collect()
+    }
+}
+
+impl ConfigValidator for JsonSchemaValidator {
+    fn validate(&self, config: &Value) -> Result<()> {
+        let failures = self.failures(config, &Redaction::default());
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::SchemaViolation(failures))
+        }
+    }
+}
+
+/// `.`-separated path for a JSON pointer
+fn pointer_to_path(pointer: &str) -> String {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>()
+        .join(".")
+}
@@ -1,4 +1,257 @@
-// Configuration watchers for real-time updates stub
-pub struct ConfigWatcher {
-    // Implementation will go here
-}
\ No newline at end of file
+// Configuration watchers for real-time updates
+//
+// A `ConfigWatcher` yields the typed configuration after every reload.
+// Reloads happen when asked for; when `ConfigEngine::spawn_polling` sees
+// a polled source's contents change; or when a source that pushes changes,
+// such as Redis or a file, notifies `ConfigEngine::spawn_subscriptions`.
+// Either way only that source is read again, and only its layer replaced.
+//
+// Notifications are gathered for the engine's reload debounce after the
+// first of a burst, and the burst is read once; a read that finds the
+// source as it was makes no new version. A source whose subscription was
+// lost and restored always makes one, so watchers are sent the current
+// configuration even if nothing appears to have changed.
+//
+// A watcher can debounce: reloads arriving within the window of each other
+// are read once, after the last of them. A coalescing watcher also holds
+// back a configuration that serializes identically to the one it last
+// delivered. Each watcher keeps its own window and last value, so watchers
+// of different types never suppress each other's changes.
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::engine::{ConfigEngine, ConfigEvent};
+use crate::error::Result;
+use crate::providers::SourceNotification;
+
+/// How a watcher turns reloads into notifications
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WatchConfig {
+    /// Quiet period after a reload before reading the configuration; zero
+    /// reads after every reload
+    pub debounce: Duration,
+    /// Skip notifications whose configuration is unchanged
+    pub coalesce: bool,
+}
+
+impl WatchConfig {
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn with_coalesce(mut self, coalesce: bool) -> Self {
+        self.coalesce = coalesce;
+        self
+    }
+}
+
+/// Serializes a delivered value so a later one can be compared with it
+type Fingerprint<T> = fn(&T) -> Option<Vec<u8>>;
+
+pub struct ConfigWatcher<'a, T> {
+    engine: &'a ConfigEngine,
+    events: broadcast::Receiver<ConfigEvent>,
+    config: WatchConfig,
+    /// Set when coalescing
+    fingerprint: Option<Fingerprint<T>>,
+    /// Fingerprint of the value last delivered
+    delivered: Option<Vec<u8>>,
+    _config: PhantomData<fn() -> T>,
+}
+
+/// What an event means to a watcher
+enum Signal {
+    Changed,
+    Other,
+    Closed,
+}
+
+impl<T: DeserializeOwned> ConfigWatcher<'_, T> {
+    /// Configuration after the next reload or patch; `None` once the engine
+    /// is gone
+    ///
+    /// Reloads whose result cannot be read as `T` are logged and skipped.
+    /// A watcher that falls behind skips to the current configuration.
+    pub async fn next(&mut self) -> Option<T> {
+        loop {
+            match self.receive().await {
+                Signal::Changed => {}
+                Signal::Other => continue,
+                Signal::Closed => return None,
+            }
+            let closed = !self.config.debounce.is_zero() && self.settle().await;
+
+            match self.engine.get::<T>().await {
+                Ok(config) => {
+                    if let Some(fingerprint) = self.fingerprint {
+                        let current = fingerprint(&config);
+                        if current.is_some() && current == self.delivered {
+                            if closed {
+                                return None;
+                            }
+                            continue;
+                        }
+                        self.delivered = current;
+                    }
+                    return Some(config);
+                }
+                Err(error) => {
+                    let version = self.engine.version().await;
+                    tracing::warn!(version, error = %error, "Reloaded configuration is not readable by watcher");
+                    if closed {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn receive(&mut self) -> Signal {
+        match self.events.recv().await {
+            Ok(ConfigEvent::Reloaded { .. } | ConfigEvent::Patched { .. }) => Signal::Changed,
+            Ok(_) => Signal::Other,
+            // Missed events may have included reloads
+            Err(broadcast::error::RecvError::Lagged(_)) => Signal::Changed,
+            Err(broadcast::error::RecvError::Closed) => Signal::Closed,
+        }
+    }
+
+    /// Wait until no reload has arrived for the debounce window; true if
+    /// the engine went away meanwhile
+    async fn settle(&mut self) -> bool {
+        let mut deadline = Instant::now() + self.config.debounce;
+        loop {
+            match tokio::time::timeout_at(deadline, self.receive()).await {
+                Err(_) => return false,
+                Ok(Signal::Changed) => deadline = Instant::now() + self.config.debounce,
+                Ok(Signal::Other) => {}
+                Ok(Signal::Closed) => return true,
+            }
+        }
+    }
+}
+
+fn serialized<T: Serialize>(value: &T) -> Option<Vec<u8>> {
+    serde_json::to_vec(value).ok()
+}
+
+impl ConfigEngine {
+    /// Watch for reloads; the current configuration must already be readable as `T`
+    pub async fn watch<T: DeserializeOwned>(&self) -> Result<ConfigWatcher<'_, T>> {
+        let events = self.subscribe();
+        self.get::<T>().await?;
+        Ok(ConfigWatcher {
+            engine: self,
+            events,
+            config: WatchConfig::default(),
+            fingerprint: None,
+            delivered: None,
+            _config: PhantomData,
+        })
+    }
+
+    /// `watch`, debouncing and coalescing as `config` says; a coalescing
+    /// watcher compares the serialized `T`, starting from the current one
+    pub async fn watch_with<T: DeserializeOwned + Serialize>(
+        &self,
+        config: WatchConfig,
+    ) -> Result<ConfigWatcher<'_, T>> {
+        let events = self.subscribe();
+        let current = self.get::<T>().await?;
+        let fingerprint = config
+            .coalesce
+            .then_some(serialized::<T> as Fingerprint<T>);
+        Ok(ConfigWatcher {
+            engine: self,
+            events,
+            config,
+            delivered: fingerprint.and_then(|fingerprint| fingerprint(&current)),
+            fingerprint,
+            _config: PhantomData,
+        })
+    }
+
+    /// Poll every source that asks for it, reloading when one has changed
+    pub fn spawn_polling(self: &Arc<Self>) -> Vec<JoinHandle<()>> {
+        self.sources()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, source)| Some((index, source.provider().poll_interval()?)))
+            .map(|(index, interval)| {
+                let engine = Arc::clone(self);
+                tokio::spawn(async move {
+                    let mut ticker = tokio::time::interval(interval);
+                    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                    // The first tick completes at once; the source was just loaded
+                    ticker.tick().await;
+                    loop {
+                        ticker.tick().await;
+                        engine.refresh_source(index, false).await;
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Listen to every source that pushes changes, reloading a source when
+    /// it has changed
+    pub fn spawn_subscriptions(self: &Arc<Self>) -> Vec<JoinHandle<()>> {
+        self.sources()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, source)| Some((index, source.provider().subscribe()?)))
+            .map(|(index, mut notifications)| {
+                let engine = Arc::clone(self);
+                tokio::spawn(async move {
+                    while let Some(first) = notifications.recv().await {
+                        // The rest of a burst joins the first's reload
+                        let mut reconnected = first == SourceNotification::Reconnected;
+                        let mut closed = false;
+                        let deadline = Instant::now() + engine.reload_debounce();
+                        while let Ok(next) = tokio::time::timeout_at(deadline, notifications.recv()).await {
+                            match next {
+                                Some(notification) => reconnected |= notification == SourceNotification::Reconnected,
+                                None => {
+                                    closed = true;
+                                    break;
+                                }
+                            }
+                        }
+                        if reconnected {
+                            let source = engine.sources()[index].name();
+                            tracing::info!(source, "Configuration source reconnected; reloading");
+                        }
+                        engine.refresh_source(index, reconnected).await;
+                        if closed {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Read the source at `index` again and replace its layer if it changed,
+    /// or regardless when `force`d
+    async fn refresh_source(&self, index: usize, force: bool) {
+        let source = &self.sources()[index];
+        let current = match self.load_source(source).await {
+            Ok(current) => current,
+            Err(error) => {
+                tracing::warn!(source = source.name(), error = %error, "Reading configuration source failed");
+                return;
+            }
+        };
+        if let Err(error) = self.replace_layer(index, current, force).await {
+            tracing::error!(source = source.name(), error = %error, "Configuration reload failed");
+        }
+    }
+}
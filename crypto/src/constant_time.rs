@@ -57,6 +57,22 @@ pub fn ct_eq_str(a: &str, b: &str) -> bool {
     ct_eq(a.as_bytes(), b.as_bytes())
 }
 
+/// Constant-time comparison that does not reveal whether lengths differ
+/// 
+/// [`ct_eq`] returns early on a length mismatch, which tells a timing
+/// attacker how long the secret is. This walks the longer input in full
+/// instead, so execution time depends only on the longer length.
+pub fn ct_eq_len_hiding(a: &[u8], b: &[u8]) -> bool {
+    let len = a.len().max(b.len());
+    let mut equal = (a.len() as u64).ct_eq(&(b.len() as u64));
+    for i in 0..len {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        equal &= x.ct_eq(&y);
+    }
+    equal.into()
+}
+
 /// Constant-time selection between two values
 /// 
 /// Returns `true_val` if `condition` is true, `false_val` otherwise.
@@ -291,4 +307,13 @@ mod tests {
         assert!(!ct_less_than_u32(200, 100));
         assert!(!ct_less_than_u32(100, 100));
     }
+
+    #[test]
+    fn test_ct_eq_len_hiding() {
+        assert!(ct_eq_len_hiding(b"secret", b"secret"));
+        assert!(!ct_eq_len_hiding(b"secret", b"secreT"));
+        assert!(!ct_eq_len_hiding(b"secret", b"secret\0"));
+        assert!(!ct_eq_len_hiding(b"", b"\0"));
+        assert!(ct_eq_len_hiding(b"", b""));
+    }
 }
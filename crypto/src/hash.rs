@@ -0,0 +1,44 @@
+use sha2::{Digest, Sha256};
+
+/// Length of a SHA-256 digest in bytes
+pub const SHA256_LENGTH: usize = 32;
+
+/// SHA-256 digest of `data`
+pub fn sha256(data: &[u8]) -> [u8; SHA256_LENGTH] {
+    Sha256::digest(data).into()
+}
+
+/// Next link of a hash chain: `SHA-256(prev_hash || entry)`
+///
+/// Each link commits to every entry before it, so changing, removing or
+/// reordering an earlier entry changes all the links that follow.
+pub fn chain_hash(prev_hash: &[u8], entry: &[u8]) -> [u8; SHA256_LENGTH] {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash);
+    hasher.update(entry);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_known_answer() {
+        assert_eq!(
+            hex::encode(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_chain_hash() {
+        let genesis = [0u8; SHA256_LENGTH];
+        let first = chain_hash(&genesis, b"entry 1");
+        let second = chain_hash(&first, b"entry 2");
+
+        assert_eq!(first, sha256(&[&genesis[..], b"entry 1"].concat()));
+        assert_ne!(second, chain_hash(&genesis, b"entry 2"));
+        assert_ne!(second, chain_hash(&chain_hash(&genesis, b"entry 1!"), b"entry 2"));
+    }
+}
@@ -5,6 +5,8 @@ pub mod kdf;
 pub mod envelope;
 pub mod kms;
 pub mod constant_time;
+pub mod signatures;
+pub mod hash;
 pub mod memory_security;
 pub mod config;
 
@@ -14,6 +16,8 @@ pub use aes_gcm::*;
 pub use kdf::*;
 pub use envelope::*;
 pub use constant_time::*;
+pub use signatures::*;
+pub use hash::*;
 pub use memory_security::*;
 pub use config::*;
 
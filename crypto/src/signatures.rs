@@ -0,0 +1,143 @@
+use crate::error::{CryptoError, CryptoResult};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+
+/// Length of an Ed25519 public key in bytes
+pub const ED25519_PUBLIC_KEY_LENGTH: usize = ed25519_dalek::PUBLIC_KEY_LENGTH;
+
+/// Length of an Ed25519 signature in bytes
+pub const ED25519_SIGNATURE_LENGTH: usize = ed25519_dalek::SIGNATURE_LENGTH;
+
+/// Ed25519 key pair for detached signatures
+///
+/// The secret key is zeroized on drop.
+pub struct Ed25519KeyPair {
+    signing_key: SigningKey,
+}
+
+impl Ed25519KeyPair {
+    /// Generate a new key pair from the OS random number generator
+    pub fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    /// Restore a key pair from its 32-byte secret key
+    pub fn from_secret_key(secret: &[u8]) -> CryptoResult<Self> {
+        let secret: [u8; 32] = secret
+            .try_into()
+            .map_err(|_| CryptoError::InvalidKeyLength {
+                expected: 32,
+                got: secret.len(),
+            })?;
+        Ok(Self {
+            signing_key: SigningKey::from_bytes(&secret),
+        })
+    }
+
+    pub fn public_key(&self) -> [u8; ED25519_PUBLIC_KEY_LENGTH] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    /// Detached signature over `message`
+    pub fn sign(&self, message: &[u8]) -> [u8; ED25519_SIGNATURE_LENGTH] {
+        self.signing_key.sign(message).to_bytes()
+    }
+}
+
+/// Verify a detached Ed25519 signature
+///
+/// Uses strict verification, which also rejects weak public keys and
+/// non-canonical signatures so a signature cannot be altered into a second
+/// valid one.
+pub fn verify_ed25519(public_key: &[u8], message: &[u8], signature: &[u8]) -> CryptoResult<()> {
+    let public_key: [u8; ED25519_PUBLIC_KEY_LENGTH] =
+        public_key
+            .try_into()
+            .map_err(|_| CryptoError::InvalidKeyLength {
+                expected: ED25519_PUBLIC_KEY_LENGTH,
+                got: public_key.len(),
+            })?;
+    let verifying_key = VerifyingKey::from_bytes(&public_key)
+        .map_err(|e| CryptoError::InvalidKey(format!("Invalid Ed25519 public key: {}", e)))?;
+
+    let signature = Signature::from_slice(signature).map_err(|_| {
+        CryptoError::SignatureVerificationFailed(format!(
+            "expected a {}-byte signature, got {} bytes",
+            ED25519_SIGNATURE_LENGTH,
+            signature.len()
+        ))
+    })?;
+
+    verifying_key
+        .verify_strict(message, &signature)
+        .map_err(|_| {
+            CryptoError::SignatureVerificationFailed("signature does not match".to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify_roundtrip() {
+        let key_pair = Ed25519KeyPair::generate();
+        let signature = key_pair.sign(b"package contents");
+
+        assert!(verify_ed25519(&key_pair.public_key(), b"package contents", &signature).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_message_fails() {
+        let key_pair = Ed25519KeyPair::generate();
+        let signature = key_pair.sign(b"package contents");
+
+        let result = verify_ed25519(&key_pair.public_key(), b"package c0ntents", &signature);
+        assert!(matches!(
+            result,
+            Err(CryptoError::SignatureVerificationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_other_key_fails() {
+        let signer = Ed25519KeyPair::generate();
+        let other = Ed25519KeyPair::generate();
+        let signature = signer.sign(b"package contents");
+
+        assert!(verify_ed25519(&other.public_key(), b"package contents", &signature).is_err());
+    }
+
+    #[test]
+    fn test_restore_from_secret_key() {
+        let secret = [7u8; 32];
+        let first = Ed25519KeyPair::from_secret_key(&secret).unwrap();
+        let second = Ed25519KeyPair::from_secret_key(&secret).unwrap();
+        assert_eq!(first.public_key(), second.public_key());
+
+        assert!(matches!(
+            Ed25519KeyPair::from_secret_key(&[0u8; 16]),
+            Err(CryptoError::InvalidKeyLength {
+                expected: 32,
+                got: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_inputs() {
+        let key_pair = Ed25519KeyPair::generate();
+        let signature = key_pair.sign(b"message");
+
+        assert!(matches!(
+            verify_ed25519(&key_pair.public_key()[..31], b"message", &signature),
+            Err(CryptoError::InvalidKeyLength { .. })
+        ));
+        assert!(matches!(
+            verify_ed25519(&key_pair.public_key(), b"message", &signature[..63]),
+            Err(CryptoError::SignatureVerificationFailed(_))
+        ));
+    }
+}
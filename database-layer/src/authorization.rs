@@ -146,14 +146,19 @@ impl AuthorizationMiddleware {
         let subject = Subject::user(&user_id.to_string());
         let relation = Relation::new(action);
         
-        let mut results = Vec::new();
-        for (resource_type, resource_id) in resources {
-            let object = Object::new(&resource_type, &resource_id);
-            let allowed = self.engine.check(subject.clone(), relation.clone(), object).await?;
-            results.push((resource_type, resource_id, allowed));
-        }
+        let checks = resources
+            .iter()
+            .map(|(resource_type, resource_id)| {
+                (subject.clone(), relation.clone(), Object::new(resource_type, resource_id))
+            })
+            .collect();
+        let responses = self.engine.batch_check(checks).await?;
         
-        Ok(results)
+        Ok(resources
+            .into_iter()
+            .zip(responses)
+            .map(|((resource_type, resource_id), response)| (resource_type, resource_id, response.allowed))
+            .collect())
     }
 }
 
@@ -0,0 +1,174 @@
+// Optimistic concurrency control
+//
+// A versioned table carries a row version - an integer counter, or an
+// `updated_at` timestamp - that every update checks and advances in the same
+// statement (`... WHERE id = $1 AND version = $2`). An update made against a
+// stale copy matches no row and fails with
+// `DatabaseError::ConcurrencyConflict`, carrying the row's current version so
+// the caller can reload, reapply its change and retry. Over HTTP the version
+// travels in the ETag, and the `If-Match` header supplies the expected
+// version.
+use crate::error::{DatabaseError, DatabaseResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tracing::debug;
+
+/// Attempts [`retry_on_conflict`] makes when not told otherwise
+pub const DEFAULT_CONFLICT_ATTEMPTS: u32 = 3;
+
+/// The column holding a table's row version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionColumn {
+    /// Integer column incremented by each update
+    Counter(String),
+    /// Timestamp column set to the time of each update
+    Timestamp(String),
+}
+
+impl VersionColumn {
+    /// The conventional `version` counter
+    pub fn version() -> Self {
+        VersionColumn::Counter("version".to_string())
+    }
+
+    /// The conventional `updated_at` timestamp
+    pub fn updated_at() -> Self {
+        VersionColumn::Timestamp("updated_at".to_string())
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            VersionColumn::Counter(name) | VersionColumn::Timestamp(name) => name,
+        }
+    }
+
+    /// Expression reading the version; counters are widened so `INTEGER`
+    /// and `BIGINT` columns both decode as `i64`
+    pub(crate) fn select_expr(&self) -> String {
+        match self {
+            VersionColumn::Counter(name) => format!("{name}::bigint"),
+            VersionColumn::Timestamp(name) => name.clone(),
+        }
+    }
+
+    /// Assignment advancing the version. `clock_timestamp()` rather than
+    /// `NOW()`, so two updates in one transaction still differ.
+    pub(crate) fn bump(&self) -> String {
+        match self {
+            VersionColumn::Counter(name) => format!("{name} = {name} + 1"),
+            VersionColumn::Timestamp(name) => format!("{name} = clock_timestamp()"),
+        }
+    }
+}
+
+/// A row version as read from, or expected of, a versioned table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RowVersion {
+    Counter(i64),
+    Timestamp(DateTime<Utc>),
+}
+
+impl RowVersion {
+    /// Parse a version as rendered by `Display`, e.g. from an ETag
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Ok(counter) = value.parse::<i64>() {
+            return Some(RowVersion::Counter(counter));
+        }
+        // Timestamps render as microseconds since the epoch, to keep them
+        // free of characters that need quoting in a header
+        value
+            .strip_prefix('t')
+            .and_then(|micros| micros.parse::<i64>().ok())
+            .and_then(DateTime::from_timestamp_micros)
+            .map(RowVersion::Timestamp)
+    }
+}
+
+impl fmt::Display for RowVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowVersion::Counter(counter) => write!(f, "{}", counter),
+            RowVersion::Timestamp(at) => write!(f, "t{}", at.timestamp_micros()),
+        }
+    }
+}
+
+/// Run a read-modify-write, retrying it from the read when it loses a race
+///
+/// `attempt` should load the row, apply the change and write it back with a
+/// versioned update; a [`DatabaseError::ConcurrencyConflict`] re-runs it, up
+/// to `max_attempts` times in all, after a short randomized pause. Other
+/// errors, and the last conflict, are returned. Only suitable where the
+/// change can simply be reapplied to the newer row - anything that needs a
+/// person to reconcile the edits should surface the conflict instead.
+pub async fn retry_on_conflict<T, F, Fut>(max_attempts: u32, mut attempt: F) -> DatabaseResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = DatabaseResult<T>>,
+{
+    let mut tries = 0;
+    loop {
+        tries += 1;
+        match attempt().await {
+            Err(DatabaseError::ConcurrencyConflict { table, id, current }) if tries < max_attempts => {
+                debug!(table = %table, id = %id, current = ?current, tries, "Update conflicted; retrying");
+                let pause = Duration::from_millis(10 * u64::from(tries) + rand::random::<u64>() % 20);
+                tokio::time::sleep(pause).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_row_version_round_trips_through_display() {
+        let counter = RowVersion::Counter(42);
+        assert_eq!(RowVersion::parse(&counter.to_string()), Some(counter));
+
+        let at = DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        let timestamp = RowVersion::Timestamp(at);
+        assert_eq!(RowVersion::parse(&timestamp.to_string()), Some(timestamp));
+        assert_eq!(RowVersion::parse("not-a-version"), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_conflict_reruns_until_success_or_limit() {
+        let calls = AtomicU32::new(0);
+        let result = retry_on_conflict(3, || async {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(DatabaseError::ConcurrencyConflict {
+                    table: "patients".to_string(),
+                    id: "1".to_string(),
+                    current: Some(RowVersion::Counter(2)),
+                })
+            } else {
+                Ok("saved")
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "saved");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        let result: DatabaseResult<()> = retry_on_conflict(2, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(DatabaseError::ConcurrencyConflict {
+                table: "patients".to_string(),
+                id: "1".to_string(),
+                current: None,
+            })
+        })
+        .await;
+        assert!(matches!(result, Err(DatabaseError::ConcurrencyConflict { .. })));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
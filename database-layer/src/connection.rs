@@ -2,7 +2,8 @@
 use async_trait::async_trait;
 use crate::error::{DatabaseError, DatabaseResult};
 use crate::rls::RlsContext;
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use crate::routing::ReplicaSet;
+use sqlx::{PgConnection, PgPool, postgres::PgPoolOptions};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{info, warn};
@@ -14,6 +15,7 @@ pub struct DatabasePool {
     rls_enabled: bool,
     audit_enabled: bool,
     encryption_enabled: bool,
+    replicas: Option<Arc<ReplicaSet>>,
 }
 
 impl DatabasePool {
@@ -36,6 +38,7 @@ impl DatabasePool {
             rls_enabled: false,
             audit_enabled: false,
             encryption_enabled: false,
+            replicas: None,
         })
     }
 
@@ -46,6 +49,7 @@ impl DatabasePool {
             rls_enabled: false,
             audit_enabled: false,
             encryption_enabled: false,
+            replicas: None,
         }
     }
 
@@ -67,11 +71,38 @@ impl DatabasePool {
         self
     }
 
-    /// Get the underlying PgPool
+    /// Send read-only queries to these replicas. See the
+    /// [`routing`](crate::routing) module for the consistency trade-off.
+    pub fn with_replicas(mut self, replicas: Arc<ReplicaSet>) -> Self {
+        self.replicas = Some(replicas);
+        self
+    }
+
+    /// Get the underlying PgPool (the primary)
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 
+    pub fn replicas(&self) -> Option<&Arc<ReplicaSet>> {
+        self.replicas.as_ref()
+    }
+
+    /// Pool for a read-only query: a healthy replica, or the primary when
+    /// there is none or the session is pinned after a write
+    pub fn read_pool(&self, session: Option<&str>) -> &PgPool {
+        self.replicas
+            .as_ref()
+            .and_then(|replicas| replicas.select(session))
+            .map_or(self.pool.as_ref(), |replica| replica.pool())
+    }
+
+    /// Note that a session wrote, for read-your-writes routing
+    pub fn record_write(&self, session: &str) {
+        if let Some(replicas) = &self.replicas {
+            replicas.record_write(session);
+        }
+    }
+
     /// Check if the pool is healthy
     pub async fn is_healthy(&self) -> bool {
         match sqlx::query("SELECT 1")
@@ -126,6 +157,38 @@ impl DatabasePool {
         Ok(())
     }
 
+    /// Apply RLS context to the transaction open on `conn`, for the
+    /// statements that follow it there; settings end with the transaction
+    pub async fn apply_rls_context_on(&self, conn: &mut PgConnection, context: &RlsContext) -> DatabaseResult<()> {
+        if !self.rls_enabled {
+            return Ok(());
+        }
+
+        let mut settings = vec![
+            ("app.current_user_id", context.user_id.to_string()),
+            ("app.current_tenant_id", context.tenant_id.clone()),
+        ];
+        if let Some(org_id) = context.organization_id {
+            settings.push(("app.organization_id", org_id.to_string()));
+        }
+        if !context.roles.is_empty() {
+            settings.push(("app.user_roles", context.roles.join(",")));
+        }
+        if !context.permissions.is_empty() {
+            settings.push(("app.user_permissions", context.permissions.join(",")));
+        }
+        let (names, values): (Vec<&str>, Vec<String>) = settings.into_iter().unzip();
+
+        sqlx::query("SELECT set_config(name, value, true) FROM unnest($1::text[], $2::text[]) AS s(name, value)")
+            .bind(names)
+            .bind(values)
+            .execute(conn)
+            .await
+            .map_err(|e| DatabaseError::QueryFailed(format!("Failed to apply RLS context: {}", e)))?;
+
+        Ok(())
+    }
+
     /// Close the pool
     pub async fn close(&self) {
         self.pool.close().await;
@@ -1,3 +1,4 @@
+use crate::concurrency::RowVersion;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,6 +12,18 @@ pub enum DatabaseError {
     #[error("Query error: {0}")]
     QueryError(String),
     
+    #[error("Query timed out: {0}")]
+    Timeout(String),
+    
+    #[error("{table} row {id} was changed concurrently (current version: {current:?})")]
+    ConcurrencyConflict {
+        table: String,
+        id: String,
+        /// Version the row has now; `None` if it no longer exists or is not
+        /// visible to the caller
+        current: Option<RowVersion>,
+    },
+    
     #[error("Configuration error: {0}")]
     ConfigurationError(String),
     
@@ -23,6 +36,21 @@ pub enum DatabaseError {
     #[error("Migration error: {0}")]
     MigrationError(String),
     
+    #[error("Migration {version} ({description}) was modified after it was applied (checksum mismatch)")]
+    MigrationChecksumMismatch { version: i64, description: String },
+    
+    #[error("Migration {version} ({description}) has no down script; cannot revert past it")]
+    MigrationNotReversible { version: i64, description: String },
+    
+    #[error("Backup error: {0}")]
+    BackupError(String),
+    
+    #[error("Backup manifest signature is invalid")]
+    BackupSignatureInvalid,
+    
+    #[error("Backup is corrupted: {0}")]
+    BackupCorrupted(String),
+    
     #[error("Database error: {0}")]
     SqlxError(#[from] sqlx::Error),
     
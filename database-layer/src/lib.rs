@@ -5,6 +5,10 @@ pub mod rls;
 pub mod encryption;
 pub mod migration;
 pub mod query;
+pub mod concurrency;
+pub mod routing;
+pub mod soft_delete;
+pub mod slow_query;
 pub mod transaction;
 pub mod audit;
 pub mod backup;
@@ -18,7 +22,12 @@ pub use connection::*;
 pub use models::*;
 pub use rls::*;
 pub use encryption::*;
+pub use migration::*;
 pub use query::*;
+pub use concurrency::*;
+pub use routing::*;
+pub use soft_delete::*;
+pub use slow_query::{SlowQuery, SlowQueryConfig, SlowQueryLog};
 pub use error::*;
 pub use audit::*;
 pub use authorization::*;
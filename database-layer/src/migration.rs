@@ -1,8 +1,346 @@
 // Database migration system
-pub struct MigrationManager;
+//
+// Migrations are the `migrations/*.sql` files applied by `sqlx migrate`, and
+// history lives in the same `_sqlx_migrations` table, so this runner and
+// sqlx-cli can be used interchangeably against one database.
+use crate::error::{DatabaseError, DatabaseResult};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::migrate::{Migrate, Migration, Migrator};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgConnection, PgPool, Row};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tracing::info;
+
+/// State of a single migration relative to the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the file has changed since
+    ChecksumMismatch,
+    /// Applied, but no longer present in the migrations directory
+    Missing,
+    /// Recorded as failed part way through
+    Dirty,
+}
+
+impl MigrationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::ChecksumMismatch => "checksum mismatch",
+            MigrationState::Missing => "missing",
+            MigrationState::Dirty => "dirty",
+        }
+    }
+
+    pub fn is_applied(&self) -> bool {
+        !matches!(self, MigrationState::Pending)
+    }
+}
+
+/// One row of `MigrationManager::status`
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+    /// A down script exists for this version
+    pub reversible: bool,
+    pub installed_on: Option<DateTime<Utc>>,
+}
+
+/// Migration that was (or, for a dry run, would be) applied or reverted
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationRun {
+    pub version: i64,
+    pub description: String,
+    /// `None` for dry runs
+    pub duration_ms: Option<u128>,
+}
+
+struct AppliedRow {
+    description: String,
+    checksum: Vec<u8>,
+    success: bool,
+    installed_on: DateTime<Utc>,
+}
+
+/// Applies and reverts schema migrations
+pub struct MigrationManager {
+    pool: PgPool,
+    migrator: Migrator,
+}
 
 impl MigrationManager {
-    pub fn new() -> Self {
-        Self
+    /// Load migrations from `source` (normally the repository `migrations/` directory)
+    pub async fn new(pool: PgPool, source: impl AsRef<Path>) -> DatabaseResult<Self> {
+        let migrator = Migrator::new(source.as_ref()).await.map_err(|e| {
+            DatabaseError::MigrationError(format!(
+                "Failed to load migrations from {}: {}",
+                source.as_ref().display(),
+                e
+            ))
+        })?;
+        Ok(Self { pool, migrator })
+    }
+
+    /// Open a small dedicated pool for running migrations
+    pub async fn connect(database_url: &str, source: impl AsRef<Path>) -> DatabaseResult<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(Duration::from_secs(30))
+            .connect(database_url)
+            .await
+            .map_err(|e| DatabaseError::ConnectionFailed(e.to_string()))?;
+        Self::new(pool, source).await
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Every known migration, from the directory and the history table, by version
+    pub async fn status(&self) -> DatabaseResult<Vec<MigrationStatus>> {
+        let mut conn = self.pool.acquire().await?;
+        self.status_on(&mut conn).await
+    }
+
+    async fn status_on(&self, conn: &mut PgConnection) -> DatabaseResult<Vec<MigrationStatus>> {
+        let mut applied = applied_rows(conn).await?;
+        let mut statuses = Vec::new();
+
+        for migration in self.up_migrations() {
+            let (state, installed_on) = match applied.remove(&migration.version) {
+                Some(row) if !row.success => (MigrationState::Dirty, Some(row.installed_on)),
+                Some(row) if row.checksum != *migration.checksum => {
+                    (MigrationState::ChecksumMismatch, Some(row.installed_on))
+                }
+                Some(row) => (MigrationState::Applied, Some(row.installed_on)),
+                None => (MigrationState::Pending, None),
+            };
+            statuses.push(MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+                reversible: self.down_migration(migration.version).is_some(),
+                installed_on,
+            });
+        }
+
+        for (version, row) in applied {
+            statuses.push(MigrationStatus {
+                version,
+                description: row.description,
+                state: if row.success {
+                    MigrationState::Missing
+                } else {
+                    MigrationState::Dirty
+                },
+                reversible: false,
+                installed_on: Some(row.installed_on),
+            });
+        }
+
+        statuses.sort_by_key(|status| status.version);
+        Ok(statuses)
+    }
+
+    /// Apply all pending migrations in version order
+    pub async fn migrate_up(&self, dry_run: bool) -> DatabaseResult<Vec<MigrationRun>> {
+        let mut conn = self.pool.acquire().await?;
+        if dry_run {
+            let statuses = self.status_on(&mut conn).await?;
+            return Ok(self.plan_up(&statuses)?.into_iter().map(planned).collect());
+        }
+
+        // Plan under the lock, so migrations applied by a concurrent run are
+        // seen as applied rather than run a second time
+        conn.lock().await.map_err(migrate_error)?;
+        let result = self.apply_pending(&mut conn).await;
+        conn.unlock().await.map_err(migrate_error)?;
+        result
+    }
+
+    async fn apply_pending(&self, conn: &mut PgConnection) -> DatabaseResult<Vec<MigrationRun>> {
+        conn.ensure_migrations_table()
+            .await
+            .map_err(migrate_error)?;
+        let statuses = self.status_on(conn).await?;
+        let pending = self.plan_up(&statuses)?;
+
+        let mut runs = Vec::with_capacity(pending.len());
+        for migration in pending {
+            let elapsed = conn.apply(migration).await.map_err(migrate_error)?;
+            info!(
+                version = migration.version,
+                description = %migration.description,
+                "Applied migration"
+            );
+            runs.push(finished(migration, elapsed));
+        }
+        Ok(runs)
     }
-}
\ No newline at end of file
+
+    fn plan_up(&self, statuses: &[MigrationStatus]) -> DatabaseResult<Vec<&Migration>> {
+        check_history(statuses)?;
+        Ok(self
+            .up_migrations()
+            .filter(|migration| {
+                statuses.iter().any(|status| {
+                    status.version == migration.version && status.state == MigrationState::Pending
+                })
+            })
+            .collect())
+    }
+
+    /// Revert the most recent `steps` applied migrations, newest first.
+    ///
+    /// Fails without reverting anything if any of them lacks a down script,
+    /// since skipping one would leave the schema out of step with the history.
+    pub async fn migrate_down(
+        &self,
+        steps: usize,
+        dry_run: bool,
+    ) -> DatabaseResult<Vec<MigrationRun>> {
+        let mut conn = self.pool.acquire().await?;
+        if dry_run {
+            let statuses = self.status_on(&mut conn).await?;
+            return Ok(self.plan_down(&statuses, steps)?.into_iter().map(planned).collect());
+        }
+
+        conn.lock().await.map_err(migrate_error)?;
+        let result = self.revert_applied(&mut conn, steps).await;
+        conn.unlock().await.map_err(migrate_error)?;
+        result
+    }
+
+    async fn revert_applied(&self, conn: &mut PgConnection, steps: usize) -> DatabaseResult<Vec<MigrationRun>> {
+        let statuses = self.status_on(conn).await?;
+        let plan = self.plan_down(&statuses, steps)?;
+
+        let mut runs = Vec::with_capacity(plan.len());
+        for migration in plan {
+            let elapsed = conn.revert(migration).await.map_err(migrate_error)?;
+            info!(
+                version = migration.version,
+                description = %migration.description,
+                "Reverted migration"
+            );
+            runs.push(finished(migration, elapsed));
+        }
+        Ok(runs)
+    }
+
+    fn plan_down(&self, statuses: &[MigrationStatus], steps: usize) -> DatabaseResult<Vec<&Migration>> {
+        check_history(statuses)?;
+        statuses
+            .iter()
+            .rev()
+            .filter(|status| status.state.is_applied())
+            .take(steps)
+            .map(|status| {
+                self.down_migration(status.version)
+                    .ok_or_else(|| DatabaseError::MigrationNotReversible {
+                        version: status.version,
+                        description: status.description.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    fn up_migrations(&self) -> impl Iterator<Item = &Migration> {
+        self.migrator
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+    }
+
+    fn down_migration(&self, version: i64) -> Option<&Migration> {
+        self.migrator.iter().find(|migration| {
+            migration.version == version && migration.migration_type.is_down_migration()
+        })
+    }
+}
+
+async fn applied_rows(conn: &mut PgConnection) -> DatabaseResult<HashMap<i64, AppliedRow>> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+    if !exists {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query(
+        "SELECT version, description, success, checksum, installed_on \
+         FROM _sqlx_migrations ORDER BY version",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok((
+                row.try_get("version")?,
+                AppliedRow {
+                    description: row.try_get("description")?,
+                    checksum: row.try_get("checksum")?,
+                    success: row.try_get("success")?,
+                    installed_on: row.try_get("installed_on")?,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Refuse to migrate over a history that no longer matches the files
+pub fn check_history(statuses: &[MigrationStatus]) -> DatabaseResult<()> {
+    for status in statuses {
+        match status.state {
+            MigrationState::ChecksumMismatch => {
+                return Err(DatabaseError::MigrationChecksumMismatch {
+                    version: status.version,
+                    description: status.description.clone(),
+                })
+            }
+            MigrationState::Missing => {
+                return Err(DatabaseError::MigrationError(format!(
+                    "migration {} ({}) was applied but is missing from the migrations directory",
+                    status.version, status.description
+                )))
+            }
+            MigrationState::Dirty => {
+                return Err(DatabaseError::MigrationError(format!(
+                    "migration {} ({}) is partially applied; fix it and remove its row from _sqlx_migrations",
+                    status.version, status.description
+                )))
+            }
+            MigrationState::Applied | MigrationState::Pending => {}
+        }
+    }
+    Ok(())
+}
+
+fn planned(migration: &Migration) -> MigrationRun {
+    MigrationRun {
+        version: migration.version,
+        description: migration.description.to_string(),
+        duration_ms: None,
+    }
+}
+
+fn finished(migration: &Migration, elapsed: Duration) -> MigrationRun {
+    MigrationRun {
+        version: migration.version,
+        description: migration.description.to_string(),
+        duration_ms: Some(elapsed.as_millis()),
+    }
+}
+
+fn migrate_error(error: sqlx::migrate::MigrateError) -> DatabaseError {
+    DatabaseError::MigrationError(error.to_string())
+}
@@ -1,18 +1,47 @@
 // Query builder and executor with RLS support
+use crate::concurrency::{RowVersion, VersionColumn};
 use crate::connection::DatabasePool;
 use crate::encryption::DatabaseEncryption;
 use crate::error::{DatabaseError, DatabaseResult};
 use crate::rls::RlsContext;
+use crate::soft_delete::{SoftDeleteRegistry, LEGAL_HOLDS_TABLE};
+use crate::audit::AuditLogger;
+use crate::slow_query::{is_read_only, redact_sql, SlowQueryLog, UNLABELLED_QUERY};
 use serde_json::Value as JsonValue;
-use sqlx::{FromRow, Row};
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, FromRow, PgConnection, PgPool, Postgres, Row};
+use std::future::Future;
 use std::sync::Arc;
-use tracing::{debug, error};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// SQLSTATE Postgres reports for a query cancelled by `statement_timeout`
+/// or `pg_cancel_backend`
+const QUERY_CANCELED: &str = "57014";
 
 /// Query executor with automatic RLS context application
+///
+/// Each query runs on a connection held for its duration. If the caller
+/// drops the future before it completes (e.g. an HTTP request timed out),
+/// the query is cancelled on the server with `pg_cancel_backend` and the
+/// connection is closed instead of going back to the pool mid-query. With a
+/// [timeout](Self::with_timeout) set, a query that exceeds it is cancelled
+/// the same way.
+///
+/// Queries go to the primary unless marked [`read_only`](Self::read_only)
+/// and the pool has replicas; see the [`routing`](crate::routing) module
+/// for what replica reads can and cannot see.
 pub struct QueryExecutor {
     pool: DatabasePool,
     rls_context: Option<RlsContext>,
     encryption: Option<Arc<DatabaseEncryption>>,
+    timeout: Option<Duration>,
+    slow_queries: Option<Arc<SlowQueryLog>>,
+    label: String,
+    read_only: bool,
+    session: Option<String>,
+    soft_deletes: Option<Arc<SoftDeleteRegistry>>,
 }
 
 impl QueryExecutor {
@@ -21,15 +50,62 @@ impl QueryExecutor {
             pool,
             rls_context: None,
             encryption: None,
+            timeout: None,
+            slow_queries: None,
+            label: UNLABELLED_QUERY.to_string(),
+            read_only: false,
+            session: None,
+            soft_deletes: None,
         }
     }
 
+    /// Cancel each query that runs longer than `timeout` and fail it with
+    /// [`DatabaseError::Timeout`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Attach an encryption engine to the executor
     pub fn with_encryption(mut self, encryption: DatabaseEncryption) -> Self {
         self.encryption = Some(Arc::new(encryption));
         self
     }
 
+    /// Record query durations and slow queries to `log`
+    pub fn with_slow_query_log(mut self, log: Arc<SlowQueryLog>) -> Self {
+        self.slow_queries = Some(log);
+        self
+    }
+
+    /// Label the executor's queries in slow-query logs and the duration
+    /// histogram. Use a fixed name per call site, e.g. `"patients.by_mrn"`,
+    /// never anything derived from request data.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Allow the executor's fetches to be served by a read replica, which
+    /// may lag the primary. Commands still go to the primary.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Session that read-your-writes pinning applies to. Defaults to the RLS
+    /// context's session, then its user.
+    pub fn with_session(mut self, session: impl Into<String>) -> Self {
+        self.session = Some(session.into());
+        self
+    }
+
+    /// Tables whose deletes are soft, and whose deleted rows selects skip
+    pub fn with_soft_deletes(mut self, registry: Arc<SoftDeleteRegistry>) -> Self {
+        self.soft_deletes = Some(registry);
+        self
+    }
+
     /// Set RLS context for this query
     pub fn with_rls_context(mut self, context: RlsContext) -> Self {
         self.rls_context = Some(context);
@@ -64,9 +140,16 @@ impl QueryExecutor {
         let query = sqlx::query_as::<_, T>(sql);
         let query = bind_fn(query);
 
-        query.fetch_one(self.pool.pool()).await.map_err(|e| {
+        let pool = self.query_pool(sql);
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let result = self
+            .within_timeout(pool, sql, query.fetch_one(in_flight.connection()))
+            .await?;
+        in_flight.finish();
+
+        result.map_err(|e| {
             error!("Query failed: {}", e);
-            DatabaseError::QueryFailed(e.to_string())
+            query_failed(e)
         })
     }
 
@@ -98,9 +181,16 @@ impl QueryExecutor {
         let query = sqlx::query_as::<_, T>(sql);
         let query = bind_fn(query);
 
-        query.fetch_all(self.pool.pool()).await.map_err(|e| {
+        let pool = self.query_pool(sql);
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let result = self
+            .within_timeout(pool, sql, query.fetch_all(in_flight.connection()))
+            .await?;
+        in_flight.finish();
+
+        result.map_err(|e| {
             error!("Query failed: {}", e);
-            DatabaseError::QueryFailed(e.to_string())
+            query_failed(e)
         })
     }
 
@@ -136,9 +226,16 @@ impl QueryExecutor {
         let query = sqlx::query_as::<_, T>(sql);
         let query = bind_fn(query);
 
-        query.fetch_optional(self.pool.pool()).await.map_err(|e| {
+        let pool = self.query_pool(sql);
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let result = self
+            .within_timeout(pool, sql, query.fetch_optional(in_flight.connection()))
+            .await?;
+        in_flight.finish();
+
+        result.map_err(|e| {
             error!("Query failed: {}", e);
-            DatabaseError::QueryFailed(e.to_string())
+            query_failed(e)
         })
     }
 
@@ -165,9 +262,16 @@ impl QueryExecutor {
         let query = sqlx::query(sql);
         let query = bind_fn(query);
 
-        let result = query.execute(self.pool.pool()).await.map_err(|e| {
+        let pool = self.pool.pool();
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let result = self
+            .within_timeout(pool, sql, query.execute(in_flight.connection()))
+            .await?;
+        in_flight.finish();
+
+        let result = result.map_err(|e| {
             error!("Command failed: {}", e);
-            DatabaseError::QueryFailed(e.to_string())
+            query_failed(e)
         })?;
 
         Ok(result.rows_affected())
@@ -209,9 +313,16 @@ impl QueryExecutor {
             query = query.bind(to_bind);
         }
 
-        let result = query.execute(self.pool.pool()).await.map_err(|e| {
+        let pool = self.pool.pool();
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let result = self
+            .within_timeout(pool, sql, query.execute(in_flight.connection()))
+            .await?;
+        in_flight.finish();
+
+        let result = result.map_err(|e| {
             error!("Command failed: {}", e);
-            DatabaseError::QueryFailed(e.to_string())
+            query_failed(e)
         })?;
 
         Ok(result.rows_affected())
@@ -228,13 +339,17 @@ impl QueryExecutor {
 
         debug!("Executing query (json decrypt): {}", sql);
 
-        let row = sqlx::query(sql)
-            .fetch_one(self.pool.pool())
-            .await
-            .map_err(|e| {
-                error!("Query failed: {}", e);
-                DatabaseError::QueryFailed(e.to_string())
-            })?;
+        let pool = self.query_pool(sql);
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let result = self
+            .within_timeout(pool, sql, sqlx::query(sql).fetch_one(in_flight.connection()))
+            .await?;
+        in_flight.finish();
+
+        let row = result.map_err(|e| {
+            error!("Query failed: {}", e);
+            query_failed(e)
+        })?;
 
         // Extract first column as JSON value
         let json: JsonValue = row.try_get(0).map_err(|e| {
@@ -256,13 +371,17 @@ impl QueryExecutor {
 
         debug!("Executing query (json decrypt): {}", sql);
 
-        let rows = sqlx::query(sql)
-            .fetch_all(self.pool.pool())
-            .await
-            .map_err(|e| {
-                error!("Query failed: {}", e);
-                DatabaseError::QueryFailed(e.to_string())
-            })?;
+        let pool = self.query_pool(sql);
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let result = self
+            .within_timeout(pool, sql, sqlx::query(sql).fetch_all(in_flight.connection()))
+            .await?;
+        in_flight.finish();
+
+        let rows = result.map_err(|e| {
+            error!("Query failed: {}", e);
+            query_failed(e)
+        })?;
 
         let mut out = Vec::with_capacity(rows.len());
         for row in rows.into_iter() {
@@ -276,6 +395,264 @@ impl QueryExecutor {
         Ok(out)
     }
 
+    /// Start a select over `table`, leaving out soft-deleted rows if the
+    /// table is soft-deletable
+    pub fn select(&self, table: &str) -> QueryBuilder {
+        QueryBuilder::select(table).soft_deletable(
+            self.soft_deletes
+                .as_ref()
+                .is_some_and(|registry| registry.is_soft_deletable(table)),
+        )
+    }
+
+    /// Start a versioned update of `table`, leaving soft-deleted rows alone
+    /// if the table is soft-deletable
+    pub fn update(&self, table: &str, version: VersionColumn) -> UpdateBuilder {
+        UpdateBuilder::new(table, version).soft_deletable(
+            self.soft_deletes
+                .as_ref()
+                .is_some_and(|registry| registry.is_soft_deletable(table)),
+        )
+    }
+
+    /// Apply a versioned update to the row `id`, provided it is still at
+    /// `expected`. Returns the row's new version, or
+    /// [`DatabaseError::ConcurrencyConflict`] with its current version if
+    /// someone else changed it first.
+    pub async fn update_versioned<F>(
+        &self,
+        update: &UpdateBuilder,
+        id: Uuid,
+        expected: RowVersion,
+        bind_fn: F,
+    ) -> DatabaseResult<RowVersion>
+    where
+        F: FnOnce(
+            sqlx::query::Query<'_, sqlx::Postgres, sqlx::postgres::PgArguments>,
+        ) -> sqlx::query::Query<'_, sqlx::Postgres, sqlx::postgres::PgArguments>,
+    {
+        let sql = update.build()?;
+        if let Some(context) = &self.rls_context {
+            self.pool.apply_rls_context(context).await?;
+        }
+
+        debug!("Executing versioned update: {}", sql);
+
+        let query = sqlx::query(&sql).bind(id);
+        let query = match expected {
+            RowVersion::Counter(counter) => query.bind(counter),
+            RowVersion::Timestamp(at) => query.bind(at),
+        };
+        let query = bind_fn(query);
+
+        let pool = self.pool.pool();
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let result = self
+            .within_timeout(pool, &sql, query.fetch_optional(in_flight.connection()))
+            .await?;
+        in_flight.finish();
+
+        let row = result.map_err(|e| {
+            error!("Command failed: {}", e);
+            query_failed(e)
+        })?;
+        match row {
+            Some(row) => read_version(&row, update.version_column()),
+            None => {
+                let current = sqlx::query(&update.current_version_sql())
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await
+                    .map_err(query_failed)?
+                    .map(|row| read_version(&row, update.version_column()))
+                    .transpose()?;
+                warn!(table = update.table(), id = %id, expected = %expected, current = ?current, "Versioned update conflicted");
+                Err(DatabaseError::ConcurrencyConflict {
+                    table: update.table().to_string(),
+                    id: id.to_string(),
+                    current,
+                })
+            }
+        }
+    }
+
+    /// Delete a row by id. On a soft-deletable table this sets `deleted_at`
+    /// and keeps the row; otherwise the row is removed. Returns whether a
+    /// row was deleted.
+    pub async fn delete(&self, table: &str, id: Uuid) -> DatabaseResult<bool> {
+        validate_identifier(table)?;
+        let Some(config) = self.soft_deletes.as_ref().and_then(|registry| registry.get(table)) else {
+            let sql = format!("DELETE FROM {} WHERE id = $1", table);
+            let deleted = self.execute_with(&sql, |q| q.bind(id)).await? > 0;
+            if deleted {
+                self.audit("DELETE", table, id).await;
+            }
+            return Ok(deleted);
+        };
+
+        let sql = format!(
+            "UPDATE {} SET deleted_at = NOW(), deleted_by = $2 WHERE {} = $1 AND deleted_at IS NULL",
+            table, config.id_column
+        );
+        let deleted_by = self.rls_context.as_ref().map(|context| context.user_id);
+        let deleted = self.execute_scoped(&sql, |q| q.bind(id).bind(deleted_by)).await? > 0;
+        if deleted {
+            self.audit("SOFT_DELETE", table, id).await;
+        }
+        Ok(deleted)
+    }
+
+    /// Bring back a soft-deleted row. Returns whether a row was restored;
+    /// rows RLS hides from the caller are never restored.
+    pub async fn restore(&self, table: &str, id: Uuid) -> DatabaseResult<bool> {
+        let config = self
+            .soft_deletes
+            .as_ref()
+            .and_then(|registry| registry.get(table))
+            .ok_or_else(|| DatabaseError::QueryError(format!("Table {} is not soft-deletable", table)))?;
+
+        let sql = format!(
+            "UPDATE {} SET deleted_at = NULL, deleted_by = NULL WHERE {} = $1 AND deleted_at IS NOT NULL",
+            config.table, config.id_column
+        );
+        let restored = self.execute_scoped(&sql, |q| q.bind(id)).await? > 0;
+        if restored {
+            self.audit("RESTORE", table, id).await;
+        }
+        Ok(restored)
+    }
+
+    /// Hard-delete soft-deleted rows older than each table's retention
+    /// window, skipping rows under an active legal hold. Returns the number
+    /// of rows purged.
+    pub async fn purge_soft_deleted(&self) -> DatabaseResult<u64> {
+        let Some(registry) = &self.soft_deletes else {
+            return Ok(0);
+        };
+
+        let mut purged = 0;
+        for config in registry.tables() {
+            let sql = format!(
+                "DELETE FROM {table} WHERE deleted_at IS NOT NULL \
+                 AND deleted_at < NOW() - make_interval(days => $1) \
+                 AND NOT EXISTS ( \
+                     SELECT 1 FROM {holds} h \
+                     WHERE h.table_name = $2 AND h.record_id = {table}.{id} AND h.released_at IS NULL \
+                 )",
+                table = config.table,
+                id = config.id_column,
+                holds = LEGAL_HOLDS_TABLE,
+            );
+            let count = self
+                .execute_scoped(&sql, |q| q.bind(config.retention_days).bind(config.table.clone()))
+                .await?;
+            if count > 0 {
+                info!(
+                    target: "audit",
+                    table_name = %config.table,
+                    purged = count,
+                    retention_days = config.retention_days,
+                    "Purged soft-deleted rows past retention"
+                );
+            }
+            purged += count;
+        }
+        Ok(purged)
+    }
+
+    /// Run a command in a transaction on its own connection, with the RLS
+    /// context set there first, so the database's policies see the caller
+    async fn execute_scoped<F>(&self, sql: &str, bind_fn: F) -> DatabaseResult<u64>
+    where
+        F: FnOnce(
+            sqlx::query::Query<'_, sqlx::Postgres, sqlx::postgres::PgArguments>,
+        ) -> sqlx::query::Query<'_, sqlx::Postgres, sqlx::postgres::PgArguments>,
+    {
+        debug!("Executing scoped command: {}", sql);
+
+        let query = bind_fn(sqlx::query(sql));
+        let pool = self.pool.pool();
+        let mut in_flight = InFlightQuery::start(pool).await?;
+        let mut tx = in_flight.connection().begin().await.map_err(query_failed)?;
+        if let Some(context) = &self.rls_context {
+            self.pool.apply_rls_context_on(&mut tx, context).await?;
+        }
+        let result = match self.within_timeout(pool, sql, query.execute(&mut *tx)).await? {
+            Ok(done) => tx.commit().await.map(|_| done),
+            Err(e) => {
+                // Rolled back before the connection is next used
+                drop(tx);
+                Err(e)
+            }
+        };
+        in_flight.finish();
+
+        let result = result.map_err(|e| {
+            error!("Command failed: {}", e);
+            query_failed(e)
+        })?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn audit(&self, operation: &str, table: &str, id: Uuid) {
+        let (user_id, tenant_id) = self
+            .rls_context
+            .as_ref()
+            .map_or((Uuid::nil(), String::new()), |context| (context.user_id, context.tenant_id.clone()));
+        let metadata = serde_json::json!({ "audit_type": "record_lifecycle" });
+        // log_operation reports its own storage failures
+        let _ = AuditLogger::new(self.pool.pool().clone())
+            .log_operation(user_id, &tenant_id, operation, table, Some(&id.to_string()), metadata)
+            .await;
+    }
+
+    /// Await a query, failing with [`DatabaseError::Timeout`] if the
+    /// executor's timeout elapses first. Returning early drops the caller's
+    /// [`InFlightQuery`], which cancels the query on the server.
+    async fn within_timeout<R>(
+        &self,
+        pool: &PgPool,
+        sql: &str,
+        query: impl Future<Output = Result<R, sqlx::Error>>,
+    ) -> DatabaseResult<Result<R, sqlx::Error>> {
+        let started = Instant::now();
+        let result = match self.timeout {
+            Some(limit) => tokio::time::timeout(limit, query).await.map_err(|_| {
+                warn!("Query timed out after {}ms: {}", limit.as_millis(), redact_sql(sql));
+                DatabaseError::Timeout(format!("query exceeded {}ms", limit.as_millis()))
+            }),
+            None => Ok(query.await),
+        };
+        if let Some(log) = &self.slow_queries {
+            log.observe(pool, &self.label, sql, started.elapsed());
+        }
+        if matches!(result, Ok(Ok(_))) && !is_read_only(sql) {
+            if let Some(session) = self.session_key() {
+                self.pool.record_write(&session);
+            }
+        }
+        result
+    }
+
+    /// Pool for a fetch. Statements that could write stay on the primary
+    /// even on a read-only executor.
+    fn query_pool(&self, sql: &str) -> &PgPool {
+        if self.read_only && is_read_only(sql) {
+            self.pool.read_pool(self.session_key().as_deref())
+        } else {
+            self.pool.pool()
+        }
+    }
+
+    fn session_key(&self) -> Option<String> {
+        self.session.clone().or_else(|| {
+            self.rls_context
+                .as_ref()
+                .map(|context| context.session_id.clone().unwrap_or_else(|| context.user_id.to_string()))
+        })
+    }
+
     /// Recursively walk JSON and attempt to decrypt string fields using attached encryption engine
     fn try_decrypt_json(&self, mut v: serde_json::Value) -> serde_json::Value {
         if self.encryption.is_none() {
@@ -317,6 +694,98 @@ impl QueryExecutor {
     }
 }
 
+/// A query running on a connection taken out of the pool
+///
+/// Dropped before [`finish`](Self::finish) - because the query timed out or
+/// the caller stopped waiting - it cancels the query on the server and
+/// closes the connection. The connection is held until the cancel has been
+/// issued, so the cancel cannot land on another caller's query.
+struct InFlightQuery {
+    pool: PgPool,
+    connection: Option<PoolConnection<Postgres>>,
+    backend_pid: i32,
+}
+
+impl InFlightQuery {
+    async fn start(pool: &PgPool) -> DatabaseResult<Self> {
+        let mut connection = pool
+            .acquire()
+            .await
+            .map_err(|e| DatabaseError::ConnectionFailed(e.to_string()))?;
+        let backend_pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+            .fetch_one(&mut *connection)
+            .await
+            .map_err(query_failed)?;
+
+        Ok(Self {
+            pool: pool.clone(),
+            connection: Some(connection),
+            backend_pid,
+        })
+    }
+
+    fn connection(&mut self) -> &mut PgConnection {
+        self.connection
+            .as_mut()
+            .expect("connection is held until the query finishes")
+    }
+
+    /// The query completed; return the connection to the pool
+    fn finish(mut self) {
+        self.connection.take();
+    }
+}
+
+impl Drop for InFlightQuery {
+    fn drop(&mut self) {
+        let Some(connection) = self.connection.take() else {
+            return;
+        };
+        let backend_pid = self.backend_pid;
+        warn!(backend_pid, "Cancelling abandoned query");
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let pool = self.pool.clone();
+                runtime.spawn(async move {
+                    if let Err(e) = sqlx::query("SELECT pg_cancel_backend($1)")
+                        .bind(backend_pid)
+                        .execute(&pool)
+                        .await
+                    {
+                        error!(backend_pid, "Failed to cancel query: {}", e);
+                    }
+                    let _ = connection.close().await;
+                });
+            }
+            // No runtime to cancel from; closing the socket is all we can do
+            Err(_) => drop(connection.detach()),
+        }
+    }
+}
+
+/// The version in the first column of a row
+fn read_version(row: &sqlx::postgres::PgRow, column: &VersionColumn) -> DatabaseResult<RowVersion> {
+    let version = match column {
+        VersionColumn::Counter(_) => row.try_get::<i64, _>(0).map(RowVersion::Counter),
+        VersionColumn::Timestamp(_) => row.try_get::<chrono::DateTime<chrono::Utc>, _>(0).map(RowVersion::Timestamp),
+    };
+    version.map_err(|e| DatabaseError::QueryFailed(format!("Failed to read row version: {}", e)))
+}
+
+/// Map a query error, reporting server-side cancellation as a timeout
+fn query_failed(e: sqlx::Error) -> DatabaseError {
+    let canceled = e
+        .as_database_error()
+        .and_then(|db| db.code())
+        .is_some_and(|code| code == QUERY_CANCELED);
+    if canceled {
+        DatabaseError::Timeout(e.to_string())
+    } else {
+        DatabaseError::QueryFailed(e.to_string())
+    }
+}
+
 /// Macro to simplify parameterized queries with QueryExecutor
 ///
 /// Example:
@@ -333,10 +802,357 @@ macro_rules! query_exec {
     };
 }
 
-pub struct QueryBuilder;
+/// Which soft-deleted rows a [`QueryBuilder`] select returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletedRows {
+    /// Live rows only (the default)
+    Exclude,
+    /// Live and soft-deleted rows
+    Include,
+    /// Soft-deleted rows only, e.g. for a recycle bin
+    Only,
+}
+
+/// Builds a `SELECT` over one table
+///
+/// Conditions are SQL fragments with `$n` placeholders, bound through the
+/// executor's `*_with` methods. On a soft-deletable table, rows with
+/// `deleted_at` set are left out unless [`with_deleted`](Self::with_deleted)
+/// or [`only_deleted`](Self::only_deleted) asks for them. That filter is
+/// added alongside RLS policies, never instead of them, so another tenant's
+/// deleted rows stay invisible either way.
+#[derive(Debug, Clone)]
+pub struct QueryBuilder {
+    table: String,
+    columns: String,
+    conditions: Vec<String>,
+    order_by: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    soft_deletable: bool,
+    deleted: DeletedRows,
+}
 
 impl QueryBuilder {
-    pub fn new() -> Self {
-        Self
+    /// Select every column of `table`
+    pub fn select(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            columns: "*".to_string(),
+            conditions: Vec::new(),
+            order_by: None,
+            limit: None,
+            offset: None,
+            soft_deletable: false,
+            deleted: DeletedRows::Exclude,
+        }
+    }
+
+    pub fn columns(mut self, columns: impl Into<String>) -> Self {
+        self.columns = columns.into();
+        self
+    }
+
+    /// Add a condition; conditions are joined with `AND`
+    pub fn filter(mut self, condition: impl Into<String>) -> Self {
+        self.conditions.push(condition.into());
+        self
+    }
+
+    pub fn order_by(mut self, order_by: impl Into<String>) -> Self {
+        self.order_by = Some(order_by.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: i64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Mark the table as having a `deleted_at` column.
+    /// [`QueryExecutor::select`] sets this from its soft-delete registry.
+    pub fn soft_deletable(mut self, soft_deletable: bool) -> Self {
+        self.soft_deletable = soft_deletable;
+        self
+    }
+
+    /// Include soft-deleted rows
+    pub fn with_deleted(mut self) -> Self {
+        self.deleted = DeletedRows::Include;
+        self
+    }
+
+    /// Return only soft-deleted rows
+    pub fn only_deleted(mut self) -> Self {
+        self.deleted = DeletedRows::Only;
+        self
+    }
+
+    pub fn build(&self) -> DatabaseResult<String> {
+        validate_identifier(&self.table)?;
+
+        let mut conditions: Vec<String> = self.conditions.iter().map(|c| format!("({})", c)).collect();
+        if self.soft_deletable {
+            match self.deleted {
+                DeletedRows::Exclude => conditions.push(format!("{}.deleted_at IS NULL", self.table)),
+                DeletedRows::Only => conditions.push(format!("{}.deleted_at IS NOT NULL", self.table)),
+                DeletedRows::Include => {}
+            }
+        }
+
+        let mut sql = format!("SELECT {} FROM {}", self.columns, self.table);
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        if let Some(order_by) = &self.order_by {
+            sql.push_str(&format!(" ORDER BY {}", order_by));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        if let Some(offset) = self.offset {
+            sql.push_str(&format!(" OFFSET {}", offset));
+        }
+        Ok(sql)
+    }
+}
+
+/// Builds a versioned `UPDATE` of one row
+///
+/// The statement binds the row id as `$1` and the expected version as `$2`,
+/// so the caller's assignments start at `$3`. It advances the version and
+/// returns the new one. Run it with [`QueryExecutor::update_versioned`].
+///
+/// ```rust,ignore
+/// let update = executor.update("patients", VersionColumn::version()).set("notes = $3");
+/// let version = executor
+///     .update_versioned(&update, patient_id, expected, |q| q.bind(notes))
+///     .await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct UpdateBuilder {
+    table: String,
+    id_column: String,
+    version: VersionColumn,
+    assignments: Vec<String>,
+    soft_deletable: bool,
+}
+
+impl UpdateBuilder {
+    pub fn new(table: impl Into<String>, version: VersionColumn) -> Self {
+        Self {
+            table: table.into(),
+            id_column: "id".to_string(),
+            version,
+            assignments: Vec::new(),
+            soft_deletable: false,
+        }
+    }
+
+    pub fn with_id_column(mut self, column: impl Into<String>) -> Self {
+        self.id_column = column.into();
+        self
+    }
+
+    /// Add an assignment such as `"notes = $3"`
+    pub fn set(mut self, assignment: impl Into<String>) -> Self {
+        self.assignments.push(assignment.into());
+        self
+    }
+
+    /// Leave soft-deleted rows alone; they conflict as if missing
+    pub fn soft_deletable(mut self, soft_deletable: bool) -> Self {
+        self.soft_deletable = soft_deletable;
+        self
+    }
+
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    pub fn version_column(&self) -> &VersionColumn {
+        &self.version
+    }
+
+    pub fn build(&self) -> DatabaseResult<String> {
+        validate_identifier(&self.table)?;
+        validate_identifier(&self.id_column)?;
+        validate_identifier(self.version.name())?;
+        if self.assignments.is_empty() {
+            return Err(DatabaseError::QueryError("An update needs at least one assignment".to_string()));
+        }
+
+        let mut assignments = self.assignments.clone();
+        assignments.push(self.version.bump());
+        let mut sql = format!(
+            "UPDATE {} SET {} WHERE {} = $1 AND {} = $2",
+            self.table,
+            assignments.join(", "),
+            self.id_column,
+            self.version.name()
+        );
+        if self.soft_deletable {
+            sql.push_str(" AND deleted_at IS NULL");
+        }
+        sql.push_str(&format!(" RETURNING {}", self.version.select_expr()));
+        Ok(sql)
+    }
+
+    /// Query reading the row's current version, for conflict reports
+    fn current_version_sql(&self) -> String {
+        let mut sql = format!(
+            "SELECT {} FROM {} WHERE {} = $1",
+            self.version.select_expr(),
+            self.table,
+            self.id_column
+        );
+        if self.soft_deletable {
+            sql.push_str(" AND deleted_at IS NULL");
+        }
+        sql
+    }
+}
+
+/// Reject anything but a plain, optionally schema-qualified, identifier,
+/// since table and column names cannot be bound as parameters
+pub(crate) fn validate_identifier(identifier: &str) -> DatabaseResult<()> {
+    let valid = !identifier.is_empty()
+        && identifier.split('.').all(|part| {
+            part.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(DatabaseError::QueryError(format!("Invalid identifier: {}", identifier)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Executor against `DATABASE_URL`; the tests are skipped without one
+    async fn executor() -> Option<(QueryExecutor, PgPool)> {
+        let url = std::env::var("DATABASE_URL").ok()?;
+        let pool = PgPool::connect(&url).await.ok()?;
+        Some((QueryExecutor::new(DatabasePool::from_pool(pool.clone())), pool))
+    }
+
+    /// Whether a statement is still running on the server
+    async fn running(pool: &PgPool, sql: &str) -> bool {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_stat_activity WHERE query = $1 AND state = 'active')")
+            .bind(sql)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_query_over_timeout_fails_with_timeout_and_is_cancelled() {
+        let Some((executor, pool)) = executor().await else {
+            return;
+        };
+        let sql = "SELECT pg_sleep(5.01)";
+        let executor = executor.with_timeout(Duration::from_millis(100));
+
+        let err = executor.execute(sql).await.unwrap_err();
+        assert!(matches!(err, DatabaseError::Timeout(_)), "{:?}", err);
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!running(&pool, sql).await);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_query_is_cancelled_on_the_server() {
+        let Some((executor, pool)) = executor().await else {
+            return;
+        };
+        let sql = "SELECT pg_sleep(5.02)";
+        let executor = executor.with_timeout(Duration::from_secs(30));
+
+        let abandoned = tokio::time::timeout(Duration::from_millis(200), executor.execute(sql)).await;
+        assert!(abandoned.is_err());
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!running(&pool, sql).await);
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_and_restore_run_under_the_rls_context() {
+        use crate::rls::RlsContext;
+        use crate::soft_delete::SoftDeleteTable;
+        use sqlx::Executor;
+
+        let Some((_, pool)) = executor().await else {
+            return;
+        };
+        let table = format!("test_rls_scope_{}", Uuid::new_v4().simple());
+        // Records the tenant setting each update sees
+        pool.execute(
+            format!(
+                "CREATE TABLE {table} (id UUID PRIMARY KEY, deleted_at TIMESTAMPTZ, deleted_by UUID, seen_tenant TEXT);
+                 CREATE FUNCTION {table}_seen() RETURNS trigger AS $$ BEGIN
+                     NEW.seen_tenant := current_setting('app.current_tenant_id', true); RETURN NEW;
+                 END $$ LANGUAGE plpgsql;
+                 CREATE TRIGGER {table}_seen BEFORE UPDATE ON {table} FOR EACH ROW EXECUTE FUNCTION {table}_seen();"
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap();
+        let id = Uuid::new_v4();
+        sqlx::query(&format!("INSERT INTO {} (id) VALUES ($1)", table))
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let registry = SoftDeleteRegistry::new().register(SoftDeleteTable::new(&table)).unwrap();
+        let executor = QueryExecutor::new(DatabasePool::from_pool(pool.clone()).with_rls(true))
+            .with_soft_deletes(Arc::new(registry))
+            .with_rls_context(RlsContext::new().with_tenant_id("tenant-a"));
+        let seen = || async {
+            sqlx::query_scalar::<_, Option<String>>(&format!("SELECT seen_tenant FROM {} WHERE id = $1", table))
+                .bind(id)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+
+        assert!(executor.delete(&table, id).await.unwrap());
+        assert_eq!(seen().await.as_deref(), Some("tenant-a"));
+        sqlx::query(&format!("UPDATE {} SET seen_tenant = NULL WHERE id = $1", table))
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(seen().await, None);
+        assert!(executor.restore(&table, id).await.unwrap());
+        assert_eq!(seen().await.as_deref(), Some("tenant-a"));
+
+        pool.execute(format!("DROP TABLE {table}; DROP FUNCTION {table}_seen();").as_str())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_query_without_timeout_is_cancelled_on_the_server() {
+        let Some((executor, pool)) = executor().await else {
+            return;
+        };
+        let sql = "SELECT pg_sleep(5.03)";
+
+        let abandoned = tokio::time::timeout(Duration::from_millis(200), executor.execute(sql)).await;
+        assert!(abandoned.is_err());
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!running(&pool, sql).await);
     }
 }
@@ -0,0 +1,252 @@
+// Read/write routing between the primary and read replicas
+//
+// # Consistency
+//
+// Replicas apply the primary's changes asynchronously, so a read routed to
+// a replica can miss writes that have already committed on the primary:
+// a record just created may not be found, or an update may appear undone.
+// Queries only go to a replica when the caller marks them read-only
+// (`QueryExecutor::read_only`), and callers should only do so where a
+// slightly stale answer is acceptable - listings, searches, reports - and
+// never for a read that decides what to write next.
+//
+// Read-your-writes narrows the gap for one session: after the session
+// writes, its reads stay on the primary for a configurable window, which
+// should exceed the usual replication lag. It cannot help across sessions,
+// and lag beyond the window still shows through.
+//
+// Writes, and everything inside a transaction, always use the primary.
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How a read picks among healthy replicas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplicaStrategy {
+    #[default]
+    RoundRobin,
+    /// The replica with the fewest connections checked out
+    LeastConnections,
+}
+
+/// A read replica and whether it is in rotation
+pub struct Replica {
+    name: String,
+    pool: PgPool,
+    healthy: AtomicBool,
+}
+
+impl Replica {
+    pub fn new(name: impl Into<String>, pool: PgPool) -> Self {
+        Self {
+            name: name.into(),
+            pool,
+            healthy: AtomicBool::new(true),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    /// Connections currently checked out of the replica's pool
+    fn in_use(&self) -> usize {
+        (self.pool.size() as usize).saturating_sub(self.pool.num_idle())
+    }
+}
+
+/// Health of one replica, as reported by [`ReplicaSet::status`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicaStatus {
+    pub name: String,
+    pub healthy: bool,
+    pub connections_in_use: usize,
+}
+
+/// Read replicas with health-checked rotation and read-your-writes pins
+pub struct ReplicaSet {
+    replicas: Vec<Replica>,
+    strategy: ReplicaStrategy,
+    next: AtomicUsize,
+    read_your_writes: Option<Duration>,
+    pins: Mutex<HashMap<String, Instant>>,
+}
+
+impl ReplicaSet {
+    pub fn new(replicas: Vec<Replica>) -> Self {
+        Self {
+            replicas,
+            strategy: ReplicaStrategy::default(),
+            next: AtomicUsize::new(0),
+            read_your_writes: None,
+            pins: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: ReplicaStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Keep a session's reads on the primary for `window` after it writes
+    pub fn with_read_your_writes(mut self, window: Duration) -> Self {
+        self.read_your_writes = Some(window);
+        self
+    }
+
+    pub fn replicas(&self) -> &[Replica] {
+        &self.replicas
+    }
+
+    pub fn status(&self) -> Vec<ReplicaStatus> {
+        self.replicas
+            .iter()
+            .map(|replica| ReplicaStatus {
+                name: replica.name.clone(),
+                healthy: replica.is_healthy(),
+                connections_in_use: replica.in_use(),
+            })
+            .collect()
+    }
+
+    /// A healthy replica to read from, or `None` if the session is pinned
+    /// to the primary or no replica is healthy
+    pub fn select(&self, session: Option<&str>) -> Option<&Replica> {
+        if session.is_some_and(|session| self.is_pinned(session)) {
+            return None;
+        }
+
+        match self.strategy {
+            ReplicaStrategy::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..self.replicas.len())
+                    .map(|offset| &self.replicas[(start + offset) % self.replicas.len()])
+                    .find(|replica| replica.is_healthy())
+            }
+            ReplicaStrategy::LeastConnections => self
+                .replicas
+                .iter()
+                .filter(|replica| replica.is_healthy())
+                .min_by_key(|replica| replica.in_use()),
+        }
+    }
+
+    /// Record that a session wrote, pinning its reads to the primary for
+    /// the read-your-writes window
+    pub fn record_write(&self, session: &str) {
+        let Some(window) = self.read_your_writes else {
+            return;
+        };
+        let now = Instant::now();
+        let mut pins = self.pins.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        pins.retain(|_, until| *until > now);
+        pins.insert(session.to_string(), now + window);
+    }
+
+    fn is_pinned(&self, session: &str) -> bool {
+        let pins = self.pins.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        pins.get(session).is_some_and(|until| *until > Instant::now())
+    }
+
+    /// Probe every replica, taking failing ones out of rotation and
+    /// returning recovered ones to it
+    pub async fn check_health(&self, timeout: Duration) {
+        for replica in &self.replicas {
+            let probe = sqlx::query("SELECT 1").execute(&replica.pool);
+            let healthy = matches!(tokio::time::timeout(timeout, probe).await, Ok(Ok(_)));
+            let was_healthy = replica.healthy.swap(healthy, Ordering::Relaxed);
+            if was_healthy && !healthy {
+                warn!(replica = %replica.name, "Read replica failed its health check; removed from rotation");
+            } else if !was_healthy && healthy {
+                info!(replica = %replica.name, "Read replica recovered; returned to rotation");
+            }
+        }
+    }
+
+    /// Run [`check_health`](Self::check_health) every `interval` until the
+    /// set is dropped
+    pub fn spawn_health_checks(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let replicas = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(replicas) = replicas.upgrade() else {
+                    break;
+                };
+                replicas.check_health(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replica_set(names: &[&str]) -> ReplicaSet {
+        let replicas = names
+            .iter()
+            .map(|name| {
+                let pool = sqlx::postgres::PgPoolOptions::new()
+                    .connect_lazy("postgres://localhost/replica")
+                    .unwrap();
+                Replica::new(*name, pool)
+            })
+            .collect();
+        ReplicaSet::new(replicas)
+    }
+
+    fn selected(set: &ReplicaSet, session: Option<&str>) -> Option<String> {
+        set.select(session).map(|replica| replica.name().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_skips_unhealthy_replicas() {
+        let set = replica_set(&["a", "b", "c"]);
+        let picks: Vec<_> = (0..3).filter_map(|_| selected(&set, None)).collect();
+        assert_eq!(picks, ["a", "b", "c"]);
+
+        set.replicas[1].healthy.store(false, Ordering::Relaxed);
+        let picks: Vec<_> = (0..4).filter_map(|_| selected(&set, None)).collect();
+        assert!(picks.iter().all(|name| name != "b"), "{:?}", picks);
+
+        for replica in &set.replicas {
+            replica.healthy.store(false, Ordering::Relaxed);
+        }
+        assert_eq!(selected(&set, None), None);
+    }
+
+    #[tokio::test]
+    async fn test_writes_pin_the_session_to_the_primary_for_the_window() {
+        let set = replica_set(&["a"]).with_read_your_writes(Duration::from_millis(100));
+        set.record_write("alice");
+
+        assert_eq!(selected(&set, Some("alice")), None);
+        assert_eq!(selected(&set, Some("bob")).as_deref(), Some("a"));
+        assert_eq!(selected(&set, None).as_deref(), Some("a"));
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(selected(&set, Some("alice")).as_deref(), Some("a"));
+    }
+
+    #[tokio::test]
+    async fn test_writes_do_not_pin_without_read_your_writes() {
+        let set = replica_set(&["a"]);
+        set.record_write("alice");
+        assert_eq!(selected(&set, Some("alice")).as_deref(), Some("a"));
+    }
+}
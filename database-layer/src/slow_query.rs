@@ -0,0 +1,341 @@
+// Slow-query logging and execution-plan capture
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Histogram of query durations in seconds, labelled by `query`
+pub const QUERY_DURATION_METRIC: &str = "db_query_duration_seconds";
+
+/// Label for queries the caller did not name
+pub const UNLABELLED_QUERY: &str = "unlabelled";
+
+/// Keywords that make a statement unsafe to run under `EXPLAIN ANALYZE`,
+/// which executes it
+const MUTATING_KEYWORDS: &[&str] = &[
+    "insert", "update", "delete", "merge", "truncate", "copy", "create", "alter", "drop",
+    "grant", "revoke", "call", "do", "lock", "vacuum", "analyze", "refresh", "into", "for",
+    "nextval", "setval", "set_config",
+];
+
+/// Slow-query logging settings
+#[derive(Debug, Clone)]
+pub struct SlowQueryConfig {
+    /// Queries taking at least this long are logged
+    pub threshold: Duration,
+    /// Fraction of slow read-only queries whose plan is captured (0.0 - 1.0)
+    pub plan_sample_rate: f64,
+    /// Slow queries kept for [`SlowQueryLog::recent`]
+    pub capacity: usize,
+}
+
+impl Default for SlowQueryConfig {
+    fn default() -> Self {
+        Self {
+            threshold: Duration::from_millis(500),
+            plan_sample_rate: 0.05,
+            capacity: 100,
+        }
+    }
+}
+
+impl SlowQueryConfig {
+    /// Load slow-query settings from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            threshold: std::env::var("DATABASE_SLOW_QUERY_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map_or(defaults.threshold, Duration::from_millis),
+            plan_sample_rate: std::env::var("DATABASE_SLOW_QUERY_PLAN_SAMPLE_RATE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.plan_sample_rate),
+            capacity: std::env::var("DATABASE_SLOW_QUERY_CAPACITY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.capacity),
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_plan_sample_rate(mut self, rate: f64) -> Self {
+        self.plan_sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+/// A query that exceeded the slow-query threshold
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowQuery {
+    pub id: uuid::Uuid,
+    pub label: String,
+    /// Statement text with literals replaced; bound parameters are never
+    /// recorded
+    pub sql: String,
+    pub parameter_count: usize,
+    pub duration_ms: u64,
+    pub at: DateTime<Utc>,
+    /// `EXPLAIN` output, if this query was sampled for plan capture
+    pub plan: Option<String>,
+}
+
+/// Records query durations and keeps the most recent slow queries
+///
+/// Every query's duration goes to the [`QUERY_DURATION_METRIC`] histogram.
+/// Queries over the threshold are logged with their literals redacted, and a
+/// sample of the read-only ones has its plan captured in the background:
+/// `EXPLAIN (ANALYZE, BUFFERS)` for statements without bind parameters, and
+/// `EXPLAIN (GENERIC_PLAN)` (PostgreSQL 16+, estimates only) for those with
+/// them, since the bound values are not kept. Plans run in a transaction
+/// that is always rolled back, with `statement_timeout` capped, and never
+/// for a statement that could write.
+pub struct SlowQueryLog {
+    config: SlowQueryConfig,
+    recent: Arc<Mutex<VecDeque<SlowQuery>>>,
+}
+
+impl SlowQueryLog {
+    pub fn new(config: SlowQueryConfig) -> Self {
+        let metrics = telemetry::MetricsCollector::global();
+        metrics.describe_histogram(
+            QUERY_DURATION_METRIC,
+            "Database query latency in seconds by query label",
+            telemetry::DEFAULT_BUCKETS,
+        );
+        metrics.restrict_labels(QUERY_DURATION_METRIC, telemetry::MetricKind::Histogram, &["query"]);
+
+        Self {
+            config,
+            recent: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn config(&self) -> &SlowQueryConfig {
+        &self.config
+    }
+
+    /// Slow queries, most recent first
+    pub fn recent(&self) -> Vec<SlowQuery> {
+        self.lock().iter().rev().cloned().collect()
+    }
+
+    /// Forget all recorded slow queries
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Record a query's duration; log it, and perhaps capture its plan, if
+    /// it was slow
+    pub fn observe(&self, pool: &PgPool, label: &str, sql: &str, duration: Duration) {
+        telemetry::MetricsCollector::global()
+            .histogram(QUERY_DURATION_METRIC)
+            .with_label("query", label)
+            .record(duration.as_secs_f64());
+
+        if duration < self.config.threshold {
+            return;
+        }
+
+        let entry = SlowQuery {
+            id: uuid::Uuid::new_v4(),
+            label: label.to_string(),
+            sql: redact_sql(sql),
+            parameter_count: parameter_count(sql),
+            duration_ms: duration.as_millis() as u64,
+            at: Utc::now(),
+            plan: None,
+        };
+        warn!(
+            query = %entry.label,
+            duration_ms = entry.duration_ms,
+            parameters = entry.parameter_count,
+            sql = %entry.sql,
+            "Slow query"
+        );
+
+        let capture = is_read_only(sql) && rand::random::<f64>() < self.config.plan_sample_rate;
+        let id = entry.id;
+        {
+            let mut recent = self.lock();
+            recent.push_back(entry);
+            while recent.len() > self.config.capacity {
+                recent.pop_front();
+            }
+        }
+
+        if capture {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                let pool = pool.clone();
+                let sql = sql.to_string();
+                let recent = self.recent.clone();
+                // Give the plan room to finish, but not to run away
+                let timeout = duration.saturating_mul(2).max(Duration::from_secs(1));
+                runtime.spawn(async move {
+                    match capture_plan(&pool, &sql, timeout).await {
+                        Ok(plan) => {
+                            let mut recent = recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                            if let Some(entry) = recent.iter_mut().find(|entry| entry.id == id) {
+                                entry.plan = Some(plan);
+                            }
+                        }
+                        Err(e) => debug!("Failed to capture query plan: {}", e),
+                    }
+                });
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<SlowQuery>> {
+        self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for SlowQueryLog {
+    fn default() -> Self {
+        Self::new(SlowQueryConfig::default())
+    }
+}
+
+/// Run `EXPLAIN` for a read-only statement inside a transaction that is
+/// rolled back
+async fn capture_plan(pool: &PgPool, sql: &str, timeout: Duration) -> Result<String, sqlx::Error> {
+    let explain = if parameter_count(sql) == 0 {
+        format!("EXPLAIN (ANALYZE, BUFFERS) {}", sql)
+    } else {
+        format!("EXPLAIN (GENERIC_PLAN) {}", sql)
+    };
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION READ ONLY").execute(&mut *tx).await?;
+    sqlx::query(&format!("SET LOCAL statement_timeout = {}", timeout.as_millis()))
+        .execute(&mut *tx)
+        .await?;
+    let lines: Vec<String> = sqlx::query_scalar(&explain).fetch_all(&mut *tx).await?;
+    tx.rollback().await?;
+    Ok(lines.join("\n"))
+}
+
+/// Whether a statement is a plain query that cannot write
+///
+/// Conservative: anything that is not a single `SELECT`/`WITH`/`VALUES`/
+/// `TABLE` statement free of writing keywords (including `SELECT ... INTO`
+/// and `FOR UPDATE` locks) is treated as mutating.
+pub fn is_read_only(sql: &str) -> bool {
+    let stripped = redact_sql(sql).to_ascii_lowercase();
+    let statement = stripped.trim().trim_end_matches(';');
+    if statement.contains(';') {
+        return false;
+    }
+    let mut words = statement
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty());
+    if !matches!(words.next(), Some("select" | "with" | "values" | "table")) {
+        return false;
+    }
+    !words.any(|word| MUTATING_KEYWORDS.contains(&word))
+}
+
+/// Replace string and numeric literals in a statement so that no values
+/// reach the logs. Identifiers, keywords and `$n` placeholders are kept.
+pub fn redact_sql(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut previous: Option<char> = None;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // Quoted string; '' is an escaped quote inside it
+                while let Some(next) = chars.next() {
+                    if next == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push_str("'?'");
+                previous = Some('\'');
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                // Comments can carry values too
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+                previous = Some('\n');
+            }
+            c if c.is_ascii_digit()
+                && !previous.is_some_and(|p| p.is_alphanumeric() || p == '_' || p == '$') =>
+            {
+                while chars.peek().is_some_and(|next| next.is_ascii_alphanumeric() || *next == '.') {
+                    chars.next();
+                }
+                out.push('?');
+                previous = Some('?');
+            }
+            c => {
+                out.push(c);
+                previous = Some(c);
+            }
+        }
+    }
+    out
+}
+
+/// Number of distinct `$n` placeholders in a statement
+pub fn parameter_count(sql: &str) -> usize {
+    redact_sql(sql)
+        .split('$')
+        .skip(1)
+        .filter_map(|rest| {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<usize>().ok()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_sql_removes_literals() {
+        let sql = "SELECT * FROM patients WHERE mrn = 'MRN-0042' AND age > 65 AND id = $1 -- jane doe\nLIMIT 10";
+        assert_eq!(
+            redact_sql(sql),
+            "SELECT * FROM patients WHERE mrn = '?' AND age > ? AND id = $1 \nLIMIT ?"
+        );
+        assert_eq!(redact_sql("SELECT 'O''Brien' FROM t1"), "SELECT '?' FROM t1");
+        assert_eq!(parameter_count("SELECT $1, $3 FROM t WHERE a = $2"), 3);
+    }
+
+    #[test]
+    fn test_only_plain_queries_are_read_only() {
+        assert!(is_read_only("SELECT id FROM patients WHERE id = $1"));
+        assert!(is_read_only("WITH recent AS (SELECT 1) SELECT * FROM recent;"));
+        assert!(is_read_only("SELECT * FROM audit WHERE note = 'delete me'"));
+        assert!(!is_read_only("UPDATE patients SET name = $1"));
+        assert!(!is_read_only("WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone"));
+        assert!(!is_read_only("SELECT * FROM t FOR UPDATE"));
+        assert!(!is_read_only("SELECT * INTO backup FROM t"));
+        assert!(!is_read_only("SELECT 1; DROP TABLE t"));
+    }
+}
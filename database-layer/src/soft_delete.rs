@@ -0,0 +1,241 @@
+// Soft deletes, legal holds and the recycle-bin purge
+//
+// Healthcare records are rarely removed outright. A soft-deletable table has
+// `deleted_at` and `deleted_by` columns; `QueryExecutor::delete` sets them,
+// `QueryExecutor::restore` clears them, and `QueryExecutor::select` leaves
+// deleted rows out unless asked. Rows stay in the recycle bin for the
+// table's retention window, after which the purge job removes them - unless
+// a legal hold covers the row.
+use crate::error::{DatabaseError, DatabaseResult};
+use crate::query::{validate_identifier, QueryExecutor};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Table recording legal holds on individual rows
+pub const LEGAL_HOLDS_TABLE: &str = "record_legal_holds";
+
+/// Days a soft-deleted row is kept when a table does not say otherwise
+pub const DEFAULT_RETENTION_DAYS: i32 = 90;
+
+/// A table whose deletes are soft
+#[derive(Debug, Clone)]
+pub struct SoftDeleteTable {
+    pub table: String,
+    /// UUID primary key column
+    pub id_column: String,
+    /// Days a deleted row stays recoverable before it is purged
+    pub retention_days: i32,
+}
+
+impl SoftDeleteTable {
+    pub fn new(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            id_column: "id".to_string(),
+            retention_days: DEFAULT_RETENTION_DAYS,
+        }
+    }
+
+    pub fn with_id_column(mut self, column: impl Into<String>) -> Self {
+        self.id_column = column.into();
+        self
+    }
+
+    pub fn with_retention_days(mut self, days: i32) -> Self {
+        self.retention_days = days;
+        self
+    }
+
+    /// DDL adding the soft-delete columns, for the table's migration
+    pub fn migration_sql(&self) -> String {
+        let index_name = format!("idx_{}_deleted_at", self.table.replace('.', "_"));
+        format!(
+            "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;\n\
+             ALTER TABLE {table} ADD COLUMN IF NOT EXISTS deleted_by UUID;\n\
+             CREATE INDEX IF NOT EXISTS {index} ON {table}(deleted_at) WHERE deleted_at IS NOT NULL;",
+            table = self.table,
+            index = index_name,
+        )
+    }
+}
+
+/// The soft-deletable tables
+#[derive(Debug, Clone, Default)]
+pub struct SoftDeleteRegistry {
+    tables: HashMap<String, SoftDeleteTable>,
+}
+
+impl SoftDeleteRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(mut self, table: SoftDeleteTable) -> DatabaseResult<Self> {
+        validate_identifier(&table.table)?;
+        validate_identifier(&table.id_column)?;
+        if table.retention_days < 0 {
+            return Err(DatabaseError::ConfigurationError(format!(
+                "Retention for {} cannot be negative",
+                table.table
+            )));
+        }
+        self.tables.insert(table.table.clone(), table);
+        Ok(self)
+    }
+
+    pub fn get(&self, table: &str) -> Option<&SoftDeleteTable> {
+        self.tables.get(table)
+    }
+
+    pub fn is_soft_deletable(&self, table: &str) -> bool {
+        self.tables.contains_key(table)
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &SoftDeleteTable> {
+        self.tables.values()
+    }
+}
+
+/// A hold keeping one row from being purged
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LegalHold {
+    pub id: Uuid,
+    pub table_name: String,
+    pub record_id: Uuid,
+    pub reason: String,
+    pub placed_by: Uuid,
+    pub placed_at: DateTime<Utc>,
+    pub released_by: Option<Uuid>,
+    pub released_at: Option<DateTime<Utc>>,
+}
+
+/// Places and releases legal holds on rows
+///
+/// Holds are never deleted: releasing one records who released it and when.
+pub struct LegalHolds {
+    pool: PgPool,
+}
+
+impl LegalHolds {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn place(&self, table: &str, record_id: Uuid, reason: &str, placed_by: Uuid) -> DatabaseResult<LegalHold> {
+        validate_identifier(table)?;
+        if reason.trim().is_empty() {
+            return Err(DatabaseError::QueryError("A legal hold needs a reason".to_string()));
+        }
+
+        let hold: LegalHold = sqlx::query_as(&format!(
+            "INSERT INTO {} (id, table_name, record_id, reason, placed_by, placed_at) \
+             VALUES ($1, $2, $3, $4, $5, NOW()) RETURNING *",
+            LEGAL_HOLDS_TABLE
+        ))
+        .bind(Uuid::new_v4())
+        .bind(table)
+        .bind(record_id)
+        .bind(reason)
+        .bind(placed_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(
+            target: "audit",
+            hold_id = %hold.id,
+            table_name = %table,
+            record_id = %record_id,
+            placed_by = %placed_by,
+            "Legal hold placed"
+        );
+        Ok(hold)
+    }
+
+    /// Release an active hold. Returns whether one was released.
+    pub async fn release(&self, hold_id: Uuid, released_by: Uuid) -> DatabaseResult<bool> {
+        let result = sqlx::query(&format!(
+            "UPDATE {} SET released_by = $2, released_at = NOW() WHERE id = $1 AND released_at IS NULL",
+            LEGAL_HOLDS_TABLE
+        ))
+        .bind(hold_id)
+        .bind(released_by)
+        .execute(&self.pool)
+        .await?;
+
+        let released = result.rows_affected() > 0;
+        if released {
+            info!(target: "audit", hold_id = %hold_id, released_by = %released_by, "Legal hold released");
+        }
+        Ok(released)
+    }
+
+    /// Active holds on a row
+    pub async fn active(&self, table: &str, record_id: Uuid) -> DatabaseResult<Vec<LegalHold>> {
+        let holds = sqlx::query_as(&format!(
+            "SELECT * FROM {} WHERE table_name = $1 AND record_id = $2 AND released_at IS NULL \
+             ORDER BY placed_at",
+            LEGAL_HOLDS_TABLE
+        ))
+        .bind(table)
+        .bind(record_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(holds)
+    }
+}
+
+/// Run [`QueryExecutor::purge_soft_deleted`] every `interval`. The executor
+/// should carry no RLS context, so the purge covers every tenant.
+pub fn spawn_purge_job(executor: Arc<QueryExecutor>, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = executor.purge_soft_deleted().await {
+                error!("Soft-delete purge failed: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryBuilder;
+
+    #[test]
+    fn test_select_excludes_soft_deleted_rows_by_default() {
+        let select = QueryBuilder::select("patients")
+            .filter("tenant_id = $1")
+            .soft_deletable(true);
+        assert_eq!(
+            select.build().unwrap(),
+            "SELECT * FROM patients WHERE (tenant_id = $1) AND patients.deleted_at IS NULL"
+        );
+        assert_eq!(
+            select.clone().with_deleted().build().unwrap(),
+            "SELECT * FROM patients WHERE (tenant_id = $1)"
+        );
+        assert_eq!(
+            select.only_deleted().limit(10).build().unwrap(),
+            "SELECT * FROM patients WHERE (tenant_id = $1) AND patients.deleted_at IS NOT NULL LIMIT 10"
+        );
+        assert_eq!(QueryBuilder::select("audit_log").build().unwrap(), "SELECT * FROM audit_log");
+    }
+
+    #[test]
+    fn test_registry_rejects_unsafe_identifiers() {
+        assert!(SoftDeleteRegistry::new().register(SoftDeleteTable::new("patients; DROP TABLE x")).is_err());
+        let registry = SoftDeleteRegistry::new()
+            .register(SoftDeleteTable::new("clinical.notes").with_retention_days(365))
+            .unwrap();
+        assert!(registry.is_soft_deletable("clinical.notes"));
+        assert!(registry.get("clinical.notes").unwrap().migration_sql().contains("idx_clinical_notes_deleted_at"));
+    }
+}
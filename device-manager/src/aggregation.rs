@@ -0,0 +1,392 @@
+use crate::{DeviceData, DeviceReading, DeviceRepository};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex, RwLock};
+use uuid::Uuid;
+
+/// `DeviceData::data_type` of stored aggregates
+pub const AGGREGATE_DATA_TYPE: &str = "aggregate";
+
+const STREAM_CAPACITY: usize = 1024;
+
+/// How a device type's numeric readings are downsampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregationConfig {
+    /// Length of each window; windows are aligned to the Unix epoch so every
+    /// device of the type shares the same boundaries
+    pub window: Duration,
+    /// Keep raw readings in memory for this long, for alarm evaluation
+    pub raw_retention: Option<Duration>,
+}
+
+impl AggregationConfig {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            raw_retention: None,
+        }
+    }
+
+    pub fn with_raw_retention(mut self, retention: Duration) -> Self {
+        self.raw_retention = Some(retention);
+        self
+    }
+}
+
+/// Summary of one series over one window. Only readings that were actually
+/// received are counted; a window without readings is never produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregatedReading {
+    /// Registered device the readings came in on
+    pub device_id: Uuid,
+    /// Device identifier given in the messages themselves
+    pub source: String,
+    pub code: String,
+    pub code_system: Option<String>,
+    pub display: Option<String>,
+    pub unit: Option<String>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Timestamps of the first and last reading in the window
+    pub first_at: DateTime<Utc>,
+    pub last_at: DateTime<Utc>,
+    /// Flushed before the window's end time had passed, so it may be
+    /// missing readings the device would still have sent
+    pub partial: bool,
+}
+
+/// A raw reading as it was received
+#[derive(Debug, Clone)]
+pub struct RawReading {
+    pub device_id: Uuid,
+    pub reading: DeviceReading,
+}
+
+struct RawBuffer {
+    retention: Duration,
+    readings: VecDeque<(Instant, DeviceReading)>,
+}
+
+impl RawBuffer {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .readings
+            .front()
+            .is_some_and(|(received, _)| now.duration_since(*received) > self.retention)
+        {
+            self.readings.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SeriesKey {
+    device_id: Uuid,
+    source: String,
+    code: String,
+    unit: Option<String>,
+}
+
+struct Window {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    code_system: Option<String>,
+    display: Option<String>,
+    count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    first_at: DateTime<Utc>,
+    last_at: DateTime<Utc>,
+    /// Arrival of the latest reading, used to close windows of series that
+    /// stopped reporting regardless of device clock skew
+    updated: Instant,
+}
+
+impl Window {
+    fn open(start: DateTime<Utc>, length: Duration, reading: &DeviceReading, value: f64) -> Self {
+        Self {
+            start,
+            end: start + ChronoDuration::from_std(length).unwrap_or(ChronoDuration::zero()),
+            code_system: reading.code_system.clone(),
+            display: reading.display.clone(),
+            count: 1,
+            min: value,
+            max: value,
+            sum: value,
+            first_at: reading.timestamp,
+            last_at: reading.timestamp,
+            updated: Instant::now(),
+        }
+    }
+
+    fn add(&mut self, timestamp: DateTime<Utc>, value: f64) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.first_at = self.first_at.min(timestamp);
+        self.last_at = self.last_at.max(timestamp);
+        self.updated = Instant::now();
+    }
+
+    fn close(self, key: SeriesKey, partial: bool) -> AggregatedReading {
+        AggregatedReading {
+            device_id: key.device_id,
+            source: key.source,
+            code: key.code,
+            code_system: self.code_system,
+            display: self.display,
+            unit: key.unit,
+            window_start: self.start,
+            window_end: self.end,
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: self.sum / self.count as f64,
+            first_at: self.first_at,
+            last_at: self.last_at,
+            partial,
+        }
+    }
+}
+
+/// Start of the epoch-aligned window containing `timestamp`
+fn window_start(timestamp: DateTime<Utc>, length: Duration) -> DateTime<Utc> {
+    let length_ms = length.as_millis().max(1) as i64;
+    let millis = timestamp.timestamp_millis();
+    DateTime::from_timestamp_millis(millis - millis.rem_euclid(length_ms)).unwrap_or(timestamp)
+}
+
+/// Downsamples numeric readings into per-series windows and publishes raw
+/// and aggregated streams.
+///
+/// A window closes when a reading for a later window arrives, when the
+/// series has been idle for longer than the window, or when the device
+/// disconnects. Readings for a window that has already closed are late:
+/// they still go to the raw stream but are not aggregated.
+pub struct ReadingAggregator {
+    configs: RwLock<HashMap<String, AggregationConfig>>,
+    windows: Mutex<HashMap<SeriesKey, Window>>,
+    raw: Mutex<HashMap<Uuid, RawBuffer>>,
+    raw_events: broadcast::Sender<RawReading>,
+    aggregated_events: broadcast::Sender<AggregatedReading>,
+}
+
+impl ReadingAggregator {
+    pub fn new() -> Self {
+        let (raw_events, _) = broadcast::channel(STREAM_CAPACITY);
+        let (aggregated_events, _) = broadcast::channel(STREAM_CAPACITY);
+        Self {
+            configs: RwLock::new(HashMap::new()),
+            windows: Mutex::new(HashMap::new()),
+            raw: Mutex::new(HashMap::new()),
+            raw_events,
+            aggregated_events,
+        }
+    }
+
+    pub async fn set_config(&self, device_type: String, config: AggregationConfig) {
+        self.configs.write().await.insert(device_type, config);
+    }
+
+    pub async fn remove_config(&self, device_type: &str) -> Option<AggregationConfig> {
+        self.configs.write().await.remove(device_type)
+    }
+
+    pub async fn config(&self, device_type: &str) -> Option<AggregationConfig> {
+        self.configs.read().await.get(device_type).copied()
+    }
+
+    /// Every reading at full resolution, for alarm evaluation
+    pub fn subscribe_raw(&self) -> broadcast::Receiver<RawReading> {
+        self.raw_events.subscribe()
+    }
+
+    /// Closed windows, for charting
+    pub fn subscribe_aggregated(&self) -> broadcast::Receiver<AggregatedReading> {
+        self.aggregated_events.subscribe()
+    }
+
+    /// Raw readings still inside the device's retention period, oldest first
+    pub async fn raw_readings(&self, device_id: Uuid) -> Vec<DeviceReading> {
+        let mut raw = self.raw.lock().await;
+        let Some(buffer) = raw.get_mut(&device_id) else {
+            return Vec::new();
+        };
+        buffer.prune(Instant::now());
+        buffer
+            .readings
+            .iter()
+            .map(|(_, reading)| reading.clone())
+            .collect()
+    }
+
+    /// Publish the readings on the raw stream and fold the numeric ones into
+    /// their windows. Returns the windows this closed; nothing is aggregated
+    /// when the device type has no configuration.
+    pub async fn record(
+        &self,
+        device_id: Uuid,
+        device_type: &str,
+        readings: &[DeviceReading],
+    ) -> Vec<AggregatedReading> {
+        for reading in readings {
+            // No subscribers is not an error
+            let _ = self.raw_events.send(RawReading {
+                device_id,
+                reading: reading.clone(),
+            });
+        }
+
+        let Some(config) = self.config(device_type).await else {
+            return Vec::new();
+        };
+        if let Some(retention) = config.raw_retention {
+            self.retain_raw(device_id, readings, retention).await;
+        }
+
+        let mut closed = Vec::new();
+        let mut windows = self.windows.lock().await;
+        for reading in readings {
+            let Some(value) = reading.value.as_f64() else {
+                continue;
+            };
+            let key = SeriesKey {
+                device_id,
+                source: reading.device_id.clone(),
+                code: reading.code.clone(),
+                unit: reading.unit.clone(),
+            };
+            let start = window_start(reading.timestamp, config.window);
+
+            match windows.get_mut(&key) {
+                Some(window) if window.start == start => window.add(reading.timestamp, value),
+                Some(window) if window.start > start => {
+                    tracing::debug!(
+                        device_id = %device_id,
+                        code = %reading.code,
+                        timestamp = %reading.timestamp,
+                        "Reading arrived after its window closed; not aggregated"
+                    );
+                }
+                _ => {
+                    let window = Window::open(start, config.window, reading, value);
+                    if let Some(previous) = windows.insert(key.clone(), window) {
+                        closed.push(previous.close(key, false));
+                    }
+                }
+            }
+        }
+        drop(windows);
+
+        self.publish(&closed);
+        closed
+    }
+
+    /// Close every open window of a device, e.g. when it disconnects
+    pub async fn flush_device(&self, device_id: Uuid) -> Vec<AggregatedReading> {
+        let closed = self.take_windows(|key, _| key.device_id == device_id).await;
+        self.raw.lock().await.remove(&device_id);
+        self.publish(&closed);
+        closed
+    }
+
+    /// Close windows of series that have received nothing for longer than
+    /// their own length
+    pub async fn flush_idle(&self) -> Vec<AggregatedReading> {
+        let closed = self
+            .take_windows(|_, window| {
+                let length = (window.end - window.start).to_std().unwrap_or_default();
+                window.updated.elapsed() > length
+            })
+            .await;
+        self.publish(&closed);
+        closed
+    }
+
+    async fn take_windows<F>(&self, mut matches: F) -> Vec<AggregatedReading>
+    where
+        F: FnMut(&SeriesKey, &Window) -> bool,
+    {
+        let mut windows = self.windows.lock().await;
+        let keys: Vec<SeriesKey> = windows
+            .iter()
+            .filter(|(key, window)| matches(key, window))
+            .map(|(key, _)| key.clone())
+            .collect();
+        let now = Utc::now();
+        keys.into_iter()
+            .filter_map(|key| {
+                let window = windows.remove(&key)?;
+                let partial = now < window.end;
+                Some(window.close(key, partial))
+            })
+            .collect()
+    }
+
+    async fn retain_raw(&self, device_id: Uuid, readings: &[DeviceReading], retention: Duration) {
+        let now = Instant::now();
+        let mut raw = self.raw.lock().await;
+        let buffer = raw.entry(device_id).or_insert_with(|| RawBuffer {
+            retention,
+            readings: VecDeque::new(),
+        });
+        buffer.retention = retention;
+        buffer
+            .readings
+            .extend(readings.iter().map(|reading| (now, reading.clone())));
+        buffer.prune(now);
+    }
+
+    fn publish(&self, closed: &[AggregatedReading]) {
+        for aggregate in closed {
+            let _ = self.aggregated_events.send(aggregate.clone());
+        }
+    }
+}
+
+impl Default for ReadingAggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Store closed windows as device data. Failures are logged, since the
+/// windows have already been published and cannot be reopened.
+pub async fn persist_aggregates(repository: &DeviceRepository, aggregates: &[AggregatedReading]) {
+    for aggregate in aggregates {
+        let parsed = match serde_json::to_value(aggregate) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                tracing::warn!(device_id = %aggregate.device_id, error = %e, "Failed to serialize aggregate");
+                continue;
+            }
+        };
+        let now = Utc::now();
+        let data = DeviceData {
+            id: Uuid::new_v4(),
+            device_id: aggregate.device_id,
+            timestamp: aggregate.window_start,
+            data_type: AGGREGATE_DATA_TYPE.to_string(),
+            format: AGGREGATE_DATA_TYPE.to_string(),
+            raw_data: serde_json::json!({}),
+            parsed_data: Some(parsed),
+            normalized_data: None,
+            patient_id: None,
+            encounter_id: None,
+            provider_id: None,
+            metadata: serde_json::json!({ "partial": aggregate.partial }),
+            created_at: now,
+        };
+        if let Err(e) = repository.save_device_data(&data).await {
+            tracing::warn!(device_id = %aggregate.device_id, error = %e, "Failed to store aggregate");
+        }
+    }
+}
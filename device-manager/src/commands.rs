@@ -0,0 +1,297 @@
+use crate::{DeviceCommand, DeviceConfig, DeviceError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Acknowledgment timeout used when a device does not configure one
+pub const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Status of a command the device never acknowledged. It may or may not
+/// have been applied.
+pub const UNACKNOWLEDGED_STATUS: &str = "unacknowledged";
+
+const ALERT_CAPACITY: usize = 256;
+
+// ============================================================================
+// REQUESTS AND OUTCOMES
+// ============================================================================
+
+/// Capability needed to send a command, and granted to the caller sending it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandCapability {
+    /// Routine commands such as starting a measurement
+    Standard,
+    /// Commands that change how the device treats a patient, such as alarm
+    /// thresholds or calibration
+    Elevated,
+}
+
+impl CommandCapability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandCapability::Standard => "standard",
+            CommandCapability::Elevated => "elevated",
+        }
+    }
+}
+
+/// A command to send to a device
+#[derive(Debug, Clone)]
+pub struct CommandRequest {
+    pub command: String,
+    pub parameters: serde_json::Value,
+    /// Same on every retry of one command, so that a retry after a lost
+    /// acknowledgment is not applied twice
+    pub idempotency_key: String,
+    /// Capability the caller was granted
+    pub capability: CommandCapability,
+    pub issued_by: Option<Uuid>,
+    /// Overrides the device's acknowledgment timeout
+    pub ack_timeout: Option<Duration>,
+}
+
+impl CommandRequest {
+    /// A standard-capability command under a fresh idempotency key
+    pub fn new(command: impl Into<String>, parameters: serde_json::Value) -> Self {
+        Self {
+            command: command.into(),
+            parameters,
+            idempotency_key: Uuid::new_v4().to_string(),
+            capability: CommandCapability::Standard,
+            issued_by: None,
+            ack_timeout: None,
+        }
+    }
+
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = key.into();
+        self
+    }
+
+    pub fn with_capability(mut self, capability: CommandCapability) -> Self {
+        self.capability = capability;
+        self
+    }
+
+    pub fn issued_by(mut self, user_id: Uuid) -> Self {
+        self.issued_by = Some(user_id);
+        self
+    }
+
+    pub fn with_ack_timeout(mut self, timeout: Duration) -> Self {
+        self.ack_timeout = Some(timeout);
+        self
+    }
+
+    /// New pending record of this command
+    pub(crate) fn record(&self, device_id: Uuid) -> DeviceCommand {
+        DeviceCommand {
+            id: Uuid::new_v4(),
+            device_id,
+            command: self.command.clone(),
+            parameters: self.parameters.clone(),
+            metadata: serde_json::json!({
+                "idempotency_key": self.idempotency_key,
+                "capability": self.capability,
+                "issued_by": self.issued_by,
+            }),
+            created_at: Utc::now(),
+            executed_at: None,
+            completed_at: None,
+            status: "pending".to_string(),
+            response: None,
+            error: None,
+        }
+    }
+}
+
+/// How a sent command ended
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum CommandOutcome {
+    /// The device acknowledged the command as applied
+    Acknowledged { response: serde_json::Value },
+    /// The device refused the command or failed to apply it
+    Failed { error: String },
+    /// No acknowledgment arrived in time. The command may or may not have
+    /// been applied; retry it under the same idempotency key.
+    TimedOut { timeout_ms: u64 },
+}
+
+impl CommandOutcome {
+    /// Outcome stored on a command record, if the command finished
+    pub fn from_record(record: &DeviceCommand) -> Option<Self> {
+        match record.status.as_str() {
+            "completed" => Some(CommandOutcome::Acknowledged {
+                response: record.response.clone().unwrap_or(serde_json::Value::Null),
+            }),
+            "failed" => Some(CommandOutcome::Failed {
+                error: record.error.clone().unwrap_or_default(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Status code stored on the command record
+    pub fn status(&self) -> &'static str {
+        match self {
+            CommandOutcome::Acknowledged { .. } => "completed",
+            CommandOutcome::Failed { .. } => "failed",
+            CommandOutcome::TimedOut { .. } => UNACKNOWLEDGED_STATUS,
+        }
+    }
+
+    /// Response and error stored on the command record
+    pub(crate) fn into_record_fields(self) -> (Option<serde_json::Value>, Option<String>) {
+        match self {
+            CommandOutcome::Acknowledged { response } => (Some(response), None),
+            CommandOutcome::Failed { error } => (None, Some(error)),
+            CommandOutcome::TimedOut { timeout_ms } => (
+                None,
+                Some(format!("no acknowledgment within {}ms", timeout_ms)),
+            ),
+        }
+    }
+}
+
+/// Result of [`DeviceManager::send_command`](crate::DeviceManager::send_command)
+#[derive(Debug, Clone)]
+pub struct CommandResult {
+    pub command: DeviceCommand,
+    pub outcome: CommandOutcome,
+    /// The outcome was stored by an earlier send under the same idempotency
+    /// key, and nothing was sent to the device this time
+    pub replayed: bool,
+}
+
+/// A command the device never acknowledged
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandAlert {
+    pub command_id: Uuid,
+    pub device_id: Uuid,
+    pub command: String,
+    pub idempotency_key: String,
+    pub reason: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Time to wait for a device to acknowledge a command. Uses
+/// `command_ack_timeout_ms` from the advanced settings, then `timeout_ms`.
+pub fn ack_timeout(config: &DeviceConfig) -> Duration {
+    config
+        .advanced
+        .as_ref()
+        .and_then(|settings| settings.command_ack_timeout_ms.or(settings.timeout_ms))
+        .map_or(DEFAULT_ACK_TIMEOUT, Duration::from_millis)
+}
+
+// ============================================================================
+// COMMAND TRACKER
+// ============================================================================
+
+/// Commands being sent right now, with a stream of commands that were never
+/// acknowledged
+pub struct CommandTracker {
+    in_flight: Arc<Mutex<HashSet<(Uuid, String)>>>,
+    alerts: broadcast::Sender<CommandAlert>,
+}
+
+/// Marks a command as being sent until dropped
+pub(crate) struct InFlight {
+    in_flight: Arc<Mutex<HashSet<(Uuid, String)>>>,
+    key: (Uuid, String),
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(&self.key);
+    }
+}
+
+impl CommandTracker {
+    pub fn new() -> Self {
+        let (alerts, _) = broadcast::channel(ALERT_CAPACITY);
+        Self {
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+            alerts,
+        }
+    }
+
+    /// Receive every unacknowledged command from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<CommandAlert> {
+        self.alerts.subscribe()
+    }
+
+    /// Claim an idempotency key for the duration of one send, so a retry
+    /// racing the original is refused rather than sent twice
+    pub(crate) fn begin(&self, device_id: Uuid, idempotency_key: &str) -> Result<InFlight> {
+        let key = (device_id, idempotency_key.to_string());
+        let mut in_flight = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !in_flight.insert(key.clone()) {
+            return Err(DeviceError::Busy(format!(
+                "Command '{}' is already being sent to device {}",
+                idempotency_key, device_id
+            )));
+        }
+        Ok(InFlight {
+            in_flight: self.in_flight.clone(),
+            key,
+        })
+    }
+
+    pub(crate) fn alert(&self, record: &DeviceCommand, idempotency_key: &str, reason: String) {
+        tracing::error!(
+            device_id = %record.device_id,
+            command_id = %record.id,
+            command = %record.command,
+            reason = %reason,
+            "Device command was not acknowledged"
+        );
+        // No subscribers is not an error
+        let _ = self.alerts.send(CommandAlert {
+            command_id: record.id,
+            device_id: record.device_id,
+            command: record.command.clone(),
+            idempotency_key: idempotency_key.to_string(),
+            reason,
+            at: Utc::now(),
+        });
+    }
+}
+
+impl Default for CommandTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// AUDIT
+// ============================================================================
+
+/// Record a command event on the audit log. The command records themselves
+/// are the durable trail; this makes each step visible as it happens.
+pub(crate) fn audit(device_id: Uuid, request: &CommandRequest, event: &str, detail: &str) {
+    tracing::info!(
+        target: "audit",
+        device_id = %device_id,
+        command = %request.command,
+        idempotency_key = %request.idempotency_key,
+        capability = request.capability.as_str(),
+        issued_by = ?request.issued_by,
+        event,
+        detail,
+        "Device command audit"
+    );
+}
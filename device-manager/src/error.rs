@@ -32,6 +32,9 @@ pub enum DeviceError {
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
     
+    #[error("Unsupported by device: {0}")]
+    Unsupported(String),
+    
     #[error("Database error: {0}")]
     DatabaseError(#[from] sqlx::Error),
     
@@ -48,4 +51,19 @@ pub enum DeviceError {
     Unknown(String),
 }
 
+impl DeviceError {
+    /// Whether the failure may clear up on its own, so retrying the same
+    /// operation later can succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            DeviceError::ConnectionError(_)
+                | DeviceError::CommunicationError(_)
+                | DeviceError::Timeout(_)
+                | DeviceError::Busy(_)
+                | DeviceError::IoError(_)
+        )
+    }
+}
+
 pub type Result<T> = std::result::Result<T, DeviceError>;
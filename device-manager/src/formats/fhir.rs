@@ -0,0 +1,319 @@
+use super::units::{normalize_unit, UcumUnits, UnitNormalizer};
+use crate::{DeviceError, DeviceReading, FormatPlugin, ReadingValue, Result, ValidationResult};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Format code for FHIR R4 JSON resources
+pub const FHIR_R4_FORMAT: &str = "fhir_r4";
+
+/// Code system URI for UCUM units
+pub const UCUM_SYSTEM: &str = "http://unitsofmeasure.org";
+
+/// FHIR R4 adapter for Observation resources.
+///
+/// Accepts a single Observation or a Bundle whose entries contain them;
+/// other resources in a Bundle are skipped. An Observation with components
+/// (blood pressure, for example) yields one reading per component.
+pub struct FhirObservationPlugin {
+    units: Arc<dyn UnitNormalizer>,
+}
+
+impl FhirObservationPlugin {
+    pub fn new() -> Self {
+        Self {
+            units: Arc::new(UcumUnits::new()),
+        }
+    }
+
+    pub fn with_unit_normalizer(mut self, units: Arc<dyn UnitNormalizer>) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Readings from an Observation or a Bundle of Observations
+    pub fn parse_resource(&self, resource: &Value) -> Result<Vec<DeviceReading>> {
+        let readings = match resource["resourceType"].as_str() {
+            Some("Observation") => self.parse_observation(resource)?,
+            Some("Bundle") => {
+                let mut readings = Vec::new();
+                for entry in resource["entry"].as_array().into_iter().flatten() {
+                    if entry["resource"]["resourceType"] == "Observation" {
+                        readings.extend(self.parse_observation(&entry["resource"])?);
+                    }
+                }
+                readings
+            }
+            Some(other) => {
+                return Err(DeviceError::ProtocolError(format!(
+                    "unsupported FHIR resource type {}",
+                    other
+                )))
+            }
+            None => {
+                return Err(DeviceError::ProtocolError(
+                    "FHIR resource has no resourceType".to_string(),
+                ))
+            }
+        };
+
+        if readings.is_empty() {
+            return Err(DeviceError::ValidationError(
+                "no Observation with a value".to_string(),
+            ));
+        }
+        Ok(readings)
+    }
+
+    fn parse_observation(&self, observation: &Value) -> Result<Vec<DeviceReading>> {
+        let id = observation["id"].as_str().unwrap_or("(no id)");
+        let timestamp = ["effectiveDateTime", "effectiveInstant", "issued"]
+            .iter()
+            .find_map(|field| observation[field].as_str())
+            .or_else(|| observation["effectivePeriod"]["start"].as_str())
+            .ok_or_else(|| {
+                DeviceError::ValidationError(format!("Observation {} has no effective time", id))
+            })
+            .and_then(|value| {
+                parse_date_time(value).ok_or_else(|| {
+                    DeviceError::ParsingError(format!(
+                        "Observation {} has invalid effective time '{}'",
+                        id, value
+                    ))
+                })
+            })?;
+
+        let device = &observation["device"];
+        let device_id = device["reference"]
+            .as_str()
+            .map(|reference| reference.trim_start_matches("Device/"))
+            .or_else(|| device["identifier"]["value"].as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        // The observation's own value wins; components are only read when
+        // there is none
+        let mut readings = Vec::new();
+        if let Some((value, unit)) = self.parse_value(observation, id)? {
+            readings.push(self.reading(observation, id, value, unit, &device_id, timestamp)?);
+        } else {
+            for component in observation["component"].as_array().into_iter().flatten() {
+                if let Some((value, unit)) = self.parse_value(component, id)? {
+                    readings.push(self.reading(component, id, value, unit, &device_id, timestamp)?);
+                }
+            }
+        }
+        Ok(readings)
+    }
+
+    fn reading(
+        &self,
+        element: &Value,
+        id: &str,
+        value: ReadingValue,
+        unit: Option<String>,
+        device_id: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<DeviceReading> {
+        let coding = element["code"]["coding"]
+            .as_array()
+            .and_then(|codings| codings.iter().find(|coding| coding["code"].is_string()))
+            .ok_or_else(|| {
+                DeviceError::ValidationError(format!("Observation {} has no coded code", id))
+            })?;
+
+        Ok(DeviceReading {
+            device_id: device_id.to_string(),
+            code: coding["code"].as_str().unwrap_or_default().to_string(),
+            code_system: coding["system"].as_str().map(str::to_string),
+            display: coding["display"]
+                .as_str()
+                .or_else(|| element["code"]["text"].as_str())
+                .map(str::to_string),
+            value,
+            unit,
+            timestamp,
+        })
+    }
+
+    /// `value[x]` of an Observation or component, with its unit
+    fn parse_value(
+        &self,
+        element: &Value,
+        id: &str,
+    ) -> Result<Option<(ReadingValue, Option<String>)>> {
+        if let Some(quantity) = element.get("valueQuantity") {
+            let value = quantity["value"].as_f64().ok_or_else(|| {
+                DeviceError::ParsingError(format!(
+                    "Observation {} has a valueQuantity without a numeric value",
+                    id
+                ))
+            })?;
+            let unit = match (quantity["system"].as_str(), quantity["code"].as_str()) {
+                (Some(UCUM_SYSTEM), Some(code)) => Some(code.to_string()),
+                (_, code) => quantity["unit"]
+                    .as_str()
+                    .or(code)
+                    .and_then(|unit| normalize_unit(self.units.as_ref(), unit)),
+            };
+            return Ok(Some((ReadingValue::Numeric(value), unit)));
+        }
+
+        let value = if let Some(value) = element["valueInteger"].as_i64() {
+            ReadingValue::Numeric(value as f64)
+        } else if let Some(value) = element["valueString"].as_str() {
+            ReadingValue::Text(value.to_string())
+        } else if let Some(value) = element["valueBoolean"].as_bool() {
+            ReadingValue::Text(value.to_string())
+        } else if let Some(concept) = element.get("valueCodeableConcept") {
+            let text = concept["text"]
+                .as_str()
+                .or_else(|| concept["coding"][0]["display"].as_str())
+                .or_else(|| concept["coding"][0]["code"].as_str())
+                .unwrap_or_default();
+            ReadingValue::Text(text.to_string())
+        } else {
+            return Ok(None);
+        };
+        Ok(Some((value, None)))
+    }
+}
+
+impl Default for FhirObservationPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FormatPlugin for FhirObservationPlugin {
+    fn name(&self) -> &str {
+        "fhir_observation"
+    }
+
+    fn supported_formats(&self) -> Vec<String> {
+        vec![FHIR_R4_FORMAT.to_string()]
+    }
+
+    fn parse(&self, raw: &[u8], _format: &str) -> Result<serde_json::Value> {
+        let resource: Value = serde_json::from_slice(raw)
+            .map_err(|e| DeviceError::ParsingError(format!("invalid FHIR JSON: {}", e)))?;
+        Ok(serde_json::to_value(self.parse_resource(&resource)?)?)
+    }
+
+    fn generate(&self, data: &serde_json::Value, _format: &str) -> Result<Vec<u8>> {
+        let readings: Vec<DeviceReading> = serde_json::from_value(data.clone())?;
+        Ok(serde_json::to_vec(&readings_to_bundle(&readings))?)
+    }
+
+    fn validate(&self, data: &[u8], format: &str) -> Result<ValidationResult> {
+        let errors = match self.parse(data, format) {
+            Ok(_) => Vec::new(),
+            Err(e) => vec![e.to_string()],
+        };
+        Ok(ValidationResult {
+            valid: errors.is_empty(),
+            errors,
+            warnings: Vec::new(),
+        })
+    }
+
+    fn detect_format(&self, data: &[u8]) -> Option<String> {
+        let resource: Value = serde_json::from_slice(data).ok()?;
+        matches!(
+            resource["resourceType"].as_str(),
+            Some("Observation" | "Bundle")
+        )
+        .then(|| FHIR_R4_FORMAT.to_string())
+    }
+
+    fn normalize(&self, data: &serde_json::Value, _from_format: &str) -> Result<serde_json::Value> {
+        let readings: Vec<DeviceReading> = serde_json::from_value(data.clone())?;
+        Ok(readings_to_bundle(&readings))
+    }
+
+    /// An OperationOutcome describing why the resource was rejected
+    fn acknowledge(
+        &self,
+        _raw: &[u8],
+        _format: &str,
+        error: Option<&DeviceError>,
+    ) -> Option<Vec<u8>> {
+        let error = error?;
+        let code = match error {
+            DeviceError::ProtocolError(_) => "not-supported",
+            DeviceError::ValidationError(_) => "required",
+            DeviceError::ParsingError(_) => "structure",
+            _ => "exception",
+        };
+        let outcome = json!({
+            "resourceType": "OperationOutcome",
+            "issue": [{
+                "severity": "error",
+                "code": code,
+                "diagnostics": error.to_string(),
+            }],
+        });
+        serde_json::to_vec(&outcome).ok()
+    }
+}
+
+/// FHIR Observation for a reading
+pub fn reading_to_observation(reading: &DeviceReading) -> Value {
+    // FHIR JSON does not allow nulls, so optional elements are only added
+    // when present
+    let mut coding = json!({ "code": reading.code });
+    if let Some(system) = &reading.code_system {
+        coding["system"] = json!(system);
+    }
+    if let Some(display) = &reading.display {
+        coding["display"] = json!(display);
+    }
+    let mut observation = json!({
+        "resourceType": "Observation",
+        "status": "final",
+        "code": { "coding": [coding] },
+        "effectiveDateTime": reading.timestamp.to_rfc3339(),
+    });
+    if !reading.device_id.is_empty() {
+        observation["device"] = json!({ "reference": format!("Device/{}", reading.device_id) });
+    }
+    match &reading.value {
+        ReadingValue::Numeric(value) => {
+            let mut quantity = json!({ "value": value });
+            if let Some(unit) = &reading.unit {
+                quantity["unit"] = json!(unit);
+                quantity["system"] = json!(UCUM_SYSTEM);
+                quantity["code"] = json!(unit);
+            }
+            observation["valueQuantity"] = quantity;
+        }
+        ReadingValue::Text(text) => observation["valueString"] = json!(text),
+    }
+    observation
+}
+
+/// Collection Bundle with one Observation per reading
+pub fn readings_to_bundle(readings: &[DeviceReading]) -> Value {
+    let entries: Vec<Value> = readings
+        .iter()
+        .map(|reading| json!({ "resource": reading_to_observation(reading) }))
+        .collect();
+    json!({
+        "resourceType": "Bundle",
+        "type": "collection",
+        "entry": entries,
+    })
+}
+
+/// FHIR `dateTime`/`instant`; a date without a time is read as midnight UTC
+fn parse_date_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let date = match value.len() {
+        4 => NaiveDate::from_ymd_opt(value.parse().ok()?, 1, 1)?,
+        7 => NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d").ok()?,
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?,
+    };
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
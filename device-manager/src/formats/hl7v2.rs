@@ -0,0 +1,559 @@
+use super::fhir::readings_to_bundle;
+use super::units::{normalize_unit, UcumUnits, UnitNormalizer};
+use crate::{DeviceError, DeviceReading, FormatPlugin, ReadingValue, Result, ValidationResult};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use std::sync::Arc;
+
+/// Format code for HL7 v2 messages
+pub const HL7V2_FORMAT: &str = "hl7v2";
+
+/// MLLP block framing characters
+const MLLP_START: char = '\u{0b}';
+const MLLP_END: char = '\u{1c}';
+
+/// Version used in generated messages when the incoming one does not say
+const DEFAULT_VERSION: &str = "2.5";
+
+#[derive(Debug, Clone, Copy)]
+struct Delimiters {
+    field: char,
+    component: char,
+    repetition: char,
+    escape: char,
+    subcomponent: char,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            field: '|',
+            component: '^',
+            repetition: '~',
+            escape: '\\',
+            subcomponent: '&',
+        }
+    }
+}
+
+impl Delimiters {
+    fn encoding_characters(&self) -> String {
+        [
+            self.component,
+            self.repetition,
+            self.escape,
+            self.subcomponent,
+        ]
+        .iter()
+        .collect()
+    }
+
+    /// Resolve `\F\`-style escape sequences; unsupported ones are dropped
+    fn unescape(&self, value: &str) -> String {
+        let mut out = String::with_capacity(value.len());
+        let mut parts = value.split(self.escape);
+        out.push_str(parts.next().unwrap_or_default());
+        while let Some(sequence) = parts.next() {
+            match sequence {
+                "F" => out.push(self.field),
+                "S" => out.push(self.component),
+                "T" => out.push(self.subcomponent),
+                "R" => out.push(self.repetition),
+                "E" => out.push(self.escape),
+                ".br" => out.push('\n'),
+                _ => {}
+            }
+            // Text after the closing escape character
+            if let Some(text) = parts.next() {
+                out.push_str(text);
+            }
+        }
+        out
+    }
+
+    fn escape(&self, value: &str) -> String {
+        let mut out = String::with_capacity(value.len());
+        for c in value.chars() {
+            let sequence = match c {
+                c if c == self.escape => "E",
+                c if c == self.field => "F",
+                c if c == self.component => "S",
+                c if c == self.subcomponent => "T",
+                c if c == self.repetition => "R",
+                '\r' | '\n' => ".br",
+                _ => {
+                    out.push(c);
+                    continue;
+                }
+            };
+            out.push(self.escape);
+            out.push_str(sequence);
+            out.push(self.escape);
+        }
+        out
+    }
+}
+
+/// One segment, with fields indexed by their HL7 position
+struct Segment<'a> {
+    fields: Vec<&'a str>,
+}
+
+impl<'a> Segment<'a> {
+    fn id(&self) -> &'a str {
+        self.fields[0]
+    }
+
+    fn field(&self, index: usize) -> &'a str {
+        self.fields.get(index).copied().unwrap_or_default()
+    }
+
+    /// First repetition of a field, split into components (1-based)
+    fn component(&self, delimiters: &Delimiters, index: usize, component: usize) -> &'a str {
+        self.field(index)
+            .split(delimiters.repetition)
+            .next()
+            .unwrap_or_default()
+            .split(delimiters.component)
+            .nth(component - 1)
+            .unwrap_or_default()
+    }
+}
+
+/// A message split into segments
+struct Message<'a> {
+    delimiters: Delimiters,
+    segments: Vec<Segment<'a>>,
+}
+
+impl<'a> Message<'a> {
+    fn parse(raw: &'a str) -> Result<Self> {
+        let raw = raw
+            .trim_start_matches(['\u{feff}', MLLP_START])
+            .trim_end_matches([MLLP_END, '\r', '\n']);
+        if !raw.starts_with("MSH") || raw.len() < 8 {
+            return Err(DeviceError::ProtocolError(
+                "message does not start with an MSH segment".to_string(),
+            ));
+        }
+
+        let mut chars = raw[3..].chars();
+        let field = chars.next().unwrap_or('|');
+        let encoding: Vec<char> = chars.take_while(|c| *c != field).collect();
+        let defaults = Delimiters::default();
+        let delimiters = Delimiters {
+            field,
+            component: encoding.first().copied().unwrap_or(defaults.component),
+            repetition: encoding.get(1).copied().unwrap_or(defaults.repetition),
+            escape: encoding.get(2).copied().unwrap_or(defaults.escape),
+            subcomponent: encoding.get(3).copied().unwrap_or(defaults.subcomponent),
+        };
+
+        let segments = raw
+            .split(['\r', '\n'])
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut fields: Vec<&str> = line.split(field).collect();
+                // MSH-1 is the field separator itself, so MSH fields are
+                // shifted by one compared to every other segment
+                if fields[0] == "MSH" {
+                    fields.insert(1, &raw[3..3 + field.len_utf8()]);
+                }
+                Segment { fields }
+            })
+            .collect();
+
+        Ok(Self {
+            delimiters,
+            segments,
+        })
+    }
+
+    fn header(&self) -> &Segment<'a> {
+        &self.segments[0]
+    }
+
+    fn component(&self, segment: &Segment<'a>, index: usize, component: usize) -> String {
+        self.delimiters
+            .unescape(segment.component(&self.delimiters, index, component))
+    }
+}
+
+/// HL7 v2 adapter for ORU^R01 observation messages.
+///
+/// Each OBX segment becomes a [`DeviceReading`]. The observation time is
+/// taken from OBX-14, falling back to the preceding OBR-7 and then MSH-7,
+/// and the device from OBX-18, falling back to the sending application.
+/// Timestamps without an offset are read as UTC.
+pub struct Hl7v2Plugin {
+    units: Arc<dyn UnitNormalizer>,
+}
+
+impl Hl7v2Plugin {
+    pub fn new() -> Self {
+        Self {
+            units: Arc::new(UcumUnits::new()),
+        }
+    }
+
+    pub fn with_unit_normalizer(mut self, units: Arc<dyn UnitNormalizer>) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Readings from an ORU^R01 message
+    pub fn parse_oru(&self, raw: &str) -> Result<Vec<DeviceReading>> {
+        let message = Message::parse(raw)?;
+        let header = message.header();
+
+        let message_type = message.component(header, 9, 1);
+        let trigger = message.component(header, 9, 2);
+        if message_type != "ORU" || trigger != "R01" {
+            return Err(DeviceError::ProtocolError(format!(
+                "unsupported message type {}^{}",
+                message_type, trigger
+            )));
+        }
+
+        let message_time = parse_timestamp(&message.component(header, 7, 1));
+        let sender = message.component(header, 3, 1);
+        let mut observation_time = None;
+        let mut readings = Vec::new();
+
+        for segment in &message.segments[1..] {
+            match segment.id() {
+                "OBR" => observation_time = parse_timestamp(&message.component(segment, 7, 1)),
+                "OBX" => {
+                    if let Some(reading) = self.parse_obx(
+                        &message,
+                        segment,
+                        observation_time.or(message_time),
+                        &sender,
+                    )? {
+                        readings.push(reading);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if readings.is_empty() {
+            return Err(DeviceError::ValidationError(
+                "message has no OBX segments with a value".to_string(),
+            ));
+        }
+        Ok(readings)
+    }
+
+    fn parse_obx(
+        &self,
+        message: &Message<'_>,
+        obx: &Segment<'_>,
+        fallback_time: Option<DateTime<Utc>>,
+        sender: &str,
+    ) -> Result<Option<DeviceReading>> {
+        let set_id = obx.field(1);
+        let raw_value = obx
+            .field(5)
+            .split(message.delimiters.repetition)
+            .next()
+            .unwrap_or_default();
+        if raw_value.is_empty() {
+            return Ok(None);
+        }
+
+        let code = message.component(obx, 3, 1);
+        if code.is_empty() {
+            return Err(DeviceError::ValidationError(format!(
+                "OBX {} has no observation identifier",
+                set_id
+            )));
+        }
+
+        let value = match obx.field(2) {
+            "NM" => {
+                let text = message.delimiters.unescape(raw_value);
+                ReadingValue::Numeric(text.trim().parse().map_err(|_| {
+                    DeviceError::ParsingError(format!(
+                        "OBX {} has non-numeric NM value '{}'",
+                        set_id, text
+                    ))
+                })?)
+            }
+            // Structured numeric: comparator^number^separator^number
+            "SN" => {
+                let comparator = message.component(obx, 5, 1);
+                let number = message.component(obx, 5, 2);
+                match number.trim().parse() {
+                    Ok(number) if comparator.is_empty() || comparator == "=" => {
+                        ReadingValue::Numeric(number)
+                    }
+                    _ => ReadingValue::Text(message.delimiters.unescape(raw_value)),
+                }
+            }
+            "CE" | "CWE" => {
+                let text = message.component(obx, 5, 2);
+                if text.is_empty() {
+                    ReadingValue::Text(message.component(obx, 5, 1))
+                } else {
+                    ReadingValue::Text(text)
+                }
+            }
+            _ => ReadingValue::Text(message.delimiters.unescape(raw_value)),
+        };
+
+        let timestamp = parse_timestamp(&message.component(obx, 14, 1))
+            .or(fallback_time)
+            .ok_or_else(|| {
+                DeviceError::ValidationError(format!("OBX {} has no observation time", set_id))
+            })?;
+
+        let unit_system = message.component(obx, 6, 3);
+        let unit_code = message.component(obx, 6, 1);
+        let unit = if unit_system.eq_ignore_ascii_case("UCUM") && !unit_code.is_empty() {
+            Some(unit_code)
+        } else if unit_code.is_empty() {
+            normalize_unit(self.units.as_ref(), &message.component(obx, 6, 2))
+        } else {
+            normalize_unit(self.units.as_ref(), &unit_code)
+        };
+
+        let device_id = match message.component(obx, 18, 1) {
+            id if id.is_empty() => sender.to_string(),
+            id => id,
+        };
+        let display = message.component(obx, 3, 2);
+        let code_system = message.component(obx, 3, 3);
+
+        Ok(Some(DeviceReading {
+            device_id,
+            code,
+            code_system: (!code_system.is_empty()).then_some(code_system),
+            display: (!display.is_empty()).then_some(display),
+            value,
+            unit,
+            timestamp,
+        }))
+    }
+
+    /// General acknowledgment for a received message: `AA` when `error` is
+    /// `None`, `AR` when the message could not be processed at all (bad
+    /// header, unsupported type) and `AE` when its content was invalid
+    pub fn acknowledgment(raw: &str, error: Option<&DeviceError>) -> String {
+        let message = Message::parse(raw).ok();
+        let delimiters = message
+            .as_ref()
+            .map(|message| message.delimiters)
+            .unwrap_or_default();
+        let header = |index: usize| -> String {
+            message
+                .as_ref()
+                .map(|message| message.header().field(index).to_string())
+                .unwrap_or_default()
+        };
+
+        let (ack_code, error_code) = match error {
+            None => ("AA", None),
+            Some(DeviceError::ProtocolError(_)) => ("AR", Some("200^Unsupported message type")),
+            Some(DeviceError::ValidationError(_)) => ("AE", Some("101^Required field missing")),
+            Some(DeviceError::ParsingError(_)) => ("AE", Some("102^Data type error")),
+            Some(_) => ("AE", Some("207^Application internal error")),
+        };
+        let trigger = message
+            .as_ref()
+            .map(|message| message.component(message.header(), 9, 2))
+            .unwrap_or_default();
+        let version = match header(12) {
+            version if version.is_empty() => DEFAULT_VERSION.to_string(),
+            version => version,
+        };
+        let processing_id = match header(11) {
+            id if id.is_empty() => "P".to_string(),
+            id => id,
+        };
+
+        let f = delimiters.field;
+        let c = delimiters.component;
+        let control_id: String = uuid::Uuid::new_v4().simple().to_string()[..20].to_string();
+        let mut ack = format!(
+            "MSH{f}{enc}{f}{}{f}{}{f}{}{f}{}{f}{}{f}{f}ACK{c}{}{c}ACK{f}{}{f}{}{f}{}\r",
+            header(5),
+            header(6),
+            header(3),
+            header(4),
+            Utc::now().format("%Y%m%d%H%M%S%z"),
+            trigger,
+            control_id,
+            processing_id,
+            version,
+            enc = delimiters.encoding_characters(),
+        );
+        ack.push_str(&format!("MSA{f}{}{f}{}", ack_code, header(10)));
+        if let (Some(error), Some(code)) = (error, error_code) {
+            ack.push_str(&format!(
+                "{f}{}\rERR{f}{f}{f}{}{c}HL70357{f}E",
+                delimiters.escape(&error.to_string()),
+                code.replace('^', &c.to_string()),
+            ));
+        }
+        ack.push('\r');
+        ack
+    }
+
+    /// ORU^R01 carrying the given readings as OBX segments
+    pub fn generate_oru(&self, readings: &[DeviceReading]) -> String {
+        let d = Delimiters::default();
+        let now = Utc::now();
+        let sender = readings
+            .first()
+            .map(|reading| d.escape(&reading.device_id))
+            .unwrap_or_default();
+        let mut message = format!(
+            "MSH|{}|{}||||{}||ORU^R01^ORU_R01|{}|P|{}\rOBR|1||||||{}\r",
+            d.encoding_characters(),
+            sender,
+            format_timestamp(now),
+            &uuid::Uuid::new_v4().simple().to_string()[..20],
+            DEFAULT_VERSION,
+            format_timestamp(readings.first().map_or(now, |reading| reading.timestamp)),
+        );
+        for (index, reading) in readings.iter().enumerate() {
+            let (value_type, value) = match &reading.value {
+                ReadingValue::Numeric(value) => ("NM", value.to_string()),
+                ReadingValue::Text(text) => ("ST", d.escape(text)),
+            };
+            let unit = reading
+                .unit
+                .as_deref()
+                .map(|unit| format!("{}^{}^UCUM", d.escape(unit), d.escape(unit)))
+                .unwrap_or_default();
+            message.push_str(&format!(
+                "OBX|{}|{}|{}^{}^{}||{}|{}|||||F|||{}||||{}\r",
+                index + 1,
+                value_type,
+                d.escape(&reading.code),
+                d.escape(reading.display.as_deref().unwrap_or_default()),
+                d.escape(reading.code_system.as_deref().unwrap_or_default()),
+                value,
+                unit,
+                format_timestamp(reading.timestamp),
+                d.escape(&reading.device_id),
+            ));
+        }
+        message
+    }
+}
+
+impl Default for Hl7v2Plugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn message_text(raw: &[u8]) -> Result<&str> {
+    std::str::from_utf8(raw)
+        .map_err(|e| DeviceError::ParsingError(format!("HL7 message is not valid UTF-8: {}", e)))
+}
+
+impl FormatPlugin for Hl7v2Plugin {
+    fn name(&self) -> &str {
+        "hl7v2"
+    }
+
+    fn supported_formats(&self) -> Vec<String> {
+        vec![HL7V2_FORMAT.to_string()]
+    }
+
+    fn parse(&self, raw: &[u8], _format: &str) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self.parse_oru(message_text(raw)?)?)?)
+    }
+
+    fn generate(&self, data: &serde_json::Value, _format: &str) -> Result<Vec<u8>> {
+        let readings: Vec<DeviceReading> = serde_json::from_value(data.clone())?;
+        Ok(self.generate_oru(&readings).into_bytes())
+    }
+
+    fn validate(&self, data: &[u8], _format: &str) -> Result<ValidationResult> {
+        let errors = match message_text(data).and_then(|raw| self.parse_oru(raw)) {
+            Ok(_) => Vec::new(),
+            Err(e) => vec![e.to_string()],
+        };
+        Ok(ValidationResult {
+            valid: errors.is_empty(),
+            errors,
+            warnings: Vec::new(),
+        })
+    }
+
+    fn detect_format(&self, data: &[u8]) -> Option<String> {
+        let text = std::str::from_utf8(data).ok()?;
+        text.trim_start_matches(['\u{feff}', MLLP_START])
+            .starts_with("MSH")
+            .then(|| HL7V2_FORMAT.to_string())
+    }
+
+    fn normalize(&self, data: &serde_json::Value, _from_format: &str) -> Result<serde_json::Value> {
+        let readings: Vec<DeviceReading> = serde_json::from_value(data.clone())?;
+        Ok(readings_to_bundle(&readings))
+    }
+
+    fn acknowledge(
+        &self,
+        raw: &[u8],
+        _format: &str,
+        error: Option<&DeviceError>,
+    ) -> Option<Vec<u8>> {
+        let text = String::from_utf8_lossy(raw);
+        Some(Self::acknowledgment(&text, error).into_bytes())
+    }
+}
+
+/// `YYYY[MM[DD[HH[MM[SS[.S+]]]]]][+/-ZZZZ]`, at least to the day
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    let (local, offset) = match value.rfind(['+', '-']) {
+        Some(index) if index >= 8 => (&value[..index], Some(&value[index..])),
+        _ => (value, None),
+    };
+    let (digits, fraction) = local.split_once('.').unwrap_or((local, ""));
+    if digits.len() < 8 || digits.len() % 2 != 0 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let part = |range: std::ops::Range<usize>| -> u32 {
+        digits
+            .get(range)
+            .and_then(|part| part.parse().ok())
+            .unwrap_or(0)
+    };
+    let micros = match fraction {
+        "" => 0,
+        fraction => format!("{:0<6}", &fraction[..fraction.len().min(6)])
+            .parse()
+            .ok()?,
+    };
+    let naive = NaiveDate::from_ymd_opt(digits[..4].parse().ok()?, part(4..6), part(6..8))?
+        .and_hms_micro_opt(part(8..10), part(10..12), part(12..14), micros)?;
+
+    let offset_seconds = match offset {
+        Some(offset) if offset.len() == 5 => {
+            let hours: i32 = offset[1..3].parse().ok()?;
+            let minutes: i32 = offset[3..5].parse().ok()?;
+            let seconds = hours * 3600 + minutes * 60;
+            if offset.starts_with('-') {
+                -seconds
+            } else {
+                seconds
+            }
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    chrono::FixedOffset::east_opt(offset_seconds)?
+        .from_local_datetime(&naive)
+        .single()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn format_timestamp(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%d%H%M%S+0000").to_string()
+}
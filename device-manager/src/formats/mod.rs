@@ -0,0 +1,8 @@
+// Adapters that turn device messages into `DeviceReading`s
+pub mod fhir;
+pub mod hl7v2;
+pub mod units;
+
+pub use fhir::*;
+pub use hl7v2::*;
+pub use units::*;
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+/// Maps the unit strings devices send to UCUM codes
+pub trait UnitNormalizer: Send + Sync {
+    /// UCUM code for `unit`, or `None` if the unit is not recognised
+    fn normalize(&self, unit: &str) -> Option<String>;
+}
+
+/// UCUM code for `unit`, keeping the original string when it is unknown
+pub fn normalize_unit(normalizer: &dyn UnitNormalizer, unit: &str) -> Option<String> {
+    let unit = unit.trim();
+    if unit.is_empty() {
+        return None;
+    }
+    Some(normalizer.normalize(unit).unwrap_or_else(|| {
+        tracing::debug!(unit, "No UCUM mapping for unit");
+        unit.to_string()
+    }))
+}
+
+/// Table-driven normalizer covering the units common on bedside monitors.
+/// Vendor-specific spellings can be added with [`with_alias`](Self::with_alias).
+#[derive(Debug, Clone)]
+pub struct UcumUnits {
+    aliases: HashMap<String, String>,
+}
+
+impl UcumUnits {
+    pub fn new() -> Self {
+        let builtin: &[(&str, &[&str])] = &[
+            (
+                "/min",
+                &[
+                    "/min",
+                    "bpm",
+                    "beats/min",
+                    "beat/min",
+                    "{beats}/min",
+                    "breaths/min",
+                    "br/min",
+                    "rpm",
+                ],
+            ),
+            ("mm[Hg]", &["mm[hg]", "mmhg", "mm hg", "torr"]),
+            ("cm[H2O]", &["cm[h2o]", "cmh2o", "cm h2o"]),
+            ("Cel", &["cel", "°c", "degc", "deg c", "c", "celsius"]),
+            (
+                "[degF]",
+                &["[degf]", "°f", "degf", "deg f", "f", "fahrenheit"],
+            ),
+            ("%", &["%", "percent", "pct"]),
+            ("kg", &["kg"]),
+            ("g", &["g"]),
+            ("mg", &["mg"]),
+            ("[lb_av]", &["[lb_av]", "lb", "lbs"]),
+            ("cm", &["cm"]),
+            ("mm", &["mm"]),
+            ("m", &["m"]),
+            ("[in_i]", &["[in_i]", "in", "inch"]),
+            ("mL", &["ml"]),
+            ("L", &["l"]),
+            ("L/min", &["l/min", "lpm"]),
+            ("mL/min", &["ml/min"]),
+            ("mg/dL", &["mg/dl"]),
+            ("mmol/L", &["mmol/l"]),
+            ("meq/L", &["meq/l"]),
+            ("s", &["s", "sec"]),
+            ("ms", &["ms", "msec"]),
+            ("mV", &["mv"]),
+        ];
+
+        let aliases = builtin
+            .iter()
+            .flat_map(|(ucum, spellings)| {
+                spellings
+                    .iter()
+                    .map(move |spelling| (spelling.to_string(), ucum.to_string()))
+            })
+            .collect();
+        Self { aliases }
+    }
+
+    /// Map a vendor unit string (matched case-insensitively) to a UCUM code
+    pub fn with_alias(mut self, unit: impl AsRef<str>, ucum: impl Into<String>) -> Self {
+        self.aliases
+            .insert(unit.as_ref().trim().to_lowercase(), ucum.into());
+        self
+    }
+}
+
+impl Default for UcumUnits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnitNormalizer for UcumUnits {
+    fn normalize(&self, unit: &str) -> Option<String> {
+        self.aliases.get(&unit.trim().to_lowercase()).cloned()
+    }
+}
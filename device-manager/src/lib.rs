@@ -5,6 +5,10 @@ pub mod error;
 pub mod repository;
 pub mod manager;
 pub mod registry;
+pub mod lifecycle;
+pub mod formats;
+pub mod aggregation;
+pub mod commands;
 
 // Re-exports
 pub use types::*;
@@ -13,3 +17,7 @@ pub use error::*;
 pub use repository::*;
 pub use manager::*;
 pub use registry::*;
+pub use lifecycle::*;
+pub use formats::*;
+pub use aggregation::*;
+pub use commands::*;
@@ -0,0 +1,591 @@
+use crate::aggregation::persist_aggregates;
+use crate::{
+    DeviceConfig, DevicePlugin, DeviceRepository, PluginRegistry, ReadingAggregator, Result,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Heartbeat interval used when a device does not configure one
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often the monitor looks for devices that stopped reporting
+pub const DEFAULT_MONITOR_TICK: Duration = Duration::from_secs(1);
+
+const EVENT_CAPACITY: usize = 256;
+
+// ============================================================================
+// CONNECTION STATE
+// ============================================================================
+
+/// Where a tracked device is in its connection lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStatus {
+    /// Connected and reporting within its heartbeat timeout
+    Online,
+    /// Missed its heartbeat, or every reconnect attempt failed
+    Offline,
+    /// Offline and being reconnected with backoff
+    Reconnecting,
+    /// Disconnected on request
+    Disconnected,
+}
+
+impl ConnectionStatus {
+    /// Status code stored on the device record
+    pub fn device_status(&self) -> &'static str {
+        match self {
+            ConnectionStatus::Online => "connected",
+            ConnectionStatus::Offline => "offline",
+            ConnectionStatus::Reconnecting => "reconnecting",
+            ConnectionStatus::Disconnected => "disconnected",
+        }
+    }
+}
+
+/// Heartbeat expectations for one device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl HeartbeatConfig {
+    /// Read from the device's advanced settings. The interval falls back to
+    /// the polling interval, and the timeout to three missed intervals.
+    pub fn from_config(config: &DeviceConfig) -> Self {
+        let advanced = config.advanced.as_ref();
+        let interval = advanced
+            .and_then(|settings| {
+                settings
+                    .heartbeat_interval_ms
+                    .or(settings.polling_interval_ms)
+            })
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL);
+        let timeout = advanced
+            .and_then(|settings| settings.heartbeat_timeout_ms)
+            .map(Duration::from_millis)
+            .unwrap_or(interval * 3);
+        Self { interval, timeout }
+    }
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_HEARTBEAT_INTERVAL,
+            timeout: DEFAULT_HEARTBEAT_INTERVAL * 3,
+        }
+    }
+}
+
+/// Exponential backoff for reconnecting a device that went offline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Uses `retry_attempts` and `retry_delay_ms` from the advanced settings
+    pub fn from_config(config: &DeviceConfig) -> Self {
+        let defaults = Self::default();
+        let advanced = config.advanced.as_ref();
+        Self {
+            max_attempts: advanced
+                .and_then(|settings| settings.retry_attempts)
+                .map_or(defaults.max_attempts, u32::from),
+            initial_delay: advanced
+                .and_then(|settings| settings.retry_delay_ms)
+                .map_or(defaults.initial_delay, Duration::from_millis),
+            max_delay: defaults.max_delay,
+        }
+    }
+
+    /// Delay before the given attempt, starting at 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A device the manager holds a connection to
+#[derive(Debug, Clone)]
+pub struct ConnectionState {
+    pub device_id: Uuid,
+    pub device_type: String,
+    pub status: ConnectionStatus,
+    pub connected_at: DateTime<Utc>,
+    pub last_heartbeat: DateTime<Utc>,
+    pub heartbeat: HeartbeatConfig,
+    pub reconnect: ReconnectPolicy,
+    /// Config the device was connected with, reused when reconnecting
+    pub config: serde_json::Value,
+}
+
+impl ConnectionState {
+    pub fn new(
+        device_id: Uuid,
+        device_type: String,
+        config: &DeviceConfig,
+        config_json: serde_json::Value,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            device_id,
+            device_type,
+            status: ConnectionStatus::Online,
+            connected_at: now,
+            last_heartbeat: now,
+            heartbeat: HeartbeatConfig::from_config(config),
+            reconnect: ReconnectPolicy::from_config(config),
+            config: config_json,
+        }
+    }
+
+    /// Online but silent for longer than the heartbeat timeout
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        self.status == ConnectionStatus::Online
+            && (now - self.last_heartbeat)
+                .to_std()
+                .is_ok_and(|silence| silence > self.heartbeat.timeout)
+    }
+}
+
+/// A device moved from one connection status to another
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceStateEvent {
+    pub device_id: Uuid,
+    /// `None` when the device was not tracked before
+    pub from: Option<ConnectionStatus>,
+    pub to: ConnectionStatus,
+    pub reason: String,
+    pub at: DateTime<Utc>,
+}
+
+// ============================================================================
+// CONNECTION REGISTRY
+// ============================================================================
+
+/// Connection state of every tracked device, with a stream of status changes
+pub struct ConnectionRegistry {
+    states: RwLock<HashMap<Uuid, ConnectionState>>,
+    events: broadcast::Sender<DeviceStateEvent>,
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            states: RwLock::new(HashMap::new()),
+            events,
+        }
+    }
+
+    /// Receive every status change from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<DeviceStateEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn get(&self, device_id: Uuid) -> Option<ConnectionState> {
+        self.states.read().await.get(&device_id).cloned()
+    }
+
+    pub async fn status(&self, device_id: Uuid) -> Option<ConnectionStatus> {
+        self.states
+            .read()
+            .await
+            .get(&device_id)
+            .map(|state| state.status)
+    }
+
+    /// Devices that are connected and within their heartbeat timeout
+    pub async fn online_devices(&self) -> Vec<Uuid> {
+        self.states
+            .read()
+            .await
+            .values()
+            .filter(|state| state.status == ConnectionStatus::Online)
+            .map(|state| state.device_id)
+            .collect()
+    }
+
+    /// Start tracking a device, replacing any previous state
+    pub async fn insert(&self, state: ConnectionState, reason: &str) {
+        let device_id = state.device_id;
+        let to = state.status;
+        let from = self
+            .states
+            .write()
+            .await
+            .insert(device_id, state)
+            .map(|previous| previous.status);
+        self.emit(device_id, from, to, reason);
+    }
+
+    /// Stop tracking a device that was disconnected on request
+    pub async fn remove(&self, device_id: Uuid, reason: &str) -> Option<ConnectionState> {
+        let removed = self.states.write().await.remove(&device_id);
+        if let Some(state) = &removed {
+            self.emit(
+                device_id,
+                Some(state.status),
+                ConnectionStatus::Disconnected,
+                reason,
+            );
+        }
+        removed
+    }
+
+    /// Move a device to `to` if its current status is one of `from`.
+    /// Returns whether the transition happened.
+    pub async fn transition(
+        &self,
+        device_id: Uuid,
+        from: &[ConnectionStatus],
+        to: ConnectionStatus,
+        reason: &str,
+    ) -> bool {
+        let previous = {
+            let mut states = self.states.write().await;
+            let Some(state) = states.get_mut(&device_id) else {
+                return false;
+            };
+            if !from.contains(&state.status) {
+                return false;
+            }
+            let previous = state.status;
+            state.status = to;
+            if to == ConnectionStatus::Online {
+                state.last_heartbeat = Utc::now();
+            }
+            previous
+        };
+        self.emit(device_id, Some(previous), to, reason);
+        true
+    }
+
+    /// Note that an online device reported in. Returns false for devices
+    /// that are not online.
+    pub async fn heartbeat(&self, device_id: Uuid) -> bool {
+        match self.states.write().await.get_mut(&device_id) {
+            Some(state) if state.status == ConnectionStatus::Online => {
+                state.last_heartbeat = Utc::now();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Online devices whose heartbeat timed out as of `now`
+    pub async fn stale_devices(&self, now: DateTime<Utc>) -> Vec<ConnectionState> {
+        self.states
+            .read()
+            .await
+            .values()
+            .filter(|state| state.is_stale(now))
+            .cloned()
+            .collect()
+    }
+
+    fn emit(
+        &self,
+        device_id: Uuid,
+        from: Option<ConnectionStatus>,
+        to: ConnectionStatus,
+        reason: &str,
+    ) {
+        tracing::info!(
+            device_id = %device_id,
+            from = from.map(|status| status.device_status()),
+            to = to.device_status(),
+            reason,
+            "Device connection status changed"
+        );
+        // No subscribers is not an error
+        let _ = self.events.send(DeviceStateEvent {
+            device_id,
+            from,
+            to,
+            reason: reason.to_string(),
+            at: Utc::now(),
+        });
+    }
+}
+
+impl Default for ConnectionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// HOOKS
+// ============================================================================
+
+/// Run a plugin lifecycle hook in its own task so that a failing or
+/// panicking hook is logged instead of taking down the caller
+pub(crate) async fn run_hook<F>(device_id: Uuid, hook: &'static str, call: F)
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    match tokio::spawn(call).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            tracing::warn!(device_id = %device_id, hook, error = %e, "Device hook failed");
+        }
+        Err(e) if e.is_panic() => {
+            tracing::error!(device_id = %device_id, hook, "Device hook panicked");
+        }
+        Err(e) => {
+            tracing::warn!(device_id = %device_id, hook, error = %e, "Device hook was cancelled");
+        }
+    }
+}
+
+// ============================================================================
+// HEARTBEAT MONITOR
+// ============================================================================
+
+/// Background task that marks silent devices offline and reconnects them
+#[derive(Clone)]
+pub struct HeartbeatMonitor {
+    repository: Arc<DeviceRepository>,
+    registry: Arc<PluginRegistry>,
+    connections: Arc<ConnectionRegistry>,
+    aggregator: Option<Arc<ReadingAggregator>>,
+    tick: Duration,
+}
+
+impl HeartbeatMonitor {
+    pub fn new(
+        repository: Arc<DeviceRepository>,
+        registry: Arc<PluginRegistry>,
+        connections: Arc<ConnectionRegistry>,
+    ) -> Self {
+        Self {
+            repository,
+            registry,
+            connections,
+            aggregator: None,
+            tick: DEFAULT_MONITOR_TICK,
+        }
+    }
+
+    /// Also close idle aggregation windows on every tick, and flush a
+    /// device's windows when it goes offline
+    pub fn with_aggregator(mut self, aggregator: Arc<ReadingAggregator>) -> Self {
+        self.aggregator = Some(aggregator);
+        self
+    }
+
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(self) {
+        let mut ticker = tokio::time::interval(self.tick);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            if let Some(aggregator) = &self.aggregator {
+                persist_aggregates(&self.repository, &aggregator.flush_idle().await).await;
+            }
+            for state in self.connections.stale_devices(Utc::now()).await {
+                let reason = format!(
+                    "no heartbeat for more than {}s",
+                    state.heartbeat.timeout.as_secs_f64()
+                );
+                if !self
+                    .connections
+                    .transition(
+                        state.device_id,
+                        &[ConnectionStatus::Online],
+                        ConnectionStatus::Offline,
+                        &reason,
+                    )
+                    .await
+                {
+                    continue;
+                }
+
+                // Each drop is handled in its own task so a slow reconnect or
+                // a panicking plugin never stalls or ends the monitor loop
+                let monitor = self.clone();
+                let device_id = state.device_id;
+                let handle = tokio::spawn(async move { monitor.handle_drop(state, reason).await });
+                tokio::spawn(async move {
+                    if let Err(e) = handle.await {
+                        if e.is_panic() {
+                            tracing::error!(device_id = %device_id, "Reconnect task panicked");
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    async fn handle_drop(&self, state: ConnectionState, reason: String) {
+        let device_id = state.device_id;
+        self.persist_status(device_id, ConnectionStatus::Offline, Some(reason.clone()))
+            .await;
+        if let Some(aggregator) = &self.aggregator {
+            persist_aggregates(&self.repository, &aggregator.flush_device(device_id).await).await;
+        }
+
+        let plugin = match self.registry.get_device_plugin(&state.device_type).await {
+            Ok(plugin) => plugin,
+            Err(e) => {
+                tracing::warn!(device_id = %device_id, error = %e, "No plugin to reconnect device");
+                return;
+            }
+        };
+
+        let hook_plugin = plugin.clone();
+        run_hook(device_id, "on_disconnect", async move {
+            hook_plugin
+                .on_disconnect(&device_id.to_string(), &reason)
+                .await
+        })
+        .await;
+
+        self.reconnect(plugin, state).await;
+    }
+
+    /// Retry the connection with exponential backoff until it succeeds, the
+    /// error is not transient, the attempts run out, or the device is
+    /// disconnected on request
+    async fn reconnect(&self, plugin: Arc<dyn DevicePlugin>, state: ConnectionState) {
+        let device_id = state.device_id;
+        let policy = state.reconnect;
+        if !self
+            .connections
+            .transition(
+                device_id,
+                &[ConnectionStatus::Offline],
+                ConnectionStatus::Reconnecting,
+                "reconnecting",
+            )
+            .await
+        {
+            return;
+        }
+        self.persist_status(device_id, ConnectionStatus::Reconnecting, None)
+            .await;
+
+        let mut last_error = String::from("no reconnect attempts configured");
+        for attempt in 1..=policy.max_attempts {
+            tokio::time::sleep(policy.delay(attempt)).await;
+            if self.connections.status(device_id).await != Some(ConnectionStatus::Reconnecting) {
+                return;
+            }
+
+            match plugin
+                .connect(&device_id.to_string(), state.config.clone())
+                .await
+            {
+                Ok(()) => {
+                    let reason = format!("reconnected after {} attempt(s)", attempt);
+                    if !self
+                        .connections
+                        .transition(
+                            device_id,
+                            &[ConnectionStatus::Reconnecting],
+                            ConnectionStatus::Online,
+                            &reason,
+                        )
+                        .await
+                    {
+                        // Disconnected on request while the attempt was in flight
+                        let _ = plugin.disconnect(&device_id.to_string()).await;
+                        return;
+                    }
+                    self.persist_status(device_id, ConnectionStatus::Online, None)
+                        .await;
+                    let hook_plugin = plugin.clone();
+                    run_hook(device_id, "on_connect", async move {
+                        hook_plugin.on_connect(&device_id.to_string()).await
+                    })
+                    .await;
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        device_id = %device_id,
+                        attempt,
+                        max_attempts = policy.max_attempts,
+                        error = %e,
+                        "Device reconnect attempt failed"
+                    );
+                    last_error = e.to_string();
+                    if !e.is_transient() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let reason = format!("reconnect failed: {}", last_error);
+        if self
+            .connections
+            .transition(
+                device_id,
+                &[ConnectionStatus::Reconnecting],
+                ConnectionStatus::Offline,
+                &reason,
+            )
+            .await
+        {
+            if let Err(e) = self
+                .repository
+                .update_device_status(device_id, "error".to_string(), Some(reason))
+                .await
+            {
+                tracing::warn!(device_id = %device_id, error = %e, "Failed to persist device status");
+            }
+        }
+    }
+
+    /// Status changes are tracked in memory first; failing to record one on
+    /// the device row is logged rather than interrupting the monitor
+    async fn persist_status(
+        &self,
+        device_id: Uuid,
+        status: ConnectionStatus,
+        error: Option<String>,
+    ) {
+        if let Err(e) = self
+            .repository
+            .update_device_status(device_id, status.device_status().to_string(), error)
+            .await
+        {
+            tracing::warn!(device_id = %device_id, error = %e, "Failed to persist device status");
+        }
+    }
+}
@@ -1,24 +1,88 @@
+use crate::aggregation::persist_aggregates;
+use crate::commands::{ack_timeout, audit};
+use crate::lifecycle::run_hook;
 use crate::{
-    Device, DeviceData, DeviceCommand, DeviceConfig, DeviceError, Result,
-    DeviceRepository, PluginRegistry,
+    AggregatedReading, AggregationConfig, FormatPlugin, RawReading, ReadingAggregator,
+    CapabilityChangeEvent, CommandAlert, CommandOutcome, CommandRequest, CommandResult, CommandTracker,
+    ConnectionRegistry, ConnectionState, ConnectionStatus, Device, DeviceData, DeviceCommand,
+    DeviceConfig, DeviceError, DeviceProfile, DeviceReading, DeviceStateEvent, HeartbeatMonitor, Result, DeviceRepository,
+    PluginRegistry,
 };
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
-use std::collections::HashMap;
+
+/// Result of ingesting one device message
+#[derive(Debug, Clone)]
+pub struct IngestOutcome {
+    pub readings: Vec<DeviceReading>,
+    /// Stored record of the readings that were not aggregated
+    pub data: Option<DeviceData>,
+    /// Aggregation windows the message closed
+    pub aggregates: Vec<AggregatedReading>,
+    /// Acknowledgment to return to the sender, if the format has one
+    pub ack: Option<Vec<u8>>,
+    /// Why the message was rejected
+    pub error: Option<String>,
+}
+
+impl IngestOutcome {
+    fn rejected(ack: Option<Vec<u8>>, error: &DeviceError) -> Self {
+        Self {
+            readings: Vec::new(),
+            data: None,
+            aggregates: Vec::new(),
+            ack,
+            error: Some(error.to_string()),
+        }
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.error.is_none()
+    }
+}
 
 /// Device manager - business logic layer
 pub struct DeviceManager {
     repository: Arc<DeviceRepository>,
     registry: Arc<PluginRegistry>,
-    active_connections: Arc<RwLock<HashMap<Uuid, ConnectionState>>>,
+    connections: Arc<ConnectionRegistry>,
+    aggregator: Arc<ReadingAggregator>,
+    commands: Arc<CommandTracker>,
 }
 
-#[derive(Debug, Clone)]
-struct ConnectionState {
-    device_id: Uuid,
-    status: String,
-    connected_at: chrono::DateTime<chrono::Utc>,
+/// Device data record for readings parsed from one message
+fn readings_record(
+    format_plugin: &dyn FormatPlugin,
+    id: Uuid,
+    format: &str,
+    raw: &[u8],
+    readings: &[DeviceReading],
+) -> Result<DeviceData> {
+    let parsed = serde_json::to_value(readings)?;
+    let normalized = match format_plugin.normalize(&parsed, format) {
+        Ok(normalized) => Some(normalized),
+        Err(e) => {
+            tracing::warn!(device_id = %id, format, error = %e, "Could not normalize device readings");
+            None
+        }
+    };
+    Ok(DeviceData {
+        id: Uuid::new_v4(),
+        device_id: id,
+        timestamp: readings.iter().map(|reading| reading.timestamp).min().unwrap_or_else(chrono::Utc::now),
+        data_type: "reading".to_string(),
+        format: format.to_string(),
+        raw_data: serde_json::Value::String(String::from_utf8_lossy(raw).into_owned()),
+        parsed_data: Some(parsed),
+        normalized_data: normalized,
+        patient_id: None,
+        encounter_id: None,
+        provider_id: None,
+        metadata: serde_json::json!({}),
+        created_at: chrono::Utc::now(),
+    })
 }
 
 impl DeviceManager {
@@ -26,7 +90,9 @@ impl DeviceManager {
         Self {
             repository,
             registry,
-            active_connections: Arc::new(RwLock::new(HashMap::new())),
+            connections: Arc::new(ConnectionRegistry::new()),
+            aggregator: Arc::new(ReadingAggregator::new()),
+            commands: Arc::new(CommandTracker::new()),
         }
     }
 
@@ -91,7 +157,7 @@ impl DeviceManager {
 
     pub async fn delete_device(&self, id: Uuid) -> Result<()> {
         // Disconnect if connected
-        if self.active_connections.read().await.contains_key(&id) {
+        if self.connections.status(id).await.is_some() {
             self.disconnect_device(id).await?;
         }
 
@@ -105,9 +171,11 @@ impl DeviceManager {
     pub async fn connect_device(&self, id: Uuid) -> Result<()> {
         let device = self.repository.get_device(id).await?;
         
-        // Check if already connected
-        if self.active_connections.read().await.contains_key(&id) {
-            return Err(DeviceError::Busy(format!("Device {} already connected", id)));
+        // Check if already connected; an offline device may be connected again
+        if let Some(status) = self.connections.status(id).await {
+            if status != ConnectionStatus::Offline {
+                return Err(DeviceError::Busy(format!("Device {} already connected", id)));
+            }
         }
 
         // Get plugin for this device type
@@ -118,21 +186,27 @@ impl DeviceManager {
 
         // Get device config
         let config = device.get_config()?;
-        let config_json = serde_json::to_value(config)?;
+        let config_json = serde_json::to_value(&config)?;
 
         // Attempt connection
-        match plugin.connect(&id.to_string(), config_json).await {
+        match plugin.connect(&id.to_string(), config_json.clone()).await {
             Ok(_) => {
+                // Capabilities follow the firmware recorded on the device
+                if let Some(firmware) = device.metadata.get("firmware").and_then(|v| v.as_str()) {
+                    self.registry.report_firmware(&device, firmware).await;
+                }
+
                 // Store connection state
-                let mut connections = self.active_connections.write().await;
-                connections.insert(id, ConnectionState {
-                    device_id: id,
-                    status: "connected".to_string(),
-                    connected_at: chrono::Utc::now(),
-                });
+                let state = ConnectionState::new(id, device.device_type.clone(), &config, config_json);
+                self.connections.insert(state, "connected").await;
 
                 // Update device status
                 self.repository.update_device_status(id, "connected".to_string(), None).await?;
+
+                run_hook(id, "on_connect", async move {
+                    plugin.on_connect(&id.to_string()).await
+                })
+                .await;
                 Ok(())
             }
             Err(e) => {
@@ -151,16 +225,128 @@ impl DeviceManager {
         plugin.disconnect(&id.to_string()).await?;
 
         // Remove connection state
-        self.active_connections.write().await.remove(&id);
+        self.connections.remove(id, "disconnected on request").await;
+
+        // Partial windows are stored rather than lost
+        let aggregates = self.aggregator.flush_device(id).await;
+        persist_aggregates(&self.repository, &aggregates).await;
 
         // Update status
         self.repository.update_device_status(id, "disconnected".to_string(), None).await?;
 
+        run_hook(id, "on_disconnect", async move {
+            plugin.on_disconnect(&id.to_string(), "disconnected on request").await
+        })
+        .await;
         Ok(())
     }
 
+    /// Connected and within its heartbeat timeout
     pub async fn is_connected(&self, id: Uuid) -> bool {
-        self.active_connections.read().await.contains_key(&id)
+        self.connections.status(id).await == Some(ConnectionStatus::Online)
+    }
+
+    // ========================================================================
+    // FIRMWARE
+    // ========================================================================
+
+    /// Record the firmware version a device reported, resolve its
+    /// capabilities and store the version on the device record
+    pub async fn report_firmware(&self, id: Uuid, firmware_version: &str) -> Result<DeviceProfile> {
+        let mut device = self.repository.get_device(id).await?;
+        let profile = self.registry.report_firmware(&device, firmware_version).await;
+
+        if device.metadata.get("firmware").and_then(|v| v.as_str()) != Some(firmware_version) {
+            if !device.metadata.is_object() {
+                device.metadata = serde_json::json!({});
+            }
+            device.metadata["firmware"] = serde_json::Value::String(firmware_version.to_string());
+            self.repository.update_device(id, &device).await?;
+        }
+        Ok(profile)
+    }
+
+    /// Whether the device's current firmware supports a capability
+    pub async fn supports(&self, id: Uuid, capability: &str) -> bool {
+        self.registry.supports(id, capability).await
+    }
+
+    /// Receive firmware and capability changes
+    pub fn subscribe_capability_changes(&self) -> broadcast::Receiver<CapabilityChangeEvent> {
+        self.registry.subscribe_capability_changes()
+    }
+
+    // ========================================================================
+    // HEARTBEATS
+    // ========================================================================
+
+    /// Record that a device reported in. Readings count as heartbeats, so
+    /// this is only needed for devices that push keep-alives of their own.
+    pub async fn record_heartbeat(&self, id: Uuid) -> Result<()> {
+        if self.connections.heartbeat(id).await {
+            Ok(())
+        } else {
+            Err(DeviceError::ConnectionError("Device not connected".to_string()))
+        }
+    }
+
+    /// Devices that are currently connected and reporting
+    pub async fn online_devices(&self) -> Vec<Uuid> {
+        self.connections.online_devices().await
+    }
+
+    pub fn connections(&self) -> &Arc<ConnectionRegistry> {
+        &self.connections
+    }
+
+    /// Receive connection status changes, including devices going offline
+    pub fn subscribe_state_changes(&self) -> broadcast::Receiver<DeviceStateEvent> {
+        self.connections.subscribe()
+    }
+
+    /// Monitor for this manager's connections, for callers that want a
+    /// different tick than [`start_heartbeat_monitor`](Self::start_heartbeat_monitor)
+    pub fn heartbeat_monitor(&self) -> HeartbeatMonitor {
+        HeartbeatMonitor::new(
+            self.repository.clone(),
+            self.registry.clone(),
+            self.connections.clone(),
+        )
+        .with_aggregator(self.aggregator.clone())
+    }
+
+    /// Start marking silent devices offline and reconnecting them
+    pub fn start_heartbeat_monitor(&self) -> JoinHandle<()> {
+        self.heartbeat_monitor().spawn()
+    }
+
+    // ========================================================================
+    // AGGREGATION
+    // ========================================================================
+
+    /// Downsample numeric readings from devices of this type before they
+    /// are stored
+    pub async fn set_aggregation(&self, device_type: String, config: AggregationConfig) {
+        self.aggregator.set_config(device_type, config).await;
+    }
+
+    pub fn aggregator(&self) -> &Arc<ReadingAggregator> {
+        &self.aggregator
+    }
+
+    /// Every ingested reading at full resolution
+    pub fn subscribe_raw_readings(&self) -> broadcast::Receiver<RawReading> {
+        self.aggregator.subscribe_raw()
+    }
+
+    /// Aggregation windows as they close
+    pub fn subscribe_aggregated_readings(&self) -> broadcast::Receiver<AggregatedReading> {
+        self.aggregator.subscribe_aggregated()
+    }
+
+    /// Raw readings still within the device type's retention period
+    pub async fn recent_raw_readings(&self, id: Uuid) -> Vec<DeviceReading> {
+        self.aggregator.raw_readings(id).await
     }
 
     // ========================================================================
@@ -196,8 +382,89 @@ impl DeviceManager {
             created_at: chrono::Utc::now(),
         };
 
+        self.connections.heartbeat(id).await;
+
         // Save to database
-        self.repository.save_device_data(&device_data).await
+        let device_data = self.repository.save_device_data(&device_data).await?;
+
+        let reading = device_data.clone();
+        run_hook(id, "on_reading", async move {
+            plugin.on_reading(&id.to_string(), &reading).await
+        })
+        .await;
+        Ok(device_data)
+    }
+
+    /// Parse a message pushed by a device with the format plugin registered
+    /// for `format`, and store its readings.
+    ///
+    /// Rejected messages are not an `Err`: the outcome carries the reason
+    /// and, for formats that acknowledge messages, the negative
+    /// acknowledgment to send back so the sender can retry or alert.
+    pub async fn ingest_message(&self, id: Uuid, format: &str, raw: &[u8]) -> Result<IngestOutcome> {
+        let device = self.repository.get_device(id).await?;
+        let format_plugin = self.registry.get_format_plugin(format).await?;
+
+        let mut readings = match format_plugin.parse_readings(raw, format) {
+            Ok(readings) => readings,
+            Err(e) => {
+                tracing::warn!(device_id = %id, format, error = %e, "Rejected device message");
+                return Ok(IngestOutcome::rejected(format_plugin.acknowledge(raw, format, Some(&e)), &e));
+            }
+        };
+        for reading in &mut readings {
+            if reading.device_id.is_empty() {
+                reading.device_id = id.to_string();
+            }
+        }
+
+        // With aggregation configured for the device type, numeric readings
+        // are stored as windows once those close and only the rest is stored
+        // as received
+        let aggregated = self.aggregator.config(&device.device_type).await.is_some();
+        let stored: Vec<DeviceReading> = readings
+            .iter()
+            .filter(|reading| !aggregated || reading.value.as_f64().is_none())
+            .cloned()
+            .collect();
+
+        let mut device_data = None;
+        if !stored.is_empty() {
+            let record = readings_record(format_plugin.as_ref(), id, format, raw, &stored)?;
+
+            // A message that parsed but could not be stored is still NACK'd
+            // so the sender does not consider it delivered
+            match self.repository.save_device_data(&record).await {
+                Ok(saved) => device_data = Some(saved),
+                Err(e) => {
+                    tracing::error!(device_id = %id, format, error = %e, "Failed to store device readings");
+                    return Ok(IngestOutcome::rejected(format_plugin.acknowledge(raw, format, Some(&e)), &e));
+                }
+            }
+        }
+
+        let aggregates = self.aggregator.record(id, &device.device_type, &readings).await;
+        persist_aggregates(&self.repository, &aggregates).await;
+
+        self.connections.heartbeat(id).await;
+        if let Ok(plugin) = self.registry.get_device_plugin(&device.device_type).await {
+            let reading = match &device_data {
+                Some(saved) if !aggregated => saved.clone(),
+                _ => readings_record(format_plugin.as_ref(), id, format, raw, &readings)?,
+            };
+            run_hook(id, "on_reading", async move {
+                plugin.on_reading(&id.to_string(), &reading).await
+            })
+            .await;
+        }
+
+        Ok(IngestOutcome {
+            readings,
+            data: device_data,
+            aggregates,
+            ack: format_plugin.acknowledge(raw, format, None),
+            error: None,
+        })
     }
 
     pub async fn get_device_data_history(
@@ -217,67 +484,107 @@ impl DeviceManager {
     // COMMAND EXECUTION
     // ========================================================================
 
-    pub async fn send_command(
-        &self,
-        device_id: Uuid,
-        command: String,
-        parameters: serde_json::Value,
-    ) -> Result<DeviceCommand> {
+    /// Send a command through the device's plugin and wait for the device
+    /// to acknowledge it.
+    ///
+    /// A command already sent under the same idempotency key is not sent
+    /// again: its stored outcome is returned with `replayed` set. Only a
+    /// command that was never acknowledged is resent, under its original
+    /// key so the device can recognise it. A missing acknowledgment is an
+    /// `Ok` outcome of `TimedOut` and is also published as a
+    /// [`CommandAlert`], since the command may or may not have been applied.
+    pub async fn send_command(&self, device_id: Uuid, request: CommandRequest) -> Result<CommandResult> {
         let device = self.repository.get_device(device_id).await?;
-        
+        let plugin = self.registry.get_device_plugin(&device.device_type).await?;
+
+        let required = plugin.command_capability(&request.command);
+        if request.capability < required {
+            audit(device_id, &request, "refused", "missing capability");
+            return Err(DeviceError::PermissionDenied(format!(
+                "Command '{}' requires the {} capability",
+                request.command,
+                required.as_str()
+            )));
+        }
+
+        self.registry.check_command(device_id, &request.command).await?;
+
+        let _in_flight = self.commands.begin(device_id, &request.idempotency_key)?;
+        let existing = self
+            .repository
+            .find_device_command(device_id, &request.idempotency_key)
+            .await?;
+        if let Some(record) = &existing {
+            if record.command != request.command {
+                return Err(DeviceError::ValidationError(format!(
+                    "Idempotency key '{}' was already used for command '{}'",
+                    request.idempotency_key, record.command
+                )));
+            }
+            if let Some(outcome) = CommandOutcome::from_record(record) {
+                audit(device_id, &request, "replayed", outcome.status());
+                return Ok(CommandResult {
+                    command: record.clone(),
+                    outcome,
+                    replayed: true,
+                });
+            }
+        }
+
         if !self.is_connected(device_id).await {
             return Err(DeviceError::ConnectionError("Device not connected".to_string()));
         }
-
-        // Create command record
-        let mut device_command = DeviceCommand {
-            id: Uuid::new_v4(),
-            device_id,
-            command: command.clone(),
-            parameters: parameters.clone(),
-            metadata: serde_json::json!({}),
-            created_at: chrono::Utc::now(),
-            executed_at: None,
-            completed_at: None,
-            status: "pending".to_string(),
-            response: None,
-            error: None,
+        let timeout = match request.ack_timeout {
+            Some(timeout) => timeout,
+            None => ack_timeout(&device.get_config()?),
         };
 
-        // Save command
-        device_command = self.repository.save_device_command(&device_command).await?;
+        // Save command, or resend the unacknowledged one under its record
+        let record = match existing {
+            Some(record) => record,
+            None => self.repository.save_device_command(&request.record(device_id)).await?,
+        };
+        let record = self.repository
+            .update_command_status(record.id, "executing".to_string(), None, None)
+            .await?;
+        audit(device_id, &request, "dispatched", &record.id.to_string());
+
+        let device_key = device_id.to_string();
+        let dispatch = plugin.execute_command(
+            &device_key,
+            &request.command,
+            request.parameters.clone(),
+            &request.idempotency_key,
+        );
+        let timeout_ms = timeout.as_millis() as u64;
+        let outcome = match tokio::time::timeout(timeout, dispatch).await {
+            Ok(Ok(response)) => CommandOutcome::Acknowledged { response },
+            Ok(Err(DeviceError::Timeout(_))) | Err(_) => CommandOutcome::TimedOut { timeout_ms },
+            Ok(Err(e)) => CommandOutcome::Failed { error: e.to_string() },
+        };
+        audit(device_id, &request, outcome.status(), &record.id.to_string());
+        if let CommandOutcome::TimedOut { .. } = outcome {
+            self.commands.alert(
+                &record,
+                &request.idempotency_key,
+                format!("no acknowledgment within {}ms", timeout_ms),
+            );
+        }
 
-        // Execute via plugin
-        let plugin = self.registry.get_device_plugin(&device.device_type).await?;
-        
-        // Update to executing
-        device_command = self.repository
-            .update_command_status(device_command.id, "executing".to_string(), None, None)
+        let (response, error) = outcome.clone().into_record_fields();
+        let record = self.repository
+            .update_command_status(record.id, outcome.status().to_string(), response, error)
             .await?;
+        Ok(CommandResult {
+            command: record,
+            outcome,
+            replayed: false,
+        })
+    }
 
-        // Execute command
-        match plugin.write(&device_id.to_string(), parameters).await {
-            Ok(response) => {
-                self.repository
-                    .update_command_status(
-                        device_command.id,
-                        "completed".to_string(),
-                        Some(response),
-                        None,
-                    )
-                    .await
-            }
-            Err(e) => {
-                self.repository
-                    .update_command_status(
-                        device_command.id,
-                        "failed".to_string(),
-                        None,
-                        Some(e.to_string()),
-                    )
-                    .await
-            }
-        }
+    /// Commands that were never acknowledged, as they time out
+    pub fn subscribe_command_alerts(&self) -> broadcast::Receiver<CommandAlert> {
+        self.commands.subscribe()
     }
 
     pub async fn get_device_commands(
@@ -1,6 +1,8 @@
 use async_trait::async_trait;
 use uuid::Uuid;
-use crate::error::Result;
+use crate::commands::CommandCapability;
+use crate::error::{DeviceError, Result};
+use crate::types::{DeviceData, DeviceReading};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
@@ -37,6 +39,52 @@ pub trait DevicePlugin: Send + Sync {
     
     /// Test connection without actually connecting
     async fn test_connection(&self, config: &serde_json::Value) -> Result<bool>;
+
+    /// Send a command and return the device's acknowledgment once it has
+    /// applied it. A retry of the same command carries the same
+    /// `idempotency_key`, which the device should use to avoid applying it
+    /// twice. By default the command is written as a JSON envelope.
+    async fn execute_command(
+        &self,
+        device_id: &str,
+        command: &str,
+        parameters: serde_json::Value,
+        idempotency_key: &str,
+    ) -> Result<serde_json::Value> {
+        self.write(
+            device_id,
+            serde_json::json!({
+                "command": command,
+                "parameters": parameters,
+                "idempotency_key": idempotency_key,
+            }),
+        )
+        .await
+    }
+
+    /// Capability a caller needs to send `command`. Plugins should require
+    /// `Elevated` for anything that changes alarms, calibration or therapy.
+    fn command_capability(&self, _command: &str) -> CommandCapability {
+        CommandCapability::Standard
+    }
+
+    // Lifecycle hooks. The manager runs each hook in its own task, so an
+    // error or panic in a hook is logged and never affects the connection.
+
+    /// Called after the device connects, including after a reconnect
+    async fn on_connect(&self, _device_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after the device disconnects or is marked offline
+    async fn on_disconnect(&self, _device_id: &str, _reason: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called for every reading taken from the device
+    async fn on_reading(&self, _device_id: &str, _reading: &DeviceData) -> Result<()> {
+        Ok(())
+    }
 }
 
 // ============================================================================
@@ -66,6 +114,18 @@ pub trait FormatPlugin: Send + Sync {
     
     /// Normalize to standard format (FHIR R4)
     fn normalize(&self, data: &serde_json::Value, from_format: &str) -> Result<serde_json::Value>;
+
+    /// Parse raw data into device readings. Plugins that support readings
+    /// return them from `parse` as a JSON array of `DeviceReading`.
+    fn parse_readings(&self, raw: &[u8], format: &str) -> Result<Vec<DeviceReading>> {
+        Ok(serde_json::from_value(self.parse(raw, format)?)?)
+    }
+
+    /// Reply to send to the sender of `raw`, for formats that acknowledge
+    /// messages. `error` is why the message was rejected, if it was.
+    fn acknowledge(&self, _raw: &[u8], _format: &str, _error: Option<&DeviceError>) -> Option<Vec<u8>> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
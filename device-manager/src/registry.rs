@@ -1,7 +1,123 @@
-use crate::{DevicePlugin, FormatPlugin, StoragePlugin, TransferPlugin, DeviceError, Result};
-use std::collections::HashMap;
+use crate::{Device, DevicePlugin, FormatPlugin, StoragePlugin, TransferPlugin, DeviceError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
+
+const EVENT_CAPACITY: usize = 256;
+
+// ============================================================================
+// CAPABILITY DESCRIPTORS
+// ============================================================================
+
+/// Features a device model supports, by firmware version. Loaded from JSON
+/// so new models and firmware releases need no code changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    pub manufacturer: String,
+    pub model: String,
+    /// Supported by every firmware version
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub firmware: Vec<FirmwareCapabilities>,
+}
+
+/// Capabilities added by a range of firmware versions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareCapabilities {
+    /// First version with these capabilities
+    pub min_version: String,
+    /// Last version with these capabilities, when a later release dropped them
+    #[serde(default)]
+    pub max_version: Option<String>,
+    pub capabilities: Vec<String>,
+}
+
+impl ModelCapabilities {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Capabilities of a device running `firmware_version`
+    pub fn resolve(&self, firmware_version: &str) -> BTreeSet<String> {
+        let mut capabilities: BTreeSet<String> = self.capabilities.iter().cloned().collect();
+        for range in &self.firmware {
+            let after_min = compare_versions(firmware_version, &range.min_version) != Ordering::Less;
+            let before_max = range
+                .max_version
+                .as_deref()
+                .is_none_or(|max| compare_versions(firmware_version, max) != Ordering::Greater);
+            if after_min && before_max {
+                capabilities.extend(range.capabilities.iter().cloned());
+            }
+        }
+        capabilities
+    }
+}
+
+/// Compare dotted firmware versions part by part, numerically where both
+/// parts are numbers. Missing parts count as zero, so "2.1" equals "2.1.0".
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut left = a.trim().trim_start_matches(['v', 'V']).split(['.', '-']);
+    let mut right = b.trim().trim_start_matches(['v', 'V']).split(['.', '-']);
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(part), None) | (None, Some(part)) if part.chars().all(|c| c == '0') => continue,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                _ => x.cmp(y),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Firmware and capabilities a device last reported
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceProfile {
+    pub device_id: Uuid,
+    pub manufacturer: String,
+    pub model: String,
+    pub firmware_version: String,
+    /// `None` when no descriptor is loaded for the model, so its
+    /// capabilities are unknown
+    pub capabilities: Option<BTreeSet<String>>,
+    pub reported_at: DateTime<Utc>,
+}
+
+/// Direction of a firmware change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirmwareChange {
+    /// First version reported since the registry started
+    Initial,
+    Upgrade,
+    Downgrade,
+    /// Same version, with capabilities changed by a new descriptor
+    Descriptor,
+}
+
+/// A device's firmware or capabilities changed
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilityChangeEvent {
+    pub device_id: Uuid,
+    pub change: FirmwareChange,
+    pub from_version: Option<String>,
+    pub to_version: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub at: DateTime<Utc>,
+}
 
 /// Plugin registry for managing all plugins
 pub struct PluginRegistry {
@@ -9,15 +125,22 @@ pub struct PluginRegistry {
     format_plugins: Arc<RwLock<HashMap<String, Arc<dyn FormatPlugin>>>>,
     storage_plugins: Arc<RwLock<HashMap<String, Arc<dyn StoragePlugin>>>>,
     transfer_plugins: Arc<RwLock<HashMap<String, Arc<dyn TransferPlugin>>>>,
+    descriptors: Arc<RwLock<HashMap<(String, String), ModelCapabilities>>>,
+    profiles: Arc<RwLock<HashMap<Uuid, DeviceProfile>>>,
+    capability_events: broadcast::Sender<CapabilityChangeEvent>,
 }
 
 impl PluginRegistry {
     pub fn new() -> Self {
+        let (capability_events, _) = broadcast::channel(EVENT_CAPACITY);
         Self {
             device_plugins: Arc::new(RwLock::new(HashMap::new())),
             format_plugins: Arc::new(RwLock::new(HashMap::new())),
             storage_plugins: Arc::new(RwLock::new(HashMap::new())),
             transfer_plugins: Arc::new(RwLock::new(HashMap::new())),
+            descriptors: Arc::new(RwLock::new(HashMap::new())),
+            profiles: Arc::new(RwLock::new(HashMap::new())),
+            capability_events,
         }
     }
 
@@ -124,6 +247,155 @@ impl PluginRegistry {
     pub async fn list_transfer_plugins(&self) -> Vec<String> {
         self.transfer_plugins.read().await.keys().cloned().collect()
     }
+
+    // ========================================================================
+    // DEVICE CAPABILITIES
+    // ========================================================================
+
+    /// Add or replace the descriptor for a model. Devices of that model
+    /// already reported are re-resolved against it.
+    pub async fn register_model_capabilities(&self, descriptor: ModelCapabilities) {
+        let key = (descriptor.manufacturer.clone(), descriptor.model.clone());
+        self.descriptors.write().await.insert(key.clone(), descriptor.clone());
+
+        let mut profiles = self.profiles.write().await;
+        for profile in profiles.values_mut() {
+            if (&profile.manufacturer, &profile.model) != (&key.0, &key.1) {
+                continue;
+            }
+            let capabilities = Some(descriptor.resolve(&profile.firmware_version));
+            let version = profile.firmware_version.clone();
+            self.update_profile(profile, FirmwareChange::Descriptor, Some(version), capabilities);
+        }
+    }
+
+    /// Load every `*.json` model descriptor in a directory. Returns how many
+    /// were loaded.
+    pub async fn load_model_capabilities(&self, dir: impl AsRef<Path>) -> Result<usize> {
+        let mut entries = tokio::fs::read_dir(dir.as_ref()).await?;
+        let mut loaded = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let json = tokio::fs::read_to_string(&path).await?;
+            let descriptor = ModelCapabilities::from_json(&json).map_err(|e| {
+                DeviceError::InvalidConfig(format!("{}: {}", path.display(), e))
+            })?;
+            self.register_model_capabilities(descriptor).await;
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Record the firmware a device reports and resolve its capabilities.
+    /// A changed version or capability set is published as a change event.
+    pub async fn report_firmware(&self, device: &Device, firmware_version: &str) -> DeviceProfile {
+        let capabilities = self
+            .descriptors
+            .read()
+            .await
+            .get(&(device.manufacturer.clone(), device.model.clone()))
+            .map(|descriptor| descriptor.resolve(firmware_version));
+
+        let mut profiles = self.profiles.write().await;
+        let profile = profiles.entry(device.id).or_insert_with(|| DeviceProfile {
+            device_id: device.id,
+            manufacturer: device.manufacturer.clone(),
+            model: device.model.clone(),
+            firmware_version: String::new(),
+            capabilities: None,
+            reported_at: Utc::now(),
+        });
+        let change = if profile.firmware_version.is_empty() {
+            FirmwareChange::Initial
+        } else {
+            match compare_versions(firmware_version, &profile.firmware_version) {
+                Ordering::Greater => FirmwareChange::Upgrade,
+                Ordering::Less => FirmwareChange::Downgrade,
+                Ordering::Equal => FirmwareChange::Descriptor,
+            }
+        };
+        let from_version = Some(profile.firmware_version.clone()).filter(|version| !version.is_empty());
+        profile.firmware_version = firmware_version.to_string();
+        profile.reported_at = Utc::now();
+        self.update_profile(profile, change, from_version, capabilities);
+        profile.clone()
+    }
+
+    /// Firmware and capabilities last reported by a device
+    pub async fn device_profile(&self, device_id: Uuid) -> Option<DeviceProfile> {
+        self.profiles.read().await.get(&device_id).cloned()
+    }
+
+    /// Whether the device's current firmware is known to support a
+    /// capability. Devices with unknown capabilities support nothing.
+    pub async fn supports(&self, device_id: Uuid, capability: &str) -> bool {
+        self.profiles
+            .read()
+            .await
+            .get(&device_id)
+            .and_then(|profile| profile.capabilities.as_ref())
+            .is_some_and(|capabilities| capabilities.contains(capability))
+    }
+
+    /// Refuse a command the device's current firmware does not support.
+    /// Devices with unknown capabilities are not gated.
+    pub async fn check_command(&self, device_id: Uuid, command: &str) -> Result<()> {
+        let profiles = self.profiles.read().await;
+        let Some(profile) = profiles.get(&device_id) else {
+            return Ok(());
+        };
+        match &profile.capabilities {
+            Some(capabilities) if !capabilities.contains(command) => Err(DeviceError::Unsupported(format!(
+                "Device {} ({} {}, firmware {}) does not support command '{}'",
+                device_id, profile.manufacturer, profile.model, profile.firmware_version, command
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Receive firmware and capability changes from now on
+    pub fn subscribe_capability_changes(&self) -> broadcast::Receiver<CapabilityChangeEvent> {
+        self.capability_events.subscribe()
+    }
+
+    fn update_profile(
+        &self,
+        profile: &mut DeviceProfile,
+        change: FirmwareChange,
+        from_version: Option<String>,
+        capabilities: Option<BTreeSet<String>>,
+    ) {
+        let before = profile.capabilities.clone().unwrap_or_default();
+        let after = capabilities.clone().unwrap_or_default();
+        let version_changed = from_version.as_deref() != Some(profile.firmware_version.as_str());
+        if !version_changed && before == after && profile.capabilities.is_some() == capabilities.is_some() {
+            return;
+        }
+        profile.capabilities = capabilities;
+
+        let event = CapabilityChangeEvent {
+            device_id: profile.device_id,
+            change,
+            from_version,
+            to_version: profile.firmware_version.clone(),
+            added: after.difference(&before).cloned().collect(),
+            removed: before.difference(&after).cloned().collect(),
+            at: Utc::now(),
+        };
+        tracing::info!(
+            device_id = %event.device_id,
+            from = ?event.from_version,
+            to = %event.to_version,
+            added = ?event.added,
+            removed = ?event.removed,
+            "Device capabilities changed"
+        );
+        // No subscribers is not an error
+        let _ = self.capability_events.send(event);
+    }
 }
 
 impl Default for PluginRegistry {
@@ -310,6 +310,28 @@ impl DeviceRepository {
         Ok(command)
     }
 
+    /// Latest command sent to the device under this idempotency key
+    pub async fn find_device_command(
+        &self,
+        device_id: Uuid,
+        idempotency_key: &str,
+    ) -> Result<Option<DeviceCommand>> {
+        let command = sqlx::query_as::<_, DeviceCommand>(
+            r#"
+            SELECT * FROM device_commands
+            WHERE device_id = $1 AND metadata->>'idempotency_key' = $2
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(device_id)
+        .bind(idempotency_key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(command)
+    }
+
     pub async fn get_device_commands(
         &self,
         device_id: Uuid,
@@ -119,6 +119,12 @@ pub struct AdvancedSettings {
     pub queue_size: Option<usize>,
     pub batch_size: Option<usize>,
     pub keep_alive: Option<bool>,
+    /// How often the device is expected to report in
+    pub heartbeat_interval_ms: Option<u64>,
+    /// Silence after which the device is considered offline
+    pub heartbeat_timeout_ms: Option<u64>,
+    /// How long to wait for a command to be acknowledged
+    pub command_ack_timeout_ms: Option<u64>,
     pub custom: HashMap<String, serde_json::Value>,
 }
 
@@ -228,6 +234,41 @@ pub struct DeviceData {
     pub created_at: DateTime<Utc>,
 }
 
+// ============================================================================
+// DEVICE READING - COMMON MODEL FOR PARSED OBSERVATIONS
+// ============================================================================
+
+/// One observation parsed from a device message, independent of the wire
+/// format it arrived in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceReading {
+    /// Identifier the message gives for the device; empty when it has none
+    pub device_id: String,
+    pub code: String,
+    pub code_system: Option<String>,
+    pub display: Option<String>,
+    pub value: ReadingValue,
+    /// UCUM code where the unit could be normalized
+    pub unit: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ReadingValue {
+    Numeric(f64),
+    Text(String),
+}
+
+impl ReadingValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ReadingValue::Numeric(value) => Some(*value),
+            ReadingValue::Text(_) => None,
+        }
+    }
+}
+
 // ============================================================================
 // DEVICE COMMAND - EXTENSIBLE
 // ============================================================================
@@ -244,7 +285,7 @@ pub struct DeviceCommand {
     pub executed_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
     
-    pub status: String, // pending, executing, completed, failed
+    pub status: String, // pending, executing, completed, failed, unacknowledged
     pub response: Option<serde_json::Value>,
     pub error: Option<String>,
 }
@@ -11,6 +11,7 @@ pub mod authentication {
     pub const INVALID_CREDENTIALS: &str = "AUTH_2001";
     pub const TOKEN_EXPIRED: &str = "AUTH_2002";
     pub const SESSION_INVALID: &str = "AUTH_2003";
+    pub const AUTHENTICATION_FAILED: &str = "AUTH_2004";
 }
 
 pub mod authorization {
@@ -22,4 +23,26 @@ pub mod database {
     pub const CONNECTION_FAILED: &str = "DB_4001";
     pub const QUERY_FAILED: &str = "DB_4002";
     pub const CONSTRAINT_VIOLATION: &str = "DB_4003";
-}
\ No newline at end of file
+}
+
+pub mod network {
+    pub const REQUEST_FAILED: &str = "NET_5001";
+    pub const GRPC_FAILED: &str = "NET_5002";
+    pub const WEBSOCKET_FAILED: &str = "NET_5003";
+}
+
+pub mod business {
+    pub const RULE_VIOLATION: &str = "BIZ_6001";
+}
+
+pub mod system {
+    pub const UNKNOWN: &str = "SYS_7000";
+    pub const SERVER_ERROR: &str = "SYS_7001";
+    pub const INTERNAL_ERROR: &str = "SYS_7002";
+    pub const CONFIGURATION_ERROR: &str = "SYS_7003";
+}
+
+pub mod external {
+    pub const SERVICE_UNAVAILABLE: &str = "EXT_8001";
+    pub const CIRCUIT_OPEN: &str = "EXT_8002";
+}
@@ -1,44 +1,331 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
 
-/// Error context information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::sanitization::DataSanitizer;
+use crate::types::RustCareError;
+
+/// Error context information.
+///
+/// Each call to [`RustCareError::with_context`] adds one of these as a frame
+/// on top of the error, so the chain records what was being attempted at
+/// every layer the error passed through.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ErrorContext {
+    /// What was being attempted when the error occurred
+    pub message: Option<String>,
     pub request_id: Option<String>,
     pub user_id: Option<String>,
     pub session_id: Option<String>,
     pub trace_id: Option<String>,
     pub additional: HashMap<String, String>,
+    /// Underlying error that this frame was created from
+    #[serde(skip)]
+    pub source: Option<Arc<dyn StdError + Send + Sync>>,
 }
 
 impl ErrorContext {
     pub fn new() -> Self {
-        Self {
-            request_id: None,
-            user_id: None,
-            session_id: None,
-            trace_id: None,
-            additional: HashMap::new(),
-        }
+        Self::default()
+    }
+
+    pub fn with_message<M: Into<String>>(mut self, message: M) -> Self {
+        self.message = Some(message.into());
+        self
     }
-    
+
+    pub fn with_source<E: StdError + Send + Sync + 'static>(mut self, source: E) -> Self {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
     pub fn with_request_id(mut self, request_id: String) -> Self {
         self.request_id = Some(request_id);
         self
     }
-    
+
     pub fn with_user_id(mut self, user_id: String) -> Self {
         self.user_id = Some(user_id);
         self
     }
-    
+
     pub fn with_trace_id(mut self, trace_id: String) -> Self {
         self.trace_id = Some(trace_id);
         self
     }
-    
+
     pub fn add_context<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
         self.additional.insert(key.into(), value.into());
         self
     }
-}
\ No newline at end of file
+
+    /// Identifiers and key/value fields, sorted by key
+    fn fields(&self) -> BTreeMap<String, String> {
+        let mut fields: BTreeMap<String, String> = self
+            .additional
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        for (key, value) in [
+            ("request_id", &self.request_id),
+            ("user_id", &self.user_id),
+            ("session_id", &self.session_id),
+            ("trace_id", &self.trace_id),
+        ] {
+            if let Some(value) = value {
+                fields.insert(key.to_string(), value.clone());
+            }
+        }
+        fields
+    }
+}
+
+impl From<&str> for ErrorContext {
+    fn from(message: &str) -> Self {
+        Self::new().with_message(message)
+    }
+}
+
+impl From<String> for ErrorContext {
+    fn from(message: String) -> Self {
+        Self::new().with_message(message)
+    }
+}
+
+/// Adds context frames to the error of a `Result`
+pub trait ErrorContextExt<T> {
+    fn context<C: Into<ErrorContext>>(self, context: C) -> Result<T, RustCareError>;
+
+    /// Like [`context`](Self::context), but only builds the frame on error
+    fn with_context<C, F>(self, context: F) -> Result<T, RustCareError>
+    where
+        C: Into<ErrorContext>,
+        F: FnOnce() -> C;
+}
+
+impl<T> ErrorContextExt<T> for Result<T, RustCareError> {
+    fn context<C: Into<ErrorContext>>(self, context: C) -> Result<T, RustCareError> {
+        self.map_err(|error| error.with_context(context))
+    }
+
+    fn with_context<C, F>(self, context: F) -> Result<T, RustCareError>
+    where
+        C: Into<ErrorContext>,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|error| error.with_context(context()))
+    }
+}
+
+/// One link of an error chain, sanitized for logs and reports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainEntry {
+    pub message: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl RustCareError {
+    /// Layer a context frame on top of this error, keeping it as the source
+    pub fn with_context<C: Into<ErrorContext>>(self, context: C) -> Self {
+        RustCareError::WithContext {
+            context: Box::new(context.into()),
+            source: Box::new(self),
+        }
+    }
+
+    /// The error underneath all context frames
+    pub fn root(&self) -> &RustCareError {
+        match self {
+            RustCareError::WithContext { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// Context frames, outermost first
+    pub fn frames(&self) -> impl Iterator<Item = &ErrorContext> {
+        let mut next = Some(self);
+        std::iter::from_fn(move || match next? {
+            RustCareError::WithContext { context, source } => {
+                next = Some(source);
+                Some(context.as_ref())
+            }
+            _ => {
+                next = None;
+                None
+            }
+        })
+    }
+
+    /// Request id from the outermost frame that has one
+    pub fn request_id(&self) -> Option<&str> {
+        self.frames().find_map(|frame| frame.request_id.as_deref())
+    }
+
+    /// Trace id from the outermost frame that has one
+    pub fn trace_id(&self) -> Option<&str> {
+        self.frames().find_map(|frame| frame.trace_id.as_deref())
+    }
+
+    /// Every frame, frame source and the root error, outermost first, with
+    /// PII removed from messages and field values
+    pub fn chain(&self) -> Vec<ChainEntry> {
+        let sanitizer = DataSanitizer::new();
+        let mut entries = Vec::new();
+
+        for frame in self.frames() {
+            entries.push(ChainEntry {
+                message: frame
+                    .message
+                    .as_deref()
+                    .map(|message| sanitizer.sanitize(message))
+                    .unwrap_or_default(),
+                fields: frame
+                    .fields()
+                    .into_iter()
+                    .map(|(key, value)| (key, sanitizer.sanitize(&value)))
+                    .collect(),
+            });
+            if let Some(source) = &frame.source {
+                push_sources(&sanitizer, &mut entries, Some(source.as_ref()));
+            }
+        }
+
+        let root = self.root();
+        push_sources(&sanitizer, &mut entries, Some(root));
+        entries
+    }
+
+    /// Multi-line, sanitized description of the whole chain. Fields of a
+    /// frame without a message are shown under the next message instead.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let mut pending = BTreeMap::new();
+        let mut causes = 0;
+        for entry in self.chain() {
+            pending.extend(entry.fields);
+            if entry.message.is_empty() {
+                continue;
+            }
+
+            if report.is_empty() {
+                report.push_str(&entry.message);
+            } else {
+                if causes == 0 {
+                    report.push_str("\n\nCaused by:");
+                }
+                report.push_str(&format!("\n    {}: {}", causes, entry.message));
+                causes += 1;
+            }
+            for (key, value) in std::mem::take(&mut pending) {
+                report.push_str(&format!("\n        {} = {}", key, value));
+            }
+        }
+        report
+    }
+
+    /// Emit the error and its chain as structured fields at error level
+    pub fn trace(&self, message: &str) {
+        let chain = serde_json::to_string(&self.chain()).unwrap_or_default();
+        tracing::error!(
+            error_code = self.code(),
+            error = %self,
+            error_chain = %chain,
+            request_id = self.request_id(),
+            trace_id = self.trace_id(),
+            "{}",
+            message
+        );
+    }
+}
+
+impl fmt::Debug for RustCareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.report())
+    }
+}
+
+/// Append `source` and everything it was caused by
+fn push_sources(
+    sanitizer: &DataSanitizer,
+    entries: &mut Vec<ChainEntry>,
+    mut source: Option<&(dyn StdError + 'static)>,
+) {
+    while let Some(error) = source {
+        entries.push(ChainEntry {
+            message: sanitizer.sanitize(&error.to_string()),
+            fields: BTreeMap::new(),
+        });
+        source = error.source();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    fn failing_lookup() -> Result<(), RustCareError> {
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "connection refused");
+        Err(
+            RustCareError::DatabaseConnectionError("pool timed out".to_string())
+                .with_context(ErrorContext::from("acquiring connection").with_source(io)),
+        )
+    }
+
+    #[test]
+    fn test_chain_keeps_frames_and_sources() {
+        let error = failing_lookup()
+            .with_context(|| {
+                ErrorContext::from("loading chart for jane.doe@example.com")
+                    .with_request_id("req-7".to_string())
+                    .add_context("patient_id", "4471923")
+            })
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "loading chart for jane.doe@example.com");
+        assert_eq!(error.code(), "DB_4001");
+        assert_eq!(error.request_id(), Some("req-7"));
+
+        let messages: Vec<String> = error
+            .chain()
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "loading chart for [EMAIL]",
+                "acquiring connection",
+                "connection refused",
+                "Database connection error: pool timed out",
+            ]
+        );
+        assert_eq!(error.chain()[0].fields["patient_id"], "[NUMBER]");
+    }
+
+    #[test]
+    fn test_report_is_sanitized() {
+        let error = failing_lookup()
+            .context(ErrorContext::new().add_context("email", "jane.doe@example.com"))
+            .unwrap_err();
+        let report = format!("{:?}", error);
+
+        assert!(report.starts_with("acquiring connection\n        email = [EMAIL]"));
+        assert!(report.contains("Caused by:\n    0: connection refused"));
+        assert!(!report.contains("jane.doe"));
+    }
+
+    #[tokio::test]
+    async fn test_chain_crosses_tasks() {
+        assert_send_sync::<RustCareError>();
+        let error = tokio::spawn(async { failing_lookup().context("syncing encounters") })
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.frames().count(), 2);
+    }
+}
@@ -68,8 +68,11 @@ pub mod codes;
 pub mod reporting;
 pub mod recovery;
 pub mod sanitization;
+pub mod problem;
 
 pub use types::*;
 pub use context::*;
 pub use codes::*;
-pub use reporting::*;
\ No newline at end of file
+pub use reporting::*;
+pub use recovery::*;
+pub use problem::*;
\ No newline at end of file
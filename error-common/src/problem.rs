@@ -0,0 +1,229 @@
+// RFC 7807 problem details for HTTP error responses
+
+use serde::{Deserialize, Serialize};
+
+use crate::context::ErrorContext;
+use crate::sanitization::DataSanitizer;
+use crate::types::RustCareError;
+
+/// Media type of a problem details body
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Prefix of the `type` URI; the error code is appended
+pub const PROBLEM_TYPE_BASE: &str = "https://docs.rustcare.dev/errors/";
+
+/// Client-facing error body (`application/problem+json`).
+///
+/// Only correlation ids are carried over from an [`ErrorContext`]; user,
+/// session and free-form fields stay in the server-side trace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+impl ProblemDetails {
+    /// URI of the request that failed
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    pub fn with_context(mut self, context: &ErrorContext) -> Self {
+        self.request_id = context.request_id.clone();
+        self.trace_id = context.trace_id.clone();
+        self
+    }
+}
+
+impl RustCareError {
+    /// Problem details body for this error.
+    ///
+    /// Client errors describe what was wrong, with PII stripped. Server
+    /// errors get a fixed message so internals never reach the client.
+    /// Context frames contribute their correlation ids and nothing else.
+    pub fn to_problem_details(&self) -> ProblemDetails {
+        let status = self.status_code();
+        let detail = if status.is_client_error() {
+            DataSanitizer::new().sanitize(&self.client_message())
+        } else {
+            match status.as_u16() {
+                502 => "An upstream service failed. Try again later.",
+                503 => "The service is temporarily unavailable. Try again later.",
+                _ => "An internal error occurred.",
+            }
+            .to_string()
+        };
+
+        ProblemDetails {
+            problem_type: format!("{}{}", PROBLEM_TYPE_BASE, self.code()),
+            title: self.title().to_string(),
+            status: status.as_u16(),
+            detail,
+            code: self.code().to_string(),
+            instance: None,
+            request_id: self.request_id().map(str::to_string),
+            trace_id: self.trace_id().map(str::to_string),
+        }
+    }
+
+    /// The message without the variant prefix added by `Display`
+    fn client_message(&self) -> String {
+        match self {
+            RustCareError::WebSocketError(message)
+            | RustCareError::GrpcError(message)
+            | RustCareError::NetworkError(message)
+            | RustCareError::ServerError(message)
+            | RustCareError::AuthError(message)
+            | RustCareError::DatabaseError(message)
+            | RustCareError::DatabaseConnectionError(message)
+            | RustCareError::BusinessError(message)
+            | RustCareError::ValidationError(message)
+            | RustCareError::InternalError(message)
+            | RustCareError::ExternalError(message)
+            | RustCareError::ConfigError(message)
+            | RustCareError::Generic { message } => message.clone(),
+            RustCareError::Other(error) => error.to_string(),
+            RustCareError::CircuitOpen { .. } => self.to_string(),
+            RustCareError::WithContext { source, .. } => source.client_message(),
+        }
+    }
+}
+
+#[cfg(feature = "axum")]
+mod response {
+    use super::*;
+    use crate::recovery::Retryable;
+    use axum::http::{header, HeaderValue, StatusCode};
+    use axum::response::{IntoResponse, Response};
+
+    impl IntoResponse for ProblemDetails {
+        fn into_response(self) -> Response {
+            let status =
+                StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            let body = serde_json::to_vec(&self).unwrap_or_default();
+            (
+                status,
+                [(header::CONTENT_TYPE, PROBLEM_JSON_CONTENT_TYPE)],
+                body,
+            )
+                .into_response()
+        }
+    }
+
+    impl IntoResponse for RustCareError {
+        fn into_response(self) -> Response {
+            let status = self.status_code();
+            // Context fields and the full message stay in the server-side
+            // trace; the body only gets what `to_problem_details` allows
+            let chain = serde_json::to_string(&self.chain()).unwrap_or_default();
+            if status.is_server_error() {
+                tracing::error!(
+                    error_code = self.code(),
+                    status = status.as_u16(),
+                    error = %self,
+                    error_chain = %chain,
+                    request_id = self.request_id(),
+                    trace_id = self.trace_id(),
+                    "Request failed"
+                );
+            } else {
+                tracing::warn!(
+                    error_code = self.code(),
+                    status = status.as_u16(),
+                    error = %self,
+                    error_chain = %chain,
+                    request_id = self.request_id(),
+                    trace_id = self.trace_id(),
+                    "Request rejected"
+                );
+            }
+
+            let mut response = self.to_problem_details().into_response();
+            if status == StatusCode::SERVICE_UNAVAILABLE {
+                if let Some(backoff) = self.suggested_backoff() {
+                    let secs = backoff.as_secs_f64().ceil().max(1.0) as u64;
+                    response
+                        .headers_mut()
+                        .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+                }
+            }
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_error_detail_is_sanitized() {
+        let error = RustCareError::ValidationError(
+            "patient jane.doe@example.com has invalid SSN 123-45-6789".to_string(),
+        );
+        let problem = error.to_problem_details();
+
+        assert_eq!(problem.status, 400);
+        assert_eq!(problem.code, "VALIDATION_1001");
+        assert_eq!(problem.detail, "patient [EMAIL] has invalid SSN [SSN]");
+    }
+
+    #[test]
+    fn test_server_error_detail_is_generic() {
+        let error = RustCareError::InternalError(
+            "decrypting record 4471923 for jane.doe@example.com".to_string(),
+        );
+        let problem = error.to_problem_details();
+
+        assert_eq!(problem.status, 500);
+        assert_eq!(problem.detail, "An internal error occurred.");
+    }
+
+    #[test]
+    fn test_context_keeps_only_correlation_ids() {
+        let context = ErrorContext::new()
+            .with_request_id("req-1".to_string())
+            .with_trace_id("trace-1".to_string())
+            .with_user_id("user-42".to_string())
+            .add_context("mrn", "4471923");
+        let body = serde_json::to_value(
+            RustCareError::AuthError("token expired".to_string())
+                .to_problem_details()
+                .with_context(&context),
+        )
+        .unwrap();
+
+        assert_eq!(body["type"], "https://docs.rustcare.dev/errors/AUTH_2004");
+        assert_eq!(body["request_id"], "req-1");
+        assert_eq!(body["trace_id"], "trace-1");
+        assert!(!body.to_string().contains("user-42"));
+        assert!(!body.to_string().contains("4471923"));
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn test_into_response_uses_problem_json() {
+        use axum::response::IntoResponse;
+
+        let response =
+            RustCareError::DatabaseConnectionError("pool timed out".to_string()).into_response();
+
+        assert_eq!(response.status(), 503);
+        assert_eq!(
+            response.headers()["content-type"],
+            PROBLEM_JSON_CONTENT_TYPE
+        );
+        assert_eq!(response.headers()["retry-after"], "1");
+    }
+}
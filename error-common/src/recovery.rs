@@ -1,12 +1,674 @@
 // Recovery mechanisms for errors
 // This module provides structured retry and recovery patterns
 
-pub struct RecoveryStrategy {
-    // Implementation for error recovery
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use tokio::sync::broadcast;
+
+use crate::types::RustCareError;
+
+/// Whether an error is worth another attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Likely to succeed if tried again later (timeouts, dropped connections)
+    Transient,
+    /// Will fail the same way every time (bad input, missing permission)
+    Permanent,
+}
+
+/// Classifies errors for retry decisions
+pub trait Retryable {
+    fn error_class(&self) -> ErrorClass;
+
+    /// Minimum wait this kind of error calls for before the next attempt
+    fn suggested_backoff(&self) -> Option<Duration> {
+        None
+    }
+
+    fn is_retryable(&self) -> bool {
+        self.error_class() == ErrorClass::Transient
+    }
+}
+
+impl Retryable for RustCareError {
+    fn error_class(&self) -> ErrorClass {
+        match self {
+            RustCareError::NetworkError(_)
+            | RustCareError::DatabaseConnectionError(_)
+            | RustCareError::ExternalError(_)
+            | RustCareError::GrpcError(_)
+            | RustCareError::WebSocketError(_)
+            | RustCareError::CircuitOpen { .. } => ErrorClass::Transient,
+            RustCareError::ServerError(_)
+            | RustCareError::AuthError(_)
+            | RustCareError::DatabaseError(_)
+            | RustCareError::BusinessError(_)
+            | RustCareError::ValidationError(_)
+            | RustCareError::InternalError(_)
+            | RustCareError::ConfigError(_)
+            | RustCareError::Generic { .. }
+            | RustCareError::Other(_) => ErrorClass::Permanent,
+            RustCareError::WithContext { source, .. } => source.error_class(),
+        }
+    }
+
+    fn suggested_backoff(&self) -> Option<Duration> {
+        match self {
+            RustCareError::NetworkError(_)
+            | RustCareError::GrpcError(_)
+            | RustCareError::WebSocketError(_) => Some(Duration::from_millis(200)),
+            RustCareError::DatabaseConnectionError(_) => Some(Duration::from_millis(500)),
+            // Third parties rate limit aggressive clients
+            RustCareError::ExternalError(_) => Some(Duration::from_secs(1)),
+            RustCareError::CircuitOpen { retry_after, .. } => Some(*retry_after),
+            RustCareError::WithContext { source, .. } => source.suggested_backoff(),
+            _ => None,
+        }
+    }
+}
+
+/// Exponential backoff with jitter, capped by attempts and total time
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one
+    pub max_attempts: u32,
+    /// Give up once this much time has passed since the first attempt
+    pub max_elapsed: Option<Duration>,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Fraction of each delay (0.0 to 1.0) that is randomized away
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            max_elapsed: Some(Duration::from_secs(30)),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run once and never retry
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    pub fn without_max_elapsed(mut self) -> Self {
+        self.max_elapsed = None;
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay before the retry that follows attempt number `attempt` (1-based),
+    /// before jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        Duration::try_from_secs_f64(delay)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// Delay for `attempt`, at least `floor`, with jitter applied
+    fn delay(&self, attempt: u32, floor: Option<Duration>) -> Duration {
+        let backoff = self.backoff(attempt);
+        let delay = floor.map_or(backoff, |floor| backoff.max(floor));
+        if self.jitter <= 0.0 {
+            return delay;
+        }
+        let keep = 1.0 - self.jitter * rand::thread_rng().gen::<f64>();
+        delay.mul_f64(keep)
+    }
+}
+
+/// Run `op` until it succeeds, fails with a permanent error, or the policy
+/// runs out of attempts or time. The last error is returned on failure.
+pub async fn retry_with_policy<T, E, F, Fut>(mut op: F, policy: &RetryPolicy) -> Result<T, E>
+where
+    E: Retryable + std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let started = Instant::now();
+    let mut attempt = 1;
+    loop {
+        let error = match op().await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+
+        if !error.is_retryable() || attempt >= policy.max_attempts {
+            return Err(error);
+        }
+        let delay = policy.delay(attempt, error.suggested_backoff());
+        if let Some(max_elapsed) = policy.max_elapsed {
+            if started.elapsed() + delay > max_elapsed {
+                return Err(error);
+            }
+        }
+
+        tracing::warn!(
+            attempt,
+            max_attempts = policy.max_attempts,
+            delay_ms = delay.as_millis() as u64,
+            error = %error,
+            "Retrying after transient error"
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// State of a [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CircuitState {
+    /// Calls go through and outcomes are counted
+    Closed,
+    /// Calls fail fast until the cooldown has passed
+    Open,
+    /// A limited number of trial calls decide whether to close again
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// When a [`CircuitBreaker`] opens and how it recovers
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Open after this many failures in a row
+    pub consecutive_failures: u32,
+    /// Open when this fraction of the recent calls failed...
+    pub failure_rate_threshold: f64,
+    /// ...once at least this many calls are in the window
+    pub minimum_calls: usize,
+    /// Number of recent calls the failure rate is measured over
+    pub window_size: usize,
+    /// How long to fail fast before letting trial calls through
+    pub open_cooldown: Duration,
+    /// Trial calls that must all succeed before closing again
+    pub half_open_max_calls: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: 5,
+            failure_rate_threshold: 0.5,
+            minimum_calls: 10,
+            window_size: 20,
+            open_cooldown: Duration::from_secs(30),
+            half_open_max_calls: 3,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn with_consecutive_failures(mut self, consecutive_failures: u32) -> Self {
+        self.consecutive_failures = consecutive_failures.max(1);
+        self
+    }
+
+    pub fn with_failure_rate(mut self, threshold: f64, minimum_calls: usize) -> Self {
+        self.failure_rate_threshold = threshold.clamp(0.0, 1.0);
+        self.minimum_calls = minimum_calls.max(1);
+        self
+    }
+
+    pub fn with_window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size.max(1);
+        self
+    }
+
+    pub fn with_open_cooldown(mut self, open_cooldown: Duration) -> Self {
+        self.open_cooldown = open_cooldown;
+        self
+    }
+
+    pub fn with_half_open_max_calls(mut self, half_open_max_calls: u32) -> Self {
+        self.half_open_max_calls = half_open_max_calls.max(1);
+        self
+    }
+}
+
+/// Published on every state change of a [`CircuitBreaker`]
+#[derive(Debug, Clone)]
+pub struct CircuitEvent {
+    pub breaker: String,
+    pub from: CircuitState,
+    pub to: CircuitState,
+    pub reason: &'static str,
+    pub at: DateTime<Utc>,
+}
+
+struct BreakerInner {
+    state: CircuitState,
+    /// Recent outcomes while closed, `true` for failure
+    window: VecDeque<bool>,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trials_in_flight: u32,
+    trial_successes: u32,
+}
+
+/// Stops calling a dependency that keeps failing.
+///
+/// Only transient errors (see [`Retryable`]) count as failures: a validation
+/// or authorization error means the dependency answered, so it is healthy.
+pub struct CircuitBreaker {
+    name: String,
+    config: CircuitBreakerConfig,
+    inner: Mutex<BreakerInner>,
+    events: broadcast::Sender<CircuitEvent>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: impl Into<String>, config: CircuitBreakerConfig) -> Self {
+        let (events, _) = broadcast::channel(64);
+        Self {
+            name: name.into(),
+            config,
+            inner: Mutex::new(BreakerInner {
+                state: CircuitState::Closed,
+                window: VecDeque::new(),
+                consecutive_failures: 0,
+                opened_at: None,
+                trials_in_flight: 0,
+                trial_successes: 0,
+            }),
+            events,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.lock().state
+    }
+
+    /// State transitions from now on, for telemetry
+    pub fn subscribe(&self) -> broadcast::Receiver<CircuitEvent> {
+        self.events.subscribe()
+    }
+
+    /// Run `op` unless the circuit is open, in which case fail immediately
+    /// with [`RustCareError::CircuitOpen`]
+    pub async fn call<T, F, Fut>(&self, op: F) -> Result<T, RustCareError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, RustCareError>>,
+    {
+        let mut permit = self.acquire()?;
+        let result = op().await;
+        let failed = result.as_ref().is_err_and(|error| error.is_retryable());
+        permit.finish(failed);
+        result
+    }
+
+    fn acquire(&self) -> Result<Permit<'_>, RustCareError> {
+        let mut inner = self.lock();
+        if inner.state == CircuitState::Open {
+            let elapsed = inner.opened_at.map_or(Duration::MAX, |at| at.elapsed());
+            if elapsed < self.config.open_cooldown {
+                return Err(self.rejection(self.config.open_cooldown - elapsed));
+            }
+            self.transition(&mut inner, CircuitState::HalfOpen, "cooldown elapsed");
+        }
+
+        let trial = inner.state == CircuitState::HalfOpen;
+        if trial {
+            if inner.trials_in_flight + inner.trial_successes >= self.config.half_open_max_calls {
+                return Err(self.rejection(Duration::ZERO));
+            }
+            inner.trials_in_flight += 1;
+        }
+        Ok(Permit {
+            breaker: self,
+            trial,
+            finished: false,
+        })
+    }
+
+    fn record(&self, trial: bool, failed: bool) {
+        let mut inner = self.lock();
+        if trial {
+            inner.trials_in_flight = inner.trials_in_flight.saturating_sub(1);
+        }
+
+        match inner.state {
+            // A trial settles the half-open state; calls that started while
+            // closed and finish now no longer say anything about recovery
+            CircuitState::HalfOpen if trial => {
+                if failed {
+                    self.transition(&mut inner, CircuitState::Open, "trial call failed");
+                } else {
+                    inner.trial_successes += 1;
+                    if inner.trial_successes >= self.config.half_open_max_calls {
+                        self.transition(&mut inner, CircuitState::Closed, "trial calls succeeded");
+                    }
+                }
+            }
+            CircuitState::Closed => {
+                if inner.window.len() == self.config.window_size {
+                    inner.window.pop_front();
+                }
+                inner.window.push_back(failed);
+                inner.consecutive_failures = if failed {
+                    inner.consecutive_failures + 1
+                } else {
+                    0
+                };
+
+                let failures = inner.window.iter().filter(|failed| **failed).count();
+                if inner.consecutive_failures >= self.config.consecutive_failures {
+                    self.transition(&mut inner, CircuitState::Open, "consecutive failures");
+                } else if inner.window.len() >= self.config.minimum_calls
+                    && failures as f64 / inner.window.len() as f64
+                        >= self.config.failure_rate_threshold
+                {
+                    self.transition(&mut inner, CircuitState::Open, "failure rate exceeded");
+                }
+            }
+            CircuitState::HalfOpen | CircuitState::Open => {}
+        }
+    }
+
+    /// Give back a trial slot whose call was cancelled before finishing
+    fn release(&self) {
+        let mut inner = self.lock();
+        inner.trials_in_flight = inner.trials_in_flight.saturating_sub(1);
+    }
+
+    fn transition(&self, inner: &mut BreakerInner, to: CircuitState, reason: &'static str) {
+        let from = inner.state;
+        inner.state = to;
+        inner.window.clear();
+        inner.consecutive_failures = 0;
+        inner.trial_successes = 0;
+        inner.opened_at = (to == CircuitState::Open).then(Instant::now);
+
+        if to == CircuitState::Open {
+            tracing::warn!(breaker = %self.name, from = from.as_str(), reason, "Circuit opened");
+        } else {
+            tracing::info!(breaker = %self.name, from = from.as_str(), to = to.as_str(), reason, "Circuit state changed");
+        }
+        // No subscribers is fine
+        let _ = self.events.send(CircuitEvent {
+            breaker: self.name.clone(),
+            from,
+            to,
+            reason,
+            at: Utc::now(),
+        });
+    }
+
+    fn rejection(&self, retry_after: Duration) -> RustCareError {
+        RustCareError::CircuitOpen {
+            name: self.name.clone(),
+            retry_after,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BreakerInner> {
+        // The state is updated atomically under the lock, so it is still
+        // consistent if a holder panicked
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A call admitted by the breaker; cancelled calls free their trial slot
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    trial: bool,
+    finished: bool,
+}
+
+impl Permit<'_> {
+    fn finish(&mut self, failed: bool) {
+        self.finished = true;
+        self.breaker.record(self.trial, failed);
+    }
 }
 
-impl RecoveryStrategy {
-    pub fn new() -> Self {
-        Self {}
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.trial && !self.finished {
+            self.breaker.release();
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(5))
+            .with_jitter(0.0)
+    }
+
+    #[test]
+    fn test_classification() {
+        assert!(RustCareError::NetworkError("reset".into()).is_retryable());
+        assert!(RustCareError::DatabaseConnectionError("refused".into()).is_retryable());
+        assert!(!RustCareError::ValidationError("bad dob".into()).is_retryable());
+        assert!(!RustCareError::AuthError("denied".into()).is_retryable());
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy =
+            RetryPolicy::default().with_backoff(Duration::from_millis(100), Duration::from_secs(1));
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(10), Duration::from_secs(1));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_until_success() {
+        let calls = AtomicU32::new(0);
+        let result = retry_with_policy(
+            || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(RustCareError::NetworkError("timeout".into())),
+                    _ => Ok("done"),
+                }
+            },
+            &fast_policy(),
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_error_is_not_retried() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = retry_with_policy(
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(RustCareError::ValidationError("missing mrn".into()))
+            },
+            &fast_policy(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(RustCareError::ValidationError(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_max_elapsed_stops_retrying() {
+        let calls = AtomicU32::new(0);
+        let policy = fast_policy()
+            .with_max_attempts(10)
+            .with_max_elapsed(Duration::from_millis(300));
+        let result: Result<(), _> = retry_with_policy(
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(RustCareError::ExternalError(
+                    "clearinghouse unavailable".into(),
+                ))
+            },
+            &policy,
+        )
+        .await;
+
+        // The 1s suggested backoff for external errors exceeds the budget
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    fn breaker(cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(
+            "clearinghouse",
+            CircuitBreakerConfig::default()
+                .with_consecutive_failures(3)
+                .with_open_cooldown(cooldown)
+                .with_half_open_max_calls(2),
+        )
+    }
+
+    async fn outage(breaker: &CircuitBreaker) -> Result<(), RustCareError> {
+        breaker
+            .call(|| async { Err(RustCareError::ExternalError("timeout".into())) })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_and_fails_fast() {
+        let breaker = breaker(Duration::from_secs(60));
+        let mut events = breaker.subscribe();
+        for _ in 0..3 {
+            assert!(matches!(
+                outage(&breaker).await,
+                Err(RustCareError::ExternalError(_))
+            ));
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let calls = AtomicU32::new(0);
+        let result = breaker
+            .call(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(RustCareError::CircuitOpen { .. })));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(
+            (event.from, event.to),
+            (CircuitState::Closed, CircuitState::Open)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_permanent_errors_do_not_open_the_circuit() {
+        let breaker = breaker(Duration::from_secs(60));
+        for _ in 0..10 {
+            let _ = breaker
+                .call(|| async { Err::<(), _>(RustCareError::ValidationError("bad claim".into())) })
+                .await;
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_failure_rate_opens_the_circuit() {
+        let breaker = CircuitBreaker::new(
+            "secrets",
+            CircuitBreakerConfig::default().with_failure_rate(0.5, 4),
+        );
+        for call in 0..4 {
+            let _ = breaker
+                .call(|| async move {
+                    if call % 2 == 0 {
+                        Err(RustCareError::NetworkError("reset".into()))
+                    } else {
+                        Ok(())
+                    }
+                })
+                .await;
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_trials_close_or_reopen() {
+        let breaker = breaker(Duration::from_millis(20));
+        for _ in 0..3 {
+            let _ = outage(&breaker).await;
+        }
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let _ = outage(&breaker).await;
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        breaker.call(|| async { Ok(()) }).await.unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.call(|| async { Ok(()) }).await.unwrap();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -14,10 +14,7 @@ impl ErrorReporter {
     
     pub async fn report_error(&self, error: &RustCareError) -> Result<(), Box<dyn std::error::Error>> {
         // TODO: Implement error reporting to external systems
-        tracing::error!(
-            error = %error,
-            "Error reported"
-        );
+        error.trace("Error reported");
         Ok(())
     }
 }
\ No newline at end of file
@@ -1,17 +1,60 @@
 // Sanitization utilities
 // This module provides data sanitization for security
 
-pub struct DataSanitizer {
-    // Implementation for data sanitization
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Patterns for identifiers that must never appear in client-facing or
+/// logged error text, with the placeholder each is replaced by
+fn patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r"(?i)\bbearer\s+[A-Za-z0-9\-._~+/]+=*", "Bearer [TOKEN]"),
+            (
+                r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+                "[TOKEN]",
+            ),
+            (r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "[EMAIL]"),
+            (r"\b\d{3}-\d{2}-\d{4}\b", "[SSN]"),
+            (r"\b\d{4}-\d{2}-\d{2}\b|\b\d{1,2}/\d{1,2}/\d{4}\b", "[DATE]"),
+            (
+                r"(?:\+?1[\s.-]?)?\(?\b\d{3}\)?[\s.-]\d{3}[\s.-]\d{4}\b",
+                "[PHONE]",
+            ),
+            // Record numbers, card and account numbers
+            (r"\b\d{7,19}\b", "[NUMBER]"),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| {
+            (
+                Regex::new(pattern).expect("sanitization pattern is valid"),
+                replacement,
+            )
+        })
+        .collect()
+    })
 }
 
+#[derive(Default)]
+pub struct DataSanitizer {}
+
 impl DataSanitizer {
     pub fn new() -> Self {
         Self {}
     }
-    
+
+    /// Replace emails, SSNs, phone numbers, dates, long numeric identifiers
+    /// and bearer tokens with placeholders
+    pub fn sanitize(&self, data: &str) -> String {
+        patterns()
+            .iter()
+            .fold(data.to_string(), |text, (pattern, replacement)| {
+                pattern.replace_all(&text, *replacement).into_owned()
+            })
+    }
+
     pub fn sanitize_for_logging(&self, data: &str) -> String {
-        // Basic sanitization - remove potential sensitive data
-        data.to_string()
+        self.sanitize(data)
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,15 @@
+use http::StatusCode;
+use std::time::Duration;
 use thiserror::Error;
 
+use crate::codes;
+use crate::context::ErrorContext;
+
 /// Simplified error enum for common use cases
-#[derive(Error, Debug)]
+///
+/// `Debug` prints the sanitized report of the whole context chain; see
+/// [`RustCareError::report`].
+#[derive(Error)]
 pub enum RustCareError {
     /// WebSocket-related errors
     #[error("WebSocket error: {0}")]
@@ -27,6 +35,10 @@ pub enum RustCareError {
     #[error("Database error: {0}")]
     DatabaseError(String),
     
+    /// Database unreachable or connection lost; safe to retry
+    #[error("Database connection error: {0}")]
+    DatabaseConnectionError(String),
+    
     /// Business logic errors
     #[error("Business logic error: {0}")]
     BusinessError(String),
@@ -51,9 +63,99 @@ pub enum RustCareError {
     #[error("Error: {message}")]
     Generic { message: String },
     
+    /// Call rejected without being attempted because the dependency's
+    /// circuit breaker is open
+    #[error("Circuit breaker '{name}' is open")]
+    CircuitOpen { name: String, retry_after: Duration },
+    
     /// Wrapped external errors
     #[error(transparent)]
     Other(#[from] anyhow::Error),
+
+    /// An error with a context frame layered on top
+    #[error("{}", top_message(.context, .source))]
+    WithContext {
+        context: Box<ErrorContext>,
+        #[source]
+        source: Box<RustCareError>,
+    },
+}
+
+/// The frame's message, or the wrapped error's if the frame only adds fields
+fn top_message(context: &ErrorContext, source: &RustCareError) -> String {
+    match &context.message {
+        Some(message) => message.clone(),
+        None => source.to_string(),
+    }
+}
+
+impl RustCareError {
+    /// Stable error code reported to clients
+    pub fn code(&self) -> &'static str {
+        match self {
+            RustCareError::WebSocketError(_) => codes::network::WEBSOCKET_FAILED,
+            RustCareError::GrpcError(_) => codes::network::GRPC_FAILED,
+            RustCareError::NetworkError(_) => codes::network::REQUEST_FAILED,
+            RustCareError::ServerError(_) => codes::system::SERVER_ERROR,
+            RustCareError::AuthError(_) => codes::authentication::AUTHENTICATION_FAILED,
+            RustCareError::DatabaseError(_) => codes::database::QUERY_FAILED,
+            RustCareError::DatabaseConnectionError(_) => codes::database::CONNECTION_FAILED,
+            RustCareError::BusinessError(_) => codes::business::RULE_VIOLATION,
+            RustCareError::ValidationError(_) => codes::validation::INVALID_INPUT,
+            RustCareError::InternalError(_) | RustCareError::Other(_) => {
+                codes::system::INTERNAL_ERROR
+            }
+            RustCareError::ExternalError(_) => codes::external::SERVICE_UNAVAILABLE,
+            RustCareError::CircuitOpen { .. } => codes::external::CIRCUIT_OPEN,
+            RustCareError::ConfigError(_) => codes::system::CONFIGURATION_ERROR,
+            RustCareError::Generic { .. } => codes::system::UNKNOWN,
+            RustCareError::WithContext { source, .. } => source.code(),
+        }
+    }
+
+    /// HTTP status for this error when returned from a handler
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            RustCareError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            RustCareError::AuthError(_) => StatusCode::UNAUTHORIZED,
+            RustCareError::BusinessError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            RustCareError::NetworkError(_)
+            | RustCareError::GrpcError(_)
+            | RustCareError::WebSocketError(_)
+            | RustCareError::ExternalError(_) => StatusCode::BAD_GATEWAY,
+            RustCareError::DatabaseConnectionError(_) | RustCareError::CircuitOpen { .. } => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            RustCareError::DatabaseError(_)
+            | RustCareError::ServerError(_)
+            | RustCareError::InternalError(_)
+            | RustCareError::ConfigError(_)
+            | RustCareError::Generic { .. }
+            | RustCareError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            RustCareError::WithContext { source, .. } => source.status_code(),
+        }
+    }
+
+    /// Short, fixed summary of the error kind
+    pub fn title(&self) -> &'static str {
+        match self {
+            RustCareError::WebSocketError(_) => "WebSocket Error",
+            RustCareError::GrpcError(_) => "gRPC Error",
+            RustCareError::NetworkError(_) => "Network Error",
+            RustCareError::ServerError(_) => "Server Error",
+            RustCareError::AuthError(_) => "Authentication Failed",
+            RustCareError::DatabaseError(_) => "Database Error",
+            RustCareError::DatabaseConnectionError(_) => "Database Unavailable",
+            RustCareError::BusinessError(_) => "Business Rule Violation",
+            RustCareError::ValidationError(_) => "Validation Error",
+            RustCareError::InternalError(_) | RustCareError::Other(_) => "Internal Error",
+            RustCareError::ExternalError(_) => "External Service Error",
+            RustCareError::CircuitOpen { .. } => "Dependency Unavailable",
+            RustCareError::ConfigError(_) => "Configuration Error",
+            RustCareError::Generic { .. } => "Error",
+            RustCareError::WithContext { source, .. } => source.title(),
+        }
+    }
 }
 
 /// Result type alias for RustCare operations
@@ -61,9 +163,14 @@ pub type Result<T> = std::result::Result<T, RustCareError>;
 
 /// Async logging function for errors
 pub async fn log_error(context: &str, error: &RustCareError) {
+    let chain = serde_json::to_string(&error.chain()).unwrap_or_default();
     tracing::error!(
         context = context,
+        error_code = error.code(),
         error = %error,
+        error_chain = %chain,
+        request_id = error.request_id(),
+        trace_id = error.trace_id(),
         "RustCare error occurred"
     );
 }
\ No newline at end of file
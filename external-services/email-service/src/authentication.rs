@@ -1,8 +1,554 @@
-// Email authentication
-pub struct EmailAuthentication;
+// Email authentication (SPF, DKIM and DMARC evaluation of inbound mail)
+use std::net::IpAddr;
+use std::time::Duration;
+
+use mail_auth::common::verify::VerifySignature;
+use mail_auth::dmarc::Policy;
+use mail_auth::hickory_resolver::system_conf::read_system_conf;
+use mail_auth::{
+    AuthenticatedMessage, DkimOutput, DkimResult, DmarcOutput, DmarcResult, Resolver, SpfOutput,
+    SpfResult,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EmailError, EmailResult};
+
+/// Outcome of a single authentication mechanism
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthStatus {
+    Pass,
+    Fail,
+    SoftFail,
+    Neutral,
+    None,
+    TempError,
+    PermError,
+}
+
+impl AuthStatus {
+    pub fn is_pass(&self) -> bool {
+        matches!(self, AuthStatus::Pass)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthStatus::Pass => "pass",
+            AuthStatus::Fail => "fail",
+            AuthStatus::SoftFail => "softfail",
+            AuthStatus::Neutral => "neutral",
+            AuthStatus::None => "none",
+            AuthStatus::TempError => "temperror",
+            AuthStatus::PermError => "permerror",
+        }
+    }
+}
+
+impl From<SpfResult> for AuthStatus {
+    fn from(result: SpfResult) -> Self {
+        match result {
+            SpfResult::Pass => AuthStatus::Pass,
+            SpfResult::Fail => AuthStatus::Fail,
+            SpfResult::SoftFail => AuthStatus::SoftFail,
+            SpfResult::Neutral => AuthStatus::Neutral,
+            SpfResult::TempError => AuthStatus::TempError,
+            SpfResult::PermError => AuthStatus::PermError,
+            SpfResult::None => AuthStatus::None,
+        }
+    }
+}
+
+impl From<&DkimResult> for AuthStatus {
+    fn from(result: &DkimResult) -> Self {
+        match result {
+            DkimResult::Pass => AuthStatus::Pass,
+            DkimResult::Neutral(_) => AuthStatus::Neutral,
+            DkimResult::Fail(_) => AuthStatus::Fail,
+            DkimResult::PermError(_) => AuthStatus::PermError,
+            DkimResult::TempError(_) => AuthStatus::TempError,
+            DkimResult::None => AuthStatus::None,
+        }
+    }
+}
+
+impl From<&DmarcResult> for AuthStatus {
+    fn from(result: &DmarcResult) -> Self {
+        match result {
+            DmarcResult::Pass => AuthStatus::Pass,
+            DmarcResult::Fail(_) => AuthStatus::Fail,
+            DmarcResult::TempError(_) => AuthStatus::TempError,
+            DmarcResult::PermError(_) => AuthStatus::PermError,
+            DmarcResult::None => AuthStatus::None,
+        }
+    }
+}
+
+/// Policy published by the RFC5322.From domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DmarcPolicy {
+    None,
+    Quarantine,
+    Reject,
+}
+
+impl From<Policy> for DmarcPolicy {
+    fn from(policy: Policy) -> Self {
+        match policy {
+            Policy::Quarantine => DmarcPolicy::Quarantine,
+            Policy::Reject => DmarcPolicy::Reject,
+            Policy::None | Policy::Unspecified => DmarcPolicy::None,
+        }
+    }
+}
+
+/// What the receiver should do with the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    Accept,
+    Quarantine,
+    Reject,
+}
+
+/// SPF result for the envelope sender
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpfCheck {
+    pub status: AuthStatus,
+    pub domain: String,
+}
+
+/// Result of verifying one DKIM-Signature header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DkimCheck {
+    pub status: AuthStatus,
+    pub domain: Option<String>,
+    pub selector: Option<String>,
+    pub error: Option<String>,
+}
+
+/// DMARC evaluation for the RFC5322.From domain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DmarcCheck {
+    pub status: AuthStatus,
+    pub domain: String,
+    pub policy: DmarcPolicy,
+    pub spf_aligned: bool,
+    pub dkim_aligned: bool,
+}
+
+/// Combined SPF, DKIM and DMARC outcome for an inbound message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MailAuthResult {
+    pub spf: SpfCheck,
+    /// One entry per DKIM-Signature header, in header order
+    pub dkim: Vec<DkimCheck>,
+    pub dmarc: DmarcCheck,
+    /// Whether a passing SPF or DKIM identity aligns with the From domain
+    pub aligned: bool,
+}
+
+impl MailAuthResult {
+    /// Result used when the checks could not finish in time
+    fn temp_error(from_domain: &str, mail_from_domain: &str) -> Self {
+        Self {
+            spf: SpfCheck {
+                status: AuthStatus::TempError,
+                domain: mail_from_domain.to_string(),
+            },
+            dkim: Vec::new(),
+            dmarc: DmarcCheck {
+                status: AuthStatus::TempError,
+                domain: from_domain.to_string(),
+                policy: DmarcPolicy::None,
+                spf_aligned: false,
+                dkim_aligned: false,
+            },
+            aligned: false,
+        }
+    }
+
+    pub fn dkim_pass(&self) -> bool {
+        self.dkim.iter().any(|check| check.status.is_pass())
+    }
+
+    /// Disposition requested by the sender's DMARC policy
+    pub fn disposition(&self) -> Disposition {
+        if self.dmarc.status != AuthStatus::Fail {
+            return Disposition::Accept;
+        }
+        match self.dmarc.policy {
+            DmarcPolicy::Reject => Disposition::Reject,
+            DmarcPolicy::Quarantine => Disposition::Quarantine,
+            DmarcPolicy::None => Disposition::Accept,
+        }
+    }
+
+    /// Value for an Authentication-Results header (RFC 8601)
+    pub fn authentication_results(&self, authserv_id: &str) -> String {
+        let mut parts = vec![format!(
+            "spf={} smtp.mailfrom={}",
+            self.spf.status.as_str(),
+            self.spf.domain
+        )];
+        if self.dkim.is_empty() {
+            parts.push("dkim=none".to_string());
+        }
+        for check in &self.dkim {
+            match &check.domain {
+                Some(domain) => parts.push(format!(
+                    "dkim={} header.d={}",
+                    check.status.as_str(),
+                    domain
+                )),
+                None => parts.push(format!("dkim={}", check.status.as_str())),
+            }
+        }
+        parts.push(format!(
+            "dmarc={} header.from={}",
+            self.dmarc.status.as_str(),
+            self.dmarc.domain
+        ));
+        format!("{}; {}", authserv_id, parts.join("; "))
+    }
+}
+
+/// Resolver and timeout settings for `EmailAuthentication`
+#[derive(Debug, Clone)]
+pub struct AuthenticationConfig {
+    /// Name of this receiving host, used in SPF macro expansion
+    pub host_domain: String,
+    /// Timeout for each individual DNS query
+    pub dns_timeout: Duration,
+    pub dns_attempts: usize,
+    /// Entries kept per record type in the DNS cache
+    pub cache_capacity: usize,
+    /// Upper bound for the whole SPF, DKIM and DMARC evaluation
+    pub verification_timeout: Duration,
+}
+
+impl Default for AuthenticationConfig {
+    fn default() -> Self {
+        Self {
+            host_domain: hostname::get()
+                .ok()
+                .and_then(|name| name.into_string().ok())
+                .unwrap_or_else(|| "localhost".to_string()),
+            dns_timeout: Duration::from_secs(5),
+            dns_attempts: 2,
+            cache_capacity: 1024,
+            verification_timeout: Duration::from_secs(20),
+        }
+    }
+}
+
+impl AuthenticationConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |name: &str, default: Duration| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+
+        Self {
+            host_domain: std::env::var("EMAIL_AUTH_HOST_DOMAIN").unwrap_or(defaults.host_domain),
+            dns_timeout: secs("EMAIL_AUTH_DNS_TIMEOUT_SECS", defaults.dns_timeout),
+            dns_attempts: std::env::var("EMAIL_AUTH_DNS_ATTEMPTS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.dns_attempts),
+            cache_capacity: std::env::var("EMAIL_AUTH_DNS_CACHE_CAPACITY")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.cache_capacity),
+            verification_timeout: secs("EMAIL_AUTH_TIMEOUT_SECS", defaults.verification_timeout),
+        }
+    }
+}
+
+/// SPF/DKIM/DMARC verifier for inbound messages.
+///
+/// DNS answers are cached in per-record-type LRU caches and expire with the
+/// record TTL, so a single instance should be shared across messages.
+pub struct EmailAuthentication {
+    resolver: Resolver,
+    config: AuthenticationConfig,
+}
 
 impl EmailAuthentication {
-    pub fn new() -> Self {
-        Self
+    /// Create a verifier using the system resolver configuration
+    pub fn new(config: AuthenticationConfig) -> EmailResult<Self> {
+        let (resolver_config, mut options) = read_system_conf().map_err(|e| {
+            EmailError::AuthenticationError(format!("Failed to read resolver configuration: {}", e))
+        })?;
+        options.timeout = config.dns_timeout;
+        options.attempts = config.dns_attempts;
+
+        let resolver = Resolver::with_capacity(resolver_config, options, config.cache_capacity)
+            .map_err(|e| {
+                EmailError::AuthenticationError(format!("Failed to create resolver: {}", e))
+            })?;
+
+        Ok(Self { resolver, config })
+    }
+
+    pub fn from_env() -> EmailResult<Self> {
+        Self::new(AuthenticationConfig::from_env())
+    }
+
+    pub fn config(&self) -> &AuthenticationConfig {
+        &self.config
+    }
+
+    /// Authenticate a raw RFC 5322 message received from `client_ip`.
+    ///
+    /// `mail_from` is the SMTP envelope sender; for null-sender bounces the
+    /// HELO domain is used as the SPF identity. If the evaluation exceeds the
+    /// configured timeout every mechanism is reported as `TempError`.
+    pub async fn authenticate(
+        &self,
+        raw_message: &[u8],
+        client_ip: IpAddr,
+        helo_domain: &str,
+        mail_from: &str,
+    ) -> EmailResult<MailAuthResult> {
+        let message = AuthenticatedMessage::parse(raw_message).ok_or_else(|| {
+            EmailError::AuthenticationError("Message has no parsable headers".to_string())
+        })?;
+        let from_domain = domain_of(message.from()).to_lowercase();
+        let mail_from_domain = match mail_from.rsplit_once('@') {
+            Some((_, domain)) if !domain.is_empty() => domain.to_lowercase(),
+            _ => helo_domain.to_lowercase(),
+        };
+
+        let evaluation = self.evaluate(
+            &message,
+            client_ip,
+            helo_domain,
+            mail_from,
+            &mail_from_domain,
+        );
+        match tokio::time::timeout(self.config.verification_timeout, evaluation).await {
+            Ok(result) => Ok(result),
+            Err(_) => {
+                tracing::warn!(
+                    client_ip = %client_ip,
+                    from_domain = %from_domain,
+                    "Email authentication timed out"
+                );
+                Ok(MailAuthResult::temp_error(&from_domain, &mail_from_domain))
+            }
+        }
+    }
+
+    async fn evaluate(
+        &self,
+        message: &AuthenticatedMessage<'_>,
+        client_ip: IpAddr,
+        helo_domain: &str,
+        mail_from: &str,
+        mail_from_domain: &str,
+    ) -> MailAuthResult {
+        let sender = if mail_from.contains('@') {
+            mail_from.to_string()
+        } else {
+            format!("postmaster@{}", helo_domain)
+        };
+        let spf_output = self
+            .resolver
+            .verify_spf_sender(client_ip, helo_domain, &self.config.host_domain, &sender)
+            .await;
+        let dkim_output = self.resolver.verify_dkim(message).await;
+        let dmarc_output = self
+            .resolver
+            .verify_dmarc(message, &dkim_output, mail_from_domain, &spf_output)
+            .await;
+
+        build_result(
+            domain_of(message.from()),
+            &spf_output,
+            &dkim_output,
+            &dmarc_output,
+        )
+    }
+}
+
+fn build_result(
+    from_domain: &str,
+    spf_output: &SpfOutput,
+    dkim_output: &[DkimOutput<'_>],
+    dmarc_output: &DmarcOutput,
+) -> MailAuthResult {
+    let spf = SpfCheck {
+        status: spf_output.result().into(),
+        domain: spf_output.domain().to_lowercase(),
+    };
+    let dkim: Vec<DkimCheck> = dkim_output
+        .iter()
+        .map(|output| DkimCheck {
+            status: output.result().into(),
+            domain: output.signature().map(|s| s.domain().to_lowercase()),
+            selector: output.signature().map(|s| s.selector().to_string()),
+            error: match output.result() {
+                DkimResult::Neutral(e)
+                | DkimResult::Fail(e)
+                | DkimResult::PermError(e)
+                | DkimResult::TempError(e) => Some(e.to_string()),
+                DkimResult::Pass | DkimResult::None => None,
+            },
+        })
+        .collect();
+
+    let from_domain = from_domain.to_lowercase();
+    let has_record = dmarc_output.dmarc_record().is_some();
+    let (spf_aligned, dkim_aligned) = if has_record {
+        (
+            matches!(dmarc_output.spf_result(), DmarcResult::Pass),
+            matches!(dmarc_output.dkim_result(), DmarcResult::Pass),
+        )
+    } else {
+        // Without a DMARC record mail-auth skips the alignment check, so
+        // fall back to relaxed alignment against the From domain.
+        (
+            spf.status.is_pass() && relaxed_aligned(&spf.domain, &from_domain),
+            dkim.iter().any(|check| {
+                check.status.is_pass()
+                    && check
+                        .domain
+                        .as_deref()
+                        .is_some_and(|domain| relaxed_aligned(domain, &from_domain))
+            }),
+        )
+    };
+
+    let spf_status = AuthStatus::from(dmarc_output.spf_result());
+    let dkim_status = AuthStatus::from(dmarc_output.dkim_result());
+    let status = if spf_status.is_pass() || dkim_status.is_pass() {
+        AuthStatus::Pass
+    } else if [spf_status, dkim_status].contains(&AuthStatus::TempError) {
+        AuthStatus::TempError
+    } else if [spf_status, dkim_status].contains(&AuthStatus::PermError) {
+        AuthStatus::PermError
+    } else if has_record {
+        AuthStatus::Fail
+    } else {
+        AuthStatus::None
+    };
+
+    MailAuthResult {
+        spf,
+        dkim,
+        dmarc: DmarcCheck {
+            status,
+            domain: from_domain,
+            policy: dmarc_output.policy().into(),
+            spf_aligned,
+            dkim_aligned,
+        },
+        aligned: spf_aligned || dkim_aligned,
+    }
+}
+
+/// Relaxed identifier alignment: one domain equals or is a subdomain of the other
+fn relaxed_aligned(domain: &str, from_domain: &str) -> bool {
+    !domain.is_empty()
+        && !from_domain.is_empty()
+        && (domain.eq_ignore_ascii_case(from_domain)
+            || domain.ends_with(&format!(".{}", from_domain))
+            || from_domain.ends_with(&format!(".{}", domain)))
+}
+
+fn domain_of(address: &str) -> &str {
+    address.rsplit_once('@').map_or("", |(_, domain)| domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(dmarc_status: AuthStatus, policy: DmarcPolicy) -> MailAuthResult {
+        let mut result = MailAuthResult::temp_error("example.org", "example.org");
+        result.dmarc.status = dmarc_status;
+        result.dmarc.policy = policy;
+        result
+    }
+
+    #[test]
+    fn test_relaxed_alignment() {
+        assert!(relaxed_aligned("example.org", "example.org"));
+        assert!(relaxed_aligned("mail.example.org", "example.org"));
+        assert!(relaxed_aligned("example.org", "news.example.org"));
+        assert!(!relaxed_aligned("badexample.org", "example.org"));
+        assert!(!relaxed_aligned("", "example.org"));
+    }
+
+    #[test]
+    fn test_disposition_follows_policy_only_on_fail() {
+        assert_eq!(
+            result(AuthStatus::Fail, DmarcPolicy::Reject).disposition(),
+            Disposition::Reject
+        );
+        assert_eq!(
+            result(AuthStatus::Fail, DmarcPolicy::Quarantine).disposition(),
+            Disposition::Quarantine
+        );
+        assert_eq!(
+            result(AuthStatus::Fail, DmarcPolicy::None).disposition(),
+            Disposition::Accept
+        );
+        assert_eq!(
+            result(AuthStatus::Pass, DmarcPolicy::Reject).disposition(),
+            Disposition::Accept
+        );
+        assert_eq!(
+            result(AuthStatus::TempError, DmarcPolicy::Reject).disposition(),
+            Disposition::Accept
+        );
+    }
+
+    #[test]
+    fn test_authentication_results_header() {
+        let mut result = result(AuthStatus::Pass, DmarcPolicy::Reject);
+        result.spf.status = AuthStatus::Pass;
+        result.dkim = vec![
+            DkimCheck {
+                status: AuthStatus::Pass,
+                domain: Some("example.org".to_string()),
+                selector: Some("s1".to_string()),
+                error: None,
+            },
+            DkimCheck {
+                status: AuthStatus::Fail,
+                domain: Some("relay.net".to_string()),
+                selector: Some("s2".to_string()),
+                error: Some("body hash mismatch".to_string()),
+            },
+        ];
+
+        assert_eq!(
+            result.authentication_results("mx.rustcare.dev"),
+            "mx.rustcare.dev; spf=pass smtp.mailfrom=example.org; \
+             dkim=pass header.d=example.org; dkim=fail header.d=relay.net; \
+             dmarc=pass header.from=example.org"
+        );
+        assert!(result.dkim_pass());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unparsable_message() {
+        let auth = EmailAuthentication::new(AuthenticationConfig::default()).unwrap();
+        let err = auth
+            .authenticate(
+                b"",
+                "192.0.2.1".parse().unwrap(),
+                "mx.example.org",
+                "a@example.org",
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EmailError::AuthenticationError(_)));
     }
-}
\ No newline at end of file
+}
@@ -1,8 +1,581 @@
-// Email encryption
-pub struct EmailEncryption;
+// S/MIME signing and encryption of outbound email
+//
+// Every message is signed with the organization certificate (detached
+// multipart/signed, RFC 8551 §3.5.3) so recipients can verify it came from
+// us. When the recipient's certificate is known, the signed entity is then
+// enveloped to it (application/pkcs7-mime). Certificates and the signing key
+// are loaded from the secrets service.
+//
+// Recipients without a certificate receive signed-only mail over TLS; for
+// messages flagged as containing PHI, `UnencryptablePhiPolicy` decides
+// whether that fallback is allowed.
+use crate::error::{EmailError, EmailResult};
+use crate::queue::OutgoingEmail;
+use base64::Engine;
+use cms::builder::{
+    create_signing_time_attribute, ContentEncryptionAlgorithm, EnvelopedDataBuilder, KeyEncryptionInfo,
+    KeyTransRecipientInfoBuilder, SignedDataBuilder, SignerInfoBuilder,
+};
+use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+use cms::content_info::ContentInfo;
+use cms::enveloped_data::RecipientIdentifier;
+use cms::signed_data::{EncapsulatedContentInfo, SignerIdentifier};
+use rand::rngs::OsRng;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use secrets_service::{SecretProvider, SecretsError};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+use uuid::Uuid;
+use x509_cert::der::asn1::Any;
+use x509_cert::der::{DecodePem, Encode};
+use x509_cert::spki::AlgorithmIdentifierOwned;
+use x509_cert::Certificate;
+
+/// What to do with PHI mail to a recipient without a known certificate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnencryptablePhiPolicy {
+    /// Refuse to send
+    Block,
+    /// Send signed only, relying on TLS transport
+    SendOverTls,
+}
+
+/// Where S/MIME key material lives in the secrets service
+#[derive(Debug, Clone)]
+pub struct SmimeConfig {
+    /// PEM certificate of the organization
+    pub certificate_secret: String,
+    /// PEM private key (PKCS#8 or PKCS#1) matching the certificate
+    pub private_key_secret: String,
+    /// Recipient certificates are stored under this prefix plus the address
+    pub recipient_certificate_prefix: String,
+    pub phi_policy: UnencryptablePhiPolicy,
+}
+
+impl Default for SmimeConfig {
+    fn default() -> Self {
+        Self {
+            certificate_secret: "email/smime/certificate".to_string(),
+            private_key_secret: "email/smime/private-key".to_string(),
+            recipient_certificate_prefix: "email/smime/recipients/".to_string(),
+            phi_policy: UnencryptablePhiPolicy::Block,
+        }
+    }
+}
+
+impl SmimeConfig {
+    /// Load S/MIME configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let phi_policy = match std::env::var("EMAIL_SMIME_PHI_POLICY")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "tls" | "send_over_tls" => UnencryptablePhiPolicy::SendOverTls,
+            _ => UnencryptablePhiPolicy::Block,
+        };
+
+        Self {
+            certificate_secret: std::env::var("EMAIL_SMIME_CERTIFICATE_SECRET")
+                .unwrap_or(defaults.certificate_secret),
+            private_key_secret: std::env::var("EMAIL_SMIME_PRIVATE_KEY_SECRET")
+                .unwrap_or(defaults.private_key_secret),
+            recipient_certificate_prefix: std::env::var("EMAIL_SMIME_RECIPIENT_PREFIX")
+                .unwrap_or(defaults.recipient_certificate_prefix),
+            phi_policy,
+        }
+    }
+}
+
+/// MIME entity produced by `EmailEncryption::protect`
+#[derive(Debug, Clone)]
+pub struct SmimeEntity {
+    /// Content headers and body, ready to follow the message headers
+    pub content: Vec<u8>,
+    /// Whether the entity was enveloped to the recipient
+    pub encrypted: bool,
+}
+
+/// S/MIME signer and encryptor for outbound mail
+pub struct EmailEncryption {
+    certificate: Certificate,
+    private_key: RsaPrivateKey,
+    config: SmimeConfig,
+    secrets: Option<Arc<dyn SecretProvider>>,
+    recipients: RwLock<HashMap<String, Certificate>>,
+}
 
 impl EmailEncryption {
-    pub fn new() -> Self {
-        Self
+    /// Load the organization certificate and key from the secrets service
+    pub async fn load(secrets: Arc<dyn SecretProvider>, config: SmimeConfig) -> EmailResult<Self> {
+        let certificate = secrets
+            .get_secret(&config.certificate_secret)
+            .await
+            .map_err(|e| EmailError::EncryptionError(format!("Failed to load S/MIME certificate: {}", e)))?;
+        let private_key = secrets
+            .get_secret(&config.private_key_secret)
+            .await
+            .map_err(|e| EmailError::EncryptionError(format!("Failed to load S/MIME private key: {}", e)))?;
+
+        let mut encryption = Self::from_pem(&certificate.value, &private_key.value, config)?;
+        encryption.secrets = Some(secrets);
+        Ok(encryption)
+    }
+
+    /// Build from PEM material directly; recipient certificates must then be
+    /// added with `add_recipient_certificate`
+    pub fn from_pem(certificate_pem: &str, private_key_pem: &str, config: SmimeConfig) -> EmailResult<Self> {
+        let certificate = parse_certificate(certificate_pem)?;
+        let private_key = RsaPrivateKey::from_pkcs8_pem(private_key_pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(private_key_pem))
+            .map_err(|e| EmailError::EncryptionError(format!("Invalid S/MIME private key: {}", e)))?;
+
+        if public_key(&certificate)? != private_key.to_public_key() {
+            return Err(EmailError::EncryptionError(
+                "S/MIME private key does not match the certificate".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            certificate,
+            private_key,
+            config,
+            secrets: None,
+            recipients: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn config(&self) -> &SmimeConfig {
+        &self.config
+    }
+
+    /// Register a recipient's certificate
+    pub async fn add_recipient_certificate(&self, address: &str, certificate_pem: &str) -> EmailResult<()> {
+        let certificate = parse_certificate(certificate_pem)?;
+        self.recipients
+            .write()
+            .await
+            .insert(address.trim().to_lowercase(), certificate);
+        Ok(())
+    }
+
+    /// Currently valid certificate of a recipient, if one is known
+    pub async fn recipient_certificate(&self, address: &str) -> EmailResult<Option<Certificate>> {
+        let address = address.trim().to_lowercase();
+        let cached = self.recipients.read().await.get(&address).cloned();
+
+        let certificate = match (cached, &self.secrets) {
+            (Some(certificate), _) => certificate,
+            (None, Some(secrets)) => {
+                let key = format!("{}{}", self.config.recipient_certificate_prefix, address);
+                match secrets.get_secret(&key).await {
+                    Ok(secret) => {
+                        let certificate = parse_certificate(&secret.value)?;
+                        self.recipients.write().await.insert(address.clone(), certificate.clone());
+                        certificate
+                    }
+                    Err(SecretsError::NotFound(_)) => return Ok(None),
+                    Err(e) => {
+                        return Err(EmailError::EncryptionError(format!(
+                            "Failed to load recipient certificate: {}",
+                            e
+                        )))
+                    }
+                }
+            }
+            (None, None) => return Ok(None),
+        };
+
+        if !is_current(&certificate) {
+            warn!(email = %address, "Recipient S/MIME certificate is not valid at this time");
+            return Ok(None);
+        }
+        Ok(Some(certificate))
+    }
+
+    /// Sign and, when possible, encrypt the body of an outgoing email
+    pub async fn protect(&self, email: &OutgoingEmail) -> EmailResult<SmimeEntity> {
+        let signed = self.sign(&body_entity(&email.body, email.html))?;
+
+        match self.recipient_certificate(&email.to).await? {
+            Some(certificate) => Ok(SmimeEntity {
+                content: self.encrypt(&signed, &certificate)?,
+                encrypted: true,
+            }),
+            None if email.contains_phi && self.config.phi_policy == UnencryptablePhiPolicy::Block => {
+                Err(EmailError::ComplianceViolation(format!(
+                    "No S/MIME certificate for {}; PHI cannot be sent unencrypted",
+                    email.to
+                )))
+            }
+            None => {
+                if email.contains_phi {
+                    warn!(email = %email.to, "Sending PHI without S/MIME encryption, relying on TLS");
+                }
+                Ok(SmimeEntity {
+                    content: signed,
+                    encrypted: false,
+                })
+            }
+        }
+    }
+
+    /// Wrap a MIME entity in a detached multipart/signed entity
+    pub fn sign(&self, entity: &[u8]) -> EmailResult<Vec<u8>> {
+        let digest = Sha256::digest(entity);
+        let content = EncapsulatedContentInfo {
+            econtent_type: const_oid::db::rfc5911::ID_DATA,
+            econtent: None,
+        };
+        let digest_algorithm = AlgorithmIdentifierOwned {
+            oid: const_oid::db::rfc5912::ID_SHA_256,
+            parameters: None,
+        };
+        let signer = rsa::pkcs1v15::SigningKey::<Sha256>::new(self.private_key.clone());
+
+        let mut signer_info = SignerInfoBuilder::new(
+            &signer,
+            SignerIdentifier::IssuerAndSerialNumber(issuer_and_serial(&self.certificate)),
+            digest_algorithm.clone(),
+            &content,
+            Some(digest.as_slice()),
+        )
+        .map_err(cms_error)?;
+        signer_info
+            .add_signed_attribute(create_signing_time_attribute().map_err(cms_error)?)
+            .map_err(cms_error)?;
+
+        let signature = SignedDataBuilder::new(&content)
+            .add_digest_algorithm(digest_algorithm)
+            .map_err(cms_error)?
+            .add_certificate(CertificateChoices::Certificate(self.certificate.clone()))
+            .map_err(cms_error)?
+            .add_signer_info::<_, rsa::pkcs1v15::Signature>(signer_info)
+            .map_err(cms_error)?
+            .build()
+            .map_err(cms_error)?
+            .to_der()
+            .map_err(|e| EmailError::EncryptionError(format!("Failed to encode S/MIME signature: {}", e)))?;
+
+        let boundary = format!("smime-{}", Uuid::new_v4().simple());
+        let mut signed = format!(
+            "Content-Type: multipart/signed; protocol=\"application/pkcs7-signature\"; micalg=sha-256;\r\n \
+             boundary=\"{boundary}\"\r\n\r\n\
+             This is an S/MIME signed message\r\n\r\n--{boundary}\r\n"
+        )
+        .into_bytes();
+        // The CRLF before a boundary belongs to the delimiter, not to the signed entity
+        signed.extend_from_slice(entity);
+        signed.extend_from_slice(
+            format!(
+                "\r\n--{boundary}\r\n\
+                 Content-Type: application/pkcs7-signature; name=\"smime.p7s\"\r\n\
+                 Content-Transfer-Encoding: base64\r\n\
+                 Content-Disposition: attachment; filename=\"smime.p7s\"\r\n\r\n\
+                 {}\r\n--{boundary}--\r\n",
+                base64_lines(&signature)
+            )
+            .as_bytes(),
+        );
+        Ok(signed)
+    }
+
+    /// Envelope a MIME entity to the recipient and to our own certificate
+    pub fn encrypt(&self, entity: &[u8], recipient: &Certificate) -> EmailResult<Vec<u8>> {
+        let holders = [recipient, &self.certificate];
+        let mut rngs = [OsRng; 2];
+
+        let mut builder = EnvelopedDataBuilder::new(None, entity, ContentEncryptionAlgorithm::Aes256Cbc, None)
+            .map_err(cms_error)?;
+        for (holder, rng) in holders.iter().zip(rngs.iter_mut()) {
+            let recipient_info = KeyTransRecipientInfoBuilder::new(
+                RecipientIdentifier::IssuerAndSerialNumber(issuer_and_serial(holder)),
+                KeyEncryptionInfo::Rsa(public_key(holder)?),
+                rng,
+            )
+            .map_err(cms_error)?;
+            builder.add_recipient_info(recipient_info).map_err(cms_error)?;
+        }
+        let enveloped = builder.build_with_rng(&mut OsRng).map_err(cms_error)?;
+
+        let content_info = ContentInfo {
+            content_type: const_oid::db::rfc5911::ID_ENVELOPED_DATA,
+            content: Any::encode_from(&enveloped)
+                .map_err(|e| EmailError::EncryptionError(format!("Failed to encode S/MIME envelope: {}", e)))?,
+        };
+        let der = content_info
+            .to_der()
+            .map_err(|e| EmailError::EncryptionError(format!("Failed to encode S/MIME envelope: {}", e)))?;
+
+        debug!("Encrypted message with S/MIME");
+        Ok(format!(
+            "Content-Type: application/pkcs7-mime; smime-type=enveloped-data; name=\"smime.p7m\"\r\n\
+             Content-Transfer-Encoding: base64\r\n\
+             Content-Disposition: attachment; filename=\"smime.p7m\"\r\n\r\n\
+             {}\r\n",
+            base64_lines(&der)
+        )
+        .into_bytes())
+    }
+}
+
+/// Compose a complete message from top-level headers and a MIME entity
+pub fn compose_message(from_name: &str, from_email: &str, to: &str, subject: &str, entity: &[u8]) -> Vec<u8> {
+    let domain = from_email.rsplit('@').next().unwrap_or("localhost");
+    let mut message = format!(
+        "From: \"{}\" <{}>\r\n\
+         To: <{}>\r\n\
+         Subject: {}\r\n\
+         Date: {}\r\n\
+         Message-ID: <{}@{}>\r\n\
+         MIME-Version: 1.0\r\n",
+        from_name.replace(['"', '\\'], ""),
+        from_email,
+        to,
+        encode_header(subject),
+        chrono::Utc::now().to_rfc2822(),
+        Uuid::new_v4(),
+        domain
+    )
+    .into_bytes();
+    message.extend_from_slice(entity);
+    message
+}
+
+/// Body as a base64-encoded MIME entity, which survives transport unchanged
+/// so the signature stays valid
+fn body_entity(body: &str, html: bool) -> Vec<u8> {
+    format!(
+        "Content-Type: {}; charset=\"utf-8\"\r\nContent-Transfer-Encoding: base64\r\n\r\n{}",
+        if html { "text/html" } else { "text/plain" },
+        base64_lines(body.as_bytes())
+    )
+    .into_bytes()
+}
+
+/// Base64 wrapped at 76 characters per line
+fn base64_lines(data: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// RFC 2047 encoded-words for non-ASCII header values
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for ch in value.chars() {
+        // 45 bytes encode to 60 characters, keeping each word under 75
+        if chunk.len() + ch.len_utf8() > 45 {
+            words.push(format!("=?utf-8?B?{}?=", engine.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(ch);
+    }
+    if !chunk.is_empty() {
+        words.push(format!("=?utf-8?B?{}?=", engine.encode(&chunk)));
+    }
+    words.join("\r\n ")
+}
+
+fn parse_certificate(pem: &str) -> EmailResult<Certificate> {
+    Certificate::from_pem(pem.trim().as_bytes())
+        .map_err(|e| EmailError::EncryptionError(format!("Invalid S/MIME certificate: {}", e)))
+}
+
+fn public_key(certificate: &Certificate) -> EmailResult<RsaPublicKey> {
+    let spki = certificate
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(|e| EmailError::EncryptionError(format!("Invalid certificate public key: {}", e)))?;
+    RsaPublicKey::from_public_key_der(&spki)
+        .map_err(|e| EmailError::EncryptionError(format!("S/MIME requires an RSA certificate: {}", e)))
+}
+
+fn issuer_and_serial(certificate: &Certificate) -> IssuerAndSerialNumber {
+    IssuerAndSerialNumber {
+        issuer: certificate.tbs_certificate.issuer.clone(),
+        serial_number: certificate.tbs_certificate.serial_number.clone(),
+    }
+}
+
+fn is_current(certificate: &Certificate) -> bool {
+    let validity = &certificate.tbs_certificate.validity;
+    let now = std::time::SystemTime::now();
+    validity.not_before.to_system_time() <= now && now <= validity.not_after.to_system_time()
+}
+
+fn cms_error(error: cms::builder::Error) -> EmailError {
+    EmailError::EncryptionError(format!("S/MIME error: {:?}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+    use cms::enveloped_data::{EnvelopedData, RecipientInfo};
+    use cms::signed_data::SignedData;
+    use rsa::signature::Verifier;
+    use x509_cert::der::Decode;
+
+    const SENDER_CERT: &str = include_str!("../tests/fixtures/smime_sender_cert.pem");
+    const SENDER_KEY: &str = include_str!("../tests/fixtures/smime_sender_key.pem");
+    const RECIPIENT_CERT: &str = include_str!("../tests/fixtures/smime_recipient_cert.pem");
+    const RECIPIENT_KEY: &str = include_str!("../tests/fixtures/smime_recipient_key.pem");
+
+    fn encryption(policy: UnencryptablePhiPolicy) -> EmailEncryption {
+        EmailEncryption::from_pem(
+            SENDER_CERT,
+            SENDER_KEY,
+            SmimeConfig {
+                phi_policy: policy,
+                ..SmimeConfig::default()
+            },
+        )
+        .unwrap()
+    }
+
+    /// Content of the MIME part after the given header, up to the next boundary
+    fn part_after<'a>(message: &'a str, header: &str) -> &'a str {
+        let start = message.find(header).unwrap();
+        let body = &message[start..];
+        let body = &body[body.find("\r\n\r\n").unwrap() + 4..];
+        body.split("\r\n--").next().unwrap()
+    }
+
+    fn decode_base64(lines: &str) -> Vec<u8> {
+        base64::engine::general_purpose::STANDARD
+            .decode(lines.replace("\r\n", "").trim())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_signature_covers_entity() {
+        let encryption = encryption(UnencryptablePhiPolicy::Block);
+        let entity = body_entity("Your lab results are ready.", false);
+        let signed = String::from_utf8(encryption.sign(&entity).unwrap()).unwrap();
+        assert!(signed.starts_with("Content-Type: multipart/signed;"));
+
+        let boundary = signed.split("boundary=\"").nth(1).unwrap().split('"').next().unwrap();
+        let delimiter = format!("\r\n--{}\r\n", boundary);
+        let signed_part = signed.split(&delimiter).nth(1).unwrap();
+        assert_eq!(signed_part.as_bytes(), entity.as_slice());
+
+        let der = decode_base64(part_after(&signed, "Content-Type: application/pkcs7-signature"));
+        let content_info = ContentInfo::from_der(&der).unwrap();
+        let signed_data: SignedData = content_info.content.decode_as().unwrap();
+        let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+        let signed_attrs = signer_info.signed_attrs.as_ref().unwrap();
+
+        let digest = signed_attrs
+            .iter()
+            .find(|attr| attr.oid == const_oid::db::rfc5911::ID_MESSAGE_DIGEST)
+            .unwrap();
+        let digest = digest.values.get(0).unwrap().value().to_vec();
+        assert_eq!(digest, Sha256::digest(&entity).to_vec());
+
+        let verifying_key = rsa::pkcs1v15::VerifyingKey::<Sha256>::new(encryption.private_key.to_public_key());
+        let signature = rsa::pkcs1v15::Signature::try_from(signer_info.signature.as_bytes()).unwrap();
+        verifying_key.verify(&signed_attrs.to_der().unwrap(), &signature).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recipient_with_certificate_gets_encrypted_mail() {
+        let encryption = encryption(UnencryptablePhiPolicy::Block);
+        encryption
+            .add_recipient_certificate("Recipient@RustCare.local", RECIPIENT_CERT)
+            .await
+            .unwrap();
+
+        let email = OutgoingEmail::text("recipient@rustcare.local", "Results", "Hemoglobin 13.5 g/dL").with_phi();
+        let protected = encryption.protect(&email).await.unwrap();
+        assert!(protected.encrypted);
+
+        let entity = String::from_utf8(protected.content).unwrap();
+        assert!(entity.starts_with("Content-Type: application/pkcs7-mime; smime-type=enveloped-data"));
+        assert!(!entity.contains("Hemoglobin"));
+
+        // Decrypt as the recipient
+        let der = decode_base64(part_after(&entity, "application/pkcs7-mime"));
+        let enveloped: EnvelopedData = ContentInfo::from_der(&der).unwrap().content.decode_as().unwrap();
+        assert_eq!(enveloped.recip_infos.0.len(), 2);
+
+        let recipient_key = RsaPrivateKey::from_pkcs8_pem(RECIPIENT_KEY).unwrap();
+        let recipient_serial = parse_certificate(RECIPIENT_CERT).unwrap().tbs_certificate.serial_number;
+        let key = enveloped
+            .recip_infos
+            .0
+            .iter()
+            .find_map(|info| match info {
+                RecipientInfo::Ktri(ktri) => match &ktri.rid {
+                    RecipientIdentifier::IssuerAndSerialNumber(id) if id.serial_number == recipient_serial => {
+                        Some(recipient_key.decrypt(rsa::Pkcs1v15Encrypt, ktri.enc_key.as_bytes()).unwrap())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap();
+
+        let content = &enveloped.encrypted_content;
+        let iv = content.content_enc_alg.parameters.as_ref().unwrap().value().to_vec();
+        let mut ciphertext = content.encrypted_content.as_ref().unwrap().as_bytes().to_vec();
+        let plaintext = cbc::Decryptor::<aes::Aes256>::new_from_slices(&key, &iv)
+            .unwrap()
+            .decrypt_padded_mut::<Pkcs7>(&mut ciphertext)
+            .unwrap();
+
+        let inner = String::from_utf8(plaintext.to_vec()).unwrap();
+        assert!(inner.starts_with("Content-Type: multipart/signed;"));
+        let body = decode_base64(part_after(&inner, "text/plain"));
+        assert_eq!(body, b"Hemoglobin 13.5 g/dL");
+    }
+
+    #[tokio::test]
+    async fn test_phi_without_certificate_follows_policy() {
+        let email = OutgoingEmail::text("unknown@example.com", "Results", "Hemoglobin 13.5 g/dL").with_phi();
+
+        let blocked = encryption(UnencryptablePhiPolicy::Block).protect(&email).await;
+        assert!(matches!(blocked, Err(EmailError::ComplianceViolation(_))));
+
+        let sent = encryption(UnencryptablePhiPolicy::SendOverTls)
+            .protect(&email)
+            .await
+            .unwrap();
+        assert!(!sent.encrypted);
+
+        let not_phi = OutgoingEmail::text("unknown@example.com", "Welcome", "Hello");
+        let sent = encryption(UnencryptablePhiPolicy::Block).protect(&not_phi).await.unwrap();
+        assert!(!sent.encrypted);
+    }
+
+    #[test]
+    fn test_mismatched_key_is_rejected() {
+        let result = EmailEncryption::from_pem(SENDER_CERT, RECIPIENT_KEY, SmimeConfig::default());
+        assert!(matches!(result, Err(EmailError::EncryptionError(_))));
+    }
+
+    #[test]
+    fn test_non_ascii_subject_is_encoded() {
+        assert_eq!(encode_header("Results"), "Results");
+        let encoded = encode_header("Résultats de laboratoire");
+        assert!(encoded.starts_with("=?utf-8?B?"));
+        assert!(encoded.is_ascii());
     }
-}
\ No newline at end of file
+}
@@ -17,6 +17,18 @@ pub enum EmailError {
     #[error("Compliance violation: {0}")]
     ComplianceViolation(String),
     
+    #[error("Queue error: {0}")]
+    QueueError(String),
+    
+    #[error("Recipient suppressed: {0}")]
+    RecipientSuppressed(String),
+    
+    #[error("Webhook verification failed: {0}")]
+    WebhookVerificationFailed(String),
+    
+    #[error("Invalid webhook payload: {0}")]
+    InvalidWebhookPayload(String),
+    
     #[error("Internal error: {0}")]
     InternalError(#[from] anyhow::Error),
 }
@@ -1,8 +1,578 @@
-// Email queue
-pub struct EmailQueue;
+// Persistent email send queue
+//
+// Messages are stored in `email_queue` and delivered by workers polling the
+// table. A worker claims messages with `FOR UPDATE SKIP LOCKED`, so several
+// workers, in one process or many, never deliver the same message. A claim
+// is a lease: if the worker dies, the message becomes claimable again once
+// `locked_until` passes. Workers claim one message at a time, just before
+// sending it, so a slow send never eats into the lease of the next. Failed sends are retried with exponential backoff
+// until `max_attempts`, then moved to `email_dead_letters`.
+use crate::error::{EmailError, EmailResult};
+use crate::service::EmailService;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::Duration;
+use telemetry::{MetricKind, MetricsCollector};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// Pending messages per priority
+pub const QUEUE_DEPTH_METRIC: &str = "email_queue_depth";
+/// Age of the oldest pending message per priority
+pub const QUEUE_OLDEST_AGE_METRIC: &str = "email_queue_oldest_age_seconds";
+/// Messages in the dead-letter table
+pub const DEAD_LETTERS_METRIC: &str = "email_queue_dead_letters";
+/// Delivery attempts by outcome (sent, retried, dead_lettered)
+pub const DELIVERIES_METRIC: &str = "email_queue_deliveries_total";
+
+/// Delivery priority; higher priorities are claimed first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailPriority {
+    /// Newsletters and other mass mail
+    Bulk,
+    #[default]
+    Normal,
+    High,
+    /// Password resets, credentials, security notices
+    Critical,
+}
+
+impl EmailPriority {
+    pub const ALL: [EmailPriority; 4] = [
+        EmailPriority::Bulk,
+        EmailPriority::Normal,
+        EmailPriority::High,
+        EmailPriority::Critical,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailPriority::Bulk => "bulk",
+            EmailPriority::Normal => "normal",
+            EmailPriority::High => "high",
+            EmailPriority::Critical => "critical",
+        }
+    }
+
+    fn as_i16(self) -> i16 {
+        match self {
+            EmailPriority::Bulk => 0,
+            EmailPriority::Normal => 1,
+            EmailPriority::High => 2,
+            EmailPriority::Critical => 3,
+        }
+    }
+
+    fn from_i16(value: i16) -> Self {
+        match value {
+            i16::MIN..=0 => EmailPriority::Bulk,
+            1 => EmailPriority::Normal,
+            2 => EmailPriority::High,
+            _ => EmailPriority::Critical,
+        }
+    }
+}
+
+/// Message to deliver
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub html: bool,
+    pub priority: EmailPriority,
+    /// Subject to `UnencryptablePhiPolicy` when it cannot be S/MIME encrypted
+    #[serde(default)]
+    pub contains_phi: bool,
+}
+
+impl OutgoingEmail {
+    /// Plain text message with normal priority
+    pub fn text(to: impl Into<String>, subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            to: to.into(),
+            subject: subject.into(),
+            body: body.into(),
+            html: false,
+            priority: EmailPriority::Normal,
+            contains_phi: false,
+        }
+    }
+
+    /// HTML message with normal priority
+    pub fn html(to: impl Into<String>, subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            html: true,
+            ..Self::text(to, subject, body)
+        }
+    }
+
+    pub fn with_priority(mut self, priority: EmailPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Mark the message as containing protected health information
+    pub fn with_phi(mut self) -> Self {
+        self.contains_phi = true;
+        self
+    }
+}
+
+/// Queue and worker settings
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    /// Messages delivered per poll, each claimed as it is sent
+    pub batch_size: i64,
+    /// Wait between polls when the queue is empty
+    pub poll_interval: Duration,
+    /// How long a claim is held; must exceed the time to send one message
+    pub lease: Duration,
+    /// Attempts before a message is dead-lettered
+    pub max_attempts: i32,
+    /// Delay before the first retry, doubled on every further attempt
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 10,
+            poll_interval: Duration::from_secs(5),
+            lease: Duration::from_secs(120),
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl QueueConfig {
+    /// Delay before retrying after the given (1-based) failed attempt
+    pub fn backoff(&self, attempt: i32) -> Duration {
+        let exponent = u32::try_from(attempt.saturating_sub(1)).unwrap_or(0).min(31);
+        self.initial_backoff
+            .checked_mul(2u32.saturating_pow(exponent))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/// A claimed message
+#[derive(Debug, Clone)]
+pub struct QueuedEmail {
+    pub id: Uuid,
+    pub email: OutgoingEmail,
+    /// Attempts including the current one
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Backlog of one priority level
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityStats {
+    pub priority: EmailPriority,
+    pub depth: i64,
+    pub oldest_age_seconds: f64,
+}
+
+/// Queue backlog snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueStats {
+    pub priorities: Vec<PriorityStats>,
+    pub dead_letters: i64,
+}
+
+impl QueueStats {
+    pub fn depth(&self) -> i64 {
+        self.priorities.iter().map(|stats| stats.depth).sum()
+    }
+}
+
+/// Database-backed email queue
+pub struct EmailQueue {
+    pool: PgPool,
+    config: QueueConfig,
+    worker_id: String,
+}
 
 impl EmailQueue {
-    pub fn new() -> Self {
-        Self
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            config: QueueConfig::default(),
+            worker_id: format!(
+                "{}-{}",
+                hostname::get()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|_| "worker".to_string()),
+                Uuid::new_v4()
+            ),
+        }
+    }
+
+    pub fn with_config(mut self, config: QueueConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &QueueConfig {
+        &self.config
+    }
+
+    /// Store a message for delivery
+    pub async fn enqueue(&self, email: &OutgoingEmail) -> EmailResult<Uuid> {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO email_queue (id, priority, recipient, subject, body, is_html, contains_phi, max_attempts)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(id)
+        .bind(email.priority.as_i16())
+        .bind(&email.to)
+        .bind(&email.subject)
+        .bind(&email.body)
+        .bind(email.html)
+        .bind(email.contains_phi)
+        .bind(self.config.max_attempts)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailError::QueueError(format!("Failed to enqueue email: {}", e)))?;
+
+        debug!(queue_id = %id, priority = email.priority.as_str(), "Email queued");
+        Ok(id)
+    }
+
+    /// Claim up to `limit` due messages, highest priority first
+    ///
+    /// Rows locked by another worker's claim are skipped, and messages whose
+    /// lease expired are taken over.
+    pub async fn claim(&self, limit: i64) -> EmailResult<Vec<QueuedEmail>> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE email_queue
+            SET status = 'processing',
+                locked_by = $1,
+                locked_until = NOW() + make_interval(secs => $2),
+                attempts = attempts + 1,
+                updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM email_queue
+                WHERE (status = 'pending' AND available_at <= NOW())
+                   OR (status = 'processing' AND locked_until < NOW())
+                ORDER BY priority DESC, available_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, priority, recipient, subject, body, is_html, contains_phi, attempts, max_attempts, created_at
+            "#,
+        )
+        .bind(&self.worker_id)
+        .bind(self.config.lease.as_secs_f64())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailError::QueueError(format!("Failed to claim emails: {}", e)))?;
+
+        let mut claimed = rows
+            .iter()
+            .map(|row| {
+                Ok(QueuedEmail {
+                    id: row.try_get("id")?,
+                    email: OutgoingEmail {
+                        to: row.try_get("recipient")?,
+                        subject: row.try_get("subject")?,
+                        body: row.try_get("body")?,
+                        html: row.try_get("is_html")?,
+                        priority: EmailPriority::from_i16(row.try_get("priority")?),
+                        contains_phi: row.try_get("contains_phi")?,
+                    },
+                    attempts: row.try_get("attempts")?,
+                    max_attempts: row.try_get("max_attempts")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            })
+            .collect::<Result<Vec<_>, sqlx::Error>>()
+            .map_err(|e| EmailError::QueueError(format!("Failed to read claimed emails: {}", e)))?;
+
+        // RETURNING does not preserve the subquery order
+        claimed.sort_by(|a, b| b.email.priority.cmp(&a.email.priority).then(a.created_at.cmp(&b.created_at)));
+        Ok(claimed)
+    }
+
+    /// Remove a delivered message
+    pub async fn complete(&self, id: Uuid) -> EmailResult<()> {
+        sqlx::query("DELETE FROM email_queue WHERE id = $1 AND locked_by = $2")
+            .bind(id)
+            .bind(&self.worker_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EmailError::QueueError(format!("Failed to complete email {}: {}", id, e)))?;
+        Ok(())
+    }
+
+    /// Schedule a retry, or dead-letter the message if it cannot succeed
+    pub async fn fail(&self, message: &QueuedEmail, error: &EmailError) -> EmailResult<()> {
+        if message.attempts >= message.max_attempts || is_permanent(error) {
+            return self.dead_letter(message, error).await;
+        }
+
+        let delay = self.config.backoff(message.attempts);
+        sqlx::query(
+            r#"
+            UPDATE email_queue
+            SET status = 'pending',
+                available_at = NOW() + make_interval(secs => $3),
+                locked_by = NULL,
+                locked_until = NULL,
+                last_error = $4,
+                updated_at = NOW()
+            WHERE id = $1 AND locked_by = $2
+            "#,
+        )
+        .bind(message.id)
+        .bind(&self.worker_id)
+        .bind(delay.as_secs_f64())
+        .bind(error.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailError::QueueError(format!("Failed to reschedule email {}: {}", message.id, e)))?;
+
+        MetricsCollector::global()
+            .counter(DELIVERIES_METRIC)
+            .with_label("outcome", "retried")
+            .increment();
+        warn!(
+            queue_id = %message.id,
+            attempt = message.attempts,
+            retry_in_secs = delay.as_secs(),
+            error = %error,
+            "Email delivery failed, retrying"
+        );
+        Ok(())
+    }
+
+    async fn dead_letter(&self, message: &QueuedEmail, error: &EmailError) -> EmailResult<()> {
+        sqlx::query(
+            r#"
+            WITH moved AS (
+                DELETE FROM email_queue WHERE id = $1 AND locked_by = $2 RETURNING *
+            )
+            INSERT INTO email_dead_letters
+                (id, priority, recipient, subject, body, is_html, contains_phi, attempts, last_error, created_at)
+            SELECT id, priority, recipient, subject, body, is_html, contains_phi, attempts, $3, created_at
+            FROM moved
+            "#,
+        )
+        .bind(message.id)
+        .bind(&self.worker_id)
+        .bind(error.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailError::QueueError(format!("Failed to dead-letter email {}: {}", message.id, e)))?;
+
+        MetricsCollector::global()
+            .counter(DELIVERIES_METRIC)
+            .with_label("outcome", "dead_lettered")
+            .increment();
+        error!(
+            queue_id = %message.id,
+            attempts = message.attempts,
+            error = %error,
+            "Email delivery failed permanently, moved to dead letters"
+        );
+        Ok(())
+    }
+
+    /// Depth and age of the backlog per priority
+    pub async fn stats(&self) -> EmailResult<QueueStats> {
+        let rows = sqlx::query(
+            r#"
+            SELECT priority,
+                   COUNT(*) AS depth,
+                   EXTRACT(EPOCH FROM NOW() - MIN(created_at))::FLOAT8 AS oldest_age_seconds
+            FROM email_queue
+            GROUP BY priority
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailError::QueueError(format!("Failed to read queue stats: {}", e)))?;
+
+        let dead_letters: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM email_dead_letters")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| EmailError::QueueError(format!("Failed to read queue stats: {}", e)))?;
+
+        let mut priorities: Vec<PriorityStats> = EmailPriority::ALL
+            .iter()
+            .map(|&priority| PriorityStats {
+                priority,
+                depth: 0,
+                oldest_age_seconds: 0.0,
+            })
+            .collect();
+        for row in &rows {
+            let priority = EmailPriority::from_i16(
+                row.try_get("priority")
+                    .map_err(|e| EmailError::QueueError(format!("Failed to read queue stats: {}", e)))?,
+            );
+            if let Some(stats) = priorities.iter_mut().find(|stats| stats.priority == priority) {
+                stats.depth = row.try_get("depth").unwrap_or_default();
+                stats.oldest_age_seconds = row.try_get("oldest_age_seconds").unwrap_or_default();
+            }
+        }
+
+        Ok(QueueStats {
+            priorities,
+            dead_letters,
+        })
+    }
+
+    /// Publish backlog gauges to the telemetry collector
+    pub async fn record_metrics(&self) -> EmailResult<QueueStats> {
+        let stats = self.stats().await?;
+        let metrics = MetricsCollector::global();
+        for priority in &stats.priorities {
+            metrics
+                .gauge(QUEUE_DEPTH_METRIC)
+                .with_label("priority", priority.priority.as_str())
+                .set(priority.depth as f64);
+            metrics
+                .gauge(QUEUE_OLDEST_AGE_METRIC)
+                .with_label("priority", priority.priority.as_str())
+                .set(priority.oldest_age_seconds);
+        }
+        metrics.gauge(DEAD_LETTERS_METRIC).set(stats.dead_letters as f64);
+        Ok(stats)
+    }
+
+    /// Deliver up to a batch of messages; returns the number claimed
+    ///
+    /// Each message is claimed only when it is about to be sent, so its lease
+    /// covers one send rather than the whole batch.
+    pub async fn process_batch(&self, service: &EmailService) -> EmailResult<usize> {
+        let mut claimed = 0;
+        while claimed < self.config.batch_size {
+            let Some(message) = self.claim(1).await?.pop() else {
+                break;
+            };
+            claimed += 1;
+
+            match service.deliver(&message.email).await {
+                Ok(message_id) => {
+                    MetricsCollector::global()
+                        .counter(DELIVERIES_METRIC)
+                        .with_label("outcome", "sent")
+                        .increment();
+                    debug!(queue_id = %message.id, message_id = %message_id, "Queued email delivered");
+                    // The email is out; a failure here only risks a duplicate
+                    // once the lease expires, so carry on with the batch
+                    if let Err(e) = self.complete(message.id).await {
+                        error!(queue_id = %message.id, error = %e, "Failed to remove delivered email from queue");
+                    }
+                }
+                Err(e) => {
+                    if let Err(queue_error) = self.fail(&message, &e).await {
+                        error!(queue_id = %message.id, error = %queue_error, "Failed to record email delivery failure");
+                    }
+                }
+            }
+        }
+
+        Ok(usize::try_from(claimed).unwrap_or_default())
+    }
+
+    /// Deliver queued messages in the background until the task is aborted
+    ///
+    /// Several workers may run against the same table; each claims its own
+    /// messages.
+    pub fn spawn_worker(self: Arc<Self>, service: Arc<EmailService>) -> JoinHandle<()> {
+        describe_queue_metrics();
+        info!(worker_id = %self.worker_id, "Starting email queue worker");
+
+        tokio::spawn(async move {
+            loop {
+                let claimed = match self.process_batch(&service).await {
+                    Ok(claimed) => claimed,
+                    Err(e) => {
+                        error!(error = %e, "Email queue worker failed to process batch");
+                        0
+                    }
+                };
+                if let Err(e) = self.record_metrics().await {
+                    warn!(error = %e, "Failed to record email queue metrics");
+                }
+
+                // Keep draining while there is work; poll otherwise
+                if claimed < usize::try_from(self.config.batch_size).unwrap_or(usize::MAX) {
+                    tokio::time::sleep(self.config.poll_interval).await;
+                }
+            }
+        })
+    }
+}
+
+/// Register help text for the queue metrics
+pub fn describe_queue_metrics() {
+    let metrics = MetricsCollector::global();
+    metrics.describe(QUEUE_DEPTH_METRIC, MetricKind::Gauge, "Emails waiting for delivery");
+    metrics.describe(
+        QUEUE_OLDEST_AGE_METRIC,
+        MetricKind::Gauge,
+        "Age in seconds of the oldest email waiting for delivery",
+    );
+    metrics.describe(DEAD_LETTERS_METRIC, MetricKind::Gauge, "Emails that failed permanently");
+    metrics.describe(DELIVERIES_METRIC, MetricKind::Counter, "Queued email delivery attempts by outcome");
+}
+
+/// Errors that retrying cannot fix
+fn is_permanent(error: &EmailError) -> bool {
+    matches!(
+        error,
+        EmailError::RecipientSuppressed(_) | EmailError::ComplianceViolation(_) | EmailError::TemplateError(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let config = QueueConfig {
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(300),
+            ..QueueConfig::default()
+        };
+
+        assert_eq!(config.backoff(1), Duration::from_secs(30));
+        assert_eq!(config.backoff(2), Duration::from_secs(60));
+        assert_eq!(config.backoff(4), Duration::from_secs(240));
+        assert_eq!(config.backoff(5), Duration::from_secs(300));
+        assert_eq!(config.backoff(100), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_priority_round_trip_and_order() {
+        for priority in EmailPriority::ALL {
+            assert_eq!(EmailPriority::from_i16(priority.as_i16()), priority);
+        }
+        assert!(EmailPriority::Critical > EmailPriority::Bulk);
+        assert!(EmailPriority::Critical.as_i16() > EmailPriority::Bulk.as_i16());
+    }
+
+    #[test]
+    fn test_permanent_errors_skip_retries() {
+        assert!(is_permanent(&EmailError::RecipientSuppressed("a@example.com".to_string())));
+        assert!(!is_permanent(&EmailError::SendFailed("connection reset".to_string())));
     }
-}
\ No newline at end of file
+}
@@ -1,9 +1,14 @@
 // Email service implementation with multiple provider support
+use crate::encryption::{compose_message, EmailEncryption};
 use crate::error::{EmailError, EmailResult};
+use crate::queue::{EmailPriority, EmailQueue, OutgoingEmail};
+use crate::tracking::EmailTracking;
 use mail_builder::MessageBuilder;
+use mail_send::smtp::message::{IntoMessage, Message};
 use mail_send::SmtpClientBuilder;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, info};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 /// Email provider types
@@ -187,18 +192,123 @@ impl EmailConfig {
     }
 }
 
+/// mail-send and reqwest enable different rustls crypto backends, so rustls
+/// cannot choose a process-wide default by itself; pick ring unless the
+/// application already installed one
+fn install_crypto_provider() {
+    if rustls::crypto::CryptoProvider::get_default().is_none() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+    }
+}
+
 /// Email service for sending transactional emails via Stalwart
 pub struct EmailService {
     config: EmailConfig,
+    tracking: Option<Arc<EmailTracking>>,
+    queue: Option<Arc<EmailQueue>>,
+    encryption: Option<Arc<EmailEncryption>>,
 }
 
 impl EmailService {
     /// Create a new email service
     pub fn new(config: EmailConfig) -> EmailResult<Self> {
+        install_crypto_provider();
         if !config.email_enabled {
             info!("Email service disabled by configuration");
         }
-        Ok(Self { config })
+        Ok(Self {
+            config,
+            tracking: None,
+            queue: None,
+            encryption: None,
+        })
+    }
+
+    /// Track sent messages and skip suppressed recipients
+    pub fn with_tracking(mut self, tracking: Arc<EmailTracking>) -> Self {
+        self.tracking = Some(tracking);
+        self
+    }
+
+    /// Queue delivery through the persistent send queue
+    ///
+    /// A queue worker (`EmailQueue::spawn_worker`) must be running against
+    /// the same database for queued email to go out.
+    pub fn with_queue(mut self, queue: Arc<EmailQueue>) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Sign outgoing mail with S/MIME and encrypt it to recipients with a
+    /// known certificate
+    pub fn with_encryption(mut self, encryption: Arc<EmailEncryption>) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Send an email, through the queue when one is configured
+    ///
+    /// Returns the queue id of a queued message, or the message id of one
+    /// sent directly.
+    pub async fn send(&self, email: OutgoingEmail) -> EmailResult<String> {
+        match &self.queue {
+            Some(queue) if self.config.email_enabled => Ok(queue.enqueue(&email).await?.to_string()),
+            _ => self.deliver(&email).await,
+        }
+    }
+
+    /// Send an email immediately through the configured provider
+    ///
+    /// Suppressed recipients are refused and, with S/MIME configured, the
+    /// message is signed and encrypted where possible.
+    pub async fn deliver(&self, email: &OutgoingEmail) -> EmailResult<String> {
+        if !self.config.email_enabled {
+            debug!("Email disabled, skipping send to: {}", email.to);
+            return Ok(format!("disabled-{}", Uuid::new_v4()));
+        }
+
+        if let Some(tracking) = &self.tracking {
+            if tracking.is_suppressed(&email.to).await? {
+                warn!(email = %email.to, "Recipient is suppressed, not sending");
+                return Err(EmailError::RecipientSuppressed(email.to.clone()));
+            }
+        }
+
+        let message_id = match &self.encryption {
+            Some(encryption) => {
+                let entity = encryption.protect(email).await?;
+                let raw = compose_message(
+                    &self.config.from_name,
+                    &self.config.from_email,
+                    &email.to,
+                    &email.subject,
+                    &entity.content,
+                );
+                debug!(encrypted = entity.encrypted, "Sending S/MIME message");
+                self.send_message(Message::new(self.config.from_email.as_str(), [email.to.as_str()], raw))
+                    .await?
+            }
+            None => {
+                let message = MessageBuilder::new()
+                    .from((
+                        self.config.from_name.as_str(),
+                        self.config.from_email.as_str(),
+                    ))
+                    .to(email.to.as_str())
+                    .subject(email.subject.as_str());
+                let message = if email.html {
+                    message.html_body(email.body.as_str())
+                } else {
+                    message.text_body(email.body.as_str())
+                };
+                self.send_message(message).await?
+            }
+        };
+
+        if let Some(tracking) = &self.tracking {
+            tracking.record_sent(&message_id, &email.to).await?;
+        }
+        Ok(message_id)
     }
 
     /// Send a plain text email
@@ -208,21 +318,7 @@ impl EmailService {
         subject: &str,
         body: &str,
     ) -> EmailResult<String> {
-        if !self.config.email_enabled {
-            debug!("Email disabled, skipping send to: {}", to);
-            return Ok(format!("disabled-{}", Uuid::new_v4()));
-        }
-
-        let message = MessageBuilder::new()
-            .from((
-                self.config.from_name.as_str(),
-                self.config.from_email.as_str(),
-            ))
-            .to(to)
-            .subject(subject)
-            .text_body(body);
-
-        self.send_message(message).await
+        self.deliver(&OutgoingEmail::text(to, subject, body)).await
     }
 
     /// Send an HTML email
@@ -232,21 +328,7 @@ impl EmailService {
         subject: &str,
         html_body: &str,
     ) -> EmailResult<String> {
-        if !self.config.email_enabled {
-            debug!("Email disabled, skipping send to: {}", to);
-            return Ok(format!("disabled-{}", Uuid::new_v4()));
-        }
-
-        let message = MessageBuilder::new()
-            .from((
-                self.config.from_name.as_str(),
-                self.config.from_email.as_str(),
-            ))
-            .to(to)
-            .subject(subject)
-            .html_body(html_body);
-
-        self.send_message(message).await
+        self.deliver(&OutgoingEmail::html(to, subject, html_body)).await
     }
 
     /// Send organization welcome email
@@ -315,7 +397,7 @@ impl EmailService {
             "Sending organization welcome email"
         );
 
-        self.send_html_email(to_email, &subject, &body).await
+        self.send(OutgoingEmail::html(to_email, subject, body)).await
     }
 
     /// Send email verification with DNS records
@@ -392,7 +474,7 @@ impl EmailService {
             "Sending email domain verification instructions"
         );
 
-        self.send_html_email(to_email, &subject, &body).await
+        self.send(OutgoingEmail::html(to_email, subject, body)).await
     }
 
     /// Send user account credentials (hospital onboarding)
@@ -479,7 +561,8 @@ impl EmailService {
             "Sending user credentials email"
         );
 
-        self.send_html_email(to_email, &subject, &body).await
+        self.send(OutgoingEmail::html(to_email, subject, body).with_priority(EmailPriority::Critical))
+            .await
     }
 
     /// Test email configuration by checking connection without sending
@@ -556,7 +639,7 @@ impl EmailService {
     }
 
     /// Internal method to send a constructed message using configured provider
-    async fn send_message(&self, message: MessageBuilder<'_>) -> EmailResult<String> {
+    async fn send_message<'x>(&self, message: impl IntoMessage<'x>) -> EmailResult<String> {
         match &self.config.provider {
             EmailProvider::Smtp { host, port, username, password, use_tls } => {
                 // Build SMTP client
@@ -1,8 +1,443 @@
-// Email templates
-pub struct EmailTemplateEngine;
+// Email templates (sandboxed Handlebars rendering)
+use std::collections::{HashMap, HashSet};
+
+use handlebars::template::{HelperTemplate, Parameter, TemplateElement};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, Path, RenderContext, RenderError,
+    Template,
+};
+use serde::Serialize;
+
+use crate::error::{EmailError, EmailResult};
+
+/// Helpers templates may call. Everything else, including the built-in
+/// `lookup` (dynamic access to any context key) and `log` (writes context
+/// values to the application log), is rejected at registration.
+pub const ALLOWED_HELPERS: &[&str] = &[
+    "if", "unless", "each", "with", "eq", "ne", "gt", "gte", "lt", "lte", "and", "or", "not", "len",
+];
+
+/// Built-in helpers that are replaced with a refusing implementation
+const DENIED_HELPERS: &[&str] = &["lookup", "log"];
+
+/// Block helpers that change the context for their body
+const SCOPING_HELPERS: &[&str] = &["each", "with"];
+
+/// What to do when a template references a variable missing from the data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingVariablePolicy {
+    /// Fail the render (Handlebars strict mode)
+    #[default]
+    Error,
+    /// Render the missing value as an empty string
+    RenderEmpty,
+}
+
+/// Registered template together with its declared variables
+#[derive(Debug, Clone)]
+struct RegisteredTemplate {
+    variables: Vec<String>,
+}
+
+/// Handlebars engine restricted to an allow-listed set of helpers, with
+/// every template checked against its declared variables on registration.
+pub struct EmailTemplateEngine {
+    registry: Handlebars<'static>,
+    templates: HashMap<String, RegisteredTemplate>,
+    missing_variables: MissingVariablePolicy,
+}
 
 impl EmailTemplateEngine {
     pub fn new() -> Self {
-        Self
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(true);
+        registry.set_dev_mode(false);
+        for name in DENIED_HELPERS {
+            registry.register_helper(name, Box::new(DeniedHelper));
+        }
+
+        Self {
+            registry,
+            templates: HashMap::new(),
+            missing_variables: MissingVariablePolicy::default(),
+        }
+    }
+
+    pub fn with_missing_variable_policy(mut self, policy: MissingVariablePolicy) -> Self {
+        self.registry
+            .set_strict_mode(policy == MissingVariablePolicy::Error);
+        self.missing_variables = policy;
+        self
+    }
+
+    pub fn missing_variable_policy(&self) -> MissingVariablePolicy {
+        self.missing_variables
+    }
+
+    /// Validate `source` against `variables` and register it under `name`
+    pub fn register_template(
+        &mut self,
+        name: &str,
+        source: &str,
+        variables: &[&str],
+    ) -> EmailResult<()> {
+        validate_template(source, variables)
+            .map_err(|e| EmailError::TemplateError(format!("Template '{}': {}", name, e)))?;
+
+        self.registry
+            .register_template_string(name, source)
+            .map_err(|e| EmailError::TemplateError(format!("Template '{}': {}", name, e)))?;
+        self.templates.insert(
+            name.to_string(),
+            RegisteredTemplate {
+                variables: variables.iter().map(|v| v.to_string()).collect(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn has_template(&self, name: &str) -> bool {
+        self.templates.contains_key(name)
+    }
+
+    /// Variables declared when `name` was registered
+    pub fn variables(&self, name: &str) -> Option<&[String]> {
+        self.templates
+            .get(name)
+            .map(|template| template.variables.as_slice())
+    }
+
+    pub fn render<T: Serialize>(&self, name: &str, data: &T) -> EmailResult<String> {
+        if !self.has_template(name) {
+            return Err(EmailError::TemplateError(format!(
+                "Template '{}' is not registered",
+                name
+            )));
+        }
+
+        self.registry
+            .render(name, data)
+            .map_err(|e| EmailError::TemplateError(format!("Template '{}': {}", name, e)))
+    }
+}
+
+impl Default for EmailTemplateEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check a template before it is used.
+///
+/// The source must compile, may only call helpers from `ALLOWED_HELPERS`,
+/// may not use partials, decorators or `@root`, and every variable read from
+/// the top-level context must be in `expected_vars`. A dotted declaration
+/// such as `patient.name` also permits `patient` itself (e.g. in `#if`);
+/// declaring `patient` permits any field below it. Paths inside `#each` and
+/// `#with` bodies refer to the inner context and are not checked, except
+/// where `../` climbs back to the top level.
+pub fn validate_template(source: &str, expected_vars: &[&str]) -> EmailResult<()> {
+    let template = Template::compile(source)
+        .map_err(|e| EmailError::TemplateError(format!("Invalid template: {}", e)))?;
+
+    let mut validator = TemplateValidator {
+        expected: expected_vars.iter().map(|v| v.trim()).collect(),
+        problems: Vec::new(),
+    };
+    validator.check_template(&template, 0);
+
+    if validator.problems.is_empty() {
+        Ok(())
+    } else {
+        Err(EmailError::TemplateError(validator.problems.join("; ")))
+    }
+}
+
+struct TemplateValidator<'a> {
+    expected: HashSet<&'a str>,
+    problems: Vec<String>,
+}
+
+impl TemplateValidator<'_> {
+    fn check_template(&mut self, template: &Template, depth: usize) {
+        for element in &template.elements {
+            self.check_element(element, depth);
+        }
+    }
+
+    fn check_element(&mut self, element: &TemplateElement, depth: usize) {
+        match element {
+            TemplateElement::RawString(_) | TemplateElement::Comment(_) => {}
+            TemplateElement::Expression(helper) | TemplateElement::HtmlExpression(helper) => {
+                self.check_expression(helper, depth)
+            }
+            TemplateElement::HelperBlock(helper) => self.check_block(helper, depth),
+            TemplateElement::PartialExpression(_) | TemplateElement::PartialBlock(_) => {
+                self.problems.push("partials are not allowed".to_string())
+            }
+            TemplateElement::DecoratorExpression(_) | TemplateElement::DecoratorBlock(_) => {
+                self.problems.push("decorators are not allowed".to_string())
+            }
+        }
+    }
+
+    fn check_expression(&mut self, helper: &HelperTemplate, depth: usize) {
+        let is_plain_value = helper.params.is_empty() && helper.hash.is_empty();
+        match &helper.name {
+            // `{{name}}` is a helper call only if such a helper exists
+            Parameter::Name(name) if is_plain_value && !is_helper(name) => {
+                self.check_path(name, depth)
+            }
+            Parameter::Path(path) if is_plain_value => self.check_path(raw_path(path), depth),
+            name => {
+                self.check_helper_name(name);
+                self.check_params(&helper.params, &helper.hash, depth);
+            }
+        }
+    }
+
+    fn check_block(&mut self, helper: &HelperTemplate, depth: usize) {
+        self.check_helper_name(&helper.name);
+        self.check_params(&helper.params, &helper.hash, depth);
+
+        let scoped = helper
+            .name
+            .as_name()
+            .is_some_and(|name| SCOPING_HELPERS.contains(&name));
+        if let Some(template) = &helper.template {
+            self.check_template(template, if scoped { depth + 1 } else { depth });
+        }
+        if let Some(inverse) = &helper.inverse {
+            self.check_template(inverse, depth);
+        }
+    }
+
+    fn check_helper_name(&mut self, name: &Parameter) {
+        match name.as_name() {
+            Some(name) if is_helper(name) => {}
+            Some(name) => self
+                .problems
+                .push(format!("helper '{}' is not allowed", name)),
+            None => self
+                .problems
+                .push("dynamic helper names are not allowed".to_string()),
+        }
+    }
+
+    fn check_params(
+        &mut self,
+        params: &[Parameter],
+        hash: &HashMap<String, Parameter>,
+        depth: usize,
+    ) {
+        for param in params.iter().chain(hash.values()) {
+            match param {
+                Parameter::Name(name) => self.check_path(name, depth),
+                Parameter::Path(path) => self.check_path(raw_path(path), depth),
+                Parameter::Literal(_) => {}
+                Parameter::Subexpression(subexpression) => {
+                    self.check_element(subexpression.as_element(), depth)
+                }
+            }
+        }
+    }
+
+    fn check_path(&mut self, raw: &str, depth: usize) {
+        if raw.split(['.', '/']).any(|segment| segment == "@root") {
+            self.problems
+                .push(format!("'{}' reads from @root, which is not allowed", raw));
+            return;
+        }
+
+        let mut path = raw;
+        let mut ups = 0;
+        while let Some(rest) = path.strip_prefix("../") {
+            path = rest;
+            ups += 1;
+        }
+        if ups > depth {
+            self.problems
+                .push(format!("'{}' climbs above the template context", raw));
+            return;
+        }
+        if ups < depth {
+            // Relative to an #each / #with body
+            return;
+        }
+
+        let path = path
+            .strip_prefix("this.")
+            .or_else(|| path.strip_prefix("./"))
+            .unwrap_or(path);
+        if path.is_empty() || path == "this" || path == "." || path.starts_with('@') {
+            return;
+        }
+
+        let path = path.replace('/', ".");
+        if !self.is_declared(&path) {
+            self.problems
+                .push(format!("variable '{}' is not declared", raw));
+        }
+    }
+
+    fn is_declared(&self, path: &str) -> bool {
+        if self.expected.contains(path) {
+            return true;
+        }
+        // A declared parent covers its fields
+        let mut prefix = path;
+        while let Some((parent, _)) = prefix.rsplit_once('.') {
+            if self.expected.contains(parent) {
+                return true;
+            }
+            prefix = parent;
+        }
+        // A declared field implies its parent object exists
+        let parent = format!("{}.", path);
+        self.expected.iter().any(|var| var.starts_with(&parent))
+    }
+}
+
+fn is_helper(name: &str) -> bool {
+    ALLOWED_HELPERS.contains(&name)
+}
+
+fn raw_path(path: &Path) -> &str {
+    match path {
+        Path::Relative((_, raw)) => raw,
+        Path::Local((_, _, raw)) => raw,
+    }
+}
+
+/// Stand-in for built-in helpers the sandbox does not allow
+struct DeniedHelper;
+
+impl HelperDef for DeniedHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        helper: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        _: &mut dyn Output,
+    ) -> HelperResult {
+        Err(RenderError::new(format!(
+            "helper '{}' is not allowed in email templates",
+            helper.name()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_accepts_declared_variables() {
+        let source = "Hello {{patient.name}}, see you on {{appointment_date}}.\
+            {{#if clinic}}{{clinic.phone}}{{/if}}\
+            {{#each items}}{{this.label}} {{@index}} {{../patient.name}}{{/each}}\
+            {{#if (eq status \"confirmed\")}}ok{{else}}{{reason}}{{/if}}";
+
+        validate_template(
+            source,
+            &[
+                "patient.name",
+                "appointment_date",
+                "clinic",
+                "items",
+                "status",
+                "reason",
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_validate_reports_undeclared_variables() {
+        let err = validate_template(
+            "{{patient.name}} {{patient.ssn}} {{#each items}}{{../doctor}}{{/each}}",
+            &["patient.name", "items"],
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("'patient.ssn' is not declared"));
+        assert!(err.contains("'../doctor' is not declared"));
+        assert!(!err.contains("patient.name"));
+    }
+
+    #[test]
+    fn test_validate_rejects_unsafe_constructs() {
+        for source in [
+            "{{lookup patient key}}",
+            "{{log patient}}",
+            "{{@root.patient}}",
+            "{{> header}}",
+            "{{#*inline \"x\"}}y{{/inline}}",
+            "{{#each items}}{{../../secret}}{{/each}}",
+            "{{{{raw}}}}{{x}}{{{{/raw}}}}",
+        ] {
+            assert!(
+                validate_template(source, &["patient", "key", "items", "x"]).is_err(),
+                "expected rejection of {}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_variable_policy() {
+        let data = json!({ "patient": { "name": "Ada" } });
+
+        let mut strict = EmailTemplateEngine::new();
+        strict
+            .register_template(
+                "greeting",
+                "Hi {{patient.name}} {{title}}",
+                &["patient.name", "title"],
+            )
+            .unwrap();
+        assert!(matches!(
+            strict.render("greeting", &data),
+            Err(EmailError::TemplateError(_))
+        ));
+
+        let mut lenient = EmailTemplateEngine::new()
+            .with_missing_variable_policy(MissingVariablePolicy::RenderEmpty);
+        lenient
+            .register_template(
+                "greeting",
+                "Hi {{patient.name}} {{title}}",
+                &["patient.name", "title"],
+            )
+            .unwrap();
+        assert_eq!(lenient.render("greeting", &data).unwrap(), "Hi Ada ");
+    }
+
+    #[test]
+    fn test_register_rejects_invalid_template() {
+        let mut engine = EmailTemplateEngine::new();
+        let err = engine
+            .register_template("reminder", "{{lookup patient \"ssn\"}}", &["patient"])
+            .unwrap_err();
+
+        assert!(err.to_string().contains("helper 'lookup' is not allowed"));
+        assert!(!engine.has_template("reminder"));
+    }
+
+    #[test]
+    fn test_denied_helpers_fail_at_render() {
+        let engine = EmailTemplateEngine::new();
+        let err = engine
+            .registry
+            .render_template(
+                "{{lookup patient \"name\"}}",
+                &json!({ "patient": { "name": "Ada" } }),
+            )
+            .unwrap_err();
+
+        assert!(err.to_string().contains("not allowed"));
     }
-}
\ No newline at end of file
+}
@@ -1,8 +1,1200 @@
-// Email tracking
-pub struct EmailTracking;
+// Email delivery tracking fed by provider webhooks
+//
+// SES (through SNS), SendGrid and Mailgun report deliveries, bounces,
+// complaints and opens in their own formats. `EmailTracking::handle_webhook`
+// verifies the provider's signature, normalizes the payload into
+// `DeliveryEvent`s and applies them to the message's tracking record. Hard
+// bounces and complaints put the recipient on the suppression list, which
+// `EmailService` checks before sending.
+use crate::error::{EmailError, EmailResult};
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, TimeZone, Utc};
+use ring::{hmac, signature};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+use x509_parser::prelude::FromDer;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+/// SendGrid signed event webhook headers
+const SENDGRID_SIGNATURE_HEADER: &str = "x-twilio-email-event-webhook-signature";
+const SENDGRID_TIMESTAMP_HEADER: &str = "x-twilio-email-event-webhook-timestamp";
+
+/// Provider a delivery webhook was received from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookProvider {
+    Ses,
+    SendGrid,
+    Mailgun,
+}
+
+impl WebhookProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookProvider::Ses => "ses",
+            WebhookProvider::SendGrid => "sendgrid",
+            WebhookProvider::Mailgun => "mailgun",
+        }
+    }
+}
+
+impl FromStr for WebhookProvider {
+    type Err = EmailError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ses" => Ok(WebhookProvider::Ses),
+            "sendgrid" => Ok(WebhookProvider::SendGrid),
+            "mailgun" => Ok(WebhookProvider::Mailgun),
+            other => Err(EmailError::InvalidWebhookPayload(format!(
+                "Unsupported webhook provider: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Bounce classification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BounceType {
+    /// Permanent failure, e.g. unknown mailbox; the address is suppressed
+    Hard,
+    /// Temporary failure, e.g. full mailbox or greylisting
+    Soft,
+}
+
+/// What happened to a message, independent of the reporting provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DeliveryEventKind {
+    Delivered,
+    Bounced { bounce_type: BounceType },
+    Complained,
+    Opened,
+}
+
+/// Normalized delivery event for one recipient of a message
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DeliveryEvent {
+    pub provider: WebhookProvider,
+    /// Provider message id
+    pub message_id: String,
+    pub recipient: String,
+    pub kind: DeliveryEventKind,
+    pub timestamp: DateTime<Utc>,
+    /// Provider diagnostic, e.g. the SMTP response of a bounce
+    pub reason: Option<String>,
+}
+
+/// Delivery status of a tracked message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Sent,
+    SoftBounced,
+    Delivered,
+    Opened,
+    HardBounced,
+    Complained,
+}
+
+impl DeliveryStatus {
+    fn from_event(kind: DeliveryEventKind) -> Self {
+        match kind {
+            DeliveryEventKind::Delivered => DeliveryStatus::Delivered,
+            DeliveryEventKind::Bounced { bounce_type: BounceType::Soft } => DeliveryStatus::SoftBounced,
+            DeliveryEventKind::Bounced { bounce_type: BounceType::Hard } => DeliveryStatus::HardBounced,
+            DeliveryEventKind::Complained => DeliveryStatus::Complained,
+            DeliveryEventKind::Opened => DeliveryStatus::Opened,
+        }
+    }
+
+    /// Webhooks arrive out of order; a record never moves to a lower rank
+    fn rank(&self) -> u8 {
+        match self {
+            DeliveryStatus::Sent => 0,
+            DeliveryStatus::SoftBounced => 1,
+            DeliveryStatus::Delivered => 2,
+            DeliveryStatus::Opened => 3,
+            DeliveryStatus::HardBounced => 4,
+            DeliveryStatus::Complained => 5,
+        }
+    }
+}
+
+/// Tracking state of a message for one recipient
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TrackingRecord {
+    pub message_id: String,
+    pub recipient: String,
+    pub status: DeliveryStatus,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub opened_at: Option<DateTime<Utc>>,
+    pub bounced_at: Option<DateTime<Utc>>,
+    pub soft_bounces: u32,
+    pub last_reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TrackingRecord {
+    fn new(message_id: &str, recipient: &str) -> Self {
+        Self {
+            message_id: message_id.to_string(),
+            recipient: normalize_address(recipient),
+            status: DeliveryStatus::Sent,
+            sent_at: None,
+            delivered_at: None,
+            opened_at: None,
+            bounced_at: None,
+            soft_bounces: 0,
+            last_reason: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn apply(&mut self, event: &DeliveryEvent) {
+        match event.kind {
+            DeliveryEventKind::Delivered => {
+                self.delivered_at.get_or_insert(event.timestamp);
+            }
+            DeliveryEventKind::Opened => {
+                self.opened_at.get_or_insert(event.timestamp);
+            }
+            DeliveryEventKind::Bounced { bounce_type } => {
+                self.bounced_at = Some(event.timestamp);
+                if bounce_type == BounceType::Soft {
+                    self.soft_bounces += 1;
+                }
+            }
+            DeliveryEventKind::Complained => {}
+        }
+
+        let status = DeliveryStatus::from_event(event.kind);
+        if status.rank() >= self.status.rank() {
+            self.status = status;
+        }
+        if event.reason.is_some() {
+            self.last_reason = event.reason.clone();
+        }
+        self.updated_at = Utc::now();
+    }
+}
+
+/// Why an address is no longer mailed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuppressionReason {
+    HardBounce,
+    Complaint,
+}
+
+/// Suppression list entry
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SuppressionEntry {
+    pub address: String,
+    pub reason: SuppressionReason,
+    pub provider: WebhookProvider,
+    pub message_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Storage for tracking records and the suppression list
+#[async_trait]
+pub trait TrackingStore: Send + Sync {
+    async fn get(&self, message_id: &str, recipient: &str) -> EmailResult<Option<TrackingRecord>>;
+
+    async fn put(&self, record: TrackingRecord) -> EmailResult<()>;
+
+    async fn suppression(&self, address: &str) -> EmailResult<Option<SuppressionEntry>>;
+
+    /// Keeps the existing entry if the address is already suppressed
+    async fn suppress(&self, entry: SuppressionEntry) -> EmailResult<()>;
+}
+
+/// In-process tracking store
+#[derive(Default)]
+pub struct InMemoryTrackingStore {
+    records: RwLock<HashMap<(String, String), TrackingRecord>>,
+    suppressions: RwLock<HashMap<String, SuppressionEntry>>,
+}
+
+impl InMemoryTrackingStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TrackingStore for InMemoryTrackingStore {
+    async fn get(&self, message_id: &str, recipient: &str) -> EmailResult<Option<TrackingRecord>> {
+        let key = (message_id.to_string(), normalize_address(recipient));
+        Ok(self.records.read().await.get(&key).cloned())
+    }
+
+    async fn put(&self, record: TrackingRecord) -> EmailResult<()> {
+        let key = (record.message_id.clone(), record.recipient.clone());
+        self.records.write().await.insert(key, record);
+        Ok(())
+    }
+
+    async fn suppression(&self, address: &str) -> EmailResult<Option<SuppressionEntry>> {
+        Ok(self.suppressions.read().await.get(&normalize_address(address)).cloned())
+    }
+
+    async fn suppress(&self, entry: SuppressionEntry) -> EmailResult<()> {
+        self.suppressions
+            .write()
+            .await
+            .entry(entry.address.clone())
+            .or_insert(entry);
+        Ok(())
+    }
+}
+
+/// Keys used to verify provider webhooks; a provider without a key is rejected
+#[derive(Clone)]
+pub struct WebhookSecrets {
+    /// SNS topics allowed to deliver SES notifications
+    pub ses_topic_arns: Vec<String>,
+    /// Base64 DER public key of the SendGrid signed event webhook
+    pub sendgrid_public_key: Option<String>,
+    /// Mailgun webhook signing key
+    pub mailgun_signing_key: Option<String>,
+    /// Maximum age of a signed SNS, SendGrid or Mailgun timestamp
+    pub max_timestamp_age: Duration,
+}
+
+impl Default for WebhookSecrets {
+    fn default() -> Self {
+        Self {
+            ses_topic_arns: Vec::new(),
+            sendgrid_public_key: None,
+            mailgun_signing_key: None,
+            max_timestamp_age: Duration::from_secs(300),
+        }
+    }
+}
+
+impl WebhookSecrets {
+    /// Load webhook keys from environment variables
+    pub fn from_env() -> Self {
+        let ses_topic_arns = std::env::var("SES_WEBHOOK_TOPIC_ARNS")
+            .map(|arns| {
+                arns.split(',')
+                    .map(str::trim)
+                    .filter(|arn| !arn.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            ses_topic_arns,
+            sendgrid_public_key: std::env::var("SENDGRID_WEBHOOK_PUBLIC_KEY").ok(),
+            mailgun_signing_key: std::env::var("MAILGUN_WEBHOOK_SIGNING_KEY").ok(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Delivery tracking and suppression list
+pub struct EmailTracking {
+    store: Arc<dyn TrackingStore>,
+    secrets: WebhookSecrets,
+    http: reqwest::Client,
+    /// SNS signing certificate URL -> RSA public key
+    sns_keys: RwLock<HashMap<String, SnsSigningKey>>,
+}
+
+/// Public key of an SNS signing certificate, usable until the certificate expires
+#[derive(Clone)]
+struct SnsSigningKey {
+    key: Vec<u8>,
+    not_after: DateTime<Utc>,
+}
 
 impl EmailTracking {
     pub fn new() -> Self {
-        Self
+        Self {
+            store: Arc::new(InMemoryTrackingStore::new()),
+            secrets: WebhookSecrets::default(),
+            http: reqwest::Client::new(),
+            sns_keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Use a different tracking store
+    pub fn with_store(mut self, store: Arc<dyn TrackingStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Set the keys used to verify webhooks
+    pub fn with_secrets(mut self, secrets: WebhookSecrets) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Start tracking a message handed to the provider
+    pub async fn record_sent(&self, message_id: &str, recipient: &str) -> EmailResult<()> {
+        let mut record = TrackingRecord::new(message_id, recipient);
+        record.sent_at = Some(Utc::now());
+        self.store.put(record).await
+    }
+
+    /// Tracking record of a message for one recipient
+    pub async fn record(&self, message_id: &str, recipient: &str) -> EmailResult<Option<TrackingRecord>> {
+        self.store.get(message_id, recipient).await
+    }
+
+    /// Whether the address hard-bounced or complained before
+    pub async fn is_suppressed(&self, address: &str) -> EmailResult<bool> {
+        Ok(self.store.suppression(address).await?.is_some())
+    }
+
+    /// Verify, parse and apply a delivery webhook
+    ///
+    /// `headers` are the request headers, matched case-insensitively; `body`
+    /// is the raw request body, since signatures cover the exact bytes.
+    pub async fn handle_webhook(
+        &self,
+        provider: WebhookProvider,
+        headers: &HashMap<String, String>,
+        body: &[u8],
+    ) -> EmailResult<Vec<DeliveryEvent>> {
+        let events = match provider {
+            WebhookProvider::Ses => {
+                let envelope: SnsEnvelope = serde_json::from_slice(body)
+                    .map_err(|e| EmailError::InvalidWebhookPayload(format!("Invalid SNS message: {}", e)))?;
+                self.verify_sns(&envelope).await?;
+
+                match envelope.message_type.as_str() {
+                    "Notification" => parse_ses_message(&envelope.message)?,
+                    "SubscriptionConfirmation" => {
+                        self.confirm_sns_subscription(&envelope).await?;
+                        Vec::new()
+                    }
+                    other => {
+                        debug!(message_type = other, "Ignoring SNS message");
+                        Vec::new()
+                    }
+                }
+            }
+            WebhookProvider::SendGrid => {
+                self.verify_sendgrid(headers, body)?;
+                parse_sendgrid_events(body)?
+            }
+            WebhookProvider::Mailgun => {
+                self.verify_mailgun(body)?;
+                parse_mailgun_event(body)?
+            }
+        };
+
+        for event in &events {
+            self.apply(event).await?;
+        }
+
+        info!(provider = provider.as_str(), events = events.len(), "Processed delivery webhook");
+        Ok(events)
+    }
+
+    /// Apply a normalized event to its tracking record and the suppression list
+    pub async fn apply(&self, event: &DeliveryEvent) -> EmailResult<()> {
+        let mut record = self
+            .store
+            .get(&event.message_id, &event.recipient)
+            .await?
+            .unwrap_or_else(|| TrackingRecord::new(&event.message_id, &event.recipient));
+        record.apply(event);
+        self.store.put(record).await?;
+
+        let reason = match event.kind {
+            DeliveryEventKind::Bounced { bounce_type: BounceType::Hard } => SuppressionReason::HardBounce,
+            DeliveryEventKind::Complained => SuppressionReason::Complaint,
+            _ => return Ok(()),
+        };
+
+        warn!(
+            provider = event.provider.as_str(),
+            message_id = %event.message_id,
+            reason = ?reason,
+            "Suppressing recipient"
+        );
+        self.store
+            .suppress(SuppressionEntry {
+                address: normalize_address(&event.recipient),
+                reason,
+                provider: event.provider,
+                message_id: event.message_id.clone(),
+                created_at: event.timestamp,
+            })
+            .await
+    }
+
+    async fn verify_sns(&self, envelope: &SnsEnvelope) -> EmailResult<()> {
+        if !self.secrets.ses_topic_arns.contains(&envelope.topic_arn) {
+            return Err(EmailError::WebhookVerificationFailed(format!(
+                "SNS topic not allowed: {}",
+                envelope.topic_arn
+            )));
+        }
+        let signed_at = parse_rfc3339(&envelope.timestamp)
+            .ok_or_else(|| EmailError::WebhookVerificationFailed("Malformed SNS timestamp".to_string()))?;
+        self.check_signed_at(signed_at.timestamp())?;
+
+        let algorithm: &'static dyn signature::VerificationAlgorithm = match envelope.signature_version.as_str() {
+            "1" => &signature::RSA_PKCS1_2048_8192_SHA1_FOR_LEGACY_USE_ONLY,
+            "2" => &signature::RSA_PKCS1_2048_8192_SHA256,
+            other => {
+                return Err(EmailError::WebhookVerificationFailed(format!(
+                    "Unsupported SNS signature version: {}",
+                    other
+                )))
+            }
+        };
+
+        let key = self.sns_public_key(&envelope.signing_cert_url).await?;
+        let signature_bytes = base64::engine::general_purpose::STANDARD
+            .decode(&envelope.signature)
+            .map_err(|_| EmailError::WebhookVerificationFailed("Malformed SNS signature".to_string()))?;
+
+        signature::UnparsedPublicKey::new(algorithm, key)
+            .verify(envelope.string_to_sign().as_bytes(), &signature_bytes)
+            .map_err(|_| EmailError::WebhookVerificationFailed("SNS signature mismatch".to_string()))
+    }
+
+    /// Fetch the SNS signing certificate once per URL and keep its public key
+    /// until the certificate expires
+    async fn sns_public_key(&self, cert_url: &str) -> EmailResult<Vec<u8>> {
+        if let Some(key) = self.cached_sns_key(cert_url).await {
+            return Ok(key);
+        }
+
+        validate_sns_cert_url(cert_url)?;
+        let pem = self
+            .http
+            .get(cert_url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| EmailError::WebhookVerificationFailed(format!("Failed to fetch SNS certificate: {}", e)))?
+            .bytes()
+            .await
+            .map_err(|e| EmailError::WebhookVerificationFailed(format!("Failed to fetch SNS certificate: {}", e)))?;
+
+        let (_, pem) = x509_parser::pem::parse_x509_pem(&pem)
+            .map_err(|e| EmailError::WebhookVerificationFailed(format!("Invalid SNS certificate: {}", e)))?;
+        let certificate = pem
+            .parse_x509()
+            .map_err(|e| EmailError::WebhookVerificationFailed(format!("Invalid SNS certificate: {}", e)))?;
+        if !certificate.validity().is_valid() {
+            return Err(EmailError::WebhookVerificationFailed(
+                "SNS certificate expired".to_string(),
+            ));
+        }
+
+        let key = certificate.public_key().subject_public_key.data.to_vec();
+        let not_after = Utc
+            .timestamp_opt(certificate.validity().not_after.timestamp(), 0)
+            .single()
+            .ok_or_else(|| EmailError::WebhookVerificationFailed("Invalid SNS certificate validity".to_string()))?;
+        let mut keys = self.sns_keys.write().await;
+        keys.retain(|_, cached| cached.not_after > Utc::now());
+        keys.insert(cert_url.to_string(), SnsSigningKey { key: key.clone(), not_after });
+        Ok(key)
+    }
+
+    /// Cached key for `cert_url`, evicting it once its certificate has expired
+    async fn cached_sns_key(&self, cert_url: &str) -> Option<Vec<u8>> {
+        match self.sns_keys.read().await.get(cert_url) {
+            Some(cached) if cached.not_after > Utc::now() => return Some(cached.key.clone()),
+            Some(_) => {}
+            None => return None,
+        }
+        self.sns_keys.write().await.remove(cert_url);
+        None
+    }
+
+    async fn confirm_sns_subscription(&self, envelope: &SnsEnvelope) -> EmailResult<()> {
+        let url = envelope.subscribe_url.as_deref().ok_or_else(|| {
+            EmailError::InvalidWebhookPayload("SubscriptionConfirmation without SubscribeURL".to_string())
+        })?;
+        validate_sns_cert_url_host(url)?;
+
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| EmailError::SendFailed(format!("Failed to confirm SNS subscription: {}", e)))?;
+
+        info!(topic_arn = %envelope.topic_arn, "Confirmed SNS subscription");
+        Ok(())
+    }
+
+    fn verify_sendgrid(&self, headers: &HashMap<String, String>, body: &[u8]) -> EmailResult<()> {
+        let public_key = self.secrets.sendgrid_public_key.as_deref().ok_or_else(|| {
+            EmailError::WebhookVerificationFailed("SendGrid webhook public key not configured".to_string())
+        })?;
+        let signature_header = header(headers, SENDGRID_SIGNATURE_HEADER).ok_or_else(|| {
+            EmailError::WebhookVerificationFailed("Missing SendGrid signature header".to_string())
+        })?;
+        let timestamp = header(headers, SENDGRID_TIMESTAMP_HEADER).ok_or_else(|| {
+            EmailError::WebhookVerificationFailed("Missing SendGrid timestamp header".to_string())
+        })?;
+        self.check_timestamp_age(timestamp)?;
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let spki = engine
+            .decode(public_key.trim())
+            .map_err(|_| EmailError::WebhookVerificationFailed("Malformed SendGrid public key".to_string()))?;
+        let (_, spki) = SubjectPublicKeyInfo::from_der(&spki)
+            .map_err(|_| EmailError::WebhookVerificationFailed("Malformed SendGrid public key".to_string()))?;
+        let signature_bytes = engine
+            .decode(signature_header.trim())
+            .map_err(|_| EmailError::WebhookVerificationFailed("Malformed SendGrid signature".to_string()))?;
+
+        let mut signed = timestamp.as_bytes().to_vec();
+        signed.extend_from_slice(body);
+
+        signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, &spki.subject_public_key.data)
+            .verify(&signed, &signature_bytes)
+            .map_err(|_| EmailError::WebhookVerificationFailed("SendGrid signature mismatch".to_string()))
+    }
+
+    fn verify_mailgun(&self, body: &[u8]) -> EmailResult<()> {
+        let signing_key = self.secrets.mailgun_signing_key.as_deref().ok_or_else(|| {
+            EmailError::WebhookVerificationFailed("Mailgun webhook signing key not configured".to_string())
+        })?;
+
+        #[derive(Deserialize)]
+        struct Signed {
+            signature: MailgunSignature,
+        }
+        #[derive(Deserialize)]
+        struct MailgunSignature {
+            timestamp: String,
+            token: String,
+            signature: String,
+        }
+
+        let signed: Signed = serde_json::from_slice(body)
+            .map_err(|_| EmailError::WebhookVerificationFailed("Missing Mailgun signature".to_string()))?;
+        let signature = signed.signature;
+        self.check_timestamp_age(&signature.timestamp)?;
+
+        let tag = hex::decode(&signature.signature)
+            .map_err(|_| EmailError::WebhookVerificationFailed("Malformed Mailgun signature".to_string()))?;
+        let key = hmac::Key::new(hmac::HMAC_SHA256, signing_key.as_bytes());
+        hmac::verify(&key, format!("{}{}", signature.timestamp, signature.token).as_bytes(), &tag)
+            .map_err(|_| EmailError::WebhookVerificationFailed("Mailgun signature mismatch".to_string()))
+    }
+
+    /// Reject replays of old signed payloads
+    fn check_timestamp_age(&self, timestamp: &str) -> EmailResult<()> {
+        let signed_at: i64 = timestamp
+            .trim()
+            .parse()
+            .map_err(|_| EmailError::WebhookVerificationFailed("Malformed webhook timestamp".to_string()))?;
+        self.check_signed_at(signed_at)
+    }
+
+    /// Reject a signed payload whose Unix timestamp is outside `max_timestamp_age`
+    fn check_signed_at(&self, signed_at: i64) -> EmailResult<()> {
+        let max_age = i64::try_from(self.secrets.max_timestamp_age.as_secs()).unwrap_or(i64::MAX);
+
+        if (Utc::now().timestamp() - signed_at).abs() > max_age {
+            return Err(EmailError::WebhookVerificationFailed(
+                "Webhook timestamp outside the allowed window".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for EmailTracking {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// SNS HTTP(S) message
+#[derive(Debug, Deserialize)]
+struct SnsEnvelope {
+    #[serde(rename = "Type")]
+    message_type: String,
+    #[serde(rename = "MessageId")]
+    message_id: String,
+    #[serde(rename = "TopicArn")]
+    topic_arn: String,
+    #[serde(rename = "Subject")]
+    subject: Option<String>,
+    #[serde(rename = "Message")]
+    message: String,
+    #[serde(rename = "Timestamp")]
+    timestamp: String,
+    #[serde(rename = "Token")]
+    token: Option<String>,
+    #[serde(rename = "SubscribeURL")]
+    subscribe_url: Option<String>,
+    #[serde(rename = "SignatureVersion")]
+    signature_version: String,
+    #[serde(rename = "Signature")]
+    signature: String,
+    #[serde(rename = "SigningCertURL")]
+    signing_cert_url: String,
+}
+
+impl SnsEnvelope {
+    /// Canonical string SNS signs: selected fields as "Name\nvalue\n" in a fixed order
+    fn string_to_sign(&self) -> String {
+        let mut fields: Vec<(&str, &str)> = vec![("Message", &self.message), ("MessageId", &self.message_id)];
+        if self.message_type == "Notification" {
+            if let Some(subject) = &self.subject {
+                fields.push(("Subject", subject));
+            }
+        } else {
+            fields.push(("SubscribeURL", self.subscribe_url.as_deref().unwrap_or_default()));
+        }
+        fields.push(("Timestamp", &self.timestamp));
+        if self.message_type != "Notification" {
+            fields.push(("Token", self.token.as_deref().unwrap_or_default()));
+        }
+        fields.push(("TopicArn", &self.topic_arn));
+        fields.push(("Type", &self.message_type));
+
+        fields
+            .into_iter()
+            .map(|(name, value)| format!("{}\n{}\n", name, value))
+            .collect()
+    }
+}
+
+/// Only fetch signing certificates from SNS itself
+fn validate_sns_cert_url(url: &str) -> EmailResult<()> {
+    validate_sns_cert_url_host(url)?;
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|_| EmailError::WebhookVerificationFailed("Invalid SNS certificate URL".to_string()))?;
+    if !parsed.path().ends_with(".pem") {
+        return Err(EmailError::WebhookVerificationFailed(format!(
+            "Untrusted SNS certificate URL: {}",
+            url
+        )));
+    }
+    Ok(())
+}
+
+fn validate_sns_cert_url_host(url: &str) -> EmailResult<()> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|_| EmailError::WebhookVerificationFailed("Invalid SNS URL".to_string()))?;
+    let trusted = parsed.scheme() == "https"
+        && parsed
+            .host_str()
+            .is_some_and(is_sns_host);
+
+    if !trusted {
+        return Err(EmailError::WebhookVerificationFailed(format!("Untrusted SNS URL: {}", url)));
+    }
+    Ok(())
+}
+
+/// `sns.<region>.amazonaws.com`, or `.com.cn` in the China partition; the
+/// region is a single label so lookalikes such as bucket hosts don't match
+fn is_sns_host(host: &str) -> bool {
+    let Some(rest) = host.strip_prefix("sns.") else {
+        return false;
+    };
+    let region = rest
+        .strip_suffix(".amazonaws.com")
+        .or_else(|| rest.strip_suffix(".amazonaws.com.cn"));
+    region.is_some_and(|region| {
+        !region.is_empty() && region.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    })
+}
+
+/// Parse a webhook body into normalized events without verifying it
+pub fn parse_webhook(provider: WebhookProvider, body: &[u8]) -> EmailResult<Vec<DeliveryEvent>> {
+    match provider {
+        WebhookProvider::Ses => {
+            let envelope: SnsEnvelope = serde_json::from_slice(body)
+                .map_err(|e| EmailError::InvalidWebhookPayload(format!("Invalid SNS message: {}", e)))?;
+            if envelope.message_type == "Notification" {
+                parse_ses_message(&envelope.message)
+            } else {
+                Ok(Vec::new())
+            }
+        }
+        WebhookProvider::SendGrid => parse_sendgrid_events(body),
+        WebhookProvider::Mailgun => parse_mailgun_event(body),
+    }
+}
+
+/// SES notification or event publishing record carried in an SNS message
+fn parse_ses_message(message: &str) -> EmailResult<Vec<DeliveryEvent>> {
+    let message: Value = serde_json::from_str(message)
+        .map_err(|e| EmailError::InvalidWebhookPayload(format!("Invalid SES notification: {}", e)))?;
+
+    let event_type = message
+        .get("notificationType")
+        .or_else(|| message.get("eventType"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let message_id = message
+        .pointer("/mail/messageId")
+        .and_then(Value::as_str)
+        .ok_or_else(|| EmailError::InvalidWebhookPayload("SES notification without mail.messageId".to_string()))?;
+
+    let addresses = |list: Option<&Value>, field: Option<&str>| -> Vec<(String, Option<String>)> {
+        list.and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| match field {
+                        Some(field) => item.get(field).and_then(Value::as_str).map(|address| {
+                            let reason = item.get("diagnosticCode").and_then(Value::as_str).map(String::from);
+                            (address.to_string(), reason)
+                        }),
+                        None => item.as_str().map(|address| (address.to_string(), None)),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let (kind, section, recipients) = match event_type {
+        "Delivery" => (
+            DeliveryEventKind::Delivered,
+            "delivery",
+            addresses(message.pointer("/delivery/recipients"), None),
+        ),
+        "Bounce" => {
+            // "Undetermined" bounces are retried by SES, so treat them as soft
+            let bounce_type = match message.pointer("/bounce/bounceType").and_then(Value::as_str) {
+                Some("Permanent") => BounceType::Hard,
+                _ => BounceType::Soft,
+            };
+            (
+                DeliveryEventKind::Bounced { bounce_type },
+                "bounce",
+                addresses(message.pointer("/bounce/bouncedRecipients"), Some("emailAddress")),
+            )
+        }
+        "Complaint" => (
+            DeliveryEventKind::Complained,
+            "complaint",
+            addresses(message.pointer("/complaint/complainedRecipients"), Some("emailAddress")),
+        ),
+        "Open" => (
+            DeliveryEventKind::Opened,
+            "open",
+            addresses(message.pointer("/mail/destination"), None),
+        ),
+        other => {
+            debug!(event_type = other, "Ignoring SES event");
+            return Ok(Vec::new());
+        }
+    };
+
+    let timestamp = message
+        .get(section)
+        .and_then(|section| section.get("timestamp"))
+        .and_then(Value::as_str)
+        .and_then(parse_rfc3339)
+        .unwrap_or_else(Utc::now);
+    let smtp_response = message
+        .pointer("/delivery/smtpResponse")
+        .and_then(Value::as_str)
+        .map(String::from);
+
+    Ok(recipients
+        .into_iter()
+        .map(|(recipient, reason)| DeliveryEvent {
+            provider: WebhookProvider::Ses,
+            message_id: message_id.to_string(),
+            recipient,
+            kind,
+            timestamp,
+            reason: reason.or_else(|| smtp_response.clone()),
+        })
+        .collect())
+}
+
+/// SendGrid event webhook: a JSON array of events
+fn parse_sendgrid_events(body: &[u8]) -> EmailResult<Vec<DeliveryEvent>> {
+    let events: Vec<Value> = serde_json::from_slice(body)
+        .map_err(|e| EmailError::InvalidWebhookPayload(format!("Invalid SendGrid events: {}", e)))?;
+
+    let mut parsed = Vec::with_capacity(events.len());
+    for event in &events {
+        let field = |name: &str| event.get(name).and_then(Value::as_str);
+
+        let kind = match field("event") {
+            Some("delivered") => DeliveryEventKind::Delivered,
+            Some("open") => DeliveryEventKind::Opened,
+            Some("spamreport") => DeliveryEventKind::Complained,
+            Some("bounce") => {
+                // "blocked" is a reputation or content block, not a bad address
+                let bounce_type = if field("type") == Some("blocked") || field("status").is_some_and(|s| s.starts_with('4')) {
+                    BounceType::Soft
+                } else {
+                    BounceType::Hard
+                };
+                DeliveryEventKind::Bounced { bounce_type }
+            }
+            other => {
+                debug!(event = ?other, "Ignoring SendGrid event");
+                continue;
+            }
+        };
+
+        // sg_message_id is the X-Message-Id followed by a filter suffix
+        let message_id = field("sg_message_id")
+            .and_then(|id| id.split('.').next())
+            .or_else(|| field("smtp-id"))
+            .ok_or_else(|| EmailError::InvalidWebhookPayload("SendGrid event without message id".to_string()))?;
+        let recipient = field("email")
+            .ok_or_else(|| EmailError::InvalidWebhookPayload("SendGrid event without email".to_string()))?;
+
+        parsed.push(DeliveryEvent {
+            provider: WebhookProvider::SendGrid,
+            message_id: message_id.to_string(),
+            recipient: recipient.to_string(),
+            kind,
+            timestamp: event
+                .get("timestamp")
+                .and_then(Value::as_i64)
+                .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+                .unwrap_or_else(Utc::now),
+            reason: field("reason").map(String::from),
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// Mailgun webhook: one signed event per request
+fn parse_mailgun_event(body: &[u8]) -> EmailResult<Vec<DeliveryEvent>> {
+    let payload: Value = serde_json::from_slice(body)
+        .map_err(|e| EmailError::InvalidWebhookPayload(format!("Invalid Mailgun event: {}", e)))?;
+    let event = payload
+        .get("event-data")
+        .ok_or_else(|| EmailError::InvalidWebhookPayload("Mailgun payload without event-data".to_string()))?;
+    let field = |pointer: &str| event.pointer(pointer).and_then(Value::as_str);
+
+    let kind = match field("/event") {
+        Some("delivered") => DeliveryEventKind::Delivered,
+        Some("opened") => DeliveryEventKind::Opened,
+        Some("complained") => DeliveryEventKind::Complained,
+        Some("failed") => DeliveryEventKind::Bounced {
+            bounce_type: if field("/severity") == Some("permanent") {
+                BounceType::Hard
+            } else {
+                BounceType::Soft
+            },
+        },
+        other => {
+            debug!(event = ?other, "Ignoring Mailgun event");
+            return Ok(Vec::new());
+        }
+    };
+
+    let message_id = field("/message/headers/message-id")
+        .ok_or_else(|| EmailError::InvalidWebhookPayload("Mailgun event without message-id".to_string()))?;
+    let recipient = field("/recipient")
+        .ok_or_else(|| EmailError::InvalidWebhookPayload("Mailgun event without recipient".to_string()))?;
+    let timestamp = event
+        .get("timestamp")
+        .and_then(Value::as_f64)
+        .and_then(|seconds| Utc.timestamp_millis_opt((seconds * 1000.0) as i64).single())
+        .unwrap_or_else(Utc::now);
+    let reason = field("/delivery-status/description")
+        .filter(|description| !description.is_empty())
+        .or_else(|| field("/delivery-status/message"))
+        .or_else(|| field("/reason"))
+        .map(String::from);
+
+    Ok(vec![DeliveryEvent {
+        provider: WebhookProvider::Mailgun,
+        message_id: message_id.to_string(),
+        recipient: recipient.to_string(),
+        kind,
+        timestamp,
+        reason,
+    }])
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn parse_rfc3339(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+fn normalize_address(address: &str) -> String {
+    address.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    /// DER prefix of a P-256 SubjectPublicKeyInfo, followed by the 65-byte point
+    const P256_SPKI_PREFIX: &str = "3059301306072a8648ce3d020106082a8648ce3d030107034200";
+
+    fn ses_bounce(bounce_type: &str) -> Vec<u8> {
+        let message = serde_json::json!({
+            "notificationType": "Bounce",
+            "bounce": {
+                "bounceType": bounce_type,
+                "bouncedRecipients": [
+                    { "emailAddress": "Patient@Example.com", "diagnosticCode": "smtp; 550 5.1.1 user unknown" }
+                ],
+                "timestamp": "2024-05-01T10:00:00.000Z"
+            },
+            "mail": { "messageId": "ses-1", "destination": ["patient@example.com"] }
+        });
+        serde_json::to_vec(&serde_json::json!({
+            "Type": "Notification",
+            "MessageId": "sns-1",
+            "TopicArn": "arn:aws:sns:us-east-1:123456789012:ses-events",
+            "Message": message.to_string(),
+            "Timestamp": "2024-05-01T10:00:01.000Z",
+            "SignatureVersion": "1",
+            "Signature": "",
+            "SigningCertURL": "https://sns.us-east-1.amazonaws.com/cert.pem"
+        }))
+        .unwrap()
+    }
+
+    fn mailgun_body(key: &str, timestamp: i64, event: Value) -> Vec<u8> {
+        let token = "a1b2c3";
+        let tag = hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes()),
+            format!("{}{}", timestamp, token).as_bytes(),
+        );
+        serde_json::to_vec(&serde_json::json!({
+            "signature": {
+                "timestamp": timestamp.to_string(),
+                "token": token,
+                "signature": hex::encode(tag.as_ref())
+            },
+            "event-data": event
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ses_hard_bounce_suppresses_recipient() {
+        let tracking = EmailTracking::new();
+        tracking.record_sent("ses-1", "patient@example.com").await.unwrap();
+
+        let events = parse_webhook(WebhookProvider::Ses, &ses_bounce("Permanent")).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, DeliveryEventKind::Bounced { bounce_type: BounceType::Hard });
+        tracking.apply(&events[0]).await.unwrap();
+
+        let record = tracking.record("ses-1", "patient@example.com").await.unwrap().unwrap();
+        assert_eq!(record.status, DeliveryStatus::HardBounced);
+        assert_eq!(record.last_reason.as_deref(), Some("smtp; 550 5.1.1 user unknown"));
+        assert!(tracking.is_suppressed("PATIENT@example.com").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_soft_bounce_does_not_suppress() {
+        let tracking = EmailTracking::new();
+        for event in parse_webhook(WebhookProvider::Ses, &ses_bounce("Transient")).unwrap() {
+            tracking.apply(&event).await.unwrap();
+        }
+
+        let record = tracking.record("ses-1", "patient@example.com").await.unwrap().unwrap();
+        assert_eq!(record.status, DeliveryStatus::SoftBounced);
+        assert_eq!(record.soft_bounces, 1);
+        assert!(!tracking.is_suppressed("patient@example.com").await.unwrap());
+    }
+
+    #[test]
+    fn test_sendgrid_event_classification() {
+        let body = serde_json::to_vec(&serde_json::json!([
+            { "event": "processed", "email": "a@example.com", "sg_message_id": "m1.filter0001", "timestamp": 1714557600 },
+            { "event": "bounce", "type": "bounce", "status": "5.1.1", "email": "a@example.com", "sg_message_id": "m1.filter0001", "timestamp": 1714557600 },
+            { "event": "bounce", "type": "blocked", "status": "5.7.1", "email": "b@example.com", "sg_message_id": "m2.filter0001", "timestamp": 1714557600 },
+            { "event": "spamreport", "email": "c@example.com", "sg_message_id": "m3.filter0001", "timestamp": 1714557600 }
+        ]))
+        .unwrap();
+
+        let events = parse_webhook(WebhookProvider::SendGrid, &body).unwrap();
+        let kinds: Vec<_> = events.iter().map(|event| (event.message_id.as_str(), event.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("m1", DeliveryEventKind::Bounced { bounce_type: BounceType::Hard }),
+                ("m2", DeliveryEventKind::Bounced { bounce_type: BounceType::Soft }),
+                ("m3", DeliveryEventKind::Complained),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sendgrid_signature_verification() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let mut spki = hex::decode(P256_SPKI_PREFIX).unwrap();
+        spki.extend_from_slice(key_pair.public_key().as_ref());
+
+        let tracking = EmailTracking::new().with_secrets(WebhookSecrets {
+            sendgrid_public_key: Some(base64::engine::general_purpose::STANDARD.encode(&spki)),
+            ..WebhookSecrets::default()
+        });
+
+        let body = br#"[{"event":"delivered","email":"a@example.com","sg_message_id":"m1.filter0001","timestamp":1714557600}]"#;
+        let timestamp = Utc::now().timestamp().to_string();
+        let signed = [timestamp.as_bytes(), body.as_slice()].concat();
+        let signature = key_pair.sign(&rng, &signed).unwrap();
+
+        let mut headers = HashMap::new();
+        headers.insert("X-Twilio-Email-Event-Webhook-Timestamp".to_string(), timestamp);
+        headers.insert(
+            "X-Twilio-Email-Event-Webhook-Signature".to_string(),
+            base64::engine::general_purpose::STANDARD.encode(signature.as_ref()),
+        );
+
+        let events = tracking.handle_webhook(WebhookProvider::SendGrid, &headers, body).await.unwrap();
+        assert_eq!(events.len(), 1);
+
+        let tampered = body.iter().copied().map(|b| if b == b'1' { b'2' } else { b }).collect::<Vec<_>>();
+        let result = tracking.handle_webhook(WebhookProvider::SendGrid, &headers, &tampered).await;
+        assert!(matches!(result, Err(EmailError::WebhookVerificationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_mailgun_signature_verification() {
+        let tracking = EmailTracking::new().with_secrets(WebhookSecrets {
+            mailgun_signing_key: Some("mailgun-key".to_string()),
+            ..WebhookSecrets::default()
+        });
+        let event = serde_json::json!({
+            "event": "complained",
+            "recipient": "patient@example.com",
+            "timestamp": 1714557600.5,
+            "message": { "headers": { "message-id": "mg-1@example.com" } }
+        });
+
+        let body = mailgun_body("mailgun-key", Utc::now().timestamp(), event.clone());
+        let events = tracking.handle_webhook(WebhookProvider::Mailgun, &HashMap::new(), &body).await.unwrap();
+        assert_eq!(events[0].kind, DeliveryEventKind::Complained);
+        assert!(tracking.is_suppressed("patient@example.com").await.unwrap());
+
+        let forged = mailgun_body("other-key", Utc::now().timestamp(), event.clone());
+        let result = tracking.handle_webhook(WebhookProvider::Mailgun, &HashMap::new(), &forged).await;
+        assert!(matches!(result, Err(EmailError::WebhookVerificationFailed(_))));
+
+        let replayed = mailgun_body("mailgun-key", Utc::now().timestamp() - 3600, event);
+        let result = tracking.handle_webhook(WebhookProvider::Mailgun, &HashMap::new(), &replayed).await;
+        assert!(matches!(result, Err(EmailError::WebhookVerificationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_ses_rejects_unknown_topic() {
+        let tracking = EmailTracking::new();
+        let result = tracking
+            .handle_webhook(WebhookProvider::Ses, &HashMap::new(), &ses_bounce("Permanent"))
+            .await;
+        assert!(matches!(result, Err(EmailError::WebhookVerificationFailed(_))));
+        assert!(!tracking.is_suppressed("patient@example.com").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_ses_rejects_stale_timestamp() {
+        let tracking = EmailTracking::new().with_secrets(WebhookSecrets {
+            ses_topic_arns: vec!["arn:aws:sns:us-east-1:123456789012:ses-events".to_string()],
+            ..WebhookSecrets::default()
+        });
+
+        // Rejected on its timestamp before the signing certificate is fetched
+        let result = tracking
+            .handle_webhook(WebhookProvider::Ses, &HashMap::new(), &ses_bounce("Permanent"))
+            .await;
+        assert!(matches!(result, Err(EmailError::WebhookVerificationFailed(reason)) if reason.contains("window")));
+    }
+
+    #[tokio::test]
+    async fn test_expired_sns_key_is_evicted() {
+        let tracking = EmailTracking::new();
+        let url = "https://sns.us-east-1.amazonaws.com/cert.pem";
+        let cache = |not_after| SnsSigningKey { key: vec![1, 2, 3], not_after };
+
+        tracking.sns_keys.write().await.insert(url.to_string(), cache(Utc::now() + chrono::Duration::hours(1)));
+        assert_eq!(tracking.cached_sns_key(url).await, Some(vec![1, 2, 3]));
+
+        tracking.sns_keys.write().await.insert(url.to_string(), cache(Utc::now() - chrono::Duration::seconds(1)));
+        assert_eq!(tracking.cached_sns_key(url).await, None);
+        assert!(tracking.sns_keys.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_late_delivery_does_not_regress_status() {
+        let tracking = EmailTracking::new();
+        let event = |kind| DeliveryEvent {
+            provider: WebhookProvider::SendGrid,
+            message_id: "m1".to_string(),
+            recipient: "a@example.com".to_string(),
+            kind,
+            timestamp: Utc::now(),
+            reason: None,
+        };
+
+        tracking.apply(&event(DeliveryEventKind::Opened)).await.unwrap();
+        tracking.apply(&event(DeliveryEventKind::Delivered)).await.unwrap();
+
+        let record = tracking.record("m1", "a@example.com").await.unwrap().unwrap();
+        assert_eq!(record.status, DeliveryStatus::Opened);
+        assert!(record.delivered_at.is_some());
+    }
+
+    #[test]
+    fn test_sns_cert_url_validation() {
+        assert!(validate_sns_cert_url("https://sns.us-east-1.amazonaws.com/SimpleNotificationService-abc.pem").is_ok());
+        assert!(validate_sns_cert_url("http://sns.us-east-1.amazonaws.com/cert.pem").is_err());
+        assert!(validate_sns_cert_url("https://sns.us-east-1.amazonaws.com.evil.com/cert.pem").is_err());
+        assert!(validate_sns_cert_url("https://sns.us-east-1.amazonaws.com/cert.txt").is_err());
+        assert!(validate_sns_cert_url("https://sns.cn-north-1.amazonaws.com.cn/cert.pem").is_ok());
+        assert!(validate_sns_cert_url("https://sns.evil.s3.amazonaws.com/cert.pem").is_err());
+        assert!(validate_sns_cert_url("https://sns..amazonaws.com/cert.pem").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscription_confirmation_rejects_lookalike_host() {
+        let tracking = EmailTracking::new();
+        let envelope: SnsEnvelope = serde_json::from_value(serde_json::json!({
+            "Type": "SubscriptionConfirmation",
+            "MessageId": "m-1",
+            "TopicArn": "arn:aws:sns:us-east-1:123456789012:ses-events",
+            "Message": "confirm",
+            "Timestamp": "2024-01-01T00:00:00Z",
+            "SubscribeURL": "https://sns.evil.s3.amazonaws.com/?Action=ConfirmSubscription",
+            "SignatureVersion": "1",
+            "Signature": "",
+            "SigningCertURL": "https://sns.us-east-1.amazonaws.com/cert.pem",
+        }))
+        .unwrap();
+        assert!(tracking.confirm_sns_subscription(&envelope).await.is_err());
     }
-}
\ No newline at end of file
+}
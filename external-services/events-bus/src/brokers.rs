@@ -1,4 +1,7 @@
 // Event brokers stub (Kafka, RabbitMQ, Redis, etc.)
+//
+// Backends secure their connections with `crate::tls::BrokerTlsConfig`, as
+// `NatsJetStreamBroker::connect` does, rather than their own TLS settings.
 pub trait EventBroker {
     async fn connect(&self) -> crate::error::Result<()>;
     async fn disconnect(&self) -> crate::error::Result<()>;
@@ -1,10 +1,88 @@
-// Event bus implementation stub
+// In-process event bus
+use crate::error::Result;
+use crate::event::Event;
+use crate::partition::{PartitionConfig, PartitionedSubscription};
+use crate::subscriber::EventSubscriber;
+use std::future::Future;
+use tokio::sync::broadcast;
+
+/// Fan-out of events to every live subscriber in this process
+///
+/// Each subscriber gets its own bounded queue of `capacity` events. A
+/// subscriber that falls further behind loses the oldest events and is told
+/// how many it missed (see [`EventSubscriber::recv`]); publishers never
+/// block. Dropping a subscriber unsubscribes it.
+#[derive(Clone)]
 pub struct EventBus {
-    // Implementation will go here
+    sender: broadcast::Sender<Event>,
 }
 
 impl EventBus {
-    pub async fn new() -> crate::error::Result<Self> {
-        Ok(Self {})
+    /// Events buffered per subscriber by [`EventBus::new`]
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    pub async fn new() -> Result<Self> {
+        Ok(Self::with_capacity(Self::DEFAULT_CAPACITY))
+    }
+
+    /// Create a bus buffering up to `capacity` events per subscriber
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Deliver `event` to current subscribers, returning how many received it
+    ///
+    /// Publishing with no subscribers is not an error; the event is dropped.
+    pub fn publish(&self, event: impl Into<Event>) -> usize {
+        self.sender.send(event.into()).unwrap_or(0)
+    }
+
+    /// Subscribe to events whose type matches `pattern`
+    ///
+    /// Patterns use NATS subject syntax: tokens are separated by `.`, `*`
+    /// matches exactly one token and a trailing `>` matches one or more.
+    pub async fn subscribe(&self, pattern: &str) -> Result<EventSubscriber> {
+        Ok(EventSubscriber::new(self.sender.subscribe(), pattern))
     }
-}
\ No newline at end of file
+
+    /// Deliver events matching `pattern` to `handler`, in order per partition key
+    ///
+    /// Events sharing a [`partition_key`](Event::partition_key) are handled
+    /// one at a time in publish order, including redeliveries after a
+    /// failure; different keys are handled in parallel. See
+    /// [`PartitionConfig`] for choosing the partition count.
+    pub async fn subscribe_partitioned<F, Fut>(
+        &self,
+        pattern: &str,
+        config: PartitionConfig,
+        handler: F,
+    ) -> Result<PartitionedSubscription>
+    where
+        F: Fn(Event) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let subscriber = self.subscribe(pattern).await?;
+        Ok(PartitionedSubscription::spawn(subscriber, config, handler))
+    }
+
+    /// Number of live subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+/// Whether `event_type` matches the subscription `pattern`
+pub fn event_type_matches(pattern: &str, event_type: &str) -> bool {
+    let mut pattern_tokens = pattern.split('.');
+    let mut type_tokens = event_type.split('.');
+    loop {
+        match (pattern_tokens.next(), type_tokens.next()) {
+            (Some(">"), Some(_)) => return pattern_tokens.next().is_none(),
+            (Some("*"), Some(_)) => {}
+            (Some(expected), Some(actual)) if expected == actual => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// Event payload compression
+//
+// Publishers compress payloads of at least `CompressionConfig::min_size`
+// bytes and name the algorithm in the `content-encoding` header of the
+// message. Subscribers decompress according to that header alone, so a
+// subscriber reads messages from publishers with any setting, and messages
+// published before compression existed carry no header and are read as is.
+// A header naming an algorithm this build does not know is an error, not
+// something to pass through.
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EventBusError, Result};
+
+/// Message header naming the payload compression
+pub const CONTENT_ENCODING_HEADER: &str = "content-encoding";
+
+/// Compression of an event payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    Gzip,
+    Zstd,
+}
+
+impl CompressionAlgorithm {
+    /// Value of the `content-encoding` header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Gzip => "gzip",
+            CompressionAlgorithm::Zstd => "zstd",
+        }
+    }
+}
+
+impl fmt::Display for CompressionAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CompressionAlgorithm {
+    type Err = EventBusError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gzip" => Ok(CompressionAlgorithm::Gzip),
+            "zstd" => Ok(CompressionAlgorithm::Zstd),
+            _ => Err(EventBusError::UnsupportedCompression(s.to_string())),
+        }
+    }
+}
+
+/// Publisher side compression settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    /// Algorithm for new messages; `None` publishes uncompressed
+    pub algorithm: Option<CompressionAlgorithm>,
+    /// Payloads smaller than this many bytes are sent uncompressed
+    pub min_size: usize,
+    /// Largest payload a subscriber will inflate a message to, bounding the
+    /// memory a hostile or corrupt message can claim
+    pub max_decompressed_size: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: None,
+            min_size: 1024,
+            max_decompressed_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn new(algorithm: CompressionAlgorithm) -> Self {
+        Self {
+            algorithm: Some(algorithm),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+}
+
+/// Compression counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CompressionMetrics {
+    /// Messages published compressed
+    pub compressed: u64,
+    /// Messages published uncompressed: below the threshold, or not smaller
+    /// once compressed
+    pub skipped: u64,
+    /// Payload bytes of the compressed messages before compression
+    pub bytes_before: u64,
+    /// Payload bytes of the compressed messages after compression
+    pub bytes_after: u64,
+    pub decompressed: u64,
+    pub decompression_failures: u64,
+}
+
+impl CompressionMetrics {
+    /// Compressed size over original size of the compressed messages, e.g.
+    /// 0.25 for payloads shrunk to a quarter; 1.0 before any compression
+    pub fn ratio(&self) -> f64 {
+        if self.bytes_before == 0 {
+            1.0
+        } else {
+            self.bytes_after as f64 / self.bytes_before as f64
+        }
+    }
+}
+
+/// Compresses outgoing and decompresses incoming payloads, counting both
+#[derive(Debug, Default)]
+pub struct PayloadCompression {
+    config: CompressionConfig,
+    compressed: AtomicU64,
+    skipped: AtomicU64,
+    bytes_before: AtomicU64,
+    bytes_after: AtomicU64,
+    decompressed: AtomicU64,
+    decompression_failures: AtomicU64,
+}
+
+impl PayloadCompression {
+    pub fn new(config: CompressionConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> &CompressionConfig {
+        &self.config
+    }
+
+    /// Payload to publish and the `content-encoding` to send with it, if any
+    pub fn compress(&self, payload: Vec<u8>) -> Result<(Vec<u8>, Option<CompressionAlgorithm>)> {
+        let Some(algorithm) = self.config.algorithm else {
+            return Ok((payload, None));
+        };
+        if payload.len() < self.config.min_size {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            return Ok((payload, None));
+        }
+
+        let compressed = match algorithm {
+            CompressionAlgorithm::Gzip => {
+                let mut encoder =
+                    flate2::read::GzEncoder::new(payload.as_slice(), flate2::Compression::default());
+                let mut out = Vec::new();
+                encoder.read_to_end(&mut out).map(|_| out)
+            }
+            CompressionAlgorithm::Zstd => zstd::stream::encode_all(payload.as_slice(), 0),
+        }
+        .map_err(|e| EventBusError::CompressionFailed {
+            algorithm: algorithm.to_string(),
+            reason: e.to_string(),
+        })?;
+
+        if compressed.len() >= payload.len() {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            return Ok((payload, None));
+        }
+        self.compressed.fetch_add(1, Ordering::Relaxed);
+        self.bytes_before.fetch_add(payload.len() as u64, Ordering::Relaxed);
+        self.bytes_after.fetch_add(compressed.len() as u64, Ordering::Relaxed);
+        Ok((compressed, Some(algorithm)))
+    }
+
+    /// Original payload of a message received with `content_encoding`
+    pub fn decompress(&self, content_encoding: Option<&str>, payload: &[u8]) -> Result<Vec<u8>> {
+        let result = self.inflate(content_encoding, payload);
+        match (&result, content_encoding) {
+            (Err(_), _) => self.decompression_failures.fetch_add(1, Ordering::Relaxed),
+            (Ok(_), Some(_)) => self.decompressed.fetch_add(1, Ordering::Relaxed),
+            (Ok(_), None) => 0,
+        };
+        result
+    }
+
+    fn inflate(&self, content_encoding: Option<&str>, payload: &[u8]) -> Result<Vec<u8>> {
+        let algorithm = match content_encoding {
+            None => return Ok(payload.to_vec()),
+            Some(encoding) if encoding.trim().eq_ignore_ascii_case("identity") => {
+                return Ok(payload.to_vec())
+            }
+            Some(encoding) => encoding.parse::<CompressionAlgorithm>()?,
+        };
+        let failed = |reason: String| EventBusError::DecompressionFailed {
+            algorithm: algorithm.to_string(),
+            reason,
+        };
+
+        let reader: Box<dyn Read + '_> = match algorithm {
+            CompressionAlgorithm::Gzip => Box::new(flate2::read::GzDecoder::new(payload)),
+            CompressionAlgorithm::Zstd => {
+                Box::new(zstd::stream::read::Decoder::new(payload).map_err(|e| failed(e.to_string()))?)
+            }
+        };
+        let limit = self.config.max_decompressed_size;
+        let mut out = Vec::new();
+        reader
+            .take(limit as u64 + 1)
+            .read_to_end(&mut out)
+            .map_err(|e| failed(e.to_string()))?;
+        if out.len() > limit {
+            return Err(failed(format!("payload inflates beyond {} bytes", limit)));
+        }
+        Ok(out)
+    }
+
+    pub fn metrics(&self) -> CompressionMetrics {
+        CompressionMetrics {
+            compressed: self.compressed.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            bytes_before: self.bytes_before.load(Ordering::Relaxed),
+            bytes_after: self.bytes_after.load(Ordering::Relaxed),
+            decompressed: self.decompressed.load(Ordering::Relaxed),
+            decompression_failures: self.decompression_failures.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -17,15 +17,51 @@ pub enum EventBusError {
     #[error("Event broker connection failed")]
     BrokerConnectionError,
     
+    #[error("Invalid broker TLS configuration: {0}")]
+    TlsConfigError(String),
+    
+    #[error("TLS material '{name}' could not be fetched: {reason}")]
+    TlsMaterialUnavailable { name: String, reason: String },
+    
+    #[error("TLS material '{name}' is not usable: {reason}")]
+    TlsMaterialInvalid { name: String, reason: String },
+    
+    #[error("Broker certificate is not trusted by the configured CA: {0}")]
+    TlsUntrustedServer(String),
+    
+    #[error("Broker certificate is not valid for {0}; check the configured server name")]
+    TlsServerNameMismatch(String),
+    
+    #[error("Broker certificate is expired or not yet valid: {0}")]
+    TlsServerCertificateExpired(String),
+    
+    #[error("Broker rejected the client certificate: {0}")]
+    TlsClientCertificateRejected(String),
+    
+    #[error("TLS handshake with the broker failed: {0}")]
+    TlsHandshakeFailed(String),
+    
     #[error("Event processing timeout")]
     ProcessingTimeout,
     
     #[error("Invalid event format")]
     InvalidEventFormat,
     
+    #[error("Unsupported payload compression '{0}'")]
+    UnsupportedCompression(String),
+    
+    #[error("Payload compression with {algorithm} failed: {reason}")]
+    CompressionFailed { algorithm: String, reason: String },
+    
+    #[error("Payload is not valid {algorithm} data: {reason}")]
+    DecompressionFailed { algorithm: String, reason: String },
+    
     #[error("Event queue full")]
     QueueFullError,
     
+    #[error("Subscriber fell behind and missed {0} events")]
+    Lagged(u64),
+    
     #[error("Event handler not found")]
     HandlerNotFound,
     
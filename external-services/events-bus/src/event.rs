@@ -9,12 +9,25 @@ pub struct Event {
     pub event_type: String,
     pub data: serde_json::Value,
     pub timestamp: DateTime<Utc>,
+    /// Events with the same key are delivered in order by partitioned
+    /// subscriptions, e.g. the patient id for patient-scoped events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_key: Option<String>,
+}
+
+impl Event {
+    pub fn with_partition_key(mut self, key: impl Into<String>) -> Self {
+        self.partition_key = Some(key.into());
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DomainEvent {
     pub event_type: String,
     pub data: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_key: Option<String>,
 }
 
 impl DomainEvent {
@@ -22,6 +35,24 @@ impl DomainEvent {
         Self {
             event_type: event_type.to_string(),
             data,
+            partition_key: None,
         }
     }
-}
\ No newline at end of file
+
+    pub fn with_partition_key(mut self, key: impl Into<String>) -> Self {
+        self.partition_key = Some(key.into());
+        self
+    }
+}
+
+impl From<DomainEvent> for Event {
+    fn from(event: DomainEvent) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type: event.event_type,
+            data: event.data,
+            timestamp: Utc::now(),
+            partition_key: event.partition_key,
+        }
+    }
+}
@@ -4,10 +4,13 @@
 //! - Publish/Subscribe patterns
 //! - Event sourcing capabilities
 //! - Multiple broker backends (Kafka, RabbitMQ, Redis, In-Memory)
+//! - TLS and mutual TLS to brokers, with certificates from the secrets service
 //! - Guaranteed delivery and at-least-once semantics
+//! - In-order delivery per partition key (e.g. per patient), parallel across keys
 //! - Dead letter queues for failed events
 //! - Event replay and time travel debugging
 //! - Schema evolution and versioning
+//! - Gzip or zstd compression of large payloads, recorded per message
 //! 
 //! # Event Types
 //! 
@@ -35,7 +38,7 @@
 //!         })
 //!     );
 //!     
-//!     bus.publish(event).await?;
+//!     bus.publish(event);
 //!     
 //!     // Subscribe to events
 //!     let mut subscriber = bus.subscribe("user.*").await?;
@@ -55,10 +58,16 @@ pub mod subscriber;
 pub mod publisher;
 pub mod error;
 pub mod nats;
+pub mod tls;
+pub mod partition;
+pub mod compression;
 
 pub use bus::*;
 pub use event::*;
 pub use subscriber::*;
 pub use publisher::*;
 pub use error::*;
-pub use nats::*;
\ No newline at end of file
+pub use nats::*;
+pub use tls::*;
+pub use partition::*;
+pub use compression::*;
\ No newline at end of file
@@ -1,6 +1,8 @@
 use async_nats;
+use crate::compression::{CompressionConfig, CompressionMetrics, PayloadCompression, CONTENT_ENCODING_HEADER};
 use crate::event::Event;
 use crate::error::{EventBusError, Result as EventBusResult};
+use crate::tls::{BrokerTlsConfig, TlsMaterialSource};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::collections::HashMap;
@@ -12,6 +14,7 @@ use chrono::Utc;
 pub struct NatsJetStreamBroker {
     client: Arc<async_nats::Client>,
     subscriptions: Arc<RwLock<HashMap<String, tokio::task::JoinHandle<()>>>>,
+    compression: Arc<PayloadCompression>,
 }
 
 impl NatsJetStreamBroker {
@@ -19,10 +22,50 @@ impl NatsJetStreamBroker {
         let client = async_nats::connect(nats_url).await
             .map_err(|_| EventBusError::BrokerConnectionError)?;
         
-        Ok(Self {
+        Ok(Self::from_client(client))
+    }
+
+    /// Connect with the transport security of `tls`, fetching certificates from `source`
+    ///
+    /// With TLS disabled this is the same as [`NatsJetStreamBroker::new`].
+    pub async fn connect(nats_url: &str, tls: &BrokerTlsConfig, source: &dyn TlsMaterialSource) -> EventBusResult<Self> {
+        if !tls.enabled {
+            return Self::new(nats_url).await;
+        }
+        let prepared = tls.prepare(source).await?;
+        let mut options = async_nats::ConnectOptions::new()
+            .require_tls(true)
+            .tls_client_config(prepared.client_config());
+        if tls.handshake_first {
+            options = options.tls_first();
+        }
+
+        let client = options.connect(nats_url).await.map_err(|e| match e.kind() {
+            async_nats::ConnectErrorKind::Tls | async_nats::ConnectErrorKind::Io => prepared.connect_error(e),
+            _ => EventBusError::BrokerConnectionError,
+        })?;
+        Ok(Self::from_client(client))
+    }
+
+    fn from_client(client: async_nats::Client) -> Self {
+        Self {
             client: Arc::new(client),
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
-        })
+            compression: Arc::new(PayloadCompression::default()),
+        }
+    }
+
+    /// Compress published payloads per `config`
+    ///
+    /// Subscriptions decompress whatever the publisher chose, with or
+    /// without this setting.
+    pub fn with_compression(mut self, config: CompressionConfig) -> Self {
+        self.compression = Arc::new(PayloadCompression::new(config));
+        self
+    }
+
+    pub fn compression_metrics(&self) -> CompressionMetrics {
+        self.compression.metrics()
     }
 
     pub async fn publish_event(&self, event: &Event) -> EventBusResult<()> {
@@ -32,6 +75,9 @@ impl NatsJetStreamBroker {
         let mut headers = async_nats::HeaderMap::new();
         headers.insert("event_id", event.id.to_string().as_str());
         headers.insert("timestamp", event.timestamp.to_rfc3339().as_str());
+        if let Some(partition_key) = &event.partition_key {
+            headers.insert("partition_key", partition_key.as_str());
+        }
         
         // HIPAA compliance tracking - extract from data field
         if let Some(patient_id) = event.data.get("patient_id").and_then(|v| v.as_str()) {
@@ -45,6 +91,10 @@ impl NatsJetStreamBroker {
 
         let payload = serde_json::to_vec(&event)
             .map_err(|_| EventBusError::SerializationError)?;
+        let (payload, algorithm) = self.compression.compress(payload)?;
+        if let Some(algorithm) = algorithm {
+            headers.insert(CONTENT_ENCODING_HEADER, algorithm.as_str());
+        }
 
         self.client.publish_with_headers(subject, headers, payload.into()).await
             .map_err(|_| EventBusError::PublishError)?;
@@ -60,11 +110,22 @@ impl NatsJetStreamBroker {
         let client = self.client.clone();
         let subject = subject.to_string();
         let handler = Arc::new(handler);
+        let compression = self.compression.clone();
         
         let handle = tokio::spawn(async move {
             if let Ok(mut subscriber) = client.subscribe(subject).await {
                 while let Some(message) = subscriber.next().await {
-                    if let Ok(event) = serde_json::from_slice::<Event>(&message.payload) {
+                    let encoding = message.headers.as_ref()
+                        .and_then(|headers| headers.get(CONTENT_ENCODING_HEADER))
+                        .map(|value| value.as_str().to_string());
+                    let payload = match compression.decompress(encoding.as_deref(), &message.payload) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            tracing::error!(subject = %message.subject, "Dropping undecodable event: {}", e);
+                            continue;
+                        }
+                    };
+                    if let Ok(event) = serde_json::from_slice::<Event>(&payload) {
                         if let Err(e) = handler(event) {
                             eprintln!("Handler error: {}", e);
                         }
@@ -91,6 +152,7 @@ impl NatsJetStreamBroker {
             id: Uuid::new_v4(),
             event_type: "audit.access".to_string(),
             timestamp: Utc::now(),
+            partition_key: None,
             data: serde_json::json!({
                 "user_id": user_id,
                 "resource": resource,
@@ -109,6 +171,7 @@ impl NatsJetStreamBroker {
             id: Uuid::new_v4(),
             event_type: if value > threshold * 1.2 { "vitals.critical" } else { "vitals.alert" }.to_string(),
             timestamp: Utc::now(),
+            partition_key: Some(patient_id.to_string()),
             data: serde_json::json!({
                 "patient_id": patient_id,
                 "vital_type": vital_type,
@@ -0,0 +1,294 @@
+// Ordered delivery per partition key
+//
+// A partitioned subscription delivers the events of one partition key
+// strictly in publish order, one at a time, and events of different keys in
+// parallel. Each key with undelivered events has its own queue; a key takes
+// one of `PartitionConfig::partitions` delivery slots while it has events
+// and gives it back after each event, so keys are not pinned to a slot the
+// way hashed partitions pin them. A slow or failing handler for one key
+// therefore only holds that key's slot, never the keys that would have
+// hashed next to it.
+//
+// The cost is throughput per key: a key gets at most one event per handler
+// round trip, redeliveries included, however many slots are idle. The
+// partition count bounds how many handlers run at once, so it is chosen from
+// what the handler's downstream (database pool, remote API) can take rather
+// than from the number of keys: total throughput is roughly
+// `partitions / handler latency`, until most slots are held by slow keys.
+//
+// A failed delivery is retried in place with backoff before the next event
+// of the same key is handed out. After `RedeliveryPolicy::max_attempts` the
+// event is set aside as a dead letter and the key moves on, so one poison
+// event does not stall a patient's stream indefinitely. Events without a
+// key have no ordering and are delivered as slots free up.
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
+
+use crate::error::Result;
+use crate::event::Event;
+use crate::subscriber::EventSubscriber;
+
+/// How failed deliveries are retried
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedeliveryPolicy {
+    /// Deliveries of one event, the first included, before it is dead-lettered
+    pub max_attempts: u32,
+    /// Wait before the first redelivery; doubled for each one after
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RedeliveryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RedeliveryPolicy {
+    /// Wait before redelivering after failed attempt number `attempt`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// Settings of a partitioned subscription
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionConfig {
+    /// Events delivered at the same time, each for a different key
+    ///
+    /// Sized to the concurrency the handler's downstream can take, not to
+    /// the number of keys; a single key never uses more than one.
+    pub partitions: usize,
+    /// Events received but not yet delivered; when reached the subscription
+    /// stops reading and falls behind on the bus like a slow subscriber
+    pub max_buffered: usize,
+    pub redelivery: RedeliveryPolicy,
+}
+
+impl Default for PartitionConfig {
+    fn default() -> Self {
+        Self {
+            partitions: 16,
+            max_buffered: 10_000,
+            redelivery: RedeliveryPolicy::default(),
+        }
+    }
+}
+
+/// An event given up on after every delivery attempt failed
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub event: Event,
+    pub attempts: u32,
+    /// Error of the last attempt
+    pub error: String,
+}
+
+/// Counters of a partitioned subscription
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PartitionStats {
+    pub delivered: u64,
+    pub redelivered: u64,
+    pub dead_lettered: u64,
+    /// Events missed because the subscription fell behind the bus
+    pub missed: u64,
+    /// Events received and not yet delivered
+    pub buffered: usize,
+    /// Keys with events in flight or queued
+    pub active_keys: usize,
+}
+
+type Handler = Box<dyn Fn(Event) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+struct Queued {
+    event: Event,
+    _buffered: OwnedSemaphorePermit,
+}
+
+struct Shared {
+    handler: Handler,
+    redelivery: RedeliveryPolicy,
+    slots: Arc<Semaphore>,
+    buffer: Arc<Semaphore>,
+    max_buffered: usize,
+    /// Queue per key with an event in flight; absent keys are idle
+    keys: Mutex<HashMap<String, VecDeque<Queued>>>,
+    dead_letters: Mutex<Vec<DeadLetter>>,
+    delivered: AtomicU64,
+    redelivered: AtomicU64,
+    dead_lettered: AtomicU64,
+    missed: AtomicU64,
+}
+
+/// Subscription delivering events in order per partition key
+///
+/// Dropping it stops receiving new events; events already received are
+/// still delivered.
+pub struct PartitionedSubscription {
+    shared: Arc<Shared>,
+    dispatcher: JoinHandle<()>,
+}
+
+impl PartitionedSubscription {
+    pub(crate) fn spawn<F, Fut>(subscriber: EventSubscriber, config: PartitionConfig, handler: F) -> Self
+    where
+        F: Fn(Event) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let max_buffered = config.max_buffered.max(1);
+        let shared = Arc::new(Shared {
+            handler: Box::new(move |event| handler(event).boxed()),
+            redelivery: config.redelivery,
+            slots: Arc::new(Semaphore::new(config.partitions.max(1))),
+            buffer: Arc::new(Semaphore::new(max_buffered)),
+            max_buffered,
+            keys: Mutex::new(HashMap::new()),
+            dead_letters: Mutex::new(Vec::new()),
+            delivered: AtomicU64::new(0),
+            redelivered: AtomicU64::new(0),
+            dead_lettered: AtomicU64::new(0),
+            missed: AtomicU64::new(0),
+        });
+        let dispatcher = tokio::spawn(dispatch(shared.clone(), subscriber));
+        Self { shared, dispatcher }
+    }
+
+    pub fn stats(&self) -> PartitionStats {
+        PartitionStats {
+            delivered: self.shared.delivered.load(Ordering::Relaxed),
+            redelivered: self.shared.redelivered.load(Ordering::Relaxed),
+            dead_lettered: self.shared.dead_lettered.load(Ordering::Relaxed),
+            missed: self.shared.missed.load(Ordering::Relaxed),
+            buffered: self.shared.max_buffered - self.shared.buffer.available_permits(),
+            active_keys: lock(&self.shared.keys).len(),
+        }
+    }
+
+    /// Dead letters since the last call
+    pub fn take_dead_letters(&self) -> Vec<DeadLetter> {
+        std::mem::take(&mut *lock(&self.shared.dead_letters))
+    }
+}
+
+impl Drop for PartitionedSubscription {
+    fn drop(&mut self) {
+        self.dispatcher.abort();
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Read events off the bus in publish order and hand them to their key
+async fn dispatch(shared: Arc<Shared>, mut subscriber: EventSubscriber) {
+    loop {
+        let event = match subscriber.recv().await {
+            Ok(Some(event)) => event,
+            Ok(None) => return,
+            Err(crate::error::EventBusError::Lagged(missed)) => {
+                shared.missed.fetch_add(missed, Ordering::Relaxed);
+                tracing::warn!(pattern = %subscriber.pattern(), missed, "Partitioned subscription fell behind");
+                continue;
+            }
+            Err(_) => continue,
+        };
+        let Ok(permit) = shared.buffer.clone().acquire_owned().await else {
+            return;
+        };
+        let queued = Queued {
+            event,
+            _buffered: permit,
+        };
+
+        match queued.event.partition_key.clone() {
+            Some(key) => {
+                {
+                    let mut keys = lock(&shared.keys);
+                    if let Some(queue) = keys.get_mut(&key) {
+                        queue.push_back(queued);
+                        continue;
+                    }
+                    keys.insert(key.clone(), VecDeque::new());
+                }
+                tokio::spawn(run_key(shared.clone(), key, queued));
+            }
+            None => {
+                let shared = shared.clone();
+                tokio::spawn(async move {
+                    let Ok(_slot) = shared.slots.clone().acquire_owned().await else {
+                        return;
+                    };
+                    shared.deliver(queued).await;
+                });
+            }
+        }
+    }
+}
+
+/// Deliver the events of `key` one after the other until its queue is empty
+async fn run_key(shared: Arc<Shared>, key: String, first: Queued) {
+    let mut next = first;
+    loop {
+        {
+            let Ok(_slot) = shared.slots.clone().acquire_owned().await else {
+                return;
+            };
+            shared.deliver(next).await;
+        }
+        let mut keys = lock(&shared.keys);
+        match keys.get_mut(&key).and_then(VecDeque::pop_front) {
+            Some(queued) => next = queued,
+            None => {
+                keys.remove(&key);
+                return;
+            }
+        }
+    }
+}
+
+impl Shared {
+    async fn deliver(&self, queued: Queued) {
+        let event = queued.event;
+        let mut attempt = 1;
+        loop {
+            let outcome = AssertUnwindSafe((self.handler)(event.clone())).catch_unwind().await;
+            let error = match outcome {
+                Ok(Ok(())) => {
+                    self.delivered.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => "handler panicked".to_string(),
+            };
+            if attempt >= self.redelivery.max_attempts {
+                tracing::error!(event_id = %event.id, event_type = %event.event_type, attempts = attempt, error = %error, "Event dead-lettered");
+                self.dead_lettered.fetch_add(1, Ordering::Relaxed);
+                lock(&self.dead_letters).push(DeadLetter {
+                    event,
+                    attempts: attempt,
+                    error,
+                });
+                return;
+            }
+            tracing::warn!(event_id = %event.id, event_type = %event.event_type, attempt, error = %error, "Event delivery failed, redelivering");
+            tokio::time::sleep(self.redelivery.backoff(attempt)).await;
+            self.redelivered.fetch_add(1, Ordering::Relaxed);
+            attempt += 1;
+        }
+    }
+}
@@ -1,10 +1,54 @@
 // Event subscriber implementation
+use crate::bus::event_type_matches;
+use crate::error::{EventBusError, Result};
+use crate::event::Event;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Subscription to the events of an [`EventBus`](crate::EventBus)
+///
+/// Unsubscribes when dropped.
 pub struct EventSubscriber {
-    // Implementation will go here
+    receiver: broadcast::Receiver<Event>,
+    pattern: String,
 }
 
 impl EventSubscriber {
-    pub async fn next(&mut self) -> Option<crate::event::Event> {
-        None // Stub implementation
+    pub(crate) fn new(receiver: broadcast::Receiver<Event>, pattern: &str) -> Self {
+        Self {
+            receiver,
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// Pattern this subscriber was created with
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Next matching event, skipping over any that were missed
+    ///
+    /// Returns `None` once the bus is gone.
+    pub async fn next(&mut self) -> Option<Event> {
+        loop {
+            match self.recv().await {
+                Ok(event) => return event,
+                Err(e) => tracing::warn!(pattern = %self.pattern, "{}", e),
+            }
+        }
+    }
+
+    /// Next matching event, or `Lagged` if the subscriber fell behind
+    ///
+    /// After `Lagged` the subscriber resumes from the oldest event still
+    /// buffered. Returns `Ok(None)` once the bus is gone.
+    pub async fn recv(&mut self) -> Result<Option<Event>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if event_type_matches(&self.pattern, &event.event_type) => return Ok(Some(event)),
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => return Err(EventBusError::Lagged(missed)),
+                Err(RecvError::Closed) => return Ok(None),
+            }
+        }
     }
-}
\ No newline at end of file
+}
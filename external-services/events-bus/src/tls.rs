@@ -0,0 +1,310 @@
+// Transport encryption for broker connections
+//
+// Every broker backend takes a `BrokerTlsConfig` and turns it into a rustls
+// client configuration with `BrokerTlsConfig::prepare` when it connects, so
+// certificates are read fresh from the secret store for each connection and
+// a rotated certificate is picked up on the next connect. What goes wrong
+// during the handshake is reported as a specific `EventBusError` by
+// `PreparedTls::connect_error`, whatever the broker.
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, CertificateError, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EventBusError, Result};
+
+/// Source of PEM encoded certificates and keys, usually the secrets service
+#[async_trait]
+pub trait TlsMaterialSource: Send + Sync {
+    /// PEM contents of the secret `name`
+    async fn fetch(&self, name: &str) -> anyhow::Result<String>;
+}
+
+/// Fixed material keyed by secret name, for local development
+#[async_trait]
+impl TlsMaterialSource for HashMap<String, String> {
+    async fn fetch(&self, name: &str) -> anyhow::Result<String> {
+        self.get(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no such secret"))
+    }
+}
+
+/// TLS settings of a broker connection
+///
+/// Certificates and keys are named by their secret, not given inline, and
+/// are fetched from a [`TlsMaterialSource`] each time a connection is made.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BrokerTlsConfig {
+    /// Encrypt the connection; the other settings only apply when set
+    pub enabled: bool,
+    /// Secret with the CA bundle the broker certificate must chain to; the
+    /// platform trust store when unset
+    #[serde(default)]
+    pub ca_secret: Option<String>,
+    /// Secret with the client certificate chain, for mutual TLS
+    #[serde(default)]
+    pub client_cert_secret: Option<String>,
+    /// Secret with the private key of the client certificate
+    #[serde(default)]
+    pub client_key_secret: Option<String>,
+    /// Name the broker certificate must be valid for, when it differs from
+    /// the host in the broker URL (e.g. connecting through an IP address)
+    #[serde(default)]
+    pub server_name: Option<String>,
+    /// Start the TLS handshake before the broker's greeting instead of
+    /// upgrading after it; needed behind TLS terminating proxies
+    #[serde(default)]
+    pub handshake_first: bool,
+}
+
+impl BrokerTlsConfig {
+    /// TLS against the platform trust store, without a client certificate
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_ca(mut self, secret: impl Into<String>) -> Self {
+        self.ca_secret = Some(secret.into());
+        self
+    }
+
+    /// Present a client certificate (mutual TLS)
+    pub fn with_client_identity(mut self, cert_secret: impl Into<String>, key_secret: impl Into<String>) -> Self {
+        self.client_cert_secret = Some(cert_secret.into());
+        self.client_key_secret = Some(key_secret.into());
+        self
+    }
+
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    pub fn with_handshake_first(mut self) -> Self {
+        self.handshake_first = true;
+        self
+    }
+
+    /// Whether a client certificate is presented
+    pub fn is_mutual(&self) -> bool {
+        self.client_cert_secret.is_some()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match (&self.client_cert_secret, &self.client_key_secret) {
+            (Some(_), None) => {
+                return Err(EventBusError::TlsConfigError(
+                    "client certificate is set without a client key".to_string(),
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(EventBusError::TlsConfigError(
+                    "client key is set without a client certificate".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        if let Some(name) = &self.server_name {
+            ServerName::try_from(name.as_str()).map_err(|_| {
+                EventBusError::TlsConfigError(format!("server name '{}' is not a valid DNS name or IP address", name))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Fetch the material and build the client configuration for one connection
+    pub async fn prepare(&self, source: &dyn TlsMaterialSource) -> Result<PreparedTls> {
+        self.validate()?;
+        let roots = match &self.ca_secret {
+            Some(name) => ca_roots(name, &fetch(source, name).await?)?,
+            None => platform_roots()?,
+        };
+        let server_name = match &self.server_name {
+            Some(name) => Some(ServerName::try_from(name.as_str()).map_err(|e| EventBusError::TlsConfigError(e.to_string()))?),
+            None => None,
+        };
+        let verifier = Arc::new(RecordingVerifier {
+            inner: WebPkiVerifier::new(roots, None),
+            server_name,
+            outcome: Mutex::new(None),
+        });
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(verifier.clone());
+
+        let client_config = match (&self.client_cert_secret, &self.client_key_secret) {
+            (Some(cert_name), Some(key_name)) => {
+                let chain = certificates(cert_name, &fetch(source, cert_name).await?)?;
+                let key = private_key(key_name, &fetch(source, key_name).await?)?;
+                builder
+                    .with_client_auth_cert(chain, key)
+                    .map_err(|e| EventBusError::TlsMaterialInvalid {
+                        name: key_name.clone(),
+                        reason: e.to_string(),
+                    })?
+            }
+            _ => builder.with_no_client_auth(),
+        };
+
+        Ok(PreparedTls {
+            client_config,
+            verifier,
+            server_name: self.server_name.clone(),
+            mutual: self.is_mutual(),
+        })
+    }
+}
+
+/// Client configuration for one connection, and what its handshakes saw
+pub struct PreparedTls {
+    client_config: ClientConfig,
+    verifier: Arc<RecordingVerifier>,
+    server_name: Option<String>,
+    mutual: bool,
+}
+
+impl PreparedTls {
+    pub fn client_config(&self) -> ClientConfig {
+        self.client_config.clone()
+    }
+
+    /// Error for a connection attempt that failed with `detail`
+    ///
+    /// A rejected broker certificate is reported from what the verifier
+    /// recorded. An alert received after the broker certificate was accepted
+    /// means the broker refused our side of the handshake.
+    pub fn connect_error(&self, detail: impl std::fmt::Display) -> EventBusError {
+        let detail = detail.to_string();
+        let outcome = self
+            .verifier
+            .outcome
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        match outcome {
+            Some(Err(rustls::Error::InvalidCertificate(error))) => match error {
+                CertificateError::Expired | CertificateError::NotValidYet => {
+                    EventBusError::TlsServerCertificateExpired(format!("{:?}", error))
+                }
+                CertificateError::NotValidForName => EventBusError::TlsServerNameMismatch(
+                    self.server_name
+                        .as_ref()
+                        .map(|name| format!("'{}'", name))
+                        .unwrap_or_else(|| "the broker host".to_string()),
+                ),
+                other => EventBusError::TlsUntrustedServer(format!("{:?}", other)),
+            },
+            Some(Err(other)) => EventBusError::TlsUntrustedServer(other.to_string()),
+            Some(Ok(())) if detail.contains("alert") => {
+                if self.mutual {
+                    EventBusError::TlsClientCertificateRejected(detail)
+                } else {
+                    EventBusError::TlsClientCertificateRejected(format!(
+                        "{} (no client certificate is configured; the broker may require mutual TLS)",
+                        detail
+                    ))
+                }
+            }
+            _ => EventBusError::TlsHandshakeFailed(detail),
+        }
+    }
+}
+
+/// Verifies the broker certificate, remembering the result so a failed
+/// connection can say why
+struct RecordingVerifier {
+    inner: WebPkiVerifier,
+    server_name: Option<ServerName>,
+    outcome: Mutex<Option<std::result::Result<(), rustls::Error>>>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let server_name = self.server_name.as_ref().unwrap_or(server_name);
+        let result = self
+            .inner
+            .verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now);
+        *self.outcome.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            Some(result.as_ref().map(|_| ()).map_err(Clone::clone));
+        result
+    }
+}
+
+async fn fetch(source: &dyn TlsMaterialSource, name: &str) -> Result<String> {
+    source
+        .fetch(name)
+        .await
+        .map_err(|e| EventBusError::TlsMaterialUnavailable {
+            name: name.to_string(),
+            reason: e.to_string(),
+        })
+}
+
+fn invalid(name: &str, reason: impl Into<String>) -> EventBusError {
+    EventBusError::TlsMaterialInvalid {
+        name: name.to_string(),
+        reason: reason.into(),
+    }
+}
+
+fn certificates(name: &str, pem: &str) -> Result<Vec<Certificate>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(pem.as_bytes()))
+        .map_err(|e| invalid(name, e.to_string()))?;
+    if certs.is_empty() {
+        return Err(invalid(name, "no PEM certificate found"));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn private_key(name: &str, pem: &str) -> Result<PrivateKey> {
+    let items = rustls_pemfile::read_all(&mut BufReader::new(pem.as_bytes()))
+        .map_err(|e| invalid(name, e.to_string()))?;
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| invalid(name, "no PEM private key found"))
+}
+
+fn ca_roots(name: &str, pem: &str) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in certificates(name, pem)? {
+        roots.add(&cert).map_err(|e| invalid(name, e.to_string()))?;
+    }
+    Ok(roots)
+}
+
+fn platform_roots() -> Result<RootCertStore> {
+    let certs = rustls_native_certs::load_native_certs()
+        .map_err(|e| EventBusError::TlsConfigError(format!("could not load platform trust store: {}", e)))?;
+    let mut roots = RootCertStore::empty();
+    let der: Vec<Vec<u8>> = certs.into_iter().map(|cert| cert.0).collect();
+    roots.add_parsable_certificates(&der);
+    if roots.is_empty() {
+        return Err(EventBusError::TlsConfigError(
+            "platform trust store is empty; configure a CA secret".to_string(),
+        ));
+    }
+    Ok(roots)
+}
@@ -0,0 +1,352 @@
+//! Role-based access control for secrets
+//!
+//! Access is granted with Zanzibar tuples on `secret_path` objects. The object
+//! id is either a full secret key or a prefix pattern ending in `/*`:
+//!
+//! - `group:ops#member` is `reader` of `secret_path:kv/prod/*`
+//! - `service:release-bot` is `writer` of `secret_path:kv/prod/deploy/*`
+//!
+//! A caller may act on a key when they hold the relation on the key itself or
+//! on any pattern above it (`*` matches every key). `writer` implies `reader`.
+
+use crate::{Result, SecretsError};
+use auth_zanzibar::{AuthorizationEngine, Object, Relation, Subject, Tuple};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// Zanzibar object type for secret keys and path patterns
+pub const SECRET_PATH_OBJECT_TYPE: &str = "secret_path";
+
+/// The caller a secrets operation is performed for
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "id", rename_all = "lowercase")]
+pub enum Principal {
+    User(String),
+    Service(String),
+}
+
+impl Principal {
+    pub fn user(id: &str) -> Self {
+        Self::User(id.to_string())
+    }
+
+    pub fn service(id: &str) -> Self {
+        Self::Service(id.to_string())
+    }
+
+    /// The Zanzibar subject checks are evaluated for
+    pub fn subject(&self) -> Subject {
+        match self {
+            Self::User(id) => Subject::user(id),
+            Self::Service(id) => Subject::service(id),
+        }
+    }
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User(id) => write!(f, "user:{}", id),
+            Self::Service(id) => write!(f, "service:{}", id),
+        }
+    }
+}
+
+/// What an operation does to a secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretAction {
+    /// Get a secret or a version of it, list keys and versions
+    Read,
+    /// Set, rotate or delete a secret
+    Write,
+}
+
+impl SecretAction {
+    fn relation(self) -> Relation {
+        match self {
+            Self::Read => Relation::new("reader"),
+            Self::Write => Relation::new("writer"),
+        }
+    }
+}
+
+impl fmt::Display for SecretAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+        }
+    }
+}
+
+/// Decides secrets access with the Zanzibar authorization engine
+#[derive(Clone)]
+pub struct SecretsAuthorizer {
+    engine: Arc<AuthorizationEngine>,
+}
+
+impl SecretsAuthorizer {
+    pub fn new(engine: Arc<AuthorizationEngine>) -> Self {
+        Self { engine }
+    }
+
+    /// Grant `action` on every key matching `pattern`
+    ///
+    /// `grantee` may be a user or service subject, or a userset such as
+    /// `Subject::userset("group", "ops", "member")` to grant a whole team.
+    pub async fn grant(&self, grantee: Subject, action: SecretAction, pattern: &str) -> Result<()> {
+        let tuple = Tuple::new(grantee, action.relation(), policy_object(pattern)?);
+        self.engine
+            .write_tuple(tuple)
+            .await
+            .map_err(|e| SecretsError::AuthorizationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Remove a grant previously made with [`grant`](Self::grant)
+    pub async fn revoke(
+        &self,
+        grantee: Subject,
+        action: SecretAction,
+        pattern: &str,
+    ) -> Result<()> {
+        let tuple = Tuple::new(grantee, action.relation(), policy_object(pattern)?);
+        self.engine
+            .delete_tuple(tuple)
+            .await
+            .map_err(|e| SecretsError::AuthorizationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Whether `principal` may perform `action` on `key`
+    pub async fn is_allowed(
+        &self,
+        principal: &Principal,
+        action: SecretAction,
+        key: &str,
+    ) -> Result<bool> {
+        let allowed = self
+            .allowed_keys(principal, action, std::slice::from_ref(&key))
+            .await?;
+        Ok(!allowed.is_empty())
+    }
+
+    /// The subset of `keys` that `principal` may perform `action` on, in input
+    /// order
+    ///
+    /// All keys are decided in a single batch check.
+    pub async fn allowed_keys<K: AsRef<str>>(
+        &self,
+        principal: &Principal,
+        action: SecretAction,
+        keys: &[K],
+    ) -> Result<Vec<String>> {
+        let subject = principal.subject();
+        let relation = action.relation();
+
+        let mut owners = Vec::new();
+        let mut requests = Vec::new();
+        for (index, key) in keys.iter().enumerate() {
+            for candidate in matching_patterns(key.as_ref()) {
+                owners.push(index);
+                requests.push((
+                    subject.clone(),
+                    relation.clone(),
+                    Object::new(SECRET_PATH_OBJECT_TYPE, &candidate),
+                ));
+            }
+        }
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let responses = self
+            .engine
+            .batch_check(requests)
+            .await
+            .map_err(|e| SecretsError::AuthorizationFailed(e.to_string()))?;
+
+        let mut allowed = vec![false; keys.len()];
+        for (index, response) in owners.into_iter().zip(responses) {
+            if response.allowed {
+                allowed[index] = true;
+            }
+        }
+
+        Ok(keys
+            .iter()
+            .zip(allowed)
+            .filter(|(_, allowed)| *allowed)
+            .map(|(key, _)| key.as_ref().to_string())
+            .collect())
+    }
+}
+
+/// Policy object for a key or `prefix/*` pattern
+fn policy_object(pattern: &str) -> Result<Object> {
+    let pattern = pattern.trim_start_matches('/');
+    let valid = match pattern.find('*') {
+        None => !pattern.is_empty() && !pattern.ends_with('/'),
+        Some(position) => {
+            position == pattern.len() - 1 && (pattern == "*" || pattern.ends_with("/*"))
+        }
+    };
+    if !valid {
+        return Err(SecretsError::InvalidFormat(format!(
+            "invalid secret path pattern '{}': expected a key, 'prefix/*' or '*'",
+            pattern
+        )));
+    }
+    Ok(Object::new(SECRET_PATH_OBJECT_TYPE, pattern))
+}
+
+/// Policy object ids that cover `key`, most specific first
+///
+/// `kv/prod/db` is covered by `kv/prod/db`, `kv/prod/*`, `kv/*` and `*`.
+fn matching_patterns(key: &str) -> Vec<String> {
+    let key = key.trim_start_matches('/');
+    let mut patterns = vec![key.to_string()];
+    patterns.extend(
+        key.match_indices('/')
+            .rev()
+            .map(|(position, _)| format!("{}*", &key[..=position])),
+    );
+    patterns.push("*".to_string());
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use auth_zanzibar::repository::InMemoryTupleRepository;
+
+    async fn authorizer() -> SecretsAuthorizer {
+        let repository = Arc::new(InMemoryTupleRepository::new());
+        SecretsAuthorizer::new(Arc::new(
+            AuthorizationEngine::new(repository).await.unwrap(),
+        ))
+    }
+
+    #[test]
+    fn test_matching_patterns() {
+        assert_eq!(
+            matching_patterns("kv/prod/db"),
+            vec!["kv/prod/db", "kv/prod/*", "kv/*", "*"]
+        );
+        assert_eq!(matching_patterns("/token"), vec!["token", "*"]);
+    }
+
+    #[test]
+    fn test_policy_patterns() {
+        assert!(policy_object("kv/prod/*").is_ok());
+        assert!(policy_object("kv/prod/db").is_ok());
+        assert!(policy_object("*").is_ok());
+
+        for invalid in ["", "kv/prod/", "kv/pr*", "kv/*/db", "kv/**"] {
+            assert!(
+                matches!(policy_object(invalid), Err(SecretsError::InvalidFormat(_))),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefix_policies() {
+        let authz = authorizer().await;
+        let ops = Subject::userset("group", "ops", "member");
+        authz
+            .grant(ops, SecretAction::Read, "kv/prod/*")
+            .await
+            .unwrap();
+        authz
+            .grant(
+                Principal::service("release-bot").subject(),
+                SecretAction::Write,
+                "kv/prod/deploy/*",
+            )
+            .await
+            .unwrap();
+        authz
+            .engine
+            .write_tuple(Tuple::new(
+                Subject::user("alice"),
+                Relation::new("member"),
+                Object::new("group", "ops"),
+            ))
+            .await
+            .unwrap();
+
+        let alice = Principal::user("alice");
+        let bot = Principal::service("release-bot");
+
+        assert!(authz
+            .is_allowed(&alice, SecretAction::Read, "kv/prod/db/password")
+            .await
+            .unwrap());
+        assert!(!authz
+            .is_allowed(&alice, SecretAction::Write, "kv/prod/deploy/token")
+            .await
+            .unwrap());
+        assert!(!authz
+            .is_allowed(&alice, SecretAction::Read, "kv/staging/db")
+            .await
+            .unwrap());
+
+        // Writers can also read what they manage, and nothing else
+        assert!(authz
+            .is_allowed(&bot, SecretAction::Write, "kv/prod/deploy/token")
+            .await
+            .unwrap());
+        assert!(authz
+            .is_allowed(&bot, SecretAction::Read, "kv/prod/deploy/token")
+            .await
+            .unwrap());
+        assert!(!authz
+            .is_allowed(&bot, SecretAction::Write, "kv/prod/db/password")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_allowed_keys_filters() {
+        let authz = authorizer().await;
+        let carol = Principal::user("carol");
+        authz
+            .grant(carol.subject(), SecretAction::Read, "kv/prod/*")
+            .await
+            .unwrap();
+        authz
+            .grant(carol.subject(), SecretAction::Read, "kv/staging/api-key")
+            .await
+            .unwrap();
+
+        let keys = [
+            "kv/prod/db",
+            "kv/staging/db",
+            "kv/staging/api-key",
+            "kv/prod/deploy/token",
+        ];
+        assert_eq!(
+            authz
+                .allowed_keys(&carol, SecretAction::Read, &keys)
+                .await
+                .unwrap(),
+            vec!["kv/prod/db", "kv/staging/api-key", "kv/prod/deploy/token"]
+        );
+
+        authz
+            .revoke(carol.subject(), SecretAction::Read, "kv/prod/*")
+            .await
+            .unwrap();
+        assert_eq!(
+            authz
+                .allowed_keys(&carol, SecretAction::Read, &keys)
+                .await
+                .unwrap(),
+            vec!["kv/staging/api-key"]
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Broker TLS material
+//!
+//! Event bus brokers name their CA, client certificate and key by secret key
+//! (see [`events_bus::BrokerTlsConfig`]). [`SecretsTlsMaterial`] resolves
+//! those names against a provider, so certificates rotated in the secret
+//! store are used from the next broker connection on.
+
+use crate::SecretProvider;
+use async_trait::async_trait;
+use events_bus::TlsMaterialSource;
+use std::sync::Arc;
+
+/// Serves broker TLS material from a secret provider
+pub struct SecretsTlsMaterial {
+    provider: Arc<dyn SecretProvider>,
+}
+
+impl SecretsTlsMaterial {
+    pub fn new(provider: Arc<dyn SecretProvider>) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl TlsMaterialSource for SecretsTlsMaterial {
+    async fn fetch(&self, name: &str) -> anyhow::Result<String> {
+        Ok(self.provider.get_secret(name).await?.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MemoryProvider;
+    use events_bus::{BrokerTlsConfig, EventBusError};
+
+    #[tokio::test]
+    async fn test_missing_and_malformed_material_name_the_secret() {
+        let provider = Arc::new(MemoryProvider::default());
+        provider.set_secret("nats/ca", "not a certificate", None).await.unwrap();
+        let source = SecretsTlsMaterial::new(provider);
+
+        let tls = BrokerTlsConfig::enabled().with_ca("nats/ca");
+        match tls.prepare(&source).await {
+            Err(EventBusError::TlsMaterialInvalid { name, .. }) => assert_eq!(name, "nats/ca"),
+            other => panic!("expected invalid material, got {:?}", other.err()),
+        }
+
+        let tls = BrokerTlsConfig::enabled().with_ca("nats/missing-ca");
+        match tls.prepare(&source).await {
+            Err(EventBusError::TlsMaterialUnavailable { name, .. }) => assert_eq!(name, "nats/missing-ca"),
+            other => panic!("expected unavailable material, got {:?}", other.err()),
+        }
+    }
+}
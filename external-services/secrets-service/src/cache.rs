@@ -1,36 +1,134 @@
 //! Secret caching implementation
+//!
+//! Entries are fresh for the TTL. Keys covered by a [`CachePolicy`] with a
+//! stale-while-revalidate window are then served stale for that long while
+//! the manager refreshes them in the background, so reads never wait on an
+//! expiring entry.
 
+use crate::config::CachePolicy;
+use crate::invalidation::{record_eviction_lag, InvalidationBus};
 use crate::{Result, Secret};
 use moka::future::Cache;
-use std::time::Duration;
+use moka::ops::compute::Op;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
 
+#[derive(Clone)]
+struct CachedSecret {
+    secret: Secret,
+    fetched_at: Instant,
+}
+
+/// Result of a cache lookup
+#[derive(Debug, Clone)]
+pub enum CacheLookup {
+    /// Within the TTL
+    Fresh(Secret),
+    /// Past the TTL but inside the key's stale-while-revalidate window; the
+    /// caller should serve it and refresh in the background
+    Stale(Secret),
+    Miss,
+}
+
+#[derive(Clone)]
 pub struct SecretCache {
-    cache: Cache<String, Secret>,
+    cache: Cache<String, CachedSecret>,
     ttl: Duration,
+    policies: Arc<Vec<CachePolicy>>,
+    refreshing: Arc<Mutex<HashSet<String>>>,
 }
 
 impl SecretCache {
     pub fn new(ttl_seconds: u64, max_entries: usize) -> Self {
+        Self::with_policies(ttl_seconds, max_entries, Vec::new())
+    }
+    
+    /// Cache applying per-key warming and stale-while-revalidate policies
+    pub fn with_policies(ttl_seconds: u64, max_entries: usize, policies: Vec<CachePolicy>) -> Self {
+        let ttl = Duration::from_secs(ttl_seconds);
+        // Entries have to outlive the TTL by the longest stale window; each
+        // lookup enforces its own key's max age
+        let longest_stale = policies
+            .iter()
+            .map(|policy| policy.stale_while_revalidate_seconds)
+            .max()
+            .unwrap_or(0);
         let cache = Cache::builder()
             .max_capacity(max_entries as u64)
-            .time_to_live(Duration::from_secs(ttl_seconds))
+            .time_to_live(ttl + Duration::from_secs(longest_stale))
             .build();
         
         Self {
             cache,
-            ttl: Duration::from_secs(ttl_seconds),
+            ttl,
+            policies: Arc::new(policies),
+            refreshing: Arc::new(Mutex::new(HashSet::new())),
         }
     }
     
+    /// Cached secret that may be served now, fresh or stale
     pub async fn get(&self, key: &str) -> Option<Secret> {
-        self.cache.get(key).await
+        match self.lookup(key).await {
+            CacheLookup::Fresh(secret) | CacheLookup::Stale(secret) => Some(secret),
+            CacheLookup::Miss => None,
+        }
+    }
+    
+    /// Cached secret and whether it is due for a refresh
+    ///
+    /// Secrets past their own expiry, and entries past the key's max age,
+    /// are evicted and reported as a miss.
+    pub async fn lookup(&self, key: &str) -> CacheLookup {
+        let Some(entry) = self.cache.get(key).await else {
+            return CacheLookup::Miss;
+        };
+        let expired = entry
+            .secret
+            .metadata
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= chrono::Utc::now());
+        let age = entry.fetched_at.elapsed();
+        
+        if !expired && age < self.ttl {
+            CacheLookup::Fresh(entry.secret)
+        } else if !expired && age < self.ttl + self.stale_window(key) {
+            CacheLookup::Stale(entry.secret)
+        } else {
+            self.cache.invalidate(key).await;
+            CacheLookup::Miss
+        }
     }
     
     pub async fn set(&self, key: String, secret: Secret) -> Result<()> {
-        self.cache.insert(key, secret).await;
+        self.cache
+            .insert(key, CachedSecret { secret, fetched_at: Instant::now() })
+            .await;
         Ok(())
     }
     
+    /// Store the result of a background refresh
+    ///
+    /// Skipped if the entry was evicted or replaced since the refresh
+    /// started, so a refresh racing a rotation can't restore the old value.
+    pub(crate) async fn complete_refresh(&self, key: &str, started: Instant, secret: Secret) {
+        self.cache
+            .entry_by_ref(key)
+            .and_compute_with(|current| {
+                let op = match current {
+                    Some(entry) if entry.value().fetched_at <= started => {
+                        Op::Put(CachedSecret { secret, fetched_at: Instant::now() })
+                    }
+                    _ => Op::Nop,
+                };
+                std::future::ready(op)
+            })
+            .await;
+    }
+    
     pub async fn invalidate(&self, key: &str) -> Result<()> {
         self.cache.invalidate(key).await;
         Ok(())
@@ -44,4 +142,88 @@ impl SecretCache {
     pub fn ttl(&self) -> Duration {
         self.ttl
     }
+    
+    /// First configured policy matching `key`
+    pub fn policy_for(&self, key: &str) -> Option<&CachePolicy> {
+        self.policies.iter().find(|policy| policy.matches(key))
+    }
+    
+    /// Policies whose keys are fetched at startup
+    pub fn warm_policies(&self) -> impl Iterator<Item = &CachePolicy> {
+        self.policies.iter().filter(|policy| policy.warm)
+    }
+    
+    fn stale_window(&self, key: &str) -> Duration {
+        self.policy_for(key)
+            .map(|policy| Duration::from_secs(policy.stale_while_revalidate_seconds))
+            .unwrap_or_default()
+    }
+    
+    /// Claim the background refresh of `key`
+    ///
+    /// Returns `None` while another refresh of the key is running, so a
+    /// burst of stale reads triggers a single provider call.
+    pub(crate) fn begin_refresh(&self, key: &str) -> Option<RefreshGuard> {
+        let mut refreshing = self.refreshing.lock().unwrap();
+        if !refreshing.insert(key.to_string()) {
+            return None;
+        }
+        Some(RefreshGuard {
+            key: key.to_string(),
+            refreshing: self.refreshing.clone(),
+            started: Instant::now(),
+        })
+    }
+    
+    /// Cached keys with the provider version of each cached value
+    pub fn versions(&self) -> Vec<(String, Option<String>)> {
+        self.cache
+            .iter()
+            .map(|(key, entry)| (key.as_ref().clone(), entry.secret.metadata.version))
+            .collect()
+    }
+
+    /// Evict keys as invalidation events arrive on `bus`
+    ///
+    /// If the listener falls behind and events are dropped, the whole cache
+    /// is cleared rather than risk serving a rotated secret.
+    pub fn subscribe_to(&self, bus: &InvalidationBus) -> JoinHandle<()> {
+        let mut events = bus.subscribe();
+        let cache = self.cache.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        cache.invalidate(&event.key).await;
+                        record_eviction_lag(&event);
+                        debug!("Evicted '{}' from secret cache ({})", event.key, event.reason);
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Missed {} cache invalidations, clearing secret cache", missed);
+                        cache.invalidate_all();
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+/// An in-progress background refresh; releases the key when dropped
+pub(crate) struct RefreshGuard {
+    key: String,
+    refreshing: Arc<Mutex<HashSet<String>>>,
+    started: Instant,
+}
+
+impl RefreshGuard {
+    pub(crate) fn started(&self) -> Instant {
+        self.started
+    }
+}
+
+impl Drop for RefreshGuard {
+    fn drop(&mut self) {
+        self.refreshing.lock().unwrap().remove(&self.key);
+    }
 }
@@ -97,6 +97,42 @@ pub struct CacheConfig {
     pub enabled: bool,
     pub ttl_seconds: u64,
     pub max_entries: usize,
+    
+    /// Per-key overrides; the first policy whose pattern matches applies
+    #[serde(default)]
+    pub policies: Vec<CachePolicy>,
+}
+
+/// Caching behaviour for keys matching `pattern`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachePolicy {
+    /// A full key, `prefix/*` or `*`
+    pub pattern: String,
+    
+    /// Fetch matching keys into the cache at startup
+    #[serde(default)]
+    pub warm: bool,
+    
+    /// How long after the TTL an entry is still served while a background
+    /// refresh runs. Past this hard max age the entry is dropped and reads
+    /// wait on the provider again. 0 disables stale serving.
+    #[serde(default)]
+    pub stale_while_revalidate_seconds: u64,
+}
+
+impl CachePolicy {
+    pub fn matches(&self, key: &str) -> bool {
+        let key = key.trim_start_matches('/');
+        match self.pattern.trim_start_matches('/').strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == self.pattern.trim_start_matches('/'),
+        }
+    }
+    
+    /// Whether the pattern names a single key rather than a prefix
+    pub fn is_exact(&self) -> bool {
+        !self.pattern.ends_with('*')
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -119,6 +155,7 @@ impl Default for CacheConfig {
             enabled: true,
             ttl_seconds: 300,
             max_entries: 1000,
+            policies: Vec::new(),
         }
     }
 }
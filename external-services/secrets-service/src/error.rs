@@ -13,6 +13,9 @@ pub enum SecretsError {
     #[error("Authorization failed: {0}")]
     AuthorizationFailed(String),
     
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    
     #[error("Provider error: {0}")]
     ProviderError(String),
     
@@ -0,0 +1,461 @@
+//! Cache invalidation events
+//!
+//! Rotations, writes and deletions publish an [`InvalidationEvent`] on the
+//! [`InvalidationBus`]; every [`SecretCache`] subscribed to the bus evicts the
+//! key as soon as the event arrives instead of serving the old value until its
+//! TTL runs out. With an [`InvalidationTransport`] attached, events are also
+//! exchanged with the other instances of the service. Providers that can
+//! detect changes made outside the service implement [`ChangeNotifier`] and
+//! publish through the same bus.
+
+use crate::{cache::SecretCache, Result, SecretsError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use events_bus::{Event, NatsJetStreamBroker};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Histogram of the delay between a secret changing and its cache entry
+/// being evicted, labelled by `reason`
+pub const EVICTION_LAG_METRIC: &str = "secrets_cache_eviction_lag_seconds";
+
+/// Bucket bounds for [`EVICTION_LAG_METRIC`], from in-process delivery up to
+/// provider polling intervals
+const EVICTION_LAG_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 15.0, 30.0, 60.0, 300.0,
+];
+
+/// Events buffered per subscriber before a slow cache falls behind
+const LOCAL_CHANNEL_CAPACITY: usize = 1024;
+
+/// Event bus subject shared by all instances
+pub const INVALIDATION_SUBJECT: &str = "secrets.cache.invalidate";
+
+/// Why a cached secret is no longer current
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidationReason {
+    Rotated,
+    Updated,
+    Deleted,
+    /// The provider reported a change made outside this service
+    ProviderChanged,
+}
+
+impl fmt::Display for InvalidationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rotated => write!(f, "rotated"),
+            Self::Updated => write!(f, "updated"),
+            Self::Deleted => write!(f, "deleted"),
+            Self::ProviderChanged => write!(f, "provider_changed"),
+        }
+    }
+}
+
+/// A secret whose cached value must be dropped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvalidationEvent {
+    pub key: String,
+    pub reason: InvalidationReason,
+    /// When the secret changed; eviction lag is measured from here
+    pub occurred_at: DateTime<Utc>,
+    /// Instance that published the event
+    pub origin: Uuid,
+}
+
+/// Carries invalidation events between instances
+#[async_trait]
+pub trait InvalidationTransport: Send + Sync {
+    /// Send an event to the other instances
+    async fn publish(&self, event: &InvalidationEvent) -> Result<()>;
+
+    /// Deliver events published by any instance to `sink`
+    async fn subscribe(&self, sink: mpsc::UnboundedSender<InvalidationEvent>) -> Result<()>;
+}
+
+/// Source of change notifications for secrets modified outside this service
+#[async_trait]
+pub trait ChangeNotifier: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Publish an invalidation on `bus` for every change observed
+    ///
+    /// Runs until the watch fails. Notifiers that poll rather than receive
+    /// pushes only need to check the keys present in `cache`.
+    async fn watch(&self, bus: InvalidationBus, cache: SecretCache) -> Result<()>;
+}
+
+/// Fan-out of invalidation events to the caches of this instance and, with a
+/// transport, to every other instance
+#[derive(Clone)]
+pub struct InvalidationBus {
+    instance_id: Uuid,
+    local: broadcast::Sender<InvalidationEvent>,
+    transport: Option<Arc<dyn InvalidationTransport>>,
+}
+
+impl InvalidationBus {
+    pub fn new() -> Self {
+        let (local, _) = broadcast::channel(LOCAL_CHANNEL_CAPACITY);
+        Self {
+            instance_id: Uuid::new_v4(),
+            local,
+            transport: None,
+        }
+    }
+
+    /// Exchange events with other instances over `transport`
+    ///
+    /// Subscribers of this bus, including those added before the transport,
+    /// receive events published by other instances. Events that come back
+    /// from this instance are dropped, they were delivered locally already.
+    pub async fn with_transport(
+        mut self,
+        transport: Arc<dyn InvalidationTransport>,
+    ) -> Result<Self> {
+        let (sink, mut remote) = mpsc::unbounded_channel();
+        transport.subscribe(sink).await?;
+
+        let instance_id = self.instance_id;
+        let local = self.local.clone();
+        tokio::spawn(async move {
+            while let Some(event) = remote.recv().await {
+                if event.origin != instance_id {
+                    let _ = local.send(event);
+                }
+            }
+        });
+
+        self.transport = Some(transport);
+        Ok(self)
+    }
+
+    pub fn instance_id(&self) -> Uuid {
+        self.instance_id
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<InvalidationEvent> {
+        self.local.subscribe()
+    }
+
+    /// Announce that `key` changed just now
+    pub async fn invalidate(&self, key: &str, reason: InvalidationReason) -> Result<()> {
+        self.invalidate_at(key, reason, Utc::now()).await
+    }
+
+    /// Announce that `key` changed at `occurred_at`
+    pub async fn invalidate_at(
+        &self,
+        key: &str,
+        reason: InvalidationReason,
+        occurred_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let event = InvalidationEvent {
+            key: key.to_string(),
+            reason,
+            occurred_at,
+            origin: self.instance_id,
+        };
+        debug!(
+            "Invalidating cached secret '{}' ({})",
+            event.key, event.reason
+        );
+
+        // No receivers just means no cache on this instance
+        let _ = self.local.send(event.clone());
+
+        if let Some(ref transport) = self.transport {
+            transport.publish(&event).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for InvalidationBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Register help text and bucket bounds for [`EVICTION_LAG_METRIC`]
+pub fn describe_invalidation_metrics() {
+    let metrics = telemetry::MetricsCollector::global();
+    metrics.describe_histogram(
+        EVICTION_LAG_METRIC,
+        "Seconds from a secret changing to its cache entry being evicted, by reason",
+        EVICTION_LAG_BUCKETS,
+    );
+    metrics.restrict_labels(
+        EVICTION_LAG_METRIC,
+        telemetry::MetricKind::Histogram,
+        &["reason"],
+    );
+}
+
+/// Record how long `event` took to reach the cache
+pub(crate) fn record_eviction_lag(event: &InvalidationEvent) {
+    let lag = (Utc::now() - event.occurred_at)
+        .to_std()
+        .unwrap_or_default();
+    telemetry::MetricsCollector::global()
+        .histogram(EVICTION_LAG_METRIC)
+        .with_label("reason", event.reason.to_string())
+        .record(lag.as_secs_f64());
+}
+
+/// Invalidation transport over the NATS event bus
+pub struct NatsInvalidationTransport {
+    broker: Arc<NatsJetStreamBroker>,
+}
+
+impl NatsInvalidationTransport {
+    pub fn new(broker: Arc<NatsJetStreamBroker>) -> Self {
+        Self { broker }
+    }
+}
+
+#[async_trait]
+impl InvalidationTransport for NatsInvalidationTransport {
+    async fn publish(&self, event: &InvalidationEvent) -> Result<()> {
+        let event = Event {
+            id: Uuid::new_v4(),
+            event_type: INVALIDATION_SUBJECT.to_string(),
+            data: serde_json::to_value(event)?,
+            timestamp: event.occurred_at,
+            partition_key: None,
+        };
+        self.broker.publish_event(&event).await.map_err(|e| {
+            SecretsError::NetworkError(format!("Failed to publish invalidation: {}", e))
+        })
+    }
+
+    async fn subscribe(&self, sink: mpsc::UnboundedSender<InvalidationEvent>) -> Result<()> {
+        self.broker
+            .subscribe_to_events(INVALIDATION_SUBJECT, move |event| {
+                let event: InvalidationEvent = serde_json::from_value(event.data)?;
+                sink.send(event)?;
+                Ok(())
+            })
+            .await
+            .map_err(|e| {
+                SecretsError::NetworkError(format!("Failed to subscribe to invalidations: {}", e))
+            })?;
+        Ok(())
+    }
+}
+
+/// Keep a provider's change notifications flowing, restarting the watch
+/// after failures
+pub(crate) async fn run_notifier(
+    notifier: Arc<dyn ChangeNotifier>,
+    bus: InvalidationBus,
+    cache: SecretCache,
+    retry_delay: std::time::Duration,
+) {
+    loop {
+        match notifier.watch(bus.clone(), cache.clone()).await {
+            Ok(()) => debug!("Change notifications from {} ended", notifier.name()),
+            Err(e) => warn!(
+                "Change notifications from {} failed: {}",
+                notifier.name(),
+                e
+            ),
+        }
+        // Changes may have been missed while the watch was down
+        if let Err(e) = cache.clear().await {
+            warn!("Failed to clear secret cache: {}", e);
+        }
+        tokio::time::sleep(retry_delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Secret, SecretMetadata};
+    use std::time::Duration;
+
+    /// Stand-in for a message broker shared by several instances
+    #[derive(Clone)]
+    struct SharedTransport {
+        wire: broadcast::Sender<InvalidationEvent>,
+    }
+
+    #[async_trait]
+    impl InvalidationTransport for SharedTransport {
+        async fn publish(&self, event: &InvalidationEvent) -> Result<()> {
+            let _ = self.wire.send(event.clone());
+            Ok(())
+        }
+
+        async fn subscribe(&self, sink: mpsc::UnboundedSender<InvalidationEvent>) -> Result<()> {
+            let mut wire = self.wire.subscribe();
+            tokio::spawn(async move {
+                while let Ok(event) = wire.recv().await {
+                    if sink.send(event).is_err() {
+                        break;
+                    }
+                }
+            });
+            Ok(())
+        }
+    }
+
+    fn secret(key: &str) -> Secret {
+        Secret {
+            metadata: SecretMetadata {
+                key: key.to_string(),
+                version: Some("1".to_string()),
+                created_at: None,
+                updated_at: None,
+                expires_at: None,
+                rotation_enabled: true,
+                rotation_interval_days: Some(30),
+                tags: Default::default(),
+            },
+            value: "old".to_string(),
+        }
+    }
+
+    async fn eventually_evicted(cache: &SecretCache, key: &str) -> bool {
+        for _ in 0..100 {
+            if cache.get(key).await.is_none() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_local_invalidation_evicts() {
+        let bus = InvalidationBus::new();
+        let cache = SecretCache::new(300, 100);
+        cache.subscribe_to(&bus);
+        cache
+            .set("db/password".to_string(), secret("db/password"))
+            .await
+            .unwrap();
+        cache
+            .set("api/key".to_string(), secret("api/key"))
+            .await
+            .unwrap();
+
+        bus.invalidate("db/password", InvalidationReason::Rotated)
+            .await
+            .unwrap();
+
+        assert!(eventually_evicted(&cache, "db/password").await);
+        assert!(cache.get("api/key").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_reaches_other_instances() {
+        let transport = Arc::new(SharedTransport {
+            wire: broadcast::channel(16).0,
+        });
+
+        let first = InvalidationBus::new();
+        let first_cache = SecretCache::new(300, 100);
+        first_cache.subscribe_to(&first);
+        let first = first.with_transport(transport.clone()).await.unwrap();
+
+        let second = InvalidationBus::new()
+            .with_transport(transport.clone())
+            .await
+            .unwrap();
+        let second_cache = SecretCache::new(300, 100);
+        second_cache.subscribe_to(&second);
+
+        for cache in [&first_cache, &second_cache] {
+            cache
+                .set("db/password".to_string(), secret("db/password"))
+                .await
+                .unwrap();
+        }
+
+        // Own events are not redelivered from the wire
+        let mut own = first.subscribe();
+        first
+            .invalidate("db/password", InvalidationReason::Rotated)
+            .await
+            .unwrap();
+
+        assert!(eventually_evicted(&first_cache, "db/password").await);
+        assert!(eventually_evicted(&second_cache, "db/password").await);
+        assert_eq!(own.recv().await.unwrap().origin, first.instance_id());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(own.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_eviction_lag_is_recorded() {
+        describe_invalidation_metrics();
+        let bus = InvalidationBus::new();
+        let cache = SecretCache::new(300, 100);
+        cache.subscribe_to(&bus);
+        cache
+            .set("lag/test".to_string(), secret("lag/test"))
+            .await
+            .unwrap();
+
+        let before = lag_count("deleted");
+        bus.invalidate_at(
+            "lag/test",
+            InvalidationReason::Deleted,
+            Utc::now() - chrono::Duration::seconds(2),
+        )
+        .await
+        .unwrap();
+        assert!(eventually_evicted(&cache, "lag/test").await);
+
+        for _ in 0..100 {
+            if lag_count("deleted") > before {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("eviction lag was not recorded");
+    }
+
+    fn lag_count(reason: &str) -> u64 {
+        telemetry::MetricsCollector::global()
+            .snapshot_family(EVICTION_LAG_METRIC)
+            .and_then(|family| {
+                family.series.into_iter().find_map(|series| {
+                    let labelled = series
+                        .labels
+                        .iter()
+                        .any(|(key, value)| key == "reason" && value == reason);
+                    match series.value {
+                        telemetry::SeriesValue::Histogram(histogram) if labelled => {
+                            Some(histogram.count)
+                        }
+                        _ => None,
+                    }
+                })
+            })
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_event_wire_format() {
+        let event = InvalidationEvent {
+            key: "kv/prod/db".to_string(),
+            reason: InvalidationReason::ProviderChanged,
+            occurred_at: Utc::now(),
+            origin: Uuid::new_v4(),
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["reason"], "provider_changed");
+        assert_eq!(
+            serde_json::from_value::<InvalidationEvent>(value).unwrap(),
+            event
+        );
+    }
+}
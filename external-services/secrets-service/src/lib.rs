@@ -12,11 +12,13 @@
 //! 
 //! ## Features:
 //! - Secret rotation
-//! - Caching with TTL
+//! - Caching with TTL, evicted as soon as a secret is rotated or changed
+//! - Cache warming on startup and stale-while-revalidate for critical keys
 //! - Audit logging
 //! - Health checks
 //! - UI for secret management
 //! - Role-based access control
+//! - TLS certificates and keys for event bus brokers
 
 pub mod config;
 pub mod providers;
@@ -25,12 +27,23 @@ pub mod error;
 pub mod manager;
 pub mod rotation;
 pub mod audit;
+pub mod authz;
 pub mod health;
+pub mod invalidation;
+pub mod broker_tls;
+
+#[cfg(test)]
+mod test_support;
 
 pub use config::*;
 pub use providers::*;
 pub use error::*;
-pub use manager::SecretsManager;
+pub use authz::{Principal, SecretAction, SecretsAuthorizer};
+pub use invalidation::{
+    ChangeNotifier, InvalidationBus, InvalidationEvent, InvalidationReason, InvalidationTransport,
+};
+pub use manager::{PrincipalSecrets, SecretsManager};
+pub use broker_tls::SecretsTlsMaterial;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -4,17 +4,37 @@ use async_trait::async_trait;
 use crate::{
     SecretProvider, Secret, SecretMetadata, Result, SecretsError, HealthStatus,
     config::{ProviderConfig, CacheConfig, AuditConfig},
-    cache::SecretCache,
+    cache::{CacheLookup, SecretCache},
     audit::{AuditLogger, AuditEvent, AuditEventType},
+    authz::{Principal, SecretAction, SecretsAuthorizer},
+    invalidation::{
+        describe_invalidation_metrics, run_notifier, ChangeNotifier, InvalidationBus,
+        InvalidationReason, InvalidationTransport,
+    },
     providers::{VaultProvider, AwsSecretsManagerProvider},
 };
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
 use tracing::{debug, info, warn};
 
+/// Pause before restarting a failed change notifier
+const NOTIFIER_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Coordinates providers, caching and audit logging
+///
+/// The `SecretProvider` implementation on the manager itself is unrestricted
+/// and meant for the service's own use (rotation, health checks). Requests
+/// made on behalf of users or other services go through
+/// [`for_principal`](Self::for_principal), which enforces the configured
+/// access policies.
 pub struct SecretsManager {
     providers: Vec<Arc<dyn SecretProvider + Send + Sync>>,
     cache: Option<SecretCache>,
     audit: AuditLogger,
+    authorizer: Option<SecretsAuthorizer>,
+    invalidation: InvalidationBus,
+    notifiers: Vec<Arc<dyn ChangeNotifier>>,
 }
 
 impl SecretsManager {
@@ -24,13 +44,15 @@ impl SecretsManager {
         audit_config: AuditConfig,
     ) -> Result<Self> {
         let mut providers: Vec<Arc<dyn SecretProvider + Send + Sync>> = Vec::new();
+        let mut notifiers: Vec<Arc<dyn ChangeNotifier>> = Vec::new();
         
         for config in provider_configs {
             match config {
                 ProviderConfig::Vault(vault_config) => {
                     info!("Initializing Vault provider: {}", vault_config.address);
-                    let provider = VaultProvider::new(vault_config).await?;
-                    providers.push(Arc::new(provider));
+                    let provider = Arc::new(VaultProvider::new(vault_config).await?);
+                    notifiers.push(provider.clone());
+                    providers.push(provider);
                 }
                 ProviderConfig::AwsSecretsManager(aws_config) => {
                     info!("Initializing AWS Secrets Manager provider");
@@ -64,7 +86,7 @@ impl SecretsManager {
         
         let cache = if let Some(cfg) = cache_config {
             if cfg.enabled {
-                Some(SecretCache::new(cfg.ttl_seconds, cfg.max_entries))
+                Some(SecretCache::with_policies(cfg.ttl_seconds, cfg.max_entries, cfg.policies))
             } else {
                 None
             }
@@ -72,71 +94,337 @@ impl SecretsManager {
             None
         };
         
+        let invalidation = InvalidationBus::new();
+        if let Some(ref cache) = cache {
+            describe_invalidation_metrics();
+            cache.subscribe_to(&invalidation);
+        }
+        
         let audit = AuditLogger::new(audit_config.enabled, audit_config.log_all_access);
         
-        Ok(Self {
+        let manager = Self {
             providers,
             cache,
             audit,
-        })
+            authorizer: None,
+            invalidation,
+            notifiers,
+        };
+        manager.warm_cache().await;
+        
+        Ok(manager)
     }
     
-    /// Try to get secret from cache first, then from providers
-    async fn get_with_cache(&self, key: &str) -> Result<Secret> {
-        // Check cache first
-        if let Some(ref cache) = self.cache {
-            if let Some(secret) = cache.get(key).await {
-                debug!("Secret found in cache: {}", key);
-                self.audit.log_access(key, None);
-                return Ok(secret);
+    /// Fetch the keys of every warming cache policy into the cache
+    ///
+    /// Run on startup so the first reads after a deploy don't pay the
+    /// provider round trip. Keys that fail to load are logged and left to be
+    /// fetched on first use. Returns the number of keys cached.
+    pub async fn warm_cache(&self) -> usize {
+        let Some(ref cache) = self.cache else {
+            return 0;
+        };
+        
+        let mut keys: Vec<String> = Vec::new();
+        let mut prefixes = Vec::new();
+        for policy in cache.warm_policies() {
+            if policy.is_exact() {
+                keys.push(policy.pattern.clone());
+            } else {
+                prefixes.push(policy.clone());
             }
         }
+        if !prefixes.is_empty() {
+            match self.providers[0].list_secrets().await {
+                Ok(available) => keys.extend(
+                    available
+                        .into_iter()
+                        .filter(|key| prefixes.iter().any(|policy| policy.matches(key))),
+                ),
+                Err(e) => warn!("Failed to list secrets for cache warming: {}", e),
+            }
+        }
+        keys.sort();
+        keys.dedup();
         
-        // Try each provider in order
-        let mut last_error = None;
-        for provider in &self.providers {
-            match provider.get_secret(key).await {
+        let mut warmed = 0;
+        for key in keys {
+            match fetch_from_providers(&self.providers, &key).await {
                 Ok(secret) => {
-                    // Cache the secret if caching is enabled
-                    if let Some(ref cache) = self.cache {
-                        let _ = cache.set(key.to_string(), secret.clone()).await;
-                    }
-                    
-                    self.audit.log_event(AuditEvent {
-                        timestamp: chrono::Utc::now(),
-                        event_type: AuditEventType::SecretAccessed,
-                        secret_key: key.to_string(),
-                        user: None,
-                        success: true,
-                        error_message: None,
-                    });
-                    
-                    return Ok(secret);
-                }
-                Err(SecretsError::NotFound(_)) => {
-                    // Continue to next provider
-                    continue;
+                    let _ = cache.set(key, secret).await;
+                    warmed += 1;
                 }
-                Err(e) => {
-                    warn!("Provider error for key '{}': {}", key, e);
-                    last_error = Some(e);
+                Err(e) => warn!("Failed to warm cache for '{}': {}", key, e),
+            }
+        }
+        
+        info!("Warmed secret cache with {} keys", warmed);
+        warmed
+    }
+    
+    /// Refresh a stale cache entry without blocking the caller
+    ///
+    /// On failure the stale value keeps being served until the key's max
+    /// age, with each later read retrying the refresh.
+    fn spawn_refresh(&self, cache: &SecretCache, key: &str) {
+        let Some(guard) = cache.begin_refresh(key) else {
+            return;
+        };
+        let providers = self.providers.clone();
+        let cache = cache.clone();
+        let key = key.to_string();
+        tokio::spawn(async move {
+            match fetch_from_providers(&providers, &key).await {
+                Ok(secret) => {
+                    cache.complete_refresh(&key, guard.started(), secret).await;
+                    debug!("Refreshed stale secret in cache: {}", key);
                 }
+                Err(e) => warn!("Background refresh of '{}' failed, serving stale value: {}", key, e),
             }
+            drop(guard);
+        });
+    }
+    
+    /// Share cache invalidations with the other instances of the service
+    pub async fn with_invalidation_transport(
+        mut self,
+        transport: Arc<dyn InvalidationTransport>,
+    ) -> Result<Self> {
+        self.invalidation = self.invalidation.with_transport(transport).await?;
+        Ok(self)
+    }
+    
+    /// Bus that evicts secrets from this manager's cache, e.g. for a
+    /// `RotationManager` rotating through a provider directly
+    pub fn invalidation_bus(&self) -> InvalidationBus {
+        self.invalidation.clone()
+    }
+    
+    /// Start change notifications for the configured providers that support
+    /// them
+    pub fn start_change_watchers(&self) -> Vec<JoinHandle<()>> {
+        self.notifiers
+            .iter()
+            .filter_map(|notifier| self.watch_changes(notifier.clone()))
+            .collect()
+    }
+    
+    /// Evict secrets that `notifier` reports as changed
+    ///
+    /// The watch is restarted if it fails, clearing the cache first since
+    /// changes may have been missed. Returns `None` when caching is disabled.
+    pub fn watch_changes(&self, notifier: Arc<dyn ChangeNotifier>) -> Option<JoinHandle<()>> {
+        let cache = self.cache.clone()?;
+        info!("Watching {} for secret changes", notifier.name());
+        Some(tokio::spawn(run_notifier(
+            notifier,
+            self.invalidation.clone(),
+            cache,
+            NOTIFIER_RETRY_DELAY,
+        )))
+    }
+    
+    /// Evict `key` here and on every other instance
+    async fn publish_invalidation(&self, key: &str, reason: InvalidationReason) {
+        // Evict locally before returning so the caller never reads back the
+        // old value; the bus event reaches the other instances
+        if let Some(ref cache) = self.cache {
+            let _ = cache.invalidate(key).await;
+        }
+        if let Err(e) = self.invalidation.invalidate(key, reason).await {
+            warn!("Failed to publish cache invalidation for '{}': {}", key, e);
         }
+    }
+    
+    /// Enforce access policies for principal-scoped operations
+    pub fn with_authorizer(mut self, authorizer: SecretsAuthorizer) -> Self {
+        self.authorizer = Some(authorizer);
+        self
+    }
+    
+    /// Operations performed on behalf of `principal`
+    ///
+    /// Every call is checked against the access policies; denials fail with
+    /// `SecretsError::Forbidden` and are audited. Listing returns only the
+    /// keys the principal may read. Without an authorizer everything is
+    /// denied.
+    pub fn for_principal(&self, principal: Principal) -> PrincipalSecrets<'_> {
+        PrincipalSecrets {
+            manager: self,
+            principal,
+        }
+    }
+    
+    /// Check that `principal` may perform `action` on `key`, auditing denials
+    async fn authorize(&self, principal: &Principal, action: SecretAction, key: &str) -> Result<()> {
+        let decision = match self.authorizer {
+            Some(ref authorizer) => authorizer.is_allowed(principal, action, key).await,
+            None => Ok(false),
+        };
         
-        let error = last_error.unwrap_or(SecretsError::NotFound(key.to_string()));
+        let error = match decision {
+            Ok(true) => return Ok(()),
+            Ok(false) => SecretsError::Forbidden(format!(
+                "{} may not {} '{}'",
+                principal, action, key
+            )),
+            Err(e) => e,
+        };
         
+        warn!("Denied {} of '{}' for {}: {}", action, key, principal, error);
         self.audit.log_event(AuditEvent {
             timestamp: chrono::Utc::now(),
-            event_type: AuditEventType::SecretAccessed,
+            event_type: AuditEventType::AccessDenied,
             secret_key: key.to_string(),
-            user: None,
+            user: Some(principal.to_string()),
             success: false,
             error_message: Some(error.to_string()),
         });
         
         Err(error)
     }
+    
+    /// Try to get secret from cache first, then from providers
+    async fn get_with_cache(&self, key: &str, user: Option<&str>) -> Result<Secret> {
+        // Check cache first
+        if let Some(ref cache) = self.cache {
+            match cache.lookup(key).await {
+                CacheLookup::Fresh(secret) => {
+                    debug!("Secret found in cache: {}", key);
+                    self.audit.log_access(key, user);
+                    return Ok(secret);
+                }
+                CacheLookup::Stale(secret) => {
+                    debug!("Serving stale secret from cache: {}", key);
+                    self.spawn_refresh(cache, key);
+                    self.audit.log_access(key, user);
+                    return Ok(secret);
+                }
+                CacheLookup::Miss => {}
+            }
+        }
+        
+        match fetch_from_providers(&self.providers, key).await {
+            Ok(secret) => {
+                // Cache the secret if caching is enabled
+                if let Some(ref cache) = self.cache {
+                    let _ = cache.set(key.to_string(), secret.clone()).await;
+                }
+                
+                self.audit.log_event(AuditEvent {
+                    timestamp: chrono::Utc::now(),
+                    event_type: AuditEventType::SecretAccessed,
+                    secret_key: key.to_string(),
+                    user: user.map(str::to_string),
+                    success: true,
+                    error_message: None,
+                });
+                
+                Ok(secret)
+            }
+            Err(error) => {
+                self.audit.log_event(AuditEvent {
+                    timestamp: chrono::Utc::now(),
+                    event_type: AuditEventType::SecretAccessed,
+                    secret_key: key.to_string(),
+                    user: user.map(str::to_string),
+                    success: false,
+                    error_message: Some(error.to_string()),
+                });
+                
+                Err(error)
+            }
+        }
+    }
+    
+    async fn set_secret_for(
+        &self,
+        key: &str,
+        value: &str,
+        metadata: Option<SecretMetadata>,
+        user: Option<&str>,
+    ) -> Result<()> {
+        debug!("Setting secret: {}", key);
+        
+        // Try first provider (primary)
+        self.providers[0].set_secret(key, value, metadata).await?;
+        
+        self.publish_invalidation(key, InvalidationReason::Updated).await;
+        
+        self.audit.log_event(AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: AuditEventType::SecretCreated,
+            secret_key: key.to_string(),
+            user: user.map(str::to_string),
+            success: true,
+            error_message: None,
+        });
+        
+        Ok(())
+    }
+    
+    async fn delete_secret_for(&self, key: &str, user: Option<&str>) -> Result<()> {
+        debug!("Deleting secret: {}", key);
+        
+        // Try first provider (primary)
+        self.providers[0].delete_secret(key).await?;
+        
+        self.publish_invalidation(key, InvalidationReason::Deleted).await;
+        
+        self.audit.log_event(AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: AuditEventType::SecretDeleted,
+            secret_key: key.to_string(),
+            user: user.map(str::to_string),
+            success: true,
+            error_message: None,
+        });
+        
+        Ok(())
+    }
+    
+    async fn rotate_secret_for(&self, key: &str, user: Option<&str>) -> Result<String> {
+        debug!("Rotating secret: {}", key);
+        
+        // Try first provider (primary)
+        let result = self.providers[0].rotate_secret(key).await?;
+        
+        self.publish_invalidation(key, InvalidationReason::Rotated).await;
+        
+        self.audit.log_event(AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: AuditEventType::SecretRotated,
+            secret_key: key.to_string(),
+            user: user.map(str::to_string),
+            success: true,
+            error_message: None,
+        });
+        
+        Ok(result)
+    }
+}
+
+/// Get `key` from the first provider that has it
+async fn fetch_from_providers(
+    providers: &[Arc<dyn SecretProvider + Send + Sync>],
+    key: &str,
+) -> Result<Secret> {
+    let mut last_error = None;
+    for provider in providers {
+        match provider.get_secret(key).await {
+            Ok(secret) => return Ok(secret),
+            Err(SecretsError::NotFound(_)) => {
+                // Continue to next provider
+                continue;
+            }
+            Err(e) => {
+                warn!("Provider error for key '{}': {}", key, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    
+    Err(last_error.unwrap_or(SecretsError::NotFound(key.to_string())))
 }
 
 #[async_trait]
@@ -182,7 +470,7 @@ impl SecretProvider for SecretsManager {
     }
     
     async fn get_secret(&self, key: &str) -> Result<Secret> {
-        self.get_with_cache(key).await
+        self.get_with_cache(key, None).await
     }
     
     async fn get_secret_version(&self, key: &str, version: &str) -> Result<Secret> {
@@ -201,49 +489,11 @@ impl SecretProvider for SecretsManager {
     }
     
     async fn set_secret(&self, key: &str, value: &str, metadata: Option<SecretMetadata>) -> Result<()> {
-        debug!("Setting secret: {}", key);
-        
-        // Try first provider (primary)
-        self.providers[0].set_secret(key, value, metadata).await?;
-        
-        // Invalidate cache
-        if let Some(ref cache) = self.cache {
-            let _ = cache.invalidate(key).await;
-        }
-        
-        self.audit.log_event(AuditEvent {
-            timestamp: chrono::Utc::now(),
-            event_type: AuditEventType::SecretCreated,
-            secret_key: key.to_string(),
-            user: None,
-            success: true,
-            error_message: None,
-        });
-        
-        Ok(())
+        self.set_secret_for(key, value, metadata, None).await
     }
     
     async fn delete_secret(&self, key: &str) -> Result<()> {
-        debug!("Deleting secret: {}", key);
-        
-        // Try first provider (primary)
-        self.providers[0].delete_secret(key).await?;
-        
-        // Invalidate cache
-        if let Some(ref cache) = self.cache {
-            let _ = cache.invalidate(key).await;
-        }
-        
-        self.audit.log_event(AuditEvent {
-            timestamp: chrono::Utc::now(),
-            event_type: AuditEventType::SecretDeleted,
-            secret_key: key.to_string(),
-            user: None,
-            success: true,
-            error_message: None,
-        });
-        
-        Ok(())
+        self.delete_secret_for(key, None).await
     }
     
     async fn list_secrets(&self) -> Result<Vec<String>> {
@@ -269,26 +519,85 @@ impl SecretProvider for SecretsManager {
     }
     
     async fn rotate_secret(&self, key: &str) -> Result<String> {
-        debug!("Rotating secret: {}", key);
-        
-        // Try first provider (primary)
-        let result = self.providers[0].rotate_secret(key).await?;
-        
-        // Invalidate cache
-        if let Some(ref cache) = self.cache {
-            let _ = cache.invalidate(key).await;
-        }
-        
-        self.audit.log_event(AuditEvent {
-            timestamp: chrono::Utc::now(),
-            event_type: AuditEventType::SecretRotated,
-            secret_key: key.to_string(),
-            user: None,
-            success: true,
-            error_message: None,
-        });
+        self.rotate_secret_for(key, None).await
+    }
+}
+
+/// A `SecretsManager` scoped to one caller
+///
+/// Created with [`SecretsManager::for_principal`].
+pub struct PrincipalSecrets<'a> {
+    manager: &'a SecretsManager,
+    principal: Principal,
+}
+
+impl PrincipalSecrets<'_> {
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+}
+
+#[async_trait]
+impl SecretProvider for PrincipalSecrets<'_> {
+    fn name(&self) -> &str {
+        self.manager.name()
+    }
+    
+    async fn health_check(&self) -> Result<HealthStatus> {
+        self.manager.health_check().await
+    }
+    
+    async fn get_secret(&self, key: &str) -> Result<Secret> {
+        self.manager.authorize(&self.principal, SecretAction::Read, key).await?;
+        let user = self.principal.to_string();
+        self.manager.get_with_cache(key, Some(&user)).await
+    }
+    
+    async fn get_secret_version(&self, key: &str, version: &str) -> Result<Secret> {
+        self.manager.authorize(&self.principal, SecretAction::Read, key).await?;
+        self.manager.get_secret_version(key, version).await
+    }
+    
+    async fn set_secret(&self, key: &str, value: &str, metadata: Option<SecretMetadata>) -> Result<()> {
+        self.manager.authorize(&self.principal, SecretAction::Write, key).await?;
+        let user = self.principal.to_string();
+        self.manager.set_secret_for(key, value, metadata, Some(&user)).await
+    }
+    
+    async fn delete_secret(&self, key: &str) -> Result<()> {
+        self.manager.authorize(&self.principal, SecretAction::Write, key).await?;
+        let user = self.principal.to_string();
+        self.manager.delete_secret_for(key, Some(&user)).await
+    }
+    
+    /// Only the keys the principal may read
+    async fn list_secrets(&self) -> Result<Vec<String>> {
+        let Some(ref authorizer) = self.manager.authorizer else {
+            return Ok(Vec::new());
+        };
         
-        Ok(result)
+        let keys = self.manager.list_secrets().await?;
+        let visible = authorizer
+            .allowed_keys(&self.principal, SecretAction::Read, &keys)
+            .await?;
+        debug!(
+            "Listing {} of {} secrets for {}",
+            visible.len(),
+            keys.len(),
+            self.principal
+        );
+        Ok(visible)
+    }
+    
+    async fn list_versions(&self, key: &str) -> Result<Vec<String>> {
+        self.manager.authorize(&self.principal, SecretAction::Read, key).await?;
+        self.manager.list_versions(key).await
+    }
+    
+    async fn rotate_secret(&self, key: &str) -> Result<String> {
+        self.manager.authorize(&self.principal, SecretAction::Write, key).await?;
+        let user = self.principal.to_string();
+        self.manager.rotate_secret_for(key, Some(&user)).await
     }
 }
 
@@ -296,6 +605,7 @@ impl SecretProvider for SecretsManager {
 mod tests {
     use super::*;
     use crate::config::VaultConfig;
+    use crate::test_support::MemoryProvider;
     
     #[tokio::test]
     #[ignore] // Requires running Vault
@@ -317,6 +627,7 @@ mod tests {
             enabled: true,
             ttl_seconds: 300,
             max_entries: 1000,
+            policies: Vec::new(),
         };
         
         let audit_config = AuditConfig {
@@ -344,4 +655,247 @@ mod tests {
         
         manager.delete_secret("test/app").await.unwrap();
     }
+    
+    async fn manager_with_policies() -> SecretsManager {
+        use auth_zanzibar::{repository::InMemoryTupleRepository, AuthorizationEngine, Subject};
+        
+        let engine = AuthorizationEngine::new(Arc::new(InMemoryTupleRepository::new()))
+            .await
+            .unwrap();
+        let authorizer = SecretsAuthorizer::new(Arc::new(engine));
+        authorizer
+            .grant(Subject::user("alice"), SecretAction::Read, "kv/prod/*")
+            .await
+            .unwrap();
+        authorizer
+            .grant(Subject::service("release-bot"), SecretAction::Write, "kv/prod/deploy/*")
+            .await
+            .unwrap();
+        
+        SecretsManager {
+            providers: vec![Arc::new(MemoryProvider::with_keys(&[
+                "kv/prod/db/password",
+                "kv/prod/deploy/token",
+                "kv/staging/db/password",
+            ]))],
+            cache: Some(SecretCache::new(300, 100)),
+            audit: AuditLogger::new(true, true),
+            authorizer: None,
+            invalidation: InvalidationBus::new(),
+            notifiers: Vec::new(),
+        }
+        .with_authorizer(authorizer)
+    }
+    
+    #[tokio::test]
+    async fn test_principal_operations_are_authorized() {
+        let manager = manager_with_policies().await;
+        let alice = manager.for_principal(Principal::user("alice"));
+        let bot = manager.for_principal(Principal::service("release-bot"));
+        
+        let secret = alice.get_secret("kv/prod/db/password").await.unwrap();
+        assert_eq!(secret.value, "value of kv/prod/db/password");
+        assert!(matches!(
+            alice.get_secret("kv/staging/db/password").await,
+            Err(SecretsError::Forbidden(_))
+        ));
+        assert!(matches!(
+            alice.set_secret("kv/prod/deploy/token", "stolen", None).await,
+            Err(SecretsError::Forbidden(_))
+        ));
+        assert!(matches!(
+            alice.list_versions("kv/staging/db/password").await,
+            Err(SecretsError::Forbidden(_))
+        ));
+        
+        bot.set_secret("kv/prod/deploy/token", "v2", None).await.unwrap();
+        assert_eq!(bot.get_secret("kv/prod/deploy/token").await.unwrap().value, "v2");
+        assert!(matches!(
+            bot.delete_secret("kv/prod/db/password").await,
+            Err(SecretsError::Forbidden(_))
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_principal_listing_is_filtered() {
+        let manager = manager_with_policies().await;
+        
+        let alice = manager.for_principal(Principal::user("alice"));
+        assert_eq!(
+            alice.list_secrets().await.unwrap(),
+            vec!["kv/prod/db/password", "kv/prod/deploy/token"]
+        );
+        
+        let bot = manager.for_principal(Principal::service("release-bot"));
+        assert_eq!(bot.list_secrets().await.unwrap(), vec!["kv/prod/deploy/token"]);
+        
+        let mallory = manager.for_principal(Principal::user("mallory"));
+        assert!(mallory.list_secrets().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_principal_denied_without_authorizer() {
+        let manager = SecretsManager {
+            providers: vec![Arc::new(MemoryProvider::with_keys(&["kv/prod/db/password"]))],
+            cache: None,
+            audit: AuditLogger::new(true, true),
+            authorizer: None,
+            invalidation: InvalidationBus::new(),
+            notifiers: Vec::new(),
+        };
+        let alice = manager.for_principal(Principal::user("alice"));
+        
+        assert!(matches!(
+            alice.get_secret("kv/prod/db/password").await,
+            Err(SecretsError::Forbidden(_))
+        ));
+        assert!(alice.list_secrets().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_changes_invalidate_cache() {
+        let manager = SecretsManager {
+            providers: vec![Arc::new(MemoryProvider::with_keys(&["db/password"]))],
+            cache: Some(SecretCache::new(300, 100)),
+            audit: AuditLogger::new(true, true),
+            authorizer: None,
+            invalidation: InvalidationBus::new(),
+            notifiers: Vec::new(),
+        };
+        let mut events = manager.invalidation_bus().subscribe();
+        
+        manager.get_secret("db/password").await.unwrap();
+        manager.set_secret("db/password", "new", None).await.unwrap();
+        
+        // Evicted before set_secret returns, not when the event is handled
+        let cache = manager.cache.as_ref().unwrap();
+        assert!(cache.get("db/password").await.is_none());
+        assert_eq!(manager.get_secret("db/password").await.unwrap().value, "new");
+        
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.key, "db/password");
+        assert_eq!(event.reason, InvalidationReason::Updated);
+        
+        manager.rotate_secret("db/password").await.unwrap();
+        assert_eq!(events.recv().await.unwrap().reason, InvalidationReason::Rotated);
+    }
+    
+    fn policy(pattern: &str, warm: bool, stale_while_revalidate_seconds: u64) -> crate::config::CachePolicy {
+        crate::config::CachePolicy {
+            pattern: pattern.to_string(),
+            warm,
+            stale_while_revalidate_seconds,
+        }
+    }
+    
+    fn manager_with_cache(provider: Arc<MemoryProvider>, cache: SecretCache) -> SecretsManager {
+        SecretsManager {
+            providers: vec![provider],
+            cache: Some(cache),
+            audit: AuditLogger::new(true, true),
+            authorizer: None,
+            invalidation: InvalidationBus::new(),
+            notifiers: Vec::new(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_warm_cache() {
+        let provider = Arc::new(MemoryProvider::with_keys(&[
+            "db/password",
+            "kv/prod/api",
+            "kv/prod/smtp",
+            "kv/staging/api",
+        ]));
+        let cache = SecretCache::with_policies(300, 100, vec![
+            policy("db/password", true, 0),
+            policy("kv/prod/*", true, 0),
+            policy("kv/*", false, 60),
+            policy("missing/key", true, 0),
+        ]);
+        let manager = manager_with_cache(provider, cache);
+        
+        assert_eq!(manager.warm_cache().await, 3);
+        
+        let cache = manager.cache.as_ref().unwrap();
+        for key in ["db/password", "kv/prod/api", "kv/prod/smtp"] {
+            assert!(matches!(cache.lookup(key).await, CacheLookup::Fresh(_)), "{} not warmed", key);
+        }
+        assert!(matches!(cache.lookup("kv/staging/api").await, CacheLookup::Miss));
+        assert_eq!(cache.policy_for("kv/staging/api").unwrap().stale_while_revalidate_seconds, 60);
+    }
+    
+    #[tokio::test]
+    async fn test_stale_entry_served_while_refreshing() {
+        let provider = Arc::new(MemoryProvider::with_keys(&["db/password"]));
+        // Zero TTL: every cached read is stale
+        let cache = SecretCache::with_policies(0, 100, vec![policy("db/*", false, 60)]);
+        let manager = manager_with_cache(provider.clone(), cache);
+        
+        assert_eq!(manager.get_secret("db/password").await.unwrap().value, "value of db/password");
+        
+        provider.set_secret("db/password", "new", None).await.unwrap();
+        assert_eq!(manager.get_secret("db/password").await.unwrap().value, "value of db/password");
+        
+        for _ in 0..100 {
+            if manager.get_secret("db/password").await.unwrap().value == "new" {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("stale entry was not refreshed in the background");
+    }
+    
+    #[tokio::test]
+    async fn test_stale_entry_expires_at_max_age() {
+        let provider = Arc::new(MemoryProvider::with_keys(&["db/password"]));
+        let cache = SecretCache::with_policies(0, 100, vec![policy("db/*", false, 1)]);
+        let manager = manager_with_cache(provider.clone(), cache);
+        
+        manager.get_secret("db/password").await.unwrap();
+        provider.set_unavailable(true);
+        
+        // Refreshes fail, so the stale value keeps being served
+        assert_eq!(manager.get_secret("db/password").await.unwrap().value, "value of db/password");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(manager.get_secret("db/password").await.unwrap().value, "value of db/password");
+        
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(matches!(
+            manager.get_secret("db/password").await,
+            Err(SecretsError::ProviderError(_))
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_keys_without_stale_window_block_on_expiry() {
+        let provider = Arc::new(MemoryProvider::with_keys(&["db/password", "api/key"]));
+        let cache = SecretCache::with_policies(0, 100, vec![policy("db/*", false, 60)]);
+        let manager = manager_with_cache(provider.clone(), cache);
+        
+        manager.get_secret("api/key").await.unwrap();
+        provider.set_secret("api/key", "new", None).await.unwrap();
+        assert_eq!(manager.get_secret("api/key").await.unwrap().value, "new");
+    }
+    
+    #[tokio::test]
+    async fn test_refresh_does_not_restore_invalidated_entry() {
+        let cache = SecretCache::with_policies(0, 100, vec![policy("*", false, 60)]);
+        let secret = MemoryProvider::with_keys(&["db/password"])
+            .get_secret("db/password")
+            .await
+            .unwrap();
+        cache.set("db/password".to_string(), secret.clone()).await.unwrap();
+        
+        let guard = cache.begin_refresh("db/password").unwrap();
+        assert!(cache.begin_refresh("db/password").is_none());
+        
+        // Rotated while the refresh was in flight
+        cache.invalidate("db/password").await.unwrap();
+        cache.complete_refresh("db/password", guard.started(), secret).await;
+        assert!(matches!(cache.lookup("db/password").await, CacheLookup::Miss));
+        
+        drop(guard);
+        assert!(cache.begin_refresh("db/password").is_some());
+    }
 }
@@ -0,0 +1,132 @@
+//! Change notifications for Kubernetes Secrets
+//!
+//! Watches the Secrets of one namespace and invalidates the cached secret of
+//! the same name whenever one is modified or deleted.
+
+use crate::cache::SecretCache;
+use crate::invalidation::{ChangeNotifier, InvalidationBus, InvalidationReason};
+use crate::{Result, SecretsError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Secret as K8sSecret;
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Api, Client, ResourceExt};
+use tracing::{debug, warn};
+
+pub struct KubernetesSecretWatcher {
+    client: Client,
+    namespace: String,
+}
+
+impl KubernetesSecretWatcher {
+    pub fn new(client: Client, namespace: &str) -> Self {
+        Self {
+            client,
+            namespace: namespace.to_string(),
+        }
+    }
+
+    /// Watch `namespace` with the in-cluster or kubeconfig credentials
+    pub async fn try_default(namespace: &str) -> Result<Self> {
+        let client = Client::try_default().await.map_err(|e| {
+            SecretsError::ConfigurationError(format!("Failed to create Kubernetes client: {}", e))
+        })?;
+        Ok(Self::new(client, namespace))
+    }
+}
+
+/// Secret keys to invalidate for a watch event, with when each changed
+///
+/// A restarted watch may have missed updates, so every listed secret is
+/// invalidated.
+fn changed_secrets(event: watcher::Event<K8sSecret>) -> Vec<(String, DateTime<Utc>)> {
+    let now = Utc::now();
+    match event {
+        watcher::Event::Applied(secret) => vec![(secret.name_any(), now)],
+        watcher::Event::Deleted(secret) => {
+            let deleted_at = secret
+                .metadata
+                .deletion_timestamp
+                .as_ref()
+                .map_or(now, |time| time.0);
+            vec![(secret.name_any(), deleted_at)]
+        }
+        watcher::Event::Restarted(secrets) => secrets
+            .iter()
+            .map(|secret| (secret.name_any(), now))
+            .collect(),
+    }
+}
+
+#[async_trait]
+impl ChangeNotifier for KubernetesSecretWatcher {
+    fn name(&self) -> &str {
+        "kubernetes"
+    }
+
+    async fn watch(&self, bus: InvalidationBus, _cache: SecretCache) -> Result<()> {
+        let api: Api<K8sSecret> = Api::namespaced(self.client.clone(), &self.namespace);
+        let mut events = watcher(api, watcher::Config::default())
+            .default_backoff()
+            .boxed();
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                // The watcher retries with backoff on its own
+                Err(e) => {
+                    warn!("Kubernetes secret watch error in {}: {}", self.namespace, e);
+                    continue;
+                }
+            };
+
+            for (key, changed_at) in changed_secrets(event) {
+                debug!("Secret changed in Kubernetes: {}/{}", self.namespace, key);
+                if let Err(e) = bus
+                    .invalidate_at(&key, InvalidationReason::ProviderChanged, changed_at)
+                    .await
+                {
+                    warn!("Failed to publish invalidation for {}: {}", key, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+
+    fn secret(name: &str) -> K8sSecret {
+        K8sSecret {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_changed_secrets() {
+        let applied = changed_secrets(watcher::Event::Applied(secret("db-password")));
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].0, "db-password");
+
+        let deleted_at = Utc::now() - chrono::Duration::seconds(5);
+        let mut deleted = secret("api-key");
+        deleted.metadata.deletion_timestamp = Some(Time(deleted_at));
+        assert_eq!(
+            changed_secrets(watcher::Event::Deleted(deleted)),
+            vec![("api-key".to_string(), deleted_at)]
+        );
+
+        let restarted = changed_secrets(watcher::Event::Restarted(vec![secret("a"), secret("b")]));
+        let keys: Vec<_> = restarted.into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["a", "b"]);
+    }
+}
@@ -2,6 +2,8 @@
 
 pub mod vault;
 pub mod aws;
+pub mod kubernetes;
 
 pub use vault::VaultProvider;
 pub use aws::AwsSecretsManagerProvider;
+pub use kubernetes::KubernetesSecretWatcher;
@@ -2,17 +2,26 @@
 
 use async_trait::async_trait;
 use vaultrs::client::{VaultClient, VaultClientSettingsBuilder};
+use vaultrs::error::ClientError;
 use vaultrs::kv2;
 use crate::{SecretProvider, Secret, SecretMetadata, Result, SecretsError, HealthStatus};
+use crate::cache::SecretCache;
 use crate::config::VaultConfig;
+use crate::invalidation::{ChangeNotifier, InvalidationBus, InvalidationReason};
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use std::time::Duration;
 use tracing::{debug, warn};
 
+/// How often cached secrets are compared against Vault's current versions
+const DEFAULT_CHANGE_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
 pub struct VaultProvider {
     client: VaultClient,
     mount: String,
     #[allow(dead_code)]
     namespace: Option<String>,
+    change_poll_interval: Duration,
 }
 
 impl VaultProvider {
@@ -78,8 +87,80 @@ impl VaultProvider {
             client,
             mount: config.mount_path,
             namespace: config.namespace,
+            change_poll_interval: DEFAULT_CHANGE_POLL_INTERVAL,
         })
     }
+    
+    /// Set how often cached secrets are checked for changes made directly in
+    /// Vault
+    pub fn with_change_poll_interval(mut self, interval: Duration) -> Self {
+        self.change_poll_interval = interval;
+        self
+    }
+    
+    /// When `key` changed, if `cached_version` is no longer its live version
+    ///
+    /// A new version, a deleted or destroyed current version and a removed
+    /// secret all count as changes.
+    async fn changed_since(&self, key: &str, cached_version: Option<&str>) -> Result<Option<DateTime<Utc>>> {
+        let metadata = match kv2::read_metadata(&self.client, &self.mount, key).await {
+            Ok(metadata) => metadata,
+            Err(ClientError::APIError { code: 404, .. }) => return Ok(Some(Utc::now())),
+            Err(e) => return Err(SecretsError::ProviderError(format!("Vault metadata error: {}", e))),
+        };
+        
+        let current_version = metadata.current_version.to_string();
+        if let Some(current) = metadata.versions.get(&current_version) {
+            if current.destroyed || !current.deletion_time.is_empty() {
+                return Ok(Some(parse_vault_time(&current.deletion_time)));
+            }
+        }
+        
+        if cached_version == Some(current_version.as_str()) {
+            Ok(None)
+        } else {
+            Ok(Some(parse_vault_time(&metadata.updated_time)))
+        }
+    }
+}
+
+/// Vault timestamps are RFC 3339; fall back to now for anything else
+fn parse_vault_time(timestamp: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+/// Vault has no change feed for KV secrets, so cached keys are polled and
+/// evicted once Vault reports a different current version
+#[async_trait]
+impl ChangeNotifier for VaultProvider {
+    fn name(&self) -> &str {
+        "vault"
+    }
+    
+    async fn watch(&self, bus: InvalidationBus, cache: SecretCache) -> Result<()> {
+        let mut ticker = tokio::time::interval(self.change_poll_interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        
+        loop {
+            ticker.tick().await;
+            
+            for (key, cached_version) in cache.versions() {
+                match self.changed_since(&key, cached_version.as_deref()).await {
+                    Ok(Some(changed_at)) => {
+                        debug!("Secret changed in Vault: {}", key);
+                        if let Err(e) = bus.invalidate_at(&key, InvalidationReason::ProviderChanged, changed_at).await {
+                            warn!("Failed to publish invalidation for {}: {}", key, e);
+                        }
+                    }
+                    Ok(None) => {}
+                    // Keep serving the cache while Vault is unreachable
+                    Err(e) => warn!("Failed to check {} for changes: {}", key, e),
+                }
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -314,4 +395,80 @@ mod tests {
         // Test delete secret
         provider.delete_secret("test/myapp").await.unwrap();
     }
+    
+    fn metadata_body(current_version: u64, deletion_time: &str) -> String {
+        serde_json::json!({
+            "request_id": "",
+            "lease_id": "",
+            "renewable": false,
+            "lease_duration": 0,
+            "wrap_info": null,
+            "warnings": null,
+            "auth": null,
+            "data": {
+                "cas_required": false,
+                "created_time": "2025-01-01T00:00:00.000000000Z",
+                "current_version": current_version,
+                "delete_version_after": "0s",
+                "max_versions": 0,
+                "oldest_version": 0,
+                "updated_time": "2025-02-01T12:30:00.250000000Z",
+                "custom_metadata": null,
+                "versions": {
+                    current_version.to_string(): {
+                        "created_time": "2025-02-01T12:30:00.250000000Z",
+                        "deletion_time": deletion_time,
+                        "destroyed": false
+                    }
+                }
+            }
+        })
+        .to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_changed_since() {
+        let mut server = mockito::Server::new_async().await;
+        let config = VaultConfig {
+            address: server.url(),
+            token: None,
+            app_role: None,
+            kubernetes_auth: None,
+            mount_path: "secret".to_string(),
+            namespace: None,
+            tls_ca_cert: None,
+            tls_client_cert: None,
+            tls_client_key: None,
+            timeout_seconds: 5,
+        };
+        let provider = VaultProvider::new(config).await.unwrap();
+        
+        let _current = server
+            .mock("GET", "/v1/secret/metadata/app/db")
+            .with_body(metadata_body(3, ""))
+            .create_async()
+            .await;
+        let _deleted = server
+            .mock("GET", "/v1/secret/metadata/app/old")
+            .with_body(metadata_body(2, "2025-02-02T08:00:00Z"))
+            .create_async()
+            .await;
+        let _missing = server
+            .mock("GET", "/v1/secret/metadata/app/gone")
+            .with_status(404)
+            .with_body(r#"{"errors":[]}"#)
+            .create_async()
+            .await;
+        
+        assert_eq!(provider.changed_since("app/db", Some("3")).await.unwrap(), None);
+        assert_eq!(
+            provider.changed_since("app/db", Some("2")).await.unwrap(),
+            Some(parse_vault_time("2025-02-01T12:30:00.25Z"))
+        );
+        assert_eq!(
+            provider.changed_since("app/old", Some("2")).await.unwrap(),
+            Some(parse_vault_time("2025-02-02T08:00:00Z"))
+        );
+        assert!(provider.changed_since("app/gone", Some("1")).await.unwrap().is_some());
+    }
 }
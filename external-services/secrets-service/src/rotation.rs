@@ -1,10 +1,11 @@
 //! Secret rotation management
 
 use crate::{SecretProvider, Result, SecretsError};
+use crate::invalidation::{InvalidationBus, InvalidationReason};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Rotation policy for a secret
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,15 +51,27 @@ impl RotationPolicy {
 /// Rotation manager
 pub struct RotationManager {
     policies: HashMap<String, RotationPolicy>,
+    invalidation: Option<InvalidationBus>,
 }
 
 impl RotationManager {
     pub fn new() -> Self {
         Self {
             policies: HashMap::new(),
+            invalidation: None,
         }
     }
     
+    /// Publish an invalidation for every rotated secret so caches drop the
+    /// old value immediately
+    ///
+    /// Not needed when rotating through a `SecretsManager`, which publishes
+    /// its own.
+    pub fn with_invalidation(mut self, bus: InvalidationBus) -> Self {
+        self.invalidation = Some(bus);
+        self
+    }
+    
     /// Add or update a rotation policy
     pub fn add_policy(&mut self, policy: RotationPolicy) {
         self.policies.insert(policy.key.clone(), policy);
@@ -107,6 +120,12 @@ impl RotationManager {
         
         info!("Secret rotated successfully: {} (version: {})", key, version);
         
+        if let Some(ref bus) = self.invalidation {
+            if let Err(e) = bus.invalidate_at(key, InvalidationReason::Rotated, policy.last_rotated).await {
+                warn!("Failed to publish invalidation for rotated secret {}: {}", key, e);
+            }
+        }
+        
         Ok(version)
     }
     
@@ -136,6 +155,7 @@ impl Default for RotationManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::MemoryProvider;
     
     #[test]
     fn test_rotation_policy() {
@@ -176,4 +196,26 @@ mod tests {
         assert_eq!(needs_rotation.len(), 1);
         assert_eq!(needs_rotation[0], "test/secret");
     }
+    
+    #[tokio::test]
+    async fn test_rotation_publishes_invalidation() {
+        let bus = InvalidationBus::new();
+        let mut events = bus.subscribe();
+        let mut manager = RotationManager::new().with_invalidation(bus);
+        manager.add_policy(RotationPolicy {
+            key: "test/secret".to_string(),
+            interval_days: 30,
+            last_rotated: Utc::now() - Duration::days(31),
+            enabled: true,
+            custom_handler: None,
+        });
+        
+        let provider = MemoryProvider::with_keys(&["test/secret"]);
+        manager.rotate_secret("test/secret", &provider).await.unwrap();
+        
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.key, "test/secret");
+        assert_eq!(event.reason, InvalidationReason::Rotated);
+        assert_eq!(event.occurred_at, manager.get_policy("test/secret").unwrap().last_rotated);
+    }
 }
@@ -0,0 +1,89 @@
+//! Test doubles shared by the unit tests
+
+use crate::{HealthStatus, Result, Secret, SecretMetadata, SecretProvider, SecretsError};
+use async_trait::async_trait;
+
+/// In-memory provider for exercising the manager without a backend
+#[derive(Default)]
+pub(crate) struct MemoryProvider {
+    secrets: std::sync::Mutex<std::collections::BTreeMap<String, String>>,
+    unavailable: std::sync::atomic::AtomicBool,
+}
+
+impl MemoryProvider {
+    pub(crate) fn with_keys(keys: &[&str]) -> Self {
+        let provider = Self::default();
+        provider.secrets.lock().unwrap().extend(
+            keys.iter().map(|key| (key.to_string(), format!("value of {}", key))),
+        );
+        provider
+    }
+    
+    /// Fail reads as if the backend were down
+    pub(crate) fn set_unavailable(&self, unavailable: bool) {
+        self.unavailable.store(unavailable, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl SecretProvider for MemoryProvider {
+    fn name(&self) -> &str {
+        "memory"
+    }
+    
+    async fn health_check(&self) -> Result<HealthStatus> {
+        Ok(HealthStatus {
+            healthy: true,
+            message: "ok".to_string(),
+            latency_ms: 0,
+            last_check: chrono::Utc::now(),
+        })
+    }
+    
+    async fn get_secret(&self, key: &str) -> Result<Secret> {
+        if self.unavailable.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(SecretsError::ProviderError("memory provider unavailable".to_string()));
+        }
+        let value = self.secrets.lock().unwrap().get(key).cloned()
+            .ok_or_else(|| SecretsError::NotFound(key.to_string()))?;
+        Ok(Secret {
+            metadata: SecretMetadata {
+                key: key.to_string(),
+                version: None,
+                created_at: None,
+                updated_at: None,
+                expires_at: None,
+                rotation_enabled: false,
+                rotation_interval_days: None,
+                tags: Default::default(),
+            },
+            value,
+        })
+    }
+    
+    async fn get_secret_version(&self, key: &str, _version: &str) -> Result<Secret> {
+        self.get_secret(key).await
+    }
+    
+    async fn set_secret(&self, key: &str, value: &str, _metadata: Option<SecretMetadata>) -> Result<()> {
+        self.secrets.lock().unwrap().insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+    
+    async fn delete_secret(&self, key: &str) -> Result<()> {
+        self.secrets.lock().unwrap().remove(key);
+        Ok(())
+    }
+    
+    async fn list_secrets(&self) -> Result<Vec<String>> {
+        Ok(self.secrets.lock().unwrap().keys().cloned().collect())
+    }
+    
+    async fn list_versions(&self, _key: &str) -> Result<Vec<String>> {
+        Ok(vec!["1".to_string()])
+    }
+    
+    async fn rotate_secret(&self, key: &str) -> Result<String> {
+        Ok(self.get_secret(key).await?.value)
+    }
+}
@@ -0,0 +1,334 @@
+//! Batch eligibility checks
+//!
+//! Types behind [`EligibilityVerifier::check_batch`](crate::EligibilityVerifier::check_batch):
+//! the clearinghouse the inquiries go to, the cache that answers same-day
+//! re-checks, the checkpoints that let an interrupted batch resume, and the
+//! report and progress views the front desk and monitoring read.
+
+use crate::error::{InsuranceError, InsuranceResult};
+use crate::models::EligibilityResult;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Eligibility inquiry for one patient's coverage on a date of service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EligibilityRequest {
+    pub patient_id: Uuid,
+    pub insurance_id: Uuid,
+    pub payer_id: String,
+    pub member_id: String,
+    pub service_date: NaiveDate,
+    pub appointment_id: Option<Uuid>,
+}
+
+impl EligibilityRequest {
+    /// Identifies the inquiry in the cache and in batch checkpoints
+    pub fn key(&self) -> String {
+        format!("{}:{}:{}", self.patient_id, self.insurance_id, self.service_date)
+    }
+}
+
+/// Batch id for a set of requests; the same requests always get the same id,
+/// so running them again resumes the earlier batch
+pub fn batch_id(requests: &[EligibilityRequest]) -> Uuid {
+    let mut keys: Vec<String> = requests.iter().map(EligibilityRequest::key).collect();
+    keys.sort();
+    keys.dedup();
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, keys.join("\n").as_bytes())
+}
+
+/// Sends 270 inquiries and returns the payer's 271 answer
+#[async_trait]
+pub trait Clearinghouse: Send + Sync {
+    /// Transient failures should be reported as [`InsuranceError::Unavailable`]
+    /// or as network errors so they are retried
+    async fn inquire(&self, request: &EligibilityRequest) -> InsuranceResult<EligibilityResult>;
+}
+
+/// Limits and timings for batch checks
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Inquiries in flight at once
+    pub concurrency: usize,
+    /// Attempts per inquiry, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for each further one
+    pub retry_backoff: Duration,
+    /// How long a clearinghouse answer is reused
+    pub cache_ttl: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(500),
+            cache_ttl: Duration::from_secs(12 * 60 * 60),
+        }
+    }
+}
+
+/// What a check found for one request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum EligibilityOutcome {
+    Active { result: EligibilityResult },
+    Inactive { result: EligibilityResult, reason: String },
+    /// The inquiry could not be answered; re-running the batch tries it again
+    Error { reason: String },
+}
+
+impl EligibilityOutcome {
+    /// Classify a clearinghouse answer for the date of service
+    pub fn from_result(result: EligibilityResult, service_date: NaiveDate) -> Self {
+        let reason = if !result.active {
+            Some("Coverage is not active".to_string())
+        } else if let Some(start) = result.effective_date.filter(|start| start.date_naive() > service_date) {
+            Some(format!("Coverage starts on {}, after the date of service", start.date_naive()))
+        } else if let Some(end) = result.termination_date.filter(|end| end.date_naive() <= service_date) {
+            Some(format!("Coverage ended on {}", end.date_naive()))
+        } else if !result.is_eligible {
+            Some("Payer reports the patient is not eligible".to_string())
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => EligibilityOutcome::Inactive { result, reason },
+            None => EligibilityOutcome::Active { result },
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, EligibilityOutcome::Error { .. })
+    }
+}
+
+/// Where an outcome came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeSource {
+    Clearinghouse,
+    Cache,
+    /// Recorded by an earlier run of the same batch
+    Checkpoint,
+}
+
+/// Outcome of one request in a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchEntry {
+    pub request: EligibilityRequest,
+    pub outcome: EligibilityOutcome,
+    pub source: OutcomeSource,
+    /// Clearinghouse attempts made in this run
+    pub attempts: u32,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Results of a batch, one entry per distinct request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub batch_id: Uuid,
+    pub entries: Vec<BatchEntry>,
+    pub progress: BatchProgress,
+}
+
+impl BatchReport {
+    pub fn active(&self) -> impl Iterator<Item = &BatchEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.outcome, EligibilityOutcome::Active { .. }))
+    }
+
+    pub fn inactive(&self) -> impl Iterator<Item = &BatchEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.outcome, EligibilityOutcome::Inactive { .. }))
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &BatchEntry> {
+        self.entries.iter().filter(|entry| entry.outcome.is_error())
+    }
+}
+
+/// Running totals of a batch, for monitoring
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchProgress {
+    pub batch_id: Uuid,
+    pub total: usize,
+    pub completed: usize,
+    /// Completed by an earlier run and skipped
+    pub resumed: usize,
+    pub from_cache: usize,
+    pub active: usize,
+    pub inactive: usize,
+    pub errors: usize,
+    pub retries: u32,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl BatchProgress {
+    pub(crate) fn new(batch_id: Uuid, total: usize) -> Self {
+        Self {
+            batch_id,
+            total,
+            completed: 0,
+            resumed: 0,
+            from_cache: 0,
+            active: 0,
+            inactive: 0,
+            errors: 0,
+            retries: 0,
+            started_at: Utc::now(),
+            finished_at: None,
+        }
+    }
+
+    pub(crate) fn record(&mut self, entry: &BatchEntry) {
+        self.completed += 1;
+        self.retries += entry.attempts.saturating_sub(1);
+        match entry.source {
+            OutcomeSource::Checkpoint => self.resumed += 1,
+            OutcomeSource::Cache => self.from_cache += 1,
+            OutcomeSource::Clearinghouse => {}
+        }
+        match entry.outcome {
+            EligibilityOutcome::Active { .. } => self.active += 1,
+            EligibilityOutcome::Inactive { .. } => self.inactive += 1,
+            EligibilityOutcome::Error { .. } => self.errors += 1,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished_at.is_some()
+    }
+
+    /// Share of requests completed, from 0 to 100
+    pub fn percent_complete(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0;
+        }
+        self.completed as f64 * 100.0 / self.total as f64
+    }
+}
+
+/// Clearinghouse answers kept for [`BatchConfig::cache_ttl`]
+#[derive(Default)]
+pub struct EligibilityCache {
+    entries: RwLock<HashMap<String, (EligibilityResult, Instant)>>,
+}
+
+impl EligibilityCache {
+    pub async fn get(&self, request: &EligibilityRequest, ttl: Duration) -> Option<EligibilityResult> {
+        let entries = self.entries.read().await;
+        entries
+            .get(&request.key())
+            .filter(|(_, stored)| stored.elapsed() < ttl)
+            .map(|(result, _)| result.clone())
+    }
+
+    pub async fn put(&self, request: &EligibilityRequest, result: EligibilityResult) {
+        self.entries.write().await.insert(request.key(), (result, Instant::now()));
+    }
+
+    /// Drop entries older than `ttl`
+    pub async fn purge(&self, ttl: Duration) {
+        self.entries.write().await.retain(|_, (_, stored)| stored.elapsed() < ttl);
+    }
+}
+
+/// Completed entries of each batch, so a re-run skips them
+#[async_trait]
+pub trait BatchCheckpointStore: Send + Sync {
+    async fn completed(&self, batch_id: Uuid) -> InsuranceResult<Vec<BatchEntry>>;
+
+    /// Only entries with an answer are recorded; errors are retried on the next run
+    async fn record(&self, batch_id: Uuid, entry: &BatchEntry) -> InsuranceResult<()>;
+}
+
+/// In-process checkpoints; a batch resumes only within the same process
+#[derive(Default)]
+pub struct InMemoryCheckpointStore {
+    batches: RwLock<HashMap<Uuid, HashMap<String, BatchEntry>>>,
+}
+
+impl InMemoryCheckpointStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl BatchCheckpointStore for InMemoryCheckpointStore {
+    async fn completed(&self, batch_id: Uuid) -> InsuranceResult<Vec<BatchEntry>> {
+        let batches = self.batches.read().await;
+        Ok(batches.get(&batch_id).map(|entries| entries.values().cloned().collect()).unwrap_or_default())
+    }
+
+    async fn record(&self, batch_id: Uuid, entry: &BatchEntry) -> InsuranceResult<()> {
+        self.batches
+            .write()
+            .await
+            .entry(batch_id)
+            .or_default()
+            .insert(entry.request.key(), entry.clone());
+        Ok(())
+    }
+}
+
+/// Checkpoints in the `eligibility_batch_checkpoints` table, surviving restarts
+pub struct PostgresCheckpointStore {
+    pool: PgPool,
+}
+
+impl PostgresCheckpointStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl BatchCheckpointStore for PostgresCheckpointStore {
+    async fn completed(&self, batch_id: Uuid) -> InsuranceResult<Vec<BatchEntry>> {
+        let rows = sqlx::query("SELECT entry FROM eligibility_batch_checkpoints WHERE batch_id = $1")
+            .bind(batch_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| InsuranceError::Storage(format!("Failed to read batch checkpoints: {}", e)))?;
+
+        rows.iter()
+            .map(|row| {
+                let entry: serde_json::Value = row
+                    .try_get("entry")
+                    .map_err(|e| InsuranceError::Storage(format!("Invalid batch checkpoint: {}", e)))?;
+                Ok(serde_json::from_value(entry)?)
+            })
+            .collect()
+    }
+
+    async fn record(&self, batch_id: Uuid, entry: &BatchEntry) -> InsuranceResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO eligibility_batch_checkpoints (batch_id, request_key, entry, recorded_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (batch_id, request_key) DO UPDATE SET entry = EXCLUDED.entry, recorded_at = EXCLUDED.recorded_at
+            "#,
+        )
+        .bind(batch_id)
+        .bind(entry.request.key())
+        .bind(serde_json::to_value(entry)?)
+        .bind(entry.checked_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| InsuranceError::Storage(format!("Failed to record batch checkpoint: {}", e)))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,436 @@
+//! Coordination of benefits
+//!
+//! Orders a patient's active coverages into primary, secondary and tertiary
+//! payers. Each pair of coverages is decided by the first rule that applies:
+//!
+//! 1. Medicaid pays last, and TRICARE pays after every other plan but Medicaid
+//! 2. Medicare against another plan follows the Medicare Secondary Payer rules:
+//!    a group plan from current employment pays first for the working aged
+//!    (employer of 20 or more) and for the disabled (100 or more), any group
+//!    plan pays first during the 30-month ESRD coordination period, and
+//!    Medicare pays first otherwise
+//! 3. Two other plans follow the NAIC model rules: a court decree, then the
+//!    plan covering the patient as subscriber over one covering them as a
+//!    dependent, the birthday rule for dependent children, active employment
+//!    over retiree or COBRA coverage, and finally the longer-held coverage
+//!
+//! When no rule separates two coverages, or a rule needs information the
+//! coverage lacks, the pair is reported for manual review instead of being
+//! ordered by guesswork.
+
+use crate::error::{InsuranceError, InsuranceResult};
+use crate::models::{InsurancePlan, PlanType};
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use uuid::Uuid;
+
+/// Employees an employer needs for its plan to pay before Medicare for the working aged
+pub const MSP_WORKING_AGED_EMPLOYER_SIZE: u32 = 20;
+/// Employees an employer needs for its plan to pay before Medicare for the disabled
+pub const MSP_DISABILITY_EMPLOYER_SIZE: u32 = 100;
+/// Months a group plan pays before Medicare once a patient is eligible through ESRD
+pub const ESRD_COORDINATION_MONTHS: u32 = 30;
+
+/// The patient's relationship to the plan subscriber
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriberRelationship {
+    #[serde(rename = "self")]
+    Subscriber,
+    Spouse,
+    Child,
+    Other,
+}
+
+/// What the subscriber holds the coverage through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageBasis {
+    /// Group plan from the subscriber's current employment
+    ActiveEmployment,
+    Retiree,
+    Cobra,
+    /// Individual or marketplace policy
+    Individual,
+    /// Medicare, Medicaid or TRICARE
+    Government,
+}
+
+impl CoverageBasis {
+    fn is_group(&self) -> bool {
+        matches!(self, CoverageBasis::ActiveEmployment | CoverageBasis::Retiree | CoverageBasis::Cobra)
+    }
+}
+
+/// Why a patient is entitled to Medicare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "basis")]
+pub enum MedicareEntitlement {
+    Age,
+    Disability,
+    /// End-stage renal disease, starting the coordination period on `eligible_from`
+    Esrd { eligible_from: NaiveDate },
+}
+
+/// A patient's enrollment in a plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Coverage {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub plan: InsurancePlan,
+    pub member_id: String,
+    pub relationship: SubscriberRelationship,
+    /// Needed for the birthday rule when the patient is a dependent child
+    pub subscriber_birth_date: Option<NaiveDate>,
+    pub basis: CoverageBasis,
+    /// Needed for Medicare Secondary Payer decisions on employment-based plans
+    pub employer_size: Option<u32>,
+    /// Set on Medicare coverages
+    pub medicare_entitlement: Option<MedicareEntitlement>,
+    /// A court decree makes this plan responsible for the child's health care
+    #[serde(default)]
+    pub court_ordered_primary: bool,
+    pub effective_date: DateTime<Utc>,
+    pub termination_date: Option<DateTime<Utc>>,
+}
+
+impl Coverage {
+    /// Whether the coverage is in force on `date`
+    pub fn is_active_on(&self, date: NaiveDate) -> bool {
+        self.plan.is_active
+            && self.effective_date.date_naive() <= date
+            && self.termination_date.is_none_or(|end| date < end.date_naive())
+    }
+
+    fn payer_class(&self) -> PayerClass {
+        match self.plan.plan_type {
+            PlanType::Medicaid => PayerClass::Medicaid,
+            PlanType::Tricare => PayerClass::Tricare,
+            PlanType::Medicare => PayerClass::Medicare,
+            PlanType::Commercial | PlanType::SelfPay => PayerClass::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PayerClass {
+    Other,
+    Medicare,
+    Tricare,
+    Medicaid,
+}
+
+/// Facts about the encounter the order is determined for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CobContext {
+    pub patient_id: Uuid,
+    /// Date of service; decides which coverages are in force
+    pub as_of: NaiveDate,
+}
+
+/// Rule that ordered a payer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CobRule {
+    OnlyCoverage,
+    MedicaidLastResort,
+    TricareSecondary,
+    MspWorkingAged,
+    MspDisability,
+    MspEsrdCoordination,
+    MedicarePrimary,
+    CourtDecree,
+    SubscriberBeforeDependent,
+    BirthdayRule,
+    ActiveBeforeInactive,
+    LongerCoverage,
+    /// No rule applied; the position is provisional
+    ManualReview,
+}
+
+/// Position in the order payers are billed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayerRank {
+    #[default]
+    Primary,
+    Secondary,
+    Tertiary,
+    /// Fourth payer or later
+    Subsequent,
+}
+
+impl PayerRank {
+    fn at(index: usize) -> Self {
+        match index {
+            0 => PayerRank::Primary,
+            1 => PayerRank::Secondary,
+            2 => PayerRank::Tertiary,
+            _ => PayerRank::Subsequent,
+        }
+    }
+}
+
+/// A coverage in its place in the billing order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CobPayer {
+    pub coverage_id: Uuid,
+    pub plan_id: Uuid,
+    pub payer_id: String,
+    pub payer_name: String,
+    pub rank: PayerRank,
+    /// Rule that put the payer behind the one before it; for the primary, the
+    /// rule that put it ahead of the secondary
+    pub rule: CobRule,
+    pub reason: String,
+}
+
+/// Two coverages no rule could order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CobReview {
+    pub coverage_ids: [Uuid; 2],
+    pub reason: String,
+}
+
+/// Billing order of a patient's coverages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CobDetermination {
+    pub patient_id: Uuid,
+    pub as_of: NaiveDate,
+    pub payers: Vec<CobPayer>,
+    /// When not empty, `payers` is a proposal that must be confirmed by staff
+    pub manual_review: Vec<CobReview>,
+    /// Self-pay entries and coverages not in force on `as_of`
+    pub excluded: Vec<Uuid>,
+}
+
+impl CobDetermination {
+    pub fn needs_review(&self) -> bool {
+        !self.manual_review.is_empty()
+    }
+
+    pub fn payer(&self, rank: PayerRank) -> Option<&CobPayer> {
+        self.payers.iter().find(|payer| payer.rank == rank)
+    }
+
+    pub fn primary(&self) -> Option<&CobPayer> {
+        self.payer(PayerRank::Primary)
+    }
+
+    /// Payers billed before the one holding `coverage_id`
+    pub fn payers_before(&self, coverage_id: Uuid) -> Option<&[CobPayer]> {
+        let index = self.payers.iter().position(|payer| payer.coverage_id == coverage_id)?;
+        Some(&self.payers[..index])
+    }
+}
+
+struct Decision {
+    order: Ordering,
+    rule: CobRule,
+    reason: String,
+}
+
+impl Decision {
+    fn first(first_is_a: bool, rule: CobRule, reason: String) -> Self {
+        let order = if first_is_a { Ordering::Less } else { Ordering::Greater };
+        Self { order, rule, reason }
+    }
+}
+
+/// Order `coverages` that are in force on the context date
+pub fn determine_cob_order(coverages: &[Coverage], context: &CobContext) -> InsuranceResult<CobDetermination> {
+    if let Some(other) = coverages.iter().find(|coverage| coverage.patient_id != context.patient_id) {
+        return Err(InsuranceError::Eligibility(format!(
+            "Coverage {} belongs to a different patient",
+            other.id
+        )));
+    }
+
+    let (active, inactive): (Vec<&Coverage>, Vec<&Coverage>) = coverages
+        .iter()
+        .partition(|coverage| coverage.is_active_on(context.as_of) && !matches!(coverage.plan.plan_type, PlanType::SelfPay));
+
+    // Insertion sort, since a pair may need review and the rules are not a total order
+    let mut ordered: Vec<&Coverage> = Vec::with_capacity(active.len());
+    let mut manual_review = Vec::new();
+    for coverage in active {
+        let mut at = ordered.len();
+        for (index, placed) in ordered.iter().enumerate() {
+            match decide(coverage, placed, context) {
+                Ok(decision) if decision.order == Ordering::Less => {
+                    at = index;
+                    break;
+                }
+                Ok(_) => {}
+                Err(reason) => manual_review.push(CobReview {
+                    coverage_ids: [placed.id, coverage.id],
+                    reason,
+                }),
+            }
+        }
+        ordered.insert(at, coverage);
+    }
+
+    let payers = ordered
+        .iter()
+        .enumerate()
+        .map(|(index, coverage)| {
+            let neighbour = match index {
+                0 => ordered.get(1),
+                _ => ordered.get(index - 1),
+            };
+            let (rule, reason) = match neighbour {
+                None => (CobRule::OnlyCoverage, "Only coverage in force".to_string()),
+                Some(neighbour) => match decide(coverage, neighbour, context) {
+                    Ok(decision) => (decision.rule, decision.reason),
+                    Err(reason) => (CobRule::ManualReview, reason),
+                },
+            };
+            CobPayer {
+                coverage_id: coverage.id,
+                plan_id: coverage.plan.id,
+                payer_id: coverage.plan.payer_id.clone(),
+                payer_name: coverage.plan.payer_name.clone(),
+                rank: PayerRank::at(index),
+                rule,
+                reason,
+            }
+        })
+        .collect();
+
+    Ok(CobDetermination {
+        patient_id: context.patient_id,
+        as_of: context.as_of,
+        payers,
+        manual_review,
+        excluded: inactive.iter().map(|coverage| coverage.id).collect(),
+    })
+}
+
+/// Which of `a` and `b` pays first, or why that cannot be decided
+fn decide(a: &Coverage, b: &Coverage, context: &CobContext) -> Result<Decision, String> {
+    use PayerClass::*;
+    match (a.payer_class(), b.payer_class()) {
+        (Medicaid, Medicaid) => Err("Patient has more than one Medicaid coverage".to_string()),
+        (Medicaid, _) | (_, Medicaid) => Ok(Decision::first(
+            b.payer_class() == Medicaid,
+            CobRule::MedicaidLastResort,
+            "Medicaid is the payer of last resort".to_string(),
+        )),
+        (Tricare, Tricare) => Err("Patient has more than one TRICARE coverage".to_string()),
+        (Tricare, _) | (_, Tricare) => Ok(Decision::first(
+            b.payer_class() == Tricare,
+            CobRule::TricareSecondary,
+            "TRICARE pays after other health insurance".to_string(),
+        )),
+        (Medicare, Medicare) => Err("Patient has more than one Medicare coverage".to_string()),
+        (Medicare, Other) => medicare_secondary_payer(a, b, context).map(|decision| Decision {
+            order: decision.order.reverse(),
+            ..decision
+        }),
+        (Other, Medicare) => medicare_secondary_payer(b, a, context),
+        (Other, Other) => naic(a, b),
+    }
+}
+
+/// Whether `plan` pays before `medicare` (`Ordering::Less`) under the MSP rules
+fn medicare_secondary_payer(medicare: &Coverage, plan: &Coverage, context: &CobContext) -> Result<Decision, String> {
+    let entitlement = medicare
+        .medicare_entitlement
+        .ok_or_else(|| format!("Medicare coverage {} does not record the basis of entitlement", medicare.id))?;
+    let medicare_first = |reason: &str| Ok(Decision::first(false, CobRule::MedicarePrimary, reason.to_string()));
+
+    match entitlement {
+        MedicareEntitlement::Esrd { eligible_from } => {
+            let coordination_ends = eligible_from
+                .checked_add_months(Months::new(ESRD_COORDINATION_MONTHS))
+                .unwrap_or(NaiveDate::MAX);
+            if plan.basis.is_group() && context.as_of < coordination_ends {
+                Ok(Decision::first(
+                    true,
+                    CobRule::MspEsrdCoordination,
+                    format!("Group plan pays first during the ESRD coordination period ending {coordination_ends}"),
+                ))
+            } else {
+                medicare_first("Medicare pays first for ESRD outside the coordination period")
+            }
+        }
+        MedicareEntitlement::Age | MedicareEntitlement::Disability if plan.basis == CoverageBasis::ActiveEmployment => {
+            let (threshold, rule, who) = match entitlement {
+                MedicareEntitlement::Age => (MSP_WORKING_AGED_EMPLOYER_SIZE, CobRule::MspWorkingAged, "working aged"),
+                _ => (MSP_DISABILITY_EMPLOYER_SIZE, CobRule::MspDisability, "disabled"),
+            };
+            let size = plan
+                .employer_size
+                .ok_or_else(|| format!("Employer size for coverage {} is needed to order it against Medicare", plan.id))?;
+            if size >= threshold {
+                Ok(Decision::first(
+                    true,
+                    rule,
+                    format!("Employer plan with {size} employees pays before Medicare for the {who}"),
+                ))
+            } else {
+                medicare_first(&format!("Medicare pays before an employer plan with fewer than {threshold} employees"))
+            }
+        }
+        MedicareEntitlement::Age | MedicareEntitlement::Disability => match plan.basis {
+            CoverageBasis::Retiree => medicare_first("Medicare pays before retiree coverage"),
+            CoverageBasis::Cobra => medicare_first("Medicare pays before COBRA continuation coverage"),
+            _ => medicare_first("Medicare pays before coverage not based on current employment"),
+        },
+    }
+}
+
+/// Whether `a` pays before `b` under the NAIC model COB rules
+fn naic(a: &Coverage, b: &Coverage) -> Result<Decision, String> {
+    use SubscriberRelationship::*;
+
+    if a.court_ordered_primary != b.court_ordered_primary {
+        return Ok(Decision::first(
+            a.court_ordered_primary,
+            CobRule::CourtDecree,
+            "A court decree makes this plan responsible".to_string(),
+        ));
+    }
+    if (a.relationship == Subscriber) != (b.relationship == Subscriber) {
+        return Ok(Decision::first(
+            a.relationship == Subscriber,
+            CobRule::SubscriberBeforeDependent,
+            "Plan covering the patient as subscriber pays before one covering them as a dependent".to_string(),
+        ));
+    }
+    if a.relationship == Child && b.relationship == Child {
+        let (Some(a_birth), Some(b_birth)) = (a.subscriber_birth_date, b.subscriber_birth_date) else {
+            return Err("Subscriber birth dates are needed to apply the birthday rule".to_string());
+        };
+        let (a_day, b_day) = ((a_birth.month(), a_birth.day()), (b_birth.month(), b_birth.day()));
+        if a_day != b_day {
+            return Ok(Decision::first(
+                a_day < b_day,
+                CobRule::BirthdayRule,
+                "Plan of the parent whose birthday falls earlier in the year pays first".to_string(),
+            ));
+        }
+    }
+    let is_active = |coverage: &Coverage| coverage.basis == CoverageBasis::ActiveEmployment;
+    let is_inactive = |coverage: &Coverage| matches!(coverage.basis, CoverageBasis::Retiree | CoverageBasis::Cobra);
+    if (is_active(a) && is_inactive(b)) || (is_inactive(a) && is_active(b)) {
+        return Ok(Decision::first(
+            is_active(a),
+            CobRule::ActiveBeforeInactive,
+            "Coverage from active employment pays before retiree or COBRA coverage".to_string(),
+        ));
+    }
+    match a.effective_date.date_naive().cmp(&b.effective_date.date_naive()) {
+        Ordering::Equal => Err(format!(
+            "Coverages {} and {} have equal priority under the COB rules",
+            a.id, b.id
+        )),
+        order => Ok(Decision {
+            order,
+            rule: CobRule::LongerCoverage,
+            reason: "Plan that has covered the patient longer pays first".to_string(),
+        }),
+    }
+}
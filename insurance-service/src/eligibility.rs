@@ -1,14 +1,52 @@
+use crate::batch::{
+    self, BatchCheckpointStore, BatchConfig, BatchEntry, BatchProgress, BatchReport, Clearinghouse,
+    EligibilityCache, EligibilityOutcome, EligibilityRequest, InMemoryCheckpointStore, OutcomeSource,
+};
+use crate::coverage::{self, CobContext, CobDetermination, Coverage};
 use crate::models::{EligibilityResult, InsurancePlan};
-use crate::error::InsuranceResult;
+use crate::error::{InsuranceError, InsuranceResult};
+use futures::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
 /// Eligibility verification service
-pub struct EligibilityVerifier;
+pub struct EligibilityVerifier {
+    clearinghouse: Option<Arc<dyn Clearinghouse>>,
+    checkpoints: Arc<dyn BatchCheckpointStore>,
+    cache: EligibilityCache,
+    batches: RwLock<HashMap<Uuid, BatchProgress>>,
+    config: BatchConfig,
+}
 
 impl EligibilityVerifier {
     /// Create a new eligibility verifier
     pub fn new() -> Self {
-        Self
+        Self {
+            clearinghouse: None,
+            checkpoints: Arc::new(InMemoryCheckpointStore::new()),
+            cache: EligibilityCache::default(),
+            batches: RwLock::new(HashMap::new()),
+            config: BatchConfig::default(),
+        }
+    }
+
+    /// Send inquiries to `clearinghouse`
+    pub fn with_clearinghouse(mut self, clearinghouse: Arc<dyn Clearinghouse>) -> Self {
+        self.clearinghouse = Some(clearinghouse);
+        self
+    }
+
+    /// Keep batch checkpoints in `store`, e.g. Postgres so batches resume after a restart
+    pub fn with_checkpoint_store(mut self, store: Arc<dyn BatchCheckpointStore>) -> Self {
+        self.checkpoints = store;
+        self
+    }
+
+    pub fn with_batch_config(mut self, config: BatchConfig) -> Self {
+        self.config = config;
+        self
     }
 
     /// Check eligibility real-time via 270/271 EDI
@@ -30,15 +68,162 @@ impl EligibilityVerifier {
             coverage_type: format!("{:?}", plan.plan_type),
             copay_info: Some(format!("Primary: ${:?}, Specialist: ${:?}", plan.benefits.copay_primary, plan.benefits.copay_specialist)),
             deductible_info: Some(format!("Deductible: ${}", plan.benefits.deductible)),
+            cob_rank: None,
             checked_at: chrono::Utc::now(),
         })
     }
 
-    /// Batch eligibility check
-    pub async fn check_batch(&self, patient_ids: Vec<Uuid>, insurance_id: Uuid) -> InsuranceResult<Vec<EligibilityResult>> {
-        // TODO: Implement batch eligibility check
-        Ok(vec![])
+    /// Check every coverage a patient holds, in coordination-of-benefits order
+    pub async fn check_coordinated(&self, coverages: &[Coverage], context: &CobContext) -> InsuranceResult<CoordinatedEligibility> {
+        let determination = coverage::determine_cob_order(coverages, context)?;
+        let mut results = Vec::with_capacity(determination.payers.len());
+        for payer in &determination.payers {
+            let Some(coverage) = coverages.iter().find(|coverage| coverage.id == payer.coverage_id) else {
+                continue;
+            };
+            let mut result = self.check_with_plan(&coverage.plan, context.patient_id).await?;
+            result.cob_rank = Some(payer.rank);
+            results.push(result);
+        }
+        Ok(CoordinatedEligibility { determination, results })
     }
+
+    /// Check many requests at once, e.g. the next day's appointments overnight.
+    ///
+    /// Requests already answered by an earlier run of the same batch (see
+    /// [`batch::batch_id`]) are taken from its checkpoints, and requests
+    /// answered within the cache TTL from the cache; the rest go to the
+    /// clearinghouse with bounded concurrency, retrying transient failures.
+    pub async fn check_batch(&self, requests: Vec<EligibilityRequest>) -> InsuranceResult<BatchReport> {
+        let clearinghouse = self
+            .clearinghouse
+            .as_ref()
+            .ok_or_else(|| InsuranceError::Config("No clearinghouse configured for eligibility checks".to_string()))?;
+
+        let batch_id = batch::batch_id(&requests);
+        let mut seen = HashSet::new();
+        let requests: Vec<EligibilityRequest> =
+            requests.into_iter().filter(|request| seen.insert(request.key())).collect();
+        self.batches
+            .write()
+            .await
+            .insert(batch_id, BatchProgress::new(batch_id, requests.len()));
+
+        let mut resumed: HashMap<String, BatchEntry> = self
+            .checkpoints
+            .completed(batch_id)
+            .await?
+            .into_iter()
+            .map(|entry| (entry.request.key(), entry))
+            .collect();
+        let mut entries = Vec::with_capacity(requests.len());
+        let mut pending = Vec::new();
+        for request in requests {
+            match resumed.remove(&request.key()) {
+                Some(entry) => {
+                    let entry = BatchEntry {
+                        source: OutcomeSource::Checkpoint,
+                        attempts: 0,
+                        ..entry
+                    };
+                    self.update_progress(batch_id, &entry).await;
+                    entries.push(entry);
+                }
+                None => pending.push(request),
+            }
+        }
+
+        let mut checked = stream::iter(pending)
+            .map(|request| self.check_one(clearinghouse.as_ref(), request))
+            .buffer_unordered(self.config.concurrency.max(1));
+        while let Some(entry) = checked.next().await {
+            if !entry.outcome.is_error() {
+                self.checkpoints.record(batch_id, &entry).await?;
+            }
+            self.update_progress(batch_id, &entry).await;
+            entries.push(entry);
+        }
+
+        let mut batches = self.batches.write().await;
+        let progress = batches
+            .get_mut(&batch_id)
+            .map(|progress| {
+                progress.finished_at = Some(chrono::Utc::now());
+                progress.clone()
+            })
+            .unwrap_or_else(|| BatchProgress::new(batch_id, entries.len()));
+        Ok(BatchReport { batch_id, entries, progress })
+    }
+
+    /// Progress of a batch started by this verifier
+    pub async fn batch_progress(&self, batch_id: Uuid) -> Option<BatchProgress> {
+        self.batches.read().await.get(&batch_id).cloned()
+    }
+
+    /// Progress of every batch started by this verifier
+    pub async fn batches(&self) -> Vec<BatchProgress> {
+        self.batches.read().await.values().cloned().collect()
+    }
+
+    /// Drop cached answers older than the cache TTL
+    pub async fn purge_cache(&self) {
+        self.cache.purge(self.config.cache_ttl).await;
+    }
+
+    async fn check_one(&self, clearinghouse: &dyn Clearinghouse, request: EligibilityRequest) -> BatchEntry {
+        if let Some(result) = self.cache.get(&request, self.config.cache_ttl).await {
+            return BatchEntry {
+                outcome: EligibilityOutcome::from_result(result, request.service_date),
+                request,
+                source: OutcomeSource::Cache,
+                attempts: 0,
+                checked_at: chrono::Utc::now(),
+            };
+        }
+
+        let mut attempts = 0;
+        let outcome = loop {
+            attempts += 1;
+            match clearinghouse.inquire(&request).await {
+                Ok(result) => {
+                    self.cache.put(&request, result.clone()).await;
+                    break EligibilityOutcome::from_result(result, request.service_date);
+                }
+                Err(error) if error.is_transient() && attempts < self.config.max_attempts => {
+                    tracing::warn!(request = %request.key(), attempt = attempts, error = %error, "Retrying eligibility inquiry");
+                    tokio::time::sleep(self.config.retry_backoff * 2u32.saturating_pow(attempts - 1)).await;
+                }
+                Err(error) => {
+                    let reason = if error.is_transient() {
+                        format!("{} (gave up after {} attempts)", error, attempts)
+                    } else {
+                        error.to_string()
+                    };
+                    break EligibilityOutcome::Error { reason };
+                }
+            }
+        };
+        BatchEntry {
+            request,
+            outcome,
+            source: OutcomeSource::Clearinghouse,
+            attempts,
+            checked_at: chrono::Utc::now(),
+        }
+    }
+
+    async fn update_progress(&self, batch_id: Uuid, entry: &BatchEntry) {
+        if let Some(progress) = self.batches.write().await.get_mut(&batch_id) {
+            progress.record(entry);
+        }
+    }
+}
+
+/// Eligibility of each coverage in force, primary first
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CoordinatedEligibility {
+    pub determination: CobDetermination,
+    pub results: Vec<EligibilityResult>,
 }
 
 impl Default for EligibilityVerifier {
@@ -14,6 +14,13 @@ pub enum InsuranceError {
     #[error("Insurance provider error: {0}")]
     Provider(String),
 
+    /// The clearinghouse or payer could not answer right now; worth retrying
+    #[error("Clearinghouse unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
@@ -24,5 +31,22 @@ pub enum InsuranceError {
     Unknown(String),
 }
 
+impl InsuranceError {
+    /// Whether the same request may succeed if sent again
+    pub fn is_transient(&self) -> bool {
+        match self {
+            InsuranceError::Unavailable(_) => true,
+            InsuranceError::Network(error) => {
+                error.is_timeout()
+                    || error.is_connect()
+                    || error
+                        .status()
+                        .is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
+            }
+            _ => false,
+        }
+    }
+}
+
 pub type InsuranceResult<T> = Result<T, InsuranceError>;
 
@@ -5,16 +5,21 @@
 //! - Prior authorization management
 //! - Insurance plan configuration
 //! - Benefit coverage tracking
+//! - Coordination of benefits across multiple coverages
 //! - Pre-certification workflows
 
 pub mod service;
 pub mod models;
+pub mod batch;
+pub mod coverage;
 pub mod eligibility;
 pub mod authorization;
 pub mod error;
 
 pub use service::*;
 pub use models::*;
+pub use batch::*;
+pub use coverage::*;
 pub use eligibility::*;
 pub use authorization::*;
 pub use error::*;
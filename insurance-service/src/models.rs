@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use crate::coverage::PayerRank;
 
 /// Insurance plan
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +50,9 @@ pub struct EligibilityResult {
     pub coverage_type: String,
     pub copay_info: Option<String>,
     pub deductible_info: Option<String>,
+    /// Where the coverage falls in coordination of benefits, when determined
+    #[serde(default)]
+    pub cob_rank: Option<PayerRank>,
     pub checked_at: DateTime<Utc>,
 }
 
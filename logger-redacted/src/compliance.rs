@@ -1,8 +1,437 @@
 // Compliance logging
-pub struct ComplianceLogger;
+//
+// Compliance events go to their own sink, never through `tracing`: a
+// directory of JSON lines files with its own rotation and retention,
+// optionally encrypted. Application log rotation and retention never touch
+// it, and the long HIPAA retention applies here only.
+//
+// Structured fields (`user_id`, `resource`, `action` and any others) are
+// written as given, because an audit has to name the real patient or record;
+// the sink's access controls and encryption protect them. The free-text
+// message still passes through the PII redactor unless configured otherwise.
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use crypto::Aes256GcmEncryptor;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::redactor::{PiiRedactor, RedactionConfig};
+
+/// HIPAA requires six years; compliance records are kept seven
+pub const DEFAULT_COMPLIANCE_RETENTION_DAYS: u32 = 7 * 365;
+
+/// Regulation an event is recorded for
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComplianceLevel {
+    HIPAA,
+    SOX,
+    GDPR,
+    PCI,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    DataAccess,
+    DataModification,
+    DataDeletion,
+    DataExport,
+    Authentication,
+    PermissionChange,
+    /// Break-glass access outside the normal permissions
+    EmergencyAccess,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComplianceEvent {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub level: ComplianceLevel,
+    pub event: AuditEvent,
+    pub user_id: Option<String>,
+    pub resource: Option<String>,
+    pub action: Option<String>,
+    pub message: String,
+    pub fields: BTreeMap<String, Value>,
+}
+
+impl ComplianceEvent {
+    pub fn new(level: ComplianceLevel, event: AuditEvent, message: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            level,
+            event,
+            user_id: None,
+            resource: None,
+            action: None,
+            message: message.into(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Set a field; `user_id`, `resource` and `action` fill their own slots
+    pub fn set(&mut self, key: &str, value: impl Serialize) {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        let text = || match &value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        match key {
+            "user_id" => self.user_id = Some(text()),
+            "resource" => self.resource = Some(text()),
+            "action" => self.action = Some(text()),
+            _ => {
+                self.fields.insert(key.to_string(), value);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    Hourly,
+    Daily,
+    /// One file, never rotated and so never pruned
+    Never,
+}
+
+/// Where and how compliance events are kept, independent of application logs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComplianceSinkConfig {
+    pub directory: PathBuf,
+    /// Files are named `<prefix>.<period>.jsonl`
+    pub file_prefix: String,
+    pub rotation: Rotation,
+    /// Files whose period ended longer ago than this are deleted
+    pub retention_days: u32,
+    /// Require an encryption key and encrypt every line with it
+    pub encrypt: bool,
+    /// Pass the free-text message through the PII redactor
+    pub redact_message: bool,
+}
+
+impl Default for ComplianceSinkConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("/var/log/rustcare/compliance"),
+            file_prefix: "compliance".to_string(),
+            rotation: Rotation::Daily,
+            retention_days: DEFAULT_COMPLIANCE_RETENTION_DAYS,
+            encrypt: false,
+            redact_message: true,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ComplianceError {
+    #[error("Invalid compliance sink configuration: {0}")]
+    Config(String),
+
+    #[error("Compliance log I/O on {path} failed: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Compliance log encryption failed: {0}")]
+    Encryption(String),
+
+    #[error("Corrupt compliance log line in {path}: {reason}")]
+    Corrupt { path: PathBuf, reason: String },
+
+    #[error("No compliance logger is installed")]
+    NotInstalled,
+
+    #[error("A compliance logger is already installed")]
+    AlreadyInstalled,
+}
+
+struct OpenFile {
+    name: String,
+    file: File,
+}
+
+/// Writer for the compliance stream
+pub struct ComplianceLogger {
+    config: ComplianceSinkConfig,
+    encryptor: Option<Aes256GcmEncryptor>,
+    redactor: PiiRedactor,
+    current: Mutex<Option<OpenFile>>,
+}
+
+impl std::fmt::Debug for ComplianceLogger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComplianceLogger")
+            .field("config", &self.config)
+            .field("encrypted", &self.encryptor.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+static INSTALLED: OnceLock<ComplianceLogger> = OnceLock::new();
+
+/// Record an event with the installed logger; used by `compliance_log!`
+pub fn record(event: ComplianceEvent) -> Result<(), ComplianceError> {
+    INSTALLED.get().ok_or(ComplianceError::NotInstalled)?.record(event)
+}
 
 impl ComplianceLogger {
-    pub fn new() -> Self {
-        Self
+    /// Open the sink; `encryptor` must be given exactly when `config.encrypt` is set
+    pub fn open(config: ComplianceSinkConfig, encryptor: Option<Aes256GcmEncryptor>) -> Result<Self, ComplianceError> {
+        if config.encrypt != encryptor.is_some() {
+            return Err(ComplianceError::Config(if config.encrypt {
+                "encrypt is set but no encryption key was given".to_string()
+            } else {
+                "an encryption key was given but encrypt is not set".to_string()
+            }));
+        }
+        if config.retention_days == 0 {
+            return Err(ComplianceError::Config("retention_days must be at least 1".to_string()));
+        }
+        if config.file_prefix.is_empty() || config.file_prefix.contains(['/', '\\']) {
+            return Err(ComplianceError::Config(format!("invalid file prefix '{}'", config.file_prefix)));
+        }
+        fs::create_dir_all(&config.directory).map_err(|source| ComplianceError::Io {
+            path: config.directory.clone(),
+            source,
+        })?;
+
+        Ok(Self {
+            config,
+            encryptor,
+            redactor: PiiRedactor::new(RedactionConfig::default()),
+            current: Mutex::new(None),
+        })
+    }
+
+    /// Make this the logger `compliance_log!` writes to
+    pub fn install(self) -> Result<(), ComplianceError> {
+        INSTALLED.set(self).map_err(|_| ComplianceError::AlreadyInstalled)
+    }
+
+    /// Append an event to the file for its timestamp's period and flush it to disk
+    pub fn record(&self, mut event: ComplianceEvent) -> Result<(), ComplianceError> {
+        if self.config.redact_message {
+            event.message = self.redactor.redact(&event.message);
+        }
+        let json = serde_json::to_string(&event).expect("compliance events always serialize");
+        let mut line = match &self.encryptor {
+            Some(encryptor) => encryptor
+                .encrypt_string(&json)
+                .map_err(|e| ComplianceError::Encryption(e.to_string()))?,
+            None => json,
+        };
+        line.push('\n');
+
+        let name = self.file_name(event.timestamp);
+        let mut current = self.current.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if current.as_ref().is_none_or(|open| open.name != name) {
+            let path = self.config.directory.join(&name);
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|source| ComplianceError::Io { path, source })?;
+            let rotated = current.is_some();
+            *current = Some(OpenFile { name, file });
+            if rotated {
+                if let Err(error) = self.prune(event.timestamp) {
+                    tracing::warn!(error = %error, "Pruning expired compliance logs failed");
+                }
+            }
+        }
+
+        let open = current.as_mut().expect("a compliance log file is open");
+        let path = self.config.directory.join(&open.name);
+        open.file
+            .write_all(line.as_bytes())
+            .and_then(|()| open.file.sync_data())
+            .map_err(|source| ComplianceError::Io { path, source })
+    }
+
+    /// Delete files whose period ended more than `retention_days` before `now`
+    ///
+    /// Only this sink's own files are considered. Returns the deleted paths.
+    pub fn prune(&self, now: DateTime<Utc>) -> Result<Vec<PathBuf>, ComplianceError> {
+        let cutoff = now - Duration::days(i64::from(self.config.retention_days));
+        let directory = &self.config.directory;
+        let entries = fs::read_dir(directory).map_err(|source| ComplianceError::Io {
+            path: directory.clone(),
+            source,
+        })?;
+
+        let mut deleted = Vec::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(period_end) = self.period_end(&name) else {
+                continue;
+            };
+            if period_end <= cutoff {
+                let path = entry.path();
+                fs::remove_file(&path).map_err(|source| ComplianceError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+                tracing::info!(file = %path.display(), "Deleted compliance log past retention");
+                deleted.push(path);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Read back the events in one of this sink's files, decrypting them
+    pub fn read_file(&self, path: &Path) -> Result<Vec<ComplianceEvent>, ComplianceError> {
+        let content = fs::read_to_string(path).map_err(|source| ComplianceError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let corrupt = |reason: String| ComplianceError::Corrupt {
+            path: path.to_path_buf(),
+            reason,
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let json = match &self.encryptor {
+                    Some(encryptor) => encryptor.decrypt_string(line).map_err(|e| corrupt(e.to_string()))?,
+                    None => line.to_string(),
+                };
+                serde_json::from_str(&json).map_err(|e| corrupt(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Path of the file an event at `timestamp` goes to
+    pub fn file_path(&self, timestamp: DateTime<Utc>) -> PathBuf {
+        self.config.directory.join(self.file_name(timestamp))
+    }
+
+    fn file_name(&self, timestamp: DateTime<Utc>) -> String {
+        let prefix = &self.config.file_prefix;
+        match self.config.rotation {
+            Rotation::Hourly => format!("{}.{}.jsonl", prefix, timestamp.format("%Y-%m-%d-%H")),
+            Rotation::Daily => format!("{}.{}.jsonl", prefix, timestamp.format("%Y-%m-%d")),
+            Rotation::Never => format!("{}.jsonl", prefix),
+        }
+    }
+
+    /// End of the period a file of this sink covers; `None` for other files
+    fn period_end(&self, name: &str) -> Option<DateTime<Utc>> {
+        let period = name
+            .strip_prefix(self.config.file_prefix.as_str())?
+            .strip_prefix('.')?
+            .strip_suffix(".jsonl")?;
+        match self.config.rotation {
+            Rotation::Hourly => {
+                let start = NaiveDateTime::parse_from_str(&format!("{}:00", period), "%Y-%m-%d-%H:%M").ok()?;
+                Some(start.and_utc() + Duration::hours(1))
+            }
+            Rotation::Daily => {
+                let start = NaiveDate::parse_from_str(period, "%Y-%m-%d").ok()?;
+                Some(start.and_hms_opt(0, 0, 0)?.and_utc() + Duration::days(1))
+            }
+            Rotation::Never => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sink_config(encrypt: bool) -> ComplianceSinkConfig {
+        ComplianceSinkConfig {
+            directory: std::env::temp_dir().join(format!("compliance-{}", Uuid::new_v4())),
+            encrypt,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rotation_and_retention_apply_to_own_files_only() {
+        let config = sink_config(false);
+        let logger = ComplianceLogger::open(config.clone(), None).unwrap();
+        let now = Utc::now();
+
+        let mut old = ComplianceEvent::new(ComplianceLevel::HIPAA, AuditEvent::DataAccess, "old access");
+        old.timestamp = now - Duration::days(i64::from(DEFAULT_COMPLIANCE_RETENTION_DAYS) + 2);
+        let mut recent = ComplianceEvent::new(ComplianceLevel::HIPAA, AuditEvent::DataAccess, "recent access");
+        recent.timestamp = now - Duration::days(6 * 365);
+        logger.record(old.clone()).unwrap();
+        logger.record(recent.clone()).unwrap();
+
+        // An application log in the same directory is not the sink's to delete
+        let app_log = config.directory.join("app.2001-01-01.log");
+        fs::write(&app_log, "app").unwrap();
+
+        let deleted = logger.prune(now).unwrap();
+        assert_eq!(deleted, vec![logger.file_path(old.timestamp)]);
+        assert!(logger.file_path(recent.timestamp).exists());
+        assert!(app_log.exists());
+    }
+
+    #[test]
+    fn test_fields_kept_verbatim_message_redacted_and_encrypted() {
+        let key = crypto::KeyGenerator::generate_aes256_key();
+        let config = sink_config(true);
+        assert!(ComplianceLogger::open(config.clone(), None).is_err());
+        let logger = ComplianceLogger::open(config, Some(Aes256GcmEncryptor::new(key).unwrap())).unwrap();
+
+        let mut event = ComplianceEvent::new(
+            ComplianceLevel::HIPAA,
+            AuditEvent::DataAccess,
+            "Record viewed, callback (555) 123-4567",
+        );
+        event.set("user_id", "dr.smith");
+        event.set("patient_mrn", "MRN123456");
+        logger.record(event.clone()).unwrap();
+
+        let path = logger.file_path(event.timestamp);
+        assert!(!fs::read_to_string(&path).unwrap().contains("MRN123456"));
+
+        let events = logger.read_file(&path).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].user_id.as_deref(), Some("dr.smith"));
+        assert_eq!(events[0].fields["patient_mrn"], "MRN123456");
+        assert!(!events[0].message.contains("123-4567"));
+    }
+
+    #[test]
+    fn test_macro_records_to_installed_logger() {
+        let logger = ComplianceLogger::open(sink_config(false), None).unwrap();
+        let path = logger.file_path(Utc::now());
+        logger.install().unwrap();
+
+        let patient = "P-42";
+        crate::compliance_log!(
+            level = ComplianceLevel::HIPAA,
+            event = AuditEvent::DataAccess,
+            user_id = "nurse.jones",
+            resource = "patient_record",
+            patient_id = patient,
+            "Patient record {} accessed",
+            patient
+        )
+        .unwrap();
+
+        let events = INSTALLED.get().unwrap().read_file(&path).unwrap();
+        let event = events.last().unwrap();
+        assert_eq!(event.resource.as_deref(), Some("patient_record"));
+        assert_eq!(event.fields["patient_id"], "P-42");
+        assert_eq!(event.message, "Patient record P-42 accessed");
     }
-}
\ No newline at end of file
+}
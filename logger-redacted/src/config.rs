@@ -1,11 +1,25 @@
 // Logger configuration
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::compliance::ComplianceSinkConfig;
+use crate::control::SamplingRule;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggerConfig {
     pub redaction_enabled: bool,
     pub compliance_logging: bool,
     pub log_level: String,
+    /// Sink for `compliance_log!`, rotated and retained apart from application logs
+    #[serde(default)]
+    pub compliance: ComplianceSinkConfig,
+    /// Levels for individual targets, e.g. `sqlx = "warn"`; adjustable at runtime through `LogControl`
+    #[serde(default)]
+    pub target_levels: BTreeMap<String, String>,
+    /// Targets logged one in N events; errors are always kept
+    #[serde(default)]
+    pub sampling: BTreeMap<String, SamplingRule>,
 }
 
 impl Default for LoggerConfig {
@@ -14,6 +28,9 @@ impl Default for LoggerConfig {
             redaction_enabled: true,
             compliance_logging: true,
             log_level: "info".to_string(),
+            compliance: ComplianceSinkConfig::default(),
+            target_levels: BTreeMap::new(),
+            sampling: BTreeMap::new(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,430 @@
+// Runtime log levels and sampling
+//
+// `LogControl` holds per-target levels and sampling rules that can be changed
+// while the process runs, e.g. from an admin endpoint. Its `layer()` is added
+// to the existing subscriber and filters for every layer after it.
+//
+// Targets match like `EnvFilter` directives: a rule for `sqlx` applies to
+// `sqlx` and `sqlx::query`, and the longest matching target wins.
+//
+// Sampling keeps one in N events of a target, and always keeps errors. Inside
+// a span tree the decision is made once per trace from the root span's
+// `trace_id` field (or its span id), so a request is either kept whole or
+// dropped whole, and because the decision is a threshold on a hash of the
+// trace id, a trace kept under 1-in-100 is also kept under 1-in-10.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::LoggerConfig;
+
+/// Root span field that identifies a trace for sampling
+pub const TRACE_ID_FIELD: &str = "trace_id";
+
+/// Keep one in `one_in` events of a target; errors are always kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamplingRule {
+    pub one_in: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum ControlError {
+    #[error("Invalid log level '{0}'")]
+    InvalidLevel(String),
+
+    #[error("Sampling rate for '{0}' must keep at least one in every N events (N >= 1)")]
+    InvalidRate(String),
+}
+
+/// Active sampling for a target and what it has done so far
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SamplingStatus {
+    pub one_in: u32,
+    pub kept: u64,
+    pub dropped: u64,
+}
+
+/// What is currently in effect, for operators
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogControlSnapshot {
+    pub default_level: String,
+    pub levels: BTreeMap<String, String>,
+    pub sampling: BTreeMap<String, SamplingStatus>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    /// Events seen outside any trace, for 1-in-N counting
+    untraced: AtomicU64,
+    kept: AtomicU64,
+    dropped: AtomicU64,
+}
+
+struct Sampler {
+    rule: SamplingRule,
+    counters: Arc<Counters>,
+}
+
+struct State {
+    default_level: LevelFilter,
+    levels: BTreeMap<String, LevelFilter>,
+    sampling: BTreeMap<String, Sampler>,
+}
+
+/// Shared handle to runtime log levels and sampling; clones share state
+#[derive(Clone)]
+pub struct LogControl {
+    state: Arc<RwLock<State>>,
+}
+
+impl fmt::Debug for LogControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LogControl").field(&self.snapshot()).finish()
+    }
+}
+
+impl LogControl {
+    pub fn new(default_level: LevelFilter) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(State {
+                default_level,
+                levels: BTreeMap::new(),
+                sampling: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Levels and sampling from `log_level`, `target_levels` and `sampling`
+    pub fn from_config(config: &LoggerConfig) -> Result<Self, ControlError> {
+        let control = Self::new(parse_level(&config.log_level)?);
+        for (target, level) in &config.target_levels {
+            control.set_level(target, level)?;
+        }
+        for (target, rule) in &config.sampling {
+            control.set_sampling(target, *rule)?;
+        }
+        Ok(control)
+    }
+
+    /// Layer applying this control; add it to the subscriber before the
+    /// layers that write logs
+    pub fn layer(&self) -> LogControlLayer {
+        LogControlLayer { control: self.clone() }
+    }
+
+    pub fn set_default_level(&self, level: &str) -> Result<(), ControlError> {
+        let level = parse_level(level)?;
+        self.write().default_level = level;
+        tracing::callsite::rebuild_interest_cache();
+        Ok(())
+    }
+
+    /// Set the level for `target` and everything under it
+    pub fn set_level(&self, target: &str, level: &str) -> Result<(), ControlError> {
+        let level = parse_level(level)?;
+        self.write().levels.insert(target.to_string(), level);
+        tracing::callsite::rebuild_interest_cache();
+        Ok(())
+    }
+
+    /// Return `target` to the level of its parent target or the default
+    pub fn clear_level(&self, target: &str) {
+        self.write().levels.remove(target);
+        tracing::callsite::rebuild_interest_cache();
+    }
+
+    /// Sample `target` and everything under it; resets its counters
+    pub fn set_sampling(&self, target: &str, rule: SamplingRule) -> Result<(), ControlError> {
+        if rule.one_in == 0 {
+            return Err(ControlError::InvalidRate(target.to_string()));
+        }
+        self.write().sampling.insert(
+            target.to_string(),
+            Sampler {
+                rule,
+                counters: Arc::default(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn clear_sampling(&self, target: &str) {
+        self.write().sampling.remove(target);
+    }
+
+    /// Level in effect for `target`
+    pub fn effective_level(&self, target: &str) -> LevelFilter {
+        let state = self.read();
+        longest_match(&state.levels, target).copied().unwrap_or(state.default_level)
+    }
+
+    pub fn snapshot(&self) -> LogControlSnapshot {
+        let state = self.read();
+        LogControlSnapshot {
+            default_level: state.default_level.to_string(),
+            levels: state
+                .levels
+                .iter()
+                .map(|(target, level)| (target.clone(), level.to_string()))
+                .collect(),
+            sampling: state
+                .sampling
+                .iter()
+                .map(|(target, sampler)| {
+                    let status = SamplingStatus {
+                        one_in: sampler.rule.one_in,
+                        kept: sampler.counters.kept.load(Ordering::Relaxed),
+                        dropped: sampler.counters.dropped.load(Ordering::Relaxed),
+                    };
+                    (target.clone(), status)
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether to keep an event of `target`, given its trace's sample key
+    fn sample(&self, target: &str, trace_key: Option<u64>) -> bool {
+        let (rule, counters) = {
+            let state = self.read();
+            match longest_match(&state.sampling, target) {
+                Some(sampler) => (sampler.rule, sampler.counters.clone()),
+                None => return true,
+            }
+        };
+        let keep = match trace_key {
+            Some(key) => key <= u64::MAX / u64::from(rule.one_in),
+            None => counters.untraced.fetch_add(1, Ordering::Relaxed) % u64::from(rule.one_in) == 0,
+        };
+        let counter = if keep { &counters.kept } else { &counters.dropped };
+        counter.fetch_add(1, Ordering::Relaxed);
+        keep
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, State> {
+        self.state.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, State> {
+        self.state.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, ControlError> {
+    LevelFilter::from_str(level).map_err(|_| ControlError::InvalidLevel(level.to_string()))
+}
+
+/// Value for the longest key that is `target` or a `::` parent of it
+fn longest_match<'a, T>(rules: &'a BTreeMap<String, T>, target: &str) -> Option<&'a T> {
+    rules
+        .iter()
+        .filter(|(key, _)| {
+            target == key.as_str()
+                || (target.starts_with(key.as_str()) && target[key.len()..].starts_with("::"))
+        })
+        .max_by_key(|(key, _)| key.len())
+        .map(|(_, value)| value)
+}
+
+/// Sample key of a trace, stored on its root span
+struct TraceKey(u64);
+
+fn trace_key(trace_id: &str) -> u64 {
+    let digest = Sha256::digest(trace_id.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().expect("digest is at least 8 bytes"))
+}
+
+#[derive(Default)]
+struct TraceIdVisitor(Option<String>);
+
+impl Visit for TraceIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == TRACE_ID_FIELD {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == TRACE_ID_FIELD {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Filtering layer for a `LogControl`
+pub struct LogControlLayer {
+    control: LogControl,
+}
+
+impl<S> Layer<S> for LogControlLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // Levels change at runtime, so no callsite may be cached as disabled
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        *metadata.level() <= self.control.effective_level(metadata.target())
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if span.parent().is_some() {
+            return;
+        }
+        let mut visitor = TraceIdVisitor::default();
+        attrs.record(&mut visitor);
+        let key = visitor.0.unwrap_or_else(|| id.into_u64().to_string());
+        span.extensions_mut().insert(TraceKey(trace_key(&key)));
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, ctx: Context<'_, S>) -> bool {
+        let metadata = event.metadata();
+        if *metadata.level() == Level::ERROR {
+            return true;
+        }
+        let trace_key = ctx
+            .event_scope(event)
+            .and_then(|scope| scope.from_root().next())
+            .and_then(|root| root.extensions().get::<TraceKey>().map(|key| key.0));
+        self.control.sample(metadata.target(), trace_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Records the target of every event that reaches it
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<String>>>);
+
+    impl<S: Subscriber> Layer<S> for Capture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(event.metadata().target().to_string());
+        }
+    }
+
+    impl Capture {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    fn run(control: &LogControl, capture: &Capture, f: impl FnOnce()) {
+        let subscriber = tracing_subscriber::registry().with(control.layer()).with(capture.clone());
+        tracing::subscriber::with_default(subscriber, f);
+    }
+
+    #[test]
+    fn test_level_override_per_target_at_runtime() {
+        let control = LogControl::new(LevelFilter::DEBUG);
+        let capture = Capture::default();
+        let emit = || {
+            tracing::debug!(target: "sqlx::query", "select");
+            tracing::warn!(target: "sqlx", "slow");
+            tracing::debug!(target: "sqlxish", "unrelated");
+            tracing::info!(target: "app", "hello");
+        };
+
+        run(&control, &capture, emit);
+        assert_eq!(capture.take().len(), 4);
+
+        control.set_level("sqlx", "warn").unwrap();
+        run(&control, &capture, emit);
+        assert_eq!(capture.take(), vec!["sqlx", "sqlxish", "app"]);
+        assert_eq!(control.effective_level("sqlx::query"), LevelFilter::WARN);
+
+        control.clear_level("sqlx");
+        run(&control, &capture, emit);
+        assert_eq!(capture.take().len(), 4);
+        assert!(control.set_level("sqlx", "loud").is_err());
+    }
+
+    #[test]
+    fn test_sampling_keeps_one_in_n_and_all_errors() {
+        let control = LogControl::new(LevelFilter::INFO);
+        control.set_sampling("hot", SamplingRule { one_in: 4 }).unwrap();
+        let capture = Capture::default();
+
+        run(&control, &capture, || {
+            for _ in 0..100 {
+                tracing::info!(target: "hot::path", "tick");
+            }
+            for _ in 0..3 {
+                tracing::error!(target: "hot::path", "failed");
+                tracing::info!(target: "cold", "rare");
+            }
+        });
+        let kept = capture.take();
+        assert_eq!(kept.iter().filter(|target| *target == "hot::path").count(), 25 + 3);
+        assert_eq!(kept.iter().filter(|target| *target == "cold").count(), 3);
+
+        let snapshot = control.snapshot();
+        assert_eq!(snapshot.default_level, "info");
+        assert_eq!(snapshot.sampling["hot"], SamplingStatus { one_in: 4, kept: 25, dropped: 75 });
+    }
+
+    #[test]
+    fn test_sampling_is_consistent_within_a_trace() {
+        let control = LogControl::new(LevelFilter::INFO);
+        control.set_sampling("hot", SamplingRule { one_in: 2 }).unwrap();
+        control.set_sampling("hotter", SamplingRule { one_in: 4 }).unwrap();
+        let capture = Capture::default();
+
+        let mut kept_per_trace = Vec::new();
+        for request in 0..64 {
+            run(&control, &capture, || {
+                let root = tracing::info_span!("request", trace_id = %format!("trace-{}", request));
+                let _root = root.enter();
+                let child = tracing::info_span!("handler");
+                let _child = child.enter();
+                for _ in 0..3 {
+                    tracing::info!(target: "hot", "step");
+                }
+                tracing::info!(target: "hotter", "detail");
+            });
+            let kept = capture.take();
+            let hot = kept.iter().filter(|target| *target == "hot").count();
+            let hotter = kept.iter().filter(|target| *target == "hotter").count();
+            // All or nothing per target, and kept at the rarer rate implies kept at the commoner
+            assert!(hot == 0 || hot == 3);
+            assert!(hotter == 0 || hot == 3);
+            kept_per_trace.push(hot == 3);
+        }
+        assert!(kept_per_trace.iter().any(|kept| *kept));
+        assert!(kept_per_trace.iter().any(|kept| !*kept));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = LoggerConfig::default();
+        config.target_levels.insert("sqlx".to_string(), "warn".to_string());
+        config.sampling.insert("hot".to_string(), SamplingRule { one_in: 10 });
+        let control = LogControl::from_config(&config).unwrap();
+        let snapshot = control.snapshot();
+        assert_eq!(snapshot.levels["sqlx"], "warn");
+        assert_eq!(snapshot.sampling["hot"].one_in, 10);
+
+        config.sampling.insert("bad".to_string(), SamplingRule { one_in: 0 });
+        assert!(LogControl::from_config(&config).is_err());
+    }
+}
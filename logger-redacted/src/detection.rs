@@ -0,0 +1,218 @@
+// PII detection and overlap resolution
+//
+// Detectors report candidate spans; the redactor resolves them together and
+// only then rewrites the text. A detector may be a regular expression or
+// anything else implementing `PiiDetector`, such as an ML model; all
+// candidates go through the same resolution.
+use std::fmt;
+use std::ops::Range;
+
+use regex::Regex;
+
+/// Kind of sensitive data a detection found
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PiiKind {
+    Email,
+    Phone,
+    Ssn,
+    CreditCard,
+    IpAddress,
+    /// Custom patterns and external detectors, e.g. "person_name"
+    Other(String),
+}
+
+impl PiiKind {
+    /// Priority of the built-in patterns for this kind
+    ///
+    /// The more specific formats rank higher, so a card or social security
+    /// number is not taken apart by a looser phone or IP match inside it.
+    pub fn default_priority(&self) -> u32 {
+        match self {
+            PiiKind::Ssn => 90,
+            PiiKind::CreditCard => 80,
+            PiiKind::Email => 70,
+            PiiKind::Phone => 60,
+            PiiKind::IpAddress => 50,
+            PiiKind::Other(_) => 50,
+        }
+    }
+}
+
+/// Priority of `RedactionConfig::custom_patterns`, above every built-in kind:
+/// a custom pattern describes an organization's own identifiers and should
+/// win over a generic pattern matching part of one
+pub const CUSTOM_PATTERN_PRIORITY: u32 = 100;
+
+/// A candidate span of sensitive data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    /// Byte range in the scanned text
+    pub span: Range<usize>,
+    pub kind: PiiKind,
+    /// Decides between overlapping detections; higher wins
+    pub priority: u32,
+    /// How sure the detector is, from 0.0 to 1.0; pattern matches are 1.0
+    pub confidence: f32,
+    /// Text to put in place of the span; `None` uses the redactor's format for `kind`
+    pub replacement: Option<String>,
+}
+
+impl Detection {
+    pub fn new(span: Range<usize>, kind: PiiKind) -> Self {
+        let priority = kind.default_priority();
+        Self {
+            span,
+            kind,
+            priority,
+            confidence: 1.0,
+            replacement: None,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_confidence(mut self, confidence: f32) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    pub fn with_replacement(mut self, replacement: impl Into<String>) -> Self {
+        self.replacement = Some(replacement.into());
+        self
+    }
+
+    fn overlaps(&self, other: &Detection) -> bool {
+        self.span.start < other.span.end && other.span.start < self.span.end
+    }
+}
+
+/// Finds sensitive spans in text
+pub trait PiiDetector: Send + Sync + fmt::Debug {
+    /// Candidate spans in `text`; they may overlap each other
+    fn detect(&self, text: &str) -> Vec<Detection>;
+}
+
+/// Detector backed by a regular expression
+#[derive(Debug, Clone)]
+pub struct RegexDetector {
+    regex: Regex,
+    kind: PiiKind,
+    priority: u32,
+    /// Replacement template; `$1`, `$name` expand to capture groups
+    replacement: Option<String>,
+}
+
+impl RegexDetector {
+    pub fn new(regex: Regex, kind: PiiKind) -> Self {
+        let priority = kind.default_priority();
+        Self {
+            regex,
+            kind,
+            priority,
+            replacement: None,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_replacement(mut self, replacement: impl Into<String>) -> Self {
+        self.replacement = Some(replacement.into());
+        self
+    }
+}
+
+impl PiiDetector for RegexDetector {
+    fn detect(&self, text: &str) -> Vec<Detection> {
+        self.regex
+            .captures_iter(text)
+            .filter_map(|caps| {
+                let matched = caps.get(0)?;
+                let mut detection =
+                    Detection::new(matched.range(), self.kind.clone()).with_priority(self.priority);
+                if let Some(ref template) = self.replacement {
+                    let mut replacement = String::new();
+                    caps.expand(template, &mut replacement);
+                    detection.replacement = Some(replacement);
+                }
+                Some(detection)
+            })
+            .collect()
+    }
+}
+
+/// Keep a non-overlapping subset of `detections`, ordered by position
+///
+/// Detections are taken in rank order and kept unless they overlap one
+/// already kept. Rank is highest priority first, then the longest span, then
+/// the highest confidence, then the earliest start, so the outcome does not
+/// depend on which detector reported what first. Empty spans and spans that
+/// are out of bounds or split a character of `text` are dropped.
+pub fn resolve_overlaps(text: &str, mut detections: Vec<Detection>) -> Vec<Detection> {
+    detections.retain(|detection| {
+        detection.span.start < detection.span.end
+            && detection.span.end <= text.len()
+            && text.is_char_boundary(detection.span.start)
+            && text.is_char_boundary(detection.span.end)
+    });
+    detections.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then_with(|| b.span.len().cmp(&a.span.len()))
+            .then_with(|| b.confidence.total_cmp(&a.confidence))
+            .then_with(|| a.span.start.cmp(&b.span.start))
+    });
+
+    let mut kept: Vec<Detection> = Vec::with_capacity(detections.len());
+    for detection in detections {
+        if !kept.iter().any(|other| other.overlaps(&detection)) {
+            kept.push(detection);
+        }
+    }
+    kept.sort_by_key(|detection| detection.span.start);
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn other(span: Range<usize>, label: &str, priority: u32) -> Detection {
+        Detection::new(span, PiiKind::Other(label.to_string())).with_priority(priority)
+    }
+
+    #[test]
+    fn test_priority_then_length_wins() {
+        let text = "acct ACC-555-123-4567-99 end";
+        let detections = vec![
+            other(9..21, "phone", 60),
+            other(5..24, "account", 100),
+            other(5..12, "prefix", 100),
+        ];
+        let kept = resolve_overlaps(text, detections);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].span, 5..24);
+
+        // Equal priority: the longer span wins, whatever the input order
+        let forward = resolve_overlaps(text, vec![other(5..12, "a", 10), other(9..21, "b", 10)]);
+        let backward = resolve_overlaps(text, vec![other(9..21, "b", 10), other(5..12, "a", 10)]);
+        assert_eq!(forward, backward);
+        assert_eq!(forward[0].span, 9..21);
+    }
+
+    #[test]
+    fn test_disjoint_kept_in_order_and_invalid_dropped() {
+        let text = "naïve 123";
+        let kept = resolve_overlaps(
+            text,
+            vec![other(7..10, "num", 1), other(0..2, "a", 1), other(3..4, "split", 5), other(8..40, "oob", 9)],
+        );
+        let spans: Vec<Range<usize>> = kept.into_iter().map(|detection| detection.span).collect();
+        assert_eq!(spans, vec![0..2, 7..10]);
+    }
+}
@@ -1,15 +1,19 @@
 pub mod redactor;
+pub mod detection;
 pub mod formatters;
 pub mod filters;
 pub mod compliance;
+pub mod control;
 pub mod macros;
 pub mod audit;
 pub mod config;
 
 pub use redactor::*;
+pub use detection::*;
 pub use formatters::*;
 pub use filters::*;
 pub use compliance::*;
+pub use control::*;
 pub use config::*;
 
 /// HIPAA-compliant logging system with automatic PII redaction
@@ -29,6 +33,7 @@ pub use config::*;
 /// - **Performance Optimized**: Minimal impact on application performance
 /// - **Structured Logging**: JSON-formatted logs with proper field separation
 /// - **Log Retention**: Automatic log rotation and retention policies
+/// - **Runtime Control**: Per-target levels and 1-in-N sampling adjustable while running
 /// 
 /// # Detected Data Types
 /// 
@@ -106,10 +111,11 @@ pub use config::*;
 ///         - pattern: "\\bPatient\\s+\\w+"
 ///           replacement: "Patient [NAME]"
 ///   
-///   compliance:
-///     audit_log_file: "/var/log/rustcare/audit.log"
-///     retention_days: 2555  # 7 years for HIPAA
-///     encryption: true
+///   compliance:  # separate from application logs
+///     directory: "/var/log/rustcare/compliance"
+///     rotation: daily
+///     retention_days: 2555  # 7 years for HIPAA, compliance stream only
+///     encrypt: true
 ///     
 ///   performance:
 ///     async_logging: true
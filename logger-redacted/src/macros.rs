@@ -11,4 +11,31 @@ macro_rules! redacted_error {
     ($($arg:tt)*) => {
         tracing::error!($($arg)*)
     };
-}
\ No newline at end of file
+}
+
+/// Record a compliance event with the installed `ComplianceLogger`
+///
+/// Takes `level` and `event`, then any `key = value` fields, then the message
+/// and its format arguments. Evaluates to the `Result` of recording, which
+/// callers should not discard: a compliance event that cannot be written is
+/// an error, not a dropped log line. Nothing is emitted through `tracing`.
+#[macro_export]
+macro_rules! compliance_log {
+    (level = $level:expr, event = $event:expr, $($rest:tt)+) => {{
+        let mut event = $crate::compliance::ComplianceEvent::new($level, $event, String::new());
+        $crate::__compliance_fields!(event; $($rest)+);
+        $crate::compliance::record(event)
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __compliance_fields {
+    ($event:ident; $key:ident = $value:expr, $($rest:tt)+) => {
+        $event.set(stringify!($key), $value);
+        $crate::__compliance_fields!($event; $($rest)+);
+    };
+    ($event:ident; $($message:tt)+) => {
+        $event.message = format!($($message)+);
+    };
+}
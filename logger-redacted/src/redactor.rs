@@ -2,6 +2,11 @@ use regex::Regex;
 use lazy_static::lazy_static;
 use sha2::{Sha256, Digest};
 use base64::{Engine as _, engine::general_purpose};
+use std::sync::Arc;
+
+use crate::detection::{
+    resolve_overlaps, Detection, PiiDetector, PiiKind, RegexDetector, CUSTOM_PATTERN_PRIORITY,
+};
 
 lazy_static! {
     static ref EMAIL_REGEX: Regex = Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Z|a-z]{2,}\b").expect("Invalid EMAIL_REGEX");
@@ -20,7 +25,12 @@ pub struct RedactionConfig {
     pub redact_credit_cards: bool,
     pub redact_ip_addresses: bool,
     pub hash_for_correlation: bool,
+    /// Pattern and replacement; `$1`, `$name` expand to capture groups
     pub custom_patterns: Vec<(Regex, String)>,
+    /// Further detectors, e.g. an ML model, run alongside the patterns
+    pub detectors: Vec<Arc<dyn PiiDetector>>,
+    /// Detections less confident than this are ignored
+    pub min_confidence: f32,
 }
 
 impl Default for RedactionConfig {
@@ -33,110 +43,96 @@ impl Default for RedactionConfig {
             redact_ip_addresses: true,
             hash_for_correlation: true,
             custom_patterns: Vec::new(),
+            detectors: Vec::new(),
+            min_confidence: 0.0,
         }
     }
 }
 
 /// PII redactor for log messages
+///
+/// Every enabled pattern and detector scans the original text. Overlapping
+/// candidates are settled by `resolve_overlaps`, and the survivors are
+/// replaced from the end of the text backwards so earlier offsets stay valid.
 pub struct PiiRedactor {
     config: RedactionConfig,
+    detectors: Vec<Arc<dyn PiiDetector>>,
 }
 
 impl PiiRedactor {
     pub fn new(config: RedactionConfig) -> Self {
-        Self { config }
+        let builtin = [
+            (config.redact_emails, &*EMAIL_REGEX, PiiKind::Email),
+            (config.redact_phones, &*PHONE_REGEX, PiiKind::Phone),
+            (config.redact_ssn, &*SSN_REGEX, PiiKind::Ssn),
+            (config.redact_credit_cards, &*CREDIT_CARD_REGEX, PiiKind::CreditCard),
+            (config.redact_ip_addresses, &*IP_REGEX, PiiKind::IpAddress),
+        ];
+        let mut detectors: Vec<Arc<dyn PiiDetector>> = builtin
+            .into_iter()
+            .filter(|(enabled, _, _)| *enabled)
+            .map(|(_, regex, kind)| Arc::new(RegexDetector::new(regex.clone(), kind)) as Arc<dyn PiiDetector>)
+            .collect();
+        for (pattern, replacement) in &config.custom_patterns {
+            detectors.push(Arc::new(
+                RegexDetector::new(pattern.clone(), PiiKind::Other("custom".to_string()))
+                    .with_priority(CUSTOM_PATTERN_PRIORITY)
+                    .with_replacement(replacement.clone()),
+            ));
+        }
+        detectors.extend(config.detectors.iter().cloned());
+        Self { config, detectors }
     }
     
     pub fn redact(&self, text: &str) -> String {
-        let mut result = text.to_string();
-        
-        if self.config.redact_emails {
-            result = self.redact_emails(&result);
-        }
-        
-        if self.config.redact_phones {
-            result = self.redact_phones(&result);
-        }
+        let candidates: Vec<Detection> = self
+            .detectors
+            .iter()
+            .flat_map(|detector| detector.detect(text))
+            .filter(|detection| detection.confidence >= self.config.min_confidence)
+            .collect();
         
-        if self.config.redact_ssn {
-            result = self.redact_ssn(&result);
-        }
-        
-        if self.config.redact_credit_cards {
-            result = self.redact_credit_cards(&result);
-        }
-        
-        if self.config.redact_ip_addresses {
-            result = self.redact_ip_addresses(&result);
-        }
-        
-        for (pattern, replacement) in &self.config.custom_patterns {
-            result = pattern.replace_all(&result, replacement).to_string();
+        let mut result = text.to_string();
+        for detection in resolve_overlaps(text, candidates).into_iter().rev() {
+            let replacement = self.replacement(&detection, &text[detection.span.clone()]);
+            result.replace_range(detection.span, &replacement);
         }
-        
         result
     }
     
-    fn redact_emails(&self, text: &str) -> String {
-        EMAIL_REGEX.replace_all(text, |caps: &regex::Captures| {
-            let email = &caps[0];
-            if self.config.hash_for_correlation {
-                format!("EMAIL[{}]", self.hash_value(email))
-            } else {
-                let parts: Vec<&str> = email.split('@').collect();
+    fn replacement(&self, detection: &Detection, matched: &str) -> String {
+        if let Some(ref replacement) = detection.replacement {
+            return replacement.clone();
+        }
+        let hashed = self.config.hash_for_correlation;
+        match &detection.kind {
+            PiiKind::Email if hashed => format!("EMAIL[{}]", self.hash_value(matched)),
+            PiiKind::Email => {
+                let parts: Vec<&str> = matched.split('@').collect();
                 if parts.len() == 2 {
                     format!("{}***@{}***", &parts[0][..1.min(parts[0].len())], &parts[1][..1.min(parts[1].len())])
                 } else {
                     "***@***.com".to_string()
                 }
             }
-        }).to_string()
-    }
-    
-    fn redact_phones(&self, text: &str) -> String {
-        PHONE_REGEX.replace_all(text, |caps: &regex::Captures| {
-            if self.config.hash_for_correlation {
-                format!("PHONE[{}]", self.hash_value(&caps[0]))
-            } else {
-                "(***) ***-****".to_string()
-            }
-        }).to_string()
-    }
-    
-    fn redact_ssn(&self, text: &str) -> String {
-        SSN_REGEX.replace_all(text, |caps: &regex::Captures| {
-            if self.config.hash_for_correlation {
-                format!("SSN[{}]", self.hash_value(&caps[0]))
-            } else {
-                "***-**-****".to_string()
-            }
-        }).to_string()
-    }
-    
-    fn redact_credit_cards(&self, text: &str) -> String {
-        CREDIT_CARD_REGEX.replace_all(text, |caps: &regex::Captures| {
-            if self.config.hash_for_correlation {
-                format!("CC[{}]", self.hash_value(&caps[0]))
-            } else {
-                "****-****-****-****".to_string()
-            }
-        }).to_string()
-    }
-    
-    fn redact_ip_addresses(&self, text: &str) -> String {
-        IP_REGEX.replace_all(text, |caps: &regex::Captures| {
-            if self.config.hash_for_correlation {
-                format!("IP[{}]", self.hash_value(&caps[0]))
-            } else {
-                let ip = &caps[0];
-                let parts: Vec<&str> = ip.split('.').collect();
+            PiiKind::Phone if hashed => format!("PHONE[{}]", self.hash_value(matched)),
+            PiiKind::Phone => "(***) ***-****".to_string(),
+            PiiKind::Ssn if hashed => format!("SSN[{}]", self.hash_value(matched)),
+            PiiKind::Ssn => "***-**-****".to_string(),
+            PiiKind::CreditCard if hashed => format!("CC[{}]", self.hash_value(matched)),
+            PiiKind::CreditCard => "****-****-****-****".to_string(),
+            PiiKind::IpAddress if hashed => format!("IP[{}]", self.hash_value(matched)),
+            PiiKind::IpAddress => {
+                let parts: Vec<&str> = matched.split('.').collect();
                 if parts.len() == 4 {
                     format!("{}.***.***.{}", parts[0], parts[3])
                 } else {
                     "***.***.***.***".to_string()
                 }
             }
-        }).to_string()
+            PiiKind::Other(label) if hashed => format!("{}[{}]", label.to_uppercase(), self.hash_value(matched)),
+            PiiKind::Other(label) => format!("[{}]", label.to_uppercase()),
+        }
     }
     
     fn hash_value(&self, value: &str) -> String {
@@ -174,4 +170,83 @@ mod tests {
         let redacted = redactor.redact(text);
         assert!(redacted.contains("(***) ***-****"));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_custom_pattern_wins_over_phone_inside_it() {
+        let redactor = PiiRedactor::new(RedactionConfig {
+            hash_for_correlation: false,
+            custom_patterns: vec![(Regex::new(r"\bACCT-\d{3}-\d{3}-\d{4}-\d{2}\b").unwrap(), "ACCT[REDACTED]".to_string())],
+            ..Default::default()
+        });
+        
+        // The phone pattern also matches inside the account id; only the
+        // account id is replaced, once, and the rest of the text is intact
+        let text = "Account ACCT-555-123-4567-01 and phone 555-987-6543";
+        assert_eq!(redactor.redact(text), "Account ACCT[REDACTED] and phone (***) ***-****");
+    }
+    
+    #[test]
+    fn test_overlapping_patterns_resolve_independent_of_order() {
+        let overlapping = |reversed: bool| {
+            let mut custom_patterns = vec![
+                (Regex::new(r"\d{3}-\d{2}").unwrap(), "SHORT".to_string()),
+                (Regex::new(r"\d{2}-\d{4}-x").unwrap(), "LONG".to_string()),
+            ];
+            if reversed {
+                custom_patterns.reverse();
+            }
+            PiiRedactor::new(RedactionConfig {
+                redact_phones: false,
+                redact_ssn: false,
+                custom_patterns,
+                ..Default::default()
+            })
+        };
+        
+        // Same priority: the longer match wins and the shorter is dropped,
+        // rather than both rewriting the shared digits
+        let text = "id 123-45-6789-x.";
+        assert_eq!(overlapping(false).redact(text), "id 123-LONG.");
+        assert_eq!(overlapping(true).redact(text), "id 123-LONG.");
+    }
+    
+    #[test]
+    fn test_pluggable_detector_uses_same_resolution() {
+        #[derive(Debug)]
+        struct FakeModel;
+        
+        impl PiiDetector for FakeModel {
+            fn detect(&self, text: &str) -> Vec<Detection> {
+                let mut detections = Vec::new();
+                if let Some(start) = text.find("Jane Roe") {
+                    detections.push(
+                        Detection::new(start..start + 8, PiiKind::Other("person_name".to_string()))
+                            .with_priority(95)
+                            .with_confidence(0.9),
+                    );
+                }
+                // Low confidence guess overlapping the SSN
+                if let Some(start) = text.find("45-6789") {
+                    detections.push(
+                        Detection::new(start..start + 7, PiiKind::Other("mrn".to_string()))
+                            .with_priority(99)
+                            .with_confidence(0.2),
+                    );
+                }
+                detections
+            }
+        }
+        
+        let redactor = PiiRedactor::new(RedactionConfig {
+            hash_for_correlation: false,
+            detectors: vec![Arc::new(FakeModel)],
+            min_confidence: 0.5,
+            ..Default::default()
+        });
+        
+        // The email overlaps the name; the model's higher priority wins. The
+        // unconfident guess is ignored and the SSN pattern applies.
+        let text = "Jane Roe@example.com has SSN 123-45-6789";
+        assert_eq!(redactor.redact(text), "[PERSON_NAME]@example.com has SSN ***-**-****");
+    }
+}
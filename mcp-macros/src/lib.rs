@@ -4,13 +4,17 @@
 //! MCP tools from handler functions with auth and Zanzibar context.
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, ItemFn, Meta, Lit, punctuated::Punctuated, Token};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, punctuated::Punctuated, spanned::Spanned, FnArg, ItemFn, Lit, Meta, Token,
+    Type,
+};
 
 /// Decorator macro to mark a handler function as an MCP tool
 ///
 /// Usage:
-/// ```rust
+/// ```rust,ignore
 /// #[mcp_tool(
 ///     name = "get_patient",
 ///     description = "Retrieve patient information by ID",
@@ -20,15 +24,58 @@ use syn::{parse_macro_input, ItemFn, Meta, Lit, punctuated::Punctuated, Token};
 ///     response_type = "Patient",
 ///     render_type = "json"
 /// )]
-/// pub async fn get_patient(...) -> Result<...> {
+/// pub async fn get_patient(args: GetPatientArgs, auth: &AuthContext) -> McpResult<Patient> {
 ///     // handler implementation
 /// }
 /// ```
+///
+/// Read-only tools can have their results cached, and write tools can drop
+/// the cached results of the tools they affect:
+/// ```rust,ignore
+/// #[mcp_tool(idempotent = true, cache_ttl_secs = 300)]
+/// pub async fn formulary_lookup(args: FormularyArgs) -> McpResult<Vec<Drug>> { .. }
+///
+/// #[mcp_tool(invalidates = "formulary_lookup")]
+/// pub async fn update_formulary(args: UpdateArgs) -> McpResult<()> { .. }
+/// ```
+/// `cache_ttl_secs` requires `idempotent = true` and is rejected on
+/// sensitive tools. `invalidates` takes a comma-separated list of tool names.
+///
+/// Tools that can match many records page their results: the handler takes
+/// a `PageRequest` and returns a `Page`, and clients follow `next_cursor`
+/// from page to page. `page_size` defaults to `DEFAULT_PAGE_SIZE`:
+/// ```rust,ignore
+/// #[mcp_tool(page_size = 25)]
+/// pub async fn search_patients(args: SearchArgs, page: PageRequest) -> McpResult<Page<Patient>> { .. }
+/// ```
+///
+/// The function is kept as written and a `ToolRegistration` for it is
+/// submitted to `mcp_server`'s tool inventory, which `ToolsRegistry` collects
+/// at startup. The handler may be sync or async and takes:
+/// - at most one arguments parameter, deserialized from the tool call's JSON
+///   arguments. Its type implements `serde::Deserialize` and
+///   `schemars::JsonSchema`; the tool's `inputSchema` is generated from it,
+///   including field doc comments, optional fields and enums.
+/// - optionally an `AuthContext`, by reference or by value
+/// - optionally a `PageRequest`, by reference or by value
+///
+/// It returns `Result<T, E>` where `T: Serialize` and `McpError: From<E>`.
+/// Tools must live in crates that depend on `mcp-server`.
 #[proc_macro_attribute]
 pub fn mcp_tool(args: TokenStream, input: TokenStream) -> TokenStream {
     let input_fn = parse_macro_input!(input as ItemFn);
     let attr_args = parse_macro_input!(args with Punctuated<Meta, Token![,]>::parse_terminated);
-    
+
+    match expand_mcp_tool(attr_args, input_fn) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_mcp_tool(
+    attr_args: Punctuated<Meta, Token![,]>,
+    input_fn: ItemFn,
+) -> syn::Result<TokenStream2> {
     // Parse the mcp_tool attributes
     let mut tool_name = None;
     let mut description = None;
@@ -37,105 +84,294 @@ pub fn mcp_tool(args: TokenStream, input: TokenStream) -> TokenStream {
     let mut sensitive = None;
     let mut response_type = None;
     let mut render_type = None;
-    
+    let mut idempotent = None;
+    let mut cache_ttl_secs = None;
+    let mut invalidates = None;
+    let mut page_size = None;
+
     for arg in attr_args {
-        if let Meta::NameValue(meta) = arg {
-            let ident = meta.path.get_ident().map(|i| i.to_string());
-            
-            if let Some(ident_str) = ident {
-                match ident_str.as_str() {
-                    "name" => {
-                        if let syn::Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) = meta.value {
-                            tool_name = Some(s.value());
-                        }
-                    }
-                    "description" => {
-                        if let syn::Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) = meta.value {
-                            description = Some(s.value());
-                        }
-                    }
-                    "category" => {
-                        if let syn::Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) = meta.value {
-                            category = Some(s.value());
-                        }
-                    }
-                    "requires_permission" => {
-                        if let syn::Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) = meta.value {
-                            requires_permission = Some(s.value());
-                        }
-                    }
-                    "sensitive" => {
-                        if let syn::Expr::Lit(syn::ExprLit { lit: Lit::Bool(b), .. }) = meta.value {
-                            sensitive = Some(b.value);
-                        }
-                    }
-                    "response_type" => {
-                        if let syn::Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) = meta.value {
-                            response_type = Some(s.value());
-                        }
-                    }
-                    "render_type" => {
-                        if let syn::Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) = meta.value {
-                            render_type = Some(s.value());
-                        }
-                    }
-                    _ => {}
-                }
+        let Meta::NameValue(meta) = arg else {
+            return Err(syn::Error::new(
+                arg.span(),
+                "expected `key = value` arguments",
+            ));
+        };
+        let ident_str = meta
+            .path
+            .get_ident()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        let lit = match &meta.value {
+            syn::Expr::Lit(syn::ExprLit { lit, .. }) => lit.clone(),
+            other => return Err(syn::Error::new(other.span(), "expected a literal")),
+        };
+
+        match (ident_str.as_str(), lit) {
+            ("name", Lit::Str(s)) => tool_name = Some(s.value()),
+            ("description", Lit::Str(s)) => description = Some(s.value()),
+            ("category", Lit::Str(s)) => category = Some(s.value()),
+            ("requires_permission", Lit::Str(s)) => requires_permission = Some(s.value()),
+            ("sensitive", Lit::Bool(b)) => sensitive = Some(b.value),
+            ("response_type", Lit::Str(s)) => response_type = Some(s.value()),
+            ("render_type", Lit::Str(s)) => render_type = Some(s),
+            ("idempotent", Lit::Bool(b)) => idempotent = Some(b.value),
+            ("cache_ttl_secs", Lit::Int(i)) => cache_ttl_secs = Some(i),
+            ("invalidates", Lit::Str(s)) => invalidates = Some(s),
+            ("page_size", Lit::Int(i)) => page_size = Some(i),
+            (
+                "name"
+                | "description"
+                | "category"
+                | "requires_permission"
+                | "response_type"
+                | "render_type"
+                | "invalidates",
+                other,
+            ) => return Err(syn::Error::new(other.span(), "expected a string literal")),
+            ("sensitive" | "idempotent", other) => {
+                return Err(syn::Error::new(other.span(), "expected `true` or `false`"))
+            }
+            ("cache_ttl_secs", other) => {
+                return Err(syn::Error::new(other.span(), "expected a number of seconds"))
+            }
+            ("page_size", other) => {
+                return Err(syn::Error::new(other.span(), "expected a number of items"))
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    meta.path.span(),
+                    format!("unknown mcp_tool argument `{}`", ident_str),
+                ))
             }
         }
     }
-    
+
     // Default values
     let tool_name = tool_name.unwrap_or_else(|| {
         // Default to function name with underscores
         input_fn.sig.ident.to_string()
     });
-    
+
     let description = description.unwrap_or_else(|| format!("Execute {}", tool_name));
     let category = category.unwrap_or_else(|| "general".to_string());
-    let requires_permission_str = requires_permission.as_ref().map(|s| quote! { Some(#s) }).unwrap_or_else(|| quote! { None });
+    let requires_permission_str = requires_permission
+        .as_ref()
+        .map(|s| quote! { Some(#s) })
+        .unwrap_or_else(|| quote! { None });
     let sensitive_bool = sensitive.unwrap_or(false);
-    let response_type_str = response_type.as_ref().map(|s| quote! { Some(#s) }).unwrap_or_else(|| quote! { None });
-    
+    let response_type_str = response_type
+        .as_ref()
+        .map(|s| quote! { Some(#s) })
+        .unwrap_or_else(|| quote! { None });
+    let idempotent_bool = idempotent.unwrap_or(false);
+
+    // Results are only cached for tools that are safe to replay
+    let cache_ttl = match &cache_ttl_secs {
+        None => quote! { None },
+        Some(ttl) if sensitive_bool => {
+            return Err(syn::Error::new(
+                ttl.span(),
+                "results of sensitive tools are never cached",
+            ))
+        }
+        Some(ttl) if !idempotent_bool => {
+            return Err(syn::Error::new(
+                ttl.span(),
+                "cache_ttl_secs requires `idempotent = true`",
+            ))
+        }
+        Some(ttl) => {
+            let secs: u64 = ttl.base10_parse()?;
+            quote! { Some(#secs) }
+        }
+    };
+    let invalidated_tools: Vec<String> = invalidates
+        .as_ref()
+        .map(|tools| {
+            tools
+                .value()
+                .split(',')
+                .map(str::trim)
+                .filter(|tool| !tool.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
     // Parse render_type string to RenderType enum
-    let render_type_enum = render_type.as_ref().map(|rt_str| {
-        match rt_str.as_str() {
-            "json" => quote! { Some(::mcp_server::protocol::RenderType::Json) },
-            "markdown" => quote! { Some(::mcp_server::protocol::RenderType::Markdown) },
-            "html" => quote! { Some(::mcp_server::protocol::RenderType::Html) },
-            "table" => quote! { Some(::mcp_server::protocol::RenderType::Table) },
-            "list" => quote! { Some(::mcp_server::protocol::RenderType::List) },
-            "text" => quote! { Some(::mcp_server::protocol::RenderType::Text) },
-            _ => quote! { Some(::mcp_server::protocol::RenderType::Json) }, // Default to JSON
+    let render_type_enum = match &render_type {
+        None => quote! { None },
+        Some(rt) => {
+            let variant = match rt.value().as_str() {
+                "json" => quote! { Json },
+                "markdown" => quote! { Markdown },
+                "html" => quote! { Html },
+                "table" => quote! { Table },
+                "list" => quote! { List },
+                "text" => quote! { Text },
+                other => {
+                    return Err(syn::Error::new(
+                        rt.span(),
+                        format!(
+                            "unknown render_type `{}`; expected json, markdown, html, table, list or text",
+                            other
+                        ),
+                    ))
+                }
+            };
+            quote! { Some(::mcp_server::protocol::RenderType::#variant) }
+        }
+    };
+
+    // Map the handler's parameters onto the tool call
+    if let Some(receiver) = input_fn.sig.receiver() {
+        return Err(syn::Error::new(
+            receiver.span(),
+            "mcp_tool handlers must be free functions",
+        ));
+    }
+    let mut args_type: Option<&Type> = None;
+    let mut paginated = false;
+    let mut call_args = Vec::new();
+    for input in &input_fn.sig.inputs {
+        let FnArg::Typed(pat_type) = input else {
+            continue;
+        };
+        if let Some(passing) = context_param(&pat_type.ty, "PageRequest") {
+            paginated = true;
+            call_args.push(match passing {
+                Passing::Borrowed => quote! { &page },
+                Passing::Owned => quote! { page.clone() },
+            });
+            continue;
+        }
+        match context_param(&pat_type.ty, "AuthContext") {
+            Some(Passing::Borrowed) => call_args.push(quote! { &auth }),
+            Some(Passing::Owned) => call_args.push(quote! { auth.clone() }),
+            None if args_type.is_some() => {
+                return Err(syn::Error::new(
+                    pat_type.span(),
+                    "mcp_tool handlers take a single arguments parameter; group the arguments into one struct",
+                ))
+            }
+            None => {
+                args_type = Some(&pat_type.ty);
+                call_args.push(quote! { arguments });
+            }
+        }
+    }
+
+    let page_size = match (&page_size, paginated) {
+        (Some(size), false) => {
+            return Err(syn::Error::new(
+                size.span(),
+                "page_size requires a `PageRequest` parameter",
+            ))
+        }
+        (Some(size), true) => {
+            let size: usize = size.base10_parse()?;
+            quote! { Some(#size) }
         }
-    }).unwrap_or_else(|| quote! { None });
-    
+        (None, true) => quote! { Some(::mcp_server::pagination::DEFAULT_PAGE_SIZE) },
+        (None, false) => quote! { None },
+    };
+    let tool_output = if paginated {
+        quote! { ::mcp_server::pagination::page_output(output) }
+    } else {
+        quote! { ::mcp_server::tools::tool_output(output) }
+    };
+
     // Generate the tool registration code
     let fn_name = &input_fn.sig.ident;
-    let fn_vis = &input_fn.vis;
-    let fn_attrs = &input_fn.attrs;
-    let fn_sig = &input_fn.sig;
-    let fn_block = &input_fn.block;
-    
+    let call_fn = format_ident!("__mcp_tool_call_{}", fn_name);
+    let fn_name_str = fn_name.to_string();
+
+    let schema_fn = format_ident!("__mcp_tool_schema_{}", fn_name);
+    let (parse_arguments, input_schema) = match args_type {
+        Some(ty) => (
+            quote! {
+                let arguments: #ty = ::mcp_server::tools::parse_tool_arguments(#tool_name, arguments)?;
+            },
+            quote! { ::mcp_server::tools::input_schema_for::<#ty>() },
+        ),
+        None => (
+            quote! { let _ = arguments; },
+            quote! { ::mcp_server::tools::empty_input_schema() },
+        ),
+    };
+    let await_output = input_fn.sig.asyncness.map(|_| quote! { .await });
+
     let expanded = quote! {
-        #(#fn_attrs)*
-        #fn_vis #fn_sig {
-            #fn_block
+        #input_fn
+
+        #[doc(hidden)]
+        #[allow(non_snake_case, unused_variables)]
+        fn #call_fn(
+            arguments: ::mcp_server::__private::serde_json::Value,
+            auth: ::mcp_server::tools::AuthContext,
+            page: ::mcp_server::pagination::PageRequest,
+        ) -> ::mcp_server::tools::ToolFuture {
+            ::std::boxed::Box::pin(async move {
+                #parse_arguments
+                let output = #fn_name(#(#call_args),*) #await_output ?;
+                #tool_output
+            })
+        }
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        fn #schema_fn() -> ::mcp_server::__private::serde_json::Value {
+            #input_schema
+        }
+
+        ::mcp_server::__private::inventory::submit! {
+            ::mcp_server::tools::ToolRegistration {
+                definition: ::mcp_server::tools::ToolDefinition {
+                    name: #tool_name,
+                    description: #description,
+                    category: #category,
+                    requires_permission: #requires_permission_str,
+                    sensitive: #sensitive_bool,
+                    response_type: #response_type_str,
+                    render_type: #render_type_enum,
+                    idempotent: #idempotent_bool,
+                    cache_ttl_secs: #cache_ttl,
+                    invalidates: &[#(#invalidated_tools),*],
+                    page_size: #page_size,
+                    handler_function: #fn_name_str,
+                    handler_file: file!(),
+                    input_schema: #schema_fn,
+                },
+                handler: #call_fn,
+            }
         }
-        
-        // Auto-generated MCP tool metadata
-        // This function is registered as an MCP tool with the following metadata:
-        // - Name: #tool_name
-        // - Description: #description
-        // - Category: #category
-        // - Required Permission: #requires_permission_str
-        // - Sensitive: #sensitive_bool
-        // - Response Type: #response_type_str
-        // - Render Type: #render_type_enum
-        //
-        // The tool will be automatically discovered by build.rs scanning for #[mcp_tool] attributes
     };
-    
-    TokenStream::from(expanded)
+
+    Ok(expanded)
+}
+
+enum Passing {
+    Borrowed,
+    Owned,
 }
 
+/// Whether a parameter receives the call context named `context`
+/// (`AuthContext` or `PageRequest`)
+fn context_param(ty: &Type, context: &str) -> Option<Passing> {
+    let (ty, param) = match ty {
+        Type::Reference(reference) => (&*reference.elem, Passing::Borrowed),
+        ty => (ty, Passing::Owned),
+    };
+    match ty {
+        Type::Path(path)
+            if path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == context) =>
+        {
+            Some(param)
+        }
+        _ => None,
+    }
+}
@@ -1,37 +1,61 @@
 //! Example showing how to use the MCP tool pattern
 //!
-//! This demonstrates how handler functions can be exposed as MCP tools.
-//! 
-//! NOTE: The #[mcp_tool] macro is conceptual and would be implemented
-//! via the mcp-macros crate. This example shows the intended usage pattern.
+//! This demonstrates how handler functions are exposed as MCP tools with the
+//! #[mcp_tool] macro. Decorated functions are collected at startup, so the
+//! tools registry lists them without any manual registration.
+
+use mcp_macros::mcp_tool;
+use mcp_server::error::McpResult;
+use mcp_server::tools::{AuthContext, ToolsRegistry};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Filters for listing pharmacies
+#[derive(Deserialize, JsonSchema)]
+pub struct ListPharmaciesArgs {
+    /// Only list pharmacies in this city
+    pub city: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Pharmacy {
+    pub name: String,
+    pub city: String,
+}
+
+#[mcp_tool(
+    name = "list_pharmacies",
+    description = "List all pharmacies",
+    category = "pharmacy",
+    requires_permission = "pharmacy:read",
+    sensitive = false,
+    response_type = "Vec<Pharmacy>",
+    render_type = "table"
+)]
+pub async fn list_pharmacies(
+    args: ListPharmaciesArgs,
+    _auth: &AuthContext,
+) -> McpResult<Vec<Pharmacy>> {
+    let city = args.city.unwrap_or_else(|| "Springfield".to_string());
+    Ok(vec![Pharmacy {
+        name: "Main Street Pharmacy".to_string(),
+        city,
+    }])
+}
 
 fn main() {
     println!("MCP Tool Decorator Pattern Example");
     println!("===================================\n");
-    
-    println!("This example demonstrates how MCP tools would be registered:");
-    println!("  1. Use #[mcp_tool] attribute on handler functions");
-    println!("  2. Specify metadata (name, description, category, permissions)");
-    println!("  3. Mark sensitive tools that should be excluded from LLM access");
-    println!("  4. Tools are auto-discovered and registered\n");
-    
-    println!("Example usage:");
-    println!(r#"
-    #[mcp_tool(
-        name = "list_pharmacies",
-        description = "List all pharmacies",
-        category = "pharmacy",
-        requires_permission = "pharmacy:read",
-        sensitive = false
-    )]
-    pub async fn list_pharmacies(
-        params: ToolInput,
-        auth: &AuthContext,
-    ) -> McpResult<ToolResult> {{
-        // Implementation
-    }}
-    "#);
-    
+
+    let registry = ToolsRegistry::new();
+    for tool in registry.list(false) {
+        println!("  {} - {}", tool.name, tool.description);
+        println!(
+            "    input schema: {}",
+            serde_json::to_string_pretty(&tool.input_schema).unwrap_or_default()
+        );
+    }
+
     println!("\nFor a complete implementation, see:");
     println!("  - mcp-server/src/tools.rs");
     println!("  - mcp-server/src/tool_wrapper.rs");
@@ -1,49 +0,0 @@
-//! Build script to auto-discover MCP tools from handler functions
-//!
-//! Scans the codebase for #[mcp_tool] attributes and generates
-//! tool registration code automatically.
-
-use std::env;
-use std::fs;
-use std::path::PathBuf;
-
-fn main() {
-    println!("cargo:rerun-if-changed=build.rs");
-    
-    // Scan rustcare-server handlers for #[mcp_tool] attributes
-    let handlers_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
-        .parent()
-        .unwrap()
-        .join("rustcare-server/src/handlers");
-    
-    if handlers_dir.exists() {
-        println!("cargo:rerun-if-changed={}", handlers_dir.display());
-        generate_tool_registrations(&handlers_dir);
-    }
-}
-
-fn generate_tool_registrations(handlers_dir: &PathBuf) {
-    // In a real implementation, this would:
-    // 1. Parse Rust source files
-    // 2. Find functions with #[mcp_tool] attributes
-    // 3. Extract tool metadata (name, description, permissions, etc.)
-    // 4. Generate registration code
-    
-    // For now, we'll create a placeholder that can be expanded
-    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
-    let generated = out_dir.join("tools_generated.rs");
-    
-    // Placeholder - actual implementation would parse AST
-    let code = r#"
-        // Auto-generated MCP tool registrations
-        // This file is generated by build.rs scanning for #[mcp_tool] attributes
-        
-        pub fn register_discovered_tools(registry: &mut crate::tools::ToolsRegistry) {
-            // Tools will be registered here based on discovered #[mcp_tool] attributes
-        }
-    "#;
-    
-    fs::write(&generated, code).expect("Failed to write generated tools file");
-    println!("cargo:rustc-env=TOOLS_GENERATED={}", generated.display());
-}
-
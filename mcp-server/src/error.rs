@@ -28,6 +28,11 @@ pub enum McpError {
 
     #[error("Permission error: {0}")]
     Permission(String),
+
+    /// A pagination cursor no longer matches its query; the client should
+    /// restart from the first page
+    #[error("Stale cursor: {0}")]
+    StaleCursor(String),
 }
 
 pub type McpResult<T> = Result<T, McpError>;
@@ -44,6 +44,8 @@ pub mod tool_wrapper;
 pub mod sensitive_filter;
 pub mod render;
 pub mod registry;
+pub mod result_cache;
+pub mod pagination;
 
 pub use server::*;
 pub use protocol::*;
@@ -52,8 +54,22 @@ pub use capabilities::*;
 pub use tool_wrapper::*;
 pub use sensitive_filter::*;
 pub use render::*;
+pub use result_cache::ToolResultCache;
+pub use pagination::{Page, PagePosition, PageRequest};
 pub use error::{McpError as Error, McpResult as Result};
 
+// Lets `#[mcp_tool]` expansions name this crate as `::mcp_server` from
+// inside it too
+extern crate self as mcp_server;
+
+/// Re-exports used by `#[mcp_tool]` expansions
+#[doc(hidden)]
+pub mod __private {
+    pub use inventory;
+    pub use schemars;
+    pub use serde_json;
+}
+
 /// MCP Server for RustCare
 pub struct McpServer {
     server: server::Server,
@@ -0,0 +1,175 @@
+//! Cursor-based pagination of tool results
+//!
+//! A tool whose handler takes a [`PageRequest`] and returns a [`Page`]
+//! answers each call with one page of items plus an opaque `next_cursor`.
+//! The client passes the cursor back in `ToolInput::cursor` to get the next
+//! page, keeping individual responses bounded however many records match.
+//!
+//! Cursors record where the next page starts (an offset or the sort key of
+//! the last item) and a fingerprint of the tool and its arguments. A cursor
+//! replayed with different arguments fails with [`McpError::StaleCursor`],
+//! telling the client to restart from the first page.
+
+use crate::error::{McpError, McpResult};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Page size of paginated tools that don't declare one
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+const CURSOR_VERSION: u8 = 1;
+
+/// Where a page starts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PagePosition {
+    Start,
+    /// Number of items to skip
+    Offset(usize),
+    /// Sort key of the last item of the previous page (keyset pagination)
+    After(Value),
+}
+
+/// The page a paginated handler is asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageRequest {
+    pub position: PagePosition,
+    pub page_size: usize,
+}
+
+impl PageRequest {
+    pub fn first(page_size: usize) -> Self {
+        Self {
+            position: PagePosition::Start,
+            page_size,
+        }
+    }
+
+    /// Items to skip; zero unless paging by offset
+    pub fn offset(&self) -> usize {
+        match self.position {
+            PagePosition::Offset(offset) => offset,
+            _ => 0,
+        }
+    }
+
+    /// Sort key to continue after, when paging by keyset
+    pub fn after(&self) -> Option<&Value> {
+        match &self.position {
+            PagePosition::After(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Rows to fetch: one more than the page size, so [`Page::offset`] and
+    /// [`Page::keyset`] can tell whether another page follows
+    pub fn fetch_limit(&self) -> usize {
+        self.page_size.saturating_add(1)
+    }
+}
+
+/// One page of a handler's results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Start of the next page; `None` on the last page
+    pub next: Option<PagePosition>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, next: Option<PagePosition>) -> Self {
+        Self { items, next }
+    }
+
+    /// Page from up to [`PageRequest::fetch_limit`] rows fetched at
+    /// [`PageRequest::offset`]
+    pub fn offset(mut items: Vec<T>, request: &PageRequest) -> Self {
+        let next = (items.len() > request.page_size)
+            .then(|| PagePosition::Offset(request.offset() + request.page_size));
+        items.truncate(request.page_size);
+        Self { items, next }
+    }
+
+    /// Page from up to [`PageRequest::fetch_limit`] rows fetched after
+    /// [`PageRequest::after`], in `sort_key` order
+    pub fn keyset(mut items: Vec<T>, request: &PageRequest, sort_key: impl Fn(&T) -> Value) -> Self {
+        let more = items.len() > request.page_size;
+        items.truncate(request.page_size);
+        let next = if more {
+            items.last().map(|last| PagePosition::After(sort_key(last)))
+        } else {
+            None
+        };
+        Self { items, next }
+    }
+
+    /// Page of an in-memory result set, by offset
+    pub fn slice(items: impl IntoIterator<Item = T>, request: &PageRequest) -> Self {
+        let items = items
+            .into_iter()
+            .skip(request.offset())
+            .take(request.fetch_limit())
+            .collect();
+        Self::offset(items, request)
+    }
+}
+
+/// Serialize a paginated handler's output
+pub fn page_output<T: Serialize>(page: Page<T>) -> McpResult<Value> {
+    Ok(serde_json::to_value(page)?)
+}
+
+/// Decoded form of the opaque cursor handed to clients
+#[derive(Debug, Serialize, Deserialize)]
+struct PageCursor {
+    #[serde(rename = "v")]
+    version: u8,
+    #[serde(rename = "fp")]
+    fingerprint: String,
+    #[serde(rename = "pos")]
+    position: PagePosition,
+}
+
+/// Cursor for the page at `position` of the query identified by `fingerprint`
+pub fn encode_cursor(fingerprint: &str, position: PagePosition) -> String {
+    let cursor = PageCursor {
+        version: CURSOR_VERSION,
+        fingerprint: fingerprint.to_string(),
+        position,
+    };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+}
+
+/// Position a cursor resumes at, if it was issued for the query
+/// identified by `fingerprint`
+pub fn decode_cursor(cursor: &str, fingerprint: &str) -> McpResult<PagePosition> {
+    let cursor: PageCursor = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| McpError::Protocol("Malformed pagination cursor".to_string()))?;
+    if cursor.version != CURSOR_VERSION || cursor.fingerprint != fingerprint {
+        return Err(McpError::StaleCursor(
+            "The query changed since this cursor was issued; request the first page again"
+                .to_string(),
+        ));
+    }
+    Ok(cursor.position)
+}
+
+/// Fingerprint of a paginated call: the tool and its normalized arguments
+pub fn query_fingerprint(tool: &str, arguments: &Value) -> String {
+    let arguments = match arguments {
+        Value::Null => Value::Object(Default::default()),
+        arguments => crate::result_cache::normalize(arguments),
+    };
+    let digest = Sha256::new()
+        .chain_update(tool.as_bytes())
+        .chain_update([0])
+        .chain_update(arguments.to_string().as_bytes())
+        .finalize();
+    digest[..16].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
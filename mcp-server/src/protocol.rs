@@ -85,6 +85,9 @@ pub struct Tool {
     /// Expected render type
     #[serde(skip_serializing_if = "Option::is_none")]
     pub render_type: Option<RenderType>,
+    /// Items per page, for tools that page their results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<usize>,
 }
 
 /// Render type for tool responses
@@ -117,6 +120,9 @@ pub struct ToolInput {
     pub name: String,
     /// Tool arguments
     pub arguments: serde_json::Value,
+    /// `next_cursor` of the previous page, to fetch the page after it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
 }
 
 /// Tool execution result
@@ -134,6 +140,9 @@ pub struct ToolResult {
     /// Rendered output (if different from data)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rendered: Option<String>,
+    /// Opaque cursor of the next page, when more results follow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Response type information
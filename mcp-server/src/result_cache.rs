@@ -0,0 +1,154 @@
+//! Result caching for idempotent MCP tools
+//!
+//! Agents tend to repeat the same reference-data lookups within one
+//! conversation. Tools declared with `idempotent = true` and a
+//! `cache_ttl_secs` have their results cached per (tool, arguments, caller
+//! permission scope), and write tools can name the read tools whose cached
+//! results they make stale with `invalidates`.
+
+use crate::protocol::ToolResult;
+use crate::tools::AuthContext;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Counter of cache lookups, labelled by `tool` and `result` (`hit`/`miss`)
+pub const TOOL_CACHE_METRIC: &str = "mcp_tool_cache_requests_total";
+
+/// Cached results kept per tool before the soonest-expiring is evicted
+const MAX_ENTRIES_PER_TOOL: usize = 1_000;
+
+struct CachedResult {
+    result: ToolResult,
+    expires_at: Instant,
+}
+
+/// Cache of tool results shared by the tools of a registry
+#[derive(Clone, Default)]
+pub struct ToolResultCache {
+    tools: Arc<Mutex<HashMap<String, HashMap<String, CachedResult>>>>,
+}
+
+impl ToolResultCache {
+    pub fn new() -> Self {
+        describe_tool_cache_metrics();
+        Self::default()
+    }
+
+    /// Cache key for a call: normalized arguments plus the caller's
+    /// permission scope
+    ///
+    /// The scope is the organization, roles and permissions, so callers
+    /// with the same access share results. Handlers whose output depends on
+    /// who exactly is calling should not be cached.
+    pub fn cache_key(arguments: &Value, auth: &AuthContext) -> String {
+        let arguments = match arguments {
+            Value::Null => Value::Object(Default::default()),
+            arguments => normalize(arguments),
+        };
+        let mut roles = auth.roles.clone();
+        roles.sort();
+        let mut permissions = auth.permissions.clone();
+        permissions.sort();
+        serde_json::json!([arguments, auth.organization_id, roles, permissions]).to_string()
+    }
+
+    /// Unexpired cached result, recording a hit or miss
+    pub fn get(&self, tool: &str, key: &str) -> Option<ToolResult> {
+        let now = Instant::now();
+        let result = self
+            .lock()
+            .get(tool)
+            .and_then(|entries| entries.get(key))
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| entry.result.clone());
+        record_lookup(tool, result.is_some());
+        result
+    }
+
+    pub fn insert(&self, tool: &str, key: String, result: ToolResult, ttl: Duration) {
+        let now = Instant::now();
+        let mut tools = self.lock();
+        let entries = tools.entry(tool.to_string()).or_default();
+        if entries.len() >= MAX_ENTRIES_PER_TOOL {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        if entries.len() >= MAX_ENTRIES_PER_TOOL {
+            let soonest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone());
+            if let Some(soonest) = soonest {
+                entries.remove(&soonest);
+            }
+        }
+        entries.insert(key, CachedResult { result, expires_at: now + ttl });
+    }
+
+    /// Drop every cached result of `tool`, returning how many were dropped
+    pub fn invalidate_tool(&self, tool: &str) -> usize {
+        let dropped = self.lock().remove(tool).map_or(0, |entries| entries.len());
+        tracing::debug!(tool, dropped, "Invalidated cached MCP tool results");
+        dropped
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Number of cached results, including expired ones not yet evicted
+    pub fn len(&self) -> usize {
+        self.lock().values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, HashMap<String, CachedResult>>> {
+        self.tools.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Register help text and labels of the cache metrics
+pub fn describe_tool_cache_metrics() {
+    let metrics = telemetry::MetricsCollector::global();
+    metrics.describe(
+        TOOL_CACHE_METRIC,
+        telemetry::MetricKind::Counter,
+        "MCP tool result cache lookups, by tool and hit/miss",
+    );
+    metrics.restrict_labels(
+        TOOL_CACHE_METRIC,
+        telemetry::MetricKind::Counter,
+        &["tool", "result"],
+    );
+}
+
+fn record_lookup(tool: &str, hit: bool) {
+    telemetry::MetricsCollector::global()
+        .counter(TOOL_CACHE_METRIC)
+        .with_label("tool", tool)
+        .with_label("result", if hit { "hit" } else { "miss" })
+        .increment();
+}
+
+/// Arguments with object keys in a fixed order, so `{"a":1,"b":2}` and
+/// `{"b":2,"a":1}` share a cache entry
+pub(crate) fn normalize(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut sorted: Vec<_> = fields.iter().collect();
+            sorted.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                sorted
+                    .into_iter()
+                    .map(|(key, field)| (key.clone(), normalize(field)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(normalize).collect()),
+        other => other.clone(),
+    }
+}
@@ -1,8 +1,12 @@
 //! Sensitive endpoint filter for MCP tools
 //!
 //! Defines which endpoints should be excluded from public LLM access
-//! due to security, privacy, or compliance concerns.
+//! due to security, privacy, or compliance concerns, and which fields of a
+//! tool's result each caller may see.
 
+use crate::tools::AuthContext;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashSet;
 
 /// Check if an endpoint path is sensitive and should be excluded
@@ -138,3 +142,136 @@ impl SensitiveConfig {
     }
 }
 
+/// Replacement for field values the caller may not see
+pub const MASKED_VALUE: &str = "[MASKED]";
+
+/// Condition on the caller's auth context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimPredicate {
+    /// Every caller
+    Always,
+    /// Caller has the role
+    Role(String),
+    /// Caller has the permission
+    Permission(String),
+    /// Caller's claim equals the value
+    Claim { name: String, value: Value },
+    AnyOf(Vec<ClaimPredicate>),
+    AllOf(Vec<ClaimPredicate>),
+    Not(Box<ClaimPredicate>),
+}
+
+impl ClaimPredicate {
+    pub fn role(role: &str) -> Self {
+        Self::Role(role.to_string())
+    }
+    
+    pub fn permission(permission: &str) -> Self {
+        Self::Permission(permission.to_string())
+    }
+    
+    pub fn claim(name: &str, value: impl Into<Value>) -> Self {
+        Self::Claim {
+            name: name.to_string(),
+            value: value.into(),
+        }
+    }
+    
+    /// Check the predicate against a caller
+    pub fn evaluate(&self, auth: &AuthContext) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Role(role) => auth.roles.iter().any(|r| r == role),
+            Self::Permission(permission) => auth.permissions.iter().any(|p| p == permission),
+            Self::Claim { name, value } => auth.claims.get(name) == Some(value),
+            Self::AnyOf(predicates) => predicates.iter().any(|p| p.evaluate(auth)),
+            Self::AllOf(predicates) => predicates.iter().all(|p| p.evaluate(auth)),
+            Self::Not(predicate) => !predicate.evaluate(auth),
+        }
+    }
+}
+
+/// Reveal the field at `path` to callers matching `reveal_to`
+///
+/// `path` is a dot-separated list of object keys, e.g. `notes.psych`.
+/// Arrays are transparent: a path applies to every element, so the same
+/// rules work whether a tool returns one record or a list of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldRule {
+    pub path: String,
+    pub reveal_to: ClaimPredicate,
+}
+
+impl FieldRule {
+    pub fn new(path: &str, reveal_to: ClaimPredicate) -> Self {
+        Self {
+            path: path.to_string(),
+            reveal_to,
+        }
+    }
+}
+
+/// Field-level masking rules for one tool's results
+///
+/// A field is governed by the rule with the longest path that covers it,
+/// so `notes` can be revealed to nurses while `notes.psych` stays limited to
+/// psychiatry. Fields no rule covers are revealed for ordinary tools and
+/// masked for tools marked sensitive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MaskingPolicy {
+    pub rules: Vec<FieldRule>,
+}
+
+impl MaskingPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a rule revealing `path` to callers matching `reveal_to`
+    pub fn reveal(mut self, path: &str, reveal_to: ClaimPredicate) -> Self {
+        self.rules.push(FieldRule::new(path, reveal_to));
+        self
+    }
+    
+    /// Mask every value in `data` the caller may not see
+    ///
+    /// `default_reveal` applies to fields no rule covers. Masked values are
+    /// replaced in place, keeping the shape of the data so each render type
+    /// lays it out as usual.
+    pub fn apply(&self, data: &mut Value, auth: &AuthContext, default_reveal: bool) {
+        // Evaluate each rule once per call rather than once per field
+        let decisions: Vec<(&str, bool)> = self
+            .rules
+            .iter()
+            .map(|rule| (rule.path.as_str(), rule.reveal_to.evaluate(auth)))
+            .collect();
+        mask_value(data, "", &decisions, default_reveal);
+    }
+}
+
+fn mask_value(value: &mut Value, path: &str, decisions: &[(&str, bool)], reveal: bool) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                let field_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let reveal = decisions
+                    .iter()
+                    .find(|(rule_path, _)| *rule_path == field_path)
+                    .map_or(reveal, |(_, decision)| *decision);
+                mask_value(field, &field_path, decisions, reveal);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                mask_value(item, path, decisions, reveal);
+            }
+        }
+        _ if !reveal => *value = Value::String(MASKED_VALUE.to_string()),
+        _ => {}
+    }
+}
@@ -78,6 +78,7 @@ impl Server {
                     roles: vec![],
                     permissions: vec![],
                     email: None,
+                    claims: Default::default(),
                 };
                 
                 let result = self.tools.execute(tool_input, &auth_context, None).await?;
@@ -3,11 +3,14 @@
 //! This module provides utilities to wrap RustCare handler functions
 //! and expose them as MCP tools with automatic auth/Zanzibar integration.
 
-use crate::tools::{McpTool, AuthContext, ZanzibarClient};
-use crate::protocol::{ToolInput, ToolResult};
+use crate::tools::{McpTool, AuthContext, ToolRegistration, ZanzibarClient};
+use crate::protocol::{ResponseType, ToolInput, ToolResult, ToolStatus};
 use crate::error::{McpResult, McpError};
+use crate::pagination::{self, Page, PagePosition, PageRequest};
+use crate::result_cache::ToolResultCache;
 use async_trait::async_trait;
 use serde_json::Value;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// Wrapper that converts a handler function into an MCP tool
@@ -21,7 +24,15 @@ pub struct HandlerToolWrapper {
     output_schema: Option<Value>,
     render_type: Option<crate::protocol::RenderType>,
     response_type_name: Option<String>,
-    handler_fn: Box<dyn Fn(Value, &AuthContext) -> std::pin::Pin<Box<dyn std::future::Future<Output = McpResult<ToolResult>> + Send>> + Send + Sync>,
+    handler_function: String,
+    handler_file: String,
+    masking_policy: Option<crate::sensitive_filter::MaskingPolicy>,
+    idempotent: bool,
+    cache_ttl: Option<Duration>,
+    invalidates: Vec<String>,
+    result_cache: Option<ToolResultCache>,
+    page_size: Option<usize>,
+    handler_fn: Box<dyn Fn(Value, &AuthContext, PageRequest) -> std::pin::Pin<Box<dyn std::future::Future<Output = McpResult<ToolResult>> + Send>> + Send + Sync>,
 }
 
 impl HandlerToolWrapper {
@@ -48,9 +59,142 @@ impl HandlerToolWrapper {
             output_schema,
             render_type,
             response_type_name,
-            handler_fn: Box::new(handler_fn),
+            handler_function: "handler_wrapper".to_string(),
+            handler_file: "tool_wrapper.rs".to_string(),
+            masking_policy: None,
+            idempotent: false,
+            cache_ttl: None,
+            invalidates: Vec::new(),
+            result_cache: None,
+            page_size: None,
+            handler_fn: Box::new(move |arguments, auth, _page| handler_fn(arguments, auth)),
         }
     }
+
+    /// Mark the tool as free of side effects, making its results cacheable
+    pub fn idempotent(mut self) -> Self {
+        self.idempotent = true;
+        self
+    }
+
+    /// Cache results for `ttl`; only applies to idempotent, non-sensitive
+    /// tools with a result cache attached
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Drop the cached results of `tools` whenever this tool succeeds
+    pub fn invalidates(mut self, tools: &[&str]) -> Self {
+        self.invalidates = tools.iter().map(|tool| tool.to_string()).collect();
+        self
+    }
+
+    /// Serve and store results through `cache`
+    pub fn with_result_cache(mut self, cache: ToolResultCache) -> Self {
+        self.result_cache = Some(cache);
+        self
+    }
+
+    /// Cache TTL, if results of this tool may be cached
+    fn cacheable_ttl(&self) -> Option<Duration> {
+        if self.sensitive || !self.idempotent {
+            return None;
+        }
+        self.cache_ttl
+    }
+
+    /// Page the tool's results, `page_size` items at a time
+    ///
+    /// `handler` replaces the wrapped handler. It is called with the page
+    /// the client asked for and returns a serialized [`Page`]; clients get
+    /// the page's items with a cursor for the next page.
+    pub fn with_page_handler(
+        mut self,
+        page_size: usize,
+        handler: impl Fn(Value, &AuthContext, PageRequest) -> std::pin::Pin<Box<dyn std::future::Future<Output = McpResult<ToolResult>> + Send>> + Send + Sync + 'static,
+    ) -> Self {
+        self.page_size = Some(page_size);
+        self.handler_fn = Box::new(handler);
+        self
+    }
+
+    /// Page a call asks for and the fingerprint of its query, for tools
+    /// that page their results
+    fn requested_page(&self, input: &ToolInput) -> McpResult<Option<(PageRequest, String)>> {
+        let Some(page_size) = self.page_size else {
+            if input.cursor.is_some() {
+                return Err(McpError::Protocol(
+                    format!("Tool '{}' does not paginate its results", self.name)
+                ));
+            }
+            return Ok(None);
+        };
+        let fingerprint = pagination::query_fingerprint(&self.name, &input.arguments);
+        let position = match &input.cursor {
+            Some(cursor) => pagination::decode_cursor(cursor, &fingerprint)?,
+            None => PagePosition::Start,
+        };
+        Ok(Some((PageRequest { position, page_size }, fingerprint)))
+    }
+
+    /// Mask fields of the tool's results by caller claims
+    pub fn with_masking_policy(mut self, policy: crate::sensitive_filter::MaskingPolicy) -> Self {
+        self.masking_policy = Some(policy);
+        self
+    }
+
+    /// Wrap a tool declared with `#[mcp_tool]`
+    pub fn from_registration(registration: &'static ToolRegistration) -> Self {
+        let definition = &registration.definition;
+        let handler = registration.handler;
+        let response_type = definition.response_type.map(|type_name| ResponseType {
+            type_name: type_name.to_string(),
+            render_type: definition.render_type.clone(),
+            schema: None,
+        });
+
+        let run = move |arguments: Value, auth: &AuthContext, page: PageRequest| {
+            let output = handler(arguments, auth.clone(), page);
+            let response_type = response_type.clone();
+            Box::pin(async move {
+                Ok(ToolResult {
+                    status: ToolStatus::Success,
+                    data: Some(output.await?),
+                    error: None,
+                    response_type,
+                    rendered: None,
+                    next_cursor: None,
+                })
+            }) as std::pin::Pin<Box<dyn std::future::Future<Output = McpResult<ToolResult>> + Send>>
+        };
+        let unpaged = run.clone();
+
+        let mut wrapper = Self::new(
+            definition.name.to_string(),
+            definition.description.to_string(),
+            definition.category.to_string(),
+            definition.requires_permission.map(str::to_string),
+            definition.sensitive,
+            (definition.input_schema)(),
+            None,
+            definition.render_type.clone(),
+            definition.response_type.map(str::to_string),
+            // Handlers of unpaginated tools ignore the page
+            move |arguments: Value, auth: &AuthContext| {
+                unpaged(arguments, auth, PageRequest::first(usize::MAX))
+            },
+        );
+        if let Some(page_size) = definition.page_size {
+            wrapper = wrapper.with_page_handler(page_size, run);
+        }
+        wrapper.handler_function = definition.handler_function.to_string();
+        wrapper.handler_file = definition.handler_file.to_string();
+        wrapper.idempotent = definition.idempotent;
+        wrapper.cache_ttl = definition.cache_ttl_secs.map(Duration::from_secs);
+        wrapper.invalidates = definition.invalidates.iter().map(|tool| tool.to_string()).collect();
+        wrapper
+    }
 }
 
 #[async_trait]
@@ -80,17 +224,25 @@ impl McpTool for HandlerToolWrapper {
     }
     
     fn handler_function(&self) -> &str {
-        "handler_wrapper"
+        &self.handler_function
     }
     
     fn handler_file(&self) -> &str {
-        "tool_wrapper.rs"
+        &self.handler_file
     }
     
     fn response_type_name(&self) -> Option<&str> {
         self.response_type_name.as_deref()
     }
     
+    fn masking_policy(&self) -> Option<&crate::sensitive_filter::MaskingPolicy> {
+        self.masking_policy.as_ref()
+    }
+    
+    fn page_size(&self) -> Option<usize> {
+        self.page_size
+    }
+    
     fn required_permission(&self) -> Option<&str> {
         self.required_permission.as_deref()
     }
@@ -105,8 +257,51 @@ impl McpTool for HandlerToolWrapper {
         auth_context: &AuthContext,
         _zanzibar_client: Option<&dyn ZanzibarClient>,
     ) -> McpResult<ToolResult> {
+        let page = self.requested_page(&input)?;
+        let cache = self.result_cache.as_ref();
+        let cached = cache.zip(self.cacheable_ttl()).map(|(cache, ttl)| {
+            let mut key = ToolResultCache::cache_key(&input.arguments, auth_context);
+            if let Some(cursor) = &input.cursor {
+                key.push_str(cursor);
+            }
+            (cache, ttl, key)
+        });
+        if let Some((cache, _, ref key)) = cached {
+            if let Some(result) = cache.get(&self.name, key) {
+                return Ok(result);
+            }
+        }
+        
         // Call the wrapped handler function
-        (self.handler_fn)(input.arguments, auth_context).await
+        let (page_request, fingerprint) = match page {
+            Some((request, fingerprint)) => (request, Some(fingerprint)),
+            None => (PageRequest::first(usize::MAX), None),
+        };
+        let mut result = (self.handler_fn)(input.arguments, auth_context, page_request).await?;
+        
+        // Hand out the page's items, and a cursor in place of its position
+        if let Some(fingerprint) = fingerprint {
+            if let Some(data) = result.data.take() {
+                let page: Page<Value> = serde_json::from_value(data)?;
+                result.data = Some(Value::Array(page.items));
+                result.next_cursor = page
+                    .next
+                    .map(|position| pagination::encode_cursor(&fingerprint, position));
+            }
+        }
+        
+        if matches!(result.status, ToolStatus::Success) {
+            if let Some((cache, ttl, key)) = cached {
+                cache.insert(&self.name, key, result.clone(), ttl);
+            }
+            if let Some(cache) = cache {
+                for tool in &self.invalidates {
+                    cache.invalidate_tool(tool);
+                }
+            }
+        }
+        
+        Ok(result)
     }
 }
 
@@ -156,6 +351,7 @@ macro_rules! wrap_handler_as_tool {
                             schema: None,
                         }),
                         rendered: None,
+                        next_cursor: None,
                     })
                 })
             },
@@ -4,7 +4,13 @@ use crate::error::McpResult;
 use std::collections::HashMap;
 use async_trait::async_trait;
 use uuid::Uuid;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
 
 /// Trait for MCP tool implementations (auto-generated by #[mcp_tool] macro)
 pub trait McpToolImpl: Send + Sync {
@@ -24,6 +30,91 @@ pub trait McpToolImpl: Send + Sync {
     fn is_sensitive() -> bool;
 }
 
+/// Metadata of a tool declared with `#[mcp_tool]`
+#[derive(Debug, Clone)]
+pub struct ToolDefinition {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub category: &'static str,
+    pub requires_permission: Option<&'static str>,
+    pub sensitive: bool,
+    pub response_type: Option<&'static str>,
+    pub render_type: Option<crate::protocol::RenderType>,
+    /// Calling the tool has no side effects
+    pub idempotent: bool,
+    /// How long results are cached, for idempotent tools that opt in
+    pub cache_ttl_secs: Option<u64>,
+    /// Tools whose cached results are dropped after this tool succeeds
+    pub invalidates: &'static [&'static str],
+    /// Items per page, for handlers that take a `PageRequest`
+    pub page_size: Option<usize>,
+    /// Name of the decorated function
+    pub handler_function: &'static str,
+    /// Source file the function is declared in
+    pub handler_file: &'static str,
+    /// JSON Schema of the handler's arguments
+    pub input_schema: fn() -> Value,
+}
+
+/// Future returned by a generated tool handler, resolving to the handler's
+/// serialized output
+pub type ToolFuture = Pin<Box<dyn Future<Output = McpResult<Value>> + Send>>;
+
+/// A tool submitted by `#[mcp_tool]`, collected by [`ToolsRegistry`] at startup
+pub struct ToolRegistration {
+    pub definition: ToolDefinition,
+    /// Deserializes the call's arguments and invokes the decorated function
+    /// for the requested page; non-paginated handlers ignore the page
+    pub handler: fn(Value, AuthContext, crate::pagination::PageRequest) -> ToolFuture,
+}
+
+inventory::collect!(ToolRegistration);
+
+/// Every tool declared with `#[mcp_tool]` in the linked crates
+pub fn registered_tools() -> impl Iterator<Item = &'static ToolRegistration> {
+    inventory::iter::<ToolRegistration>.into_iter()
+}
+
+/// Deserialize a tool call's arguments into the handler's parameter type.
+/// Missing arguments are treated as an empty object.
+pub fn parse_tool_arguments<T: DeserializeOwned>(tool_name: &str, arguments: Value) -> McpResult<T> {
+    let arguments = if arguments.is_null() {
+        Value::Object(Default::default())
+    } else {
+        arguments
+    };
+    serde_json::from_value(arguments).map_err(|e| {
+        crate::error::McpError::Tool(format!("Invalid arguments for tool '{}': {}", tool_name, e))
+    })
+}
+
+/// JSON Schema for a tool's argument type, as advertised in `tools/list`.
+///
+/// Subschemas are inlined rather than referenced so clients get a single
+/// self-contained object. Optional fields are left out of `required`, and
+/// doc comments on fields and enum variants become descriptions.
+pub fn input_schema_for<T: JsonSchema>() -> Value {
+    let mut settings = SchemaSettings::draft07();
+    settings.inline_subschemas = true;
+    settings.option_add_null_type = false;
+    settings.meta_schema = None;
+    let schema = settings.into_generator().into_root_schema_for::<T>();
+    serde_json::to_value(schema).unwrap_or_else(|_| empty_input_schema())
+}
+
+/// Input schema of a tool that takes no arguments
+pub fn empty_input_schema() -> Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {}
+    })
+}
+
+/// Serialize a handler's output as tool result data
+pub fn tool_output<T: Serialize>(output: T) -> McpResult<Value> {
+    Ok(serde_json::to_value(output)?)
+}
+
 /// Enhanced MCP tool with auth and Zanzibar context
 #[async_trait]
 pub trait McpTool: Send + Sync {
@@ -60,6 +151,16 @@ pub trait McpTool: Send + Sync {
     /// Get handler file path (for registration)
     fn handler_file(&self) -> &str;
     
+    /// Field masking rules applied to this tool's results
+    fn masking_policy(&self) -> Option<&crate::sensitive_filter::MaskingPolicy> {
+        None
+    }
+    
+    /// Items per page, if the tool pages its results
+    fn page_size(&self) -> Option<usize> {
+        None
+    }
+    
     /// Execute the tool with auth and Zanzibar context
     async fn execute(
         &self,
@@ -77,6 +178,8 @@ pub struct AuthContext {
     pub roles: Vec<String>,
     pub permissions: Vec<String>,
     pub email: Option<String>,
+    /// Further attributes of the caller (department, clearance, ...)
+    pub claims: HashMap<String, Value>,
 }
 
 /// Zanzibar client trait for permission checks
@@ -97,6 +200,8 @@ pub trait ZanzibarClient: Send + Sync {
 pub struct ToolsRegistry {
     tools: HashMap<String, Box<dyn McpTool>>,
     sensitive_tools: Vec<String>,
+    masking_policies: HashMap<String, crate::sensitive_filter::MaskingPolicy>,
+    result_cache: crate::result_cache::ToolResultCache,
     registry_service: Option<crate::registry::McpToolRegistryService>,
 }
 
@@ -106,6 +211,8 @@ impl ToolsRegistry {
         let mut registry = Self {
             tools: HashMap::new(),
             sensitive_tools: Vec::new(),
+            masking_policies: HashMap::new(),
+            result_cache: crate::result_cache::ToolResultCache::new(),
             registry_service: None,
         };
         
@@ -122,6 +229,8 @@ impl ToolsRegistry {
         let mut registry = Self {
             tools: HashMap::new(),
             sensitive_tools: Vec::new(),
+            masking_policies: HashMap::new(),
+            result_cache: crate::result_cache::ToolResultCache::new(),
             registry_service: Some(registry_service),
         };
         
@@ -130,14 +239,28 @@ impl ToolsRegistry {
         registry
     }
 
-    /// Discover tools automatically using build-time code generation
+    /// Register every tool declared with `#[mcp_tool]` in the linked crates
     fn discover_tools(&mut self) {
-        // This will be populated by build.rs scanning for #[mcp_tool] attributes
-        // For now, we'll manually register tools, but in production this would
-        // be code-generated at build time
-        
-        // Example: Register tools from handlers
-        // This would be auto-generated from proc macro scanning
+        for registration in registered_tools() {
+            let name = registration.definition.name;
+            if self.tools.contains_key(name) {
+                tracing::warn!(
+                    tool = name,
+                    handler = registration.definition.handler_function,
+                    file = registration.definition.handler_file,
+                    "Duplicate MCP tool name; keeping the first registration"
+                );
+                continue;
+            }
+
+            if registration.definition.sensitive {
+                self.sensitive_tools.push(name.to_string());
+            }
+            let tool = crate::tool_wrapper::HandlerToolWrapper::from_registration(registration)
+                .with_result_cache(self.result_cache.clone());
+            self.tools.insert(name.to_string(), Box::new(tool));
+        }
+        tracing::debug!(count = self.tools.len(), "Discovered MCP tools");
     }
 
     /// Register a new tool (automatically stores in DB if registry_service is set)
@@ -188,6 +311,37 @@ impl ToolsRegistry {
         Ok(())
     }
 
+    /// Result cache shared by the registry's tools, for attaching to tools
+    /// registered by hand
+    pub fn result_cache(&self) -> crate::result_cache::ToolResultCache {
+        self.result_cache.clone()
+    }
+    
+    /// Drop the cached results of `tool_name`, e.g. after changing the data
+    /// it reads outside of a tool call. Returns how many were dropped.
+    pub fn invalidate_cached_results(&self, tool_name: &str) -> usize {
+        self.result_cache.invalidate_tool(tool_name)
+    }
+    
+    /// Mask fields of `tool_name`'s results by caller claims
+    ///
+    /// Overrides any policy the tool declares itself. Sensitive tools are
+    /// only callable once they have a policy, and then reveal only the
+    /// fields it explicitly allows.
+    pub fn set_masking_policy(
+        &mut self,
+        tool_name: &str,
+        policy: crate::sensitive_filter::MaskingPolicy,
+    ) -> McpResult<()> {
+        if !self.tools.contains_key(tool_name) {
+            return Err(crate::error::McpError::Tool(
+                format!("Tool '{}' not found", tool_name)
+            ));
+        }
+        self.masking_policies.insert(tool_name.to_string(), policy);
+        Ok(())
+    }
+    
     /// List all available tools (excluding sensitive ones for public access)
     pub fn list(&self, include_sensitive: bool) -> Vec<Tool> {
         self.tools.values()
@@ -198,6 +352,7 @@ impl ToolsRegistry {
                 input_schema: t.input_schema(),
                 output_schema: t.output_schema(),
                 render_type: t.render_type(),
+                page_size: t.page_size(),
             })
             .collect()
     }
@@ -214,8 +369,11 @@ impl ToolsRegistry {
                 format!("Tool '{}' not found", input.name)
             ))?;
         
-        // Check if tool is sensitive
-        if tool.is_sensitive() {
+        let masking_policy = self.masking_policies.get(&input.name)
+            .or_else(|| tool.masking_policy());
+        
+        // Sensitive tools are only exposed through a masking policy
+        if tool.is_sensitive() && masking_policy.is_none() {
             return Err(crate::error::McpError::Permission(
                 "Access to sensitive tools is restricted".to_string()
             ));
@@ -241,7 +399,17 @@ impl ToolsRegistry {
         }
         
         // Execute the tool
-        tool.execute(input, auth_context, zanzibar_client).await
+        let mut result = tool.execute(input, auth_context, zanzibar_client).await?;
+        
+        // Mask the structured data before anything renders it
+        if let Some(policy) = masking_policy {
+            if let Some(ref mut data) = result.data {
+                policy.apply(data, auth_context, !tool.is_sensitive());
+            }
+            result.rendered = None;
+        }
+        
+        Ok(result)
     }
 }
 
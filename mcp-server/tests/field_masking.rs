@@ -0,0 +1,182 @@
+//! Claim-based field masking of tool results
+
+use mcp_macros::mcp_tool;
+use mcp_server::error::{McpError, McpResult};
+use mcp_server::protocol::{RenderType, ToolInput};
+use mcp_server::render::render_result;
+use mcp_server::sensitive_filter::{ClaimPredicate, MaskingPolicy, MASKED_VALUE};
+use mcp_server::tools::{AuthContext, ToolsRegistry};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[mcp_tool(name = "masking_chart", category = "healthcare", sensitive = true)]
+fn chart() -> McpResult<Value> {
+    Ok(json!({
+        "name": "Jane Doe",
+        "ssn": "123-45-6789",
+        "medications": ["Metformin 500mg", "Lisinopril 10mg"],
+        "notes": {
+            "general": "Stable",
+            "psych": "Generalized anxiety disorder"
+        }
+    }))
+}
+
+#[mcp_tool(name = "masking_visits", category = "healthcare")]
+fn visits() -> McpResult<Value> {
+    Ok(json!([
+        { "date": "2024-01-02", "reason": "Follow-up", "billing_code": "99213" },
+        { "date": "2024-02-03", "reason": "Lab review", "billing_code": "99214" }
+    ]))
+}
+
+fn caller(roles: &[&str]) -> AuthContext {
+    AuthContext {
+        user_id: Uuid::new_v4(),
+        organization_id: Uuid::new_v4(),
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+        permissions: vec![],
+        email: None,
+        claims: Default::default(),
+    }
+}
+
+fn chart_policy() -> MaskingPolicy {
+    let clinician = ClaimPredicate::AnyOf(vec![
+        ClaimPredicate::role("nurse"),
+        ClaimPredicate::role("psychiatrist"),
+    ]);
+    MaskingPolicy::new()
+        .reveal("name", clinician.clone())
+        .reveal("medications", clinician.clone())
+        .reveal("notes", clinician)
+        .reveal("notes.psych", ClaimPredicate::role("psychiatrist"))
+}
+
+async fn call(registry: &ToolsRegistry, tool: &str, auth: &AuthContext) -> McpResult<Value> {
+    let result = registry
+        .execute(
+            ToolInput {
+                name: tool.to_string(),
+                arguments: Value::Null,
+                cursor: None,
+            },
+            auth,
+            None,
+        )
+        .await?;
+    Ok(result.data.unwrap())
+}
+
+#[tokio::test]
+async fn test_sensitive_tool_requires_a_policy() {
+    let registry = ToolsRegistry::new();
+    assert!(matches!(
+        call(&registry, "masking_chart", &caller(&["nurse"])).await,
+        Err(McpError::Permission(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_fields_are_revealed_by_role() {
+    let mut registry = ToolsRegistry::new();
+    registry.set_masking_policy("masking_chart", chart_policy()).unwrap();
+
+    let nurse = call(&registry, "masking_chart", &caller(&["nurse"])).await.unwrap();
+    assert_eq!(nurse["name"], "Jane Doe");
+    assert_eq!(nurse["medications"], json!(["Metformin 500mg", "Lisinopril 10mg"]));
+    assert_eq!(nurse["notes"]["general"], "Stable");
+    assert_eq!(nurse["notes"]["psych"], MASKED_VALUE);
+    // Not covered by any rule, so denied on a sensitive tool
+    assert_eq!(nurse["ssn"], MASKED_VALUE);
+
+    let psychiatrist = call(&registry, "masking_chart", &caller(&["psychiatrist"])).await.unwrap();
+    assert_eq!(psychiatrist["notes"]["psych"], "Generalized anxiety disorder");
+
+    let clerk = call(&registry, "masking_chart", &caller(&["front_desk"])).await.unwrap();
+    assert_eq!(
+        clerk,
+        json!({
+            "name": MASKED_VALUE,
+            "ssn": MASKED_VALUE,
+            "medications": [MASKED_VALUE, MASKED_VALUE],
+            "notes": { "general": MASKED_VALUE, "psych": MASKED_VALUE }
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_ordinary_tools_reveal_unlisted_fields() {
+    let mut registry = ToolsRegistry::new();
+    let billing = ClaimPredicate::AllOf(vec![
+        ClaimPredicate::permission("billing:read"),
+        ClaimPredicate::Not(Box::new(ClaimPredicate::claim("contractor", true))),
+    ]);
+    registry
+        .set_masking_policy("masking_visits", MaskingPolicy::new().reveal("billing_code", billing))
+        .unwrap();
+
+    let nurse = call(&registry, "masking_visits", &caller(&["nurse"])).await.unwrap();
+    assert_eq!(nurse[0]["reason"], "Follow-up");
+    assert_eq!(nurse[0]["billing_code"], MASKED_VALUE);
+    assert_eq!(nurse[1]["billing_code"], MASKED_VALUE);
+
+    let mut biller = caller(&[]);
+    biller.permissions.push("billing:read".to_string());
+    let visits = call(&registry, "masking_visits", &biller).await.unwrap();
+    assert_eq!(visits[1]["billing_code"], "99214");
+
+    biller.claims.insert("contractor".to_string(), json!(true));
+    let visits = call(&registry, "masking_visits", &biller).await.unwrap();
+    assert_eq!(visits[1]["billing_code"], MASKED_VALUE);
+}
+
+#[tokio::test]
+async fn test_every_render_type_sees_masked_data() {
+    let mut registry = ToolsRegistry::new();
+    registry
+        .set_masking_policy(
+            "masking_visits",
+            MaskingPolicy::new().reveal("billing_code", ClaimPredicate::role("billing")),
+        )
+        .unwrap();
+
+    let result = registry
+        .execute(
+            ToolInput {
+                name: "masking_visits".to_string(),
+                arguments: Value::Null,
+                cursor: None,
+            },
+            &caller(&["nurse"]),
+            None,
+        )
+        .await
+        .unwrap();
+
+    let render_types = [
+        RenderType::Json,
+        RenderType::Markdown,
+        RenderType::Html,
+        RenderType::Table,
+        RenderType::List,
+        RenderType::Text,
+        RenderType::Structured {
+            format: "csv".to_string(),
+            schema: Value::Null,
+        },
+    ];
+    for render_type in &render_types {
+        let rendered = render_result(&result, Some(render_type));
+        assert!(!rendered.contains("99213"), "{:?} leaked a masked field", render_type);
+        assert!(rendered.contains(MASKED_VALUE), "{:?} dropped the mask", render_type);
+    }
+}
+
+#[test]
+fn test_unknown_tools_cannot_get_a_policy() {
+    let mut registry = ToolsRegistry::new();
+    assert!(registry
+        .set_masking_policy("no_such_tool", MaskingPolicy::new())
+        .is_err());
+}
@@ -0,0 +1,231 @@
+//! `#[mcp_tool]` registration and argument handling
+
+use mcp_macros::mcp_tool;
+use mcp_server::error::McpResult;
+use mcp_server::protocol::{RenderType, ToolInput, ToolStatus};
+use mcp_server::tools::{registered_tools, AuthContext, ToolsRegistry};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+/// Patient lookup
+#[derive(Debug, Deserialize, JsonSchema)]
+struct LookupArgs {
+    /// Medical record number
+    patient_id: String,
+    /// Also match discharged patients
+    #[serde(default)]
+    include_inactive: bool,
+    /// Section of the chart to return
+    section: Option<ChartSection>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum ChartSection {
+    Demographics,
+    Medications,
+    Allergies,
+}
+
+#[derive(Debug, Serialize)]
+struct PatientSummary {
+    patient_id: String,
+    requested_by: Uuid,
+    include_inactive: bool,
+    section: Option<ChartSection>,
+}
+
+#[mcp_tool(
+    name = "test_lookup_patient",
+    description = "Look up a patient",
+    category = "healthcare",
+    requires_permission = "patient:read",
+    response_type = "PatientSummary",
+    render_type = "table"
+)]
+async fn lookup_patient(args: LookupArgs, auth: &AuthContext) -> McpResult<PatientSummary> {
+    Ok(PatientSummary {
+        patient_id: args.patient_id,
+        requested_by: auth.user_id,
+        include_inactive: args.include_inactive,
+        section: args.section,
+    })
+}
+
+#[mcp_tool(description = "Report service health")]
+fn test_health() -> McpResult<&'static str> {
+    Ok("ok")
+}
+
+#[mcp_tool(name = "test_rotate_keys", category = "secrets", sensitive = true)]
+async fn rotate_keys(auth: AuthContext) -> McpResult<Uuid> {
+    Ok(auth.organization_id)
+}
+
+fn auth() -> AuthContext {
+    AuthContext {
+        user_id: Uuid::new_v4(),
+        organization_id: Uuid::new_v4(),
+        roles: vec!["clinician".to_string()],
+        permissions: vec![],
+        email: None,
+        claims: Default::default(),
+    }
+}
+
+#[test]
+fn test_decorated_functions_are_registered() {
+    let definition = &registered_tools()
+        .find(|registration| registration.definition.name == "test_lookup_patient")
+        .expect("lookup_patient is registered")
+        .definition;
+    assert_eq!(definition.description, "Look up a patient");
+    assert_eq!(definition.category, "healthcare");
+    assert_eq!(definition.requires_permission, Some("patient:read"));
+    assert!(!definition.sensitive);
+    assert_eq!(definition.response_type, Some("PatientSummary"));
+    assert!(matches!(definition.render_type, Some(RenderType::Table)));
+    assert_eq!(definition.handler_function, "lookup_patient");
+    assert!(definition.handler_file.ends_with("mcp_tool_macro.rs"));
+
+    // Defaults
+    let health = &registered_tools()
+        .find(|registration| registration.definition.name == "test_health")
+        .expect("test_health is registered")
+        .definition;
+    assert_eq!(health.category, "general");
+    assert_eq!(health.requires_permission, None);
+}
+
+#[test]
+fn test_registry_lists_discovered_tools() {
+    let registry = ToolsRegistry::new();
+
+    let public: Vec<String> = registry
+        .list(false)
+        .into_iter()
+        .map(|tool| tool.name)
+        .collect();
+    assert!(public.contains(&"test_lookup_patient".to_string()));
+    assert!(public.contains(&"test_health".to_string()));
+    assert!(!public.contains(&"test_rotate_keys".to_string()));
+
+    let all: Vec<String> = registry
+        .list(true)
+        .into_iter()
+        .map(|tool| tool.name)
+        .collect();
+    assert!(all.contains(&"test_rotate_keys".to_string()));
+}
+
+#[tokio::test]
+async fn test_tool_call_deserializes_arguments() {
+    let registry = ToolsRegistry::new();
+    let auth = auth();
+
+    let result = registry
+        .execute(
+            ToolInput {
+                name: "test_lookup_patient".to_string(),
+                arguments: json!({ "patient_id": "12345" }),
+                cursor: None,
+            },
+            &auth,
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert!(matches!(result.status, ToolStatus::Success));
+    assert_eq!(
+        result.data,
+        Some(json!({
+            "patient_id": "12345",
+            "requested_by": auth.user_id,
+            "include_inactive": false,
+            "section": null,
+        }))
+    );
+    assert_eq!(result.response_type.unwrap().type_name, "PatientSummary");
+}
+
+#[test]
+fn test_input_schema_follows_the_argument_type() {
+    let registry = ToolsRegistry::new();
+    let tools = registry.list(false);
+    let lookup = tools
+        .iter()
+        .find(|tool| tool.name == "test_lookup_patient")
+        .unwrap();
+
+    let schema = &lookup.input_schema;
+    assert_eq!(schema["type"], "object");
+    assert_eq!(schema["required"], json!(["patient_id"]));
+    assert_eq!(
+        schema["properties"]["patient_id"],
+        json!({ "type": "string", "description": "Medical record number" })
+    );
+    assert_eq!(
+        schema["properties"]["include_inactive"]["description"],
+        "Also match discharged patients"
+    );
+    assert_eq!(schema["properties"]["include_inactive"]["type"], "boolean");
+
+    // Optional enum, inlined rather than referenced
+    let section = &schema["properties"]["section"];
+    assert_eq!(section["description"], "Section of the chart to return");
+    assert!(section
+        .to_string()
+        .contains(r#"["demographics","medications","allergies"]"#));
+    assert!(!schema.to_string().contains("$ref"));
+
+    // Tools without arguments still advertise an object schema
+    let health = tools
+        .iter()
+        .find(|tool| tool.name == "test_health")
+        .unwrap();
+    assert_eq!(
+        health.input_schema,
+        json!({ "type": "object", "properties": {} })
+    );
+}
+
+#[tokio::test]
+async fn test_tool_call_without_arguments() {
+    let registry = ToolsRegistry::new();
+    let result = registry
+        .execute(
+            ToolInput {
+                name: "test_health".to_string(),
+                arguments: serde_json::Value::Null,
+                cursor: None,
+            },
+            &auth(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.data, Some(json!("ok")));
+}
+
+#[tokio::test]
+async fn test_invalid_arguments_are_rejected() {
+    let registry = ToolsRegistry::new();
+    let err = registry
+        .execute(
+            ToolInput {
+                name: "test_lookup_patient".to_string(),
+                arguments: json!({ "patient_id": 12345 }),
+                cursor: None,
+            },
+            &auth(),
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Invalid arguments for tool 'test_lookup_patient'"));
+}
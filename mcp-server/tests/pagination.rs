@@ -0,0 +1,183 @@
+//! Cursor-based pagination of tool results
+
+use mcp_macros::mcp_tool;
+use mcp_server::error::{McpError, McpResult};
+use mcp_server::pagination::{Page, PageRequest, DEFAULT_PAGE_SIZE};
+use mcp_server::protocol::{ToolInput, ToolResult};
+use mcp_server::tools::{AuthContext, ToolsRegistry};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SearchArgs {
+    family_name: String,
+    #[serde(default)]
+    active_only: bool,
+}
+
+/// 60 Smiths and 10 Joneses, by MRN
+fn patients() -> impl Iterator<Item = Value> {
+    (0..70).map(|n| {
+        let family_name = if n < 60 { "Smith" } else { "Jones" };
+        json!({ "mrn": format!("MRN{:04}", n), "family_name": family_name })
+    })
+}
+
+#[mcp_tool(name = "paging_search_patients", page_size = 25)]
+fn search_patients(args: SearchArgs, page: PageRequest) -> McpResult<Page<Value>> {
+    let matches = patients().filter(|patient| patient["family_name"] == args.family_name.as_str());
+    Ok(Page::slice(matches, &page))
+}
+
+#[mcp_tool(name = "paging_encounters")]
+async fn encounters(page: &PageRequest) -> McpResult<Page<u64>> {
+    let after = page.after().and_then(Value::as_u64).unwrap_or(0);
+    let rows = (after + 1..=120).take(page.fetch_limit()).collect();
+    Ok(Page::keyset(rows, page, |id| json!(id)))
+}
+
+#[mcp_tool(name = "paging_ward_count")]
+fn ward_count() -> McpResult<u32> {
+    Ok(12)
+}
+
+fn caller() -> AuthContext {
+    AuthContext {
+        user_id: Uuid::new_v4(),
+        organization_id: Uuid::new_v4(),
+        roles: vec!["clinician".to_string()],
+        permissions: vec![],
+        email: None,
+        claims: Default::default(),
+    }
+}
+
+async fn call(
+    registry: &ToolsRegistry,
+    tool: &str,
+    arguments: Value,
+    cursor: Option<String>,
+) -> McpResult<ToolResult> {
+    let input = ToolInput {
+        name: tool.to_string(),
+        arguments,
+        cursor,
+    };
+    registry.execute(input, &caller(), None).await
+}
+
+/// Follow `next_cursor` to the last page, returning each page's items
+async fn all_pages(registry: &ToolsRegistry, tool: &str, arguments: Value) -> Vec<Vec<Value>> {
+    let mut pages = Vec::new();
+    let mut cursor = None;
+    loop {
+        let result = call(registry, tool, arguments.clone(), cursor).await.unwrap();
+        let Some(Value::Array(items)) = result.data else {
+            panic!("page data is not an array");
+        };
+        pages.push(items);
+        match result.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return pages,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_offset_pages_cover_every_match_once() {
+    let registry = ToolsRegistry::new();
+    let pages = all_pages(&registry, "paging_search_patients", json!({ "family_name": "Smith" })).await;
+
+    assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![25, 25, 10]);
+    let mrns: Vec<_> = pages.concat().iter().map(|patient| patient["mrn"].clone()).collect();
+    let expected: Vec<_> = (0..60).map(|n| json!(format!("MRN{:04}", n))).collect();
+    assert_eq!(mrns, expected);
+}
+
+#[tokio::test]
+async fn test_keyset_pages_use_the_default_page_size() {
+    let registry = ToolsRegistry::new();
+    let pages = all_pages(&registry, "paging_encounters", Value::Null).await;
+
+    assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![50, 50, 20]);
+    assert_eq!(pages.concat(), (1..=120).map(|id| json!(id)).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_page_size_is_advertised() {
+    let registry = ToolsRegistry::new();
+    let tools = registry.list(false);
+    let page_size = |name: &str| tools.iter().find(|tool| tool.name == name).unwrap().page_size;
+
+    assert_eq!(page_size("paging_search_patients"), Some(25));
+    assert_eq!(page_size("paging_encounters"), Some(DEFAULT_PAGE_SIZE));
+    assert_eq!(page_size("paging_ward_count"), None);
+}
+
+#[tokio::test]
+async fn test_cursors_survive_reordered_arguments() {
+    let registry = ToolsRegistry::new();
+    let first = call(
+        &registry,
+        "paging_search_patients",
+        json!({ "family_name": "Smith", "active_only": false }),
+        None,
+    )
+    .await
+    .unwrap();
+
+    let second = call(
+        &registry,
+        "paging_search_patients",
+        json!({ "active_only": false, "family_name": "Smith" }),
+        first.next_cursor,
+    )
+    .await
+    .unwrap();
+    assert_eq!(second.data.unwrap()[0]["mrn"], "MRN0025");
+}
+
+#[tokio::test]
+async fn test_cursors_are_stale_once_the_query_changes() {
+    let registry = ToolsRegistry::new();
+    let first = call(&registry, "paging_search_patients", json!({ "family_name": "Smith" }), None)
+        .await
+        .unwrap();
+    let cursor = first.next_cursor.unwrap();
+
+    let changed = call(
+        &registry,
+        "paging_search_patients",
+        json!({ "family_name": "Jones" }),
+        Some(cursor.clone()),
+    )
+    .await;
+    assert!(matches!(changed, Err(McpError::StaleCursor(_))));
+
+    let other_tool = call(&registry, "paging_encounters", Value::Null, Some(cursor)).await;
+    assert!(matches!(other_tool, Err(McpError::StaleCursor(_))));
+}
+
+#[tokio::test]
+async fn test_invalid_cursors_are_rejected() {
+    let registry = ToolsRegistry::new();
+
+    let malformed = call(
+        &registry,
+        "paging_encounters",
+        Value::Null,
+        Some("not-a-cursor".to_string()),
+    )
+    .await;
+    assert!(matches!(malformed, Err(McpError::Protocol(_))));
+
+    let first = call(&registry, "paging_encounters", Value::Null, None).await.unwrap();
+    let unpaginated = call(&registry, "paging_ward_count", Value::Null, first.next_cursor).await;
+    assert!(matches!(unpaginated, Err(McpError::Protocol(_))));
+
+    let result = call(&registry, "paging_ward_count", Value::Null, None).await.unwrap();
+    assert_eq!(result.data, Some(json!(12)));
+    assert!(result.next_cursor.is_none());
+}
@@ -0,0 +1,207 @@
+//! Result caching of idempotent tools
+
+use mcp_macros::mcp_tool;
+use mcp_server::error::McpResult;
+use mcp_server::protocol::{ToolInput, ToolResult, ToolStatus};
+use mcp_server::result_cache::TOOL_CACHE_METRIC;
+use mcp_server::tool_wrapper::HandlerToolWrapper;
+use mcp_server::tools::{AuthContext, McpTool, ToolsRegistry};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+static FORMULARY_CALLS: AtomicUsize = AtomicUsize::new(0);
+static DRUG_CLASS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FormularyArgs {
+    drug: String,
+    #[serde(default)]
+    generic_only: bool,
+}
+
+#[mcp_tool(name = "cache_formulary_lookup", idempotent = true, cache_ttl_secs = 300)]
+fn formulary_lookup(args: FormularyArgs) -> McpResult<Value> {
+    FORMULARY_CALLS.fetch_add(1, Ordering::SeqCst);
+    Ok(json!({ "drug": args.drug, "generic_only": args.generic_only, "tier": 2 }))
+}
+
+#[mcp_tool(name = "cache_drug_classes", idempotent = true, cache_ttl_secs = 300)]
+fn drug_classes() -> McpResult<Vec<&'static str>> {
+    DRUG_CLASS_CALLS.fetch_add(1, Ordering::SeqCst);
+    Ok(vec!["biguanide", "statin"])
+}
+
+#[mcp_tool(name = "cache_allergy_classes", idempotent = true, cache_ttl_secs = 300)]
+fn allergy_classes() -> McpResult<Vec<&'static str>> {
+    Ok(vec!["penicillins", "sulfonamides"])
+}
+
+#[mcp_tool(name = "cache_update_formulary", invalidates = "cache_drug_classes, cache_unknown")]
+fn update_formulary() -> McpResult<()> {
+    Ok(())
+}
+
+fn caller(roles: &[&str]) -> AuthContext {
+    AuthContext {
+        user_id: Uuid::new_v4(),
+        organization_id: Uuid::nil(),
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+        permissions: vec![],
+        email: None,
+        claims: Default::default(),
+    }
+}
+
+fn input(name: &str, arguments: Value) -> ToolInput {
+    ToolInput {
+        name: name.to_string(),
+        arguments,
+        cursor: None,
+    }
+}
+
+#[tokio::test]
+async fn test_results_are_cached_per_arguments_and_scope() {
+    let registry = ToolsRegistry::new();
+    let nurse = caller(&["nurse", "staff"]);
+
+    let first = registry
+        .execute(input("cache_formulary_lookup", json!({ "drug": "metformin", "generic_only": true })), &nurse, None)
+        .await
+        .unwrap();
+    // Same arguments in another order, from another user with the same roles
+    let second = registry
+        .execute(
+            input("cache_formulary_lookup", json!({ "generic_only": true, "drug": "metformin" })),
+            &caller(&["staff", "nurse"]),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(first.data, second.data);
+    assert_eq!(FORMULARY_CALLS.load(Ordering::SeqCst), 1);
+
+    registry
+        .execute(input("cache_formulary_lookup", json!({ "drug": "insulin" })), &nurse, None)
+        .await
+        .unwrap();
+    assert_eq!(FORMULARY_CALLS.load(Ordering::SeqCst), 2);
+
+    // Different permission scope
+    registry
+        .execute(
+            input("cache_formulary_lookup", json!({ "drug": "metformin", "generic_only": true })),
+            &caller(&["pharmacist"]),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(FORMULARY_CALLS.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_write_tools_invalidate_related_caches() {
+    let registry = ToolsRegistry::new();
+    let auth = caller(&["pharmacist"]);
+    let before = DRUG_CLASS_CALLS.load(Ordering::SeqCst);
+
+    for _ in 0..2 {
+        registry.execute(input("cache_drug_classes", Value::Null), &auth, None).await.unwrap();
+    }
+    assert_eq!(DRUG_CLASS_CALLS.load(Ordering::SeqCst), before + 1);
+
+    registry.execute(input("cache_update_formulary", Value::Null), &auth, None).await.unwrap();
+    registry.execute(input("cache_drug_classes", Value::Null), &auth, None).await.unwrap();
+    assert_eq!(DRUG_CLASS_CALLS.load(Ordering::SeqCst), before + 2);
+
+    assert_eq!(registry.invalidate_cached_results("cache_drug_classes"), 1);
+    registry.execute(input("cache_drug_classes", Value::Null), &auth, None).await.unwrap();
+    assert_eq!(DRUG_CLASS_CALLS.load(Ordering::SeqCst), before + 3);
+}
+
+fn counting_tool(calls: Arc<AtomicUsize>, sensitive: bool) -> HandlerToolWrapper {
+    HandlerToolWrapper::new(
+        "cache_counting".to_string(),
+        "Counts calls".to_string(),
+        "general".to_string(),
+        None,
+        sensitive,
+        json!({ "type": "object" }),
+        None,
+        None,
+        None,
+        move |_arguments: Value, _auth: &AuthContext| {
+            let calls = calls.clone();
+            Box::pin(async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(ToolResult {
+                    status: ToolStatus::Success,
+                    data: Some(json!("ok")),
+                    error: None,
+                    response_type: None,
+                    rendered: None,
+                    next_cursor: None,
+                })
+            })
+        },
+    )
+    .with_cache_ttl(Duration::from_secs(300))
+    .with_result_cache(Default::default())
+}
+
+async fn run_twice(tool: &HandlerToolWrapper) {
+    let auth = caller(&[]);
+    for _ in 0..2 {
+        tool.execute(input("cache_counting", Value::Null), &auth, None).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_only_idempotent_non_sensitive_tools_are_cached() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    run_twice(&counting_tool(calls.clone(), false)).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 2, "non-idempotent tool was cached");
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    run_twice(&counting_tool(calls.clone(), true).idempotent()).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 2, "sensitive tool was cached");
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    run_twice(&counting_tool(calls.clone(), false).idempotent()).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_cache_lookups_are_counted() {
+    let registry = ToolsRegistry::new();
+    let auth = caller(&["metrics"]);
+    let lookups = |result: &str| lookup_count("cache_allergy_classes", result);
+
+    for _ in 0..3 {
+        registry.execute(input("cache_allergy_classes", Value::Null), &auth, None).await.unwrap();
+    }
+
+    assert_eq!(lookups("miss"), 1.0);
+    assert_eq!(lookups("hit"), 2.0);
+}
+
+fn lookup_count(tool: &str, result: &str) -> f64 {
+    telemetry::MetricsCollector::global()
+        .snapshot_family(TOOL_CACHE_METRIC)
+        .and_then(|family| {
+            family.series.into_iter().find_map(|series| {
+                let matches = series.labels.iter().any(|(key, value)| key == "tool" && value == tool)
+                    && series.labels.iter().any(|(key, value)| key == "result" && value == result);
+                match series.value {
+                    telemetry::SeriesValue::Counter(count) if matches => Some(count),
+                    _ => None,
+                }
+            })
+        })
+        .unwrap_or(0.0)
+}
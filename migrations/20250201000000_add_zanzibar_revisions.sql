@@ -0,0 +1,10 @@
+-- Add revisions to Zanzibar tuples
+-- Every tuple write or delete advances a global revision. Consistency tokens
+-- (zookies) encode this revision so checks can require "at least as fresh as".
+
+CREATE SEQUENCE IF NOT EXISTS zanzibar_revision_seq;
+
+ALTER TABLE zanzibar_tuples
+    ADD COLUMN IF NOT EXISTS revision BIGINT NOT NULL DEFAULT nextval('zanzibar_revision_seq');
+
+CREATE INDEX IF NOT EXISTS idx_zanzibar_revision ON zanzibar_tuples(revision);
@@ -0,0 +1,27 @@
+-- Create zanzibar_tuple_changes table
+-- Append-only log of tuple writes and deletes, keyed by the revision from
+-- zanzibar_revision_seq. Watchers poll it with a revision cursor to drive
+-- cache invalidation and audit feeds.
+
+CREATE TABLE IF NOT EXISTS zanzibar_tuple_changes (
+    revision BIGINT PRIMARY KEY,
+    operation VARCHAR(10) NOT NULL CHECK (operation IN ('write', 'delete')),
+    organization_id UUID,
+
+    subject_namespace VARCHAR(50) NOT NULL,
+    subject_type VARCHAR(50) NOT NULL,
+    subject_id VARCHAR(255) NOT NULL,
+    subject_relation VARCHAR(50),
+
+    relation_name VARCHAR(50) NOT NULL,
+
+    object_namespace VARCHAR(50) NOT NULL,
+    object_type VARCHAR(50) NOT NULL,
+    object_id VARCHAR(255) NOT NULL,
+
+    tuple_created_at TIMESTAMPTZ NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Supports pruning old changes by age
+CREATE INDEX IF NOT EXISTS idx_zanzibar_tuple_changes_changed_at ON zanzibar_tuple_changes(changed_at);
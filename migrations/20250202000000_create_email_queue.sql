@@ -0,0 +1,55 @@
+-- Create email_queue and email_dead_letters tables
+-- Outbound email waiting for delivery. Workers claim rows with
+-- FOR UPDATE SKIP LOCKED and hold them for a lease (locked_until); rows whose
+-- lease expired are claimable again, so a crashed worker loses nothing.
+-- Messages that exhaust their attempts move to email_dead_letters.
+
+CREATE TABLE IF NOT EXISTS email_queue (
+    id UUID PRIMARY KEY,
+    -- Higher is delivered first: 0 bulk, 1 normal, 2 high, 3 critical
+    priority SMALLINT NOT NULL DEFAULT 1 CHECK (priority BETWEEN 0 AND 3),
+
+    recipient VARCHAR(320) NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    is_html BOOLEAN NOT NULL DEFAULT FALSE,
+
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'processing')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    available_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_by VARCHAR(255),
+    locked_until TIMESTAMPTZ,
+    last_error TEXT,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Claim order for pending messages
+CREATE INDEX IF NOT EXISTS idx_email_queue_claim
+    ON email_queue(priority DESC, available_at)
+    WHERE status = 'pending';
+
+-- Finds messages whose worker lease expired
+CREATE INDEX IF NOT EXISTS idx_email_queue_locked_until
+    ON email_queue(locked_until)
+    WHERE status = 'processing';
+
+CREATE TABLE IF NOT EXISTS email_dead_letters (
+    id UUID PRIMARY KEY,
+    priority SMALLINT NOT NULL,
+
+    recipient VARCHAR(320) NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    is_html BOOLEAN NOT NULL,
+
+    attempts INTEGER NOT NULL,
+    last_error TEXT,
+
+    created_at TIMESTAMPTZ NOT NULL,
+    failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_dead_letters_failed_at ON email_dead_letters(failed_at);
@@ -0,0 +1,6 @@
+-- Add contains_phi to email_queue and email_dead_letters
+-- Queued messages keep their PHI flag so the worker applies the S/MIME
+-- policy for unencryptable PHI at delivery time.
+
+ALTER TABLE email_queue ADD COLUMN IF NOT EXISTS contains_phi BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE email_dead_letters ADD COLUMN IF NOT EXISTS contains_phi BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,72 @@
+-- Create plugin registry tables
+-- Marketplace index of plugins, their released versions and user reviews.
+-- A plugin is identified by its slug (e.g. 'auth-saml-connector'). Search runs
+-- against a weighted tsvector over name and description, and tag filters use
+-- a GIN index on the tags array.
+
+CREATE TABLE IF NOT EXISTS plugins (
+    id VARCHAR(128) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    category VARCHAR(64) NOT NULL,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+
+    author_id UUID,
+    author_name VARCHAR(255) NOT NULL,
+    homepage_url TEXT,
+    repository_url TEXT,
+    license VARCHAR(64),
+
+    download_count BIGINT NOT NULL DEFAULT 0 CHECK (download_count >= 0),
+    status VARCHAR(20) NOT NULL DEFAULT 'published'
+        CHECK (status IN ('draft', 'published', 'unpublished')),
+
+    -- Name matches rank above description matches
+    search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', coalesce(name, '')), 'A') ||
+        setweight(to_tsvector('english', coalesce(description, '')), 'B')
+    ) STORED,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_plugins_search ON plugins USING GIN(search_vector);
+CREATE INDEX IF NOT EXISTS idx_plugins_tags ON plugins USING GIN(tags);
+CREATE INDEX IF NOT EXISTS idx_plugins_category ON plugins(category) WHERE status = 'published';
+CREATE INDEX IF NOT EXISTS idx_plugins_downloads ON plugins(download_count DESC) WHERE status = 'published';
+
+CREATE TABLE IF NOT EXISTS plugin_versions (
+    id UUID PRIMARY KEY,
+    plugin_id VARCHAR(128) NOT NULL REFERENCES plugins(id) ON DELETE CASCADE,
+    -- Semantic version of this release
+    version VARCHAR(64) NOT NULL,
+    -- Semver range of RustCare engine versions the release runs on
+    engine_requirement VARCHAR(255) NOT NULL DEFAULT '*',
+    changelog TEXT,
+    package_url TEXT,
+    -- Yanked releases are skipped by resolution but stay downloadable by
+    -- exact version
+    yanked BOOLEAN NOT NULL DEFAULT FALSE,
+    published_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (plugin_id, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_plugin_versions_plugin ON plugin_versions(plugin_id, published_at DESC);
+
+CREATE TABLE IF NOT EXISTS plugin_reviews (
+    id UUID PRIMARY KEY,
+    plugin_id VARCHAR(128) NOT NULL REFERENCES plugins(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL,
+    rating SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    title VARCHAR(255),
+    body TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    -- One review per user per plugin; resubmitting updates it
+    UNIQUE (plugin_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_plugin_reviews_plugin ON plugin_reviews(plugin_id);
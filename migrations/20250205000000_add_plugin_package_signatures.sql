@@ -0,0 +1,31 @@
+-- Add package integrity and publisher signing keys
+-- Every release records the SHA-256 of its package and an Ed25519 signature
+-- by one of its publisher's keys. Publishers rotate keys by registering a new
+-- one: the previous active key is retired, and packages it signed before
+-- retirement keep verifying. Revoked keys verify nothing.
+
+CREATE TABLE IF NOT EXISTS publisher_keys (
+    id UUID PRIMARY KEY,
+    -- Matches plugins.author_id
+    publisher_id UUID NOT NULL,
+    algorithm VARCHAR(20) NOT NULL DEFAULT 'ed25519' CHECK (algorithm IN ('ed25519')),
+    public_key BYTEA NOT NULL,
+    status TEXT NOT NULL DEFAULT 'active'
+        CHECK (status IN ('active', 'retired', 'revoked')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    retired_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+
+    UNIQUE (publisher_id, public_key)
+);
+
+-- At most one signing key per publisher
+CREATE UNIQUE INDEX IF NOT EXISTS idx_publisher_keys_active
+    ON publisher_keys(publisher_id) WHERE status = 'active';
+
+ALTER TABLE plugin_versions
+    -- Lowercase hex SHA-256 of the package archive
+    ADD COLUMN IF NOT EXISTS content_hash VARCHAR(64),
+    -- Ed25519 signature over "<plugin_id>@<version>:<content_hash>"
+    ADD COLUMN IF NOT EXISTS signature BYTEA,
+    ADD COLUMN IF NOT EXISTS signing_key_id UUID REFERENCES publisher_keys(id);
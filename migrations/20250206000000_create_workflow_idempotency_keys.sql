@@ -0,0 +1,21 @@
+-- Create workflow_idempotency_keys table
+-- Output of each workflow task that ran under an idempotency key. A task whose
+-- key is already here returns the stored output instead of running again, so
+-- retried and restarted executions don't repeat side effects such as charges.
+-- Keys are scoped to the workflow and task that recorded them.
+
+CREATE TABLE IF NOT EXISTS workflow_idempotency_keys (
+    workflow VARCHAR(255) NOT NULL,
+    task VARCHAR(255) NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    output JSONB NOT NULL,
+    -- Execution whose run produced the output
+    execution_id UUID NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (workflow, task, idempotency_key)
+);
+
+-- Supports pruning old keys
+CREATE INDEX IF NOT EXISTS idx_workflow_idempotency_keys_recorded_at
+    ON workflow_idempotency_keys(recorded_at);
@@ -0,0 +1,19 @@
+-- Create eligibility_batch_checkpoints table
+-- Answered requests of each batch eligibility check. Re-running a batch that
+-- stopped partway skips the requests recorded here. Failed inquiries are not
+-- recorded, so the next run tries them again.
+
+CREATE TABLE IF NOT EXISTS eligibility_batch_checkpoints (
+    -- Derived from the batch's requests, so the same requests share an id
+    batch_id UUID NOT NULL,
+    -- patient_id:insurance_id:service_date
+    request_key TEXT NOT NULL,
+    entry JSONB NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (batch_id, request_key)
+);
+
+-- Supports pruning finished batches
+CREATE INDEX IF NOT EXISTS idx_eligibility_batch_checkpoints_recorded_at
+    ON eligibility_batch_checkpoints(recorded_at);
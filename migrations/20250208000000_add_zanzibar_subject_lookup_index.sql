@@ -0,0 +1,14 @@
+-- Index for subject-side lookups ("which objects can this subject access")
+-- list_objects walks the relation graph from the subject, reading every tuple
+-- whose subject is the user or one of the usersets it belongs to, one batch
+-- per level of group nesting. The existing idx_zanzibar_reverse leaves out
+-- the subject namespace and relation, so userset subjects such as
+-- role:nurse#member could not be matched from the index alone.
+--
+-- The relation and object columns are included so each lookup is answered
+-- by an index-only scan, keeping its cost proportional to the subject's own
+-- grants as the tuple table grows.
+
+CREATE INDEX IF NOT EXISTS idx_zanzibar_subject_lookup ON zanzibar_tuples(
+    subject_namespace, subject_type, subject_id, subject_relation
+) INCLUDE (relation_name, object_namespace, object_type, object_id, expires_at);
@@ -0,0 +1,22 @@
+-- Create record_legal_holds table
+-- A hold keeps a soft-deleted row from being purged however long it has sat
+-- in the recycle bin, for as long as the hold is active. Holds are never
+-- deleted: releasing one records who released it and when, so the history
+-- of holds is itself auditable.
+
+CREATE TABLE IF NOT EXISTS record_legal_holds (
+    id UUID PRIMARY KEY,
+    -- Table and primary key of the held row
+    table_name VARCHAR(255) NOT NULL,
+    record_id UUID NOT NULL,
+    reason TEXT NOT NULL,
+    placed_by UUID NOT NULL,
+    placed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    released_by UUID,
+    released_at TIMESTAMPTZ
+);
+
+-- Supports the purge's check for an active hold on each row
+CREATE INDEX IF NOT EXISTS idx_record_legal_holds_active
+    ON record_legal_holds(table_name, record_id)
+    WHERE released_at IS NULL;
@@ -0,0 +1,173 @@
+// Command-line interface definition
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::CliConfig;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "rustcare",
+    version,
+    about = "Operations CLI for RustCare Engine"
+)]
+pub struct Cli {
+    /// Verbose output
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Configuration file path
+    #[arg(long = "config", global = true, default_value = "rustcare.yaml")]
+    pub config_path: String,
+
+    /// Print machine-readable JSON instead of tables
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    pub fn config(&self) -> CliConfig {
+        CliConfig {
+            verbose: self.verbose,
+            config_path: self.config_path.clone(),
+            json: self.json,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Database operations
+    #[command(subcommand)]
+    Data(DataCommand),
+
+    /// Logs, metrics and alerts
+    #[command(subcommand)]
+    Monitor(MonitorCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DataCommand {
+    /// Apply, revert and inspect schema migrations
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up {
+        #[command(flatten)]
+        database: DatabaseArgs,
+
+        /// List the migrations that would run without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Revert the most recently applied migrations
+    Down {
+        #[command(flatten)]
+        database: DatabaseArgs,
+
+        /// Number of migrations to revert
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+
+        /// List the migrations that would be reverted without reverting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show applied and pending migrations
+    Status {
+        #[command(flatten)]
+        database: DatabaseArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct DatabaseArgs {
+    /// Database connection URL
+    #[arg(long, env = "DATABASE_URL")]
+    pub database_url: String,
+
+    /// Directory containing the migration files
+    #[arg(long, default_value = "migrations")]
+    pub migrations_dir: PathBuf,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MonitorCommand {
+    /// Show and tail the structured JSON service logs
+    Logs(LogsArgs),
+
+    /// Request rate, error rate, latency and dependency health
+    Metrics(MetricsArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct MetricsArgs {
+    /// Open the interactive dashboard and keep polling
+    #[arg(long)]
+    pub live: bool,
+
+    /// Service to poll as `name=http://host:port`; repeat for several
+    #[arg(
+        long = "target",
+        value_name = "[NAME=]URL",
+        default_value = "rustcare-server=http://localhost:8080"
+    )]
+    pub targets: Vec<String>,
+
+    /// Bearer token for `/metrics`, if the server sets METRICS_TOKEN
+    #[arg(long, env = "METRICS_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+
+    /// Seconds between polls
+    #[arg(long, default_value_t = 2)]
+    pub interval: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// JSON log file written by the services
+    #[arg(
+        long,
+        env = "RUSTCARE_LOG_FILE",
+        default_value = "/var/log/rustcare/rustcare.log"
+    )]
+    pub file: PathBuf,
+
+    /// Keep printing new entries as they are written
+    #[arg(short, long)]
+    pub follow: bool,
+
+    /// Only show entries from this service (e.g. auth-gateway)
+    #[arg(long)]
+    pub service: Option<String>,
+
+    /// Minimum level to show: trace, debug, info, warn or error
+    #[arg(long)]
+    pub level: Option<String>,
+
+    /// Only entries at or after this time (RFC 3339, or ago like 15m, 2h, 1d)
+    #[arg(long)]
+    pub since: Option<String>,
+
+    /// Only entries at or before this time (RFC 3339, or ago like 15m, 2h, 1d)
+    #[arg(long)]
+    pub until: Option<String>,
+
+    /// Regular expression matched against the (already redacted) message text
+    #[arg(long)]
+    pub grep: Option<String>,
+
+    /// Show only the last N matching entries before following
+    #[arg(short = 'n', long)]
+    pub lines: Option<usize>,
+
+    /// Colored output in the server's development format
+    #[arg(long)]
+    pub pretty: bool,
+}
@@ -0,0 +1,14 @@
+// Command dispatch
+use anyhow::Result;
+
+use crate::cli::{Cli, Command, DataCommand};
+use crate::{migration, monitoring};
+
+/// Run the parsed command
+pub async fn execute(cli: Cli) -> Result<()> {
+    let config = cli.config();
+    match cli.command {
+        Command::Data(DataCommand::Migrate(command)) => migration::run(&config, command).await,
+        Command::Monitor(command) => monitoring::run(&config, command).await,
+    }
+}
@@ -0,0 +1,507 @@
+// Live metrics dashboard for `rustcare monitor metrics --live`
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Sparkline, Table, Tabs};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::io::{self, Stdout};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender};
+
+use crate::metrics::{format_latency, HealthSummary, MetricsClient, MetricsSnapshot, Target};
+
+/// Points kept for each sparkline
+const HISTORY_LEN: usize = 120;
+/// How long to wait for a key press before redrawing
+const INPUT_POLL: Duration = Duration::from_millis(100);
+
+/// Run the dashboard until the user quits
+pub async fn run(targets: Vec<Target>, client: MetricsClient, interval: Duration) -> Result<()> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let pollers: Vec<_> = targets
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(index, target)| {
+            tokio::spawn(poll(index, target, client.clone(), interval, tx.clone()))
+        })
+        .collect();
+    drop(tx);
+
+    let names = targets.into_iter().map(|target| target.name).collect();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut terminal = TerminalGuard::enter()?;
+        Dashboard::new(names).run(&mut terminal.terminal, rx)
+    })
+    .await;
+
+    for poller in pollers {
+        poller.abort();
+    }
+    result?
+}
+
+/// Result of one poll of a target
+struct Update {
+    target: usize,
+    at: Instant,
+    metrics: Result<MetricsSnapshot, String>,
+    health: Result<HealthSummary, String>,
+}
+
+async fn poll(
+    target: usize,
+    endpoint: Target,
+    client: MetricsClient,
+    interval: Duration,
+    tx: UnboundedSender<Update>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let (metrics, health) = tokio::join!(client.snapshot(&endpoint), client.health(&endpoint));
+        let update = Update {
+            target,
+            at: Instant::now(),
+            metrics: metrics.map_err(|e| format!("{:#}", e)),
+            health: health.map_err(|e| format!("{:#}", e)),
+        };
+        if tx.send(update).is_err() {
+            // Dashboard closed
+            return;
+        }
+    }
+}
+
+/// Switches the terminal into raw mode on the alternate screen, and back
+/// again when dropped, including when unwinding from a panic
+struct TerminalGuard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        if let Err(e) = crossterm::execute!(io::stdout(), EnterAlternateScreen) {
+            let _ = disable_raw_mode();
+            return Err(e.into());
+        }
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        terminal.hide_cursor()?;
+        Ok(Self { terminal })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = crossterm::execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = self.terminal.show_cursor();
+    }
+}
+
+/// Rates and latencies over the most recent poll interval
+#[derive(Debug, Clone, Copy, Default)]
+struct Rates {
+    requests_per_sec: f64,
+    error_ratio: Option<f64>,
+    p50: Option<f64>,
+    p95: Option<f64>,
+    p99: Option<f64>,
+}
+
+enum Connection {
+    Connecting,
+    Connected,
+    Reconnecting { error: String, failures: u32 },
+}
+
+struct TargetState {
+    name: String,
+    connection: Connection,
+    /// Latest scrape, the baseline for the next interval's deltas
+    last: Option<(Instant, MetricsSnapshot)>,
+    rates: Option<Rates>,
+    request_history: VecDeque<u64>,
+    error_history: VecDeque<u64>,
+    health: Option<Result<HealthSummary, String>>,
+}
+
+impl TargetState {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            connection: Connection::Connecting,
+            last: None,
+            rates: None,
+            request_history: VecDeque::with_capacity(HISTORY_LEN),
+            error_history: VecDeque::with_capacity(HISTORY_LEN),
+            health: None,
+        }
+    }
+
+    /// Fold in a poll result. While paused only the baseline moves, so the
+    /// panels stay frozen and resuming does not report one long interval.
+    fn apply(&mut self, update: Update, paused: bool) {
+        let snapshot = match update.metrics {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                let failures = match &self.connection {
+                    Connection::Reconnecting { failures, .. } => failures + 1,
+                    _ => 1,
+                };
+                self.connection = Connection::Reconnecting { error, failures };
+                // Counters may have reset by the time it comes back
+                self.last = None;
+                return;
+            }
+        };
+        self.connection = Connection::Connected;
+
+        let previous = self.last.replace((update.at, snapshot.clone()));
+        if paused {
+            return;
+        }
+        self.health = Some(update.health);
+
+        let Some((previous_at, previous)) = previous else {
+            return;
+        };
+        let elapsed = update.at.duration_since(previous_at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return;
+        }
+
+        let delta = snapshot.delta(&previous);
+        let rates = Rates {
+            requests_per_sec: delta.requests / elapsed,
+            error_ratio: delta.error_ratio(),
+            p50: delta.latency_quantile(0.50),
+            p95: delta.latency_quantile(0.95),
+            p99: delta.latency_quantile(0.99),
+        };
+        // Sparklines take integers; keep a decimal place of request rate and
+        // error ratio in tenths of a percent so quiet services still register
+        push_history(
+            &mut self.request_history,
+            (rates.requests_per_sec * 10.0).round() as u64,
+        );
+        push_history(
+            &mut self.error_history,
+            (rates.error_ratio.unwrap_or(0.0) * 1000.0).round() as u64,
+        );
+        self.rates = Some(rates);
+    }
+}
+
+fn push_history(history: &mut VecDeque<u64>, value: u64) {
+    if history.len() == HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(value);
+}
+
+struct Dashboard {
+    targets: Vec<TargetState>,
+    selected: usize,
+    paused: bool,
+}
+
+impl Dashboard {
+    fn new(names: Vec<String>) -> Self {
+        Self {
+            targets: names.into_iter().map(TargetState::new).collect(),
+            selected: 0,
+            paused: false,
+        }
+    }
+
+    fn run(
+        mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        mut rx: UnboundedReceiver<Update>,
+    ) -> Result<()> {
+        loop {
+            loop {
+                match rx.try_recv() {
+                    Ok(update) => {
+                        let paused = self.paused;
+                        if let Some(target) = self.targets.get_mut(update.target) {
+                            target.apply(update, paused);
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(INPUT_POLL)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns `false` when the user asked to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let count = self.targets.len();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('p') | KeyCode::Char(' ') => self.paused = !self.paused,
+            KeyCode::Tab
+            | KeyCode::Right
+            | KeyCode::Down
+            | KeyCode::Char('l')
+            | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1) % count;
+            }
+            KeyCode::BackTab
+            | KeyCode::Left
+            | KeyCode::Up
+            | KeyCode::Char('h')
+            | KeyCode::Char('k') => {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                let index = c.to_digit(10).unwrap_or(0) as usize;
+                if (1..=count).contains(&index) {
+                    self.selected = index - 1;
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let target = &self.targets[self.selected];
+        let banner = self.banner(target);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(u16::from(banner.is_some())),
+                Constraint::Length(9),
+                Constraint::Min(5),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+
+        self.draw_tabs(frame, rows[0]);
+        if let Some(banner) = banner {
+            frame.render_widget(banner, rows[1]);
+        }
+
+        let panels = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(35),
+                Constraint::Percentage(35),
+                Constraint::Percentage(30),
+            ])
+            .split(rows[2]);
+        draw_request_rate(frame, panels[0], target);
+        draw_error_rate(frame, panels[1], target);
+        draw_latency(frame, panels[2], target);
+        draw_health(frame, rows[3], target);
+
+        let help = Paragraph::new(Line::from(vec![
+            Span::styled(" q", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" quit  "),
+            Span::styled("p", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(if self.paused { " resume  " } else { " pause  " }),
+            Span::styled("tab/←→", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" select service"),
+        ]))
+        .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, rows[4]);
+    }
+
+    fn draw_tabs(&self, frame: &mut Frame, area: Rect) {
+        let titles: Vec<Line> = self
+            .targets
+            .iter()
+            .map(|target| {
+                let color = match target.connection {
+                    Connection::Connected => Color::Green,
+                    Connection::Connecting => Color::Gray,
+                    Connection::Reconnecting { .. } => Color::Red,
+                };
+                Line::from(Span::styled(
+                    target.name.clone(),
+                    Style::default().fg(color),
+                ))
+            })
+            .collect();
+        let tabs = Tabs::new(titles)
+            .select(self.selected)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" RustCare metrics "),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+        frame.render_widget(tabs, area);
+    }
+
+    fn banner(&self, target: &TargetState) -> Option<Paragraph<'static>> {
+        let (text, color) = match (&target.connection, self.paused) {
+            (Connection::Reconnecting { error, failures }, _) => (
+                format!(
+                    " Endpoint unreachable, reconnecting (attempt {}): {}",
+                    failures, error
+                ),
+                Color::Red,
+            ),
+            (_, true) => (" Paused; press p to resume".to_string(), Color::Yellow),
+            _ => return None,
+        };
+        Some(
+            Paragraph::new(text).style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(color)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        )
+    }
+}
+
+fn panel(title: String) -> Block<'static> {
+    Block::default().borders(Borders::ALL).title(title)
+}
+
+fn draw_request_rate(frame: &mut Frame, area: Rect, target: &TargetState) {
+    let title = match target.rates {
+        Some(rates) => format!(" Requests  {:.1}/s ", rates.requests_per_sec),
+        None => " Requests  -/s ".to_string(),
+    };
+    let data: Vec<u64> = target.request_history.iter().copied().collect();
+    let sparkline = Sparkline::default()
+        .block(panel(title))
+        .data(&data)
+        .style(Style::default().fg(Color::Cyan));
+    frame.render_widget(sparkline, area);
+}
+
+fn draw_error_rate(frame: &mut Frame, area: Rect, target: &TargetState) {
+    let ratio = target.rates.and_then(|rates| rates.error_ratio);
+    let title = match ratio {
+        Some(ratio) => format!(" 5xx errors  {:.2}% ", ratio * 100.0),
+        None => " 5xx errors  - ".to_string(),
+    };
+    let color = match ratio {
+        Some(ratio) if ratio >= 0.05 => Color::Red,
+        Some(ratio) if ratio > 0.0 => Color::Yellow,
+        _ => Color::Green,
+    };
+    let data: Vec<u64> = target.error_history.iter().copied().collect();
+    let sparkline = Sparkline::default()
+        .block(panel(title))
+        .data(&data)
+        .style(Style::default().fg(color));
+    frame.render_widget(sparkline, area);
+}
+
+fn draw_latency(frame: &mut Frame, area: Rect, target: &TargetState) {
+    let rates = target.rates.unwrap_or_default();
+    let lines: Vec<Line> = [("p50", rates.p50), ("p95", rates.p95), ("p99", rates.p99)]
+        .into_iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(format!(" {}  ", label), Style::default().fg(Color::Gray)),
+                Span::styled(
+                    format_latency(value),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+            ])
+        })
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(panel(" Latency ".to_string())),
+        area,
+    );
+}
+
+fn draw_health(frame: &mut Frame, area: Rect, target: &TargetState) {
+    let summary = match &target.health {
+        Some(Ok(summary)) => summary,
+        Some(Err(error)) => {
+            let message = Paragraph::new(format!(" Health unavailable: {}", error))
+                .style(Style::default().fg(Color::Red))
+                .block(panel(" Dependencies ".to_string()));
+            frame.render_widget(message, area);
+            return;
+        }
+        None => {
+            frame.render_widget(
+                Paragraph::new(" Waiting for /ready…").block(panel(" Dependencies ".to_string())),
+                area,
+            );
+            return;
+        }
+    };
+
+    let rows = summary.checks.iter().map(|(name, check)| {
+        Row::new(vec![
+            Cell::from(name.clone()),
+            Cell::from(check.status.clone())
+                .style(Style::default().fg(health_color(&check.status))),
+            Cell::from(if check.critical { "yes" } else { "no" }),
+            Cell::from(format!("{} ms", check.latency_ms)),
+            Cell::from(check.error.clone().unwrap_or_default()),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(20),
+            Constraint::Length(10),
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(vec!["DEPENDENCY", "STATUS", "CRITICAL", "LATENCY", "ERROR"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(Line::from(vec![
+                Span::raw(" Dependencies  "),
+                Span::styled(
+                    summary.status.clone(),
+                    Style::default()
+                        .fg(health_color(&summary.status))
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" "),
+            ])),
+    );
+    frame.render_widget(table, area);
+}
+
+fn health_color(status: &str) -> Color {
+    match status {
+        "healthy" => Color::Green,
+        "degraded" => Color::Yellow,
+        _ => Color::Red,
+    }
+}
@@ -1,16 +1,18 @@
+pub mod cli;
+pub mod commands;
+pub mod interactive;
+pub mod metrics;
+pub mod migration;
+pub mod monitoring;
+
 // Module declarations - to be implemented
-// pub mod cli;
-// pub mod commands;
 // pub mod config;
-// pub mod interactive;
-// pub mod monitoring;
 // pub mod deployment;
 // pub mod backup;
-// pub mod migration;
 // pub mod error;
 
-// pub use cli::*;
-// pub use commands::*;
+pub use cli::*;
+pub use commands::*;
 // pub use error::*;
 
 /// Operations CLI for RustCare Engine management and administration
@@ -71,6 +73,8 @@ pub struct CliConfig {
     pub verbose: bool,
     /// Configuration file path
     pub config_path: String,
+    /// Emit JSON instead of human-readable output
+    pub json: bool,
 }
 
 /// Initialize CLI with default configuration
@@ -78,5 +82,6 @@ pub fn init() -> CliConfig {
     CliConfig {
         verbose: false,
         config_path: "rustcare.yaml".to_string(),
+        json: false,
     }
 }
\ No newline at end of file
@@ -1,21 +1,29 @@
-// TODO: Implement CLI modules
-// mod cli;
-// mod commands;
-
-use anyhow::Result;
+use clap::Parser;
+use console::style;
+use ops_cli::{commands, Cli};
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    println!("RustCare Engine CLI - Coming Soon");
-    
-    // TODO: Initialize telemetry
-    // tracing_subscriber::fmt::init();
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
 
-    // TODO: Parse command line arguments
-    // let cli = cli::Cli::parse();
+    if cli.verbose {
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .with_env_filter("info")
+            .init();
+    }
 
-    // TODO: Execute the command
-    // commands::execute(cli).await
-    
-    Ok(())
-}
\ No newline at end of file
+    let json = cli.json;
+    match commands::execute(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            if json {
+                println!("{}", serde_json::json!({ "error": format!("{:#}", error) }));
+            } else {
+                eprintln!("{} {:#}", style("error:").red().bold(), error);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
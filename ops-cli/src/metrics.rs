@@ -0,0 +1,324 @@
+// Scraping the server's `/metrics` and `/ready` endpoints
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Counter written by the server's request timing middleware
+const REQUESTS_TOTAL: &str = "http_requests_total";
+/// Histogram written by the server's request timing middleware
+const REQUEST_DURATION: &str = "http_request_duration_seconds";
+
+/// A service whose endpoints the dashboard polls
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub name: String,
+    pub base_url: String,
+}
+
+impl Target {
+    /// `name=http://host:port` or a bare URL, which is named after its host
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, url) = match spec.split_once('=') {
+            Some((name, url)) => (Some(name.trim().to_string()), url.trim()),
+            None => (None, spec.trim()),
+        };
+        let Some(host) = url
+            .strip_prefix("http://")
+            .or_else(|| url.strip_prefix("https://"))
+        else {
+            bail!(
+                "invalid target '{}': expected [name=]http(s)://host:port",
+                spec
+            );
+        };
+
+        let base_url = url.trim_end_matches('/').to_string();
+        let name = name
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| host.split('/').next().unwrap_or(host).to_string());
+        Ok(Self { name, base_url })
+    }
+}
+
+/// One sample line of the Prometheus text format
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: HashMap<String, String>,
+    pub value: f64,
+}
+
+impl Sample {
+    fn label(&self, name: &str) -> Option<&str> {
+        self.labels.get(name).map(String::as_str)
+    }
+}
+
+/// Parse the Prometheus text exposition format, skipping comments and
+/// lines that do not parse
+pub fn parse_exposition(text: &str) -> Vec<Sample> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(parse_sample)
+        .collect()
+}
+
+fn parse_sample(line: &str) -> Option<Sample> {
+    // OpenMetrics exemplars trail the value after " # "
+    let line = line.split(" # ").next()?;
+
+    let (name, labels, rest) = match line.find(['{', ' ']) {
+        Some(index) if line.as_bytes()[index] == b'{' => {
+            let (labels, rest) = parse_labels(&line[index + 1..])?;
+            (&line[..index], labels, rest)
+        }
+        Some(index) => (&line[..index], HashMap::new(), &line[index..]),
+        None => return None,
+    };
+
+    let value = match rest.split_whitespace().next()? {
+        "+Inf" => f64::INFINITY,
+        "-Inf" => f64::NEG_INFINITY,
+        value => value.parse().ok()?,
+    };
+    Some(Sample {
+        name: name.to_string(),
+        labels,
+        value,
+    })
+}
+
+/// Labels up to the closing brace, and the remainder of the line
+fn parse_labels(input: &str) -> Option<(HashMap<String, String>, &str)> {
+    let mut labels = HashMap::new();
+    let mut rest = input;
+    loop {
+        rest = rest.trim_start_matches([' ', ',']);
+        if let Some(after) = rest.strip_prefix('}') {
+            return Some((labels, after));
+        }
+
+        let (key, after_key) = rest.split_once('=')?;
+        let mut chars = after_key.strip_prefix('"')?.char_indices();
+        let mut value = String::new();
+        let end = loop {
+            match chars.next()? {
+                (index, '"') => break index,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    other => value.push(other),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.insert(key.trim().to_string(), value);
+        rest = &after_key[1 + end + 1..];
+    }
+}
+
+/// Request totals and latency distribution at one point in time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    pub requests: f64,
+    /// Requests answered with a 5xx status
+    pub errors: f64,
+    /// Cumulative latency buckets by upper bound, ascending, ending in `+Inf`
+    pub latency_buckets: Vec<(f64, f64)>,
+}
+
+impl MetricsSnapshot {
+    /// Sum the request metrics across all methods and routes
+    pub fn from_samples(samples: &[Sample]) -> Self {
+        let mut snapshot = Self::default();
+        let mut buckets: BTreeMap<u64, (f64, f64)> = BTreeMap::new();
+
+        for sample in samples {
+            if sample.name == REQUESTS_TOTAL {
+                snapshot.requests += sample.value;
+                if sample
+                    .label("status")
+                    .is_some_and(|status| status.starts_with('5'))
+                {
+                    snapshot.errors += sample.value;
+                }
+            } else if sample.name == format!("{}_bucket", REQUEST_DURATION) {
+                let Some(bound) = sample.label("le").and_then(parse_bound) else {
+                    continue;
+                };
+                // Key on the bit pattern so bounds sort and merge exactly
+                buckets.entry(bound.to_bits()).or_insert((bound, 0.0)).1 += sample.value;
+            }
+        }
+
+        snapshot.latency_buckets = buckets.into_values().collect();
+        snapshot.latency_buckets.sort_by(|a, b| a.0.total_cmp(&b.0));
+        snapshot
+    }
+
+    /// Activity between `earlier` and `self`.
+    ///
+    /// A counter that went backwards means the server restarted, in which
+    /// case everything since the restart is the delta.
+    pub fn delta(&self, earlier: &MetricsSnapshot) -> MetricsSnapshot {
+        if self.requests < earlier.requests {
+            return self.clone();
+        }
+        let latency_buckets = self
+            .latency_buckets
+            .iter()
+            .map(|&(bound, count)| {
+                let before = earlier
+                    .latency_buckets
+                    .iter()
+                    .find(|(other, _)| *other == bound)
+                    .map_or(0.0, |(_, count)| *count);
+                (bound, (count - before).max(0.0))
+            })
+            .collect();
+        MetricsSnapshot {
+            requests: self.requests - earlier.requests,
+            errors: (self.errors - earlier.errors).max(0.0),
+            latency_buckets,
+        }
+    }
+
+    /// Fraction of requests that failed, if there were any
+    pub fn error_ratio(&self) -> Option<f64> {
+        (self.requests > 0.0).then(|| self.errors / self.requests)
+    }
+
+    /// Latency quantile in seconds, interpolated within its bucket the way
+    /// Prometheus' `histogram_quantile` does
+    pub fn latency_quantile(&self, quantile: f64) -> Option<f64> {
+        let total = self.latency_buckets.last()?.1;
+        if total <= 0.0 {
+            return None;
+        }
+
+        let rank = quantile.clamp(0.0, 1.0) * total;
+        let mut lower = (0.0, 0.0);
+        for &(bound, count) in &self.latency_buckets {
+            if count >= rank {
+                if bound.is_infinite() {
+                    // Nothing to interpolate towards; report the last finite bound
+                    return Some(lower.0);
+                }
+                let in_bucket = count - lower.1;
+                if in_bucket <= 0.0 {
+                    return Some(bound);
+                }
+                return Some(lower.0 + (bound - lower.0) * (rank - lower.1) / in_bucket);
+            }
+            lower = (bound, count);
+        }
+        Some(lower.0)
+    }
+}
+
+/// Latency for display: milliseconds below a second, seconds above
+pub fn format_latency(seconds: Option<f64>) -> String {
+    match seconds {
+        Some(seconds) if seconds >= 1.0 => format!("{:.2} s", seconds),
+        Some(seconds) => format!("{:.1} ms", seconds * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+fn parse_bound(value: &str) -> Option<f64> {
+    match value {
+        "+Inf" => Some(f64::INFINITY),
+        value => value.parse().ok(),
+    }
+}
+
+/// `/ready` report from the server's health registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSummary {
+    pub status: String,
+    #[serde(default)]
+    pub checks: BTreeMap<String, DependencyHealth>,
+}
+
+/// One dependency check in a `/ready` report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyHealth {
+    pub status: String,
+    #[serde(default)]
+    pub critical: bool,
+    #[serde(default)]
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiEnvelope<T> {
+    data: T,
+}
+
+/// HTTP client for a target's metrics and readiness endpoints
+#[derive(Clone)]
+pub struct MetricsClient {
+    http: reqwest::Client,
+    token: Option<String>,
+}
+
+impl MetricsClient {
+    pub fn new(token: Option<String>, timeout: Duration) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .connect_timeout(timeout.min(Duration::from_secs(2)))
+            .build()
+            .context("could not build HTTP client")?;
+        Ok(Self {
+            http,
+            token: token.filter(|token| !token.is_empty()),
+        })
+    }
+
+    pub async fn snapshot(&self, target: &Target) -> Result<MetricsSnapshot> {
+        let url = format!("{}/metrics", target.base_url);
+        let mut request = self.http.get(&url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("could not reach {}", url))?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            bail!(
+                "{} rejected the metrics token (set METRICS_TOKEN or --token)",
+                url
+            );
+        }
+        let text = response
+            .error_for_status()
+            .with_context(|| format!("{} returned an error", url))?
+            .text()
+            .await?;
+        Ok(MetricsSnapshot::from_samples(&parse_exposition(&text)))
+    }
+
+    /// Readiness report; a 503 still carries the per-dependency results
+    pub async fn health(&self, target: &Target) -> Result<HealthSummary> {
+        let url = format!("{}/ready", target.base_url);
+        let response = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .with_context(|| format!("could not reach {}", url))?;
+        let status = response.status();
+        if !status.is_success() && status != reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            bail!("{} returned {}", url, status);
+        }
+        let envelope: ApiEnvelope<HealthSummary> = response
+            .json()
+            .await
+            .with_context(|| format!("unexpected response from {}", url))?;
+        Ok(envelope.data)
+    }
+}
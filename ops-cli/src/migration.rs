@@ -0,0 +1,143 @@
+// `rustcare data migrate` commands
+use anyhow::Result;
+use console::style;
+use database_layer::{
+    check_history, MigrationManager, MigrationRun, MigrationState, MigrationStatus,
+};
+use serde_json::json;
+
+use crate::cli::{DatabaseArgs, MigrateCommand};
+use crate::CliConfig;
+
+pub async fn run(config: &CliConfig, command: MigrateCommand) -> Result<()> {
+    match command {
+        MigrateCommand::Up { database, dry_run } => {
+            let manager = connect(&database).await?;
+            let runs = manager.migrate_up(dry_run).await?;
+            print_runs(config, "up", dry_run, &runs);
+            Ok(())
+        }
+        MigrateCommand::Down {
+            database,
+            steps,
+            dry_run,
+        } => {
+            let manager = connect(&database).await?;
+            let runs = manager.migrate_down(steps, dry_run).await?;
+            print_runs(config, "down", dry_run, &runs);
+            Ok(())
+        }
+        MigrateCommand::Status { database } => {
+            let manager = connect(&database).await?;
+            let statuses = manager.status().await?;
+            let healthy = check_history(&statuses);
+            print_status(config, &statuses, healthy.is_ok());
+            healthy.map_err(Into::into)
+        }
+    }
+}
+
+async fn connect(database: &DatabaseArgs) -> Result<MigrationManager> {
+    Ok(MigrationManager::connect(&database.database_url, &database.migrations_dir).await?)
+}
+
+fn print_status(config: &CliConfig, statuses: &[MigrationStatus], healthy: bool) {
+    let pending = statuses
+        .iter()
+        .filter(|status| status.state == MigrationState::Pending)
+        .count();
+
+    if config.json {
+        println!(
+            "{}",
+            json!({
+                "migrations": statuses,
+                "applied": statuses.len() - pending,
+                "pending": pending,
+                "ok": healthy,
+            })
+        );
+        return;
+    }
+
+    println!(
+        "{:<16} {:<44} {:<18} {:<10} INSTALLED",
+        "VERSION", "DESCRIPTION", "STATE", "REVERSIBLE"
+    );
+    for status in statuses {
+        let state = format!("{:<18}", status.state.as_str());
+        let state = match status.state {
+            MigrationState::Applied => style(state).green(),
+            MigrationState::Pending => style(state).yellow(),
+            _ => style(state).red().bold(),
+        };
+        println!(
+            "{:<16} {:<44} {} {:<10} {}",
+            status.version,
+            truncate(&status.description, 44),
+            state,
+            if status.reversible { "yes" } else { "no" },
+            status
+                .installed_on
+                .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        );
+    }
+    println!();
+    println!("{} applied, {} pending", statuses.len() - pending, pending);
+}
+
+fn print_runs(config: &CliConfig, direction: &str, dry_run: bool, runs: &[MigrationRun]) {
+    if config.json {
+        println!(
+            "{}",
+            json!({
+                "direction": direction,
+                "dry_run": dry_run,
+                "migrations": runs,
+            })
+        );
+        return;
+    }
+
+    let verb = match (direction, dry_run) {
+        ("up", true) => "Would apply",
+        ("up", false) => "Applied",
+        (_, true) => "Would revert",
+        (_, false) => "Reverted",
+    };
+
+    if runs.is_empty() {
+        match direction {
+            "up" => println!("Nothing to apply; the database is up to date"),
+            _ => println!("No applied migrations to revert"),
+        }
+        return;
+    }
+
+    for run in runs {
+        let duration = run
+            .duration_ms
+            .map(|ms| format!(" ({} ms)", ms))
+            .unwrap_or_default();
+        println!(
+            "{} {} {}{}",
+            style(verb).green(),
+            run.version,
+            run.description,
+            duration
+        );
+    }
+    println!();
+    println!("{} {} migration(s)", verb, runs.len());
+}
+
+fn truncate(value: &str, width: usize) -> String {
+    if value.chars().count() <= width {
+        value.to_string()
+    } else {
+        let mut truncated: String = value.chars().take(width - 1).collect();
+        truncated.push('…');
+        truncated
+    }
+}
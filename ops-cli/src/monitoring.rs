@@ -0,0 +1,513 @@
+// `rustcare monitor` commands
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use colored::Colorize;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::fs::{File, Metadata};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cli::{LogsArgs, MetricsArgs, MonitorCommand};
+use crate::metrics::{format_latency, MetricsClient, Target};
+use crate::{interactive, CliConfig};
+
+/// How often a followed file is checked for new lines and rotation
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub async fn run(config: &CliConfig, command: MonitorCommand) -> Result<()> {
+    match command {
+        MonitorCommand::Logs(args) => logs(config, args).await,
+        MonitorCommand::Metrics(args) => metrics(config, args).await,
+    }
+}
+
+/// Log levels in increasing severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "trace" => Some(LogLevel::Trace),
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+/// One line of the structured JSON log.
+///
+/// Fields are kept exactly as written: redaction happened when the line
+/// was logged, and nothing here tries to reverse it.
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub level: Option<LogLevel>,
+    pub service: Option<String>,
+    pub target: Option<String>,
+    pub message: String,
+    pub fields: Map<String, Value>,
+}
+
+impl LogRecord {
+    /// Parse a `tracing_subscriber` JSON line; `None` for anything else
+    pub fn parse(line: &str) -> Option<Self> {
+        let Value::Object(mut object) = serde_json::from_str::<Value>(line).ok()? else {
+            return None;
+        };
+
+        let mut fields = match object.remove("fields") {
+            Some(Value::Object(fields)) => fields,
+            _ => Map::new(),
+        };
+        let message = fields
+            .remove("message")
+            .or_else(|| object.remove("message"))
+            .map(|value| match value {
+                Value::String(text) => text,
+                other => other.to_string(),
+            })
+            .unwrap_or_default();
+
+        let text = |object: &Map<String, Value>, key: &str| {
+            object.get(key).and_then(Value::as_str).map(str::to_string)
+        };
+        let target = text(&object, "target");
+        let service = text(&object, "service")
+            .or_else(|| text(&object, "service.name"))
+            .or_else(|| text(&fields, "service"))
+            .or_else(|| {
+                target
+                    .as_deref()
+                    .and_then(|target| target.split("::").next())
+                    .map(|krate| krate.replace('_', "-"))
+            });
+
+        Some(Self {
+            timestamp: text(&object, "timestamp")
+                .and_then(|ts| DateTime::parse_from_rfc3339(&ts).ok())
+                .map(|ts| ts.with_timezone(&Utc)),
+            level: text(&object, "level").and_then(|level| LogLevel::parse(&level)),
+            service,
+            target,
+            message,
+            fields,
+        })
+    }
+
+    /// Render the record the way the server's development formatter does
+    pub fn pretty(&self) -> String {
+        let timestamp = self
+            .timestamp
+            .map(|ts| ts.format("%H:%M:%S%.3f").to_string())
+            .unwrap_or_else(|| "--:--:--.---".to_string());
+        let level = match self.level {
+            Some(LogLevel::Trace) => "TRACE".bright_purple(),
+            Some(LogLevel::Debug) => "DEBUG".bright_blue(),
+            Some(LogLevel::Info) => " INFO".bright_green(),
+            Some(LogLevel::Warn) => " WARN".bright_yellow(),
+            Some(LogLevel::Error) => "ERROR".bright_red(),
+            None => "  ???".normal(),
+        };
+        let target = self
+            .target
+            .as_deref()
+            .and_then(|target| target.split("::").last())
+            .or(self.service.as_deref())
+            .unwrap_or("");
+
+        let mut line = format!(
+            "{} [{}] {:<15} {}",
+            timestamp.bright_black(),
+            level,
+            target.bright_cyan(),
+            self.message.white().bold()
+        );
+        for (key, value) in &self.fields {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            line.push_str(&format!(
+                " {}={}",
+                key.bright_yellow(),
+                value.bright_white()
+            ));
+        }
+        line
+    }
+}
+
+/// Filters from the `monitor logs` flags
+pub struct LogFilter {
+    pub service: Option<String>,
+    pub min_level: Option<LogLevel>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub grep: Option<Regex>,
+}
+
+impl LogFilter {
+    fn from_args(args: &LogsArgs) -> Result<Self> {
+        let min_level = match &args.level {
+            Some(level) => Some(
+                LogLevel::parse(level).with_context(|| format!("unknown log level '{}'", level))?,
+            ),
+            None => None,
+        };
+        let grep = match &args.grep {
+            Some(pattern) => Some(
+                Regex::new(pattern)
+                    .with_context(|| format!("invalid --grep regex '{}'", pattern))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            service: args.service.as_deref().map(normalize_service),
+            min_level,
+            since: args.since.as_deref().map(parse_time).transpose()?,
+            until: args.until.as_deref().map(parse_time).transpose()?,
+            grep,
+        })
+    }
+
+    fn is_structural(&self) -> bool {
+        self.service.is_some()
+            || self.min_level.is_some()
+            || self.since.is_some()
+            || self.until.is_some()
+    }
+
+    /// Whether `line` (already parsed into `record`, if JSON) should be shown
+    pub fn matches(&self, line: &str, record: Option<&LogRecord>) -> bool {
+        let Some(record) = record else {
+            // Non-JSON lines (panics, startup banners) carry no metadata to
+            // filter on, so they only survive a pure --grep.
+            return !self.is_structural()
+                && self.grep.as_ref().is_none_or(|grep| grep.is_match(line));
+        };
+
+        if let Some(service) = &self.service {
+            if record.service.as_deref().map(normalize_service).as_deref() != Some(service) {
+                return false;
+            }
+        }
+        if let Some(min_level) = self.min_level {
+            if record.level.is_none_or(|level| level < min_level) {
+                return false;
+            }
+        }
+        if let Some(since) = self.since {
+            if record.timestamp.is_none_or(|ts| ts < since) {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if record.timestamp.is_none_or(|ts| ts > until) {
+                return false;
+            }
+        }
+        if let Some(grep) = &self.grep {
+            if !grep.is_match(&record.message) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+async fn logs(config: &CliConfig, args: LogsArgs) -> Result<()> {
+    let filter = LogFilter::from_args(&args)?;
+    let printer = Printer {
+        pretty: args.pretty && !config.json,
+    };
+
+    let mut tail = LogTail::open(&args.file)
+        .with_context(|| format!("could not open log file {}", args.file.display()))?;
+
+    // Existing content, limited to the last --lines matches if requested
+    let mut backlog = Vec::new();
+    while let Some(line) = tail.next_line()? {
+        let record = LogRecord::parse(&line);
+        if filter.matches(&line, record.as_ref()) {
+            backlog.push((line, record));
+        }
+    }
+    let skip = args
+        .lines
+        .map_or(0, |lines| backlog.len().saturating_sub(lines));
+    for (line, record) in backlog.into_iter().skip(skip) {
+        printer.print(&line, record.as_ref());
+    }
+
+    if !args.follow {
+        return Ok(());
+    }
+
+    loop {
+        match tail.next_line()? {
+            Some(line) => {
+                let record = LogRecord::parse(&line);
+                if filter.matches(&line, record.as_ref()) {
+                    printer.print(&line, record.as_ref());
+                }
+            }
+            None => {
+                tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+                tail.check_rotation()?;
+            }
+        }
+    }
+}
+
+async fn metrics(config: &CliConfig, args: MetricsArgs) -> Result<()> {
+    let targets = args
+        .targets
+        .iter()
+        .map(|spec| Target::parse(spec))
+        .collect::<Result<Vec<_>>>()?;
+    let interval = Duration::from_secs(args.interval.max(1));
+    let client = MetricsClient::new(args.token, interval.max(Duration::from_secs(5)))?;
+
+    if args.live {
+        if config.json {
+            bail!("--live cannot be combined with --json");
+        }
+        return interactive::run(targets, client, interval).await;
+    }
+
+    // One-shot: totals since each server started
+    let mut reports = Vec::with_capacity(targets.len());
+    for target in &targets {
+        let snapshot = client.snapshot(target).await?;
+        let health = client.health(target).await;
+        reports.push((target, snapshot, health));
+    }
+
+    if config.json {
+        let reports: Vec<Value> = reports
+            .iter()
+            .map(|(target, snapshot, health)| {
+                json!({
+                    "target": target.name,
+                    "url": target.base_url,
+                    "requests": snapshot.requests,
+                    "errors": snapshot.errors,
+                    "error_ratio": snapshot.error_ratio(),
+                    "latency_seconds": {
+                        "p50": snapshot.latency_quantile(0.50),
+                        "p95": snapshot.latency_quantile(0.95),
+                        "p99": snapshot.latency_quantile(0.99),
+                    },
+                    "health": match health {
+                        Ok(health) => json!(health),
+                        Err(e) => json!({ "error": format!("{:#}", e) }),
+                    },
+                })
+            })
+            .collect();
+        println!("{}", json!({ "targets": reports }));
+        return Ok(());
+    }
+
+    for (target, snapshot, health) in reports {
+        println!("{} ({})", target.name.bold(), target.base_url);
+        println!(
+            "  requests {}   5xx {} ({})",
+            snapshot.requests,
+            snapshot.errors,
+            snapshot
+                .error_ratio()
+                .map_or_else(|| "-".to_string(), |ratio| format!("{:.2}%", ratio * 100.0))
+        );
+        println!(
+            "  latency  p50 {}   p95 {}   p99 {}",
+            format_latency(snapshot.latency_quantile(0.50)),
+            format_latency(snapshot.latency_quantile(0.95)),
+            format_latency(snapshot.latency_quantile(0.99))
+        );
+        match health {
+            Ok(health) => {
+                println!("  health   {}", colored_health(&health.status, 0));
+                for (name, check) in &health.checks {
+                    println!(
+                        "    {:<20} {} {:>6} ms  {}",
+                        name,
+                        colored_health(&check.status, 10),
+                        check.latency_ms,
+                        check.error.as_deref().unwrap_or("")
+                    );
+                }
+            }
+            Err(e) => println!("  health   {} {:#}", "unavailable:".red(), e),
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn colored_health(status: &str, width: usize) -> colored::ColoredString {
+    let padded = format!("{:<width$}", status, width = width);
+    match status {
+        "healthy" => padded.green(),
+        "degraded" => padded.yellow(),
+        _ => padded.red(),
+    }
+}
+
+struct Printer {
+    pretty: bool,
+}
+
+impl Printer {
+    fn print(&self, line: &str, record: Option<&LogRecord>) {
+        match record {
+            Some(record) if self.pretty => println!("{}", record.pretty()),
+            _ => println!("{}", line),
+        }
+    }
+}
+
+/// Reads complete lines from a log file and survives rotation.
+///
+/// Rotation is detected by the path pointing at a different file (rename
+/// and recreate) or the file shrinking below our offset (copy-truncate).
+/// Either way the remaining lines of the old file are drained first.
+struct LogTail {
+    path: PathBuf,
+    reader: BufReader<File>,
+    identity: FileIdentity,
+    offset: u64,
+    partial: String,
+}
+
+impl LogTail {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let identity = FileIdentity::of(&file.metadata()?);
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            identity,
+            offset: 0,
+            partial: String::new(),
+        })
+    }
+
+    /// Next complete line, or `None` at the current end of file
+    fn next_line(&mut self) -> Result<Option<String>> {
+        loop {
+            let mut chunk = String::new();
+            let read = self.reader.read_line(&mut chunk)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.offset += read as u64;
+            self.partial.push_str(&chunk);
+            if self.partial.ends_with('\n') {
+                let line = std::mem::take(&mut self.partial);
+                let line = line.trim_end_matches(['\n', '\r']);
+                if line.is_empty() {
+                    continue;
+                }
+                return Ok(Some(line.to_string()));
+            }
+            // A writer is mid-line; wait for the rest
+            return Ok(None);
+        }
+    }
+
+    fn check_rotation(&mut self) -> Result<()> {
+        let metadata = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Between the rename and the new file being created
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        if FileIdentity::of(&metadata) != self.identity {
+            let file = File::open(&self.path)?;
+            self.identity = FileIdentity::of(&file.metadata()?);
+            self.reader = BufReader::new(file);
+            self.offset = 0;
+            self.partial.clear();
+        } else if metadata.len() < self.offset {
+            self.reader.seek(SeekFrom::Start(0))?;
+            self.offset = 0;
+            self.partial.clear();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    device: u64,
+    inode: u64,
+}
+
+impl FileIdentity {
+    #[cfg(unix)]
+    fn of(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn of(_metadata: &Metadata) -> Self {
+        // Only truncation is detectable without inode numbers
+        Self {
+            device: 0,
+            inode: 0,
+        }
+    }
+}
+
+fn normalize_service(service: &str) -> String {
+    service.trim().to_ascii_lowercase().replace('_', "-")
+}
+
+/// RFC 3339 timestamp, or a duration ago such as `30s`, `15m`, `2h`, `1d`
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let Ok(amount) = amount.parse::<i64>() else {
+        bail!(
+            "invalid time '{}': use RFC 3339 or a duration like 15m",
+            value
+        );
+    };
+    let ago = match unit {
+        "s" => ChronoDuration::seconds(amount),
+        "m" => ChronoDuration::minutes(amount),
+        "h" => ChronoDuration::hours(amount),
+        "d" => ChronoDuration::days(amount),
+        _ => bail!(
+            "invalid time '{}': use RFC 3339 or a duration like 15m",
+            value
+        ),
+    };
+    Ok(Utc::now() - ago)
+}
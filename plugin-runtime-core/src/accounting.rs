@@ -0,0 +1,235 @@
+//! Plugin resource accounting
+//!
+//! Tracks what each plugin consumes across calls, whichever backend runs it,
+//! and checks each call against the plugin's quota so operators are warned
+//! before a hard limit trips.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Event type published when a plugin call comes close to its quota
+pub const QUOTA_WARNING_EVENT: &str = "plugin.quota.warning";
+
+/// Calls made to a plugin, labelled by `plugin` and `outcome`
+pub const PLUGIN_CALLS_METRIC: &str = "plugin_calls_total";
+/// CPU time consumed by a plugin, labelled by `plugin`
+pub const PLUGIN_CPU_SECONDS_METRIC: &str = "plugin_cpu_seconds_total";
+/// WASM fuel consumed by a plugin, labelled by `plugin`
+pub const PLUGIN_FUEL_METRIC: &str = "plugin_fuel_consumed_total";
+/// Current memory of a plugin, labelled by `plugin`
+pub const PLUGIN_MEMORY_METRIC: &str = "plugin_memory_bytes";
+/// Peak memory of a plugin, labelled by `plugin`
+pub const PLUGIN_PEAK_MEMORY_METRIC: &str = "plugin_memory_peak_bytes";
+/// Quota warnings, labelled by `plugin` and `resource`
+pub const PLUGIN_QUOTA_WARNINGS_METRIC: &str = "plugin_quota_warnings_total";
+
+/// Cumulative resource usage of a plugin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginStats {
+    /// Calls made
+    pub calls: u64,
+    /// Calls that returned an error
+    pub failed_calls: u64,
+    /// CPU time consumed (microseconds)
+    pub cpu_time_us: u64,
+    /// WASM fuel consumed; zero for native plugins
+    pub fuel_consumed: u64,
+    /// Memory held at the end of the last call (bytes)
+    pub current_memory_bytes: u64,
+    /// Highest memory seen (bytes)
+    pub peak_memory_bytes: u64,
+}
+
+/// Source of a plugin's resource usage
+///
+/// Implemented by the WASM and native runtimes for the plugins they run, so
+/// the lifecycle manager accounts for both the same way.
+pub trait ResourceStats: Send + Sync {
+    /// Usage so far
+    fn stats(&self) -> PluginStats;
+}
+
+/// Usage counters a backend updates as a plugin runs
+#[derive(Debug, Default)]
+pub struct ResourceMeter {
+    calls: AtomicU64,
+    failed_calls: AtomicU64,
+    cpu_time_us: AtomicU64,
+    fuel_consumed: AtomicU64,
+    current_memory_bytes: AtomicU64,
+    peak_memory_bytes: AtomicU64,
+}
+
+impl ResourceMeter {
+    /// Create a meter with nothing recorded
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a finished call
+    pub fn record_call(&self, cpu_time: Duration, fuel_consumed: u64, succeeded: bool) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if !succeeded {
+            self.failed_calls.fetch_add(1, Ordering::Relaxed);
+        }
+        let micros = u64::try_from(cpu_time.as_micros()).unwrap_or(u64::MAX);
+        self.cpu_time_us.fetch_add(micros, Ordering::Relaxed);
+        self.fuel_consumed.fetch_add(fuel_consumed, Ordering::Relaxed);
+    }
+
+    /// Record the memory the plugin currently holds
+    pub fn record_memory(&self, bytes: u64) {
+        self.current_memory_bytes.store(bytes, Ordering::Relaxed);
+        self.peak_memory_bytes.fetch_max(bytes, Ordering::Relaxed);
+    }
+}
+
+impl ResourceStats for ResourceMeter {
+    fn stats(&self) -> PluginStats {
+        PluginStats {
+            calls: self.calls.load(Ordering::Relaxed),
+            failed_calls: self.failed_calls.load(Ordering::Relaxed),
+            cpu_time_us: self.cpu_time_us.load(Ordering::Relaxed),
+            fuel_consumed: self.fuel_consumed.load(Ordering::Relaxed),
+            current_memory_bytes: self.current_memory_bytes.load(Ordering::Relaxed),
+            peak_memory_bytes: self.peak_memory_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Resource a quota applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaResource {
+    /// CPU time of a single call
+    CpuTime,
+    /// WASM fuel of a single call
+    Fuel,
+    /// Memory held by the plugin
+    Memory,
+}
+
+impl QuotaResource {
+    /// Label used in metrics and events
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaResource::CpuTime => "cpu_time",
+            QuotaResource::Fuel => "fuel",
+            QuotaResource::Memory => "memory",
+        }
+    }
+}
+
+/// Per plugin limits that trigger a warning when approached
+///
+/// These mirror the hard limits enforced by the sandbox; the quota does not
+/// stop a call, it only reports one that came within `warn_threshold` of a
+/// limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceQuota {
+    /// CPU time allowed per call
+    pub max_cpu_time_per_call: Option<Duration>,
+    /// WASM fuel allowed per call
+    pub max_fuel_per_call: Option<u64>,
+    /// Memory allowed (bytes)
+    pub max_memory_bytes: Option<u64>,
+    /// Fraction of a limit at which to warn, e.g. 0.8 for 80%
+    pub warn_threshold: f64,
+}
+
+/// Usage of a resource that reached the warning threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaWarning {
+    /// Plugin ID
+    pub plugin_id: Uuid,
+    /// Plugin name
+    pub plugin_name: String,
+    /// Resource close to its limit
+    pub resource: QuotaResource,
+    /// Amount used (microseconds, fuel units or bytes)
+    pub used: u64,
+    /// Limit in the same unit
+    pub limit: u64,
+    /// `used` as a fraction of `limit`
+    pub ratio: f64,
+}
+
+impl ResourceQuota {
+    /// Quota mirroring the hard limits of a sandbox
+    pub fn from_limits(limits: &crate::sandbox::ResourceLimits) -> Self {
+        Self {
+            max_cpu_time_per_call: Some(limits.max_cpu_time),
+            max_fuel_per_call: None,
+            max_memory_bytes: Some(limits.max_memory as u64),
+            warn_threshold: 0.8,
+        }
+    }
+
+    /// Usage against each limit for the call that took usage from `before` to `after`
+    ///
+    /// Returns `(resource, used, limit)` for every configured limit.
+    pub fn usage(&self, before: &PluginStats, after: &PluginStats) -> Vec<(QuotaResource, u64, u64)> {
+        let mut usage = Vec::new();
+        if let Some(limit) = self.max_cpu_time_per_call {
+            let limit = u64::try_from(limit.as_micros()).unwrap_or(u64::MAX);
+            usage.push((QuotaResource::CpuTime, after.cpu_time_us.saturating_sub(before.cpu_time_us), limit));
+        }
+        if let Some(limit) = self.max_fuel_per_call {
+            usage.push((QuotaResource::Fuel, after.fuel_consumed.saturating_sub(before.fuel_consumed), limit));
+        }
+        if let Some(limit) = self.max_memory_bytes {
+            usage.push((QuotaResource::Memory, after.current_memory_bytes, limit));
+        }
+        usage
+    }
+
+    /// Whether `used` of `limit` is at or above the warning threshold
+    pub fn is_near_limit(&self, used: u64, limit: u64) -> bool {
+        limit > 0 && used as f64 >= limit as f64 * self.warn_threshold
+    }
+}
+
+impl Default for ResourceQuota {
+    fn default() -> Self {
+        Self::from_limits(&crate::sandbox::ResourceLimits::default())
+    }
+}
+
+/// Publish a plugin's usage change to the telemetry metrics
+pub(crate) fn record_metrics(plugin: &str, before: &PluginStats, after: &PluginStats) {
+    let metrics = telemetry::MetricsCollector::global();
+    let failed = after.failed_calls.saturating_sub(before.failed_calls);
+    let succeeded = after.calls.saturating_sub(before.calls).saturating_sub(failed);
+    if succeeded > 0 {
+        metrics
+            .counter(PLUGIN_CALLS_METRIC)
+            .with_label("plugin", plugin)
+            .with_label("outcome", "ok")
+            .increment_by(succeeded as f64);
+    }
+    if failed > 0 {
+        metrics
+            .counter(PLUGIN_CALLS_METRIC)
+            .with_label("plugin", plugin)
+            .with_label("outcome", "error")
+            .increment_by(failed as f64);
+    }
+    metrics
+        .counter(PLUGIN_CPU_SECONDS_METRIC)
+        .with_label("plugin", plugin)
+        .increment_by(after.cpu_time_us.saturating_sub(before.cpu_time_us) as f64 / 1_000_000.0);
+    metrics
+        .counter(PLUGIN_FUEL_METRIC)
+        .with_label("plugin", plugin)
+        .increment_by(after.fuel_consumed.saturating_sub(before.fuel_consumed) as f64);
+    metrics
+        .gauge(PLUGIN_MEMORY_METRIC)
+        .with_label("plugin", plugin)
+        .set(after.current_memory_bytes as f64);
+    metrics
+        .gauge(PLUGIN_PEAK_MEMORY_METRIC)
+        .with_label("plugin", plugin)
+        .set(after.peak_memory_bytes as f64);
+}
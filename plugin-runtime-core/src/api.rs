@@ -6,6 +6,7 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 use utoipa::ToSchema;
 
@@ -26,6 +27,13 @@ pub trait PluginApi: Send + Sync {
     
     /// Health check
     async fn health_check(&self) -> Result<HealthStatus, crate::error::PluginRuntimeError>;
+    
+    /// Resource usage reported by the backend running the plugin
+    ///
+    /// Plugins returning `None` are accounted by call time only.
+    fn resource_stats(&self) -> Option<Arc<dyn crate::accounting::ResourceStats>> {
+        None
+    }
 }
 
 /// Plugin information structure
@@ -50,10 +50,55 @@ pub enum PluginRuntimeError {
     #[error("Unsupported plugin type")]
     UnsupportedPluginType,
     
+    /// A declared dependency is neither loaded nor being loaded
+    #[error("Plugin '{plugin}' depends on plugin {dependency} ({requirement}), which is not available")]
+    MissingDependency {
+        /// Plugin declaring the dependency
+        plugin: String,
+        /// Missing plugin ID
+        dependency: Uuid,
+        /// Required version range
+        requirement: String,
+    },
+    
+    /// A dependency is available in a version outside the required range
+    #[error("Plugin '{plugin}' requires '{dependency}' {requirement}, but version {found} is available")]
+    IncompatibleDependency {
+        /// Plugin declaring the dependency
+        plugin: String,
+        /// Name of the dependency
+        dependency: String,
+        /// Required version range
+        requirement: String,
+        /// Version available
+        found: String,
+    },
+    
+    /// Plugins depend on each other in a cycle
+    #[error("Plugin dependency cycle: {}", .0.join(" -> "))]
+    DependencyCycle(Vec<String>),
+    
+    /// Loaded plugins depend on the plugin being unloaded
+    #[error("Plugin '{plugin}' is required by loaded plugins: {}", .dependents.join(", "))]
+    HasDependents {
+        /// Plugin being unloaded
+        plugin: String,
+        /// Names of the loaded plugins depending on it
+        dependents: Vec<String>,
+    },
+    
     /// Plugin timeout
     #[error("Plugin operation timed out: {0}")]
     Timeout(String),
     
+    /// Plugin code panicked; the panic was contained
+    #[error("Plugin panicked: {0}")]
+    Panicked(String),
+    
+    /// Plugin refused after failing repeatedly
+    #[error("Plugin quarantined: {0}")]
+    Quarantined(String),
+    
     /// Configuration error
     #[error("Configuration error: {0}")]
     ConfigurationError(String),
@@ -260,6 +305,28 @@ impl PluginRuntimeError {
                 recovery_actions: vec![RecoveryAction::UpdateSecurityPolicy],
                 retry_policy: None,
             },
+            Self::MissingDependency { .. }
+            | Self::IncompatibleDependency { .. }
+            | Self::DependencyCycle(_) => ErrorRecovery {
+                is_recoverable: false,
+                recovery_actions: vec![RecoveryAction::ReloadConfiguration],
+                retry_policy: None,
+            },
+            Self::HasDependents { .. } => ErrorRecovery {
+                is_recoverable: false,
+                recovery_actions: vec![],
+                retry_policy: None,
+            },
+            Self::Panicked(_) => ErrorRecovery {
+                is_recoverable: true,
+                recovery_actions: vec![RecoveryAction::RestartPlugin],
+                retry_policy: None,
+            },
+            Self::Quarantined(_) => ErrorRecovery {
+                is_recoverable: false,
+                recovery_actions: vec![RecoveryAction::RestartPlugin],
+                retry_policy: None,
+            },
             Self::Timeout(_) => ErrorRecovery {
                 is_recoverable: true,
                 recovery_actions: vec![RecoveryAction::RestartPlugin],
@@ -304,7 +371,13 @@ impl PluginRuntimeError {
             Self::InvalidModule(_) => ErrorCategory::Module,
             Self::InvalidManifest(_) => ErrorCategory::Configuration,
             Self::UnsupportedPluginType => ErrorCategory::Configuration,
+            Self::MissingDependency { .. }
+            | Self::IncompatibleDependency { .. }
+            | Self::DependencyCycle(_)
+            | Self::HasDependents { .. } => ErrorCategory::Dependency,
             Self::Timeout(_) => ErrorCategory::Timeout,
+            Self::Panicked(_) => ErrorCategory::Execution,
+            Self::Quarantined(_) => ErrorCategory::State,
             Self::ConfigurationError(_) => ErrorCategory::Configuration,
             Self::InitializationFailed(_) => ErrorCategory::Initialization,
             Self::CommunicationError(_) => ErrorCategory::Communication,
@@ -347,6 +420,8 @@ pub enum ErrorCategory {
     Serialization,
     /// I/O errors
     Io,
+    /// Plugin dependency errors
+    Dependency,
     /// Generic plugin errors
     Plugin,
 }
@@ -368,6 +443,7 @@ impl fmt::Display for ErrorCategory {
             Self::Communication => write!(f, "communication"),
             Self::Serialization => write!(f, "serialization"),
             Self::Io => write!(f, "io"),
+            Self::Dependency => write!(f, "dependency"),
             Self::Plugin => write!(f, "plugin"),
         }
     }
@@ -0,0 +1,314 @@
+//! Virtual filesystem for sandboxed WASM plugins
+//!
+//! A plugin's manifest maps guest directories onto host directories. Each
+//! mapping becomes a WASI preopen, and the guest sees nothing else: there is
+//! no ambient filesystem behind the preopens. Path resolution inside a
+//! preopen is confined to its host directory, so `..` sequences, absolute
+//! paths and symlinks cannot reach anything outside of it.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use wasmtime_wasi::preview2::preview1::{WasiPreview1Adapter, WasiPreview1View};
+use wasmtime_wasi::preview2::{DirPerms, FilePerms, Table, WasiCtx, WasiCtxBuilder, WasiView};
+
+/// Access a plugin has to a granted directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryAccess {
+    /// List and read files
+    ReadOnly,
+    /// Also create, write, rename and remove files and directories
+    ReadWrite,
+}
+
+/// Host directory made visible to a plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryGrant {
+    /// Absolute path the plugin sees the directory at, e.g. `/templates`
+    pub guest_path: String,
+    /// Directory on the host
+    pub host_path: PathBuf,
+    /// Access level
+    pub access: DirectoryAccess,
+}
+
+/// Directories visible to a plugin
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VirtualFileSystem {
+    grants: Vec<DirectoryGrant>,
+}
+
+impl VirtualFileSystem {
+    /// Filesystem with no directories
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Add a directory
+    pub fn grant(
+        mut self,
+        guest_path: impl Into<String>,
+        host_path: impl Into<PathBuf>,
+        access: DirectoryAccess,
+    ) -> Self {
+        self.grants.push(DirectoryGrant {
+            guest_path: guest_path.into(),
+            host_path: host_path.into(),
+            access,
+        });
+        self
+    }
+
+    /// Granted directories
+    pub fn grants(&self) -> &[DirectoryGrant] {
+        &self.grants
+    }
+
+    /// Whether the plugin sees no directories at all
+    pub fn is_empty(&self) -> bool {
+        self.grants.is_empty()
+    }
+
+    /// Check that guest paths are absolute, normalized and distinct, and that
+    /// host paths are existing directories
+    pub fn validate(&self) -> Result<(), crate::error::PluginRuntimeError> {
+        let mut guest_paths = HashSet::new();
+        for grant in &self.grants {
+            let guest = Path::new(&grant.guest_path);
+            let normalized = guest.is_absolute()
+                && guest
+                    .components()
+                    .all(|c| matches!(c, Component::RootDir | Component::Normal(_)));
+            if !normalized {
+                return Err(crate::error::PluginRuntimeError::InvalidManifest(format!(
+                    "Guest path '{}' must be absolute without '.' or '..' components",
+                    grant.guest_path
+                )));
+            }
+            if !guest_paths.insert(grant.guest_path.trim_end_matches('/')) {
+                return Err(crate::error::PluginRuntimeError::InvalidManifest(format!(
+                    "Guest path '{}' is granted more than once",
+                    grant.guest_path
+                )));
+            }
+            if !grant.host_path.is_dir() {
+                return Err(crate::error::PluginRuntimeError::ConfigurationError(format!(
+                    "Host directory '{}' for '{}' does not exist",
+                    grant.host_path.display(),
+                    grant.guest_path
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// WASI context exposing only the granted directories
+    ///
+    /// Stdio, environment, arguments and network are left empty.
+    pub fn wasi_ctx(&self) -> Result<WasiCtx, crate::error::PluginRuntimeError> {
+        self.validate()?;
+        let mut builder = WasiCtxBuilder::new();
+        for grant in &self.grants {
+            let dir = cap_std::fs::Dir::open_ambient_dir(&grant.host_path, cap_std::ambient_authority())
+                .map_err(|e| {
+                    crate::error::PluginRuntimeError::ConfigurationError(format!(
+                        "Cannot open host directory '{}': {}",
+                        grant.host_path.display(),
+                        e
+                    ))
+                })?;
+            let (dir_perms, file_perms) = match grant.access {
+                DirectoryAccess::ReadOnly => (DirPerms::READ, FilePerms::READ),
+                DirectoryAccess::ReadWrite => (DirPerms::all(), FilePerms::all()),
+            };
+            builder.preopened_dir(dir, dir_perms, file_perms, &grant.guest_path);
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Store data of a sandboxed WASM instance
+pub struct WasiSandboxState {
+    table: Table,
+    ctx: WasiCtx,
+    adapter: WasiPreview1Adapter,
+}
+
+impl WasiSandboxState {
+    /// State for an instance seeing `filesystem`
+    pub fn new(filesystem: &VirtualFileSystem) -> Result<Self, crate::error::PluginRuntimeError> {
+        Ok(Self {
+            table: Table::new(),
+            ctx: filesystem.wasi_ctx()?,
+            adapter: WasiPreview1Adapter::new(),
+        })
+    }
+
+    /// Add the `wasi_snapshot_preview1` imports to `linker`
+    ///
+    /// The imports block on the host filesystem; call into instances from a
+    /// blocking thread or a multi-threaded Tokio runtime.
+    pub fn add_to_linker(linker: &mut wasmtime::Linker<Self>) -> Result<(), crate::error::PluginRuntimeError> {
+        wasmtime_wasi::preview2::preview1::add_to_linker_sync(linker)
+            .map_err(|e| crate::error::PluginRuntimeError::InitializationFailed(e.to_string()))
+    }
+}
+
+impl WasiView for WasiSandboxState {
+    fn table(&self) -> &Table {
+        &self.table
+    }
+
+    fn table_mut(&mut self) -> &mut Table {
+        &mut self.table
+    }
+
+    fn ctx(&self) -> &WasiCtx {
+        &self.ctx
+    }
+
+    fn ctx_mut(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+impl WasiPreview1View for WasiSandboxState {
+    fn adapter(&self) -> &WasiPreview1Adapter {
+        &self.adapter
+    }
+
+    fn adapter_mut(&mut self) -> &mut WasiPreview1Adapter {
+        &mut self.adapter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::{Engine, Instance, Linker, Module, Store};
+
+    /// Guest exporting `open(dir_fd, path_ptr, path_len, oflags, rights) -> errno`,
+    /// a thin wrapper around `path_open` that closes what it opened
+    const GUEST: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "path_open"
+            (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "open")
+            (param $dir i32) (param $ptr i32) (param $len i32) (param $oflags i32) (param $rights i64)
+            (result i32)
+            (local $errno i32)
+            (local.set $errno
+              (call $path_open (local.get $dir) (i32.const 1) (local.get $ptr) (local.get $len)
+                (local.get $oflags) (local.get $rights) (i64.const 0) (i32.const 0) (i32.const 0)))
+            (if (i32.eqz (local.get $errno))
+              (then (drop (call $fd_close (i32.load (i32.const 0))))))
+            (local.get $errno)))
+    "#;
+
+    const FD_READ: i64 = 1 << 1;
+    const FD_WRITE: i64 = 1 << 6;
+    const O_CREAT: i32 = 1;
+    /// First preopened directory
+    const PREOPEN: i32 = 3;
+
+    struct Guest {
+        store: Store<WasiSandboxState>,
+        instance: Instance,
+    }
+
+    impl Guest {
+        fn new(filesystem: &VirtualFileSystem) -> Self {
+            let engine = Engine::default();
+            let mut linker = Linker::new(&engine);
+            WasiSandboxState::add_to_linker(&mut linker).unwrap();
+            let module = Module::new(&engine, GUEST).unwrap();
+            let mut store = Store::new(&engine, WasiSandboxState::new(filesystem).unwrap());
+            let instance = linker.instantiate(&mut store, &module).unwrap();
+            Self { store, instance }
+        }
+
+        fn open(&mut self, path: &str, oflags: i32, rights: i64) -> i32 {
+            let memory = self.instance.get_memory(&mut self.store, "memory").unwrap();
+            memory.write(&mut self.store, 64, path.as_bytes()).unwrap();
+            self.instance
+                .get_typed_func::<(i32, i32, i32, i32, i64), i32>(&mut self.store, "open")
+                .unwrap()
+                .call(&mut self.store, (PREOPEN, 64, path.len() as i32, oflags, rights))
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn test_plugin_cannot_escape_granted_directories() {
+        let root = std::env::temp_dir().join(format!("plugin-fs-{}", uuid::Uuid::new_v4()));
+        let templates = root.join("plugin").join("templates");
+        std::fs::create_dir_all(templates.join("letters")).unwrap();
+        std::fs::write(templates.join("discharge.txt"), "template").unwrap();
+        std::fs::write(root.join("plugin").join("secret.txt"), "secret").unwrap();
+        std::fs::write(root.join("secret.txt"), "secret").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("secret.txt"), templates.join("link.txt")).unwrap();
+
+        let filesystem = VirtualFileSystem::empty().grant("/templates", &templates, DirectoryAccess::ReadOnly);
+        let mut guest = Guest::new(&filesystem);
+        assert_eq!(guest.open("discharge.txt", 0, FD_READ), 0);
+        assert_eq!(guest.open("letters/../discharge.txt", 0, FD_READ), 0);
+        for escape in [
+            "../secret.txt",
+            "../../secret.txt",
+            "letters/../../secret.txt",
+            "./letters/../../../secret.txt",
+            "/secret.txt",
+            "link.txt",
+        ] {
+            assert_ne!(guest.open(escape, 0, FD_READ), 0, "opened {}", escape);
+        }
+        // Read-only grants refuse writes and new files
+        assert_ne!(guest.open("discharge.txt", 0, FD_WRITE), 0);
+        assert_ne!(guest.open("new.txt", O_CREAT, FD_READ | FD_WRITE), 0);
+        assert!(!templates.join("new.txt").exists());
+
+        let writable = VirtualFileSystem::empty().grant("/tmp", &templates, DirectoryAccess::ReadWrite);
+        let mut guest = Guest::new(&writable);
+        assert_eq!(guest.open("new.txt", O_CREAT, FD_READ | FD_WRITE), 0);
+        assert!(templates.join("new.txt").exists());
+        assert_ne!(guest.open("../escaped.txt", O_CREAT, FD_READ | FD_WRITE), 0);
+        assert!(!root.join("plugin").join("escaped.txt").exists());
+
+        // Without grants there is no directory to open anything in
+        let mut guest = Guest::new(&VirtualFileSystem::empty());
+        assert_ne!(guest.open("discharge.txt", 0, FD_READ), 0);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_sandbox_capabilities_filter_grants() {
+        use crate::sandbox::{Capability, PluginSandbox, ResourceLimits};
+
+        let dir = std::env::temp_dir();
+        let grants = vec![
+            DirectoryGrant { guest_path: "/templates".into(), host_path: dir.clone(), access: DirectoryAccess::ReadOnly },
+            DirectoryGrant { guest_path: "/scratch".into(), host_path: dir.clone(), access: DirectoryAccess::ReadWrite },
+        ];
+
+        let none = PluginSandbox::new(ResourceLimits::default(), vec![]);
+        assert!(none.filesystem(&grants).is_empty());
+
+        let read_only = PluginSandbox::new(
+            ResourceLimits::default(),
+            vec![Capability::FileSystemRead(vec!["/".into()])],
+        );
+        let filesystem = read_only.filesystem(&grants);
+        assert_eq!(filesystem.grants().len(), 1);
+        assert_eq!(filesystem.grants()[0].guest_path, "/templates");
+
+        assert!(VirtualFileSystem::empty()
+            .grant("/templates/../etc", &dir, DirectoryAccess::ReadOnly)
+            .validate()
+            .is_err());
+    }
+}
@@ -5,11 +5,14 @@
 //! - Native dynamic library plugins for performance-critical extensions
 //! - Plugin lifecycle management (install, enable, disable, update, uninstall)
 //! - Resource isolation and security sandboxing
+//! - Virtual filesystem of allow-listed host directories for WASM plugins
 //! - Plugin API versioning and backward compatibility
+//! - Dependency-ordered loading with version ranges between plugins
 //! - Inter-plugin communication through event bus
 //! - Plugin marketplace and registry integration
 //! - Hot-plugging without service restart
 //! - Resource quotas and rate limiting
+//! - Per-plugin resource accounting with quota warnings
 //! 
 //! # Plugin Types
 //! 
@@ -74,10 +77,14 @@ pub mod security;
 pub mod wasm;
 pub mod native;
 pub mod error;
+pub mod accounting;
+pub mod filesystem;
 
 pub use runtime::*;
 pub use loader::*;
 pub use sandbox::*;
 pub use api::*;
 pub use lifecycle::*;
-pub use error::*;
\ No newline at end of file
+pub use error::*;
+pub use accounting::*;
+pub use filesystem::*;
\ No newline at end of file
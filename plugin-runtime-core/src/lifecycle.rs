@@ -3,18 +3,27 @@
 //! Manages the complete lifecycle of plugins from installation
 //! through execution to cleanup and removal.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::accounting::{
+    PluginStats, QuotaResource, QuotaWarning, ResourceMeter, ResourceQuota, ResourceStats,
+    PLUGIN_QUOTA_WARNINGS_METRIC, QUOTA_WARNING_EVENT,
+};
+
 /// Plugin lifecycle manager
 pub struct LifecycleManager {
     /// Active plugins registry
     plugins: Arc<RwLock<HashMap<Uuid, PluginEntry>>>,
     /// Lifecycle configuration
     config: LifecycleConfig,
+    /// Quota for newly installed plugins
+    default_quota: ResourceQuota,
+    /// Bus receiving quota warnings
+    event_bus: Option<events_bus::EventBus>,
 }
 
 /// Plugin lifecycle configuration
@@ -44,6 +53,14 @@ pub struct PluginEntry {
     pub last_accessed: DateTime<Utc>,
     /// Usage statistics
     pub usage_stats: UsageStatistics,
+    /// Resource usage, as reported by the plugin's backend
+    pub resources: Arc<dyn ResourceStats>,
+    /// Quota checked after each call
+    pub quota: ResourceQuota,
+    /// Meter recording call time for plugins whose backend reports no usage
+    own_meter: Option<Arc<ResourceMeter>>,
+    /// Resources that were near their limit after the last call
+    near_limit: HashSet<QuotaResource>,
 }
 
 /// Plugin lifecycle state
@@ -88,9 +105,23 @@ impl LifecycleManager {
         Self {
             plugins: Arc::new(RwLock::new(HashMap::new())),
             config,
+            default_quota: ResourceQuota::default(),
+            event_bus: None,
         }
     }
     
+    /// Set the quota of plugins installed from now on
+    pub fn with_default_quota(mut self, quota: ResourceQuota) -> Self {
+        self.default_quota = quota;
+        self
+    }
+    
+    /// Publish quota warnings on `bus` as `plugin.quota.warning` events
+    pub fn with_event_bus(mut self, bus: events_bus::EventBus) -> Self {
+        self.event_bus = Some(bus);
+        self
+    }
+    
     /// Install a new plugin
     pub async fn install_plugin(
         &self,
@@ -107,6 +138,13 @@ impl LifecycleManager {
         }
         
         let plugin_id = plugin_info.id;
+        let (resources, own_meter) = match plugin_instance.resource_stats() {
+            Some(stats) => (stats, None),
+            None => {
+                let meter = Arc::new(ResourceMeter::new());
+                (meter.clone() as Arc<dyn ResourceStats>, Some(meter))
+            }
+        };
         let entry = PluginEntry {
             metadata: plugin_info,
             state: PluginState::Installed,
@@ -114,6 +152,10 @@ impl LifecycleManager {
             installed_at: Utc::now(),
             last_accessed: Utc::now(),
             usage_stats: UsageStatistics::default(),
+            resources,
+            quota: self.default_quota.clone(),
+            own_meter,
+            near_limit: HashSet::new(),
         };
         
         plugins.insert(plugin_id, entry);
@@ -163,7 +205,7 @@ impl LifecycleManager {
         let start_time = std::time::Instant::now();
         
         // Update state to running
-        {
+        let usage_before = {
             let mut plugins = self.plugins.write().await;
             if let Some(entry) = plugins.get_mut(&plugin_id) {
                 if entry.state != PluginState::Ready {
@@ -173,10 +215,11 @@ impl LifecycleManager {
                 }
                 entry.state = PluginState::Running;
                 entry.last_accessed = Utc::now();
+                entry.resources.stats()
             } else {
                 return Err(crate::error::PluginRuntimeError::PluginNotFound(plugin_id));
             }
-        }
+        };
         
         // Execute plugin
         let result = {
@@ -193,7 +236,7 @@ impl LifecycleManager {
         };
         
         // Update state and statistics
-        {
+        let warnings = {
             let mut plugins = self.plugins.write().await;
             if let Some(entry) = plugins.get_mut(&plugin_id) {
                 let duration = start_time.elapsed();
@@ -212,12 +255,98 @@ impl LifecycleManager {
                         entry.state = PluginState::Error("Execution failed".to_string());
                     }
                 }
+                
+                if let Some(meter) = &entry.own_meter {
+                    meter.record_call(duration, 0, result.is_ok());
+                }
+                let usage_after = entry.resources.stats();
+                crate::accounting::record_metrics(&entry.metadata.name, &usage_before, &usage_after);
+                Self::check_quota(plugin_id, entry, &usage_before, &usage_after)
+            } else {
+                Vec::new()
             }
+        };
+        
+        for warning in warnings {
+            self.emit_quota_warning(warning);
         }
         
         result
     }
     
+    /// Warnings for resources that reached the warning threshold in this call
+    ///
+    /// A resource is reported when it first crosses the threshold and again
+    /// only after a call in which it fell back below it.
+    fn check_quota(
+        plugin_id: Uuid,
+        entry: &mut PluginEntry,
+        before: &PluginStats,
+        after: &PluginStats,
+    ) -> Vec<QuotaWarning> {
+        let mut warnings = Vec::new();
+        for (resource, used, limit) in entry.quota.usage(before, after) {
+            if !entry.quota.is_near_limit(used, limit) {
+                entry.near_limit.remove(&resource);
+            } else if entry.near_limit.insert(resource) {
+                warnings.push(QuotaWarning {
+                    plugin_id,
+                    plugin_name: entry.metadata.name.clone(),
+                    resource,
+                    used,
+                    limit,
+                    ratio: used as f64 / limit as f64,
+                });
+            }
+        }
+        warnings
+    }
+    
+    fn emit_quota_warning(&self, warning: QuotaWarning) {
+        tracing::warn!(
+            plugin_id = %warning.plugin_id,
+            plugin = %warning.plugin_name,
+            resource = warning.resource.as_str(),
+            used = warning.used,
+            limit = warning.limit,
+            "Plugin is approaching its resource quota"
+        );
+        telemetry::MetricsCollector::global()
+            .counter(PLUGIN_QUOTA_WARNINGS_METRIC)
+            .with_label("plugin", warning.plugin_name.clone())
+            .with_label("resource", warning.resource.as_str())
+            .increment();
+        if let Some(bus) = &self.event_bus {
+            match serde_json::to_value(&warning) {
+                Ok(data) => {
+                    bus.publish(events_bus::DomainEvent::new(QUOTA_WARNING_EVENT, data)
+                        .with_partition_key(warning.plugin_id.to_string()));
+                }
+                Err(e) => tracing::error!("Failed to serialize quota warning: {}", e),
+            }
+        }
+    }
+    
+    /// Resource usage of a plugin
+    pub async fn plugin_stats(&self, plugin_id: Uuid) -> Result<PluginStats, crate::error::PluginRuntimeError> {
+        let plugins = self.plugins.read().await;
+        plugins
+            .get(&plugin_id)
+            .map(|entry| entry.resources.stats())
+            .ok_or(crate::error::PluginRuntimeError::PluginNotFound(plugin_id))
+    }
+    
+    /// Replace the quota of an installed plugin
+    pub async fn set_plugin_quota(&self, plugin_id: Uuid, quota: ResourceQuota) -> Result<(), crate::error::PluginRuntimeError> {
+        let mut plugins = self.plugins.write().await;
+        let entry = plugins
+            .get_mut(&plugin_id)
+            .ok_or(crate::error::PluginRuntimeError::PluginNotFound(plugin_id))?;
+        entry.quota = quota;
+        entry.near_limit.clear();
+        Ok(())
+    }
+    
     /// Stop and unload a plugin
     pub async fn stop_plugin(&self, plugin_id: Uuid) -> Result<(), crate::error::PluginRuntimeError> {
         let mut plugins = self.plugins.write().await;
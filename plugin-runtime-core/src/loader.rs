@@ -2,15 +2,24 @@
 //! 
 //! Handles loading plugins from various sources including WASM modules
 //! and native shared libraries with security validation.
+//!
+//! Plugins declare the plugins they depend on in their manifest. The loader
+//! loads a set of plugins dependencies first and keeps track of what is
+//! loaded, so a plugin cannot be unloaded while loaded plugins still need it.
 
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::path::Path;
 use uuid::Uuid;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 
 /// Plugin loader for different plugin types
 pub struct PluginLoader {
     /// Loader configuration
     config: LoaderConfig,
+    /// Manifests of the loaded plugins
+    loaded: HashMap<Uuid, PluginManifest>,
 }
 
 /// Loader configuration
@@ -52,14 +61,47 @@ pub struct PluginManifest {
     pub entry_point: String,
     /// Required permissions
     pub permissions: Vec<String>,
-    /// Plugin dependencies
-    pub dependencies: Vec<String>,
+    /// Plugins that must be loaded before this one
+    #[serde(default)]
+    pub dependencies: Vec<PluginDependency>,
+    /// Host directories the plugin asks to see, if its sandbox allows
+    #[serde(default)]
+    pub filesystem: Vec<crate::filesystem::DirectoryGrant>,
+}
+
+/// Dependency of a plugin on another plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginDependency {
+    /// ID of the plugin depended on
+    pub id: Uuid,
+    /// Accepted versions as a semver range, e.g. `^1.2` or `>=1.0, <3`
+    #[serde(default = "any_version")]
+    pub version: String,
+}
+
+fn any_version() -> String {
+    "*".to_string()
+}
+
+/// What to do with the dependents of a plugin being unloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnloadMode {
+    /// Refuse to unload a plugin other loaded plugins depend on
+    Restrict,
+    /// Unload the plugins depending on it as well
+    ///
+    /// Callers should show the user [`PluginLoader::dependents`] and have
+    /// them confirm before unloading with this mode.
+    Cascade,
 }
 
 impl PluginLoader {
     /// Create a new plugin loader
     pub fn new(config: LoaderConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            loaded: HashMap::new(),
+        }
     }
     
     /// Load plugin from path
@@ -81,6 +123,222 @@ impl PluginLoader {
         
         Ok(())
     }
+    
+    /// Order in which to load `manifests`, dependencies first
+    ///
+    /// Every dependency must either be among `manifests` or already loaded,
+    /// in a version matching the declared range.
+    pub fn resolve_load_order<'a>(
+        &self,
+        manifests: &'a [PluginManifest],
+    ) -> Result<Vec<&'a PluginManifest>, crate::error::PluginRuntimeError> {
+        let mut batch = HashMap::new();
+        for manifest in manifests {
+            if self.loaded.contains_key(&manifest.id) {
+                return Err(crate::error::PluginRuntimeError::InvalidOperation(
+                    format!("Plugin '{}' is already loaded", manifest.name),
+                ));
+            }
+            if batch.insert(manifest.id, manifest).is_some() {
+                return Err(crate::error::PluginRuntimeError::InvalidManifest(
+                    format!("Plugin '{}' is listed more than once", manifest.name),
+                ));
+            }
+        }
+        
+        for manifest in manifests {
+            for dependency in &manifest.dependencies {
+                let target = batch
+                    .get(&dependency.id)
+                    .copied()
+                    .or_else(|| self.loaded.get(&dependency.id))
+                    .ok_or_else(|| crate::error::PluginRuntimeError::MissingDependency {
+                        plugin: manifest.name.clone(),
+                        dependency: dependency.id,
+                        requirement: dependency.version.clone(),
+                    })?;
+                check_version(manifest, dependency, target)?;
+            }
+        }
+        
+        let mut order = Vec::with_capacity(manifests.len());
+        let mut done = HashSet::new();
+        let mut path = Vec::new();
+        for manifest in manifests {
+            visit(manifest, &batch, &mut done, &mut path, &mut order)?;
+        }
+        Ok(order)
+    }
+    
+    /// Load `manifests` in dependency order with `load`
+    ///
+    /// Stops at the first plugin that fails to load. The plugins loaded
+    /// before it stay loaded and are listed by [`Self::loaded_plugins`].
+    pub async fn load_plugins<T, F, Fut>(
+        &mut self,
+        manifests: Vec<PluginManifest>,
+        mut load: F,
+    ) -> Result<Vec<T>, crate::error::PluginRuntimeError>
+    where
+        F: FnMut(&PluginManifest) -> Fut,
+        Fut: Future<Output = Result<T, crate::error::PluginRuntimeError>>,
+    {
+        let order: Vec<PluginManifest> = self
+            .resolve_load_order(&manifests)?
+            .into_iter()
+            .cloned()
+            .collect();
+        for manifest in &order {
+            self.validate_manifest(manifest)?;
+        }
+        
+        let mut instances = Vec::with_capacity(order.len());
+        for manifest in order {
+            instances.push(load(&manifest).await?);
+            tracing::info!(plugin = %manifest.name, version = %manifest.version, "Plugin loaded");
+            self.loaded.insert(manifest.id, manifest);
+        }
+        Ok(instances)
+    }
+    
+    /// Loaded plugins
+    pub fn loaded_plugins(&self) -> impl Iterator<Item = &PluginManifest> {
+        self.loaded.values()
+    }
+    
+    /// Loaded plugins that depend on `plugin_id`, directly or through other
+    /// plugins, in the order they would be unloaded
+    pub fn dependents(&self, plugin_id: Uuid) -> Vec<&PluginManifest> {
+        let mut order = self.unload_order(plugin_id);
+        order.retain(|manifest| manifest.id != plugin_id);
+        order
+    }
+    
+    /// Unload a plugin, returning the manifests of the plugins unloaded in
+    /// the order their instances should be shut down
+    ///
+    /// With [`UnloadMode::Restrict`] this fails if loaded plugins depend on
+    /// it; with [`UnloadMode::Cascade`] they are unloaded first.
+    pub fn unload(
+        &mut self,
+        plugin_id: Uuid,
+        mode: UnloadMode,
+    ) -> Result<Vec<PluginManifest>, crate::error::PluginRuntimeError> {
+        let target = self
+            .loaded
+            .get(&plugin_id)
+            .ok_or(crate::error::PluginRuntimeError::PluginNotFound(plugin_id))?;
+        let dependents = self.dependents(plugin_id);
+        if mode == UnloadMode::Restrict && !dependents.is_empty() {
+            return Err(crate::error::PluginRuntimeError::HasDependents {
+                plugin: target.name.clone(),
+                dependents: dependents.iter().map(|manifest| manifest.name.clone()).collect(),
+            });
+        }
+        
+        let ids: Vec<Uuid> = self.unload_order(plugin_id).iter().map(|manifest| manifest.id).collect();
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| self.loaded.remove(&id))
+            .collect())
+    }
+    
+    /// `plugin_id` and its loaded dependents, each after every plugin that
+    /// depends on it
+    fn unload_order(&self, plugin_id: Uuid) -> Vec<&PluginManifest> {
+        let mut dependents: HashMap<Uuid, Vec<&PluginManifest>> = HashMap::new();
+        for manifest in self.loaded.values() {
+            for dependency in &manifest.dependencies {
+                dependents.entry(dependency.id).or_default().push(manifest);
+            }
+        }
+        for list in dependents.values_mut() {
+            list.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        }
+        
+        fn walk<'a>(
+            id: Uuid,
+            loaded: &'a HashMap<Uuid, PluginManifest>,
+            dependents: &HashMap<Uuid, Vec<&'a PluginManifest>>,
+            seen: &mut HashSet<Uuid>,
+            order: &mut Vec<&'a PluginManifest>,
+        ) {
+            if !seen.insert(id) {
+                return;
+            }
+            for dependent in dependents.get(&id).into_iter().flatten() {
+                walk(dependent.id, loaded, dependents, seen, order);
+            }
+            if let Some(manifest) = loaded.get(&id) {
+                order.push(manifest);
+            }
+        }
+        
+        let mut order = Vec::new();
+        walk(plugin_id, &self.loaded, &dependents, &mut HashSet::new(), &mut order);
+        order
+    }
+}
+
+/// Check that `target` satisfies the version range `manifest` declares for it
+fn check_version(
+    manifest: &PluginManifest,
+    dependency: &PluginDependency,
+    target: &PluginManifest,
+) -> Result<(), crate::error::PluginRuntimeError> {
+    let requirement = VersionReq::parse(&dependency.version).map_err(|e| {
+        crate::error::PluginRuntimeError::InvalidManifest(format!(
+            "Plugin '{}' has an invalid version range '{}' for '{}': {}",
+            manifest.name, dependency.version, target.name, e
+        ))
+    })?;
+    let version = Version::parse(&target.version).map_err(|e| {
+        crate::error::PluginRuntimeError::InvalidManifest(format!(
+            "Plugin '{}' has an invalid version '{}': {}",
+            target.name, target.version, e
+        ))
+    })?;
+    if !requirement.matches(&version) {
+        return Err(crate::error::PluginRuntimeError::IncompatibleDependency {
+            plugin: manifest.name.clone(),
+            dependency: target.name.clone(),
+            requirement: dependency.version.clone(),
+            found: target.version.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Depth-first visit appending `manifest` to `order` after its dependencies
+/// in the batch; `path` holds the plugins being visited, to report a cycle
+fn visit<'a>(
+    manifest: &'a PluginManifest,
+    batch: &HashMap<Uuid, &'a PluginManifest>,
+    done: &mut HashSet<Uuid>,
+    path: &mut Vec<&'a PluginManifest>,
+    order: &mut Vec<&'a PluginManifest>,
+) -> Result<(), crate::error::PluginRuntimeError> {
+    if done.contains(&manifest.id) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|visiting| visiting.id == manifest.id) {
+        let mut cycle: Vec<String> = path[start..].iter().map(|m| m.name.clone()).collect();
+        cycle.push(manifest.name.clone());
+        return Err(crate::error::PluginRuntimeError::DependencyCycle(cycle));
+    }
+    
+    path.push(manifest);
+    for dependency in &manifest.dependencies {
+        // Dependencies outside the batch are already loaded
+        if let Some(target) = batch.get(&dependency.id) {
+            visit(target, batch, done, path, order)?;
+        }
+    }
+    path.pop();
+    
+    done.insert(manifest.id);
+    order.push(manifest);
+    Ok(())
 }
 
 impl Default for LoaderConfig {
@@ -91,4 +349,98 @@ impl Default for LoaderConfig {
             allowed_types: vec![PluginType::Wasm, PluginType::Native],
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PluginRuntimeError;
+
+    fn manifest(name: &str, version: &str, dependencies: &[(&PluginManifest, &str)]) -> PluginManifest {
+        PluginManifest {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            version: version.to_string(),
+            description: String::new(),
+            plugin_type: PluginType::Wasm,
+            entry_point: format!("{}.wasm", name),
+            permissions: vec![],
+            dependencies: dependencies
+                .iter()
+                .map(|(target, range)| PluginDependency {
+                    id: target.id,
+                    version: range.to_string(),
+                })
+                .collect(),
+            filesystem: vec![],
+        }
+    }
+
+    fn names(manifests: &[&PluginManifest]) -> Vec<String> {
+        manifests.iter().map(|m| m.name.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_dependency_graph() {
+        // ui -> connector -> storage, audit -> storage
+        let storage = manifest("storage", "2.1.0", &[]);
+        let connector = manifest("connector", "1.4.0", &[(&storage, "^2")]);
+        let ui = manifest("ui", "0.3.0", &[(&connector, ">=1.2, <2")]);
+        let audit = manifest("audit", "1.0.0", &[(&storage, "*")]);
+        let (storage_id, ui_id) = (storage.id, ui.id);
+
+        let mut loader = PluginLoader::new(LoaderConfig::default());
+        let batch = vec![ui.clone(), audit.clone(), connector.clone(), storage.clone()];
+        let order = loader.resolve_load_order(&batch).unwrap();
+        assert_eq!(names(&order), ["storage", "connector", "ui", "audit"]);
+
+        let loaded = loader
+            .load_plugins(batch, |m| {
+                let name = m.name.clone();
+                async move { Ok::<_, PluginRuntimeError>(name) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(loaded, ["storage", "connector", "ui", "audit"]);
+
+        // A cycle anywhere in the batch is reported with its members
+        let mut a = manifest("a", "1.0.0", &[]);
+        let mut b = manifest("b", "1.0.0", &[]);
+        let c = manifest("c", "1.0.0", &[(&a, "*")]);
+        b.dependencies.push(PluginDependency { id: c.id, version: "*".to_string() });
+        a.dependencies.push(PluginDependency { id: b.id, version: "*".to_string() });
+        let tail = manifest("tail", "1.0.0", &[(&a, "*"), (&storage, "^2")]);
+        match loader.resolve_load_order(&[tail, a, b, c]) {
+            Err(PluginRuntimeError::DependencyCycle(cycle)) => assert_eq!(cycle, ["a", "b", "c", "a"]),
+            other => panic!("expected a cycle, got {:?}", other.map(|o| names(&o))),
+        }
+
+        // Dependencies must exist in a matching version
+        let ghost = manifest("ghost", "1.0.0", &[]);
+        let orphan = manifest("orphan", "1.0.0", &[(&ghost, "^1")]);
+        assert!(matches!(
+            loader.resolve_load_order(&[orphan]),
+            Err(PluginRuntimeError::MissingDependency { dependency, .. }) if dependency == ghost.id
+        ));
+        let legacy = manifest("legacy", "1.0.0", &[(&connector, "^2")]);
+        assert!(matches!(
+            loader.resolve_load_order(&[legacy]),
+            Err(PluginRuntimeError::IncompatibleDependency { found, .. }) if found == "1.4.0"
+        ));
+
+        // Unloading storage is refused while anything needs it, or takes its
+        // dependents down first when cascading
+        match loader.unload(storage_id, UnloadMode::Restrict) {
+            Err(PluginRuntimeError::HasDependents { dependents, .. }) => {
+                assert_eq!(dependents, ["audit", "ui", "connector"])
+            }
+            other => panic!("expected unload to be refused, got {:?}", other),
+        }
+        assert_eq!(loader.unload(ui_id, UnloadMode::Restrict).unwrap().len(), 1);
+        let unloaded = loader.unload(storage_id, UnloadMode::Cascade).unwrap();
+        assert_eq!(
+            unloaded.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+            ["audit", "connector", "storage"]
+        );
+        assert_eq!(loader.loaded_plugins().count(), 0);
+    }
+}
@@ -2,12 +2,40 @@
 //! 
 //! Provides support for loading and executing native shared library plugins
 //! with dynamic loading and security controls.
+//!
+//! # Isolation
+//!
+//! Native plugins run inside the host process. Each library gets a worker
+//! thread of its own and every call runs there:
+//!
+//! - A Rust panic raised on the worker is caught at the call boundary and
+//!   returned as [`PluginRuntimeError::Panicked`](crate::error::PluginRuntimeError::Panicked).
+//!   After `max_panics` panics the library is quarantined and refuses calls
+//!   until it is unloaded and loaded again.
+//! - A call that overruns `call_timeout` is abandoned and the library is
+//!   marked unhealthy. A thread cannot be killed safely, so the call keeps
+//!   running, keeps its worker busy and keeps the library mapped until it
+//!   returns, if it ever does.
+//!
+//! This is containment, not isolation. Native code shares the host's memory
+//! and address space, so a plugin that calls `abort`, dereferences a bad
+//! pointer, overflows its stack or panics through an `extern "C"` boundary
+//! (which aborts) still takes the whole host down, and nothing limits the
+//! memory or CPU it uses. WASM plugins are isolated from all of this by the
+//! runtime and should be preferred; choose native only for trusted, signed
+//! code that needs native performance or system libraries.
 
 use std::collections::HashMap;
 use std::ffi::CString;
+use std::panic::AssertUnwindSafe;
 use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 use uuid::Uuid;
 
+use crate::accounting::{ResourceMeter, ResourceStats};
+
 /// Native plugin runtime
 pub struct NativeRuntime {
     /// Runtime ID
@@ -31,6 +59,24 @@ pub struct NativeConfig {
     symbol_prefix_filter: Option<String>,
     /// Enable library isolation
     library_isolation: bool,
+    /// Time a call may take before it is abandoned
+    call_timeout: Duration,
+    /// Panics after which a library is quarantined
+    max_panics: u32,
+}
+
+impl NativeConfig {
+    /// Set the time a call may take before it is abandoned
+    pub fn with_call_timeout(mut self, call_timeout: Duration) -> Self {
+        self.call_timeout = call_timeout;
+        self
+    }
+    
+    /// Set the number of panics after which a library is quarantined
+    pub fn with_max_panics(mut self, max_panics: u32) -> Self {
+        self.max_panics = max_panics.max(1);
+        self
+    }
 }
 
 /// Native shared library wrapper
@@ -43,8 +89,26 @@ pub struct NativeLibrary {
     pub path: String,
     /// Loaded symbols
     pub symbols: HashMap<String, NativeSymbol>,
-    /// Library handle (platform specific)
-    pub handle: Option<libloading::Library>,
+    /// Library handle (platform specific), shared with calls in flight
+    pub handle: Option<Arc<libloading::Library>>,
+    /// Resources consumed by calls into the library
+    pub meter: Arc<ResourceMeter>,
+    /// Thread running the calls into the library
+    worker: NativeWorker,
+}
+
+/// Health of a loaded native library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NativeHealth {
+    /// Calls are accepted
+    Healthy,
+    /// A call was abandoned and may still be running; calls are refused
+    Unhealthy(String),
+    /// The library panicked too often; calls are refused
+    Quarantined {
+        /// Panics caught
+        panics: u32,
+    },
 }
 
 /// Native library metadata
@@ -244,13 +308,16 @@ impl NativeRuntime {
         // Extract symbols
         let symbols = self.extract_symbols(&library_handle)?;
         
+        let worker = NativeWorker::spawn(&metadata.name)?;
         let library_id = Uuid::new_v4();
         let native_library = NativeLibrary {
             id: library_id,
             metadata,
             path: path_str,
             symbols,
-            handle: Some(library_handle),
+            handle: Some(Arc::new(library_handle)),
+            meter: Arc::new(ResourceMeter::new()),
+            worker,
         };
         
         self.libraries.insert(library_id, native_library);
@@ -280,6 +347,11 @@ impl NativeRuntime {
             ));
         }
         
+        let handle = library.handle.clone()
+            .ok_or_else(|| crate::error::PluginRuntimeError::InvalidState(
+                "Library not loaded".to_string()
+            ))?;
+        
         // Create call context
         let context = NativeCallContext {
             id: Uuid::new_v4(),
@@ -289,48 +361,34 @@ impl NativeRuntime {
             start_time: std::time::Instant::now(),
         };
         
-        // Execute the function call
-        self.execute_native_call(context, symbol).await
+        // Execute the function call on the library's worker thread
+        let start_time = context.start_time;
+        let result = library.worker
+            .run(
+                &library.metadata.name,
+                self.config.call_timeout,
+                self.config.max_panics,
+                move || execute_native_call(&handle, context),
+            )
+            .await;
+        library.meter.record_call(start_time.elapsed(), 0, result.is_ok());
+        result
     }
     
-    /// Execute native function call with safety checks
-    async fn execute_native_call(
-        &self,
-        context: NativeCallContext,
-        _symbol: &NativeSymbol,
-    ) -> Result<NativeValue, crate::error::PluginRuntimeError> {
-        // Get library handle
-        let library = self.libraries.get(&context.library_id)
-            .ok_or_else(|| crate::error::PluginRuntimeError::PluginNotFound(context.library_id))?;
-        
-        let handle = library.handle.as_ref()
-            .ok_or_else(|| crate::error::PluginRuntimeError::InvalidState(
-                "Library not loaded".to_string()
-            ))?;
-        
-        // For safety, we'll implement a basic function call framework
-        // In a real implementation, this would use FFI with proper type marshaling
-        
-        // Get symbol from library
-        let symbol_result: Result<libloading::Symbol<unsafe extern "C" fn()>, _> = unsafe {
-            handle.get(context.function_name.as_bytes())
-        };
-        
-        match symbol_result {
-            Ok(_symbol) => {
-                // In a real implementation, we would:
-                // 1. Marshal parameters according to calling convention
-                // 2. Set up stack and registers
-                // 3. Call the function with proper exception handling
-                // 4. Marshal return value back to NativeValue
-                
-                // For now, return a placeholder
-                Ok(NativeValue::I32(0))
-            }
-            Err(e) => Err(crate::error::PluginRuntimeError::ExecutionFailed(
-                format!("Symbol lookup failed: {}", e)
-            ))
-        }
+    /// Health of a loaded library
+    pub fn library_health(&self, library_id: Uuid) -> Result<NativeHealth, crate::error::PluginRuntimeError> {
+        let library = self.libraries.get(&library_id)
+            .ok_or_else(|| crate::error::PluginRuntimeError::PluginNotFound(library_id))?;
+        Ok(library.worker.health())
+    }
+    
+    /// Resource usage of a loaded library
+    ///
+    /// Native code shares the host's heap, so only call time is measured.
+    pub fn library_stats(&self, library_id: Uuid) -> Option<Arc<dyn ResourceStats>> {
+        self.libraries
+            .get(&library_id)
+            .map(|library| library.meter.clone() as Arc<dyn ResourceStats>)
     }
     
     /// Validate library path against security policy
@@ -389,7 +447,8 @@ impl NativeRuntime {
         let library = self.libraries.remove(&library_id)
             .ok_or_else(|| crate::error::PluginRuntimeError::PluginNotFound(library_id))?;
         
-        // Drop the library handle to unload it
+        // Drop the library handle to unload it; the library stays mapped
+        // until an abandoned call still running on its worker returns
         drop(library.handle);
         
         Ok(())
@@ -411,6 +470,143 @@ impl NativeRuntime {
     }
 }
 
+/// Execute native function call with safety checks
+fn execute_native_call(
+    handle: &libloading::Library,
+    context: NativeCallContext,
+) -> Result<NativeValue, crate::error::PluginRuntimeError> {
+    // For safety, we'll implement a basic function call framework
+    // In a real implementation, this would use FFI with proper type marshaling
+    
+    // Get symbol from library
+    let symbol_result: Result<libloading::Symbol<unsafe extern "C" fn()>, _> = unsafe {
+        handle.get(context.function_name.as_bytes())
+    };
+    
+    match symbol_result {
+        Ok(_symbol) => {
+            // In a real implementation, we would:
+            // 1. Marshal parameters according to calling convention
+            // 2. Set up stack and registers
+            // 3. Call the function with proper exception handling
+            // 4. Marshal return value back to NativeValue
+            
+            // For now, return a placeholder
+            Ok(NativeValue::I32(0))
+        }
+        Err(e) => Err(crate::error::PluginRuntimeError::ExecutionFailed(
+            format!("Symbol lookup failed: {}", e)
+        ))
+    }
+}
+
+type NativeJob = Box<dyn FnOnce() + Send>;
+
+/// Thread running the calls into one library, one at a time
+struct NativeWorker {
+    /// Calls waiting for the thread; dropping it stops the thread once the
+    /// current call returns
+    jobs: mpsc::Sender<NativeJob>,
+    health: Mutex<NativeHealth>,
+    panics: AtomicU32,
+}
+
+impl NativeWorker {
+    fn spawn(library_name: &str) -> Result<Self, crate::error::PluginRuntimeError> {
+        let (jobs, queue) = mpsc::channel::<NativeJob>();
+        std::thread::Builder::new()
+            .name(format!("native-plugin-{}", library_name))
+            .spawn(move || {
+                for job in queue {
+                    job();
+                }
+            })
+            .map_err(|e| crate::error::PluginRuntimeError::LoadingFailed(
+                format!("Failed to start worker thread: {}", e)
+            ))?;
+        Ok(Self {
+            jobs,
+            health: Mutex::new(NativeHealth::Healthy),
+            panics: AtomicU32::new(0),
+        })
+    }
+    
+    fn health(&self) -> NativeHealth {
+        self.health.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+    
+    fn set_health(&self, health: NativeHealth) {
+        *self.health.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = health;
+    }
+    
+    /// Run `call` on the worker, containing its panics and giving up on it
+    /// after `timeout`
+    async fn run<T, F>(
+        &self,
+        library_name: &str,
+        timeout: Duration,
+        max_panics: u32,
+        call: F,
+    ) -> Result<T, crate::error::PluginRuntimeError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, crate::error::PluginRuntimeError> + Send + 'static,
+    {
+        match self.health() {
+            NativeHealth::Healthy => {}
+            NativeHealth::Unhealthy(reason) => {
+                return Err(crate::error::PluginRuntimeError::InvalidState(
+                    format!("Native library '{}' is unhealthy: {}", library_name, reason)
+                ));
+            }
+            NativeHealth::Quarantined { panics } => {
+                return Err(crate::error::PluginRuntimeError::Quarantined(
+                    format!("native library '{}' panicked {} times", library_name, panics)
+                ));
+            }
+        }
+        
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let job: NativeJob = Box::new(move || {
+            let _ = sender.send(std::panic::catch_unwind(AssertUnwindSafe(call)));
+        });
+        self.jobs.send(job).map_err(|_| crate::error::PluginRuntimeError::ExecutionFailed(
+            "Native worker thread has stopped".to_string()
+        ))?;
+        
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(Ok(result))) => result,
+            Ok(Ok(Err(payload))) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                let panics = self.panics.fetch_add(1, Ordering::Relaxed) + 1;
+                if panics >= max_panics {
+                    tracing::error!(library = %library_name, panics, "Native library quarantined after repeated panics");
+                    self.set_health(NativeHealth::Quarantined { panics });
+                } else {
+                    tracing::warn!(library = %library_name, panics, panic = %message, "Native library call panicked");
+                }
+                Err(crate::error::PluginRuntimeError::Panicked(message))
+            }
+            Ok(Err(_)) => Err(crate::error::PluginRuntimeError::ExecutionFailed(
+                "Native worker thread stopped during the call".to_string()
+            )),
+            Err(_) => {
+                tracing::error!(library = %library_name, timeout = ?timeout, "Native library call abandoned after timeout");
+                self.set_health(NativeHealth::Unhealthy(
+                    format!("a call was abandoned after {:?} and may still be running", timeout)
+                ));
+                Err(crate::error::PluginRuntimeError::Timeout(
+                    format!("native library '{}' did not return within {:?}", library_name, timeout)
+                ))
+            }
+        }
+    }
+}
+
 impl Default for NativeConfig {
     fn default() -> Self {
         Self {
@@ -419,6 +615,51 @@ impl Default for NativeConfig {
             verify_signatures: true,
             symbol_prefix_filter: Some("plugin_".to_string()),
             library_isolation: true,
+            call_timeout: Duration::from_secs(30),
+            max_panics: 3,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PluginRuntimeError;
+
+    #[tokio::test]
+    async fn test_worker_contains_panics_and_quarantines() {
+        let worker = NativeWorker::spawn("flaky").unwrap();
+        let timeout = Duration::from_secs(5);
+
+        let result = worker.run("flaky", timeout, 2, || Ok(7)).await;
+        assert_eq!(result.unwrap(), 7);
+
+        let result = worker.run::<(), _>("flaky", timeout, 2, || panic!("bad input")).await;
+        assert!(matches!(result, Err(PluginRuntimeError::Panicked(message)) if message == "bad input"));
+        assert_eq!(worker.health(), NativeHealth::Healthy);
+
+        let result = worker.run::<(), _>("flaky", timeout, 2, || panic!("bad input")).await;
+        assert!(matches!(result, Err(PluginRuntimeError::Panicked(_))));
+        assert_eq!(worker.health(), NativeHealth::Quarantined { panics: 2 });
+
+        let result = worker.run("flaky", timeout, 2, || Ok(7)).await;
+        assert!(matches!(result, Err(PluginRuntimeError::Quarantined(_))));
+    }
+
+    #[tokio::test]
+    async fn test_worker_abandons_call_after_timeout() {
+        let worker = NativeWorker::spawn("slow").unwrap();
+
+        let result = worker
+            .run("slow", Duration::from_millis(20), 3, || {
+                std::thread::sleep(Duration::from_millis(200));
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(PluginRuntimeError::Timeout(_))));
+        assert!(matches!(worker.health(), NativeHealth::Unhealthy(_)));
+
+        let result = worker.run("slow", Duration::from_secs(5), 3, || Ok(())).await;
+        assert!(matches!(result, Err(PluginRuntimeError::InvalidState(_))));
+    }
+}
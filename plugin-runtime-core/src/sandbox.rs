@@ -4,8 +4,11 @@
 //! unauthorized access to system resources.
 
 use std::collections::HashMap;
+use std::path::Path;
 use std::time::{Duration, Instant};
 
+use crate::filesystem::{DirectoryAccess, DirectoryGrant, VirtualFileSystem};
+
 /// Plugin sandbox for secure execution
 pub struct PluginSandbox {
     /// Sandbox ID
@@ -34,9 +37,9 @@ pub struct ResourceLimits {
 /// Sandbox capabilities
 #[derive(Debug, Clone)]
 pub enum Capability {
-    /// File system read access
+    /// File system read access to the listed guest directories
     FileSystemRead(Vec<String>),
-    /// File system write access  
+    /// File system read and write access to the listed guest directories
     FileSystemWrite(Vec<String>),
     /// Network access
     NetworkAccess(Vec<String>),
@@ -92,6 +95,37 @@ impl PluginSandbox {
         Ok(result)
     }
     
+    /// Filesystem for a plugin requesting `grants` in its manifest
+    ///
+    /// Only grants covered by the sandbox's filesystem capabilities are kept,
+    /// so a sandbox without any gets an empty filesystem.
+    pub fn filesystem(&self, grants: &[DirectoryGrant]) -> VirtualFileSystem {
+        let mut filesystem = VirtualFileSystem::empty();
+        for grant in grants {
+            if self.allows_directory(&grant.guest_path, grant.access) {
+                filesystem = filesystem.grant(grant.guest_path.clone(), grant.host_path.clone(), grant.access);
+            } else {
+                tracing::warn!(
+                    sandbox_id = %self.id,
+                    guest_path = %grant.guest_path,
+                    access = ?grant.access,
+                    "Directory grant not covered by sandbox capabilities, leaving it out"
+                );
+            }
+        }
+        filesystem
+    }
+    
+    /// Whether a capability covers `access` to `guest_path`
+    fn allows_directory(&self, guest_path: &str, access: DirectoryAccess) -> bool {
+        let covers = |paths: &Vec<String>| paths.iter().any(|allowed| Path::new(guest_path).starts_with(allowed));
+        self.capabilities.iter().any(|capability| match (capability, access) {
+            (Capability::FileSystemWrite(paths), _) => covers(paths),
+            (Capability::FileSystemRead(paths), DirectoryAccess::ReadOnly) => covers(paths),
+            _ => false,
+        })
+    }
+    
     /// Check if resource limits are exceeded
     fn check_limits(&self) -> Result<(), crate::error::PluginRuntimeError> {
         if self.context.start_time.elapsed() > self.limits.max_cpu_time {
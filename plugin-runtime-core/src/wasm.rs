@@ -7,6 +7,11 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::accounting::{ResourceMeter, ResourceStats};
+use crate::filesystem::{VirtualFileSystem, WasiSandboxState};
+use crate::loader::{PluginManifest, PluginType};
+use crate::sandbox::PluginSandbox;
+
 /// WebAssembly plugin runtime
 pub struct WasmRuntime {
     /// Runtime ID
@@ -44,6 +49,10 @@ pub struct WasmModule {
     pub exports: Vec<WasmExport>,
     /// Module imports
     pub imports: Vec<WasmImport>,
+    /// Resources consumed by calls into the module
+    pub meter: Arc<ResourceMeter>,
+    /// Directories instances of the module see
+    pub filesystem: VirtualFileSystem,
 }
 
 /// WASM module metadata
@@ -183,10 +192,46 @@ impl WasmRuntime {
     }
     
     /// Load a WASM module from bytecode
+    ///
+    /// The module sees no directories; use [`WasmRuntime::load_plugin_module`]
+    /// to give it the ones its manifest asks for.
     pub async fn load_module(
         &mut self,
         bytecode: Vec<u8>,
         metadata: WasmModuleMetadata,
+    ) -> Result<Uuid, crate::error::PluginRuntimeError> {
+        self.insert_module(bytecode, metadata, VirtualFileSystem::empty())
+    }
+    
+    /// Load a WASM plugin, granting it the manifest's directories its sandbox allows
+    pub async fn load_plugin_module(
+        &mut self,
+        bytecode: Vec<u8>,
+        metadata: WasmModuleMetadata,
+        manifest: &PluginManifest,
+        sandbox: &PluginSandbox,
+    ) -> Result<Uuid, crate::error::PluginRuntimeError> {
+        if manifest.plugin_type != PluginType::Wasm {
+            return Err(crate::error::PluginRuntimeError::UnsupportedPluginType);
+        }
+        let filesystem = sandbox.filesystem(&manifest.filesystem);
+        filesystem.validate()?;
+        self.insert_module(bytecode, metadata, filesystem)
+    }
+    
+    /// Store data for a new instance of a module, with WASI seeing only the
+    /// module's granted directories
+    pub fn sandbox_state(&self, module_id: Uuid) -> Result<WasiSandboxState, crate::error::PluginRuntimeError> {
+        let module = self.modules.get(&module_id)
+            .ok_or_else(|| crate::error::PluginRuntimeError::PluginNotFound(module_id))?;
+        WasiSandboxState::new(&module.filesystem)
+    }
+    
+    fn insert_module(
+        &mut self,
+        bytecode: Vec<u8>,
+        metadata: WasmModuleMetadata,
+        filesystem: VirtualFileSystem,
     ) -> Result<Uuid, crate::error::PluginRuntimeError> {
         // Validate module bytecode
         self.validate_module(&bytecode)?;
@@ -201,6 +246,8 @@ impl WasmRuntime {
             bytecode,
             exports,
             imports,
+            meter: Arc::new(ResourceMeter::new()),
+            filesystem,
         };
         
         self.modules.insert(module_id, module);
@@ -242,7 +289,21 @@ impl WasmRuntime {
         };
         
         // Execute with timeout and resource limits
-        self.execute_with_limits(context).await
+        let start_time = context.start_time;
+        let memory_size = context.memory_size;
+        let result = self.execute_with_limits(context).await;
+        
+        // Fuel metering is not enabled on the engine yet, so no fuel is recorded
+        module.meter.record_call(start_time.elapsed(), 0, result.is_ok());
+        module.meter.record_memory(memory_size as u64);
+        result
+    }
+    
+    /// Resource usage of a loaded module
+    pub fn module_stats(&self, module_id: Uuid) -> Option<Arc<dyn ResourceStats>> {
+        self.modules
+            .get(&module_id)
+            .map(|module| module.meter.clone() as Arc<dyn ResourceStats>)
     }
     
     /// Execute with resource limits
@@ -331,4 +392,59 @@ impl Default for WasmConfig {
             host_functions: vec![],
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{DirectoryAccess, DirectoryGrant};
+    use crate::sandbox::{Capability, ResourceLimits};
+
+    fn metadata() -> WasmModuleMetadata {
+        WasmModuleMetadata {
+            name: "letters".to_string(),
+            version: "1.0.0".to_string(),
+            description: String::new(),
+            author: String::new(),
+            target: "wasm32-wasi".to_string(),
+            hash: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plugin_module_gets_sandboxed_filesystem() {
+        let dir = std::env::temp_dir();
+        let manifest = PluginManifest {
+            id: Uuid::new_v4(),
+            name: "letters".to_string(),
+            version: "1.0.0".to_string(),
+            description: String::new(),
+            plugin_type: PluginType::Wasm,
+            entry_point: "letters.wasm".to_string(),
+            permissions: vec![],
+            dependencies: vec![],
+            filesystem: vec![
+                DirectoryGrant { guest_path: "/templates".into(), host_path: dir.clone(), access: DirectoryAccess::ReadOnly },
+                DirectoryGrant { guest_path: "/scratch".into(), host_path: dir, access: DirectoryAccess::ReadWrite },
+            ],
+        };
+        let sandbox = PluginSandbox::new(
+            ResourceLimits::default(),
+            vec![Capability::FileSystemRead(vec!["/templates".into()])],
+        );
+        let bytecode = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+        let mut runtime = WasmRuntime::new(WasmConfig::default());
+        let module_id = runtime
+            .load_plugin_module(bytecode.clone(), metadata(), &manifest, &sandbox)
+            .await
+            .unwrap();
+        let grants = runtime.modules[&module_id].filesystem.grants();
+        assert_eq!(grants.len(), 1);
+        assert_eq!(grants[0].guest_path, "/templates");
+        assert!(runtime.sandbox_state(module_id).is_ok());
+
+        let plain = runtime.load_module(bytecode, metadata()).await.unwrap();
+        assert!(runtime.modules[&plain].filesystem.is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+// Plugin search: query parameters, result types and the SQL behind them
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Postgres, QueryBuilder};
+
+use crate::error::{RegistryError, RegistryResult};
+
+/// Largest page a search may request
+pub const MAX_PER_PAGE: u32 = 100;
+
+const DEFAULT_PER_PAGE: u32 = 20;
+
+/// Result ordering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    /// Most downloaded first
+    #[default]
+    Popularity,
+    /// Most recently released first
+    Recency,
+    /// Highest average rating first
+    Rating,
+    /// Best text match first; same as popularity without search text
+    Relevance,
+}
+
+/// Marketplace search. Every filter that is set must match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchQuery {
+    /// Full-text match on name and description
+    pub text: Option<String>,
+    pub category: Option<String>,
+    /// Plugins must carry all of these tags
+    pub tags: Vec<String>,
+    /// Minimum average rating; unrated plugins never match
+    pub min_rating: Option<f64>,
+    pub sort: SortBy,
+    /// 1-based
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        Self {
+            text: None,
+            category: None,
+            tags: Vec::new(),
+            min_rating: None,
+            sort: SortBy::default(),
+            page: 1,
+            per_page: DEFAULT_PER_PAGE,
+        }
+    }
+}
+
+impl SearchQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn min_rating(mut self, rating: f64) -> Self {
+        self.min_rating = Some(rating);
+        self
+    }
+
+    pub fn sort_by(mut self, sort: SortBy) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = page;
+        self
+    }
+
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = per_page;
+        self
+    }
+
+    pub fn validate(&self) -> RegistryResult<()> {
+        if let Some(rating) = self.min_rating {
+            if !(0.0..=5.0).contains(&rating) {
+                return Err(RegistryError::InvalidQuery(format!(
+                    "min_rating must be between 0 and 5, got {}",
+                    rating
+                )));
+            }
+        }
+        if self.page == 0 {
+            return Err(RegistryError::InvalidQuery("page starts at 1".to_string()));
+        }
+        if self.per_page == 0 || self.per_page > MAX_PER_PAGE {
+            return Err(RegistryError::InvalidQuery(format!(
+                "per_page must be between 1 and {}",
+                MAX_PER_PAGE
+            )));
+        }
+        Ok(())
+    }
+
+    /// Search text with surrounding whitespace removed, if any is left
+    fn search_text(&self) -> Option<&str> {
+        self.text
+            .as_deref()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+    }
+
+    fn offset(&self) -> i64 {
+        i64::from(self.page - 1) * i64::from(self.per_page)
+    }
+}
+
+/// One plugin in a result page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginSummary {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub author_name: String,
+    pub download_count: i64,
+    /// Average review rating, `None` until the plugin has reviews
+    pub rating: Option<f64>,
+    pub rating_count: i64,
+    /// Highest non-yanked release that runs on this engine version
+    pub latest_version: Option<String>,
+    pub latest_release_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One page of search results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub items: Vec<PluginSummary>,
+    /// Matches across all pages
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl SearchResults {
+    pub fn total_pages(&self) -> u32 {
+        let per_page = i64::from(self.per_page.max(1));
+        ((self.total + per_page - 1) / per_page) as u32
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub(crate) struct SearchRow {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub author_name: String,
+    pub download_count: i64,
+    pub average_rating: Option<f64>,
+    pub rating_count: i64,
+    pub latest_release_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+    pub total_count: i64,
+}
+
+impl SearchRow {
+    pub fn into_summary(self, latest_version: Option<String>) -> PluginSummary {
+        PluginSummary {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            category: self.category,
+            tags: self.tags,
+            author_name: self.author_name,
+            download_count: self.download_count,
+            rating: self.average_rating,
+            rating_count: self.rating_count,
+            latest_version,
+            latest_release_at: self.latest_release_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Published plugins joined with their review aggregate and latest release
+/// time. Shared by search and single-plugin lookups so both report the same
+/// figures.
+pub(crate) const PLUGIN_SELECT: &str = r#"
+    SELECT p.id, p.name, p.description, p.category, p.tags, p.author_name,
+           p.download_count, r.average_rating,
+           COALESCE(r.rating_count, 0) AS rating_count,
+           v.latest_release_at, p.updated_at,
+           COUNT(*) OVER () AS total_count
+    FROM plugins p
+    LEFT JOIN (
+        SELECT plugin_id, AVG(rating)::FLOAT8 AS average_rating, COUNT(*) AS rating_count
+        FROM plugin_reviews
+        GROUP BY plugin_id
+    ) r ON r.plugin_id = p.id
+    LEFT JOIN (
+        SELECT plugin_id, MAX(published_at) AS latest_release_at
+        FROM plugin_versions
+        WHERE NOT yanked
+        GROUP BY plugin_id
+    ) v ON v.plugin_id = p.id
+    WHERE p.status = 'published'
+"#;
+
+/// Page of matching plugins, with the total match count on every row
+pub(crate) fn search_query(query: &SearchQuery) -> QueryBuilder<'_, Postgres> {
+    let mut builder = QueryBuilder::new(PLUGIN_SELECT);
+    push_filters(&mut builder, query);
+
+    builder.push(" ORDER BY ");
+    match (query.sort, query.search_text()) {
+        (SortBy::Relevance, Some(text)) => {
+            builder.push("ts_rank(p.search_vector, websearch_to_tsquery('english', ");
+            builder.push_bind(text);
+            builder.push(")) DESC, p.download_count DESC");
+        }
+        (SortBy::Popularity | SortBy::Relevance, _) => {
+            builder.push("p.download_count DESC");
+        }
+        (SortBy::Recency, _) => {
+            builder.push("COALESCE(v.latest_release_at, p.created_at) DESC");
+        }
+        (SortBy::Rating, _) => {
+            builder.push("r.average_rating DESC NULLS LAST, r.rating_count DESC NULLS LAST");
+        }
+    }
+    // Tie-break on the key so pages never overlap
+    builder.push(", p.id ASC LIMIT ");
+    builder.push_bind(i64::from(query.per_page));
+    builder.push(" OFFSET ");
+    builder.push_bind(query.offset());
+    builder
+}
+
+/// Number of matching plugins, for pages past the last result where the
+/// windowed count is not available
+pub(crate) fn count_query(query: &SearchQuery) -> QueryBuilder<'_, Postgres> {
+    let mut builder = QueryBuilder::new("SELECT COUNT(*) FROM (");
+    builder.push(PLUGIN_SELECT);
+    push_filters(&mut builder, query);
+    builder.push(") matches");
+    builder
+}
+
+fn push_filters<'a>(builder: &mut QueryBuilder<'a, Postgres>, query: &'a SearchQuery) {
+    if let Some(category) = &query.category {
+        builder.push(" AND lower(p.category) = lower(");
+        builder.push_bind(category.as_str());
+        builder.push(")");
+    }
+    if !query.tags.is_empty() {
+        builder.push(" AND p.tags @> ");
+        builder.push_bind(&query.tags[..]);
+    }
+    if let Some(rating) = query.min_rating {
+        builder.push(" AND r.average_rating >= ");
+        builder.push_bind(rating);
+    }
+    if let Some(text) = query.search_text() {
+        // Full-text covers whole words; the name match catches prefixes
+        // and identifiers such as "saml-conn" that the parser splits up
+        builder.push(" AND (p.search_vector @@ websearch_to_tsquery('english', ");
+        builder.push_bind(text);
+        builder.push(") OR p.name ILIKE ");
+        builder.push_bind(like_pattern(text));
+        builder.push(" OR p.id ILIKE ");
+        builder.push_bind(like_pattern(text));
+        builder.push(")");
+    }
+}
+
+/// `%text%` with LIKE wildcards in the text escaped
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
@@ -0,0 +1,39 @@
+use database_layer::DatabaseError;
+use thiserror::Error;
+
+use crate::versioning::NoCompatibleVersion;
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("Plugin not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
+    #[error("Invalid version: {0}")]
+    InvalidVersion(String),
+
+    #[error("{0}")]
+    NoCompatibleVersion(NoCompatibleVersion),
+
+    #[error("Package integrity check failed: {0}")]
+    IntegrityFailure(String),
+
+    #[error("Invalid publisher key: {0}")]
+    InvalidKey(String),
+
+    #[error("Package storage error: {0}")]
+    Storage(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+}
+
+impl From<sqlx::Error> for RegistryError {
+    fn from(error: sqlx::Error) -> Self {
+        RegistryError::Database(DatabaseError::SqlxError(error))
+    }
+}
+
+pub type RegistryResult<T> = Result<T, RegistryError>;
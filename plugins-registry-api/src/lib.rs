@@ -1,20 +1,21 @@
 // Module declarations - to be implemented
-// pub mod registry;
+pub mod registry;
 // pub mod marketplace;
-// pub mod package;
-// pub mod versioning;
+pub mod package;
+pub mod versioning;
 // pub mod publishing;
-// pub mod discovery;
+pub mod discovery;
 // pub mod reviews;
 // pub mod analytics;
 // pub mod handlers;
-// pub mod error;
+pub mod error;
 
-// pub use registry::*;
+pub use registry::*;
 // pub use marketplace::*;
-// pub use package::*;
-// pub use versioning::*;
-// pub use error::*;
+pub use package::*;
+pub use versioning::*;
+pub use discovery::*;
+pub use error::*;
 
 /// Plugin registry and marketplace API for RustCare Engine (Phase 1.5)
 /// 
@@ -81,8 +82,8 @@
 ///         "1.2.3"
 ///     ).await?;
 ///     
-///     // Verify package integrity
-///     let verified = package.verify_signature().await?;
+///     // Packages are verified on download; re-check before installing
+///     package.verify_signature()?;
 ///     
 ///     Ok(())
 /// }
@@ -0,0 +1,344 @@
+// Plugin packages, their integrity checks and where their contents are
+// fetched from
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+use crate::error::{RegistryError, RegistryResult};
+use crate::versioning::PluginRelease;
+
+/// Lowercase hex SHA-256 of a package archive
+pub fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Bytes a publisher signs for a release. Naming the plugin and version
+/// stops a validly signed package from being served as a different release.
+pub fn signing_message(plugin_id: &str, version: &str, content_hash: &str) -> Vec<u8> {
+    format!("{}@{}:{}", plugin_id, version, content_hash).into_bytes()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStatus {
+    /// Signs new releases; a publisher has at most one
+    Active,
+    /// Replaced by a newer key; still vouches for releases published
+    /// before it was retired
+    Retired,
+    /// Compromised or withdrawn; vouches for nothing
+    Revoked,
+}
+
+/// A publisher's Ed25519 signing key
+#[derive(Debug, Clone, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct PublisherKey {
+    pub id: Uuid,
+    pub publisher_id: Uuid,
+    pub public_key: Vec<u8>,
+    pub status: KeyStatus,
+    pub created_at: DateTime<Utc>,
+    pub retired_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl PublisherKey {
+    /// Whether the key vouches for a release published at `published_at`
+    pub fn trusted_for(&self, published_at: DateTime<Utc>) -> bool {
+        match self.status {
+            KeyStatus::Active => true,
+            KeyStatus::Retired => self
+                .retired_at
+                .is_some_and(|retired_at| published_at <= retired_at),
+            KeyStatus::Revoked => false,
+        }
+    }
+}
+
+/// A downloaded plugin release. Packages handed out by the registry have
+/// already passed [`verify_signature`](Self::verify_signature).
+#[derive(Debug, Clone)]
+pub struct PluginPackage {
+    pub plugin_id: String,
+    pub version: Version,
+    pub engine_requirement: String,
+    /// Set when an exact download picked a yanked release
+    pub yanked: bool,
+    pub published_at: DateTime<Utc>,
+    /// Package archive bytes
+    pub content: Vec<u8>,
+    /// SHA-256 the publisher recorded for the archive
+    pub content_hash: String,
+    pub signature: Vec<u8>,
+    pub signing_key: PublisherKey,
+}
+
+impl PluginPackage {
+    /// Check that the content matches the recorded hash and that the
+    /// publisher's key signed this plugin, version and hash
+    pub fn verify_signature(&self) -> RegistryResult<()> {
+        let release = format!("{} {}", self.plugin_id, self.version);
+        let integrity_failure =
+            |reason: String| RegistryError::IntegrityFailure(format!("{}: {}", release, reason));
+
+        let actual_hash = content_hash(&self.content);
+        if !actual_hash.eq_ignore_ascii_case(&self.content_hash) {
+            return Err(integrity_failure(format!(
+                "content hash {} does not match published hash {}",
+                actual_hash, self.content_hash
+            )));
+        }
+
+        if !self.signing_key.trusted_for(self.published_at) {
+            return Err(integrity_failure(format!(
+                "signing key {} is {:?} and no longer vouches for this release",
+                self.signing_key.id, self.signing_key.status
+            )));
+        }
+
+        let message = signing_message(
+            &self.plugin_id,
+            &self.version.to_string(),
+            &self.content_hash.to_ascii_lowercase(),
+        );
+        crypto::verify_ed25519(&self.signing_key.public_key, &message, &self.signature)
+            .map_err(|e| integrity_failure(e.to_string()))
+    }
+
+    /// Verify the package and write it to `dir`, returning the file's path.
+    /// Nothing is written when verification fails.
+    pub async fn save(&self, dir: impl AsRef<Path>) -> RegistryResult<PathBuf> {
+        self.verify_signature()?;
+
+        let dir = dir.as_ref();
+        let path = dir.join(format!("{}-{}.tar.gz", self.plugin_id, self.version));
+        // Write under a temporary name so a partial file is never mistaken
+        // for the package
+        let partial = dir.join(format!(".{}-{}.partial", self.plugin_id, self.version));
+        let io_error = |e: std::io::Error| {
+            RegistryError::Storage(format!("failed to write {}: {}", path.display(), e))
+        };
+        tokio::fs::create_dir_all(dir).await.map_err(io_error)?;
+        tokio::fs::write(&partial, &self.content)
+            .await
+            .map_err(io_error)?;
+        tokio::fs::rename(&partial, &path).await.map_err(io_error)?;
+        Ok(path)
+    }
+}
+
+/// Source of package archives
+#[async_trait]
+pub trait PackageStore: Send + Sync {
+    async fn fetch(&self, plugin_id: &str, release: &PluginRelease) -> RegistryResult<Vec<u8>>;
+}
+
+fn package_location<'a>(plugin_id: &str, release: &'a PluginRelease) -> RegistryResult<&'a str> {
+    release.package_url.as_deref().ok_or_else(|| {
+        RegistryError::Storage(format!("{} {} has no package", plugin_id, release.version))
+    })
+}
+
+/// Fetches packages over HTTP. Relative package URLs are resolved against
+/// the registry URL.
+pub struct HttpPackageStore {
+    client: reqwest::Client,
+    base_url: reqwest::Url,
+}
+
+impl HttpPackageStore {
+    pub fn new(base_url: &str) -> RegistryResult<Self> {
+        let base_url = reqwest::Url::parse(base_url).map_err(|e| {
+            RegistryError::Storage(format!("invalid registry URL '{}': {}", base_url, e))
+        })?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            base_url,
+        })
+    }
+}
+
+#[async_trait]
+impl PackageStore for HttpPackageStore {
+    async fn fetch(&self, plugin_id: &str, release: &PluginRelease) -> RegistryResult<Vec<u8>> {
+        let location = package_location(plugin_id, release)?;
+        let url = self.base_url.join(location).map_err(|e| {
+            RegistryError::Storage(format!("invalid package URL '{}': {}", location, e))
+        })?;
+
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RegistryError::Storage(format!("failed to fetch {}: {}", url, e)))?;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| RegistryError::Storage(format!("failed to read {}: {}", url, e)))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Reads packages from a local mirror, with package URLs taken as paths
+/// relative to its root
+pub struct FsPackageStore {
+    root: PathBuf,
+}
+
+impl FsPackageStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[async_trait]
+impl PackageStore for FsPackageStore {
+    async fn fetch(&self, plugin_id: &str, release: &PluginRelease) -> RegistryResult<Vec<u8>> {
+        let location = package_location(plugin_id, release)?;
+        let relative = Path::new(location);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(RegistryError::Storage(format!(
+                "package path '{}' escapes the mirror root",
+                location
+            )));
+        }
+
+        let path = self.root.join(relative);
+        tokio::fs::read(&path).await.map_err(|e| {
+            RegistryError::Storage(format!("failed to read {}: {}", path.display(), e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crypto::Ed25519KeyPair;
+
+    fn key(key_pair: &Ed25519KeyPair, status: KeyStatus) -> PublisherKey {
+        PublisherKey {
+            id: Uuid::new_v4(),
+            publisher_id: Uuid::new_v4(),
+            public_key: key_pair.public_key().to_vec(),
+            status,
+            created_at: Utc::now() - Duration::days(30),
+            retired_at: None,
+            revoked_at: None,
+        }
+    }
+
+    fn signed_package(key_pair: &Ed25519KeyPair, signing_key: PublisherKey) -> PluginPackage {
+        let content = b"plugin archive".to_vec();
+        let hash = content_hash(&content);
+        let signature = key_pair.sign(&signing_message("auth-saml-connector", "1.2.3", &hash));
+        PluginPackage {
+            plugin_id: "auth-saml-connector".to_string(),
+            version: Version::new(1, 2, 3),
+            engine_requirement: "*".to_string(),
+            yanked: false,
+            published_at: Utc::now() - Duration::days(10),
+            content,
+            content_hash: hash,
+            signature: signature.to_vec(),
+            signing_key,
+        }
+    }
+
+    fn assert_integrity_failure(result: RegistryResult<()>) {
+        assert!(
+            matches!(result, Err(RegistryError::IntegrityFailure(_))),
+            "expected an integrity failure, got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_valid_package_verifies() {
+        let key_pair = Ed25519KeyPair::generate();
+        let package = signed_package(&key_pair, key(&key_pair, KeyStatus::Active));
+        package.verify_signature().unwrap();
+    }
+
+    #[test]
+    fn test_tampered_content_fails() {
+        let key_pair = Ed25519KeyPair::generate();
+        let mut package = signed_package(&key_pair, key(&key_pair, KeyStatus::Active));
+        package.content.push(0);
+        assert_integrity_failure(package.verify_signature());
+    }
+
+    #[test]
+    fn test_rehashed_tampered_content_fails() {
+        let key_pair = Ed25519KeyPair::generate();
+        let mut package = signed_package(&key_pair, key(&key_pair, KeyStatus::Active));
+        package.content.push(0);
+        package.content_hash = content_hash(&package.content);
+        assert_integrity_failure(package.verify_signature());
+    }
+
+    #[test]
+    fn test_signature_is_bound_to_the_release() {
+        let key_pair = Ed25519KeyPair::generate();
+        let mut package = signed_package(&key_pair, key(&key_pair, KeyStatus::Active));
+        package.version = Version::new(1, 2, 4);
+        assert_integrity_failure(package.verify_signature());
+    }
+
+    #[test]
+    fn test_other_publisher_key_fails() {
+        let key_pair = Ed25519KeyPair::generate();
+        let other = Ed25519KeyPair::generate();
+        let package = signed_package(&key_pair, key(&other, KeyStatus::Active));
+        assert_integrity_failure(package.verify_signature());
+    }
+
+    #[test]
+    fn test_retired_key_covers_earlier_releases_only() {
+        let key_pair = Ed25519KeyPair::generate();
+        let mut retired = key(&key_pair, KeyStatus::Retired);
+        retired.retired_at = Some(Utc::now() - Duration::days(5));
+        let mut package = signed_package(&key_pair, retired);
+        package.verify_signature().unwrap();
+
+        package.published_at = Utc::now() - Duration::days(1);
+        assert_integrity_failure(package.verify_signature());
+    }
+
+    #[test]
+    fn test_revoked_key_fails() {
+        let key_pair = Ed25519KeyPair::generate();
+        let package = signed_package(&key_pair, key(&key_pair, KeyStatus::Revoked));
+        assert_integrity_failure(package.verify_signature());
+    }
+
+    #[tokio::test]
+    async fn test_failed_package_is_not_saved() {
+        let dir = std::env::temp_dir().join(format!("plugin-packages-{}", Uuid::new_v4()));
+        let key_pair = Ed25519KeyPair::generate();
+        let mut package = signed_package(&key_pair, key(&key_pair, KeyStatus::Active));
+        package.content.push(0);
+
+        assert!(matches!(
+            package.save(&dir).await,
+            Err(RegistryError::IntegrityFailure(_))
+        ));
+        assert!(!dir.exists());
+
+        package.content.pop();
+        let path = package.save(&dir).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), package.content);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
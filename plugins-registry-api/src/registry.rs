@@ -0,0 +1,412 @@
+// Database-backed plugin registry
+use database_layer::DatabasePool;
+use semver::Version;
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::discovery::{self, SearchQuery, SearchResults, SearchRow};
+use crate::error::{RegistryError, RegistryResult};
+use crate::package::{HttpPackageStore, PackageStore, PluginPackage, PublisherKey};
+use crate::versioning::{self, PluginRelease, VersionSpec};
+
+/// Marketplace index over the plugin registry tables
+#[derive(Clone)]
+pub struct PluginRegistry {
+    pool: DatabasePool,
+    /// RustCare version that release compatibility is checked against
+    engine_version: Version,
+    store: Arc<dyn PackageStore>,
+}
+
+const PUBLISHER_KEY_COLUMNS: &str =
+    "id, publisher_id, public_key, status, created_at, retired_at, revoked_at";
+
+#[derive(Debug, FromRow)]
+struct VersionRow {
+    plugin_id: String,
+    version: String,
+    engine_requirement: String,
+}
+
+impl PluginRegistry {
+    /// Connect using `DATABASE_URL`
+    pub async fn new() -> RegistryResult<Self> {
+        let url = std::env::var("DATABASE_URL").map_err(|_| {
+            database_layer::DatabaseError::ConfigurationError("DATABASE_URL is not set".to_string())
+        })?;
+        Ok(Self::from_pool(DatabasePool::new(&url).await?))
+    }
+
+    pub fn from_pool(pool: DatabasePool) -> Self {
+        let store = HttpPackageStore::new(&crate::init().registry_url)
+            .expect("default registry URL is valid");
+        Self {
+            pool,
+            engine_version: Version::parse(env!("CARGO_PKG_VERSION"))
+                .expect("crate version is valid semver"),
+            store: Arc::new(store),
+        }
+    }
+
+    /// Check compatibility against another engine version than this build's
+    pub fn with_engine_version(mut self, version: Version) -> Self {
+        self.engine_version = version;
+        self
+    }
+
+    pub fn engine_version(&self) -> &Version {
+        &self.engine_version
+    }
+
+    /// Fetch package archives from somewhere other than the public registry
+    pub fn with_package_store(mut self, store: impl PackageStore + 'static) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// One page of published plugins matching `query`
+    pub async fn search(&self, query: SearchQuery) -> RegistryResult<SearchResults> {
+        query.validate()?;
+
+        let rows: Vec<SearchRow> = discovery::search_query(&query)
+            .build_query_as()
+            .fetch_all(self.pool.pool())
+            .await?;
+
+        let total = match rows.first() {
+            Some(row) => row.total_count,
+            None if query.page > 1 => {
+                discovery::count_query(&query)
+                    .build_query_scalar()
+                    .fetch_one(self.pool.pool())
+                    .await?
+            }
+            None => 0,
+        };
+
+        let ids: Vec<String> = rows.iter().map(|row| row.id.clone()).collect();
+        let mut latest = self.latest_compatible_versions(&ids).await?;
+        let items = rows
+            .into_iter()
+            .map(|row| {
+                let version = latest.remove(&row.id);
+                row.into_summary(version)
+            })
+            .collect();
+
+        Ok(SearchResults {
+            items,
+            total,
+            page: query.page,
+            per_page: query.per_page,
+        })
+    }
+
+    /// Every release of a published plugin, newest first
+    pub async fn list_versions(&self, plugin_id: &str) -> RegistryResult<Vec<PluginRelease>> {
+        let releases: Vec<PluginRelease> = sqlx::query_as(
+            r#"
+            SELECT v.version, v.engine_requirement, v.yanked, v.package_url, v.published_at,
+                   v.content_hash, v.signature, v.signing_key_id
+            FROM plugin_versions v
+            JOIN plugins p ON p.id = v.plugin_id
+            WHERE v.plugin_id = $1 AND p.status = 'published'
+            ORDER BY v.published_at DESC
+            "#,
+        )
+        .bind(plugin_id)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        if releases.is_empty() && !self.plugin_exists(plugin_id).await? {
+            return Err(RegistryError::NotFound(plugin_id.to_string()));
+        }
+        Ok(releases)
+    }
+
+    /// Highest release of a plugin that satisfies `range` and runs on
+    /// `engine_version`. An exact version (`1.2.3` or `=1.2.3`) also
+    /// resolves to a yanked release.
+    pub async fn resolve_version(
+        &self,
+        plugin_id: &str,
+        range: &str,
+        engine_version: &Version,
+    ) -> RegistryResult<Version> {
+        let spec = VersionSpec::parse(range)?;
+        let releases = self.list_versions(plugin_id).await?;
+        let (version, _) = versioning::resolve_release(plugin_id, &spec, engine_version, &releases)
+            .map_err(RegistryError::NoCompatibleVersion)?;
+        Ok(version)
+    }
+
+    /// Resolve `range` against this engine and fetch the chosen release.
+    /// The package's hash and publisher signature are verified before it is
+    /// returned; a package that fails returns
+    /// [`RegistryError::IntegrityFailure`].
+    pub async fn download_plugin(
+        &self,
+        plugin_id: &str,
+        range: &str,
+    ) -> RegistryResult<PluginPackage> {
+        let spec = VersionSpec::parse(range)?;
+        let releases = self.list_versions(plugin_id).await?;
+        let (version, release) =
+            versioning::resolve_release(plugin_id, &spec, &self.engine_version, &releases)
+                .map_err(RegistryError::NoCompatibleVersion)?;
+
+        let (Some(content_hash), Some(signature), Some(key_id)) = (
+            release.content_hash.clone(),
+            release.signature.clone(),
+            release.signing_key_id,
+        ) else {
+            return Err(RegistryError::IntegrityFailure(format!(
+                "{} {} is not signed",
+                plugin_id, version
+            )));
+        };
+        let signing_key = self.release_signing_key(plugin_id, key_id).await?;
+
+        let content = self.store.fetch(plugin_id, release).await?;
+        let package = PluginPackage {
+            plugin_id: plugin_id.to_string(),
+            version,
+            engine_requirement: release.engine_requirement.clone(),
+            yanked: release.yanked,
+            published_at: release.published_at,
+            content,
+            content_hash,
+            signature,
+            signing_key,
+        };
+        if let Err(e) = package.verify_signature() {
+            tracing::warn!(
+                plugin_id,
+                version = %package.version,
+                key_id = %key_id,
+                error = %e,
+                "Rejected plugin package"
+            );
+            return Err(e);
+        }
+
+        sqlx::query("UPDATE plugins SET download_count = download_count + 1 WHERE id = $1")
+            .bind(plugin_id)
+            .execute(self.pool.pool())
+            .await?;
+
+        tracing::info!(
+            plugin_id,
+            version = %package.version,
+            requested = range,
+            yanked = package.yanked,
+            "Plugin downloaded"
+        );
+        Ok(package)
+    }
+
+    /// Download and verify a release, then write it to `dir`. Nothing is
+    /// written unless verification passes.
+    pub async fn install_plugin(
+        &self,
+        plugin_id: &str,
+        range: &str,
+        dir: impl AsRef<Path>,
+    ) -> RegistryResult<PathBuf> {
+        self.download_plugin(plugin_id, range)
+            .await?
+            .save(dir)
+            .await
+    }
+
+    /// Register a publisher's new signing key. Any key that was active is
+    /// retired, so packages it signed until now keep verifying.
+    pub async fn register_publisher_key(
+        &self,
+        publisher_id: Uuid,
+        public_key: &[u8],
+    ) -> RegistryResult<PublisherKey> {
+        if public_key.len() != crypto::ED25519_PUBLIC_KEY_LENGTH {
+            return Err(RegistryError::InvalidKey(format!(
+                "expected a {}-byte Ed25519 public key, got {} bytes",
+                crypto::ED25519_PUBLIC_KEY_LENGTH,
+                public_key.len()
+            )));
+        }
+
+        let mut tx = self.pool.pool().begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE publisher_keys SET status = 'retired', retired_at = NOW()
+            WHERE publisher_id = $1 AND status = 'active'
+            "#,
+        )
+        .bind(publisher_id)
+        .execute(&mut *tx)
+        .await?;
+        let key: PublisherKey = sqlx::query_as(&format!(
+            r#"
+            INSERT INTO publisher_keys (id, publisher_id, public_key, status)
+            VALUES ($1, $2, $3, 'active')
+            RETURNING {}
+            "#,
+            PUBLISHER_KEY_COLUMNS
+        ))
+        .bind(Uuid::new_v4())
+        .bind(publisher_id)
+        .bind(public_key)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!(publisher_id = %publisher_id, key_id = %key.id, "Publisher signing key registered");
+        Ok(key)
+    }
+
+    /// Stop a key from signing new releases while it still vouches for
+    /// those published before now
+    pub async fn retire_publisher_key(&self, key_id: Uuid) -> RegistryResult<PublisherKey> {
+        self.set_key_status(
+            key_id,
+            "status = 'retired', retired_at = NOW()",
+            "status = 'active'",
+        )
+        .await
+    }
+
+    /// Withdraw all trust in a key, e.g. after it leaked. Every release it
+    /// signed stops verifying.
+    pub async fn revoke_publisher_key(&self, key_id: Uuid) -> RegistryResult<PublisherKey> {
+        self.set_key_status(
+            key_id,
+            "status = 'revoked', revoked_at = NOW()",
+            "status <> 'revoked'",
+        )
+        .await
+    }
+
+    /// All of a publisher's keys, newest first
+    pub async fn publisher_keys(&self, publisher_id: Uuid) -> RegistryResult<Vec<PublisherKey>> {
+        let keys = sqlx::query_as(&format!(
+            "SELECT {} FROM publisher_keys WHERE publisher_id = $1 ORDER BY created_at DESC",
+            PUBLISHER_KEY_COLUMNS
+        ))
+        .bind(publisher_id)
+        .fetch_all(self.pool.pool())
+        .await?;
+        Ok(keys)
+    }
+
+    async fn set_key_status(
+        &self,
+        key_id: Uuid,
+        assignment: &str,
+        precondition: &str,
+    ) -> RegistryResult<PublisherKey> {
+        let key: Option<PublisherKey> = sqlx::query_as(&format!(
+            "UPDATE publisher_keys SET {} WHERE id = $1 AND {} RETURNING {}",
+            assignment, precondition, PUBLISHER_KEY_COLUMNS
+        ))
+        .bind(key_id)
+        .fetch_optional(self.pool.pool())
+        .await?;
+        match key {
+            Some(key) => {
+                tracing::info!(key_id = %key_id, status = ?key.status, "Publisher signing key updated");
+                Ok(key)
+            }
+            None => Err(RegistryError::NotFound(format!("publisher key {}", key_id))),
+        }
+    }
+
+    /// Key a release was signed with, provided it belongs to the plugin's
+    /// publisher
+    async fn release_signing_key(
+        &self,
+        plugin_id: &str,
+        key_id: Uuid,
+    ) -> RegistryResult<PublisherKey> {
+        let key: Option<PublisherKey> = sqlx::query_as(&format!(
+            r#"
+            SELECT {} FROM publisher_keys
+            WHERE id = $1
+              AND publisher_id = (SELECT author_id FROM plugins WHERE id = $2)
+            "#,
+            PUBLISHER_KEY_COLUMNS
+        ))
+        .bind(key_id)
+        .bind(plugin_id)
+        .fetch_optional(self.pool.pool())
+        .await?;
+        key.ok_or_else(|| {
+            RegistryError::IntegrityFailure(format!(
+                "{} is signed with key {}, which is not registered to its publisher",
+                plugin_id, key_id
+            ))
+        })
+    }
+
+    async fn plugin_exists(&self, plugin_id: &str) -> RegistryResult<bool> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM plugins WHERE id = $1 AND status = 'published')",
+        )
+        .bind(plugin_id)
+        .fetch_one(self.pool.pool())
+        .await?;
+        Ok(exists)
+    }
+
+    /// Highest non-yanked version of each plugin whose engine requirement
+    /// accepts this engine. Stable releases win over pre-releases; a
+    /// pre-release is only reported for plugins without a compatible
+    /// stable one.
+    async fn latest_compatible_versions(
+        &self,
+        plugin_ids: &[String],
+    ) -> RegistryResult<HashMap<String, String>> {
+        if plugin_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows: Vec<VersionRow> = sqlx::query_as(
+            r#"
+            SELECT plugin_id, version, engine_requirement
+            FROM plugin_versions
+            WHERE plugin_id = ANY($1) AND NOT yanked
+            "#,
+        )
+        .bind(plugin_ids)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        let mut latest: HashMap<String, Version> = HashMap::new();
+        for row in rows {
+            let Ok(version) = Version::parse(&row.version) else {
+                tracing::warn!(
+                    plugin_id = %row.plugin_id,
+                    version = %row.version,
+                    "Skipping release with unparseable version"
+                );
+                continue;
+            };
+            if versioning::check_engine(&row.engine_requirement, &self.engine_version).is_err() {
+                continue;
+            }
+
+            let better = |current: &Version| {
+                (version.pre.is_empty(), &version) > (current.pre.is_empty(), current)
+            };
+            if latest.get(&row.plugin_id).is_none_or(better) {
+                latest.insert(row.plugin_id, version);
+            }
+        }
+
+        Ok(latest
+            .into_iter()
+            .map(|(plugin_id, version)| (plugin_id, version.to_string()))
+            .collect())
+    }
+}
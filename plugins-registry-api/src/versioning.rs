@@ -0,0 +1,455 @@
+// Semantic version ranges and release resolution
+use chrono::{DateTime, Utc};
+use semver::{Op, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::cmp::Ordering;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::error::{RegistryError, RegistryResult};
+
+/// A released version of a plugin as stored in the registry
+#[derive(Debug, Clone, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct PluginRelease {
+    pub version: String,
+    /// Semver range of engine versions the release runs on
+    pub engine_requirement: String,
+    pub yanked: bool,
+    pub package_url: Option<String>,
+    pub published_at: DateTime<Utc>,
+    /// Hex SHA-256 of the package archive
+    pub content_hash: Option<String>,
+    /// Publisher's detached signature, see [`crate::package::signing_message`]
+    pub signature: Option<Vec<u8>>,
+    pub signing_key_id: Option<Uuid>,
+}
+
+/// The version a caller asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSpec {
+    /// One release, named as `1.2.3` or `=1.2.3`. Yanked releases still
+    /// resolve this way so existing installs can be reproduced.
+    Exact(Version),
+    /// Any release in the range, e.g. `^1.2` or `>=1.0, <2.0`
+    Range(VersionReq),
+}
+
+impl VersionSpec {
+    pub fn parse(spec: &str) -> RegistryResult<Self> {
+        let spec = spec.trim();
+        if let Ok(version) = Version::parse(spec) {
+            return Ok(VersionSpec::Exact(version));
+        }
+
+        let req = VersionReq::parse(spec).map_err(|e| {
+            RegistryError::InvalidVersion(format!("'{}' is not a version range: {}", spec, e))
+        })?;
+        // `=1.2.3` names a single release too; `=1.2` is still a range
+        if let [comparator] = req.comparators.as_slice() {
+            if let (Op::Exact, Some(minor), Some(patch)) =
+                (comparator.op, comparator.minor, comparator.patch)
+            {
+                return Ok(VersionSpec::Exact(Version {
+                    major: comparator.major,
+                    minor,
+                    patch,
+                    pre: comparator.pre.clone(),
+                    build: semver::BuildMetadata::EMPTY,
+                }));
+            }
+        }
+        Ok(VersionSpec::Range(req))
+    }
+
+    /// Whether `version` satisfies the spec. Exact specs ignore build
+    /// metadata, which carries no precedence.
+    pub fn matches(&self, version: &Version) -> bool {
+        match self {
+            VersionSpec::Exact(exact) => exact.cmp_precedence(version) == Ordering::Equal,
+            VersionSpec::Range(req) => req.matches(version),
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, VersionSpec::Exact(_))
+    }
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSpec::Exact(version) => write!(f, "={}", version),
+            VersionSpec::Range(req) => write!(f, "{}", req),
+        }
+    }
+}
+
+/// Why a release was passed over
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Rejection {
+    /// The stored version is not valid semver
+    InvalidVersion,
+    OutOfRange,
+    /// Yanked releases are only served by exact version
+    Yanked,
+    EngineIncompatible {
+        requirement: String,
+    },
+    /// The stored engine requirement is not a valid semver range
+    InvalidEngineRequirement {
+        requirement: String,
+    },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::InvalidVersion => write!(f, "not a valid semantic version"),
+            Rejection::OutOfRange => write!(f, "outside the requested range"),
+            Rejection::Yanked => write!(f, "yanked"),
+            Rejection::EngineIncompatible { requirement } => {
+                write!(f, "requires engine {}", requirement)
+            }
+            Rejection::InvalidEngineRequirement { requirement } => {
+                write!(f, "invalid engine requirement '{}'", requirement)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedVersion {
+    pub version: String,
+    #[serde(flatten)]
+    pub reason: Rejection,
+}
+
+/// No release of a plugin satisfies a request, with the reason each
+/// candidate was rejected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoCompatibleVersion {
+    pub plugin_id: String,
+    pub requested: String,
+    pub engine_version: String,
+    /// Highest version first
+    pub rejected: Vec<RejectedVersion>,
+}
+
+impl fmt::Display for NoCompatibleVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no version of {} matches '{}' on engine {}",
+            self.plugin_id, self.requested, self.engine_version
+        )?;
+        if self.rejected.is_empty() {
+            return write!(f, " (no releases)");
+        }
+        for (i, rejected) in self.rejected.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { "; " };
+            write!(f, "{}{} {}", separator, rejected.version, rejected.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check a release's engine requirement against the running engine
+pub fn check_engine(requirement: &str, engine_version: &Version) -> Result<(), Rejection> {
+    let req = VersionReq::parse(requirement).map_err(|_| Rejection::InvalidEngineRequirement {
+        requirement: requirement.to_string(),
+    })?;
+    if req.matches(engine_version) {
+        Ok(())
+    } else {
+        Err(Rejection::EngineIncompatible {
+            requirement: requirement.to_string(),
+        })
+    }
+}
+
+/// Pick the release with the highest precedence that satisfies `spec` and
+/// runs on `engine_version`.
+///
+/// Pre-releases follow semver range rules: a range only admits them when one
+/// of its comparators names a pre-release of the same major.minor.patch, so
+/// `^1.2` never resolves to `1.3.0-beta.1` but `^1.3.0-beta.1` may resolve to
+/// `1.3.0-rc.1` or `1.3.0`.
+pub fn resolve_release<'a>(
+    plugin_id: &str,
+    spec: &VersionSpec,
+    engine_version: &Version,
+    releases: &'a [PluginRelease],
+) -> Result<(Version, &'a PluginRelease), NoCompatibleVersion> {
+    let mut best: Option<(Version, &PluginRelease)> = None;
+    let mut rejected: Vec<(Option<Version>, RejectedVersion)> = Vec::new();
+
+    for release in releases {
+        let version = Version::parse(&release.version).ok();
+        let outcome = match &version {
+            None => Err(Rejection::InvalidVersion),
+            Some(version) if !spec.matches(version) => Err(Rejection::OutOfRange),
+            Some(_) if release.yanked && !spec.is_exact() => Err(Rejection::Yanked),
+            Some(_) => check_engine(&release.engine_requirement, engine_version),
+        };
+
+        match (outcome, version) {
+            (Ok(()), Some(version)) => {
+                let higher = best.as_ref().is_none_or(|(current, _)| {
+                    version.cmp_precedence(current) == Ordering::Greater
+                });
+                if higher {
+                    best = Some((version, release));
+                }
+            }
+            (Err(reason), version) => rejected.push((
+                version,
+                RejectedVersion {
+                    version: release.version.clone(),
+                    reason,
+                },
+            )),
+            (Ok(()), None) => unreachable!("unparseable versions are rejected"),
+        }
+    }
+
+    best.ok_or_else(|| {
+        // Highest first; unparseable versions last
+        rejected.sort_by(|(a, _), (b, _)| match (a, b) {
+            (Some(a), Some(b)) => b.cmp_precedence(a),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        NoCompatibleVersion {
+            plugin_id: plugin_id.to_string(),
+            requested: spec.to_string(),
+            engine_version: engine_version.to_string(),
+            rejected: rejected.into_iter().map(|(_, rejected)| rejected).collect(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version: &str) -> PluginRelease {
+        PluginRelease {
+            version: version.to_string(),
+            engine_requirement: "*".to_string(),
+            yanked: false,
+            package_url: None,
+            published_at: Utc::now(),
+            content_hash: None,
+            signature: None,
+            signing_key_id: None,
+        }
+    }
+
+    fn yanked(version: &str) -> PluginRelease {
+        PluginRelease {
+            yanked: true,
+            ..release(version)
+        }
+    }
+
+    fn engine() -> Version {
+        Version::new(0, 1, 0)
+    }
+
+    fn resolve(spec: &str, releases: &[PluginRelease]) -> Result<String, NoCompatibleVersion> {
+        let spec = VersionSpec::parse(spec).unwrap();
+        resolve_release("test-plugin", &spec, &engine(), releases)
+            .map(|(version, _)| version.to_string())
+    }
+
+    #[test]
+    fn test_spec_parsing() {
+        assert!(VersionSpec::parse("1.2.3").unwrap().is_exact());
+        assert!(VersionSpec::parse("=1.2.3-rc.1").unwrap().is_exact());
+        assert!(!VersionSpec::parse("=1.2").unwrap().is_exact());
+        assert!(!VersionSpec::parse("^1.2").unwrap().is_exact());
+        assert!(!VersionSpec::parse(">=1.0, <2.0").unwrap().is_exact());
+        assert!(matches!(
+            VersionSpec::parse("one.two"),
+            Err(RegistryError::InvalidVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolves_highest_in_range() {
+        let releases = [
+            release("1.2.0"),
+            release("1.10.0"),
+            release("1.9.3"),
+            release("2.0.0"),
+        ];
+        assert_eq!(resolve("^1.2", &releases).unwrap(), "1.10.0");
+        assert_eq!(resolve("~1.9", &releases).unwrap(), "1.9.3");
+        assert_eq!(resolve("*", &releases).unwrap(), "2.0.0");
+    }
+
+    #[test]
+    fn test_range_skips_prerelease_of_other_versions() {
+        let releases = [release("1.2.5"), release("1.3.0-beta.1")];
+        assert_eq!(resolve("^1.2", &releases).unwrap(), "1.2.5");
+        assert_eq!(resolve(">=1.2.0", &releases).unwrap(), "1.2.5");
+    }
+
+    #[test]
+    fn test_range_with_prerelease_admits_same_version_prereleases() {
+        let releases = [
+            release("1.3.0-alpha.1"),
+            release("1.3.0-beta.2"),
+            release("1.3.0-rc.1"),
+            release("1.4.0-alpha.1"),
+        ];
+        // alpha < beta < rc, and 1.4.0-alpha.1 is a pre-release of another
+        // version so the range does not admit it
+        assert_eq!(resolve("^1.3.0-beta.1", &releases).unwrap(), "1.3.0-rc.1");
+
+        let err = resolve("^1.3.0-rc.2", &releases).unwrap_err();
+        assert!(err
+            .rejected
+            .iter()
+            .all(|rejected| rejected.reason == Rejection::OutOfRange));
+    }
+
+    #[test]
+    fn test_release_outranks_its_prereleases() {
+        let releases = [release("2.0.0-rc.9"), release("2.0.0")];
+        assert_eq!(resolve(">=2.0.0-rc.1", &releases).unwrap(), "2.0.0");
+    }
+
+    #[test]
+    fn test_numeric_prerelease_identifiers_compare_numerically() {
+        let releases = [release("1.0.0-beta.2"), release("1.0.0-beta.11")];
+        assert_eq!(
+            resolve("^1.0.0-beta.1", &releases).unwrap(),
+            "1.0.0-beta.11"
+        );
+    }
+
+    #[test]
+    fn test_longer_prerelease_outranks_its_prefix() {
+        let releases = [release("1.0.0-alpha.1"), release("1.0.0-alpha")];
+        assert_eq!(resolve("^1.0.0-alpha", &releases).unwrap(), "1.0.0-alpha.1");
+
+        // Numeric identifiers rank below alphanumeric ones
+        let releases = [release("1.0.0-alpha.beta"), release("1.0.0-alpha.1")];
+        assert_eq!(
+            resolve("^1.0.0-alpha", &releases).unwrap(),
+            "1.0.0-alpha.beta"
+        );
+    }
+
+    #[test]
+    fn test_exact_prerelease() {
+        let releases = [release("1.0.0-rc.1"), release("1.0.0")];
+        assert_eq!(resolve("1.0.0-rc.1", &releases).unwrap(), "1.0.0-rc.1");
+        assert_eq!(resolve("=1.0.0-rc.1", &releases).unwrap(), "1.0.0-rc.1");
+    }
+
+    #[test]
+    fn test_exact_ignores_build_metadata() {
+        let releases = [release("1.0.0+build.7")];
+        assert_eq!(resolve("1.0.0", &releases).unwrap(), "1.0.0+build.7");
+    }
+
+    #[test]
+    fn test_yanked_only_by_exact_version() {
+        let releases = [release("1.2.3"), yanked("1.2.4")];
+        assert_eq!(resolve("^1.2", &releases).unwrap(), "1.2.3");
+        assert_eq!(resolve("1.2.4", &releases).unwrap(), "1.2.4");
+        assert_eq!(resolve("=1.2.4", &releases).unwrap(), "1.2.4");
+
+        let err = resolve("^1.2.4", &releases).unwrap_err();
+        assert_eq!(
+            err.rejected,
+            vec![
+                RejectedVersion {
+                    version: "1.2.4".to_string(),
+                    reason: Rejection::Yanked,
+                },
+                RejectedVersion {
+                    version: "1.2.3".to_string(),
+                    reason: Rejection::OutOfRange,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_engine_requirement_rejects_even_exact_versions() {
+        let releases = [
+            PluginRelease {
+                engine_requirement: ">=0.2".to_string(),
+                ..release("1.3.0")
+            },
+            PluginRelease {
+                engine_requirement: "not a range".to_string(),
+                ..release("1.2.9")
+            },
+            release("1.2.0"),
+        ];
+        assert_eq!(resolve("^1.2", &releases).unwrap(), "1.2.0");
+
+        let err = resolve("1.3.0", &releases).unwrap_err();
+        assert_eq!(
+            err.rejected[0].reason,
+            Rejection::EngineIncompatible {
+                requirement: ">=0.2".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_no_compatible_version_lists_every_candidate() {
+        let releases = [
+            release("0.9.0"),
+            yanked("1.1.0"),
+            release("latest"),
+            PluginRelease {
+                engine_requirement: "^0.3".to_string(),
+                ..release("1.2.0")
+            },
+        ];
+        let err = resolve("^1.0", &releases).unwrap_err();
+        assert_eq!(err.plugin_id, "test-plugin");
+        assert_eq!(err.engine_version, "0.1.0");
+        let reasons: Vec<(&str, &Rejection)> = err
+            .rejected
+            .iter()
+            .map(|rejected| (rejected.version.as_str(), &rejected.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (
+                    "1.2.0",
+                    &Rejection::EngineIncompatible {
+                        requirement: "^0.3".to_string()
+                    }
+                ),
+                ("1.1.0", &Rejection::Yanked),
+                ("0.9.0", &Rejection::OutOfRange),
+                ("latest", &Rejection::InvalidVersion),
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            "no version of test-plugin matches '^1.0' on engine 0.1.0: \
+             1.2.0 requires engine ^0.3; 1.1.0 yanked; \
+             0.9.0 outside the requested range; latest not a valid semantic version"
+        );
+    }
+
+    #[test]
+    fn test_no_releases() {
+        let err = resolve("^1.0", &[]).unwrap_err();
+        assert!(err.rejected.is_empty());
+        assert!(err.to_string().ends_with("(no releases)"));
+    }
+}
@@ -1,7 +1,11 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+
     tonic_build::configure()
         .build_server(true)
         .build_client(true)
+        // Served by the gRPC reflection service
+        .file_descriptor_set_path(out_dir.join("rustcare_descriptor.bin"))
         .compile(
             &[
                 "proto/healthcare.proto",
@@ -171,6 +171,7 @@ impl OAuthProvider {
         
         let response = self.http_client
             .get(userinfo_url)
+            .headers(telemetry::trace_context_headers())
             .bearer_auth(access_token)
             .send()
             .await
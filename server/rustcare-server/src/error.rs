@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Json, Response},
 };
 use database_layer::DatabaseError;
@@ -85,7 +85,11 @@ pub enum ApiError {
     Conflict { message: String },
 
     #[error("Rate limit exceeded: {message}")]
-    RateLimit { message: String },
+    RateLimit {
+        message: String,
+        /// Seconds until the client may retry (sent as `Retry-After`)
+        retry_after_secs: Option<u64>,
+    },
 
     #[error("Database error: {0}")]
     Database(#[from] DatabaseError),
@@ -102,11 +106,31 @@ pub enum ApiError {
     #[error("Unprocessable entity: {message}")]
     UnprocessableEntity { message: String },
 
+    #[error("Payload too large: {message}")]
+    PayloadTooLarge { message: String },
+
+    #[error("Precondition failed: {message}")]
+    PreconditionFailed { message: String },
+
     #[error("Network error: {message}")]
     Network { message: String },
 
     #[error("Configuration error: {message}")]
     Configuration { message: String },
+
+    #[error("CSRF validation failed: {message}")]
+    Csrf {
+        /// Machine-readable rejection reason, returned as the error type
+        code: &'static str,
+        message: String,
+    },
+
+    #[error("API key rejected: {message}")]
+    ApiKey {
+        /// Why the key was rejected, returned as the error type
+        code: &'static str,
+        message: String,
+    },
 }
 
 impl ApiError {
@@ -143,9 +167,26 @@ impl ApiError {
         }
     }
     
+    /// Create a CSRF rejection with a machine-readable `code`
+    pub fn csrf(code: &'static str, message: impl Into<String>) -> Self {
+        Self::Csrf {
+            code,
+            message: message.into(),
+        }
+    }
+
     pub fn rate_limit(message: impl Into<String>) -> Self {
         Self::RateLimit {
             message: message.into(),
+            retry_after_secs: None,
+        }
+    }
+
+    /// Create a rate limit error telling the client when to retry
+    pub fn rate_limit_retry_after(message: impl Into<String>, retry_after_secs: u64) -> Self {
+        Self::RateLimit {
+            message: message.into(),
+            retry_after_secs: Some(retry_after_secs),
         }
     }
 
@@ -177,6 +218,27 @@ impl ApiError {
         }
     }
 
+    /// Create an unprocessable entity error
+    pub fn unprocessable_entity(message: impl Into<String>) -> Self {
+        Self::UnprocessableEntity {
+            message: message.into(),
+        }
+    }
+
+    /// Create a payload too large error
+    pub fn payload_too_large(message: impl Into<String>) -> Self {
+        Self::PayloadTooLarge {
+            message: message.into(),
+        }
+    }
+
+    /// Create a precondition failed error
+    pub fn precondition_failed(message: impl Into<String>) -> Self {
+        Self::PreconditionFailed {
+            message: message.into(),
+        }
+    }
+
     /// Create a service unavailable error
     pub fn service_unavailable(message: impl Into<String>) -> Self {
         Self::ServiceUnavailable {
@@ -197,14 +259,20 @@ impl ApiError {
                 DatabaseError::RlsPolicyViolation => StatusCode::FORBIDDEN,
                 DatabaseError::QueryFailed(_) => StatusCode::BAD_REQUEST,
                 DatabaseError::ConnectionFailed(_) => StatusCode::SERVICE_UNAVAILABLE,
+                DatabaseError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+                DatabaseError::ConcurrencyConflict { .. } => StatusCode::PRECONDITION_FAILED,
                 _ => StatusCode::INTERNAL_SERVER_ERROR,
             },
             ApiError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::ServiceUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
             ApiError::BadRequest { .. } => StatusCode::BAD_REQUEST,
             ApiError::UnprocessableEntity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
             ApiError::Network { .. } => StatusCode::BAD_GATEWAY,
             ApiError::Configuration { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Csrf { .. } => StatusCode::FORBIDDEN,
+            ApiError::ApiKey { .. } => StatusCode::UNAUTHORIZED,
         }
     }
 
@@ -222,8 +290,11 @@ impl ApiError {
             ApiError::ServiceUnavailable { .. } => "service_unavailable",
             ApiError::BadRequest { .. } => "bad_request",
             ApiError::UnprocessableEntity { .. } => "unprocessable_entity",
+            ApiError::PayloadTooLarge { .. } => "payload_too_large",
+            ApiError::PreconditionFailed { .. } => "precondition_failed",
             ApiError::Network { .. } => "network_error",
             ApiError::Configuration { .. } => "configuration_error",
+            ApiError::Csrf { code, .. } | ApiError::ApiKey { code, .. } => code,
         }
     }
 
@@ -259,11 +330,24 @@ impl ApiError {
                     "Try again in a few moments".to_string(),
                     "Contact support if the issue persists".to_string(),
                 ]),
+                DatabaseError::ConcurrencyConflict { .. } => Some(vec![
+                    "Fetch the latest version of the resource and retry with its ETag".to_string(),
+                ]),
                 _ => Some(vec![
                     "Verify your request data is valid".to_string(),
                     "Contact support if the issue persists".to_string(),
                 ]),
             },
+            ApiError::PreconditionFailed { .. } => Some(vec![
+                "Fetch the latest version of the resource and retry with its ETag".to_string(),
+            ]),
+            ApiError::Csrf { .. } => Some(vec![
+                "Fetch a token from /api/v1/csrf-token and send it in the X-CSRF-Token header".to_string(),
+            ]),
+            ApiError::ApiKey { .. } => Some(vec![
+                "Check that the key was copied in full and is still active".to_string(),
+                "Issue a new API key if this one was revoked or has expired".to_string(),
+            ]),
             ApiError::RateLimit { .. } => Some(vec![
                 "Wait before making additional requests".to_string(),
                 "Consider implementing exponential backoff".to_string(),
@@ -301,6 +385,13 @@ impl ApiError {
             DatabaseError::ConfigurationError(msg) => {
                 format!("Database configuration error: {}", msg)
             }
+            DatabaseError::ConcurrencyConflict { current, .. } => match current {
+                Some(version) => format!(
+                    "The record was changed by someone else and is now at version {}. Reload it and retry.",
+                    version
+                ),
+                None => "The record was changed or removed by someone else.".to_string(),
+            },
             DatabaseError::SqlxError(sqlx_err) => {
                 match sqlx_err {
                     sqlx::Error::RowNotFound => "Requested record not found.".to_string(),
@@ -332,6 +423,11 @@ impl IntoResponse for ApiError {
             "API error occurred"
         );
 
+        let retry_after = match &self {
+            ApiError::RateLimit { retry_after_secs, .. } => *retry_after_secs,
+            _ => None,
+        };
+
         let field_errors = match &self {
             ApiError::Validation { field_errors, .. } => field_errors.clone(),
             _ => None,
@@ -353,7 +449,11 @@ impl IntoResponse for ApiError {
             suggestions: self.suggestions(),
         };
 
-        (status_code, Json(error_response)).into_response()
+        let mut response = (status_code, Json(error_response)).into_response();
+        if let Some(secs) = retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
     }
 }
 
@@ -457,6 +557,31 @@ impl From<anyhow::Error> for ApiError {
     }
 }
 
+/// Convert gateway rejections to API errors, keeping API key rejection reasons
+impl From<auth_gateway::GatewayError> for ApiError {
+    fn from(error: auth_gateway::GatewayError) -> Self {
+        use auth_gateway::GatewayError;
+
+        match error {
+            GatewayError::IpNotAllowed { .. } | GatewayError::TenantMismatch { .. } => {
+                ApiError::authorization(error.to_string())
+            }
+            GatewayError::TenantUnresolved | GatewayError::InvalidTenant(_) => ApiError::bad_request(error.to_string()),
+            GatewayError::RateLimited { retry_after_secs, .. } => {
+                ApiError::rate_limit_retry_after(error.to_string(), retry_after_secs)
+            }
+            GatewayError::KeyNotFound(_) => ApiError::not_found("api_key"),
+            GatewayError::InvalidScopes(_) => ApiError::validation(error.to_string()),
+            GatewayError::InvalidApiKey | GatewayError::ApiKeyRevoked(_) | GatewayError::ApiKeyExpired(_) => {
+                ApiError::ApiKey {
+                    code: error.code(),
+                    message: error.to_string(),
+                }
+            }
+        }
+    }
+}
+
 /// Convert serde JSON errors to API errors
 impl From<serde_json::Error> for ApiError {
     fn from(error: serde_json::Error) -> Self {
@@ -0,0 +1,198 @@
+//! `rustcare.auth.v1.AuthService`
+//!
+//! Only token validation is served over gRPC, for services that need to
+//! check tokens presented to them. Logins, sessions, OAuth and MFA go
+//! through the HTTP API.
+
+use super::context::{audit, CallGuard};
+use super::not_implemented;
+use super::proto::auth::auth_service_server::AuthService;
+use super::proto::auth::*;
+use crate::middleware::auth_context::validate_jwt_token;
+use crate::middleware::AuthContext;
+use tonic::{Request, Response, Status};
+
+/// gRPC authentication service
+#[derive(Clone)]
+pub struct AuthGrpcService {
+    guard: CallGuard,
+}
+
+impl AuthGrpcService {
+    pub(crate) fn new(guard: CallGuard) -> Self {
+        Self { guard }
+    }
+}
+
+/// Validate `access_token` and check it grants every permission in `required`
+fn validate_token(access_token: &str, required: &[String]) -> ValidateTokenResponse {
+    let auth = match validate_jwt_token(access_token) {
+        Ok(auth) => auth,
+        Err(e) => {
+            return ValidateTokenResponse {
+                valid: false,
+                error_message: e.to_string(),
+                ..Default::default()
+            }
+        }
+    };
+
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|permission| !auth.permissions.contains(permission))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return ValidateTokenResponse {
+            valid: false,
+            error_message: format!("Missing required permissions: {}", missing.join(", ")),
+            ..Default::default()
+        };
+    }
+
+    ValidateTokenResponse {
+        valid: true,
+        user: Some(user_of(&auth)),
+        expires_at: None,
+        permissions: auth.permissions,
+        error_message: String::new(),
+    }
+}
+
+fn user_of(auth: &AuthContext) -> User {
+    User {
+        user_id: auth.user_id.to_string(),
+        email: auth.email.clone().unwrap_or_default(),
+        permissions: auth.permissions.clone(),
+        healthcare_organization_id: auth.organization_id.to_string(),
+        ..Default::default()
+    }
+}
+
+#[tonic::async_trait]
+impl AuthService for AuthGrpcService {
+    async fn authenticate(
+        &self,
+        _request: Request<AuthenticateRequest>,
+    ) -> Result<Response<AuthenticateResponse>, Status> {
+        Err(not_implemented("Authenticate"))
+    }
+
+    async fn refresh_token(
+        &self,
+        _request: Request<RefreshTokenRequest>,
+    ) -> Result<Response<AuthenticateResponse>, Status> {
+        Err(not_implemented("RefreshToken"))
+    }
+
+    async fn validate_token(
+        &self,
+        request: Request<ValidateTokenRequest>,
+    ) -> Result<Response<ValidateTokenResponse>, Status> {
+        // The token under test travels in the message, so callers need not
+        // authenticate; they are rate limited by address instead
+        self.guard.rate_limit_anonymous(&request, "ValidateToken").await?;
+        let request = request.into_inner();
+        let result = Ok(Response::new(validate_token(
+            &request.access_token,
+            &request.required_permissions,
+        )));
+        audit("ValidateToken", None, &result);
+        result
+    }
+
+    async fn revoke_token(&self, _request: Request<RevokeTokenRequest>) -> Result<Response<()>, Status> {
+        Err(not_implemented("RevokeToken"))
+    }
+
+    async fn initiate_o_auth(
+        &self,
+        _request: Request<InitiateOAuthRequest>,
+    ) -> Result<Response<InitiateOAuthResponse>, Status> {
+        Err(not_implemented("InitiateOAuth"))
+    }
+
+    async fn complete_o_auth(
+        &self,
+        _request: Request<CompleteOAuthRequest>,
+    ) -> Result<Response<AuthenticateResponse>, Status> {
+        Err(not_implemented("CompleteOAuth"))
+    }
+
+    async fn initiate_mfa(
+        &self,
+        _request: Request<InitiateMfaRequest>,
+    ) -> Result<Response<InitiateMfaResponse>, Status> {
+        Err(not_implemented("InitiateMFA"))
+    }
+
+    async fn complete_mfa(
+        &self,
+        _request: Request<CompleteMfaRequest>,
+    ) -> Result<Response<AuthenticateResponse>, Status> {
+        Err(not_implemented("CompleteMFA"))
+    }
+
+    async fn get_session(
+        &self,
+        _request: Request<GetSessionRequest>,
+    ) -> Result<Response<SessionResponse>, Status> {
+        Err(not_implemented("GetSession"))
+    }
+
+    async fn invalidate_session(
+        &self,
+        _request: Request<InvalidateSessionRequest>,
+    ) -> Result<Response<()>, Status> {
+        Err(not_implemented("InvalidateSession"))
+    }
+
+    async fn list_active_sessions(
+        &self,
+        _request: Request<ListActiveSessionsRequest>,
+    ) -> Result<Response<ListActiveSessionsResponse>, Status> {
+        Err(not_implemented("ListActiveSessions"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use uuid::Uuid;
+
+    fn token_with_permissions(user_id: Uuid, permissions: &[&str]) -> String {
+        let secret = std::env::var("JWT_SECRET")
+            .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string());
+        let claims = serde_json::json!({
+            "sub": user_id.to_string(),
+            "org_id": Uuid::new_v4().to_string(),
+            "permissions": permissions,
+            "exp": chrono::Utc::now().timestamp() + 3600,
+        });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_valid_tokens_resolve_to_their_user() {
+        let user_id = Uuid::new_v4();
+        let token = token_with_permissions(user_id, &["patients:read"]);
+
+        let response = validate_token(&token, &["patients:read".to_string()]);
+        assert!(response.valid, "{}", response.error_message);
+        assert_eq!(response.user.unwrap().user_id, user_id.to_string());
+        assert_eq!(response.permissions, vec!["patients:read".to_string()]);
+    }
+
+    #[test]
+    fn test_tokens_missing_required_permissions_are_invalid() {
+        let token = token_with_permissions(Uuid::new_v4(), &["patients:read"]);
+
+        let response = validate_token(&token, &["patients:write".to_string()]);
+        assert!(!response.valid);
+        assert!(response.user.is_none());
+        assert!(response.error_message.contains("patients:write"));
+
+        assert!(!validate_token("not-a-jwt", &[]).valid);
+    }
+}
@@ -0,0 +1,234 @@
+//! `rustcare.auth.v1.AuthorizationService`
+//!
+//! Permission checks answered by the Zanzibar engine. Callers may check
+//! their own permissions freely; checking on behalf of another subject
+//! requires the `check` permission on `permissions`. Relationship writes
+//! and expansion stay on the HTTP API for now.
+
+use super::context::{audit, CallGuard};
+use super::not_implemented;
+use super::proto::auth::authorization_service_server::AuthorizationService;
+use super::proto::auth::subject::SubjectType;
+use super::proto::auth::*;
+use crate::middleware::{AuthContext, ZanzibarEngineWrapper};
+use auth_zanzibar::{Consistency, ConsistencyToken, Object, Relation};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+/// Largest batch accepted by `BatchCheckPermissions`
+const MAX_BATCH_CHECKS: usize = 100;
+
+/// gRPC authorization service
+#[derive(Clone)]
+pub struct AuthorizationGrpcService {
+    guard: CallGuard,
+    engine: Option<Arc<ZanzibarEngineWrapper>>,
+}
+
+impl AuthorizationGrpcService {
+    pub(crate) fn new(guard: CallGuard, engine: Option<Arc<ZanzibarEngineWrapper>>) -> Self {
+        Self { guard, engine }
+    }
+
+    async fn check(
+        &self,
+        auth: &AuthContext,
+        request: CheckPermissionRequest,
+    ) -> Result<CheckPermissionResponse, Status> {
+        let engine = self
+            .engine
+            .as_ref()
+            .ok_or_else(|| Status::unavailable("Authorization engine is not configured"))?;
+
+        let object = request
+            .object
+            .ok_or_else(|| Status::invalid_argument("object is required"))?;
+        if object.object_type.is_empty() || object.object_id.is_empty() || request.relation.is_empty() {
+            return Err(Status::invalid_argument(
+                "object_type, object_id and relation are required",
+            ));
+        }
+
+        let caller = auth_zanzibar::Subject::user(&auth.user_id.to_string());
+        let subject = match request.subject {
+            Some(subject) => to_zanzibar_subject(subject)?,
+            None => caller.clone(),
+        };
+        if subject != caller {
+            auth.require_permission("permissions", None, "check").await?;
+        }
+
+        let consistency = if request.consistency_token.is_empty() {
+            Consistency::MinimizeLatency
+        } else {
+            let token = ConsistencyToken {
+                token: request.consistency_token.clone(),
+                created_at: chrono::Utc::now(),
+            };
+            token
+                .revision()
+                .map_err(|_| Status::invalid_argument("Malformed consistency_token"))?;
+            Consistency::AtLeastAsFresh(token)
+        };
+
+        let (allowed, evaluated_at) = engine
+            .engine()
+            .check_with_revision(
+                subject,
+                Relation::new(&request.relation),
+                Object::new(&object.object_type, &object.object_id),
+                consistency,
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "Zanzibar check failed");
+                Status::internal("Permission check failed")
+            })?;
+
+        // The revision actually evaluated, which may be newer than requested
+        Ok(CheckPermissionResponse {
+            allowed,
+            consistency_token: evaluated_at.token,
+        })
+    }
+}
+
+/// Zanzibar subject named by a request
+fn to_zanzibar_subject(subject: Subject) -> Result<auth_zanzibar::Subject, Status> {
+    match subject.subject_type {
+        Some(SubjectType::Object(object)) => match object.object_type.as_str() {
+            "user" => Ok(auth_zanzibar::Subject::user(&object.object_id)),
+            "group" => Ok(auth_zanzibar::Subject::group(&object.object_id)),
+            "service" => Ok(auth_zanzibar::Subject::service(&object.object_id)),
+            other => Err(Status::invalid_argument(format!(
+                "Unsupported subject type '{}'; use a subject set",
+                other
+            ))),
+        },
+        Some(SubjectType::SubjectSet(set)) => {
+            let object = set
+                .object
+                .ok_or_else(|| Status::invalid_argument("subject_set.object is required"))?;
+            Ok(auth_zanzibar::Subject::userset(
+                &object.object_type,
+                &object.object_id,
+                &set.relation,
+            ))
+        }
+        None => Err(Status::invalid_argument("subject is empty")),
+    }
+}
+
+#[tonic::async_trait]
+impl AuthorizationService for AuthorizationGrpcService {
+    async fn check_permission(
+        &self,
+        request: Request<CheckPermissionRequest>,
+    ) -> Result<Response<CheckPermissionResponse>, Status> {
+        let auth = self.guard.authenticate(&request, "CheckPermission").await?;
+        let result = self
+            .check(&auth, request.into_inner())
+            .await
+            .map(Response::new);
+        audit("CheckPermission", Some(&auth), &result);
+        result
+    }
+
+    async fn batch_check_permissions(
+        &self,
+        request: Request<BatchCheckPermissionsRequest>,
+    ) -> Result<Response<BatchCheckPermissionsResponse>, Status> {
+        let auth = self.guard.authenticate(&request, "BatchCheckPermissions").await?;
+        let requests = request.into_inner().requests;
+        let result = async {
+            if requests.len() > MAX_BATCH_CHECKS {
+                return Err(Status::invalid_argument(format!(
+                    "At most {} checks are allowed per batch",
+                    MAX_BATCH_CHECKS
+                )));
+            }
+            let mut responses = Vec::with_capacity(requests.len());
+            for check in requests {
+                responses.push(self.check(&auth, check).await?);
+            }
+            Ok(Response::new(BatchCheckPermissionsResponse { responses }))
+        }
+        .await;
+        audit("BatchCheckPermissions", Some(&auth), &result);
+        result
+    }
+
+    async fn write_relationship(
+        &self,
+        _request: Request<WriteRelationshipRequest>,
+    ) -> Result<Response<()>, Status> {
+        Err(not_implemented("WriteRelationship"))
+    }
+
+    async fn delete_relationship(
+        &self,
+        _request: Request<DeleteRelationshipRequest>,
+    ) -> Result<Response<()>, Status> {
+        Err(not_implemented("DeleteRelationship"))
+    }
+
+    async fn read_relationships(
+        &self,
+        _request: Request<ReadRelationshipsRequest>,
+    ) -> Result<Response<ReadRelationshipsResponse>, Status> {
+        Err(not_implemented("ReadRelationships"))
+    }
+
+    async fn expand(&self, _request: Request<ExpandRequest>) -> Result<Response<ExpandResponse>, Status> {
+        Err(not_implemented("Expand"))
+    }
+
+    type WatchStream = Pin<Box<dyn Stream<Item = Result<WatchResponse, Status>> + Send>>;
+
+    async fn watch(&self, _request: Request<WatchRequest>) -> Result<Response<Self::WatchStream>, Status> {
+        Err(not_implemented("Watch"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    fn object(object_type: &str, object_id: &str) -> ObjectReference {
+        ObjectReference {
+            object_type: object_type.to_string(),
+            object_id: object_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_subjects_map_to_zanzibar_subjects() {
+        let user = Subject {
+            subject_type: Some(SubjectType::Object(object("user", "u1"))),
+        };
+        assert_eq!(to_zanzibar_subject(user).unwrap(), auth_zanzibar::Subject::user("u1"));
+
+        let care_team = Subject {
+            subject_type: Some(SubjectType::SubjectSet(SubjectSet {
+                object: Some(object("care_team", "t1")),
+                relation: "member".to_string(),
+            })),
+        };
+        assert_eq!(
+            to_zanzibar_subject(care_team).unwrap(),
+            auth_zanzibar::Subject::userset("care_team", "t1", "member")
+        );
+
+        let patient = Subject {
+            subject_type: Some(SubjectType::Object(object("patient", "p1"))),
+        };
+        assert_eq!(to_zanzibar_subject(patient).unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(
+            to_zanzibar_subject(Subject { subject_type: None }).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Per-call authentication, rate limiting and audit logging
+//!
+//! The gRPC counterpart of the HTTP `AuthContext` extractor and audit
+//! middleware.
+
+use crate::middleware::auth_context::{extract_token, validate_jwt_token};
+use crate::middleware::{
+    AuthContext, RateLimiter, RequestContext, SecurityMiddlewareState, ZanzibarCheck,
+};
+use crate::server::RustCareServer;
+use std::sync::Arc;
+use tonic::{Code, Request, Response, Status};
+
+/// Shared checks every gRPC call passes through
+#[derive(Clone)]
+pub(crate) struct CallGuard {
+    rate_limiter: Option<Arc<RateLimiter>>,
+    zanzibar_engine: Option<Arc<dyn ZanzibarCheck>>,
+}
+
+impl CallGuard {
+    pub(crate) fn new(server: &RustCareServer, security: &SecurityMiddlewareState) -> Self {
+        Self {
+            rate_limiter: security.rate_limiter.clone(),
+            zanzibar_engine: server
+                .zanzibar_engine
+                .clone()
+                .map(|engine| engine as Arc<dyn ZanzibarCheck>),
+        }
+    }
+
+    /// Authenticate the caller from its bearer token and count the call
+    /// against their rate limit
+    pub(crate) async fn authenticate<T>(
+        &self,
+        request: &Request<T>,
+        method: &'static str,
+    ) -> Result<AuthContext, Status> {
+        let result = self.try_authenticate(request).await;
+        if let Err(ref status) = result {
+            audit_failure(method, request_context(request).remote_addr.as_deref(), status);
+        }
+        result
+    }
+
+    async fn try_authenticate<T>(&self, request: &Request<T>) -> Result<AuthContext, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let token = extract_token(&headers)?;
+        let mut auth = validate_jwt_token(&token)?;
+        auth.request = request_context(request);
+
+        if let Some(ref limiter) = self.rate_limiter {
+            let key = limiter.key_for(
+                Some((auth.user_id, auth.organization_id)),
+                auth.request.remote_addr.as_deref(),
+            );
+            limiter.check(&key).await?;
+            auth.rate_limiter = Some(limiter.clone());
+        }
+        auth.zanzibar_engine = self.zanzibar_engine.clone();
+        Ok(auth)
+    }
+
+    /// Count an unauthenticated call against the caller's address
+    pub(crate) async fn rate_limit_anonymous<T>(
+        &self,
+        request: &Request<T>,
+        method: &'static str,
+    ) -> Result<(), Status> {
+        let Some(ref limiter) = self.rate_limiter else {
+            return Ok(());
+        };
+        let remote_addr = request_context(request).remote_addr;
+        let key = limiter.key_for(None, remote_addr.as_deref());
+        limiter.check(&key).await.map(|_| ()).map_err(|e| {
+            let status = Status::from(e);
+            audit_failure(method, remote_addr.as_deref(), &status);
+            status
+        })
+    }
+}
+
+fn request_context<T>(request: &Request<T>) -> RequestContext {
+    let headers = request.metadata().clone().into_headers();
+    let remote_addr = request.remote_addr().map(|addr| addr.ip().to_string());
+    RequestContext::from_headers(&headers, remote_addr)
+}
+
+/// Log the outcome of a call for the audit trail
+pub(crate) fn audit<T>(
+    method: &'static str,
+    caller: Option<&AuthContext>,
+    result: &Result<Response<T>, Status>,
+) {
+    let code = match result {
+        Ok(_) => Code::Ok,
+        Err(status) => status.code(),
+    };
+    let remote_addr = caller.and_then(|auth| auth.request.remote_addr.as_deref());
+    log_call(method, caller, remote_addr, code);
+}
+
+/// Log a call rejected before its caller was authenticated
+fn audit_failure(method: &'static str, remote_addr: Option<&str>, status: &Status) {
+    log_call(method, None, remote_addr, status.code());
+}
+
+// TODO: Integrate with audit-engine, as for HTTP requests
+fn log_call(method: &'static str, caller: Option<&AuthContext>, remote_addr: Option<&str>, code: Code) {
+    tracing::info!(
+        method,
+        code = ?code,
+        user_id = ?caller.map(|auth| auth.user_id),
+        organization_id = ?caller.map(|auth| auth.organization_id),
+        request_id = ?caller.map(AuthContext::request_id),
+        remote_addr = ?remote_addr,
+        trace_id = ?telemetry::current_trace_id(),
+        "gRPC request audit"
+    );
+}
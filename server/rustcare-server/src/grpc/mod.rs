@@ -0,0 +1,147 @@
+//! gRPC API
+//!
+//! Serves the services defined in `proto/` alongside the HTTP API:
+//! token validation, Zanzibar permission checks and workflow executions.
+//! Calls authenticate with the same bearer JWTs as HTTP requests (sent as
+//! `authorization` metadata), count against the same rate limits and are
+//! audit logged like HTTP requests. Server reflection is enabled so tools
+//! such as `grpcurl` can discover the API.
+
+pub mod auth;
+pub mod authorization;
+mod context;
+pub mod workflow;
+
+use crate::error::ApiError;
+use crate::middleware::SecurityMiddlewareState;
+use crate::server::RustCareServer;
+use axum::http::StatusCode;
+use std::future::Future;
+use std::net::SocketAddr;
+use tonic::server::NamedService;
+use tonic::transport::Server;
+use tonic::Status;
+
+pub use auth::AuthGrpcService;
+pub use authorization::AuthorizationGrpcService;
+pub use workflow::WorkflowGrpcService;
+
+use context::CallGuard;
+use proto::auth::auth_service_server::AuthServiceServer;
+use proto::auth::authorization_service_server::AuthorizationServiceServer;
+use proto::workflow::workflow_service_server::WorkflowServiceServer;
+
+/// Code generated from `proto/`
+#[allow(clippy::all, clippy::pedantic, clippy::nursery, clippy::unwrap_used, clippy::shadow_unrelated)]
+pub mod proto {
+    pub mod auth {
+        tonic::include_proto!("rustcare.auth.v1");
+    }
+
+    pub mod workflow {
+        tonic::include_proto!("rustcare.workflow.v1");
+    }
+
+    /// Encoded `FileDescriptorSet` of every compiled proto file and its imports
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("rustcare_descriptor");
+}
+
+/// Serve the gRPC API on `addr` until `shutdown` completes
+///
+/// `security` should be the state the HTTP router was built with, so both
+/// transports share one rate limiter.
+pub async fn serve(
+    addr: SocketAddr,
+    server: RustCareServer,
+    security: SecurityMiddlewareState,
+    shutdown: impl Future<Output = ()> + Send,
+) -> anyhow::Result<()> {
+    let guard = CallGuard::new(&server, &security);
+
+    // Both reflection versions, since clients such as grpcurl still ask for v1alpha
+    let reflection_v1 = reflection().build_v1()?;
+    let reflection_v1alpha = reflection().build_v1alpha()?;
+
+    tracing::info!(%addr, "gRPC server listening");
+    Server::builder()
+        .add_service(AuthServiceServer::new(AuthGrpcService::new(guard.clone())))
+        .add_service(AuthorizationServiceServer::new(AuthorizationGrpcService::new(
+            guard.clone(),
+            server.zanzibar_engine.clone(),
+        )))
+        .add_service(WorkflowServiceServer::new(WorkflowGrpcService::new(
+            guard,
+            server.workflow_executions.clone(),
+        )))
+        .add_service(reflection_v1)
+        .add_service(reflection_v1alpha)
+        .serve_with_shutdown(addr, shutdown)
+        .await?;
+    tracing::info!("gRPC server stopped");
+    Ok(())
+}
+
+/// Reflection over the services this server implements
+fn reflection() -> tonic_reflection::server::Builder<'static> {
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
+        .with_service_name(<AuthServiceServer<AuthGrpcService> as NamedService>::NAME)
+        .with_service_name(<AuthorizationServiceServer<AuthorizationGrpcService> as NamedService>::NAME)
+        .with_service_name(<WorkflowServiceServer<WorkflowGrpcService> as NamedService>::NAME)
+}
+
+impl From<ApiError> for Status {
+    fn from(error: ApiError) -> Self {
+        match error.status_code() {
+            StatusCode::BAD_REQUEST
+            | StatusCode::UNPROCESSABLE_ENTITY
+            | StatusCode::PAYLOAD_TOO_LARGE => Status::invalid_argument(error.to_string()),
+            StatusCode::UNAUTHORIZED => Status::unauthenticated(error.to_string()),
+            StatusCode::FORBIDDEN => Status::permission_denied(error.to_string()),
+            StatusCode::NOT_FOUND => Status::not_found(error.to_string()),
+            StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED => {
+                Status::failed_precondition(error.to_string())
+            }
+            StatusCode::TOO_MANY_REQUESTS => Status::resource_exhausted(error.to_string()),
+            StatusCode::SERVICE_UNAVAILABLE | StatusCode::BAD_GATEWAY => {
+                Status::unavailable(error.to_string())
+            }
+            _ => {
+                // Keep internal details out of responses, as the HTTP API does
+                tracing::error!(error = %error, "gRPC call failed");
+                Status::internal("Internal server error")
+            }
+        }
+    }
+}
+
+/// Status of an RPC this server defines but does not implement yet
+fn not_implemented(method: &str) -> Status {
+    Status::unimplemented(format!("{} is not available over gRPC yet", method))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    #[test]
+    fn test_api_errors_map_to_grpc_codes() {
+        let cases = [
+            (ApiError::validation("bad id"), Code::InvalidArgument),
+            (ApiError::authentication("expired"), Code::Unauthenticated),
+            (ApiError::authorization("denied"), Code::PermissionDenied),
+            (ApiError::not_found("workflow_execution"), Code::NotFound),
+            (ApiError::conflict("already cancelled"), Code::FailedPrecondition),
+            (ApiError::rate_limit("slow down"), Code::ResourceExhausted),
+            (ApiError::service_unavailable("down"), Code::Unavailable),
+        ];
+        for (error, code) in cases {
+            assert_eq!(Status::from(error).code(), code);
+        }
+
+        let internal = Status::from(ApiError::internal("connection string leaked"));
+        assert_eq!(internal.code(), Code::Internal);
+        assert!(!internal.message().contains("leaked"));
+    }
+}
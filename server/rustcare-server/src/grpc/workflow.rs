@@ -0,0 +1,311 @@
+//! `rustcare.workflow.v1.WorkflowService`
+//!
+//! Starts, follows and cancels workflow executions in the same registry as
+//! the HTTP workflow endpoints, scoped to the caller's organization.
+
+use super::context::{audit, CallGuard};
+use super::not_implemented;
+use super::proto::workflow::workflow_service_server::WorkflowService;
+use super::proto::workflow::*;
+use crate::middleware::AuthContext;
+use crate::services::workflow_executions::{
+    CancelError, ExecutionState, StartExecution, WorkflowExecutionRecord, WorkflowExecutions,
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+/// Execution context keys for the healthcare fields of a start request
+const CONTEXT_FIELDS: [&str; 3] = ["patient_id", "provider_id", "encounter_id"];
+
+/// gRPC workflow service
+#[derive(Clone)]
+pub struct WorkflowGrpcService {
+    guard: CallGuard,
+    executions: Arc<WorkflowExecutions>,
+}
+
+impl WorkflowGrpcService {
+    pub(crate) fn new(guard: CallGuard, executions: Arc<WorkflowExecutions>) -> Self {
+        Self { guard, executions }
+    }
+
+    async fn start(
+        &self,
+        auth: &AuthContext,
+        request: StartWorkflowExecutionRequest,
+    ) -> Result<WorkflowExecutionResponse, Status> {
+        if request.definition_id.trim().is_empty() {
+            return Err(Status::invalid_argument("definition_id is required"));
+        }
+
+        let priority = match ExecutionPriority::try_from(request.priority) {
+            Ok(ExecutionPriority::Unspecified) | Err(_) => None,
+            Ok(priority) => Some(priority.as_str_name().to_lowercase()),
+        };
+        let context = CONTEXT_FIELDS
+            .into_iter()
+            .zip([request.patient_id, request.provider_id, request.encounter_id])
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+
+        // The caller starts the execution whatever `started_by` claims
+        let record = self.executions.start(StartExecution {
+            workflow_id: request.definition_id,
+            organization_id: auth.organization_id,
+            started_by: auth.user_id,
+            priority,
+            input_data: request
+                .input_data
+                .into_iter()
+                .map(|(key, value)| (key, Value::String(value)))
+                .collect(),
+            context,
+        })
+        .await;
+        Ok(WorkflowExecutionResponse {
+            execution: Some(to_proto(record)),
+        })
+    }
+
+    async fn get(&self, auth: &AuthContext, execution_id: &str) -> Result<WorkflowExecutionResponse, Status> {
+        let execution_id = parse_execution_id(execution_id)?;
+        let record = self
+            .executions
+            .get(auth.organization_id, execution_id)
+            .await
+            .ok_or_else(|| Status::not_found("Workflow execution not found"))?;
+        Ok(WorkflowExecutionResponse {
+            execution: Some(to_proto(record)),
+        })
+    }
+
+    async fn cancel(&self, auth: &AuthContext, execution_id: &str) -> Result<(), Status> {
+        let execution_id = parse_execution_id(execution_id)?;
+        match self.executions.cancel(auth.organization_id, execution_id).await {
+            Ok(_) => Ok(()),
+            Err(CancelError::NotFound) => Err(Status::not_found("Workflow execution not found")),
+            Err(CancelError::AlreadyFinished(state)) => Err(Status::failed_precondition(format!(
+                "Execution is already {}",
+                state.as_str()
+            ))),
+        }
+    }
+}
+
+fn parse_execution_id(execution_id: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(execution_id).map_err(|_| Status::invalid_argument("execution_id must be a UUID"))
+}
+
+fn timestamp(at: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: at.timestamp(),
+        nanos: i32::try_from(at.timestamp_subsec_nanos()).unwrap_or_default(),
+    }
+}
+
+fn to_proto(record: WorkflowExecutionRecord) -> WorkflowExecution {
+    let status = match record.state {
+        ExecutionState::Running => ExecutionStatus::ExecutionRunning,
+        ExecutionState::Completed => ExecutionStatus::ExecutionCompleted,
+        ExecutionState::Failed => ExecutionStatus::ExecutionFailed,
+        ExecutionState::Cancelled => ExecutionStatus::ExecutionCancelled,
+    };
+    let priority = record
+        .priority
+        .as_deref()
+        .and_then(|priority| ExecutionPriority::from_str_name(&priority.to_uppercase()))
+        .unwrap_or(ExecutionPriority::Unspecified);
+    let input_data: HashMap<String, String> = record
+        .input_data
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => (key, value),
+            value => (key, value.to_string()),
+        })
+        .collect();
+    let mut context = record.context;
+
+    WorkflowExecution {
+        execution_id: record.execution_id.to_string(),
+        definition_id: record.workflow_id.clone(),
+        name: record.workflow_id,
+        status: status as i32,
+        started_at: Some(timestamp(record.started_at)),
+        completed_at: record.completed_at.map(timestamp),
+        started_by: record.started_by.to_string(),
+        input_data,
+        output_data: HashMap::new(),
+        current_step_id: record.current_step.unwrap_or_default(),
+        task_executions: vec![],
+        priority: priority as i32,
+        patient_id: context.remove("patient_id").unwrap_or_default(),
+        provider_id: context.remove("provider_id").unwrap_or_default(),
+        encounter_id: context.remove("encounter_id").unwrap_or_default(),
+    }
+}
+
+#[tonic::async_trait]
+impl WorkflowService for WorkflowGrpcService {
+    async fn create_workflow_definition(
+        &self,
+        _request: Request<CreateWorkflowDefinitionRequest>,
+    ) -> Result<Response<WorkflowDefinitionResponse>, Status> {
+        Err(not_implemented("CreateWorkflowDefinition"))
+    }
+
+    async fn get_workflow_definition(
+        &self,
+        _request: Request<GetWorkflowDefinitionRequest>,
+    ) -> Result<Response<WorkflowDefinitionResponse>, Status> {
+        Err(not_implemented("GetWorkflowDefinition"))
+    }
+
+    async fn update_workflow_definition(
+        &self,
+        _request: Request<UpdateWorkflowDefinitionRequest>,
+    ) -> Result<Response<WorkflowDefinitionResponse>, Status> {
+        Err(not_implemented("UpdateWorkflowDefinition"))
+    }
+
+    async fn list_workflow_definitions(
+        &self,
+        _request: Request<ListWorkflowDefinitionsRequest>,
+    ) -> Result<Response<ListWorkflowDefinitionsResponse>, Status> {
+        Err(not_implemented("ListWorkflowDefinitions"))
+    }
+
+    async fn delete_workflow_definition(
+        &self,
+        _request: Request<DeleteWorkflowDefinitionRequest>,
+    ) -> Result<Response<()>, Status> {
+        Err(not_implemented("DeleteWorkflowDefinition"))
+    }
+
+    async fn start_workflow_execution(
+        &self,
+        request: Request<StartWorkflowExecutionRequest>,
+    ) -> Result<Response<WorkflowExecutionResponse>, Status> {
+        let auth = self.guard.authenticate(&request, "StartWorkflowExecution").await?;
+        let result = self.start(&auth, request.into_inner()).await.map(Response::new);
+        audit("StartWorkflowExecution", Some(&auth), &result);
+        result
+    }
+
+    async fn get_workflow_execution(
+        &self,
+        request: Request<GetWorkflowExecutionRequest>,
+    ) -> Result<Response<WorkflowExecutionResponse>, Status> {
+        let auth = self.guard.authenticate(&request, "GetWorkflowExecution").await?;
+        let result = self.get(&auth, &request.get_ref().execution_id).await.map(Response::new);
+        audit("GetWorkflowExecution", Some(&auth), &result);
+        result
+    }
+
+    async fn list_workflow_executions(
+        &self,
+        _request: Request<ListWorkflowExecutionsRequest>,
+    ) -> Result<Response<ListWorkflowExecutionsResponse>, Status> {
+        Err(not_implemented("ListWorkflowExecutions"))
+    }
+
+    async fn cancel_workflow_execution(
+        &self,
+        request: Request<CancelWorkflowExecutionRequest>,
+    ) -> Result<Response<()>, Status> {
+        let auth = self.guard.authenticate(&request, "CancelWorkflowExecution").await?;
+        let result = self.cancel(&auth, &request.get_ref().execution_id).await.map(Response::new);
+        audit("CancelWorkflowExecution", Some(&auth), &result);
+        result
+    }
+
+    async fn pause_workflow_execution(
+        &self,
+        _request: Request<PauseWorkflowExecutionRequest>,
+    ) -> Result<Response<()>, Status> {
+        Err(not_implemented("PauseWorkflowExecution"))
+    }
+
+    async fn resume_workflow_execution(
+        &self,
+        _request: Request<ResumeWorkflowExecutionRequest>,
+    ) -> Result<Response<()>, Status> {
+        Err(not_implemented("ResumeWorkflowExecution"))
+    }
+
+    async fn complete_task(
+        &self,
+        _request: Request<CompleteTaskRequest>,
+    ) -> Result<Response<TaskResponse>, Status> {
+        Err(not_implemented("CompleteTask"))
+    }
+
+    async fn assign_task(&self, _request: Request<AssignTaskRequest>) -> Result<Response<TaskResponse>, Status> {
+        Err(not_implemented("AssignTask"))
+    }
+
+    async fn get_task(&self, _request: Request<GetTaskRequest>) -> Result<Response<TaskResponse>, Status> {
+        Err(not_implemented("GetTask"))
+    }
+
+    async fn list_tasks(
+        &self,
+        _request: Request<ListTasksRequest>,
+    ) -> Result<Response<ListTasksResponse>, Status> {
+        Err(not_implemented("ListTasks"))
+    }
+
+    async fn get_workflow_metrics(
+        &self,
+        _request: Request<GetWorkflowMetricsRequest>,
+    ) -> Result<Response<WorkflowMetricsResponse>, Status> {
+        Err(not_implemented("GetWorkflowMetrics"))
+    }
+
+    type StreamWorkflowEventsStream =
+        Pin<Box<dyn Stream<Item = Result<WorkflowEvent, Status>> + Send>>;
+
+    async fn stream_workflow_events(
+        &self,
+        _request: Request<StreamWorkflowEventsRequest>,
+    ) -> Result<Response<Self::StreamWorkflowEventsStream>, Status> {
+        Err(not_implemented("StreamWorkflowEvents"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_records_convert_to_proto_executions() {
+        let executions = WorkflowExecutions::new();
+        let record = executions.start(StartExecution {
+            workflow_id: "medication-reconciliation".to_string(),
+            organization_id: Uuid::new_v4(),
+            started_by: Uuid::new_v4(),
+            priority: Some("urgent".to_string()),
+            input_data: HashMap::from([
+                ("ward".to_string(), Value::String("4B".to_string())),
+                ("bed".to_string(), serde_json::json!(12)),
+            ]),
+            context: HashMap::from([("patient_id".to_string(), "p-42".to_string())]),
+        })
+        .await;
+
+        let execution = to_proto(record.clone());
+        assert_eq!(execution.execution_id, record.execution_id.to_string());
+        assert_eq!(execution.status, ExecutionStatus::ExecutionRunning as i32);
+        assert_eq!(execution.priority, ExecutionPriority::Urgent as i32);
+        assert_eq!(execution.input_data["ward"], "4B");
+        assert_eq!(execution.input_data["bed"], "12");
+        assert_eq!(execution.patient_id, "p-42");
+        assert!(execution.completed_at.is_none());
+    }
+}
@@ -2,7 +2,13 @@ use crate::error::{api_success, ApiError, ApiResponse};
 use crate::server::RustCareServer;
 use crate::validation::RequestValidation;
 use crate::{validate_email, validate_field, validate_length, validate_required};
-use axum::{extract::State, http::StatusCode, Json};
+use crate::middleware::{CsrfValidator, SecurityConfig, SecurityMiddlewareState};
+use axum::{
+    extract::State,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -299,6 +305,59 @@ pub async fn validate_token(
     Ok(Json(api_success(response)))
 }
 
+/// CSRF token issued for double-submit protection
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CsrfTokenResponse {
+    /// Token to send back in the header on state-changing requests
+    #[schema(example = "3q2-7wQbGQ9yqjR2mF0xS1bJ8m4tLwzq5dC6uV7hK0E")]
+    pub csrf_token: String,
+    /// Header the token must be sent in
+    #[schema(example = "X-CSRF-Token")]
+    pub header_name: String,
+}
+
+/// Issue a CSRF token
+///
+/// Sets the token as a cookie and returns it in the body. Cookie-authenticated
+/// clients echo it in the `X-CSRF-Token` header on POST, PUT, PATCH and
+/// DELETE requests.
+#[utoipa::path(
+    get,
+    path = crate::routes::paths::api_v1::AUTH_CSRF_TOKEN,
+    tag = "authentication",
+    responses(
+        (status = 200, description = "Token issued and set as the csrf_token cookie", body = CsrfTokenResponse)
+    )
+)]
+pub async fn issue_csrf_token(
+    security: Option<Extension<SecurityMiddlewareState>>,
+) -> Result<Response, ApiError> {
+    let (validator, config) = match security {
+        Some(Extension(state)) => (
+            state.csrf_validator.as_deref().cloned().unwrap_or_default(),
+            state.config,
+        ),
+        None => (CsrfValidator::new(), SecurityConfig::default()),
+    };
+
+    let token = CsrfValidator::issue_token();
+    let cookie = HeaderValue::from_str(&validator.cookie(&token, config.cookie_same_site()))
+        .map_err(|_| ApiError::internal("Failed to encode CSRF cookie"))?;
+    let body = CsrfTokenResponse {
+        csrf_token: token,
+        header_name: validator.header_name,
+    };
+
+    Ok((
+        [
+            (header::SET_COOKIE, cookie),
+            (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+        ],
+        Json(api_success(body)),
+    )
+        .into_response())
+}
+
 /// User logout handler
 pub async fn logout(
     State(server): State<RustCareServer>,
@@ -0,0 +1,241 @@
+//! ETag and conditional request support
+//!
+//! Handlers take a `Preconditions` extractor and:
+//! - return `preconditions.respond(resource, version)` from GET handlers to
+//!   send a strong `ETag` and answer `If-None-Match` with 304 when unchanged
+//! - call `preconditions.require_match(&current, version)` before mutating to
+//!   enforce `If-Match` (412 when the client's copy is stale)
+//! - or pass `preconditions.expected_version()` to a versioned update
+//!   (`QueryExecutor::update_versioned`), which checks the version in the
+//!   same statement; its `ConcurrencyConflict` also answers 412
+//!
+//! The ETag is `"<version>-<digest>"` where the digest hashes the serialized
+//! resource together with its row version, so any change to either produces
+//! a new tag.
+//!
+//! ```rust,ignore
+//! pub async fn get_thing(preconditions: Preconditions, ...) -> Result<Conditional<Thing>, ApiError> {
+//!     let thing = load(...).await?;
+//!     let version = thing.version;
+//!     preconditions.respond(thing, version)
+//! }
+//! ```
+
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::fmt::Display;
+
+use database_layer::RowVersion;
+
+use crate::error::{api_success, ApiError};
+
+/// Compute the strong ETag for a resource at a given row version
+pub fn strong_etag<T: Serialize>(resource: &T, version: impl Display) -> Result<String, ApiError> {
+    let version = version.to_string();
+    let serialized = serde_json::to_vec(resource)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(version.as_bytes());
+    hasher.update([0]);
+    hasher.update(&serialized);
+    let digest = hex::encode(hasher.finalize());
+
+    Ok(format!("\"{}-{}\"", version, digest.get(..32).unwrap_or(&digest)))
+}
+
+/// Split a header like `If-None-Match` into its entity tags
+fn entity_tags(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|tag| !tag.is_empty())
+}
+
+/// Conditional request headers sent by the client
+#[derive(Debug, Clone, Default)]
+pub struct Preconditions {
+    if_match: Option<String>,
+    if_none_match: Option<String>,
+}
+
+impl Preconditions {
+    /// Read `If-Match`/`If-None-Match` from request headers
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let value = |name| {
+            headers
+                .get(name)
+                .and_then(|h: &HeaderValue| h.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            if_match: value(header::IF_MATCH),
+            if_none_match: value(header::IF_NONE_MATCH),
+        }
+    }
+
+    /// Whether `If-None-Match` matches `etag` (weak comparison)
+    pub fn is_not_modified(&self, etag: &str) -> bool {
+        self.if_none_match.as_deref().is_some_and(|value| {
+            entity_tags(value).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        })
+    }
+
+    /// Build a GET response carrying an ETag, or 304 if the client is current
+    pub fn respond<T: Serialize>(&self, resource: T, version: impl Display) -> Result<Conditional<T>, ApiError> {
+        let etag = strong_etag(&resource, version)?;
+        let body = (!self.is_not_modified(&etag)).then_some(resource);
+        Ok(Conditional { etag, body })
+    }
+
+    /// Row version named by a strong `If-Match` tag, for a versioned update.
+    /// `None` without `If-Match`, for `*`, or for a tag not issued here.
+    pub fn expected_version(&self) -> Option<RowVersion> {
+        let value = self.if_match.as_deref()?;
+        entity_tags(value).find_map(|tag| {
+            let tag = tag.strip_prefix('"')?.strip_suffix('"')?;
+            let (version, _digest) = tag.split_once('-')?;
+            RowVersion::parse(version)
+        })
+    }
+
+    /// Enforce `If-Match` against the current state of a resource
+    ///
+    /// Requests without `If-Match` are allowed. Weak tags never match, as
+    /// required for `If-Match` (strong comparison).
+    pub fn require_match<T: Serialize>(&self, current: &T, version: impl Display) -> Result<(), ApiError> {
+        let Some(value) = self.if_match.as_deref() else {
+            return Ok(());
+        };
+        let etag = strong_etag(current, version)?;
+        if entity_tags(value).any(|tag| tag == "*" || tag == etag) {
+            Ok(())
+        } else {
+            Err(ApiError::precondition_failed(
+                "Resource has been modified since it was retrieved",
+            ))
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Preconditions
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+/// JSON response tagged with a strong ETag
+///
+/// Serializes as the usual `ApiResponse` envelope, or as an empty 304 when
+/// the client already has this version.
+#[derive(Debug)]
+pub struct Conditional<T> {
+    etag: String,
+    body: Option<T>,
+}
+
+impl<T: Serialize> Conditional<T> {
+    /// Tag a response unconditionally, e.g. after a successful update
+    pub fn new(resource: T, version: impl Display) -> Result<Self, ApiError> {
+        Preconditions::default().respond(resource, version)
+    }
+
+    /// The response's ETag
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+}
+
+impl<T: Serialize> IntoResponse for Conditional<T> {
+    fn into_response(self) -> Response {
+        let mut response = match self.body {
+            Some(resource) => Json(api_success(resource)).into_response(),
+            None => StatusCode::NOT_MODIFIED.into_response(),
+        };
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            response.headers_mut().insert(header::ETAG, etag);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Record {
+        name: &'static str,
+    }
+
+    fn with_header(name: header::HeaderName, value: &str) -> Preconditions {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        Preconditions::from_headers(&headers)
+    }
+
+    #[test]
+    fn test_etag_changes_with_content_and_version() {
+        let etag = strong_etag(&Record { name: "a" }, 1).unwrap();
+        assert!(etag.starts_with("\"1-") && etag.ends_with('"'));
+        assert_eq!(etag, strong_etag(&Record { name: "a" }, 1).unwrap());
+        assert_ne!(etag, strong_etag(&Record { name: "b" }, 1).unwrap());
+        assert_ne!(etag, strong_etag(&Record { name: "a" }, 2).unwrap());
+    }
+
+    #[test]
+    fn test_if_none_match_returns_304() {
+        let etag = strong_etag(&Record { name: "a" }, 1).unwrap();
+        let preconditions = with_header(header::IF_NONE_MATCH, &format!("\"other\", W/{}", etag));
+
+        let response = preconditions.respond(Record { name: "a" }, 1).unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+        let changed = preconditions.respond(Record { name: "b" }, 2).unwrap().into_response();
+        assert_eq!(changed.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_if_match_enforces_current_version() {
+        let current = Record { name: "a" };
+        let etag = strong_etag(&current, 3).unwrap();
+
+        assert!(Preconditions::default().require_match(&current, 3).is_ok());
+        assert!(with_header(header::IF_MATCH, &etag).require_match(&current, 3).is_ok());
+        assert!(with_header(header::IF_MATCH, "*").require_match(&current, 3).is_ok());
+
+        let stale = with_header(header::IF_MATCH, &strong_etag(&current, 2).unwrap());
+        let err = stale.require_match(&current, 3).unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::PRECONDITION_FAILED);
+
+        // Weak tags never satisfy If-Match
+        let weak = with_header(header::IF_MATCH, &format!("W/{}", etag));
+        assert!(weak.require_match(&current, 3).is_err());
+    }
+
+    #[test]
+    fn test_expected_version_comes_from_strong_if_match() {
+        let etag = strong_etag(&Record { name: "a" }, 7).unwrap();
+        assert_eq!(
+            with_header(header::IF_MATCH, &etag).expected_version(),
+            Some(RowVersion::Counter(7))
+        );
+        assert_eq!(with_header(header::IF_MATCH, "*").expected_version(), None);
+        assert_eq!(
+            with_header(header::IF_MATCH, &format!("W/{}", etag)).expected_version(),
+            None
+        );
+        assert_eq!(Preconditions::default().expected_version(), None);
+    }
+}
@@ -1,4 +1,7 @@
 //! Common handler utilities and traits
 
 pub mod crud;
+pub mod conditional;
+
+pub use conditional::{Conditional, Preconditions};
 
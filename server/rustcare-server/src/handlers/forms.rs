@@ -16,6 +16,7 @@ use uuid::Uuid;
 
 use crate::{
     error::{api_success, ApiError, ApiResponse},
+    handlers::common::{Conditional, Preconditions},
     middleware::AuthContext,
     server::RustCareServer,
     types::pagination::PaginationParams,
@@ -23,7 +24,7 @@ use crate::{
     validation::{RequestValidation},
     validate_field, validate_length, validate_required, validate_email,
 };
-use database_layer::{QueryExecutor, RlsContext};
+use database_layer::QueryExecutor;
 use uuid::Uuid as UuidType;
 
 // ============================================================================
@@ -341,16 +342,11 @@ pub async fn get_form_definition(
     Path(form_id): Path<Uuid>,
     State(server): State<RustCareServer>,
     auth: AuthContext,
-) -> Result<Json<ApiResponse<FormDefinition>>, ApiError> {
-    // Create RLS context
-    let rls_context = RlsContext::new()
-        .with_user_id(auth.user_id)
-        .with_tenant_id(auth.organization_id.to_string())
-        .with_organization_id(auth.organization_id);
-
-    let executor = server.query_executor_with_rls(rls_context);
+    preconditions: Preconditions,
+) -> Result<Conditional<FormDefinition>, ApiError> {
+    let executor = server.query_executor_with_rls(auth.rls_context());
 
-    let form = executor
+    let form: Option<FormDefinition> = executor
         .fetch_optional_with(
             "SELECT 
                 id, organization_id, form_name, form_slug, display_name, description,
@@ -367,7 +363,10 @@ pub async fn get_form_definition(
         .map_err(|e| ApiError::internal(format!("Failed to fetch form: {}", e)))?;
 
     match form {
-        Some(f) => Ok(Json(api_success(f))),
+        Some(f) => {
+            let version = f.version;
+            preconditions.respond(f, version)
+        }
         None => Err(ApiError::not_found("Form definition not found")),
     }
 }
@@ -529,16 +528,24 @@ pub async fn create_form_definition(
 pub async fn update_form_definition(
     State(server): State<RustCareServer>,
     auth: AuthContext,
+    preconditions: Preconditions,
     Path(form_id): Path<Uuid>,
     Json(req): Json<UpdateFormDefinitionRequest>,
-) -> Result<Json<ApiResponse<FormDefinition>>, ApiError> {
+) -> Result<Conditional<FormDefinition>, ApiError> {
     // Verify form exists and belongs to organization
-    let existing = sqlx::query!(
-        "SELECT id, version FROM form_definitions 
+    let existing = sqlx::query_as::<_, FormDefinition>(
+        "SELECT 
+            id, organization_id, form_name, form_slug, display_name, description,
+            module_name, entity_type, form_schema, form_layout, validation_rules,
+            submission_handler, is_active, is_template, allow_multiple_submissions,
+            require_approval, requires_permission, required_roles, allowed_roles,
+            version, parent_form_id, tags, category, icon,
+            created_at, updated_at, created_by
+         FROM form_definitions
          WHERE id = $1 AND organization_id = $2 AND deleted_at IS NULL",
-        form_id,
-        auth.organization_id
     )
+    .bind(form_id)
+    .bind(auth.organization_id)
     .fetch_optional(&server.db_pool)
     .await
     .map_err(|e| ApiError::internal(format!("Failed to check form: {}", e)))?;
@@ -548,6 +555,9 @@ pub async fn update_form_definition(
         None => return Err(ApiError::not_found("Form definition not found")),
     };
 
+    // Optimistic concurrency: reject if the client's copy is stale
+    preconditions.require_match(&existing, existing.version)?;
+
     // Build update query using QueryBuilder for dynamic updates
     let mut query_builder = sqlx::QueryBuilder::new("UPDATE form_definitions SET ");
 
@@ -694,7 +704,8 @@ pub async fn update_form_definition(
         .await
         .map_err(|e| ApiError::internal(format!("Failed to update form: {}", e)))?;
 
-    Ok(Json(api_success(form)))
+    let version = form.version;
+    Conditional::new(form, version)
 }
 
 /// Submit form data
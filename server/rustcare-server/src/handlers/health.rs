@@ -7,6 +7,7 @@ use serde::Serialize;
 use std::collections::HashMap;
 use crate::server::RustCareServer;
 use crate::error::{ApiError, ApiResponse, api_success};
+use telemetry::HealthReport;
 use utoipa::ToSchema;
 
 /// Health check response
@@ -25,7 +26,58 @@ pub struct HealthResponse {
     #[schema(example = 3600)]
     pub uptime: u64,
     /// Individual service health checks
-    pub checks: HashMap<String, String>,
+    pub checks: HashMap<String, HealthCheckDetail>,
+}
+
+/// Result of an individual health check
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthCheckDetail {
+    /// Check status: healthy, degraded or unhealthy
+    #[schema(example = "healthy")]
+    pub status: String,
+    /// Whether failure of this check makes the service unavailable
+    pub critical: bool,
+    /// Time the check took in milliseconds
+    #[schema(example = 3)]
+    pub latency_ms: u64,
+    /// Failure description if the check failed
+    pub error: Option<String>,
+}
+
+impl HealthResponse {
+    fn from_report(report: HealthReport) -> Self {
+        let checks = report
+            .checks
+            .into_iter()
+            .map(|check| {
+                let detail = HealthCheckDetail {
+                    status: check.status.as_str().to_string(),
+                    critical: check.critical,
+                    latency_ms: check.latency_ms,
+                    error: check.error,
+                };
+                (check.name, detail)
+            })
+            .collect();
+
+        Self {
+            status: report.status.as_str().to_string(),
+            timestamp: report.timestamp.to_rfc3339(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime: report.uptime_seconds,
+            checks,
+        }
+    }
+}
+
+/// Render a health report, with 503 when the service is unavailable
+fn health_response(report: HealthReport) -> (StatusCode, Json<ApiResponse<HealthResponse>>) {
+    let status = if report.status.is_available() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(api_success(HealthResponse::from_report(report))))
 }
 
 /// Version information response
@@ -89,7 +141,11 @@ pub struct ServiceStatus {
     pub error: Option<String>,
 }
 
-/// Health check handler
+/// Liveness check handler
+///
+/// Reports whether the process is up. Dependency outages do not fail
+/// liveness, so orchestrators do not restart instances that are only
+/// waiting for a dependency to recover; see `/ready` for those.
 #[utoipa::path(
     get,
     path = "/health",
@@ -100,31 +156,29 @@ pub struct ServiceStatus {
     )
 )]
 pub async fn health_check(
-    State(_server): State<RustCareServer>
-) -> Result<Json<ApiResponse<HealthResponse>>, ApiError> {
-    let mut checks = HashMap::new();
-    
-    // Check database connectivity
-    checks.insert("database".to_string(), "healthy".to_string());
-    
-    // Check plugin runtime
-    checks.insert("plugin_runtime".to_string(), "healthy".to_string());
-    
-    // Check authentication service
-    checks.insert("auth_service".to_string(), "healthy".to_string());
-    
-    // Check audit engine
-    checks.insert("audit_engine".to_string(), "healthy".to_string());
-
-    let response = HealthResponse {
-        status: "healthy".to_string(),
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime: 0, // TODO: Implement actual uptime tracking
-        checks,
-    };
+    State(server): State<RustCareServer>
+) -> (StatusCode, Json<ApiResponse<HealthResponse>>) {
+    health_response(server.health.liveness().await)
+}
 
-    Ok(Json(api_success(response)))
+/// Readiness check handler
+///
+/// Runs all dependency checks concurrently. A failing non-critical
+/// dependency reports `degraded` but stays ready; a failing critical one
+/// returns 503.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "System can serve traffic", body = HealthResponse),
+        (status = 503, description = "A critical dependency is unavailable", body = HealthResponse)
+    )
+)]
+pub async fn readiness_check(
+    State(server): State<RustCareServer>
+) -> (StatusCode, Json<ApiResponse<HealthResponse>>) {
+    health_response(server.health.readiness().await)
 }
 
 /// Version information handler
@@ -207,4 +261,45 @@ pub async fn system_status(
     };
 
     Ok(Json(api_success(response)))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use telemetry::{CheckOptions, HealthRegistry};
+
+    #[tokio::test]
+    async fn test_non_critical_failure_degrades_readiness_only() {
+        let registry = HealthRegistry::new();
+        let timeout = Duration::from_millis(50);
+        registry.register_fn("database", CheckOptions::critical(timeout), || async { Ok(()) });
+        registry.register_fn("redis", CheckOptions::non_critical(timeout), || async {
+            Err("connection refused".to_string())
+        });
+
+        let (status, Json(body)) = health_response(registry.readiness().await);
+        let response = body.data;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.status, "degraded");
+        assert_eq!(response.checks["redis"].error.as_deref(), Some("connection refused"));
+
+        let (live_status, Json(live)) = health_response(registry.liveness().await);
+        assert_eq!(live_status, StatusCode::OK);
+        assert_eq!(live.data.status, "healthy");
+    }
+
+    #[tokio::test]
+    async fn test_critical_timeout_fails_readiness() {
+        let registry = HealthRegistry::new();
+        registry.register_fn("database", CheckOptions::critical(Duration::from_millis(10)), || async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        });
+
+        let (status, Json(body)) = health_response(registry.readiness().await);
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let response = body.data;
+        assert_eq!(response.status, "unhealthy");
+        assert!(response.checks["database"].error.as_deref().unwrap().contains("timed out"));
+    }
+}
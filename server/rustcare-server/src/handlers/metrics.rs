@@ -0,0 +1,149 @@
+//! Prometheus metrics endpoint
+//!
+//! Serves the process-wide `telemetry::MetricsCollector` in the Prometheus
+//! text format. The endpoint sits outside the authenticated API: set
+//! `METRICS_TOKEN` to require `Authorization: Bearer <token>` from the
+//! scraper, and `METRICS_PORT` to serve it on a separate admin listener
+//! instead of the public one.
+//!
+//! Scrapers that accept OpenMetrics get histogram exemplars carrying the
+//! trace id of a recent request in each latency bucket.
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use subtle::ConstantTimeEq;
+use telemetry::{ExpositionFormat, PrometheusExporter};
+
+/// Env var holding the bearer token scrapers must present
+pub const METRICS_TOKEN_ENV: &str = "METRICS_TOKEN";
+
+/// Env var selecting a dedicated admin port for `/metrics`
+pub const METRICS_PORT_ENV: &str = "METRICS_PORT";
+
+/// Admin port for the metrics listener, if configured
+#[must_use]
+pub fn admin_port() -> Option<u16> {
+    std::env::var(METRICS_PORT_ENV).ok()?.parse().ok()
+}
+
+fn is_authorized(headers: &HeaderMap, token: Option<&str>) -> bool {
+    let Some(expected) = token.filter(|t| !t.is_empty()) else {
+        return true;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|presented| bool::from(presented.as_bytes().ct_eq(expected.as_bytes())))
+}
+
+/// Render all metrics in the Prometheus or OpenMetrics text format
+pub async fn prometheus_metrics(headers: HeaderMap) -> Response {
+    let token = std::env::var(METRICS_TOKEN_ENV).ok();
+    if !is_authorized(&headers, token.as_deref()) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+        )
+            .into_response();
+    }
+
+    let format = ExpositionFormat::negotiate(headers.get(header::ACCEPT).and_then(|h| h.to_str().ok()));
+    (
+        [(header::CONTENT_TYPE, format.content_type())],
+        PrometheusExporter::default().render_as(format),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::request_timing_middleware;
+    use axum::{body::Body, extract::Request, http::HeaderValue, middleware::from_fn, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_metrics_token_check() {
+        let mut headers = HeaderMap::new();
+        assert!(is_authorized(&headers, None));
+        assert!(!is_authorized(&headers, Some("secret")));
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer wrong"));
+        assert!(!is_authorized(&headers, Some("secret")));
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        assert!(is_authorized(&headers, Some("secret")));
+    }
+
+    #[tokio::test]
+    async fn test_request_metrics_labelled_by_route_template() {
+        let app = Router::new()
+            .route("/metrics-test/items/:item_id", get(|| async { "ok" }))
+            .merge(crate::routes::metrics_routes())
+            .layer(from_fn(request_timing_middleware));
+
+        let item = Request::builder().uri("/metrics-test/items/42").body(Body::empty()).unwrap();
+        app.clone().oneshot(item).await.unwrap();
+
+        let scrape = Request::builder().uri("/metrics").body(Body::empty()).unwrap();
+        let response = app.oneshot(scrape).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PrometheusExporter::CONTENT_TYPE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains("# TYPE http_requests_total counter"));
+        assert!(text.contains(
+            r#"http_requests_total{method="GET",route="/metrics-test/items/:item_id",status="200"} 1"#
+        ));
+        assert!(text.contains(
+            r#"http_request_duration_seconds_bucket{method="GET",route="/metrics-test/items/:item_id",le="+Inf"} 1"#
+        ));
+        assert!(!text.contains("/metrics-test/items/42"));
+    }
+
+    #[tokio::test]
+    async fn test_openmetrics_scrape_links_buckets_to_traces() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let subscriber = tracing_subscriber::registry().with(telemetry::TracingSystem::new("test").layer());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route("/exemplar-test", get(|| async { "ok" }))
+            .merge(crate::routes::metrics_routes())
+            .layer(from_fn(request_timing_middleware))
+            .layer(from_fn(crate::middleware::trace_context_middleware));
+
+        let traced = Request::builder()
+            .uri("/exemplar-test")
+            .header("traceparent", "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01")
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(traced).await.unwrap();
+
+        let scrape = Request::builder()
+            .uri("/metrics")
+            .header(header::ACCEPT, "application/openmetrics-text; version=1.0.0")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(scrape).await.unwrap();
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("application/openmetrics-text"));
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.ends_with("# EOF\n"));
+        assert!(text.contains("# TYPE http_requests counter"));
+        assert!(text.lines().any(|line| {
+            line.starts_with("http_request_duration_seconds_bucket")
+                && line.contains(r#"route="/exemplar-test""#)
+                && line.contains(r#" # {trace_id="0af7651916cd43dd8448eb211c80319c""#)
+        }));
+    }
+}
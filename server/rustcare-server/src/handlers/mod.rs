@@ -18,7 +18,8 @@ pub mod plugins;
 pub mod forms;
 pub mod ui_components;
 pub mod common;
-// pub mod websocket; // Temporarily disabled
+pub mod metrics;
+pub mod websocket;
 
 // Re-export all handler modules for easy access
 // pub use health::*;
@@ -1,442 +1,723 @@
-use axum::{
-    extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        State, ConnectInfo,
-    },
-    response::Response,
-};
-use futures::{sink::SinkExt, stream::StreamExt};
+//! Real-time event push over WebSocket
+//!
+//! `GET /ws` upgrades an authenticated connection and streams events from
+//! the server's [`EventBus`] as JSON text frames. The bearer token goes in
+//! the `Authorization` header or, for browsers that cannot set headers on a
+//! WebSocket handshake, the `access_token` query parameter; it is validated
+//! before the upgrade.
+//!
+//! # Scoping
+//!
+//! An event is only pushed when it names the resource it concerns and the
+//! connected user may `view` that resource, so a connection never sees
+//! events for patients or records its user cannot read. The resource is
+//! taken from the event data: `resource_type` + `resource_id`, else
+//! `patient_id` (a patient), else `organization_id` (the organization).
+//! Events naming no resource, or another organization, are never pushed.
+//! Permission decisions are cached per connection for
+//! [`WebSocketConfig::permission_cache_ttl`].
+//!
+//! # Protocol
+//!
+//! Event types are selected with NATS-style patterns, initially from the
+//! comma separated `events` query parameter (default `>`, everything):
+//!
+//! ```json
+//! {"action": "subscribe", "events": "vitals.>"}
+//! {"action": "unsubscribe", "events": "vitals.>"}
+//! ```
+//!
+//! Server frames carry a `type` of `connected`, `subscriptions`, `event`,
+//! `events_dropped` or `error`.
+//!
+//! # Slow clients and keepalive
+//!
+//! Each connection buffers up to the event bus capacity. A client that falls
+//! further behind either skips the missed events and receives an
+//! `events_dropped` frame, or is disconnected, per
+//! [`SlowClientPolicy`]. A frame that cannot be written within
+//! [`WebSocketConfig::send_timeout`] always disconnects. The server pings
+//! every [`WebSocketConfig::ping_interval`] and closes connections that have
+//! not answered the previous ping.
+
+use crate::error::ApiError;
+use crate::middleware::auth_context::{extract_token, validate_jwt_token};
+use crate::middleware::{AuthContext, ZanzibarCheck};
+use crate::server::RustCareServer;
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::Response;
+use axum::Extension;
+use events_bus::{event_type_matches, Event, EventBusError, EventSubscriber};
+use futures::stream::{SplitSink, StreamExt};
+use futures::SinkExt;
 use serde::{Deserialize, Serialize};
-use std::{
-    collections::HashMap,
-    net::SocketAddr,
-    sync::{Arc, atomic::{AtomicU64, Ordering}},
-};
-use tokio::sync::{RwLock, broadcast};
-use tracing::{debug, error, info, warn};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::server::RustCareServer;
-use error_common::{RustCareError, Result};
-use logger_redacted::RedactedLogger;
-
-/// WebSocket connection manager for healthcare real-time communication
-#[derive(Clone)]
-pub struct WebSocketManager {
-    /// Active connections registry
-    connections: Arc<RwLock<HashMap<String, WebSocketConnection>>>,
-    /// Broadcast channel for server-wide messages
-    broadcast_tx: broadcast::Sender<WebSocketMessage>,
-    /// Connection counter for unique IDs
-    connection_counter: Arc<AtomicU64>,
-    /// Redacted logger for HIPAA compliance
-    logger: Arc<RedactedLogger>,
+/// Most event patterns a single connection may subscribe to
+const MAX_PATTERNS: usize = 32;
+
+/// Permission decisions cached per connection before the cache is reset
+const MAX_CACHED_DECISIONS: usize = 1024;
+
+/// Relation a user needs on a resource to receive its events
+const VIEW_PERMISSION: &str = "view";
+
+/// Close codes (RFC 6455)
+const CLOSE_GOING_AWAY: u16 = 1001;
+const CLOSE_POLICY_VIOLATION: u16 = 1008;
+
+/// What to do with a client that falls behind the event stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowClientPolicy {
+    /// Skip the missed events and tell the client how many it lost
+    DropEvents,
+    /// Close the connection so the client reconnects and resynchronizes
+    Disconnect,
 }
 
-/// WebSocket connection metadata
+/// WebSocket event push settings
 #[derive(Debug, Clone)]
-pub struct WebSocketConnection {
-    /// Connection ID
-    pub connection_id: String,
-    /// User ID (if authenticated)
-    pub user_id: Option<String>,
-    /// User role (for permission checks)
-    pub user_role: Option<String>,
-    /// Connection timestamp
-    pub connected_at: chrono::DateTime<chrono::Utc>,
-    /// Client IP address
-    pub client_ip: String,
-    /// Subscribed channels
-    pub subscribed_channels: Vec<String>,
-    /// Connection type
-    pub connection_type: WebSocketConnectionType,
+pub struct WebSocketConfig {
+    /// How often connections are pinged
+    pub ping_interval: Duration,
+    /// Longest a single frame may take to write before the client is dropped
+    pub send_timeout: Duration,
+    /// Handling of clients that fall behind the event stream
+    pub slow_client_policy: SlowClientPolicy,
+    /// How long a permission decision is reused for a resource
+    pub permission_cache_ttl: Duration,
 }
 
-/// WebSocket connection types for healthcare
-#[derive(Debug, Clone, PartialEq)]
-pub enum WebSocketConnectionType {
-    /// Healthcare provider dashboard
-    ProviderDashboard,
-    /// Patient portal
-    PatientPortal,
-    /// Administrative console
-    AdminConsole,
-    /// Real-time monitoring
-    RealtimeMonitoring,
-    /// Emergency alerts
-    EmergencyAlerts,
-    /// System notifications
-    SystemNotifications,
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(30),
+            send_timeout: Duration::from_secs(10),
+            slow_client_policy: SlowClientPolicy::DropEvents,
+            permission_cache_ttl: Duration::from_secs(30),
+        }
+    }
 }
 
-/// WebSocket message types
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WebSocketMessage {
-    /// Message ID
-    pub id: String,
-    /// Message type
-    pub message_type: WebSocketMessageType,
-    /// Target channel
-    pub channel: String,
-    /// Message payload
-    pub payload: serde_json::Value,
-    /// Timestamp
-    pub timestamp: chrono::DateTime<chrono::Utc>,
-    /// Sender ID (optional)
-    pub sender_id: Option<String>,
-    /// HIPAA compliance flag
-    pub hipaa_compliant: bool,
+impl WebSocketConfig {
+    /// Defaults, with the slow client policy taken from `WS_SLOW_CLIENT_POLICY`
+    /// (`drop` or `disconnect`)
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        match std::env::var("WS_SLOW_CLIENT_POLICY").as_deref() {
+            Ok("drop") | Err(_) => {}
+            Ok("disconnect") => config.slow_client_policy = SlowClientPolicy::Disconnect,
+            Ok(other) => tracing::warn!(
+                policy = other,
+                "Unknown WS_SLOW_CLIENT_POLICY; dropping events for slow clients"
+            ),
+        }
+        config
+    }
 }
 
-/// WebSocket message types for healthcare
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum WebSocketMessageType {
-    // Real-time updates
-    PatientVitalSigns,
-    AppointmentUpdate,
-    MedicalRecordUpdate,
-    
-    // Notifications
-    SystemNotification,
-    ProviderAlert,
-    PatientAlert,
-    EmergencyAlert,
-    
-    // Chat and communication
-    ChatMessage,
-    ConsultationRequest,
-    ConsultationResponse,
-    
-    // System events
-    ConnectionStatus,
-    HeartBeat,
-    Error,
-    
-    // Admin events
-    UserStatusChange,
-    SystemMaintenance,
-    AuditAlert,
+/// Query parameters of the upgrade request
+#[derive(Debug, Default, Deserialize)]
+pub struct WebSocketParams {
+    /// Bearer token, for clients that cannot send an `Authorization` header
+    pub access_token: Option<String>,
+    /// Comma separated event type patterns to subscribe to
+    pub events: Option<String>,
 }
 
-/// WebSocket client message
+/// Messages accepted from clients
 #[derive(Debug, Deserialize)]
-pub struct ClientMessage {
-    pub action: String,
-    pub channel: Option<String>,
-    pub payload: Option<serde_json::Value>,
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ClientFrame {
+    Subscribe { events: String },
+    Unsubscribe { events: String },
 }
 
-/// WebSocket server response
+/// Messages sent to clients
 #[derive(Debug, Serialize)]
-pub struct ServerResponse {
-    pub success: bool,
-    pub message: String,
-    pub data: Option<serde_json::Value>,
-    pub timestamp: chrono::DateTime<chrono::Utc>,
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerFrame<'a> {
+    Connected {
+        connection_id: Uuid,
+        subscriptions: &'a [String],
+    },
+    Subscriptions {
+        subscriptions: &'a [String],
+    },
+    Event {
+        event: &'a Event,
+    },
+    EventsDropped {
+        count: u64,
+    },
+    Error {
+        message: String,
+    },
 }
 
-impl WebSocketManager {
-    /// Create a new WebSocket manager
-    pub fn new(logger: Arc<RedactedLogger>) -> Self {
-        let (broadcast_tx, _) = broadcast::channel(1000);
-        
-        Self {
-            connections: Arc::new(RwLock::new(HashMap::new())),
-            broadcast_tx,
-            connection_counter: Arc::new(AtomicU64::new(0)),
-            logger,
-        }
+/// Why the server ended a connection
+enum Disconnect {
+    /// The client closed the connection or it failed
+    Client,
+    /// The server closes the connection with a close frame
+    Server { code: u16, reason: &'static str },
+}
+
+/// Upgrade an authenticated request to an event push connection
+pub async fn websocket_handler(
+    State(server): State<RustCareServer>,
+    Query(params): Query<WebSocketParams>,
+    zanzibar: Option<Extension<Arc<dyn ZanzibarCheck>>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let token = match (extract_token(&headers), params.access_token) {
+        (Ok(token), _) | (Err(_), Some(token)) => token,
+        (Err(e), None) => return Err(e),
+    };
+    let mut auth = validate_jwt_token(&token)?;
+    auth.zanzibar_engine = zanzibar.map(|Extension(engine)| engine);
+
+    let patterns = match params.events.as_deref() {
+        Some(events) => parse_patterns(events)?,
+        None => vec![">".to_string()],
+    };
+    let subscriber = server
+        .event_bus
+        .subscribe(">")
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to subscribe to events: {}", e)))?;
+    let config = server.config.websocket.clone();
+
+    Ok(ws.on_upgrade(move |socket| serve_connection(socket, auth, subscriber, patterns, config)))
+}
+
+/// Split a comma separated pattern list, rejecting empty or oversized lists
+fn parse_patterns(events: &str) -> Result<Vec<String>, ApiError> {
+    let patterns: Vec<String> = events
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(str::to_string)
+        .collect();
+    if patterns.is_empty() {
+        return Err(ApiError::validation("At least one event pattern is required"));
+    }
+    if patterns.len() > MAX_PATTERNS {
+        return Err(ApiError::validation(format!(
+            "At most {} event patterns are allowed",
+            MAX_PATTERNS
+        )));
     }
+    Ok(patterns)
+}
 
-    /// Get broadcast sender for publishing messages
-    pub fn get_broadcaster(&self) -> broadcast::Sender<WebSocketMessage> {
-        self.broadcast_tx.clone()
+/// Push events to `socket` until either side disconnects
+///
+/// `subscriber` is dropped on return, which ends the bus subscription.
+async fn serve_connection(
+    socket: WebSocket,
+    auth: AuthContext,
+    mut subscriber: EventSubscriber,
+    patterns: Vec<String>,
+    config: WebSocketConfig,
+) {
+    let metrics = telemetry::MetricsCollector::global();
+    metrics.gauge("websocket_connections").add(1.0);
+
+    let (sink, mut stream) = socket.split();
+    let mut connection = Connection {
+        id: Uuid::new_v4(),
+        sink,
+        filter: EventFilter::new(&auth, patterns, config.permission_cache_ttl),
+        config: &config,
+        awaiting_pong: false,
+        delivered: 0,
+    };
+    tracing::info!(connection_id = %connection.id, user_id = %auth.user_id, "WebSocket connection opened");
+
+    let mut ping = tokio::time::interval(config.ping_interval);
+    ping.reset();
+    let mut outcome = connection.send_connected().await;
+    while outcome.is_ok() {
+        outcome = tokio::select! {
+            received = subscriber.recv() => connection.on_event(received).await,
+            message = stream.next() => connection.on_message(message).await,
+            _ = ping.tick() => connection.on_ping_tick().await,
+        };
     }
 
-    /// Register a new WebSocket connection
-    pub async fn register_connection(
-        &self,
-        connection: WebSocketConnection,
-    ) -> Result<broadcast::Receiver<WebSocketMessage>> {
-        let mut connections = self.connections.write().await;
-        let receiver = self.broadcast_tx.subscribe();
-        
-        connections.insert(connection.connection_id.clone(), connection.clone());
-        
-        self.logger.info(&format!(
-            "WebSocket connection registered: {} (Type: {:?}, User: {:?})",
-            connection.connection_id,
-            connection.connection_type,
-            connection.user_id
-        )).await;
-
-        Ok(receiver)
+    connection.close(outcome).await;
+    metrics.gauge("websocket_connections").add(-1.0);
+}
+
+/// Outgoing half of a connection and its per-connection state
+struct Connection<'a> {
+    id: Uuid,
+    sink: SplitSink<WebSocket, Message>,
+    filter: EventFilter<'a>,
+    config: &'a WebSocketConfig,
+    awaiting_pong: bool,
+    delivered: u64,
+}
+
+impl Connection<'_> {
+    async fn send_connected(&mut self) -> Result<(), Disconnect> {
+        let text = frame_text(&ServerFrame::Connected {
+            connection_id: self.id,
+            subscriptions: self.filter.patterns(),
+        });
+        self.send_text(text).await
     }
 
-    /// Unregister a WebSocket connection
-    pub async fn unregister_connection(&self, connection_id: &str) {
-        let mut connections = self.connections.write().await;
-        
-        if let Some(connection) = connections.remove(connection_id) {
-            self.logger.info(&format!(
-                "WebSocket connection unregistered: {} (User: {:?})",
-                connection_id,
-                connection.user_id
-            )).await;
+    async fn on_event(&mut self, received: events_bus::Result<Option<Event>>) -> Result<(), Disconnect> {
+        match received {
+            Ok(Some(event)) => {
+                if !self.filter.allows(&event).await {
+                    return Ok(());
+                }
+                self.delivered = self.delivered.saturating_add(1);
+                self.send_text(frame_text(&ServerFrame::Event { event: &event })).await
+            }
+            Ok(None) => Err(Disconnect::Server {
+                code: CLOSE_GOING_AWAY,
+                reason: "server shutting down",
+            }),
+            Err(EventBusError::Lagged(missed)) => {
+                telemetry::MetricsCollector::global()
+                    .counter("websocket_events_dropped_total")
+                    .increment_by(f64::from(u32::try_from(missed).unwrap_or(u32::MAX)));
+                tracing::warn!(connection_id = %self.id, missed, "WebSocket client fell behind");
+                match self.config.slow_client_policy {
+                    SlowClientPolicy::DropEvents => {
+                        self.send_text(frame_text(&ServerFrame::EventsDropped { count: missed })).await
+                    }
+                    SlowClientPolicy::Disconnect => Err(Disconnect::Server {
+                        code: CLOSE_POLICY_VIOLATION,
+                        reason: "client too slow",
+                    }),
+                }
+            }
+            Err(e) => {
+                tracing::error!(connection_id = %self.id, error = %e, "Event subscription failed");
+                Err(Disconnect::Server {
+                    code: CLOSE_GOING_AWAY,
+                    reason: "event stream unavailable",
+                })
+            }
         }
     }
 
-    /// Broadcast message to all connections in a channel
-    pub async fn broadcast_to_channel(&self, channel: &str, message: WebSocketMessage) -> Result<()> {
-        let connections = self.connections.read().await;
-        
-        // Filter connections by channel subscription
-        let target_connections: Vec<_> = connections
-            .values()
-            .filter(|conn| conn.subscribed_channels.contains(&channel.to_string()))
-            .cloned()
-            .collect();
-
-        if !target_connections.is_empty() {
-            if let Err(e) = self.broadcast_tx.send(message.clone()) {
-                self.logger.error(&format!(
-                    "Failed to broadcast message to channel {}: {}",
-                    channel, e
-                )).await;
-                return Err(RustCareError::WebSocketError(format!("Broadcast failed: {}", e)));
+    async fn on_message(&mut self, message: Option<Result<Message, axum::Error>>) -> Result<(), Disconnect> {
+        match message {
+            Some(Ok(Message::Text(text))) => {
+                let reply = self.filter.apply(&text);
+                let reply_text = frame_text(&reply.as_frame(self.filter.patterns()));
+                self.send_text(reply_text).await
             }
-
-            self.logger.debug(&format!(
-                "Broadcasted message to {} connections in channel {}",
-                target_connections.len(),
-                channel
-            )).await;
+            Some(Ok(Message::Pong(_))) => {
+                self.awaiting_pong = false;
+                Ok(())
+            }
+            // Pings are answered by axum
+            Some(Ok(Message::Ping(_))) => Ok(()),
+            Some(Ok(Message::Binary(_))) => {
+                let error = ServerFrame::Error {
+                    message: "Binary frames are not supported".to_string(),
+                };
+                self.send_text(frame_text(&error)).await
+            }
+            Some(Ok(Message::Close(_)) | Err(_)) | None => Err(Disconnect::Client),
         }
-
-        Ok(())
     }
 
-    /// Send message to specific connection
-    pub async fn send_to_connection(&self, connection_id: &str, message: WebSocketMessage) -> Result<()> {
-        // In a real implementation, we would maintain individual connection senders
-        // For now, we'll use the broadcast mechanism with connection-specific filtering
-        self.broadcast_tx.send(message)
-            .map_err(|e| RustCareError::WebSocketError(format!("Send failed: {}", e)))?;
-        
-        Ok(())
+    async fn on_ping_tick(&mut self) -> Result<(), Disconnect> {
+        if self.awaiting_pong {
+            return Err(Disconnect::Server {
+                code: CLOSE_POLICY_VIOLATION,
+                reason: "ping timeout",
+            });
+        }
+        self.awaiting_pong = true;
+        self.send(Message::Ping(Vec::new())).await
     }
 
-    /// Get connection statistics
-    pub async fn get_connection_stats(&self) -> ConnectionStats {
-        let connections = self.connections.read().await;
-        
-        let mut stats = ConnectionStats {
-            total_connections: connections.len(),
-            connections_by_type: HashMap::new(),
-            connections_by_role: HashMap::new(),
+    /// Send the close frame for a server-side disconnect and log the closure
+    async fn close(&mut self, outcome: Result<(), Disconnect>) {
+        let reason = match outcome {
+            Err(Disconnect::Server { code, reason }) => {
+                let close = Message::Close(Some(CloseFrame {
+                    code,
+                    reason: Cow::Borrowed(reason),
+                }));
+                // Best effort: the client may already be unreachable
+                let _ = self.send(close).await;
+                reason
+            }
+            Err(Disconnect::Client) | Ok(()) => "client disconnected",
         };
+        tracing::info!(
+            connection_id = %self.id,
+            user_id = %self.filter.auth.user_id,
+            delivered = self.delivered,
+            reason,
+            "WebSocket connection closed"
+        );
+    }
 
-        for connection in connections.values() {
-            // Count by connection type
-            *stats.connections_by_type.entry(connection.connection_type.clone()).or_insert(0) += 1;
-            
-            // Count by user role
-            if let Some(ref role) = connection.user_role {
-                *stats.connections_by_role.entry(role.clone()).or_insert(0) += 1;
-            }
+    async fn send_text(&mut self, text: Option<String>) -> Result<(), Disconnect> {
+        match text {
+            Some(text) => self.send(Message::Text(text)).await,
+            None => Ok(()),
         }
+    }
 
-        stats
+    async fn send(&mut self, message: Message) -> Result<(), Disconnect> {
+        match tokio::time::timeout(self.config.send_timeout, self.sink.send(message)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(_)) => Err(Disconnect::Client),
+            Err(_) => Err(Disconnect::Server {
+                code: CLOSE_POLICY_VIOLATION,
+                reason: "client too slow",
+            }),
+        }
     }
 }
 
-/// Connection statistics
-#[derive(Debug, Serialize)]
-pub struct ConnectionStats {
-    pub total_connections: usize,
-    pub connections_by_type: HashMap<WebSocketConnectionType, usize>,
-    pub connections_by_role: HashMap<String, usize>,
+/// Serialize a frame; failures are logged and the frame skipped
+fn frame_text(frame: &ServerFrame<'_>) -> Option<String> {
+    serde_json::to_string(frame)
+        .map_err(|e| tracing::error!(error = %e, "Failed to serialize WebSocket frame"))
+        .ok()
 }
 
-/// Handle WebSocket upgrade and connection
-pub async fn websocket_handler(
-    ws: WebSocketUpgrade,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    State(server): State<RustCareServer>,
-) -> Response {
-    info!("WebSocket connection attempt from: {}", addr);
-    
-    ws.on_upgrade(move |socket| handle_websocket_connection(socket, addr, server))
+/// Resource an event concerns
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EventResource {
+    resource_type: String,
+    resource_id: Uuid,
 }
 
-/// Handle individual WebSocket connection
-async fn handle_websocket_connection(
-    socket: WebSocket,
-    addr: SocketAddr,
-    server: RustCareServer,
-) {
-    let connection_id = format!("ws_{}", Uuid::new_v4());
-    let logger = Arc::new(RedactedLogger::new("websocket").await);
-    let ws_manager = WebSocketManager::new(logger.clone());
-    
-    // Create connection metadata
-    let connection = WebSocketConnection {
-        connection_id: connection_id.clone(),
-        user_id: None, // Will be set after authentication
-        user_role: None, // Will be set after authentication
-        connected_at: chrono::Utc::now(),
-        client_ip: addr.ip().to_string(),
-        subscribed_channels: vec!["general".to_string()],
-        connection_type: WebSocketConnectionType::SystemNotifications,
-    };
+impl EventResource {
+    fn of(data: &Value) -> Option<Self> {
+        let uuid = |key: &str| data.get(key)?.as_str().and_then(|id| Uuid::parse_str(id).ok());
+        let resource = |resource_type: &str, resource_id| Self {
+            resource_type: resource_type.to_string(),
+            resource_id,
+        };
 
-    // Register connection and get broadcast receiver
-    let mut broadcast_rx = match ws_manager.register_connection(connection.clone()).await {
-        Ok(rx) => rx,
-        Err(e) => {
-            error!("Failed to register WebSocket connection: {}", e);
-            return;
+        if let Some(resource_type) = data.get("resource_type").and_then(Value::as_str) {
+            return uuid("resource_id").map(|id| resource(resource_type, id));
         }
-    };
+        uuid("patient_id")
+            .map(|id| resource("patient", id))
+            .or_else(|| uuid("organization_id").map(|id| resource("organization", id)))
+    }
+}
 
-    // Split socket into sender and receiver
-    let (mut sender, mut receiver) = socket.split();
-
-    // Send welcome message
-    let welcome_msg = WebSocketMessage {
-        id: Uuid::new_v4().to_string(),
-        message_type: WebSocketMessageType::ConnectionStatus,
-        channel: "system".to_string(),
-        payload: serde_json::json!({
-            "status": "connected",
-            "connection_id": connection_id,
-            "server_info": {
-                "name": "RustCare Engine",
-                "version": env!("CARGO_PKG_VERSION"),
-                "hipaa_compliant": true
-            }
-        }),
-        timestamp: chrono::Utc::now(),
-        sender_id: Some("system".to_string()),
-        hipaa_compliant: true,
-    };
+/// Reply to a client frame
+enum Reply {
+    Subscriptions,
+    Error(String),
+}
 
-    if let Ok(welcome_json) = serde_json::to_string(&welcome_msg) {
-        let _ = sender.send(Message::Text(welcome_json)).await;
+impl Reply {
+    fn as_frame<'a>(&self, patterns: &'a [String]) -> ServerFrame<'a> {
+        match self {
+            Self::Subscriptions => ServerFrame::Subscriptions {
+                subscriptions: patterns,
+            },
+            Self::Error(message) => ServerFrame::Error {
+                message: message.clone(),
+            },
+        }
     }
+}
 
-    // Handle incoming and outgoing messages concurrently
-    let connection_id_clone = connection_id.clone();
-    let ws_manager_clone = ws_manager.clone();
-    
-    tokio::select! {
-        // Handle incoming messages from client
-        _ = async {
-            while let Some(msg) = receiver.next().await {
-                match msg {
-                    Ok(Message::Text(text)) => {
-                        if let Err(e) = handle_client_message(&text, &connection_id, &server, &ws_manager).await {
-                            logger.error(&format!("Error handling client message: {}", e)).await;
-                        }
-                    }
-                    Ok(Message::Binary(bin)) => {
-                        debug!("Received binary message: {} bytes", bin.len());
-                        // Handle binary messages (e.g., file uploads) if needed
-                    }
-                    Ok(Message::Close(_)) => {
-                        info!("WebSocket connection closed by client: {}", connection_id);
-                        break;
-                    }
-                    Ok(Message::Ping(ping)) => {
-                        let _ = sender.send(Message::Pong(ping)).await;
-                    }
-                    Ok(Message::Pong(_)) => {
-                        // Handle pong if needed
-                    }
-                    Err(e) => {
-                        error!("WebSocket error: {}", e);
-                        break;
+/// Event selection and permission scoping for one connection
+struct EventFilter<'a> {
+    auth: &'a AuthContext,
+    patterns: Vec<String>,
+    decisions: HashMap<EventResource, (bool, Instant)>,
+    cache_ttl: Duration,
+}
+
+impl<'a> EventFilter<'a> {
+    fn new(auth: &'a AuthContext, patterns: Vec<String>, cache_ttl: Duration) -> Self {
+        Self {
+            auth,
+            patterns,
+            decisions: HashMap::new(),
+            cache_ttl,
+        }
+    }
+
+    fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Apply a client frame to the subscribed patterns
+    fn apply(&mut self, text: &str) -> Reply {
+        match serde_json::from_str::<ClientFrame>(text) {
+            Ok(ClientFrame::Subscribe { events }) => {
+                let patterns = match parse_patterns(&events) {
+                    Ok(patterns) => patterns,
+                    Err(e) => return Reply::Error(e.to_string()),
+                };
+                for pattern in patterns {
+                    if !self.patterns.contains(&pattern) {
+                        self.patterns.push(pattern);
                     }
                 }
-            }
-        } => {},
-        
-        // Handle broadcast messages
-        _ = async {
-            while let Ok(broadcast_msg) = broadcast_rx.recv().await {
-                if let Ok(msg_json) = serde_json::to_string(&broadcast_msg) {
-                    if sender.send(Message::Text(msg_json)).await.is_err() {
-                        break;
-                    }
+                if self.patterns.len() > MAX_PATTERNS {
+                    self.patterns.truncate(MAX_PATTERNS);
+                    return Reply::Error(format!("At most {} event patterns are allowed", MAX_PATTERNS));
                 }
+                Reply::Subscriptions
+            }
+            Ok(ClientFrame::Unsubscribe { events }) => {
+                let removed: Vec<&str> = events.split(',').map(str::trim).collect();
+                self.patterns.retain(|pattern| !removed.contains(&pattern.as_str()));
+                Reply::Subscriptions
             }
-        } => {}
+            Err(e) => Reply::Error(format!("Invalid message: {}", e)),
+        }
     }
 
-    // Cleanup connection
-    ws_manager_clone.unregister_connection(&connection_id_clone).await;
-    info!("WebSocket connection closed: {}", connection_id_clone);
+    /// Whether the connection is subscribed to `event` and may see it
+    async fn allows(&mut self, event: &Event) -> bool {
+        if !self
+            .patterns
+            .iter()
+            .any(|pattern| event_type_matches(pattern, &event.event_type))
+        {
+            return false;
+        }
+        let Some(resource) = EventResource::of(&event.data) else {
+            return false;
+        };
+        let organization = event
+            .data
+            .get("organization_id")
+            .and_then(Value::as_str)
+            .map(Uuid::parse_str);
+        match organization {
+            None => {}
+            Some(Ok(organization_id)) if organization_id == self.auth.organization_id => {}
+            Some(_) => return false,
+        }
+
+        if let Some(&(allowed, decided_at)) = self.decisions.get(&resource) {
+            if decided_at.elapsed() < self.cache_ttl {
+                return allowed;
+            }
+        }
+        match self
+            .auth
+            .check_permission(&resource.resource_type, Some(resource.resource_id), VIEW_PERMISSION)
+            .await
+        {
+            Ok(allowed) => {
+                if self.decisions.len() >= MAX_CACHED_DECISIONS {
+                    self.decisions.clear();
+                }
+                self.decisions.insert(resource, (allowed, Instant::now()));
+                allowed
+            }
+            Err(e) => {
+                // Not cached, so the next event for the resource retries
+                tracing::warn!(error = %e, "Permission check for WebSocket event failed");
+                false
+            }
+        }
+    }
 }
 
-/// Handle client message
-async fn handle_client_message(
-    text: &str,
-    connection_id: &str,
-    server: &RustCareServer,
-    ws_manager: &WebSocketManager,
-) -> Result<()> {
-    let client_msg: ClientMessage = serde_json::from_str(text)
-        .map_err(|e| RustCareError::WebSocketError(format!("Invalid message format: {}", e)))?;
-
-    match client_msg.action.as_str() {
-        "subscribe" => {
-            if let Some(channel) = client_msg.channel {
-                // Handle channel subscription
-                info!("Connection {} subscribing to channel: {}", connection_id, channel);
-                
-                let response = ServerResponse {
-                    success: true,
-                    message: format!("Subscribed to channel: {}", channel),
-                    data: Some(serde_json::json!({ "channel": channel })),
-                    timestamp: chrono::Utc::now(),
-                };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use events_bus::{DomainEvent, EventBus};
+    use serde_json::json;
+    use tokio_tungstenite::tungstenite;
+
+    fn event(event_type: &str, data: Value) -> Event {
+        DomainEvent::new(event_type, data).into()
+    }
 
-                // Send response back to client
-                let response_msg = WebSocketMessage {
-                    id: Uuid::new_v4().to_string(),
-                    message_type: WebSocketMessageType::SystemNotification,
-                    channel: "system".to_string(),
-                    payload: serde_json::to_value(response)?,
-                    timestamp: chrono::Utc::now(),
-                    sender_id: Some("system".to_string()),
-                    hipaa_compliant: true,
-                };
+    #[test]
+    fn test_events_resolve_to_the_resource_they_concern() {
+        let id = Uuid::new_v4();
+        let org = Uuid::new_v4();
+
+        let explicit = EventResource::of(&json!({"resource_type": "lab_result", "resource_id": id.to_string(), "patient_id": Uuid::new_v4().to_string()}));
+        assert_eq!(explicit.unwrap().resource_type, "lab_result");
+
+        let patient = EventResource::of(&json!({"patient_id": id.to_string(), "organization_id": org.to_string()})).unwrap();
+        assert_eq!((patient.resource_type.as_str(), patient.resource_id), ("patient", id));
 
-                ws_manager.send_to_connection(connection_id, response_msg).await?;
+        let organization = EventResource::of(&json!({"organization_id": org.to_string()})).unwrap();
+        assert_eq!(organization.resource_type, "organization");
+
+        assert!(EventResource::of(&json!({"resource_type": "lab_result", "resource_id": "not-a-uuid"})).is_none());
+        assert!(EventResource::of(&json!({"message": "maintenance at midnight"})).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_filter_scopes_events_to_permitted_resources_in_the_organization() {
+        let org = Uuid::new_v4();
+        let auth = AuthContext::with_permissions(Uuid::new_v4(), org, vec![], vec!["patient:view".to_string()]);
+        let mut filter = EventFilter::new(&auth, vec!["vitals.>".to_string()], Duration::from_secs(30));
+        let patient = Uuid::new_v4().to_string();
+
+        assert!(filter.allows(&event("vitals.alert", json!({"patient_id": patient, "organization_id": org.to_string()}))).await);
+        // Not subscribed
+        assert!(!filter.allows(&event("labs.result", json!({"patient_id": patient}))).await);
+        // Another tenant
+        assert!(!filter.allows(&event("vitals.alert", json!({"patient_id": patient, "organization_id": Uuid::new_v4().to_string()}))).await);
+        // No view permission on lab results
+        assert!(!filter.allows(&event("vitals.alert", json!({"resource_type": "lab_result", "resource_id": Uuid::new_v4().to_string()}))).await);
+        // Names no resource
+        assert!(!filter.allows(&event("vitals.alert", json!({"value": 120}))).await);
+    }
+
+    #[test]
+    fn test_clients_manage_their_subscriptions() {
+        let auth = AuthContext::new(Uuid::new_v4(), Uuid::new_v4());
+        let mut filter = EventFilter::new(&auth, vec![">".to_string()], Duration::from_secs(30));
+
+        assert!(matches!(filter.apply(r#"{"action":"subscribe","events":"vitals.*, labs.>"}"#), Reply::Subscriptions));
+        assert_eq!(filter.patterns(), [">", "vitals.*", "labs.>"]);
+        assert!(matches!(filter.apply(r#"{"action":"unsubscribe","events":">,labs.>"}"#), Reply::Subscriptions));
+        assert_eq!(filter.patterns(), ["vitals.*"]);
+
+        assert!(matches!(filter.apply(r#"{"action":"subscribe","events":" , "}"#), Reply::Error(_)));
+        assert!(matches!(filter.apply("not json"), Reply::Error(_)));
+        assert_eq!(filter.patterns(), ["vitals.*"]);
+    }
+
+    /// Serve `bus` on an ephemeral port to a user with `permissions`
+    async fn spawn_server(bus: EventBus, config: WebSocketConfig, permissions: Vec<String>) -> (String, Uuid) {
+        let org = Uuid::new_v4();
+        let handler = move |ws: WebSocketUpgrade| {
+            let bus = bus.clone();
+            let config = config.clone();
+            let auth = AuthContext::with_permissions(Uuid::new_v4(), org, vec![], permissions.clone());
+            async move {
+                let subscriber = bus.subscribe(">").await.unwrap();
+                ws.on_upgrade(move |socket| serve_connection(socket, auth, subscriber, vec![">".to_string()], config))
+            }
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, Router::new().route("/ws", get(handler))).await });
+        (format!("ws://{}/ws", addr), org)
+    }
+
+    async fn next_frame<S>(client: &mut S) -> Value
+    where
+        S: futures::Stream<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap() {
+                tungstenite::Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+                tungstenite::Message::Ping(_) => {}
+                other => panic!("unexpected message {:?}", other),
             }
         }
-        "unsubscribe" => {
-            if let Some(channel) = client_msg.channel {
-                info!("Connection {} unsubscribing from channel: {}", connection_id, channel);
+    }
+
+    #[tokio::test]
+    async fn test_pushes_only_permitted_events_and_unsubscribes_on_disconnect() {
+        let bus = EventBus::with_capacity(16);
+        let (url, org) = spawn_server(bus.clone(), WebSocketConfig::default(), vec!["patient:view".to_string()]).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        assert_eq!(next_frame(&mut client).await["type"], "connected");
+        assert_eq!(bus.subscriber_count(), 1);
+
+        let hidden = event("labs.result", json!({"resource_type": "lab_result", "resource_id": Uuid::new_v4().to_string()}));
+        let visible = event("vitals.alert", json!({"patient_id": Uuid::new_v4().to_string(), "organization_id": org.to_string()}));
+        bus.publish(hidden);
+        bus.publish(visible.clone());
+
+        let frame = next_frame(&mut client).await;
+        assert_eq!(frame["type"], "event");
+        assert_eq!(frame["event"]["id"], visible.id.to_string());
+
+        client.close(None).await.unwrap();
+        for _ in 0..50 {
+            if bus.subscriber_count() == 0 {
+                return;
             }
+            tokio::time::sleep(Duration::from_millis(20)).await;
         }
-        "ping" => {
-            let pong_msg = WebSocketMessage {
-                id: Uuid::new_v4().to_string(),
-                message_type: WebSocketMessageType::HeartBeat,
-                channel: "system".to_string(),
-                payload: serde_json::json!({ "type": "pong" }),
-                timestamp: chrono::Utc::now(),
-                sender_id: Some("system".to_string()),
-                hipaa_compliant: true,
+        panic!("subscription outlived the connection");
+    }
+
+    #[tokio::test]
+    async fn test_slow_clients_are_told_about_dropped_events_or_disconnected() {
+        for policy in [SlowClientPolicy::DropEvents, SlowClientPolicy::Disconnect] {
+            let bus = EventBus::with_capacity(2);
+            let config = WebSocketConfig {
+                slow_client_policy: policy,
+                ..WebSocketConfig::default()
             };
+            let (url, org) = spawn_server(bus.clone(), config, vec!["patient:view".to_string()]).await;
+            let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+            assert_eq!(next_frame(&mut client).await["type"], "connected");
+
+            // Overflow the connection's buffer faster than it is drained
+            let data = json!({"patient_id": Uuid::new_v4().to_string(), "organization_id": org.to_string()});
+            for _ in 0..10 {
+                bus.publish(event("vitals.alert", data.clone()));
+            }
 
-            ws_manager.send_to_connection(connection_id, pong_msg).await?;
-        }
-        _ => {
-            warn!("Unknown WebSocket action: {}", client_msg.action);
+            let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap();
+            match policy {
+                SlowClientPolicy::DropEvents => {
+                    let frame: Value = serde_json::from_str(frame.to_text().unwrap()).unwrap();
+                    assert_eq!(frame["type"], "events_dropped");
+                    assert_eq!(frame["count"], 8);
+                }
+                SlowClientPolicy::Disconnect => {
+                    let tungstenite::Message::Close(Some(close)) = frame else {
+                        panic!("expected a close frame, got {:?}", frame);
+                    };
+                    assert_eq!(u16::from(close.code), CLOSE_POLICY_VIOLATION);
+                }
+            }
         }
     }
 
-    Ok(())
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_unresponsive_clients_are_disconnected() {
+        let config = WebSocketConfig {
+            ping_interval: Duration::from_millis(50),
+            ..WebSocketConfig::default()
+        };
+        let (url, _) = spawn_server(EventBus::with_capacity(4), config, vec![]).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        assert_eq!(next_frame(&mut client).await["type"], "connected");
+
+        // Pongs are only sent while the client is polled
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap() {
+                tungstenite::Message::Ping(_) => {}
+                tungstenite::Message::Close(Some(close)) => {
+                    assert_eq!(close.reason, "ping timeout");
+                    return;
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+    }
+}
@@ -9,6 +9,9 @@ use crate::server::RustCareServer;
 use crate::middleware::AuthContext;
 use crate::error::{ApiError, ApiResponse, api_success};
 use crate::types::pagination::PaginationParams;
+use crate::services::workflow_executions::{
+    CancelError, ExecutionState, StartExecution, WorkflowExecutionRecord,
+};
 use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
@@ -302,17 +305,26 @@ pub async fn execute_workflow(
     auth: AuthContext,
     Json(execution_request): Json<WorkflowExecutionRequest>,
 ) -> Result<Json<ApiResponse<WorkflowExecutionResponse>>, ApiError> {
-    // TODO: Integrate with workflow-engine module
-    // This is a placeholder implementation
-    
-    let execution_id = Uuid::new_v4().to_string();
-    
-    let response = WorkflowExecutionResponse {
-        execution_id,
+    if execution_request.workflow_id.trim().is_empty() {
+        return Err(ApiError::validation("workflow_id is required"));
+    }
+
+    let record = server.workflow_executions.start(StartExecution {
         workflow_id: execution_request.workflow_id,
-        status: "running".to_string(),
-        started_at: chrono::Utc::now().to_rfc3339(),
-        current_step: Some("step_verify_identity".to_string()),
+        organization_id: auth.organization_id,
+        started_by: auth.user_id,
+        priority: execution_request.priority,
+        input_data: execution_request.input_data,
+        context: execution_request.execution_context.unwrap_or_default(),
+    })
+    .await;
+
+    let response = WorkflowExecutionResponse {
+        execution_id: record.execution_id.to_string(),
+        workflow_id: record.workflow_id,
+        status: record.state.as_str().to_string(),
+        started_at: record.started_at.to_rfc3339(),
+        current_step: record.current_step,
         progress_percent: 0.0,
     };
 
@@ -337,24 +349,14 @@ pub async fn get_execution_status(
     Path(execution_id): Path<String>,
     auth: AuthContext,
 ) -> Result<Json<ApiResponse<WorkflowExecutionStatus>>, ApiError> {
-    // TODO: Integrate with workflow-engine module
-    // This is a placeholder implementation
-    
-    let status = WorkflowExecutionStatus {
-        execution_id,
-        workflow_id: "wf_patient_registration".to_string(),
-        status: "completed".to_string(),
-        started_at: chrono::Utc::now().to_rfc3339(),
-        completed_at: Some(chrono::Utc::now().to_rfc3339()),
-        current_step: None,
-        completed_steps: vec!["step_verify_identity".to_string()],
-        failed_steps: vec![],
-        progress_percent: 100.0,
-        output_data: Some(HashMap::new()),
-        error_message: None,
-    };
+    let execution_id = parse_execution_id(&execution_id)?;
+    let record = server
+        .workflow_executions
+        .get(auth.organization_id, execution_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("workflow_execution"))?;
 
-    Ok(Json(api_success(status)))
+    Ok(Json(api_success(execution_status(record))))
 }
 
 /// Cancel workflow execution
@@ -376,9 +378,35 @@ pub async fn cancel_execution(
     Path(execution_id): Path<String>,
     auth: AuthContext,
 ) -> Result<StatusCode, ApiError> {
-    // TODO: Integrate with workflow-engine module
-    // This is a placeholder implementation
-    
-    // Simulate cancellation logic
-    Ok(StatusCode::NO_CONTENT)
-}
\ No newline at end of file
+    let execution_id = parse_execution_id(&execution_id)?;
+    match server.workflow_executions.cancel(auth.organization_id, execution_id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(CancelError::NotFound) => Err(ApiError::not_found("workflow_execution")),
+        Err(CancelError::AlreadyFinished(state)) => Err(ApiError::conflict(format!(
+            "Execution is already {}",
+            state.as_str()
+        ))),
+    }
+}
+
+fn parse_execution_id(execution_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(execution_id)
+        .map_err(|_| ApiError::validation("execution_id must be a UUID"))
+}
+
+fn execution_status(record: WorkflowExecutionRecord) -> WorkflowExecutionStatus {
+    let progress_percent = if record.state == ExecutionState::Completed { 100.0 } else { 0.0 };
+    WorkflowExecutionStatus {
+        execution_id: record.execution_id.to_string(),
+        workflow_id: record.workflow_id,
+        status: record.state.as_str().to_string(),
+        started_at: record.started_at.to_rfc3339(),
+        completed_at: record.completed_at.map(|at| at.to_rfc3339()),
+        current_step: record.current_step,
+        completed_steps: vec![],
+        failed_steps: vec![],
+        progress_percent,
+        output_data: None,
+        error_message: record.error_message,
+    }
+}
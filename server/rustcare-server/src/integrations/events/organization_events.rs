@@ -32,6 +32,7 @@ impl OrganizationEventPublisher {
             id: Uuid::new_v4(),
             event_type: "organization.created".to_string(),
             timestamp: chrono::Utc::now(),
+            partition_key: None,
             data: serde_json::json!({
                 "organization_id": org_id.to_string(),
                 "name": org_name,
@@ -70,6 +71,7 @@ impl OrganizationEventPublisher {
             id: Uuid::new_v4(),
             event_type: "organization.verified".to_string(),
             timestamp: chrono::Utc::now(),
+            partition_key: None,
             data: serde_json::json!({
                 "organization_id": org_id.to_string(),
                 "name": org_name,
@@ -106,6 +108,7 @@ impl OrganizationEventPublisher {
             id: Uuid::new_v4(),
             event_type: "organization.updated".to_string(),
             timestamp: chrono::Utc::now(),
+            partition_key: None,
             data: serde_json::json!({
                 "organization_id": org_id.to_string(),
                 "name": org_name,
@@ -143,6 +146,7 @@ impl OrganizationEventPublisher {
             id: Uuid::new_v4(),
             event_type: "organization.deleted".to_string(),
             timestamp: chrono::Utc::now(),
+            partition_key: None,
             data: serde_json::json!({
                 "organization_id": org_id.to_string(),
                 "name": org_name,
@@ -180,6 +184,7 @@ impl OrganizationEventPublisher {
             id: Uuid::new_v4(),
             event_type: "organization.subscription_changed".to_string(),
             timestamp: chrono::Utc::now(),
+            partition_key: None,
             data: serde_json::json!({
                 "organization_id": org_id.to_string(),
                 "name": org_name,
@@ -219,6 +224,7 @@ impl OrganizationEventPublisher {
             id: Uuid::new_v4(),
             event_type: "organization.email_domain_verified".to_string(),
             timestamp: chrono::Utc::now(),
+            partition_key: None,
             data: serde_json::json!({
                 "organization_id": org_id.to_string(),
                 "name": org_name,
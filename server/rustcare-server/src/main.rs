@@ -11,7 +11,8 @@ use tracing_subscriber::{
 };
 use tracing_subscriber::fmt::FormatFields;
 
-use rustcare_server::{create_app, RustCareServer, SecurityState};
+use events_bus::NatsJetStreamBroker;
+use rustcare_server::{create_app_with_security, security_middleware_state, RustCareServer, SecurityState};
 use error_common::{RustCareError, Result};
 
 /// RustCare Engine HTTP Server
@@ -84,15 +85,38 @@ async fn main() -> Result<()> {
     // Initialize the RustCare server
     let server = RustCareServer::new(&args.config).await?;
     
-    // Create the router with all routes
-    let app = create_app(server);
+    // Relay broker events to WebSocket clients when NATS is configured
+    let _event_relay = match std::env::var("NATS_URL") {
+        Ok(nats_url) => relay_broker_events(&nats_url, &server).await,
+        Err(_) => None,
+    };
 
-    // Start gRPC server if enabled (disabled temporarily)
-    let grpc_handle: Option<tokio::task::JoinHandle<()>> = None;
-    
-    if args.enable_grpc {
-        tracing::warn!("gRPC server is temporarily disabled for testing");
-    }
+    // HTTP and gRPC share rate limits
+    let security_middleware_state = security_middleware_state();
+
+    // Create the router with all routes
+    let app = create_app_with_security(server.clone(), security_middleware_state.clone());
+
+    // Both servers drain in-flight requests once a shutdown signal arrives
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("🛑 {}", "Shutdown signal received, draining requests...".bright_yellow());
+        let _ = shutdown_tx.send(true);
+    });
+
+    // Start gRPC server if enabled
+    let grpc_handle = if args.enable_grpc {
+        let grpc_addr = SocketAddr::from(([0, 0, 0, 0], args.grpc_port));
+        let shutdown = wait_for_shutdown(shutdown_rx.clone());
+        Some(tokio::spawn(async move {
+            if let Err(e) = rustcare_server::grpc::serve(grpc_addr, server, security_middleware_state, shutdown).await {
+                tracing::error!("gRPC server error: {}", e);
+            }
+        }))
+    } else {
+        None
+    };
 
     // Bind and serve HTTP server
     let addr = SocketAddr::from(([0, 0, 0, 0], args.port));
@@ -110,8 +134,23 @@ async fn main() -> Result<()> {
         info!("🔧 {}", format!("gRPC server available on grpc://{}:{}", args.host, args.grpc_port).bright_purple());
     }
 
+    // Serve /metrics on a dedicated admin listener when configured
+    if let Some(metrics_port) = rustcare_server::handlers::metrics::admin_port() {
+        let metrics_addr = SocketAddr::from(([0, 0, 0, 0], metrics_port));
+        let metrics_listener = tokio::net::TcpListener::bind(metrics_addr).await
+            .map_err(|e| RustCareError::NetworkError(format!("Failed to bind to {}: {}", metrics_addr, e)))?;
+        info!("📈 {}", format!("Metrics available at: http://{}:{}/metrics", args.host, metrics_port).bright_blue());
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(metrics_listener, rustcare_server::routes::metrics_routes::<()>()).await {
+                tracing::error!("Metrics server error: {}", e);
+            }
+        });
+    }
+
     // Run HTTP server
-    let http_result = axum::serve(listener, app).await
+    let http_result = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(wait_for_shutdown(shutdown_rx))
+        .await
         .map_err(|e| RustCareError::ServerError(format!("HTTP server error: {}", e)));
 
     // Wait for gRPC server to finish if it was started
@@ -120,9 +159,73 @@ async fn main() -> Result<()> {
     }
 
     http_result?;
+    info!("👋 {}", "RustCare Engine stopped".bright_cyan());
     Ok(())
 }
 
+/// Forward every event published on NATS to the server's event bus
+///
+/// The returned broker owns the subscription and must be kept alive.
+async fn relay_broker_events(nats_url: &str, server: &RustCareServer) -> Option<NatsJetStreamBroker> {
+    let broker = match NatsJetStreamBroker::new(nats_url).await {
+        Ok(broker) => broker,
+        Err(e) => {
+            tracing::warn!(error = %e, "NATS unavailable; WebSocket clients only receive local events");
+            return None;
+        }
+    };
+    let bus = server.event_bus.clone();
+    let relay = broker
+        .subscribe_to_events(">", move |event| {
+            bus.publish(event);
+            Ok(())
+        })
+        .await;
+    match relay {
+        Ok(_) => Some(broker),
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to subscribe to NATS events");
+            None
+        }
+    }
+}
+
+/// Completes on Ctrl+C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Completes once shutdown has been signalled on `shutdown`
+async fn wait_for_shutdown(mut shutdown: tokio::sync::watch::Receiver<bool>) {
+    // An error means the sender is gone, which only happens after it signalled
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
 async fn init_tracing(verbose: bool) -> Result<()> {
     let level = if verbose {
         Level::DEBUG
@@ -142,10 +245,23 @@ async fn init_tracing(verbose: bool) -> Result<()> {
             ).into()
         });
 
+    // Bridge spans to OpenTelemetry so W3C trace context propagates, and
+    // export spans and metrics over OTLP when OTEL_EXPORTER_OTLP_ENDPOINT is set
+    let mut telemetry_engine = telemetry::TelemetryEngine::new();
+    if let Some(otlp) = telemetry::OtlpConfig::from_env()
+        .map_err(|e| RustCareError::ConfigError(format!("Invalid OTLP configuration: {}", e)))?
+    {
+        telemetry_engine = telemetry_engine.with_otlp_config(otlp);
+    }
+    let tracing_system = telemetry_engine
+        .init("rustcare-server")
+        .map_err(|e| RustCareError::InternalError(format!("Telemetry init failed: {}", e)))?;
+
     if is_development && use_colors {
         // Beautiful colored development logging
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(tracing_system.layer())
             .with(
                 fmt::layer()
                     .with_target(true)
@@ -167,6 +283,7 @@ async fn init_tracing(verbose: bool) -> Result<()> {
         // Structured JSON logging for production
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(tracing_system.layer())
             .with(
                 fmt::layer()
                     .with_target(false)
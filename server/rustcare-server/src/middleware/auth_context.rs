@@ -2,15 +2,22 @@
 //!
 //! This module provides automatic extraction of authentication context from JWT tokens,
 //! eliminating the need for manual token parsing and placeholder user IDs.
+//!
+//! Requests may instead present an API key, either in the `X-API-Key` header or
+//! as `Authorization: ApiKey <key>`, when an `ApiKeyProvider` is in the request
+//! extensions. The key's scopes become the context's permissions.
 
 use axum::extract::FromRequestParts;
 use axum::http::{header::AUTHORIZATION, request::Parts, Method, HeaderMap};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use async_trait::async_trait;
+use auth_gateway::{ApiKeyPrincipal, ApiKeyProvider, GatewayError, TenantId};
+use database_layer::RlsContext;
 use std::sync::Arc;
 use crate::error::ApiError;
-use crate::middleware::{RequestContext, SecurityMiddlewareState};
+use crate::middleware::{RateLimitDecision, RequestContext, SecurityMiddlewareState};
+use crate::middleware::tenant::audit_cross_tenant_access;
 
 /// Authentication context extracted from JWT token
 ///
@@ -89,6 +96,16 @@ impl AuthContext {
         }
     }
     
+    /// Row-level security context for queries made on behalf of this caller
+    pub fn rls_context(&self) -> RlsContext {
+        RlsContext::new()
+            .with_user_id(self.user_id)
+            .with_tenant_id(self.organization_id.to_string())
+            .with_organization_id(self.organization_id)
+            .with_roles(self.roles.clone())
+            .with_permissions(self.permissions.clone())
+    }
+
     /// Get request ID (convenience method)
     pub fn request_id(&self) -> &str {
         &self.request.request_id
@@ -102,12 +119,10 @@ impl AuthContext {
     /// Get remaining rate limit requests
     pub async fn rate_limit_remaining(&self) -> u32 {
         if let Some(ref limiter) = self.rate_limiter {
-            let key = limiter.config.by_user.then(|| self.user_id.to_string())
-                .unwrap_or_else(|| {
-                    self.request.remote_addr
-                        .clone()
-                        .unwrap_or_else(|| "unknown".to_string())
-                });
+            let key = limiter.key_for(
+                Some((self.user_id, self.organization_id)),
+                self.request.remote_addr.as_deref(),
+            );
             limiter.remaining(&key).await
         } else {
             u32::MAX
@@ -164,9 +179,7 @@ impl AuthContext {
 }
 
 /// Extract and validate JWT token from Authorization header
-fn extract_token(parts: &Parts) -> Result<String, ApiError> {
-        let headers = &parts.headers;
-    
+pub(crate) fn extract_token(headers: &HeaderMap) -> Result<String, ApiError> {
     let auth_header = headers
         .get(AUTHORIZATION)
         .and_then(|h| h.to_str().ok())
@@ -179,10 +192,53 @@ fn extract_token(parts: &Parts) -> Result<String, ApiError> {
         .map(|s| s.to_string())
 }
 
+/// Header carrying an API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Extract a presented API key, if the request uses API key authentication
+pub(crate) fn extract_api_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get(API_KEY_HEADER).and_then(|h| h.to_str().ok()) {
+        return Some(key.trim());
+    }
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("ApiKey "))
+        .map(str::trim)
+}
+
+/// Authenticate an API key against the provider in the request extensions
+async fn validate_api_key(parts: &Parts, key: &str, remote_addr: Option<&str>) -> Result<AuthContext, ApiError> {
+    let provider = parts
+        .extensions
+        .get::<Arc<ApiKeyProvider>>()
+        .ok_or_else(|| ApiError::authentication("API key authentication is not enabled"))?;
+    let client_ip = remote_addr.and_then(|addr| addr.parse().ok());
+
+    let ApiKeyPrincipal {
+        key_id,
+        owner_id,
+        organization_id,
+        scopes,
+    } = provider.validate(key, client_ip).await?;
+    tracing::debug!(key_id = %key_id, "Authenticated request with API key");
+
+    Ok(AuthContext::with_permissions(owner_id, organization_id, Vec::new(), scopes))
+}
+
+/// Resolve the authenticated caller from request headers
+///
+/// Unlike the `AuthContext` extractor this performs no rate limiting or CSRF
+/// checks, so middleware can scope state to the caller without side effects.
+pub(crate) fn authenticated_context(headers: &HeaderMap) -> Option<AuthContext> {
+    let token = extract_token(headers).ok()?;
+    validate_jwt_token(&token).ok()
+}
+
 /// Validate JWT token and extract claims
 ///
 /// Uses the existing TokenClaims structure from auth/tokens module
-fn validate_jwt_token(token: &str) -> Result<AuthContext, ApiError> {
+pub(crate) fn validate_jwt_token(token: &str) -> Result<AuthContext, ApiError> {
     // For now, we'll use a simple approach:
     // 1. Try to decode the token using jsonwebtoken
     // 2. Extract claims
@@ -275,14 +331,29 @@ where
         // Extract RequestContext first (for same-site validation)
         let request = RequestContext::from_request_parts(parts, _state).await?;
         
-        // Extract JWT token from Authorization header
-        let token = extract_token(parts)?;
-        
-        // Validate and decode JWT
-        let mut auth_ctx = validate_jwt_token(&token)?;
+        // Authenticate with an API key when one is presented, otherwise with a JWT
+        let mut auth_ctx = match extract_api_key(&parts.headers) {
+            Some(key) => validate_api_key(parts, key, request.remote_addr.as_deref()).await?,
+            None => validate_jwt_token(&extract_token(&parts.headers)?)?,
+        };
         
         // Attach request context
         auth_ctx.request = request;
+
+        // Hold the caller to the tenant the request was bound to
+        if let Some(tenant) = auth_ctx.request.tenant_id {
+            let caller = TenantId::new(auth_ctx.organization_id);
+            if caller != tenant {
+                audit_cross_tenant_access(
+                    Some(auth_ctx.user_id),
+                    caller,
+                    tenant,
+                    &format!("{} {}", parts.method, parts.uri.path()),
+                    auth_ctx.request.remote_addr.as_deref(),
+                );
+                return Err(GatewayError::TenantMismatch { caller, target: tenant }.into());
+            }
+        }
         
         // Get security middleware state from extensions (if available)
         let security_state = parts.extensions
@@ -292,14 +363,16 @@ where
         // Perform rate limiting check if security state is available
         if let Some(ref state) = security_state {
             if let Some(ref limiter) = state.rate_limiter {
-                let key = if limiter.config.by_user {
-                    auth_ctx.user_id.to_string()
-                } else {
-                    auth_ctx.request.remote_addr
-                        .clone()
-                        .unwrap_or_else(|| "unknown".to_string())
-                };
-                limiter.check(&key).await?;
+                // Skip if rate_limit_middleware already counted this request,
+                // and record the decision so later extractors skip it too
+                if parts.extensions.get::<RateLimitDecision>().is_none() {
+                    let key = limiter.key_for(
+                        Some((auth_ctx.user_id, auth_ctx.organization_id)),
+                        auth_ctx.request.remote_addr.as_deref(),
+                    );
+                    let decision = limiter.check(&key).await?;
+                    parts.extensions.insert(decision);
+                }
                 
                 // Attach rate limiter for remaining() method
                 auth_ctx.rate_limiter = state.rate_limiter.clone();
@@ -307,9 +380,7 @@ where
             
             // Perform CSRF validation for state-changing methods
             if let Some(ref validator) = state.csrf_validator {
-                let method = &parts.method;
-                let headers = &parts.headers;
-                validator.validate(method, headers, auth_ctx.request.origin.as_ref())?;
+                validator.validate(&parts.method, &parts.headers)?;
             }
             
             // Enforce strict same-site if configured
@@ -346,6 +417,95 @@ mod tests {
         assert!(ctx.permissions.is_empty());
     }
 
+    async fn authenticate(provider: &Arc<ApiKeyProvider>, header: (&str, String)) -> Result<AuthContext, ApiError> {
+        let request = axum::http::Request::get("/patients")
+            .header(header.0, header.1)
+            .extension(Arc::clone(provider))
+            .body(())
+            .unwrap();
+        let (mut parts, ()) = request.into_parts();
+        AuthContext::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_authenticate_as_their_owner_with_scoped_permissions() {
+        let provider = Arc::new(ApiKeyProvider::default());
+        let (owner, org) = (Uuid::new_v4(), Uuid::new_v4());
+        let issued = provider
+            .issue(auth_gateway::IssueApiKey::new(owner, org, "lab-sync", ["lab_results:read"]))
+            .await
+            .unwrap();
+
+        for header in [(API_KEY_HEADER, issued.key.clone()), ("authorization", format!("ApiKey {}", issued.key))] {
+            let ctx = authenticate(&provider, header).await.unwrap();
+            assert_eq!((ctx.user_id, ctx.organization_id), (owner, org));
+            assert!(ctx.check_permission("lab_results", None, "read").await.unwrap());
+            assert!(!ctx.check_permission("lab_results", None, "write").await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revoked_api_keys_are_rejected_with_a_distinct_error() {
+        let provider = Arc::new(ApiKeyProvider::default());
+        let issued = provider
+            .issue(auth_gateway::IssueApiKey::new(Uuid::new_v4(), Uuid::new_v4(), "old", ["patients:read"]))
+            .await
+            .unwrap();
+        provider.revoke(&issued.key_id).await.unwrap();
+
+        let err = authenticate(&provider, (API_KEY_HEADER, issued.key)).await.unwrap_err();
+        assert_eq!(err.status_code(), axum::http::StatusCode::UNAUTHORIZED);
+        assert_eq!(err.error_type(), "api_key_revoked");
+        let unknown = authenticate(&provider, (API_KEY_HEADER, "rck_nope_nope".to_string())).await.unwrap_err();
+        assert_eq!(unknown.error_type(), "api_key_invalid");
+    }
+
+    #[tokio::test]
+    async fn test_spoofed_forwarded_for_does_not_satisfy_ip_allow_list() {
+        let allowed: std::net::IpAddr = "203.0.113.7".parse().unwrap();
+        let provider = Arc::new(ApiKeyProvider::default());
+        let issued = provider
+            .issue(auth_gateway::IssueApiKey::new(Uuid::new_v4(), Uuid::new_v4(), "pinned", ["patients:read"]).allow_ip(allowed))
+            .await
+            .unwrap();
+        let request = |peer: &str| {
+            let request = axum::http::Request::get("/api/v1/patients")
+                .header(API_KEY_HEADER, issued.key.clone())
+                .header("X-Forwarded-For", allowed.to_string())
+                .extension(Arc::clone(&provider))
+                .extension(axum::extract::ConnectInfo(peer.parse::<std::net::SocketAddr>().unwrap()))
+                .body(())
+                .unwrap();
+            request.into_parts().0
+        };
+
+        let mut spoofed = request("198.51.100.9:40000");
+        let err = AuthContext::from_request_parts(&mut spoofed, &()).await.unwrap_err();
+        assert_eq!(err.status_code(), axum::http::StatusCode::FORBIDDEN);
+
+        let mut direct = request("203.0.113.7:40000");
+        assert!(AuthContext::from_request_parts(&mut direct, &()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_callers_outside_the_bound_tenant_are_rejected() {
+        let provider = Arc::new(ApiKeyProvider::default());
+        let issued = provider
+            .issue(auth_gateway::IssueApiKey::new(Uuid::new_v4(), Uuid::new_v4(), "etl", ["patients:read"]))
+            .await
+            .unwrap();
+        let request = axum::http::Request::get("/api/v1/patients")
+            .header(API_KEY_HEADER, issued.key)
+            .extension(provider)
+            .extension(TenantId::new(Uuid::new_v4()))
+            .body(())
+            .unwrap();
+        let (mut parts, ()) = request.into_parts();
+
+        let err = AuthContext::from_request_parts(&mut parts, &()).await.unwrap_err();
+        assert_eq!(err.status_code(), axum::http::StatusCode::FORBIDDEN);
+    }
+
     #[test]
     fn test_extract_token_format() {
         // Test that extract_token properly strips "Bearer " prefix
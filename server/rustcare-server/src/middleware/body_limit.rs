@@ -0,0 +1,257 @@
+//! Request body size limits and JSON structure guards
+//!
+//! Rejects oversized bodies with 413 before they are buffered (via
+//! `Content-Length` up front, and by capping the stream for chunked bodies),
+//! and rejects JSON bodies nested too deeply or with too many elements in a
+//! single array/object with a structured 400 before handlers deserialize them.
+//!
+//! Limits can be raised per route (e.g. file uploads) with
+//! `BodyLimitConfig::with_route_limit`.
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use std::collections::HashMap;
+use crate::error::ApiError;
+
+/// Structural limits applied to JSON request bodies
+#[derive(Debug, Clone)]
+pub struct JsonLimits {
+    /// Maximum nesting depth of arrays/objects
+    pub max_depth: usize,
+    /// Maximum number of elements in a single array or object
+    pub max_elements: usize,
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 32,
+            max_elements: 10_000,
+        }
+    }
+}
+
+impl JsonLimits {
+    /// Scan a JSON document without parsing it and enforce the limits
+    ///
+    /// Malformed JSON is left for the handler's extractor to reject.
+    pub fn check(&self, bytes: &[u8]) -> Result<(), ApiError> {
+        // Element count of each open container
+        let mut stack: Vec<usize> = Vec::new();
+        let mut in_string = false;
+        let mut escaped = false;
+
+        for &byte in bytes {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match byte {
+                b' ' | b'\t' | b'\n' | b'\r' | b':' => {}
+                b']' | b'}' => {
+                    stack.pop();
+                }
+                b',' => {
+                    if let Some(count) = stack.last_mut() {
+                        *count += 1;
+                        if *count > self.max_elements {
+                            return Err(ApiError::bad_request(format!(
+                                "JSON body has more than {} elements in an array or object",
+                                self.max_elements
+                            )));
+                        }
+                    }
+                }
+                _ => {
+                    // First token of a container is its first element
+                    if let Some(count) = stack.last_mut() {
+                        if *count == 0 {
+                            *count = 1;
+                        }
+                    }
+                    match byte {
+                        b'"' => in_string = true,
+                        b'[' | b'{' => {
+                            stack.push(0);
+                            if stack.len() > self.max_depth {
+                                return Err(ApiError::bad_request(format!(
+                                    "JSON body is nested deeper than {} levels",
+                                    self.max_depth
+                                )));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Body limit configuration, made available to the middleware via extensions
+#[derive(Debug, Clone)]
+pub struct BodyLimitConfig {
+    /// Default maximum request body size in bytes
+    pub max_body_bytes: usize,
+    /// Structural limits for JSON bodies
+    pub json: JsonLimits,
+    /// Per-route overrides of `max_body_bytes`, keyed by route pattern
+    pub route_limits: HashMap<String, usize>,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 2 * 1024 * 1024,
+            json: JsonLimits::default(),
+            route_limits: HashMap::new(),
+        }
+    }
+}
+
+impl BodyLimitConfig {
+    /// Override the body size limit for a route, e.g. an upload endpoint
+    #[must_use]
+    pub fn with_route_limit(mut self, route: impl Into<String>, max_body_bytes: usize) -> Self {
+        self.route_limits.insert(route.into(), max_body_bytes);
+        self
+    }
+
+    /// Body size limit that applies to `route`
+    pub fn max_body_bytes_for(&self, route: &str) -> usize {
+        self.route_limits.get(route).copied().unwrap_or(self.max_body_bytes)
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|ct| ct.split(';').next())
+        .is_some_and(|mime| {
+            let mime = mime.trim();
+            mime.eq_ignore_ascii_case("application/json") || mime.ends_with("+json")
+        })
+}
+
+fn too_large(max_body_bytes: usize) -> Response {
+    ApiError::payload_too_large(format!("Request body exceeds {} bytes", max_body_bytes)).into_response()
+}
+
+/// Body size and JSON structure guard middleware
+pub async fn body_limit_middleware(request: Request, next: Next) -> Response {
+    let Some(config) = request.extensions().get::<BodyLimitConfig>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let max_body_bytes = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(config.max_body_bytes, |path| config.max_body_bytes_for(path.as_str()));
+
+    // Reject declared oversized bodies without reading them
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > max_body_bytes as u64) {
+        return too_large(max_body_bytes);
+    }
+
+    let check_json = is_json(request.headers());
+    let (parts, body) = request.into_parts();
+    let limited = Limited::new(body, max_body_bytes);
+
+    // Non-JSON bodies stream through; extractors see the length error if exceeded
+    if !check_json {
+        return next.run(Request::from_parts(parts, Body::new(limited))).await;
+    }
+
+    let bytes = match limited.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) if e.downcast_ref::<LengthLimitError>().is_some() => return too_large(max_body_bytes),
+        Err(e) => {
+            return ApiError::bad_request(format!("Failed to read request body: {}", e)).into_response();
+        }
+    };
+
+    if let Err(e) = config.json.check(&bytes) {
+        return e.into_response();
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, middleware::from_fn, routing::post, Extension, Router};
+    use tower::ServiceExt;
+
+    fn app(config: BodyLimitConfig) -> Router {
+        Router::new()
+            .route("/records", post(|body: String| async move { body.len().to_string() }))
+            .route("/uploads", post(|body: String| async move { body.len().to_string() }))
+            .layer(from_fn(body_limit_middleware))
+            .layer(Extension(config))
+    }
+
+    fn json_request(path: &str, body: String) -> Request {
+        Request::builder()
+            .method("POST")
+            .uri(path)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_json_depth_and_element_limits() {
+        let limits = JsonLimits { max_depth: 3, max_elements: 3 };
+        assert!(limits.check(br#"{"a":{"b":[1,2,3]}}"#).is_ok());
+        assert!(limits.check(br#"{"a":{"b":[[1]]}}"#).is_err());
+        assert!(limits.check(b"[1,2,3,4]").is_err());
+        // Brackets and commas inside strings are not structure
+        assert!(limits.check(br#"{"a":"[[[[,,,,\"]]]]"}"#).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_rejected_with_413() {
+        let config = BodyLimitConfig {
+            max_body_bytes: 16,
+            ..BodyLimitConfig::default()
+        }
+        .with_route_limit("/uploads", 1024);
+        let app = app(config);
+        let body = format!("\"{}\"", "x".repeat(64));
+
+        let rejected = app.clone().oneshot(json_request("/records", body.clone())).await.unwrap();
+        assert_eq!(rejected.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let accepted = app.oneshot(json_request("/uploads", body)).await.unwrap();
+        assert_eq!(accepted.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_deeply_nested_json_rejected_with_400() {
+        let app = app(BodyLimitConfig::default());
+        let body = format!("{}{}", "[".repeat(100), "]".repeat(100));
+        let response = app.oneshot(json_request("/records", body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
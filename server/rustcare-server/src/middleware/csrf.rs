@@ -0,0 +1,128 @@
+//! CSRF and `SameSite` enforcement for every request
+//!
+//! Handlers that take an `AuthContext` already run the CSRF check, but
+//! cookie-authenticated routes do not go through that extractor. This
+//! middleware applies the double-submit check from `CsrfValidator` to all
+//! requests and, when `strict_same_site` is configured, rewrites every
+//! cookie the server sets to `SameSite=Strict`.
+
+use crate::middleware::SecurityMiddlewareState;
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Reject cross-site forgeries and harden outgoing cookies
+///
+/// A no-op unless `SecurityMiddlewareState` is in the request extensions.
+pub async fn csrf_middleware(request: Request, next: Next) -> Response {
+    let Some(state) = request.extensions().get::<SecurityMiddlewareState>().cloned() else {
+        return next.run(request).await;
+    };
+
+    if let Some(ref validator) = state.csrf_validator {
+        if let Err(e) = validator.validate(request.method(), request.headers()) {
+            tracing::warn!(
+                method = %request.method(),
+                path = %request.uri().path(),
+                reason = e.error_type(),
+                "CSRF validation failed"
+            );
+            return e.into_response();
+        }
+    }
+
+    let mut response = next.run(request).await;
+    if state.config.strict_same_site {
+        enforce_strict_same_site(response.headers_mut());
+    }
+    response
+}
+
+/// Replace the `SameSite` attribute of every `Set-Cookie` header with `Strict`
+fn enforce_strict_same_site(headers: &mut HeaderMap) {
+    let cookies: Vec<HeaderValue> = headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|value| match value.to_str() {
+            Ok(cookie) => {
+                let mut attributes: Vec<&str> = cookie
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|attribute| !attribute.to_ascii_lowercase().starts_with("samesite"))
+                    .collect();
+                attributes.push("SameSite=Strict");
+                HeaderValue::from_str(&attributes.join("; ")).unwrap_or_else(|_| value.clone())
+            }
+            Err(_) => value.clone(),
+        })
+        .collect();
+
+    if cookies.is_empty() {
+        return;
+    }
+    headers.remove(header::SET_COOKIE);
+    for cookie in cookies {
+        headers.append(header::SET_COOKIE, cookie);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{SecurityConfig, SecurityMiddlewareState};
+    use axum::{body::Body, http::StatusCode, middleware::from_fn, routing::post, Extension, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_set_cookies_are_rewritten_to_strict() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::SET_COOKIE, "session=abc; Path=/; SameSite=None; Secure".parse().unwrap());
+        headers.append(header::SET_COOKIE, "theme=dark".parse().unwrap());
+
+        enforce_strict_same_site(&mut headers);
+
+        let cookies: Vec<_> = headers.get_all(header::SET_COOKIE).iter().map(|v| v.to_str().unwrap()).collect();
+        assert_eq!(
+            cookies,
+            ["session=abc; Path=/; Secure; SameSite=Strict", "theme=dark; SameSite=Strict"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cookie_requests_without_a_matching_token_are_forbidden() {
+        let state = SecurityMiddlewareState::new(SecurityConfig {
+            rate_limit: None,
+            strict_same_site: true,
+            ..SecurityConfig::default()
+        });
+        let app = Router::new()
+            .route(
+                "/notes",
+                post(|| async { ([(header::SET_COOKIE, "session=abc; SameSite=Lax")], "ok") }),
+            )
+            .layer(from_fn(csrf_middleware))
+            .layer(Extension(state));
+
+        let forged = Request::post("/notes")
+            .header(header::COOKIE, "session=abc; csrf_token=t0k3n")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(forged).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error_type"], "csrf_token_missing");
+
+        let genuine = Request::post("/notes")
+            .header(header::COOKIE, "session=abc; csrf_token=t0k3n")
+            .header("X-CSRF-Token", "t0k3n")
+            .body(Body::empty())
+            .unwrap();
+        let accepted = app.oneshot(genuine).await.unwrap();
+        assert_eq!(accepted.status(), StatusCode::OK);
+        assert_eq!(accepted.headers()[header::SET_COOKIE], "session=abc; SameSite=Strict");
+    }
+}
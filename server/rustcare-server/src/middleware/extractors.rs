@@ -7,7 +7,7 @@ use axum::extract::FromRequestParts;
 use axum::http::{Method, HeaderMap, request::Parts};
 use async_trait::async_trait;
 use crate::error::ApiError;
-use crate::middleware::{AuthContext, RequestContext, SecurityContext, SecurityMiddlewareState};
+use crate::middleware::{AuthContext, RateLimitDecision, RequestContext, SecurityContext, SecurityMiddlewareState};
 
 /// Extractor that automatically creates SecurityContext with all checks
 /// 
@@ -53,6 +53,7 @@ where
             method,
             headers,
             security_state,
+            parts.extensions.get::<RateLimitDecision>(),
         ).await?;
         
         Ok(SecureContext(security))
@@ -0,0 +1,718 @@
+//! Idempotency-Key support for mutating endpoints
+//!
+//! Clients retry POSTs after timeouts, which would otherwise create duplicate
+//! patients or orders. When a mutating request carries an `Idempotency-Key`
+//! header, the first response is stored under (key, method + route, caller)
+//! and replayed byte-for-byte for any retry within the TTL. A retry that
+//! arrives while the original is still being processed gets `409 Conflict`;
+//! one whose body differs from the original's gets `422 Unprocessable Entity`.
+//!
+//! - Only POST, PUT, PATCH and DELETE are considered
+//! - 5xx responses, and responses over `max_body_bytes`, are not stored so
+//!   the client can retry them
+//! - The caller is the user a valid JWT names, or the presented API key;
+//!   requests with neither pass through untouched, since there is no
+//!   principal to scope the key to
+//! - Routes that are naturally idempotent opt out with
+//!   `IdempotencyConfig::exempt_route`
+//!
+//! Use `RedisIdempotencyStore` when running more than one replica; the
+//! in-memory store only deduplicates retries that land on the same instance.
+
+use async_trait::async_trait;
+use axum::{
+    body::{Body, Bytes},
+    extract::{MatchedPath, Request},
+    http::{HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, RwLock};
+use crate::error::ApiError;
+use crate::middleware::auth_context::{authenticated_context, extract_api_key};
+
+/// Request header carrying the client-chosen idempotency key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set on replayed responses
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Idempotency configuration
+#[derive(Debug, Clone)]
+pub struct IdempotencyConfig {
+    /// How long a completed response is replayed
+    pub ttl: Duration,
+    /// How long an in-flight request holds its key before a retry may run again
+    pub lock_ttl: Duration,
+    /// Responses with larger bodies are streamed back but not stored
+    pub max_body_bytes: usize,
+    /// Requests with larger bodies are rejected, as their body is buffered
+    /// to fingerprint it
+    pub max_request_bytes: usize,
+    /// Maximum accepted length of the `Idempotency-Key` header
+    pub max_key_length: usize,
+    /// Route patterns (as registered with the router) that skip idempotency
+    pub exempt_routes: HashSet<String>,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(24 * 60 * 60),
+            lock_ttl: Duration::from_secs(60),
+            max_body_bytes: 1024 * 1024,
+            max_request_bytes: 50 * 1024 * 1024,
+            max_key_length: 255,
+            exempt_routes: HashSet::new(),
+        }
+    }
+}
+
+impl IdempotencyConfig {
+    /// Opt a naturally idempotent route out, e.g. `"/api/v1/patients/:id"`
+    #[must_use]
+    pub fn exempt_route(mut self, route: impl Into<String>) -> Self {
+        self.exempt_routes.insert(route.into());
+        self
+    }
+}
+
+/// A response captured for replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Exact response body bytes (base64 when serialized)
+    #[serde(with = "base64_bytes")]
+    pub body: Vec<u8>,
+}
+
+impl StoredResponse {
+    /// Rebuild the response, marking it as a replay
+    pub fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = status;
+
+        let headers = response.headers_mut();
+        for (name, value) in self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Outcome of claiming an idempotency key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyStatus {
+    /// Key was free; the caller now owns it and must complete or release it
+    Started,
+    /// Another request with this key is still being processed
+    InFlight,
+    /// A response was already recorded for this key
+    Completed(StoredResponse),
+    /// The key is held by a request with a different body
+    Mismatch,
+}
+
+/// Backing store for idempotency keys
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Atomically claim `key` for a request whose body hashes to
+    /// `fingerprint`, or report what already holds it
+    async fn begin(&self, key: &str, fingerprint: &str, lock_ttl: Duration) -> Result<IdempotencyStatus, ApiError>;
+
+    /// Record the response for a claimed key
+    async fn complete(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        response: &StoredResponse,
+        ttl: Duration,
+    ) -> Result<(), ApiError>;
+
+    /// Give up a claimed key without recording a response
+    async fn release(&self, key: &str) -> Result<(), ApiError>;
+}
+
+/// Stored state for a key, with the fingerprint of the request holding it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum Entry {
+    InFlight { fingerprint: String },
+    Completed { fingerprint: String, response: StoredResponse },
+}
+
+impl Entry {
+    /// What a request hashing to `fingerprint` finds under this entry
+    fn status_for(self, fingerprint: &str) -> IdempotencyStatus {
+        match self {
+            Entry::InFlight { fingerprint: held } | Entry::Completed { fingerprint: held, .. } if held != fingerprint => {
+                IdempotencyStatus::Mismatch
+            }
+            Entry::InFlight { .. } => IdempotencyStatus::InFlight,
+            Entry::Completed { response, .. } => IdempotencyStatus::Completed(response),
+        }
+    }
+}
+
+/// In-memory idempotency store (for single-instance deployments)
+#[derive(Debug, Default)]
+pub struct InMemoryIdempotencyStore {
+    entries: RwLock<HashMap<String, (Entry, Instant)>>,
+}
+
+impl InMemoryIdempotencyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn begin(&self, key: &str, fingerprint: &str, lock_ttl: Duration) -> Result<IdempotencyStatus, ApiError> {
+        let mut entries = self.entries.write().await;
+        let now = Instant::now();
+
+        // Clean up expired entries periodically
+        if entries.len() > 10000 {
+            entries.retain(|_, (_, expires_at)| *expires_at > now);
+        }
+
+        match entries.get(key) {
+            Some((entry, expires_at)) if *expires_at > now => Ok(entry.clone().status_for(fingerprint)),
+            _ => {
+                let entry = Entry::InFlight { fingerprint: fingerprint.to_string() };
+                entries.insert(key.to_string(), (entry, now + lock_ttl));
+                Ok(IdempotencyStatus::Started)
+            }
+        }
+    }
+
+    async fn complete(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        response: &StoredResponse,
+        ttl: Duration,
+    ) -> Result<(), ApiError> {
+        let entry = Entry::Completed {
+            fingerprint: fingerprint.to_string(),
+            response: response.clone(),
+        };
+        self.entries
+            .write()
+            .await
+            .insert(key.to_string(), (entry, Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn release(&self, key: &str) -> Result<(), ApiError> {
+        self.entries.write().await.remove(key);
+        Ok(())
+    }
+}
+
+/// Redis-backed idempotency store shared by all replicas
+///
+/// Keys are claimed with `SET NX PX`, so only one replica runs the handler.
+pub struct RedisIdempotencyStore {
+    client: redis::Client,
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl RedisIdempotencyStore {
+    /// Create a store for `redis_url`; the connection is opened on first use
+    pub fn new(redis_url: &str) -> Result<Self, ApiError> {
+        let client = redis::Client::open(redis_url)
+            .map_err(|e| ApiError::internal(format!("Invalid Redis URL for idempotency store: {}", e)))?;
+        Ok(Self {
+            client,
+            connection: OnceCell::new(),
+        })
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager, ApiError> {
+        self.connection
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+            .await
+            .cloned()
+            .map_err(redis_unavailable)
+    }
+
+    fn redis_key(key: &str) -> String {
+        format!("idempotency:{}", key)
+    }
+}
+
+fn redis_unavailable(e: redis::RedisError) -> ApiError {
+    ApiError::service_unavailable(format!("Idempotency store unavailable: {}", e))
+}
+
+fn ttl_millis(ttl: Duration) -> u64 {
+    u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1)
+}
+
+#[async_trait]
+impl IdempotencyStore for RedisIdempotencyStore {
+    async fn begin(&self, key: &str, fingerprint: &str, lock_ttl: Duration) -> Result<IdempotencyStatus, ApiError> {
+        let mut conn = self.connection().await?;
+        let redis_key = Self::redis_key(key);
+        let in_flight = serde_json::to_string(&Entry::InFlight { fingerprint: fingerprint.to_string() })?;
+
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(&redis_key)
+            .arg(&in_flight)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_millis(lock_ttl))
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_unavailable)?;
+        if claimed.is_some() {
+            return Ok(IdempotencyStatus::Started);
+        }
+
+        let existing: Option<String> = conn.get(&redis_key).await.map_err(redis_unavailable)?;
+        match existing.map(|raw| serde_json::from_str::<Entry>(&raw)).transpose()? {
+            Some(entry) => Ok(entry.status_for(fingerprint)),
+            // Expired between SET and GET: report in-flight and let the client retry
+            None => Ok(IdempotencyStatus::InFlight),
+        }
+    }
+
+    async fn complete(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        response: &StoredResponse,
+        ttl: Duration,
+    ) -> Result<(), ApiError> {
+        let mut conn = self.connection().await?;
+        let entry = serde_json::to_string(&Entry::Completed {
+            fingerprint: fingerprint.to_string(),
+            response: response.clone(),
+        })?;
+        redis::cmd("SET")
+            .arg(Self::redis_key(key))
+            .arg(entry)
+            .arg("PX")
+            .arg(ttl_millis(ttl))
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(redis_unavailable)
+    }
+
+    async fn release(&self, key: &str) -> Result<(), ApiError> {
+        let mut conn = self.connection().await?;
+        conn.del::<_, ()>(Self::redis_key(key)).await.map_err(redis_unavailable)
+    }
+}
+
+/// Idempotency state made available to the middleware via request extensions
+#[derive(Clone)]
+pub struct IdempotencyState {
+    pub store: Arc<dyn IdempotencyStore>,
+    pub config: IdempotencyConfig,
+}
+
+impl IdempotencyState {
+    pub fn new(store: Arc<dyn IdempotencyStore>, config: IdempotencyConfig) -> Self {
+        Self { store, config }
+    }
+
+    /// Use Redis when `REDIS_URL` is set, otherwise fall back to memory
+    pub fn from_env(config: IdempotencyConfig) -> Self {
+        let redis_store = std::env::var("REDIS_URL")
+            .ok()
+            .and_then(|url| match RedisIdempotencyStore::new(&url) {
+                Ok(store) => Some(store),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Falling back to in-memory idempotency store");
+                    None
+                }
+            });
+
+        match redis_store {
+            Some(store) => Self::new(Arc::new(store), config),
+            None => {
+                tracing::warn!("REDIS_URL not set; idempotency keys are only tracked per replica");
+                Self::new(Arc::new(InMemoryIdempotencyStore::new()), config)
+            }
+        }
+    }
+}
+
+fn is_mutating(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE)
+}
+
+/// The caller idempotency keys are scoped to: the user a valid JWT names, or
+/// the presented API key
+///
+/// API keys are not validated here, which would spend their rate limit
+/// before the handler's `AuthContext` does; the key's digest stands in for
+/// it, so a scope is only shared by holders of the same key.
+fn caller_scope(headers: &axum::http::HeaderMap) -> Option<String> {
+    if let Some(api_key) = extract_api_key(headers) {
+        return Some(format!("api-key:{}", hex::encode(Sha256::digest(api_key.as_bytes()))));
+    }
+    authenticated_context(headers).map(|auth| format!("user:{}", auth.user_id))
+}
+
+/// Derive the store key from (key, method + route, caller)
+///
+/// Hashing keeps client-supplied keys from colliding across scopes.
+fn storage_key(idempotency_key: &str, method: &Method, route: &str, caller: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [caller, method.as_str(), route, idempotency_key] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Idempotency middleware for mutating requests carrying `Idempotency-Key`
+pub async fn idempotency_middleware(request: Request, next: Next) -> Response {
+    let Some(state) = request.extensions().get::<IdempotencyState>().cloned() else {
+        return next.run(request).await;
+    };
+    if !is_mutating(request.method()) {
+        return next.run(request).await;
+    }
+    let Some(header) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+
+    let idempotency_key = match header.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= state.config.max_key_length => key.to_string(),
+        _ => {
+            return ApiError::bad_request(format!(
+                "Idempotency-Key must be 1-{} visible ASCII characters",
+                state.config.max_key_length
+            ))
+            .into_response();
+        }
+    };
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path().to_string(), |path| path.as_str().to_string());
+    if state.config.exempt_routes.contains(&route) {
+        return next.run(request).await;
+    }
+
+    let Some(caller) = caller_scope(request.headers()) else {
+        return next.run(request).await;
+    };
+    let key = storage_key(&idempotency_key, request.method(), &route, &caller);
+
+    // The body is fingerprinted so a reused key with another payload is refused
+    let (parts, body) = request.into_parts();
+    let body = match Limited::new(body, state.config.max_request_bytes).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        // The body limit layer's own `Limited` surfaces as a source of the error
+        Err(e) if exceeds_length_limit(e.as_ref()) => {
+            return ApiError::payload_too_large(format!(
+                "Idempotent request bodies are limited to {} bytes",
+                state.config.max_request_bytes
+            ))
+            .into_response();
+        }
+        Err(e) => {
+            return ApiError::bad_request(format!("Failed to read request body: {}", e)).into_response();
+        }
+    };
+    let fingerprint = hex::encode(Sha256::digest(&body));
+    let request = Request::from_parts(parts, Body::from(body));
+
+    match state.store.begin(&key, &fingerprint, state.config.lock_ttl).await {
+        Ok(IdempotencyStatus::Started) => {}
+        Ok(IdempotencyStatus::InFlight) => {
+            return ApiError::conflict("A request with this Idempotency-Key is still being processed")
+                .into_response();
+        }
+        Ok(IdempotencyStatus::Completed(stored)) => {
+            tracing::debug!(route = %route, "Replaying idempotent response");
+            return stored.into_response();
+        }
+        Ok(IdempotencyStatus::Mismatch) => {
+            return ApiError::unprocessable_entity(
+                "This Idempotency-Key was already used with a different request body",
+            )
+            .into_response();
+        }
+        Err(e) => return e.into_response(),
+    }
+
+    let response = next.run(request).await;
+    record_response(&state, &key, &fingerprint, &route, response).await
+}
+
+fn exceeds_length_limit(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(e) = current {
+        if e.is::<LengthLimitError>() {
+            return true;
+        }
+        current = e.source();
+    }
+    false
+}
+
+/// Store the handler's response for replay unless it failed
+///
+/// At most `max_body_bytes + 1` bytes are buffered; a larger body is not
+/// stored, and is streamed back starting with what was already read.
+async fn record_response(
+    state: &IdempotencyState,
+    key: &str,
+    fingerprint: &str,
+    route: &str,
+    response: Response,
+) -> Response {
+    let (parts, mut body) = response.into_parts();
+    if parts.status.is_server_error() {
+        release(state, key).await;
+        return Response::from_parts(parts, body);
+    }
+
+    let mut buffered = Vec::new();
+    while let Some(frame) = body.frame().await {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) => {
+                release(state, key).await;
+                return ApiError::internal(format!("Failed to buffer response: {}", e)).into_response();
+            }
+        };
+        // Trailers are not replayed
+        let Ok(data) = frame.into_data() else {
+            continue;
+        };
+        buffered.extend_from_slice(&data);
+        if buffered.len() > state.config.max_body_bytes {
+            release(state, key).await;
+            let head = futures::stream::once(async move { Ok::<_, axum::Error>(Bytes::from(buffered)) });
+            return Response::from_parts(parts, Body::from_stream(head.chain(body.into_data_stream())));
+        }
+    }
+    let bytes = Bytes::from(buffered);
+
+    let stored = StoredResponse {
+        status: parts.status.as_u16(),
+        headers: parts
+            .headers
+            .iter()
+            .filter_map(|(name, value)| {
+                value.to_str().ok().map(|v| (name.as_str().to_string(), v.to_string()))
+            })
+            .collect(),
+        body: bytes.to_vec(),
+    };
+    if let Err(e) = state.store.complete(key, fingerprint, &stored, state.config.ttl).await {
+        tracing::warn!(error = %e, route = %route, "Failed to record idempotent response");
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+async fn release(state: &IdempotencyState, key: &str) {
+    if let Err(e) = state.store.release(key).await {
+        tracing::warn!(error = %e, "Failed to release idempotency key");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware::from_fn, routing::post, Extension, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    fn token_for(user_id: Uuid) -> String {
+        let secret = std::env::var("JWT_SECRET")
+            .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string());
+        let claims = serde_json::json!({
+            "sub": user_id.to_string(),
+            "exp": chrono::Utc::now().timestamp() + 3600,
+        });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    fn app(config: IdempotencyConfig, calls: Arc<AtomicUsize>) -> Router {
+        let handler = move |body: String| {
+            let calls = calls.clone();
+            async move {
+                let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                (StatusCode::CREATED, format!("{{\"created\":{},\"echo\":\"{}\"}}", n, body))
+            }
+        };
+        let state = IdempotencyState::new(Arc::new(InMemoryIdempotencyStore::new()), config);
+        Router::new()
+            .route("/patients", post(handler.clone()))
+            .route("/patients/:id/merge", post(handler))
+            .layer(from_fn(idempotency_middleware))
+            .layer(Extension(state))
+    }
+
+    fn request(path: &str, user_id: Uuid, key: &str) -> Request {
+        Request::builder()
+            .method(Method::POST)
+            .uri(path)
+            .header("authorization", format!("Bearer {}", token_for(user_id)))
+            .header(IDEMPOTENCY_KEY_HEADER, key)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn api_key_request(api_key: &str, key: &str, body: &str) -> Request {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/patients")
+            .header("x-api-key", api_key)
+            .header(IDEMPOTENCY_KEY_HEADER, key)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn body_of(response: Response) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_replay_returns_stored_response() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(IdempotencyConfig::default(), calls.clone());
+        let user = Uuid::new_v4();
+
+        let first = app.clone().oneshot(request("/patients", user, "k1")).await.unwrap();
+        assert_eq!(first.status(), StatusCode::CREATED);
+        let first_body = body_of(first).await;
+
+        let replay = app.clone().oneshot(request("/patients", user, "k1")).await.unwrap();
+        assert_eq!(replay.status(), StatusCode::CREATED);
+        assert!(replay.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        assert_eq!(body_of(replay).await, first_body);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Same key from another user or on another route is a new request
+        app.clone().oneshot(request("/patients", Uuid::new_v4(), "k1")).await.unwrap();
+        app.oneshot(request("/patients/1/merge", user, "k1")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_exempt_route_is_not_deduplicated() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let config = IdempotencyConfig::default().exempt_route("/patients/:id/merge");
+        let app = app(config, calls.clone());
+        let user = Uuid::new_v4();
+
+        app.clone().oneshot(request("/patients/1/merge", user, "k1")).await.unwrap();
+        app.oneshot(request("/patients/1/merge", user, "k1")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_key_conflicts() {
+        let store = InMemoryIdempotencyStore::new();
+        let lock = Duration::from_secs(60);
+        assert_eq!(store.begin("k", "f", lock).await.unwrap(), IdempotencyStatus::Started);
+        assert_eq!(store.begin("k", "f", lock).await.unwrap(), IdempotencyStatus::InFlight);
+
+        let user = Uuid::new_v4();
+        let state = IdempotencyState::new(Arc::new(store), IdempotencyConfig::default());
+        let key = storage_key("k1", &Method::POST, "/patients", &format!("user:{}", user));
+        let empty_body = hex::encode(Sha256::digest(b""));
+        state.store.begin(&key, &empty_body, lock).await.unwrap();
+        let app = Router::new()
+            .route("/patients", post(|| async { StatusCode::CREATED }))
+            .layer(from_fn(idempotency_middleware))
+            .layer(Extension(state));
+        let response = app.oneshot(request("/patients", user, "k1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_scope_and_fingerprint_requests() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(IdempotencyConfig::default(), calls.clone());
+
+        let first = app.clone().oneshot(api_key_request("key-a", "k1", "one")).await.unwrap();
+        assert_eq!(first.status(), StatusCode::CREATED);
+        let replay = app.clone().oneshot(api_key_request("key-a", "k1", "one")).await.unwrap();
+        assert!(replay.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Another API key does not see the first key's response
+        let other = app.clone().oneshot(api_key_request("key-b", "k1", "one")).await.unwrap();
+        assert!(!other.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Reusing the key with another payload is refused, not replayed
+        let changed = app.oneshot(api_key_request("key-a", "k1", "two")).await.unwrap();
+        assert_eq!(changed.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_response_is_streamed_and_not_stored() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let config = IdempotencyConfig {
+            max_body_bytes: 8,
+            ..IdempotencyConfig::default()
+        };
+        let app = app(config, calls.clone());
+        let user = Uuid::new_v4();
+
+        let first = app.clone().oneshot(request("/patients", user, "k1")).await.unwrap();
+        assert_eq!(body_of(first).await, b"{\"created\":1,\"echo\":\"\"}".to_vec());
+
+        let retry = app.oneshot(request("/patients", user, "k1")).await.unwrap();
+        assert!(!retry.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_stored_response_roundtrip() {
+        let stored = StoredResponse {
+            status: 201,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: b"{\"id\":1}".to_vec(),
+        };
+        let json = serde_json::to_string(&stored).unwrap();
+        assert_eq!(serde_json::from_str::<StoredResponse>(&json).unwrap(), stored);
+    }
+}
@@ -4,36 +4,97 @@ pub mod auth_context;
 pub mod request_context;
 pub mod security;
 pub mod security_middleware;
+pub mod csrf;
+pub mod tenant;
 pub mod extractors;
 pub mod zanzibar_engine;
+pub mod idempotency;
+pub mod rate_limit;
+pub mod body_limit;
+pub mod trace_context;
 
 // Re-export for convenience
-pub use auth_context::AuthContext;
+pub use auth_context::{AuthContext, API_KEY_HEADER};
 pub use request_context::RequestContext;
-pub use security::{SecurityContext, SecurityConfig, SecurityMiddlewareState, RateLimiter, RateLimitConfig, CsrfValidator};
+pub use security::{SecurityContext, SecurityConfig, SecurityMiddlewareState, RateLimiter, RateLimitConfig, CsrfValidator, CSRF_COOKIE_NAME};
+pub use csrf::csrf_middleware;
+pub use tenant::tenant_middleware;
 pub use security_middleware::security_middleware;
 pub use extractors::{SecureContext, ReqContext};
 pub use zanzibar_engine::ZanzibarEngineWrapper;
 pub use auth_context::ZanzibarCheck;
+pub use rate_limit::{rate_limit_middleware, RateLimitBackend, RateLimitDecision, RedisRateLimitBackend};
+pub use trace_context::{trace_context_middleware, REQUEST_ID_HEADER};
+pub use body_limit::{body_limit_middleware, BodyLimitConfig, JsonLimits};
+pub use idempotency::{idempotency_middleware, IdempotencyConfig, IdempotencyState, IdempotencyStore};
 
 use axum::{
     http::{header, Method},
     middleware::Next,
     response::Response,
-    extract::Request,
+    extract::{MatchedPath, Request},
 };
 use tower_http::cors::CorsLayer;
 use std::time::{Duration, Instant};
 
+/// Route label for requests that matched no route
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Register help text and allowed labels for the request metrics recorded below
+pub fn describe_request_metrics() {
+    let metrics = telemetry::MetricsCollector::global();
+    metrics.describe(
+        "http_requests_total",
+        telemetry::MetricKind::Counter,
+        "HTTP requests by method, route and status code",
+    );
+    metrics.describe_histogram(
+        "http_request_duration_seconds",
+        "HTTP request latency in seconds by method and route",
+        telemetry::DEFAULT_BUCKETS,
+    );
+    metrics.restrict_labels(
+        "http_requests_total",
+        telemetry::MetricKind::Counter,
+        &["method", "route", "status"],
+    );
+    metrics.restrict_labels(
+        "http_request_duration_seconds",
+        telemetry::MetricKind::Histogram,
+        &["method", "route"],
+    );
+}
+
 /// Request timing middleware for performance monitoring
+///
+/// Records `http_requests_total` and `http_request_duration_seconds` labelled
+/// by route template (not the raw path, which would explode cardinality).
 pub async fn request_timing_middleware(
     request: Request,
     next: Next,
 ) -> Response {
     let path = request.uri().path().to_string();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| UNMATCHED_ROUTE.to_string(), |p| p.as_str().to_string());
     let start = Instant::now();
     let response = next.run(request).await;
     let duration = start.elapsed();
+
+    let metrics = telemetry::MetricsCollector::global();
+    metrics
+        .counter("http_requests_total")
+        .with_label("method", method.as_str())
+        .with_label("route", route.as_str())
+        .with_label("status", response.status().as_str())
+        .increment();
+    metrics
+        .histogram("http_request_duration_seconds")
+        .with_label("method", method)
+        .with_label("route", route)
+        .record(duration.as_secs_f64());
     
     // Log slow requests
     if duration > Duration::from_secs(1) {
@@ -59,6 +120,8 @@ pub async fn audit_logging_middleware(
         .get(header::USER_AGENT)
         .and_then(|h| h.to_str().ok())
         .map(|s| s.to_string());
+    let request_id = trace_context::request_id(&request).map(|s| s.to_string());
+    let trace_id = telemetry::current_trace_id();
     
     // Execute request
     let response = next.run(request).await;
@@ -69,6 +132,8 @@ pub async fn audit_logging_middleware(
         path = %path,
         status = %response.status(),
         user_agent = ?user_agent,
+        request_id = ?request_id,
+        trace_id = ?trace_id,
         "API request audit"
     );
     
@@ -84,6 +149,8 @@ pub fn create_cors_layer() -> CorsLayer {
             header::CONTENT_TYPE,
             header::AUTHORIZATION,
             header::ACCEPT,
+            header::HeaderName::from_static(API_KEY_HEADER),
+            header::HeaderName::from_static(auth_gateway::TENANT_HEADER),
         ])
         .max_age(Duration::from_secs(3600))
 }
@@ -0,0 +1,420 @@
+//! Rate limit backends and response headers
+//!
+//! `RateLimiter` (see `security`) delegates counting to a `RateLimitBackend`:
+//! - `InMemoryRateLimitBackend`: fixed window per process
+//! - `RedisRateLimitBackend`: sliding window shared by all replicas, so a
+//!   user gets the same quota regardless of how many instances are running
+//!   and limits survive restarts. After a failed connect it waits
+//!   `reconnect_backoff` before trying again, so requests during an outage
+//!   fail open without waiting on Redis
+//!
+//! `rate_limit_middleware` enforces the limit once per request and adds
+//! `X-RateLimit-Limit`/`X-RateLimit-Remaining` (and `Retry-After` on 429)
+//! to the response.
+
+use async_trait::async_trait;
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use redis::aio::ConnectionManager;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, RwLock};
+use uuid::Uuid;
+use crate::error::ApiError;
+use crate::middleware::auth_context::authenticated_context;
+use crate::middleware::request_context::client_addr;
+use crate::middleware::{RateLimitConfig, SecurityMiddlewareState};
+
+/// Response header carrying the request quota
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "x-ratelimit-limit";
+
+/// Response header carrying the requests left in the current window
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+
+/// Result of counting a request against a rate limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until a request will be allowed again (set when denied)
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitDecision {
+    /// Decision used when the backend could not be consulted
+    pub fn unchecked(config: &RateLimitConfig) -> Self {
+        Self {
+            allowed: true,
+            limit: config.max_requests,
+            remaining: config.max_requests,
+            retry_after: None,
+        }
+    }
+
+    /// `Retry-After` value in whole seconds (at least 1)
+    pub fn retry_after_secs(&self) -> u64 {
+        self.retry_after
+            .map_or(1, |d| d.as_secs() + u64::from(d.subsec_nanos() > 0))
+            .max(1)
+    }
+
+    /// Add `X-RateLimit-*` headers to a response
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(RATE_LIMIT_LIMIT_HEADER, HeaderValue::from(self.limit));
+        headers.insert(RATE_LIMIT_REMAINING_HEADER, HeaderValue::from(self.remaining));
+    }
+}
+
+/// Storage and counting strategy for rate limits
+#[async_trait]
+pub trait RateLimitBackend: Send + Sync {
+    /// Count a request against `key` and decide whether it is allowed
+    async fn hit(&self, key: &str, config: &RateLimitConfig) -> Result<RateLimitDecision, ApiError>;
+
+    /// Requests left for `key` in the current window, without counting one
+    async fn remaining(&self, key: &str, config: &RateLimitConfig) -> Result<u32, ApiError>;
+}
+
+/// Rate limit entry tracking requests in a time window
+#[derive(Debug, Clone)]
+struct RateLimitEntry {
+    count: u32,
+    window_start: Instant,
+}
+
+/// In-memory fixed-window backend (for single-instance deployments)
+#[derive(Debug, Default)]
+pub struct InMemoryRateLimitBackend {
+    entries: RwLock<HashMap<String, RateLimitEntry>>,
+}
+
+impl InMemoryRateLimitBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitBackend for InMemoryRateLimitBackend {
+    async fn hit(&self, key: &str, config: &RateLimitConfig) -> Result<RateLimitDecision, ApiError> {
+        let window = Duration::from_secs(config.window_seconds);
+        let mut entries = self.entries.write().await;
+
+        // Clean up old entries periodically
+        if entries.len() > 10000 {
+            entries.retain(|_, entry| entry.window_start.elapsed() < window);
+        }
+
+        let now = Instant::now();
+        let entry = entries.entry(key.to_string()).or_insert_with(|| {
+            RateLimitEntry {
+                count: 0,
+                window_start: now,
+            }
+        });
+
+        // Reset if window expired
+        if entry.window_start.elapsed() >= window {
+            entry.count = 0;
+            entry.window_start = now;
+        }
+
+        if entry.count >= config.max_requests {
+            return Ok(RateLimitDecision {
+                allowed: false,
+                limit: config.max_requests,
+                remaining: 0,
+                retry_after: Some(window.saturating_sub(entry.window_start.elapsed())),
+            });
+        }
+
+        entry.count += 1;
+        Ok(RateLimitDecision {
+            allowed: true,
+            limit: config.max_requests,
+            remaining: config.max_requests.saturating_sub(entry.count),
+            retry_after: None,
+        })
+    }
+
+    async fn remaining(&self, key: &str, config: &RateLimitConfig) -> Result<u32, ApiError> {
+        let entries = self.entries.read().await;
+        Ok(match entries.get(key) {
+            Some(entry) if entry.window_start.elapsed().as_secs() < config.window_seconds => {
+                config.max_requests.saturating_sub(entry.count)
+            }
+            _ => config.max_requests,
+        })
+    }
+}
+
+/// Sliding-window log kept in a sorted set scored by request time (ms)
+///
+/// Uses the Redis server clock so replicas with skewed clocks agree.
+/// Arguments are `window_ms`, `limit` and a unique `member` (empty to only
+/// report the remaining quota); returns `{allowed, remaining, retry_after_ms}`.
+const SLIDING_WINDOW_SCRIPT: &str = r"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local window = tonumber(ARGV[1])
+local limit = tonumber(ARGV[2])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+local count = redis.call('ZCARD', KEYS[1])
+if ARGV[3] == '' then
+  return {1, math.max(limit - count, 0), 0}
+end
+if count < limit then
+  redis.call('ZADD', KEYS[1], now, ARGV[3])
+  redis.call('PEXPIRE', KEYS[1], window)
+  return {1, limit - count - 1, 0}
+end
+local oldest = redis.call('ZRANGE', KEYS[1], 0, 0, 'WITHSCORES')
+local retry = window
+if oldest[2] then
+  retry = tonumber(oldest[2]) + window - now
+end
+return {0, 0, retry}
+";
+
+/// Default wait after a failed connect before the next attempt
+pub const DEFAULT_RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Redis-backed sliding-window backend shared by all replicas
+pub struct RedisRateLimitBackend {
+    client: redis::Client,
+    connection: OnceCell<ConnectionManager>,
+    script: redis::Script,
+    reconnect_backoff: Duration,
+    /// When the connect may be retried after the last failure
+    next_connect_at: Mutex<Option<Instant>>,
+}
+
+impl RedisRateLimitBackend {
+    /// Create a backend for `redis_url`; the connection is opened on first use
+    pub fn new(redis_url: &str) -> Result<Self, ApiError> {
+        let client = redis::Client::open(redis_url)
+            .map_err(|e| ApiError::internal(format!("Invalid Redis URL for rate limiting: {}", e)))?;
+        Ok(Self {
+            client,
+            connection: OnceCell::new(),
+            script: redis::Script::new(SLIDING_WINDOW_SCRIPT),
+            reconnect_backoff: DEFAULT_RECONNECT_BACKOFF,
+            next_connect_at: Mutex::new(None),
+        })
+    }
+
+    /// Set how long to wait after a failed connect before trying again
+    pub fn with_reconnect_backoff(mut self, backoff: Duration) -> Self {
+        self.reconnect_backoff = backoff;
+        self
+    }
+
+    /// Shared connection, opened on first use
+    ///
+    /// Within the backoff after a failed connect this fails immediately
+    /// instead of connecting again.
+    async fn connection(&self) -> Result<ConnectionManager, ApiError> {
+        self.connection
+            .get_or_try_init(|| async {
+                let backing_off = self
+                    .next_connect_at
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .is_some_and(|at| Instant::now() < at);
+                if backing_off {
+                    return Err(ApiError::service_unavailable(
+                        "Rate limit store unavailable, waiting to reconnect",
+                    ));
+                }
+
+                let result = ConnectionManager::new(self.client.clone()).await;
+                *self.next_connect_at.lock().unwrap_or_else(|e| e.into_inner()) =
+                    result.is_err().then(|| Instant::now() + self.reconnect_backoff);
+                result.map_err(redis_unavailable)
+            })
+            .await
+            .cloned()
+    }
+
+    async fn run(&self, key: &str, config: &RateLimitConfig, member: &str) -> Result<(i64, i64, i64), ApiError> {
+        let mut conn = self.connection().await?;
+
+        self.script
+            .key(format!("ratelimit:{}", key))
+            .arg(config.window_seconds.saturating_mul(1000))
+            .arg(config.max_requests)
+            .arg(member)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(redis_unavailable)
+    }
+}
+
+fn redis_unavailable(e: redis::RedisError) -> ApiError {
+    ApiError::service_unavailable(format!("Rate limit store unavailable: {}", e))
+}
+
+#[async_trait]
+impl RateLimitBackend for RedisRateLimitBackend {
+    async fn hit(&self, key: &str, config: &RateLimitConfig) -> Result<RateLimitDecision, ApiError> {
+        let member = Uuid::new_v4().to_string();
+        let (allowed, remaining, retry_after_ms) = self.run(key, config, &member).await?;
+        Ok(RateLimitDecision {
+            allowed: allowed == 1,
+            limit: config.max_requests,
+            remaining: u32::try_from(remaining).unwrap_or(0),
+            retry_after: (allowed != 1)
+                .then(|| Duration::from_millis(u64::try_from(retry_after_ms).unwrap_or(0))),
+        })
+    }
+
+    async fn remaining(&self, key: &str, config: &RateLimitConfig) -> Result<u32, ApiError> {
+        let (_, remaining, _) = self.run(key, config, "").await?;
+        Ok(u32::try_from(remaining).unwrap_or(0))
+    }
+}
+
+/// Rate limiting middleware
+///
+/// Keys authenticated callers by tenant and user (when `by_user` is set) and
+/// everyone else by client address. The decision is stored in request
+/// extensions so the `AuthContext` extractor does not count the request twice.
+pub async fn rate_limit_middleware(mut request: Request, next: Next) -> Response {
+    let Some(limiter) = request
+        .extensions()
+        .get::<SecurityMiddlewareState>()
+        .and_then(|state| state.rate_limiter.clone())
+    else {
+        return next.run(request).await;
+    };
+
+    let user = authenticated_context(request.headers())
+        .map(|auth| (auth.user_id, auth.organization_id));
+    let remote_addr = client_addr(request.extensions(), request.headers());
+    let key = limiter.key_for(user, remote_addr.as_deref());
+
+    match limiter.check(&key).await {
+        Ok(decision) => {
+            request.extensions_mut().insert(decision.clone());
+            let mut response = next.run(request).await;
+            decision.apply_headers(response.headers_mut());
+            response
+        }
+        Err(e) => {
+            let mut response = e.into_response();
+            RateLimitDecision {
+                allowed: false,
+                limit: limiter.config.max_requests,
+                remaining: 0,
+                retry_after: None,
+            }
+            .apply_headers(response.headers_mut());
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{RateLimiter, SecurityConfig};
+    use axum::{body::Body, http::StatusCode, middleware::from_fn, routing::get, Extension, Router};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn config(max_requests: u32) -> RateLimitConfig {
+        RateLimitConfig {
+            max_requests,
+            window_seconds: 60,
+            by_user: true,
+        }
+    }
+
+    struct UnavailableBackend;
+
+    #[async_trait]
+    impl RateLimitBackend for UnavailableBackend {
+        async fn hit(&self, _key: &str, _config: &RateLimitConfig) -> Result<RateLimitDecision, ApiError> {
+            Err(ApiError::service_unavailable("down"))
+        }
+
+        async fn remaining(&self, _key: &str, _config: &RateLimitConfig) -> Result<u32, ApiError> {
+            Err(ApiError::service_unavailable("down"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_denied_request_reports_retry_after() {
+        let limiter = RateLimiter::new(config(2));
+        assert_eq!(limiter.check("k").await.unwrap().remaining, 1);
+        assert_eq!(limiter.check("k").await.unwrap().remaining, 0);
+
+        let denied = limiter.check("k").await;
+        assert!(matches!(
+            denied,
+            Err(ApiError::RateLimit { retry_after_secs: Some(secs), .. }) if (1..=60).contains(&secs)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_backend_fails_open() {
+        let limiter = RateLimiter::with_backend(config(1), Arc::new(UnavailableBackend));
+        for _ in 0..3 {
+            assert!(limiter.check("k").await.unwrap().allowed);
+        }
+        assert_eq!(limiter.remaining("k").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_redis_connect_backs_off() {
+        // Nothing listens on port 1, so the connect is refused
+        let backend = RedisRateLimitBackend::new("redis://127.0.0.1:1")
+            .unwrap()
+            .with_reconnect_backoff(Duration::from_secs(60));
+        assert!(backend.hit("k", &config(1)).await.is_err());
+        let retry_at = backend.next_connect_at.lock().unwrap().expect("failure recorded");
+
+        let backing_off = backend.hit("k", &config(1)).await;
+        assert!(matches!(backing_off, Err(ApiError::ServiceUnavailable { message }) if message.contains("waiting")));
+        assert_eq!(*backend.next_connect_at.lock().unwrap(), Some(retry_at));
+    }
+
+    #[test]
+    fn test_key_scoped_by_tenant_and_user() {
+        let limiter = RateLimiter::new(config(1));
+        let (user, org) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(limiter.key_for(Some((user, org)), Some("10.0.0.1")), format!("user:{}:{}", org, user));
+        assert_eq!(limiter.key_for(None, Some("10.0.0.1")), "ip:10.0.0.1");
+
+        let by_ip = RateLimiter::new(RateLimitConfig { by_user: false, ..config(1) });
+        assert_eq!(by_ip.key_for(Some((user, org)), None), "ip:unknown");
+    }
+
+    #[tokio::test]
+    async fn test_middleware_sets_rate_limit_headers() {
+        let state = SecurityMiddlewareState::new(SecurityConfig {
+            rate_limit: Some(config(1)),
+            ..SecurityConfig::default()
+        });
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(from_fn(rate_limit_middleware))
+            .layer(Extension(state));
+        let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+        let ok = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(ok.status(), StatusCode::OK);
+        assert_eq!(ok.headers()[RATE_LIMIT_REMAINING_HEADER], "0");
+
+        let limited = app.oneshot(request()).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()[RATE_LIMIT_REMAINING_HEADER], "0");
+        assert!(limited.headers().contains_key("retry-after"));
+    }
+}
@@ -7,11 +7,13 @@
 //! - Security headers validation
 
 use axum::extract::FromRequestParts;
-use axum::http::{header, request::Parts, HeaderMap};
+use axum::http::{header, request::Parts, Extensions, HeaderMap};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use auth_gateway::TenantId;
 use async_trait::async_trait;
 use crate::error::ApiError;
+use std::net::IpAddr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Request context containing security and tracing information
@@ -31,6 +33,8 @@ pub struct RequestContext {
     pub timestamp: u64,
     /// Same-site validation result
     pub same_site_valid: bool,
+    /// Tenant the request was bound to by `tenant_middleware`
+    pub tenant_id: Option<TenantId>,
 }
 
 impl RequestContext {
@@ -47,6 +51,7 @@ impl RequestContext {
                 .unwrap_or_default()
                 .as_secs(),
             same_site_valid: true, // Default to true, will be validated
+            tenant_id: None,
         }
     }
     
@@ -89,6 +94,7 @@ impl RequestContext {
                 .unwrap_or_default()
                 .as_secs(),
             same_site_valid,
+            tenant_id: None,
         }
     }
     
@@ -172,6 +178,47 @@ impl Default for RequestContext {
     }
 }
 
+/// Resolve the client address from connection info or proxy headers
+///
+/// The connection's peer is the client unless it is one of the proxies listed
+/// in `TRUSTED_PROXIES`; only then is the address it forwarded in
+/// X-Forwarded-For or X-Real-IP used. Anyone can set those headers, so without
+/// connection info they are not trusted at all.
+pub(crate) fn client_addr(extensions: &Extensions, headers: &HeaderMap) -> Option<String> {
+    let peer = extensions
+        .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
+        .map(|ci| ci.0.ip())?;
+    resolve_client_addr(peer, headers, &trusted_proxies())
+}
+
+/// Proxies whose forwarding headers are believed, from `TRUSTED_PROXIES`
+fn trusted_proxies() -> Vec<IpAddr> {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|s| s.trim().parse().ok())
+        .collect()
+}
+
+fn resolve_client_addr(peer: IpAddr, headers: &HeaderMap, trusted: &[IpAddr]) -> Option<String> {
+    if !trusted.contains(&peer) {
+        return Some(peer.to_string());
+    }
+    headers
+        .get("X-Forwarded-For")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.split(',').next())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or_else(|| {
+            headers
+                .get("X-Real-IP")
+                .and_then(|h| h.to_str().ok())
+                .map(|s| s.trim().to_string())
+        })
+        .or_else(|| Some(peer.to_string()))
+}
+
 #[async_trait]
 impl<S> FromRequestParts<S> for RequestContext
 where
@@ -186,24 +233,10 @@ where
         let headers = &parts.headers;
         
         // Extract remote address from extensions or headers
-        let remote_addr = parts
-            .extensions
-            .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
-            .map(|ci| ci.0.ip().to_string())
-            .or_else(|| {
-                headers
-                    .get("X-Forwarded-For")
-                    .and_then(|h| h.to_str().ok())
-                    .map(|s| s.split(',').next().unwrap_or("").trim().to_string())
-            })
-            .or_else(|| {
-                headers
-                    .get("X-Real-IP")
-                    .and_then(|h| h.to_str().ok())
-                    .map(|s| s.to_string())
-            });
+        let remote_addr = client_addr(&parts.extensions, headers);
         
-        let ctx = RequestContext::from_headers(headers, remote_addr);
+        let mut ctx = RequestContext::from_headers(headers, remote_addr);
+        ctx.tenant_id = parts.extensions.get::<TenantId>().copied();
         
         // If same-site validation fails, log warning but don't reject
         // (allows API clients that don't send Origin/Referer)
@@ -263,5 +296,16 @@ mod tests {
         let referer = None;
         assert!(RequestContext::validate_same_site(&origin, &referer));
     }
-}
 
+    #[test]
+    fn test_forwarding_headers_trusted_only_from_proxies() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", HeaderValue::from_static("203.0.113.7, 10.0.0.2"));
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let stranger: IpAddr = "198.51.100.9".parse().unwrap();
+
+        assert_eq!(resolve_client_addr(proxy, &headers, &[proxy]).as_deref(), Some("203.0.113.7"));
+        assert_eq!(resolve_client_addr(stranger, &headers, &[proxy]).as_deref(), Some("198.51.100.9"));
+        assert!(client_addr(&Extensions::new(), &headers).is_none());
+    }
+}
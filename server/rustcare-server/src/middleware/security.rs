@@ -11,11 +11,9 @@
 use axum::http::{header, HeaderMap, Method};
 use uuid::Uuid;
 use std::sync::Arc;
-use std::collections::HashMap;
-use std::time::Instant;
-use tokio::sync::RwLock;
 use crate::error::ApiError;
 use crate::middleware::{AuthContext, RequestContext};
+use crate::middleware::rate_limit::{InMemoryRateLimitBackend, RateLimitBackend, RateLimitDecision};
 
 /// Rate limit configuration
 #[derive(Debug, Clone)]
@@ -38,145 +36,206 @@ impl Default for RateLimitConfig {
     }
 }
 
-/// Rate limit entry tracking requests in a time window
-#[derive(Debug, Clone)]
-struct RateLimitEntry {
-    count: u32,
-    window_start: Instant,
-}
-
-/// In-memory rate limiter (for single-instance deployments)
-/// For distributed systems, use Redis or similar
-#[derive(Debug)]
+/// Rate limiter enforcing `RateLimitConfig` through a pluggable backend
+///
+/// Defaults to the in-memory backend (single-instance deployments). Use
+/// `RedisRateLimitBackend` so limits hold cluster-wide and across restarts.
 pub struct RateLimiter {
-    entries: Arc<RwLock<HashMap<String, RateLimitEntry>>>,
+    backend: Arc<dyn RateLimitBackend>,
     pub config: RateLimitConfig,
 }
 
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
 impl RateLimiter {
     pub fn new(config: RateLimitConfig) -> Self {
-        Self {
-            entries: Arc::new(RwLock::new(HashMap::new())),
-            config,
+        Self::with_backend(config, Arc::new(InMemoryRateLimitBackend::new()))
+    }
+
+    /// Create a rate limiter using a specific backend
+    pub fn with_backend(config: RateLimitConfig, backend: Arc<dyn RateLimitBackend>) -> Self {
+        Self { backend, config }
+    }
+
+    /// Build the rate limit key for a caller
+    ///
+    /// With `by_user` the key is scoped to tenant and user so a user's quota
+    /// is shared across all replicas; otherwise (or for anonymous callers)
+    /// it falls back to the client address.
+    pub fn key_for(&self, user: Option<(Uuid, Uuid)>, remote_addr: Option<&str>) -> String {
+        match user {
+            Some((user_id, organization_id)) if self.config.by_user => {
+                format!("user:{}:{}", organization_id, user_id)
+            }
+            _ => format!("ip:{}", remote_addr.unwrap_or("unknown")),
         }
     }
-    
+
     /// Check if request should be rate limited
-    pub async fn check(&self, key: &str) -> Result<(), ApiError> {
-        let mut entries = self.entries.write().await;
-        
-        // Clean up old entries periodically
-        if entries.len() > 10000 {
-            entries.retain(|_, entry| {
-                entry.window_start.elapsed().as_secs() < self.config.window_seconds
-            });
-        }
-        
-        let now = Instant::now();
-        let entry = entries.entry(key.to_string()).or_insert_with(|| {
-            RateLimitEntry {
-                count: 0,
-                window_start: now,
+    ///
+    /// Fails open when the backend is unavailable so an outage of the shared
+    /// store does not take the API down with it.
+    pub async fn check(&self, key: &str) -> Result<RateLimitDecision, ApiError> {
+        let decision = match self.backend.hit(key, &self.config).await {
+            Ok(decision) => decision,
+            Err(e) => {
+                tracing::warn!(
+                    telemetry.event = "rate_limit_fail_open",
+                    error = %e,
+                    "Rate limit backend unavailable; allowing request"
+                );
+                return Ok(RateLimitDecision::unchecked(&self.config));
             }
-        });
-        
-        // Reset if window expired
-        if entry.window_start.elapsed().as_secs() >= self.config.window_seconds {
-            entry.count = 0;
-            entry.window_start = now;
+        };
+
+        if decision.allowed {
+            return Ok(decision);
         }
-        
-        // Check limit
-        if entry.count >= self.config.max_requests {
-            return Err(ApiError::rate_limit(format!(
+        Err(ApiError::rate_limit_retry_after(
+            format!(
                 "Rate limit exceeded: {} requests per {} seconds",
                 self.config.max_requests,
                 self.config.window_seconds
-            )));
-        }
-        
-        entry.count += 1;
-        Ok(())
+            ),
+            decision.retry_after_secs(),
+        ))
     }
-    
+
     /// Get remaining requests in current window
     pub async fn remaining(&self, key: &str) -> u32 {
-        let entries = self.entries.read().await;
-        if let Some(entry) = entries.get(key) {
-            if entry.window_start.elapsed().as_secs() < self.config.window_seconds {
-                self.config.max_requests.saturating_sub(entry.count)
-            } else {
-                self.config.max_requests
-            }
-        } else {
-            self.config.max_requests
-        }
+        self.backend
+            .remaining(key, &self.config)
+            .await
+            .unwrap_or(self.config.max_requests)
     }
 }
 
-/// CSRF token validator
+/// Cookie carrying the issued CSRF token
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+
+/// How long an issued CSRF token cookie lives
+const CSRF_COOKIE_MAX_AGE_SECS: u64 = 12 * 60 * 60;
+
+/// CSRF token validator (double-submit cookie)
+///
+/// A token issued by `GET /api/v1/csrf-token` is set as a cookie and
+/// returned in the body; state-changing requests must echo it in the
+/// `X-CSRF-Token` header. A cross-site page can make the browser send the
+/// cookie but cannot read it to forge the header. Requests authenticated by
+/// an `Authorization` header, or carrying no cookies at all, have no ambient
+/// credentials to abuse and are exempt.
 #[derive(Debug, Clone)]
 pub struct CsrfValidator {
     /// Expected CSRF token header name
     pub header_name: String,
+    /// Cookie the issued token is stored in
+    pub cookie_name: String,
     /// Whether to require CSRF token for state-changing operations
     pub require_for_mutations: bool,
 }
 
+impl Default for CsrfValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CsrfValidator {
     pub fn new() -> Self {
         Self {
             header_name: "X-CSRF-Token".to_string(),
+            cookie_name: CSRF_COOKIE_NAME.to_string(),
             require_for_mutations: true,
         }
     }
-    
+
+    /// Generate a new random token (256 bits, URL-safe base64)
+    pub fn issue_token() -> String {
+        use base64::Engine;
+        use rand::RngCore;
+
+        let mut bytes = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// `Set-Cookie` value storing `token`
+    pub fn cookie(&self, token: &str, same_site: &str) -> String {
+        format!(
+            "{}={}; Path=/; Max-Age={}; Secure; HttpOnly; SameSite={}",
+            self.cookie_name, token, CSRF_COOKIE_MAX_AGE_SECS, same_site
+        )
+    }
+
     /// Validate CSRF token for a request
-    pub fn validate(
-        &self,
-        method: &Method,
-        headers: &HeaderMap,
-        origin: Option<&String>,
-    ) -> Result<(), ApiError> {
-        // Only check CSRF for state-changing methods
-        if !self.require_for_mutations {
-            return Ok(());
-        }
-        
+    ///
+    /// Rejections are `403` with error type `csrf_token_missing` or
+    /// `csrf_token_mismatch`.
+    pub fn validate(&self, method: &Method, headers: &HeaderMap) -> Result<(), ApiError> {
         let is_mutation = matches!(
-            method,
-            &Method::POST | &Method::PUT | &Method::PATCH | &Method::DELETE
+            *method,
+            Method::POST | Method::PUT | Method::PATCH | Method::DELETE
         );
-        
-        if !is_mutation {
+        if !self.require_for_mutations || !is_mutation {
             return Ok(());
         }
-        
-        // Get CSRF token from header
-        let csrf_token = headers
+
+        // Token-based and cookieless requests cannot be forged cross-site
+        if headers.contains_key(header::AUTHORIZATION)
+            || headers.contains_key(crate::middleware::auth_context::API_KEY_HEADER)
+            || !headers.contains_key(header::COOKIE)
+        {
+            return Ok(());
+        }
+
+        let header_token = headers
             .get(&self.header_name)
-            .or_else(|| headers.get("X-Csrf-Token"))
-            .and_then(|h| h.to_str().ok());
-        
-        // If no token and same-site validation passed, allow
-        // (for API clients that use Origin-based validation)
-        if csrf_token.is_none() {
-            // Rely on same-site validation via Origin header
-            // This is acceptable for REST APIs
-            if origin.is_some() {
-                return Ok(());
+            .and_then(|h| h.to_str().ok())
+            .filter(|token| !token.is_empty());
+        let cookie_token = cookie_value(headers, &self.cookie_name).filter(|token| !token.is_empty());
+
+        match (header_token, cookie_token) {
+            (Some(header_token), Some(cookie_token)) => {
+                use subtle::ConstantTimeEq;
+
+                if bool::from(header_token.as_bytes().ct_eq(cookie_token.as_bytes())) {
+                    Ok(())
+                } else {
+                    Err(ApiError::csrf(
+                        "csrf_token_mismatch",
+                        format!("{} header does not match the CSRF cookie", self.header_name),
+                    ))
+                }
             }
-            return Err(ApiError::authentication(
-                "CSRF token required for state-changing operations"
-            ));
+            _ => Err(ApiError::csrf(
+                "csrf_token_missing",
+                format!(
+                    "CSRF token required for state-changing operations: send the {} cookie and {} header",
+                    self.cookie_name, self.header_name
+                ),
+            )),
         }
-        
-        // In a full implementation, validate token against session/cookie
-        // For now, we just check presence and rely on same-site validation
-        Ok(())
     }
 }
 
+/// Value of the cookie `name` in the request's `Cookie` headers
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
 /// Unified security context combining all security features
 #[derive(Debug, Clone)]
 pub struct SecurityContext {
@@ -206,14 +265,11 @@ impl SecurityContext {
     /// Check rate limit
     pub async fn check_rate_limit(&self) -> Result<(), ApiError> {
         if let Some(ref limiter) = self.rate_limiter {
-            let key = if limiter.config.by_user {
-                self.auth.user_id.to_string()
-            } else {
-                self.request.remote_addr
-                    .clone()
-                    .unwrap_or_else(|| "unknown".to_string())
-            };
-            limiter.check(&key).await
+            let key = limiter.key_for(
+                Some((self.auth.user_id, self.auth.organization_id)),
+                self.request.remote_addr.as_deref(),
+            );
+            limiter.check(&key).await.map(|_| ())
         } else {
             Ok(())
         }
@@ -222,13 +278,10 @@ impl SecurityContext {
     /// Get remaining rate limit requests
     pub async fn rate_limit_remaining(&self) -> u32 {
         if let Some(ref limiter) = self.rate_limiter {
-            let key = if limiter.config.by_user {
-                self.auth.user_id.to_string()
-            } else {
-                self.request.remote_addr
-                    .clone()
-                    .unwrap_or_else(|| "unknown".to_string())
-            };
+            let key = limiter.key_for(
+                Some((self.auth.user_id, self.auth.organization_id)),
+                self.request.remote_addr.as_deref(),
+            );
             limiter.remaining(&key).await
         } else {
             u32::MAX
@@ -238,7 +291,7 @@ impl SecurityContext {
     /// Validate CSRF token
     pub fn validate_csrf(&self, method: &Method, headers: &HeaderMap) -> Result<(), ApiError> {
         if let Some(ref validator) = self.csrf_validator {
-            validator.validate(method, headers, self.request.origin.as_ref())
+            validator.validate(method, headers)
         } else {
             Ok(())
         }
@@ -277,10 +330,22 @@ pub struct SecurityConfig {
     pub rate_limit: Option<RateLimitConfig>,
     /// CSRF validation configuration
     pub csrf: Option<CsrfValidator>,
-    /// Whether to enforce same-site validation strictly
+    /// Reject cross-site requests and mark cookies `SameSite=Strict`
+    /// (otherwise `Lax`)
     pub strict_same_site: bool,
 }
 
+impl SecurityConfig {
+    /// `SameSite` attribute for cookies set by this server
+    pub fn cookie_same_site(&self) -> &'static str {
+        if self.strict_same_site {
+            "Strict"
+        } else {
+            "Lax"
+        }
+    }
+}
+
 impl Default for SecurityConfig {
     fn default() -> Self {
         Self {
@@ -306,11 +371,18 @@ impl SecurityMiddlewareState {
         Self {
             rate_limiter: config.rate_limit.as_ref()
                 .map(|cfg| Arc::new(RateLimiter::new(cfg.clone()))),
-            csrf_validator: config.csrf.as_ref()
-                .map(|v| Arc::new(CsrfValidator::new())),
+            csrf_validator: config.csrf.clone().map(Arc::new),
             config,
         }
     }
+
+    /// Enforce the configured rate limit through `backend` instead of memory
+    #[must_use]
+    pub fn with_rate_limit_backend(mut self, backend: Arc<dyn RateLimitBackend>) -> Self {
+        self.rate_limiter = self.config.rate_limit.as_ref()
+            .map(|cfg| Arc::new(RateLimiter::with_backend(cfg.clone(), backend)));
+        self
+    }
 }
 
 // Note: SecurityContext cannot implement FromRequestParts directly because
@@ -339,30 +411,32 @@ impl SecurityContext {
     }
     
     /// Create from contexts and perform security checks
+    ///
+    /// `rate_limited` is the decision already made for this request, if any
+    /// (from `rate_limit_middleware` or the `AuthContext` extractor); the
+    /// request is only counted against the limit when there is none.
     pub async fn from_contexts_with_checks(
         auth: AuthContext,
         request: RequestContext,
         method: &Method,
         headers: &HeaderMap,
         security_state: &SecurityMiddlewareState,
+        rate_limited: Option<&RateLimitDecision>,
     ) -> Result<Self, ApiError> {
         // Perform rate limiting check
         let rate_limiter = security_state.rate_limiter.clone();
-        if let Some(ref limiter) = rate_limiter {
-            let key = if limiter.config.by_user {
-                auth.user_id.to_string()
-            } else {
-                request.remote_addr
-                    .clone()
-                    .unwrap_or_else(|| "unknown".to_string())
-            };
+        if let (Some(ref limiter), None) = (&rate_limiter, rate_limited) {
+            let key = limiter.key_for(
+                Some((auth.user_id, auth.organization_id)),
+                request.remote_addr.as_deref(),
+            );
             limiter.check(&key).await?;
         }
         
         // Perform CSRF validation
         let csrf_validator = security_state.csrf_validator.clone();
         if let Some(ref validator) = csrf_validator {
-            validator.validate(method, headers, request.origin.as_ref())?;
+            validator.validate(method, headers)?;
         }
         
         // Enforce strict same-site if configured
@@ -432,22 +506,91 @@ mod tests {
     #[test]
     fn test_csrf_validator() {
         let validator = CsrfValidator::new();
+        let token = CsrfValidator::issue_token();
         let mut headers = HeaderMap::new();
-        
+        headers.insert(header::COOKIE, format!("session=abc; csrf_token={}", token).parse().unwrap());
+
         // GET request should pass without token
-        assert!(validator.validate(&Method::GET, &headers, None).is_ok());
-        
-        // POST without token should fail
-        assert!(validator.validate(&Method::POST, &headers, None).is_err());
-        
-        // POST with token should pass
-        headers.insert("X-CSRF-Token", "test-token".parse().unwrap());
-        assert!(validator.validate(&Method::POST, &headers, None).is_ok());
-        
-        // POST with origin should pass (same-site validation)
-        headers.remove("X-CSRF-Token");
-        let origin = Some("https://example.com".to_string());
-        assert!(validator.validate(&Method::POST, &headers, origin.as_ref()).is_ok());
+        assert!(validator.validate(&Method::GET, &headers).is_ok());
+
+        // Cookie-bearing POST without the header token should fail
+        let missing = validator.validate(&Method::POST, &headers).unwrap_err();
+        assert_eq!(missing.status_code(), axum::http::StatusCode::FORBIDDEN);
+        assert_eq!(missing.error_type(), "csrf_token_missing");
+
+        // POST echoing the cookie token should pass
+        headers.insert("X-CSRF-Token", token.parse().unwrap());
+        assert!(validator.validate(&Method::POST, &headers).is_ok());
+
+        // POST with a different token should fail
+        headers.insert("X-CSRF-Token", CsrfValidator::issue_token().parse().unwrap());
+        let mismatch = validator.validate(&Method::DELETE, &headers).unwrap_err();
+        assert_eq!(mismatch.error_type(), "csrf_token_mismatch");
     }
-}
 
+    #[test]
+    fn test_csrf_exempts_requests_without_ambient_credentials() {
+        let validator = CsrfValidator::new();
+
+        // No cookies: nothing a cross-site page could ride on
+        assert!(validator.validate(&Method::POST, &HeaderMap::new()).is_ok());
+
+        // Bearer tokens are not sent automatically by browsers
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, "session=abc".parse().unwrap());
+        headers.insert(header::AUTHORIZATION, "Bearer token".parse().unwrap());
+        assert!(validator.validate(&Method::POST, &headers).is_ok());
+    }
+
+    #[test]
+    fn test_csrf_tokens_are_random_and_cookies_follow_same_site_config() {
+        let token = CsrfValidator::issue_token();
+        assert_eq!(token.len(), 43);
+        assert_ne!(token, CsrfValidator::issue_token());
+
+        let strict = SecurityConfig {
+            strict_same_site: true,
+            ..SecurityConfig::default()
+        };
+        let cookie = CsrfValidator::new().cookie(&token, strict.cookie_same_site());
+        assert!(cookie.starts_with(&format!("csrf_token={};", token)));
+        assert!(cookie.ends_with("HttpOnly; SameSite=Strict"));
+        assert_eq!(SecurityConfig::default().cookie_same_site(), "Lax");
+    }
+
+    #[tokio::test]
+    async fn test_security_context_reuses_the_rate_limit_decision() {
+        let state = SecurityMiddlewareState::new(SecurityConfig {
+            rate_limit: Some(RateLimitConfig {
+                max_requests: 1,
+                window_seconds: 60,
+                by_user: true,
+            }),
+            ..SecurityConfig::default()
+        });
+        let auth = AuthContext::new(Uuid::new_v4(), Uuid::new_v4());
+        let check = |decision: Option<RateLimitDecision>| {
+            let (auth, state) = (auth.clone(), state.clone());
+            async move {
+                SecurityContext::from_contexts_with_checks(
+                    auth,
+                    RequestContext::new(),
+                    &Method::GET,
+                    &HeaderMap::new(),
+                    &state,
+                    decision.as_ref(),
+                )
+                .await
+                .map(|_| ())
+            }
+        };
+
+        // Requests already counted by the middleware are not counted again
+        let counted = state.rate_limiter.as_ref().unwrap().check("elsewhere").await.unwrap();
+        for _ in 0..3 {
+            assert!(check(Some(counted.clone())).await.is_ok());
+        }
+        assert!(check(None).await.is_ok());
+        assert!(check(None).await.is_err());
+    }
+}
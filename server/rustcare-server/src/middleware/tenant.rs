@@ -0,0 +1,168 @@
+//! Tenant binding and cross-tenant isolation
+//!
+//! Every request is bound to the tenant of its caller (the JWT `org_id`
+//! claim), or for anonymous requests to the tenant named by the
+//! `X-Tenant-ID` header or subdomain. The resolved `TenantId` is put in the
+//! request extensions for handlers, `RequestContext` and `AuthContext`.
+//!
+//! Requests naming another tenant, in a header, subdomain or an
+//! `/organizations/{org_id}` path, are rejected here before any handler runs.
+
+use crate::error::ApiError;
+use crate::middleware::auth_context::authenticated_context;
+use crate::middleware::request_context::client_addr;
+use auth_gateway::{GatewayError, TenantId, TenantResolver};
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Path prefix of routes scoped to a single organization
+const ORGANIZATIONS_PREFIX: &str = "/api/v1/organizations/";
+
+/// Bind the request to a tenant and reject cross-tenant access
+///
+/// A no-op unless an `Arc<TenantResolver>` is in the request extensions.
+pub async fn tenant_middleware(mut request: Request, next: Next) -> Response {
+    let Some(resolver) = request.extensions().get::<Arc<TenantResolver>>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let caller = authenticated_context(request.headers());
+    let claimed = caller
+        .as_ref()
+        .map(|ctx| ctx.organization_id)
+        .filter(|id| !id.is_nil())
+        .map(TenantId::new);
+
+    let tenant = match resolver.resolve(claimed, request.headers()) {
+        Ok(found) => found.map(|(tenant, _)| tenant),
+        Err(GatewayError::TenantMismatch { caller: tenant, target }) => {
+            return reject_cross_tenant(&request, caller.map(|ctx| ctx.user_id), tenant, target);
+        }
+        Err(e) => return ApiError::from(e).into_response(),
+    };
+
+    // A path naming an organization binds the request to it; the AuthContext
+    // extractor then holds callers not authenticated by JWT to that tenant
+    let tenant = match (tenant, organization_in_path(request.uri().path())) {
+        (Some(tenant), Some(target)) if tenant != target => {
+            return reject_cross_tenant(&request, caller.map(|ctx| ctx.user_id), tenant, target);
+        }
+        (tenant, target) => tenant.or(target),
+    };
+
+    if let Some(tenant) = tenant {
+        request.extensions_mut().insert(tenant);
+    }
+    next.run(request).await
+}
+
+/// Organization addressed by an `/api/v1/organizations/{org_id}/...` path
+fn organization_in_path(path: &str) -> Option<TenantId> {
+    let rest = path.strip_prefix(ORGANIZATIONS_PREFIX)?;
+    let segment = rest.split('/').next()?;
+    Uuid::parse_str(segment).ok().map(TenantId::new)
+}
+
+fn reject_cross_tenant(request: &Request, user_id: Option<Uuid>, caller: TenantId, target: TenantId) -> Response {
+    audit_cross_tenant_access(
+        user_id,
+        caller,
+        target,
+        &format!("{} {}", request.method(), request.uri().path()),
+        client_addr(request.extensions(), request.headers()).as_deref(),
+    );
+    ApiError::from(GatewayError::TenantMismatch { caller, target }).into_response()
+}
+
+/// Record an attempt by a caller in one tenant to reach another tenant's data
+pub(crate) fn audit_cross_tenant_access(
+    user_id: Option<Uuid>,
+    caller: TenantId,
+    target: TenantId,
+    action: &str,
+    remote_addr: Option<&str>,
+) {
+    tracing::warn!(
+        security_event = "cross_tenant_access",
+        user_id = ?user_id,
+        caller_tenant = %caller,
+        target_tenant = %target,
+        action = %action,
+        remote_addr = ?remote_addr,
+        "Cross-tenant access attempt rejected"
+    );
+    telemetry::MetricsCollector::global()
+        .counter("tenant_isolation_violations_total")
+        .increment();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, middleware::from_fn, routing::get, Extension, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use tower::ServiceExt;
+
+    fn token(org_id: Uuid) -> String {
+        let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key-change-in-production".to_string());
+        let claims = serde_json::json!({
+            "sub": Uuid::new_v4().to_string(),
+            "org_id": org_id.to_string(),
+            "exp": chrono::Utc::now().timestamp().saturating_add(300),
+        });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/api/v1/organizations/:org_id/patients", get(|tenant: TenantId| async move { tenant.to_string() }))
+            .route("/api/v1/patients", get(|tenant: TenantId| async move { tenant.to_string() }))
+            .layer(from_fn(tenant_middleware))
+            .layer(Extension(Arc::new(TenantResolver::new())))
+    }
+
+    async fn send(uri: &str, org_id: Uuid, tenant_header: Option<Uuid>) -> (StatusCode, String) {
+        let mut request = Request::get(uri).header("Authorization", format!("Bearer {}", token(org_id)));
+        if let Some(tenant) = tenant_header {
+            request = request.header(auth_gateway::TENANT_HEADER, tenant.to_string());
+        }
+        let response = app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_bound_to_the_callers_tenant() {
+        let org = Uuid::new_v4();
+        assert_eq!(send("/api/v1/patients", org, None).await, (StatusCode::OK, org.to_string()));
+        let scoped = format!("/api/v1/organizations/{org}/patients");
+        assert_eq!(send(&scoped, org, Some(org)).await, (StatusCode::OK, org.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_cross_tenant_requests_are_rejected_before_handlers() {
+        let (caller, target) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let (by_path, body) = send(&format!("/api/v1/organizations/{target}/patients"), caller, None).await;
+        assert_eq!(by_path, StatusCode::FORBIDDEN);
+        assert!(body.contains(&target.to_string()));
+
+        let (by_header, _) = send("/api/v1/patients", caller, Some(target)).await;
+        assert_eq!(by_header, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_organization_is_read_from_scoped_paths() {
+        let org = Uuid::new_v4();
+        assert_eq!(organization_in_path(&format!("/api/v1/organizations/{org}/roles")), Some(TenantId::new(org)));
+        assert_eq!(organization_in_path(&format!("/api/v1/organizations/{org}")), Some(TenantId::new(org)));
+        assert_eq!(organization_in_path("/api/v1/organizations"), None);
+        assert_eq!(organization_in_path("/api/v1/organizations/search"), None);
+    }
+}
@@ -0,0 +1,89 @@
+//! W3C trace-context propagation
+//!
+//! Continues an incoming `traceparent`/`tracestate` so this service's spans
+//! join the caller's distributed trace instead of starting a new one. Each
+//! request also gets a stable `X-Request-ID` (taken from the client or
+//! generated here) that is echoed on the response and used by the audit log.
+//!
+//! Outgoing calls made from handlers carry the context forward with
+//! `telemetry::trace_context_headers()`.
+
+use axum::{
+    extract::Request,
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Correlation id header shared with `RequestContext`
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Request id of a request (set by `trace_context_middleware`)
+pub fn request_id(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|h| h.to_str().ok())
+}
+
+/// Trace-context middleware; must wrap all other layers
+pub async fn trace_context_middleware(mut request: Request, next: Next) -> Response {
+    let request_id = if let Some(id) = request_id(&request) {
+        id.to_string()
+    } else {
+        let id = Uuid::new_v4().to_string();
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            request.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        id
+    };
+
+    let span = tracing::info_span!(
+        "http_request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id = %request_id,
+        trace_id = tracing::field::Empty,
+    );
+    telemetry::set_remote_parent(&span, request.headers());
+    if let Some(trace_id) = span.in_scope(telemetry::current_trace_id) {
+        span.record("trace_id", trace_id.as_str());
+    }
+
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware::from_fn, routing::get, Router};
+    use telemetry::TracingSystem;
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test]
+    async fn test_continues_remote_trace() {
+        let subscriber = tracing_subscriber::registry().with(TracingSystem::new("test").layer());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route("/", get(|| async { telemetry::current_trace_id().unwrap_or_default() }))
+            .layer(from_fn(trace_context_middleware));
+        let request = Request::builder()
+            .uri("/")
+            .header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert!(response.headers().contains_key(REQUEST_ID_HEADER));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"4bf92f3577b34da6a3ce929d0e0e4736");
+    }
+}
@@ -27,6 +27,11 @@ impl ZanzibarEngineWrapper {
     pub fn new(engine: Arc<AuthorizationEngine>) -> Self {
         Self { engine }
     }
+
+    /// The wrapped engine, for checks beyond user permissions on a resource
+    pub fn engine(&self) -> &Arc<AuthorizationEngine> {
+        &self.engine
+    }
 }
 
 #[async_trait]
@@ -9,16 +9,19 @@ use crate::server::RustCareServer;
     paths(
         // Health endpoints
         crate::handlers::health::health_check,
+        crate::handlers::health::readiness_check,
         crate::handlers::health::version_info,
         crate::handlers::health::system_status,
         
         // Authentication endpoints
         crate::handlers::auth::login,
+        crate::handlers::auth::issue_csrf_token,
     ),
     components(
         schemas(
             // Health schemas
             crate::handlers::health::HealthResponse,
+            crate::handlers::health::HealthCheckDetail,
             crate::handlers::health::VersionResponse,
             crate::handlers::health::StatusResponse,
             crate::handlers::health::ServiceStatus,
@@ -29,6 +32,7 @@ use crate::server::RustCareServer;
             crate::handlers::auth::OAuthRequest,
             crate::handlers::auth::TokenValidationRequest,
             crate::handlers::auth::TokenValidationResponse,
+            crate::handlers::auth::CsrfTokenResponse,
             
             // Workflow schemas
             crate::handlers::workflow::WorkflowDefinition,
@@ -5,7 +5,7 @@ use axum::{
     Router,
 };
 use crate::{
-    handlers::{health, auth, workflow, sync, permissions, geographic, compliance, organizations, devices, secrets, kms, healthcare, pharmacy, vendors, notifications, onboarding, ui_components, plugins, forms}, // websocket temporarily disabled
+    handlers::{health, auth, workflow, sync, permissions, geographic, compliance, organizations, devices, secrets, kms, healthcare, pharmacy, vendors, notifications, onboarding, ui_components, plugins, forms, metrics, websocket},
     server::RustCareServer,
     openapi,
 };
@@ -14,10 +14,21 @@ use crate::{
 pub fn health_routes() -> Router<RustCareServer> {
     Router::new()
         .route(paths::health::HEALTH, get(health::health_check))
+        .route(paths::health::READY, get(health::readiness_check))
         .route(paths::health::VERSION, get(health::version_info))
         .route(paths::health::STATUS, get(health::system_status))
 }
 
+/// Create the Prometheus metrics route
+///
+/// Stateless so it can also be served on the admin listener.
+pub fn metrics_routes<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new().route(paths::metrics::METRICS, get(metrics::prometheus_metrics))
+}
+
 /// Create authentication routes
 pub fn auth_routes() -> Router<RustCareServer> {
     Router::new()
@@ -352,6 +363,7 @@ pub fn form_routes() -> Router<RustCareServer> {
 pub fn api_v1_routes() -> Router<RustCareServer> {
     Router::new()
         .nest("/auth", auth_routes())
+        .route(paths::auth::CSRF_TOKEN, get(auth::issue_csrf_token))
         .nest("/workflow", workflow_routes())
         .merge(sync_routes())
         .merge(permission_routes())
@@ -376,12 +388,10 @@ pub fn api_v1_routes() -> Router<RustCareServer> {
         // .nest("/analytics", analytics_routes())
 }
 
-/// Create WebSocket routes (temporarily disabled)
+/// Create WebSocket routes
 pub fn websocket_routes() -> Router<RustCareServer> {
     Router::new()
-        // TODO: Re-enable WebSocket routes after fixing compilation issues
-        // .route("/ws", get(websocket::websocket_handler))
-        // .route("/ws/health", get(websocket::websocket_handler))
+        .route(paths::websocket::EVENTS, get(websocket::websocket_handler))
 }
 
 /// Postman collection handler
@@ -391,7 +401,7 @@ pub async fn postman_collection() -> axum::response::Json<serde_json::Value> {
 
 /// Create all application routes
 pub fn create_routes() -> Router<RustCareServer> {
-    Router::new()
+    let router = Router::new()
         // Health check routes (no authentication required)
         .merge(health_routes())
         // API documentation routes
@@ -400,6 +410,15 @@ pub fn create_routes() -> Router<RustCareServer> {
         .route("/postman-collection.json", get(postman_collection))
         // API v1 routes (authentication required)
         .nest(paths::API_V1, api_v1_routes())
+        // Real-time event push (authenticated on upgrade)
+        .merge(websocket_routes());
         // TODO: Add API versioning:
         // .nest("/api/v2", api_v2_routes())
+
+    // Metrics move to the admin listener when a dedicated port is configured
+    if metrics::admin_port().is_some() {
+        router
+    } else {
+        router.merge(metrics_routes())
+    }
 }
\ No newline at end of file
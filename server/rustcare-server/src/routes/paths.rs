@@ -32,10 +32,21 @@ pub const API_V1: &str = "/api/v1";
 pub mod health {
     use super::API_V1;
     pub const HEALTH: &str = "/health";
+    pub const READY: &str = "/ready";
     pub const VERSION: &str = "/version";
     pub const STATUS: &str = "/status";
 }
 
+/// Prometheus scrape endpoint
+pub mod metrics {
+    pub const METRICS: &str = "/metrics";
+}
+
+/// Real-time event push
+pub mod websocket {
+    pub const EVENTS: &str = "/ws";
+}
+
 /// Authentication endpoints
 pub mod auth {
     use super::API_V1;
@@ -44,6 +55,7 @@ pub mod auth {
     pub const OAUTH_AUTHORIZE: &str = "/oauth/authorize";
     pub const TOKEN_VALIDATE: &str = "/token/validate";
     pub const CHECK: &str = "/auth/check";
+    pub const CSRF_TOKEN: &str = "/csrf-token";
 }
 
 /// Workflow endpoints
@@ -230,6 +242,7 @@ pub mod api_v1 {
     pub const AUTH_OAUTH_AUTHORIZE: &str = "/api/v1/oauth/authorize";
     pub const AUTH_TOKEN_VALIDATE: &str = "/api/v1/token/validate";
     pub const AUTH_CHECK: &str = "/api/v1/auth/check";
+    pub const AUTH_CSRF_TOKEN: &str = "/api/v1/csrf-token";
     
     // Pharmacy
     pub const PHARMACY_PHARMACIES: &str = "/api/v1/pharmacy/pharmacies";
@@ -2,11 +2,16 @@ use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use sqlx::{Pool, Postgres};
-use database_layer::{GeographicRepository, ComplianceRepository, QueryExecutor, DatabasePool, RlsContext};
+use database_layer::{GeographicRepository, ComplianceRepository, QueryExecutor, DatabasePool, RlsContext, Replica, ReplicaSet, ReplicaStrategy, SlowQuery, SlowQueryConfig, SlowQueryLog};
 use secrets_service::SecretsManager;
 use crypto::kms::KeyManagementService;
+use auth_gateway::{ApiKeyConfig, ApiKeyProvider};
 use auth_zanzibar::{AuthorizationEngine, repository::PostgresTupleRepository};
 use crate::middleware::ZanzibarEngineWrapper;
+use crate::handlers::websocket::WebSocketConfig;
+use crate::services::WorkflowExecutions;
+use events_bus::EventBus;
+use telemetry::{CheckOptions, HealthRegistry};
 
 /// Main RustCare server state
 #[derive(Clone)]
@@ -15,6 +20,8 @@ pub struct RustCareServer {
     pub config: ServerConfig,
     /// Database connection pool
     pub db_pool: Pool<Postgres>,
+    /// Primary pool plus read replicas, used by QueryExecutor
+    pub database_pool: DatabasePool,
     /// Geographic repository
     pub geographic_repo: GeographicRepository,
     /// Compliance repository
@@ -23,8 +30,8 @@ pub struct RustCareServer {
     pub secrets_manager: Option<Arc<SecretsManager>>,
     /// KMS provider for encryption operations
     pub kms_provider: Option<Arc<dyn KeyManagementService>>,
-    /// Authentication gateway instance (placeholder)
-    pub auth_gateway: Arc<()>,
+    /// API key authentication, accepted alongside JWTs
+    pub auth_gateway: Arc<ApiKeyProvider>,
     /// Plugin runtime instance
     pub plugin_runtime: Arc<plugin_runtime_core::LifecycleManager>,
     /// Audit engine instance (placeholder)
@@ -35,6 +42,14 @@ pub struct RustCareServer {
     pub email_service: Arc<()>,
     /// Zanzibar authorization engine (optional)
     pub zanzibar_engine: Option<Arc<ZanzibarEngineWrapper>>,
+    /// Liveness and readiness checks of the server and its dependencies
+    pub health: Arc<HealthRegistry>,
+    /// Workflow executions started over HTTP or gRPC
+    pub workflow_executions: Arc<WorkflowExecutions>,
+    /// In-process event bus feeding WebSocket connections
+    pub event_bus: Arc<EventBus>,
+    /// Query durations and recent slow queries of every QueryExecutor
+    pub slow_queries: Arc<SlowQueryLog>,
 }
 
 /// Server configuration
@@ -52,6 +67,8 @@ pub struct ServerConfig {
     pub audit_logging: bool,
     /// Plugin directory
     pub plugin_directory: String,
+    /// WebSocket event push settings
+    pub websocket: WebSocketConfig,
 }
 
 impl RustCareServer {
@@ -65,6 +82,7 @@ impl RustCareServer {
             request_timeout: 30,
             audit_logging: true,
             plugin_directory: "./plugins".to_string(),
+            websocket: WebSocketConfig::from_env(),
         };
 
         // Initialize database connection pool
@@ -94,8 +112,8 @@ impl RustCareServer {
         // Initialize compliance repository
         let compliance_repo = ComplianceRepository::new(db_pool.clone());
 
-        // Initialize auth gateway (placeholder)
-        let auth_gateway = Arc::new(());
+        // Initialize API key authentication
+        let auth_gateway = Arc::new(ApiKeyProvider::new(ApiKeyConfig::default()));
 
         // Initialize plugin runtime
         let plugin_runtime_config = plugin_runtime_core::LifecycleConfig {
@@ -124,9 +142,16 @@ impl RustCareServer {
         // Initialize Zanzibar authorization engine (optional)
         let zanzibar_engine = Self::initialize_zanzibar_engine(db_pool.clone()).await.ok();
 
+        // Route read-only queries to replicas when any are configured
+        let database_pool = Self::initialize_database_pool(db_pool.clone())?;
+
+        // Register dependency health checks
+        let health = Arc::new(Self::initialize_health_registry(&db_pool, secrets_manager.as_ref()));
+
         Ok(Self {
             config,
             db_pool,
+            database_pool,
             geographic_repo,
             compliance_repo,
             secrets_manager,
@@ -137,9 +162,77 @@ impl RustCareServer {
             database,
             email_service,
             zanzibar_engine,
+            health,
+            workflow_executions: Arc::new(WorkflowExecutions::new()),
+            event_bus: Arc::new(EventBus::with_capacity(EventBus::DEFAULT_CAPACITY)),
+            slow_queries: Arc::new(SlowQueryLog::new(SlowQueryConfig::from_env())),
         })
     }
 
+    /// Register health checks for the dependencies this server uses
+    ///
+    /// The database is required to serve requests; Redis (rate limiting and
+    /// idempotency fall back to memory) and the secrets provider only degrade
+    /// readiness.
+    fn initialize_health_registry(
+        db_pool: &Pool<Postgres>,
+        secrets_manager: Option<&Arc<SecretsManager>>,
+    ) -> HealthRegistry {
+        let registry = HealthRegistry::new();
+        let timeout = std::time::Duration::from_secs(2);
+
+        let pool = db_pool.clone();
+        registry.register_fn("database", CheckOptions::critical(timeout), move || {
+            let pool = pool.clone();
+            async move {
+                sqlx::query("SELECT 1")
+                    .execute(&pool)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+        });
+
+        if let Ok(redis_url) = std::env::var("REDIS_URL") {
+            match redis::Client::open(redis_url) {
+                Ok(client) => registry.register_fn("redis", CheckOptions::non_critical(timeout), move || {
+                    let client = client.clone();
+                    async move {
+                        let mut conn = client
+                            .get_multiplexed_tokio_connection()
+                            .await
+                            .map_err(|e| e.to_string())?;
+                        redis::cmd("PING")
+                            .query_async::<_, String>(&mut conn)
+                            .await
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    }
+                }),
+                Err(e) => tracing::warn!("Invalid REDIS_URL; skipping Redis health check: {}", e),
+            }
+        }
+
+        if let Some(secrets_manager) = secrets_manager {
+            use secrets_service::SecretProvider;
+
+            let secrets_manager = secrets_manager.clone();
+            registry.register_fn("secrets_provider", CheckOptions::non_critical(timeout), move || {
+                let secrets_manager = secrets_manager.clone();
+                async move {
+                    let status = secrets_manager.health_check().await.map_err(|e| e.to_string())?;
+                    if status.healthy {
+                        Ok(())
+                    } else {
+                        Err(status.message)
+                    }
+                }
+            });
+        }
+
+        registry
+    }
+
     /// Get server configuration
     pub fn get_config(&self) -> &ServerConfig {
         &self.config
@@ -158,14 +251,56 @@ impl RustCareServer {
     /// Get QueryExecutor with optional RLS context
     /// This is the preferred way to execute database queries
     pub fn query_executor(&self) -> QueryExecutor {
-        let db_pool = DatabasePool::from_pool(self.db_pool.clone());
-        QueryExecutor::new(db_pool)
+        QueryExecutor::new(self.database_pool.clone()).with_slow_query_log(self.slow_queries.clone())
     }
 
     /// Get QueryExecutor with RLS context
     pub fn query_executor_with_rls(&self, rls_context: RlsContext) -> QueryExecutor {
-        let db_pool = DatabasePool::from_pool(self.db_pool.clone());
-        QueryExecutor::new(db_pool).with_rls_context(rls_context)
+        QueryExecutor::new(self.database_pool.clone())
+            .with_slow_query_log(self.slow_queries.clone())
+            .with_rls_context(rls_context)
+    }
+
+    /// Recent slow queries, most recent first, for admin diagnostics
+    pub fn recent_slow_queries(&self) -> Vec<SlowQuery> {
+        self.slow_queries.recent()
+    }
+
+    /// Wrap the primary pool with the read replicas listed in
+    /// `DATABASE_REPLICA_URLS` (comma-separated). Replica connections open
+    /// lazily and are health-checked every 10 seconds.
+    fn initialize_database_pool(db_pool: Pool<Postgres>) -> Result<DatabasePool> {
+        let pool = DatabasePool::from_pool(db_pool);
+        let urls = std::env::var("DATABASE_REPLICA_URLS").unwrap_or_default();
+        let urls: Vec<&str> = urls.split(',').map(str::trim).filter(|url| !url.is_empty()).collect();
+        if urls.is_empty() {
+            return Ok(pool);
+        }
+
+        let mut replicas = Vec::with_capacity(urls.len());
+        for (index, url) in urls.iter().enumerate() {
+            let replica_pool = sqlx::postgres::PgPoolOptions::new()
+                .max_connections(20)
+                .connect_lazy(url)?;
+            replicas.push(Replica::new(format!("replica-{}", index), replica_pool));
+        }
+        let strategy = match std::env::var("DATABASE_REPLICA_STRATEGY").as_deref() {
+            Ok("least_connections") => ReplicaStrategy::LeastConnections,
+            _ => ReplicaStrategy::RoundRobin,
+        };
+        let read_your_writes = std::env::var("DATABASE_READ_YOUR_WRITES_MS")
+            .ok()
+            .and_then(|ms| ms.parse().ok())
+            .unwrap_or(2000);
+
+        let replicas = Arc::new(
+            ReplicaSet::new(replicas)
+                .with_strategy(strategy)
+                .with_read_your_writes(std::time::Duration::from_millis(read_your_writes)),
+        );
+        replicas.spawn_health_checks(std::time::Duration::from_secs(10));
+        tracing::info!(count = urls.len(), ?strategy, "Read replicas configured");
+        Ok(pool.with_replicas(replicas))
     }
 
     /// Initialize secrets manager from environment configuration
@@ -230,6 +365,11 @@ impl RustCareServer {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(1000),
+            // JSON list of per-key warming / stale-while-revalidate policies
+            policies: std::env::var("SECRETS_CACHE_POLICIES")
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
         };
 
         // Audit configuration
@@ -392,6 +532,7 @@ impl Default for ServerConfig {
             request_timeout: 30,
             audit_logging: true,
             plugin_directory: "./plugins".to_string(),
+            websocket: WebSocketConfig::default(),
         }
     }
 }
\ No newline at end of file
@@ -6,7 +6,9 @@
 pub mod organization_service;
 pub mod compliance_service;
 pub mod audit;
+pub mod workflow_executions;
 
 pub use organization_service::OrganizationService;
 pub use compliance_service::ComplianceService;
 pub use audit::AuditService;
+pub use workflow_executions::WorkflowExecutions;
@@ -0,0 +1,180 @@
+//! Workflow executions started through the API
+//!
+//! Shared by the HTTP and gRPC workflow endpoints, so an execution started
+//! over one can be followed over the other. Executions are tracked in memory
+//! per organization until the workflow engine takes over running them.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Lifecycle state of a workflow execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl ExecutionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionState::Running => "running",
+            ExecutionState::Completed => "completed",
+            ExecutionState::Failed => "failed",
+            ExecutionState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, ExecutionState::Running)
+    }
+}
+
+/// A workflow execution and what it was started with
+#[derive(Debug, Clone)]
+pub struct WorkflowExecutionRecord {
+    pub execution_id: Uuid,
+    pub workflow_id: String,
+    pub organization_id: Uuid,
+    pub started_by: Uuid,
+    pub state: ExecutionState,
+    pub priority: Option<String>,
+    pub input_data: HashMap<String, Value>,
+    /// Clinical context such as the patient or encounter the execution is for
+    pub context: HashMap<String, String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub current_step: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Parameters of a new execution
+#[derive(Debug, Clone, Default)]
+pub struct StartExecution {
+    pub workflow_id: String,
+    pub organization_id: Uuid,
+    pub started_by: Uuid,
+    pub priority: Option<String>,
+    pub input_data: HashMap<String, Value>,
+    pub context: HashMap<String, String>,
+}
+
+/// Why an execution could not be cancelled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CancelError {
+    NotFound,
+    AlreadyFinished(ExecutionState),
+}
+
+/// In-memory registry of workflow executions
+#[derive(Debug, Default)]
+pub struct WorkflowExecutions {
+    executions: RwLock<HashMap<Uuid, WorkflowExecutionRecord>>,
+}
+
+impl WorkflowExecutions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a new running execution
+    pub async fn start(&self, start: StartExecution) -> WorkflowExecutionRecord {
+        // TODO: Hand the execution to workflow-engine once it can run definitions
+        let record = WorkflowExecutionRecord {
+            execution_id: Uuid::new_v4(),
+            workflow_id: start.workflow_id,
+            organization_id: start.organization_id,
+            started_by: start.started_by,
+            state: ExecutionState::Running,
+            priority: start.priority,
+            input_data: start.input_data,
+            context: start.context,
+            started_at: Utc::now(),
+            completed_at: None,
+            current_step: None,
+            error_message: None,
+        };
+        self.executions
+            .write()
+            .await
+            .insert(record.execution_id, record.clone());
+        record
+    }
+
+    /// Execution `execution_id`, if it belongs to `organization_id`
+    pub async fn get(&self, organization_id: Uuid, execution_id: Uuid) -> Option<WorkflowExecutionRecord> {
+        self.executions
+            .read()
+            .await
+            .get(&execution_id)
+            .filter(|record| record.organization_id == organization_id)
+            .cloned()
+    }
+
+    /// Cancel a running execution of `organization_id`
+    pub async fn cancel(
+        &self,
+        organization_id: Uuid,
+        execution_id: Uuid,
+    ) -> Result<WorkflowExecutionRecord, CancelError> {
+        let mut executions = self.executions.write().await;
+        let record = executions
+            .get_mut(&execution_id)
+            .filter(|record| record.organization_id == organization_id)
+            .ok_or(CancelError::NotFound)?;
+        if record.state.is_finished() {
+            return Err(CancelError::AlreadyFinished(record.state));
+        }
+        record.state = ExecutionState::Cancelled;
+        record.completed_at = Some(Utc::now());
+        Ok(record.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start(executions: &WorkflowExecutions, organization_id: Uuid) -> WorkflowExecutionRecord {
+        executions.start(StartExecution {
+            workflow_id: "patient-admission".to_string(),
+            organization_id,
+            started_by: Uuid::new_v4(),
+            ..Default::default()
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_executions_are_scoped_to_their_organization() {
+        let executions = WorkflowExecutions::new();
+        let org = Uuid::new_v4();
+        let record = start(&executions, org).await;
+
+        assert_eq!(executions.get(org, record.execution_id).await.unwrap().state, ExecutionState::Running);
+        assert!(executions.get(Uuid::new_v4(), record.execution_id).await.is_none());
+        assert_eq!(
+            executions.cancel(Uuid::new_v4(), record.execution_id).await.unwrap_err(),
+            CancelError::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_finished_executions_cannot_be_cancelled() {
+        let executions = WorkflowExecutions::new();
+        let org = Uuid::new_v4();
+        let record = start(&executions, org).await;
+
+        let cancelled = executions.cancel(org, record.execution_id).await.unwrap();
+        assert_eq!(cancelled.state, ExecutionState::Cancelled);
+        assert!(cancelled.completed_at.is_some());
+        assert_eq!(
+            executions.cancel(org, record.execution_id).await.unwrap_err(),
+            CancelError::AlreadyFinished(ExecutionState::Cancelled)
+        );
+    }
+}
@@ -15,18 +15,32 @@
 //!
 //! - Append-only audit log (no updates/deletes)
 //! - Cryptographic hash chain for tamper detection
+//!   (`entry_hash = SHA-256(prev_hash || serialized_entry)`), with the chain
+//!   head stored alongside and synced to the server
 //! - Separate audit database file with restricted permissions
 //! - Automatic rotation and archival
 
 use crate::error::{SyncError, SyncResult};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use sqlx::sqlite::SqlitePool;
 use sqlx::Row;
 use std::path::Path;
 use uuid::Uuid;
 
+/// `prev_hash` of the first entry in the chain
+const GENESIS_HASH: &str = "0";
+
+/// Entries hashed by concatenating their fields, before chaining over a
+/// serialized entry
+const LEGACY_HASH_VERSION: i64 = 1;
+
+/// Entries hashed as `SHA-256(prev_hash || serialized_entry)`
+const CHAIN_HASH_VERSION: i64 = 2;
+
+/// Entries read per query while verifying the chain
+const VERIFY_BATCH_SIZE: i64 = 1_000;
+
 /// Audit event type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -130,25 +144,125 @@ impl AuditEntry {
         entry
     }
     
-    /// Compute cryptographic hash of this entry
+    /// Compute cryptographic hash of this entry, chained to `prev_hash`
     fn compute_hash(&self) -> String {
-        let mut hasher = Sha256::new();
-        
-        // Include all fields except entry_hash itself
         // Use string representations to match database storage format
-        hasher.update(self.id.to_string().as_bytes());
-        hasher.update(self.timestamp.to_rfc3339().as_bytes());
-        hasher.update(format!("{:?}", self.action).as_bytes());
-        hasher.update(self.actor.as_bytes());
-        hasher.update(self.resource.as_bytes());
-        hasher.update(self.entity_type.as_ref().map(|s| s.as_str()).unwrap_or("").as_bytes());
-        hasher.update(self.entity_id.as_ref().map(|id| id.to_string()).unwrap_or_default().as_bytes());
-        hasher.update(&[self.phi_flag as u8]);
-        hasher.update(&[self.success as u8]);
-        hasher.update(self.metadata.to_string().as_bytes());
-        hasher.update(self.prev_hash.as_bytes());
-        
-        format!("{:x}", hasher.finalize())
+        let id = self.id.to_string();
+        let timestamp = self.timestamp.to_rfc3339();
+        let action = format!("{:?}", self.action);
+        let entity_id = self.entity_id.map(|id| id.to_string());
+        let metadata = self.metadata.to_string();
+        
+        StoredEntry {
+            id: &id,
+            timestamp: &timestamp,
+            action: &action,
+            actor: &self.actor,
+            resource: &self.resource,
+            entity_type: self.entity_type.as_deref(),
+            entity_id: entity_id.as_deref(),
+            phi_flag: self.phi_flag,
+            success: self.success,
+            metadata: &metadata,
+        }
+        .chain_hash(&self.prev_hash)
+    }
+}
+
+/// An entry's columns as stored, in the form that is hashed into the chain
+///
+/// Hashing the stored strings rather than re-serialized values means
+/// verification never depends on how a value round-trips through the
+/// database.
+#[derive(Serialize)]
+struct StoredEntry<'a> {
+    id: &'a str,
+    timestamp: &'a str,
+    action: &'a str,
+    actor: &'a str,
+    resource: &'a str,
+    entity_type: Option<&'a str>,
+    entity_id: Option<&'a str>,
+    phi_flag: bool,
+    success: bool,
+    metadata: &'a str,
+}
+
+impl StoredEntry<'_> {
+    /// `SHA-256(prev_hash || serialized_entry)`, hex encoded
+    fn chain_hash(&self, prev_hash: &str) -> String {
+        let serialized = serde_json::to_vec(self).expect("audit entry serializes to JSON");
+        hex::encode(crypto::chain_hash(prev_hash.as_bytes(), &serialized))
+    }
+    
+    /// Hash of entries written before chain hashing
+    ///
+    /// Fields were concatenated without separators, so this is only used to
+    /// verify existing entries.
+    fn legacy_hash(&self, prev_hash: &str) -> String {
+        let mut data = Vec::new();
+        data.extend_from_slice(self.id.as_bytes());
+        data.extend_from_slice(self.timestamp.as_bytes());
+        data.extend_from_slice(self.action.as_bytes());
+        data.extend_from_slice(self.actor.as_bytes());
+        data.extend_from_slice(self.resource.as_bytes());
+        data.extend_from_slice(self.entity_type.unwrap_or("").as_bytes());
+        data.extend_from_slice(self.entity_id.unwrap_or("").as_bytes());
+        data.push(self.phi_flag as u8);
+        data.push(self.success as u8);
+        data.extend_from_slice(self.metadata.as_bytes());
+        data.extend_from_slice(prev_hash.as_bytes());
+        hex::encode(crypto::sha256(&data))
+    }
+}
+
+/// Position and hash of the newest audit entry
+///
+/// Any retroactive edit changes the hash of every later entry, so a head
+/// recorded elsewhere (e.g. on the sync server) pins the log up to that point.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainHead {
+    /// Sequence number of the entry, starting at 1
+    pub seq: i64,
+    /// Hash of the entry
+    pub entry_hash: String,
+}
+
+/// Why a link in the audit chain failed verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainBreak {
+    /// Entries are missing before this one
+    SequenceGap { expected_seq: i64 },
+    /// The entry does not point at the hash of the entry before it
+    PrevHashMismatch,
+    /// The entry's contents no longer match its hash
+    HashMismatch,
+    /// The log does not end at the stored or given chain head
+    HeadMismatch { expected: Option<ChainHead> },
+}
+
+/// The first link that failed verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BrokenLink {
+    /// Sequence number of the offending entry (0 if the log is empty)
+    pub seq: i64,
+    /// ID of the offending entry, if there is one
+    pub entry_id: Option<String>,
+    pub reason: ChainBreak,
+}
+
+/// Outcome of [`AuditLogger::verify_chain`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainVerification {
+    /// Every link verified up to `head` (`None` for an empty log)
+    Intact { verified: u64, head: Option<ChainHead> },
+    Broken(BrokenLink),
+}
+
+impl ChainVerification {
+    pub fn is_intact(&self) -> bool {
+        matches!(self, ChainVerification::Intact { .. })
     }
 }
 
@@ -184,6 +298,7 @@ pub struct AuditLogger {
     pool: SqlitePool,
     config: AuditConfig,
     last_hash: String,
+    last_seq: i64,
 }
 
 impl AuditLogger {
@@ -213,7 +328,8 @@ impl AuditLogger {
         let logger = Self {
             pool,
             config,
-            last_hash: "0".to_string(), // Genesis hash
+            last_hash: GENESIS_HASH.to_string(),
+            last_seq: 0,
         };
         
         // Initialize schema
@@ -242,7 +358,47 @@ impl AuditLogger {
                 success INTEGER NOT NULL,
                 metadata TEXT NOT NULL,
                 prev_hash TEXT NOT NULL,
-                entry_hash TEXT NOT NULL
+                entry_hash TEXT NOT NULL,
+                seq INTEGER,
+                hash_version INTEGER NOT NULL DEFAULT 1
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        
+        // Logs created before entries were sequenced were appended in rowid
+        // order and hashed with the legacy scheme
+        let columns = sqlx::query("PRAGMA table_info(audit_log)")
+            .fetch_all(&self.pool)
+            .await?;
+        let has_seq = columns
+            .iter()
+            .any(|column| column.try_get::<String, _>("name").is_ok_and(|name| name == "seq"));
+        if !has_seq {
+            sqlx::query("ALTER TABLE audit_log ADD COLUMN seq INTEGER")
+                .execute(&self.pool)
+                .await?;
+            sqlx::query("ALTER TABLE audit_log ADD COLUMN hash_version INTEGER NOT NULL DEFAULT 1")
+                .execute(&self.pool)
+                .await?;
+            sqlx::query("UPDATE audit_log SET seq = rowid")
+                .execute(&self.pool)
+                .await?;
+        }
+        
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_audit_seq ON audit_log(seq)")
+            .execute(&self.pool)
+            .await?;
+        
+        // Single-row table holding the newest entry of the chain
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_chain_head (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                seq INTEGER NOT NULL,
+                entry_hash TEXT NOT NULL,
+                updated_at TEXT NOT NULL
             )
             "#,
         )
@@ -274,22 +430,91 @@ impl AuditLogger {
     }
     
     /// Load the last hash from the database
+    ///
+    /// New entries continue from the stored chain head. Logs written before
+    /// the head was stored get one from their newest entry.
     async fn load_last_hash(mut self) -> SyncResult<Self> {
-        let row = sqlx::query(
+        let head = match self.stored_head().await? {
+            Some(head) => Some(head),
+            None => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT seq, entry_hash FROM audit_log
+                    ORDER BY seq DESC
+                    LIMIT 1
+                    "#,
+                )
+                .fetch_optional(&self.pool)
+                .await?;
+                
+                match row {
+                    Some(row) => {
+                        let head = ChainHead {
+                            seq: row.try_get("seq")?,
+                            entry_hash: row.try_get("entry_hash")?,
+                        };
+                        Self::store_head(&head, &self.pool).await?;
+                        Some(head)
+                    }
+                    None => None,
+                }
+            }
+        };
+        
+        if let Some(head) = head {
+            self.last_seq = head.seq;
+            self.last_hash = head.entry_hash;
+        }
+        
+        Ok(self)
+    }
+    
+    /// Chain head as stored in the database
+    async fn stored_head(&self) -> SyncResult<Option<ChainHead>> {
+        let row = sqlx::query("SELECT seq, entry_hash FROM audit_chain_head WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        
+        row.map(|row| {
+            Ok(ChainHead {
+                seq: row.try_get("seq")?,
+                entry_hash: row.try_get("entry_hash")?,
+            })
+        })
+        .transpose()
+    }
+    
+    async fn store_head<'e, E>(head: &ChainHead, executor: E) -> SyncResult<()>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        sqlx::query(
             r#"
-            SELECT entry_hash FROM audit_log
-            ORDER BY timestamp DESC
-            LIMIT 1
+            INSERT INTO audit_chain_head (id, seq, entry_hash, updated_at)
+            VALUES (1, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                seq = excluded.seq,
+                entry_hash = excluded.entry_hash,
+                updated_at = excluded.updated_at
             "#,
         )
-        .fetch_optional(&self.pool)
+        .bind(head.seq)
+        .bind(&head.entry_hash)
+        .bind(Utc::now().to_rfc3339())
+        .execute(executor)
         .await?;
         
-        if let Some(row) = row {
-            self.last_hash = row.try_get("entry_hash")?;
-        }
-        
-        Ok(self)
+        Ok(())
+    }
+    
+    /// Head of the chain, for syncing to the server
+    ///
+    /// `None` until the first entry is logged.
+    pub fn chain_head(&self) -> Option<ChainHead> {
+        (self.last_seq > 0).then(|| ChainHead {
+            seq: self.last_seq,
+            entry_hash: self.last_hash.clone(),
+        })
     }
     
     /// Log an audit entry
@@ -322,14 +547,20 @@ impl AuditLogger {
             self.last_hash.clone(),
         );
         
-        // Store in database
+        let head = ChainHead {
+            seq: self.last_seq + 1,
+            entry_hash: entry.entry_hash.clone(),
+        };
+        
+        // Store the entry and advance the chain head together
+        let mut tx = self.pool.begin().await?;
         sqlx::query(
             r#"
             INSERT INTO audit_log (
                 id, timestamp, action, actor, resource,
                 entity_type, entity_id, phi_flag, success,
-                metadata, prev_hash, entry_hash
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                metadata, prev_hash, entry_hash, seq, hash_version
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(entry.id.to_string())
@@ -344,10 +575,15 @@ impl AuditLogger {
         .bind(entry.metadata.to_string())
         .bind(&entry.prev_hash)
         .bind(&entry.entry_hash)
-        .execute(&self.pool)
+        .bind(head.seq)
+        .bind(CHAIN_HASH_VERSION)
+        .execute(&mut *tx)
         .await?;
+        Self::store_head(&head, &mut *tx).await?;
+        tx.commit().await?;
         
         // Update last hash for next entry
+        self.last_seq = head.seq;
         self.last_hash = entry.entry_hash;
         
         Ok(entry.id)
@@ -355,72 +591,160 @@ impl AuditLogger {
     
     /// Verify audit trail integrity
     pub async fn verify_integrity(&self) -> SyncResult<bool> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, timestamp, action, actor, resource,
-                   entity_type, entity_id, phi_flag, success, metadata, prev_hash, entry_hash
-            FROM audit_log
-            ORDER BY timestamp ASC
-            "#,
-        )
-        .fetch_all(&self.pool)
-        .await?;
-        
-        let mut expected_prev_hash = "0".to_string();
+        Ok(self.verify_chain().await?.is_intact())
+    }
+    
+    /// Walk the whole hash chain and report the first broken link
+    ///
+    /// Detects edited, removed, reordered and truncated entries. Entries are
+    /// read in batches, so memory use stays flat on large logs.
+    pub async fn verify_chain(&self) -> SyncResult<ChainVerification> {
+        let head = self.stored_head().await?;
+        self.walk_chain(0, GENESIS_HASH.to_string(), head).await
+    }
+    
+    /// Verify only the entries after a previously verified chain head
+    ///
+    /// `checkpoint` is typically the head last synced to the server. The
+    /// entry at the checkpoint must still carry the checkpoint's hash, so
+    /// edits before it are caught without rehashing the whole log.
+    pub async fn verify_chain_since(&self, checkpoint: &ChainHead) -> SyncResult<ChainVerification> {
+        let row = sqlx::query("SELECT id, entry_hash FROM audit_log WHERE seq = ?")
+            .bind(checkpoint.seq)
+            .fetch_optional(&self.pool)
+            .await?;
         
-        for row in rows {
-            let prev_hash: String = row.try_get("prev_hash")?;
-            let entry_hash: String = row.try_get("entry_hash")?;
-            
-            // Verify hash chain
-            if prev_hash != expected_prev_hash {
-                return Ok(false);
+        let entry_id = match row {
+            Some(row) => {
+                let entry_hash: String = row.try_get("entry_hash")?;
+                if crypto::ct_eq_str(&entry_hash, &checkpoint.entry_hash) {
+                    None
+                } else {
+                    Some(row.try_get("id")?)
+                }
             }
+            None => Some(None),
+        };
+        if let Some(entry_id) = entry_id {
+            return Ok(ChainVerification::Broken(BrokenLink {
+                seq: checkpoint.seq,
+                entry_id,
+                reason: ChainBreak::HeadMismatch {
+                    expected: Some(checkpoint.clone()),
+                },
+            }));
+        }
+        
+        let head = self.stored_head().await?;
+        self.walk_chain(checkpoint.seq, checkpoint.entry_hash.clone(), head).await
+    }
+    
+    /// Verify entries after `after_seq`, whose predecessor has `prev_hash`,
+    /// through to `head`
+    async fn walk_chain(
+        &self,
+        after_seq: i64,
+        mut prev_hash: String,
+        head: Option<ChainHead>,
+    ) -> SyncResult<ChainVerification> {
+        let mut last_seq = after_seq;
+        let mut verified = 0u64;
+        
+        loop {
+            let rows = sqlx::query(
+                r#"
+                SELECT id, timestamp, action, actor, resource,
+                       entity_type, entity_id, phi_flag, success, metadata,
+                       prev_hash, entry_hash, seq, hash_version
+                FROM audit_log
+                WHERE seq > ?
+                ORDER BY seq ASC
+                LIMIT ?
+                "#,
+            )
+            .bind(last_seq)
+            .bind(VERIFY_BATCH_SIZE)
+            .fetch_all(&self.pool)
+            .await?;
             
-            // Reconstruct entry to verify hash
-            let id: String = row.try_get("id")?;
-            let timestamp: String = row.try_get("timestamp")?;
-            let action: String = row.try_get("action")?;
-            let actor: String = row.try_get("actor")?;
-            let resource: String = row.try_get("resource")?;
-            let entity_type: Option<String> = row.try_get("entity_type")?;
-            let entity_id: Option<String> = row.try_get("entity_id")?;
-            let phi_flag: i32 = row.try_get("phi_flag")?;
-            let success: i32 = row.try_get("success")?;
-            let metadata: String = row.try_get("metadata")?;
-            
-            // Calculate expected hash (must match compute_hash logic)
-            let mut hasher = Sha256::new();
-            hasher.update(id.as_bytes());
-            hasher.update(timestamp.as_bytes());
-            hasher.update(action.as_bytes());
-            hasher.update(actor.as_bytes());
-            hasher.update(resource.as_bytes());
-            hasher.update(entity_type.as_ref().map(|s| s.as_str()).unwrap_or("").as_bytes());
-            hasher.update(entity_id.as_ref().map(|s| s.as_str()).unwrap_or("").as_bytes());
-            hasher.update(&[phi_flag as u8]);
-            hasher.update(&[success as u8]);
-            hasher.update(metadata.as_bytes());
-            hasher.update(prev_hash.as_bytes());
-            
-            let calculated_hash = format!("{:x}", hasher.finalize());
-            
-            if calculated_hash != entry_hash {
-                #[cfg(test)]
-                {
-                    eprintln!("Hash mismatch!");
-                    eprintln!("  ID: {}", id);
-                    eprintln!("  Expected hash: {}", entry_hash);
-                    eprintln!("  Calculated hash: {}", calculated_hash);
-                    eprintln!("  Prev hash: {}", prev_hash);
+            for row in &rows {
+                let seq: i64 = row.try_get("seq")?;
+                let id: String = row.try_get("id")?;
+                let broken = |reason| {
+                    Ok(ChainVerification::Broken(BrokenLink {
+                        seq,
+                        entry_id: Some(id.clone()),
+                        reason,
+                    }))
+                };
+                
+                if seq != last_seq + 1 {
+                    return broken(ChainBreak::SequenceGap {
+                        expected_seq: last_seq + 1,
+                    });
+                }
+                
+                let entry_prev_hash: String = row.try_get("prev_hash")?;
+                if !crypto::ct_eq_str(&entry_prev_hash, &prev_hash) {
+                    return broken(ChainBreak::PrevHashMismatch);
+                }
+                
+                let timestamp: String = row.try_get("timestamp")?;
+                let action: String = row.try_get("action")?;
+                let actor: String = row.try_get("actor")?;
+                let resource: String = row.try_get("resource")?;
+                let entity_type: Option<String> = row.try_get("entity_type")?;
+                let entity_id: Option<String> = row.try_get("entity_id")?;
+                let metadata: String = row.try_get("metadata")?;
+                let stored = StoredEntry {
+                    id: &id,
+                    timestamp: &timestamp,
+                    action: &action,
+                    actor: &actor,
+                    resource: &resource,
+                    entity_type: entity_type.as_deref(),
+                    entity_id: entity_id.as_deref(),
+                    phi_flag: row.try_get::<i32, _>("phi_flag")? != 0,
+                    success: row.try_get::<i32, _>("success")? != 0,
+                    metadata: &metadata,
+                };
+                
+                let calculated_hash = match row.try_get::<i64, _>("hash_version")? {
+                    LEGACY_HASH_VERSION => stored.legacy_hash(&prev_hash),
+                    _ => stored.chain_hash(&prev_hash),
+                };
+                let entry_hash: String = row.try_get("entry_hash")?;
+                if !crypto::ct_eq_str(&calculated_hash, &entry_hash) {
+                    return broken(ChainBreak::HashMismatch);
                 }
-                return Ok(false);
+                
+                last_seq = seq;
+                prev_hash = entry_hash;
+                verified += 1;
             }
             
-            expected_prev_hash = entry_hash;
+            if (rows.len() as i64) < VERIFY_BATCH_SIZE {
+                break;
+            }
         }
         
-        Ok(true)
+        // Entries removed from the end leave an intact but shorter chain
+        let reached = (last_seq > 0).then_some(ChainHead {
+            seq: last_seq,
+            entry_hash: prev_hash,
+        });
+        if reached != head {
+            return Ok(ChainVerification::Broken(BrokenLink {
+                seq: last_seq,
+                entry_id: None,
+                reason: ChainBreak::HeadMismatch { expected: head },
+            }));
+        }
+        
+        Ok(ChainVerification::Intact {
+            verified,
+            head: reached,
+        })
     }
     
     /// Get audit entries for a specific actor
@@ -667,4 +991,206 @@ mod tests {
         // Should still have genesis hash since logging is disabled
         assert_eq!(logger.last_hash, "0");
     }
+    
+    async fn log_entries(logger: &mut AuditLogger, count: usize) {
+        for i in 0..count {
+            logger.log(
+                AuditAction::Read,
+                format!("user{}", i),
+                format!("patient/{}", i),
+                true,
+                true,
+                json!({"action": i}),
+            ).await.unwrap();
+        }
+    }
+    
+    fn broken_link(verification: ChainVerification) -> BrokenLink {
+        match verification {
+            ChainVerification::Broken(link) => link,
+            ChainVerification::Intact { .. } => panic!("expected a broken chain"),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_verify_chain_intact() {
+        let mut logger = create_test_logger().await.unwrap();
+        assert_eq!(
+            logger.verify_chain().await.unwrap(),
+            ChainVerification::Intact { verified: 0, head: None }
+        );
+        
+        log_entries(&mut logger, 5).await;
+        
+        let head = logger.chain_head().unwrap();
+        assert_eq!(head.seq, 5);
+        assert_eq!(
+            logger.verify_chain().await.unwrap(),
+            ChainVerification::Intact { verified: 5, head: Some(head) }
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_verify_chain_detects_tampered_entry() {
+        let mut logger = create_test_logger().await.unwrap();
+        log_entries(&mut logger, 5).await;
+        
+        sqlx::query("UPDATE audit_log SET actor = 'mallory' WHERE seq = 3")
+            .execute(&logger.pool)
+            .await
+            .unwrap();
+        
+        let link = broken_link(logger.verify_chain().await.unwrap());
+        assert_eq!(link.seq, 3);
+        assert_eq!(link.reason, ChainBreak::HashMismatch);
+        assert!(!logger.verify_integrity().await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_verify_chain_detects_deleted_entry() {
+        let mut logger = create_test_logger().await.unwrap();
+        log_entries(&mut logger, 5).await;
+        
+        sqlx::query("DELETE FROM audit_log WHERE seq = 2")
+            .execute(&logger.pool)
+            .await
+            .unwrap();
+        
+        let link = broken_link(logger.verify_chain().await.unwrap());
+        assert_eq!(link.seq, 3);
+        assert_eq!(link.reason, ChainBreak::SequenceGap { expected_seq: 2 });
+    }
+    
+    #[tokio::test]
+    async fn test_verify_chain_detects_truncation() {
+        let mut logger = create_test_logger().await.unwrap();
+        log_entries(&mut logger, 5).await;
+        let head = logger.chain_head().unwrap();
+        
+        sqlx::query("DELETE FROM audit_log WHERE seq > 3")
+            .execute(&logger.pool)
+            .await
+            .unwrap();
+        
+        let link = broken_link(logger.verify_chain().await.unwrap());
+        assert_eq!(link.seq, 3);
+        assert_eq!(link.reason, ChainBreak::HeadMismatch { expected: Some(head) });
+    }
+    
+    #[tokio::test]
+    async fn test_verify_chain_since_checkpoint() {
+        let mut logger = create_test_logger().await.unwrap();
+        log_entries(&mut logger, 3).await;
+        let checkpoint = logger.chain_head().unwrap();
+        log_entries(&mut logger, 2).await;
+        
+        match logger.verify_chain_since(&checkpoint).await.unwrap() {
+            ChainVerification::Intact { verified, head } => {
+                assert_eq!(verified, 2);
+                assert_eq!(head.unwrap().seq, 5);
+            }
+            broken => panic!("unexpected {:?}", broken),
+        }
+        
+        // A rewritten entry at the checkpoint no longer matches it
+        sqlx::query("UPDATE audit_log SET entry_hash = 'forged' WHERE seq = 3")
+            .execute(&logger.pool)
+            .await
+            .unwrap();
+        let link = broken_link(logger.verify_chain_since(&checkpoint).await.unwrap());
+        assert_eq!(link.seq, 3);
+        assert!(matches!(link.reason, ChainBreak::HeadMismatch { .. }));
+    }
+    
+    #[tokio::test]
+    async fn test_legacy_log_is_migrated() {
+        let audit_db_path = get_test_audit_path();
+        let path = audit_db_path.to_str().unwrap().to_string();
+        
+        // Log written before entries were sequenced
+        {
+            let pool = SqlitePool::connect(&format!("sqlite:{}?mode=rwc", path)).await.unwrap();
+            sqlx::query(
+                r#"
+                CREATE TABLE audit_log (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    timestamp TEXT NOT NULL,
+                    action TEXT NOT NULL,
+                    actor TEXT NOT NULL,
+                    resource TEXT NOT NULL,
+                    entity_type TEXT,
+                    entity_id TEXT,
+                    phi_flag INTEGER NOT NULL,
+                    success INTEGER NOT NULL,
+                    metadata TEXT NOT NULL,
+                    prev_hash TEXT NOT NULL,
+                    entry_hash TEXT NOT NULL
+                )
+                "#,
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            
+            let mut prev_hash = GENESIS_HASH.to_string();
+            for i in 0..2 {
+                let id = Uuid::new_v4().to_string();
+                let timestamp = Utc::now().to_rfc3339();
+                let actor = format!("user{}", i);
+                let entry_hash = StoredEntry {
+                    id: &id,
+                    timestamp: &timestamp,
+                    action: "Read",
+                    actor: &actor,
+                    resource: "patient/1",
+                    entity_type: None,
+                    entity_id: None,
+                    phi_flag: true,
+                    success: true,
+                    metadata: "{}",
+                }
+                .legacy_hash(&prev_hash);
+                
+                sqlx::query(
+                    r#"
+                    INSERT INTO audit_log (
+                        id, timestamp, action, actor, resource, phi_flag, success,
+                        metadata, prev_hash, entry_hash
+                    ) VALUES (?, ?, 'Read', ?, 'patient/1', 1, 1, '{}', ?, ?)
+                    "#,
+                )
+                .bind(&id)
+                .bind(&timestamp)
+                .bind(&actor)
+                .bind(&prev_hash)
+                .bind(&entry_hash)
+                .execute(&pool)
+                .await
+                .unwrap();
+                prev_hash = entry_hash;
+            }
+            pool.close().await;
+        }
+        
+        let config = AuditConfig {
+            audit_db_path: path,
+            enabled: true,
+            max_entries_before_rotation: 100_000,
+            log_reads: true,
+        };
+        let mut logger = AuditLogger::new(config).await.unwrap();
+        assert_eq!(logger.chain_head().unwrap().seq, 2);
+        
+        log_entries(&mut logger, 1).await;
+        
+        match logger.verify_chain().await.unwrap() {
+            ChainVerification::Intact { verified, head } => {
+                assert_eq!(verified, 3);
+                assert_eq!(head.unwrap().seq, 3);
+            }
+            broken => panic!("unexpected {:?}", broken),
+        }
+        
+        let _ = std::fs::remove_file(&audit_db_path);
+    }
 }
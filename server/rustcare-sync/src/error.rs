@@ -43,6 +43,12 @@ pub enum SyncError {
         retry_after: std::time::Duration,
     },
     
+    #[error("Rate limit bucket {bucket} has no token before the deadline, retry after {retry_after:?}")]
+    BucketRateLimited {
+        bucket: String,
+        retry_after: std::time::Duration,
+    },
+    
     #[error("Internal error: {0}")]
     Internal(String),
 }
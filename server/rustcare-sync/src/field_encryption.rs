@@ -32,14 +32,33 @@
 //! // Decrypt PHI fields when retrieving from sync queue
 //! let decrypted_data = encryptor.decrypt_phi_fields(&encrypted_data).await?;
 //! ```
+//!
+//! # Key Rotation
+//!
+//! Field keys are versioned and managed by [`LocalDbKeyManager`]. Every
+//! encrypted field records the key version it was written with
+//! (`ENC:v<version>:<base64>`), and decryption selects the key by that
+//! version, so rotating the key never breaks existing records. After
+//! [`FieldEncryption::rotate_field_key`], new writes use the new key; older
+//! records are re-encrypted lazily when rewritten through
+//! [`FieldEncryption::reencrypt_phi_fields`], or eagerly by
+//! `LocalDatabase::reencrypt_field_data`.
 
 use crate::error::SyncResult;
+use crate::key_manager::LocalDbKeyManager;
 use crypto::Aes256GcmEncryptor;
 use crypto::Encryptor;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use zeroize::Zeroizing;
+
+/// Prefix marking an encrypted field value
+const ENCRYPTED_PREFIX: &str = "ENC:";
+
+/// Key version of fields written before versioning (`ENC:<base64>`)
+const LEGACY_KEY_VERSION: u32 = 1;
 
 /// Configuration for field-level encryption
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,38 +111,114 @@ impl Default for FieldEncryptionConfig {
     }
 }
 
+/// Field encryption keys by version
+pub struct FieldKeys {
+    current_version: u32,
+    keys: BTreeMap<u32, Zeroizing<Vec<u8>>>,
+}
+
+impl FieldKeys {
+    /// Create a key set that encrypts with `current_version`
+    pub fn new(current_version: u32, keys: BTreeMap<u32, Zeroizing<Vec<u8>>>) -> SyncResult<Self> {
+        if !keys.contains_key(&current_version) {
+            return Err(crate::error::SyncError::Internal(
+                format!("Missing field key for current version {}", current_version)
+            ));
+        }
+        
+        Ok(Self {
+            current_version,
+            keys,
+        })
+    }
+    
+    /// Version new fields are encrypted with
+    pub fn current_version(&self) -> u32 {
+        self.current_version
+    }
+}
+
 /// Field-level encryption handler
 pub struct FieldEncryption {
     config: FieldEncryptionConfig,
-    encryptor: Aes256GcmEncryptor,
+    encryptors: BTreeMap<u32, Aes256GcmEncryptor>,
+    current_version: u32,
     phi_fields_set: HashSet<String>,
 }
 
 impl FieldEncryption {
     /// Create a new field encryption handler
+    ///
+    /// `master_key` is used as field key version 1.
     pub fn new(config: FieldEncryptionConfig, master_key: &[u8]) -> SyncResult<Self> {
-        // Convert slice to array
-        if master_key.len() != 32 {
-            return Err(crate::error::SyncError::Internal(
-                format!("Invalid key length: expected 32 bytes, got {}", master_key.len())
-            ));
-        }
-        
-        let mut key = [0u8; 32];
-        key.copy_from_slice(master_key);
+        let mut keys = BTreeMap::new();
+        keys.insert(LEGACY_KEY_VERSION, Zeroizing::new(master_key.to_vec()));
         
-        let encryptor = Aes256GcmEncryptor::new(key)
-            .map_err(|e| crate::error::SyncError::Internal(format!("Failed to initialize AES-GCM: {}", e)))?;
+        Self::with_keys(config, FieldKeys::new(LEGACY_KEY_VERSION, keys)?)
+    }
+    
+    /// Create a field encryption handler from versioned keys
+    pub fn with_keys(config: FieldEncryptionConfig, keys: FieldKeys) -> SyncResult<Self> {
+        let mut encryptors = BTreeMap::new();
+        for (version, key) in &keys.keys {
+            encryptors.insert(*version, Self::encryptor_for(key)?);
+        }
         
         let phi_fields_set: HashSet<String> = config.phi_fields.iter().cloned().collect();
         
         Ok(Self {
             config,
-            encryptor,
+            encryptors,
+            current_version: keys.current_version,
             phi_fields_set,
         })
     }
     
+    /// Create a field encryption handler with the keys held by `key_manager`
+    pub async fn from_key_manager(
+        config: FieldEncryptionConfig,
+        key_manager: &LocalDbKeyManager,
+    ) -> SyncResult<Self> {
+        let keys = key_manager.get_or_create_field_keys().await?;
+        Self::with_keys(config, keys)
+    }
+    
+    fn encryptor_for(key: &[u8]) -> SyncResult<Aes256GcmEncryptor> {
+        // Convert slice to array
+        if key.len() != 32 {
+            return Err(crate::error::SyncError::Internal(
+                format!("Invalid key length: expected 32 bytes, got {}", key.len())
+            ));
+        }
+        
+        let mut key_bytes = Zeroizing::new([0u8; 32]);
+        key_bytes.copy_from_slice(key);
+        
+        Aes256GcmEncryptor::new(*key_bytes)
+            .map_err(|e| crate::error::SyncError::Internal(format!("Failed to initialize AES-GCM: {}", e)))
+    }
+    
+    /// Rotate to a new field key from `key_manager`
+    ///
+    /// New writes use the new key immediately. Fields encrypted under earlier
+    /// versions keep decrypting until they are re-encrypted, lazily via
+    /// [`reencrypt_phi_fields`](Self::reencrypt_phi_fields) or eagerly with
+    /// `LocalDatabase::reencrypt_field_data`. Returns the new key version.
+    pub async fn rotate_field_key(&mut self, key_manager: &LocalDbKeyManager) -> SyncResult<u32> {
+        let (version, key) = key_manager.rotate_field_key().await?;
+        self.encryptors.insert(version, Self::encryptor_for(&key)?);
+        self.current_version = version;
+        
+        tracing::info!(key_version = version, "Rotated field encryption key");
+        
+        Ok(version)
+    }
+    
+    /// Key version new fields are encrypted with
+    pub fn current_key_version(&self) -> u32 {
+        self.current_version
+    }
+    
     /// Encrypt PHI fields in a JSON value
     ///
     /// This recursively traverses the JSON structure and encrypts any fields
@@ -149,6 +244,49 @@ impl FieldEncryption {
         self.decrypt_value(data)
     }
     
+    /// Whether any encrypted field in `data` uses an older key version
+    pub fn needs_reencryption(&self, data: &Value) -> bool {
+        match data {
+            Value::String(s) => {
+                matches!(parse_encrypted(s), Some((version, _)) if version != self.current_version)
+            }
+            Value::Object(map) => map.values().any(|v| self.needs_reencryption(v)),
+            Value::Array(arr) => arr.iter().any(|v| self.needs_reencryption(v)),
+            _ => false,
+        }
+    }
+    
+    /// Re-encrypt fields written under older key versions with the current key
+    ///
+    /// Fields already on the current version are left untouched, so this is
+    /// safe to apply repeatedly, e.g. on every write of a record.
+    pub fn reencrypt_phi_fields(&self, data: &Value) -> SyncResult<Value> {
+        match data {
+            Value::String(s) => match parse_encrypted(s) {
+                Some((version, _)) if version != self.current_version => {
+                    let plaintext = self.decrypt_field(data)?;
+                    self.encrypt_field(&plaintext)
+                }
+                _ => Ok(data.clone()),
+            },
+            Value::Object(map) => {
+                let mut reencrypted_map = serde_json::Map::new();
+                for (key, val) in map {
+                    reencrypted_map.insert(key.clone(), self.reencrypt_phi_fields(val)?);
+                }
+                Ok(Value::Object(reencrypted_map))
+            }
+            Value::Array(arr) => {
+                let reencrypted_arr: Result<Vec<Value>, _> = arr
+                    .iter()
+                    .map(|v| self.reencrypt_phi_fields(v))
+                    .collect();
+                Ok(Value::Array(reencrypted_arr?))
+            }
+            _ => Ok(data.clone()),
+        }
+    }
+    
     /// Recursively encrypt a JSON value
     fn encrypt_value(&self, value: &Value) -> SyncResult<Value> {
         match value {
@@ -184,7 +322,7 @@ impl FieldEncryption {
                 for (key, val) in map {
                     // Check if this value is encrypted (starts with our prefix)
                     if let Value::String(s) = val {
-                        if s.starts_with("ENC:") {
+                        if s.starts_with(ENCRYPTED_PREFIX) {
                             decrypted_map.insert(key.clone(), self.decrypt_field(val)?);
                             continue;
                         }
@@ -211,12 +349,18 @@ impl FieldEncryption {
         let plaintext = serde_json::to_string(value)
             .map_err(|e| crate::error::SyncError::Internal(format!("Failed to serialize value: {}", e)))?;
         
-        // Encrypt the plaintext
-        let ciphertext = self.encryptor.encrypt(plaintext.as_bytes())
+        // Encrypt the plaintext with the current key
+        let encryptor = &self.encryptors[&self.current_version];
+        let ciphertext = encryptor.encrypt(plaintext.as_bytes())
             .map_err(|e| crate::error::SyncError::Internal(format!("Failed to encrypt field: {}", e)))?;
         
-        // Encode as base64 with prefix
-        let encoded = format!("ENC:{}", BASE64.encode(&ciphertext));
+        // Encode as base64 with prefix and key version
+        let encoded = format!(
+            "{}v{}:{}",
+            ENCRYPTED_PREFIX,
+            self.current_version,
+            BASE64.encode(&ciphertext)
+        );
         
         Ok(Value::String(encoded))
     }
@@ -224,17 +368,19 @@ impl FieldEncryption {
     /// Decrypt a single field value
     fn decrypt_field(&self, value: &Value) -> SyncResult<Value> {
         if let Value::String(s) = value {
-            if !s.starts_with("ENC:") {
-                return Err(crate::error::SyncError::Internal("Invalid encrypted field format".to_string()));
-            }
+            let (version, encoded) = parse_encrypted(s)
+                .ok_or_else(|| crate::error::SyncError::Internal("Invalid encrypted field format".to_string()))?;
             
-            // Remove prefix and decode from base64
-            let encoded = &s[4..];
+            // Select the key the field was written with
+            let encryptor = self.encryptors.get(&version)
+                .ok_or_else(|| crate::error::SyncError::Internal(format!("No field key for version {}", version)))?;
+            
+            // Decode from base64
             let ciphertext = BASE64.decode(encoded)
                 .map_err(|e| crate::error::SyncError::Internal(format!("Failed to decode base64: {}", e)))?;
             
             // Decrypt the ciphertext
-            let plaintext_bytes = self.encryptor.decrypt(&ciphertext)
+            let plaintext_bytes = encryptor.decrypt(&ciphertext)
                 .map_err(|e| crate::error::SyncError::Internal(format!("Failed to decrypt field: {}", e)))?;
             
             // Parse back to JSON value
@@ -261,6 +407,20 @@ impl FieldEncryption {
     }
 }
 
+/// Split an encrypted field into its key version and base64 ciphertext
+fn parse_encrypted(s: &str) -> Option<(u32, &str)> {
+    let rest = s.strip_prefix(ENCRYPTED_PREFIX)?;
+    
+    // Base64 never contains ':', so a second separator means a versioned field
+    match rest.split_once(':') {
+        Some((version, encoded)) => {
+            let version = version.strip_prefix('v')?.parse().ok()?;
+            Some((version, encoded))
+        }
+        None => Some((LEGACY_KEY_VERSION, rest)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -439,4 +599,83 @@ mod tests {
             assert_eq!(decrypted, original);
         }
     }
+    
+    fn versioned_keys(current_version: u32, versions: &[u32]) -> FieldKeys {
+        let keys = versions
+            .iter()
+            .map(|version| (*version, Zeroizing::new(vec![*version as u8; 32])))
+            .collect();
+        FieldKeys::new(current_version, keys).unwrap()
+    }
+    
+    fn ssn_config() -> FieldEncryptionConfig {
+        FieldEncryptionConfig {
+            enabled: true,
+            phi_fields: vec!["ssn".to_string()],
+        }
+    }
+    
+    #[test]
+    fn test_old_key_version_reads_after_rotation() {
+        let before = FieldEncryption::with_keys(ssn_config(), versioned_keys(1, &[1])).unwrap();
+        let original = json!({"ssn": "123-45-6789", "name": "John Doe"});
+        let old_record = before.encrypt_phi_fields(&original).unwrap();
+        assert!(old_record["ssn"].as_str().unwrap().starts_with("ENC:v1:"));
+        
+        let after = FieldEncryption::with_keys(ssn_config(), versioned_keys(2, &[1, 2])).unwrap();
+        assert_eq!(after.current_key_version(), 2);
+        assert_eq!(after.decrypt_phi_fields(&old_record).unwrap(), original);
+        
+        // New writes use the new key
+        let new_record = after.encrypt_phi_fields(&original).unwrap();
+        assert!(new_record["ssn"].as_str().unwrap().starts_with("ENC:v2:"));
+        assert_eq!(after.decrypt_phi_fields(&new_record).unwrap(), original);
+    }
+    
+    #[test]
+    fn test_reencrypt_phi_fields() {
+        let before = FieldEncryption::with_keys(ssn_config(), versioned_keys(1, &[1])).unwrap();
+        let original = json!({"patients": [{"ssn": "123-45-6789"}, {"ssn": "987-65-4321"}]});
+        let old_record = before.encrypt_phi_fields(&original).unwrap();
+        
+        let after = FieldEncryption::with_keys(ssn_config(), versioned_keys(2, &[1, 2])).unwrap();
+        assert!(after.needs_reencryption(&old_record));
+        
+        let reencrypted = after.reencrypt_phi_fields(&old_record).unwrap();
+        assert!(!after.needs_reencryption(&reencrypted));
+        assert!(reencrypted["patients"][0]["ssn"].as_str().unwrap().starts_with("ENC:v2:"));
+        assert_eq!(after.decrypt_phi_fields(&reencrypted).unwrap(), original);
+        
+        // Fields already on the current key are left as they are
+        assert_eq!(after.reencrypt_phi_fields(&reencrypted).unwrap(), reencrypted);
+        
+        // The retired key alone can no longer read the record
+        assert!(before.decrypt_phi_fields(&reencrypted).is_err());
+    }
+    
+    #[test]
+    fn test_unversioned_fields_use_first_key() {
+        let key = create_test_key();
+        let encryptor = FieldEncryption::new(ssn_config(), &key).unwrap();
+        let original = json!({"ssn": "123-45-6789"});
+        
+        // Fields written before key versioning carry no version
+        let encrypted = encryptor.encrypt_phi_fields(&original).unwrap();
+        let legacy = encrypted["ssn"].as_str().unwrap().replacen("ENC:v1:", "ENC:", 1);
+        let legacy_record = json!({"ssn": legacy});
+        
+        assert_eq!(encryptor.decrypt_phi_fields(&legacy_record).unwrap(), original);
+        assert!(!encryptor.needs_reencryption(&legacy_record));
+    }
+    
+    #[test]
+    fn test_unknown_key_version_rejected() {
+        let encryptor = FieldEncryption::with_keys(ssn_config(), versioned_keys(3, &[3])).unwrap();
+        let original = json!({"ssn": "123-45-6789"});
+        let encrypted = encryptor.encrypt_phi_fields(&original).unwrap();
+        
+        let other = FieldEncryption::with_keys(ssn_config(), versioned_keys(1, &[1, 2])).unwrap();
+        assert!(other.decrypt_phi_fields(&encrypted).is_err());
+        assert!(FieldKeys::new(4, BTreeMap::new()).is_err());
+    }
 }
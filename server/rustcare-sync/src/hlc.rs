@@ -13,7 +13,21 @@
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::fmt;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default bound on how far a remote timestamp may run ahead of our wall clock
+pub const DEFAULT_MAX_DRIFT_MS: u64 = 60_000;
+
+/// Source of physical time, in milliseconds since the UNIX epoch
+pub type PhysicalClock = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+fn system_time_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time before UNIX epoch")
+        .as_millis() as u64
+}
 
 /// Hybrid Logical Clock timestamp
 /// 
@@ -129,6 +143,8 @@ impl fmt::Display for HybridTimestamp {
 pub struct HybridLogicalClock {
     node_id: u64,
     last_timestamp: HybridTimestamp,
+    max_drift_ms: u64,
+    physical_clock: PhysicalClock,
 }
 
 impl HybridLogicalClock {
@@ -137,15 +153,41 @@ impl HybridLogicalClock {
         Self {
             node_id,
             last_timestamp: HybridTimestamp::now(node_id),
+            max_drift_ms: DEFAULT_MAX_DRIFT_MS,
+            physical_clock: Arc::new(system_time_millis),
+        }
+    }
+
+    /// Limit how far ahead of our wall clock a remote timestamp may move us
+    pub fn with_max_drift(mut self, max_drift: Duration) -> Self {
+        self.max_drift_ms = max_drift.as_millis() as u64;
+        self
+    }
+
+    /// Read physical time from `physical_clock` instead of the system clock
+    ///
+    /// Resets the clock to the new source's current time, so call this
+    /// before [`advance_to`](Self::advance_to).
+    pub fn with_physical_clock(mut self, physical_clock: PhysicalClock) -> Self {
+        self.last_timestamp = HybridTimestamp::new(physical_clock(), 0, self.node_id);
+        self.physical_clock = physical_clock;
+        self
+    }
+
+    /// Ensure every timestamp issued from now on is above `floor`
+    ///
+    /// Used on startup with the last timestamp issued before a restart, so a
+    /// wall clock that jumped backward cannot reissue earlier timestamps.
+    pub fn advance_to(&mut self, floor: HybridTimestamp) {
+        let floor = HybridTimestamp::new(floor.physical, floor.logical, self.node_id);
+        if floor > self.last_timestamp {
+            self.last_timestamp = floor;
         }
     }
 
     /// Generate a new timestamp for a local event
     pub fn tick(&mut self) -> HybridTimestamp {
-        let physical_now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time before UNIX epoch")
-            .as_millis() as u64;
+        let physical_now = (self.physical_clock)();
 
         if physical_now > self.last_timestamp.physical {
             // Physical time advanced, reset logical counter
@@ -160,11 +202,25 @@ impl HybridLogicalClock {
 
     /// Update clock on receiving a remote timestamp
     /// Returns the new timestamp to associate with the received event
+    ///
+    /// A remote physical time more than the maximum drift ahead of our wall
+    /// clock is clamped to that bound, so a misbehaving peer cannot drag our
+    /// clock arbitrarily far into the future.
     pub fn update(&mut self, remote: HybridTimestamp) -> HybridTimestamp {
-        let physical_now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time before UNIX epoch")
-            .as_millis() as u64;
+        let physical_now = (self.physical_clock)();
+
+        let ceiling = physical_now.saturating_add(self.max_drift_ms);
+        let remote = if remote.physical > ceiling {
+            tracing::warn!(
+                remote = %remote,
+                drift_ms = remote.physical - physical_now,
+                max_drift_ms = self.max_drift_ms,
+                "Remote timestamp exceeds maximum clock drift, clamping"
+            );
+            HybridTimestamp::new(ceiling, 0, remote.node_id)
+        } else {
+            remote
+        };
 
         let max_physical = physical_now.max(self.last_timestamp.physical).max(remote.physical);
 
@@ -196,13 +252,25 @@ impl HybridLogicalClock {
     pub fn node_id(&self) -> u64 {
         self.node_id
     }
+
+    /// Maximum accepted drift of remote timestamps ahead of our wall clock
+    pub fn max_drift(&self) -> Duration {
+        Duration::from_millis(self.max_drift_ms)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
     use std::thread;
-    use std::time::Duration;
+
+    /// A wall clock the test can move, including backward
+    fn manual_clock(start: u64) -> (Arc<AtomicU64>, PhysicalClock) {
+        let wall = Arc::new(AtomicU64::new(start));
+        let source = wall.clone();
+        (wall, Arc::new(move || source.load(AtomicOrdering::SeqCst)))
+    }
 
     #[test]
     fn test_timestamp_creation() {
@@ -379,4 +447,69 @@ mod tests {
         // Full causality chain
         assert!(ts1.happens_before(&ts4));
     }
+
+    #[test]
+    fn test_tick_monotonic_after_backward_wall_clock_jump() {
+        let (wall, source) = manual_clock(10_000);
+        let mut clock = HybridLogicalClock::new(1).with_physical_clock(source);
+
+        let before = clock.tick();
+        wall.store(4_000, AtomicOrdering::SeqCst);
+        let after = clock.tick();
+
+        assert!(before.happens_before(&after));
+        assert_eq!(after.physical, 10_000);
+
+        // Once the wall clock catches up, physical time takes over again
+        wall.store(10_001, AtomicOrdering::SeqCst);
+        let caught_up = clock.tick();
+        assert_eq!(caught_up, HybridTimestamp::new(10_001, 0, 1));
+    }
+
+    #[test]
+    fn test_restart_after_backward_wall_clock_jump() {
+        let (_wall, source) = manual_clock(10_000);
+        let mut clock = HybridLogicalClock::new(1).with_physical_clock(source);
+        clock.tick();
+        clock.tick();
+        let last_issued = clock.tick();
+
+        // Restart on a device whose clock is now six seconds behind
+        let (_wall, source) = manual_clock(4_000);
+        let mut restarted = HybridLogicalClock::new(1).with_physical_clock(source);
+        restarted.advance_to(last_issued);
+
+        let next = restarted.tick();
+        assert!(last_issued.happens_before(&next));
+        assert_eq!(next, HybridTimestamp::new(10_000, last_issued.logical + 1, 1));
+    }
+
+    #[test]
+    fn test_advance_to_never_moves_backward() {
+        let (_wall, source) = manual_clock(10_000);
+        let mut clock = HybridLogicalClock::new(1).with_physical_clock(source);
+        let ts = clock.tick();
+
+        clock.advance_to(HybridTimestamp::new(5_000, 7, 2));
+        assert_eq!(clock.peek(), ts);
+    }
+
+    #[test]
+    fn test_update_clamps_remote_drift() {
+        let (_wall, source) = manual_clock(10_000);
+        let mut clock = HybridLogicalClock::new(1)
+            .with_physical_clock(source)
+            .with_max_drift(Duration::from_secs(1));
+
+        // A peer claiming to be an hour ahead only moves us by the max drift
+        let remote = HybridTimestamp::new(10_000 + 3_600_000, 42, 2);
+        let ts = clock.update(remote);
+        assert_eq!(ts, HybridTimestamp::new(11_000, 1, 1));
+
+        // Remote timestamps within the bound are taken as-is
+        let remote = HybridTimestamp::new(10_900, 3, 2);
+        let ts = clock.update(remote);
+        assert_eq!(ts, HybridTimestamp::new(11_000, 2, 1));
+        assert_eq!(clock.max_drift(), Duration::from_secs(1));
+    }
 }
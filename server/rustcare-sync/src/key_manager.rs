@@ -11,10 +11,11 @@
 
 use crate::error::{SyncError, SyncResult};
 use crate::encryption::DatabaseKey;
+use crate::field_encryption::FieldKeys;
 use chrono::Utc;
 use crypto::kms::KeyManagementService;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use zeroize::Zeroizing;
@@ -93,6 +94,37 @@ pub struct LocalDbKeyMetadata {
     pub next_rotation: Option<chrono::DateTime<Utc>>,
 }
 
+/// A field encryption key version, wrapped by the KEK
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldKeyVersion {
+    /// Version recorded in every field encrypted with this key
+    pub version: u32,
+    
+    /// Encrypted Data Encryption Key (wrapped by KEK)
+    pub encrypted_dek: Vec<u8>,
+    
+    /// Creation timestamp
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+/// All field encryption key versions for a database
+///
+/// Old versions are kept so fields written before a rotation stay readable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldKeyRing {
+    /// KMS Key Encryption Key ID
+    pub kek_id: String,
+    
+    /// Encryption context used
+    pub encryption_context: Option<HashMap<String, String>>,
+    
+    /// Version new fields are encrypted with
+    pub current_version: u32,
+    
+    /// Every key version, oldest first
+    pub keys: Vec<FieldKeyVersion>,
+}
+
 /// Local Database Key Manager
 ///
 /// Manages encryption keys for local SQLite databases using KMS.
@@ -126,6 +158,13 @@ impl LocalDbKeyManager {
             })
     }
     
+    /// Get field key ring file path
+    fn field_keys_path(&self) -> PathBuf {
+        let mut path = self.db_path.clone();
+        path.set_extension("fieldkeys");
+        path
+    }
+    
     /// Generate a new DEK using KMS
     ///
     /// This creates a new Data Encryption Key via KMS, which encrypts it with the KEK.
@@ -296,6 +335,116 @@ impl LocalDbKeyManager {
             self.initialize().await
         }
     }
+    
+    /// Load the field key ring from file
+    pub async fn load_field_key_ring(&self) -> SyncResult<FieldKeyRing> {
+        let path = self.field_keys_path();
+        let contents = tokio::fs::read(&path).await
+            .map_err(|e| SyncError::Internal(format!("Failed to read field key ring: {}", e)))?;
+        
+        let ring: FieldKeyRing = serde_json::from_slice(&contents)
+            .map_err(|e| SyncError::Deserialization(format!("Invalid field key ring: {}", e)))?;
+        
+        Ok(ring)
+    }
+    
+    /// Save the field key ring to file
+    async fn save_field_key_ring(&self, ring: &FieldKeyRing) -> SyncResult<()> {
+        let path = self.field_keys_path();
+        let contents = serde_json::to_vec_pretty(ring)
+            .map_err(|e| SyncError::Serialization(format!("Failed to serialize field key ring: {}", e)))?;
+        
+        // Write to a temporary file and rename, so an interrupted rotation
+        // never leaves a truncated key ring behind
+        let mut temp_path = path.clone();
+        temp_path.set_extension("fieldkeys.tmp");
+        tokio::fs::write(&temp_path, &contents).await
+            .map_err(|e| SyncError::Internal(format!("Failed to write field key ring: {}", e)))?;
+        
+        // Set restrictive permissions (Unix only)
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let permissions = std::fs::Permissions::from_mode(0o600);
+            std::fs::set_permissions(&temp_path, permissions)
+                .map_err(|e| SyncError::Internal(format!("Failed to set permissions: {}", e)))?;
+        }
+        
+        tokio::fs::rename(&temp_path, &path).await
+            .map_err(|e| SyncError::Internal(format!("Failed to write field key ring: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    /// Generate a field key version via KMS
+    async fn generate_field_key(&self, version: u32) -> SyncResult<(Zeroizing<Vec<u8>>, FieldKeyVersion)> {
+        let (plaintext_dek, encrypted_dek) = self.kms
+            .generate_data_key(
+                &self.config.kek_id,
+                &self.config.dek_spec,
+                self.config.encryption_context.as_ref(),
+            )
+            .await
+            .map_err(SyncError::Encryption)?;
+        
+        let key = FieldKeyVersion {
+            version,
+            encrypted_dek,
+            created_at: Utc::now(),
+        };
+        
+        Ok((plaintext_dek, key))
+    }
+    
+    /// Get or create the field encryption keys
+    ///
+    /// Decrypts every key version in the ring via KMS. If no ring exists yet,
+    /// one is created with a single version 1 key.
+    pub async fn get_or_create_field_keys(&self) -> SyncResult<FieldKeys> {
+        if !self.field_keys_path().exists() {
+            let (plaintext_dek, key) = self.generate_field_key(1).await?;
+            let ring = FieldKeyRing {
+                kek_id: self.config.kek_id.clone(),
+                encryption_context: self.config.encryption_context.clone(),
+                current_version: 1,
+                keys: vec![key],
+            };
+            self.save_field_key_ring(&ring).await?;
+            
+            let mut keys = BTreeMap::new();
+            keys.insert(1, plaintext_dek);
+            return FieldKeys::new(1, keys);
+        }
+        
+        let ring = self.load_field_key_ring().await?;
+        let mut keys = BTreeMap::new();
+        for key in &ring.keys {
+            let plaintext_dek = self.kms
+                .decrypt_data_key(&key.encrypted_dek, ring.encryption_context.as_ref())
+                .await
+                .map_err(SyncError::Encryption)?;
+            keys.insert(key.version, plaintext_dek);
+        }
+        
+        FieldKeys::new(ring.current_version, keys)
+    }
+    
+    /// Add a new field key version and make it current
+    ///
+    /// Earlier versions stay in the ring so existing fields remain readable
+    /// until they are re-encrypted. Returns the new version and its plaintext
+    /// key.
+    pub async fn rotate_field_key(&self) -> SyncResult<(u32, Zeroizing<Vec<u8>>)> {
+        let mut ring = self.load_field_key_ring().await?;
+        let version = ring.keys.iter().map(|key| key.version).max().unwrap_or(0) + 1;
+        
+        let (plaintext_dek, key) = self.generate_field_key(version).await?;
+        ring.keys.push(key);
+        ring.current_version = version;
+        self.save_field_key_ring(&ring).await?;
+        
+        Ok((version, plaintext_dek))
+    }
 }
 
 #[cfg(test)]
@@ -308,6 +457,11 @@ mod tests {
     use tempfile::TempDir;
     use tokio::sync::Mutex;
     
+    /// Simulated KEK wrapping, its own inverse
+    fn wrap(key: &[u8]) -> Vec<u8> {
+        key.iter().map(|byte| byte ^ 0xA5).collect()
+    }
+    
     // Mock KMS for testing
     struct MockKms {
         generated_keys: Arc<Mutex<Vec<Vec<u8>>>>,
@@ -329,22 +483,23 @@ mod tests {
             _key_spec: &str,
             _context: Option<&HashMap<String, String>>,
         ) -> KmsResult<(Zeroizing<Vec<u8>>, Vec<u8>)> {
-            // Generate random 32-byte key
-            let plaintext = vec![1u8; 32];
-            let encrypted = vec![2u8; 64]; // Simulated encrypted version
+            // Generate a distinct 32-byte key per call
+            let mut generated_keys = self.generated_keys.lock().await;
+            let plaintext = vec![generated_keys.len() as u8 + 1; 32];
+            let encrypted = wrap(&plaintext); // Simulated encrypted version
             
-            self.generated_keys.lock().await.push(plaintext.clone());
+            generated_keys.push(plaintext.clone());
             
             Ok((Zeroizing::new(plaintext), encrypted))
         }
         
         async fn decrypt_data_key(
             &self,
-            _encrypted_dek: &[u8],
+            encrypted_dek: &[u8],
             _context: Option<&HashMap<String, String>>,
         ) -> KmsResult<Zeroizing<Vec<u8>>> {
-            // Return mock plaintext (in real impl, this would decrypt)
-            Ok(Zeroizing::new(vec![1u8; 32]))
+            // Reverse the simulated wrapping
+            Ok(Zeroizing::new(wrap(encrypted_dek)))
         }
         
         // Implement other required trait methods (not used in tests)
@@ -516,4 +671,42 @@ mod tests {
         
         assert_eq!(key1.to_hex(), key2.to_hex());
     }
+    
+    #[tokio::test]
+    async fn test_rotate_field_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        
+        let kms = Arc::new(MockKms::new());
+        let manager = LocalDbKeyManager::new(kms, KeyManagerConfig::default(), &db_path);
+        let config = crate::field_encryption::FieldEncryptionConfig {
+            enabled: true,
+            phi_fields: vec!["ssn".to_string()],
+        };
+        
+        let mut encryption = crate::field_encryption::FieldEncryption::from_key_manager(config.clone(), &manager)
+            .await
+            .unwrap();
+        assert_eq!(encryption.current_key_version(), 1);
+        
+        let original = serde_json::json!({"ssn": "123-45-6789"});
+        let old_record = encryption.encrypt_phi_fields(&original).unwrap();
+        
+        assert_eq!(encryption.rotate_field_key(&manager).await.unwrap(), 2);
+        let new_record = encryption.encrypt_phi_fields(&original).unwrap();
+        assert_ne!(old_record, new_record);
+        assert_eq!(encryption.decrypt_phi_fields(&old_record).unwrap(), original);
+        
+        // Both versions are kept in the ring and reload after a restart
+        let ring = manager.load_field_key_ring().await.unwrap();
+        assert_eq!(ring.current_version, 2);
+        assert_eq!(ring.keys.len(), 2);
+        
+        let reloaded = crate::field_encryption::FieldEncryption::from_key_manager(config, &manager)
+            .await
+            .unwrap();
+        assert_eq!(reloaded.current_key_version(), 2);
+        assert_eq!(reloaded.decrypt_phi_fields(&old_record).unwrap(), original);
+        assert_eq!(reloaded.decrypt_phi_fields(&new_record).unwrap(), original);
+    }
 }
@@ -30,10 +30,10 @@ pub use crdt::{Crdt, LwwRegister, GCounter, PnCounter, OrSet, Rga};
 pub use sync_protocol::{SyncProtocol, SyncConfig, SyncStats};
 pub use p2p::{P2PSync, P2PConfig, PeerInfo, PeerStatus};
 pub use encryption::{EncryptionConfig, EncryptionKeyManager, DatabaseKey, EncryptionMetadata};
-pub use field_encryption::{FieldEncryption, FieldEncryptionConfig};
-pub use audit::{AuditLogger, AuditConfig, AuditAction, AuditEntry};
-pub use rate_limiter::{RateLimiter, RateLimiterConfig};
-pub use key_manager::{LocalDbKeyManager, KeyManagerConfig, LocalDbKeyMetadata};
+pub use field_encryption::{FieldEncryption, FieldEncryptionConfig, FieldKeys};
+pub use audit::{AuditLogger, AuditConfig, AuditAction, AuditEntry, ChainHead, ChainVerification, BrokenLink, ChainBreak};
+pub use rate_limiter::{RateLimiter, RateLimiterConfig, BucketConfig, BucketLevel};
+pub use key_manager::{LocalDbKeyManager, KeyManagerConfig, LocalDbKeyMetadata, FieldKeyRing, FieldKeyVersion};
 pub use secure_memory::{
     SecureString, SecureVec, SecureData, SecurePatientData, SecureMedicalRecord,
     IntoSecure, IntoSecureVec,
@@ -91,6 +91,7 @@ mod tests {
             user_email: None,
             rate_limiter_config: None,
             kms_config: None,
+            max_clock_drift_ms: crate::hlc::DEFAULT_MAX_DRIFT_MS,
         };
         
         let engine = SyncEngine::new(config).await.unwrap();
@@ -7,13 +7,24 @@
 //! - Conflict detection and resolution
 
 use crate::error::{SyncError, SyncResult};
-use crate::audit::{AuditLogger, AuditConfig, AuditAction};
+use crate::audit::{AuditLogger, AuditConfig, AuditAction, ChainHead};
+use crate::field_encryption::FieldEncryption;
+use crate::hlc::{HybridLogicalClock, HybridTimestamp};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{sqlite::SqlitePool, Row};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::str::FromStr;
 use uuid::Uuid;
 use tokio::sync::Mutex;
 
+/// `sync_metadata` key holding the last HLC timestamp issued by this node
+const HLC_LAST_ISSUED_KEY: &str = "hlc_last_issued";
+
+/// `sync_metadata` key holding the progress of a field re-encryption pass,
+/// as `<key version>:<last operation id>`
+const FIELD_REENCRYPT_CURSOR_KEY: &str = "field_reencrypt_cursor";
+
 /// Configuration for local database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalDbConfig {
@@ -40,6 +51,14 @@ pub struct LocalDbConfig {
     /// When configured, uses KMS (AWS KMS, Vault, etc.) to manage encryption keys
     /// instead of password-based key derivation
     pub kms_config: Option<crate::key_manager::KeyManagerConfig>,
+    /// Maximum drift (milliseconds) a remote HLC timestamp may be ahead of
+    /// the local wall clock before it is clamped
+    #[serde(default = "default_max_clock_drift_ms")]
+    pub max_clock_drift_ms: u64,
+}
+
+fn default_max_clock_drift_ms() -> u64 {
+    crate::hlc::DEFAULT_MAX_DRIFT_MS
 }
 
 impl Default for LocalDbConfig {
@@ -55,6 +74,7 @@ impl Default for LocalDbConfig {
             user_email: None,
             rate_limiter_config: Some(crate::rate_limiter::RateLimiterConfig::default()),
             kms_config: None, // KMS is optional, defaults to password-based key derivation
+            max_clock_drift_ms: default_max_clock_drift_ms(),
         }
     }
 }
@@ -129,6 +149,7 @@ pub struct LocalDatabase {
     user_id: Option<String>,
     user_email: Option<String>,
     rate_limiter: Option<crate::rate_limiter::RateLimiter>,
+    clock: Mutex<HybridLogicalClock>,
 }
 
 impl LocalDatabase {
@@ -137,26 +158,26 @@ impl LocalDatabase {
         // Create database file if it doesn't exist
         let db_url = format!("sqlite:{}", config.db_path);
         
-        // Create connection pool
-        let pool = SqlitePool::connect(&db_url).await?;
+        // Pragmas are per connection, so set them on every pooled connection
+        // rather than on whichever one runs a PRAGMA statement
+        let mut options = SqliteConnectOptions::from_str(&db_url)?
+            // Enable foreign keys
+            .foreign_keys(true);
         
         // Enable WAL mode for better concurrency
         if config.enable_wal {
-            sqlx::query("PRAGMA journal_mode = WAL")
-                .execute(&pool)
-                .await?;
+            options = options.journal_mode(SqliteJournalMode::Wal);
         }
         
         // Enable secure deletion to overwrite freed pages (HIPAA requirement)
         if config.enable_secure_delete {
-            sqlx::query("PRAGMA secure_delete = ON")
-                .execute(&pool)
-                .await?;
+            options = options.pragma("secure_delete", "ON");
         }
         
-        // Enable foreign keys
-        sqlx::query("PRAGMA foreign_keys = ON")
-            .execute(&pool)
+        // Create connection pool
+        let pool = SqlitePoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect_with(options)
             .await?;
         
         // Initialize audit logger if configured
@@ -172,18 +193,30 @@ impl LocalDatabase {
         let user_id = config.user_id.clone();
         let user_email = config.user_email.clone();
         
-        let db = Self {
+        // Convert UUID to u64 for clock (use first 8 bytes)
+        let clock_node_id = u64::from_le_bytes(config.node_id.as_bytes()[..8].try_into().unwrap());
+        let clock = HybridLogicalClock::new(clock_node_id)
+            .with_max_drift(std::time::Duration::from_millis(config.max_clock_drift_ms));
+        
+        let mut db = Self {
             pool,
             node_id: config.node_id,
             audit_logger,
             user_id,
             user_email,
             rate_limiter,
+            clock: Mutex::new(clock),
         };
         
         // Initialize schema
         db.initialize_schema().await?;
         
+        // Never issue timestamps below those written before a restart, even
+        // if the wall clock has since moved backward
+        if let Some(last_issued) = db.last_issued_timestamp().await? {
+            db.clock.get_mut().advance_to(last_issued);
+        }
+        
         // Log database open event
         db.audit_log(
             AuditAction::DatabaseOpen,
@@ -365,6 +398,8 @@ impl LocalDatabase {
         data: serde_json::Value,
         vector_clock: &str,
     ) -> SyncResult<Uuid> {
+        self.acquire_rate_limit(crate::rate_limiter::LOCAL_WRITE_BUCKET).await?;
+        
         let operation_id = Uuid::new_v4();
         let now = Utc::now();
         
@@ -549,6 +584,217 @@ impl LocalDatabase {
         self.get_vector_clock_counter().await
     }
     
+    /// Wait for a token from a named rate limit bucket
+    ///
+    /// Waits at most the configured `max_wait`. A no-op when rate limiting is
+    /// not configured.
+    pub async fn acquire_rate_limit(&self, bucket: &str) -> SyncResult<()> {
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            rate_limiter.acquire_default(bucket).await?;
+        }
+        Ok(())
+    }
+    
+    /// Head of the local audit hash chain, if auditing is enabled and
+    /// anything has been logged
+    pub async fn audit_chain_head(&self) -> Option<ChainHead> {
+        match self.audit_logger {
+            Some(ref logger) => logger.lock().await.chain_head(),
+            None => None,
+        }
+    }
+    
+    /// Get the rate limiter, if configured
+    pub fn rate_limiter(&self) -> Option<&crate::rate_limiter::RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+    
+    /// Issue an HLC timestamp for a local event
+    ///
+    /// The timestamp is persisted before it is returned, so it survives a
+    /// restart as the floor for the clock.
+    pub async fn next_timestamp(&self) -> SyncResult<HybridTimestamp> {
+        let mut clock = self.clock.lock().await;
+        let timestamp = clock.tick();
+        self.persist_hlc_timestamp(timestamp).await?;
+        Ok(timestamp)
+    }
+    
+    /// Merge a timestamp received from a peer into the local clock
+    ///
+    /// Returns the timestamp to associate with the received event.
+    pub async fn receive_timestamp(&self, remote: HybridTimestamp) -> SyncResult<HybridTimestamp> {
+        let mut clock = self.clock.lock().await;
+        let timestamp = clock.update(remote);
+        self.persist_hlc_timestamp(timestamp).await?;
+        Ok(timestamp)
+    }
+    
+    /// Last HLC timestamp issued by this database, if any
+    pub async fn last_issued_timestamp(&self) -> SyncResult<Option<HybridTimestamp>> {
+        let row = sqlx::query("SELECT value FROM sync_metadata WHERE key = ?")
+            .bind(HLC_LAST_ISSUED_KEY)
+            .fetch_optional(&self.pool)
+            .await?;
+        
+        match row {
+            Some(row) => {
+                let value: String = row.try_get("value")?;
+                let timestamp = HybridTimestamp::from_string(&value)
+                    .map_err(SyncError::Deserialization)?;
+                Ok(Some(timestamp))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    async fn persist_hlc_timestamp(&self, timestamp: HybridTimestamp) -> SyncResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sync_metadata (key, value, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET
+                value = excluded.value,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(HLC_LAST_ISSUED_KEY)
+        .bind(timestamp.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Re-encrypt PHI fields of queued operations with the current field key
+    ///
+    /// Used after `FieldEncryption::rotate_field_key` to move every record off
+    /// older keys. Operations are rewritten in batches of `batch_size`, each
+    /// committed together with a cursor, so an interrupted pass resumes after
+    /// the last committed batch. Returns the number of operations rewritten.
+    pub async fn reencrypt_field_data(
+        &self,
+        encryption: &FieldEncryption,
+        batch_size: i64,
+    ) -> SyncResult<usize> {
+        let mut reencrypted = 0;
+        loop {
+            let (rewritten, done) = self.reencrypt_field_batch(encryption, batch_size).await?;
+            reencrypted += rewritten;
+            if done {
+                break;
+            }
+        }
+        
+        self.audit_log(
+            AuditAction::Update,
+            "sync_queue".to_string(),
+            true,
+            true,
+            serde_json::json!({
+                "field_key_version": encryption.current_key_version(),
+                "reencrypted": reencrypted,
+            }),
+        ).await?;
+        
+        Ok(reencrypted)
+    }
+    
+    /// Re-encrypt the next batch, returning how many operations were
+    /// rewritten and whether the pass is complete
+    async fn reencrypt_field_batch(
+        &self,
+        encryption: &FieldEncryption,
+        batch_size: i64,
+    ) -> SyncResult<(usize, bool)> {
+        let version = encryption.current_key_version().to_string();
+        
+        let cursor = sqlx::query("SELECT value FROM sync_metadata WHERE key = ?")
+            .bind(FIELD_REENCRYPT_CURSOR_KEY)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.try_get::<String, _>("value"))
+            .transpose()?;
+        
+        // A cursor left by a pass for an earlier key version is stale, so
+        // start that pass over from the beginning
+        let after = cursor
+            .as_deref()
+            .and_then(|cursor| cursor.split_once(':'))
+            .filter(|(cursor_version, _)| *cursor_version == version)
+            .map(|(_, last_id)| last_id.to_string())
+            .unwrap_or_default();
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT id, data FROM sync_queue
+            WHERE id > ?
+            ORDER BY id ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(&after)
+        .bind(batch_size)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut tx = self.pool.begin().await?;
+        let mut rewritten = 0;
+        for row in &rows {
+            let id: String = row.try_get("id")?;
+            let data: serde_json::Value = serde_json::from_str(&row.try_get::<String, _>("data")?)?;
+            if !encryption.needs_reencryption(&data) {
+                continue;
+            }
+            
+            let reencrypted = encryption.reencrypt_phi_fields(&data)?;
+            sqlx::query("UPDATE sync_queue SET data = ? WHERE id = ?")
+                .bind(reencrypted.to_string())
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+            rewritten += 1;
+        }
+        
+        let done = (rows.len() as i64) < batch_size;
+        match rows.last() {
+            Some(last) if !done => {
+                let last_id: String = last.try_get("id")?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO sync_metadata (key, value, updated_at)
+                    VALUES (?, ?, ?)
+                    ON CONFLICT(key) DO UPDATE SET
+                        value = excluded.value,
+                        updated_at = excluded.updated_at
+                    "#,
+                )
+                .bind(FIELD_REENCRYPT_CURSOR_KEY)
+                .bind(format!("{}:{}", version, last_id))
+                .bind(Utc::now().to_rfc3339())
+                .execute(&mut *tx)
+                .await?;
+            }
+            _ => {
+                sqlx::query("DELETE FROM sync_metadata WHERE key = ?")
+                    .bind(FIELD_REENCRYPT_CURSOR_KEY)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+        
+        tracing::debug!(
+            field_key_version = %version,
+            scanned = rows.len(),
+            rewritten,
+            "Re-encrypted batch of queued operations"
+        );
+        
+        Ok((rewritten, done))
+    }
+    
     /// Get node ID
     pub fn node_id(&self) -> Uuid {
         self.node_id
@@ -794,7 +1040,8 @@ mod tests {
     use super::*;
     use tempfile::NamedTempFile;
     
-    async fn create_test_db() -> SyncResult<LocalDatabase> {
+    /// The database file is deleted when the returned `NamedTempFile` drops
+    async fn create_test_db() -> SyncResult<(LocalDatabase, NamedTempFile)> {
         let temp_file = NamedTempFile::new().unwrap();
         let db_path = temp_file.path().to_str().unwrap().to_string();
         
@@ -809,20 +1056,21 @@ mod tests {
             user_email: Some("test@example.com".to_string()),
             rate_limiter_config: None, // Disable rate limiting for most tests
             kms_config: None, // Use password-based key derivation for tests
+            max_clock_drift_ms: crate::hlc::DEFAULT_MAX_DRIFT_MS,
         };
         
-        LocalDatabase::new(config).await
+        Ok((LocalDatabase::new(config).await?, temp_file))
     }
     
     #[tokio::test]
     async fn test_database_creation() {
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         assert_eq!(db.get_vector_clock_counter().await.unwrap(), 0);
     }
     
     #[tokio::test]
     async fn test_queue_operation() {
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         
         let data = serde_json::json!({
             "name": "John Doe",
@@ -848,7 +1096,7 @@ mod tests {
     
     #[tokio::test]
     async fn test_mark_synced() {
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         
         let op_id = db.queue_operation(
             "patient",
@@ -866,7 +1114,7 @@ mod tests {
     
     #[tokio::test]
     async fn test_vector_clock() {
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         
         assert_eq!(db.get_vector_clock_counter().await.unwrap(), 0);
         
@@ -879,7 +1127,7 @@ mod tests {
     
     #[tokio::test]
     async fn test_mark_failed() {
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         
         let op_id = db.queue_operation(
             "appointment",
@@ -900,7 +1148,7 @@ mod tests {
     #[tokio::test]
     async fn test_secure_delete_enabled() {
         // Test that secure_delete pragma is properly set
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         
         // Query the secure_delete pragma
         let row = sqlx::query("PRAGMA secure_delete")
@@ -914,7 +1162,7 @@ mod tests {
     
     #[tokio::test]
     async fn test_vacuum_operation() {
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         
         // Add some operations
         for i in 0..10 {
@@ -954,6 +1202,7 @@ mod tests {
             user_email: None,
             rate_limiter_config: None,
             kms_config: None, // Use password-based key derivation for tests
+            max_clock_drift_ms: crate::hlc::DEFAULT_MAX_DRIFT_MS,
         };
         
         let db = LocalDatabase::new(config).await.unwrap();
@@ -972,7 +1221,7 @@ mod tests {
     async fn test_store_and_get_unresolved_conflict() {
         use crate::conflict_resolution::{ConflictResolver, ConflictType};
         
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         let resolver = ConflictResolver::new();
         
         let entity_id = Uuid::new_v4();
@@ -1002,7 +1251,7 @@ mod tests {
     async fn test_resolve_conflict() {
         use crate::conflict_resolution::{ConflictResolver, ConflictType};
         
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         let resolver = ConflictResolver::new();
         
         let entity_id = Uuid::new_v4();
@@ -1041,7 +1290,7 @@ mod tests {
     async fn test_get_conflicts_assigned_to() {
         use crate::conflict_resolution::{ConflictResolver, ConflictType};
         
-        let db = create_test_db().await.unwrap();
+        let (db, _db_file) = create_test_db().await.unwrap();
         let resolver = ConflictResolver::new();
         
         // Create conflicts assigned to different users
@@ -1080,4 +1329,107 @@ mod tests {
         assert_eq!(user2_conflicts.len(), 1);
         assert_eq!(user2_conflicts[0].id, conflict2.id);
     }
+    
+    #[tokio::test]
+    async fn test_hlc_survives_restart_after_backward_clock_jump() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let config = LocalDbConfig {
+            db_path: temp_file.path().to_str().unwrap().to_string(),
+            audit_config: None,
+            rate_limiter_config: None,
+            ..Default::default()
+        };
+        
+        let db = LocalDatabase::new(config.clone()).await.unwrap();
+        let first = db.next_timestamp().await.unwrap();
+        assert_eq!(db.last_issued_timestamp().await.unwrap(), Some(first));
+        
+        // Before the restart the wall clock ran an hour ahead of where it is now
+        let pre_restart = HybridTimestamp::new(first.physical + 3_600_000, 5, first.node_id);
+        db.persist_hlc_timestamp(pre_restart).await.unwrap();
+        db.close().await.unwrap();
+        
+        let db = LocalDatabase::new(config).await.unwrap();
+        let next = db.next_timestamp().await.unwrap();
+        assert!(pre_restart.happens_before(&next));
+        assert_eq!(db.last_issued_timestamp().await.unwrap(), Some(next));
+    }
+    
+    #[tokio::test]
+    async fn test_receive_timestamp_clamps_drift() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let config = LocalDbConfig {
+            db_path: temp_file.path().to_str().unwrap().to_string(),
+            audit_config: None,
+            rate_limiter_config: None,
+            max_clock_drift_ms: 1_000,
+            ..Default::default()
+        };
+        let db = LocalDatabase::new(config).await.unwrap();
+        
+        let now = HybridTimestamp::now(0).physical;
+        let remote = HybridTimestamp::new(now + 3_600_000, 0, 42);
+        let received = db.receive_timestamp(remote).await.unwrap();
+        
+        assert!(received.physical <= HybridTimestamp::now(0).physical + 1_000);
+        assert!(received < remote);
+        assert_eq!(db.last_issued_timestamp().await.unwrap(), Some(received));
+    }
+    
+    #[tokio::test]
+    async fn test_reencrypt_field_data_resumes() {
+        use crate::field_encryption::{FieldEncryptionConfig, FieldKeys};
+        use std::collections::BTreeMap;
+        use zeroize::Zeroizing;
+        
+        let config = FieldEncryptionConfig {
+            enabled: true,
+            phi_fields: vec!["ssn".to_string()],
+        };
+        let keys = |current: u32| {
+            let keys: BTreeMap<_, _> = (1..=current)
+                .map(|version| (version, Zeroizing::new(vec![version as u8; 32])))
+                .collect();
+            FieldKeys::new(current, keys).unwrap()
+        };
+        let before = FieldEncryption::with_keys(config.clone(), keys(1)).unwrap();
+        let after = FieldEncryption::with_keys(config, keys(2)).unwrap();
+        
+        let (db, _db_file) = create_test_db().await.unwrap();
+        let mut originals = Vec::new();
+        for i in 0..5 {
+            let original = serde_json::json!({"ssn": format!("000-00-000{}", i)});
+            let encrypted = before.encrypt_phi_fields(&original).unwrap();
+            db.queue_operation("patient", Uuid::new_v4(), OperationType::Create, encrypted, "n1:1")
+                .await
+                .unwrap();
+            originals.push(original);
+        }
+        
+        // Interrupted after the first batch
+        let (rewritten, done) = db.reencrypt_field_batch(&after, 2).await.unwrap();
+        assert_eq!((rewritten, done), (2, false));
+        
+        // Resuming only rewrites what the first batch did not reach
+        assert_eq!(db.reencrypt_field_data(&after, 2).await.unwrap(), 3);
+        assert_eq!(db.reencrypt_field_data(&after, 2).await.unwrap(), 0);
+        
+        let pending = db.get_pending_operations(10).await.unwrap();
+        let mut decrypted: Vec<_> = pending
+            .iter()
+            .map(|entry| {
+                assert!(!after.needs_reencryption(&entry.data));
+                after.decrypt_phi_fields(&entry.data).unwrap()
+            })
+            .collect();
+        decrypted.sort_by_key(|value| value.to_string());
+        assert_eq!(decrypted, originals);
+        
+        let cursor = sqlx::query("SELECT value FROM sync_metadata WHERE key = ?")
+            .bind(FIELD_REENCRYPT_CURSOR_KEY)
+            .fetch_optional(db.pool())
+            .await
+            .unwrap();
+        assert!(cursor.is_none());
+    }
 }
@@ -304,6 +304,7 @@ mod tests {
             user_email: None,
             rate_limiter_config: None,
             kms_config: None,
+            max_clock_drift_ms: crate::hlc::DEFAULT_MAX_DRIFT_MS,
         };
         
         Arc::new(LocalDatabase::new(config).await.unwrap())
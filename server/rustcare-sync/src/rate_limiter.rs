@@ -2,15 +2,26 @@
 //! 
 //! Implements token bucket algorithm to prevent abuse and flooding.
 //! Each user gets a bucket of tokens that refills over time.
+//!
+//! Independently of the per-user buckets, named buckets throttle classes of
+//! work such as sync uploads and local writes, each with its own rate and
+//! burst capacity. Acquiring from a named bucket waits for a token instead
+//! of failing outright, up to a deadline.
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
 use crate::error::{SyncError, SyncResult};
 
+/// Named bucket for uploads to the sync server
+pub const SYNC_UPLOAD_BUCKET: &str = "sync_upload";
+
+/// Named bucket for writes to the local database
+pub const LOCAL_WRITE_BUCKET: &str = "local_write";
+
 /// Rate limiter configuration
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RateLimiterConfig {
@@ -21,6 +32,36 @@ pub struct RateLimiterConfig {
     pub window_duration: Duration,
     /// Whether rate limiting is enabled
     pub enabled: bool,
+    /// Named token buckets, acquired with [`RateLimiter::acquire`]
+    #[serde(default)]
+    pub buckets: HashMap<String, BucketConfig>,
+    /// How long internal callers wait for a named bucket before failing
+    /// (in seconds, for serialization)
+    #[serde(default = "default_max_wait", with = "duration_secs")]
+    pub max_wait: Duration,
+}
+
+/// Token bucket parameters for a named bucket
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BucketConfig {
+    /// Tokens added per second
+    pub rate_per_second: f64,
+    /// Maximum tokens, i.e. the largest burst allowed after the bucket idles
+    pub burst: u32,
+}
+
+/// Current fill of a named bucket
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct BucketLevel {
+    /// Tokens available now; negative when waiters have reserved tokens that
+    /// have not refilled yet
+    pub available: f64,
+    /// Maximum tokens
+    pub capacity: f64,
+}
+
+fn default_max_wait() -> Duration {
+    Duration::from_secs(5)
 }
 
 // Serialize Duration as seconds
@@ -50,6 +91,17 @@ impl Default for RateLimiterConfig {
             max_operations: 10,
             window_duration: Duration::from_secs(1),
             enabled: true,
+            buckets: HashMap::from([
+                (
+                    SYNC_UPLOAD_BUCKET.to_string(),
+                    BucketConfig { rate_per_second: 2.0, burst: 10 },
+                ),
+                (
+                    LOCAL_WRITE_BUCKET.to_string(),
+                    BucketConfig { rate_per_second: 50.0, burst: 100 },
+                ),
+            ]),
+            max_wait: default_max_wait(),
         }
     }
 }
@@ -99,11 +151,44 @@ impl TokenBucket {
         }
     }
 
+    /// Reserve a token, returning how long to wait until it has refilled
+    ///
+    /// Tokens may go negative so that concurrent waiters queue up behind one
+    /// another. If the token would not be ready within `max_wait`, nothing is
+    /// reserved and the required wait is returned as the error.
+    fn reserve(&mut self, max_wait: Duration) -> Result<Duration, Duration> {
+        self.refill();
+        
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(Duration::ZERO);
+        }
+        
+        // A bucket that never refills cannot satisfy any wait
+        let wait = Duration::try_from_secs_f64((1.0 - self.tokens) / self.refill_rate)
+            .unwrap_or(Duration::MAX);
+        if wait > max_wait {
+            return Err(wait);
+        }
+        
+        self.tokens -= 1.0;
+        Ok(wait)
+    }
+
     /// Get remaining tokens
     fn available_tokens(&mut self) -> u32 {
         self.refill();
         self.tokens.floor() as u32
     }
+
+    /// Current fill of the bucket
+    fn level(&mut self) -> BucketLevel {
+        self.refill();
+        BucketLevel {
+            available: self.tokens,
+            capacity: self.capacity,
+        }
+    }
 }
 
 /// Rate limiter for sync operations
@@ -112,15 +197,75 @@ pub struct RateLimiter {
     config: RateLimiterConfig,
     /// Token buckets per user
     buckets: Arc<RwLock<HashMap<Uuid, TokenBucket>>>,
+    /// Named token buckets
+    named_buckets: HashMap<String, Mutex<TokenBucket>>,
 }
 
 impl RateLimiter {
     /// Create a new rate limiter
     pub fn new(config: RateLimiterConfig) -> Self {
+        let named_buckets = config
+            .buckets
+            .iter()
+            .map(|(name, bucket)| {
+                let bucket = TokenBucket::new(bucket.burst, bucket.rate_per_second);
+                (name.clone(), Mutex::new(bucket))
+            })
+            .collect();
+        
         Self {
             config,
             buckets: Arc::new(RwLock::new(HashMap::new())),
+            named_buckets,
+        }
+    }
+
+    /// Take a token from the named bucket, waiting for one to refill
+    ///
+    /// Waiters are served in the order they arrive. Fails immediately with
+    /// `SyncError::BucketRateLimited` if a token could not be ready by
+    /// `deadline`, and with `SyncError::InvalidOperation` if no bucket named
+    /// `bucket` is configured.
+    pub async fn acquire(&self, bucket: &str, deadline: Instant) -> SyncResult<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let named_bucket = self.named_buckets.get(bucket).ok_or_else(|| {
+            SyncError::InvalidOperation(format!("Unknown rate limit bucket: {}", bucket))
+        })?;
+
+        let max_wait = deadline.saturating_duration_since(Instant::now());
+        let wait = named_bucket
+            .lock()
+            .await
+            .reserve(max_wait)
+            .map_err(|retry_after| SyncError::BucketRateLimited {
+                bucket: bucket.to_string(),
+                retry_after,
+            })?;
+
+        if !wait.is_zero() {
+            tracing::debug!(bucket = bucket, wait_ms = wait.as_millis() as u64, "Waiting for rate limit token");
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(())
+    }
+
+    /// Take a token from the named bucket, waiting at most the configured
+    /// `max_wait`
+    pub async fn acquire_default(&self, bucket: &str) -> SyncResult<()> {
+        self.acquire(bucket, Instant::now() + self.config.max_wait).await
+    }
+
+    /// Current token level of every named bucket, for telemetry
+    pub async fn token_levels(&self) -> HashMap<String, BucketLevel> {
+        let mut levels = HashMap::with_capacity(self.named_buckets.len());
+        for (name, bucket) in &self.named_buckets {
+            levels.insert(name.clone(), bucket.lock().await.level());
         }
+        levels
     }
 
     /// Check if an operation is allowed for the user
@@ -189,6 +334,7 @@ mod tests {
             max_operations: 5,
             window_duration: Duration::from_secs(1),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
         let user_id = Uuid::new_v4();
@@ -206,6 +352,7 @@ mod tests {
             max_operations: 3,
             window_duration: Duration::from_secs(1),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
         let user_id = Uuid::new_v4();
@@ -233,6 +380,7 @@ mod tests {
             max_operations: 2,
             window_duration: Duration::from_millis(500),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
         let user_id = Uuid::new_v4();
@@ -257,6 +405,7 @@ mod tests {
             max_operations: 1,
             window_duration: Duration::from_secs(1),
             enabled: false,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
         let user_id = Uuid::new_v4();
@@ -273,6 +422,7 @@ mod tests {
             max_operations: 2,
             window_duration: Duration::from_secs(1),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
         let user1 = Uuid::new_v4();
@@ -294,6 +444,7 @@ mod tests {
             max_operations: 5,
             window_duration: Duration::from_secs(1),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
         let user_id = Uuid::new_v4();
@@ -315,6 +466,7 @@ mod tests {
             max_operations: 2,
             window_duration: Duration::from_secs(1),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
         let user_id = Uuid::new_v4();
@@ -330,4 +482,128 @@ mod tests {
         // Should have tokens again
         assert!(limiter.check_rate_limit(user_id).await.is_ok());
     }
+
+    fn bucket_config(buckets: &[(&str, f64, u32)]) -> RateLimiterConfig {
+        RateLimiterConfig {
+            buckets: buckets
+                .iter()
+                .map(|(name, rate_per_second, burst)| {
+                    let bucket = BucketConfig { rate_per_second: *rate_per_second, burst: *burst };
+                    (name.to_string(), bucket)
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_named_buckets_are_independent() {
+        let limiter = RateLimiter::new(bucket_config(&[
+            (SYNC_UPLOAD_BUCKET, 1.0, 2),
+            (LOCAL_WRITE_BUCKET, 1.0, 5),
+        ]));
+        let now = Instant::now();
+
+        // Uploads may burst up to their capacity, then run dry
+        limiter.acquire(SYNC_UPLOAD_BUCKET, now).await.unwrap();
+        limiter.acquire(SYNC_UPLOAD_BUCKET, now).await.unwrap();
+        assert!(matches!(
+            limiter.acquire(SYNC_UPLOAD_BUCKET, now).await,
+            Err(SyncError::BucketRateLimited { .. })
+        ));
+
+        // Local writes are unaffected
+        for _ in 0..5 {
+            limiter.acquire(LOCAL_WRITE_BUCKET, now).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_token() {
+        let limiter = RateLimiter::new(bucket_config(&[(SYNC_UPLOAD_BUCKET, 20.0, 1)]));
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        limiter.acquire(SYNC_UPLOAD_BUCKET, deadline).await.unwrap();
+
+        // The next token refills after 50ms
+        let started = Instant::now();
+        limiter.acquire(SYNC_UPLOAD_BUCKET, deadline).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_acquire_fails_past_deadline() {
+        let limiter = RateLimiter::new(bucket_config(&[(SYNC_UPLOAD_BUCKET, 1.0, 1)]));
+        limiter.acquire(SYNC_UPLOAD_BUCKET, Instant::now()).await.unwrap();
+
+        // A token is a second away, so a 50ms deadline fails without waiting
+        let started = Instant::now();
+        let result = limiter
+            .acquire(SYNC_UPLOAD_BUCKET, Instant::now() + Duration::from_millis(50))
+            .await;
+        assert!(started.elapsed() < Duration::from_millis(50));
+        match result {
+            Err(SyncError::BucketRateLimited { bucket, retry_after }) => {
+                assert_eq!(bucket, SYNC_UPLOAD_BUCKET);
+                assert!(retry_after > Duration::from_millis(900));
+            }
+            other => panic!("Expected BucketRateLimited, got {:?}", other),
+        }
+
+        // The failed attempt did not reserve a token
+        let level = limiter.token_levels().await[SYNC_UPLOAD_BUCKET];
+        assert!(level.available >= 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_waiters_queue() {
+        let limiter = Arc::new(RateLimiter::new(bucket_config(&[(SYNC_UPLOAD_BUCKET, 20.0, 1)])));
+        let deadline = Instant::now() + Duration::from_secs(1);
+        limiter.acquire(SYNC_UPLOAD_BUCKET, deadline).await.unwrap();
+
+        // Two waiters reserve consecutive tokens, 50ms apart
+        let started = Instant::now();
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire(SYNC_UPLOAD_BUCKET, deadline).await })
+            })
+            .collect();
+        for waiter in waiters {
+            waiter.await.unwrap().unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn test_token_levels() {
+        let limiter = RateLimiter::new(bucket_config(&[
+            (SYNC_UPLOAD_BUCKET, 0.001, 10),
+            (LOCAL_WRITE_BUCKET, 0.001, 4),
+        ]));
+        limiter.acquire(SYNC_UPLOAD_BUCKET, Instant::now()).await.unwrap();
+        limiter.acquire(SYNC_UPLOAD_BUCKET, Instant::now()).await.unwrap();
+
+        let levels = limiter.token_levels().await;
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[SYNC_UPLOAD_BUCKET].capacity, 10.0);
+        assert_eq!(levels[SYNC_UPLOAD_BUCKET].available.floor(), 8.0);
+        assert_eq!(levels[LOCAL_WRITE_BUCKET].available.floor(), 4.0);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_unknown_bucket() {
+        let limiter = RateLimiter::new(bucket_config(&[]));
+        assert!(matches!(
+            limiter.acquire("bulk_export", Instant::now()).await,
+            Err(SyncError::InvalidOperation(_))
+        ));
+
+        // Disabled limiters never wait
+        let limiter = RateLimiter::new(RateLimiterConfig {
+            enabled: false,
+            ..bucket_config(&[(SYNC_UPLOAD_BUCKET, 0.001, 0)])
+        });
+        limiter.acquire(SYNC_UPLOAD_BUCKET, Instant::now()).await.unwrap();
+    }
 }
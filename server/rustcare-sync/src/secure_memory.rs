@@ -11,14 +11,217 @@
 use secrecy::{CloneableSecret, ExposeSecret, Secret, Zeroize};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use zeroize::ZeroizeOnDrop;
+
+/// Placeholder printed instead of secret contents
+const REDACTED: &str = "[REDACTED]";
 
 /// A secure string that zeroizes on drop
 /// Use for: SSN, passwords, patient names, medical record numbers, etc.
-pub type SecureString = Secret<String>;
+///
+/// `Debug` and `Display` print `[REDACTED]`, `==` is constant-time, and there
+/// is no `Serialize` impl; fields that must be serialized opt in through
+/// [`serde_exposed`].
+#[derive(Clone)]
+pub struct SecureString(String);
+
+impl SecureString {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+    
+    /// Expose the secret string
+    /// 
+    /// SECURITY WARNING: Do not copy the returned value into a non-secure
+    /// buffer; prefer [`with_exposed`](Self::with_exposed).
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+    
+    /// Run `f` over the secret bytes without copying them out
+    pub fn with_exposed<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        f(self.0.as_bytes())
+    }
+    
+    /// Constant-time comparison
+    /// 
+    /// Neither the contents nor whether the lengths match affect how long
+    /// the comparison takes; only the longer length does.
+    pub fn ct_eq(&self, other: &Self) -> bool {
+        crypto::ct_eq_len_hiding(self.0.as_bytes(), other.0.as_bytes())
+    }
+}
+
+impl PartialEq for SecureString {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
+
+impl Eq for SecureString {}
+
+impl Zeroize for SecureString {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecureString {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecureString {}
+
+impl fmt::Debug for SecureString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecureString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecureString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self::new(String::deserialize(deserializer)?))
+    }
+}
 
 /// A secure byte vector that zeroizes on drop
 /// Use for: Encryption keys, biometric data, raw PHI buffers
-pub type SecureVec = Secret<Vec<u8>>;
+///
+/// Redacted and constant-time like [`SecureString`].
+#[derive(Clone)]
+pub struct SecureVec(Vec<u8>);
+
+impl SecureVec {
+    pub fn new(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+    
+    /// Expose the secret bytes
+    /// 
+    /// SECURITY WARNING: Do not copy the returned value into a non-secure
+    /// buffer; prefer [`with_exposed`](Self::with_exposed).
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+    
+    /// Run `f` over the secret bytes without copying them out
+    pub fn with_exposed<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        f(&self.0)
+    }
+    
+    /// Constant-time comparison that also hides whether the lengths match
+    pub fn ct_eq(&self, other: &Self) -> bool {
+        crypto::ct_eq_len_hiding(&self.0, &other.0)
+    }
+}
+
+impl PartialEq for SecureVec {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
+
+impl Eq for SecureVec {}
+
+impl Zeroize for SecureVec {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecureVec {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecureVec {}
+
+impl fmt::Debug for SecureVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecureVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecureVec {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self::new(Vec::deserialize(deserializer)?))
+    }
+}
+
+/// Opt-in serialization for [`SecureString`] and [`SecureVec`]
+/// 
+/// The secure types deliberately don't implement `Serialize`, so a secret
+/// can't end up in a response body or log line by accident. A field that
+/// really has to be written out names this module:
+/// 
+/// ```
+/// use rustcare_sync::secure_memory::{serde_exposed, SecureString};
+/// use serde::{Deserialize, Serialize};
+/// 
+/// #[derive(Serialize, Deserialize)]
+/// struct Credentials {
+///     #[serde(with = "serde_exposed")]
+///     password: SecureString,
+/// }
+/// ```
+pub mod serde_exposed {
+    use super::{SecureString, SecureVec};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    
+    /// Secure types that can be serialized through this module
+    pub trait ExposedSerialize {
+        fn serialize_exposed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+    }
+    
+    impl ExposedSerialize for SecureString {
+        fn serialize_exposed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize(serializer)
+        }
+    }
+    
+    impl ExposedSerialize for SecureVec {
+        fn serialize_exposed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize(serializer)
+        }
+    }
+    
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: ExposedSerialize,
+        S: Serializer,
+    {
+        value.serialize_exposed(serializer)
+    }
+    
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer)
+    }
+}
 
 /// Wrapper for PHI data that must be kept secure in memory
 /// 
@@ -194,13 +397,13 @@ pub trait IntoSecure {
 
 impl IntoSecure for String {
     fn into_secure(self) -> SecureString {
-        Secret::new(self)
+        SecureString::new(self)
     }
 }
 
 impl IntoSecure for &str {
     fn into_secure(self) -> SecureString {
-        Secret::new(self.to_string())
+        SecureString::new(self.to_string())
     }
 }
 
@@ -211,13 +414,13 @@ pub trait IntoSecureVec {
 
 impl IntoSecureVec for Vec<u8> {
     fn into_secure_vec(self) -> SecureVec {
-        Secret::new(self)
+        SecureVec::new(self)
     }
 }
 
 impl IntoSecureVec for &[u8] {
     fn into_secure_vec(self) -> SecureVec {
-        Secret::new(self.to_vec())
+        SecureVec::new(self.to_vec())
     }
 }
 
@@ -236,7 +439,8 @@ mod tests {
     #[test]
     fn test_secure_vec_no_display() {
         let key = vec![1, 2, 3, 4, 5].into_secure_vec();
-        // SecretVec doesn't implement Debug, so we just verify it exists
+        assert_eq!(format!("{:?}", key), "[REDACTED]");
+        assert_eq!(key.to_string(), "[REDACTED]");
         assert_eq!(key.expose_secret(), &vec![1, 2, 3, 4, 5]);
     }
 
@@ -331,4 +535,89 @@ mod tests {
         
         assert_eq!(original.expose_secret(), cloned.expose_secret());
     }
+
+    #[test]
+    fn test_secure_string_redacted() {
+        let ssn = "123-45-6789".into_secure();
+        assert_eq!(format!("{:?}", ssn), "[REDACTED]");
+        assert_eq!(format!("{}", ssn), "[REDACTED]");
+        assert_eq!(format!("{:?}", Some(&ssn)), "Some([REDACTED])");
+    }
+
+    #[test]
+    fn test_secure_string_ct_eq() {
+        let a = "token-123".into_secure();
+        assert!(a.ct_eq(&"token-123".into_secure()));
+        assert!(!a.ct_eq(&"token-124".into_secure()));
+        assert!(!a.ct_eq(&"token-1234".into_secure()));
+        assert_eq!(a, "token-123".into_secure());
+        assert_ne!(a, "".into_secure());
+
+        let key = vec![1, 2, 3].into_secure_vec();
+        assert!(key.ct_eq(&vec![1, 2, 3].into_secure_vec()));
+        assert!(!key.ct_eq(&vec![1, 2].into_secure_vec()));
+    }
+
+    #[test]
+    fn test_with_exposed() {
+        let ssn = "123-45-6789".into_secure();
+        let ptr = ssn.with_exposed(|bytes| {
+            assert_eq!(bytes, b"123-45-6789");
+            bytes.as_ptr()
+        });
+        // The closure sees the secret's own buffer, not a copy
+        assert_eq!(ptr, ssn.expose_secret().as_ptr());
+
+        let key = vec![9u8; 4].into_secure_vec();
+        assert_eq!(key.with_exposed(|bytes| bytes.iter().map(|&b| b as u32).sum::<u32>()), 36);
+    }
+
+    #[test]
+    fn test_opt_in_serialization() {
+        #[derive(Serialize, Deserialize)]
+        struct Credentials {
+            #[serde(with = "serde_exposed")]
+            password: SecureString,
+            #[serde(with = "serde_exposed")]
+            key: SecureVec,
+        }
+
+        let credentials = Credentials {
+            password: "hunter2".into_secure(),
+            key: vec![1, 2].into_secure_vec(),
+        };
+        let json = serde_json::to_string(&credentials).unwrap();
+        assert_eq!(json, r#"{"password":"hunter2","key":[1,2]}"#);
+
+        let parsed: Credentials = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.password, credentials.password);
+        assert_eq!(parsed.key, credentials.key);
+
+        // Without the opt-in, the plain types only deserialize
+        let password: SecureString = serde_json::from_str(r#""hunter2""#).unwrap();
+        assert_eq!(password.expose_secret(), "hunter2");
+    }
+
+    fn assert_zeroize_on_drop<T: ZeroizeOnDrop>() {}
+
+    #[test]
+    fn test_secure_types_zeroed_on_drop() {
+        assert_zeroize_on_drop::<SecureString>();
+        assert_zeroize_on_drop::<SecureVec>();
+
+        // Drop runs exactly this wipe before the buffer is freed. Capacity
+        // is kept, so the wiped bytes can still be read back.
+        let mut ssn = "123-45-6789".into_secure();
+        let (ptr, len) = ssn.with_exposed(|bytes| (bytes.as_ptr(), bytes.len()));
+        ssn.zeroize();
+        let wiped = unsafe { std::slice::from_raw_parts(ptr, len) };
+        assert!(wiped.iter().all(|&b| b == 0));
+        assert_eq!(ssn.expose_secret(), "");
+
+        let mut key = vec![0xAB; 32].into_secure_vec();
+        let (ptr, len) = key.with_exposed(|bytes| (bytes.as_ptr(), bytes.len()));
+        key.zeroize();
+        let wiped = unsafe { std::slice::from_raw_parts(ptr, len) };
+        assert!(wiped.iter().all(|&b| b == 0));
+    }
 }
@@ -15,10 +15,12 @@
 /// - Retry with exponential backoff
 /// - Batch operations for efficiency
 
+use crate::audit::ChainHead;
 use crate::error::{SyncError, SyncResult};
 use crate::local_db::{LocalDatabase, OperationType, SyncQueueEntry};
-use crate::hlc::{HybridLogicalClock, HybridTimestamp};
+use crate::hlc::HybridTimestamp;
 use crate::causality::VectorClock;
+use crate::rate_limiter::SYNC_UPLOAD_BUCKET;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
@@ -55,8 +57,6 @@ pub struct SyncProtocol {
     local_db: Arc<LocalDatabase>,
     config: SyncConfig,
     client: reqwest::Client,
-    #[allow(dead_code)]
-    clock: HybridLogicalClock,
 }
 
 /// Operation to be synced
@@ -98,6 +98,10 @@ impl From<SyncQueueEntry> for SyncOperation {
 pub struct PushRequest {
     pub node_id: Uuid,
     pub operations: Vec<SyncOperation>,
+    /// Head of the node's audit hash chain, so the server can detect
+    /// retroactive edits to the local audit log
+    #[serde(default)]
+    pub audit_chain_head: Option<ChainHead>,
 }
 
 /// Push response from server
@@ -149,16 +153,10 @@ impl SyncProtocol {
             .build()
             .expect("Failed to create HTTP client");
         
-        let node_id = local_db.node_id();
-        // Convert UUID to u64 for clock (use first 8 bytes)
-        let node_id_u64 = u64::from_le_bytes(node_id.as_bytes()[..8].try_into().unwrap());
-        let clock = HybridLogicalClock::new(node_id_u64);
-        
         Self {
             local_db,
             config,
             client,
-            clock,
         }
     }
     
@@ -219,7 +217,10 @@ impl SyncProtocol {
             .map_err(|e| SyncError::Serialization(e.to_string()))?;
         
         // Apply remote operations to local database
-        for _operation in pull_response.operations {
+        for operation in pull_response.operations {
+            // Keep the local clock ahead of everything we have seen
+            self.local_db.receive_timestamp(operation.timestamp).await?;
+            
             // Queue operation locally
             // In a real implementation, we'd apply CRDT merge here
             stats.pulled_operations += 1;
@@ -256,8 +257,12 @@ impl SyncProtocol {
         let request = PushRequest {
             node_id,
             operations: operations.clone(),
+            audit_chain_head: self.local_db.audit_chain_head().await,
         };
         
+        // Throttle uploads independently of local writes
+        self.local_db.acquire_rate_limit(SYNC_UPLOAD_BUCKET).await?;
+        
         // Send request to server
         let url = format!("{}/api/sync/push", self.config.server_url);
         let mut req = self.client.post(&url).json(&request);
@@ -344,6 +349,7 @@ mod tests {
             user_email: None,
             rate_limiter_config: None,
             kms_config: None,
+            max_clock_drift_ms: crate::hlc::DEFAULT_MAX_DRIFT_MS,
         };
         
         Arc::new(LocalDatabase::new(config).await.unwrap())
@@ -382,6 +388,7 @@ mod tests {
         let request = PushRequest {
             node_id: Uuid::new_v4(),
             operations: vec![],
+            audit_chain_head: None,
         };
         
         let json = serde_json::to_string(&request).unwrap();
@@ -1,2 +1,744 @@
-// Alert system stub
-pub struct AlertManager {}
\ No newline at end of file
+//! Alert rules evaluated against collected metrics
+//!
+//! An `AlertRule` selects a metric, aggregates it over a window and compares
+//! the result against a threshold or against its own value some time ago
+//! (rate of change). The `AlertEngine` evaluates all rules periodically and
+//! sends `Alert` events to sinks when a rule starts firing and again when it
+//! resolves.
+//!
+//! Flapping is suppressed in both directions: a rule must hold for
+//! `for_seconds` before it fires, and must stay clear for
+//! `keep_firing_for_seconds` before it resolves.
+//!
+//! Rules are plain serde types and can be loaded from a YAML or JSON file;
+//! `AlertEngine::with_rules_file` reloads the file whenever it changes.
+//!
+//! ```yaml
+//! rules:
+//!   - name: high_error_rate
+//!     severity: critical
+//!     summary: "5xx error rate is {value}"
+//!     selector: { name: http_requests_total, labels: { status: "5*" } }
+//!     aggregation: { type: ratio, denominator: { name: http_requests_total } }
+//!     window_seconds: 300
+//!     condition: { type: threshold, op: gt, value: 0.05 }
+//!     for_seconds: 120
+//!   - name: p99_latency_doubled
+//!     severity: warning
+//!     selector: { name: http_request_duration_seconds }
+//!     aggregation: { type: quantile, quantile: 0.99 }
+//!     window_seconds: 3600
+//!     condition: { type: rate_of_change, op: gte, factor: 2.0, offset_seconds: 604800 }
+//! ```
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+
+use crate::error::{Result, TelemetryError};
+use crate::metrics::{MetricsCollector, SeriesValue};
+
+/// Severity of an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Selects the series of one metric whose labels match
+///
+/// A label value ending in `*` matches by prefix, e.g. `status: "5*"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricSelector {
+    pub name: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl MetricSelector {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            labels: BTreeMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    fn matches(&self, labels: &[(String, String)]) -> bool {
+        self.labels.iter().all(|(key, pattern)| {
+            labels.iter().any(|(k, v)| {
+                k == key
+                    && match pattern.strip_suffix('*') {
+                        Some(prefix) => v.starts_with(prefix),
+                        None => v == pattern,
+                    }
+            })
+        })
+    }
+}
+
+/// How the selected series are reduced to one value per evaluation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Aggregation {
+    /// Current value summed across series (gauges, counter totals)
+    Value,
+    /// Increase of a counter over the window
+    Increase,
+    /// Per-second increase of a counter over the window
+    Rate,
+    /// Increase of the selector divided by the increase of `denominator`
+    Ratio { denominator: MetricSelector },
+    /// Quantile of a histogram over the window, e.g. 0.99
+    Quantile { quantile: f64 },
+}
+
+/// Comparison operator of a condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Comparison {
+    fn holds(self, left: f64, right: f64) -> bool {
+        match self {
+            Comparison::Gt => left > right,
+            Comparison::Gte => left >= right,
+            Comparison::Lt => left < right,
+            Comparison::Lte => left <= right,
+        }
+    }
+}
+
+/// Condition that makes a rule fire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Aggregated value compared against a fixed value
+    Threshold { op: Comparison, value: f64 },
+    /// Aggregated value divided by its value `offset_seconds` ago, compared against `factor`
+    RateOfChange {
+        op: Comparison,
+        factor: f64,
+        offset_seconds: u64,
+    },
+}
+
+/// Alert rule definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Unique rule name
+    pub name: String,
+    pub severity: Severity,
+    /// Human readable summary; `{value}` is replaced with the current value
+    #[serde(default)]
+    pub summary: Option<String>,
+    pub selector: MetricSelector,
+    pub aggregation: Aggregation,
+    /// Aggregation window
+    #[serde(default = "default_window_seconds")]
+    pub window_seconds: u64,
+    pub condition: AlertCondition,
+    /// How long the condition must hold before the alert fires
+    #[serde(default)]
+    pub for_seconds: u64,
+    /// How long the condition must stay clear before the alert resolves
+    #[serde(default)]
+    pub keep_firing_for_seconds: u64,
+    /// Extra labels attached to alerts of this rule
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Names of the sinks to notify; all sinks when empty
+    #[serde(default)]
+    pub sinks: Vec<String>,
+}
+
+fn default_window_seconds() -> u64 {
+    300
+}
+
+/// File format of alert rule configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertRulesConfig {
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+}
+
+impl AlertRulesConfig {
+    /// Load rules from a YAML or JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| TelemetryError::AlertConfigError(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&content)
+    }
+
+    /// Parse rules from YAML (or JSON, which is valid YAML)
+    pub fn parse(content: &str) -> Result<Self> {
+        let config: Self =
+            serde_yaml::from_str(content).map_err(|e| TelemetryError::AlertConfigError(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut names = std::collections::HashSet::new();
+        for rule in &self.rules {
+            if !names.insert(rule.name.as_str()) {
+                return Err(TelemetryError::AlertConfigError(format!(
+                    "duplicate alert rule name '{}'",
+                    rule.name
+                )));
+            }
+            if let Aggregation::Quantile { quantile } = rule.aggregation {
+                if !(0.0..=1.0).contains(&quantile) {
+                    return Err(TelemetryError::AlertConfigError(format!(
+                        "alert rule '{}': quantile must be between 0 and 1",
+                        rule.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether an alert started or stopped firing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/// Alert notification produced by a rule state change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub rule: String,
+    pub severity: Severity,
+    pub status: AlertStatus,
+    pub summary: String,
+    /// Aggregated value at the time of the notification
+    pub value: f64,
+    pub labels: BTreeMap<String, String>,
+    pub started_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Destination for alert notifications
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    /// Name used by `AlertRule::sinks` to route alerts
+    fn name(&self) -> &str;
+
+    async fn send(&self, alert: &Alert) -> Result<()>;
+}
+
+/// Posts alerts as JSON to an HTTP endpoint
+pub struct WebhookSink {
+    name: String,
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .headers(crate::tracing::trace_context_headers())
+            .json(alert)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| TelemetryError::AlertDeliveryError(format!("{}: {}", self.name, e)))?;
+        Ok(())
+    }
+}
+
+/// Hands alerts to an in-process consumer
+///
+/// Used to bridge alerts into services that telemetry does not depend on,
+/// e.g. publishing them on the events bus or mailing them via the email
+/// service.
+pub struct ChannelSink {
+    name: String,
+    sender: mpsc::Sender<Alert>,
+}
+
+impl ChannelSink {
+    /// Create a sink and the receiver its alerts are delivered to
+    pub fn new(name: impl Into<String>, capacity: usize) -> (Self, mpsc::Receiver<Alert>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (
+            Self {
+                name: name.into(),
+                sender,
+            },
+            receiver,
+        )
+    }
+}
+
+#[async_trait]
+impl AlertSink for ChannelSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        self.sender
+            .send(alert.clone())
+            .await
+            .map_err(|_| TelemetryError::AlertDeliveryError(format!("{}: receiver dropped", self.name)))
+    }
+}
+
+/// Raw values of a rule's selectors at one evaluation
+#[derive(Debug, Clone)]
+struct Reading {
+    at: Instant,
+    value: f64,
+    denominator: f64,
+    /// Cumulative histogram buckets (upper bound, count), ending with `+Inf`
+    buckets: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RuleState {
+    Inactive,
+    Pending { since: Instant },
+    Firing {
+        started_at: DateTime<Utc>,
+        clear_since: Option<Instant>,
+    },
+}
+
+struct RuleEntry {
+    rule: AlertRule,
+    state: RuleState,
+    readings: VecDeque<Reading>,
+    /// Aggregated values, kept for rate-of-change conditions
+    values: VecDeque<(Instant, f64)>,
+}
+
+impl RuleEntry {
+    fn new(rule: AlertRule) -> Self {
+        Self {
+            rule,
+            state: RuleState::Inactive,
+            readings: VecDeque::new(),
+            values: VecDeque::new(),
+        }
+    }
+}
+
+struct RulesFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+/// Periodically evaluates alert rules and notifies sinks
+pub struct AlertEngine {
+    collector: MetricsCollector,
+    rules: Mutex<Vec<RuleEntry>>,
+    sinks: Vec<Arc<dyn AlertSink>>,
+    rules_file: Mutex<Option<RulesFile>>,
+}
+
+impl AlertEngine {
+    pub fn new(collector: MetricsCollector) -> Self {
+        Self {
+            collector,
+            rules: Mutex::new(Vec::new()),
+            sinks: Vec::new(),
+            rules_file: Mutex::new(None),
+        }
+    }
+
+    #[must_use]
+    pub fn with_sink(mut self, sink: Arc<dyn AlertSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    #[must_use]
+    pub fn with_rules(self, rules: Vec<AlertRule>) -> Self {
+        self.set_rules(rules);
+        self
+    }
+
+    /// Load rules from a file and reload them whenever it changes
+    pub fn with_rules_file(self, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        self.set_rules(AlertRulesConfig::from_file(&path)?.rules);
+        *lock(&self.rules_file) = Some(RulesFile { path, modified });
+        Ok(self)
+    }
+
+    /// Replace the rule set
+    ///
+    /// Rules whose definition is unchanged keep their state and history, so
+    /// a reload does not re-fire or resolve active alerts.
+    pub fn set_rules(&self, rules: Vec<AlertRule>) {
+        let mut entries = lock(&self.rules);
+        let mut previous: BTreeMap<String, RuleEntry> = entries
+            .drain(..)
+            .map(|entry| (entry.rule.name.clone(), entry))
+            .collect();
+        *entries = rules
+            .into_iter()
+            .map(|rule| match previous.remove(&rule.name) {
+                Some(entry) if entry.rule == rule => entry,
+                _ => RuleEntry::new(rule),
+            })
+            .collect();
+    }
+
+    /// Names of the configured rules
+    pub fn rule_names(&self) -> Vec<String> {
+        lock(&self.rules).iter().map(|entry| entry.rule.name.clone()).collect()
+    }
+
+    /// Reload the rules file if it changed since it was last read
+    ///
+    /// A file that fails to parse is reported and the current rules stay in place.
+    pub fn reload_if_changed(&self) {
+        let mut rules_file = lock(&self.rules_file);
+        let Some(file) = rules_file.as_mut() else {
+            return;
+        };
+        let modified = std::fs::metadata(&file.path).and_then(|m| m.modified()).ok();
+        if modified == file.modified {
+            return;
+        }
+        file.modified = modified;
+
+        match AlertRulesConfig::from_file(&file.path) {
+            Ok(config) => {
+                ::tracing::info!(path = %file.path.display(), rules = config.rules.len(), "Reloaded alert rules");
+                self.set_rules(config.rules);
+            }
+            Err(e) => {
+                ::tracing::warn!(path = %file.path.display(), error = %e, "Failed to reload alert rules");
+            }
+        }
+    }
+
+    /// Evaluate all rules at `now` and return the resulting notifications
+    pub fn evaluate_at(&self, now: Instant) -> Vec<Alert> {
+        let mut entries = lock(&self.rules);
+        entries
+            .iter_mut()
+            .filter_map(|entry| self.evaluate_rule(entry, now))
+            .collect()
+    }
+
+    /// Evaluate all rules now and deliver notifications to their sinks
+    pub async fn evaluate(&self) -> Vec<Alert> {
+        let alerts = self.evaluate_at(Instant::now());
+        for alert in &alerts {
+            self.dispatch(alert).await;
+        }
+        alerts
+    }
+
+    /// Run evaluation every `interval` until the task is aborted
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.reload_if_changed();
+                self.evaluate().await;
+            }
+        })
+    }
+
+    async fn dispatch(&self, alert: &Alert) {
+        let routes = lock(&self.rules)
+            .iter()
+            .find(|entry| entry.rule.name == alert.rule)
+            .map(|entry| entry.rule.sinks.clone())
+            .unwrap_or_default();
+
+        for sink in &self.sinks {
+            if !routes.is_empty() && !routes.iter().any(|name| name == sink.name()) {
+                continue;
+            }
+            if let Err(e) = sink.send(alert).await {
+                ::tracing::warn!(rule = %alert.rule, sink = sink.name(), error = %e, "Failed to deliver alert");
+            }
+        }
+    }
+
+    fn read(&self, selector: &MetricSelector, reading: &mut Reading, denominator: bool) {
+        let Some(family) = self.collector.snapshot_family(&selector.name) else {
+            return;
+        };
+        for series in family.series.iter().filter(|s| selector.matches(&s.labels)) {
+            let value = match &series.value {
+                SeriesValue::Counter(value) | SeriesValue::Gauge(value) => *value,
+                SeriesValue::Histogram(histogram) => {
+                    if !denominator {
+                        let mut buckets = histogram.cumulative();
+                        buckets.push((f64::INFINITY, histogram.count));
+                        merge_buckets(&mut reading.buckets, &buckets);
+                    }
+                    histogram.count as f64
+                }
+            };
+            if denominator {
+                reading.denominator += value;
+            } else {
+                reading.value += value;
+            }
+        }
+    }
+
+    fn evaluate_rule(&self, entry: &mut RuleEntry, now: Instant) -> Option<Alert> {
+        let rule = &entry.rule;
+        let mut reading = Reading {
+            at: now,
+            value: 0.0,
+            denominator: 0.0,
+            buckets: Vec::new(),
+        };
+        self.read(&rule.selector, &mut reading, false);
+        if let Aggregation::Ratio { denominator } = &rule.aggregation {
+            self.read(denominator, &mut reading, true);
+        }
+
+        let window = Duration::from_secs(rule.window_seconds);
+        entry.readings.push_back(reading);
+        prune(&mut entry.readings, now, window, |r| r.at);
+
+        let value = aggregate(&rule.aggregation, &entry.readings)?;
+        let holds = match &rule.condition {
+            AlertCondition::Threshold { op, value: threshold } => Some(op.holds(value, *threshold)),
+            AlertCondition::RateOfChange {
+                op,
+                factor,
+                offset_seconds,
+            } => {
+                // Record before looking back so early readings still become baselines
+                let offset = Duration::from_secs(*offset_seconds);
+                entry.values.push_back((now, value));
+                prune(&mut entry.values, now, offset, |(at, _)| *at);
+                now.checked_sub(offset)
+                    .and_then(|at| value_at(&entry.values, at))
+                    .filter(|baseline| *baseline != 0.0)
+                    .map(|baseline| op.holds(value / baseline, *factor))
+            }
+        };
+
+        // Without enough data the rule keeps its current state
+        let (state, notify) = transition(entry.state, holds?, rule, now);
+        entry.state = state;
+        notify.map(|(status, started_at)| build_alert(rule, status, value, started_at))
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Drop entries older than needed, keeping one at or before `now - span`
+fn prune<T>(items: &mut VecDeque<T>, now: Instant, span: Duration, at: impl Fn(&T) -> Instant) {
+    let Some(cutoff) = now.checked_sub(span) else {
+        return;
+    };
+    while items.get(1).is_some_and(|next| at(next) <= cutoff) {
+        items.pop_front();
+    }
+}
+
+/// Latest recorded value at or before `at`
+fn value_at(values: &VecDeque<(Instant, f64)>, at: Instant) -> Option<f64> {
+    values
+        .iter()
+        .take_while(|(recorded, _)| *recorded <= at)
+        .last()
+        .map(|(_, value)| *value)
+}
+
+fn merge_buckets(into: &mut Vec<(f64, f64)>, buckets: &[(f64, u64)]) {
+    if into.is_empty() {
+        into.extend(buckets.iter().map(|(bound, count)| (*bound, *count as f64)));
+        return;
+    }
+    for (bound, count) in buckets {
+        if let Some(existing) = into.iter_mut().find(|(b, _)| b == bound) {
+            existing.1 += *count as f64;
+        }
+    }
+}
+
+/// Counter increase between two readings, tolerating counter resets
+fn increase(current: f64, base: f64) -> f64 {
+    if current >= base {
+        current - base
+    } else {
+        current
+    }
+}
+
+fn aggregate(aggregation: &Aggregation, readings: &VecDeque<Reading>) -> Option<f64> {
+    let current = readings.back()?;
+    if let Aggregation::Value = aggregation {
+        return Some(current.value);
+    }
+
+    // Windowed aggregations need a reading from the start of the window
+    let base = readings.front().filter(|base| base.at < current.at)?;
+    match aggregation {
+        Aggregation::Value => Some(current.value),
+        Aggregation::Increase => Some(increase(current.value, base.value)),
+        Aggregation::Rate => {
+            let elapsed = current.at.duration_since(base.at).as_secs_f64();
+            Some(increase(current.value, base.value) / elapsed)
+        }
+        Aggregation::Ratio { .. } => {
+            let denominator = increase(current.denominator, base.denominator);
+            (denominator > 0.0).then(|| increase(current.value, base.value) / denominator)
+        }
+        Aggregation::Quantile { quantile } => {
+            let deltas: Vec<(f64, f64)> = current
+                .buckets
+                .iter()
+                .map(|(bound, count)| {
+                    let before = base
+                        .buckets
+                        .iter()
+                        .find(|(b, _)| b == bound)
+                        .map_or(0.0, |(_, c)| *c);
+                    (*bound, increase(*count, before))
+                })
+                .collect();
+            bucket_quantile(*quantile, &deltas)
+        }
+    }
+}
+
+/// Estimate a quantile from cumulative buckets by linear interpolation
+fn bucket_quantile(quantile: f64, buckets: &[(f64, f64)]) -> Option<f64> {
+    let total = buckets.last().map(|(_, count)| *count).filter(|total| *total > 0.0)?;
+    let rank = quantile * total;
+
+    let mut lower = 0.0;
+    let mut below = 0.0;
+    for (bound, count) in buckets {
+        if *count >= rank {
+            if bound.is_infinite() {
+                // Observations above the highest finite bucket
+                return Some(lower);
+            }
+            let in_bucket = count - below;
+            if in_bucket <= 0.0 {
+                return Some(*bound);
+            }
+            return Some(lower + (bound - lower) * (rank - below) / in_bucket);
+        }
+        lower = *bound;
+        below = *count;
+    }
+    None
+}
+
+/// Advance a rule's state; returns the notification to send, if any
+fn transition(
+    state: RuleState,
+    holds: bool,
+    rule: &AlertRule,
+    now: Instant,
+) -> (RuleState, Option<(AlertStatus, DateTime<Utc>)>) {
+    let pending_for = Duration::from_secs(rule.for_seconds);
+    let keep_firing_for = Duration::from_secs(rule.keep_firing_for_seconds);
+    let fire = || {
+        let started_at = Utc::now();
+        (
+            RuleState::Firing {
+                started_at,
+                clear_since: None,
+            },
+            Some((AlertStatus::Firing, started_at)),
+        )
+    };
+
+    match (state, holds) {
+        (RuleState::Inactive, true) if pending_for.is_zero() => fire(),
+        (RuleState::Inactive, true) => (RuleState::Pending { since: now }, None),
+        (RuleState::Pending { since }, true) if now.duration_since(since) >= pending_for => fire(),
+        (RuleState::Pending { .. }, true) => (state, None),
+        (RuleState::Inactive | RuleState::Pending { .. }, false) => (RuleState::Inactive, None),
+        (RuleState::Firing { started_at, .. }, true) => (
+            RuleState::Firing {
+                started_at,
+                clear_since: None,
+            },
+            None,
+        ),
+        (RuleState::Firing { started_at, clear_since }, false) => {
+            let clear_since = clear_since.unwrap_or(now);
+            if now.duration_since(clear_since) >= keep_firing_for {
+                (RuleState::Inactive, Some((AlertStatus::Resolved, started_at)))
+            } else {
+                (
+                    RuleState::Firing {
+                        started_at,
+                        clear_since: Some(clear_since),
+                    },
+                    None,
+                )
+            }
+        }
+    }
+}
+
+fn build_alert(rule: &AlertRule, status: AlertStatus, value: f64, started_at: DateTime<Utc>) -> Alert {
+    let summary = rule
+        .summary
+        .as_deref()
+        .map_or_else(|| format!("{} is {}", rule.name, value), |s| s.replace("{value}", &value.to_string()));
+    Alert {
+        rule: rule.name.clone(),
+        severity: rule.severity,
+        status,
+        summary,
+        value,
+        labels: rule.labels.clone(),
+        started_at,
+        resolved_at: (status == AlertStatus::Resolved).then(Utc::now),
+    }
+}
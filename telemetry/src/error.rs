@@ -13,12 +13,21 @@ pub enum TelemetryError {
     
     #[error("Alert system error")]
     AlertError,
+
+    #[error("Alert rule configuration error: {0}")]
+    AlertConfigError(String),
+
+    #[error("Alert delivery failed: {0}")]
+    AlertDeliveryError(String),
     
     #[error("Dashboard error")]
     DashboardError,
     
     #[error("Exporter error")]
     ExporterError,
+
+    #[error("OTLP exporter error: {0}")]
+    OtlpExporterError(String),
     
     #[error("Internal error: {0}")]
     InternalError(#[from] anyhow::Error),
@@ -1,2 +1,648 @@
-// Telemetry exporters stub
-pub struct TelemetryExporter {}
\ No newline at end of file
+//! Telemetry exporters
+//!
+//! `PrometheusExporter` renders a `MetricsCollector` snapshot for a
+//! `/metrics` scrape endpoint, either in the Prometheus text format (0.0.4)
+//! or in OpenMetrics 1.0. Only OpenMetrics carries histogram exemplars, so
+//! scrapers that ask for it can jump from a latency bucket to a trace.
+//!
+//! `OtlpConfig` pushes spans and metrics to an OpenTelemetry collector over
+//! OTLP (gRPC or HTTP/protobuf). Spans are exported in batches from a
+//! background task with bounded queueing and retries; spans that cannot be
+//! queued or exported are counted in `telemetry_otlp_dropped_spans_total`.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use futures::future::BoxFuture;
+use opentelemetry::trace::{TraceError, TraceResult};
+use opentelemetry::{Context, InstrumentationLibrary, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::metrics::data::{self, DataPoint, HistogramDataPoint, ResourceMetrics, ScopeMetrics, Temporality};
+use opentelemetry_sdk::metrics::exporter::PushMetricsExporter;
+use opentelemetry_sdk::metrics::reader::{DefaultAggregationSelector, DefaultTemporalitySelector};
+use opentelemetry_sdk::trace::{self, BatchConfigBuilder, BatchSpanProcessor, SpanProcessor, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use opentelemetry_semantic_conventions::resource::SERVICE_NAME;
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+
+use crate::error::{Result, TelemetryError};
+use crate::metrics::{Exemplar, MetricFamilySnapshot, MetricKind, MetricsCollector, SeriesValue};
+
+/// Text format used to expose metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpositionFormat {
+    /// Prometheus text format 0.0.4 (no exemplars)
+    Prometheus,
+    /// OpenMetrics 1.0 text format, with exemplars
+    OpenMetrics,
+}
+
+impl ExpositionFormat {
+    /// Pick the format from a scraper's `Accept` header
+    pub fn negotiate(accept: Option<&str>) -> Self {
+        if accept.is_some_and(|accept| accept.contains("application/openmetrics-text")) {
+            ExpositionFormat::OpenMetrics
+        } else {
+            ExpositionFormat::Prometheus
+        }
+    }
+
+    /// `Content-Type` of output in this format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExpositionFormat::Prometheus => PrometheusExporter::CONTENT_TYPE,
+            ExpositionFormat::OpenMetrics => "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        }
+    }
+}
+
+/// Telemetry exporter placeholder for push-based backends
+pub struct TelemetryExporter {}
+
+/// Renders metrics in the Prometheus text exposition format
+#[derive(Debug, Clone)]
+pub struct PrometheusExporter {
+    collector: MetricsCollector,
+}
+
+impl PrometheusExporter {
+    /// `Content-Type` of the rendered output
+    pub const CONTENT_TYPE: &'static str = "text/plain; version=0.0.4; charset=utf-8";
+
+    pub fn new(collector: MetricsCollector) -> Self {
+        Self { collector }
+    }
+
+    /// Render all metrics of the collector in the Prometheus text format
+    pub fn render(&self) -> String {
+        self.render_as(ExpositionFormat::Prometheus)
+    }
+
+    /// Render all metrics of the collector in `format`
+    pub fn render_as(&self, format: ExpositionFormat) -> String {
+        let mut out = String::new();
+        for family in self.collector.snapshot() {
+            render_family(&mut out, &family, format);
+        }
+        if format == ExpositionFormat::OpenMetrics {
+            out.push_str("# EOF\n");
+        }
+        out
+    }
+}
+
+impl Default for PrometheusExporter {
+    fn default() -> Self {
+        Self::new(MetricsCollector::global().clone())
+    }
+}
+
+fn render_family(out: &mut String, family: &MetricFamilySnapshot, format: ExpositionFormat) {
+    let open_metrics = format == ExpositionFormat::OpenMetrics;
+    // OpenMetrics names counter families without the `_total` sample suffix
+    let family_name = match family.kind {
+        MetricKind::Counter if open_metrics => family.name.strip_suffix("_total").unwrap_or(&family.name),
+        _ => family.name.as_str(),
+    };
+
+    if let Some(help) = &family.help {
+        let _ = writeln!(out, "# HELP {} {}", family_name, escape_help(help));
+    }
+    let _ = writeln!(out, "# TYPE {} {}", family_name, family.kind.as_str());
+
+    for series in &family.series {
+        match &series.value {
+            SeriesValue::Counter(value) if open_metrics => {
+                let name = format!("{}_total", family_name);
+                write_sample(out, &name, &series.labels, None, *value, None);
+            }
+            SeriesValue::Counter(value) | SeriesValue::Gauge(value) => {
+                write_sample(out, &family.name, &series.labels, None, *value, None);
+            }
+            SeriesValue::Histogram(histogram) => {
+                let bucket_name = format!("{}_bucket", family.name);
+                let exemplar = |index: usize| {
+                    histogram
+                        .exemplars
+                        .get(index)
+                        .and_then(Option::as_ref)
+                        .filter(|_| open_metrics)
+                };
+                for (index, (bound, count)) in histogram.cumulative().into_iter().enumerate() {
+                    let le = format_value(bound);
+                    write_sample(out, &bucket_name, &series.labels, Some(&le), count as f64, exemplar(index));
+                }
+                let inf = exemplar(histogram.bounds.len());
+                write_sample(out, &bucket_name, &series.labels, Some("+Inf"), histogram.count as f64, inf);
+                write_sample(out, &format!("{}_sum", family.name), &series.labels, None, histogram.sum, None);
+                write_sample(out, &format!("{}_count", family.name), &series.labels, None, histogram.count as f64, None);
+            }
+        }
+    }
+}
+
+fn write_labels<'a>(out: &mut String, pairs: impl Iterator<Item = (&'a str, &'a str)>) {
+    let mut pairs = pairs.peekable();
+    if pairs.peek().is_none() {
+        return;
+    }
+    out.push('{');
+    for (i, (key, value)) in pairs.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "{}=\"{}\"", key, escape_label_value(value));
+    }
+    out.push('}');
+}
+
+fn write_sample(
+    out: &mut String,
+    name: &str,
+    labels: &[(String, String)],
+    le: Option<&str>,
+    value: f64,
+    exemplar: Option<&Exemplar>,
+) {
+    out.push_str(name);
+    write_labels(
+        out,
+        labels
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .chain(le.map(|le| ("le", le))),
+    );
+    let _ = write!(out, " {}", format_value(value));
+
+    if let Some(exemplar) = exemplar {
+        out.push_str(" # ");
+        let trace = std::iter::once(("trace_id", exemplar.trace_id.as_str()));
+        let span = exemplar.span_id.as_deref().map(|span_id| ("span_id", span_id));
+        write_labels(out, trace.chain(span));
+        let _ = write!(out, " {} {:.3}", format_value(exemplar.value), exemplar.timestamp);
+    }
+    out.push('\n');
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Transport used to reach the OTLP collector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    /// OTLP/gRPC, usually port 4317
+    Grpc,
+    /// OTLP/HTTP with protobuf payloads, usually port 4318
+    HttpProtobuf,
+}
+
+impl std::str::FromStr for OtlpProtocol {
+    type Err = TelemetryError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "grpc" => Ok(OtlpProtocol::Grpc),
+            "http/protobuf" | "http" => Ok(OtlpProtocol::HttpProtobuf),
+            other => Err(TelemetryError::OtlpExporterError(format!("unsupported OTLP protocol '{}'", other))),
+        }
+    }
+}
+
+/// Counter of spans dropped because the export queue was full or every retry failed
+pub const DROPPED_SPANS_METRIC: &str = "telemetry_otlp_dropped_spans_total";
+
+/// OTLP export settings for spans and metrics
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    /// Collector endpoint, e.g. `http://otel-collector:4317`
+    pub endpoint: String,
+    pub protocol: OtlpProtocol,
+    /// Extra headers sent with every export, e.g. auth tokens
+    pub headers: HashMap<String, String>,
+    /// Spans buffered before new spans are dropped
+    pub max_queue_size: usize,
+    pub max_export_batch_size: usize,
+    /// Delay between span batch exports
+    pub scheduled_delay: Duration,
+    /// Timeout of a single export attempt
+    pub export_timeout: Duration,
+    /// Retries after a failed export attempt
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles up to `max_backoff`
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Interval between metric exports
+    pub metrics_interval: Duration,
+}
+
+impl OtlpConfig {
+    pub fn new(endpoint: impl Into<String>, protocol: OtlpProtocol) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            protocol,
+            headers: HashMap::new(),
+            max_queue_size: 2048,
+            max_export_batch_size: 512,
+            scheduled_delay: Duration::from_secs(5),
+            export_timeout: Duration::from_secs(10),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(5),
+            metrics_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Read the standard `OTEL_EXPORTER_OTLP_*` variables
+    ///
+    /// Returns `None` when `OTEL_EXPORTER_OTLP_ENDPOINT` is not set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(endpoint) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") else {
+            return Ok(None);
+        };
+        let protocol = match std::env::var("OTEL_EXPORTER_OTLP_PROTOCOL") {
+            Ok(protocol) => protocol.parse()?,
+            Err(_) => OtlpProtocol::Grpc,
+        };
+
+        let mut config = Self::new(endpoint, protocol);
+        if let Ok(headers) = std::env::var("OTEL_EXPORTER_OTLP_HEADERS") {
+            for pair in headers.split(',').filter(|pair| !pair.trim().is_empty()) {
+                if let Some((key, value)) = pair.split_once('=') {
+                    config.headers.insert(key.trim().to_string(), value.trim().to_string());
+                }
+            }
+        }
+        Ok(Some(config))
+    }
+
+    #[must_use]
+    pub fn with_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(key.into(), value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_queue_size(mut self, max_queue_size: usize) -> Self {
+        self.max_queue_size = max_queue_size;
+        self
+    }
+
+    #[must_use]
+    pub fn with_export_timeout(mut self, export_timeout: Duration) -> Self {
+        self.export_timeout = export_timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn with_retries(mut self, max_retries: u32, initial_backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    #[must_use]
+    pub fn with_metrics_interval(mut self, metrics_interval: Duration) -> Self {
+        self.metrics_interval = metrics_interval;
+        self
+    }
+
+    /// Delay before retry `attempt` (starting at 1)
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Upper bound of one export including all retries
+    fn total_export_time(&self) -> Duration {
+        (1..=self.max_retries).fold(
+            self.export_timeout.saturating_mul(self.max_retries.saturating_add(1)),
+            |total, attempt| total.saturating_add(self.backoff(attempt)),
+        )
+    }
+
+    /// Endpoint of one signal; OTLP/HTTP uses a path per signal
+    fn signal_endpoint(&self, path: &str) -> String {
+        match self.protocol {
+            OtlpProtocol::Grpc => self.endpoint.clone(),
+            OtlpProtocol::HttpProtobuf => format!("{}/{}", self.endpoint.trim_end_matches('/'), path),
+        }
+    }
+
+    fn grpc_metadata(&self) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        for (key, value) in &self.headers {
+            match (MetadataKey::from_bytes(key.as_bytes()), MetadataValue::try_from(value.as_str())) {
+                (Ok(key), Ok(value)) => {
+                    metadata.insert(key, value);
+                }
+                _ => ::tracing::warn!(header = %key, "Skipping invalid OTLP header"),
+            }
+        }
+        metadata
+    }
+
+    fn build_span_exporter(&self) -> Result<opentelemetry_otlp::SpanExporter> {
+        let endpoint = self.signal_endpoint("v1/traces");
+        let exporter = match self.protocol {
+            OtlpProtocol::Grpc => opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint)
+                .with_timeout(self.export_timeout)
+                .with_metadata(self.grpc_metadata())
+                .build_span_exporter(),
+            OtlpProtocol::HttpProtobuf => opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint)
+                .with_timeout(self.export_timeout)
+                .with_headers(self.headers.clone())
+                .build_span_exporter(),
+        };
+        exporter.map_err(|e| TelemetryError::OtlpExporterError(e.to_string()))
+    }
+
+    fn build_metrics_exporter(&self) -> Result<opentelemetry_otlp::MetricsExporter> {
+        let endpoint = self.signal_endpoint("v1/metrics");
+        let aggregation = Box::new(DefaultAggregationSelector::new());
+        let temporality = Box::new(DefaultTemporalitySelector::new());
+        let exporter = match self.protocol {
+            OtlpProtocol::Grpc => opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint)
+                .with_timeout(self.export_timeout)
+                .with_metadata(self.grpc_metadata())
+                .build_metrics_exporter(aggregation, temporality),
+            OtlpProtocol::HttpProtobuf => opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint)
+                .with_timeout(self.export_timeout)
+                .with_headers(self.headers.clone())
+                .build_metrics_exporter(aggregation, temporality),
+        };
+        exporter.map_err(|e| TelemetryError::OtlpExporterError(e.to_string()))
+    }
+
+    /// Tracer provider exporting spans in batches from a background task
+    pub fn tracer_provider(&self, service_name: &str) -> Result<TracerProvider> {
+        let queued = Arc::new(AtomicUsize::new(0));
+        let exporter = RetryingSpanExporter {
+            inner: Arc::new(tokio::sync::Mutex::new(self.build_span_exporter()?)),
+            config: self.clone(),
+            queued: queued.clone(),
+        };
+        let batch_config = BatchConfigBuilder::default()
+            .with_max_queue_size(self.max_queue_size)
+            .with_max_export_batch_size(self.max_export_batch_size)
+            .with_scheduled_delay(self.scheduled_delay)
+            .with_max_export_timeout(self.total_export_time())
+            .build();
+        let processor = BoundedQueueProcessor {
+            inner: BatchSpanProcessor::builder(exporter, runtime::Tokio)
+                .with_batch_config(batch_config)
+                .build(),
+            queued,
+            max_queue_size: self.max_queue_size,
+        };
+
+        Ok(TracerProvider::builder()
+            .with_span_processor(processor)
+            .with_config(trace::Config::default().with_resource(service_resource(service_name)))
+            .build())
+    }
+
+    /// Push the collector's metrics every `metrics_interval` from a background task
+    pub fn spawn_metrics_export(
+        &self,
+        collector: MetricsCollector,
+        service_name: &str,
+    ) -> Result<tokio::task::JoinHandle<()>> {
+        let exporter = self.build_metrics_exporter()?;
+        let config = self.clone();
+        let resource = service_resource(service_name);
+        let start_time = SystemTime::now();
+
+        Ok(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(config.metrics_interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let mut metrics = resource_metrics(&collector, &resource, start_time);
+                let mut attempt = 0;
+                loop {
+                    match exporter.export(&mut metrics).await {
+                        Ok(()) => break,
+                        Err(e) if attempt < config.max_retries => {
+                            attempt += 1;
+                            ::tracing::debug!(error = %e, attempt, "OTLP metrics export failed; retrying");
+                            tokio::time::sleep(config.backoff(attempt)).await;
+                        }
+                        Err(e) => {
+                            ::tracing::warn!(error = %e, "OTLP metrics export failed; skipping this interval");
+                            break;
+                        }
+                    }
+                }
+            }
+        }))
+    }
+}
+
+fn service_resource(service_name: &str) -> Resource {
+    Resource::new([KeyValue::new(SERVICE_NAME, service_name.to_string())])
+}
+
+fn record_dropped_spans(count: usize) {
+    MetricsCollector::global()
+        .counter(DROPPED_SPANS_METRIC)
+        .increment_by(count as f64);
+}
+
+/// Span exporter retrying failed batches with exponential backoff
+///
+/// Runs on the batch processor's background task, so retries never block
+/// application threads.
+#[derive(Debug)]
+struct RetryingSpanExporter {
+    inner: Arc<tokio::sync::Mutex<opentelemetry_otlp::SpanExporter>>,
+    config: OtlpConfig,
+    /// Spans accepted by `BoundedQueueProcessor` and not yet exported
+    queued: Arc<AtomicUsize>,
+}
+
+impl SpanExporter for RetryingSpanExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let inner = self.inner.clone();
+        let config = self.config.clone();
+        let queued = self.queued.clone();
+
+        Box::pin(async move {
+            let size = batch.len();
+            let mut attempt = 0;
+            let result = loop {
+                let export = inner.lock().await.export(batch.clone());
+                let error = match tokio::time::timeout(config.export_timeout, export).await {
+                    Ok(Ok(())) => break Ok(()),
+                    Ok(Err(e)) => e,
+                    Err(_) => TraceError::ExportTimedOut(config.export_timeout),
+                };
+                if attempt >= config.max_retries {
+                    record_dropped_spans(size);
+                    break Err(error);
+                }
+                attempt += 1;
+                ::tracing::debug!(error = %error, attempt, "OTLP span export failed; retrying");
+                tokio::time::sleep(config.backoff(attempt)).await;
+            };
+            queued.fetch_sub(size, Ordering::Relaxed);
+            result
+        })
+    }
+
+    fn shutdown(&mut self) {
+        if let Ok(mut inner) = self.inner.try_lock() {
+            inner.shutdown();
+        }
+    }
+}
+
+/// Drops spans instead of queueing past `max_queue_size`, counting them
+#[derive(Debug)]
+struct BoundedQueueProcessor {
+    inner: BatchSpanProcessor<runtime::Tokio>,
+    queued: Arc<AtomicUsize>,
+    max_queue_size: usize,
+}
+
+impl SpanProcessor for BoundedQueueProcessor {
+    fn on_start(&self, span: &mut trace::Span, cx: &Context) {
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, span: SpanData) {
+        if !span.span_context.is_sampled() {
+            return;
+        }
+        if self.queued.fetch_add(1, Ordering::Relaxed) >= self.max_queue_size {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            record_dropped_spans(1);
+            return;
+        }
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> TraceResult<()> {
+        self.inner.force_flush()
+    }
+
+    fn shutdown(&self) -> TraceResult<()> {
+        self.inner.shutdown()
+    }
+}
+
+/// Convert a collector snapshot into OTLP metric data (cumulative temporality)
+fn resource_metrics(collector: &MetricsCollector, resource: &Resource, start_time: SystemTime) -> ResourceMetrics {
+    let now = SystemTime::now();
+    let attributes = |labels: &[(String, String)]| -> Vec<KeyValue> {
+        labels
+            .iter()
+            .map(|(key, value)| KeyValue::new(key.clone(), value.clone()))
+            .collect()
+    };
+    let point = |labels: &[(String, String)], value: f64| DataPoint {
+        attributes: attributes(labels),
+        start_time: Some(start_time),
+        time: Some(now),
+        value,
+        exemplars: Vec::new(),
+    };
+
+    let metrics = collector
+        .snapshot()
+        .into_iter()
+        .map(|family| {
+            let data: Box<dyn data::Aggregation> = match family.kind {
+                MetricKind::Counter => Box::new(data::Sum {
+                    data_points: family
+                        .series
+                        .iter()
+                        .filter_map(|s| match s.value {
+                            SeriesValue::Counter(value) => Some(point(&s.labels, value)),
+                            _ => None,
+                        })
+                        .collect(),
+                    temporality: Temporality::Cumulative,
+                    is_monotonic: true,
+                }),
+                MetricKind::Gauge => Box::new(data::Gauge {
+                    data_points: family
+                        .series
+                        .iter()
+                        .filter_map(|s| match s.value {
+                            SeriesValue::Gauge(value) => Some(point(&s.labels, value)),
+                            _ => None,
+                        })
+                        .collect(),
+                }),
+                MetricKind::Histogram => Box::new(data::Histogram {
+                    data_points: family
+                        .series
+                        .iter()
+                        .filter_map(|s| match &s.value {
+                            SeriesValue::Histogram(histogram) => Some(HistogramDataPoint {
+                                attributes: attributes(&s.labels),
+                                start_time,
+                                time: now,
+                                count: histogram.count,
+                                bounds: histogram.bounds.clone(),
+                                bucket_counts: histogram.bucket_counts.clone(),
+                                min: None,
+                                max: None,
+                                sum: histogram.sum,
+                                exemplars: Vec::new(),
+                            }),
+                            _ => None,
+                        })
+                        .collect(),
+                    temporality: Temporality::Cumulative,
+                }),
+            };
+            data::Metric {
+                name: family.name.into(),
+                description: family.help.unwrap_or_default().into(),
+                unit: "".into(),
+                data,
+            }
+        })
+        .collect();
+
+    ResourceMetrics {
+        resource: resource.clone(),
+        scope_metrics: vec![ScopeMetrics {
+            scope: InstrumentationLibrary::builder("telemetry").build(),
+            metrics,
+        }],
+    }
+}
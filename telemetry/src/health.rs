@@ -1,2 +1,258 @@
-// Health monitoring stub
-pub struct HealthMonitor {}
\ No newline at end of file
+//! Health checks with a liveness/readiness split
+//!
+//! Components register named checks with a `HealthRegistry`. Liveness answers
+//! "is the process up" and only runs checks registered with
+//! `CheckOptions::liveness`; dependency checks (database, cache, broker,
+//! secrets provider) only affect readiness, so an outage downstream takes the
+//! instance out of rotation without getting it restarted.
+//!
+//! Checks run concurrently, each bounded by its own timeout. A failing
+//! critical check makes the report unhealthy; a failing non-critical check
+//! only degrades it.
+//!
+//! ```rust,ignore
+//! let registry = HealthRegistry::new();
+//! registry.register_fn("database", CheckOptions::critical(Duration::from_secs(2)), move || {
+//!     let pool = pool.clone();
+//!     async move { sqlx::query("SELECT 1").execute(&pool).await.map(|_| ()).map_err(|e| e.to_string()) }
+//! });
+//! let report = registry.readiness().await;
+//! ```
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+/// Status of a single check or of a whole report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    /// Serving, but a non-critical dependency is failing
+    Degraded,
+    Unhealthy,
+}
+
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+        }
+    }
+
+    /// Whether the instance can take traffic in this state
+    pub fn is_available(&self) -> bool {
+        *self != HealthStatus::Unhealthy
+    }
+}
+
+/// A health check of one component or dependency
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    /// `Err` carries a short description of the failure
+    async fn check(&self) -> std::result::Result<(), String>;
+}
+
+struct FnCheck<F>(F);
+
+#[async_trait]
+impl<F, Fut> HealthCheck for FnCheck<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = std::result::Result<(), String>> + Send,
+{
+    async fn check(&self) -> std::result::Result<(), String> {
+        (self.0)().await
+    }
+}
+
+/// How a check is run and how its failure is weighed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckOptions {
+    /// Checks exceeding the timeout count as failed
+    pub timeout: Duration,
+    /// Failure makes the report unhealthy rather than degraded
+    pub critical: bool,
+    /// Also run the check for liveness, not only readiness
+    pub liveness: bool,
+}
+
+impl CheckOptions {
+    /// Dependency required to serve traffic
+    pub fn critical(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            critical: true,
+            liveness: false,
+        }
+    }
+
+    /// Dependency whose failure only degrades service
+    pub fn non_critical(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            critical: false,
+            liveness: false,
+        }
+    }
+
+    /// Process-internal check that should also fail liveness
+    #[must_use]
+    pub fn liveness(mut self) -> Self {
+        self.liveness = true;
+        self
+    }
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self::critical(Duration::from_secs(2))
+    }
+}
+
+/// Result of one check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: HealthStatus,
+    pub critical: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Aggregated result of a liveness or readiness probe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub timestamp: DateTime<Utc>,
+    pub uptime_seconds: u64,
+    pub checks: Vec<CheckResult>,
+}
+
+struct RegisteredCheck {
+    name: String,
+    options: CheckOptions,
+    check: Arc<dyn HealthCheck>,
+}
+
+/// Registry of health checks shared by the components of a service
+pub struct HealthRegistry {
+    checks: RwLock<Vec<RegisteredCheck>>,
+    started_at: Instant,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self {
+            checks: RwLock::new(Vec::new()),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Register a check; a check with the same name is replaced
+    pub fn register(&self, name: impl Into<String>, options: CheckOptions, check: Arc<dyn HealthCheck>) {
+        let name = name.into();
+        let mut checks = self.checks.write().unwrap_or_else(PoisonError::into_inner);
+        checks.retain(|registered| registered.name != name);
+        checks.push(RegisteredCheck { name, options, check });
+    }
+
+    /// Register a check implemented by an async closure
+    pub fn register_fn<F, Fut>(&self, name: impl Into<String>, options: CheckOptions, check: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<(), String>> + Send + 'static,
+    {
+        self.register(name, options, Arc::new(FnCheck(check)));
+    }
+
+    /// Names of the registered checks
+    pub fn check_names(&self) -> Vec<String> {
+        self.checks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|registered| registered.name.clone())
+            .collect()
+    }
+
+    /// Whether the process is up; dependency failures do not affect it
+    pub async fn liveness(&self) -> HealthReport {
+        self.run(|options| options.liveness).await
+    }
+
+    /// Whether the service can serve traffic, including its dependencies
+    pub async fn readiness(&self) -> HealthReport {
+        self.run(|_| true).await
+    }
+
+    async fn run(&self, include: impl Fn(&CheckOptions) -> bool) -> HealthReport {
+        let selected: Vec<(String, CheckOptions, Arc<dyn HealthCheck>)> = self
+            .checks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|registered| include(&registered.options))
+            .map(|registered| (registered.name.clone(), registered.options, registered.check.clone()))
+            .collect();
+
+        let checks = futures::future::join_all(
+            selected
+                .into_iter()
+                .map(|(name, options, check)| run_check(name, options, check)),
+        )
+        .await;
+
+        let status = checks
+            .iter()
+            .map(|check| match check.status {
+                HealthStatus::Unhealthy if !check.critical => HealthStatus::Degraded,
+                status => status,
+            })
+            .max()
+            .unwrap_or(HealthStatus::Healthy);
+
+        HealthReport {
+            status,
+            timestamp: Utc::now(),
+            uptime_seconds: self.started_at.elapsed().as_secs(),
+            checks,
+        }
+    }
+}
+
+impl Default for HealthRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn run_check(name: String, options: CheckOptions, check: Arc<dyn HealthCheck>) -> CheckResult {
+    let start = Instant::now();
+    let outcome = match tokio::time::timeout(options.timeout, check.check()).await {
+        Ok(outcome) => outcome,
+        Err(_) => Err(format!("timed out after {}ms", options.timeout.as_millis())),
+    };
+    let latency_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    if let Err(error) = &outcome {
+        ::tracing::warn!(check = %name, critical = options.critical, error = %error, "Health check failed");
+    }
+
+    CheckResult {
+        name,
+        status: if outcome.is_ok() {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unhealthy
+        },
+        critical: options.critical,
+        latency_ms,
+        error: outcome.err(),
+    }
+}
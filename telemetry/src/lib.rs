@@ -70,4 +70,6 @@ pub use metrics::*;
 pub use tracing::*;
 pub use logging::*;
 pub use health::*;
+pub use exporters::{ExpositionFormat, OtlpConfig, OtlpProtocol, PrometheusExporter};
+pub use alerts::{Alert, AlertEngine, AlertRule, AlertRulesConfig, AlertSink, AlertStatus, ChannelSink, Severity, WebhookSink};
 pub use error::*;
\ No newline at end of file
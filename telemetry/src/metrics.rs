@@ -1,10 +1,604 @@
-// Metrics collection stub
-pub struct MetricsCollector {}
+//! Metrics collection
+//!
+//! `MetricsCollector` is an in-process registry of counters, gauges and
+//! histograms keyed by metric name and label set. Instrumentation uses the
+//! builder API:
+//!
+//! ```rust,ignore
+//! collector.counter("http_requests_total")
+//!     .with_label("method", "GET")
+//!     .increment();
+//! collector.histogram("http_request_duration_seconds").record(0.045);
+//! ```
+//!
+//! Exporters read a consistent `snapshot()` of all series.
+//!
+//! A cardinality guard caps the number of series per metric so a label with
+//! unbounded values (an id, a raw path) cannot exhaust memory here or in
+//! Prometheus. Past the limit new series are dropped or folded into a single
+//! `__overflow__` series, and metrics can restrict which label names they
+//! accept with `restrict_labels`.
 
-pub struct TelemetryEngine {}
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Result;
+use crate::exporters::{OtlpConfig, OtlpProtocol};
+use crate::tracing::TracingSystem;
+
+/// Default histogram buckets (seconds), matching the Prometheus client defaults
+pub const DEFAULT_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Type of a metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Histogram => "histogram",
+        }
+    }
+}
+
+/// Label set of a series, sorted by label name
+pub type Labels = Vec<(String, String)>;
+
+/// Label value used for series folded by the cardinality guard
+pub const OVERFLOW_LABEL_VALUE: &str = "__overflow__";
+
+/// Counter of observations rejected by the cardinality guard or a label
+/// allow-list, by metric
+pub const DROPPED_SERIES_METRIC: &str = "telemetry_metrics_dropped_series_total";
+
+/// What happens to new series once a metric reaches its series limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Discard the observation and count it in `DROPPED_SERIES_METRIC`
+    #[default]
+    Drop,
+    /// Record it in a series whose label values are all `__overflow__`
+    Fold,
+}
+
+/// Limits on the number of series per metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CardinalityConfig {
+    /// Maximum distinct label combinations per metric
+    pub max_series_per_metric: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for CardinalityConfig {
+    fn default() -> Self {
+        Self {
+            max_series_per_metric: 10_000,
+            overflow: OverflowPolicy::Drop,
+        }
+    }
+}
+
+/// Example observation linking a histogram bucket to the trace that produced it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exemplar {
+    pub value: f64,
+    pub trace_id: String,
+    pub span_id: Option<String>,
+    /// Seconds since the Unix epoch when the observation was recorded
+    pub timestamp: f64,
+}
+
+/// Histogram state: per-bucket (non-cumulative) counts plus sum and count
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+    /// Upper bounds of the finite buckets, ascending
+    pub bounds: Vec<f64>,
+    /// Observations per bucket; the extra last entry is the `+Inf` bucket
+    pub bucket_counts: Vec<u64>,
+    /// Most recent exemplar per bucket, aligned with `bucket_counts`
+    pub exemplars: Vec<Option<Exemplar>>,
+    pub sum: f64,
+    pub count: u64,
+}
+
+impl HistogramSnapshot {
+    fn new(bounds: &[f64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            bucket_counts: vec![0; bounds.len() + 1],
+            exemplars: vec![None; bounds.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64, trace: Option<(String, Option<String>)>) {
+        let index = self.bounds.partition_point(|bound| *bound < value);
+        if let Some(bucket) = self.bucket_counts.get_mut(index) {
+            *bucket += 1;
+        }
+        if let (Some((trace_id, span_id)), Some(slot)) = (trace, self.exemplars.get_mut(index)) {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_secs_f64());
+            *slot = Some(Exemplar {
+                value,
+                trace_id,
+                span_id,
+                timestamp,
+            });
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Cumulative counts per finite bucket bound (`le` semantics)
+    pub fn cumulative(&self) -> Vec<(f64, u64)> {
+        let mut total = 0;
+        self.bounds
+            .iter()
+            .zip(&self.bucket_counts)
+            .map(|(bound, count)| {
+                total += count;
+                (*bound, total)
+            })
+            .collect()
+    }
+}
+
+/// Current value of a series
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeriesValue {
+    Counter(f64),
+    Gauge(f64),
+    Histogram(HistogramSnapshot),
+}
+
+/// One labelled series of a metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeriesSnapshot {
+    pub labels: Labels,
+    pub value: SeriesValue,
+}
+
+/// All series of one metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricFamilySnapshot {
+    pub name: String,
+    pub kind: MetricKind,
+    pub help: Option<String>,
+    pub series: Vec<SeriesSnapshot>,
+}
+
+#[derive(Debug)]
+struct Family {
+    kind: MetricKind,
+    help: Option<String>,
+    buckets: Vec<f64>,
+    series: BTreeMap<Labels, SeriesValue>,
+    /// Label names accepted by this metric; any when unset
+    allowed_labels: Option<BTreeSet<String>>,
+    /// Whether the series limit has already been reported
+    overflow_reported: bool,
+    /// Disallowed labels already reported; later uses are only counted
+    rejected_labels: BTreeSet<String>,
+}
+
+impl Family {
+    fn new(kind: MetricKind) -> Self {
+        Self {
+            kind,
+            help: None,
+            buckets: DEFAULT_BUCKETS.to_vec(),
+            series: BTreeMap::new(),
+            allowed_labels: None,
+            overflow_reported: false,
+            rejected_labels: BTreeSet::new(),
+        }
+    }
+
+    /// Decide which series an observation with `labels` goes to, if any
+    fn admit(&mut self, name: &str, labels: Labels, limits: CardinalityConfig) -> Option<Labels> {
+        if let Some(allowed) = &self.allowed_labels {
+            if let Some((label, _)) = labels.iter().find(|(key, _)| !allowed.contains(key)) {
+                if self.rejected_labels.insert(label.clone()) {
+                    ::tracing::warn!(
+                        metric = %name,
+                        label = %label,
+                        "Label is not in the metric's allow-list; dropping its observations"
+                    );
+                }
+                return None;
+            }
+        }
+
+        if self.series.contains_key(&labels) || self.series.len() < limits.max_series_per_metric {
+            return Some(labels);
+        }
+
+        if !self.overflow_reported {
+            self.overflow_reported = true;
+            ::tracing::warn!(
+                metric = %name,
+                label = self.highest_cardinality_label().unwrap_or_default(),
+                limit = limits.max_series_per_metric,
+                policy = ?limits.overflow,
+                "Metric exceeded its series limit; check the instrumentation for unbounded label values"
+            );
+        }
+
+        match limits.overflow {
+            OverflowPolicy::Drop => None,
+            OverflowPolicy::Fold => Some(
+                labels
+                    .into_iter()
+                    .map(|(key, _)| (key, OVERFLOW_LABEL_VALUE.to_string()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Label with the most distinct values, the likely cause of an overflow
+    fn highest_cardinality_label(&self) -> Option<String> {
+        let mut values: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for labels in self.series.keys() {
+            for (key, value) in labels {
+                values.entry(key.as_str()).or_default().insert(value.as_str());
+            }
+        }
+        values
+            .into_iter()
+            .max_by_key(|(_, values)| values.len())
+            .map(|(key, _)| key.to_string())
+    }
+}
+
+/// In-process metrics registry
+///
+/// Cheap to clone; clones share the same registry.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsCollector {
+    families: Arc<Mutex<BTreeMap<String, Family>>>,
+    cardinality: Arc<Mutex<CardinalityConfig>>,
+}
+
+impl MetricsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process-wide collector used by default instrumentation
+    pub fn global() -> &'static MetricsCollector {
+        static GLOBAL: OnceLock<MetricsCollector> = OnceLock::new();
+        GLOBAL.get_or_init(MetricsCollector::new)
+    }
+
+    fn families(&self) -> MutexGuard<'_, BTreeMap<String, Family>> {
+        self.families.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Current series limits
+    pub fn cardinality(&self) -> CardinalityConfig {
+        *self.cardinality.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Change the series limits; existing series are kept
+    pub fn set_cardinality(&self, config: CardinalityConfig) {
+        *self.cardinality.lock().unwrap_or_else(PoisonError::into_inner) = config;
+    }
+
+    /// Accept only the given label names for a metric
+    ///
+    /// Observations carrying any other label are dropped with a warning.
+    pub fn restrict_labels(&self, name: &str, kind: MetricKind, labels: &[&str]) {
+        let mut families = self.families();
+        let family = families.entry(name.to_string()).or_insert_with(|| Family::new(kind));
+        family.allowed_labels = Some(labels.iter().map(|label| (*label).to_string()).collect());
+    }
+
+    /// Set the help text of a metric
+    pub fn describe(&self, name: &str, kind: MetricKind, help: impl Into<String>) {
+        let mut families = self.families();
+        let family = families.entry(name.to_string()).or_insert_with(|| Family::new(kind));
+        family.help = Some(help.into());
+    }
+
+    /// Set help text and bucket bounds of a histogram before first use
+    pub fn describe_histogram(&self, name: &str, help: impl Into<String>, buckets: &[f64]) {
+        let mut families = self.families();
+        let family = families
+            .entry(name.to_string())
+            .or_insert_with(|| Family::new(MetricKind::Histogram));
+        family.help = Some(help.into());
+        if family.series.is_empty() {
+            let mut buckets = buckets.to_vec();
+            buckets.sort_by(f64::total_cmp);
+            family.buckets = buckets;
+        }
+    }
+
+    pub fn counter(&self, name: impl Into<String>) -> Counter<'_> {
+        Counter(MetricHandle::new(self, name))
+    }
+
+    pub fn gauge(&self, name: impl Into<String>) -> Gauge<'_> {
+        Gauge(MetricHandle::new(self, name))
+    }
+
+    pub fn histogram(&self, name: impl Into<String>) -> Histogram<'_> {
+        Histogram {
+            handle: MetricHandle::new(self, name),
+            exemplar: None,
+        }
+    }
+
+    fn update(&self, name: &str, kind: MetricKind, labels: Labels, apply: impl FnOnce(&mut SeriesValue)) {
+        let limits = self.cardinality();
+        let mut families = self.families();
+        let family = families.entry(name.to_string()).or_insert_with(|| Family::new(kind));
+        if family.kind != kind {
+            ::tracing::warn!(
+                metric = %name,
+                registered = family.kind.as_str(),
+                used_as = kind.as_str(),
+                "Metric used with a different type than registered; ignoring"
+            );
+            return;
+        }
+
+        let Some(labels) = family.admit(name, labels, limits) else {
+            let dropped = families
+                .entry(DROPPED_SERIES_METRIC.to_string())
+                .or_insert_with(|| Family::new(MetricKind::Counter));
+            dropped
+                .help
+                .get_or_insert_with(|| "Observations dropped by the metrics cardinality guard".to_string());
+            let counter = dropped
+                .series
+                .entry(vec![("metric".to_string(), name.to_string())])
+                .or_insert(SeriesValue::Counter(0.0));
+            if let SeriesValue::Counter(value) = counter {
+                *value += 1.0;
+            }
+            return;
+        };
+
+        let buckets = &family.buckets;
+        let series = family.series.entry(labels).or_insert_with(|| match kind {
+            MetricKind::Counter => SeriesValue::Counter(0.0),
+            MetricKind::Gauge => SeriesValue::Gauge(0.0),
+            MetricKind::Histogram => SeriesValue::Histogram(HistogramSnapshot::new(buckets)),
+        });
+        apply(series);
+    }
+
+    /// Consistent copy of all metrics, sorted by name and labels
+    pub fn snapshot(&self) -> Vec<MetricFamilySnapshot> {
+        self.families()
+            .iter()
+            .map(|(name, family)| family_snapshot(name, family))
+            .collect()
+    }
+
+    /// Copy of a single metric, if it has been registered
+    pub fn snapshot_family(&self, name: &str) -> Option<MetricFamilySnapshot> {
+        self.families().get(name).map(|family| family_snapshot(name, family))
+    }
+}
+
+fn family_snapshot(name: &str, family: &Family) -> MetricFamilySnapshot {
+    MetricFamilySnapshot {
+        name: name.to_string(),
+        kind: family.kind,
+        help: family.help.clone(),
+        series: family
+            .series
+            .iter()
+            .map(|(labels, value)| SeriesSnapshot {
+                labels: labels.clone(),
+                value: value.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Name and labels of a series being recorded
+struct MetricHandle<'a> {
+    collector: &'a MetricsCollector,
+    name: String,
+    labels: Labels,
+}
+
+impl<'a> MetricHandle<'a> {
+    fn new(collector: &'a MetricsCollector, name: impl Into<String>) -> Self {
+        Self {
+            collector,
+            name: name.into(),
+            labels: Vec::new(),
+        }
+    }
+
+    fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        let value = value.into();
+        match self.labels.binary_search_by(|(k, _)| k.as_str().cmp(&key)) {
+            Ok(index) => {
+                if let Some(label) = self.labels.get_mut(index) {
+                    label.1 = value;
+                }
+            }
+            Err(index) => self.labels.insert(index, (key, value)),
+        }
+        self
+    }
+
+    fn update(self, kind: MetricKind, apply: impl FnOnce(&mut SeriesValue)) {
+        self.collector.update(&self.name, kind, self.labels, apply);
+    }
+}
+
+/// Monotonically increasing counter
+pub struct Counter<'a>(MetricHandle<'a>);
+
+impl Counter<'_> {
+    #[must_use]
+    pub fn with_label(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self(self.0.with_label(key, value))
+    }
+
+    pub fn increment(self) {
+        self.increment_by(1.0);
+    }
+
+    pub fn increment_by(self, amount: f64) {
+        self.0.update(MetricKind::Counter, |series| {
+            if let SeriesValue::Counter(value) = series {
+                *value += amount.max(0.0);
+            }
+        });
+    }
+}
+
+/// Value that can go up and down
+pub struct Gauge<'a>(MetricHandle<'a>);
+
+impl Gauge<'_> {
+    #[must_use]
+    pub fn with_label(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self(self.0.with_label(key, value))
+    }
+
+    pub fn set(self, value: f64) {
+        self.0.update(MetricKind::Gauge, |series| {
+            if let SeriesValue::Gauge(current) = series {
+                *current = value;
+            }
+        });
+    }
+
+    pub fn add(self, delta: f64) {
+        self.0.update(MetricKind::Gauge, |series| {
+            if let SeriesValue::Gauge(current) = series {
+                *current += delta;
+            }
+        });
+    }
+}
+
+/// Distribution of observed values in buckets
+///
+/// Observations made inside a sampled trace keep the trace and span id as
+/// the bucket's exemplar, so a latency spike can be followed to a trace.
+pub struct Histogram<'a> {
+    handle: MetricHandle<'a>,
+    exemplar: Option<(String, Option<String>)>,
+}
+
+impl Histogram<'_> {
+    #[must_use]
+    pub fn with_label(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            handle: self.handle.with_label(key, value),
+            ..self
+        }
+    }
+
+    /// Attach an explicit trace id instead of the active span's
+    #[must_use]
+    pub fn with_exemplar(self, trace_id: impl Into<String>) -> Self {
+        Self {
+            exemplar: Some((trace_id.into(), None)),
+            ..self
+        }
+    }
+
+    pub fn record(self, value: f64) {
+        let exemplar = self.exemplar.or_else(|| {
+            crate::tracing::current_trace_id().map(|trace_id| (trace_id, crate::tracing::current_span_id()))
+        });
+        self.handle.update(MetricKind::Histogram, |series| {
+            if let SeriesValue::Histogram(histogram) = series {
+                histogram.observe(value, exemplar);
+            }
+        });
+    }
+}
+
+/// Entry point to the telemetry platform
+#[derive(Debug, Clone)]
+pub struct TelemetryEngine {
+    metrics: MetricsCollector,
+    otlp: Option<OtlpConfig>,
+}
 
 impl TelemetryEngine {
+    /// Create an engine recording into the global metrics collector
     pub fn new() -> Self {
-        Self {}
+        Self {
+            metrics: MetricsCollector::global().clone(),
+            otlp: None,
+        }
+    }
+
+    /// Export spans and metrics to an OTLP collector with default settings
+    #[must_use]
+    pub fn with_otlp(self, endpoint: impl Into<String>, protocol: OtlpProtocol) -> Self {
+        self.with_otlp_config(OtlpConfig::new(endpoint, protocol))
+    }
+
+    /// Export spans and metrics to an OTLP collector
+    #[must_use]
+    pub fn with_otlp_config(mut self, config: OtlpConfig) -> Self {
+        self.otlp = Some(config);
+        self
+    }
+
+    pub fn otlp(&self) -> Option<&OtlpConfig> {
+        self.otlp.as_ref()
+    }
+
+    pub fn metrics(&self) -> &MetricsCollector {
+        &self.metrics
+    }
+
+    pub fn counter(&self, name: impl Into<String>) -> Counter<'_> {
+        self.metrics.counter(name)
+    }
+
+    pub fn gauge(&self, name: impl Into<String>) -> Gauge<'_> {
+        self.metrics.gauge(name)
+    }
+
+    pub fn histogram(&self, name: impl Into<String>) -> Histogram<'_> {
+        self.metrics.histogram(name)
+    }
+
+    /// Install tracing and start metric export
+    ///
+    /// With OTLP configured, spans are exported from a batch processor and
+    /// metrics are pushed periodically; otherwise spans only propagate
+    /// context. Must be called from within a Tokio runtime.
+    pub fn init(&self, service_name: &str) -> Result<TracingSystem> {
+        let Some(otlp) = &self.otlp else {
+            return Ok(TracingSystem::new(service_name));
+        };
+
+        let provider = otlp.tracer_provider(service_name)?;
+        otlp.spawn_metrics_export(self.metrics.clone(), service_name)?;
+        ::tracing::info!(endpoint = %otlp.endpoint, protocol = ?otlp.protocol, "Exporting telemetry over OTLP");
+        Ok(TracingSystem::with_provider(service_name, provider))
+    }
+}
+
+impl Default for TelemetryEngine {
+    fn default() -> Self {
+        Self::new()
     }
-}
\ No newline at end of file
+}
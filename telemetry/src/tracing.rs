@@ -1,2 +1,134 @@
-// Tracing system stub
-pub struct TracingSystem {}
\ No newline at end of file
+//! Distributed tracing with W3C trace-context propagation
+//!
+//! `TracingSystem` owns the OpenTelemetry tracer provider and exposes a
+//! `tracing-subscriber` layer, so `tracing` spans become OpenTelemetry spans.
+//! The helpers below continue an incoming `traceparent`/`tracestate` into the
+//! local span tree and inject the current context into outgoing requests.
+
+use ::tracing::Span;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::{global, Context};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+
+/// W3C trace-context parent header
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// W3C trace-context vendor state header
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+/// Tracing system bridging `tracing` spans to OpenTelemetry
+pub struct TracingSystem {
+    provider: TracerProvider,
+    service_name: String,
+}
+
+impl TracingSystem {
+    /// Create the tracing system and register the W3C propagator globally
+    ///
+    /// Spans get trace and span ids and propagate across service boundaries;
+    /// exporting them is configured on the provider by exporters.
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self::with_provider(service_name, TracerProvider::builder().build())
+    }
+
+    /// Create the tracing system around a configured provider
+    pub fn with_provider(service_name: impl Into<String>, provider: TracerProvider) -> Self {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(provider.clone());
+        Self {
+            provider,
+            service_name: service_name.into(),
+        }
+    }
+
+    /// Tracer for this service
+    pub fn tracer(&self) -> Tracer {
+        self.provider.tracer(self.service_name.clone())
+    }
+
+    /// `tracing-subscriber` layer that records spans with this tracer
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, Tracer>
+    where
+        S: ::tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer())
+    }
+}
+
+/// Reads trace-context headers from an `http::HeaderMap`
+pub struct HeaderExtractor<'a>(pub &'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// Writes trace-context headers into an `http::HeaderMap`
+pub struct HeaderInjector<'a>(pub &'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Extract the remote parent context from incoming request headers
+pub fn extract_context(headers: &HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+/// Make `span` a child of the remote parent described by `headers`
+///
+/// Does nothing when the request carries no valid `traceparent`.
+pub fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+    let context = extract_context(headers);
+    if context.span().span_context().is_valid() {
+        span.set_parent(context);
+    }
+}
+
+/// Inject the current span's context into outgoing request headers
+pub fn inject_current_context(headers: &mut HeaderMap) {
+    let context = Span::current().context();
+    TraceContextPropagator::new().inject_context(&context, &mut HeaderInjector(headers));
+}
+
+/// Trace-context headers for an outgoing request made from the current span
+///
+/// For HTTP clients that take a header map, e.g. `reqwest::RequestBuilder::headers`.
+pub fn trace_context_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    inject_current_context(&mut headers);
+    headers
+}
+
+/// Trace id of the current span, for correlating logs with traces
+pub fn current_trace_id() -> Option<String> {
+    let context = Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+/// Span id of the current span, for exemplars and log correlation
+pub fn current_span_id() -> Option<String> {
+    let context = Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_valid()
+        .then(|| span_context.span_id().to_string())
+}
@@ -0,0 +1,301 @@
+//! PHI-safe buffering of dictation audio
+//!
+//! Dictation audio is PHI. A session's audio is held in locked, zeroizing
+//! memory ([`SecureMemory`]) so it is not swapped to disk and is wiped when
+//! released. Sessions that outgrow the memory limit spool the rest to disk,
+//! encrypted with AES-256-GCM under a key generated for the session that
+//! never leaves this process's memory.
+//!
+//! # Cleanup paths
+//!
+//! - [`SessionAudioBuffer::purge`] zeroizes the memory, overwrites the spool
+//!   file with zeros, syncs it and deletes it. The service calls it when a
+//!   session stops, times out or is purged explicitly.
+//! - Dropping a buffer purges it, so a panic unwinding through the service
+//!   or a normal process exit still cleans up.
+//! - A crash or `abort` skips both. Memory is gone with the process, and any
+//!   spool left behind is ciphertext whose key died with it.
+//!   [`sweep_orphaned_spools`] deletes such files on the next start.
+//!
+//! The spool directory must belong to a single service process, since the
+//! sweep deletes every spool file in it.
+use crypto::{Aes256GcmEncryptor, Encryptor, SecureMemory};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zeroize::{Zeroize, Zeroizing};
+use crate::error::{VoiceError, VoiceResult};
+
+const SPOOL_EXTENSION: &str = "spool";
+
+/// Where and how much session audio is buffered
+#[derive(Debug, Clone)]
+pub struct AudioBufferConfig {
+    /// Bytes kept in locked memory before spooling to disk
+    pub memory_limit: usize,
+    pub spool_dir: PathBuf,
+}
+
+/// Audio captured during one dictation session
+pub struct SessionAudioBuffer {
+    session_id: Uuid,
+    config: AudioBufferConfig,
+    memory: Vec<SecureMemory>,
+    memory_bytes: usize,
+    spool: Option<Spool>,
+}
+
+impl SessionAudioBuffer {
+    pub fn new(session_id: Uuid, config: AudioBufferConfig) -> Self {
+        Self {
+            session_id,
+            config,
+            memory: Vec::new(),
+            memory_bytes: 0,
+            spool: None,
+        }
+    }
+
+    /// Append a chunk of audio. Once a chunk has been spooled, every later
+    /// chunk is spooled too, so the audio stays in order.
+    pub fn append(&mut self, chunk: &[u8]) -> VoiceResult<()> {
+        if self.spool.is_none() && self.memory_bytes + chunk.len() <= self.config.memory_limit {
+            self.memory.push(SecureMemory::new(chunk.to_vec()).map_err(secure_storage)?);
+            self.memory_bytes += chunk.len();
+            return Ok(());
+        }
+
+        if self.spool.is_none() {
+            tracing::info!(session_id = %self.session_id, "Spooling dictation audio to encrypted storage");
+            self.spool = Some(Spool::create(&self.config.spool_dir, self.session_id)?);
+        }
+        if let Some(spool) = self.spool.as_mut() {
+            spool.append(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Total bytes of audio buffered
+    pub fn len(&self) -> usize {
+        self.memory_bytes + self.spool.as_ref().map_or(0, |spool| spool.plaintext_bytes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether part of the audio is spooled to disk
+    pub fn is_spooled(&self) -> bool {
+        self.spool.is_some()
+    }
+
+    /// Path of the spool file, if the session has one
+    pub fn spool_path(&self) -> Option<&Path> {
+        self.spool.as_ref().map(|spool| spool.path.as_path())
+    }
+
+    /// All buffered audio, in order, in secure memory
+    pub fn contents(&mut self) -> VoiceResult<SecureMemory> {
+        let mut contents = SecureMemory::new_zeroed(self.len()).map_err(secure_storage)?;
+        let mut offset = 0;
+        for chunk in &self.memory {
+            contents.as_mut_slice()[offset..offset + chunk.len()].copy_from_slice(chunk.as_slice());
+            offset += chunk.len();
+        }
+        if let Some(spool) = self.spool.as_mut() {
+            spool.read_into(&mut contents.as_mut_slice()[offset..])?;
+        }
+        Ok(contents)
+    }
+
+    /// Wipe the buffered audio from memory and disk
+    pub fn purge(&mut self) -> VoiceResult<()> {
+        // SecureMemory zeroizes itself on drop
+        self.memory.clear();
+        self.memory_bytes = 0;
+        if let Some(spool) = self.spool.take() {
+            spool.destroy()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SessionAudioBuffer {
+    fn drop(&mut self) {
+        if let Err(e) = self.purge() {
+            tracing::error!(session_id = %self.session_id, error = %e, "Failed to purge dictation audio");
+        }
+    }
+}
+
+/// Encrypted on-disk continuation of a session's audio. Each record is a
+/// little-endian `u32` length followed by that many bytes of ciphertext.
+struct Spool {
+    path: PathBuf,
+    file: File,
+    encryptor: Aes256GcmEncryptor,
+    plaintext_bytes: usize,
+}
+
+impl Spool {
+    fn create(dir: &Path, session_id: Uuid) -> VoiceResult<Self> {
+        std::fs::create_dir_all(dir).map_err(secure_storage)?;
+        let path = dir.join(format!("{}.{}", session_id, SPOOL_EXTENSION));
+
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(&path).map_err(secure_storage)?;
+
+        let mut key = Aes256GcmEncryptor::generate_key();
+        let encryptor = Aes256GcmEncryptor::new(key).map_err(secure_storage);
+        key.zeroize();
+        Ok(Self {
+            path,
+            file,
+            encryptor: encryptor?,
+            plaintext_bytes: 0,
+        })
+    }
+
+    fn append(&mut self, chunk: &[u8]) -> VoiceResult<()> {
+        let ciphertext = self.encryptor.encrypt(chunk).map_err(secure_storage)?;
+        let length = u32::try_from(ciphertext.len())
+            .map_err(|_| VoiceError::SecureStorage("Audio chunk too large to spool".to_string()))?;
+        self.file.seek(SeekFrom::End(0)).map_err(secure_storage)?;
+        self.file.write_all(&length.to_le_bytes()).map_err(secure_storage)?;
+        self.file.write_all(&ciphertext).map_err(secure_storage)?;
+        self.plaintext_bytes += chunk.len();
+        Ok(())
+    }
+
+    fn read_into(&mut self, out: &mut [u8]) -> VoiceResult<()> {
+        self.file.seek(SeekFrom::Start(0)).map_err(secure_storage)?;
+        let mut offset = 0;
+        let mut length = [0u8; 4];
+        while offset < out.len() {
+            self.file.read_exact(&mut length).map_err(secure_storage)?;
+            let mut ciphertext = vec![0u8; u32::from_le_bytes(length) as usize];
+            self.file.read_exact(&mut ciphertext).map_err(secure_storage)?;
+            let chunk = Zeroizing::new(self.encryptor.decrypt(&ciphertext).map_err(secure_storage)?);
+            let end = offset + chunk.len();
+            if end > out.len() {
+                return Err(VoiceError::SecureStorage("Spooled audio is longer than recorded".to_string()));
+            }
+            out[offset..end].copy_from_slice(&chunk);
+            offset = end;
+        }
+        Ok(())
+    }
+
+    fn destroy(self) -> VoiceResult<()> {
+        let Spool { path, file, .. } = self;
+        overwrite_and_remove(&path, Some(file))
+    }
+}
+
+/// Delete spool files left behind by a process that crashed. Returns how
+/// many were removed.
+pub fn sweep_orphaned_spools(dir: &Path) -> VoiceResult<usize> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(secure_storage(e)),
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry.map_err(secure_storage)?.path();
+        if path.extension().is_some_and(|extension| extension == SPOOL_EXTENSION) {
+            overwrite_and_remove(&path, None)?;
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        tracing::warn!(removed, dir = %dir.display(), "Removed dictation audio spools left by a previous run");
+    }
+    Ok(removed)
+}
+
+/// Overwrite a file with zeros, sync it and delete it
+fn overwrite_and_remove(path: &Path, file: Option<File>) -> VoiceResult<()> {
+    let mut file = match file {
+        Some(file) => file,
+        None => OpenOptions::new().write(true).open(path).map_err(secure_storage)?,
+    };
+    let length = file.metadata().map_err(secure_storage)?.len();
+    file.seek(SeekFrom::Start(0)).map_err(secure_storage)?;
+    let zeros = [0u8; 8192];
+    let mut remaining = length;
+    while remaining > 0 {
+        let n = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n]).map_err(secure_storage)?;
+        remaining -= n as u64;
+    }
+    file.sync_all().map_err(secure_storage)?;
+    drop(file);
+    std::fs::remove_file(path).map_err(secure_storage)
+}
+
+fn secure_storage(error: impl std::fmt::Display) -> VoiceError {
+    VoiceError::SecureStorage(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(memory_limit: usize) -> AudioBufferConfig {
+        AudioBufferConfig {
+            memory_limit,
+            spool_dir: std::env::temp_dir().join(format!("voice-spool-{}", Uuid::new_v4())),
+        }
+    }
+
+    #[test]
+    fn test_spooled_audio_is_encrypted_and_read_back_in_order() {
+        let mut buffer = SessionAudioBuffer::new(Uuid::new_v4(), config(8));
+        buffer.append(b"chest").unwrap();
+        buffer.append(b" pain radiating").unwrap();
+        buffer.append(b" to left arm").unwrap();
+        assert!(buffer.is_spooled());
+
+        let spooled = std::fs::read(buffer.spool_path().unwrap()).unwrap();
+        assert!(!spooled.windows(4).any(|window| window == b"pain"));
+        assert_eq!(buffer.contents().unwrap().as_slice(), b"chest pain radiating to left arm");
+    }
+
+    #[test]
+    fn test_purge_and_drop_remove_spool() {
+        let mut buffer = SessionAudioBuffer::new(Uuid::new_v4(), config(0));
+        buffer.append(b"audio").unwrap();
+        let path = buffer.spool_path().unwrap().to_path_buf();
+        assert!(path.exists());
+        buffer.purge().unwrap();
+        assert!(!path.exists());
+        assert!(buffer.is_empty());
+
+        let mut buffer = SessionAudioBuffer::new(Uuid::new_v4(), config(0));
+        buffer.append(b"audio").unwrap();
+        let path = buffer.spool_path().unwrap().to_path_buf();
+        drop(buffer);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_sweep_removes_spools_left_by_a_crash() {
+        let config = config(0);
+        let mut buffer = SessionAudioBuffer::new(Uuid::new_v4(), config.clone());
+        buffer.append(b"audio").unwrap();
+        let path = buffer.spool_path().unwrap().to_path_buf();
+        // A crash skips the destructor
+        std::mem::forget(buffer);
+
+        assert_eq!(sweep_orphaned_spools(&config.spool_dir).unwrap(), 1);
+        assert!(!path.exists());
+    }
+}
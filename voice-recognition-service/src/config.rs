@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use crate::error::{VoiceError, VoiceResult};
 
 /// Voice recognition provider type
@@ -86,6 +87,27 @@ pub struct VoiceConfig {
     pub default_channels: u16,
     pub max_audio_duration_ms: u64,
     pub voice_enabled: bool,
+    /// Session audio kept in locked memory before spooling to disk
+    #[serde(default = "default_audio_memory_limit")]
+    pub audio_memory_limit_bytes: usize,
+    /// Directory for encrypted audio spools; used by this process only
+    #[serde(default = "default_audio_spool_dir")]
+    pub audio_spool_dir: PathBuf,
+    /// Sessions without audio for longer are finalized and their audio purged
+    #[serde(default = "default_session_timeout_secs")]
+    pub session_timeout_secs: u64,
+}
+
+fn default_audio_memory_limit() -> usize {
+    16 * 1024 * 1024
+}
+
+fn default_audio_spool_dir() -> PathBuf {
+    std::env::temp_dir().join("rustcare-voice-spool")
+}
+
+fn default_session_timeout_secs() -> u64 {
+    900
 }
 
 impl VoiceConfig {
@@ -121,6 +143,20 @@ impl VoiceConfig {
             .and_then(|s| s.parse().ok())
             .unwrap_or(300000); // 5 minutes
 
+        let audio_memory_limit_bytes = std::env::var("VOICE_AUDIO_MEMORY_LIMIT_BYTES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(default_audio_memory_limit);
+
+        let audio_spool_dir = std::env::var("VOICE_AUDIO_SPOOL_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| default_audio_spool_dir());
+
+        let session_timeout_secs = std::env::var("VOICE_SESSION_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(default_session_timeout_secs);
+
         // Detect provider from environment
         let provider = if let Ok(provider_type) = std::env::var("VOICE_PROVIDER") {
             match provider_type.to_lowercase().as_str() {
@@ -197,6 +233,9 @@ impl VoiceConfig {
             default_channels,
             max_audio_duration_ms,
             voice_enabled,
+            audio_memory_limit_bytes,
+            audio_spool_dir,
+            session_timeout_secs,
         })
     }
 }
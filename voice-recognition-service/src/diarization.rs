@@ -0,0 +1,53 @@
+//! Speaker diarization for providers that only return text and timestamps
+//!
+//! Whisper transcribes but cannot tell speakers apart, so a separate
+//! diarizer (e.g. a self-hosted pyannote service) finds who spoke when and
+//! its turns are matched to the transcript segments by time.
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use crate::error::VoiceResult;
+use crate::transcription::{TranscriptSegment, SOLO_SPEAKER};
+
+/// A stretch of audio attributed to one speaker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeakerTurn {
+    pub speaker: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Finds who spoke when in a recording
+#[async_trait]
+pub trait Diarizer: Send + Sync {
+    /// Speaker turns in time order
+    async fn diarize(
+        &self,
+        audio_data: &[u8],
+        sample_rate: u32,
+        max_speakers: Option<u32>,
+    ) -> VoiceResult<Vec<SpeakerTurn>>;
+}
+
+/// Label each segment with the speaker whose turns overlap it the most.
+/// Segments no turn overlaps keep the solo speaker label.
+pub fn assign_speakers(segments: &mut [TranscriptSegment], turns: &[SpeakerTurn]) {
+    for segment in segments {
+        let mut overlaps: Vec<(&str, u64)> = Vec::new();
+        for turn in turns {
+            let start = segment.start_ms.max(turn.start_ms);
+            let end = segment.end_ms.min(turn.end_ms);
+            if end <= start {
+                continue;
+            }
+            match overlaps.iter_mut().find(|(speaker, _)| *speaker == turn.speaker) {
+                Some((_, total)) => *total += end - start,
+                None => overlaps.push((&turn.speaker, end - start)),
+            }
+        }
+        segment.speaker = overlaps
+            .iter()
+            .max_by_key(|(_, total)| *total)
+            .map_or(SOLO_SPEAKER, |(speaker, _)| speaker)
+            .to_string();
+    }
+}
@@ -17,6 +17,9 @@ pub enum VoiceError {
     #[error("Transcription error: {0}")]
     Transcription(String),
 
+    #[error("Secure audio storage error: {0}")]
+    SecureStorage(String),
+
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
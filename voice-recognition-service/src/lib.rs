@@ -22,7 +22,9 @@
 //! - Medical vocabulary support (SNOMED, ICD-10, CPT codes)
 //! - Voice commands for EMR navigation
 //! - Real-time transcription
-//! - HIPAA-compliant audio processing
+//! - Speaker diarization for shared-room dictation
+//! - HIPAA-compliant audio processing, with session audio kept in locked
+//!   memory or an encrypted spool and wiped when the session ends
 //! - Audio format conversion and normalization
 //! - Configurable provider selection
 //!
@@ -55,10 +57,14 @@ pub mod providers;
 pub mod error;
 pub mod transcription;
 pub mod medical_vocabulary;
+pub mod diarization;
+pub mod audio_buffer;
 
 pub use service::*;
 pub use config::*;
 pub use error::*;
 pub use transcription::*;
 pub use medical_vocabulary::*;
+pub use diarization::*;
+pub use audio_buffer::*;
 
@@ -21,6 +21,10 @@ impl VoiceProviderTrait for AwsProvider {
         Err(VoiceError::Provider("AWS Transcribe Medical not yet implemented".to_string()))
     }
 
+    fn supports_diarization(&self) -> bool {
+        true
+    }
+
     async fn start_session(&self, _user_id: &str) -> VoiceResult<String> {
         Err(VoiceError::Provider("AWS session management not yet implemented".to_string()))
     }
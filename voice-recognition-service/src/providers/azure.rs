@@ -21,6 +21,10 @@ impl VoiceProviderTrait for AzureProvider {
         Err(VoiceError::Provider("Azure Speech Service not yet implemented".to_string()))
     }
 
+    fn supports_diarization(&self) -> bool {
+        true
+    }
+
     async fn start_session(&self, _user_id: &str) -> VoiceResult<String> {
         Err(VoiceError::Provider("Azure session management not yet implemented".to_string()))
     }
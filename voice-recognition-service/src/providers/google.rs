@@ -26,6 +26,10 @@ impl VoiceProviderTrait for GoogleProvider {
         Err(VoiceError::Provider("Google Cloud Speech not yet implemented".to_string()))
     }
 
+    fn supports_diarization(&self) -> bool {
+        true
+    }
+
     async fn start_session(&self, _user_id: &str) -> VoiceResult<String> {
         Err(VoiceError::Provider("Google session management not yet implemented".to_string()))
     }
@@ -9,7 +9,7 @@ pub mod azure;
 use async_trait::async_trait;
 use crate::error::VoiceResult;
 use crate::config::VoiceProvider;
-use crate::transcription::TranscriptionResult;
+use crate::transcription::{TranscriptionOptions, TranscriptionResult};
 
 /// Trait for voice recognition providers
 #[async_trait]
@@ -17,6 +17,23 @@ pub trait VoiceProviderTrait: Send + Sync {
     /// Transcribe audio data to text
     async fn transcribe(&self, audio_data: &[u8], sample_rate: u32) -> VoiceResult<TranscriptionResult>;
     
+    /// Transcribe with per-request options. Providers that label speakers
+    /// themselves override this and [`supports_diarization`](Self::supports_diarization);
+    /// the rest ignore the diarization option.
+    async fn transcribe_with_options(
+        &self,
+        audio_data: &[u8],
+        sample_rate: u32,
+        _options: &TranscriptionOptions,
+    ) -> VoiceResult<TranscriptionResult> {
+        self.transcribe(audio_data, sample_rate).await
+    }
+    
+    /// Whether the provider labels speakers itself
+    fn supports_diarization(&self) -> bool {
+        false
+    }
+    
     /// Start a continuous dictation session
     async fn start_session(&self, user_id: &str) -> VoiceResult<String>; // returns session_id
     
@@ -1,19 +1,31 @@
 use crate::config::VoiceConfig;
 use crate::error::{VoiceError, VoiceResult};
 use crate::providers::{VoiceProviderTrait, create_provider};
-use crate::transcription::{TranscriptionResult, DictationSession, SessionStatus};
+use crate::transcription::{FinalizedSession, TranscriptionOptions, TranscriptionResult, DictationSession, SessionStatus};
+use crate::diarization::{assign_speakers, Diarizer};
+use crate::audio_buffer::{sweep_orphaned_spools, AudioBufferConfig, SessionAudioBuffer};
 use crate::medical_vocabulary::MedicalVocabulary;
 use uuid::Uuid;
 use std::collections::HashMap;
-use tracing::{info, debug};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, debug, warn};
 
 /// Voice recognition service for healthcare dictation
 pub struct VoiceService {
     config: VoiceConfig,
     provider: Box<dyn VoiceProviderTrait>,
+    diarizer: Option<Arc<dyn Diarizer>>,
     active_sessions: HashMap<Uuid, DictationSession>,
+    /// Audio captured per session; purged when the session ends
+    session_audio: HashMap<Uuid, SessionAudioBuffer>,
+    /// When recently finalized sessions ended
+    finalized_sessions: HashMap<Uuid, chrono::DateTime<chrono::Utc>>,
 }
 
+/// How long a finalized session is remembered, so stopping it is a no-op
+const FINALIZED_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
 impl VoiceService {
     /// Create a new voice recognition service
     pub fn new(config: VoiceConfig) -> VoiceResult<Self> {
@@ -23,16 +35,37 @@ impl VoiceService {
 
         let provider = create_provider(&config.provider)?;
 
+        // Spools from a crashed run are unreadable without their keys, but
+        // are still removed rather than left on disk
+        sweep_orphaned_spools(&config.audio_spool_dir)?;
+
         Ok(Self {
             config,
             provider,
+            diarizer: None,
             active_sessions: HashMap::new(),
+            session_audio: HashMap::new(),
+            finalized_sessions: HashMap::new(),
         })
     }
 
-    /// Start a new dictation session
-    pub async fn start_dictation_session(&mut self, user_id: Uuid, provider_name: Option<String>) -> VoiceResult<DictationSession> {
-        let session = DictationSession::new(user_id, provider_name.unwrap_or_else(|| "default".to_string()));
+    /// Label speakers with this diarizer for providers that cannot, such
+    /// as Whisper
+    pub fn with_diarizer(mut self, diarizer: Arc<dyn Diarizer>) -> Self {
+        self.diarizer = Some(diarizer);
+        self
+    }
+
+    /// Start a new dictation session. `inactivity_timeout` overrides the
+    /// configured time without audio after which the session is finalized.
+    pub async fn start_dictation_session(
+        &mut self,
+        user_id: Uuid,
+        provider_name: Option<String>,
+        inactivity_timeout: Option<Duration>,
+    ) -> VoiceResult<DictationSession> {
+        let mut session = DictationSession::new(user_id, provider_name.unwrap_or_else(|| "default".to_string()));
+        session.inactivity_timeout_secs = inactivity_timeout.map(|timeout| timeout.as_secs());
         
         info!(session_id = %session.id, user_id = %user_id, "Starting dictation session");
         
@@ -44,29 +77,172 @@ impl VoiceService {
         Ok(session)
     }
 
-    /// Stop a dictation session
+    /// Stop a dictation session. Its audio is purged even if the provider
+    /// fails to stop. Stopping a session that was already finalized, for
+    /// example after it timed out, does nothing.
     pub async fn stop_dictation_session(&mut self, session_id: Uuid) -> VoiceResult<()> {
-        if let Some(mut session) = self.active_sessions.remove(&session_id) {
+        if self.active_sessions.contains_key(&session_id) {
             info!(session_id = %session_id, "Stopping dictation session");
-            
-            session.status = SessionStatus::Completed;
-            session.updated_at = chrono::Utc::now();
-            
-            // Stop provider session
-            self.provider.stop_session(&session_id.to_string()).await?;
-            
+            self.finalize_session(session_id, SessionStatus::Completed, &TranscriptionOptions::default())
+                .await
+                .map(|_| ())
+        } else if self.finalized_sessions.contains_key(&session_id) {
+            debug!(session_id = %session_id, "Dictation session already finalized");
             Ok(())
         } else {
             Err(VoiceError::Provider(format!("Session {} not found", session_id)))
         }
     }
 
+    /// Buffer a chunk of audio captured in an active session
+    pub fn append_session_audio(&mut self, session_id: Uuid, chunk: &[u8]) -> VoiceResult<()> {
+        let session = self.active_sessions.get_mut(&session_id)
+            .ok_or_else(|| VoiceError::Provider(format!("Session {} not found", session_id)))?;
+        session.updated_at = chrono::Utc::now();
+        session.last_audio_at = session.updated_at;
+
+        let buffer_config = AudioBufferConfig {
+            memory_limit: self.config.audio_memory_limit_bytes,
+            spool_dir: self.config.audio_spool_dir.clone(),
+        };
+        self.session_audio
+            .entry(session_id)
+            .or_insert_with(|| SessionAudioBuffer::new(session_id, buffer_config))
+            .append(chunk)
+    }
+
+    /// Transcribe everything buffered for a session so far
+    pub async fn transcribe_session(&mut self, session_id: Uuid, options: &TranscriptionOptions) -> VoiceResult<TranscriptionResult> {
+        let audio = match self.session_audio.get_mut(&session_id) {
+            Some(buffer) => buffer.contents()?,
+            None => return Err(VoiceError::AudioProcessing(format!("No audio buffered for session {}", session_id))),
+        };
+        self.transcribe_audio_with_options(audio.as_slice(), self.config.default_sample_rate, options).await
+    }
+
+    /// Wipe a session's buffered audio from memory and disk. Succeeds when
+    /// there is nothing left to remove, so it is safe to call repeatedly.
+    pub fn purge_session_data(&mut self, session_id: Uuid) -> VoiceResult<()> {
+        if let Some(mut buffer) = self.session_audio.remove(&session_id) {
+            buffer.purge()?;
+            info!(session_id = %session_id, "Purged dictation audio");
+        }
+        Ok(())
+    }
+
+    /// Finalize every session that has received no audio for longer than
+    /// its inactivity timeout: transcribe what it buffered, release the
+    /// provider session and purge the audio. Call this periodically.
+    pub async fn finalize_idle_sessions(&mut self) -> Vec<FinalizedSession> {
+        let now = chrono::Utc::now();
+        let default_timeout = self.config.session_timeout_secs;
+        let idle: Vec<Uuid> = self.active_sessions
+            .values()
+            .filter(|session| {
+                let timeout = session.inactivity_timeout_secs.unwrap_or(default_timeout);
+                now - session.last_audio_at > chrono::Duration::seconds(timeout as i64)
+            })
+            .map(|session| session.id)
+            .collect();
+
+        let mut finalized = Vec::with_capacity(idle.len());
+        for session_id in idle {
+            match self.finalize_session(session_id, SessionStatus::TimedOut, &TranscriptionOptions::default()).await {
+                Ok(session) => finalized.push(session),
+                Err(e) => warn!(session_id = %session_id, error = %e, "Failed to finalize idle dictation session"),
+            }
+        }
+
+        // Finalized sessions are remembered only long enough to make a late
+        // stop a no-op
+        let retention = chrono::Duration::seconds(FINALIZED_RETENTION.as_secs() as i64);
+        self.finalized_sessions.retain(|_, finalized_at| now - *finalized_at < retention);
+        finalized
+    }
+
+    /// End a session: transcribe what it buffered, purge its audio and stop
+    /// the provider session. The audio is purged and the session finalized
+    /// even when transcribing or stopping the provider fails.
+    async fn finalize_session(
+        &mut self,
+        session_id: Uuid,
+        status: SessionStatus,
+        options: &TranscriptionOptions,
+    ) -> VoiceResult<FinalizedSession> {
+        let mut session = self.active_sessions.remove(&session_id)
+            .ok_or_else(|| VoiceError::Provider(format!("Session {} not found", session_id)))?;
+        session.status = status;
+        session.updated_at = chrono::Utc::now();
+        self.finalized_sessions.insert(session_id, session.updated_at);
+
+        let transcription = if self.session_audio.get(&session_id).is_some_and(|buffer| !buffer.is_empty()) {
+            match self.transcribe_session(session_id, options).await {
+                Ok(transcription) => Some(transcription),
+                Err(e) => {
+                    warn!(session_id = %session_id, error = %e, "Failed to transcribe audio of finalized session");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let purged = self.purge_session_data(session_id);
+        let stopped = self.provider.stop_session(&session_id.to_string()).await;
+
+        let reason = match session.status {
+            SessionStatus::TimedOut => "inactivity",
+            _ => "stopped",
+        };
+        info!(
+            target: "audit",
+            session_id = %session_id,
+            user_id = %session.user_id,
+            reason,
+            audio_purged = purged.is_ok(),
+            transcribed = transcription.is_some(),
+            "Dictation session closed"
+        );
+
+        purged?;
+        stopped?;
+        Ok(FinalizedSession { session, transcription })
+    }
+
+    /// Purge the audio of every session, for shutdown
+    pub fn purge_all_session_data(&mut self) -> VoiceResult<()> {
+        let sessions: Vec<Uuid> = self.session_audio.keys().copied().collect();
+        for session_id in sessions {
+            self.purge_session_data(session_id)?;
+        }
+        Ok(())
+    }
+
     /// Transcribe audio data
     pub async fn transcribe_audio(&self, audio_data: &[u8], sample_rate: u32) -> VoiceResult<TranscriptionResult> {
-        debug!(audio_size = audio_data.len(), sample_rate = sample_rate, "Transcribing audio");
+        self.transcribe_audio_with_options(audio_data, sample_rate, &TranscriptionOptions::default()).await
+    }
+
+    /// Transcribe audio data, labelling speakers if requested. The result
+    /// always carries segments; without diarization there is one speaker.
+    pub async fn transcribe_audio_with_options(
+        &self,
+        audio_data: &[u8],
+        sample_rate: u32,
+        options: &TranscriptionOptions,
+    ) -> VoiceResult<TranscriptionResult> {
+        debug!(audio_size = audio_data.len(), sample_rate = sample_rate, diarization = options.diarization, "Transcribing audio");
 
         // Transcribe using provider
-        let mut result = self.provider.transcribe(audio_data, sample_rate).await?;
+        let mut result = self.provider.transcribe_with_options(audio_data, sample_rate, options).await?;
+        result.ensure_segments();
+
+        if options.diarization && !self.provider.supports_diarization() {
+            if let Some(diarizer) = &self.diarizer {
+                let turns = diarizer.diarize(audio_data, sample_rate, options.max_speakers).await?;
+                assign_speakers(&mut result.segments, &turns);
+            }
+        }
 
         // Apply medical vocabulary enhancement if enabled
         if self.config.enable_medical_vocabulary {
@@ -84,6 +260,12 @@ impl VoiceService {
         // Detect medical terms
         let detected_terms = MedicalVocabulary::detect_terms(&expanded_text);
         
+        // Segments carry the same expansions as the full text
+        let mut segments = result.segments;
+        for segment in &mut segments {
+            segment.text = MedicalVocabulary::expand_abbreviations(&segment.text);
+        }
+
         // Update metadata
         let mut metadata = result.metadata;
         metadata.medical_vocabulary_used = true;
@@ -92,6 +274,7 @@ impl VoiceService {
         Ok(TranscriptionResult {
             text: expanded_text,
             metadata,
+            segments,
             ..result
         })
     }
@@ -13,6 +13,72 @@ pub struct TranscriptionResult {
     pub duration_ms: u64,
     pub created_at: DateTime<Utc>,
     pub metadata: TranscriptionMetadata,
+    /// Transcript split by speaker, in time order. A solo dictation has a
+    /// single segment covering the whole recording.
+    #[serde(default)]
+    pub segments: Vec<TranscriptSegment>,
+}
+
+/// Label of the speaker in a recording with only one
+pub const SOLO_SPEAKER: &str = "speaker_1";
+
+impl TranscriptionResult {
+    /// Attribute the whole transcript to one speaker if the provider did
+    /// not segment it
+    pub fn ensure_segments(&mut self) {
+        if self.segments.is_empty() {
+            self.segments.push(TranscriptSegment {
+                speaker: SOLO_SPEAKER.to_string(),
+                start_ms: 0,
+                end_ms: self.duration_ms,
+                text: self.text.clone(),
+                confidence: self.confidence,
+            });
+        }
+    }
+
+    /// Distinct speaker labels, in order of first appearance
+    pub fn speakers(&self) -> Vec<&str> {
+        let mut speakers: Vec<&str> = Vec::new();
+        for segment in &self.segments {
+            if !speakers.contains(&segment.speaker.as_str()) {
+                speakers.push(&segment.speaker);
+            }
+        }
+        speakers
+    }
+}
+
+/// Part of a transcript spoken by one speaker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    /// Label such as `speaker_1`; stable within one result only
+    pub speaker: String,
+    /// Offsets from the start of the audio
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    pub confidence: f32,
+}
+
+/// Per-request transcription settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranscriptionOptions {
+    /// Label segments by speaker, for rooms with more than one person
+    /// talking. Providers without support return single-speaker segments.
+    #[serde(default)]
+    pub diarization: bool,
+    /// Upper bound on the number of speakers, when known
+    #[serde(default)]
+    pub max_speakers: Option<u32>,
+}
+
+impl TranscriptionOptions {
+    pub fn with_diarization(mut self, max_speakers: Option<u32>) -> Self {
+        self.diarization = true;
+        self.max_speakers = max_speakers;
+        self
+    }
 }
 
 /// Metadata associated with transcription
@@ -59,6 +125,12 @@ pub struct DictationSession {
     pub provider: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// When audio last arrived, or when the session started
+    #[serde(default = "Utc::now")]
+    pub last_audio_at: DateTime<Utc>,
+    /// Overrides the service's inactivity timeout for this session
+    #[serde(default)]
+    pub inactivity_timeout_secs: Option<u64>,
 }
 
 /// Dictation session status
@@ -68,6 +140,8 @@ pub enum SessionStatus {
     Active,
     Paused,
     Completed,
+    /// Finalized automatically after receiving no audio for too long
+    TimedOut,
     Cancelled,
     Error,
 }
@@ -83,7 +157,17 @@ impl DictationSession {
             provider,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            last_audio_at: Utc::now(),
+            inactivity_timeout_secs: None,
         }
     }
 }
 
+/// A session that ended, with whatever audio it had transcribed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalizedSession {
+    pub session: DictationSession,
+    /// `None` when no audio was buffered or transcribing it failed
+    pub transcription: Option<TranscriptionResult>,
+}
+
@@ -1,2 +1,513 @@
-// Workflow conditions stub
-pub struct WorkflowConditions {}
\ No newline at end of file
+// Task conditions and input templates
+use std::collections::HashSet;
+use std::iter::Peekable;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::error::{Result, WorkflowError};
+use crate::task::Task;
+
+/// Values conditions and input templates can refer to
+///
+/// `input.<path>` reads the workflow input and `outputs.<task>.<path>` the
+/// output of a task that has already run. Array elements are addressed by
+/// index, as in `input.allergies.0`.
+#[derive(Debug, Clone, Copy)]
+pub struct Scope<'a> {
+    pub input: &'a Value,
+    pub outputs: &'a Map<String, Value>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(input: &'a Value, outputs: &'a Map<String, Value>) -> Self {
+        Self { input, outputs }
+    }
+
+    pub fn lookup(&self, path: &str) -> Option<&'a Value> {
+        let mut segments = path.split('.');
+        let mut value = match segments.next()? {
+            "input" => self.input,
+            "outputs" => self.outputs.get(segments.next()?)?,
+            _ => return None,
+        };
+        for segment in segments {
+            value = match value {
+                Value::Object(fields) => fields.get(segment)?,
+                Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// A boolean expression deciding whether a task runs
+///
+/// ```text
+/// input.ward != null && (input.age >= 18 || outputs.consent.granted == true)
+/// ```
+///
+/// Operands are variable paths (see [`Scope`]), `null`, `true`, `false`,
+/// numbers and quoted strings. `==` and `!=` compare any two values, `<`,
+/// `<=`, `>` and `>=` compare numbers or strings, and `&&`, `||` and `!`
+/// combine booleans. Referring to a variable that is not defined is an
+/// error unless it is compared with `null`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self> {
+        let invalid = |message: String| WorkflowError::InvalidExpression {
+            expression: source.to_string(),
+            message,
+        };
+        let mut parser = Parser {
+            tokens: tokenize(source).map_err(invalid)?.into_iter().peekable(),
+        };
+        let expr = parser.or().map_err(invalid)?;
+        if let Some(token) = parser.tokens.next() {
+            return Err(invalid(format!("unexpected {} after the end of the expression", token.describe())));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn evaluate(&self, scope: &Scope<'_>) -> Result<bool> {
+        boolean(&self.expr, scope).map_err(|failure| match failure {
+            Failure::Undefined(variable) => WorkflowError::MissingVariable {
+                expression: self.source.clone(),
+                variable,
+            },
+            Failure::Invalid(message) => WorkflowError::InvalidExpression {
+                expression: self.source.clone(),
+                message,
+            },
+        })
+    }
+}
+
+impl FromStr for Condition {
+    type Err = WorkflowError;
+
+    fn from_str(source: &str) -> Result<Self> {
+        Self::parse(source)
+    }
+}
+
+/// Substitute `${path}` references in task inputs
+///
+/// A string that is nothing but one reference takes the referenced value as
+/// is, so `"${input.age}"` stays a number. References inside longer strings
+/// are written out as text.
+pub fn resolve_inputs(inputs: &Map<String, Value>, scope: &Scope<'_>) -> Result<Map<String, Value>> {
+    inputs
+        .iter()
+        .map(|(key, value)| Ok((key.clone(), resolve(value, scope)?)))
+        .collect()
+}
+
+/// Resolve a template such as an idempotency key to a string
+pub fn resolve_template(template: &str, scope: &Scope<'_>) -> Result<String> {
+    match resolve_text(template, scope)? {
+        Value::String(text) => Ok(text),
+        value => Ok(value.to_string()),
+    }
+}
+
+/// Inputs for `task` when it should run, `None` when it is skipped
+///
+/// A task is skipped when its condition does not hold, or when every task it
+/// depends on was skipped so that a branch not taken is skipped as a whole.
+pub(crate) fn prepare(
+    task: &Task,
+    scope: &Scope<'_>,
+    skipped: &HashSet<String>,
+) -> Result<Option<Map<String, Value>>> {
+    if !task.depends_on.is_empty() && task.depends_on.iter().all(|dependency| skipped.contains(dependency)) {
+        return Ok(None);
+    }
+    if let Some(condition) = &task.condition {
+        if !Condition::parse(condition)?.evaluate(scope)? {
+            return Ok(None);
+        }
+    }
+    resolve_inputs(&task.inputs, scope).map(Some)
+}
+
+fn resolve(value: &Value, scope: &Scope<'_>) -> Result<Value> {
+    match value {
+        Value::String(text) => resolve_text(text, scope),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| resolve(item, scope))
+            .collect::<Result<_>>()
+            .map(Value::Array),
+        Value::Object(fields) => resolve_inputs(fields, scope).map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+fn resolve_text(text: &str, scope: &Scope<'_>) -> Result<Value> {
+    let lookup = |path: &str| {
+        let path = path.trim();
+        scope.lookup(path).ok_or_else(|| WorkflowError::MissingVariable {
+            expression: text.to_string(),
+            variable: path.to_string(),
+        })
+    };
+
+    let whole = text
+        .strip_prefix("${")
+        .and_then(|rest| rest.strip_suffix('}'))
+        .filter(|path| !path.contains('}'));
+    if let Some(path) = whole {
+        return lookup(path).cloned();
+    }
+
+    let mut resolved = String::new();
+    let mut rest = text;
+    while let Some((before, after)) = rest.split_once("${") {
+        resolved.push_str(before);
+        let (path, remainder) = after.split_once('}').ok_or_else(|| WorkflowError::InvalidExpression {
+            expression: text.to_string(),
+            message: "'${' is never closed".to_string(),
+        })?;
+        match lookup(path)? {
+            Value::String(value) => resolved.push_str(value),
+            value => resolved.push_str(&value.to_string()),
+        }
+        rest = remainder;
+    }
+    resolved.push_str(rest);
+    Ok(Value::String(resolved))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    Variable(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Comparison, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn from_op(op: &str) -> Option<Self> {
+        match op {
+            "==" => Some(Comparison::Eq),
+            "!=" => Some(Comparison::Ne),
+            "<" => Some(Comparison::Lt),
+            "<=" => Some(Comparison::Le),
+            ">" => Some(Comparison::Gt),
+            ">=" => Some(Comparison::Ge),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(Value),
+    Variable(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Literal(value) => format!("value {value}"),
+            Token::Variable(path) => format!("variable '{path}'"),
+            Token::Op(op) => format!("'{op}'"),
+            Token::Open => "'('".to_string(),
+            Token::Close => "')'".to_string(),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '\'' | '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, next)) if next == c => break,
+                        Some((_, next)) => text.push(next),
+                        None => return Err(format!("string starting at column {} is never closed", start + 1)),
+                    }
+                }
+                Token::Literal(Value::String(text))
+            }
+            '=' | '!' | '<' | '>' | '&' | '|' => {
+                let second = chars
+                    .next_if(|&(_, next)| next == '=' || (next == c && matches!(c, '&' | '|')))
+                    .map(|(_, next)| next);
+                let op = match (c, second) {
+                    ('=', Some('=')) => "==",
+                    ('!', Some('=')) => "!=",
+                    ('!', None) => "!",
+                    ('<', Some('=')) => "<=",
+                    ('<', None) => "<",
+                    ('>', Some('=')) => ">=",
+                    ('>', None) => ">",
+                    ('&', Some('&')) => "&&",
+                    ('|', Some('|')) => "||",
+                    _ => return Err(format!("unexpected '{c}' at column {}", start + 1)),
+                };
+                Token::Op(op)
+            }
+            c if is_word_char(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, next)) = chars.next_if(|&(_, next)| is_word_char(next)) {
+                    end = index + next.len_utf8();
+                }
+                word(&source[start..end])?
+            }
+            other => return Err(format!("unexpected '{other}' at column {}", start + 1)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-')
+}
+
+fn word(word: &str) -> std::result::Result<Token, String> {
+    match word {
+        "null" => return Ok(Token::Literal(Value::Null)),
+        "true" => return Ok(Token::Literal(Value::Bool(true))),
+        "false" => return Ok(Token::Literal(Value::Bool(false))),
+        _ => {}
+    }
+    if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        return serde_json::from_str::<serde_json::Number>(word)
+            .map(|number| Token::Literal(Value::Number(number)))
+            .map_err(|_| format!("'{word}' is not a number"));
+    }
+
+    let mut segments = word.split('.');
+    let root = segments.next().unwrap_or_default();
+    if !matches!(root, "input" | "outputs") {
+        return Err(format!(
+            "unknown variable '{word}'; variables start with 'input.' or 'outputs.<task>.'"
+        ));
+    }
+    if segments.any(str::is_empty) || (root == "outputs" && !word.contains('.')) {
+        return Err(format!("'{word}' is not a valid variable path"));
+    }
+    Ok(Token::Variable(word.to_string()))
+}
+
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> std::result::Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> std::result::Result<Expr, String> {
+        let mut left = self.unary()?;
+        while self.eat("&&") {
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> std::result::Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        let left = self.operand()?;
+        let comparison = match self.tokens.peek() {
+            Some(Token::Op(op)) => Comparison::from_op(op),
+            _ => None,
+        };
+        match comparison {
+            Some(comparison) => {
+                self.tokens.next();
+                Ok(Expr::Compare(comparison, Box::new(left), Box::new(self.operand()?)))
+            }
+            None => Ok(left),
+        }
+    }
+
+    fn operand(&mut self) -> std::result::Result<Expr, String> {
+        match self.tokens.next() {
+            Some(Token::Literal(value)) => Ok(Expr::Literal(value)),
+            Some(Token::Variable(path)) => Ok(Expr::Variable(path)),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("'(' is never closed".to_string()),
+                }
+            }
+            Some(token) => Err(format!("expected a value, found {}", token.describe())),
+            None => Err("expected a value, found the end of the expression".to_string()),
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        self.tokens
+            .next_if(|token| matches!(token, Token::Op(found) if *found == op))
+            .is_some()
+    }
+}
+
+enum Failure {
+    Undefined(String),
+    Invalid(String),
+}
+
+fn boolean(expr: &Expr, scope: &Scope<'_>) -> std::result::Result<bool, Failure> {
+    match value(expr, scope)? {
+        Value::Bool(value) => Ok(value),
+        other => Err(Failure::Invalid(format!("expected true or false, found {other}"))),
+    }
+}
+
+fn value(expr: &Expr, scope: &Scope<'_>) -> std::result::Result<Value, Failure> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Variable(path) => scope
+            .lookup(path)
+            .cloned()
+            .ok_or_else(|| Failure::Undefined(path.clone())),
+        Expr::Not(inner) => Ok(Value::Bool(!boolean(inner, scope)?)),
+        Expr::And(left, right) => Ok(Value::Bool(boolean(left, scope)? && boolean(right, scope)?)),
+        Expr::Or(left, right) => Ok(Value::Bool(boolean(left, scope)? || boolean(right, scope)?)),
+        Expr::Compare(comparison, left, right) => {
+            // `x == null` and `x != null` test whether x is defined
+            let null = Expr::Literal(Value::Null);
+            let null_check = matches!(comparison, Comparison::Eq | Comparison::Ne)
+                && (**left == null || **right == null);
+            let operand = |expr: &Expr| match value(expr, scope) {
+                Err(Failure::Undefined(_)) if null_check => Ok(Value::Null),
+                other => other,
+            };
+            compare(*comparison, &operand(left)?, &operand(right)?).map(Value::Bool)
+        }
+    }
+}
+
+fn compare(comparison: Comparison, left: &Value, right: &Value) -> std::result::Result<bool, Failure> {
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().zip(b.as_f64()).and_then(|(a, b)| a.partial_cmp(&b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    let ordered = || ordering.ok_or_else(|| Failure::Invalid(format!("cannot order {left} and {right}")));
+    Ok(match comparison {
+        Comparison::Eq => ordering.map_or(left == right, |ordering| ordering.is_eq()),
+        Comparison::Ne => ordering.map_or(left != right, |ordering| ordering.is_ne()),
+        Comparison::Lt => ordered()?.is_lt(),
+        Comparison::Le => ordered()?.is_le(),
+        Comparison::Gt => ordered()?.is_gt(),
+        Comparison::Ge => ordered()?.is_ge(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn check(condition: &str, input: Value) -> Result<bool> {
+        let outputs = json!({ "triage": { "acuity": 2, "notes": ["stable"] } });
+        let outputs = outputs.as_object().unwrap();
+        Condition::parse(condition)?.evaluate(&Scope::new(&input, outputs))
+    }
+
+    #[test]
+    fn test_conditions_compare_and_combine_values() {
+        let input = json!({ "ward": "icu", "age": 67, "flags": [true] });
+        assert!(check("input.ward == 'icu' && input.age >= 65", input.clone()).unwrap());
+        assert!(check("outputs.triage.acuity < 3 || input.age < 18", input.clone()).unwrap());
+        assert!(check("!(input.flags.0 == false) && outputs.triage.notes.0 == \"stable\"", input.clone()).unwrap());
+        assert!(!check("input.age == 67.5", input.clone()).unwrap());
+        assert!(check("input.bed == null && input.ward != null", input).unwrap());
+    }
+
+    #[test]
+    fn test_undefined_variables_and_type_errors_are_reported() {
+        let missing = check("input.age > 18", json!({})).unwrap_err();
+        assert!(matches!(missing, WorkflowError::MissingVariable { variable, .. } if variable == "input.age"));
+
+        let unordered = check("input.ward > 3", json!({ "ward": "icu" })).unwrap_err();
+        assert!(matches!(unordered, WorkflowError::InvalidExpression { .. }));
+        assert!(check("input.ward", json!({ "ward": "icu" })).is_err());
+    }
+
+    #[test]
+    fn test_malformed_conditions_fail_to_parse() {
+        for (condition, message) in [
+            ("input.ward = 'icu'", "unexpected '=' at column 12"),
+            ("inputs.ward == null", "unknown variable 'inputs.ward'"),
+            ("(input.age > 3", "'(' is never closed"),
+            ("input.age >", "expected a value, found the end of the expression"),
+            ("input.ward == 'icu", "string starting at column 15 is never closed"),
+            ("input.age > 3 input.age", "unexpected variable 'input.age' after the end"),
+        ] {
+            let error = Condition::parse(condition).unwrap_err().to_string();
+            assert!(error.contains(message), "{condition}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_templates_resolve_from_input_and_outputs() {
+        let input = json!({ "patient": { "id": "p-1", "age": 67 } });
+        let outputs = json!({ "triage": { "acuity": 2 } });
+        let scope = Scope::new(&input, outputs.as_object().unwrap());
+        let inputs = json!({
+            "age": "${input.patient.age}",
+            "note": "patient ${input.patient.id} at acuity ${ outputs.triage.acuity }",
+            "tags": ["${input.patient.id}", 3],
+        });
+
+        let resolved = resolve_inputs(inputs.as_object().unwrap(), &scope).unwrap();
+        assert_eq!(
+            Value::Object(resolved),
+            json!({ "age": 67, "note": "patient p-1 at acuity 2", "tags": ["p-1", 3] })
+        );
+
+        let missing = json!({ "ward": "${input.ward}" });
+        let error = resolve_inputs(missing.as_object().unwrap(), &scope).unwrap_err();
+        assert!(matches!(error, WorkflowError::MissingVariable { variable, .. } if variable == "input.ward"));
+    }
+}
@@ -0,0 +1,383 @@
+// Declarative YAML/JSON workflow definitions
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{Result, WorkflowError};
+use crate::task::{RetryPolicy, Task, TaskType};
+use crate::workflow::Workflow;
+
+/// Serialized form of a [`Workflow`]
+///
+/// ```yaml
+/// name: patient_admission
+/// idempotency_key: "admission-${input.patient_id}"
+/// tasks:
+///   - name: register
+///     type: database_operation
+///     inputs: { table: admissions }
+///     compensation: cancel_registration
+///   - name: notify_ward
+///     type: http_request
+///     depends_on: [register]
+///     condition: input.ward != null
+///     timeout_ms: 5000
+///     retry: { max_attempts: 5, initial_backoff_ms: 200 }
+///   - name: cancel_registration
+///     type: database_operation
+/// ```
+///
+/// Unknown fields are rejected so a misspelt key is reported rather than
+/// silently ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    #[serde(default)]
+    pub tasks: Vec<TaskDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskDefinition {
+    pub name: String,
+    /// One of `http_request`, `database_operation` or `custom`
+    #[serde(rename = "type")]
+    pub task_type: String,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub inputs: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compensation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// Omitted means `true`; `false` opts the task out of the workflow's key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent: Option<bool>,
+}
+
+/// Retry settings; omitted fields keep the [`RetryPolicy`] defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_backoff_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_backoff_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multiplier: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_on_timeout: Option<bool>,
+}
+
+impl WorkflowDefinition {
+    /// Build the workflow, reporting every invalid field at once
+    pub fn into_workflow(self) -> Result<Workflow> {
+        let mut problems = Vec::new();
+        let tasks = self
+            .tasks
+            .into_iter()
+            .enumerate()
+            .map(|(index, task)| task.into_task(index, &mut problems))
+            .collect();
+        let workflow = Workflow {
+            name: self.name,
+            description: self.description,
+            idempotency_key: self.idempotency_key,
+            tasks,
+        };
+
+        problems.extend(workflow.problems());
+        if problems.is_empty() {
+            Ok(workflow)
+        } else {
+            Err(WorkflowError::ValidationFailed {
+                workflow: workflow.name,
+                problems,
+            })
+        }
+    }
+}
+
+impl From<&Workflow> for WorkflowDefinition {
+    fn from(workflow: &Workflow) -> Self {
+        Self {
+            name: workflow.name.clone(),
+            description: workflow.description.clone(),
+            idempotency_key: workflow.idempotency_key.clone(),
+            tasks: workflow.tasks.iter().map(TaskDefinition::from).collect(),
+        }
+    }
+}
+
+impl TaskDefinition {
+    fn into_task(self, index: usize, problems: &mut Vec<String>) -> Task {
+        let at = format!("tasks[{index}] ({})", self.name);
+        // An unknown type is reported, and the task kept so the rest of the
+        // workflow is still checked
+        let task_type = self.task_type.parse().unwrap_or_else(|e| {
+            problems.push(format!("{at}.type: {e}"));
+            TaskType::Custom
+        });
+        let retry_policy = self
+            .retry
+            .map(|retry| retry.into_policy(&at, problems))
+            .unwrap_or_default();
+
+        Task {
+            name: self.name,
+            task_type,
+            inputs: self.inputs,
+            depends_on: self.depends_on,
+            condition: self.condition,
+            compensation: self.compensation,
+            retry_policy,
+            timeout: self.timeout_ms.map(Duration::from_millis),
+            idempotency_key: self.idempotency_key,
+            idempotent: self.idempotent.unwrap_or(true),
+        }
+    }
+}
+
+impl From<&Task> for TaskDefinition {
+    fn from(task: &Task) -> Self {
+        Self {
+            name: task.name.clone(),
+            task_type: task.task_type.as_str().to_string(),
+            inputs: task.inputs.clone(),
+            depends_on: task.depends_on.clone(),
+            condition: task.condition.clone(),
+            compensation: task.compensation.clone(),
+            timeout_ms: task.timeout.map(duration_ms),
+            retry: Some(RetryDefinition::from(&task.retry_policy)).filter(|retry| !retry.is_default()),
+            idempotency_key: task.idempotency_key.clone(),
+            idempotent: (!task.idempotent).then_some(false),
+        }
+    }
+}
+
+impl RetryDefinition {
+    fn into_policy(self, at: &str, problems: &mut Vec<String>) -> RetryPolicy {
+        let defaults = RetryPolicy::default();
+        if self.max_attempts == Some(0) {
+            problems.push(format!("{at}.retry.max_attempts: must be at least 1"));
+        }
+        if self.multiplier == Some(0) {
+            problems.push(format!("{at}.retry.multiplier: must be at least 1"));
+        }
+        let initial = self.initial_backoff_ms.map_or(defaults.initial_backoff, Duration::from_millis);
+        let max = self.max_backoff_ms.map_or(defaults.max_backoff, Duration::from_millis);
+        if initial > max {
+            problems.push(format!("{at}.retry.initial_backoff_ms: must not exceed max_backoff_ms"));
+        }
+
+        let max_attempts = self.max_attempts.unwrap_or(defaults.max_attempts);
+        let multiplier = self.multiplier.unwrap_or(defaults.multiplier);
+        let retry_on_timeout = self.retry_on_timeout.unwrap_or(defaults.retry_on_timeout);
+        defaults
+            .with_max_attempts(max_attempts)
+            .with_backoff(initial, max)
+            .with_multiplier(multiplier)
+            .with_retry_on_timeout(retry_on_timeout)
+    }
+
+    fn is_default(&self) -> bool {
+        *self == RetryDefinition::from(&RetryPolicy::default())
+    }
+}
+
+impl From<&RetryPolicy> for RetryDefinition {
+    fn from(policy: &RetryPolicy) -> Self {
+        Self {
+            max_attempts: Some(policy.max_attempts),
+            initial_backoff_ms: Some(duration_ms(policy.initial_backoff)),
+            max_backoff_ms: Some(duration_ms(policy.max_backoff)),
+            multiplier: Some(policy.multiplier),
+            retry_on_timeout: Some(policy.retry_on_timeout),
+        }
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+impl Workflow {
+    /// Parse and validate a YAML workflow definition
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        let definition: WorkflowDefinition = serde_yaml::from_str(yaml).map_err(|e| {
+            let location = e.location();
+            WorkflowError::ParseError {
+                message: e.to_string(),
+                line: location.as_ref().map(|l| l.line()),
+                column: location.as_ref().map(|l| l.column()),
+            }
+        })?;
+        definition.into_workflow()
+    }
+
+    /// Parse and validate a JSON workflow definition
+    pub fn from_json(json: &str) -> Result<Self> {
+        let definition: WorkflowDefinition =
+            serde_json::from_str(json).map_err(|e| WorkflowError::ParseError {
+                message: e.to_string(),
+                line: Some(e.line()),
+                column: Some(e.column()),
+            })?;
+        definition.into_workflow()
+    }
+
+    /// Serialize to YAML that [`Workflow::from_yaml`] reads back unchanged
+    ///
+    /// A retry policy's custom `retryable` predicate has no declarative form
+    /// and is not written.
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(&WorkflowDefinition::from(self))
+            .map_err(|e| WorkflowError::InternalError(e.into()))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&WorkflowDefinition::from(self))
+            .map_err(|e| WorkflowError::InternalError(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMISSION: &str = r#"
+name: patient_admission
+description: Admit a patient to a ward
+tasks:
+  - name: register
+    type: database_operation
+    inputs:
+      table: admissions
+    compensation: cancel_registration
+  - name: notify_ward
+    type: http_request
+    depends_on: [register]
+    condition: input.ward != null
+    timeout_ms: 5000
+    retry:
+      max_attempts: 5
+      initial_backoff_ms: 200
+  - name: cancel_registration
+    type: database_operation
+"#;
+
+    fn problems(result: Result<Workflow>) -> Vec<String> {
+        match result {
+            Err(WorkflowError::ValidationFailed { problems, .. }) => problems,
+            other => panic!("expected validation failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_yaml_matches_the_builder() {
+        let parsed = Workflow::from_yaml(ADMISSION).unwrap();
+
+        let notify = parsed.task("notify_ward").unwrap();
+        assert_eq!(notify.task_type, TaskType::HttpRequest);
+        assert_eq!(notify.depends_on, ["register"]);
+        assert_eq!(notify.condition.as_deref(), Some("input.ward != null"));
+        assert_eq!(notify.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(notify.retry_policy.max_attempts, 5);
+        assert_eq!(notify.retry_policy.initial_backoff, Duration::from_millis(200));
+        assert_eq!(notify.retry_policy.max_backoff, RetryPolicy::default().max_backoff);
+
+        let built = Workflow::builder("patient_admission")
+            .description("Admit a patient to a ward")
+            .add_task(
+                Task::new("register", TaskType::DatabaseOperation)
+                    .with_input("table", "admissions")
+                    .with_compensation("cancel_registration"),
+            )
+            .add_task(
+                Task::new("notify_ward", TaskType::HttpRequest)
+                    .depends_on("register")
+                    .with_condition("input.ward != null")
+                    .with_timeout(Duration::from_secs(5))
+                    .with_retry_policy(
+                        RetryPolicy::default()
+                            .with_max_attempts(5)
+                            .with_backoff(Duration::from_millis(200), Duration::from_secs(60)),
+                    ),
+            )
+            .add_task(Task::new("cancel_registration", TaskType::DatabaseOperation))
+            .build();
+        assert_eq!(WorkflowDefinition::from(&parsed), WorkflowDefinition::from(&built));
+    }
+
+    #[test]
+    fn test_yaml_and_json_round_trip() {
+        let workflow = Workflow::from_yaml(ADMISSION).unwrap();
+        let yaml = workflow.to_yaml().unwrap();
+
+        let from_yaml = Workflow::from_yaml(&yaml).unwrap();
+        let from_json = Workflow::from_json(&workflow.to_json().unwrap()).unwrap();
+        assert_eq!(WorkflowDefinition::from(&from_yaml), WorkflowDefinition::from(&workflow));
+        assert_eq!(WorkflowDefinition::from(&from_json), WorkflowDefinition::from(&workflow));
+        // Only the task with a non-default policy gets a retry block
+        assert_eq!(yaml.matches("retry:").count(), 1);
+    }
+
+    #[test]
+    fn test_misspelt_fields_report_their_line() {
+        let yaml = "name: intake\ntasks:\n  - name: register\n    type: custom\n    dependson: [triage]\n";
+
+        match Workflow::from_yaml(yaml) {
+            Err(WorkflowError::ParseError { message, line, .. }) => {
+                assert_eq!(line, Some(5));
+                assert!(message.contains("unknown field `dependson`"), "{message}");
+                assert!(message.contains("tasks[0]"), "{message}");
+            }
+            other => panic!("expected parse error, got {other:?}"),
+        }
+        assert!(matches!(
+            Workflow::from_json("{\"name\": \"intake\",\n \"tasks\": [1]}"),
+            Err(WorkflowError::ParseError { line: Some(2), .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_definitions_list_every_problem() {
+        let yaml = r#"
+name: discharge
+tasks:
+  - name: summarize
+    type: llm_prompt
+    depends_on: [sign_off]
+    retry: { max_attempts: 0 }
+  - name: sign_off
+    type: custom
+    depends_on: [summarize]
+    compensation: reopen_chart
+"#;
+
+        assert_eq!(
+            problems(Workflow::from_yaml(yaml)),
+            [
+                "tasks[0] (summarize).type: unknown task type 'llm_prompt' (expected one of: http_request, database_operation, custom)",
+                "tasks[0] (summarize).retry.max_attempts: must be at least 1",
+                "tasks[1] (sign_off).compensation: 'reopen_chart' is not a task in this workflow",
+                "depends_on: tasks form a dependency cycle: summarize -> sign_off -> summarize",
+            ]
+        );
+    }
+}
@@ -0,0 +1,318 @@
+// Dry-run workflow execution
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::conditions::{self, Scope};
+use crate::engine::{missing_handler, TaskContext, TaskHandler};
+use crate::idempotency;
+use crate::task::TaskType;
+use crate::workflow::Workflow;
+
+/// What a real run would do with a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PredictedOutcome {
+    Run,
+    /// The condition does not hold, or every dependency was skipped
+    Skipped,
+    /// The condition, inputs or handler failed; see [`PredictedTask::error`]
+    Failed,
+}
+
+/// One step of a predicted execution trace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredictedTask {
+    pub task: String,
+    /// One of `http_request`, `database_operation` or `custom`
+    pub task_type: String,
+    /// Dependency depth; tasks in the same stage could run in parallel
+    pub stage: usize,
+    pub outcome: PredictedOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// Inputs with `${...}` references resolved against the sample input
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub inputs: Map<String, Value>,
+    /// What the handler reported it would do
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Value>,
+    /// Resolved key a real run would record the result under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compensation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Predicted execution trace of a workflow and everything wrong with it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DryRunReport {
+    pub workflow: String,
+    /// Forward tasks in the order a real run would reach them
+    pub trace: Vec<PredictedTask>,
+    pub problems: Vec<String>,
+}
+
+impl DryRunReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn task(&self, name: &str) -> Option<&PredictedTask> {
+        self.trace.iter().find(|step| step.task == name)
+    }
+}
+
+pub(crate) async fn predict(
+    workflow: &Workflow,
+    handlers: &HashMap<TaskType, Arc<dyn TaskHandler>>,
+    input: Value,
+) -> DryRunReport {
+    let mut report = DryRunReport {
+        workflow: workflow.name.clone(),
+        trace: Vec::new(),
+        problems: workflow.problems(),
+    };
+    // Without a valid dependency graph there is no order to walk
+    let Ok(order) = workflow.execution_order() else {
+        return report;
+    };
+
+    let compensations = workflow.compensation_tasks();
+    report.problems.extend(
+        workflow
+            .tasks
+            .iter()
+            .filter(|task| compensations.contains(task.name.as_str()) && !handlers.contains_key(&task.task_type))
+            .map(|task| missing_handler(task).to_string()),
+    );
+
+    let execution_id = Uuid::new_v4();
+    let mut outputs = Map::new();
+    let mut skipped = HashSet::new();
+    let mut stages: HashMap<&str, usize> = HashMap::new();
+    for task in order {
+        let stage = task
+            .depends_on
+            .iter()
+            .filter_map(|dependency| stages.get(dependency.as_str()))
+            .map(|stage| stage + 1)
+            .max()
+            .unwrap_or(0);
+        stages.insert(task.name.as_str(), stage);
+
+        let mut step = PredictedTask {
+            task: task.name.clone(),
+            task_type: task.task_type.as_str().to_string(),
+            stage,
+            outcome: PredictedOutcome::Run,
+            condition: task.condition.clone(),
+            inputs: Map::new(),
+            plan: None,
+            idempotency_key: None,
+            compensation: task.compensation.clone(),
+            error: None,
+        };
+
+        let scope = Scope::new(&input, &outputs);
+        let prepared = conditions::prepare(task, &scope, &skipped).and_then(|inputs| {
+            let key = inputs.as_ref().map(|_| idempotency::resolve_key(workflow, task, &scope)).transpose()?;
+            step.idempotency_key = key.flatten();
+            Ok(inputs)
+        });
+        let result = match (prepared, handlers.get(&task.task_type)) {
+            (Ok(None), _) => {
+                step.outcome = PredictedOutcome::Skipped;
+                skipped.insert(task.name.clone());
+                report.trace.push(step);
+                continue;
+            }
+            (Ok(Some(inputs)), Some(handler)) => {
+                step.inputs = inputs.clone();
+                let context = TaskContext {
+                    execution_id,
+                    workflow: workflow.name.clone(),
+                    input: input.clone(),
+                    outputs: outputs.clone(),
+                    inputs,
+                    dry_run: true,
+                    idempotency_key: step.idempotency_key.clone(),
+                };
+                handler.execute(task, &context).await
+            }
+            (Ok(Some(inputs)), None) => {
+                step.inputs = inputs;
+                Err(missing_handler(task))
+            }
+            (Err(error), _) => Err(error),
+        };
+
+        match result {
+            Ok(plan) => {
+                outputs.insert(task.name.clone(), plan.clone());
+                step.plan = Some(plan);
+            }
+            Err(error) => {
+                report.problems.push(format!("task '{}': {error}", task.name));
+                step.outcome = PredictedOutcome::Failed;
+                step.error = Some(error.to_string());
+            }
+        }
+        report.trace.push(step);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{ExecutionStatus, WorkflowEngine};
+    use crate::state_machine::TaskStatus;
+    use crate::error::{Result, WorkflowError};
+    use crate::task::Task;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Describes its request when dry-running; counts real calls otherwise
+    #[derive(Default)]
+    struct HttpHandler {
+        live_calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TaskHandler for HttpHandler {
+        async fn execute(&self, task: &Task, context: &TaskContext) -> Result<Value> {
+            if !context.dry_run {
+                self.live_calls.fetch_add(1, Ordering::SeqCst);
+            }
+            match context.inputs.get("url") {
+                Some(url) => Ok(json!({ "would_post": url, "bed": "b-12", "task": task.name })),
+                None => Err(WorkflowError::TaskError("url is required".to_string())),
+            }
+        }
+    }
+
+    async fn engine() -> (WorkflowEngine, Arc<HttpHandler>) {
+        let engine = WorkflowEngine::new().await.unwrap();
+        let handler = Arc::new(HttpHandler::default());
+        for task_type in TaskType::ALL {
+            engine.register_handler(task_type, handler.clone()).await;
+        }
+        (engine, handler)
+    }
+
+    fn admission() -> Workflow {
+        Workflow::from_yaml(
+            r#"
+name: admission
+tasks:
+  - name: register
+    type: database_operation
+    inputs: { url: "/patients/${input.patient.id}" }
+  - name: assign_icu_bed
+    type: database_operation
+    depends_on: [register]
+    condition: input.ward == 'icu'
+    inputs: { url: /beds/icu }
+  - name: page_intensivist
+    type: http_request
+    depends_on: [assign_icu_bed]
+    inputs: { url: "/pager?bed=${outputs.assign_icu_bed.bed}" }
+  - name: notify_family
+    type: http_request
+    depends_on: [register]
+    condition: input.contact != null
+    inputs: { url: /sms, to: "${input.contact}" }
+"#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_predicts_branches_without_side_effects() {
+        let (engine, handler) = engine().await;
+        let report = engine
+            .dry_run(&admission(), json!({ "patient": { "id": "p-7" }, "ward": "general" }))
+            .await;
+
+        assert!(report.is_valid(), "{:?}", report.problems);
+        for (name, stage, outcome) in [
+            ("register", 0, PredictedOutcome::Run),
+            ("assign_icu_bed", 1, PredictedOutcome::Skipped),
+            ("notify_family", 1, PredictedOutcome::Skipped),
+            ("page_intensivist", 2, PredictedOutcome::Skipped),
+        ] {
+            let step = report.task(name).unwrap();
+            assert_eq!((step.stage, step.outcome), (stage, outcome), "{name}");
+        }
+        assert_eq!(report.trace.len(), 4);
+        let register = report.task("register").unwrap();
+        assert_eq!(register.inputs["url"], "/patients/p-7");
+        assert_eq!(register.plan.as_ref().unwrap()["would_post"], "/patients/p-7");
+        assert_eq!(handler.live_calls.load(Ordering::SeqCst), 0);
+        assert!(engine.execution_stats(Uuid::new_v4()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_resolves_outputs_of_earlier_predictions() {
+        let (engine, _) = engine().await;
+        let sample = json!({ "patient": { "id": "p-7" }, "ward": "icu", "contact": "+15550100" });
+        let report = engine.dry_run(&admission(), sample).await;
+
+        assert!(report.is_valid(), "{:?}", report.problems);
+        assert!(report.trace.iter().all(|step| step.outcome == PredictedOutcome::Run));
+        assert_eq!(report.task("page_intensivist").unwrap().inputs["url"], "/pager?bed=b-12");
+        assert_eq!(report.task("notify_family").unwrap().inputs["to"], "+15550100");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_collects_every_problem() {
+        let (engine, _) = engine().await;
+        let workflow = Workflow::builder("admission")
+            .add_task(Task::new("register", TaskType::DatabaseOperation).with_input("url", "/patients/${input.patient.id}"))
+            .add_task(Task::new("triage", TaskType::Custom).depends_on("register").with_condition("input.age > 65"))
+            .add_task(Task::new("bill", TaskType::Custom).depends_on("register"))
+            .build();
+
+        let report = engine.dry_run(&workflow, json!({})).await;
+        assert_eq!(
+            report.problems,
+            [
+                "task 'register': Undefined variable 'input.patient.id' in '/patients/${input.patient.id}'",
+                "task 'triage': Undefined variable 'input.age' in 'input.age > 65'",
+                "task 'bill': Task execution failed: url is required",
+            ]
+        );
+        assert!(report.trace.iter().all(|step| step.outcome == PredictedOutcome::Failed));
+
+        let invalid = Workflow::builder("broken")
+            .add_task(Task::new("a", TaskType::Custom).with_condition("inputs.ward == 1"))
+            .build();
+        let report = engine.dry_run(&invalid, json!({})).await;
+        assert!(report.trace.is_empty());
+        assert_eq!(report.problems.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_live_runs_skip_tasks_the_same_way() {
+        let (engine, handler) = engine().await;
+        let execution = engine
+            .execute(admission(), json!({ "patient": { "id": "p-7" }, "ward": "general" }))
+            .await
+            .unwrap();
+
+        let status = |name: &str| execution.tasks.iter().find(|task| task.task == name).map(|task| task.status());
+        assert_eq!(execution.status, ExecutionStatus::Succeeded);
+        assert_eq!(status("register"), Some(TaskStatus::Succeeded));
+        for name in ["assign_icu_bed", "notify_family", "page_intensivist"] {
+            assert_eq!(status(name), Some(TaskStatus::Skipped), "{name}");
+        }
+        assert_eq!(handler.live_calls.load(Ordering::SeqCst), 1);
+    }
+}
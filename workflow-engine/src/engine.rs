@@ -1,8 +1,635 @@
-// Workflow engine implementation stub
-pub struct WorkflowEngine {}
+// Workflow engine
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::conditions::{self, Scope};
+use crate::dry_run::{self, DryRunReport};
+use crate::error::{Result, WorkflowError};
+use crate::executor::WorkflowExecutor;
+use crate::idempotency::{self, IdempotencyRecord, IdempotencyStore, InMemoryIdempotencyStore};
+use crate::state_machine::{TaskExecution, TaskStatus};
+use crate::stats::{self, ExecutionStats, TaskPhase, TaskTiming};
+use crate::task::{RetryPolicy, Task, TaskType};
+use crate::workflow::Workflow;
+
+/// Performs the work of every task of one [`TaskType`]
+///
+/// When [`TaskContext::dry_run`] is set the handler must not touch anything
+/// outside the engine: no HTTP calls, no database writes. It returns what it
+/// would have done instead, shaped like its real output so that later
+/// conditions and inputs referring to it resolve.
+#[async_trait]
+pub trait TaskHandler: Send + Sync {
+    async fn execute(&self, task: &Task, context: &TaskContext) -> Result<Value>;
+}
+
+/// The execution a task handler is running in
+#[derive(Debug, Clone)]
+pub struct TaskContext {
+    pub execution_id: Uuid,
+    pub workflow: String,
+    /// Input the workflow was started with
+    pub input: Value,
+    /// Outputs of the tasks that have finished, by task name
+    pub outputs: Map<String, Value>,
+    /// The task's inputs with `${...}` references resolved
+    pub inputs: Map<String, Value>,
+    /// Report what the task would do without causing side effects
+    pub dry_run: bool,
+    /// Key the task's result is recorded under, for handlers to pass on to
+    /// services that deduplicate requests themselves
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Succeeded,
+    /// A task failed and not every finished task could be compensated
+    Failed,
+    /// A task failed and every finished task was compensated
+    Compensated,
+}
+
+impl ExecutionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExecutionStatus::Succeeded => "succeeded",
+            ExecutionStatus::Failed => "failed",
+            ExecutionStatus::Compensated => "compensated",
+        }
+    }
+}
+
+/// Outcome of one workflow run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowExecution {
+    pub id: Uuid,
+    pub workflow: String,
+    pub status: ExecutionStatus,
+    pub outputs: Map<String, Value>,
+    /// Error of the task that stopped the run
+    pub error: Option<String>,
+    /// State of every task the run reached, in execution order
+    pub tasks: Vec<TaskExecution>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+pub struct WorkflowEngine {
+    executor: WorkflowExecutor,
+    handlers: RwLock<HashMap<TaskType, Arc<dyn TaskHandler>>>,
+    idempotency: Arc<dyn IdempotencyStore>,
+    history: RwLock<ExecutionHistory>,
+}
 
 impl WorkflowEngine {
+    /// Number of recent executions kept for [`Self::execution`] and [`Self::execution_stats`]
+    pub const RETAINED_EXECUTIONS: usize = 1000;
+
     pub async fn new() -> crate::error::Result<Self> {
-        Ok(Self {})
+        stats::describe_metrics();
+        Ok(Self {
+            executor: WorkflowExecutor::new(),
+            handlers: RwLock::new(HashMap::new()),
+            idempotency: Arc::new(InMemoryIdempotencyStore::new()),
+            history: RwLock::new(ExecutionHistory::new(Self::RETAINED_EXECUTIONS)),
+        })
+    }
+
+    /// Record idempotent task results in `store` rather than in memory
+    ///
+    /// Use [`crate::PostgresIdempotencyStore`] for keys to hold across restarts.
+    pub fn with_idempotency_store(mut self, store: Arc<dyn IdempotencyStore>) -> Self {
+        self.idempotency = store;
+        self
+    }
+
+    pub async fn register_handler(&self, task_type: TaskType, handler: Arc<dyn TaskHandler>) {
+        self.handlers.write().await.insert(task_type, handler);
+    }
+
+    /// Run `workflow` to completion
+    ///
+    /// Tasks run one at a time in dependency order, and a task whose
+    /// condition does not hold is skipped. A task whose idempotency key
+    /// already has a recorded result returns it without running. When a task
+    /// fails for good, the tasks that finished before it are compensated in
+    /// reverse order. Invalid workflows and task types without a handler are
+    /// rejected before any task runs.
+    pub async fn execute(&self, workflow: Workflow, input: Value) -> Result<WorkflowExecution> {
+        let order = workflow.execution_order()?;
+        let handlers = self.handlers.read().await.clone();
+        if let Some(task) = workflow.tasks.iter().find(|task| !handlers.contains_key(&task.task_type)) {
+            return Err(missing_handler(task));
+        }
+
+        let mut run = Run::new(&workflow, &handlers, self.idempotency.as_ref(), input);
+        for task in order {
+            if run.forward(&self.executor, task).await.is_err() {
+                run.compensate(&self.executor).await;
+                break;
+            }
+        }
+
+        let (execution, stats) = run.finish();
+        stats::record_workflow(&stats);
+        tracing::info!(
+            execution_id = %execution.id,
+            workflow = %execution.workflow,
+            status = execution.status.as_str(),
+            duration_ms = stats.duration_ms,
+            "Workflow execution finished"
+        );
+        self.history.write().await.insert(execution.clone(), stats);
+        Ok(execution)
+    }
+
+    /// Walk `workflow` against `sample_input` without side effects
+    ///
+    /// Conditions are evaluated and inputs resolved as in [`Self::execute`],
+    /// and each handler is called with [`TaskContext::dry_run`] set. Problems
+    /// are collected rather than stopping the walk, so one dry run reports
+    /// every bad condition and undefined variable. Nothing is recorded in
+    /// metrics or the execution history.
+    pub async fn dry_run(&self, workflow: &Workflow, sample_input: Value) -> DryRunReport {
+        let handlers = self.handlers.read().await.clone();
+        dry_run::predict(workflow, &handlers, sample_input).await
+    }
+
+    pub async fn execution(&self, execution_id: Uuid) -> Option<WorkflowExecution> {
+        self.history.read().await.get(execution_id).map(|(execution, _)| execution.clone())
+    }
+
+    /// Timings of every task of a recent run, including failed and compensated ones
+    pub async fn execution_stats(&self, execution_id: Uuid) -> Option<ExecutionStats> {
+        self.history.read().await.get(execution_id).map(|(_, stats)| stats.clone())
+    }
+}
+
+pub(crate) fn missing_handler(task: &Task) -> WorkflowError {
+    WorkflowError::TaskError(format!(
+        "no handler registered for task type '{}' of task '{}'",
+        task.task_type, task.name
+    ))
+}
+
+/// State of a workflow run in progress
+struct Run<'w> {
+    workflow: &'w Workflow,
+    handlers: &'w HashMap<TaskType, Arc<dyn TaskHandler>>,
+    idempotency: &'w dyn IdempotencyStore,
+    execution_id: Uuid,
+    input: Value,
+    outputs: Map<String, Value>,
+    started_at: DateTime<Utc>,
+    /// Forward tasks in the order they ran or were skipped
+    tasks: Vec<(&'w Task, TaskExecution)>,
+    skipped: HashSet<String>,
+    compensations: Vec<TaskTiming>,
+    error: Option<String>,
+    compensated: bool,
+}
+
+impl<'w> Run<'w> {
+    fn new(
+        workflow: &'w Workflow,
+        handlers: &'w HashMap<TaskType, Arc<dyn TaskHandler>>,
+        idempotency: &'w dyn IdempotencyStore,
+        input: Value,
+    ) -> Self {
+        Self {
+            workflow,
+            handlers,
+            idempotency,
+            execution_id: Uuid::new_v4(),
+            input,
+            outputs: Map::new(),
+            started_at: Utc::now(),
+            tasks: Vec::new(),
+            skipped: HashSet::new(),
+            compensations: Vec::new(),
+            error: None,
+            compensated: false,
+        }
+    }
+
+    fn scope(&self) -> Scope<'_> {
+        Scope::new(&self.input, &self.outputs)
+    }
+
+    async fn run(
+        &self,
+        executor: &WorkflowExecutor,
+        task: &Task,
+        inputs: Result<Map<String, Value>>,
+        phase: TaskPhase,
+    ) -> (TaskExecution, Result<Value>) {
+        let mut execution = TaskExecution::new(&task.name);
+        let key = idempotency::resolve_key(self.workflow, task, &self.scope());
+        let result = match inputs.and_then(|inputs| Ok((inputs, key?))) {
+            Ok((inputs, key)) => self.invoke(executor, task, &mut execution, inputs, key).await,
+            // Bad conditions, inputs and keys won't improve on retry, so the
+            // task fails on its first attempt without reaching the handler
+            Err(error) => fail_unstarted(&mut execution, error),
+        };
+        stats::record_task(&self.workflow.name, &TaskTiming::new(phase, &execution));
+        (execution, result)
+    }
+
+    async fn invoke(
+        &self,
+        executor: &WorkflowExecutor,
+        task: &Task,
+        execution: &mut TaskExecution,
+        inputs: Map<String, Value>,
+        key: Option<String>,
+    ) -> Result<Value> {
+        let Some(handler) = self.handlers.get(&task.task_type) else {
+            return fail_unstarted(execution, missing_handler(task));
+        };
+        if let Some(key) = &key {
+            match self.idempotency.get(&self.workflow.name, &task.name, key).await {
+                Ok(Some(record)) => return self.replay(task, execution, record),
+                Ok(None) => {}
+                Err(error) => return fail_unstarted(execution, error),
+            }
+        }
+
+        let context = TaskContext {
+            execution_id: self.execution_id,
+            workflow: self.workflow.name.clone(),
+            input: self.input.clone(),
+            outputs: self.outputs.clone(),
+            inputs,
+            dry_run: false,
+            idempotency_key: key.clone(),
+        };
+        let output = executor
+            .run_task(task, execution, || handler.execute(task, &context))
+            .await?;
+
+        if let Some(key) = key {
+            let record = IdempotencyRecord {
+                workflow: self.workflow.name.clone(),
+                task: task.name.clone(),
+                key,
+                output: output.clone(),
+                execution_id: self.execution_id,
+                recorded_at: Utc::now(),
+            };
+            // The side effect has happened; failing the task now would only
+            // trigger compensation for work that succeeded
+            if let Err(error) = self.idempotency.put(record).await {
+                tracing::warn!(
+                    execution_id = %self.execution_id,
+                    task = %task.name,
+                    error = %error,
+                    "Failed to record idempotent task result"
+                );
+            }
+        }
+        Ok(output)
+    }
+
+    /// Complete `task` with the output recorded under its idempotency key
+    fn replay(&self, task: &Task, execution: &mut TaskExecution, record: IdempotencyRecord) -> Result<Value> {
+        execution.start()?;
+        execution.succeed()?;
+        tracing::info!(
+            execution_id = %self.execution_id,
+            task = %task.name,
+            recorded_by = %record.execution_id,
+            "Task result replayed from idempotency key"
+        );
+        stats::record_replay(&self.workflow.name, &task.name);
+        Ok(record.output)
+    }
+
+    async fn forward(&mut self, executor: &WorkflowExecutor, task: &'w Task) -> Result<()> {
+        let inputs = match conditions::prepare(task, &self.scope(), &self.skipped) {
+            Ok(Some(inputs)) => Ok(inputs),
+            Ok(None) => return self.skip(task),
+            Err(error) => Err(error),
+        };
+        let (execution, result) = self.run(executor, task, inputs, TaskPhase::Forward).await;
+        self.tasks.push((task, execution));
+        match result {
+            Ok(output) => {
+                self.outputs.insert(task.name.clone(), output);
+                Ok(())
+            }
+            Err(error) => {
+                self.error = Some(error.to_string());
+                Err(error)
+            }
+        }
+    }
+
+    fn skip(&mut self, task: &'w Task) -> Result<()> {
+        let mut execution = TaskExecution::new(&task.name);
+        execution.skip()?;
+        tracing::debug!(execution_id = %self.execution_id, task = %task.name, "Task skipped");
+        stats::record_task(&self.workflow.name, &TaskTiming::new(TaskPhase::Forward, &execution));
+        self.tasks.push((task, execution));
+        self.skipped.insert(task.name.clone());
+        Ok(())
+    }
+
+    /// Undo finished tasks in reverse order, then close out the failed task
+    async fn compensate(&mut self, executor: &WorkflowExecutor) {
+        let mut complete = true;
+        for index in (0..self.tasks.len()).rev() {
+            let (task, ref execution) = self.tasks[index];
+            if execution.status() != TaskStatus::Succeeded {
+                continue;
+            }
+            let Some(compensation) = task.compensation.as_deref().and_then(|name| self.workflow.task(name)) else {
+                continue;
+            };
+
+            let inputs = conditions::resolve_inputs(&compensation.inputs, &self.scope());
+            let (compensation_execution, result) =
+                self.run(executor, compensation, inputs, TaskPhase::Compensation).await;
+            self.compensations
+                .push(TaskTiming::new(TaskPhase::Compensation, &compensation_execution));
+            match result {
+                Ok(_) => complete &= self.tasks[index].1.compensate().is_ok(),
+                Err(error) => {
+                    tracing::error!(
+                        execution_id = %self.execution_id,
+                        task = %task.name,
+                        compensation = %compensation.name,
+                        error = %error,
+                        "Compensation failed"
+                    );
+                    complete = false;
+                }
+            }
+        }
+
+        if complete {
+            if let Some((_, failed)) = self.tasks.last_mut() {
+                complete = failed.compensate().is_ok();
+            }
+        }
+        self.compensated = complete;
+    }
+
+    fn finish(self) -> (WorkflowExecution, ExecutionStats) {
+        let finished_at = Utc::now();
+        let status = match (&self.error, self.compensated) {
+            (None, _) => ExecutionStatus::Succeeded,
+            (Some(_), true) => ExecutionStatus::Compensated,
+            (Some(_), false) => ExecutionStatus::Failed,
+        };
+
+        let mut timings: Vec<TaskTiming> = self
+            .tasks
+            .iter()
+            .map(|(_, execution)| TaskTiming::new(TaskPhase::Forward, execution))
+            .chain(self.compensations)
+            .collect();
+        timings.sort_by_key(|timing| timing.started_at);
+
+        let stats = ExecutionStats {
+            execution_id: self.execution_id,
+            workflow: self.workflow.name.clone(),
+            status,
+            started_at: self.started_at,
+            finished_at,
+            duration_ms: stats::elapsed_ms(Some(self.started_at), Some(finished_at)),
+            tasks: timings,
+        };
+        let execution = WorkflowExecution {
+            id: self.execution_id,
+            workflow: self.workflow.name.clone(),
+            status,
+            outputs: self.outputs,
+            error: self.error,
+            tasks: self.tasks.into_iter().map(|(_, execution)| execution).collect(),
+            started_at: self.started_at,
+            finished_at,
+        };
+        (execution, stats)
+    }
+}
+
+/// Fail a task that never reached its handler on its first attempt
+fn fail_unstarted(execution: &mut TaskExecution, error: WorkflowError) -> Result<Value> {
+    // A fresh execution always accepts Pending -> Running -> Failed
+    let _ = execution
+        .start()
+        .and_then(|()| execution.fail(&error, &RetryPolicy::none()));
+    Err(error)
+}
+
+/// The most recent executions, oldest evicted first
+struct ExecutionHistory {
+    capacity: usize,
+    order: VecDeque<Uuid>,
+    runs: HashMap<Uuid, (WorkflowExecution, ExecutionStats)>,
+}
+
+impl ExecutionHistory {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            runs: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, execution: WorkflowExecution, stats: ExecutionStats) {
+        while self.order.len() >= self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => self.runs.remove(&oldest),
+                None => break,
+            };
+        }
+        self.order.push_back(execution.id);
+        self.runs.insert(execution.id, (execution, stats));
+    }
+
+    fn get(&self, execution_id: Uuid) -> Option<&(WorkflowExecution, ExecutionStats)> {
+        self.runs.get(&execution_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    /// Succeeds except for the named tasks; `flaky` fails its first attempt only
+    #[derive(Default)]
+    struct TestHandler {
+        failing: HashSet<&'static str>,
+        flaky_calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl TaskHandler for TestHandler {
+        async fn execute(&self, task: &Task, context: &TaskContext) -> Result<Value> {
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            if task.name == "flaky" && self.flaky_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(WorkflowError::TaskError("connection reset".to_string()));
+            }
+            if self.failing.contains(task.name.as_str()) {
+                return Err(WorkflowError::TaskError(format!("{} rejected", task.name)));
+            }
+            Ok(json!({ "task": task.name, "completed_before": context.outputs.len() }))
+        }
+    }
+
+    async fn engine(failing: &[&'static str]) -> WorkflowEngine {
+        let engine = WorkflowEngine::new().await.unwrap();
+        let handler = Arc::new(TestHandler {
+            failing: failing.iter().copied().collect(),
+            ..TestHandler::default()
+        });
+        for task_type in TaskType::ALL {
+            engine.register_handler(task_type, handler.clone()).await;
+        }
+        engine
+    }
+
+    fn no_backoff() -> RetryPolicy {
+        RetryPolicy::default().with_backoff(Duration::ZERO, Duration::ZERO)
+    }
+
+    fn admission() -> Workflow {
+        Workflow::builder("stats_admission")
+            .add_task(Task::new("register", TaskType::DatabaseOperation).with_compensation("unregister"))
+            .add_task(
+                Task::new("flaky", TaskType::HttpRequest)
+                    .depends_on("register")
+                    .with_retry_policy(no_backoff()),
+            )
+            .add_task(
+                Task::new("bill", TaskType::Custom)
+                    .depends_on("flaky")
+                    .with_retry_policy(no_backoff().with_max_attempts(2)),
+            )
+            .add_task(Task::new("unregister", TaskType::DatabaseOperation))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_stats_record_every_task_in_order() {
+        let engine = engine(&[]).await;
+        let execution = engine.execute(admission(), json!({ "patient": "p-1" })).await.unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Succeeded);
+        assert_eq!(execution.outputs["bill"]["completed_before"], 2);
+
+        let stats = engine.execution_stats(execution.id).await.unwrap();
+        let names: Vec<_> = stats.tasks.iter().map(|t| t.task.as_str()).collect();
+        assert_eq!(names, ["register", "flaky", "bill"]);
+        let flaky = stats.task("flaky", TaskPhase::Forward).unwrap();
+        assert_eq!((flaky.retries(), flaky.status), (1, TaskStatus::Succeeded));
+        assert_eq!(flaky.attempts[0].status, TaskStatus::Failed);
+        assert!(stats.tasks.windows(2).all(|w| w[0].finished_at <= w[1].started_at));
+        assert!(stats.duration_ms >= stats.tasks.iter().map(|t| t.duration_ms).sum::<u64>());
+
+        assert!(engine.execution_stats(Uuid::new_v4()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_runs_keep_timings_of_failed_and_compensated_tasks() {
+        let engine = engine(&["bill"]).await;
+        let execution = engine.execute(admission(), json!({})).await.unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Compensated);
+        assert_eq!(execution.error.as_deref(), Some("Task execution failed: bill rejected"));
+
+        let stats = engine.execution_stats(execution.id).await.unwrap();
+        let bill = stats.task("bill", TaskPhase::Forward).unwrap();
+        assert_eq!((bill.attempts.len(), bill.status), (2, TaskStatus::Compensated));
+        assert!(bill.attempts.iter().all(|attempt| attempt.finished_at.is_some()));
+        assert_eq!(stats.task("register", TaskPhase::Forward).unwrap().status, TaskStatus::Compensated);
+        let unregister = stats.task("unregister", TaskPhase::Compensation).unwrap();
+        assert_eq!(unregister.status, TaskStatus::Succeeded);
+        assert_eq!(stats.tasks.last().unwrap().task, "unregister");
+
+        let retries = telemetry::MetricsCollector::global()
+            .snapshot_family("workflow_task_retries_total")
+            .unwrap();
+        assert!(retries.series.iter().any(|series| series
+            .labels
+            .contains(&("task".to_string(), "bill".to_string()))));
+    }
+
+    /// Counts calls per task and echoes the key it was handed
+    #[derive(Default)]
+    struct CountingHandler {
+        calls: std::sync::Mutex<HashMap<String, u32>>,
+    }
+
+    impl CountingHandler {
+        fn calls(&self, task: &str) -> u32 {
+            self.calls.lock().unwrap().get(task).copied().unwrap_or(0)
+        }
+    }
+
+    #[async_trait]
+    impl TaskHandler for CountingHandler {
+        async fn execute(&self, task: &Task, context: &TaskContext) -> Result<Value> {
+            let mut calls = self.calls.lock().unwrap();
+            let call = calls.entry(task.name.clone()).or_default();
+            *call += 1;
+            Ok(json!({ "call": *call, "key": context.idempotency_key }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recorded_results_are_replayed_instead_of_rerunning() {
+        let engine = WorkflowEngine::new().await.unwrap();
+        let handler = Arc::new(CountingHandler::default());
+        for task_type in TaskType::ALL {
+            engine.register_handler(task_type, handler.clone()).await;
+        }
+        let billing = |name: &str| {
+            Workflow::builder(name)
+                .idempotency_key("order-${input.order_id}")
+                .add_task(Task::new("charge_payment", TaskType::HttpRequest))
+                .add_task(
+                    Task::new("poll_settlement", TaskType::HttpRequest)
+                        .depends_on("charge_payment")
+                        .without_idempotency(),
+                )
+                .build()
+        };
+
+        let first = engine.execute(billing("billing"), json!({ "order_id": 7 })).await.unwrap();
+        let retried = engine.execute(billing("billing"), json!({ "order_id": 7 })).await.unwrap();
+        assert_eq!(first.outputs["charge_payment"], json!({ "call": 1, "key": "order-7" }));
+        assert_eq!(retried.outputs["charge_payment"], first.outputs["charge_payment"]);
+        assert_eq!(retried.tasks[0].status(), TaskStatus::Succeeded);
+        assert_eq!(handler.calls("charge_payment"), 1);
+        assert_eq!(retried.outputs["poll_settlement"], json!({ "call": 2, "key": null }));
+
+        engine.execute(billing("billing"), json!({ "order_id": 8 })).await.unwrap();
+        engine.execute(billing("refunds"), json!({ "order_id": 7 })).await.unwrap();
+        assert_eq!(handler.calls("charge_payment"), 3);
+
+        let missing = engine.execute(billing("billing"), json!({})).await.unwrap();
+        assert!(missing.error.unwrap().contains("Undefined variable 'input.order_id'"));
+        assert_eq!(handler.calls("charge_payment"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_missing_handlers_are_rejected_before_running() {
+        let engine = WorkflowEngine::new().await.unwrap();
+        let result = engine.execute(admission(), json!({})).await;
+        assert!(matches!(result, Err(WorkflowError::TaskError(message)) if message.contains("no handler")));
     }
-}
\ No newline at end of file
+}
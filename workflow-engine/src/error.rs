@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::state_machine::TaskStatus;
+
 #[derive(Error, Debug)]
 pub enum WorkflowError {
     #[error("Workflow execution failed")]
@@ -13,13 +15,55 @@ pub enum WorkflowError {
     
     #[error("Invalid workflow definition")]
     InvalidDefinition,
-    
+
+    /// The definition is not well-formed YAML/JSON or has misspelled fields
+    #[error("Cannot parse workflow definition: {message}")]
+    ParseError {
+        message: String,
+        line: Option<usize>,
+        column: Option<usize>,
+    },
+
+    /// The definition parsed but describes a workflow that cannot run
+    #[error("Invalid workflow '{workflow}':\n  - {}", .problems.join("\n  - "))]
+    ValidationFailed {
+        workflow: String,
+        problems: Vec<String>,
+    },
+
+    /// A condition or input template that cannot be parsed or evaluated
+    #[error("Invalid expression '{expression}': {message}")]
+    InvalidExpression {
+        expression: String,
+        message: String,
+    },
+
+    /// A condition or input template refers to a value that is not defined
+    #[error("Undefined variable '{variable}' in '{expression}'")]
+    MissingVariable {
+        expression: String,
+        variable: String,
+    },
+
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
     #[error("Workflow scheduling error")]
     SchedulingError,
     
     #[error("State machine error")]
     StateMachineError,
     
+    #[error("Illegal transition for task '{task}': {from} -> {to}")]
+    InvalidTransition {
+        task: String,
+        from: TaskStatus,
+        to: TaskStatus,
+    },
+    
+    #[error("Task timed out: {0}")]
+    TaskTimedOut(String),
+    
     #[error("Compensation handling failed")]
     CompensationError,
     
@@ -1,2 +1,137 @@
-// Workflow executor stub
-pub struct WorkflowExecutor {}
\ No newline at end of file
+// Workflow executor
+use std::future::Future;
+
+use serde_json::Value;
+
+use crate::error::{Result, WorkflowError};
+use crate::state_machine::{RetryDecision, TaskExecution};
+use crate::task::Task;
+
+#[derive(Debug, Default)]
+pub struct WorkflowExecutor {}
+
+impl WorkflowExecutor {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Run `action` for `task`, applying its timeout and retry policy.
+    ///
+    /// Every attempt is driven through `execution`. When retries are
+    /// exhausted the last error is returned and `execution` is left in
+    /// `Failed` or `TimedOut` for the caller to compensate.
+    pub async fn run_task<F, Fut>(
+        &self,
+        task: &Task,
+        execution: &mut TaskExecution,
+        mut action: F,
+    ) -> Result<Value>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<Value>>,
+    {
+        let policy = &task.retry_policy;
+        loop {
+            execution.start()?;
+
+            let outcome = match task.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, action())
+                    .await
+                    .map_err(|_| timeout),
+                None => Ok(action().await),
+            };
+
+            let (decision, error) = match outcome {
+                Ok(Ok(output)) => {
+                    execution.succeed()?;
+                    return Ok(output);
+                }
+                Ok(Err(error)) => (execution.fail(&error, policy)?, error),
+                Err(timeout) => (
+                    execution.time_out(timeout, policy)?,
+                    WorkflowError::TaskTimedOut(format!("{} after {:?}", task.name, timeout)),
+                ),
+            };
+
+            match decision {
+                RetryDecision::Retry { after } => {
+                    tracing::warn!(
+                        task = %task.name,
+                        attempt = execution.attempts(),
+                        error = %error,
+                        retry_in = ?after,
+                        "Task attempt failed, retrying"
+                    );
+                    tokio::time::sleep(after).await;
+                    execution.retry()?;
+                }
+                RetryDecision::Compensate => {
+                    tracing::error!(
+                        task = %task.name,
+                        attempts = execution.attempts(),
+                        error = %error,
+                        "Task failed"
+                    );
+                    return Err(error);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_machine::TaskStatus;
+    use crate::task::{RetryPolicy, TaskType};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_timed_out_attempt_is_retried() {
+        let task = Task::new("create_profile", TaskType::DatabaseOperation)
+            .with_timeout(Duration::from_millis(20))
+            .with_retry_policy(RetryPolicy::default().with_backoff(Duration::ZERO, Duration::ZERO));
+        let mut execution = TaskExecution::new(&task.name);
+        let mut calls = 0;
+
+        let output = WorkflowExecutor::new()
+            .run_task(&task, &mut execution, || {
+                calls += 1;
+                let slow = calls == 1;
+                async move {
+                    if slow {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                    Ok(Value::from(calls))
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(output, Value::from(2));
+        assert_eq!(execution.status(), TaskStatus::Succeeded);
+        assert!(execution
+            .history()
+            .iter()
+            .any(|t| t.to == TaskStatus::TimedOut));
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_retries_leave_task_for_compensation() {
+        let task =
+            Task::new("assign_role", TaskType::Custom).with_retry_policy(RetryPolicy::none());
+        let mut execution = TaskExecution::new(&task.name);
+
+        let result = WorkflowExecutor::new()
+            .run_task(&task, &mut execution, || async {
+                Err(WorkflowError::TaskError(
+                    "role service unavailable".to_string(),
+                ))
+            })
+            .await;
+
+        assert!(matches!(result, Err(WorkflowError::TaskError(_))));
+        assert_eq!(execution.status(), TaskStatus::Failed);
+        execution.compensate().unwrap();
+    }
+}
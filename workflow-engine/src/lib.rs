@@ -57,15 +57,26 @@
 
 pub mod engine;
 pub mod workflow;
+pub mod definition;
 pub mod task;
 pub mod scheduler;
 pub mod executor;
 pub mod state_machine;
+pub mod stats;
 pub mod conditions;
+pub mod dry_run;
+pub mod idempotency;
 pub mod compensation;
 pub mod error;
 
 pub use engine::*;
 pub use workflow::*;
+pub use definition::*;
 pub use task::*;
+pub use state_machine::*;
+pub use stats::*;
+pub use executor::*;
+pub use conditions::*;
+pub use dry_run::*;
+pub use idempotency::*;
 pub use error::*;
\ No newline at end of file
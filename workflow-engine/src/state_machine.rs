@@ -1,2 +1,406 @@
-// State machine implementation stub
-pub struct StateMachine {}
\ No newline at end of file
+// Task execution state machine
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, WorkflowError};
+use crate::task::RetryPolicy;
+
+/// Lifecycle of a single task execution.
+///
+/// ```text
+/// Pending -> Running -> Succeeded -> Compensated
+///                    -> Failed    -> Pending (retry) | Compensated
+///                    -> TimedOut  -> Pending (retry) | Compensated
+///         -> Skipped
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    TimedOut,
+    Compensated,
+    /// The task's condition did not hold, so it never ran
+    Skipped,
+}
+
+impl TaskStatus {
+    pub fn can_transition_to(self, next: TaskStatus) -> bool {
+        use TaskStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Running)
+                | (Pending, Skipped)
+                | (Running, Succeeded)
+                | (Running, Failed)
+                | (Running, TimedOut)
+                | (Failed, Pending)
+                | (TimedOut, Pending)
+                | (Failed, Compensated)
+                | (TimedOut, Compensated)
+                | (Succeeded, Compensated)
+        )
+    }
+
+    /// Failed or timed out; the task must be retried or compensated
+    pub fn is_failure(self) -> bool {
+        matches!(self, TaskStatus::Failed | TaskStatus::TimedOut)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskStatus::Pending => "pending",
+            TaskStatus::Running => "running",
+            TaskStatus::Succeeded => "succeeded",
+            TaskStatus::Failed => "failed",
+            TaskStatus::TimedOut => "timed_out",
+            TaskStatus::Compensated => "compensated",
+            TaskStatus::Skipped => "skipped",
+        }
+    }
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A recorded status change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskTransition {
+    pub from: TaskStatus,
+    pub to: TaskStatus,
+    pub at: DateTime<Utc>,
+}
+
+/// One run of a task, from `Running` to the status it finished in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttemptSpan {
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the attempt is still running
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: TaskStatus,
+}
+
+impl AttemptSpan {
+    pub fn duration(&self) -> Option<Duration> {
+        self.finished_at
+            .and_then(|finished_at| (finished_at - self.started_at).to_std().ok())
+    }
+}
+
+/// What the executor should do after a failed or timed-out attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Move back to `Pending` and run again after the delay
+    Retry { after: Duration },
+    /// Attempts exhausted or error not retryable
+    Compensate,
+}
+
+/// Runtime state of one task within a workflow execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskExecution {
+    pub task: String,
+    status: TaskStatus,
+    attempts: u32,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    history: Vec<TaskTransition>,
+}
+
+impl TaskExecution {
+    pub fn new(task: &str) -> Self {
+        Self {
+            task: task.to_string(),
+            status: TaskStatus::Pending,
+            attempts: 0,
+            started_at: None,
+            finished_at: None,
+            last_error: None,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn status(&self) -> TaskStatus {
+        self.status
+    }
+
+    /// Number of times the task has been started
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.started_at
+    }
+
+    pub fn finished_at(&self) -> Option<DateTime<Utc>> {
+        self.finished_at
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    pub fn history(&self) -> &[TaskTransition] {
+        &self.history
+    }
+
+    /// Start and end of every attempt, read from the transition history
+    pub fn attempt_spans(&self) -> Vec<AttemptSpan> {
+        let mut spans: Vec<AttemptSpan> = Vec::new();
+        for transition in &self.history {
+            if transition.to == TaskStatus::Running {
+                spans.push(AttemptSpan {
+                    attempt: u32::try_from(spans.len()).unwrap_or(u32::MAX).saturating_add(1),
+                    started_at: transition.at,
+                    finished_at: None,
+                    status: TaskStatus::Running,
+                });
+            } else if transition.from == TaskStatus::Running {
+                if let Some(span) = spans.last_mut() {
+                    span.finished_at = Some(transition.at);
+                    span.status = transition.to;
+                }
+            }
+        }
+        spans
+    }
+
+    /// Pending -> Running
+    pub fn start(&mut self) -> Result<()> {
+        self.transition(TaskStatus::Running)?;
+        self.attempts += 1;
+        self.started_at = Some(Utc::now());
+        self.finished_at = None;
+        Ok(())
+    }
+
+    /// Running -> Succeeded
+    pub fn succeed(&mut self) -> Result<()> {
+        self.transition(TaskStatus::Succeeded)?;
+        self.finished_at = Some(Utc::now());
+        self.last_error = None;
+        Ok(())
+    }
+
+    /// Running -> Failed, returning whether to retry or compensate
+    pub fn fail(&mut self, error: &WorkflowError, policy: &RetryPolicy) -> Result<RetryDecision> {
+        self.transition(TaskStatus::Failed)?;
+        self.finished_at = Some(Utc::now());
+        self.last_error = Some(error.to_string());
+        Ok(self.decide(policy.is_retryable(error), policy))
+    }
+
+    /// Running -> TimedOut, returning whether to retry or compensate
+    pub fn time_out(&mut self, timeout: Duration, policy: &RetryPolicy) -> Result<RetryDecision> {
+        self.transition(TaskStatus::TimedOut)?;
+        self.finished_at = Some(Utc::now());
+        self.last_error = Some(format!("timed out after {:?}", timeout));
+        Ok(self.decide(policy.retry_on_timeout, policy))
+    }
+
+    /// Running -> TimedOut if the attempt has outlived `timeout`
+    pub fn check_timeout(
+        &mut self,
+        now: DateTime<Utc>,
+        timeout: Duration,
+        policy: &RetryPolicy,
+    ) -> Result<Option<RetryDecision>> {
+        let expired = match (self.status, self.started_at) {
+            (TaskStatus::Running, Some(started_at)) => (now - started_at)
+                .to_std()
+                .map(|elapsed| elapsed >= timeout)
+                .unwrap_or(false),
+            _ => false,
+        };
+        if expired {
+            self.time_out(timeout, policy).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Failed | TimedOut -> Pending
+    pub fn retry(&mut self) -> Result<()> {
+        self.transition(TaskStatus::Pending)
+    }
+
+    /// Pending -> Skipped
+    pub fn skip(&mut self) -> Result<()> {
+        self.transition(TaskStatus::Skipped)?;
+        self.finished_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Succeeded | Failed | TimedOut -> Compensated
+    pub fn compensate(&mut self) -> Result<()> {
+        self.transition(TaskStatus::Compensated)?;
+        self.finished_at = Some(Utc::now());
+        Ok(())
+    }
+
+    fn decide(&self, retryable: bool, policy: &RetryPolicy) -> RetryDecision {
+        if retryable && self.attempts < policy.max_attempts {
+            RetryDecision::Retry {
+                after: policy.backoff(self.attempts),
+            }
+        } else {
+            RetryDecision::Compensate
+        }
+    }
+
+    fn transition(&mut self, to: TaskStatus) -> Result<()> {
+        let from = self.status;
+        if !from.can_transition_to(to) {
+            // Callers drive the machine in a fixed order, so reaching this
+            // is a bug in the executor rather than a runtime condition.
+            debug_assert!(
+                false,
+                "illegal task transition for '{}': {} -> {}",
+                self.task, from, to
+            );
+            return Err(WorkflowError::InvalidTransition {
+                task: self.task.clone(),
+                from,
+                to,
+            });
+        }
+
+        tracing::debug!(task = %self.task, %from, %to, "Task status changed");
+        self.status = to;
+        self.history.push(TaskTransition {
+            from,
+            to,
+            at: Utc::now(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [TaskStatus; 7] = [
+        TaskStatus::Pending,
+        TaskStatus::Running,
+        TaskStatus::Succeeded,
+        TaskStatus::Failed,
+        TaskStatus::TimedOut,
+        TaskStatus::Compensated,
+        TaskStatus::Skipped,
+    ];
+
+    #[test]
+    fn test_terminal_states_have_no_exits() {
+        for next in ALL {
+            assert!(!TaskStatus::Compensated.can_transition_to(next));
+            assert!(!TaskStatus::Skipped.can_transition_to(next));
+        }
+        assert!(!TaskStatus::Pending.can_transition_to(TaskStatus::Succeeded));
+        assert!(!TaskStatus::Succeeded.can_transition_to(TaskStatus::Pending));
+    }
+
+    #[test]
+    fn test_retry_until_attempts_exhausted() {
+        let policy = RetryPolicy::default()
+            .with_max_attempts(2)
+            .with_backoff(Duration::from_millis(100), Duration::from_secs(1));
+        let error = WorkflowError::TaskError("connection reset".to_string());
+        let mut execution = TaskExecution::new("create_profile");
+
+        execution.start().unwrap();
+        assert_eq!(
+            execution.fail(&error, &policy).unwrap(),
+            RetryDecision::Retry {
+                after: Duration::from_millis(100)
+            }
+        );
+        execution.retry().unwrap();
+        execution.start().unwrap();
+        assert_eq!(
+            execution.fail(&error, &policy).unwrap(),
+            RetryDecision::Compensate
+        );
+        execution.compensate().unwrap();
+
+        assert_eq!(execution.status(), TaskStatus::Compensated);
+        assert_eq!(execution.attempts(), 2);
+        assert_eq!(execution.history().len(), 6);
+    }
+
+    #[test]
+    fn test_non_retryable_error_compensates_immediately() {
+        let policy = RetryPolicy::default()
+            .with_retryable(|error| !matches!(error, WorkflowError::InvalidDefinition));
+        let mut execution = TaskExecution::new("assign_role");
+
+        execution.start().unwrap();
+        assert_eq!(
+            execution
+                .fail(&WorkflowError::InvalidDefinition, &policy)
+                .unwrap(),
+            RetryDecision::Compensate
+        );
+    }
+
+    #[test]
+    fn test_check_timeout_moves_to_timed_out() {
+        let policy = RetryPolicy::default();
+        let mut execution = TaskExecution::new("send_welcome_email");
+        execution.start().unwrap();
+
+        let now = Utc::now();
+        assert_eq!(
+            execution
+                .check_timeout(now, Duration::from_secs(30), &policy)
+                .unwrap(),
+            None
+        );
+        let decision = execution
+            .check_timeout(
+                now + chrono::Duration::seconds(31),
+                Duration::from_secs(30),
+                &policy,
+            )
+            .unwrap();
+
+        assert!(matches!(decision, Some(RetryDecision::Retry { .. })));
+        assert_eq!(execution.status(), TaskStatus::TimedOut);
+    }
+
+    #[test]
+    fn test_attempt_spans_follow_history() {
+        let policy = RetryPolicy::default();
+        let error = WorkflowError::TaskError("busy".to_string());
+        let mut execution = TaskExecution::new("create_profile");
+
+        execution.start().unwrap();
+        execution.fail(&error, &policy).unwrap();
+        execution.retry().unwrap();
+        execution.start().unwrap();
+
+        let spans = execution.attempt_spans();
+        assert_eq!(spans.len(), 2);
+        assert_eq!((spans[0].attempt, spans[0].status), (1, TaskStatus::Failed));
+        assert!(spans[0].duration().is_some());
+        assert_eq!((spans[1].attempt, spans[1].status, spans[1].finished_at), (2, TaskStatus::Running, None));
+    }
+
+    #[test]
+    #[should_panic(expected = "illegal task transition")]
+    fn test_illegal_transition_is_caught() {
+        let mut execution = TaskExecution::new("create_profile");
+        let _ = execution.succeed();
+    }
+}
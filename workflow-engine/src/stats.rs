@@ -0,0 +1,187 @@
+// Execution timings and workflow metrics
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use telemetry::{MetricKind, MetricsCollector};
+use uuid::Uuid;
+
+use crate::engine::ExecutionStatus;
+use crate::state_machine::{AttemptSpan, TaskExecution, TaskStatus};
+
+const TASK_DURATION: &str = "workflow_task_duration_seconds";
+const TASK_EXECUTIONS: &str = "workflow_task_executions_total";
+const TASK_RETRIES: &str = "workflow_task_retries_total";
+const TASK_REPLAYS: &str = "workflow_task_replays_total";
+const WORKFLOW_DURATION: &str = "workflow_duration_seconds";
+const WORKFLOW_EXECUTIONS: &str = "workflow_executions_total";
+
+/// Buckets for task and workflow durations, from 10ms to 10 minutes
+const DURATION_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 15.0, 30.0, 60.0, 300.0, 600.0];
+
+/// Whether a task ran as part of the workflow or to undo a finished task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskPhase {
+    Forward,
+    Compensation,
+}
+
+impl TaskPhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskPhase::Forward => "forward",
+            TaskPhase::Compensation => "compensation",
+        }
+    }
+}
+
+/// When one task ran, attempt by attempt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskTiming {
+    pub task: String,
+    pub phase: TaskPhase,
+    pub status: TaskStatus,
+    pub attempts: Vec<AttemptSpan>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// From the start of the first attempt to the end of the last, backoff included
+    pub duration_ms: u64,
+}
+
+impl TaskTiming {
+    pub fn new(phase: TaskPhase, execution: &TaskExecution) -> Self {
+        let attempts = execution.attempt_spans();
+        let started_at = attempts.first().map(|span| span.started_at);
+        let finished_at = attempts.last().and_then(|span| span.finished_at);
+        Self {
+            task: execution.task.clone(),
+            phase,
+            status: execution.status(),
+            duration_ms: elapsed_ms(started_at, finished_at),
+            attempts,
+            started_at,
+            finished_at,
+        }
+    }
+
+    pub fn retries(&self) -> u32 {
+        u32::try_from(self.attempts.len()).unwrap_or(u32::MAX).saturating_sub(1)
+    }
+}
+
+/// Timings of a single workflow run, for Gantt-style views
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionStats {
+    pub execution_id: Uuid,
+    pub workflow: String,
+    pub status: ExecutionStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Every task run, compensations included, in the order they started
+    pub tasks: Vec<TaskTiming>,
+}
+
+impl ExecutionStats {
+    pub fn task(&self, name: &str, phase: TaskPhase) -> Option<&TaskTiming> {
+        self.tasks
+            .iter()
+            .find(|timing| timing.task == name && timing.phase == phase)
+    }
+}
+
+pub(crate) fn elapsed_ms(started_at: Option<DateTime<Utc>>, finished_at: Option<DateTime<Utc>>) -> u64 {
+    match (started_at, finished_at) {
+        (Some(started_at), Some(finished_at)) => (finished_at - started_at)
+            .to_std()
+            .map(|elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Register help text and labels of the workflow metrics
+pub fn describe_metrics() {
+    let metrics = MetricsCollector::global();
+    metrics.describe_histogram(
+        TASK_DURATION,
+        "Duration of each task attempt in seconds by workflow, task, phase and outcome",
+        DURATION_BUCKETS,
+    );
+    metrics.describe(
+        TASK_EXECUTIONS,
+        MetricKind::Counter,
+        "Task runs by workflow, task, phase and final status",
+    );
+    metrics.describe(TASK_RETRIES, MetricKind::Counter, "Task attempts beyond the first by workflow and task");
+    metrics.describe(
+        TASK_REPLAYS,
+        MetricKind::Counter,
+        "Task results returned from an idempotency key instead of running the handler",
+    );
+    metrics.describe_histogram(
+        WORKFLOW_DURATION,
+        "End-to-end workflow execution time in seconds by workflow and status",
+        DURATION_BUCKETS,
+    );
+    metrics.describe(WORKFLOW_EXECUTIONS, MetricKind::Counter, "Workflow executions by workflow and status");
+
+    let task_labels = ["workflow", "task", "phase", "status"];
+    metrics.restrict_labels(TASK_DURATION, MetricKind::Histogram, &task_labels);
+    metrics.restrict_labels(TASK_EXECUTIONS, MetricKind::Counter, &task_labels);
+    metrics.restrict_labels(TASK_RETRIES, MetricKind::Counter, &["workflow", "task"]);
+    metrics.restrict_labels(TASK_REPLAYS, MetricKind::Counter, &["workflow", "task"]);
+    metrics.restrict_labels(WORKFLOW_DURATION, MetricKind::Histogram, &["workflow", "status"]);
+    metrics.restrict_labels(WORKFLOW_EXECUTIONS, MetricKind::Counter, &["workflow", "status"]);
+}
+
+pub(crate) fn record_task(workflow: &str, timing: &TaskTiming) {
+    let metrics = MetricsCollector::global();
+    for attempt in &timing.attempts {
+        let Some(duration) = attempt.duration() else {
+            continue;
+        };
+        metrics
+            .histogram(TASK_DURATION)
+            .with_label("workflow", workflow)
+            .with_label("task", timing.task.as_str())
+            .with_label("phase", timing.phase.as_str())
+            .with_label("status", attempt.status.as_str())
+            .record(duration.as_secs_f64());
+    }
+    metrics
+        .counter(TASK_EXECUTIONS)
+        .with_label("workflow", workflow)
+        .with_label("task", timing.task.as_str())
+        .with_label("phase", timing.phase.as_str())
+        .with_label("status", timing.status.as_str())
+        .increment();
+    if timing.retries() > 0 {
+        metrics
+            .counter(TASK_RETRIES)
+            .with_label("workflow", workflow)
+            .with_label("task", timing.task.as_str())
+            .increment_by(f64::from(timing.retries()));
+    }
+}
+
+pub(crate) fn record_replay(workflow: &str, task: &str) {
+    MetricsCollector::global()
+        .counter(TASK_REPLAYS)
+        .with_label("workflow", workflow)
+        .with_label("task", task)
+        .increment();
+}
+
+pub(crate) fn record_workflow(stats: &ExecutionStats) {
+    let metrics = MetricsCollector::global();
+    metrics
+        .histogram(WORKFLOW_DURATION)
+        .with_label("workflow", stats.workflow.as_str())
+        .with_label("status", stats.status.as_str())
+        .record((stats.finished_at - stats.started_at).to_std().unwrap_or_default().as_secs_f64());
+    metrics
+        .counter(WORKFLOW_EXECUTIONS)
+        .with_label("workflow", stats.workflow.as_str())
+        .with_label("status", stats.status.as_str())
+        .increment();
+}
@@ -1,7 +1,32 @@
-// Task definition and types stub
+// Task definition and types
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::error::WorkflowError;
+
+#[derive(Debug, Clone)]
 pub struct Task {
     pub name: String,
     pub task_type: TaskType,
+    /// Parameters handed to the task handler
+    pub inputs: Map<String, Value>,
+    /// Tasks that must finish before this one starts
+    pub depends_on: Vec<String>,
+    /// Expression that must hold for the task to run; `None` always runs
+    pub condition: Option<String>,
+    /// Task that undoes this one when a later task fails
+    pub compensation: Option<String>,
+    pub retry_policy: RetryPolicy,
+    /// Limit for a single attempt; `None` lets the attempt run indefinitely
+    pub timeout: Option<Duration>,
+    /// Template for the key a successful result is recorded under; `None`
+    /// falls back to the workflow's key
+    pub idempotency_key: Option<String>,
+    /// `false` for tasks that must run every time, such as polling
+    pub idempotent: bool,
 }
 
 impl Task {
@@ -9,13 +34,167 @@ impl Task {
         Self {
             name: name.to_string(),
             task_type,
+            inputs: Map::new(),
+            depends_on: Vec::new(),
+            condition: None,
+            compensation: None,
+            retry_policy: RetryPolicy::default(),
+            timeout: None,
+            idempotency_key: None,
+            idempotent: true,
         }
     }
+
+    pub fn with_input(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.inputs.insert(key.to_string(), value.into());
+        self
+    }
+
+    pub fn depends_on(mut self, task: &str) -> Self {
+        self.depends_on.push(task.to_string());
+        self
+    }
+
+    pub fn with_condition(mut self, condition: &str) -> Self {
+        self.condition = Some(condition.to_string());
+        self
+    }
+
+    pub fn with_compensation(mut self, task: &str) -> Self {
+        self.compensation = Some(task.to_string());
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Record the result under `key`, e.g. `"charge-${input.order_id}"`
+    pub fn with_idempotency_key(mut self, key: &str) -> Self {
+        self.idempotency_key = Some(key.to_string());
+        self
+    }
+
+    /// Run the handler every time, even when the workflow declares a key
+    pub fn without_idempotency(mut self) -> Self {
+        self.idempotent = false;
+        self
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TaskType {
     HttpRequest,
     DatabaseOperation,
     Custom,
-}
\ No newline at end of file
+}
+
+impl TaskType {
+    pub const ALL: [TaskType; 3] = [TaskType::HttpRequest, TaskType::DatabaseOperation, TaskType::Custom];
+
+    /// Name used in workflow definitions
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskType::HttpRequest => "http_request",
+            TaskType::DatabaseOperation => "database_operation",
+            TaskType::Custom => "custom",
+        }
+    }
+}
+
+impl fmt::Display for TaskType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TaskType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|t| t.as_str() == s).ok_or_else(|| {
+            let known: Vec<_> = Self::ALL.iter().map(|t| t.as_str()).collect();
+            format!("unknown task type '{}' (expected one of: {})", s, known.join(", "))
+        })
+    }
+}
+
+/// Per-task retry settings with exponential backoff
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: u32,
+    pub retry_on_timeout: bool,
+    retryable: fn(&WorkflowError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            multiplier: 2,
+            retry_on_timeout: true,
+            retryable: |_| true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run once and never retry
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier.max(1);
+        self
+    }
+
+    pub fn with_retry_on_timeout(mut self, retry_on_timeout: bool) -> Self {
+        self.retry_on_timeout = retry_on_timeout;
+        self
+    }
+
+    /// Predicate deciding whether a failure is worth another attempt
+    pub fn with_retryable(mut self, retryable: fn(&WorkflowError) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn is_retryable(&self, error: &WorkflowError) -> bool {
+        (self.retryable)(error)
+    }
+
+    /// Delay before the retry that follows attempt number `attempt` (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(self.multiplier.saturating_pow(exponent))
+            .min(self.max_backoff)
+    }
+}
@@ -1,28 +1,296 @@
-// Workflow definition stub
+// Workflow definition
+use std::collections::{HashMap, HashSet};
+
+use petgraph::algo::{tarjan_scc, toposort};
+use petgraph::graphmap::DiGraphMap;
+
+use crate::conditions::Condition;
+use crate::error::{Result, WorkflowError};
+use crate::task::Task;
+
+/// A named set of tasks and the dependencies between them
+///
+/// Built in code with [`Workflow::builder`] or parsed from a declarative
+/// definition with [`Workflow::from_yaml`] / [`Workflow::from_json`].
+#[derive(Debug, Clone)]
 pub struct Workflow {
     pub name: String,
+    pub description: Option<String>,
+    /// Idempotency key template for tasks that don't declare their own
+    pub idempotency_key: Option<String>,
+    pub tasks: Vec<Task>,
 }
 
 impl Workflow {
     pub fn builder(name: &str) -> WorkflowBuilder {
         WorkflowBuilder::new(name)
     }
+
+    pub fn task(&self, name: &str) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.name == name)
+    }
+
+    /// Reject workflows that cannot run: duplicate or missing task names,
+    /// dependencies or compensations naming tasks that don't exist,
+    /// malformed conditions and dependency cycles
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(WorkflowError::ValidationFailed {
+                workflow: self.name.clone(),
+                problems,
+            })
+        }
+    }
+
+    /// Key template a task's result is recorded under, if it has one
+    pub fn idempotency_key_for<'a>(&'a self, task: &'a Task) -> Option<&'a str> {
+        if !task.idempotent {
+            return None;
+        }
+        task.idempotency_key.as_deref().or(self.idempotency_key.as_deref())
+    }
+
+    /// Tasks named as another task's compensation; they only run to undo it
+    pub fn compensation_tasks(&self) -> HashSet<&str> {
+        self.tasks
+            .iter()
+            .filter_map(|task| task.compensation.as_deref())
+            .collect()
+    }
+
+    /// Tasks ordered so every task follows the tasks it depends on
+    ///
+    /// Compensation tasks are left out.
+    pub fn execution_order(&self) -> Result<Vec<&Task>> {
+        self.validate()?;
+        let order = toposort(&self.dependency_graph(), None).map_err(|cycle| {
+            WorkflowError::ValidationFailed {
+                workflow: self.name.clone(),
+                problems: vec![format!("dependency cycle through task '{}'", cycle.node_id())],
+            }
+        })?;
+        let compensations = self.compensation_tasks();
+        Ok(order
+            .into_iter()
+            .filter(|name| !compensations.contains(name))
+            .filter_map(|name| self.task(name))
+            .collect())
+    }
+
+    /// Every problem found, each prefixed with the task it concerns
+    pub(crate) fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push("name: must not be empty".to_string());
+        }
+        if self.tasks.is_empty() {
+            problems.push("tasks: a workflow needs at least one task".to_string());
+        }
+
+        let mut seen = HashSet::new();
+        for (index, task) in self.tasks.iter().enumerate() {
+            let at = task_path(index, task);
+            if task.name.trim().is_empty() {
+                problems.push(format!("{at}.name: must not be empty"));
+            } else if !seen.insert(task.name.as_str()) {
+                problems.push(format!("{at}.name: another task is already named '{}'", task.name));
+            }
+        }
+
+        for (index, task) in self.tasks.iter().enumerate() {
+            let at = task_path(index, task);
+            for dependency in &task.depends_on {
+                if dependency == &task.name {
+                    problems.push(format!("{at}.depends_on: a task cannot depend on itself"));
+                } else if !seen.contains(dependency.as_str()) {
+                    problems.push(format!("{at}.depends_on: '{dependency}' is not a task in this workflow"));
+                }
+            }
+            if let Some(compensation) = &task.compensation {
+                if compensation == &task.name {
+                    problems.push(format!("{at}.compensation: a task cannot compensate itself"));
+                } else if !seen.contains(compensation.as_str()) {
+                    problems.push(format!("{at}.compensation: '{compensation}' is not a task in this workflow"));
+                }
+            }
+            if let Some(condition) = &task.condition {
+                if condition.trim().is_empty() {
+                    problems.push(format!("{at}.condition: must not be empty when given"));
+                } else if let Err(e) = Condition::parse(condition) {
+                    problems.push(format!("{at}.condition: {e}"));
+                }
+            }
+            if task.idempotency_key.is_some() && !task.idempotent {
+                problems.push(format!("{at}.idempotency_key: cannot be set on a task that is not idempotent"));
+            }
+        }
+
+        problems.extend(self.cycles().into_iter().map(|cycle| {
+            format!("depends_on: tasks form a dependency cycle: {}", cycle.join(" -> "))
+        }));
+        problems
+    }
+
+    /// Dependency edges point from a task to the tasks waiting on it
+    fn dependency_graph(&self) -> DiGraphMap<&str, ()> {
+        let mut graph = DiGraphMap::new();
+        for task in &self.tasks {
+            graph.add_node(task.name.as_str());
+            for dependency in &task.depends_on {
+                if dependency != &task.name {
+                    graph.add_edge(dependency.as_str(), task.name.as_str(), ());
+                }
+            }
+        }
+        graph
+    }
+
+    /// Each set of tasks that depend on one another in a loop, in definition order
+    fn cycles(&self) -> Vec<Vec<&str>> {
+        let position: HashMap<&str, usize> = self
+            .tasks
+            .iter()
+            .enumerate()
+            .map(|(index, task)| (task.name.as_str(), index))
+            .collect();
+
+        let mut cycles: Vec<Vec<&str>> = tarjan_scc(&self.dependency_graph())
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|mut component| {
+                component.sort_by_key(|name| position.get(name).copied().unwrap_or(usize::MAX));
+                if let Some(first) = component.first().copied() {
+                    component.push(first);
+                }
+                component
+            })
+            .collect();
+        cycles.sort();
+        cycles
+    }
+}
+
+fn task_path(index: usize, task: &Task) -> String {
+    if task.name.trim().is_empty() {
+        format!("tasks[{index}]")
+    } else {
+        format!("tasks[{index}] ({})", task.name)
+    }
 }
 
 pub struct WorkflowBuilder {
     name: String,
+    description: Option<String>,
+    idempotency_key: Option<String>,
+    tasks: Vec<Task>,
 }
 
 impl WorkflowBuilder {
     pub fn new(name: &str) -> Self {
-        Self { name: name.to_string() }
+        Self {
+            name: name.to_string(),
+            description: None,
+            idempotency_key: None,
+            tasks: Vec::new(),
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Key template applied to every idempotent task without its own key
+    pub fn idempotency_key(mut self, key: &str) -> Self {
+        self.idempotency_key = Some(key.to_string());
+        self
     }
-    
-    pub fn add_task(self, _task: crate::task::Task) -> Self {
-        self // Stub implementation
+
+    pub fn add_task(mut self, task: Task) -> Self {
+        self.tasks.push(task);
+        self
     }
-    
+
     pub fn build(self) -> Workflow {
-        Workflow { name: self.name }
+        Workflow {
+            name: self.name,
+            description: self.description,
+            idempotency_key: self.idempotency_key,
+            tasks: self.tasks,
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::TaskType;
+
+    fn problems(workflow: &Workflow) -> Vec<String> {
+        match workflow.validate() {
+            Err(WorkflowError::ValidationFailed { problems, .. }) => problems,
+            other => panic!("expected validation failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_execution_order_follows_dependencies() {
+        let workflow = Workflow::builder("admission")
+            .add_task(
+                Task::new("assign_bed", TaskType::DatabaseOperation)
+                    .depends_on("register")
+                    .with_compensation("release_bed"),
+            )
+            .add_task(Task::new("notify_ward", TaskType::HttpRequest).depends_on("assign_bed"))
+            .add_task(Task::new("register", TaskType::DatabaseOperation))
+            .add_task(Task::new("release_bed", TaskType::DatabaseOperation))
+            .build();
+
+        let order: Vec<_> = workflow.execution_order().unwrap().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(order, ["register", "assign_bed", "notify_ward"]);
+    }
+
+    #[test]
+    fn test_dangling_references_and_duplicates_are_reported_together() {
+        let workflow = Workflow::builder("admission")
+            .add_task(Task::new("register", TaskType::DatabaseOperation).with_compensation("unregister"))
+            .add_task(
+                Task::new("register", TaskType::Custom)
+                    .depends_on("triage")
+                    .with_condition("input.ward = 'icu'"),
+            )
+            .build();
+
+        assert_eq!(
+            problems(&workflow),
+            [
+                "tasks[1] (register).name: another task is already named 'register'",
+                "tasks[0] (register).compensation: 'unregister' is not a task in this workflow",
+                "tasks[1] (register).depends_on: 'triage' is not a task in this workflow",
+                "tasks[1] (register).condition: Invalid expression 'input.ward = 'icu'': unexpected '=' at column 12",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependency_cycles_are_rejected() {
+        let workflow = Workflow::builder("loop")
+            .add_task(Task::new("a", TaskType::Custom).depends_on("c"))
+            .add_task(Task::new("b", TaskType::Custom).depends_on("a"))
+            .add_task(Task::new("c", TaskType::Custom).depends_on("b"))
+            .add_task(Task::new("d", TaskType::Custom).depends_on("d"))
+            .build();
+
+        assert_eq!(
+            problems(&workflow),
+            [
+                "tasks[3] (d).depends_on: a task cannot depend on itself",
+                "depends_on: tasks form a dependency cycle: a -> b -> c -> a",
+            ]
+        );
+        assert!(workflow.execution_order().is_err());
+    }
+}